@@ -1,4 +1,4 @@
-use ate::{compact::CompactMode, prelude::*, utils::load_node_list};
+use ate::{compact::CompactMode, compact::CompactionPolicy, compact::CompactionWindow, daemon::Daemon, prelude::*, utils::load_node_list};
 use std::time::Duration;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
@@ -50,15 +50,31 @@ struct Opts {
     /// Address that DNS queries will be sent to
     #[clap(long, default_value = "8.8.8.8")]
     dns_server: String,
+    /// Configuration file (TOML, YAML or JSON) with an [ate] section and optionally
+    /// a [mesh] section - when supplied the settings in the file are used instead of
+    /// the DNS, storage, compaction and network arguments
+    #[clap(long)]
+    config: Option<String>,
+    /// Validates the configuration file, reports every problem that it finds and exits
+    #[clap(long, requires = "config")]
+    check_config: bool,
 
     #[clap(subcommand)]
-    subcmd: SubCommand,
+    subcmd: Option<SubCommand>,
 }
 
 #[derive(Parser)]
 enum SubCommand {
     #[clap()]
     Solo(Solo),
+    #[clap()]
+    Doctor(Doctor),
+    #[clap()]
+    Chain(ChainCmd),
+    #[clap()]
+    Cert(CertCmd),
+    #[clap()]
+    Query(QueryCmd),
 }
 /// Runs a solo ATE datachain and listens for connections from clients
 #[derive(Parser)]
@@ -95,15 +111,143 @@ struct Solo {
     /// Size of growth in bytes in the log file which will trigger compaction (default: 100MB) - this argument is ignored if you select a compact_mode that has no growth trigger
     #[clap(long, default_value = "104857600")]
     compact_threshold_size: u64,
+    /// Redo log size in bytes that will trigger a background compaction by the compaction policy
+    #[clap(long)]
+    compact_max_size: Option<u64>,
+    /// Ratio of tombstones to events that will trigger a background compaction by the compaction policy
+    #[clap(long)]
+    compact_max_tombstone_ratio: Option<f32>,
+    /// Time in seconds since the last compaction that will trigger a background compaction by the compaction policy
+    #[clap(long)]
+    compact_max_age: Option<u64>,
+    /// Maintenance window (UTC) that background compactions are restricted to (e.g. '02:00-04:00')
+    #[clap(long)]
+    compact_window: Option<CompactionWindow>,
+    /// Maximum number of chains that will be compacted at the same time
+    #[clap(long, default_value = "2")]
+    compact_concurrency: usize,
+    /// Expects every connection to start with a PROXY protocol v2 header (use this
+    /// when the server is behind a load balancer such as HAProxy or an NLB)
+    #[clap(long)]
+    proxy_protocol: bool,
+    /// Amount of memory in bytes that the history index of a single chain may use
+    /// before its oldest entries are moved to an index file next to the log file
+    #[clap(long)]
+    max_index_memory: Option<usize>,
+    /// Processes the events of a chain in batches while its log file is being
+    /// loaded rather than after it has been read completely
+    #[clap(long)]
+    load_streaming: bool,
+    /// File that the process ID is written to while the datachain runs
+    #[clap(long)]
+    pid_file: Option<String>,
+    /// Runs the datachain as a Windows service with this name (the service
+    /// control manager must have started the process)
+    #[cfg(all(windows, feature = "windows_service"))]
+    #[clap(long)]
+    windows_service: Option<String>,
+}
+
+/// Diagnoses connectivity and configuration problems with a datachain by
+/// running staged checks and reporting which stage fails
+#[derive(Parser)]
+struct Doctor {
+    /// URL of the datachain that will be diagnosed
+    #[clap(index = 1, default_value = "ws://localhost:5000/db")]
+    url: url::Url,
+    /// Name of a chain that will be opened (and synchronized) as the final check
+    #[clap(short, long)]
+    chain: Option<String>,
+    /// Maximum number of seconds that each stage of the diagnostics may take
+    #[clap(long, default_value = "10")]
+    stage_timeout: u64,
+    /// Skips the validation of the server certificate against the trusted certificates
+    #[clap(long)]
+    ignore_certificates: bool,
+}
+
+/// Inspects the chains held by a datachain
+#[derive(Parser)]
+struct ChainCmd {
+    #[clap(subcommand)]
+    action: ChainAction,
+}
+
+#[derive(Parser)]
+enum ChainAction {
+    #[clap()]
+    Stats(ChainStats),
 }
 
-fn ctrl_channel() -> tokio::sync::watch::Receiver<bool> {
-    let (sender, receiver) = tokio::sync::watch::channel(false);
-    ctrlc_async::set_handler(move || {
-        let _ = sender.send(true);
-    })
-    .unwrap();
-    receiver
+/// Reports what is stored inside a chain (row counts, storage by type and
+/// growth over time) which helps with capacity planning
+#[derive(Parser)]
+struct ChainStats {
+    /// URL of the datachain that holds the chain
+    #[clap(index = 1)]
+    url: url::Url,
+    /// Name of the chain
+    #[clap(index = 2)]
+    chain: String,
+    /// Outputs the statistics as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+/// Prints the rows of a chain that match a query as JSON lines, for example
+/// `export.binary == 'foo' and not (replicas < 2)` (only rows that are stored
+/// as JSON or another self describing format and that are not encrypted
+/// can be queried)
+#[derive(Parser)]
+struct QueryCmd {
+    /// URL of the datachain that holds the chain
+    #[clap(index = 1)]
+    url: url::Url,
+    /// Name of the chain
+    #[clap(index = 2)]
+    chain: String,
+    /// Expression that the rows must match (fields are compared with ==, !=,
+    /// <, <=, >, >= or contains and combined with and, or and not)
+    #[clap(index = 3)]
+    expr: String,
+    /// Only queries the rows attached to this parent (its key in hex) rather
+    /// than the rows without a parent
+    #[clap(long)]
+    parent: Option<String>,
+    /// Only queries the rows of this type (its full Rust type name)
+    #[clap(long = "type")]
+    type_name: Option<String>,
+    /// Maximum number of rows that will be scanned before the query is aborted
+    #[clap(long, default_value = "10000")]
+    scan_limit: usize,
+}
+
+/// Inspects the certificates that a datachain accepts connections with
+#[derive(Parser)]
+struct CertCmd {
+    #[clap(subcommand)]
+    action: CertAction,
+}
+
+#[derive(Parser)]
+enum CertAction {
+    #[clap()]
+    Probe(CertProbe),
+}
+
+/// Lists the certificates that a datachain advertises (more than one is
+/// advertised while a certificate is being rotated) and when they are valid
+#[derive(Parser)]
+struct CertProbe {
+    /// URL of the datachain that will be probed
+    #[clap(index = 1, default_value = "ws://localhost:5000/db")]
+    url: url::Url,
+    /// Maximum number of seconds that each stage of the probe may take
+    #[clap(long, default_value = "10")]
+    stage_timeout: u64,
+    /// Skips the validation of the server certificate against the trusted certificates
+    #[clap(long)]
+    ignore_certificates: bool,
 }
 
 #[tokio::main(flavor = "multi_thread")]
@@ -124,19 +268,65 @@ async fn main() -> Result<(), AteError> {
         },
     };
 
-    let mut conf = AteConfig::default();
-    conf.dns_sec = opts.dns_sec;
-    conf.dns_server = opts.dns_server;
+    let config = opts.config.map(|a| shellexpand::tilde(&a).to_string());
+    if opts.check_config {
+        match ConfDocument::check_file(config.unwrap_or_default()) {
+            true => return Ok(()),
+            false => std::process::exit(1),
+        }
+    }
+    let doc = config.map(ConfDocument::from_file).transpose()?;
+
+    let conf = match doc.as_ref() {
+        Some(doc) => doc.ate()?,
+        None => {
+            let mut conf = AteConfig::default();
+            conf.dns_sec = opts.dns_sec;
+            conf.dns_server = opts.dns_server;
+            conf
+        }
+    };
 
     let auth = match opts.no_auth {
         false if opts.trust.is_centralized() => Some(opts.auth),
         _ => None,
     };
 
-    match opts.subcmd {
+    let subcmd = match opts.subcmd {
+        Some(a) => a,
+        None => {
+            eprintln!("a subcommand is required (see --help)");
+            std::process::exit(2);
+        }
+    };
+    match subcmd {
         SubCommand::Solo(solo) => {
-            main_solo(solo, conf, auth, opts.trust, wire_encryption).await?;
+            main_solo(solo, conf, doc.as_ref(), auth, opts.trust, wire_encryption).await?;
         }
+        SubCommand::Doctor(doctor) => {
+            if main_doctor(doctor, conf, wire_encryption).await? == false {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        SubCommand::Chain(chain) => match chain.action {
+            ChainAction::Stats(stats) => {
+                main_chain_stats(stats, conf).await?;
+                return Ok(());
+            }
+        },
+        SubCommand::Query(query) => {
+            main_query(query, conf).await?;
+            return Ok(());
+        }
+        SubCommand::Cert(cert) => match cert.action {
+            CertAction::Probe(probe) => {
+                if main_cert_probe(probe, conf, wire_encryption).await? == false {
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+        },
     }
 
     info!("atedb::shutdown");
@@ -147,11 +337,55 @@ async fn main() -> Result<(), AteError> {
 async fn main_solo(
     solo: Solo,
     mut cfg_ate: ConfAte,
+    doc: Option<&ConfDocument>,
     auth: Option<url::Url>,
     trust: TrustMode,
     wire_encryption: Option<KeySize>,
 ) -> Result<(), AteError> {
+    // Settings from the configuration file take the place of the arguments
+    if doc.is_none() {
+        solo_overrides(&solo, &mut cfg_ate);
+    }
+
     // Create the chain flow and generate configuration
+    let flow = ChainFlow::new(&cfg_ate, auth, solo.url.clone(), trust).await;
+
+    // Create the server and listen on the port
+    let cfg_mesh = match doc.filter(|a| a.has_section(CONF_SECTION_MESH)) {
+        Some(doc) => doc.mesh()?,
+        None => {
+            let mut cfg_mesh =
+                ConfMesh::solo_from_url(&cfg_ate, &solo.url, &solo.listen, None, solo.node_id)
+                    .await?;
+            cfg_mesh.wire_protocol = StreamProtocol::parse(&solo.url)?;
+            cfg_mesh.wire_encryption = wire_encryption;
+            cfg_mesh.compact_concurrency = solo.compact_concurrency;
+            cfg_mesh.proxy_protocol = solo.proxy_protocol;
+            cfg_mesh
+        }
+    };
+
+    let mut daemon = Daemon::new();
+    if let Some(pid_file) = solo.pid_file.as_ref() {
+        daemon = daemon.with_pid_file(shellexpand::tilde(pid_file).as_ref())?;
+    }
+    #[cfg(all(windows, feature = "windows_service"))]
+    if let Some(name) = solo.windows_service.as_ref() {
+        daemon = daemon.with_windows_service(name.as_str())?;
+    }
+
+    let server = create_server(&cfg_mesh).await?;
+    server.add_route(Box::new(flow), &cfg_ate).await?;
+    daemon.ready(&server);
+
+    // Wait for ctrl-c (or for the service manager to stop the datachain)
+    println!("Press ctrl-c to exit");
+    daemon.run_until_shutdown(&server).await;
+    println!("Goodbye!");
+    Ok(())
+}
+
+fn solo_overrides(solo: &Solo, cfg_ate: &mut ConfAte) {
     cfg_ate.log_path = Some(shellexpand::tilde(&solo.logs_path).to_string());
     cfg_ate.backup_path = solo
         .backup_path
@@ -162,28 +396,129 @@ async fn main_solo(
         .with_growth_factor(solo.compact_threshold_factor)
         .with_growth_size(solo.compact_threshold_size)
         .with_timer_value(Duration::from_secs(solo.compact_timer));
-    cfg_ate.nodes = load_node_list(solo.nodes_list);
+    let mut policy = CompactionPolicy::default();
+    policy.max_log_size = solo.compact_max_size;
+    policy.max_tombstone_ratio = solo.compact_max_tombstone_ratio;
+    policy.max_age = solo.compact_max_age.map(Duration::from_secs);
+    policy.window = solo.compact_window;
+    if policy.is_active() {
+        cfg_ate.compact_policy = Some(policy);
+    }
+    cfg_ate.max_index_memory = solo.max_index_memory;
+    cfg_ate.load_streaming = solo.load_streaming;
+    cfg_ate.nodes = load_node_list(solo.nodes_list.clone());
+}
 
-    // Create the chain flow and generate configuration
-    let flow = ChainFlow::new(&cfg_ate, auth, solo.url.clone(), trust).await;
+async fn main_doctor(
+    doctor: Doctor,
+    cfg_ate: ConfAte,
+    wire_encryption: Option<KeySize>,
+) -> Result<bool, AteError> {
+    let mut registry = Registry::new(&cfg_ate).await.temporal(true).fail_fast(true);
+    if doctor.ignore_certificates {
+        registry = registry.ignore_certificates();
+    }
 
-    // Create the server and listen on the port
-    let mut cfg_mesh =
-        ConfMesh::solo_from_url(&cfg_ate, &solo.url, &solo.listen, None, solo.node_id).await?;
-    cfg_mesh.wire_protocol = StreamProtocol::parse(&solo.url)?;
-    cfg_mesh.wire_encryption = wire_encryption;
+    let mut options = DoctorOptions::default()
+        .with_stage_timeout(Duration::from_secs(doctor.stage_timeout))
+        .with_wire_encryption(wire_encryption);
+    if let Some(chain) = doctor.chain {
+        options = options.with_chain(ChainKey::from(chain));
+    }
 
-    let server = create_server(&cfg_mesh).await?;
-    server.add_route(Box::new(flow), &cfg_ate).await?;
+    let report = ate::prelude::doctor(&registry, &doctor.url, options).await;
+    println!("{}", report);
+    Ok(report.is_healthy())
+}
 
-    // Wait for ctrl-c
-    println!("Press ctrl-c to exit");
-    let mut exit = ctrl_channel();
-    while *exit.borrow() == false {
-        exit.changed().await.unwrap();
+async fn main_cert_probe(
+    probe: CertProbe,
+    cfg_ate: ConfAte,
+    wire_encryption: Option<KeySize>,
+) -> Result<bool, AteError> {
+    let mut registry = Registry::new(&cfg_ate).await.temporal(true).fail_fast(true);
+    if probe.ignore_certificates {
+        registry = registry.ignore_certificates();
+    }
+
+    let options = DoctorOptions::default()
+        .with_stage_timeout(Duration::from_secs(probe.stage_timeout))
+        .with_wire_encryption(wire_encryption.or(Some(KeySize::Bit128)));
+    let report = ate::prelude::doctor(&registry, &probe.url, options).await;
+    if report.is_healthy() == false {
+        eprintln!("{}", report);
+    }
+    if report.certificates.is_empty() && report.failed().is_some() {
+        return Ok(false);
+    }
+
+    let now = chrono::Utc::now();
+    let when = |a: Option<chrono::DateTime<chrono::Utc>>| match a {
+        Some(a) => a.to_rfc3339(),
+        None => "-".to_string(),
+    };
+    println!("Certificates advertised by {}", probe.url);
+    println!("{:<34} {:<26} {:<26} {}", "HASH", "NOT BEFORE", "NOT AFTER", "VALID");
+    for cert in report.certificates.iter() {
+        println!(
+            "{:<34} {:<26} {:<26} {}",
+            cert.hash.to_string(),
+            when(cert.not_before),
+            when(cert.not_after),
+            cert.is_valid_at(now)
+        );
+    }
+    if report.certificates.is_empty() {
+        println!("(the server did not advertise any certificates)");
+    }
+    Ok(true)
+}
+
+async fn main_chain_stats(stats: ChainStats, cfg_ate: ConfAte) -> Result<(), AteError> {
+    let registry = Registry::new(&cfg_ate).await.temporal(true);
+    let chain = registry
+        .open(&stats.url, &ChainKey::from(stats.chain), true)
+        .await?;
+    let ret = chain.statistics().await;
+    if stats.json {
+        let json = serde_json::to_string_pretty(&ret)
+            .map_err(|err| AteErrorKind::IO(std::io::Error::new(std::io::ErrorKind::Other, err)))?;
+        println!("{}", json);
+    } else {
+        print!("{}", ret);
+    }
+    Ok(())
+}
+
+async fn main_query(query: QueryCmd, cfg_ate: ConfAte) -> Result<(), AteError> {
+    let expr = match Query::parse(query.expr.as_str()) {
+        Ok(a) => a.with_scan_limit(query.scan_limit),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let parent = match query.parent {
+        Some(parent) => match u64::from_str_radix(parent.trim_start_matches("0x"), 16) {
+            Ok(a) => Some(PrimaryKey::from(a)),
+            Err(_) => {
+                eprintln!("the parent ({}) is not a key in hex", parent);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+
+    let registry = Registry::new(&cfg_ate).await.temporal(true);
+    let chain = registry
+        .open(&query.url, &ChainKey::from(query.chain), true)
+        .await?;
+    let dio = chain.dio(&AteSessionUser::new()).await;
+    let rows = dio
+        .query_rows(parent, query.type_name.as_deref(), &expr)
+        .await?;
+    for (_, row) in rows {
+        println!("{}", row);
     }
-    println!("Shutting down...");
-    server.shutdown().await;
-    println!("Goodbye!");
     Ok(())
 }
@@ -37,7 +37,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>>
     let opts: Opts = Opts::parse();
     //let opts = main_debug();
     ate::log_init(opts.verbose, opts.debug);
-        
+
+    let config = opts.config.map(|a| shellexpand::tilde(&a).to_string());
+    if opts.check_config {
+        match ConfDocument::check_file(config.unwrap_or_default()) {
+            true => return Ok(()),
+            false => std::process::exit(1),
+        }
+    }
+    let subcmd = match opts.subcmd {
+        Some(a) => a,
+        None => {
+            eprintln!("a subcommand is required (see --help)");
+            std::process::exit(2);
+        }
+    };
+
     let cert: PrivateEncryptKey = match try_load_key(opts.cert_path.clone()) {
         Some(a) => a,
         None => {
@@ -47,19 +62,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>>
     };
     ate::mesh::add_global_certificate(&cert.hash());
 
-    let mut conf = AteConfig::default();
-    conf.dns_sec = opts.dns_sec.clone();
-    conf.dns_server = opts.dns_server.clone();
+    let mut conf = match config {
+        Some(path) => ConfAte::from_file(path)?,
+        None => {
+            let mut conf = AteConfig::default();
+            conf.dns_sec = opts.dns_sec.clone();
+            conf.dns_server = opts.dns_server.clone();
+            conf
+        }
+    };
     conf.record_type_name = true;
     
+    let wire_encryption = opts.wire_encryption;
     let ret = runtime.clone().block_on(async move {
-        match opts.subcmd {
+        match subcmd {
             SubCommand::Run(solo) => {
                 conf.nodes = load_node_list(solo.nodes_list.clone());
                 let (_server, hard_exit) = setup_server(
                     solo,
                     conf,
-                    Some(opts.wire_encryption),
+                    Some(wire_encryption),
                     Some(cert)
                 ).await?;
                 
@@ -21,6 +21,13 @@ pub struct Opts {
     /// Address that DNS queries will be sent to
     #[clap(long, default_value = "8.8.8.8")]
     pub dns_server: String,
+    /// Configuration file (TOML, YAML or JSON) with an [ate] section - when supplied
+    /// the settings in the file are used instead of the DNS arguments
+    #[clap(long)]
+    pub config: Option<String>,
+    /// Validates the configuration file, reports every problem that it finds and exits
+    #[clap(long, requires = "config")]
+    pub check_config: bool,
     /// Token file to read that holds a previously created token to be used for this operation
     #[clap(long, default_value = "~/wasmer/token")]
     pub token_path: String,
@@ -39,7 +46,7 @@ pub struct Opts {
     pub wire_encryption: KeySize,
 
     #[clap(subcommand)]
-    pub subcmd: SubCommand,
+    pub subcmd: Option<SubCommand>,
 }
 
 #[derive(Parser)]
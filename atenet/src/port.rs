@@ -20,6 +20,7 @@ use crossbeam::queue::SegQueue;
 use derivative::*;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use wasmer_deploy_cli::model::AcceptFilter;
 use wasmer_deploy_cli::model::PortCommand;
 use wasmer_deploy_cli::model::PortResponse;
 use wasmer_deploy_cli::model::PortNopType;
@@ -67,6 +68,9 @@ pub struct Port
     pub(crate) mac: EthernetAddress,
     pub(crate) mac_drop: mpsc::Sender<HardwareAddress>,
     pub(crate) listen_sockets: HashMap<SocketHandle, iface::SocketHandle>,
+    pub(crate) listen_addrs: HashMap<SocketHandle, SocketAddr>,
+    pub(crate) accept_filters: HashMap<SocketHandle, AcceptFilter>,
+    pub(crate) aborted_sockets: Vec<iface::SocketHandle>,
     pub(crate) tcp_sockets: HashMap<SocketHandle, iface::SocketHandle>,
     pub(crate) udp_sockets: HashMap<SocketHandle, iface::SocketHandle>,
     pub(crate) raw_sockets: HashMap<SocketHandle, TapSocket>,
@@ -109,6 +113,9 @@ impl Port
             udp_sockets: Default::default(),
             tcp_sockets: Default::default(),
             listen_sockets: Default::default(),
+            listen_addrs: Default::default(),
+            accept_filters: Default::default(),
+            aborted_sockets: Default::default(),
             raw_sockets: Default::default(),
             icmp_sockets: Default::default(),
             dhcp_sockets: Default::default(),
@@ -258,6 +265,8 @@ impl Port
                     drop(socket);
                     self.iface.remove_socket(socket_handle);
                 }
+                self.listen_addrs.remove(&handle);
+                self.accept_filters.remove(&handle);
                 if let Some(socket_handle) = self.icmp_sockets.remove(&handle) {
                     self.iface.remove_socket(socket_handle);
                 }
@@ -414,6 +423,7 @@ impl Port
                             self.queue_error(handle, conv_err(err));
                         } else {
                             self.listen_sockets.insert(handle, self.iface.add_socket(socket));
+                            self.listen_addrs.insert(handle, local_addr);
                             self.queue_nop(handle, PortNopType::Listen);
                         }
                     },
@@ -582,6 +592,16 @@ impl Port
                     mac: HardwareAddress::from_bytes(self.mac.as_bytes())
                 });
             },
+            PortCommand::SetAcceptFilter {
+                handle,
+                filter,
+            } => {
+                if filter.is_empty() {
+                    self.accept_filters.remove(&handle);
+                } else {
+                    self.accept_filters.insert(handle, filter);
+                }
+            },
         }
         Ok(())
     }
@@ -641,10 +661,16 @@ impl Port
             .poll(Instant::now())
             .unwrap_or(false);
 
-        let wait_time = self.iface
+        let mut wait_time = self.iface
             .poll_delay(Instant::now())
             .unwrap_or(smoltcp::time::Duration::ZERO);
 
+        // Sockets that were aborted by the accept filter have now sent their
+        // reset to the peer and can be removed
+        for socket_handle in self.aborted_sockets.drain(..) {
+            self.iface.remove_socket(socket_handle);
+        }
+
         let mut ret = Vec::new();
         
         if readiness {
@@ -680,11 +706,18 @@ impl Port
             }
 
             let mut move_me = Vec::new();
+            let mut filter_me = Vec::new();
             for (handle, socket_handle) in self.listen_sockets.iter() {
                 let socket = self.iface.get_socket::<TcpSocket>(*socket_handle);
                 if socket.is_listening() == false {
                     if socket.is_active() {
                         let peer_addr = conv_addr(socket.remote_endpoint());
+                        if let Some(filter) = self.accept_filters.get(handle) {
+                            if filter.is_allowed(peer_addr.ip()) == false {
+                                filter_me.push((*handle, peer_addr));
+                                continue;
+                            }
+                        }
                         ret.push(PortResponse::TcpAccepted { handle: *handle, peer_addr });
                     } else {
                         ret.push(PortResponse::SocketError { handle: *handle, error: SocketErrorKind::ConnectionAborted.into() });
@@ -696,6 +729,36 @@ impl Port
                 if let Some(socket_handle) = self.listen_sockets.remove(&handle) {
                     self.tcp_sockets.insert(handle, socket_handle);
                 }
+                self.listen_addrs.remove(&handle);
+            }
+
+            // Connections that fail the accept filter are reset and the
+            // listener is replaced with a fresh one on the same address
+            for (handle, peer_addr) in filter_me {
+                trace!("connection from {} was filtered (handle={})", peer_addr, handle);
+                let local_addr = match self.listen_addrs.get(&handle) {
+                    Some(a) => *a,
+                    None => continue,
+                };
+                if let Some(socket_handle) = self.listen_sockets.remove(&handle) {
+                    let socket = self.iface.get_socket::<TcpSocket>(socket_handle);
+                    let hop_limit = socket.hop_limit();
+                    socket.abort();
+                    drop(socket);
+                    self.aborted_sockets.push(socket_handle);
+
+                    let rx_buffer = TcpSocketBuffer::new(self.ip_buf(self.buf_size));
+                    let tx_buffer = TcpSocketBuffer::new(self.ip_buf(self.buf_size));
+                    let mut socket = TcpSocket::new(rx_buffer, tx_buffer);
+                    socket.set_hop_limit(hop_limit);
+                    if let Err(err) = socket.listen(local_addr) {
+                        ret.push(PortResponse::SocketError { handle, error: conv_err(err).into() });
+                    } else {
+                        self.listen_sockets.insert(handle, self.iface.add_socket(socket));
+                    }
+                }
+                ret.push(PortResponse::TcpFiltered { handle, peer_addr });
+                wait_time = smoltcp::time::Duration::ZERO;
             }
 
             for (handle, socket_handle) in self.dhcp_sockets.iter() {
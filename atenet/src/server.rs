@@ -233,6 +233,14 @@ for Server
             path: path.to_string_lossy().to_string(),
             encryption: None,
             wire_format: SerializationFormat::Bincode,
+            multiplex: false,
+            version: MessageProtocolVersion::V3,
+            certificates: Vec::new(),
+            select_certificate: false,
+            keepalive: None,
+            replay_protection: false,
+            transcript: None,
+            peer_identity: None,
         };
         let hello_switch = SwitchHello {
             chain: chain.clone(),
@@ -246,7 +254,7 @@ for Server
             .split(None);
         let tx = Upstream {
             id: client_id,
-            outbox: tx,
+            outbox: tx.into(),
             wire_format: SerializationFormat::Bincode
         };
 
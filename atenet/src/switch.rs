@@ -1128,6 +1128,16 @@ impl Switch
                                 },
                             }
                         }
+                        Err(BusError(BusErrorKind::Lagged(cnt), _)) => {
+                            // Updates were dropped so the nodes are loaded again
+                            debug!("control thread lagged ({} updates dropped)", cnt);
+                            let state = self.control_plane.read().await;
+                            if let Ok(nodes) = state.inst.mesh_nodes.iter().await {
+                                for node in nodes {
+                                    self.update_node(node.key(), node.deref()).await;
+                                }
+                            }
+                        }
                         Err(err) => {
                             warn!("control thread closing (1) - {:?}", err);
                             break;
@@ -52,4 +52,51 @@ fn run_tcp(cross_switch: bool, use_dhcp: bool) {
         let test = s2.recv().await.unwrap();
         assert_eq!(test, vec![4,5,6]);
     })
-}
\ No newline at end of file
+}
+#[test]
+#[serial]
+fn tcp_accept_filter() {
+    use wasmer_bus_mio::prelude::IpCidr;
+
+    common::run(async move {
+        let _servers = common::setup().await;
+
+        let (c1, c2) = common::clients(false, false).await;
+        let c2_ip = c2.addr_ipv4().await.unwrap().unwrap();
+        let s1_addr = SocketAddr::V4(SocketAddrV4::new(c1.addr_ipv4().await.unwrap().unwrap(), 3000));
+        let s2_addr = SocketAddr::V4(SocketAddrV4::new(c2_ip, 3000));
+        let denied_addr = SocketAddr::V4(SocketAddrV4::new(c2_ip, 3001));
+
+        // Connections from the second client are denied by the host
+        let s1 = c1.listen_tcp(s1_addr).await.unwrap();
+        s1.set_accept_filter(Vec::new(), vec![IpCidr { ip: c2_ip.into(), prefix: 32 }]).await.unwrap();
+        let _ = tokio::time::timeout(
+            std::time::Duration::from_secs(2),
+            c2.connect_tcp(denied_addr, s1_addr)
+        ).await;
+        for _ in 0..100 {
+            if s1.filtered_connections() > 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert_eq!(s1.filtered_connections(), 1);
+        assert_eq!(s1.pending_connections(), 0);
+
+        // Once the filter is lifted the connection goes through and the
+        // peer address is returned with it
+        s1.set_accept_filter(Vec::new(), Vec::new()).await.unwrap();
+        tokio::task::spawn(async move {
+            let (socket, peer_addr) = s1.accept_with_addr().await.unwrap();
+            assert_eq!(peer_addr.ip(), std::net::IpAddr::V4(c2_ip));
+            let test = socket.recv().await.unwrap();
+            assert_eq!(test, vec![1,2,3]);
+            socket.send(vec![4,5,6]).await.unwrap();
+        });
+
+        let s2 = c2.connect_tcp(s2_addr, s1_addr).await.unwrap();
+        s2.send(vec![1,2,3]).await.unwrap();
+        let test = s2.recv().await.unwrap();
+        assert_eq!(test, vec![4,5,6]);
+    })
+}
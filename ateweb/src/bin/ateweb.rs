@@ -1,4 +1,13 @@
+use std::sync::Arc;
 use std::time::Duration;
+use ate::daemon::Daemon;
+use ate::mesh::AliasRoute;
+#[cfg(feature = "dashboard")]
+use ate::mesh::DashboardRoute;
+use ate::mesh::DrainRoute;
+use ate::mesh::MigrateRoute;
+use ate::mesh::StatsRoute;
+use ate::mesh::TasksRoute;
 use ate::utils::load_node_list;
 use wasmer_auth::flow::ChainFlow;
 #[allow(unused_imports)]
@@ -17,10 +26,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     ate::log_init(opts.verbose, opts.debug);
 
-    let mut conf = AteConfig::default();
-    conf.dns_sec = opts.dns_sec;
-    conf.dns_server = opts.dns_server;
-    conf.ntp_sync = opts.no_ntp == false;
+    let config = opts.config.map(|a| shellexpand::tilde(&a).to_string());
+    if opts.check_config {
+        match ConfDocument::check_file(config.unwrap_or_default()) {
+            true => return Ok(()),
+            false => std::process::exit(1),
+        }
+    }
+    let subcmd = match opts.subcmd {
+        Some(a) => a,
+        None => {
+            eprintln!("a subcommand is required (see --help)");
+            std::process::exit(2);
+        }
+    };
+
+    let mut conf = match config {
+        Some(path) => ConfAte::from_file(path)?,
+        None => {
+            let mut conf = AteConfig::default();
+            conf.dns_sec = opts.dns_sec;
+            conf.dns_server = opts.dns_server;
+            conf.ntp_sync = opts.no_ntp == false;
+            conf
+        }
+    };
 
     if let Some(pool) = opts.ntp_pool {
         conf.ntp_pool = pool;
@@ -30,7 +60,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Run the server
-    match opts.subcmd {
+    match subcmd {
         SubCommand::Auth(run) => {
             conf.nodes = load_node_list(run.nodes_list);
 
@@ -62,16 +92,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             cfg_mesh.wire_protocol = StreamProtocol::parse(&run.url)?;
             cfg_mesh.listen_certificate = Some(root_cert_key);
 
+            let mut daemon = Daemon::new();
+            if let Some(pid_file) = run.pid_file.as_ref() {
+                daemon = daemon.with_pid_file(shellexpand::tilde(pid_file).as_ref())?;
+            }
+
             let server = create_server(&cfg_mesh).await?;
             server.add_route(Box::new(flow), &conf).await?;
+            daemon.ready(&server);
 
-            // Wait for ctrl-c
-            let mut exit = ctrl_channel();
-            while *exit.borrow() == false {
-                exit.changed().await.unwrap();
-            }
-            println!("Shutting down...");
-            server.shutdown().await;
+            // Wait for ctrl-c (or for the service manager to stop the server)
+            daemon.run_until_shutdown(&server).await;
             println!("Goodbye!");
         }
 
@@ -136,6 +167,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 root.server_id(),
                 cfg_mesh.accept_timeout,
             );
+            if let Some(admin_token) = run.admin_token.clone() {
+                let drain = Arc::new(DrainRoute::new(&root, admin_token.clone()));
+                router.add_post_route("/admin/drain", drain).await;
+                let alias = Arc::new(AliasRoute::new(&root, admin_token.clone()));
+                router.add_post_route("/admin/alias", alias).await;
+                let migrate = Arc::new(MigrateRoute::new(&root, admin_token.clone()));
+                router.add_post_route("/admin/migrate", migrate).await;
+                let stats = Arc::new(StatsRoute::new(&root, admin_token.clone()));
+                router.add_post_route("/admin/stats", stats).await;
+                let tasks = Arc::new(TasksRoute::new(admin_token));
+                router.add_post_route("/admin/tasks", tasks).await;
+            }
+            #[cfg(feature = "dashboard")]
+            if let Some(dashboard_token) = run.dashboard_token.clone() {
+                let dashboard = Arc::new(DashboardRoute::new(&root, dashboard_token));
+                router.add_get_route("/admin/dashboard", dashboard).await;
+            }
+            #[cfg(not(feature = "dashboard"))]
+            if run.dashboard_token.is_some() {
+                warn!("the dashboard is disabled as ateweb was built without the dashboard feature");
+            }
             router.set_default_route(root);
 
             conf.log_path = Some(run.log_path);
@@ -155,12 +207,3 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
-
-fn ctrl_channel() -> tokio::sync::watch::Receiver<bool> {
-    let (sender, receiver) = tokio::sync::watch::channel(false);
-    ctrlc_async::set_handler(move || {
-        let _ = sender.send(true);
-    })
-    .unwrap();
-    receiver
-}
\ No newline at end of file
@@ -41,4 +41,7 @@ pub struct OptsAuth {
     /// Ensures that this authentication server runs as a specific node_id
     #[clap(short, long)]
     pub node_id: Option<u32>,
-}
\ No newline at end of file
+    /// File that the process ID is written to while the server runs
+    #[clap(long)]
+    pub pid_file: Option<String>,
+}
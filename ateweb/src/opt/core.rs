@@ -36,12 +36,19 @@ pub struct Opts {
     /// Address that DNS queries will be sent to
     #[clap(long, default_value = "8.8.8.8")]
     pub dns_server: String,
+    /// Configuration file (TOML, YAML or JSON) with an [ate] section - when supplied
+    /// the settings in the file are used instead of the DNS arguments
+    #[clap(long)]
+    pub config: Option<String>,
+    /// Validates the configuration file, reports every problem that it finds and exits
+    #[clap(long, requires = "config")]
+    pub check_config: bool,
     /// Token file to read that holds a previously created token to be used for this operation
     #[clap(long, default_value = "~/wasmer/token")]
     pub token_path: String,
 
     #[clap(subcommand)]
-    pub subcmd: SubCommand,
+    pub subcmd: Option<SubCommand>,
 }
 
 /// Runs a web server that will serve content from a Wasmer file system
@@ -120,6 +127,18 @@ pub struct OptsAll {
     /// Ensures that this authentication server runs as a specific node_id
     #[clap(short, long)]
     pub node_id: Option<u32>,
+    /// Access token that allows the datachain to be drained (before a restart)
+    /// by posting to /admin/drain, chain aliases to be managed by posting to
+    /// /admin/alias, chain statistics to be read by posting to /admin/stats and
+    /// the live tasks to be listed by posting to /admin/tasks (the routes are
+    /// disabled when not supplied)
+    #[clap(long)]
+    pub admin_token: Option<String>,
+    /// Access token that allows the read-only dashboard at
+    /// /admin/dashboard/index.html to be viewed (requires the dashboard
+    /// feature, the dashboard is disabled when not supplied)
+    #[clap(long)]
+    pub dashboard_token: Option<String>,
 }
 
 #[derive(Parser)]
@@ -4,6 +4,7 @@ use std::net::SocketAddr;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use http::*;
 use hyper::upgrade::Upgraded;
+use hyper::Body;
 use hyper_tungstenite::WebSocketStream;
 use std::result::Result;
 
@@ -49,4 +50,21 @@ impl ServerCallback for StreamRouter {
     ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
         StreamRouter::put_request(self, body, sock_addr, uri, headers).await
     }
+
+    async fn get_request(
+        &self,
+        sock_addr: SocketAddr,
+        uri: http::Uri,
+        headers: http::HeaderMap,
+    ) -> Option<Result<Vec<u8>, (Vec<u8>, StatusCode)>> {
+        StreamRouter::get_request(self, sock_addr, uri, headers).await
+    }
+
+    async fn grpc_request(
+        &self,
+        req: Request<Body>,
+        sock_addr: SocketAddr,
+    ) -> Result<Response<Body>, Request<Body>> {
+        StreamRouter::grpc_request(self, req, sock_addr).await
+    }
 }
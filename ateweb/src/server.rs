@@ -80,6 +80,27 @@ pub trait ServerCallback: Send + Sync {
         let msg = format!("Bad Request (Not Implemented)").as_bytes().to_vec();
         Err((msg, StatusCode::BAD_REQUEST))
     }
+
+    /// Hands the request back when no gRPC route handles it so that it is
+    /// processed as a normal web request instead
+    async fn grpc_request(
+        &self,
+        req: Request<Body>,
+        _sock_addr: SocketAddr,
+    ) -> Result<Response<Body>, Request<Body>> {
+        Err(req)
+    }
+
+    /// Returns None when no route handles the request so that it is served
+    /// from the static files instead
+    async fn get_request(
+        &self,
+        _sock_addr: SocketAddr,
+        _uri: http::Uri,
+        _headers: http::HeaderMap,
+    ) -> Option<Result<Vec<u8>, (Vec<u8>, StatusCode)>> {
+        None
+    }
 }
 
 pub struct Server {
@@ -124,6 +145,16 @@ async fn process(
     }
 }
 
+fn is_grpc_request(req: &Request<Body>) -> bool {
+    req.method() == Method::POST
+        && req
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|a| a.to_str().ok())
+            .map(|a| a.starts_with("application/grpc"))
+            .unwrap_or(false)
+}
+
 impl Server {
     pub(crate) async fn new(mut builder: ServerBuilder) -> Result<Arc<Server>, AteError>
     {
@@ -611,12 +642,28 @@ impl Server {
 
     pub(crate) async fn process(
         &self,
-        req: Request<Body>,
+        mut req: Request<Body>,
         sock_addr: SocketAddr,
         listen: &ServerListen,
     ) -> Result<Response<Body>, WebServerError> {
         trace!("req: {:?}", req);
 
+        // gRPC calls arrive over HTTP/2 (negotiated with ALPN when TLS is
+        // used, otherwise the client connects with prior knowledge)
+        if is_grpc_request(&req) {
+            if let Some(callback) = &self.callback {
+                trace!("perf-checkpoint: grpc request");
+                let uri = req.uri().clone();
+                req = match callback.grpc_request(req, sock_addr).await {
+                    Ok(resp) => {
+                        info!("grpc peer={} path={} - {}", sock_addr, uri, resp.status());
+                        return Ok(resp);
+                    }
+                    Err(req) => req,
+                };
+            }
+        }
+
         if hyper_tungstenite::is_upgrade_request(&req) {
             trace!("perf-checkpoint: hyper upgrade request");
             return self.process_upgrade(req, sock_addr).await;
@@ -663,6 +710,35 @@ impl Server {
             trace!("perf-checkpoint: finished put/post");
         }
 
+        if method == Method::GET {
+            if let Some(callback) = &self.callback {
+                let headers = req.headers().clone();
+                if let Some(ret) = callback.get_request(sock_addr, uri.clone(), headers).await {
+                    trace!("perf-checkpoint: routed get");
+                    let resp = match ret {
+                        Ok(resp) => {
+                            // Routes answer with JSON unless they serve a file
+                            // whose extension says otherwise (e.g. dashboards)
+                            let mut resp = Response::new(Body::from(resp));
+                            self.apply_mime(uri.path(), &mut resp)?;
+                            if resp.headers().contains_key("Content-Type") == false {
+                                resp.headers_mut()
+                                    .append("Content-Type", HeaderValue::from_static("application/json"));
+                            }
+                            resp
+                        }
+                        Err((resp, status)) => {
+                            let mut resp = Response::new(Body::from(resp));
+                            *resp.status_mut() = status;
+                            resp
+                        }
+                    };
+                    info!("http peer={} method={} path={} - {}", sock_addr, method, uri, resp.status());
+                    return Ok(resp);
+                }
+            }
+        }
+
         let is_head = method == Method::HEAD;
         let host = self.get_host(&req)?;
         let conf = self.get_conf(host.as_str()).await?;
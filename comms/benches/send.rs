@@ -0,0 +1,46 @@
+//! Micro-benchmark of the send path of the message protocols
+//!
+//! cargo bench -p ate-comms --bench send
+use std::time::Instant;
+
+use ate_comms::MessageProtocolApi;
+use ate_comms::MessageProtocolVersion;
+use ate_crypto::EncryptKey;
+use ate_crypto::KeySize;
+
+const ITERATIONS: usize = 100000;
+
+async fn bench(version: MessageProtocolVersion, wire_encryption: Option<EncryptKey>, len: usize) {
+    let mut proto = version.create(None, Some(Box::new(tokio::io::sink())));
+    let data = vec![7u8; len];
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        proto.send(&wire_encryption, &data[..]).await.unwrap();
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{} encrypted={} len={:>6}: {:>8.0} ns/msg {:>8.1} MB/s",
+        version,
+        wire_encryption.is_some(),
+        len,
+        elapsed.as_nanos() as f64 / ITERATIONS as f64,
+        (len * ITERATIONS) as f64 / elapsed.as_secs_f64() / 1048576f64
+    );
+}
+
+fn main() {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let key = EncryptKey::generate(KeySize::Bit256);
+        for version in [MessageProtocolVersion::V2, MessageProtocolVersion::V3] {
+            for len in [32usize, 1024, 65536] {
+                bench(version, None, len).await;
+                bench(version, Some(key.clone()), len).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,167 @@
+use chrono::DateTime;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use ate_crypto::AteHash;
+use ate_crypto::KeySize;
+use ate_crypto::PrivateEncryptKey;
+
+use super::CertificateValidation;
+
+/// Certificate that a server advertises in its hello, clients that pinned
+/// any of the advertised hashes can select it for the key exchange
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CertificateInfo {
+    pub hash: AteHash,
+    #[serde(default)]
+    pub not_before: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+impl CertificateInfo {
+    pub fn is_valid_at(&self, when: DateTime<Utc>) -> bool {
+        self.not_before.map(|a| a <= when).unwrap_or(true)
+            && self.not_after.map(|a| when < a).unwrap_or(true)
+    }
+}
+
+impl std::fmt::Display for CertificateInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let when = |a: Option<DateTime<Utc>>| match a {
+            Some(a) => a.to_rfc3339(),
+            None => "-".to_string(),
+        };
+        write!(
+            f,
+            "{} (not_before={}, not_after={})",
+            self.hash,
+            when(self.not_before),
+            when(self.not_after)
+        )
+    }
+}
+
+/// Picks the certificate that the client will use for the key exchange from
+/// those that the server advertised, the first one that passes the
+/// validation rules of the client is preferred
+pub fn select_certificate(
+    advertised: &[CertificateInfo],
+    validation: &CertificateValidation,
+) -> Option<AteHash> {
+    advertised
+        .iter()
+        .filter(|a| validation.validate(&a.hash))
+        .chain(advertised.iter())
+        .map(|a| a.hash.clone())
+        .next()
+}
+
+/// Certificate (private key) that a server accepts connections with and
+/// the window of time that it is valid for
+#[derive(Debug, Clone)]
+pub struct ServerCertificate {
+    pub key: PrivateEncryptKey,
+    pub hash: AteHash,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+impl ServerCertificate {
+    pub fn new(key: PrivateEncryptKey) -> ServerCertificate {
+        ServerCertificate {
+            hash: key.hash(),
+            key,
+            not_before: None,
+            not_after: None,
+        }
+    }
+
+    pub fn with_not_before(mut self, when: DateTime<Utc>) -> Self {
+        self.not_before = Some(when);
+        self
+    }
+
+    pub fn with_not_after(mut self, when: DateTime<Utc>) -> Self {
+        self.not_after = Some(when);
+        self
+    }
+
+    pub fn info(&self) -> CertificateInfo {
+        CertificateInfo {
+            hash: self.hash.clone(),
+            not_before: self.not_before,
+            not_after: self.not_after,
+        }
+    }
+
+    pub fn is_valid_at(&self, when: DateTime<Utc>) -> bool {
+        self.info().is_valid_at(when)
+    }
+}
+
+/// Set of certificates that a server holds, more than one is valid at the
+/// same time while a certificate is being rotated so that clients pinned to
+/// either the old or the new one can still connect
+#[derive(Debug, Clone, Default)]
+pub struct ServerCertificates {
+    certs: Vec<ServerCertificate>,
+}
+
+impl ServerCertificates {
+    pub fn new(certs: Vec<ServerCertificate>) -> ServerCertificates {
+        ServerCertificates { certs }
+    }
+
+    pub fn push(&mut self, cert: ServerCertificate) {
+        self.certs.retain(|a| a.hash != cert.hash);
+        self.certs.push(cert);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ServerCertificate> {
+        self.certs.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.certs.is_empty()
+    }
+
+    /// Removes the certificates whose validity window has passed
+    pub fn prune(&mut self, now: DateTime<Utc>) {
+        self.certs.retain(|a| a.not_after.map(|a| now < a).unwrap_or(true));
+    }
+
+    /// Certificates that are currently valid (oldest first)
+    pub fn valid(&self, now: DateTime<Utc>) -> Vec<&ServerCertificate> {
+        self.certs.iter().filter(|a| a.is_valid_at(now)).collect()
+    }
+
+    /// Certificate that is used with clients that can not select one, this
+    /// is the oldest valid certificate so that clients that pinned it keep
+    /// working until it expires
+    pub fn primary(&self, now: DateTime<Utc>) -> Option<&ServerCertificate> {
+        self.valid(now).into_iter().next()
+    }
+
+    /// Finds the currently valid certificate with a particular hash
+    pub fn find(&self, hash: &AteHash, now: DateTime<Utc>) -> Option<&ServerCertificate> {
+        self.valid(now).into_iter().filter(|a| a.hash == *hash).next()
+    }
+
+    /// Certificates that are advertised to clients in the hello
+    pub fn advertise(&self, now: DateTime<Utc>) -> Vec<CertificateInfo> {
+        self.valid(now).into_iter().map(|a| a.info()).collect()
+    }
+
+    /// Size of the strongest certificate that is currently valid
+    pub fn max_size(&self, now: DateTime<Utc>) -> Option<KeySize> {
+        self.valid(now).into_iter().map(|a| a.key.size()).max()
+    }
+}
+
+impl From<Option<PrivateEncryptKey>> for ServerCertificates {
+    fn from(cert: Option<PrivateEncryptKey>) -> ServerCertificates {
+        ServerCertificates {
+            certs: cert.into_iter().map(ServerCertificate::new).collect(),
+        }
+    }
+}
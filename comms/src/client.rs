@@ -86,11 +86,15 @@ impl StreamClient
 
         // We only encrypt if it actually has a certificate (otherwise
         // a simple man-in-the-middle could intercept anyway)
+        #[cfg(feature = "asymmetric")]
         let key_size = if security.quantum_encryption(https) == true {
             Some(KeySize::Bit192)
         } else {
             None
         };
+        // (without asymmetric crypto the keys can not be exchanged)
+        #[cfg(not(feature = "asymmetric"))]
+        let key_size = None;
 
         // Say hello
         let node_id = NodeId::generate_client_id();
@@ -101,24 +105,42 @@ impl StreamClient
             path.to_string(),
             domain.to_string(),
             key_size,
+            false,
+            crate::MessageProtocolVersion::default(),
+            None,
         )
         .await?;
 
         // If we are using wire encryption then exchange secrets
-        #[cfg(feature = "quantum")]
+        #[cfg(feature = "asymmetric")]
         let ek = match hello_metadata.encryption {
-            Some(key_size) => Some(
-                super::key_exchange::mesh_key_exchange_sender(
+            Some(key_size) => {
+                let selected = hello_metadata.selected_certificate(&validation);
+                let (ek, _) = super::key_exchange::mesh_key_exchange_sender_select(
                     proto.deref_mut(),
                     key_size,
                     validation,
+                    selected,
                 )
-                .await?,
-            ),
+                .await?;
+                Some(ek)
+            }
+            None => None,
+        };
+        #[cfg(not(feature = "asymmetric"))]
+        let ek = match hello_metadata.encryption {
+            Some(_) => {
+                return Err(Box::new(io::Error::new(io::ErrorKind::Unsupported, "the server requires wire encryption which is not supported without the 'asymmetric' feature")));
+            }
             None => None,
         };
-        #[cfg(not(feature = "quantum"))]
-        let ek = None;
+
+        // Make sure no one tampered with the hello messages
+        if let (Some(ek), Some(transcript)) = (ek.as_ref(), hello_metadata.transcript.as_ref()) {
+            if super::hello::mesh_hello_verify(proto.deref_mut(), ek, transcript, true).await? == false {
+                return Err(Box::new(io::Error::new(io::ErrorKind::InvalidData, "the hello exchange was tampered with")));
+            }
+        }
 
         // Create the rx and tx message streams
         let (rx, tx) = proto.split(ek);
@@ -3,13 +3,19 @@ use tokio::io::AsyncRead;
 use tokio::io::AsyncWrite;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use ate_crypto::AteHash;
+use ate_crypto::EncryptKey;
 use ate_crypto::KeySize;
 use ate_crypto::NodeId;
 use ate_crypto::SerializationFormat;
 use serde::{Deserialize, Serialize};
 
+use super::protocol::KeepAlive;
 use super::protocol::MessageProtocolVersion;
 use super::protocol::MessageProtocolApi;
+use super::certificates::select_certificate;
+use super::certificates::CertificateInfo;
+use super::CertificateValidation;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct HelloMetadata {
@@ -18,6 +24,74 @@ pub struct HelloMetadata {
     pub path: String,
     pub encryption: Option<KeySize>,
     pub wire_format: SerializationFormat,
+    /// When true the frames on this stream are prefixed with a channel id
+    /// so that multiple chains can share the same connection
+    #[serde(default)]
+    pub multiplex: bool,
+    /// Version of the stream protocol that was negotiated
+    #[serde(default = "default_stream_protocol_version")]
+    pub version: MessageProtocolVersion,
+    /// Certificates that the server currently accepts key exchanges with
+    #[serde(default)]
+    pub certificates: Vec<CertificateInfo>,
+    /// When true the client tells the server which of the advertised
+    /// certificates it selected before the key exchange starts
+    #[serde(default)]
+    pub select_certificate: bool,
+    /// Keepalive that both sides agreed on (None when either side did not
+    /// ask for one or the protocol version can not carry it)
+    #[serde(default)]
+    pub keepalive: Option<KeepAlive>,
+    /// When true the encrypted frames carry sequence numbers so that frames
+    /// which are replayed by a man-in-the-middle abort the connection (older
+    /// peers never negotiate it)
+    #[serde(default)]
+    pub replay_protection: bool,
+    /// Exact bytes of the hello messages when both sides agreed to
+    /// authenticate them after the key exchange (see `mesh_hello_verify`)
+    #[serde(skip)]
+    pub transcript: Option<HelloTranscript>,
+    /// Identity that the client proved with a certificate during the TLS
+    /// handshake (only set by servers that authenticate their clients)
+    #[serde(skip)]
+    pub peer_identity: Option<TlsPeerIdentity>,
+}
+
+/// Certificates that the peer presented (and the server verified) while the
+/// TLS tunnel that the stream runs over was established
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsPeerIdentity {
+    /// DER encoded certificate chain of the peer, the first is its own
+    pub certificates: Vec<Vec<u8>>,
+}
+
+impl HelloMetadata {
+    /// Returns the certificate that the client should select for the key
+    /// exchange (or None if the server does not support selecting one)
+    pub fn selected_certificate(&self, validation: &CertificateValidation) -> Option<AteHash> {
+        match self.select_certificate {
+            true => select_certificate(&self.certificates[..], validation),
+            false => None,
+        }
+    }
+}
+
+impl TlsPeerIdentity {
+    /// Hash of the certificate of the peer which can be used to identify it
+    pub fn hash(&self) -> Option<AteHash> {
+        self.certificates
+            .first()
+            .map(|a| AteHash::from_bytes(&a[..]))
+    }
+}
+
+/// Hello messages exactly as they were sent and received by one side of
+/// the connection, a man-in-the-middle that rewrites either of them (e.g. to
+/// downgrade the key size) makes the two sides see different transcripts
+#[derive(Debug, Clone, Default)]
+pub struct HelloTranscript {
+    pub client: Vec<u8>,
+    pub server: Vec<u8>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -28,12 +102,56 @@ struct SenderHello {
     pub key_size: Option<KeySize>,
     #[serde(default = "default_stream_protocol_version")]
     pub version: MessageProtocolVersion,
+    #[serde(default)]
+    pub multiplex: bool,
+    #[serde(default)]
+    pub authenticate: bool,
+    /// Every version of the stream protocol that the sender supports, older
+    /// senders leave this empty which means every version up to `version`
+    #[serde(default)]
+    pub versions: Vec<u16>,
+    /// Sender is able to select one of the certificates that the receiver advertises
+    #[serde(default)]
+    pub select_certificate: bool,
+    /// Keepalive that the sender would like to use
+    #[serde(default)]
+    pub keepalive: Option<KeepAlive>,
+    /// Sender is able to add sequence numbers to the encrypted frames
+    #[serde(default)]
+    pub replay_protection: bool,
 }
 
 fn default_stream_protocol_version() -> MessageProtocolVersion {
     MessageProtocolVersion::V1
 }
 
+/// Sent in place of the hello of the receiver when the two sides have no
+/// version of the stream protocol in common, the error is plain text so that
+/// senders that predate this message still log something legible
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct VersionMismatch {
+    pub error: String,
+    pub server_supports: Vec<u16>,
+}
+
+fn version_mismatch_error(server_supports: &[u16], client_supports: &[u16]) -> io::Error {
+    let list = |versions: &[u16]| {
+        versions
+            .iter()
+            .map(|a| format!("v{}", a))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "no common stream protocol version - the server supports [{}] while the client supports [{}]",
+            list(server_supports),
+            list(client_supports)
+        ),
+    )
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct ReceiverHello {
     pub id: NodeId,
@@ -41,6 +159,18 @@ struct ReceiverHello {
     pub wire_format: SerializationFormat,
     #[serde(default = "default_stream_protocol_version")]
     pub version: MessageProtocolVersion,
+    #[serde(default)]
+    pub multiplex: bool,
+    #[serde(default)]
+    pub authenticate: bool,
+    #[serde(default)]
+    pub certificates: Vec<CertificateInfo>,
+    #[serde(default)]
+    pub select_certificate: bool,
+    #[serde(default)]
+    pub keepalive: Option<KeepAlive>,
+    #[serde(default)]
+    pub replay_protection: bool,
 }
 
 pub async fn mesh_hello_exchange_sender(
@@ -50,18 +180,28 @@ pub async fn mesh_hello_exchange_sender(
     hello_path: String,
     domain: String,
     key_size: Option<KeySize>,
+    multiplex: bool,
+    max_version: MessageProtocolVersion,
+    keepalive: Option<KeepAlive>,
 ) -> tokio::io::Result<(
     Box<dyn MessageProtocolApi + Send + Sync + 'static>,
     HelloMetadata
 )> {
     // Send over the hello message and wait for a response
     trace!("client sending hello");
+    let versions = max_version.supported_up_to();
     let hello_client = SenderHello {
         id: client_id,
         path: hello_path.clone(),
         domain,
         key_size,
-        version: MessageProtocolVersion::default(),
+        version: max_version,
+        multiplex,
+        authenticate: true,
+        versions: versions.clone(),
+        select_certificate: true,
+        keepalive,
+        replay_protection: true,
     };
     let hello_client_bytes = serde_json::to_vec(&hello_client)?;
     let mut proto = MessageProtocolVersion::V1.create(
@@ -76,7 +216,15 @@ pub async fn mesh_hello_exchange_sender(
     let hello_server_bytes = proto.read_with_fixed_16bit_header().await?;
     trace!("client received hello from server");
     trace!("{}", String::from_utf8_lossy(&hello_server_bytes[..]));
-    let hello_server: ReceiverHello = serde_json::from_slice(&hello_server_bytes[..])?;
+    let hello_server: ReceiverHello = match serde_json::from_slice(&hello_server_bytes[..]) {
+        Ok(a) => a,
+        Err(err) => {
+            if let Ok(mismatch) = serde_json::from_slice::<VersionMismatch>(&hello_server_bytes[..]) {
+                return Err(version_mismatch_error(&mismatch.server_supports[..], &versions[..]));
+            }
+            return Err(err.into());
+        }
+    };
 
     // Validate the encryption is strong enough
     if let Some(needed_size) = &key_size {
@@ -94,6 +242,30 @@ pub async fn mesh_hello_exchange_sender(
     // Switch to the correct protocol version
     let version = hello_server.version.min(hello_client.version);
     proto = version.upgrade(proto);
+
+    // The server answers with the keepalive that both sides will use (older
+    // servers leave it out which means there is none)
+    let keepalive = match hello_server.keepalive {
+        Some(a) if hello_client.keepalive.is_some() && version.supports_keepalive() => Some(a),
+        _ => None,
+    };
+    proto.set_keepalive(keepalive);
+
+    // Sequence numbers are only added when the server agreed to check them
+    let replay_protection = hello_server.replay_protection && version.supports_replay_protection();
+    proto.set_replay_protection(replay_protection);
+
+    // Multiplexing is only used when both sides asked for it
+    let multiplex = hello_client.multiplex && hello_server.multiplex && version.supports_multiplex();
+
+    // Keep the hello messages so they can be authenticated after the key exchange
+    let transcript = match hello_server.authenticate {
+        true => Some(HelloTranscript {
+            client: hello_client_bytes,
+            server: hello_server_bytes,
+        }),
+        false => None,
+    };
     
     // Upgrade the key_size if the server is bigger
     trace!(
@@ -113,20 +285,55 @@ pub async fn mesh_hello_exchange_sender(
             path: hello_path,
             encryption: hello_server.encryption,
             wire_format: hello_server.wire_format,
+            multiplex,
+            version,
+            certificates: hello_server.certificates,
+            select_certificate: hello_client.select_certificate && hello_server.select_certificate,
+            keepalive,
+            replay_protection,
+            transcript,
+            peer_identity: None,
         }
     ))
 }
 
-pub async fn mesh_hello_exchange_receiver(
+/// The `multiplex` callback is given the path requested by the client and
+/// returns true if the route behind that path understands multiplexed frames
+pub async fn mesh_hello_exchange_receiver<F>(
     stream_rx: Box<dyn AsyncRead + Send + Sync + Unpin + 'static>,
     stream_tx: Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>,
     server_id: NodeId,
     key_size: Option<KeySize>,
     wire_format: SerializationFormat,
+    multiplex: F,
+    max_version: MessageProtocolVersion,
 ) -> tokio::io::Result<(
     Box<dyn MessageProtocolApi + Send + Sync + 'static>,
     HelloMetadata
 )>
+where F: Fn(&str) -> bool
+{
+    mesh_hello_receive(stream_rx, stream_tx)
+        .await?
+        .reply(server_id, key_size, wire_format, multiplex, max_version)
+        .await
+}
+
+/// Hello that was received from a client but not yet answered, splitting the
+/// exchange in two lets the server bound how long each half may take
+pub struct HelloReceived {
+    proto: Box<dyn MessageProtocolApi + Send + Sync + 'static>,
+    hello_client: SenderHello,
+    hello_client_bytes: Vec<u8>,
+    certificates: Vec<CertificateInfo>,
+    keepalive: Option<KeepAlive>,
+}
+
+/// Reads the hello message that a client sends when it first connects
+pub async fn mesh_hello_receive(
+    stream_rx: Box<dyn AsyncRead + Send + Sync + Unpin + 'static>,
+    stream_tx: Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>,
+) -> tokio::io::Result<HelloReceived>
 {
     // Read the hello message from the other side
     let mut proto = MessageProtocolVersion::V1.create(
@@ -140,37 +347,192 @@ pub async fn mesh_hello_exchange_receiver(
     //trace!("server received hello from client: {}", String::from_utf8_lossy(&hello_client_bytes[..]));
     let hello_client: SenderHello = serde_json::from_slice(&hello_client_bytes[..])?;
 
-    // Upgrade the key_size if the client is bigger
-    let encryption = mesh_hello_upgrade_key(key_size, hello_client.key_size);
+    Ok(HelloReceived {
+        proto,
+        hello_client,
+        hello_client_bytes,
+        certificates: Vec::new(),
+        keepalive: None,
+    })
+}
 
-    // Send over the hello message and wait for a response
-    trace!("server sending hello (wire_format={})", wire_format);
-    let hello_server = ReceiverHello {
-        id: server_id,
-        encryption,
-        wire_format,
-        version: MessageProtocolVersion::default(),
-    };
-    let hello_server_bytes = serde_json::to_vec(&hello_server)?;
-    proto
-        .write_with_fixed_16bit_header(&hello_server_bytes[..], false)
-        .await?;
+impl HelloReceived {
+    /// Path that the client asked to connect to
+    pub fn path(&self) -> &str {
+        self.hello_client.path.as_str()
+    }
 
-    // Switch to the correct protocol version
-    proto = hello_server.version
-        .min(hello_client.version)
-        .upgrade(proto);
+    /// Advertises the certificates that the server accepts key exchanges
+    /// with (clients that support it then select which one to use)
+    pub fn with_certificates(mut self, certificates: Vec<CertificateInfo>) -> Self {
+        self.certificates = certificates;
+        self
+    }
 
-    Ok((
-        proto,
-        HelloMetadata {
-            client_id: hello_client.id,
-            server_id,
-            path: hello_client.path,
+    /// Offers a keepalive to the client, it is only used when the client
+    /// asks for one too
+    pub fn with_keepalive(mut self, keepalive: Option<KeepAlive>) -> Self {
+        self.keepalive = keepalive;
+        self
+    }
+
+    /// Answers the hello of the client and negotiates the protocol, the
+    /// highest version that both sides support (up to `max_version`) is used
+    pub async fn reply<F>(
+        self,
+        server_id: NodeId,
+        key_size: Option<KeySize>,
+        wire_format: SerializationFormat,
+        multiplex: F,
+        max_version: MessageProtocolVersion,
+    ) -> tokio::io::Result<(
+        Box<dyn MessageProtocolApi + Send + Sync + 'static>,
+        HelloMetadata
+    )>
+    where F: Fn(&str) -> bool
+    {
+        let mut proto = self.proto;
+        let hello_client = self.hello_client;
+
+        // Pick the highest version that both sides support (older clients only
+        // tell us their highest version as they support everything below it)
+        let client_versions = match hello_client.versions.is_empty() {
+            true => hello_client.version.supported_up_to(),
+            false => hello_client.versions.clone(),
+        };
+        let server_versions = max_version.supported_up_to();
+        let version = server_versions
+            .iter()
+            .rev()
+            .filter(|a| client_versions.contains(a))
+            .filter_map(|a| MessageProtocolVersion::from_u16(*a))
+            .next();
+        let version = match version {
+            Some(a) => a,
+            None => {
+                let err = version_mismatch_error(&server_versions[..], &client_versions[..]);
+                debug!("{}", err);
+                let mismatch = VersionMismatch {
+                    error: err.to_string(),
+                    server_supports: server_versions,
+                };
+                let mismatch_bytes = serde_json::to_vec(&mismatch)?;
+                proto
+                    .write_with_fixed_16bit_header(&mismatch_bytes[..], false)
+                    .await?;
+                return Err(err);
+            }
+        };
+
+        // Upgrade the key_size if the client is bigger
+        let encryption = mesh_hello_upgrade_key(key_size, hello_client.key_size);
+
+        // Send over the hello message and wait for a response
+        trace!("server sending hello (wire_format={})", wire_format);
+        let select_certificate = hello_client.select_certificate && self.certificates.is_empty() == false;
+        let keepalive = KeepAlive::negotiate(self.keepalive, hello_client.keepalive, version);
+        let replay_protection = hello_client.replay_protection && version.supports_replay_protection();
+        let hello_server = ReceiverHello {
+            id: server_id,
             encryption,
             wire_format,
-        }
-    ))
+            version,
+            multiplex: multiplex(hello_client.path.as_str()),
+            authenticate: hello_client.authenticate,
+            certificates: self.certificates,
+            select_certificate,
+            keepalive,
+            replay_protection,
+        };
+        let hello_server_bytes = serde_json::to_vec(&hello_server)?;
+        proto
+            .write_with_fixed_16bit_header(&hello_server_bytes[..], false)
+            .await?;
+
+        // Switch to the correct protocol version
+        proto = version.upgrade(proto);
+        proto.set_keepalive(keepalive);
+        proto.set_replay_protection(replay_protection);
+        let multiplex = hello_client.multiplex && hello_server.multiplex && version.supports_multiplex();
+
+        // Keep the hello messages so they can be authenticated after the key exchange
+        let transcript = match hello_server.authenticate {
+            true => Some(HelloTranscript {
+                client: self.hello_client_bytes,
+                server: hello_server_bytes,
+            }),
+            false => None,
+        };
+
+        Ok((
+            proto,
+            HelloMetadata {
+                client_id: hello_client.id,
+                server_id,
+                path: hello_client.path,
+                encryption,
+                wire_format,
+                multiplex,
+                version,
+                certificates: hello_server.certificates,
+                select_certificate,
+                keepalive,
+                replay_protection,
+                transcript,
+                peer_identity: None,
+            }
+        ))
+    }
+}
+
+/// Once the key exchange has completed both sides send a MAC (keyed with the
+/// shared secret) of the hello messages as they saw them, returns false when
+/// the MAC from the other side does not match which means that one of the
+/// hello messages was tampered with in transit
+pub async fn mesh_hello_verify(
+    proto: &mut (dyn MessageProtocolApi + Send + Sync + 'static),
+    ek: &EncryptKey,
+    transcript: &HelloTranscript,
+    is_client: bool,
+) -> io::Result<bool> {
+    let ours = mesh_hello_mac(ek, transcript, is_client);
+    let expected = mesh_hello_mac(ek, transcript, !is_client);
+
+    // The client speaks first so that the two sides never wait on each other
+    let theirs = if is_client {
+        proto.write_with_fixed_16bit_header(ours.as_bytes(), false).await?;
+        proto.read_with_fixed_16bit_header().await?
+    } else {
+        let theirs = proto.read_with_fixed_16bit_header().await?;
+        proto.write_with_fixed_16bit_header(ours.as_bytes(), false).await?;
+        theirs
+    };
+
+    // Compare in constant time
+    let expected = expected.as_bytes();
+    if theirs.len() != expected.len() {
+        return Ok(false);
+    }
+    let diff = theirs
+        .iter()
+        .zip(expected.iter())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    Ok(diff == 0)
+}
+
+fn mesh_hello_mac(ek: &EncryptKey, transcript: &HelloTranscript, is_client: bool) -> AteHash {
+    // Each side uses its own label so that a MAC can not be reflected back
+    let label: &[u8] = match is_client {
+        true => b"ate-hello-client",
+        false => b"ate-hello-server",
+    };
+    let mut data = Vec::with_capacity(label.len() + 16 + transcript.client.len() + transcript.server.len());
+    data.extend_from_slice(label);
+    data.extend_from_slice(&(transcript.client.len() as u64).to_be_bytes());
+    data.extend_from_slice(&transcript.client[..]);
+    data.extend_from_slice(&(transcript.server.len() as u64).to_be_bytes());
+    data.extend_from_slice(&transcript.server[..]);
+    AteHash::from_bytes_twice(ek.value(), &data[..])
 }
 
 fn mesh_hello_upgrade_key(key1: Option<KeySize>, key2: Option<KeySize>) -> Option<KeySize> {
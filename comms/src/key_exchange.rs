@@ -3,7 +3,9 @@ use std::io;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use ate_crypto::KeySize;
 use ate_crypto::PrivateEncryptKey;
+use ate_crypto::AteHash;
 use ate_crypto::EncryptKey;
+#[cfg(feature = "asymmetric")]
 use ate_crypto::InitializationVector;
 use ate_crypto::PublicEncryptKey;
 
@@ -15,8 +17,66 @@ pub async fn mesh_key_exchange_sender(
     key_size: KeySize,
     validation: CertificateValidation,
 ) -> io::Result<EncryptKey> {
+    mesh_key_exchange_sender_ext(proto, key_size, validation)
+        .await
+        .map(|(ek, _)| ek)
+}
+
+/// Performs the key exchange and also returns the certificate (public key)
+/// that the server presented during the exchange
+pub async fn mesh_key_exchange_sender_ext(
+    proto: &mut (dyn MessageProtocolApi + Send + Sync + 'static),
+    key_size: KeySize,
+    validation: CertificateValidation,
+) -> io::Result<(EncryptKey, PublicEncryptKey)> {
+    mesh_key_exchange_sender_select(proto, key_size, validation, None).await
+}
+
+/// Without asymmetric crypto no keys can be exchanged
+#[cfg(not(feature = "asymmetric"))]
+fn key_exchange_unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "wire encryption is not supported without the 'asymmetric' feature",
+    )
+}
+
+#[cfg(not(feature = "asymmetric"))]
+pub async fn mesh_key_exchange_sender_select(
+    _proto: &mut (dyn MessageProtocolApi + Send + Sync + 'static),
+    _key_size: KeySize,
+    _validation: CertificateValidation,
+    _selected: Option<AteHash>,
+) -> io::Result<(EncryptKey, PublicEncryptKey)> {
+    Err(key_exchange_unsupported())
+}
+
+#[cfg(not(feature = "asymmetric"))]
+pub async fn mesh_key_exchange_receiver(
+    _proto: &mut (dyn MessageProtocolApi + Send + Sync + 'static),
+    _server_key: PrivateEncryptKey,
+) -> io::Result<EncryptKey> {
+    Err(key_exchange_unsupported())
+}
+
+/// Performs the key exchange using a particular certificate of the server,
+/// this may only be used when the server agreed (in the hello) to let the
+/// client select one of the certificates that it advertised
+#[cfg(feature = "asymmetric")]
+pub async fn mesh_key_exchange_sender_select(
+    proto: &mut (dyn MessageProtocolApi + Send + Sync + 'static),
+    key_size: KeySize,
+    validation: CertificateValidation,
+    selected: Option<AteHash>,
+) -> io::Result<(EncryptKey, PublicEncryptKey)> {
     trace!("negotiating {}bit shared secret", key_size);
 
+    // Tell the server which of its certificates we will be using
+    if let Some(selected) = selected.as_ref() {
+        trace!("client selecting certificate {}", selected);
+        proto.write_with_fixed_16bit_header(selected.to_hex_string().as_bytes(), true).await?;
+    }
+
     // Generate the encryption keys
     let sk1 = PrivateEncryptKey::generate(key_size);
     let pk1 = sk1.as_public_key();
@@ -47,6 +107,13 @@ pub async fn mesh_key_exchange_sender(
         }
     };
 
+    // The server must use the certificate that was selected
+    if let Some(selected) = selected {
+        if pk2.hash() != selected {
+            return Err(io::Error::new(io::ErrorKind::ConnectionRefused, "The server did not use the certificate that the client selected."));
+        }
+    }
+
     // Validate the public key against our validation rules
     if validation.validate(&pk2.hash()) == false {
         return Err(io::Error::new(io::ErrorKind::ConnectionRefused, "The server certificate failed the clients validation check."));
@@ -59,9 +126,10 @@ pub async fn mesh_key_exchange_sender(
 
     // Merge the two halfs to make one shared secret
     trace!("client shared secret established");
-    Ok(EncryptKey::xor(&ek1, &ek2))
+    Ok((EncryptKey::xor(&ek1, &ek2), pk2))
 }
 
+#[cfg(feature = "asymmetric")]
 pub async fn mesh_key_exchange_receiver(
     proto: &mut (dyn MessageProtocolApi + Send + Sync + 'static),
     server_key: PrivateEncryptKey,
@@ -106,3 +174,24 @@ pub async fn mesh_key_exchange_receiver(
     trace!("server shared secret established");
     Ok(EncryptKey::xor(&ek1, &ek2))
 }
+
+/// Receives the certificate that the client selected and then performs the
+/// key exchange with it
+pub async fn mesh_key_exchange_receiver_select(
+    proto: &mut (dyn MessageProtocolApi + Send + Sync + 'static),
+    certificates: &[PrivateEncryptKey],
+) -> io::Result<EncryptKey> {
+    let selected = proto.read_with_fixed_16bit_header().await?;
+    let selected = String::from_utf8_lossy(&selected[..]).to_string();
+    let server_key = AteHash::from_hex_string(selected.as_str())
+        .and_then(|hash| certificates.iter().filter(|a| a.hash() == hash).next());
+    let server_key = match server_key {
+        Some(a) => a.clone(),
+        None => {
+            return Err(io::Error::new(io::ErrorKind::ConnectionRefused, format!("The client selected a certificate ({}) that the server does not hold.", selected)));
+        }
+    };
+    trace!("server using selected certificate {}", selected);
+
+    mesh_key_exchange_receiver(proto, server_key).await
+}
@@ -1,15 +1,18 @@
 mod client;
 mod hello;
-#[cfg(feature = "quantum")]
 mod key_exchange;
 mod protocol;
 mod certificate_validation;
+mod certificates;
 #[cfg(feature = "dns")]
 #[cfg(not(target_family = "wasm"))]
 mod dns;
 mod security;
+mod tap;
 
 pub use protocol::MessageProtocolVersion;
+pub use protocol::KeepAlive;
+pub use protocol::ReplayError;
 pub use protocol::MessageProtocolApi;
 pub use protocol::StreamReadable;
 pub use protocol::StreamWritable;
@@ -17,17 +20,33 @@ pub use protocol::AsyncStream;
 pub use hello::HelloMetadata;
 pub use hello::mesh_hello_exchange_sender;
 pub use hello::mesh_hello_exchange_receiver;
-#[cfg(feature = "quantum")]
+pub use hello::mesh_hello_receive;
+pub use hello::HelloReceived;
+pub use hello::HelloTranscript;
+pub use hello::TlsPeerIdentity;
+pub use hello::mesh_hello_verify;
 pub use key_exchange::mesh_key_exchange_sender;
-#[cfg(feature = "quantum")]
+pub use key_exchange::mesh_key_exchange_sender_ext;
 pub use key_exchange::mesh_key_exchange_receiver;
+pub use key_exchange::mesh_key_exchange_sender_select;
+pub use key_exchange::mesh_key_exchange_receiver_select;
 
 pub use certificate_validation::CertificateValidation;
 pub use certificate_validation::add_global_certificate;
 pub use certificate_validation::get_global_certificates;
+pub use certificates::CertificateInfo;
+pub use certificates::select_certificate;
+pub use certificates::ServerCertificate;
+pub use certificates::ServerCertificates;
 pub use protocol::StreamRx;
 pub use protocol::StreamTx;
 pub use security::StreamSecurity;
+pub use tap::PacketTap;
+pub use tap::PacketTapHook;
+pub use tap::PcapngTap;
+pub use tap::HexDumpTap;
+pub use tap::PACKET_TAP_LINK_TYPE;
+pub use tap::PACKET_TAP_HEADER_LEN;
 pub use client::StreamClient;
 #[cfg(feature = "dns")]
 #[cfg(not(target_family = "wasm"))]
@@ -8,6 +8,7 @@ use tokio::io::AsyncWrite;
 use async_trait::async_trait;
 use ate_crypto::EncryptKey;
 
+use super::KeepAlive;
 use super::StreamRx;
 use super::StreamTx;
 
@@ -33,6 +34,18 @@ where Self: std::fmt::Debug + Send + Sync,
         data: &[u8],
     ) -> Result<u64, tokio::io::Error>;
 
+    /// Sends a message that is made up of several parts as if it were one
+    /// contiguous buffer (protocols that support vectored writes avoid
+    /// concatenating the parts)
+    async fn send_parts(
+        &mut self,
+        wire_encryption: &Option<EncryptKey>,
+        parts: &[&[u8]],
+    ) -> Result<u64, tokio::io::Error> {
+        let data = parts.concat();
+        self.send(wire_encryption, &data[..]).await
+    }
+
     async fn read_with_fixed_16bit_header(
         &mut self,
     ) -> Result<Vec<u8>, tokio::io::Error>;
@@ -55,6 +68,18 @@ where Self: std::fmt::Debug + Send + Sync,
         &mut self,
     ) -> std::io::Result<()>;
 
+    /// Turns on the keepalive that was negotiated during the hello, the
+    /// ping and pong frames are handled while reading and never returned
+    /// (protocols that can not carry them ignore this)
+    fn set_keepalive(&mut self, _keepalive: Option<KeepAlive>) {
+    }
+
+    /// Turns on the replay protection that was negotiated during the hello,
+    /// every encrypted frame then carries a sequence number that the peer
+    /// checks (protocols that can not carry it ignore this)
+    fn set_replay_protection(&mut self, _enabled: bool) {
+    }
+
     fn split(&mut self, ek: Option<EncryptKey>) -> (StreamRx, StreamTx);
 
     fn rx(&mut self) -> Option<&mut (dyn AsyncRead + Send + Sync + Unpin + 'static)>;
@@ -0,0 +1,94 @@
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::Arc;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWrite;
+
+use super::MessageProtocolVersion;
+
+/// Keepalive of a connection, each side proposes one during the hello and
+/// the server answers with the one that both sides will use
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepAlive {
+    /// Time without any frames from the peer after which it is sent a ping
+    pub interval_ms: u64,
+    /// Time that the peer is given to answer a ping with a pong
+    pub timeout_ms: u64,
+    /// Number of pongs in a row that the peer may miss before it is
+    /// considered to be dead and the connection is dropped
+    pub max_missed: u32,
+}
+
+impl KeepAlive {
+    pub fn new(interval: Duration, timeout: Duration, max_missed: u32) -> KeepAlive {
+        KeepAlive {
+            interval_ms: interval.as_millis() as u64,
+            timeout_ms: timeout.as_millis() as u64,
+            max_missed: max_missed.max(1),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms.max(1))
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.max(1))
+    }
+
+    /// Combines the keepalive of both sides, it is only used when both of
+    /// them asked for it and the protocol can carry the frames. The most
+    /// lenient of the two is taken so that neither side gives up on the
+    /// other sooner than it expects to be given up on.
+    pub fn negotiate(
+        ours: Option<KeepAlive>,
+        theirs: Option<KeepAlive>,
+        version: MessageProtocolVersion,
+    ) -> Option<KeepAlive> {
+        if version.supports_keepalive() == false {
+            return None;
+        }
+        match (ours, theirs) {
+            (Some(a), Some(b)) => Some(KeepAlive {
+                interval_ms: a.interval_ms.max(b.interval_ms),
+                timeout_ms: a.timeout_ms.max(b.timeout_ms),
+                max_missed: a.max_missed.max(b.max_missed),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Writer that is shared by both halves of a split stream so that the read
+/// half can answer pings (or send them) in between the frames of the write half
+pub(super) type SharedTx = Arc<tokio::sync::Mutex<Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>>>;
+
+pub(super) enum TxGuard<'a> {
+    Owned(&'a mut (dyn AsyncWrite + Send + Sync + Unpin + 'static)),
+    Shared(tokio::sync::MutexGuard<'a, Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>>),
+}
+
+impl<'a> Deref
+for TxGuard<'a>
+{
+    type Target = dyn AsyncWrite + Send + Sync + Unpin + 'static;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            TxGuard::Owned(a) => &**a,
+            TxGuard::Shared(a) => a.deref().deref(),
+        }
+    }
+}
+
+impl<'a> DerefMut
+for TxGuard<'a>
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            TxGuard::Owned(a) => &mut **a,
+            TxGuard::Shared(a) => a.deref_mut().deref_mut(),
+        }
+    }
+}
@@ -2,8 +2,11 @@ mod v1;
 mod v2;
 mod v3;
 mod api;
+mod keepalive;
+mod replay;
 mod stream;
 mod version;
+mod vectored;
 
 pub use api::MessageProtocolApi;
 pub use api::AsyncStream;
@@ -11,4 +14,6 @@ pub use api::StreamReadable;
 pub use api::StreamWritable;
 pub use stream::StreamRx;
 pub use stream::StreamTx;
-pub use version::MessageProtocolVersion;
\ No newline at end of file
+pub use version::MessageProtocolVersion;
+pub use keepalive::KeepAlive;
+pub use replay::ReplayError;
\ No newline at end of file
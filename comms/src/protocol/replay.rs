@@ -0,0 +1,88 @@
+use std::io;
+use ate_crypto::InitializationVector;
+
+/// Bytes of the sequence number, it is sent in the clear in front of every
+/// encrypted frame and again as the first bytes of the cipher text
+pub(super) const SEQUENCE_LEN: usize = 8;
+
+/// Reason that a frame was rejected by the replay protection, the frame is
+/// never returned and the connection is aborted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    /// Frame carries a sequence number that was already received
+    Duplicate { seq: u64, expected: u64 },
+    /// Frame skips ahead of the next sequence number (frames can not be lost
+    /// or reordered on the stream so the window only holds the next one)
+    OutOfWindow { seq: u64, expected: u64 },
+    /// Sequence number within the cipher text does not match the one in
+    /// front of it (or the frame is too short to hold them)
+    Tampered { seq: u64 },
+    /// Every sequence number was used up
+    Exhausted,
+}
+
+impl std::fmt::Display
+for ReplayError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Duplicate { seq, expected } => write!(f, "duplicate frame (seq={}, expected={})", seq, expected),
+            ReplayError::OutOfWindow { seq, expected } => write!(f, "frame is out of the window (seq={}, expected={})", seq, expected),
+            ReplayError::Tampered { seq } => write!(f, "frame was tampered with (seq={})", seq),
+            ReplayError::Exhausted => write!(f, "the sequence numbers of the connection are exhausted"),
+        }
+    }
+}
+
+impl std::error::Error
+for ReplayError
+{
+}
+
+impl From<ReplayError>
+for io::Error
+{
+    fn from(err: ReplayError) -> io::Error {
+        io::Error::new(io::ErrorKind::ConnectionAborted, err)
+    }
+}
+
+impl ReplayError {
+    /// Returns the replay error behind an IO error returned by the stream
+    pub fn find(err: &io::Error) -> Option<ReplayError> {
+        err.get_ref()
+            .and_then(|a| a.downcast_ref::<ReplayError>())
+            .map(|a| *a)
+    }
+
+    /// Checks that the sequence number of a received frame is the next one
+    pub(super) fn check(seq: u64, expected: u64) -> Result<(), ReplayError> {
+        if seq < expected {
+            Err(ReplayError::Duplicate { seq, expected })
+        } else if seq > expected {
+            Err(ReplayError::OutOfWindow { seq, expected })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Returns the next sequence number to send and advances the counter
+pub(super) fn next_sequence(counter: &mut u64) -> Result<u64, ReplayError> {
+    let seq = *counter;
+    *counter = seq.checked_add(1).ok_or(ReplayError::Exhausted)?;
+    Ok(seq)
+}
+
+/// IV that a single frame is encrypted with, the sequence number is mixed
+/// into the IV of the stream so that every frame uses its own nonce and
+/// only decrypts at the position in the stream that it was sent at
+pub(super) fn frame_iv(iv: &InitializationVector, seq: u64) -> InitializationVector {
+    let mut bytes = [0u8; 16];
+    let len = iv.bytes.len().min(16);
+    bytes[..len].copy_from_slice(&iv.bytes[..len]);
+    for (a, b) in bytes[..SEQUENCE_LEN].iter_mut().zip(seq.to_be_bytes().iter()) {
+        *a ^= *b;
+    }
+    (&bytes[..]).into()
+}
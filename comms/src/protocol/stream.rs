@@ -1,7 +1,11 @@
 use std::io;
 use ate_crypto::EncryptKey;
+use ate_crypto::NodeId;
 use async_trait::async_trait;
 
+use crate::tap::InstalledTap;
+use crate::tap::PacketTapHook;
+
 use super::MessageProtocolApi;
 use super::StreamReadable;
 use super::StreamWritable;
@@ -10,6 +14,7 @@ use super::StreamWritable;
 pub struct StreamRx {
     proto: Box<dyn MessageProtocolApi + Send + Sync + 'static>,
     ek: Option<EncryptKey>,
+    tap: Option<InstalledTap>,
 }
 
 impl StreamRx
@@ -17,14 +22,24 @@ impl StreamRx
     pub(crate) fn new(proto: Box<dyn MessageProtocolApi + Send + Sync + 'static>, ek: Option<EncryptKey>) -> Self {
         Self {
             proto,
-            ek
+            ek,
+            tap: None,
         }
     }
+
+    /// Installs a tap that observes every frame read from the peer
+    pub fn set_tap(&mut self, hook: PacketTapHook, peer: NodeId) {
+        self.tap = Some(InstalledTap { hook, peer });
+    }
     
     pub async fn read(&mut self) -> io::Result<Vec<u8>>
     {
         let mut total_read = 0u64;
-        self.proto.read_buf_with_header(&self.ek, &mut total_read).await
+        let ret = self.proto.read_buf_with_header(&self.ek, &mut total_read).await?;
+        if let Some(tap) = &self.tap {
+            tap.observe(false, self.ek.is_some(), &ret[..]);
+        }
+        Ok(ret)
     }
 }
 
@@ -42,6 +57,7 @@ for StreamRx
 pub struct StreamTx {
     proto: Box<dyn MessageProtocolApi + Send + Sync + 'static>,
     ek: Option<EncryptKey>,
+    tap: Option<InstalledTap>,
 }
 
 impl StreamTx
@@ -49,16 +65,36 @@ impl StreamTx
     pub(crate) fn new(proto: Box<dyn MessageProtocolApi + Send + Sync + 'static>, ek: Option<EncryptKey>) -> Self {
         Self {
             proto,
-            ek
+            ek,
+            tap: None,
         }
     }
 
+    /// Installs a tap that observes every frame written to the peer
+    pub fn set_tap(&mut self, hook: PacketTapHook, peer: NodeId) {
+        self.tap = Some(InstalledTap { hook, peer });
+    }
+
     pub async fn write(&mut self, data: &[u8]) -> io::Result<usize>
     {
+        if let Some(tap) = &self.tap {
+            tap.observe(true, self.ek.is_some(), data);
+        }
         self.proto.send(&self.ek, data).await
             .map(|a| a as usize)
     }
 
+    /// Writes a message made up of several parts without first joining them
+    pub async fn write_parts(&mut self, parts: &[&[u8]]) -> io::Result<usize>
+    {
+        if let Some(tap) = &self.tap {
+            // Only when tapped are the parts joined so the tap sees the frame
+            tap.observe(true, self.ek.is_some(), &parts.concat()[..]);
+        }
+        self.proto.send_parts(&self.ek, parts).await
+            .map(|a| a as usize)
+    }
+
     pub async fn flush(&mut self) -> io::Result<()> {
         self.proto.flush().await
     }
@@ -1,5 +1,6 @@
 use std::io;
 use std::ops::DerefMut;
+use bytes::BytesMut;
 use derivative::*;
 use tokio::io::AsyncWrite;
 use tokio::io::AsyncWriteExt;
@@ -10,6 +11,7 @@ use ate_crypto::{InitializationVector, EncryptKey};
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
+use super::vectored::*;
 use super::MessageProtocolApi;
 use super::StreamRx;
 use super::StreamTx;
@@ -32,7 +34,6 @@ impl MessageOpCode {
     }
 }
 const MAX_MESSAGE_OP_CODE: u8 = 4;
-const EXCESS_OP_CODE_SPACE: u8 = u8::MAX - MAX_MESSAGE_OP_CODE;
 const MESSAGE_MAX_IV_REUSE: u32 = 1000;
 
 
@@ -44,6 +45,8 @@ pub struct MessageProtocol
     iv_rx: Option<InitializationVector>,
     iv_use_cnt: u32,
     #[derivative(Debug = "ignore")]
+    scratch: BytesMut,
+    #[derivative(Debug = "ignore")]
     rx: Option<Box<dyn AsyncRead + Send + Sync + Unpin + 'static>>,
     #[derivative(Debug = "ignore")]
     tx: Option<Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>>,
@@ -56,6 +59,7 @@ impl MessageProtocol
             iv_tx: None,
             iv_rx: None,
             iv_use_cnt: 0,
+            scratch: BytesMut::new(),
             rx,
             tx
         }
@@ -71,55 +75,37 @@ impl MessageProtocol
             .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "this protocol does not support reading"))
     }
     
-    #[allow(unused_variables)]
+    /// Writes the message header followed by the parts of the message (and
+    /// optionally a new IV in front of it all) with a single vectored write
     async fn write_with_header(
         &mut self,
-        buf: &'_ [u8],
+        new_iv: Option<&[u8]>,
+        parts: [&[u8]; 2],
         delay_flush: bool,
     ) -> Result<u64, tokio::io::Error> {
-        let tx = self.tx_guard()?;
-        let len = buf.len();
-        let op = if len < EXCESS_OP_CODE_SPACE as usize {
-            let len = len as u8;
-            let op = MAX_MESSAGE_OP_CODE + len;
-            vec![ op ]
-        } else if len < u16::MAX as usize {
-            let op = MessageOpCode::Buf16bit as u8;
-            let len = len as u16;
-            let len = len.to_be_bytes();
-            vec![op, len[0], len[1]]
-        } else if len < u32::MAX as usize {
-            let op = MessageOpCode::Buf32bit as u8;
-            let len = len as u32;
-            let len = len.to_be_bytes();
-            vec![op, len[0], len[1], len[2], len[3]]
-        } else {
-            return Err(tokio::io::Error::new(
-                tokio::io::ErrorKind::InvalidData,
-                format!(
-                    "Data is too big to write (len={}, max={})",
-                    buf.len(),
-                    u32::MAX
-                ),
-            ));
+        let len = parts[0].len() + parts[1].len();
+        let (header, header_len) = message_header(
+            len,
+            MAX_MESSAGE_OP_CODE,
+            MessageOpCode::Buf16bit as u8,
+            MessageOpCode::Buf32bit as u8,
+        )?;
+        let new_iv_op = [MessageOpCode::NewIV as u8];
+        let (new_iv_op, new_iv) = match new_iv {
+            Some(iv) => (&new_iv_op[..], iv),
+            None => (&new_iv_op[..0], &new_iv_op[..0]),
         };
 
-        if len <= 62 {
-            let concatenated = [&op[..], &buf[..]].concat();
-            tx.write_all(&concatenated[..]).await?;
-            if delay_flush == false {
-                tx.flush().await?;
-            }
-            Ok(concatenated.len() as u64)
-        } else {
-            let total_sent = op.len() as u64 + len as u64;
-            tx.write_all(&op[..]).await?;
-            tx.write_all(&buf[..]).await?;
-            if delay_flush == false {
-                tx.flush().await?;
-            }
-            Ok(total_sent)
-        }        
+        let tx = self.tx_guard()?;
+        let total_sent = write_all_vectored(
+            tx,
+            [new_iv_op, new_iv, &header[..header_len], parts[0], parts[1]],
+        )
+        .await?;
+        if delay_flush == false {
+            tx.flush().await?;
+        }
+        Ok(total_sent)
     }
 
     async fn read_u8(&mut self) -> Result<u8, tokio::io::Error> {
@@ -216,30 +202,62 @@ for MessageProtocol
         wire_encryption: &Option<EncryptKey>,
         data: &[u8],
     ) -> Result<u64, tokio::io::Error> {
-        let mut total_sent = 0u64;
+        self.send_parts(wire_encryption, &[data]).await
+    }
+
+    async fn send_parts(
+        &mut self,
+        wire_encryption: &Option<EncryptKey>,
+        parts: &[&[u8]],
+    ) -> Result<u64, tokio::io::Error> {
         match wire_encryption {
             Some(key) => {
-                if self.iv_tx.is_none() || self.iv_use_cnt > MESSAGE_MAX_IV_REUSE {
-                    let iv = InitializationVector::generate();
-                    let op = MessageOpCode::NewIV as u8;
-                    let op = op.to_be_bytes();
-                    let concatenated = [&op, &iv.bytes[..]].concat();
-                    self.tx_guard()?.write_all(&concatenated[..]).await?;
-                    self.iv_tx.replace(iv);
+                let new_iv = if self.iv_tx.is_none() || self.iv_use_cnt > MESSAGE_MAX_IV_REUSE {
+                    self.iv_tx.replace(InitializationVector::generate());
                     self.iv_use_cnt = 0;
+                    true
                 } else {
                     self.iv_use_cnt += 1;
+                    false
+                };
+
+                // The cipher text is built in the scratch buffer of this
+                // connection so that its memory is reused between messages
+                let mut scratch = std::mem::take(&mut self.scratch);
+                scratch.clear();
+                for part in parts {
+                    scratch.extend_from_slice(part);
                 }
-
-                let iv = self.iv_tx.as_ref().unwrap();                
-                let enc = key.encrypt_with_iv(iv, data);
-                total_sent += self.write_with_header(&enc[..], false).await?;
-            }
-            None => {
-                total_sent += self.write_with_header(data, false).await?;
+                let iv = self.iv_tx.as_ref().unwrap();
+                key.encrypt_with_iv_in_place(iv, &mut scratch[..]);
+
+                let mut iv_bytes = [0u8; 16];
+                let iv_len = iv.bytes.len().min(16);
+                iv_bytes[..iv_len].copy_from_slice(&iv.bytes[..iv_len]);
+                let new_iv = match new_iv {
+                    true => Some(&iv_bytes[..iv_len]),
+                    false => None,
+                };
+                let ret = self.write_with_header(new_iv, [&scratch[..], &[]], false).await;
+                self.scratch = scratch;
+                ret
             }
-        };
-        Ok(total_sent)
+            None => match parts {
+                [] => self.write_with_header(None, [&[], &[]], false).await,
+                [a] => self.write_with_header(None, [*a, &[]], false).await,
+                [a, b] => self.write_with_header(None, [*a, *b], false).await,
+                _ => {
+                    let mut scratch = std::mem::take(&mut self.scratch);
+                    scratch.clear();
+                    for part in parts {
+                        scratch.extend_from_slice(part);
+                    }
+                    let ret = self.write_with_header(None, [&scratch[..], &[]], false).await;
+                    self.scratch = scratch;
+                    ret
+                }
+            },
+        }
     }
 
     async fn read_with_fixed_16bit_header(
@@ -1,5 +1,7 @@
 use std::io;
 use std::ops::DerefMut;
+use std::sync::Arc;
+use bytes::BytesMut;
 use derivative::*;
 use tokio::io::AsyncWrite;
 use tokio::io::AsyncWriteExt;
@@ -10,6 +12,9 @@ use ate_crypto::{InitializationVector, EncryptKey};
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
+use super::vectored::*;
+use super::keepalive::*;
+use super::replay::*;
 use super::MessageProtocolApi;
 use super::StreamRx;
 use super::StreamTx;
@@ -26,6 +31,8 @@ enum MessageOpCode {
     Buf16bit = 2,
     Buf32bit = 3,
     Close = 4,
+    Ping = 5,
+    Pong = 6,
 }
 impl MessageOpCode {
     fn to_u8(self) -> u8 {
@@ -33,7 +40,6 @@ impl MessageOpCode {
     }
 }
 const MAX_MESSAGE_OP_CODE: u8 = 8;
-const EXCESS_OP_CODE_SPACE: u8 = u8::MAX - MAX_MESSAGE_OP_CODE;
 const MESSAGE_MAX_IV_REUSE: u32 = 1000;
 
 #[derive(Derivative)]
@@ -43,12 +49,24 @@ pub struct MessageProtocol
     iv_tx: Option<InitializationVector>,
     iv_rx: Option<InitializationVector>,
     iv_use_cnt: u32,
+    scratch: BytesMut,
     flip_to_abort: bool,
     is_closed: bool,
+    keepalive: Option<KeepAlive>,
+    ping_outstanding: bool,
+    missed_pongs: u32,
+    /// When true the encrypted frames carry a sequence number, each
+    /// direction counts from zero once the connection is established
+    replay: bool,
+    seq_tx: u64,
+    seq_rx: u64,
     #[derivative(Debug = "ignore")]
     rx: Option<Box<dyn AsyncRead + Send + Sync + Unpin + 'static>>,
     #[derivative(Debug = "ignore")]
     tx: Option<Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>>,
+    /// Once split with a keepalive the halves write through this instead
+    #[derivative(Debug = "ignore")]
+    shared_tx: Option<SharedTx>,
 }
 
 impl MessageProtocol
@@ -58,72 +76,133 @@ impl MessageProtocol
             iv_tx: None,
             iv_rx: None,
             iv_use_cnt: 0,
+            scratch: BytesMut::new(),
             flip_to_abort: false,
             is_closed: false,
+            keepalive: None,
+            ping_outstanding: false,
+            missed_pongs: 0,
+            replay: false,
+            seq_tx: 0,
+            seq_rx: 0,
             rx,
-            tx
+            tx,
+            shared_tx: None,
         }
     }
 
-    fn tx_guard<'a>(&'a mut self) -> io::Result<&'a mut (dyn AsyncWrite + Send + Sync + Unpin + 'static)> {
+    async fn tx_guard<'a>(&'a mut self) -> io::Result<TxGuard<'a>> {
+        if let Some(shared) = self.shared_tx.as_ref() {
+            return Ok(TxGuard::Shared(shared.lock().await));
+        }
         self.tx.as_deref_mut()
+            .map(TxGuard::Owned)
             .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "this protocol does not support writing"))
     }
 
+    /// Writes an op code that carries no data (used for the keepalive), when
+    /// the write half is busy sending a frame nothing is written as that
+    /// frame already tells the peer that this side is alive
+    async fn write_control(&mut self, op: MessageOpCode) -> io::Result<()> {
+        let op = [op.to_u8()];
+        if let Some(shared) = self.shared_tx.as_ref() {
+            if let Ok(mut tx) = shared.try_lock() {
+                tx.write_all(&op[..]).await?;
+                tx.flush().await?;
+            }
+            return Ok(());
+        }
+        let mut tx = self.tx_guard().await?;
+        tx.write_all(&op[..]).await?;
+        tx.flush().await?;
+        Ok(())
+    }
+
+    /// Reads the next op code, if the peer stays quiet for longer than the
+    /// keepalive interval then it is sent a ping and when too many of them
+    /// go unanswered the peer is considered dead
+    #[cfg(not(target_os = "wasi"))]
+    async fn read_op(&mut self) -> io::Result<u8> {
+        let keepalive = match self.keepalive {
+            Some(a) => a,
+            None => {
+                return self.read_u8().await;
+            }
+        };
+        loop {
+            let wait = match self.ping_outstanding {
+                true => keepalive.timeout(),
+                false => keepalive.interval(),
+            };
+            // (reading a single byte is never left half done when it times out)
+            match tokio::time::timeout(wait, self.read_u8()).await {
+                Ok(ret) => {
+                    // Anything at all from the peer shows that it is alive
+                    self.ping_outstanding = false;
+                    self.missed_pongs = 0;
+                    return ret;
+                }
+                Err(_) => {
+                    if self.ping_outstanding {
+                        self.missed_pongs += 1;
+                        if self.missed_pongs >= keepalive.max_missed {
+                            debug!("peer is dead (missed {} keepalive pongs)", self.missed_pongs);
+                            return Err(io::Error::new(
+                                io::ErrorKind::TimedOut,
+                                format!("the peer missed {} keepalive pongs in a row", self.missed_pongs),
+                            ));
+                        }
+                    }
+                    //trace!("stream_tx::op(ping)");
+                    self.write_control(MessageOpCode::Ping).await?;
+                    self.ping_outstanding = true;
+                }
+            }
+        }
+    }
+
+    /// (without timers the pings of the peer are answered but none are sent)
+    #[cfg(target_os = "wasi")]
+    async fn read_op(&mut self) -> io::Result<u8> {
+        self.read_u8().await
+    }
+
     fn rx_guard<'a>(&'a mut self) -> io::Result<&'a mut (dyn AsyncRead + Send + Sync + Unpin + 'static)> {
         self.rx.as_deref_mut()
             .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "this protocol does not support reading"))
     }
     
-    #[allow(unused_variables)]
+    /// Writes the message header followed by the parts of the message (and
+    /// optionally a new IV in front of it all) with a single vectored write
     async fn write_with_header(
         &mut self,
-        buf: &'_ [u8],
+        new_iv: Option<&[u8]>,
+        parts: [&[u8]; 2],
         delay_flush: bool,
     ) -> Result<u64, tokio::io::Error> {
-        let tx = self.tx_guard()?;
-        let len = buf.len();
-        let op = if len < EXCESS_OP_CODE_SPACE as usize {
-            let len = len as u8;
-            let op = MAX_MESSAGE_OP_CODE + len;
-            vec![ op ]
-        } else if len < u16::MAX as usize {
-            let op = MessageOpCode::Buf16bit as u8;
-            let len = len as u16;
-            let len = len.to_be_bytes();
-            vec![op, len[0], len[1]]
-        } else if len < u32::MAX as usize {
-            let op = MessageOpCode::Buf32bit as u8;
-            let len = len as u32;
-            let len = len.to_be_bytes();
-            vec![op, len[0], len[1], len[2], len[3]]
-        } else {
-            return Err(tokio::io::Error::new(
-                tokio::io::ErrorKind::InvalidData,
-                format!(
-                    "Data is too big to write (len={}, max={})",
-                    buf.len(),
-                    u32::MAX
-                ),
-            ));
+        let len = parts[0].len() + parts[1].len();
+        let (header, header_len) = message_header(
+            len,
+            MAX_MESSAGE_OP_CODE,
+            MessageOpCode::Buf16bit as u8,
+            MessageOpCode::Buf32bit as u8,
+        )?;
+        let new_iv_op = [MessageOpCode::NewIV as u8];
+        let (new_iv_op, new_iv) = match new_iv {
+            Some(iv) => (&new_iv_op[..], iv),
+            None => (&new_iv_op[..0], &new_iv_op[..0]),
         };
 
-        if len <= 62 {
-            let concatenated = [&op[..], &buf[..]].concat();
-            tx.write_all(&concatenated[..]).await?;
-            if delay_flush == false {
-                tx.flush().await?;
-            }
-            Ok(concatenated.len() as u64)
-        } else {
-            let total_sent = op.len() as u64 + len as u64;
-            tx.write_all(&op[..]).await?;
-            tx.write_all(&buf[..]).await?;
-            if delay_flush == false {
-                tx.flush().await?;
-            }
-            Ok(total_sent)
-        }        
+        let mut tx = self.tx_guard().await?;
+        let total_sent = write_all_vectored(
+            tx.deref_mut(),
+            [new_iv_op, new_iv, &header[..header_len], parts[0], parts[1]],
+        )
+        .await?;
+        if delay_flush == false {
+            tx.flush().await?;
+        }
+        Ok(total_sent)
     }
 
     async fn read_u8(&mut self) -> Result<u8, tokio::io::Error> {
@@ -150,6 +229,25 @@ impl MessageProtocol
         Ok(())
     }
 
+    /// Decrypts a frame that carries a sequence number, frames that are not
+    /// the next one in the stream abort the connection
+    fn decrypt_sequenced(key: &EncryptKey, iv: &InitializationVector, bytes: &[u8], expected: u64) -> Result<Vec<u8>, ReplayError> {
+        if bytes.len() < SEQUENCE_LEN * 2 {
+            return Err(ReplayError::Tampered { seq: expected });
+        }
+        let mut seq = [0u8; SEQUENCE_LEN];
+        seq.copy_from_slice(&bytes[..SEQUENCE_LEN]);
+        let seq = u64::from_be_bytes(seq);
+        ReplayError::check(seq, expected)?;
+
+        let mut plain = key.decrypt(&frame_iv(iv, seq), &bytes[SEQUENCE_LEN..]);
+        if plain[..SEQUENCE_LEN] != seq.to_be_bytes() {
+            return Err(ReplayError::Tampered { seq });
+        }
+        plain.drain(..SEQUENCE_LEN);
+        Ok(plain)
+    }
+
     pub fn check_abort(&mut self) -> std::io::Result<bool>
     {
         if self.is_closed {
@@ -192,7 +290,7 @@ for MessageProtocol
         };
 
         let total_sent = header.len() as u64 + buf.len() as u64;
-        let tx = self.tx_guard()?;
+        let mut tx = self.tx_guard().await?;
         tx.write_all(&header[..]).await?;
         tx.write_all(&buf[..]).await?;
         if delay_flush == false {
@@ -225,7 +323,7 @@ for MessageProtocol
         };
 
         let total_sent = header.len() as u64 + buf.len() as u64;
-        let tx = self.tx_guard()?;
+        let mut tx = self.tx_guard().await?;
         tx.write_all(&header[..]).await?;
         tx.write_all(&buf[..]).await?;
         if delay_flush == false {
@@ -238,34 +336,81 @@ for MessageProtocol
         &mut self,
         wire_encryption: &Option<EncryptKey>,
         data: &[u8],
+    ) -> Result<u64, tokio::io::Error> {
+        self.send_parts(wire_encryption, &[data]).await
+    }
+
+    async fn send_parts(
+        &mut self,
+        wire_encryption: &Option<EncryptKey>,
+        parts: &[&[u8]],
     ) -> Result<u64, tokio::io::Error> {
         if self.check_abort()? {
             return Ok(0);
         }
-        let mut total_sent = 0u64;
         match wire_encryption {
             Some(key) => {
-                if self.iv_tx.is_none() || self.iv_use_cnt > MESSAGE_MAX_IV_REUSE {
-                    let iv = InitializationVector::generate();
-                    let op = MessageOpCode::NewIV as u8;
-                    let op = op.to_be_bytes();
-                    let concatenated = [&op, &iv.bytes[..]].concat();
-                    self.tx_guard()?.write_all(&concatenated[..]).await?;
-                    self.iv_tx.replace(iv);
+                let seq = match self.replay {
+                    true => Some(next_sequence(&mut self.seq_tx)?),
+                    false => None,
+                };
+                let new_iv = if self.iv_tx.is_none() || self.iv_use_cnt > MESSAGE_MAX_IV_REUSE {
+                    self.iv_tx.replace(InitializationVector::generate());
                     self.iv_use_cnt = 0;
+                    true
                 } else {
                     self.iv_use_cnt += 1;
+                    false
+                };
+
+                // The cipher text is built in the scratch buffer of this
+                // connection so that its memory is reused between messages
+                let mut scratch = std::mem::take(&mut self.scratch);
+                scratch.clear();
+                if let Some(seq) = seq {
+                    scratch.extend_from_slice(&seq.to_be_bytes());
+                }
+                for part in parts {
+                    scratch.extend_from_slice(part);
+                }
+                let iv = self.iv_tx.as_ref().unwrap();
+                match seq {
+                    Some(seq) => key.encrypt_with_iv_in_place(&frame_iv(iv, seq), &mut scratch[..]),
+                    None => key.encrypt_with_iv_in_place(iv, &mut scratch[..]),
                 }
 
-                let iv = self.iv_tx.as_ref().unwrap();                
-                let enc = key.encrypt_with_iv(iv, data);
-                total_sent += self.write_with_header(&enc[..], false).await?;
+                let mut iv_bytes = [0u8; 16];
+                let iv_len = iv.bytes.len().min(16);
+                iv_bytes[..iv_len].copy_from_slice(&iv.bytes[..iv_len]);
+                let new_iv = match new_iv {
+                    true => Some(&iv_bytes[..iv_len]),
+                    false => None,
+                };
+                let seq = seq.map(|a| a.to_be_bytes());
+                let seq = match seq.as_ref() {
+                    Some(a) => &a[..],
+                    None => &[],
+                };
+                let ret = self.write_with_header(new_iv, [seq, &scratch[..]], false).await;
+                self.scratch = scratch;
+                ret
             }
-            None => {
-                total_sent += self.write_with_header(data, false).await?;
-            }
-        };
-        Ok(total_sent)
+            None => match parts {
+                [] => self.write_with_header(None, [&[], &[]], false).await,
+                [a] => self.write_with_header(None, [*a, &[]], false).await,
+                [a, b] => self.write_with_header(None, [*a, *b], false).await,
+                _ => {
+                    let mut scratch = std::mem::take(&mut self.scratch);
+                    scratch.clear();
+                    for part in parts {
+                        scratch.extend_from_slice(part);
+                    }
+                    let ret = self.write_with_header(None, [&scratch[..], &[]], false).await;
+                    self.scratch = scratch;
+                    ret
+                }
+            },
+        }
     }
 
     async fn read_with_fixed_16bit_header(
@@ -315,7 +460,7 @@ for MessageProtocol
             if self.check_abort()? {
                 return Ok(vec![]);
             }
-            let op = self.read_u8().await?;
+            let op = self.read_op().await?;
             *total_read += 1;
             let len = if op == MessageOpCode::Noop.to_u8() {
                 //trace!("stream_rx::op(noop)");
@@ -342,6 +487,15 @@ for MessageProtocol
                 //trace!("stream_rx::op(close)");
                 self.is_closed = true;
                 continue;
+            } else if op == MessageOpCode::Ping.to_u8() {
+                //trace!("stream_rx::op(ping)");
+                self.write_control(MessageOpCode::Pong).await?;
+                continue;
+            } else if op == MessageOpCode::Pong.to_u8() {
+                //trace!("stream_rx::op(pong)");
+                continue;
+            } else if op < MAX_MESSAGE_OP_CODE {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("unknown op code ({})", op)));
 
             } else {
                 //trace!("stream_rx::op(buf-packed)");
@@ -364,7 +518,23 @@ for MessageProtocol
 
                 // Decrypt the bytes
                 //trace!("stream_rx::decrypt(len={})", len);
-                bytes = key.decrypt(iv, &bytes[..]);
+                bytes = match self.replay {
+                    true => match Self::decrypt_sequenced(key, iv, &bytes[..], self.seq_rx) {
+                        Ok(a) => {
+                            self.seq_rx += 1;
+                            a
+                        }
+                        Err(err) => {
+                            // Nothing more is read from a connection that
+                            // replayed or tampered with a frame
+                            warn!("aborting connection - {}", err);
+                            self.is_closed = true;
+                            self.flip_to_abort = true;
+                            return Err(err.into());
+                        }
+                    },
+                    false => key.decrypt(iv, &bytes[..]),
+                };
             }
 
             // Return the result
@@ -376,7 +546,7 @@ for MessageProtocol
     async fn send_close(
         &mut self,
     ) -> std::io::Result<()> {
-        let tx = self.tx_guard()?;
+        let mut tx = self.tx_guard().await?;
         let op = MessageOpCode::Close as u8;
         let op = op.to_be_bytes();
         tx.write_all(&op[..]).await?;
@@ -388,7 +558,7 @@ for MessageProtocol
     async fn flush(
         &mut self,
     ) -> std::io::Result<()> {
-        let tx = self.tx_guard()?;
+        let mut tx = self.tx_guard().await?;
         tx.flush().await
     }
 
@@ -405,15 +575,45 @@ for MessageProtocol
     }
 
     fn take_tx(&mut self) -> Option<Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>> {
+        if let Some(shared) = self.shared_tx.take() {
+            return Arc::try_unwrap(shared)
+                .ok()
+                .map(|a| a.into_inner());
+        }
         self.tx.take()
     }
+
+    fn set_keepalive(&mut self, keepalive: Option<KeepAlive>) {
+        self.keepalive = keepalive;
+    }
+
+    fn set_replay_protection(&mut self, enabled: bool) {
+        self.replay = enabled;
+    }
     
     fn split(&mut self, ek: Option<EncryptKey>) -> (StreamRx, StreamTx) {
         let rx = self.rx.take();
         let tx = self.tx.take();
 
-        let rx = Box::new(Self::new(rx, None));
-        let tx = Box::new(Self::new(None, tx));
+        // With a keepalive the read half needs to write pings and pongs
+        let (mut rx, mut tx) = match (self.keepalive, tx) {
+            (Some(keepalive), Some(tx)) => {
+                let shared: SharedTx = Arc::new(tokio::sync::Mutex::new(tx));
+                let mut rx = Self::new(rx, None);
+                rx.keepalive = Some(keepalive);
+                rx.shared_tx = Some(Arc::clone(&shared));
+                let mut tx = Self::new(None, None);
+                tx.shared_tx = Some(shared);
+                (Box::new(rx), Box::new(tx))
+            }
+            (_, tx) => (Box::new(Self::new(rx, None)), Box::new(Self::new(None, tx))),
+        };
+
+        // Each half carries on counting the frames of its own direction
+        rx.replay = self.replay;
+        rx.seq_rx = self.seq_rx;
+        tx.replay = self.replay;
+        tx.seq_tx = self.seq_tx;
 
         let rx = StreamRx::new(rx, ek.clone());
         let tx = StreamTx::new(tx, ek.clone());
@@ -0,0 +1,68 @@
+use std::io;
+use std::io::IoSlice;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+
+/// Encodes the op code (and length) that goes in front of every buffer sent
+/// by the message protocols, small buffers pack their length into the op code
+/// itself. Returns the header and the number of bytes of it that are used.
+pub(super) fn message_header(
+    len: usize,
+    max_op_code: u8,
+    op_buf16: u8,
+    op_buf32: u8,
+) -> io::Result<([u8; 5], usize)> {
+    let mut header = [0u8; 5];
+    if len < (u8::MAX - max_op_code) as usize {
+        header[0] = max_op_code + len as u8;
+        Ok((header, 1))
+    } else if len < u16::MAX as usize {
+        header[0] = op_buf16;
+        header[1..3].copy_from_slice(&(len as u16).to_be_bytes());
+        Ok((header, 3))
+    } else if len < u32::MAX as usize {
+        header[0] = op_buf32;
+        header[1..5].copy_from_slice(&(len as u32).to_be_bytes());
+        Ok((header, 5))
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Data is too big to write (len={}, max={})",
+                len,
+                u32::MAX
+            ),
+        ))
+    }
+}
+
+/// Writes all the parts to the stream using vectored writes so that the
+/// parts never need to be concatenated into a single buffer first
+pub(super) async fn write_all_vectored<const N: usize>(
+    tx: &mut (dyn AsyncWrite + Send + Sync + Unpin + 'static),
+    mut parts: [&[u8]; N],
+) -> io::Result<u64> {
+    let mut total_sent = 0u64;
+    loop {
+        if parts.iter().all(|a| a.is_empty()) {
+            return Ok(total_sent);
+        }
+
+        let slices = parts.map(IoSlice::new);
+        let mut amt = tx.write_vectored(&slices[..]).await?;
+        if amt == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        total_sent += amt as u64;
+
+        // Skip over whatever was written (writes are allowed to be partial)
+        for part in parts.iter_mut() {
+            let skip = amt.min(part.len());
+            *part = &part[skip..];
+            amt -= skip;
+            if amt == 0 {
+                break;
+            }
+        }
+    }
+}
@@ -6,7 +6,7 @@ use super::MessageProtocolApi;
 
 /// Version of the stream protocol used to talk to Wasmer services
 #[repr(u16)]
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum MessageProtocolVersion
 {
     V1 = 1,
@@ -22,8 +22,66 @@ for MessageProtocolVersion
     }
 }
 
+impl std::fmt::Display
+for MessageProtocolVersion
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageProtocolVersion::V1 => write!(f, "v1"),
+            MessageProtocolVersion::V2 => write!(f, "v2"),
+            MessageProtocolVersion::V3 => write!(f, "v3"),
+        }
+    }
+}
+
+impl std::str::FromStr
+for MessageProtocolVersion
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "v1" | "1" => Ok(MessageProtocolVersion::V1),
+            "v2" | "2" => Ok(MessageProtocolVersion::V2),
+            "v3" | "3" => Ok(MessageProtocolVersion::V3),
+            a => Err(format!("unknown stream protocol version ({}) - expected v1, v2 or v3", a)),
+        }
+    }
+}
+
 impl MessageProtocolVersion
 {
+    /// Every version that this build understands (oldest first)
+    pub const ALL: [MessageProtocolVersion; 3] = [
+        MessageProtocolVersion::V1,
+        MessageProtocolVersion::V2,
+        MessageProtocolVersion::V3,
+    ];
+
+    /// Number of the version as it is exchanged during the hello, the number
+    /// is used (rather than the name) so that a peer can list versions that
+    /// the other side has never heard of
+    pub fn as_u16(&self) -> u16 {
+        *self as u16
+    }
+
+    pub fn from_u16(val: u16) -> Option<MessageProtocolVersion> {
+        MessageProtocolVersion::ALL
+            .iter()
+            .filter(|a| a.as_u16() == val)
+            .map(|a| *a)
+            .next()
+    }
+
+    /// Versions that may be used when the maximum is pinned to this version
+    pub fn supported_up_to(&self) -> Vec<u16> {
+        MessageProtocolVersion::ALL
+            .iter()
+            .filter(|a| *a <= self)
+            .map(|a| a.as_u16())
+            .collect()
+    }
+
     pub fn min(&self, other: MessageProtocolVersion) -> MessageProtocolVersion {
         let first = *self as u16;
         let second = other as u16;
@@ -36,6 +94,22 @@ impl MessageProtocolVersion
         }
     }
 
+    /// Multiplexing of multiple channels over the same stream was added in V3
+    pub fn supports_multiplex(&self) -> bool {
+        (*self as u16) >= (MessageProtocolVersion::V3 as u16)
+    }
+
+    /// Keepalive frames were added in V3 (V2 has no spare op codes to carry
+    /// them and V1 has no op codes at all)
+    pub fn supports_keepalive(&self) -> bool {
+        (*self as u16) >= (MessageProtocolVersion::V3 as u16)
+    }
+
+    /// Sequence numbers on the encrypted frames were added in V3
+    pub fn supports_replay_protection(&self) -> bool {
+        (*self as u16) >= (MessageProtocolVersion::V3 as u16)
+    }
+
     pub fn upgrade(&self, mut proto: Box<dyn MessageProtocolApi + Send + Sync + 'static>) -> Box<dyn MessageProtocolApi + Send + Sync + 'static> {
         let rx = proto.take_rx();
         let tx = proto.take_tx();
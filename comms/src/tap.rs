@@ -0,0 +1,242 @@
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use ate_crypto::NodeId;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+/// Link type recorded in the capture files (the first of the link types
+/// that are reserved for private use, i.e. LINKTYPE_USER0)
+pub const PACKET_TAP_LINK_TYPE: u16 = 147;
+
+/// Size of the header that precedes every frame in the capture files
+pub const PACKET_TAP_HEADER_LEN: usize = 16;
+
+/// Observes the frames that are sent and received on a connection, this is
+/// meant for debugging protocol issues and costs nothing unless installed
+pub trait PacketTap: Send + Sync {
+    /// Called with every frame that is written to the peer
+    fn on_send(&self, peer: NodeId, bytes: &[u8]);
+
+    /// Called with every frame that is read from the peer
+    fn on_recv(&self, peer: NodeId, bytes: &[u8]);
+
+    /// Called instead of `on_send` and `on_recv` for frames that were wire
+    /// encrypted when the tap is not allowed to see what is inside of them
+    fn on_encrypted(&self, _peer: NodeId, _outbound: bool, _len: usize) {}
+}
+
+/// Tap that is installed on a connection along with whether it may see the
+/// contents of wire encrypted frames
+#[derive(Clone)]
+pub struct PacketTapHook {
+    pub tap: Arc<dyn PacketTap>,
+    /// When true the tap is given the plaintext of wire encrypted frames
+    pub tap_decrypted: bool,
+}
+
+impl PacketTapHook {
+    pub fn new(tap: Arc<dyn PacketTap>) -> PacketTapHook {
+        PacketTapHook {
+            tap,
+            tap_decrypted: false,
+        }
+    }
+
+    pub fn with_tap_decrypted(mut self, val: bool) -> PacketTapHook {
+        self.tap_decrypted = val;
+        self
+    }
+}
+
+impl fmt::Debug for PacketTapHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "packet-tap(tap_decrypted={})", self.tap_decrypted)
+    }
+}
+
+/// Tap installed on one half of a stream
+#[derive(Debug, Clone)]
+pub(crate) struct InstalledTap {
+    pub hook: PacketTapHook,
+    pub peer: NodeId,
+}
+
+impl InstalledTap {
+    pub fn observe(&self, outbound: bool, encrypted: bool, bytes: &[u8]) {
+        if encrypted && self.hook.tap_decrypted == false {
+            self.hook.tap.on_encrypted(self.peer, outbound, bytes.len());
+        } else if outbound {
+            self.hook.tap.on_send(self.peer, bytes);
+        } else {
+            self.hook.tap.on_recv(self.peer, bytes);
+        }
+    }
+}
+
+/// Writes the frames to a file in the pcapng format using a private link
+/// type so that a Wireshark dissector can be written for them. Every frame
+/// starts with a header of 16 bytes (big endian):
+///
+/// - version (u8, currently 1)
+/// - direction (u8, 0=sent, 1=received)
+/// - kind of peer (u8, 0=unknown, 1=client, 2=server)
+/// - flags (u8, bit 0 = the contents were withheld as they were encrypted)
+/// - identifier of the peer (u64, for servers the id and node are packed)
+/// - original length of the frame (u32)
+pub struct PcapngTap {
+    file: Mutex<BufWriter<File>>,
+    snap_len: usize,
+}
+
+impl PcapngTap {
+    /// Creates the capture file (frames longer than the snap length are
+    /// truncated in the file)
+    pub fn create(path: &str, snap_len: usize) -> io::Result<PcapngTap> {
+        let mut file = BufWriter::new(File::create(path)?);
+        Self::write_header(&mut file)?;
+        file.flush()?;
+        Ok(PcapngTap {
+            file: Mutex::new(file),
+            snap_len,
+        })
+    }
+
+    fn write_header(file: &mut impl Write) -> io::Result<()> {
+        // Section header block
+        file.write_all(&0x0A0D0D0Au32.to_le_bytes())?;
+        file.write_all(&28u32.to_le_bytes())?;
+        file.write_all(&0x1A2B3C4Du32.to_le_bytes())?;
+        file.write_all(&1u16.to_le_bytes())?;
+        file.write_all(&0u16.to_le_bytes())?;
+        file.write_all(&(-1i64).to_le_bytes())?;
+        file.write_all(&28u32.to_le_bytes())?;
+
+        // Interface description block
+        file.write_all(&1u32.to_le_bytes())?;
+        file.write_all(&20u32.to_le_bytes())?;
+        file.write_all(&PACKET_TAP_LINK_TYPE.to_le_bytes())?;
+        file.write_all(&0u16.to_le_bytes())?;
+        file.write_all(&0u32.to_le_bytes())?;
+        file.write_all(&20u32.to_le_bytes())?;
+        Ok(())
+    }
+
+    fn frame_header(peer: NodeId, outbound: bool, withheld: bool, len: usize) -> [u8; PACKET_TAP_HEADER_LEN] {
+        let (kind, id) = match peer {
+            NodeId::Unknown => (0u8, 0u64),
+            NodeId::Client(id) => (1u8, id),
+            NodeId::Server(server_id, node_id) => (2u8, ((server_id as u64) << 32) | node_id as u64),
+        };
+        let mut header = [0u8; PACKET_TAP_HEADER_LEN];
+        header[0] = 1;
+        header[1] = if outbound { 0 } else { 1 };
+        header[2] = kind;
+        header[3] = if withheld { 1 } else { 0 };
+        header[4..12].copy_from_slice(&id.to_be_bytes());
+        header[12..16].copy_from_slice(&(len as u32).to_be_bytes());
+        header
+    }
+
+    fn write_frame(&self, peer: NodeId, outbound: bool, withheld: bool, len: usize, bytes: &[u8]) -> io::Result<()> {
+        let header = Self::frame_header(peer, outbound, withheld, len);
+        let bytes = &bytes[..bytes.len().min(self.snap_len)];
+        let captured = header.len() + bytes.len();
+        let padding = (4 - (captured % 4)) % 4;
+        let block_len = (32 + captured + padding) as u32;
+
+        let micros = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|a| a.as_micros() as u64)
+            .unwrap_or(0);
+
+        // Enhanced packet block
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&6u32.to_le_bytes())?;
+        file.write_all(&block_len.to_le_bytes())?;
+        file.write_all(&0u32.to_le_bytes())?;
+        file.write_all(&((micros >> 32) as u32).to_le_bytes())?;
+        file.write_all(&(micros as u32).to_le_bytes())?;
+        file.write_all(&(captured as u32).to_le_bytes())?;
+        file.write_all(&((header.len() + len) as u32).to_le_bytes())?;
+        file.write_all(&header)?;
+        file.write_all(bytes)?;
+        file.write_all(&[0u8; 3][..padding])?;
+        file.write_all(&block_len.to_le_bytes())?;
+        file.flush()
+    }
+
+    fn record(&self, peer: NodeId, outbound: bool, withheld: bool, len: usize, bytes: &[u8]) {
+        if let Err(err) = self.write_frame(peer, outbound, withheld, len, bytes) {
+            warn!("failed to write to the packet capture - {}", err);
+        }
+    }
+}
+
+impl PacketTap for PcapngTap {
+    fn on_send(&self, peer: NodeId, bytes: &[u8]) {
+        self.record(peer, true, false, bytes.len(), bytes);
+    }
+
+    fn on_recv(&self, peer: NodeId, bytes: &[u8]) {
+        self.record(peer, false, false, bytes.len(), bytes);
+    }
+
+    fn on_encrypted(&self, peer: NodeId, outbound: bool, len: usize) {
+        self.record(peer, outbound, true, len, &[]);
+    }
+}
+
+/// Logs a hex dump of the frames at trace level (only the first bytes of
+/// each frame are dumped)
+pub struct HexDumpTap {
+    max_bytes: usize,
+}
+
+impl HexDumpTap {
+    pub fn new(max_bytes: usize) -> HexDumpTap {
+        HexDumpTap { max_bytes }
+    }
+
+    fn dump(&self, what: &str, peer: NodeId, bytes: &[u8]) {
+        if tracing::enabled!(Level::TRACE) == false {
+            return;
+        }
+        let shown = &bytes[..bytes.len().min(self.max_bytes)];
+        let mut dump = String::with_capacity(shown.len() * 4);
+        for (n, line) in shown.chunks(16).enumerate() {
+            dump.push_str(format!("\n{:08x}  {}", n * 16, hex::encode(line)).as_str());
+        }
+        if shown.len() < bytes.len() {
+            dump.push_str(format!("\n... {} more bytes", bytes.len() - shown.len()).as_str());
+        }
+        trace!("tap-{} peer={} len={}{}", what, peer, bytes.len(), dump);
+    }
+}
+
+impl Default for HexDumpTap {
+    fn default() -> HexDumpTap {
+        HexDumpTap::new(256)
+    }
+}
+
+impl PacketTap for HexDumpTap {
+    fn on_send(&self, peer: NodeId, bytes: &[u8]) {
+        self.dump("send", peer, bytes);
+    }
+
+    fn on_recv(&self, peer: NodeId, bytes: &[u8]) {
+        self.dump("recv", peer, bytes);
+    }
+
+    fn on_encrypted(&self, peer: NodeId, outbound: bool, len: usize) {
+        let what = if outbound { "send" } else { "recv" };
+        trace!("tap-{} peer={} len={} (encrypted)", what, peer, len);
+    }
+}
@@ -0,0 +1,140 @@
+//! Selection of the server certificate during the key exchange while a
+//! certificate is being rotated (i.e. the server holds more than one)
+#![cfg(feature = "asymmetric")]
+use std::io;
+
+use ate_comms::mesh_hello_exchange_sender;
+use ate_comms::mesh_hello_receive;
+use ate_comms::mesh_key_exchange_receiver;
+use ate_comms::mesh_key_exchange_receiver_select;
+use ate_comms::mesh_key_exchange_sender_select;
+use ate_comms::CertificateValidation;
+use ate_comms::MessageProtocolVersion;
+use ate_comms::ServerCertificate;
+use ate_comms::ServerCertificates;
+use ate_crypto::AteHash;
+use ate_crypto::EncryptKey;
+use ate_crypto::KeySize;
+use ate_crypto::NodeId;
+use ate_crypto::PrivateEncryptKey;
+use ate_crypto::SerializationFormat;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+
+type Half = (
+    Box<dyn AsyncRead + Send + Sync + Unpin + 'static>,
+    Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>,
+);
+
+fn pipe() -> (Half, Half) {
+    let (a, b) = tokio::io::duplex(64 * 1024);
+    let (a_rx, a_tx) = tokio::io::split(a);
+    let (b_rx, b_tx) = tokio::io::split(b);
+    (
+        (Box::new(a_rx), Box::new(a_tx)),
+        (Box::new(b_rx), Box::new(b_tx)),
+    )
+}
+
+async fn new_client(half: Half, validation: CertificateValidation) -> io::Result<(EncryptKey, AteHash)> {
+    let (mut proto, hello) = mesh_hello_exchange_sender(
+        half.0,
+        half.1,
+        NodeId::generate_client_id(),
+        "/test".to_string(),
+        "localhost".to_string(),
+        Some(KeySize::Bit128),
+        false,
+        MessageProtocolVersion::default(),
+        None,
+    )
+    .await?;
+    let selected = hello.selected_certificate(&validation);
+    let (ek, cert) = mesh_key_exchange_sender_select(
+        proto.as_mut(),
+        KeySize::Bit128,
+        validation,
+        selected,
+    )
+    .await?;
+    Ok((ek, cert.hash()))
+}
+
+async fn new_server(half: Half, certs: ServerCertificates) -> io::Result<EncryptKey> {
+    let now = chrono::Utc::now();
+    let (mut proto, hello) = mesh_hello_receive(half.0, half.1)
+        .await?
+        .with_certificates(certs.advertise(now))
+        .reply(
+            NodeId::Server(1, 1),
+            Some(KeySize::Bit128),
+            SerializationFormat::Bincode,
+            |_| false,
+            MessageProtocolVersion::default(),
+        )
+        .await?;
+    match hello.select_certificate {
+        true => {
+            let keys = certs.valid(now).into_iter().map(|a| a.key.clone()).collect::<Vec<_>>();
+            mesh_key_exchange_receiver_select(proto.as_mut(), &keys[..]).await
+        }
+        false => {
+            let key = certs.primary(now).unwrap().key.clone();
+            mesh_key_exchange_receiver(proto.as_mut(), key).await
+        }
+    }
+}
+
+fn rotating() -> (PrivateEncryptKey, PrivateEncryptKey, ServerCertificates) {
+    let now = chrono::Utc::now();
+    let old = PrivateEncryptKey::generate(KeySize::Bit128);
+    let new = PrivateEncryptKey::generate(KeySize::Bit128);
+    let certs = ServerCertificates::new(vec![
+        ServerCertificate::new(old.clone()).with_not_after(now + chrono::Duration::hours(1)),
+        ServerCertificate::new(new.clone()).with_not_before(now),
+    ]);
+    (old, new, certs)
+}
+
+#[tokio::test]
+async fn test_client_pinned_to_old_certificate() {
+    let (old, _, certs) = rotating();
+    let (a, b) = pipe();
+    let validation = CertificateValidation::AllowedCertificates(vec![old.hash()]);
+    let (client, server) = tokio::join!(new_client(a, validation), new_server(b, certs));
+    let (ek, hash) = client.unwrap();
+    assert_eq!(hash, old.hash());
+    assert_eq!(ek, server.unwrap());
+}
+
+#[tokio::test]
+async fn test_client_pinned_to_new_certificate() {
+    let (_, new, certs) = rotating();
+    let (a, b) = pipe();
+    let validation = CertificateValidation::AllowedCertificates(vec![new.hash()]);
+    let (client, server) = tokio::join!(new_client(a, validation), new_server(b, certs));
+    let (ek, hash) = client.unwrap();
+    assert_eq!(hash, new.hash());
+    assert_eq!(ek, server.unwrap());
+}
+
+#[tokio::test]
+async fn test_expired_certificate_is_not_advertised() {
+    let now = chrono::Utc::now();
+    let old = PrivateEncryptKey::generate(KeySize::Bit128);
+    let new = PrivateEncryptKey::generate(KeySize::Bit128);
+    let mut certs = ServerCertificates::new(vec![
+        ServerCertificate::new(old.clone()).with_not_after(now - chrono::Duration::seconds(1)),
+        ServerCertificate::new(new.clone()),
+    ]);
+    assert_eq!(certs.advertise(now).len(), 1);
+    assert_eq!(certs.primary(now).unwrap().hash, new.hash());
+
+    certs.prune(now);
+    assert_eq!(certs.iter().count(), 1);
+
+    let (a, b) = pipe();
+    let validation = CertificateValidation::AllowedCertificates(vec![old.hash()]);
+    let (client, _) = tokio::join!(new_client(a, validation), new_server(b, certs));
+    assert!(client.is_err());
+}
@@ -0,0 +1,144 @@
+//! Keepalive pings and pongs that are exchanged underneath the messages of
+//! the application once both sides agreed on a keepalive during the hello
+use std::io;
+use std::time::Duration;
+
+use ate_comms::mesh_hello_exchange_sender;
+use ate_comms::mesh_hello_receive;
+use ate_comms::HelloMetadata;
+use ate_comms::KeepAlive;
+use ate_comms::MessageProtocolApi;
+use ate_comms::MessageProtocolVersion;
+use ate_crypto::NodeId;
+use ate_crypto::SerializationFormat;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+
+type Half = (
+    Box<dyn AsyncRead + Send + Sync + Unpin + 'static>,
+    Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>,
+);
+
+type Proto = Box<dyn MessageProtocolApi + Send + Sync + 'static>;
+
+fn pipe() -> (Half, Half) {
+    let (a, b) = tokio::io::duplex(64 * 1024);
+    let (a_rx, a_tx) = tokio::io::split(a);
+    let (b_rx, b_tx) = tokio::io::split(b);
+    (
+        (Box::new(a_rx), Box::new(a_tx)),
+        (Box::new(b_rx), Box::new(b_tx)),
+    )
+}
+
+fn keepalive(interval_ms: u64) -> Option<KeepAlive> {
+    Some(KeepAlive::new(
+        Duration::from_millis(interval_ms),
+        Duration::from_millis(50),
+        2,
+    ))
+}
+
+async fn new_client(half: Half, max_version: MessageProtocolVersion, keepalive: Option<KeepAlive>) -> io::Result<(Proto, HelloMetadata)> {
+    mesh_hello_exchange_sender(
+        half.0,
+        half.1,
+        NodeId::generate_client_id(),
+        "/test".to_string(),
+        "localhost".to_string(),
+        None,
+        false,
+        max_version,
+        keepalive,
+    )
+    .await
+}
+
+async fn new_server(half: Half, max_version: MessageProtocolVersion, keepalive: Option<KeepAlive>) -> io::Result<(Proto, HelloMetadata)> {
+    mesh_hello_receive(half.0, half.1)
+        .await?
+        .with_keepalive(keepalive)
+        .reply(
+            NodeId::Server(1, 1),
+            None,
+            SerializationFormat::Bincode,
+            |_| false,
+            max_version,
+        )
+        .await
+}
+
+#[tokio::test]
+async fn test_keepalive_negotiation() {
+    // The most lenient of the two is used
+    let (a, b) = pipe();
+    let (client, server) = tokio::join!(
+        new_client(a, MessageProtocolVersion::default(), keepalive(20)),
+        new_server(b, MessageProtocolVersion::default(), keepalive(40)),
+    );
+    assert_eq!(client.unwrap().1.keepalive, keepalive(40));
+    assert_eq!(server.unwrap().1.keepalive, keepalive(40));
+
+    // Both sides must ask for it
+    let (a, b) = pipe();
+    let (client, server) = tokio::join!(
+        new_client(a, MessageProtocolVersion::default(), keepalive(20)),
+        new_server(b, MessageProtocolVersion::default(), None),
+    );
+    assert_eq!(client.unwrap().1.keepalive, None);
+    assert_eq!(server.unwrap().1.keepalive, None);
+
+    // Older versions of the protocol can not carry the pings
+    for version in [MessageProtocolVersion::V1, MessageProtocolVersion::V2] {
+        let (a, b) = pipe();
+        let (client, server) = tokio::join!(
+            new_client(a, MessageProtocolVersion::default(), keepalive(20)),
+            new_server(b, version, keepalive(20)),
+        );
+        assert_eq!(client.unwrap().1.keepalive, None);
+        assert_eq!(server.unwrap().1.keepalive, None);
+    }
+}
+
+#[tokio::test]
+async fn test_keepalive_is_not_surfaced() {
+    let (a, b) = pipe();
+    let (client, server) = tokio::join!(
+        new_client(a, MessageProtocolVersion::default(), keepalive(10)),
+        new_server(b, MessageProtocolVersion::default(), keepalive(10)),
+    );
+    let (mut client_rx, _client_tx) = client.unwrap().0.split(None);
+    let (mut server_rx, mut server_tx) = server.unwrap().0.split(None);
+
+    // Both sides read while the connection is quiet for many intervals
+    let server_reader = tokio::spawn(async move { server_rx.read().await });
+    let client_reader = tokio::spawn(async move { client_rx.read().await });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(client_reader.is_finished() == false);
+    assert!(server_reader.is_finished() == false);
+
+    // Only the messages of the application are returned
+    server_tx.write(b"hello").await.unwrap();
+    let data = client_reader.await.unwrap().unwrap();
+    assert_eq!(&data[..], b"hello");
+    server_reader.abort();
+}
+
+#[tokio::test]
+async fn test_keepalive_detects_dead_peer() {
+    let (a, b) = pipe();
+    let (client, server) = tokio::join!(
+        new_client(a, MessageProtocolVersion::default(), keepalive(10)),
+        new_server(b, MessageProtocolVersion::default(), keepalive(10)),
+    );
+    let (mut client_rx, _client_tx) = client.unwrap().0.split(None);
+
+    // The server keeps the connection open but never reads from it hence
+    // it never answers the pings
+    let _server = server.unwrap().0;
+    let err = tokio::time::timeout(Duration::from_secs(5), client_rx.read())
+        .await
+        .expect("the dead peer was not detected")
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
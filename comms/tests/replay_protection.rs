@@ -0,0 +1,193 @@
+//! Sequence numbers on the encrypted frames, a man-in-the-middle sits
+//! between the two sides and replays (or alters) the frames that it saw
+use std::io;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use ate_comms::mesh_hello_exchange_sender;
+use ate_comms::mesh_hello_receive;
+use ate_comms::HelloMetadata;
+use ate_comms::MessageProtocolApi;
+use ate_comms::MessageProtocolVersion;
+use ate_comms::ReplayError;
+use ate_crypto::EncryptKey;
+use ate_crypto::KeySize;
+use ate_crypto::NodeId;
+use ate_crypto::SerializationFormat;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+type Half = (
+    Box<dyn AsyncRead + Send + Sync + Unpin + 'static>,
+    Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>,
+);
+
+type Proto = Box<dyn MessageProtocolApi + Send + Sync + 'static>;
+
+/// Bytes that the client sent through the man-in-the-middle and a way to
+/// send more bytes to the server on its behalf
+struct Mitm {
+    captured: Arc<Mutex<Vec<u8>>>,
+    inject: mpsc::Sender<Vec<u8>>,
+}
+
+impl Mitm {
+    fn mark(&self) -> usize {
+        self.captured.lock().unwrap().len()
+    }
+
+    fn since(&self, mark: usize) -> Vec<u8> {
+        self.captured.lock().unwrap()[mark..].to_vec()
+    }
+}
+
+fn mitm() -> (Half, Half, Mitm) {
+    let (client, relay_client) = tokio::io::duplex(64 * 1024);
+    let (relay_server, server) = tokio::io::duplex(64 * 1024);
+    let (client_rx, client_tx) = tokio::io::split(client);
+    let (server_rx, server_tx) = tokio::io::split(server);
+    let (mut from_client, mut to_client) = tokio::io::split(relay_client);
+    let (mut from_server, mut to_server) = tokio::io::split(relay_server);
+
+    let captured = Arc::new(Mutex::new(Vec::new()));
+    let (inject, mut inject_rx) = mpsc::channel::<Vec<u8>>(16);
+    {
+        let captured = Arc::clone(&captured);
+        tokio::spawn(async move {
+            let mut buf = [0u8; 4096];
+            loop {
+                let data = tokio::select! {
+                    n = from_client.read(&mut buf) => match n {
+                        Ok(n) if n > 0 => {
+                            captured.lock().unwrap().extend_from_slice(&buf[..n]);
+                            buf[..n].to_vec()
+                        }
+                        _ => break,
+                    },
+                    data = inject_rx.recv() => match data {
+                        Some(a) => a,
+                        None => break,
+                    },
+                };
+                if to_server.write_all(&data[..]).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    tokio::spawn(async move {
+        let _ = tokio::io::copy(&mut from_server, &mut to_client).await;
+    });
+
+    (
+        (Box::new(client_rx), Box::new(client_tx)),
+        (Box::new(server_rx), Box::new(server_tx)),
+        Mitm { captured, inject },
+    )
+}
+
+async fn new_client(half: Half, max_version: MessageProtocolVersion) -> io::Result<(Proto, HelloMetadata)> {
+    mesh_hello_exchange_sender(
+        half.0,
+        half.1,
+        NodeId::generate_client_id(),
+        "/test".to_string(),
+        "localhost".to_string(),
+        None,
+        false,
+        max_version,
+        None,
+    )
+    .await
+}
+
+async fn new_server(half: Half, max_version: MessageProtocolVersion) -> io::Result<(Proto, HelloMetadata)> {
+    mesh_hello_receive(half.0, half.1)
+        .await?
+        .reply(
+            NodeId::Server(1, 1),
+            None,
+            SerializationFormat::Bincode,
+            |_| false,
+            max_version,
+        )
+        .await
+}
+
+#[tokio::test]
+async fn test_replay_protection_negotiation() {
+    let (a, b, _mitm) = mitm();
+    let (client, server) = tokio::join!(
+        new_client(a, MessageProtocolVersion::default()),
+        new_server(b, MessageProtocolVersion::default()),
+    );
+    assert!(client.unwrap().1.replay_protection);
+    assert!(server.unwrap().1.replay_protection);
+
+    // Older versions of the protocol do not carry the sequence numbers
+    for version in [MessageProtocolVersion::V1, MessageProtocolVersion::V2] {
+        let (a, b, _mitm) = mitm();
+        let (client, server) = tokio::join!(
+            new_client(a, MessageProtocolVersion::default()),
+            new_server(b, version),
+        );
+        assert!(client.unwrap().1.replay_protection == false);
+        assert!(server.unwrap().1.replay_protection == false);
+    }
+}
+
+#[tokio::test]
+async fn test_replayed_frame_terminates_connection() {
+    let (a, b, mitm) = mitm();
+    let (client, server) = tokio::join!(
+        new_client(a, MessageProtocolVersion::default()),
+        new_server(b, MessageProtocolVersion::default()),
+    );
+    let ek = EncryptKey::generate(KeySize::Bit128);
+    let (_client_rx, mut client_tx) = client.unwrap().0.split(Some(ek.clone()));
+    let (mut server_rx, _server_tx) = server.unwrap().0.split(Some(ek));
+
+    let mark = mitm.mark();
+    client_tx.write(b"commit").await.unwrap();
+    assert_eq!(&server_rx.read().await.unwrap()[..], b"commit");
+
+    // Sending the same cipher text again is detected as a duplicate
+    mitm.inject.send(mitm.since(mark)).await.unwrap();
+    let err = server_rx.read().await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionAborted);
+    assert_eq!(
+        ReplayError::find(&err),
+        Some(ReplayError::Duplicate { seq: 0, expected: 1 })
+    );
+
+    // Nothing more is read from the connection
+    assert!(server_rx.read().await.is_err());
+}
+
+#[tokio::test]
+async fn test_renumbered_frame_terminates_connection() {
+    let (a, b, mitm) = mitm();
+    let (client, server) = tokio::join!(
+        new_client(a, MessageProtocolVersion::default()),
+        new_server(b, MessageProtocolVersion::default()),
+    );
+    let ek = EncryptKey::generate(KeySize::Bit128);
+    let (_client_rx, mut client_tx) = client.unwrap().0.split(Some(ek.clone()));
+    let (mut server_rx, _server_tx) = server.unwrap().0.split(Some(ek));
+
+    let mark = mitm.mark();
+    client_tx.write(b"commit").await.unwrap();
+    assert_eq!(&server_rx.read().await.unwrap()[..], b"commit");
+
+    // The frame is the new IV (op code and 16 bytes), a single byte header
+    // and then the sequence number in the clear, changing it to the one the
+    // server expects next makes the cipher text decrypt to garbage
+    let mut frame = mitm.since(mark);
+    frame[18..26].copy_from_slice(&1u64.to_be_bytes());
+    mitm.inject.send(frame).await.unwrap();
+    let err = server_rx.read().await.unwrap_err();
+    assert_eq!(ReplayError::find(&err), Some(ReplayError::Tampered { seq: 1 }));
+}
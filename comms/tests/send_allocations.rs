@@ -0,0 +1,97 @@
+//! Counts the heap allocations made while sending messages to make sure the
+//! send path of the message protocols reuses its buffers between messages
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::cell::Cell;
+
+use ate_comms::MessageProtocolApi;
+use ate_comms::MessageProtocolVersion;
+use ate_crypto::EncryptKey;
+use ate_crypto::KeySize;
+
+struct CountingAllocator;
+
+thread_local! {
+    static COUNTING: Cell<bool> = Cell::new(false);
+    static ALLOCATIONS: Cell<usize> = Cell::new(0);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = COUNTING.try_with(|counting| {
+            if counting.get() {
+                let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+            }
+        });
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const ITERATIONS: usize = 100;
+
+/// Sends the same message many times and returns the number of allocations
+/// that were made per message
+async fn allocations_per_send(
+    version: MessageProtocolVersion,
+    wire_encryption: Option<EncryptKey>,
+    len: usize,
+) -> usize {
+    let mut proto = version.create(None, Some(Box::new(tokio::io::sink())));
+    let data = vec![7u8; len];
+
+    // The first message allocates the scratch buffer and the IV
+    proto.send(&wire_encryption, &data[..]).await.unwrap();
+
+    ALLOCATIONS.with(|a| a.set(0));
+    COUNTING.with(|a| a.set(true));
+    for _ in 0..ITERATIONS {
+        proto.send(&wire_encryption, &data[..]).await.unwrap();
+    }
+    COUNTING.with(|a| a.set(false));
+    ALLOCATIONS.with(|a| a.get()) / ITERATIONS
+}
+
+// Only the futures boxed by the async trait remain, the header, IV and
+// payload are no longer copied into freshly allocated buffers
+const MAX_ALLOCATIONS_PER_SEND: usize = 2;
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_send_allocations_plain() {
+    for version in [MessageProtocolVersion::V2, MessageProtocolVersion::V3] {
+        for len in [10usize, 1000, 100000] {
+            let allocs = allocations_per_send(version, None, len).await;
+            assert!(
+                allocs <= MAX_ALLOCATIONS_PER_SEND,
+                "{} made {} allocations per send (len={})",
+                version,
+                allocs,
+                len
+            );
+        }
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_send_allocations_encrypted() {
+    let key = EncryptKey::generate(KeySize::Bit256);
+    for version in [MessageProtocolVersion::V2, MessageProtocolVersion::V3] {
+        for len in [10usize, 1000, 100000] {
+            let allocs = allocations_per_send(version, Some(key.clone()), len).await;
+            assert!(
+                allocs <= MAX_ALLOCATIONS_PER_SEND,
+                "{} made {} allocations per encrypted send (len={})",
+                version,
+                allocs,
+                len
+            );
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Negotiation of the stream protocol version during the hello, the older
+//! peers are simulated by exchanging the hello messages they would send
+use std::io;
+
+use ate_comms::mesh_hello_exchange_receiver;
+use ate_comms::mesh_hello_exchange_sender;
+use ate_comms::MessageProtocolApi;
+use ate_comms::MessageProtocolVersion;
+use ate_crypto::NodeId;
+use ate_crypto::SerializationFormat;
+use serde_json::json;
+use serde_json::Value;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+
+type Half = (
+    Box<dyn AsyncRead + Send + Sync + Unpin + 'static>,
+    Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>,
+);
+
+fn pipe() -> (Half, Half) {
+    let (a, b) = tokio::io::duplex(64 * 1024);
+    let (a_rx, a_tx) = tokio::io::split(a);
+    let (b_rx, b_tx) = tokio::io::split(b);
+    (
+        (Box::new(a_rx), Box::new(a_tx)),
+        (Box::new(b_rx), Box::new(b_tx)),
+    )
+}
+
+async fn new_client(half: Half, max_version: MessageProtocolVersion) -> io::Result<MessageProtocolVersion> {
+    let (_, hello) = mesh_hello_exchange_sender(
+        half.0,
+        half.1,
+        NodeId::generate_client_id(),
+        "/test".to_string(),
+        "localhost".to_string(),
+        None,
+        false,
+        max_version,
+        None,
+    )
+    .await?;
+    Ok(hello.version)
+}
+
+async fn new_server(half: Half, max_version: MessageProtocolVersion) -> io::Result<MessageProtocolVersion> {
+    let (_, hello) = mesh_hello_exchange_receiver(
+        half.0,
+        half.1,
+        NodeId::Server(1, 1),
+        None,
+        SerializationFormat::Bincode,
+        |_| false,
+        max_version,
+    )
+    .await?;
+    Ok(hello.version)
+}
+
+/// Hello messages are always exchanged with the V1 framing
+async fn raw_exchange(half: Half, send_first: Option<Value>) -> io::Result<(Box<dyn MessageProtocolApi + Send + Sync + 'static>, Value)> {
+    let mut proto = MessageProtocolVersion::V1.create(Some(half.0), Some(half.1));
+    if let Some(msg) = send_first {
+        let data = serde_json::to_vec(&msg)?;
+        proto.write_with_fixed_16bit_header(&data[..], false).await?;
+    }
+    let data = proto.read_with_fixed_16bit_header().await?;
+    Ok((proto, serde_json::from_slice(&data[..])?))
+}
+
+#[tokio::test]
+async fn test_negotiates_highest_common_version() {
+    let (a, b) = pipe();
+    let (client, server) = tokio::join!(
+        new_client(a, MessageProtocolVersion::default()),
+        new_server(b, MessageProtocolVersion::V2),
+    );
+    assert_eq!(client.unwrap(), MessageProtocolVersion::V2);
+    assert_eq!(server.unwrap(), MessageProtocolVersion::V2);
+
+    let (a, b) = pipe();
+    let (client, server) = tokio::join!(
+        new_client(a, MessageProtocolVersion::default()),
+        new_server(b, MessageProtocolVersion::default()),
+    );
+    assert_eq!(client.unwrap(), MessageProtocolVersion::default());
+    assert_eq!(server.unwrap(), MessageProtocolVersion::default());
+}
+
+#[tokio::test]
+async fn test_new_client_old_server() {
+    // Old servers ignore the list of versions and reply with the lowest of
+    // their version and the one the client put in the legacy field
+    let old_server = |half: Half| async move {
+        let (mut proto, hello) = raw_exchange(half, None).await?;
+        assert_eq!(hello["version"], json!("V3"));
+        assert_eq!(hello["versions"], json!([1, 2, 3]));
+        let reply = json!({
+            "id": NodeId::Server(1, 1),
+            "encryption": null,
+            "wire_format": SerializationFormat::Bincode,
+            "version": "V2",
+        });
+        let data = serde_json::to_vec(&reply)?;
+        proto.write_with_fixed_16bit_header(&data[..], false).await?;
+        io::Result::Ok(())
+    };
+
+    let (a, b) = pipe();
+    let (client, server) = tokio::join!(
+        new_client(a, MessageProtocolVersion::default()),
+        old_server(b),
+    );
+    server.unwrap();
+    assert_eq!(client.unwrap(), MessageProtocolVersion::V2);
+}
+
+#[tokio::test]
+async fn test_old_client_new_server() {
+    // Old clients only send their highest version (and support all below it)
+    let hello = json!({
+        "id": NodeId::generate_client_id(),
+        "path": "/test",
+        "domain": "localhost",
+        "key_size": null,
+        "version": "V2",
+    });
+
+    let (a, b) = pipe();
+    let (client, server) = tokio::join!(
+        raw_exchange(a, Some(hello)),
+        new_server(b, MessageProtocolVersion::default()),
+    );
+    assert_eq!(server.unwrap(), MessageProtocolVersion::V2);
+    let (_, reply) = client.unwrap();
+    assert_eq!(reply["version"], json!("V2"));
+}
+
+#[tokio::test]
+async fn test_no_common_version() {
+    // A client from the future that dropped every version this server knows
+    let hello = json!({
+        "id": NodeId::generate_client_id(),
+        "path": "/test",
+        "domain": "localhost",
+        "key_size": null,
+        "version": "V3",
+        "versions": [4, 5],
+    });
+
+    let (a, b) = pipe();
+    let (client, server) = tokio::join!(
+        raw_exchange(a, Some(hello)),
+        new_server(b, MessageProtocolVersion::default()),
+    );
+    assert_eq!(server.unwrap_err().kind(), io::ErrorKind::Unsupported);
+    let (_, reply) = client.unwrap();
+    assert_eq!(reply["server_supports"], json!([1, 2, 3]));
+    assert!(reply["error"].as_str().unwrap().contains("v4, v5"));
+
+    // A server from the future that dropped every version this client knows
+    let future_server = |half: Half| async move {
+        let (mut proto, _) = raw_exchange(half, None).await?;
+        let reply = json!({
+            "error": "no common stream protocol version",
+            "server_supports": [4, 5],
+        });
+        let data = serde_json::to_vec(&reply)?;
+        proto.write_with_fixed_16bit_header(&data[..], false).await?;
+        io::Result::Ok(())
+    };
+
+    let (a, b) = pipe();
+    let (client, server) = tokio::join!(
+        new_client(a, MessageProtocolVersion::default()),
+        future_server(b),
+    );
+    server.unwrap();
+    let err = client.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    assert!(err.to_string().contains("the server supports [v4, v5]"));
+}
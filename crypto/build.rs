@@ -0,0 +1,18 @@
+use std::env;
+
+fn main() {
+    // OpenSSL is only used when it is asked for and the target is one where
+    // it is reliably available, the musl (static) builds, ARM64 and WebAssembly
+    // always use the pure Rust implementation
+    let target_env = env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let target_family = env::var("CARGO_CFG_TARGET_FAMILY").unwrap_or_default();
+    if env::var("CARGO_FEATURE_ENABLE_OPENSSL").is_ok()
+        && target_env != "musl"
+        && target_arch != "aarch64"
+        && target_family.split(',').any(|a| a == "wasm") == false
+    {
+        println!("cargo:rustc-cfg=ate_openssl");
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
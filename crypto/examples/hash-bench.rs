@@ -0,0 +1,59 @@
+//! Compares hashing large payloads on a single thread (the way it was done
+//! before) with the parallel routine used by AteHash, e.g.
+//!
+//!   cargo run --release --example hash-bench
+use ate_crypto::AteHash;
+use std::time::Duration;
+use std::time::Instant;
+
+const ITERATIONS: u32 = 20;
+
+fn serial(data: &[u8]) -> [u8; 16] {
+    let hash: [u8; 32] = blake3::hash(data).into();
+    let mut ret = [0u8; 16];
+    ret.copy_from_slice(&hash[..16]);
+    ret
+}
+
+fn measure<F>(mut f: F) -> Duration
+where
+    F: FnMut(),
+{
+    f();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn throughput(size: usize, elapsed: Duration) -> f64 {
+    (size as f64 / 1048576f64) / elapsed.as_secs_f64()
+}
+
+fn main() -> std::io::Result<()> {
+    for size in [1048576usize, 16777216usize] {
+        let data = (0..size).map(|a| (a % 251) as u8).collect::<Vec<_>>();
+
+        // The output must be identical or existing chains will break
+        let expected = serial(&data[..]);
+        assert_eq!(AteHash::from_bytes(&data[..]).val, expected);
+        assert_eq!(AteHash::from_reader(&data[..])?.val, expected);
+
+        let before = measure(|| {
+            serial(&data[..]);
+        });
+        let after = measure(|| {
+            AteHash::from_bytes(&data[..]);
+        });
+        let streamed = measure(|| {
+            AteHash::from_reader(&data[..]).unwrap();
+        });
+
+        println!("{}MB payload", size / 1048576);
+        println!("  serial:      {:>8.3}ms ({:.0} MB/s)", before.as_secs_f64() * 1000f64, throughput(size, before));
+        println!("  from_bytes:  {:>8.3}ms ({:.0} MB/s)", after.as_secs_f64() * 1000f64, throughput(size, after));
+        println!("  from_reader: {:>8.3}ms ({:.0} MB/s)", streamed.as_secs_f64() * 1000f64, throughput(size, streamed));
+    }
+    Ok(())
+}
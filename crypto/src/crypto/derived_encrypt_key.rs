@@ -33,7 +33,6 @@ impl DerivedEncryptKey {
         Ok(EncryptKey::from_bytes(&bytes[..])?)
     }
 
-    #[cfg(feature = "quantum")]
     pub fn transmute_private(&self, key: &PrivateEncryptKey) -> Result<EncryptKey, std::io::Error> {
         // Decrypt the derived key
         let bytes = key.decrypt(&self.inner.iv, &self.inner.data[..])?;
@@ -47,7 +46,7 @@ impl DerivedEncryptKey {
         Ok(())
     }
 
-    #[cfg(feature = "quantum")]
+    #[cfg(feature = "asymmetric")]
     pub fn change_private(
         &mut self,
         old: &PrivateEncryptKey,
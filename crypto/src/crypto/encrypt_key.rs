@@ -14,20 +14,22 @@ use std::result::Result;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
-#[cfg(feature = "use_openssl")]
+#[cfg(ate_openssl)]
 use openssl::symm::Cipher;
 
-#[cfg(not(feature = "enable_openssl"))]
+// The AES implementation selects the AES-NI instructions at runtime when the
+// CPU supports them (falling back to a constant time software implementation)
 use ctr::cipher::*;
-#[cfg(not(feature = "enable_openssl"))]
 type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
-#[cfg(not(feature = "enable_openssl"))]
 type Aes192Ctr = ctr::Ctr128BE<aes::Aes192>;
-#[cfg(not(feature = "enable_openssl"))]
 type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
 
 use super::*;
 
+/// Size of the chunks that are encrypted in place when using OpenSSL
+#[cfg(ate_openssl)]
+const IN_PLACE_CHUNK_SIZE: usize = 4096;
+
 /// Represents an encryption key that will give confidentiality to
 /// data stored within the redo-log. Note this does not give integrity
 /// which comes from the `PrivateKey` crypto instead.
@@ -104,7 +106,7 @@ impl EncryptKey {
         }
     }
 
-    #[cfg(feature = "enable_openssl")]
+    #[cfg(ate_openssl)]
     pub fn cipher(&self) -> Cipher {
         match self.size() {
             KeySize::Bit128 => Cipher::aes_128_ctr(),
@@ -113,7 +115,7 @@ impl EncryptKey {
         }
     }
 
-    #[cfg(feature = "enable_openssl")]
+    #[cfg(ate_openssl)]
     pub fn encrypt_with_iv(&self, iv: &InitializationVector, data: &[u8]) -> Vec<u8> {
         let mut iv_store;
         let iv = match iv.bytes.len() {
@@ -131,7 +133,7 @@ impl EncryptKey {
         openssl::symm::encrypt(self.cipher(), self.value(), Some(&iv.bytes[..]), data).unwrap()
     }
 
-    #[cfg(not(feature = "enable_openssl"))]
+    #[cfg(not(ate_openssl))]
     pub fn encrypt_with_iv(&self, iv: &InitializationVector, data: &[u8]) -> Vec<u8> {
         let mut iv_store;
         let iv = match iv.bytes.len() {
@@ -167,7 +169,59 @@ impl EncryptKey {
         data
     }
 
-    #[cfg(not(feature = "enable_openssl"))]
+    /// Encrypts the data in place (which avoids allocating a new buffer for
+    /// every message that is encrypted)
+    #[cfg(ate_openssl)]
+    pub fn encrypt_with_iv_in_place(&self, iv: &InitializationVector, data: &mut [u8]) {
+        let mut iv_bytes = [0u8; 16];
+        let len = iv.bytes.len().min(16);
+        iv_bytes[..len].copy_from_slice(&iv.bytes[..len]);
+
+        // OpenSSL can not encrypt a buffer onto itself so the data passes
+        // through buffers on the stack a chunk at a time (the counter mode
+        // has no padding hence every chunk comes out as long as it went in)
+        let mut crypter = openssl::symm::Crypter::new(
+            self.cipher(),
+            openssl::symm::Mode::Encrypt,
+            self.value(),
+            Some(&iv_bytes[..]),
+        )
+        .unwrap();
+        let mut input = [0u8; IN_PLACE_CHUNK_SIZE];
+        let mut output = [0u8; IN_PLACE_CHUNK_SIZE + 16];
+        for chunk in data.chunks_mut(IN_PLACE_CHUNK_SIZE) {
+            let input = &mut input[..chunk.len()];
+            input.copy_from_slice(chunk);
+            let len = crypter.update(input, &mut output[..]).unwrap();
+            debug_assert_eq!(len, chunk.len());
+            chunk.copy_from_slice(&output[..len]);
+        }
+    }
+
+    /// Encrypts the data in place (which avoids allocating a new buffer for
+    /// every message that is encrypted)
+    #[cfg(not(ate_openssl))]
+    pub fn encrypt_with_iv_in_place(&self, iv: &InitializationVector, data: &mut [u8]) {
+        let mut iv_bytes = [0u8; 16];
+        let len = iv.bytes.len().min(16);
+        iv_bytes[..len].copy_from_slice(&iv.bytes[..len]);
+
+        match self.size() {
+            KeySize::Bit128 => {
+                let mut cipher = Aes128Ctr::new(self.value().into(), (&iv_bytes[..]).into());
+                cipher.apply_keystream(data);
+            }
+            KeySize::Bit192 => {
+                let mut cipher = Aes192Ctr::new(self.value().into(), (&iv_bytes[..]).into());
+                cipher.apply_keystream(data);
+            }
+            KeySize::Bit256 => {
+                let mut cipher = Aes256Ctr::new(self.value().into(), (&iv_bytes[..]).into());
+                cipher.apply_keystream(data);
+            }
+        }
+    }
+
     pub fn encrypt_with_hash_iv(&self, hash: &AteHash, data: &[u8]) -> Vec<u8> {
         let iv: &[u8; 16] = hash.as_bytes();
         
@@ -190,7 +244,6 @@ impl EncryptKey {
         data
     }
 
-    #[cfg(not(feature = "enable_openssl"))]
     pub fn encrypt_with_hash_iv_with_capacity(&self, hash: &AteHash, data: &[u8], capacity: usize) -> Vec<u8> {
         let iv: &[u8; 16] = hash.as_bytes();
         
@@ -217,7 +270,6 @@ impl EncryptKey {
         ret
     }
 
-    #[cfg(not(feature = "enable_openssl"))]
     pub fn encrypt_with_hash_iv_with_capacity_and_prefix(&self, hash: &AteHash, data: &[u8], capacity: usize, prefix: &[u8]) -> Vec<u8> {
         let iv: &[u8; 16] = hash.as_bytes();
 
@@ -251,7 +303,7 @@ impl EncryptKey {
         EncryptResult { iv: iv, data: data }
     }
 
-    #[cfg(feature = "enable_openssl")]
+    #[cfg(ate_openssl)]
     pub fn decrypt(&self, iv: &InitializationVector, data: &[u8]) -> Vec<u8> {
         let mut iv_store;
         let iv = match iv.bytes.len() {
@@ -269,7 +321,7 @@ impl EncryptKey {
         openssl::symm::decrypt(self.cipher(), self.value(), Some(&iv.bytes[..]), data).unwrap()
     }
 
-    #[cfg(not(feature = "enable_openssl"))]
+    #[cfg(not(ate_openssl))]
     pub fn decrypt(&self, iv: &InitializationVector, data: &[u8]) -> Vec<u8> {
         let mut iv_store;
         let iv = match iv.bytes.len() {
@@ -305,7 +357,6 @@ impl EncryptKey {
         data
     }
 
-    #[cfg(not(feature = "enable_openssl"))]
     pub fn decrypt_with_hash_iv(&self, hash: &AteHash, data: &[u8]) -> Vec<u8> {
         let iv: &[u8; 16] = hash.as_bytes();
 
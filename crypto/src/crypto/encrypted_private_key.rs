@@ -16,6 +16,7 @@ pub struct EncryptedPrivateKey {
 }
 
 impl EncryptedPrivateKey {
+    #[cfg(feature = "asymmetric")]
     #[allow(dead_code)]
     pub fn generate(encrypt_key: &EncryptKey) -> EncryptedPrivateKey {
         let pair = PrivateSignKey::generate(encrypt_key.size());
@@ -4,6 +4,7 @@ use crate::utils::b16_serialize;
 use serde::{Deserialize, Serialize};
 use sha3::Digest;
 use std::convert::TryInto;
+use std::io;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
@@ -15,6 +16,13 @@ pub enum HashRoutine {
     Blake3,
 }
 
+/// Payloads at least this large are hashed on multiple threads (the SIMD
+/// routines that blake3 selects at runtime are used in either case)
+pub const PARALLEL_HASH_THRESHOLD: usize = 1048576usize;
+
+/// Size of the buffer used when hashing data read from a stream
+const READER_BUFFER_SIZE: usize = PARALLEL_HASH_THRESHOLD;
+
 /// Represents a hash of a piece of data that is cryptographically secure enough
 /// that it can be used for integrity but small enough that it does not bloat
 /// the redo log metadata.
@@ -46,6 +54,53 @@ impl AteHash {
     pub fn from_bytes_twice(input1: &[u8], input2: &[u8]) -> AteHash {
         Self::from_bytes_twice_by_routine(input1, input2, crate::HASH_ROUTINE)
     }
+    /// Hashes all the data read from the reader without holding it all in
+    /// memory, the result is identical to calling `from_bytes` on the data
+    /// (use `Read::chain` to get the equivalent of `from_bytes_twice`)
+    pub fn from_reader<R>(reader: R) -> io::Result<AteHash>
+    where
+        R: io::Read,
+    {
+        Self::from_reader_by_routine(reader, crate::HASH_ROUTINE)
+    }
+    fn from_reader_by_routine<R>(mut reader: R, routine: HashRoutine) -> io::Result<AteHash>
+    where
+        R: io::Read,
+    {
+        let mut sha3 = sha3::Keccak384::default();
+        let mut blake3 = blake3::Hasher::new();
+
+        let mut buf = vec![0u8; READER_BUFFER_SIZE];
+        loop {
+            // Fill the whole buffer (if possible) so that large streams
+            // still benefit from the parallel hashing
+            let mut len = 0usize;
+            while len < buf.len() {
+                match reader.read(&mut buf[len..]) {
+                    Ok(0) => break,
+                    Ok(n) => len += n,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err),
+                }
+            }
+            if len <= 0 {
+                break;
+            }
+            match routine {
+                HashRoutine::Sha3 => {
+                    sha3.update(&buf[..len]);
+                }
+                HashRoutine::Blake3 => {
+                    Self::update_blake3(&mut blake3, &buf[..len]);
+                }
+            }
+        }
+
+        Ok(match routine {
+            HashRoutine::Sha3 => Self::finalize_sha3(sha3),
+            HashRoutine::Blake3 => Self::finalize_blake3(blake3),
+        })
+    }
     fn from_bytes_by_routine(input: &[u8], routine: HashRoutine) -> AteHash {
         match routine {
             HashRoutine::Sha3 => AteHash::from_bytes_sha3(input, 1),
@@ -59,16 +114,31 @@ impl AteHash {
         }
     }
     pub fn from_bytes_blake3(input: &[u8]) -> AteHash {
-        let hash = blake3::hash(input);
-        let bytes: [u8; 32] = hash.into();
-        let mut bytes16: [u8; 16] = Default::default();
-        bytes16.copy_from_slice(&bytes[0..16]);
-        AteHash { val: bytes16 }
+        let mut hasher = blake3::Hasher::new();
+        Self::update_blake3(&mut hasher, input);
+        Self::finalize_blake3(hasher)
     }
     fn from_bytes_twice_blake3(input1: &[u8], input2: &[u8]) -> AteHash {
         let mut hasher = blake3::Hasher::new();
-        hasher.update(input1);
-        hasher.update(input2);
+        Self::update_blake3(&mut hasher, input1);
+        Self::update_blake3(&mut hasher, input2);
+        Self::finalize_blake3(hasher)
+    }
+    /// Blake3 is a tree hash so splitting the input across threads yields
+    /// exactly the same result as hashing it on a single thread
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_blake3(hasher: &mut blake3::Hasher, input: &[u8]) {
+        if input.len() >= PARALLEL_HASH_THRESHOLD {
+            hasher.update_with_join::<blake3::join::RayonJoin>(input);
+        } else {
+            hasher.update(input);
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    fn update_blake3(hasher: &mut blake3::Hasher, input: &[u8]) {
+        hasher.update(input);
+    }
+    fn finalize_blake3(hasher: blake3::Hasher) -> AteHash {
         let hash = hasher.finalize();
         let bytes: [u8; 32] = hash.into();
         let mut bytes16: [u8; 16] = Default::default();
@@ -80,18 +150,15 @@ impl AteHash {
         for _ in 0..repeat {
             hasher.update(input);
         }
-        let result = hasher.finalize();
-        let result: Vec<u8> = result.into_iter().take(16).collect();
-        let result: [u8; 16] = result
-            .try_into()
-            .expect("The hash should fit into 16 bytes!");
-
-        AteHash { val: result }
+        Self::finalize_sha3(hasher)
     }
     fn from_bytes_twice_sha3(input1: &[u8], input2: &[u8]) -> AteHash {
         let mut hasher = sha3::Keccak384::default();
         hasher.update(input1);
         hasher.update(input2);
+        Self::finalize_sha3(hasher)
+    }
+    fn finalize_sha3(hasher: sha3::Keccak384) -> AteHash {
         let result = hasher.finalize();
         let result: Vec<u8> = result.into_iter().take(16).collect();
         let result: [u8; 16] = result
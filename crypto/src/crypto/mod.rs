@@ -1,22 +1,20 @@
+// The asymmetric key types are always available (so that chains holding
+// them can be read) but without the 'asymmetric' feature keys can not be
+// generated and signing, verifying and key exchange fail as unsupported
 pub mod derived_encrypt_key;
 pub mod double_hash;
 pub mod encrypt_key;
-#[cfg(feature = "quantum")]
 pub mod encrypted_private_key;
 pub mod encrypted_secure_data;
 pub mod fast_random;
 pub mod hash;
 pub mod initialization_vector;
 pub mod key_size;
-#[cfg(feature = "quantum")]
 pub mod private_encrypt_key;
-#[cfg(feature = "quantum")]
 pub mod public_encrypted_secure_data;
 pub mod random_generator_accessor;
 pub mod short_hash;
-#[cfg(feature = "quantum")]
 pub mod sign_key;
-#[cfg(feature = "quantum")]
 pub mod signed_protected_data;
 pub mod tests;
 
@@ -25,19 +23,14 @@ pub use random_generator_accessor::*;
 pub use self::hash::*;
 pub use derived_encrypt_key::*;
 pub use encrypt_key::*;
-#[cfg(feature = "quantum")]
 pub use encrypted_private_key::*;
 pub use encrypted_secure_data::*;
 pub use initialization_vector::*;
 pub use key_size::*;
-#[cfg(feature = "quantum")]
 pub use private_encrypt_key::*;
-#[cfg(feature = "quantum")]
 pub use public_encrypted_secure_data::*;
 pub use short_hash::*;
-#[cfg(feature = "quantum")]
 pub use sign_key::*;
-#[cfg(feature = "quantum")]
 pub use signed_protected_data::*;
 #[cfg(test)]
 pub use tests::*;
@@ -1,8 +1,12 @@
 use crate::utils::vec_deserialize;
 use crate::utils::vec_serialize;
+#[cfg(feature = "asymmetric")]
 use pqcrypto_ntru_wasi::ntruhps2048509 as ntru128;
+#[cfg(feature = "asymmetric")]
 use pqcrypto_ntru_wasi::ntruhps2048677 as ntru192;
+#[cfg(feature = "asymmetric")]
 use pqcrypto_ntru_wasi::ntruhps4096821 as ntru256;
+#[cfg(feature = "asymmetric")]
 use pqcrypto_traits_wasi::kem::*;
 use serde::{Deserialize, Serialize};
 use std::result::Result;
@@ -36,6 +40,7 @@ pub enum PrivateEncryptKey {
 }
 
 impl PrivateEncryptKey {
+    #[cfg(feature = "asymmetric")]
     #[allow(dead_code)]
     pub fn generate(size: KeySize) -> PrivateEncryptKey {
         match size {
@@ -105,6 +110,14 @@ impl PrivateEncryptKey {
         }
     }
 
+    /// (without the 'asymmetric' feature nothing can be decapsulated)
+    #[cfg(not(feature = "asymmetric"))]
+    #[allow(dead_code)]
+    pub fn decapsulate(&self, _iv: &InitializationVector) -> Option<EncryptKey> {
+        None
+    }
+
+    #[cfg(feature = "asymmetric")]
     #[allow(dead_code)]
     pub fn decapsulate(&self, iv: &InitializationVector) -> Option<EncryptKey> {
         match &self {
@@ -145,6 +158,11 @@ impl PrivateEncryptKey {
     ) -> Result<Vec<u8>, std::io::Error> {
         let ek = match self.decapsulate(iv) {
             Some(a) => a,
+            #[cfg(not(feature = "asymmetric"))]
+            None => {
+                return Err(super::sign_key::asymmetric_unsupported());
+            }
+            #[cfg(feature = "asymmetric")]
             None => {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::Other,
@@ -163,6 +181,11 @@ impl PrivateEncryptKey {
     ) -> Result<Vec<u8>, std::io::Error> {
         let ek = match self.decapsulate(iv) {
             Some(a) => a,
+            #[cfg(not(feature = "asymmetric"))]
+            None => {
+                return Err(super::sign_key::asymmetric_unsupported());
+            }
+            #[cfg(feature = "asymmetric")]
             None => {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::Other,
@@ -227,10 +250,20 @@ pub enum PublicEncryptKey {
 
 impl PublicEncryptKey {
     pub fn from_bytes(bytes: Vec<u8>) -> Option<PublicEncryptKey> {
+        #[cfg(feature = "asymmetric")]
+        let (len128, len192, len256) = (
+            ntru128::public_key_bytes(),
+            ntru192::public_key_bytes(),
+            ntru256::public_key_bytes(),
+        );
+        // (sizes of the NTRU public keys when the library is not linked)
+        #[cfg(not(feature = "asymmetric"))]
+        let (len128, len192, len256) = (699usize, 930usize, 1230usize);
+
         match bytes.len() {
-            a if a == ntru128::public_key_bytes() => Some(PublicEncryptKey::Ntru128 { pk: bytes }),
-            a if a == ntru192::public_key_bytes() => Some(PublicEncryptKey::Ntru192 { pk: bytes }),
-            a if a == ntru256::public_key_bytes() => Some(PublicEncryptKey::Ntru256 { pk: bytes }),
+            a if a == len128 => Some(PublicEncryptKey::Ntru128 { pk: bytes }),
+            a if a == len192 => Some(PublicEncryptKey::Ntru192 { pk: bytes }),
+            a if a == len256 => Some(PublicEncryptKey::Ntru256 { pk: bytes }),
             _ => None,
         }
     }
@@ -252,6 +285,7 @@ impl PublicEncryptKey {
         }
     }
 
+    #[cfg(feature = "asymmetric")]
     #[allow(dead_code)]
     pub fn encapsulate(&self) -> (InitializationVector, EncryptKey) {
         match &self {
@@ -285,6 +319,7 @@ impl PublicEncryptKey {
         }
     }
 
+    #[cfg(feature = "asymmetric")]
     pub fn encrypt(&self, data: &[u8]) -> EncryptResult {
         let (iv, ek) = self.encapsulate();
         let data = ek.encrypt_with_iv(&iv, data);
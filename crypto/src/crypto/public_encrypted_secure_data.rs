@@ -10,6 +10,24 @@ use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use super::*;
 
+/// Encrypts data with a public key, which is not possible without the
+/// 'asymmetric' feature
+#[cfg(feature = "asymmetric")]
+fn encrypt_with_public_key(
+    encrypt_key: &PublicEncryptKey,
+    data: &[u8],
+) -> Result<EncryptResult, std::io::Error> {
+    Ok(encrypt_key.encrypt(data))
+}
+
+#[cfg(not(feature = "asymmetric"))]
+fn encrypt_with_public_key(
+    _encrypt_key: &PublicEncryptKey,
+    _data: &[u8],
+) -> Result<EncryptResult, std::io::Error> {
+    Err(super::sign_key::asymmetric_unsupported())
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PublicEncryptedSecureData<T>
 where
@@ -39,7 +57,7 @@ where
                 return Err(std::io::Error::new(ErrorKind::Other, err.to_string()));
             }
         };
-        let result = encrypt_key.encrypt(&data[..]);
+        let result = encrypt_with_public_key(encrypt_key, &data[..])?;
 
         Ok(PublicEncryptedSecureData {
             format,
@@ -1,8 +1,12 @@
 use crate::utils::vec_deserialize;
 use crate::utils::vec_serialize;
+#[cfg(feature = "asymmetric")]
 use pqcrypto_falcon_wasi::falcon1024;
+#[cfg(feature = "asymmetric")]
 use pqcrypto_falcon_wasi::falcon512;
+#[cfg(feature = "asymmetric")]
 use pqcrypto_traits_wasi::sign::SecretKey as PQCryptoSecretKey;
+#[cfg(feature = "asymmetric")]
 use pqcrypto_traits_wasi::sign::{DetachedSignature, PublicKey as PQCryptoPublicKey};
 use serde::{Deserialize, Serialize};
 use std::io::ErrorKind;
@@ -12,6 +16,37 @@ use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use super::*;
 
+/// Error returned when a signature can not be verified
+#[cfg(feature = "asymmetric")]
+pub type SignatureError = pqcrypto_traits_wasi::Error;
+
+/// Error returned when a signature can not be verified (without the
+/// 'asymmetric' feature no signature can be verified)
+#[cfg(not(feature = "asymmetric"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureError {
+    Unsupported,
+}
+
+#[cfg(not(feature = "asymmetric"))]
+impl std::fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureError::Unsupported => write!(f, "signatures can not be verified without the 'asymmetric' feature"),
+        }
+    }
+}
+
+/// Error returned by the operations that need asymmetric crypto when the
+/// crate was built without the 'asymmetric' feature
+#[cfg(not(feature = "asymmetric"))]
+pub(crate) fn asymmetric_unsupported() -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::Unsupported,
+        "asymmetric crypto is not supported without the 'asymmetric' feature",
+    )
+}
+
 /// Private keys provide the ability to sign records within the
 /// redo log chain-of-trust, these inserts records with associated
 /// public keys embedded within teh cahin allow
@@ -36,6 +71,7 @@ pub enum PrivateSignKey {
 }
 
 impl PrivateSignKey {
+    #[cfg(feature = "asymmetric")]
     #[allow(dead_code)]
     pub fn generate(size: KeySize) -> PrivateSignKey {
         match size {
@@ -92,6 +128,13 @@ impl PrivateSignKey {
         }
     }
 
+    #[cfg(not(feature = "asymmetric"))]
+    #[allow(dead_code)]
+    pub fn sign(&self, _data: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        Err(asymmetric_unsupported())
+    }
+
+    #[cfg(feature = "asymmetric")]
     #[allow(dead_code)]
     pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>, std::io::Error> {
         let ret = match &self {
@@ -183,8 +226,15 @@ impl PublicSignKey {
         }
     }
 
+    #[cfg(not(feature = "asymmetric"))]
+    #[allow(dead_code)]
+    pub fn verify(&self, _data: &[u8], _sig: &[u8]) -> Result<bool, SignatureError> {
+        Err(SignatureError::Unsupported)
+    }
+
+    #[cfg(feature = "asymmetric")]
     #[allow(dead_code)]
-    pub fn verify(&self, data: &[u8], sig: &[u8]) -> Result<bool, pqcrypto_traits_wasi::Error> {
+    pub fn verify(&self, data: &[u8], sig: &[u8]) -> Result<bool, SignatureError> {
         let ret = match &self {
             PublicSignKey::Falcon512 { pk } => {
                 let pk = falcon512::PublicKey::from_bytes(&pk[..])?;
@@ -40,6 +40,7 @@ fn test_encrypt_key_seeding_new() {
 }
 
 #[test]
+#[cfg(feature = "asymmetric")]
 fn test_asym_crypto_128() {
     crate::utils::bootstrap_test_env();
 
@@ -62,6 +63,7 @@ fn test_asym_crypto_128() {
 }
 
 #[test]
+#[cfg(feature = "asymmetric")]
 fn test_asym_crypto_256() {
     crate::utils::bootstrap_test_env();
 
@@ -84,6 +86,7 @@ fn test_asym_crypto_256() {
 }
 
 #[test]
+#[cfg(feature = "asymmetric")]
 fn test_ntru_encapsulate() -> Result<(), CryptoError> {
     crate::utils::bootstrap_test_env();
 
@@ -108,6 +111,7 @@ fn test_ntru_encapsulate() -> Result<(), CryptoError> {
 }
 
 #[test]
+#[cfg(feature = "asymmetric")]
 fn test_ntru_encrypt() -> Result<(), Box<dyn std::error::Error>> {
     crate::utils::bootstrap_test_env();
 
@@ -176,6 +180,7 @@ fn test_derived_keys() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 #[test]
+#[cfg(feature = "asymmetric")]
 fn test_public_secure_data() -> Result<(), Box<dyn std::error::Error>> {
     crate::utils::bootstrap_test_env();
 
@@ -217,6 +222,7 @@ fn test_secure_data() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 #[test]
+#[cfg(feature = "asymmetric")]
 fn test_multi_encrypt() -> Result<(), Box<dyn std::error::Error>> {
     crate::utils::bootstrap_test_env();
 
@@ -253,6 +259,7 @@ fn test_multi_encrypt() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 #[test]
+#[cfg(feature = "asymmetric")]
 fn test_signed_protected_data() -> Result<(), Box<dyn std::error::Error>> {
     let sign_key = PrivateSignKey::generate(KeySize::Bit256);
     let data = "test data".to_string();
@@ -265,3 +272,77 @@ fn test_signed_protected_data() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn test_hash_large_and_streaming() -> Result<(), Box<dyn std::error::Error>> {
+    crate::utils::bootstrap_test_env();
+
+    // The hash of existing chains must never change
+    assert_eq!(
+        AteHash::from_bytes(b"").to_hex_string(),
+        "af1349b9f5f9a1a6a0404dea36dcc949"
+    );
+
+    // Large payloads are hashed in parallel but give the same result
+    let mut data = vec![0u8; (PARALLEL_HASH_THRESHOLD * 3) + 17];
+    RandomGeneratorAccessor::default().fill_bytes(&mut data);
+    let mut serial = blake3::Hasher::new();
+    serial.update(b"scope");
+    serial.update(&data[..]);
+    let serial: [u8; 32] = serial.finalize().into();
+    let hash = AteHash::from_bytes_twice(b"scope", &data[..]);
+    assert_eq!(&hash.val[..], &serial[..16]);
+
+    // Streaming the data gives the same result as hashing it all at once
+    use std::io::Read;
+    let streamed = AteHash::from_reader((&b"scope"[..]).chain(&data[..]))?;
+    assert_eq!(streamed, hash);
+    assert_eq!(AteHash::from_reader(&data[..10])?, AteHash::from_bytes(&data[..10]));
+    assert_eq!(AteHash::from_reader(&b""[..])?, AteHash::from_bytes(b""));
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "asymmetric"))]
+fn test_asym_crypto_unsupported() {
+    crate::utils::bootstrap_test_env();
+
+    // Keys that were read from somewhere can be held but not used
+    let sign_key = PrivateSignKey::Falcon512 {
+        pk: PublicSignKey::Falcon512 { pk: vec![1u8; 897] },
+        sk: vec![2u8; 1281],
+    };
+    let err = sign_key.sign(b"test").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    assert_eq!(
+        sign_key.as_public_key().verify(b"test", &[0u8; 32]),
+        Err(SignatureError::Unsupported)
+    );
+
+    let encrypt_key = PublicEncryptKey::from_bytes(vec![3u8; 699]).unwrap();
+    assert_eq!(encrypt_key.size(), KeySize::Bit128);
+    let err = PublicEncryptedSecureData::new(&encrypt_key, "test".to_string()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn test_encrypt_in_place() {
+    crate::utils::bootstrap_test_env();
+
+    // Encrypting in place gives the same cipher text as encrypting into a
+    // new buffer (including buffers that span several chunks)
+    for size in [KeySize::Bit128, KeySize::Bit192, KeySize::Bit256] {
+        let key = EncryptKey::generate(size);
+        let iv = InitializationVector::generate();
+        for len in [0usize, 1, 15, 4096, 4097, 10000] {
+            let mut data = vec![0u8; len];
+            RandomGeneratorAccessor::default().fill_bytes(&mut data);
+
+            let expected = key.encrypt_with_iv(&iv, &data[..]);
+            let mut in_place = data.clone();
+            key.encrypt_with_iv_in_place(&iv, &mut in_place[..]);
+            assert_eq!(in_place, expected);
+            assert_eq!(key.decrypt(&iv, &in_place[..]), data);
+        }
+    }
+}
@@ -3,6 +3,7 @@ use tracing::metadata::LevelFilter;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use tracing_subscriber::fmt::SubscriberBuilder;
 use tracing_subscriber::EnvFilter;
+use std::io::IsTerminal;
 use std::sync::Once;
 
 static SYNC_OBJ: Once = Once::new();
@@ -21,14 +22,20 @@ pub fn log_init(verbose: i32, debug: bool) {
             log_level = Some(LevelFilter::DEBUG);
         }
 
+        // Colours are only used on terminals, service managers (e.g. journald)
+        // capture the output as plain text
+        let ansi = std::io::stderr().is_terminal();
+
         if let Some(log_level) = log_level {
             SubscriberBuilder::default()
                 .with_writer(std::io::stderr)
+                .with_ansi(ansi)
                 .with_max_level(log_level)
                 .init();
         } else {
             SubscriberBuilder::default()
                 .with_writer(std::io::stderr)
+                .with_ansi(ansi)
                 .with_env_filter(EnvFilter::from_default_env())
                 .init();
         }
@@ -20,6 +20,10 @@ for MyFuse {
             MyFileSystem { }
         ))
     }
+
+    async fn mount_with_options(&self, name: String, _options: MountOptions) -> Result<Arc<dyn api::FileSystem>, BusError> {
+        FuseSimplified::mount(self, name).await
+    }
 }
 
 #[derive(Debug)]
@@ -121,6 +125,42 @@ for MyFileSystem
             Result::Err(BusError::Aborted)
         }
     }
+
+    async fn copy(
+        &self,
+        _from: String,
+        _to: String,
+        _recursive: bool,
+        _progress: Box<dyn Fn(api::CopyProgress) + Send + Sync + 'static>,
+        _finished: Box<dyn Fn(FsResult<api::CopyProgress>) + Send + Sync + 'static>,
+    ) -> Result<Arc<dyn api::FileCopy>, BusError> {
+        Result::Err(BusError::Unsupported)
+    }
+
+    async fn search(
+        &self,
+        _root: String,
+        _glob: String,
+        _max_results: Option<u64>,
+        _return_metadata: bool,
+        _found: Box<dyn Fn(Vec<api::DirEntry>) + Send + Sync + 'static>,
+        _finished: Box<dyn Fn(FsResult<api::SearchSummary>) + Send + Sync + 'static>,
+    ) -> Result<Arc<dyn api::FileSearch>, BusError> {
+        Result::Err(BusError::Unsupported)
+    }
+
+    async fn search_content(
+        &self,
+        _root: String,
+        _glob: String,
+        _pattern: String,
+        _regex: bool,
+        _max_matches: Option<u64>,
+        _found: Box<dyn Fn(Vec<api::SearchMatch>) + Send + Sync + 'static>,
+        _finished: Box<dyn Fn(FsResult<api::SearchSummary>) + Send + Sync + 'static>,
+    ) -> Result<Arc<dyn api::FileSearch>, BusError> {
+        Result::Err(BusError::Unsupported)
+    }
 }
 
 static README: &'static str = r#"# Example Readme
@@ -148,6 +188,14 @@ for MyFile {
         FsResult::Err(FsError::PermissionDenied)
     }
 
+    async fn lock(&self, _options: api::LockOptions) -> FsResult<()> {
+        FsResult::Ok(())
+    }
+
+    async fn unlock(&self) -> FsResult<()> {
+        FsResult::Ok(())
+    }
+
     async fn io(&self) -> Result<Arc<dyn api::FileIO>, BusError> {
         Result::Ok(
             Arc::new(self.clone())
@@ -29,6 +29,9 @@ cd ..
 cd fuse
 cargo wasix build --release
 cd ..
+cd sleep-accuracy
+cargo wasix build --release
+cd ..
 cp -f ../target/wasm32-wasmer-wasi/release/tcp-listener.wasm /prog/ate/wasmer-web/public/bin/example-tcp-listener.wasm
 cp -f ../target/wasm32-wasmer-wasi/release/tcp-client.wasm /prog/ate/wasmer-web/public/bin/example-tcp-client.wasm
 cp -f ../target/wasm32-wasmer-wasi/release/multi-threading.wasm /prog/ate/wasmer-web/public/bin/example-multi-threading.wasm
@@ -39,5 +42,6 @@ cp -f ../target/wasm64-wasmer-wasi/release/wasm64-example.wasm /prog/ate/wasmer-
 cp -f ../target/wasm32-wasmer-wasi/release/ws-client.wasm /prog/ate/wasmer-web/public/bin/example-ws-client.wasm
 cp -f ../target/wasm32-wasmer-wasi/release/sub-process.wasm /prog/ate/wasmer-web/public/bin/example-sub-process.wasm
 cp -f ../target/wasm32-wasmer-wasi/release/fuse.wasm /prog/ate/wasmer-web/public/bin/example-fuse.wasm
+cp -f ../target/wasm32-wasmer-wasi/release/sleep-accuracy.wasm /prog/ate/wasmer-web/public/bin/example-sleep-accuracy.wasm
 cp -f ../target/wasm32-wasmer-wasi/release/multi-threading.wasm /prog/wasmer/lib/wasi/tests/multi-threading.wasm
 cp -f ../target/wasm32-wasmer-wasi/release/condvar.wasm /prog/wasmer/lib/wasi/tests/condvar.wasm
@@ -0,0 +1,51 @@
+use std::{
+    process,
+    thread,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// How much later than requested a sleep may wake up (browsers clamp
+/// their timers so this is far looser than what native hosts achieve)
+const TOLERANCE: Duration = Duration::from_millis(5);
+
+fn main() {
+    let mut failed = false;
+
+    // The monotonic clock must never run backwards
+    let mut last = Instant::now();
+    for _ in 0..10_000 {
+        let now = Instant::now();
+        if now < last {
+            println!("monotonic clock went backwards");
+            failed = true;
+        }
+        last = now;
+    }
+
+    for millis in [1u64, 5, 10, 50, 100, 250] {
+        let requested = Duration::from_millis(millis);
+        let start = Instant::now();
+        thread::sleep(requested);
+        let actual = start.elapsed();
+
+        let error = actual.saturating_sub(requested);
+        let ok = actual >= requested && error <= TOLERANCE;
+        println!(
+            "sleep({:?}) took {:?} (late by {:?}) - {}",
+            requested,
+            actual,
+            error,
+            if ok { "ok" } else { "FAILED" }
+        );
+        failed |= !ok;
+    }
+
+    if failed {
+        println!("sleep accuracy failed");
+        process::exit(1);
+    }
+    println!("all done");
+}
@@ -14,12 +14,14 @@ use ::ate::dio::Dao;
 use ::ate::dio::DaoObj;
 use ::ate::dio::Dio;
 use ::ate::header::PrimaryKey;
+use ::ate::meta::MetaCollectionPolicy;
 use ::ate::prelude::AteRolePurpose;
 use ::ate::prelude::ReadOption;
 use ::ate::prelude::*;
 use ::ate::{crypto::DerivedEncryptKey, prelude::TransactionScope};
 
 use super::api::*;
+use super::chunk::*;
 use super::codes::*;
 use super::error::*;
 use super::handle::*;
@@ -27,6 +29,7 @@ use super::model::*;
 use super::prelude::*;
 
 use fxhash::FxHashMap;
+use fxhash::FxHashSet;
 
 #[derive(Debug)]
 pub struct FileAccessor
@@ -47,7 +50,10 @@ where
     pub commit_lock: tokio::sync::Mutex<()>,
     pub impersonate_uid: bool,
     pub force_sudo: bool,
+    pub casefold: bool,
     pub init_flag: AsyncMutex<bool>,
+    pub chunking: Mutex<Option<ChunkConfig>>,
+    pub chunk_stats: Arc<ChunkStats>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -93,7 +99,10 @@ impl FileAccessor {
             commit_lock: tokio::sync::Mutex::new(()),
             impersonate_uid,
             force_sudo: false,
+            casefold: false,
             init_flag: AsyncMutex::new(false),
+            chunking: Mutex::new(None),
+            chunk_stats: Arc::new(ChunkStats::default()),
         }
     }
 
@@ -102,6 +111,22 @@ impl FileAccessor {
         self
     }
 
+    /// When enabled names that differ only by case refer to the same entry,
+    /// the case that the entry was created with is still what gets listed
+    pub fn with_casefold(mut self, val: bool) -> Self {
+        self.casefold = val;
+        self
+    }
+
+    /// Returns true if the two names refer to the same entry on this mount
+    pub fn name_matches(&self, a: &str, b: &str) -> bool {
+        if self.casefold {
+            a == b || a.to_lowercase() == b.to_lowercase()
+        } else {
+            a == b
+        }
+    }
+
     pub fn session_context(&self) -> RequestContext
     {
         RequestContext {
@@ -139,6 +164,18 @@ impl FileAccessor {
         };
         debug!("init");
 
+        // Determine if content-defined chunking is enabled on this chain
+        match dio.load::<ChunkConfig>(&PrimaryKey::from(CHUNK_CONFIG_ID)).await {
+            Ok(config) => {
+                debug!("chunking enabled (avg_size={})", config.avg_size);
+                self.chunking.lock().unwrap().replace(config.take());
+            }
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {}
+            Err(err) => {
+                bail!(err);
+            }
+        }
+
         // All good
         self.tick().await?;
         self.commit().await?;
@@ -149,6 +186,98 @@ impl FileAccessor {
         Ok(root)
     }
 
+    /// Enables content-defined chunking on this chain so that the data of
+    /// any files created from now on is split into chunks that are stored
+    /// once no matter how many files contain them (existing files are not
+    /// converted and remain readable)
+    pub async fn enable_chunking(&self, avg_size: u32) -> Result<()> {
+        if avg_size < CHUNK_MIN_AVG_SIZE || avg_size > CHUNK_MAX_AVG_SIZE {
+            bail!(FileSystemErrorKind::InvalidArguments);
+        }
+        if let Some(config) = self.chunking.lock().unwrap().as_ref() {
+            if config.avg_size != avg_size {
+                warn!(
+                    "chunking is already enabled with an average size of {} bytes",
+                    config.avg_size
+                );
+            }
+            return Ok(());
+        }
+
+        let dio = self.dio_mut_meta().await;
+        let config = ChunkConfig { avg_size };
+        let mut row = dio.store_with_key(config.clone(), PrimaryKey::from(CHUNK_CONFIG_ID))?;
+        row.attach_orphaned_ext(&PrimaryKey::from(1), CHUNK_COLLECTION_ID)?;
+        {
+            // Chunks are attached to this row by whoever writes them
+            let mut auth = row.auth_mut();
+            auth.read = ReadOption::Inherit;
+            auth.write = WriteOption::Everyone;
+        }
+        dio.commit().await?;
+
+        info!("chunking enabled (avg_size={})", avg_size);
+        self.chunking.lock().unwrap().replace(config);
+        Ok(())
+    }
+
+    /// Deletes the chunks that no chunked file refers to anymore (they are
+    /// shared between files so they are not deleted along with the files)
+    /// and returns how many were deleted. Nothing is deleted unless every
+    /// chunk list in the chain can be read by this session. A write that
+    /// reuses one of these chunks while they are being collected will lose
+    /// it so this should be run when no one else is writing to the chain.
+    pub async fn collect_chunks(&self) -> Result<u64> {
+        let config_key = PrimaryKey::from(CHUNK_CONFIG_ID);
+        let chunks = self
+            .dio
+            .children_keys(config_key, CHUNK_COLLECTION_ID)
+            .await?;
+        if chunks.is_empty() {
+            return Ok(0);
+        }
+
+        // Mark all the chunks that are referred to by the chunk list of a
+        // file that still exists (the lists of deleted files are swept)
+        let mut referenced = FxHashSet::default();
+        let mut orphaned = Vec::new();
+        for key in self.dio.all_keys().await {
+            let data = match self.dio.load_raw(&key).await {
+                Ok(a) => a,
+                Err(LoadError(LoadErrorKind::NotFound(_), _)) => continue,
+                Err(err) => {
+                    bail!(err);
+                }
+            };
+            if data.meta.is_of_type::<ChunkList>() == false {
+                continue;
+            }
+            if let Some(parent) = data.meta.get_parent() {
+                if self.dio.exists(&parent.vec.parent_id).await == false {
+                    orphaned.push(key);
+                    continue;
+                }
+            }
+            let list = self.dio.load_and_take::<ChunkList>(&key).await?;
+            referenced.extend(list.chunks.iter().filter_map(|a| a.key));
+        }
+
+        // Sweep the rest
+        let dio = self.dio_mut_meta().await;
+        for key in orphaned {
+            dio.delete(&key).await?;
+        }
+        let mut deleted = 0u64;
+        for key in chunks.into_iter().filter(|a| referenced.contains(a) == false) {
+            dio.delete(&key).await?;
+            deleted += 1;
+        }
+        dio.commit().await?;
+
+        info!("collected {} unreferenced chunks", deleted);
+        Ok(deleted)
+    }
+
     pub fn get_group_read_key<'a>(&'a self, gid: u32) -> Option<&'a EncryptKey> {
         let purpose = if self.is_www {
             AteRolePurpose::WebServer
@@ -288,6 +417,7 @@ impl FileAccessor {
                             child.when_created(),
                             child.when_updated(),
                             child,
+                            &self.chunk_stats,
                         )
                         .await;
                         children.push(child_spec);
@@ -311,7 +441,14 @@ impl FileAccessor {
         let spec = match writable {
             true => {
                 let data = self.load_mut_io(inode).await?;
-                Inode::as_file_spec_mut(data.key().as_u64(), created, updated, data).await
+                Inode::as_file_spec_mut(
+                    data.key().as_u64(),
+                    created,
+                    updated,
+                    data,
+                    &self.chunk_stats,
+                )
+                .await
             }
             false => Inode::as_file_spec(data.key().as_u64(), created, updated, data).await,
         };
@@ -380,11 +517,11 @@ impl FileAccessor {
             .children
             .iter()
             .await?
-            .filter(|c| *c.dentry.name == *name)
+            .filter(|c| self.name_matches(c.dentry.name.as_str(), name))
             .next()
         {
             trace!("create parent={} name={}: already-exists", parent, name);
-            bail!(FileSystemErrorKind::DoesNotExist);
+            bail!(FileSystemErrorKind::AlreadyExists);
         }
 
         let uid = self.translate_uid(req.uid, req);
@@ -393,9 +530,81 @@ impl FileAccessor {
 
         let mut child = data.as_mut().children.push(child)?;
         self.updwasmer_auth(mode, uid, gid, child.auth_mut())?;
+
+        // New files on chains with chunking enabled store their data as chunks
+        let chunking = self.chunking.lock().unwrap().clone();
+        if let Some(config) = chunking {
+            let list = ChunkList {
+                avg_size: config.avg_size,
+                chunks: Vec::new(),
+            };
+            let mut list = dio.store_with_key(list, ChunkList::key_for(child.key()))?;
+            list.attach_orphaned(child.key())?;
+        }
         return Ok(child);
     }
 
+    /// Adds a new entry to a directory within a transaction that the caller
+    /// commits (the parent must have been loaded by that transaction) so that
+    /// many entries can be written together and either all of them appear or
+    /// none do, regular files are written with the data supplied
+    pub async fn stage_node(
+        &self,
+        req: &RequestContext,
+        parent: &mut DaoMut<Inode>,
+        name: &str,
+        mode: u32,
+        kind: FileKind,
+        data: &[u8],
+    ) -> Result<DaoMut<Inode>> {
+        if parent.kind != FileKind::Directory {
+            bail!(FileSystemErrorKind::NotDirectory);
+        }
+        if let Some(_) = parent
+            .children
+            .iter()
+            .await?
+            .filter(|c| self.name_matches(c.dentry.name.as_str(), name))
+            .next()
+        {
+            trace!("stage parent={} name={}: already-exists", parent.key(), name);
+            bail!(FileSystemErrorKind::AlreadyExists);
+        }
+
+        let uid = self.translate_uid(req.uid, req);
+        let gid = self.translate_gid(req.gid, req);
+        let child = Inode::new(name.to_string(), mode, uid, gid, kind);
+        let mut child = parent.as_mut().children.push(child)?;
+        self.updwasmer_auth(mode, uid, gid, child.auth_mut())?;
+
+        if kind == FileKind::RegularFile && data.len() > 0 {
+            let dio = child.trans();
+            let child_key = child.key().clone();
+            let mut bundles = Vec::new();
+            for bundle_data in data.chunks(PAGE_SIZE * PAGES_PER_BUNDLE) {
+                let mut bundle = dio.store(PageBundle { pages: Vec::new() })?;
+                bundle.attach_orphaned(&child_key)?;
+                let bundle_key = bundle.key().clone();
+
+                let mut pages = Vec::new();
+                for page_data in bundle_data.chunks(PAGE_SIZE) {
+                    let mut page = dio.store(Page {
+                        buf: page_data.to_vec(),
+                    })?;
+                    page.attach_orphaned(&bundle_key)?;
+                    pages.push(Some(page.key().clone()));
+                }
+                bundle.as_mut().pages = pages;
+                bundles.push(Some(bundle_key));
+            }
+
+            let mut guard = child.as_mut();
+            guard.size = data.len() as u64;
+            guard.bundles = bundles;
+        }
+        Ok(child)
+    }
+
     pub async fn tick(&self) -> Result<()> {
         let secs = self.elapsed.elapsed().as_secs();
         if secs > self.last_elapsed.read() {
@@ -702,6 +911,42 @@ impl FileAccessor {
         Ok(self.spec_as_attr_reverse(&spec, req))
     }
 
+    /// Attaches a retention policy to the entries of a directory so that the
+    /// chain removes those that have not been written to for longer than the
+    /// maximum age (in milliseconds), no age removes the policy again
+    pub async fn set_retention(
+        &self,
+        _req: &RequestContext,
+        inode: u64,
+        max_age: Option<u64>,
+    ) -> Result<()> {
+        self.tick().await?;
+        trace!("set_retention inode={} max_age={:?}", inode, max_age);
+
+        let key = PrimaryKey::from(inode);
+        let dio = self.dio_mut_meta().await;
+        let mut dao = dio.load::<Inode>(&key).await?;
+        if dao.kind != FileKind::Directory {
+            bail!(FileSystemErrorKind::NotDirectory);
+        }
+
+        // Sessions set the policy every time they start so it is only
+        // written when it actually changes
+        let collection_id = dao.children.vec_id();
+        let existing = dao.as_immutable().collection_policy(collection_id);
+        if existing.and_then(|a| a.max_age) == max_age {
+            return Ok(());
+        }
+        dao.set_collection_policy(MetaCollectionPolicy {
+            collection_id,
+            max_entries: existing.and_then(|a| a.max_entries),
+            max_age,
+        })
+        .map_err(AteError::from)?;
+        dio.commit().await?;
+        Ok(())
+    }
+
     pub async fn opendir(
         &self,
         req: &RequestContext,
@@ -761,7 +1006,7 @@ impl FileAccessor {
         if let Some(entry) = open
             .children
             .iter()
-            .filter(|c| c.name.as_str() == name)
+            .filter(|c| self.name_matches(c.name.as_str(), name))
             .next()
         {
             debug!("wasmer-dfs::lookup parent={} name={}: found", parent, name);
@@ -907,6 +1152,17 @@ impl FileAccessor {
             bail!(FileSystemErrorKind::NotDirectory);
         }
 
+        if let Some(_) = data
+            .children
+            .iter()
+            .await?
+            .filter(|c| self.name_matches(c.dentry.name.as_str(), name))
+            .next()
+        {
+            trace!("mkdir parent={} name={}: already-exists", parent, name);
+            bail!(FileSystemErrorKind::AlreadyExists);
+        }
+
         let uid = self.translate_uid(req.uid, req);
         let gid = self.translate_gid(req.gid, req);
         let child = Inode::new(name.to_string(), mode, uid, gid, FileKind::Directory);
@@ -939,7 +1195,7 @@ impl FileAccessor {
         if let Some(entry) = open
             .children
             .iter()
-            .filter(|c| c.name.as_str() == name)
+            .filter(|c| self.name_matches(c.name.as_str(), name))
             .next()
         {
             debug!("wasmer-dfs::rmdir parent={} name={}: found", parent, name);
@@ -1002,6 +1258,7 @@ impl FileAccessor {
             data.when_created(),
             data.when_updated(),
             data.into(),
+            &self.chunk_stats,
         )
         .await;
         let attr = self.spec_as_attr_reverse(&spec, req);
@@ -1043,7 +1300,7 @@ impl FileAccessor {
             .children
             .iter()
             .await?
-            .filter(|c| c.dentry.name.as_str() == name)
+            .filter(|c| self.name_matches(c.dentry.name.as_str(), name))
             .next()
         {
             if data.kind == FileKind::Directory {
@@ -1086,7 +1343,7 @@ impl FileAccessor {
             .children
             .iter_mut()
             .await?
-            .filter(|c| c.dentry.name.as_str() == name)
+            .filter(|c| self.name_matches(c.dentry.name.as_str(), name))
             .next()
         {
             // If the parent has changed then move it
@@ -1103,7 +1360,7 @@ impl FileAccessor {
                     .children
                     .iter()
                     .await?
-                    .filter(|c| c.dentry.name.as_str() == new_name)
+                    .filter(|c| self.name_matches(c.dentry.name.as_str(), new_name))
                     .next()
                 {
                     dio.delete(existing.key()).await?;
@@ -1111,11 +1368,14 @@ impl FileAccessor {
                 data.detach()?;
                 data.attach(&new_parent_data, &new_parent_data.children)?;
             } else {
+                // Renames that only change the case of the name must not
+                // delete the entry that is being renamed
                 if let Some(existing) = parent_data
                     .children
                     .iter()
                     .await?
-                    .filter(|c| c.dentry.name.as_str() == new_name)
+                    .filter(|c| c.key() != data.key())
+                    .filter(|c| self.name_matches(c.dentry.name.as_str(), new_name))
                     .next()
                 {
                     dio.delete(existing.key()).await?;
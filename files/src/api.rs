@@ -1,3 +1,4 @@
+use super::chunked::ChunkedFile;
 use super::dir::Directory;
 use super::file::RegularFile;
 use super::fixed::FixedFile;
@@ -18,6 +19,7 @@ pub enum FileSpec {
     //BlockDevice,
     Directory,
     RegularFile,
+    ChunkedFile,
     SymLink,
     //Socket,
     FixedFile,
@@ -0,0 +1,184 @@
+use super::model::Chunk;
+use ate::prelude::*;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// Smallest average chunk size that may be configured
+pub const CHUNK_MIN_AVG_SIZE: u32 = 4096;
+/// Largest average chunk size that may be configured
+pub const CHUNK_MAX_AVG_SIZE: u32 = 4194304;
+/// Average chunk size that is used when none is specified
+pub const CHUNK_DEFAULT_AVG_SIZE: u32 = 65536;
+
+/// Table of random values that are rolled into the fingerprint for each byte
+/// (these must never change otherwise previously written chunks will no longer
+/// line up with new writes and the deduplication will be lost)
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut ret = [0u64; 256];
+    let mut seed = 0x9e3779b97f4a7c15u64;
+    let mut n = 0;
+    while n < 256 {
+        // splitmix64
+        seed = seed.wrapping_add(0x9e3779b97f4a7c15u64);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9u64);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111ebu64);
+        ret[n] = z ^ (z >> 31);
+        n += 1;
+    }
+    ret
+}
+
+/// Splits data into chunks at content-defined boundaries (FastCDC) so that
+/// inserting or changing bytes only changes the chunks around the edit
+#[derive(Debug, Clone, Copy)]
+pub struct Chunker {
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+    mask_small: u64,
+    mask_large: u64,
+}
+
+impl Chunker {
+    pub fn new(avg_size: u32) -> Chunker {
+        let avg_size = avg_size.clamp(CHUNK_MIN_AVG_SIZE, CHUNK_MAX_AVG_SIZE) as usize;
+        let bits = (usize::BITS - 1 - avg_size.leading_zeros()) as u64;
+        Chunker {
+            min_size: avg_size / 4,
+            avg_size,
+            max_size: avg_size * 4,
+            // Harder to match before the average size and easier after it which
+            // normalizes the chunk sizes around the average
+            mask_small: Self::mask(bits + 2),
+            mask_large: Self::mask(bits - 2),
+        }
+    }
+
+    fn mask(bits: u64) -> u64 {
+        let bits = bits.clamp(1, 63);
+        ((1u64 << bits) - 1) << (64 - bits)
+    }
+
+    /// Returns the length of the next chunk at the start of the data
+    pub fn cut(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min_size {
+            return data.len();
+        }
+        let end = data.len().min(self.max_size);
+        let normal = self.avg_size.min(end);
+
+        let mut fp = 0u64;
+        let mut n = self.min_size;
+        while n < normal {
+            fp = (fp << 1).wrapping_add(GEAR[data[n] as usize]);
+            if fp & self.mask_small == 0 {
+                return n + 1;
+            }
+            n += 1;
+        }
+        while n < end {
+            fp = (fp << 1).wrapping_add(GEAR[data[n] as usize]);
+            if fp & self.mask_large == 0 {
+                return n + 1;
+            }
+            n += 1;
+        }
+        end
+    }
+
+    /// Splits all the data into chunks (the last chunk ends where the data does)
+    pub fn split<'a>(&self, mut data: &'a [u8]) -> Vec<&'a [u8]> {
+        let mut ret = Vec::new();
+        while data.len() > 0 {
+            let len = self.cut(data);
+            ret.push(&data[..len]);
+            data = &data[len..];
+        }
+        ret
+    }
+}
+
+/// Deduplication statistics for the chunks written through a mount
+#[derive(Debug, Default)]
+pub struct ChunkStats {
+    /// Number of bytes of file data that were written as chunks
+    pub logical_bytes: AtomicU64,
+    /// Number of bytes that actually had to be stored as new chunks
+    pub stored_bytes: AtomicU64,
+    /// Number of chunks that were written
+    pub chunks: AtomicU64,
+    /// Number of chunks that were already stored and hence reused
+    pub deduplicated: AtomicU64,
+}
+
+impl ChunkStats {
+    pub fn record(&self, size: u64, stored: bool) {
+        self.logical_bytes.fetch_add(size, Ordering::Relaxed);
+        self.chunks.fetch_add(1, Ordering::Relaxed);
+        if stored {
+            self.stored_bytes.fetch_add(size, Ordering::Relaxed);
+        } else {
+            self.deduplicated.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Ratio of the bytes written to the bytes stored (e.g. 2.0 means half
+    /// of the data that was written was already present in the chain)
+    pub fn dedupe_ratio(&self) -> f64 {
+        let stored = self.stored_bytes.load(Ordering::Relaxed);
+        if stored <= 0 {
+            return 1.0;
+        }
+        self.logical_bytes.load(Ordering::Relaxed) as f64 / stored as f64
+    }
+}
+
+/// Determines how a chunk written by a file with these permissions is
+/// protected and the scope that it is deduplicated within (chunks are only
+/// shared between files that can be read with the same key)
+pub fn chunk_scope(read: &ReadOption) -> (ReadOption, AteHash) {
+    match read {
+        ReadOption::Specific(hash, derived) => {
+            (ReadOption::Specific(hash.clone(), derived.clone()), hash.clone())
+        }
+        ReadOption::Everyone(_) => (
+            ReadOption::Everyone(None),
+            AteHash::from_bytes(b"everyone"),
+        ),
+        ReadOption::Inherit => (ReadOption::Inherit, AteHash::from_bytes(b"inherit")),
+    }
+}
+
+/// Number of keys that are tried for a chunk before giving up (each one is
+/// only tried when the previous key is held by a different chunk)
+pub const CHUNK_KEY_PROBES: u32 = 8;
+
+/// Hash of a chunk within its deduplication scope
+pub fn chunk_hash(data: &[u8], scope: &AteHash) -> AteHash {
+    AteHash::from_bytes_twice(&scope.val, data)
+}
+
+/// Key of a chunk, primary keys are only 64 bits of the hash so when two
+/// chunks collide the later one moves on to the next attempt
+pub fn chunk_key(hash: &AteHash, attempt: u32) -> PrimaryKey {
+    match attempt {
+        0 => PrimaryKey::from(hash.to_u64()),
+        n => PrimaryKey::from(AteHash::from_bytes_twice(&hash.val, &n.to_be_bytes()).to_u64()),
+    }
+}
+
+/// Checks that the data of a chunk matches the hash it was referenced by
+pub fn chunk_verify(chunk: &Chunk, expected: Option<&AteHash>) -> bool {
+    if let Some(expected) = expected {
+        if chunk.hash != *expected {
+            return false;
+        }
+    }
+    match chunk.scope.as_ref() {
+        Some(scope) => chunk_hash(&chunk.buf[..], scope) == chunk.hash,
+        None => true,
+    }
+}
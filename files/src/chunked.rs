@@ -0,0 +1,613 @@
+use super::api::FileKind;
+use super::chunk::*;
+use super::model::*;
+use crate::api::FileApi;
+use async_trait::async_trait;
+use ate::prelude::*;
+use bytes::Bytes;
+use error_chain::bail;
+use fxhash::FxHashMap;
+use seqlock::SeqLock;
+use std::ops::Deref;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::error::*;
+
+const CACHED_CHUNKS: usize = 16; // Number of cached chunks per open file
+
+/// Regular file whose data is stored as content-defined chunks that are
+/// deduplicated across the chain (see `ChunkConfig`)
+pub struct ChunkedFile {
+    pub ino: u64,
+    pub created: u64,
+    pub updated: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+    pub name: String,
+    pub size: SeqLock<u64>,
+    pub state: Mutex<ChunkedState>,
+}
+
+impl ChunkedFile {
+    /// Loads the chunk list of a file (if it is not chunked then None is returned)
+    pub async fn load(inode: &Dao<Inode>) -> Option<Dao<ChunkList>> {
+        let key = ChunkList::key_for(inode.key());
+        match inode.dio().load::<ChunkList>(&key).await {
+            Ok(a) => Some(a),
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => None,
+            Err(err) => {
+                warn!("failed to load the chunk list of inode {} - {}", inode.key(), err);
+                None
+            }
+        }
+    }
+
+    /// Loads the chunk list of a file (if it is not chunked then None is returned)
+    pub async fn load_mut(inode: &DaoMut<Inode>) -> Option<DaoMut<ChunkList>> {
+        let key = ChunkList::key_for(inode.key());
+        match inode.trans().load::<ChunkList>(&key).await {
+            Ok(a) => Some(a),
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => None,
+            Err(err) => {
+                warn!("failed to load the chunk list of inode {} - {}", inode.key(), err);
+                None
+            }
+        }
+    }
+
+    pub fn new(inode: Dao<Inode>, list: Dao<ChunkList>, created: u64, updated: u64) -> ChunkedFile {
+        let uid = inode.dentry.uid;
+        let gid = inode.dentry.gid;
+        let mode = inode.dentry.mode;
+        let name = inode.dentry.name.clone();
+        let ino = inode.key().as_u64();
+        let size = inode.size;
+
+        let state = ChunkedState::new(list.deref(), size, ChunkedHandle::Immutable { inode });
+        ChunkedFile {
+            uid,
+            gid,
+            mode,
+            name,
+            ino,
+            size: SeqLock::new(state.size),
+            created,
+            updated,
+            state: Mutex::new(state),
+        }
+    }
+
+    pub fn new_mut(
+        inode: DaoMut<Inode>,
+        list: DaoMut<ChunkList>,
+        stats: &Arc<ChunkStats>,
+        created: u64,
+        updated: u64,
+    ) -> ChunkedFile {
+        let uid = inode.dentry.uid;
+        let gid = inode.dentry.gid;
+        let mode = inode.dentry.mode;
+        let name = inode.dentry.name.clone();
+        let ino = inode.key().as_u64();
+        let size = inode.size;
+
+        let state = ChunkedState::new(
+            list.deref(),
+            size,
+            ChunkedHandle::Mutable {
+                inode,
+                list,
+                stats: Arc::clone(stats),
+                dirty: false,
+            },
+        );
+        ChunkedFile {
+            uid,
+            gid,
+            mode,
+            name,
+            ino,
+            size: SeqLock::new(state.size),
+            created,
+            updated,
+            state: Mutex::new(state),
+        }
+    }
+}
+
+/// Part of a chunked file, chunks that have been changed are held in memory
+/// until the file is committed
+enum ChunkEntry {
+    Stored(ChunkRef),
+    Dirty(Vec<u8>),
+}
+
+impl ChunkEntry {
+    fn size(&self) -> u64 {
+        match self {
+            ChunkEntry::Stored(a) => a.size,
+            ChunkEntry::Dirty(a) => a.len() as u64,
+        }
+    }
+
+    fn is_hole(&self) -> bool {
+        match self {
+            ChunkEntry::Stored(a) => a.key.is_none(),
+            ChunkEntry::Dirty(_) => false,
+        }
+    }
+}
+
+enum ChunkedHandle {
+    Immutable {
+        inode: Dao<Inode>,
+    },
+    Mutable {
+        inode: DaoMut<Inode>,
+        list: DaoMut<ChunkList>,
+        stats: Arc<ChunkStats>,
+        dirty: bool,
+    },
+}
+
+pub struct ChunkedState {
+    handle: ChunkedHandle,
+    chunker: Chunker,
+    entries: Vec<ChunkEntry>,
+    starts: Vec<u64>,
+    size: u64,
+    cache: Box<[Option<(PrimaryKey, Arc<Vec<u8>>)>; CACHED_CHUNKS]>,
+}
+
+impl ChunkedState {
+    fn new(list: &ChunkList, size: u64, handle: ChunkedHandle) -> ChunkedState {
+        let mut ret = ChunkedState {
+            handle,
+            chunker: Chunker::new(list.avg_size),
+            entries: list
+                .chunks
+                .iter()
+                .map(|a| ChunkEntry::Stored(a.clone()))
+                .collect(),
+            starts: Vec::new(),
+            size: 0,
+            cache: Box::new(array_init::array_init(|_| None)),
+        };
+        ret.reindex();
+
+        // The chunks are the source of truth but the inode size may have been
+        // extended (with zeros) past them
+        if size > ret.size {
+            ret.entries.push(ChunkEntry::Stored(ChunkRef::hole(size - ret.size)));
+            ret.reindex();
+        }
+        ret
+    }
+
+    fn reindex(&mut self) {
+        self.starts.clear();
+        let mut offset = 0u64;
+        for entry in self.entries.iter() {
+            self.starts.push(offset);
+            offset += entry.size();
+        }
+        self.size = offset;
+    }
+
+    /// Returns the index of the chunk that holds the byte at this offset
+    fn index_of(&self, offset: u64) -> usize {
+        if offset >= self.size {
+            return self.entries.len();
+        }
+        self.starts.partition_point(|a| *a <= offset) - 1
+    }
+
+    /// Splits a hole in two at this offset so that writes never need to
+    /// materialize the zeros either side of them
+    fn split_hole(&mut self, offset: u64) {
+        let index = self.index_of(offset);
+        if index >= self.entries.len() || self.entries[index].is_hole() == false {
+            return;
+        }
+        let start = self.starts[index];
+        if start == offset {
+            return;
+        }
+        let size = self.entries[index].size();
+        self.entries[index] = ChunkEntry::Stored(ChunkRef::hole(offset - start));
+        self.entries.insert(
+            index + 1,
+            ChunkEntry::Stored(ChunkRef::hole(start + size - offset)),
+        );
+        self.reindex();
+    }
+
+    fn mark_dirty(&mut self) -> Result<()> {
+        match &mut self.handle {
+            ChunkedHandle::Mutable { dirty, .. } => {
+                *dirty = true;
+                Ok(())
+            }
+            ChunkedHandle::Immutable { .. } => {
+                bail!(FileSystemErrorKind::NoAccess);
+            }
+        }
+    }
+
+    async fn fetch(&mut self, key: &PrimaryKey, hash: Option<&AteHash>) -> Result<Arc<Vec<u8>>> {
+        let cache_index = key.as_u64() as usize % CACHED_CHUNKS;
+        if let Some((cached_key, data)) = &self.cache[cache_index] {
+            if cached_key == key {
+                return Ok(Arc::clone(data));
+            }
+        }
+
+        let dio = match &self.handle {
+            ChunkedHandle::Immutable { inode } => Arc::clone(inode.dio()),
+            ChunkedHandle::Mutable { inode, .. } => Arc::clone(inode.dio()),
+        };
+        let chunk = dio.load_and_take::<Chunk>(key).await?;
+        if chunk_verify(&chunk, hash) == false {
+            warn!("chunk {} does not match its hash", key);
+            bail!(FileSystemErrorKind::CorruptChunk(key.to_string()));
+        }
+        let data = Arc::new(chunk.buf);
+        self.cache[cache_index] = Some((key.clone(), Arc::clone(&data)));
+        Ok(data)
+    }
+
+    /// Reads a range of bytes that must be within the size of the file
+    async fn read_range(&mut self, offset: u64, size: u64, ret: &mut Vec<u8>) -> Result<()> {
+        let end = offset + size;
+        let mut pos = offset;
+        let mut index = self.index_of(pos);
+        while pos < end && index < self.entries.len() {
+            let start = self.starts[index];
+            let sub_offset = (pos - start) as usize;
+            let sub_size = (self.entries[index].size() - (pos - start)).min(end - pos) as usize;
+            match &self.entries[index] {
+                ChunkEntry::Dirty(buf) => {
+                    ret.extend_from_slice(&buf[sub_offset..sub_offset + sub_size]);
+                }
+                ChunkEntry::Stored(ChunkRef { key: None, .. }) => {
+                    ret.resize(ret.len() + sub_size, 0);
+                }
+                ChunkEntry::Stored(ChunkRef {
+                    key: Some(key),
+                    hash,
+                    ..
+                }) => {
+                    let (key, hash) = (key.clone(), hash.clone());
+                    let buf = self.fetch(&key, hash.as_ref()).await?;
+                    let avail = buf.len().min(sub_offset + sub_size);
+                    if avail > sub_offset {
+                        ret.extend_from_slice(&buf[sub_offset..avail]);
+                    }
+                    ret.resize(ret.len() + sub_size - avail.saturating_sub(sub_offset), 0);
+                }
+            }
+            pos += sub_size as u64;
+            index += 1;
+        }
+        Ok(())
+    }
+
+    /// Writes the data by re-chunking only the chunks that it overlaps
+    async fn write(&mut self, offset: u64, data: &[u8]) -> Result<()> {
+        self.mark_dirty()?;
+        if offset > self.size {
+            self.entries.push(ChunkEntry::Stored(ChunkRef::hole(offset - self.size)));
+            self.reindex();
+        }
+        let end = offset + data.len() as u64;
+        self.split_hole(offset);
+        self.split_hole(end);
+
+        // Find the chunks that are affected (when appending the last chunk is
+        // included as it was cut where the data ended rather than by its content)
+        let mut first = self.index_of(offset);
+        if first == self.entries.len() && first > 0 && self.entries[first - 1].is_hole() == false {
+            first -= 1;
+        }
+        let mut last = first;
+        while last < self.entries.len() && self.starts[last] < end {
+            last += 1;
+        }
+        let region_start = match self.starts.get(first) {
+            Some(a) => *a,
+            None => self.size,
+        };
+        let region_size = self.entries[first..last]
+            .iter()
+            .map(|a| a.size())
+            .sum::<u64>();
+
+        // Splice the data into the affected region and cut it into new chunks
+        let mut buf = Vec::with_capacity(region_size.max(end - region_start) as usize);
+        self.read_range(region_start, region_size, &mut buf).await?;
+        let rel = (offset - region_start) as usize;
+        if buf.len() < rel + data.len() {
+            buf.resize(rel + data.len(), 0);
+        }
+        buf[rel..rel + data.len()].copy_from_slice(data);
+
+        let chunks = self
+            .chunker
+            .split(&buf[..])
+            .into_iter()
+            .map(|a| ChunkEntry::Dirty(a.to_vec()))
+            .collect::<Vec<_>>();
+        self.entries.splice(first..last, chunks);
+        self.reindex();
+        Ok(())
+    }
+
+    async fn set_size(&mut self, size: u64) -> Result<()> {
+        self.mark_dirty()?;
+        if size > self.size {
+            self.entries.push(ChunkEntry::Stored(ChunkRef::hole(size - self.size)));
+        } else if size < self.size {
+            self.split_hole(size);
+            let index = self.index_of(size);
+            let start = self.starts[index];
+            if start < size {
+                let mut buf = Vec::with_capacity((size - start) as usize);
+                self.read_range(start, size - start, &mut buf).await?;
+                self.entries.truncate(index);
+                self.entries.push(ChunkEntry::Dirty(buf));
+            } else {
+                self.entries.truncate(index);
+            }
+        }
+        self.reindex();
+        Ok(())
+    }
+
+    /// Stores any chunks that are not already in the chain and saves the
+    /// chunk list of the file
+    async fn commit(&mut self) -> Result<()> {
+        let (inode, list, stats, dirty) = match &mut self.handle {
+            ChunkedHandle::Mutable {
+                inode,
+                list,
+                stats,
+                dirty,
+            } => (inode, list, stats, dirty),
+            ChunkedHandle::Immutable { .. } => {
+                return Ok(());
+            }
+        };
+        if *dirty == false {
+            return Ok(());
+        }
+
+        let dio = inode.trans();
+        let (read, scope) = chunk_scope(&inode.auth().read);
+        let config_key = PrimaryKey::from(CHUNK_CONFIG_ID);
+        let mut written = Vec::new();
+        for entry in self.entries.iter_mut() {
+            let buf = match entry {
+                ChunkEntry::Dirty(buf) => std::mem::take(buf),
+                ChunkEntry::Stored(_) => continue,
+            };
+            let size = buf.len() as u64;
+            let hash = chunk_hash(&buf[..], &scope);
+
+            // Identical chunks are only ever stored once, as the keys are
+            // only part of the hash the chunk already at a key must match
+            let mut found = None;
+            for attempt in 0..CHUNK_KEY_PROBES {
+                let key = chunk_key(&hash, attempt);
+                if dio.exists(&key).await == false {
+                    let chunk = Chunk {
+                        hash,
+                        buf,
+                        scope: Some(scope),
+                    };
+                    let mut chunk = dio.store_with_key(chunk, key.clone())?;
+                    chunk.attach_orphaned_ext(&config_key, CHUNK_COLLECTION_ID)?;
+                    chunk.auth_mut().read = read.clone();
+                    found = Some((key, true));
+                    break;
+                }
+                match dio.load::<Chunk>(&key).await {
+                    Ok(a) if a.hash == hash => {
+                        found = Some((key, false));
+                        break;
+                    }
+                    Ok(_) => {
+                        debug!("chunk key {} is held by another chunk", key);
+                    }
+                    Err(err) => {
+                        bail!(err);
+                    }
+                }
+            }
+            let (key, stored) = match found {
+                Some(a) => a,
+                None => {
+                    bail!(FileSystemErrorKind::CorruptChunk(hash.to_string()));
+                }
+            };
+            written.push((size, stored));
+            *entry = ChunkEntry::Stored(ChunkRef {
+                key: Some(key),
+                size,
+                hash: Some(hash),
+            });
+        }
+
+        list.as_mut().chunks = self
+            .entries
+            .iter()
+            .filter_map(|a| match a {
+                ChunkEntry::Stored(a) => Some(a.clone()),
+                ChunkEntry::Dirty(_) => None,
+            })
+            .collect();
+        inode.as_mut().size = self.size;
+        dio.commit().await?;
+
+        for (size, stored) in written {
+            stats.record(size, stored);
+        }
+        *dirty = false;
+        Ok(())
+    }
+
+    async fn set_xattr(&mut self, name: &str, value: &str) -> Result<()> {
+        match &mut self.handle {
+            ChunkedHandle::Mutable { inode, .. } => {
+                inode
+                    .as_mut()
+                    .xattr
+                    .insert(name.to_string(), value.to_string())
+                    .await?
+            }
+            ChunkedHandle::Immutable { .. } => {
+                bail!(FileSystemErrorKind::NoAccess);
+            }
+        };
+        Ok(())
+    }
+
+    async fn remove_xattr(&mut self, name: &str) -> Result<bool> {
+        let name = name.to_string();
+        let ret = match &mut self.handle {
+            ChunkedHandle::Mutable { inode, .. } => inode.as_mut().xattr.delete(&name).await?,
+            ChunkedHandle::Immutable { .. } => {
+                bail!(FileSystemErrorKind::NoAccess);
+            }
+        };
+        Ok(ret)
+    }
+
+    async fn get_xattr(&self, name: &str) -> Result<Option<String>> {
+        let name = name.to_string();
+        let ret = match &self.handle {
+            ChunkedHandle::Mutable { inode, .. } => {
+                inode.xattr.get(&name).await?.map(|a| a.deref().clone())
+            }
+            ChunkedHandle::Immutable { inode } => {
+                inode.xattr.get(&name).await?.map(|a| a.deref().clone())
+            }
+        };
+        Ok(ret)
+    }
+
+    async fn list_xattr(&self) -> Result<FxHashMap<String, String>> {
+        let mut ret = FxHashMap::default();
+        let iter = match &self.handle {
+            ChunkedHandle::Mutable { inode, .. } => inode.xattr.iter().await?,
+            ChunkedHandle::Immutable { inode } => inode.xattr.iter().await?,
+        };
+        for (k, v) in iter {
+            ret.insert(k, v.deref().clone());
+        }
+        Ok(ret)
+    }
+}
+
+#[async_trait]
+impl FileApi for ChunkedFile {
+    fn kind(&self) -> FileKind {
+        FileKind::RegularFile
+    }
+
+    fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    fn size(&self) -> u64 {
+        self.size.read()
+    }
+
+    fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn created(&self) -> u64 {
+        self.created
+    }
+
+    fn updated(&self) -> u64 {
+        self.updated
+    }
+
+    fn accessed(&self) -> u64 {
+        self.updated
+    }
+
+    async fn fallocate(&self, size: u64) -> Result<()> {
+        let mut state = self.state.lock().await;
+        state.set_size(size).await?;
+        *self.size.lock_write() = state.size;
+        Ok(())
+    }
+
+    async fn read(&self, offset: u64, mut size: u64) -> Result<Bytes> {
+        // Clip the read to the correct size (or return EOF)
+        let mut state = self.state.lock().await;
+        if offset >= state.size {
+            return Ok(Bytes::from(Vec::new()));
+        }
+        size = size.min(state.size - offset);
+
+        let mut ret = Vec::with_capacity(size as usize);
+        state.read_range(offset, size, &mut ret).await?;
+        Ok(Bytes::from(ret))
+    }
+
+    async fn write(&self, offset: u64, data: &[u8]) -> Result<u64> {
+        if data.len() <= 0 {
+            return Ok(0);
+        }
+
+        let mut state = self.state.lock().await;
+        state.write(offset, data).await?;
+        *self.size.lock_write() = state.size;
+        Ok(data.len() as u64)
+    }
+
+    async fn commit(&self) -> Result<()> {
+        let mut state = self.state.lock().await;
+        state.commit().await?;
+        Ok(())
+    }
+
+    async fn set_xattr(&mut self, name: &str, value: &str) -> Result<()> {
+        let mut state = self.state.lock().await;
+        state.set_xattr(name, value).await
+    }
+
+    async fn remove_xattr(&mut self, name: &str) -> Result<bool> {
+        let mut state = self.state.lock().await;
+        state.remove_xattr(name).await
+    }
+
+    async fn get_xattr(&self, name: &str) -> Result<Option<String>> {
+        let state = self.state.lock().await;
+        state.get_xattr(name).await
+    }
+
+    async fn list_xattr(&self) -> Result<FxHashMap<String, String>> {
+        let state = self.state.lock().await;
+        state.list_xattr().await
+    }
+}
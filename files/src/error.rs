@@ -55,6 +55,10 @@ error_chain! {
             description("the function is not implemented"),
             display("the function is not implemented")
         }
+        CorruptChunk(key: String) {
+            description("a chunk of the file does not match its hash"),
+            display("the chunk {} does not match its hash", key)
+        }
     }
 }
 
@@ -1,12 +1,15 @@
 pub mod accessor;
 pub mod api;
 pub mod attr;
+pub mod chunk;
+pub mod chunked;
 pub mod codes;
 pub mod dir;
 pub mod error;
 pub mod file;
 pub mod fixed;
 pub mod handle;
+pub mod lock;
 pub mod model;
 pub mod prelude;
 pub mod symlink;
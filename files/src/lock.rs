@@ -0,0 +1,261 @@
+use error_chain::bail;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use ::ate::dio::DaoMut;
+use ::ate::dio::DioMut;
+use ::ate::header::PrimaryKey;
+use ::ate::prelude::*;
+
+use super::accessor::*;
+use super::error::*;
+use super::model::*;
+
+/// How long a lock that was asked to wait (without saying for how long)
+/// waits before it gives up
+pub const FILE_LOCK_DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The gate is only ever held for a moment by whoever is taking a lock
+/// hence it is not worth waiting for it any longer than this
+const FILE_LOCK_GATE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileLockKind {
+    Shared,
+    Exclusive,
+}
+
+impl std::fmt::Display for FileLockKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileLockKind::Shared => write!(f, "shared"),
+            FileLockKind::Exclusive => write!(f, "exclusive"),
+        }
+    }
+}
+
+// Chain locks are always exclusive hence each shared lock holds a slot of
+// its own (the number of slots handed out is kept in a row) while an
+// exclusive lock holds the writer key, which stops any more slots from
+// being taken, once it has checked that none of the slots are held. Both
+// of them hold the gate while they do this so they never race each other.
+fn lock_gate_key(inode: u64) -> PrimaryKey {
+    PrimaryKey::from(format!("flock:{}:gate", inode))
+}
+
+fn lock_writer_key(inode: u64) -> PrimaryKey {
+    PrimaryKey::from(format!("flock:{}:writer", inode))
+}
+
+fn lock_slots_key(inode: u64) -> PrimaryKey {
+    PrimaryKey::from(format!("flock:{}:slots", inode))
+}
+
+fn lock_slot_key(inode: u64, slot: u64) -> PrimaryKey {
+    PrimaryKey::from(format!("flock:{}:{}", inode, slot))
+}
+
+/// Advisory lock held on a file within the chain, the lock is released
+/// when it is dropped (or when the session with the server is lost)
+#[derive(Debug)]
+pub struct FileLock {
+    pub inode: u64,
+    pub kind: FileLockKind,
+    keys: Vec<PrimaryKey>,
+    dio: Option<Arc<DioMut>>,
+}
+
+impl FileLock {
+    pub async fn unlock(mut self) -> Result<()> {
+        if let Some(dio) = self.dio.take() {
+            for key in self.keys.drain(..) {
+                dio.unlock(key).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        if let Some(dio) = self.dio.take() {
+            let keys = std::mem::take(&mut self.keys);
+            if keys.len() > 0 && tokio::runtime::Handle::try_current().is_ok() {
+                TaskEngine::spawn(async move {
+                    for key in keys {
+                        if let Err(err) = dio.unlock(key).await {
+                            debug!("failed to release file lock - {}", err);
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
+
+impl FileAccessor {
+    /// Attempts to acquire an advisory lock on a file without waiting
+    pub async fn try_lock(&self, inode: u64, kind: FileLockKind) -> Result<Option<FileLock>> {
+        // The number of slots must be seen by every mount before the gate
+        // is released hence it is committed to the whole chain
+        let dio = self.dio.trans(TransactionScope::Full).await;
+        dio.auto_cancel();
+
+        let gate = lock_gate_key(inode);
+        if lock_gate(&dio, gate).await? == false {
+            trace!("wasmer-dfs::lock inode={} kind={} gate contended", inode, kind);
+            return Ok(None);
+        }
+        let ret = match kind {
+            FileLockKind::Shared => try_lock_shared(&dio, inode).await,
+            FileLockKind::Exclusive => try_lock_exclusive(&dio, inode).await,
+        };
+        let unlocked = dio.unlock(gate).await;
+
+        let ret = ret?.map(|key| FileLock {
+            inode,
+            kind,
+            keys: vec![key],
+            dio: Some(dio),
+        });
+        unlocked?;
+
+        match ret.is_some() {
+            true => debug!("wasmer-dfs::lock inode={} kind={}", inode, kind),
+            false => trace!("wasmer-dfs::lock inode={} kind={} contended", inode, kind),
+        }
+        Ok(ret)
+    }
+
+    /// Acquires an advisory lock on a file, waiting (with an exponential
+    /// backoff) until either it is acquired or the timeout elapses
+    pub async fn lock_with_timeout(
+        &self,
+        inode: u64,
+        kind: FileLockKind,
+        timeout: Duration,
+    ) -> Result<Option<FileLock>> {
+        let timer = Instant::now();
+        let mut max_wait = 0u64;
+        loop {
+            if let Some(ret) = self.try_lock(inode, kind).await? {
+                return Ok(Some(ret));
+            }
+
+            let remaining = match timeout.checked_sub(timer.elapsed()) {
+                Some(a) if a > Duration::ZERO => a,
+                _ => {
+                    return Ok(None);
+                }
+            };
+
+            max_wait = ((max_wait * 12u64) / 10u64) + 5u64;
+            max_wait = max_wait.min(500u64);
+            let min_wait = max_wait / 2u64;
+
+            let random_wait = fastrand::u64(min_wait..max_wait);
+            let random_wait = Duration::from_millis(random_wait).min(remaining);
+            ::ate::engine::sleep(random_wait).await;
+        }
+    }
+}
+
+async fn lock_gate(dio: &Arc<DioMut>, gate: PrimaryKey) -> Result<bool> {
+    let timer = Instant::now();
+    loop {
+        if dio.try_lock(gate).await? {
+            return Ok(true);
+        }
+        if timer.elapsed() > FILE_LOCK_GATE_TIMEOUT {
+            return Ok(false);
+        }
+        ::ate::engine::sleep(Duration::from_millis(fastrand::u64(1..10))).await;
+    }
+}
+
+async fn load_lock_slots(dio: &Arc<DioMut>, inode: u64) -> Result<DaoMut<FileLockSlots>> {
+    let key = lock_slots_key(inode);
+    Ok(match dio.load::<FileLockSlots>(&key).await {
+        Ok(a) => a,
+        Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+            let mut row = dio.store_with_key(FileLockSlots::default(), key)?;
+            {
+                // Anyone that can open the file can lock it
+                let mut auth = row.auth_mut();
+                auth.read = ReadOption::Inherit;
+                auth.write = WriteOption::Everyone;
+            }
+            row
+        }
+        Err(err) => {
+            bail!(err);
+        }
+    })
+}
+
+/// Takes a free slot (or hands out a new one) unless the file is locked
+/// exclusively, must be called while holding the gate
+async fn try_lock_shared(dio: &Arc<DioMut>, inode: u64) -> Result<Option<PrimaryKey>> {
+    let writer = lock_writer_key(inode);
+    if dio.try_lock(writer).await? == false {
+        return Ok(None);
+    }
+    dio.unlock(writer).await?;
+
+    let mut slots = load_lock_slots(dio, inode).await?;
+    for slot in 0..slots.count {
+        let key = lock_slot_key(inode, slot);
+        if dio.try_lock(key).await? {
+            return Ok(Some(key));
+        }
+    }
+
+    let key = lock_slot_key(inode, slots.count);
+    if dio.try_lock(key).await? == false {
+        return Ok(None);
+    }
+    slots.as_mut().count += 1;
+    if let Err(err) = dio.commit().await {
+        dio.unlock(key).await?;
+        bail!(err);
+    }
+    Ok(Some(key))
+}
+
+/// Takes the writer key if none of the slots are held, must be called
+/// while holding the gate
+async fn try_lock_exclusive(dio: &Arc<DioMut>, inode: u64) -> Result<Option<PrimaryKey>> {
+    let writer = lock_writer_key(inode);
+    if dio.try_lock(writer).await? == false {
+        return Ok(None);
+    }
+    match check_slots_free(dio, inode).await {
+        Ok(true) => Ok(Some(writer)),
+        ret => {
+            dio.unlock(writer).await?;
+            ret.map(|_| None)
+        }
+    }
+}
+
+async fn check_slots_free(dio: &Arc<DioMut>, inode: u64) -> Result<bool> {
+    let mut slots = load_lock_slots(dio, inode).await?;
+    for slot in 0..slots.count {
+        let key = lock_slot_key(inode, slot);
+        if dio.try_lock(key).await? == false {
+            return Ok(false);
+        }
+        dio.unlock(key).await?;
+    }
+
+    // No one holds a slot anymore so they are handed out from the start again
+    if slots.count > 0 {
+        slots.as_mut().count = 0;
+        dio.commit().await?;
+    }
+    Ok(true)
+}
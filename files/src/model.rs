@@ -1,4 +1,6 @@
 use super::api::*;
+use super::chunk::ChunkStats;
+use super::chunked::ChunkedFile;
 use super::dir::Directory;
 use super::file::RegularFile;
 use super::fixed::FixedFile;
@@ -6,10 +8,15 @@ use super::symlink::SymLink;
 use ate::prelude::*;
 use fxhash::FxHashMap;
 use serde::*;
+use std::sync::Arc;
 
 pub const PAGES_PER_BUNDLE: usize = 1024;
 pub const PAGE_SIZE: usize = 131072;
 pub const WEB_CONFIG_ID: u64 = 0xb709d79e5cf6dd64u64;
+/// Well known key of the row that enables content-defined chunking on a chain
+pub const CHUNK_CONFIG_ID: u64 = 0x5a1f3c8e92d04b17u64;
+/// Collection that the chunking rows are attached to (under the root inode)
+pub const CHUNK_COLLECTION_ID: u64 = 0x83e6b0f4d1c27a59u64;
 
 /// Represents a block of data
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -23,6 +30,74 @@ pub struct PageBundle {
     pub pages: Vec<Option<PrimaryKey>>,
 }
 
+/// Chunking settings of a chain, when this row exists any regular files
+/// that are created afterwards store their data as deduplicated chunks
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ChunkConfig {
+    /// Target average size of a chunk in bytes
+    pub avg_size: u32,
+}
+
+/// Represents a block of data that was cut at a content-defined boundary,
+/// chunks are keyed by their hash so that identical blocks are stored once
+/// (as other files may share them they are not deleted with the file but
+/// by `FileAccessor::collect_chunks` once nothing refers to them)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Chunk {
+    pub hash: AteHash,
+    pub buf: Vec<u8>,
+    /// Deduplication scope the hash was computed in (so that the data can
+    /// be checked against the hash when it is read)
+    #[serde(default)]
+    pub scope: Option<AteHash>,
+}
+
+/// Reference to a chunk that makes up part of a file (a missing key means
+/// the range is a hole full of zeros)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ChunkRef {
+    pub key: Option<PrimaryKey>,
+    pub size: u64,
+    /// Full hash of the chunk, keys are only 64 bits so this makes sure the
+    /// row that was found is really the chunk that was written
+    #[serde(default)]
+    pub hash: Option<AteHash>,
+}
+
+impl ChunkRef {
+    /// Range of zeros that has no chunk behind it
+    pub fn hole(size: u64) -> ChunkRef {
+        ChunkRef {
+            key: None,
+            size,
+            hash: None,
+        }
+    }
+}
+
+/// Ordered list of chunks that make up the data of a chunked file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChunkList {
+    pub avg_size: u32,
+    pub chunks: Vec<ChunkRef>,
+}
+
+impl ChunkList {
+    /// Key of the chunk list that belongs to an inode (files without one
+    /// store their data in page bundles)
+    pub fn key_for(inode: &PrimaryKey) -> PrimaryKey {
+        let hash = AteHash::from_bytes_twice(&inode.as_u64().to_be_bytes(), b"chunk-list");
+        PrimaryKey::from(hash.to_u64())
+    }
+}
+
+/// Number of shared lock slots that have been handed out on a file since it
+/// was last locked exclusively, an exclusive lock must check all of them
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy)]
+pub struct FileLockSlots {
+    pub count: u64,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Dentry {
     pub parent: Option<u64>,
@@ -67,9 +142,10 @@ impl Inode {
     pub async fn as_file_spec(ino: u64, created: u64, updated: u64, dao: Dao<Inode>) -> FileSpec {
         match dao.kind {
             FileKind::Directory => FileSpec::Directory(Directory::new(dao, created, updated)),
-            FileKind::RegularFile => {
-                FileSpec::RegularFile(RegularFile::new(dao, created, updated).await)
-            }
+            FileKind::RegularFile => match ChunkedFile::load(&dao).await {
+                Some(list) => FileSpec::ChunkedFile(ChunkedFile::new(dao, list, created, updated)),
+                None => FileSpec::RegularFile(RegularFile::new(dao, created, updated).await),
+            },
             FileKind::SymLink => FileSpec::SymLink(SymLink::new(dao, created, updated)),
             FileKind::FixedFile => FileSpec::FixedFile(
                 FixedFile::new(ino, dao.dentry.name.clone(), FileKind::RegularFile)
@@ -84,12 +160,20 @@ impl Inode {
         created: u64,
         updated: u64,
         dao: DaoMut<Inode>,
+        stats: &Arc<ChunkStats>,
     ) -> FileSpec {
         match dao.kind {
             FileKind::Directory => FileSpec::Directory(Directory::new_mut(dao, created, updated)),
-            FileKind::RegularFile => {
-                FileSpec::RegularFile(RegularFile::new_mut(dao, created, updated).await)
-            }
+            FileKind::RegularFile => match ChunkedFile::load_mut(&dao).await {
+                Some(list) => FileSpec::ChunkedFile(ChunkedFile::new_mut(
+                    dao,
+                    list,
+                    stats,
+                    created,
+                    updated,
+                )),
+                None => FileSpec::RegularFile(RegularFile::new_mut(dao, created, updated).await),
+            },
             FileKind::SymLink => FileSpec::SymLink(SymLink::new_mut(dao, created, updated)),
             FileKind::FixedFile => FileSpec::FixedFile(
                 FixedFile::new(ino, dao.dentry.name.clone(), FileKind::RegularFile)
@@ -6,14 +6,19 @@ pub use crate::api::FileKind;
 pub use crate::api::FileSpec;
 pub use crate::attr::FileAttr;
 pub use crate::attr::SetAttr;
+pub use crate::chunk::ChunkStats;
 
 pub use crate::accessor::FileAccessor;
 pub use crate::accessor::RequestContext;
+pub use crate::chunked::ChunkedFile;
 pub use crate::dir::Directory;
 pub use crate::file::FileState;
 pub use crate::file::RegularFile;
 pub use crate::fixed::FixedFile;
 pub use crate::handle::DirectoryEntry;
 pub use crate::handle::OpenHandle;
+pub use crate::lock::FileLock;
+pub use crate::lock::FileLockKind;
+pub use crate::lock::FILE_LOCK_DEFAULT_TIMEOUT;
 pub use crate::model::*;
 pub use crate::symlink::SymLink;
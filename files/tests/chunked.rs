@@ -0,0 +1,117 @@
+#![allow(unused_imports)]
+use ate::prelude::*;
+use ate_files::chunk::*;
+use ate_files::prelude::*;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+async fn write_file(
+    accessor: &FileAccessor,
+    req: &RequestContext,
+    name: &str,
+    data: &[u8],
+) -> Result<u64, FileSystemError> {
+    let handle = accessor.create(req, 1, name, 0o600).await?;
+    accessor.write(req, handle.inode, handle.fh, 0, data, 0).await?;
+    accessor.release(req, handle.inode, handle.fh, 0, 0, false).await?;
+    Ok(handle.inode)
+}
+
+async fn read_file(
+    accessor: &FileAccessor,
+    req: &RequestContext,
+    inode: u64,
+) -> Result<Vec<u8>, FileSystemError> {
+    let handle = accessor.open(req, inode, 0).await?;
+    let ret = accessor.read_all(req, inode, handle.fh).await;
+    accessor.release(req, inode, handle.fh, 0, 0, false).await?;
+    ret
+}
+
+#[test]
+fn chunked_file_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let conf = ConfAte::default();
+        let builder = ChainBuilder::new(&conf).await.temporal(true).build();
+        let chain = builder.open(&ChainKey::from("chunked-file")).await?;
+        let session = AteSessionType::User(AteSessionUser::new());
+        let accessor = FileAccessor::new(
+            Arc::clone(&chain),
+            None,
+            session,
+            TransactionScope::Local,
+            TransactionScope::Local,
+            true,
+            false,
+        )
+        .await;
+        let req = RequestContext::default();
+        accessor.init(&req).await?;
+        accessor.enable_chunking(CHUNK_MIN_AVG_SIZE).await?;
+
+        // Files are read back exactly as they were written
+        let data = (0..100000u32)
+            .map(|a| (a.wrapping_mul(2654435761) >> 24) as u8)
+            .collect::<Vec<_>>();
+        let first = write_file(&accessor, &req, "first", &data[..]).await?;
+        assert_eq!(read_file(&accessor, &req, first).await?, data);
+
+        // The chunks of a copy are stored only once
+        let stored = accessor.chunk_stats.stored_bytes.load(Ordering::Relaxed);
+        let second = write_file(&accessor, &req, "second", &data[..]).await?;
+        assert_eq!(read_file(&accessor, &req, second).await?, data);
+        assert_eq!(accessor.chunk_stats.stored_bytes.load(Ordering::Relaxed), stored);
+        assert!(accessor.chunk_stats.deduplicated.load(Ordering::Relaxed) > 0);
+
+        // Chunks are only collected once no file refers to them
+        assert_eq!(accessor.collect_chunks().await?, 0);
+        accessor.unlink(&req, 1, "first").await?;
+        assert_eq!(accessor.collect_chunks().await?, 0);
+        assert_eq!(read_file(&accessor, &req, second).await?, data);
+        accessor.unlink(&req, 1, "second").await?;
+        assert!(accessor.collect_chunks().await? > 0);
+
+        // Chunks that do not match their hash are refused when read
+        let third = write_file(&accessor, &req, "third", &data[..]).await?;
+        let list = accessor
+            .dio
+            .load_and_take::<ChunkList>(&ChunkList::key_for(&PrimaryKey::from(third)))
+            .await?;
+        let key = list.chunks[0].key.unwrap();
+        {
+            let dio = accessor.dio_mut_meta().await;
+            let mut chunk = dio.load::<Chunk>(&key).await?;
+            chunk.as_mut().buf[0] ^= 0xff;
+            dio.commit().await?;
+        }
+        match read_file(&accessor, &req, third).await {
+            Err(FileSystemError(FileSystemErrorKind::CorruptChunk(_), _)) => {}
+            other => panic!("expected the chunk to be refused - {:?}", other.map(|a| a.len())),
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn chunk_key_test() {
+    let scope = AteHash::from_bytes(b"everyone");
+    let hash = chunk_hash(b"some data", &scope);
+    assert_eq!(chunk_key(&hash, 0), PrimaryKey::from(hash.to_u64()));
+    assert_ne!(chunk_key(&hash, 0), chunk_key(&hash, 1));
+
+    let mut chunk = Chunk {
+        hash,
+        buf: b"some data".to_vec(),
+        scope: Some(scope),
+    };
+    assert!(chunk_verify(&chunk, Some(&hash)));
+    assert!(chunk_verify(&chunk, Some(&scope)) == false);
+    chunk.buf[0] = b'S';
+    assert!(chunk_verify(&chunk, Some(&hash)) == false);
+}
@@ -0,0 +1,81 @@
+#![allow(unused_imports)]
+use ate::prelude::*;
+use ate_files::lock::*;
+use ate_files::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+#[test]
+fn file_lock_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let conf = ConfAte::default();
+        let builder = ChainBuilder::new(&conf).await.temporal(true).build();
+        let chain = builder.open(&ChainKey::from("file-lock")).await?;
+        let session = AteSessionType::User(AteSessionUser::new());
+        let accessor = FileAccessor::new(
+            Arc::clone(&chain),
+            None,
+            session,
+            TransactionScope::Local,
+            TransactionScope::Local,
+            true,
+            false,
+        )
+        .await;
+        let req = RequestContext::default();
+        accessor.init(&req).await?;
+        let handle = accessor.create(&req, 1, "deploy.lock", 0o600).await?;
+        let inode = handle.inode;
+
+        // Any number of shared locks can be held at the same time
+        let mut shared = Vec::new();
+        for _ in 0..20 {
+            let lock = accessor.try_lock(inode, FileLockKind::Shared).await?;
+            shared.push(lock.expect("shared locks should not contend"));
+        }
+        assert!(accessor.try_lock(inode, FileLockKind::Exclusive).await?.is_none());
+
+        // An exclusive lock is refused until the last shared lock is released
+        let last = shared.pop().unwrap();
+        for lock in shared.drain(..) {
+            lock.unlock().await?;
+        }
+        assert!(accessor.try_lock(inode, FileLockKind::Exclusive).await?.is_none());
+        last.unlock().await?;
+        let exclusive = accessor.try_lock(inode, FileLockKind::Exclusive).await?;
+        let exclusive = exclusive.expect("the exclusive lock should be free");
+
+        // Waiting for a lock gives up once the timeout elapses
+        assert!(accessor.try_lock(inode, FileLockKind::Shared).await?.is_none());
+        let start = Instant::now();
+        let timeout = Duration::from_millis(200);
+        let ret = accessor
+            .lock_with_timeout(inode, FileLockKind::Exclusive, timeout)
+            .await?;
+        assert!(ret.is_none());
+        assert!(start.elapsed() >= timeout);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // Dropping a lock releases it (like closing the file it was taken on)
+        drop(exclusive);
+        let ret = accessor
+            .lock_with_timeout(inode, FileLockKind::Shared, Duration::from_secs(5))
+            .await?;
+        assert!(ret.is_some());
+
+        // Locks on one file do not get in the way of locks on another
+        let other = accessor.create(&req, 1, "other.lock", 0o600).await?;
+        assert!(accessor
+            .try_lock(other.inode, FileLockKind::Exclusive)
+            .await?
+            .is_some());
+        Ok(())
+    })
+}
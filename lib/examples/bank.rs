@@ -31,7 +31,7 @@ async fn make_account<'a>(
     generator: &mut Generator<'a>,
 ) -> Result<(), AteError> {
     let session = AteSessionUser::new();
-    let dio = chain.dio_mut(&session).await;
+    let dio = chain.dio_mut(&session).await?;
 
     let person = Person {
         first_name: generator.next().unwrap(),
@@ -58,7 +58,7 @@ async fn main() -> Result<(), AteError> {
         .unwrap();
     let (mut bus, key) = {
         info!("writing a record ('table') to the remote chain from client 1");
-        let dio = chain_a.dio_trans(&session, TransactionScope::Full).await;
+        let dio = chain_a.dio_trans(&session, TransactionScope::Full).await?;
         let dao = dio.store(Table {
             ball: DaoVec::default(),
         })?;
@@ -82,7 +82,7 @@ async fn main() -> Result<(), AteError> {
         chain_b.sync().await?;
 
         info!("writing two records ('balls') onto the earlier saved record 'table' from client 2");
-        let dio = chain_b.dio_trans(&session, TransactionScope::Full).await;
+        let dio = chain_b.dio_trans(&session, TransactionScope::Full).await?;
         let mut dao = dio.load::<Table>(&key).await?;
         dao.as_mut().ball.push(BallSound::Ping)?;
         dao.as_mut().ball.push(BallSound::Ping)?;
@@ -91,7 +91,7 @@ async fn main() -> Result<(), AteError> {
 
     // Process any events that were received on the BUS
     {
-        let dio = chain_a.dio_trans(&session, TransactionScope::Full).await;
+        let dio = chain_a.dio_trans(&session, TransactionScope::Full).await?;
 
         // (this is a broadcast event to all current subscribers)
         info!("waiting for the first record on the BUS of client 1 which we will process as a broadcast");
@@ -30,7 +30,7 @@ async fn main() -> Result<(), AteError> {
 
     let key = {
         // Now create the data using the keys we have
-        let dio = chain.dio_mut(&session).await;
+        let dio = chain.dio_mut(&session).await?;
         let mut dao = dio.store(TrustedRecord {
             hidden_data: "Secret data".to_string(),
         })?;
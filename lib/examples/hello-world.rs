@@ -17,7 +17,7 @@ async fn main() -> Result<(), AteError> {
 
     // We interact with the data stored in the chain-of-trust using a DIO
     let session = AteSessionUser::new();
-    let dio = chain.dio_mut(&session).await;
+    let dio = chain.dio_mut(&session).await?;
 
     // In this example we store some data in the "World" object
     let key = dio
@@ -0,0 +1,127 @@
+use ate::prelude::*;
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Compares the time it takes to open a large chain and the memory that is
+/// used once its open, with and without a limit on the index memory.
+///
+/// Each measurement should run in its own process so that the memory of one
+/// run does not skew the next, e.g.
+///
+///   cargo run --release --example index-bench -- generate
+///   cargo run --release --example index-bench -- open
+///   cargo run --release --example index-bench -- open --max-index-memory 67108864 --streaming
+#[derive(Parser)]
+struct Opts {
+    /// Directory where the redo log of the benchmark chain is stored
+    #[clap(long, default_value = "/tmp/ate-bench")]
+    log_path: String,
+    #[clap(subcommand)]
+    subcmd: SubCommand,
+}
+
+#[derive(Parser)]
+enum SubCommand {
+    /// Creates a chain filled with synthetic events
+    Generate(Generate),
+    /// Opens the chain and reports the open time and memory usage
+    Open(Open),
+}
+
+#[derive(Parser)]
+struct Generate {
+    /// Number of events that will be written to the chain
+    #[clap(long, default_value = "1000000")]
+    events: usize,
+    /// Number of events that are written in each transaction
+    #[clap(long, default_value = "1000")]
+    batch: usize,
+}
+
+#[derive(Parser)]
+struct Open {
+    /// Amount of memory in bytes that the history index may use
+    #[clap(long)]
+    max_index_memory: Option<usize>,
+    /// Processes the events in batches while the log is being read
+    #[clap(long)]
+    streaming: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Row {
+    id: u64,
+    name: String,
+}
+
+/// Resident memory of this process (only available on linux)
+fn rss() -> u64 {
+    std::fs::read_to_string("/proc/self/statm")
+        .ok()
+        .and_then(|a| a.split_whitespace().nth(1).map(|a| a.to_string()))
+        .and_then(|a| a.parse::<u64>().ok())
+        .map(|a| a * 4096)
+        .unwrap_or(0)
+}
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() -> Result<(), AteError> {
+    let opts: Opts = Opts::parse();
+
+    let mut conf = ConfAte::default();
+    conf.configured_for(ConfiguredFor::BestPerformance);
+    conf.log_path = Some(opts.log_path.clone());
+    let key = ChainKey::from("index-bench");
+    let session = AteSessionUser::new();
+
+    match opts.subcmd {
+        SubCommand::Generate(generate) => {
+            let builder = ChainBuilder::new(&conf).await.truncate(true).build();
+            let chain = builder.open(&key).await?;
+
+            let start = Instant::now();
+            let mut written = 0usize;
+            while written < generate.events {
+                let dio = chain.dio_mut(&session).await?;
+                let cnt = generate.batch.min(generate.events - written);
+                for n in 0..cnt {
+                    dio.store(Row {
+                        id: (written + n) as u64,
+                        name: format!("row-{}", written + n),
+                    })?;
+                }
+                dio.commit().await?;
+                written += cnt;
+            }
+            chain.flush().await?;
+            println!(
+                "generated {} events in {}ms",
+                written,
+                start.elapsed().as_millis()
+            );
+        }
+        SubCommand::Open(open) => {
+            conf.max_index_memory = open.max_index_memory;
+            conf.load_streaming = open.streaming;
+
+            let before = rss();
+            let start = Instant::now();
+            let builder = ChainBuilder::new(&conf).await.build();
+            let chain = builder.open(&key).await?;
+            let elapsed = start.elapsed();
+
+            let usage = chain.index_usage().await;
+
+            println!("max-index-memory: {:?}", open.max_index_memory);
+            println!("streaming:        {}", open.streaming);
+            println!("open time:        {}ms", elapsed.as_millis());
+            println!("rss:              {} bytes", rss().saturating_sub(before));
+            println!("events in memory: {}", usage.events_in_memory);
+            println!("events on disk:   {}", usage.events_spilled);
+            println!("index memory:     {} bytes", usage.memory_bytes);
+        }
+    }
+
+    Ok(())
+}
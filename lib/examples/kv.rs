@@ -0,0 +1,82 @@
+#![allow(unused_imports)]
+use ate::kv::KvBucket;
+use ate::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Settings {
+    theme: String,
+    font_size: u32,
+}
+
+#[cfg(not(feature = "enable_server"))]
+fn main() {}
+
+#[cfg(feature = "enable_server")]
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), AteError> {
+    ate::log_init(0, true);
+
+    // Key-value buckets use chain locks which are served by the mesh
+    let url = url::Url::parse("ws://localhost:5000/kv-chain").unwrap();
+    let cfg_ate = ConfAte::default();
+    #[cfg(feature = "enable_dns")]
+    let cfg_mesh =
+        ConfMesh::solo_from_url(&cfg_ate, &url, &IpAddr::from_str("::").unwrap(), None, None)
+            .await?;
+    #[cfg(not(feature = "enable_dns"))]
+    let cfg_mesh = ConfMesh::solo_from_url(&cfg_ate, &url)?;
+    let server = create_ethereal_centralized_server(&cfg_ate, &cfg_mesh).await?;
+
+    let registry = Registry::new(&cfg_ate).await.cement();
+    let chain = registry
+        .open(
+            &url::Url::from_str("ws://localhost:5000/").unwrap(),
+            &ChainKey::from("kv-chain"),
+            false,
+        )
+        .await?;
+
+    // Values that are written to the bucket are encrypted with this key
+    let key = EncryptKey::generate(KeySize::Bit192);
+    let mut session = AteSessionUser::new();
+    session.add_user_read_key(&key);
+    let bucket = ate::kv::bucket(&chain.as_arc(), "users")
+        .with_session(&session)
+        .with_read(ReadOption::from_key(&key));
+
+    // Plain bytes
+    bucket.put("alice/email", b"alice@example.com".to_vec()).await?;
+    bucket.put("bob/email", b"bob@example.com".to_vec()).await?;
+    let email = bucket.get("alice/email").await?.unwrap();
+    println!("alice: {}", String::from_utf8_lossy(&email[..]));
+
+    // Typed values
+    let settings = Settings {
+        theme: "dark".to_string(),
+        font_size: 12,
+    };
+    bucket.put_as("alice/settings", &settings).await?;
+    println!("settings: {:?}", bucket.get_as::<Settings>("alice/settings").await?);
+
+    // Everything for a particular user
+    for (key, _) in bucket.list_prefix("alice/").await? {
+        println!("found: {}", key);
+    }
+
+    // Compare-and-swap only writes when nobody else changed the value
+    let bigger = Settings {
+        font_size: 14,
+        ..settings.clone()
+    };
+    let swapped = bucket
+        .compare_and_swap_as("alice/settings", Some(&settings), Some(&bigger))
+        .await?;
+    println!("swapped: {}", swapped);
+
+    bucket.delete("bob/email").await?;
+
+    server.shutdown().await;
+    Ok(())
+}
@@ -34,7 +34,7 @@ async fn main() -> Result<(), AteError> {
             )
             .await?;
         let session = AteSessionUser::new();
-        let dio = chain.dio_mut(&session).await;
+        let dio = chain.dio_mut(&session).await?;
         let dao = dio.store("my test string".to_string())?;
         dio.commit().await?;
         dao.key().clone()
@@ -0,0 +1,308 @@
+//! Export and import of a chain as a single archive file, used to inspect a
+//! copy of a chain on a machine that can not reach its root.
+//!
+//! An archive is a sequence of records that each carry their length and a
+//! checksum. The events come first (in the order of the timeline) and the
+//! archive ends with a manifest that describes what was written, an archive
+//! that is missing its manifest, holds fewer events than the manifest lists
+//! or whose checksum does not match is reported as truncated rather than
+//! being imported with some of its events missing.
+#![allow(unused_imports)]
+use bytes::Bytes;
+use error_chain::bail;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::io::Write;
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::conf::*;
+use crate::crypto::AteHash;
+use crate::error::*;
+use crate::event::*;
+use crate::index::*;
+use crate::meta::*;
+use crate::redo::*;
+use crate::spec::*;
+use crate::time::ChainTimestamp;
+use crate::trust::*;
+
+use super::*;
+
+/// Marker at the start of every archive file
+const ARCHIVE_MAGIC: &'static [u8] = b"ATE-ARCHIVE-1\n";
+
+/// Size of the header in front of every record (length and checksum)
+const ARCHIVE_RECORD_HEADER: usize = 4 + AteHash::LEN;
+
+/// Number of events that are loaded from the chain at a time while exporting
+const ARCHIVE_EXPORT_BATCH: usize = 1000;
+
+/// Describes the contents of an archive, it is the last record in the file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChainArchiveManifest {
+    /// Key of the chain that was exported
+    pub key: ChainKey,
+    /// Number of events in the archive
+    pub events: u64,
+    /// Timestamp of the newest event in the chain when it was exported
+    pub head: ChainTimestamp,
+    /// Hash of the checksums of all the event records in the archive
+    pub checksum: AteHash,
+}
+
+/// Chain that was imported from an archive
+pub struct ChainArchive {
+    /// Local read-only copy of the chain
+    pub chain: Arc<Chain>,
+    pub manifest: ChainArchiveManifest,
+    /// Timestamp of the newest event in the imported chain, this is the
+    /// point in time the archive reflects (regardless of when the file
+    /// itself was written or copied)
+    pub head: ChainTimestamp,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+enum ArchiveRecord {
+    Event {
+        meta: Metadata,
+        data: Option<Vec<u8>>,
+        format: MessageFormat,
+    },
+    Manifest(ChainArchiveManifest),
+}
+
+impl Chain {
+    /// Writes every event of the chain into an archive file that can later
+    /// be imported with `ChainBuilder::open_archive`. The archive is written
+    /// next to the path first and only moved into place once it is complete.
+    pub async fn export_archive(&self, path: &str) -> Result<ChainArchiveManifest, ChainCreationError> {
+        debug!("export archive: {} -> {}", self.key(), path);
+
+        let headers = {
+            let guard = self.inside_async.read().await;
+            guard.range(..).collect::<Vec<_>>()
+        };
+        let head = headers
+            .last()
+            .map(|a| a.0.clone())
+            .unwrap_or_else(|| ChainTimestamp::from(0u64));
+
+        let partial_path = format!("{}.partial", path);
+        let mut file = File::create(&partial_path)?;
+        file.write_all(ARCHIVE_MAGIC)?;
+
+        let multi = self.multi().await;
+        let mut checksum = Vec::with_capacity(headers.len() * AteHash::LEN);
+        for batch in headers.chunks(ARCHIVE_EXPORT_BATCH) {
+            let leafs = batch
+                .iter()
+                .map(|(_, v)| EventLeaf {
+                    record: v.event_hash.clone(),
+                    created: 0,
+                    updated: 0,
+                })
+                .collect::<Vec<_>>();
+
+            for ((_, raw), evt) in batch.iter().zip(multi.load_many(leafs).await?) {
+                // Data that is not held locally would silently go missing
+                if raw.data_size > 0 && evt.data.data_bytes.is_none() {
+                    let _ = std::fs::remove_file(&partial_path);
+                    bail!(ChainCreationErrorKind::ArchiveInvalid(
+                        path.to_string(),
+                        format!("the data of event {} is not available locally", raw.event_hash)
+                    ));
+                }
+
+                let record = ArchiveRecord::Event {
+                    meta: evt.data.meta,
+                    data: evt.data.data_bytes.map(|a| a.to_vec()),
+                    format: evt.data.format,
+                };
+                let hash = write_archive_record(&mut file, &record)?;
+                checksum.extend_from_slice(hash.as_bytes());
+            }
+        }
+
+        let manifest = ChainArchiveManifest {
+            key: self.key().clone(),
+            events: headers.len() as u64,
+            head,
+            checksum: AteHash::from_bytes(&checksum[..]),
+        };
+        write_archive_record(&mut file, &ArchiveRecord::Manifest(manifest.clone()))?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&partial_path, path)?;
+
+        debug!("exported {} events into {}", manifest.events, path);
+        Ok(manifest)
+    }
+}
+
+impl ChainBuilder {
+    /// Imports an archive into a local read-only chain. The redo log of the
+    /// chain is written under the log path of the builder (which ought to be
+    /// a temporary folder) and any log already there for the chain is
+    /// replaced.
+    pub async fn open_archive(self, path: &str) -> Result<ChainArchive, ChainCreationError> {
+        debug!("open archive: {}", path);
+        if self.cfg_ate.log_path.is_none() {
+            bail!(ChainCreationErrorKind::ArchiveInvalid(
+                path.to_string(),
+                "there is no log path to import the chain into".to_string()
+            ));
+        }
+        let (events, manifest) = read_archive(path)?;
+
+        // Write the events into a fresh redo log for the chain
+        {
+            let flags = OpenFlags {
+                read_only: false,
+                truncate: true,
+                temporal: false,
+                integrity: self.load_integrity,
+            };
+            let header_bytes = SerializationFormat::Json
+                .serialize(&ChainHeader::default())
+                .map_err(SerializationError::from)?;
+            let (mut redo_log, _) =
+                RedoLog::open(&self.cfg_ate, &manifest.key, flags, header_bytes).await?;
+            for evt in events.iter() {
+                redo_log.write(evt).await?;
+            }
+            redo_log.flush().await?;
+        }
+
+        // Load the chain back in and make sure nothing was lost on the way
+        let chain = self
+            .truncate(false)
+            .temporal(false)
+            .read_only(true)
+            .build()
+            .open(&manifest.key)
+            .await?;
+        let (loaded, head) = {
+            let guard = chain.inside_async.read().await;
+            (
+                guard.chain.timeline.history.len() as u64,
+                guard.chain.timeline.end(),
+            )
+        };
+        if loaded != manifest.events {
+            bail!(ChainCreationErrorKind::ArchiveTruncated(
+                path.to_string(),
+                format!("only {} of its {} events could be loaded", loaded, manifest.events)
+            ));
+        }
+        if head != manifest.head {
+            bail!(ChainCreationErrorKind::ArchiveTruncated(
+                path.to_string(),
+                format!("it ends at {} while the chain was exported at {}", head, manifest.head)
+            ));
+        }
+
+        debug!("imported {} events from {} (head={})", loaded, path, head);
+        Ok(ChainArchive {
+            chain,
+            manifest,
+            head,
+        })
+    }
+}
+
+fn write_archive_record(file: &mut File, record: &ArchiveRecord) -> Result<AteHash, ChainCreationError> {
+    let payload = bincode::serialize(record)
+        .map_err(|err| ChainCreationErrorKind::InternalError(err.to_string()))?;
+    let hash = AteHash::from_bytes(&payload[..]);
+    let mut buf = Vec::with_capacity(ARCHIVE_RECORD_HEADER + payload.len());
+    buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    buf.extend_from_slice(hash.as_bytes());
+    buf.extend_from_slice(&payload[..]);
+    file.write_all(&buf[..])?;
+    Ok(hash)
+}
+
+/// Reads and verifies all the records in an archive, unlike the outbound
+/// write-ahead file nothing is skipped as a partial archive must not be
+/// mistaken for the whole chain
+fn read_archive(path: &str) -> Result<(Vec<EventWeakData>, ChainArchiveManifest), ChainCreationError> {
+    let truncated = |reason: &str| -> ChainCreationError {
+        ChainCreationErrorKind::ArchiveTruncated(path.to_string(), reason.to_string()).into()
+    };
+    let invalid = |reason: String| -> ChainCreationError {
+        ChainCreationErrorKind::ArchiveInvalid(path.to_string(), reason).into()
+    };
+
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    if data.starts_with(ARCHIVE_MAGIC) == false {
+        return Err(invalid("it is not a chain archive".to_string()));
+    }
+    let mut data = &data[ARCHIVE_MAGIC.len()..];
+
+    let mut events = Vec::new();
+    let mut checksum = Vec::new();
+    let mut manifest = None;
+    while data.len() > 0 {
+        if manifest.is_some() {
+            return Err(invalid("it has data after its manifest".to_string()));
+        }
+        if data.len() < ARCHIVE_RECORD_HEADER {
+            return Err(truncated("its last record is incomplete"));
+        }
+        let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let hash = &data[4..ARCHIVE_RECORD_HEADER];
+        data = &data[ARCHIVE_RECORD_HEADER..];
+        if data.len() < len {
+            return Err(truncated("its last record is incomplete"));
+        }
+        let (payload, rest) = data.split_at(len);
+        data = rest;
+
+        if AteHash::from_bytes(payload).as_bytes()[..] != hash[..] {
+            return Err(invalid(format!(
+                "record {} is corrupted (checksum mismatch)",
+                events.len()
+            )));
+        }
+        match bincode::deserialize::<ArchiveRecord>(payload) {
+            Ok(ArchiveRecord::Event { meta, data, format }) => {
+                checksum.extend_from_slice(hash);
+                events.push(EventWeakData {
+                    meta,
+                    data_bytes: match data {
+                        Some(a) => MessageBytes::Some(Bytes::from(a)),
+                        None => MessageBytes::None,
+                    },
+                    format,
+                });
+            }
+            Ok(ArchiveRecord::Manifest(a)) => {
+                manifest = Some(a);
+            }
+            Err(err) => {
+                return Err(invalid(format!("record {} is corrupted - {}", events.len(), err)));
+            }
+        }
+    }
+
+    let mut manifest = match manifest {
+        Some(a) => a,
+        None => {
+            return Err(truncated("its manifest is missing"));
+        }
+    };
+    // The hash of the key is not serialized
+    manifest.key = ChainKey::new(manifest.key.name.clone());
+    if events.len() as u64 != manifest.events {
+        return Err(truncated(
+            format!("it holds {} of its {} events", events.len(), manifest.events).as_str(),
+        ));
+    }
+    if AteHash::from_bytes(&checksum[..]) != manifest.checksum {
+        return Err(invalid("its events do not match its manifest".to_string()));
+    }
+    Ok((events, manifest))
+}
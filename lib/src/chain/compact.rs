@@ -1,4 +1,3 @@
-use btreemultimap::BTreeMultiMap;
 use std::sync::Arc;
 use std::sync::RwLock as StdRwLock;
 use tokio::sync::RwLock;
@@ -20,6 +19,88 @@ use crate::transaction::*;
 use crate::trust::*;
 
 impl<'a> Chain {
+    /// Evaluates a compaction policy against the current state of this chain
+    /// and returns the trigger that fired (if any)
+    pub async fn compaction_trigger(
+        self: &'a Chain,
+        policy: &CompactionPolicy,
+        since_last: std::time::Duration,
+    ) -> Result<Option<CompactionTrigger>, CompactError> {
+        let now = self.time.current_timestamp()?.time_since_epoch_ms;
+        let time_of_day = ((now / 1000) % 86400) as u32;
+
+        let (log_size, events, tombstones) = {
+            let guard = self.inside_async.read().await;
+            (
+                guard.chain.redo.size(),
+                guard.chain.redo.count() as u64,
+                guard.chain.timeline.tombstones,
+            )
+        };
+        let size_after_last = self
+            .metrics
+            .lock()
+            .unwrap()
+            .last_compaction
+            .as_ref()
+            .map(|a| a.size_after);
+
+        Ok(policy.evaluate(
+            log_size,
+            size_after_last,
+            events,
+            tombstones,
+            since_last,
+            time_of_day,
+        ))
+    }
+
+    /// Compacts the chain and records the statistics of the compaction
+    /// against the metrics of this chain
+    pub async fn compact_with_stats(
+        self: &'a Chain,
+        trigger: CompactionTrigger,
+    ) -> Result<CompactionStats, CompactError> {
+        let (size_before, events_before) = {
+            let guard = self.inside_async.read().await;
+            (
+                guard.chain.redo.size(),
+                guard.chain.redo.count() as u64,
+            )
+        };
+        let start = std::time::Instant::now();
+
+        self.compact().await?;
+
+        let (size_after, events_after) = {
+            let guard = self.inside_async.read().await;
+            (
+                guard.chain.redo.size(),
+                guard.chain.redo.count() as u64,
+            )
+        };
+        let stats = CompactionStats {
+            trigger,
+            when_ms: self.time.current_timestamp()?.time_since_epoch_ms,
+            duration: start.elapsed(),
+            size_before,
+            size_after,
+            events_before,
+            events_after,
+        };
+        debug!(
+            "compacted chain: {} ({}) {} bytes -> {} bytes",
+            self.key, trigger, size_before, size_after
+        );
+
+        {
+            let mut metrics = self.metrics.lock().unwrap();
+            metrics.compactions += 1;
+            metrics.last_compaction = Some(stats.clone());
+        }
+        Ok(stats)
+    }
+
     pub async fn compact(self: &'a Chain) -> Result<(), CompactError> {
         Chain::compact_ext(
             Arc::clone(&self.inside_async),
@@ -30,6 +111,56 @@ impl<'a> Chain {
         .await
     }
 
+    /// Rewrites every row whose latest version is a patch as a full row so
+    /// that the chains of patches behind them can be removed by the next
+    /// compaction. The data of the rows is rebuilt (and encrypted again)
+    /// with the keys in the session hence rows it can not read are skipped.
+    /// Returns the number of rows that were folded.
+    pub async fn fold_patches(
+        self: &Arc<Chain>,
+        session: &'_ dyn AteSession,
+    ) -> Result<u64, CommitError> {
+        let keys = {
+            let guard = self.inside_async.read().await;
+            let mut keys = guard
+                .chain
+                .timeline
+                .history
+                .iter()
+                .filter_map(|a| a.1.as_header().ok())
+                .filter(|a| a.meta.get_patch().is_some())
+                .filter_map(|a| {
+                    let key = a.meta.get_data_key()?;
+                    match guard.chain.lookup_primary(&key) {
+                        Some(leaf) if leaf.record == a.raw.event_hash => Some(key),
+                        _ => None,
+                    }
+                })
+                .collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+            keys
+        };
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        let dio = self.dio_mut(session).await?;
+        let mut folded = 0u64;
+        for key in keys {
+            match dio.fold_patches(&key).await {
+                Ok(true) => folded += 1,
+                Ok(false) => {}
+                Err(LoadError(LoadErrorKind::TransformationError(err), _)) => {
+                    debug!("skipped folding row {} - {}", key, err);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        dio.commit().await?;
+        Ok(folded)
+    }
+
     pub(crate) async fn compact_ext(
         inside_async: Arc<RwLock<ChainProtectedAsync>>,
         inside_sync: Arc<StdRwLock<ChainProtectedSync>>,
@@ -70,9 +201,11 @@ impl<'a> Chain {
 
         // prepare
         let mut new_timeline = ChainTimeline {
-            history: BTreeMultiMap::new(),
+            history: inside_async.read().await.chain.timeline.history.fresh(),
             pointers: BinaryTreeIndexer::default(),
             compactors: Vec::new(),
+            statistics: None,
+            tombstones: 0,
         };
 
         // create the flip
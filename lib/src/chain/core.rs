@@ -1,3 +1,6 @@
+use error_chain::bail;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Mutex as StdMutex;
 use std::time::Duration;
 #[allow(unused_imports)]
@@ -15,10 +18,13 @@ use std::sync::Arc;
 use std::sync::RwLock as StdRwLock;
 use tokio::sync::broadcast;
 use tokio::sync::RwLock;
+use tokio::sync::watch;
 
 use crate::conf::ConfAte;
 use crate::conf::MeshAddress;
+use crate::loader::SyncProgress;
 use crate::mesh::BackupMode;
+use crate::mesh::QuotaStatus;
 use crate::meta::*;
 use crate::multi::*;
 use crate::pipe::*;
@@ -27,6 +33,10 @@ use crate::redo::RedoLog;
 use crate::single::*;
 use crate::spec::*;
 use crate::time::TimeKeeper;
+use crate::trust::ChainStatistics;
+use crate::trust::IndexUsage;
+use crate::trust::StatisticsTracker;
+use crate::trust::SignedRootHead;
 use crate::transaction::TransactionScope;
 use crate::trust::ChainHeader;
 use crate::trust::ChainKey;
@@ -62,11 +72,19 @@ pub struct Chain {
     pub(crate) inside_async: Arc<RwLock<ChainProtectedAsync>>,
     #[derivative(Debug = "ignore")]
     pub(crate) pipe: Arc<Box<dyn EventPipe>>,
+    #[derivative(Debug = "ignore")]
+    pub(crate) progress: Arc<PipeProgress>,
     pub(crate) time: Arc<TimeKeeper>,
     pub(crate) exit: broadcast::Sender<()>,
     pub(crate) decache: broadcast::Sender<Vec<PrimaryKey>>,
+    #[derivative(Debug = "ignore")]
+    pub(crate) shedder: Arc<LoadShedder>,
     pub(crate) metrics: Arc<StdMutex<Metrics>>,
     pub(crate) throttle: Arc<StdMutex<Throttle>>,
+    pub(crate) read_only: AtomicBool,
+    pub(crate) temporal: bool,
+    pub(crate) root_head: Arc<StdMutex<Option<SignedRootHead>>>,
+    pub(crate) sync: watch::Receiver<SyncProgress>,
 }
 
 impl<'a> Chain {
@@ -88,6 +106,82 @@ impl<'a> Chain {
         self.remote_addr.as_ref()
     }
 
+    /// Returns how far the local replica is behind the events that have
+    /// been received from the server
+    pub fn lag(&'a self) -> ChainLag {
+        self.shedder.lag()
+    }
+
+    /// Returns how far the synchronization of the history from the server
+    /// has got (chains that are not synchronized are always complete)
+    pub fn sync_progress(&'a self) -> watch::Receiver<SyncProgress> {
+        self.sync.clone()
+    }
+
+    /// Returns how much of the history index of this chain is held in
+    /// memory and how much of it has been moved to disk
+    pub async fn index_usage(&'a self) -> IndexUsage {
+        let guard = self.inside_async.read().await;
+        guard.chain.timeline.history.usage()
+    }
+
+    /// Returns a summary of what is stored in this chain (computed from the
+    /// event headers so no data is decrypted). The first call streams over
+    /// the whole history after which the statistics are maintained as events
+    /// arrive so that repeated calls are cheap (until the next compaction).
+    pub async fn statistics(&'a self) -> ChainStatistics {
+        {
+            let guard = self.inside_async.read().await;
+            let timeline = &guard.chain.timeline;
+            if let Some(statistics) = timeline.statistics.as_ref() {
+                return statistics.snapshot(timeline.pointers.count() as u64);
+            }
+        }
+
+        let mut guard = self.inside_async.write().await;
+        let timeline = &mut guard.chain.timeline;
+        if timeline.statistics.is_none() {
+            let mut statistics = StatisticsTracker::default();
+            for (_, raw) in timeline.history.iter() {
+                match raw.as_header() {
+                    Ok(header) => statistics.feed(&header),
+                    Err(err) => debug!("statistics skipped an event - {}", err),
+                }
+            }
+            timeline.statistics = Some(statistics);
+        }
+        let live_rows = timeline.pointers.count() as u64;
+        timeline
+            .statistics
+            .as_ref()
+            .map(|a| a.snapshot(live_rows))
+            .unwrap_or_default()
+    }
+
+    /// Returns false when the chain was opened read-only (or the server it
+    /// replicates has said it is read-only) and hence can not be modified
+    pub fn is_writable(&'a self) -> bool {
+        self.read_only.load(Ordering::Acquire) == false
+    }
+
+    /// Returns true when the chain is ethereal (held in memory only and
+    /// lost when it is dropped)
+    pub fn is_temporal(&'a self) -> bool {
+        self.temporal
+    }
+
+    pub(crate) fn set_read_only(&'a self, val: bool) {
+        self.read_only.store(val, Ordering::Release);
+    }
+
+    /// Fails with a ChainReadOnly error if the chain can not be modified
+    pub(crate) fn check_writable(&'a self) -> Result<(), CommitError> {
+        if self.is_writable() == false {
+            bail!(CommitErrorKind::ChainReadOnly(self.key.to_string()));
+        }
+        Ok(())
+    }
+
     pub async fn single(&'a self) -> ChainSingleUser<'a> {
         ChainSingleUser::new(self).await
     }
@@ -108,6 +202,24 @@ impl<'a> Chain {
         self.inside_async.read().await.chain.redo.count()
     }
 
+    /// Returns the number of bytes that the redo log of this chain (and its
+    /// archives) occupies on disk
+    pub async fn disk_size(&'a self) -> Result<u64, tokio::io::Error> {
+        self.inside_async.read().await.chain.redo.disk_size().await
+    }
+
+    /// Returns how many times the redo log of this chain was replaced by a
+    /// compacted copy of itself (however the compaction was triggered)
+    pub(crate) async fn redo_flips(&'a self) -> u64 {
+        self.inside_async.read().await.chain.redo.flips()
+    }
+
+    /// Asks the root that this chain is connected to how much of its storage
+    /// quota the chain is using (None for chains that are not remote)
+    pub async fn quota_status(&'a self) -> Result<Option<QuotaStatus>, LoadError> {
+        self.pipe.quota_status().await
+    }
+
     pub async fn flush(&'a self) -> Result<(), tokio::io::Error> {
         Ok(self.inside_async.write().await.chain.flush().await?)
     }
@@ -125,6 +237,7 @@ impl<'a> Chain {
             events: Vec::new(),
             timeout,
             conversation: None,
+            deferrable: false,
         };
 
         // Feed the transaction into the chain
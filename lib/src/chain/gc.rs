@@ -0,0 +1,215 @@
+//! Garbage collection of rows that have been orphaned, i.e. rows whose parent
+//! (or one of its ancestors) has been deleted which leaves them unreachable
+//! while they still take up space in the redo log.
+//!
+//! Reachability is decided purely from the index (the parent metadata is
+//! never encrypted) so rows that the session running the collection can not
+//! decrypt are never mistaken for orphans. A row is only collected when an
+//! ancestor was explicitly deleted (i.e. there is a tombstone for it in the
+//! history) - many rows hang off virtual parents that never exist as rows
+//! of their own (buckets, cursors, file chunks, logs, etc) and these must
+//! never be mistaken for orphans.
+//!
+//! The orphans are tombstoned in batches that are each committed on their
+//! own, an interrupted collection can be resumed by simply running it again
+//! as the orphans are recomputed from the index at the start of every run.
+#![allow(unused_imports)]
+use fxhash::FxHashMap;
+use fxhash::FxHashSet;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tracing::{debug, error, info, trace, warn};
+
+use crate::error::*;
+use crate::header::PrimaryKey;
+use crate::meta::*;
+use crate::session::AteSession;
+
+use super::*;
+
+pub struct GcOptions {
+    /// Number of tombstones committed in each transaction
+    pub batch_size: usize,
+    /// Stops the collection after this many batches (the rest of the
+    /// orphans are collected by the next run)
+    pub max_batches: Option<usize>,
+    /// Stops the collection after the current batch when set
+    pub stop: Option<Arc<AtomicBool>>,
+}
+
+impl Default for GcOptions {
+    fn default() -> GcOptions {
+        GcOptions {
+            batch_size: 500,
+            max_batches: None,
+            stop: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GcReport {
+    /// Number of rows that were checked
+    pub scanned: u64,
+    /// Number of rows that were found to be orphaned
+    pub orphaned: u64,
+    /// Number of orphans that were tombstoned
+    pub collected: u64,
+    /// Number of orphans that the session was not allowed to delete
+    pub denied: u64,
+    /// Number of batches that were committed
+    pub batches: u64,
+    /// False if the collection was interrupted before all the orphans
+    /// were processed
+    pub complete: bool,
+}
+
+impl Chain {
+    /// Returns the number of rows in the chain and all the rows that have
+    /// an ancestor which was deleted
+    pub async fn find_orphans(&self) -> (u64, Vec<PrimaryKey>) {
+        let guard = self.inside_async.read().await;
+        let pointers = &guard.chain.timeline.pointers;
+        let tombstones = guard
+            .chain
+            .timeline
+            .history
+            .iter()
+            .filter_map(|a| a.1.as_header().ok())
+            .filter_map(|a| a.meta.get_tombstone())
+            .collect::<FxHashSet<_>>();
+
+        let keys = pointers.all_keys().map(|a| a.clone()).collect::<Vec<_>>();
+        let mut live: FxHashMap<PrimaryKey, bool> = FxHashMap::default();
+        let mut ret = Vec::new();
+        for key in keys.iter() {
+            // Walk up the tree until we reach a root, a row that has already
+            // been resolved or a parent that no longer exists
+            let mut path = Vec::new();
+            let mut visited = FxHashSet::default();
+            let mut next = key.clone();
+            let is_live = loop {
+                if let Some(a) = live.get(&next) {
+                    break *a;
+                }
+                if pointers.contains_key(&next) == false {
+                    // Parents that were never rows of their own are virtual
+                    break tombstones.contains(&next) == false;
+                }
+                if visited.insert(next.clone()) == false {
+                    // A loop in the parents can not be resolved so its left alone
+                    break true;
+                }
+                path.push(next.clone());
+                match pointers.lookup_parent(&next) {
+                    Some(parent) => next = parent.vec.parent_id,
+                    None => break true,
+                }
+            };
+            for a in path {
+                live.insert(a.clone(), is_live);
+                if is_live == false {
+                    ret.push(a);
+                }
+            }
+        }
+        (keys.len() as u64, ret)
+    }
+
+    /// Tombstones all the orphaned rows in this chain using the write rights
+    /// of the supplied session
+    pub async fn gc_orphans(
+        self: &Arc<Chain>,
+        session: &'_ dyn AteSession,
+    ) -> Result<GcReport, CommitError> {
+        self.gc_orphans_ext(session, GcOptions::default()).await
+    }
+
+    /// Tombstones the orphaned rows in this chain in batches that can be
+    /// interrupted and resumed later
+    pub async fn gc_orphans_ext(
+        self: &Arc<Chain>,
+        session: &'_ dyn AteSession,
+        options: GcOptions,
+    ) -> Result<GcReport, CommitError> {
+        let (scanned, orphans) = self.find_orphans().await;
+        let mut ret = GcReport {
+            scanned,
+            orphaned: orphans.len() as u64,
+            ..GcReport::default()
+        };
+        debug!("gc-orphans: {} orphans in {} rows", ret.orphaned, scanned);
+
+        let batch_size = options.batch_size.max(1);
+        for batch in orphans.chunks(batch_size) {
+            if options.max_batches.map(|a| ret.batches as usize >= a).unwrap_or(false) {
+                return Ok(ret);
+            }
+            if let Some(stop) = options.stop.as_ref() {
+                if stop.load(Ordering::Acquire) {
+                    return Ok(ret);
+                }
+            }
+
+            // If the batch is rejected then the rows are retried one at a time
+            // so that rows the session can not delete do not block the others
+            match self.gc_batch(session, batch).await {
+                Ok(()) => {
+                    ret.collected += batch.len() as u64;
+                }
+                Err(err) if batch.len() > 1 => {
+                    debug!("gc-orphans: batch rejected, retrying row by row - {}", err);
+                    for key in batch {
+                        match self.gc_batch(session, std::slice::from_ref(key)).await {
+                            Ok(()) => ret.collected += 1,
+                            Err(err) if is_denied(&err) => ret.denied += 1,
+                            Err(err) => return Err(err),
+                        }
+                    }
+                }
+                Err(err) if is_denied(&err) => {
+                    ret.denied += 1;
+                }
+                Err(err) => {
+                    return Err(err);
+                }
+            }
+            ret.batches += 1;
+        }
+
+        ret.complete = true;
+        debug!(
+            "gc-orphans: collected={} denied={} batches={}",
+            ret.collected, ret.denied, ret.batches
+        );
+        Ok(ret)
+    }
+
+    async fn gc_batch(
+        self: &Arc<Chain>,
+        session: &'_ dyn AteSession,
+        keys: &[PrimaryKey],
+    ) -> Result<(), CommitError> {
+        let dio = self.dio_mut(session).await?;
+        dio.auto_cancel();
+        for key in keys {
+            if let Err(err) = dio.delete(key).await {
+                dio.cancel();
+                return Err(err.into());
+            }
+        }
+        dio.commit().await
+    }
+}
+
+fn is_denied(err: &CommitError) -> bool {
+    match err.kind() {
+        CommitErrorKind::ValidationError(_) => true,
+        CommitErrorKind::SessionRestricted(_) => true,
+        CommitErrorKind::LintError(_) => true,
+        CommitErrorKind::TransformError(_) => true,
+        _ => false,
+    }
+}
@@ -1,4 +1,3 @@
-use tokio::sync::broadcast;
 use error_chain::bail;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
@@ -24,7 +23,6 @@ use super::workers::*;
 
 pub(super) struct InboxPipe {
     pub(super) inbox: ChainWorkProcessor,
-    pub(super) decache: broadcast::Sender<Vec<PrimaryKey>>,
     pub(super) locks: StdMutex<FxHashSet<PrimaryKey>>,
     pub(super) inside_async: Arc<RwLock<ChainProtectedAsync>>,
 }
@@ -32,20 +30,9 @@ pub(super) struct InboxPipe {
 #[async_trait]
 impl EventPipe for InboxPipe {
     async fn feed(&self, work: ChainWork) -> Result<(), CommitError> {
-        // Prepare the work and submit it
-        let decache = work
-            .trans
-            .events
-            .iter()
-            .filter_map(|a| a.meta.get_data_key())
-            .collect::<Vec<_>>();
-
-        // Submit the work
+        // Submit the work (which also clears the caches)
         let ret = self.inbox.process(work).await?;
 
-        // Clear all the caches
-        let _ = self.decache.send(decache);
-
         // Success
         Ok(ret)
     }
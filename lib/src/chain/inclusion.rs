@@ -0,0 +1,106 @@
+use error_chain::bail;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::crypto::AteHash;
+use crate::crypto::PrivateSignKey;
+use crate::error::*;
+use crate::trust::inclusion::*;
+use crate::trust::InclusionProof;
+use crate::trust::SignedRootHead;
+
+use super::*;
+
+impl<'a> Chain {
+    /// Returns the most recent signed root head of this chain (if it has
+    /// been checkpointed)
+    pub fn root_head(&'a self) -> Option<SignedRootHead> {
+        self.root_head.lock().unwrap().clone()
+    }
+
+    /// Builds the hash tree over the current history of the chain and signs
+    /// its root head, the signed head becomes the one that inclusion proofs
+    /// are generated against. Mesh roots do this periodically when they are
+    /// configured with a checkpoint key.
+    pub async fn checkpoint(
+        &'a self,
+        key: &PrivateSignKey,
+    ) -> Result<SignedRootHead, InclusionError> {
+        let leaves = {
+            let guard = self.inside_async.read().await;
+            history_leaves(&guard.chain.timeline.history)
+        };
+        let timestamp = self
+            .time
+            .current_timestamp()
+            .map(|a| a.time_since_epoch_ms)
+            .unwrap_or_default();
+
+        let head = root_head(&self.key, &leaves[..], timestamp);
+        let ret = SignedRootHead::sign(head, key)?;
+        debug!(
+            "checkpointed {} (tree_size={}, root={})",
+            self.key, ret.head.tree_size, ret.head.root_hash
+        );
+        self.root_head.lock().unwrap().replace(ret.clone());
+        Ok(ret)
+    }
+
+    /// Returns true if events were added to (or removed from) the history
+    /// since the last checkpoint
+    pub(crate) async fn needs_checkpoint(&'a self) -> bool {
+        let tree_size = match self.root_head() {
+            Some(a) => a.head.tree_size,
+            None => return true,
+        };
+        let guard = self.inside_async.read().await;
+        guard.chain.timeline.history.len() as u64 != tree_size
+    }
+
+    /// Generates a proof that the event is part of the history of this
+    /// chain as of its latest signed root head.
+    ///
+    /// The hash tree is computed on demand from the history which costs a
+    /// hash per event. Events added after the checkpoint are not covered
+    /// until the next checkpoint and if the order of the history has since
+    /// changed (compaction or late arrivals) the chain must be checkpointed
+    /// again before proofs can be generated.
+    pub async fn prove_inclusion(
+        &'a self,
+        event_hash: AteHash,
+    ) -> Result<InclusionProof, InclusionError> {
+        let root_head = match self.root_head() {
+            Some(a) => a,
+            None => bail!(InclusionErrorKind::NoRootHead),
+        };
+        let tree_size = root_head.head.tree_size;
+
+        let leaves = {
+            let guard = self.inside_async.read().await;
+            guard
+                .chain
+                .timeline
+                .history
+                .iter()
+                .take(tree_size as usize)
+                .map(|(_, h)| h.event_hash)
+                .collect::<Vec<_>>()
+        };
+        let index = match leaves.iter().position(|a| *a == event_hash) {
+            Some(a) => a,
+            None => bail!(InclusionErrorKind::EventNotFound(event_hash)),
+        };
+
+        let leaves = leaves.iter().map(leaf_hash).collect::<Vec<_>>();
+        if leaves.len() as u64 != tree_size || tree_root(&leaves[..]) != root_head.head.root_hash {
+            bail!(InclusionErrorKind::StaleRootHead(tree_size));
+        }
+
+        Ok(InclusionProof {
+            event_hash,
+            leaf_index: index as u64,
+            path: tree_path(index, &leaves[..]),
+            root_head,
+        })
+    }
+}
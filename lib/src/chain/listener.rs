@@ -3,10 +3,14 @@ use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use crate::event::*;
 
-use tokio::sync::mpsc;
+use tokio::sync::broadcast;
+
+/// Listeners never hold up the notifications of a chain, once this many
+/// events are waiting for a listener the oldest ones are dropped
+pub(crate) const LISTENER_BUFFER_SIZE: usize = 100;
 
 #[derive(Debug)]
 pub(crate) struct ChainListener {
     pub(crate) id: u64,
-    pub(crate) sender: mpsc::Sender<EventWeakData>,
+    pub(crate) sender: broadcast::Sender<EventWeakData>,
 }
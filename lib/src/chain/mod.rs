@@ -1,21 +1,40 @@
+#[cfg(feature = "enable_local_fs")]
+mod archive;
 mod backup;
 mod compact;
 mod core;
+mod gc;
 mod inbox_pipe;
+mod inclusion;
 mod listener;
 mod new;
+mod progress;
 mod protected_async;
 mod protected_sync;
+#[cfg(feature = "enable_local_fs")]
+mod replay;
 #[cfg(feature = "enable_rotate")]
 mod rotate;
+mod shedding;
+mod test;
 mod workers;
 
+#[cfg(feature = "enable_local_fs")]
+pub use archive::*;
 pub use self::core::*;
 pub use compact::*;
+pub use gc::*;
 pub(crate) use listener::*;
 pub use new::*;
+pub use progress::PipeDiagnostics;
+#[cfg(feature = "enable_local_fs")]
+pub use replay::*;
+pub(crate) use progress::*;
 pub(crate) use protected_async::*;
 pub(crate) use protected_sync::*;
+pub(crate) use shedding::LoadShedder;
+pub use shedding::ChainLag;
+pub use shedding::InboundBudget;
 pub(crate) use workers::*;
 
 pub use crate::trust::ChainKey;
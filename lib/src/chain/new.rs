@@ -3,7 +3,6 @@ use error_chain::bail;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use tracing_futures::Instrument;
 
-use btreemultimap::BTreeMultiMap;
 use multimap::MultiMap;
 use tokio::sync::broadcast;
 
@@ -35,6 +34,10 @@ use super::inbox_pipe::*;
 use super::workers::ChainWorkProcessor;
 use super::*;
 
+/// Number of events that are processed at a time when a chain is loaded
+/// in streaming mode
+const LOAD_BATCH_SIZE: usize = 1000;
+
 impl<'a> Chain {
     #[allow(dead_code)]
     pub(crate) async fn new(
@@ -71,7 +74,7 @@ impl<'a> Chain {
             truncate: builder.truncate,
             temporal: builder.temporal,
             integrity: load_integrity,
-            read_only: false,
+            read_only: builder.read_only,
         };
         let compact_mode = builder.cfg_ate.compact_mode;
         let compact_bootstrap = builder.cfg_ate.compact_bootstrap;
@@ -114,36 +117,6 @@ impl<'a> Chain {
         #[cfg(not(feature = "enable_local_fs"))]
         let redo_log = { async move { RedoLog::open(header_bytes).await } };
 
-        // While the events are streamed in we build a list of all the event headers
-        // but we strip off the data itself
-        let process_local = async move {
-            #[allow(unused_mut)]
-            let mut headers = Vec::new();
-            #[cfg(feature = "enable_local_fs")]
-            while let Some(result) = rx.recv().await {
-                headers.push(result.header.as_header()?);
-            }
-            Result::<Vec<EventHeader>, SerializationError>::Ok(headers)
-        };
-
-        // Join the redo log thread earlier after the events were successfully streamed in
-        let (redo_log, process_local) = futures::join!(redo_log, process_local);
-        let headers = process_local?;
-        let redo_log = redo_log?;
-
-        // Construnct the chain-of-trust on top of the redo-log
-        let chain = ChainOfTrust {
-            debug_id: fastrand::u64(..),
-            key: key.clone(),
-            redo: redo_log,
-            timeline: ChainTimeline {
-                history: BTreeMultiMap::new(),
-                pointers: BinaryTreeIndexer::default(),
-                compactors: builder.compactors,
-            },
-            metrics: Arc::clone(&builder.metrics),
-        };
-
         // Construct all the protected fields that are behind a synchronous critical section
         // that does not wait
         let mut inside_sync = ChainProtectedSync {
@@ -169,6 +142,73 @@ impl<'a> Chain {
         // Wrap the sync object
         let inside_sync = Arc::new(StdRwLock::new(inside_sync));
 
+        // The timeline indexes all the events in the chain-of-trust
+        let mut timeline = ChainTimeline {
+            history: ChainHistory::new(&builder.cfg_ate, &key, builder.temporal),
+            pointers: BinaryTreeIndexer::default(),
+            compactors: builder.compactors,
+            statistics: None,
+            tombstones: 0,
+        };
+
+        // Events are processed within the same conversation
+        let mut conversation = ConversationSession::default();
+        if let TrustMode::Centralized(_) = load_integrity {
+            conversation.weaken_validation = true;
+        }
+        let conversation = Arc::new(conversation);
+
+        // While the events are streamed in we build a list of all the event headers
+        // but we strip off the data itself (in streaming mode the headers are instead
+        // processed in batches as they arrive so they never all sit in memory)
+        let load_streaming = builder.cfg_ate.load_streaming;
+        let process_local = {
+            let timeline = &mut timeline;
+            let inside_sync = &inside_sync;
+            let conversation = &conversation;
+            let metrics = &builder.metrics;
+            async move {
+                #[allow(unused_mut)]
+                let mut headers = Vec::new();
+                #[allow(unused_mut)]
+                let mut errors = ProcessError::default();
+                #[cfg(feature = "enable_local_fs")]
+                while let Some(result) = rx.recv().await {
+                    headers.push(result.header.as_header()?);
+                    if load_streaming && headers.len() >= LOAD_BATCH_SIZE {
+                        let mut sync = inside_sync.write().unwrap();
+                        let mut metrics = metrics.lock().unwrap();
+                        for header in headers.drain(..) {
+                            sync.process_header(&header, Some(conversation), &mut errors);
+                            metrics.chain_size += header.raw.meta_bytes.len() as u64;
+                            metrics.chain_size += header.raw.data_size as u64;
+                            timeline.add_history(header);
+                        }
+                    }
+                }
+                Result::<(Vec<EventHeader>, ProcessError), SerializationError>::Ok((headers, errors))
+            }
+        };
+
+        // Join the redo log thread earlier after the events were successfully streamed in
+        let (redo_log, process_local) = futures::join!(redo_log, process_local);
+        let (headers, streamed_errors) = process_local?;
+        let redo_log = redo_log.map_err(ChainCreationError::from_redo_log)?;
+        if let Err(err) = streamed_errors.as_result() {
+            if allow_process_errors == false {
+                return Err(err.into());
+            }
+        }
+
+        // Construnct the chain-of-trust on top of the redo-log
+        let chain = ChainOfTrust {
+            debug_id: fastrand::u64(..),
+            key: key.clone(),
+            redo: redo_log,
+            timeline,
+            metrics: Arc::clone(&builder.metrics),
+        };
+
         // Create an exit watcher
         let (exit_tx, _) = broadcast::channel(1);
 
@@ -180,6 +220,7 @@ impl<'a> Chain {
             disable_new_roots: false,
             sync_tolerance: builder.cfg_ate.sync_tolerance,
             listeners: MultiMap::new(),
+            cursor_listeners: Vec::new(),
             is_shutdown: false,
             integrity: load_integrity,
         };
@@ -193,12 +234,7 @@ impl<'a> Chain {
             }
         }
 
-        // Process all the events in the chain-of-trust
-        let mut conversation = ConversationSession::default();
-        if let TrustMode::Centralized(_) = load_integrity {
-            conversation.weaken_validation = true;
-        }
-        let conversation = Arc::new(conversation);
+        // Process all the events in the chain-of-trust (that were not already streamed)
         if let Err(err) =
             inside_async.process(inside_sync.write().unwrap(), headers, Some(&conversation))
         {
@@ -224,16 +260,23 @@ impl<'a> Chain {
         let worker_inside_async = Arc::clone(&inside_async);
         let worker_inside_sync = Arc::clone(&inside_sync);
 
-        // background thread - receives events and processes them
-        let processor = ChainWorkProcessor::new(worker_inside_async, worker_inside_sync, compact_tx);
-
         // decache subscription
         let (decache_tx, _) = broadcast::channel(1000);
 
+        // Cache maintenance that is performed after events are processed
+        let shedder = Arc::new(LoadShedder::new(decache_tx.clone(), Arc::clone(&inside_async)));
+
+        // background thread - receives events and processes them
+        let processor = ChainWorkProcessor::new(
+            worker_inside_async,
+            worker_inside_sync,
+            compact_tx,
+            Arc::clone(&shedder),
+        );
+
         // The inbox pipe intercepts requests to and processes them
         let mut pipe: Arc<Box<dyn EventPipe>> = Arc::new(Box::new(InboxPipe {
             inbox: processor,
-            decache: decache_tx.clone(),
             inside_async: inside_async.clone(),
             locks: StdMutex::new(FxHashSet::default()),
         }));
@@ -241,6 +284,13 @@ impl<'a> Chain {
             pipe = Arc::new(Box::new(DuelPipe::new(second, pipe)));
         };
 
+        // The progress of every transaction is tracked so that stuck pipes can be detected
+        let progress = Arc::new(PipeProgress::new());
+        let pipe: Arc<Box<dyn EventPipe>> = Arc::new(Box::new(ProgressPipe {
+            progress: Arc::clone(&progress),
+            next: pipe,
+        }));
+
         // Create the NTP worker thats needed to build the timeline
         let tolerance = builder.configured_for.ntp_tolerance();
         let time = Arc::new(TimeKeeper::new(&builder.cfg_ate, tolerance).await?);
@@ -256,21 +306,28 @@ impl<'a> Chain {
             inside_sync,
             inside_async,
             pipe,
+            progress,
             time,
             exit: exit_tx.clone(),
             decache: decache_tx,
+            shedder,
             metrics: Arc::clone(&builder.metrics),
             throttle: Arc::clone(&builder.throttle),
+            read_only: std::sync::atomic::AtomicBool::new(builder.read_only),
+            temporal: builder.temporal,
+            root_head: Arc::new(StdMutex::new(None)),
+            sync: tokio::sync::watch::channel(SyncProgress::completed()).1,
         };
 
-        // If we are to compact the log on bootstrap then do so
+        // If we are to compact the log on bootstrap then do so (read-only
+        // logs are never compacted as that would rewrite them)
         debug!("compact-now: {}", compact_bootstrap);
-        if compact_bootstrap {
+        if compact_bootstrap && builder.read_only == false {
             chain.compact().await?;
         }
 
         // Start the compactor worker thread on the chain
-        if builder.cfg_ate.compact_mode != CompactMode::Never {
+        if builder.cfg_ate.compact_mode != CompactMode::Never && builder.read_only == false {
             debug!("compact-mode-on: {}", builder.cfg_ate.compact_mode);
 
             let worker_exit = exit_tx.subscribe();
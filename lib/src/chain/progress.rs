@@ -0,0 +1,252 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use fxhash::FxHashSet;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::broadcast;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::crypto::AteHash;
+use crate::error::*;
+use crate::header::PrimaryKey;
+use crate::mesh::QuotaStatus;
+use crate::pipe::*;
+use crate::transaction::*;
+
+use super::*;
+
+/// Tracks the transactions that are being fed through the pipes of a chain
+/// so that a watchdog can tell when the chain has stopped making progress
+pub(crate) struct PipeProgress {
+    state: StdMutex<PipeProgressState>,
+    abort: broadcast::Sender<()>,
+}
+
+struct PipeProgressState {
+    next_id: u64,
+    pending: BTreeMap<u64, PendingFeed>,
+    last_progress: Instant,
+    locks: FxHashSet<PrimaryKey>,
+}
+
+struct PendingFeed {
+    started: Instant,
+    events: usize,
+    #[cfg(feature = "enable_dio_backtrace")]
+    backtrace: backtrace::Backtrace,
+}
+
+/// Snapshot of the transactions that are pending on a chain which is
+/// reported when the chain appears to be stuck
+#[derive(Debug, Clone)]
+pub struct PipeDiagnostics {
+    /// Number of transactions that are waiting for their feed to complete
+    pub pending: usize,
+    /// Number of events in the pending transactions
+    pub pending_events: usize,
+    /// How long the oldest pending transaction has been waiting
+    pub oldest: Option<Duration>,
+    /// Time since a transaction last completed
+    pub since_progress: Duration,
+    /// Rows that are currently locked through the pipes of the chain
+    pub locks: Vec<PrimaryKey>,
+    /// True if the chain state is currently held by another task
+    pub state_locked: bool,
+    /// Backtrace of where the oldest pending transaction was started (only
+    /// captured when the `enable_dio_backtrace` feature is on)
+    pub backtrace: Option<String>,
+}
+
+impl std::fmt::Display for PipeDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "pending transactions: {} ({} events)",
+            self.pending, self.pending_events
+        )?;
+        if let Some(oldest) = self.oldest {
+            writeln!(f, "oldest transaction:   {}ms", oldest.as_millis())?;
+        }
+        writeln!(f, "since progress:       {}ms", self.since_progress.as_millis())?;
+        writeln!(f, "state locked:         {}", self.state_locked)?;
+        write!(f, "lock holders:        ")?;
+        for key in self.locks.iter() {
+            write!(f, " {}", key)?;
+        }
+        writeln!(f)?;
+        if let Some(backtrace) = self.backtrace.as_ref() {
+            writeln!(f, "backtrace:\n{}", backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+impl PipeProgress {
+    pub(crate) fn new() -> PipeProgress {
+        let (abort, _) = broadcast::channel(1);
+        PipeProgress {
+            state: StdMutex::new(PipeProgressState {
+                next_id: 0,
+                pending: BTreeMap::new(),
+                last_progress: Instant::now(),
+                locks: FxHashSet::default(),
+            }),
+            abort,
+        }
+    }
+
+    fn begin(&self, work: &ChainWork) -> (u64, Instant) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        if state.pending.is_empty() {
+            state.last_progress = now;
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        state.pending.insert(
+            id,
+            PendingFeed {
+                started: now,
+                events: work.trans.events.len(),
+                #[cfg(feature = "enable_dio_backtrace")]
+                backtrace: backtrace::Backtrace::new(),
+            },
+        );
+        (id, now)
+    }
+
+    fn end(&self, id: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.pending.remove(&id);
+        state.last_progress = Instant::now();
+    }
+
+    /// Returns true if transactions are pending and none of them have
+    /// completed for at least the threshold
+    pub(crate) fn is_stuck(&self, threshold: Duration) -> bool {
+        let state = self.state.lock().unwrap();
+        state.pending.is_empty() == false && state.last_progress.elapsed() >= threshold
+    }
+
+    /// Aborts all the pending transactions (they fail with a timeout) and
+    /// returns how many there were
+    pub(crate) fn abort_pending(&self) -> usize {
+        let pending = self.state.lock().unwrap().pending.len();
+        let _ = self.abort.send(());
+        pending
+    }
+
+    pub(crate) fn diagnostics(&self) -> PipeDiagnostics {
+        let state = self.state.lock().unwrap();
+        let oldest = state.pending.values().next();
+        PipeDiagnostics {
+            pending: state.pending.len(),
+            pending_events: state.pending.values().map(|a| a.events).sum(),
+            oldest: oldest.map(|a| a.started.elapsed()),
+            since_progress: state.last_progress.elapsed(),
+            locks: state.locks.iter().cloned().collect(),
+            state_locked: false,
+            #[cfg(feature = "enable_dio_backtrace")]
+            backtrace: oldest.map(|a| format!("{:?}", a.backtrace)),
+            #[cfg(not(feature = "enable_dio_backtrace"))]
+            backtrace: None,
+        }
+    }
+}
+
+/// Pipe at the head of every chain that records the progress of the
+/// transactions fed through it
+pub(crate) struct ProgressPipe {
+    pub(crate) progress: Arc<PipeProgress>,
+    pub(crate) next: Arc<Box<dyn EventPipe>>,
+}
+
+#[async_trait]
+impl EventPipe for ProgressPipe {
+    async fn is_connected(&self) -> bool {
+        self.next.is_connected().await
+    }
+
+    async fn connect(
+        &self,
+    ) -> Result<tokio::sync::mpsc::Receiver<ConnectionStatusChange>, ChainCreationError> {
+        self.next.connect().await
+    }
+
+    async fn on_disconnect(&self) -> Result<(), CommsError> {
+        self.next.on_disconnect().await
+    }
+
+    async fn on_read_only(&self) -> Result<(), CommsError> {
+        self.next.on_read_only().await
+    }
+
+    async fn on_reconnect_hint(&self) -> Result<(), CommsError> {
+        self.next.on_reconnect_hint().await
+    }
+
+    async fn feed(&self, work: ChainWork) -> Result<(), CommitError> {
+        let mut abort = self.progress.abort.subscribe();
+        let (id, started) = self.progress.begin(&work);
+        let ret = tokio::select! {
+            ret = self.next.feed(work) => ret,
+            _ = abort.recv() => {
+                Err(CommitErrorKind::Timeout(format!("{}ms", started.elapsed().as_millis())).into())
+            }
+        };
+        self.progress.end(id);
+        ret
+    }
+
+    async fn load_many(&self, leafs: Vec<AteHash>) -> Result<Vec<Option<Bytes>>, LoadError> {
+        self.next.load_many(leafs).await
+    }
+
+    async fn quota_status(&self) -> Result<Option<QuotaStatus>, LoadError> {
+        self.next.quota_status().await
+    }
+
+    async fn prime(&self, records: Vec<(AteHash, Option<Bytes>)>) -> Result<(), CommsError> {
+        self.next.prime(records).await
+    }
+
+    async fn try_lock(&self, key: PrimaryKey) -> Result<bool, CommitError> {
+        let ret = self.next.try_lock(key.clone()).await?;
+        if ret {
+            self.progress.state.lock().unwrap().locks.insert(key);
+        }
+        Ok(ret)
+    }
+
+    async fn unlock(&self, key: PrimaryKey) -> Result<(), CommitError> {
+        self.progress.state.lock().unwrap().locks.remove(&key);
+        self.next.unlock(key).await
+    }
+
+    fn unlock_local(&self, key: PrimaryKey) -> Result<(), CommitError> {
+        self.progress.state.lock().unwrap().locks.remove(&key);
+        self.next.unlock_local(key)
+    }
+
+    fn set_next(&mut self, next: Arc<Box<dyn EventPipe>>) {
+        let _ = std::mem::replace(&mut self.next, next);
+    }
+
+    async fn conversation(&self) -> Option<Arc<ConversationSession>> {
+        self.next.conversation().await
+    }
+}
+
+impl Chain {
+    /// Returns a snapshot of the transactions that are waiting to be fed
+    /// through the pipes of this chain
+    pub fn pipe_diagnostics(&self) -> PipeDiagnostics {
+        let mut ret = self.progress.diagnostics();
+        ret.state_locked = self.inside_async.try_write().is_err();
+        ret
+    }
+}
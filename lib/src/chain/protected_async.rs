@@ -2,11 +2,13 @@ use error_chain::bail;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
+use crate::crypto::AteHash;
 use crate::error::*;
 use crate::event::*;
 use crate::redo::LogWritable;
 use crate::transaction::*;
 
+use fxhash::FxHashMap;
 use fxhash::FxHashSet;
 use multimap::MultiMap;
 use std::ops::*;
@@ -30,6 +32,9 @@ pub(crate) struct ChainProtectedAsync {
     pub(crate) disable_new_roots: bool,
     pub(crate) sync_tolerance: Duration,
     pub(crate) listeners: MultiMap<MetaCollection, ChainListener>,
+    /// Cursor subscriptions are woken up whenever events are added to the
+    /// chain (they read the events themselves from the history)
+    pub(crate) cursor_listeners: Vec<ChainListener>,
     pub(crate) is_shutdown: bool,
     pub(crate) integrity: TrustMode,
 }
@@ -44,21 +49,7 @@ impl ChainProtectedAsync {
         let mut ret = ProcessError::default();
 
         for header in headers.into_iter() {
-            if let Result::Err(err) = sync.validate_event(&header, conversation) {
-                ret.validation_errors.push(err);
-            }
-
-            for indexer in sync.indexers.iter_mut() {
-                if let Err(err) = indexer.feed(&header, conversation) {
-                    ret.sink_errors.push(err);
-                }
-            }
-            for plugin in sync.plugins.iter_mut() {
-                if let Err(err) = plugin.feed(&header, conversation) {
-                    ret.sink_errors.push(err);
-                }
-            }
-
+            sync.process_header(&header, conversation, &mut ret);
             self.chain.add_history(header);
         }
 
@@ -89,12 +80,38 @@ impl ChainProtectedAsync {
         evts: &Vec<EventWeakData>,
         conversation: Option<&Arc<ConversationSession>>,
     ) -> Result<(), CommitError> {
+        let headers = evts
+            .iter()
+            .map(|evt| evt.as_header())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Patches must be applied on top of the current version of their row
+        // (which may also be an earlier event in this same batch)
+        let mut versions = FxHashMap::<PrimaryKey, AteHash>::default();
+        for header in headers.iter() {
+            let key = match header.meta.get_data_key() {
+                Some(a) => a,
+                None => continue,
+            };
+            if let Some(patch) = header.meta.get_patch() {
+                let current = match versions.get(&key) {
+                    Some(a) => Some(a.clone()),
+                    None => self.chain.lookup_primary(&key).map(|a| a.record),
+                };
+                if current != Some(patch.base) {
+                    bail!(CommitErrorKind::PatchConflict(key.to_string()));
+                }
+            }
+            if header.raw.data_hash.is_some() {
+                versions.insert(key, header.raw.event_hash);
+            }
+        }
+
         let mut errors = Vec::new();
         let mut validated_evts = Vec::new();
         {
             let mut sync = sync.write().unwrap();
-            for evt in evts.iter() {
-                let header = evt.as_header()?;
+            for (evt, header) in evts.iter().zip(headers.into_iter()) {
 
                 #[cfg(feature = "enable_verbose")]
                 trace!(
@@ -154,7 +171,7 @@ impl ChainProtectedAsync {
     pub fn range<'a, R>(
         &'a self,
         range: R,
-    ) -> impl DoubleEndedIterator<Item = (&'a ChainTimestamp, &'a EventHeaderRaw)>
+    ) -> impl Iterator<Item = (ChainTimestamp, EventHeaderRaw)> + 'a
     where
         R: RangeBounds<ChainTimestamp>,
     {
@@ -170,14 +187,14 @@ impl ChainProtectedAsync {
     {
         let mut ret = self.range(range).map(|e| e.0).collect::<Vec<_>>();
         ret.dedup();
-        ret.into_iter().map(|a| a.clone())
+        ret.into_iter()
     }
 
     #[allow(dead_code)]
     pub fn range_values<'a, R>(
         &'a self,
         range: R,
-    ) -> impl DoubleEndedIterator<Item = &'a EventHeaderRaw>
+    ) -> impl Iterator<Item = EventHeaderRaw> + 'a
     where
         R: RangeBounds<ChainTimestamp>,
     {
@@ -185,6 +202,21 @@ impl ChainProtectedAsync {
     }
 
     pub(crate) async fn notify(lock: Arc<RwLock<ChainProtectedAsync>>, evts: Vec<EventWeakData>) {
+        // Wake up the cursor subscriptions (a single event is enough for them)
+        if let Some(evt) = evts.last() {
+            let mut closed = false;
+            {
+                let lock = lock.read().await;
+                for target in lock.cursor_listeners.iter() {
+                    closed |= target.sender.send(evt.clone()).is_err();
+                }
+            }
+            if closed {
+                let mut lock = lock.write().await;
+                lock.cursor_listeners.retain(|a| a.sender.receiver_count() > 0);
+            }
+        }
+
         // Build a map of event parents that will be used in the BUS notifications
         let mut notify_map = MultiMap::new();
         for evt in evts {
@@ -203,8 +235,8 @@ impl ChainProtectedAsync {
                     if let Some(targets) = lock.listeners.get_vec(&k) {
                         for target in targets {
                             for evt in v.iter() {
-                                match target.sender.send(evt.clone()).await {
-                                    Ok(()) => {}
+                                match target.sender.send(evt.clone()) {
+                                    Ok(_) => {}
                                     Err(_) => {
                                         to_remove.insert(k.clone(), target.id);
                                         break;
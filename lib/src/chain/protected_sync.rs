@@ -30,6 +30,30 @@ pub(crate) struct ChainProtectedSync {
 }
 
 impl ChainProtectedSync {
+    /// Validates an event that is being loaded and feeds it to the indexers
+    /// and plugins, any errors are collected rather than returned
+    pub(super) fn process_header(
+        &mut self,
+        header: &EventHeader,
+        conversation: Option<&Arc<ConversationSession>>,
+        ret: &mut ProcessError,
+    ) {
+        if let Result::Err(err) = self.validate_event(header, conversation) {
+            ret.validation_errors.push(err);
+        }
+
+        for indexer in self.indexers.iter_mut() {
+            if let Err(err) = indexer.feed(header, conversation) {
+                ret.sink_errors.push(err);
+            }
+        }
+        for plugin in self.plugins.iter_mut() {
+            if let Err(err) = plugin.feed(header, conversation) {
+                ret.sink_errors.push(err);
+            }
+        }
+    }
+
     #[allow(dead_code)]
     pub(super) fn validate_event(
         &self,
@@ -144,6 +168,17 @@ impl ChainProtectedSync {
         Ok(ValidationResult::Allow)
     }
 
+    /// Drives the validators (and plugins) that check the time of events
+    /// from the clock of a replay
+    pub(crate) fn set_replay_clock(&mut self, clock: Option<crate::time::ReplayClock>) {
+        for val in self.validators.iter_mut() {
+            val.set_replay_clock(clock.clone());
+        }
+        for val in self.plugins.iter_mut() {
+            val.set_replay_clock(clock.clone());
+        }
+    }
+
     pub fn set_integrity_mode(&mut self, mode: TrustMode) {
         debug!("switching to {}", mode);
 
@@ -0,0 +1,266 @@
+//! Deterministic replay of the redo log of a chain, used to reproduce bugs
+//! from a copy of a production log.
+//!
+//! A normal load interleaves the processing of events with timers, the NTP
+//! worker and background tasks which means two loads of the same log can
+//! pass through different in-memory states. A replay instead processes the
+//! events strictly in log order on the calling task, the validators and
+//! plugins are invoked in the order they were added to the builder (which is
+//! recorded in the report) and the time they see is driven by the timestamps
+//! of the events rather than the wall clock.
+//!
+//! To track down where two runs (e.g. two versions of the code) diverge:
+//!
+//! 1. Copy the redo log files of the chain into a local `log_path`.
+//! 2. Replay it in both runs with `snapshot_every` set and compare the
+//!    `index_checksum` of the snapshots, the first snapshot that differs
+//!    brackets the divergence.
+//! 3. Replay again with a smaller `snapshot_every` (or with `stop_after`)
+//!    to narrow it down to a single event.
+//! 4. Open the same log with `RedoLog::open` which returns every event in
+//!    log order (with the lookup of where it is stored) so the events around
+//!    the divergence can be inspected, the index of a snapshot is also the
+//!    position of the event in that list.
+#![allow(unused_imports)]
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::conf::*;
+use crate::crypto::AteHash;
+use crate::error::*;
+use crate::index::*;
+use crate::loader::*;
+use crate::redo::*;
+use crate::spec::SerializationFormat;
+use crate::spec::TrustMode;
+use crate::time::ChainTimestamp;
+use crate::time::ReplayClock;
+use crate::transaction::*;
+use crate::trust::*;
+
+use super::*;
+
+/// State of a replay after a number of events, two replays of the same log
+/// must produce identical snapshots
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReplaySnapshot {
+    /// Number of events that have been replayed
+    pub events: u64,
+    /// Number of rows that exist in the chain
+    pub rows: u64,
+    /// Number of events that the validators rejected
+    pub rejected: u64,
+    /// Time of the replay clock (the latest timestamp seen)
+    pub clock: ChainTimestamp,
+    /// Checksum of the primary index
+    pub index_checksum: AteHash,
+    /// Hash of the last event that was replayed
+    pub last_event: Option<AteHash>,
+}
+
+/// Event that was rejected during the replay
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReplayRejection {
+    /// Position of the event in the log
+    pub index: u64,
+    pub event_hash: AteHash,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+    /// Names of the validators and plugins in the order they were invoked
+    /// on every event
+    pub hooks: Vec<String>,
+    pub snapshots: Vec<ReplaySnapshot>,
+    pub rejected: Vec<ReplayRejection>,
+}
+
+pub struct ReplayOptions {
+    /// Number of events between snapshots (zero only takes the final one)
+    pub snapshot_every: u64,
+    /// Stops the replay after this many events
+    pub stop_after: Option<u64>,
+    /// Invoked with every snapshot as it is taken
+    pub on_snapshot: Option<Box<dyn FnMut(&ReplaySnapshot) + Send>>,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> ReplayOptions {
+        ReplayOptions {
+            snapshot_every: 1000,
+            stop_after: None,
+            on_snapshot: None,
+        }
+    }
+}
+
+impl Chain {
+    /// Replays the redo log of a chain (which is opened read-only) through
+    /// the validators, indexers and plugins of the builder, the loader (if
+    /// any) is fed the events in the same order as they are replayed.
+    pub async fn replay_from(
+        builder: &ChainBuilder,
+        key: &ChainKey,
+        mut loader: Option<Box<dyn Loader>>,
+        mut options: ReplayOptions,
+    ) -> Result<ReplayReport, ChainCreationError> {
+        debug!("replay: {}", key);
+        let builder = builder.clone();
+        let cfg_ate = builder.cfg_ate.clone();
+        let integrity = builder.load_integrity;
+
+        // Read all the events before any are processed so that nothing is
+        // interleaved with the reading of the log
+        let flags = OpenFlags {
+            read_only: true,
+            truncate: false,
+            temporal: builder.temporal,
+            integrity,
+        };
+        let header_bytes = SerializationFormat::Json
+            .serialize(&ChainHeader::default())
+            .map_err(SerializationError::from)?;
+        let (_redo_log, events) = RedoLog::open(&cfg_ate, key, flags, header_bytes)
+            .await
+            .map_err(ChainCreationError::from_redo_log)?;
+
+        let mut sync = ChainProtectedSync {
+            sniffers: Vec::new(),
+            services: Vec::new(),
+            indexers: builder.indexers,
+            plugins: builder.plugins,
+            linters: builder.linters,
+            validators: builder.validators,
+            transformers: builder.transformers,
+            default_session: builder.session,
+            integrity,
+        };
+        if let Some(tree) = builder.tree {
+            sync.plugins.push(Box::new(tree));
+        }
+        sync.set_integrity_mode(integrity);
+
+        // The validators read the time from the events instead of the wall clock
+        let clock = ReplayClock::default();
+        sync.set_replay_clock(Some(clock.clone()));
+
+        let mut timeline = ChainTimeline {
+            history: ChainHistory::new(&cfg_ate, key, builder.temporal),
+            pointers: BinaryTreeIndexer::default(),
+            compactors: Vec::new(),
+            statistics: None,
+            tombstones: 0,
+        };
+
+        let mut conversation = ConversationSession::default();
+        if let TrustMode::Centralized(_) = integrity {
+            conversation.weaken_validation = true;
+        }
+        let conversation = Arc::new(conversation);
+
+        let mut ret = ReplayReport::default();
+        ret.hooks = sync
+            .validators
+            .iter()
+            .map(|a| a.validator_name().to_string())
+            .chain(sync.plugins.iter().map(|a| a.validator_name().to_string()))
+            .collect();
+
+        if let Some(loader) = loader.as_mut() {
+            loader.start_of_history(events.len()).await;
+        }
+
+        let mut replayed = 0u64;
+        let mut last_event: Option<AteHash> = None;
+        for data in events.into_iter() {
+            if options.stop_after.map(|a| replayed >= a).unwrap_or(false) {
+                break;
+            }
+            let header = data.header.as_header()?;
+            if let Some(time) = header.meta.get_timestamp() {
+                clock.advance(time.time_since_epoch_ms);
+            }
+
+            // Events that the validators reject never make it onto a live
+            // chain so they are left out of the timeline (and the loader)
+            let accepted = match sync.validate_event(&header, Some(&conversation)) {
+                Ok(_) => {
+                    let mut errors = Vec::new();
+                    for indexer in sync.indexers.iter_mut() {
+                        if let Err(err) = indexer.feed(&header, Some(&conversation)) {
+                            errors.push(err.to_string());
+                        }
+                    }
+                    for plugin in sync.plugins.iter_mut() {
+                        if let Err(err) = plugin.feed(&header, Some(&conversation)) {
+                            errors.push(err.to_string());
+                        }
+                    }
+                    match errors.is_empty() {
+                        true => Ok(()),
+                        false => Err(errors.join(" + ")),
+                    }
+                }
+                Err(err) => Err(err.to_string()),
+            };
+            last_event = Some(header.raw.event_hash.clone());
+            if let Err(reason) = accepted {
+                trace!("replay: event {} rejected - {}", replayed, reason);
+                ret.rejected.push(ReplayRejection {
+                    index: replayed,
+                    event_hash: header.raw.event_hash.clone(),
+                    reason,
+                });
+            } else {
+                if let Some(loader) = loader.as_mut() {
+                    loader.feed_load_data(data).await;
+                }
+                timeline.add_history(header);
+            }
+            replayed += 1;
+
+            if options.snapshot_every > 0 && replayed % options.snapshot_every == 0 {
+                Self::replay_snapshot(&timeline, &clock, replayed, last_event, &mut ret, &mut options);
+            }
+        }
+
+        // There is always a snapshot of the final state
+        if ret.snapshots.last().map(|a| a.events != replayed).unwrap_or(true) {
+            Self::replay_snapshot(&timeline, &clock, replayed, last_event, &mut ret, &mut options);
+        }
+        if let Some(loader) = loader.as_mut() {
+            loader.end_of_history().await;
+        }
+
+        debug!(
+            "replay: {} events replayed ({} rejected)",
+            replayed,
+            ret.rejected.len()
+        );
+        Ok(ret)
+    }
+
+    fn replay_snapshot(
+        timeline: &ChainTimeline,
+        clock: &ReplayClock,
+        replayed: u64,
+        last_event: Option<AteHash>,
+        report: &mut ReplayReport,
+        options: &mut ReplayOptions,
+    ) {
+        let snapshot = ReplaySnapshot {
+            events: replayed,
+            rows: timeline.pointers.count() as u64,
+            rejected: report.rejected.len() as u64,
+            clock: clock.now(),
+            index_checksum: timeline.pointers.checksum(),
+            last_event,
+        };
+        if let Some(hook) = options.on_snapshot.as_mut() {
+            hook(&snapshot);
+        }
+        report.snapshots.push(snapshot);
+    }
+}
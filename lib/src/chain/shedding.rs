@@ -0,0 +1,268 @@
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use fxhash::FxHashMap;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::broadcast;
+use tokio::sync::RwLock;
+
+use crate::engine::TaskEngine;
+use crate::event::EventWeakData;
+use crate::header::PrimaryKey;
+
+use super::ChainProtectedAsync;
+
+/// # Inbound Budget
+///
+/// Limits how much cache maintenance a client performs inline for the
+/// events that stream in from the server. Once a tick has used up its
+/// budget the invalidation of cached data and the notifications sent to
+/// subscribers are deferred until the end of the tick, where multiple
+/// updates to the same key are coalesced into one.
+///
+/// The events themselves are always applied to the local replica straight
+/// away and commit confirmations and lock replies are never deferred, when
+/// one of these arrives any work that is already deferred is flushed ahead
+/// of it so that subscribers still see the updates in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InboundBudget {
+    // Number of events that are maintained inline during a single tick
+    pub events_per_tick: usize,
+    // Length of a tick (and hence the longest time work is deferred)
+    pub tick: Duration,
+    // Number of deferred keys that will force the work to be flushed early
+    pub max_queue: usize,
+}
+
+impl Default for InboundBudget {
+    fn default() -> InboundBudget {
+        InboundBudget {
+            events_per_tick: 1000,
+            tick: Duration::from_millis(100),
+            max_queue: 10000,
+        }
+    }
+}
+
+impl InboundBudget {
+    pub fn with_events_per_tick(mut self, val: usize) -> Self {
+        self.events_per_tick = val;
+        self
+    }
+
+    pub fn with_tick(mut self, val: Duration) -> Self {
+        self.tick = val;
+        self
+    }
+
+    pub fn with_max_queue(mut self, val: usize) -> Self {
+        self.max_queue = val;
+        self
+    }
+}
+
+/// Reports how far the local replica of a chain is behind the events
+/// that the server has sent to it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChainLag {
+    /// Events received from the server that are still being applied
+    pub inflight_events: usize,
+    /// Updates whose cache invalidation and notifications are deferred
+    pub deferred_events: usize,
+    /// How long the oldest deferred update has been waiting
+    pub behind: Duration,
+}
+
+#[derive(Default)]
+struct ShedderState {
+    tick_start: Option<Instant>,
+    tick_events: usize,
+    deferred_since: Option<Instant>,
+    flush_scheduled: bool,
+    decache: Vec<PrimaryKey>,
+    notify: Vec<EventWeakData>,
+    // Position of the latest update for a particular key in the notify list
+    notify_idx: FxHashMap<PrimaryKey, usize>,
+}
+
+impl ShedderState {
+    fn is_deferring(&self) -> bool {
+        self.notify.is_empty() == false
+    }
+
+    fn push(&mut self, evts: Vec<EventWeakData>) {
+        if self.deferred_since.is_none() {
+            self.deferred_since = Some(Instant::now());
+        }
+        for evt in evts {
+            match evt.meta.get_data_key() {
+                Some(key) => match self.notify_idx.get(&key) {
+                    Some(idx) => {
+                        self.notify[*idx] = evt;
+                    }
+                    None => {
+                        self.notify_idx.insert(key.clone(), self.notify.len());
+                        self.decache.push(key);
+                        self.notify.push(evt);
+                    }
+                },
+                None => self.notify.push(evt),
+            }
+        }
+    }
+
+    fn take(&mut self) -> (Vec<PrimaryKey>, Vec<EventWeakData>) {
+        self.deferred_since = None;
+        self.flush_scheduled = false;
+        self.notify_idx.clear();
+        (
+            std::mem::take(&mut self.decache),
+            std::mem::take(&mut self.notify),
+        )
+    }
+}
+
+/// Performs the cache maintenance (decache and notifications) for events
+/// that have been applied to the chain, shedding load when the inbound
+/// budget of the chain has been exceeded
+pub(crate) struct LoadShedder {
+    budget: StdMutex<Option<InboundBudget>>,
+    state: StdMutex<ShedderState>,
+    inflight: AtomicUsize,
+    decache: broadcast::Sender<Vec<PrimaryKey>>,
+    inside_async: Arc<RwLock<ChainProtectedAsync>>,
+}
+
+impl LoadShedder {
+    pub(crate) fn new(
+        decache: broadcast::Sender<Vec<PrimaryKey>>,
+        inside_async: Arc<RwLock<ChainProtectedAsync>>,
+    ) -> LoadShedder {
+        LoadShedder {
+            budget: StdMutex::new(None),
+            state: StdMutex::new(ShedderState::default()),
+            inflight: AtomicUsize::new(0),
+            decache,
+            inside_async,
+        }
+    }
+
+    pub(crate) fn set_budget(&self, budget: Option<InboundBudget>) {
+        *self.budget.lock().unwrap() = budget;
+    }
+
+    /// Records inbound events that are about to be fed into the chain
+    pub(crate) fn begin_inbound(&self, cnt: usize) {
+        self.inflight.fetch_add(cnt, Ordering::AcqRel);
+    }
+
+    /// Records inbound events that have finished being fed into the chain
+    pub(crate) fn end_inbound(&self, cnt: usize) {
+        self.inflight.fetch_sub(cnt, Ordering::AcqRel);
+    }
+
+    pub(crate) fn lag(&self) -> ChainLag {
+        let state = self.state.lock().unwrap();
+        ChainLag {
+            inflight_events: self.inflight.load(Ordering::Acquire),
+            deferred_events: state.notify.len(),
+            behind: state
+                .deferred_since
+                .map(|a| a.elapsed())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Clears the caches and notifies the subscribers of events that were
+    /// just applied, deferrable work is coalesced when over budget
+    pub(crate) fn maintain(self: &Arc<Self>, evts: Vec<EventWeakData>, deferrable: bool) {
+        let budget = match deferrable {
+            true => self.budget.lock().unwrap().clone(),
+            false => None,
+        };
+        let budget = match budget {
+            Some(a) => a,
+            None => {
+                // Work that skips the budget is still delivered after anything
+                // that was deferred before it otherwise the order is lost
+                let mut state = self.state.lock().unwrap();
+                let (mut decache, mut pending) = state.take();
+                decache.extend(Self::decache_keys(&evts));
+                pending.extend(evts);
+                self.apply(decache, pending);
+                return;
+            }
+        };
+
+        let (inline, flush_now, schedule) = {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            let new_tick = match state.tick_start {
+                Some(a) => now.duration_since(a) >= budget.tick,
+                None => true,
+            };
+            if new_tick {
+                state.tick_start = Some(now);
+                state.tick_events = 0;
+            }
+            state.tick_events += evts.len();
+
+            // Once work is being deferred everything else must queue up behind
+            // it otherwise an older update could be delivered after a newer one
+            if state.tick_events <= budget.events_per_tick && state.is_deferring() == false {
+                (Some(evts), false, false)
+            } else {
+                state.push(evts);
+                let flush_now = state.notify.len() >= budget.max_queue;
+                let schedule = flush_now == false && state.flush_scheduled == false;
+                if schedule {
+                    state.flush_scheduled = true;
+                }
+                (None, flush_now, schedule)
+            }
+        };
+
+        if let Some(evts) = inline {
+            self.apply(Self::decache_keys(&evts), evts);
+        } else if flush_now {
+            trace!("inbound queue is full - flushing early");
+            self.flush();
+        } else if schedule {
+            let shedder = Arc::clone(self);
+            TaskEngine::spawn(async move {
+                crate::engine::sleep(budget.tick).await;
+                shedder.flush();
+            });
+        }
+    }
+
+    fn flush(&self) {
+        let (decache, evts) = self.state.lock().unwrap().take();
+        if evts.is_empty() == false {
+            trace!("flushing {} deferred events", evts.len());
+            self.apply(decache, evts);
+        }
+    }
+
+    fn decache_keys(evts: &Vec<EventWeakData>) -> Vec<PrimaryKey> {
+        evts.iter()
+            .filter_map(|a| a.meta.get_data_key())
+            .collect::<Vec<_>>()
+    }
+
+    fn apply(&self, decache: Vec<PrimaryKey>, evts: Vec<EventWeakData>) {
+        // Clear all the caches
+        let _ = self.decache.send(decache);
+
+        // Notify all the listeners
+        let inside_async = Arc::clone(&self.inside_async);
+        TaskEngine::spawn(async move {
+            ChainProtectedAsync::notify(inside_async, evts).await;
+        });
+    }
+}
@@ -0,0 +1,104 @@
+#![cfg(test)]
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::crypto::AteHash;
+use crate::error::*;
+use crate::header::PrimaryKey;
+use crate::pipe::*;
+use crate::transaction::*;
+
+use super::progress::*;
+use super::*;
+
+/// Pipe whose feeds never complete (like one that has deadlocked)
+struct StuckPipe {}
+
+#[async_trait]
+impl EventPipe for StuckPipe {
+    async fn feed(&self, _work: ChainWork) -> Result<(), CommitError> {
+        std::future::pending().await
+    }
+
+    async fn load_many(&self, leafs: Vec<AteHash>) -> Result<Vec<Option<Bytes>>, LoadError> {
+        Ok(leafs.into_iter().map(|_| None).collect())
+    }
+
+    async fn prime(&self, _records: Vec<(AteHash, Option<Bytes>)>) -> Result<(), CommsError> {
+        Ok(())
+    }
+
+    async fn try_lock(&self, _key: PrimaryKey) -> Result<bool, CommitError> {
+        Ok(true)
+    }
+
+    async fn unlock(&self, _key: PrimaryKey) -> Result<(), CommitError> {
+        Ok(())
+    }
+
+    fn unlock_local(&self, _key: PrimaryKey) -> Result<(), CommitError> {
+        Ok(())
+    }
+
+    fn set_next(&mut self, _next: Arc<Box<dyn EventPipe>>) {}
+
+    async fn conversation(&self) -> Option<Arc<ConversationSession>> {
+        None
+    }
+}
+
+fn empty_work() -> ChainWork {
+    ChainWork {
+        trans: Transaction::from_events(
+            Vec::new(),
+            TransactionScope::Local,
+            false,
+            Duration::from_secs(30),
+        ),
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_progress_pipe_detects_stuck_feeds() {
+    crate::utils::bootstrap_test_env();
+
+    let progress = Arc::new(PipeProgress::new());
+    let pipe: Arc<Box<dyn EventPipe>> = Arc::new(Box::new(ProgressPipe {
+        progress: Arc::clone(&progress),
+        next: Arc::new(Box::new(StuckPipe {})),
+    }));
+
+    // A chain with nothing pending is never stuck
+    crate::engine::sleep(Duration::from_millis(20)).await;
+    assert!(progress.is_stuck(Duration::from_millis(10)) == false);
+    assert_eq!(progress.diagnostics().pending, 0);
+
+    // Locks taken through the pipes are listed as holders
+    let key = PrimaryKey::generate();
+    assert!(pipe.try_lock(key.clone()).await.unwrap());
+    assert_eq!(progress.diagnostics().locks, vec![key.clone()]);
+    pipe.unlock(key).await.unwrap();
+    assert!(progress.diagnostics().locks.is_empty());
+
+    // A feed that never completes makes the chain stuck once the threshold passes
+    let feed = {
+        let pipe = Arc::clone(&pipe);
+        crate::engine::TaskEngine::spawn(async move { pipe.feed(empty_work()).await })
+    };
+    crate::engine::sleep(Duration::from_millis(50)).await;
+    assert!(progress.is_stuck(Duration::from_millis(10)));
+    assert!(progress.is_stuck(Duration::from_secs(60)) == false);
+    let diag = progress.diagnostics();
+    assert_eq!(diag.pending, 1);
+    assert!(diag.oldest.unwrap() >= Duration::from_millis(10));
+
+    // Aborting fails the pending feed with a timeout and clears the state
+    assert_eq!(progress.abort_pending(), 1);
+    let ret = feed.await.unwrap();
+    assert!(matches!(ret, Err(CommitError(CommitErrorKind::Timeout(_), _))));
+    assert_eq!(progress.diagnostics().pending, 0);
+    assert!(progress.is_stuck(Duration::from_millis(10)) == false);
+}
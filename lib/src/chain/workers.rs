@@ -27,6 +27,7 @@ pub(crate) struct ChainWorkProcessor {
     pub(crate) inside_async: Arc<RwLock<ChainProtectedAsync>>,
     pub(crate) inside_sync: Arc<StdRwLock<ChainProtectedSync>>,
     pub(crate) compact_tx: CompactNotifications,
+    pub(crate) shedder: Arc<LoadShedder>,
 }
 
 impl ChainWorkProcessor {
@@ -34,11 +35,13 @@ impl ChainWorkProcessor {
         inside_async: Arc<RwLock<ChainProtectedAsync>>,
         inside_sync: Arc<StdRwLock<ChainProtectedSync>>,
         compact_tx: CompactNotifications,
+        shedder: Arc<LoadShedder>,
     ) -> ChainWorkProcessor {
         ChainWorkProcessor {
             inside_async,
             inside_sync,
             compact_tx,
+            shedder,
         }
     }
 
@@ -86,12 +89,9 @@ impl ChainWorkProcessor {
         // Drop the lock
         drop(lock);
 
-        {
-            let inside_async = Arc::clone(&self.inside_async);
-                TaskEngine::spawn(async move {
-                    ChainProtectedAsync::notify(inside_async, trans.events).await;
-                });
-        }
+        // Clear the caches and notify the listeners (which may be deferred
+        // when the chain is receiving more events than its budget allows)
+        self.shedder.maintain(trans.events, trans.deferrable);
 
         TaskEngine::spawn(async move {
             match crate::service::callback_events_notify(notifies).await {
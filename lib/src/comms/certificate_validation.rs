@@ -1 +1,4 @@
 pub use ate_comms::CertificateValidation;
+pub use ate_comms::CertificateInfo;
+pub use ate_comms::ServerCertificate;
+pub use ate_comms::ServerCertificates;
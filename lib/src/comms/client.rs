@@ -5,8 +5,10 @@ use std::net::ToSocketAddrs;
 use std::result::Result;
 use std::sync::Arc;
 use std::sync::Mutex as StdMutex;
+use std::sync::Weak;
 use error_chain::bail;
 use fxhash::FxHashMap;
+use once_cell::sync::Lazy;
 use serde::{de::DeserializeOwned, Serialize};
 #[cfg(feature = "enable_full")]
 use tokio::net::TcpStream;
@@ -31,12 +33,72 @@ use super::metrics::*;
 use super::rx_tx::*;
 use super::throttle::*;
 use super::CertificateValidation;
-use super::{conf::*, hello::HelloMetadata};
+use super::PacketTapHook;
+use super::Multiplexer;
+use super::StreamReadable;
+#[cfg(feature = "enable_full")]
+use super::TlsClient;
+use super::UpstreamOutbox;
+use super::{conf::*, hello::HelloMetadata, hello::KeepAlive, hello::StreamProtocolVersion};
 #[allow(unused_imports)]
 use {
     super::StreamProtocol, super::StreamRx, super::StreamTx,
 };
 
+/// Connections are only shared between chains that talk to the same
+/// server, over the same protocol and with the same credentials
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct MultiplexKey {
+    addr: String,
+    domain: String,
+    hello_path: String,
+    node_id: NodeId,
+    wire_protocol: String,
+    wire_encryption: Option<String>,
+}
+
+impl MultiplexKey {
+    fn new(
+        addr: &MeshConnectAddr,
+        conf: &MeshConfig,
+        hello_path: &str,
+        node_id: &NodeId,
+    ) -> MultiplexKey {
+        MultiplexKey {
+            addr: addr.to_string(),
+            domain: conf.cfg_mesh.domain_name.clone(),
+            hello_path: hello_path.to_string(),
+            node_id: node_id.clone(),
+            wire_protocol: conf.cfg_mesh.wire_protocol.to_string(),
+            wire_encryption: conf.cfg_mesh.wire_encryption.map(|a| a.to_string()),
+        }
+    }
+}
+
+/// Connection that is shared between all the chains that have the same
+/// multiplex key, each chain receives its own channel on the connection
+#[derive(Debug, Clone)]
+struct SharedUpstream {
+    mux: Weak<Multiplexer>,
+    addr: MeshConnectAddr,
+    wire_protocol: StreamProtocol,
+    wire_format: SerializationFormat,
+    wire_encryption: Option<EncryptKey>,
+    version: StreamProtocolVersion,
+    node_id: NodeId,
+    peer_id: NodeId,
+}
+
+static SHARED_UPSTREAMS: Lazy<StdMutex<FxHashMap<MultiplexKey, SharedUpstream>>> =
+    Lazy::new(|| StdMutex::new(FxHashMap::default()));
+
+fn find_shared_upstream(key: &MultiplexKey) -> Option<(SharedUpstream, Arc<Multiplexer>)> {
+    let guard = SHARED_UPSTREAMS.lock().unwrap();
+    let shared = guard.get(key)?;
+    let mux = Weak::upgrade(&shared.mux).filter(|a| a.is_closing() == false)?;
+    Some((shared.clone(), mux))
+}
+
 pub(crate) async fn connect<M, C>(
     conf: &MeshConfig,
     hello_path: String,
@@ -52,24 +114,68 @@ where
 {
     // Create all the outbound connections
     if let Some(target) = &conf.connect_to {
-        // Perform the connect operation
         let inbox = Box::new(inbox);
-        let upstream = mesh_connect_to::<M, C>(
-            target.clone(),
-            hello_path.clone(),
-            node_id,
-            conf.cfg_mesh.domain_name.clone(),
-            inbox,
-            conf.cfg_mesh.wire_protocol,
-            conf.cfg_mesh.wire_encryption,
-            conf.cfg_mesh.connect_timeout,
-            conf.cfg_mesh.fail_fast,
-            conf.cfg_mesh.certificate_validation.clone(),
-            Arc::clone(&metrics),
-            Arc::clone(&throttle),
-            exit,
-        )
-        .await?;
+
+        // When the stream runs over TLS the tunnel already protects the
+        // data hence the extra wire encryption is optional
+        let is_tls = conf.cfg_mesh.wire_protocol.is_tls();
+        let wire_encryption = match is_tls && conf.cfg_mesh.tls_wire_encryption == false {
+            true => None,
+            false => conf.cfg_mesh.wire_encryption,
+        };
+        let require_encryption = conf.cfg_mesh.require_encryption && is_tls == false;
+        #[cfg(feature = "enable_full")]
+        let tls = match is_tls {
+            true => Some(TlsClient::new(&conf.cfg_mesh, conf.cfg_mesh.domain_name.as_str())?),
+            false => None,
+        };
+
+        // If another chain already has a connection to this server that
+        // supports multiplexing then we open a channel on it instead
+        let multiplex = match conf.cfg_mesh.multiplex {
+            true => Some(MultiplexKey::new(target, conf, hello_path.as_str(), &node_id)),
+            false => None,
+        };
+        let shared = multiplex
+            .as_ref()
+            .map(|a| find_shared_upstream(a))
+            .flatten()
+            .filter(|(a, _)| require_encryption == false || a.wire_encryption.is_some());
+
+        // Perform the connect operation
+        let upstream = match shared {
+            Some((shared, mux)) => mesh_connect_channel::<M, C>(
+                &shared,
+                mux,
+                inbox,
+                Arc::clone(&metrics),
+                Arc::clone(&throttle),
+                exit,
+            ),
+            None => mesh_connect_to::<M, C>(
+                target.clone(),
+                hello_path.clone(),
+                node_id,
+                conf.cfg_mesh.domain_name.clone(),
+                inbox,
+                conf.cfg_mesh.wire_protocol,
+                #[cfg(feature = "enable_full")]
+                tls,
+                wire_encryption,
+                require_encryption,
+                conf.cfg_mesh.max_protocol_version,
+                conf.cfg_mesh.keepalive(),
+                conf.cfg_mesh.connect_timeout,
+                conf.cfg_mesh.fail_fast,
+                conf.cfg_mesh.certificate_validation.clone(),
+                conf.cfg_mesh.packet_tap(),
+                multiplex,
+                Arc::clone(&metrics),
+                Arc::clone(&throttle),
+                exit,
+            )
+            .await?,
+        };
 
         // Return the mesh
         Ok(Tx {
@@ -80,23 +186,30 @@ where
             metrics: Arc::clone(&metrics),
             throttle: Arc::clone(&throttle),
             exit_dependencies: Vec::new(),
+            peer: None,
         })
     } else {
         bail!(CommsErrorKind::NoAddress);
     }
 }
 
-pub(super) async fn mesh_connect_to<M, C>(
+async fn mesh_connect_to<M, C>(
     addr: MeshConnectAddr,
     hello_path: String,
     node_id: NodeId,
     domain: String,
     inbox: Box<dyn InboxProcessor<M, C>>,
     wire_protocol: StreamProtocol,
+    #[cfg(feature = "enable_full")] tls: Option<TlsClient>,
     wire_encryption: Option<KeySize>,
+    require_encryption: bool,
+    max_version: StreamProtocolVersion,
+    keepalive: Option<KeepAlive>,
     timeout: Duration,
     fail_fast: bool,
     validation: CertificateValidation,
+    packet_tap: Option<PacketTapHook>,
+    multiplex: Option<MultiplexKey>,
     metrics: Arc<StdMutex<super::metrics::Metrics>>,
     throttle: Arc<StdMutex<super::throttle::Throttle>>,
     exit: broadcast::Receiver<()>,
@@ -113,34 +226,95 @@ where
         node_id,
         domain,
         wire_protocol,
+        #[cfg(feature = "enable_full")]
+        tls,
         wire_encryption,
+        multiplex.is_some(),
+        max_version,
+        keepalive,
         fail_fast,
     );
     let mut worker_connect =
         crate::engine::timeout(timeout, worker_connect).await??;
     let wire_format = worker_connect.hello_metadata.wire_format;
     let server_id = worker_connect.hello_metadata.server_id;
+    let version = worker_connect.hello_metadata.version;
+    metrics.lock().unwrap().protocol_version = Some(version);
+
+    // Refuse to proceed without encryption when it is required, regardless
+    // of what the server said during the hello
+    if require_encryption && worker_connect.hello_metadata.encryption.is_none() {
+        bail!(CommsErrorKind::ServerEncryptionWeak);
+    }
 
     // If we are using wire encryption then exchange secrets
-    let ek = match wire_encryption {
-        Some(key_size) => Some(
-            key_exchange::mesh_key_exchange_sender(
+    let ek = match worker_connect.hello_metadata.encryption {
+        Some(key_size) => {
+            let selected = worker_connect.hello_metadata.selected_certificate(&validation);
+            let (ek, _) = key_exchange::mesh_key_exchange_sender_select(
                 worker_connect.proto.deref_mut(),
                 key_size,
                 validation,
+                selected,
             )
-            .await?,
-        ),
+            .await?;
+            Some(ek)
+        }
         None => None,
     };
 
+    // Make sure that no one tampered with the hello messages (for instance
+    // to downgrade the key size) before the connection was encrypted
+    if let (Some(ek), Some(transcript)) = (ek.as_ref(), worker_connect.hello_metadata.transcript.as_ref()) {
+        if hello::mesh_hello_verify(worker_connect.proto.deref_mut(), ek, transcript, true).await? == false {
+            bail!(CommsErrorKind::HelloTampered);
+        }
+    }
+
     // Split the stream
-    let (rx, tx) = worker_connect.proto.split(ek);
+    let (mut rx, mut tx) = worker_connect.proto.split(ek);
+    if let Some(hook) = packet_tap {
+        rx.set_tap(hook.clone(), server_id);
+        tx.set_tap(hook, server_id);
+    }
+
+    // If the server agreed to multiplex the stream then we register it so
+    // that other chains can share it, otherwise we fall back to using a
+    // dedicated connection for this chain
+    if let Some(key) = multiplex.filter(|_| worker_connect.hello_metadata.multiplex) {
+        trace!("sharing multiplexed upstream (path={})", key.hello_path);
+        let mux = Multiplexer::new(tx, true);
+        let shared = SharedUpstream {
+            mux: Arc::downgrade(&mux),
+            addr: addr.clone(),
+            wire_protocol,
+            wire_format,
+            wire_encryption: ek.clone(),
+            version,
+            node_id,
+            peer_id: server_id,
+        };
+        {
+            let mut guard = SHARED_UPSTREAMS.lock().unwrap();
+            guard.retain(|_, v| v.mux.strong_count() > 0);
+            guard.insert(key, shared.clone());
+        }
+
+        let worker_mux = Arc::clone(&mux);
+        let worker_exit = mux.exit.subscribe();
+        TaskEngine::spawn(async move {
+            if let Err(err) = worker_mux.run(rx, |_, _| false, worker_exit).await {
+                debug!("multiplexed upstream closed: {}", err);
+            }
+        });
+
+        return Ok(mesh_connect_channel(&shared, mux, inbox, metrics, throttle, exit));
+    }
 
     // background thread - connects and then runs inbox and outbox threads
     // if the upstream object signals a termination event it will exit
     trace!("spawning connect worker");
-    TaskEngine::spawn(mesh_connect_worker::<M, C>(
+    TaskEngine::spawn(mesh_connect_worker::<M, C, _>(
         rx,
         wire_protocol,
         wire_format,
@@ -157,11 +331,47 @@ where
     trace!("building upstream with tx channel");
     Ok(Upstream {
         id: node_id,
-        outbox: tx,
+        outbox: UpstreamOutbox::Stream(tx),
         wire_format,
     })
 }
 
+fn mesh_connect_channel<M, C>(
+    shared: &SharedUpstream,
+    mux: Arc<Multiplexer>,
+    inbox: Box<dyn InboxProcessor<M, C>>,
+    metrics: Arc<StdMutex<super::metrics::Metrics>>,
+    throttle: Arc<StdMutex<super::throttle::Throttle>>,
+    exit: broadcast::Receiver<()>,
+) -> Upstream
+where
+    M: Send + Sync + Serialize + DeserializeOwned + Clone + Default + 'static,
+    C: Send + Sync + Default + 'static,
+{
+    metrics.lock().unwrap().protocol_version = Some(shared.version);
+    let (tx, rx) = mux.alloc_channel();
+    trace!("spawning connect worker (channel={})", tx.channel());
+    TaskEngine::spawn(mesh_connect_worker::<M, C, _>(
+        rx,
+        shared.wire_protocol,
+        shared.wire_format,
+        shared.addr.clone(),
+        shared.wire_encryption.clone(),
+        shared.node_id,
+        shared.peer_id,
+        inbox,
+        metrics,
+        throttle,
+        exit,
+    ));
+
+    Upstream {
+        id: shared.node_id,
+        outbox: UpstreamOutbox::Channel(tx),
+        wire_format: shared.wire_format,
+    }
+}
+
 struct MeshConnectContext {
     #[allow(dead_code)]
     addr: MeshConnectAddr,
@@ -176,7 +386,11 @@ async fn mesh_connect_prepare(
     node_id: NodeId,
     domain: String,
     wire_protocol: StreamProtocol,
+    #[cfg(feature = "enable_full")] tls: Option<TlsClient>,
     wire_encryption: Option<KeySize>,
+    multiplex: bool,
+    max_version: StreamProtocolVersion,
+    keepalive: Option<KeepAlive>,
     #[allow(unused_variables)] fail_fast: bool,
 ) -> Result<MeshConnectContext, CommsError> {
     async move {
@@ -243,7 +457,7 @@ async fn mesh_connect_prepare(
                     };
 
                     // Upgrade and split
-                    let (rx, tx) = wire_protocol.upgrade_client_and_split(stream).await?;
+                    let (rx, tx) = wire_protocol.upgrade_client_and_split(stream, tls.as_ref()).await?;
                     Some((rx, tx))
                 };
 
@@ -275,6 +489,9 @@ async fn mesh_connect_prepare(
                 hello_path.clone(),
                 domain.clone(),
                 wire_encryption,
+                multiplex,
+                max_version,
+                keepalive,
             )
             .await?;
 
@@ -292,8 +509,8 @@ async fn mesh_connect_prepare(
     .await
 }
 
-async fn mesh_connect_worker<M, C>(
-    rx: StreamRx,
+async fn mesh_connect_worker<M, C, R>(
+    rx: R,
     rx_proto: StreamProtocol,
     wire_format: SerializationFormat,
     sock_addr: MeshConnectAddr,
@@ -307,6 +524,7 @@ async fn mesh_connect_worker<M, C>(
 ) where
     M: Send + Sync + Serialize + DeserializeOwned + Clone + Default + 'static,
     C: Send + Sync + Default + 'static,
+    R: StreamReadable + Send + 'static,
 {
     let span = span!(
         Level::DEBUG,
@@ -316,7 +534,7 @@ async fn mesh_connect_worker<M, C>(
     );
 
     let context = Arc::new(C::default());
-    match process_inbox::<M, C>(
+    match process_inbox::<M, C, R>(
         rx,
         rx_proto,
         inbox,
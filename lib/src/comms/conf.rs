@@ -2,21 +2,24 @@ use std::net::IpAddr;
 use std::net::SocketAddr;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
-use ate_comms::StreamTx;
 
 use crate::comms::NodeId;
 use crate::conf::ConfMesh;
 use crate::conf::MeshAddress;
+#[cfg(feature = "enable_server")]
+use crate::comms::ServerCertificates;
 use crate::crypto::EncryptKey;
 use crate::crypto::KeySize;
 use crate::crypto::PrivateEncryptKey;
 use crate::spec::*;
 
+use super::multiplex::UpstreamOutbox;
+
 #[derive(Debug)]
 pub struct Upstream {
     #[allow(dead_code)]
     pub id: NodeId,
-    pub outbox: StreamTx,
+    pub outbox: UpstreamOutbox,
     #[allow(dead_code)]
     pub wire_format: SerializationFormat,
 }
@@ -41,6 +44,8 @@ pub(crate) struct MeshConfig {
     pub listen_min_encryption: Option<KeySize>,
     #[cfg(feature = "enable_server")]
     pub listen_cert: Option<PrivateEncryptKey>,
+    #[cfg(feature = "enable_server")]
+    pub listen_certs: ServerCertificates,
     #[allow(dead_code)]
     #[cfg(feature = "enable_dns")]
     pub connect_to: Option<SocketAddr>,
@@ -61,6 +66,8 @@ impl MeshConfig {
             listen_min_encryption: cfg_mesh.listen_min_encryption.clone(),
             #[cfg(feature = "enable_server")]
             listen_cert: cfg_mesh.listen_certificate.clone(),
+            #[cfg(feature = "enable_server")]
+            listen_certs: cfg_mesh.server_certificates(),
             #[cfg(feature = "enable_dns")]
             connect_to: None,
             #[cfg(not(feature = "enable_dns"))]
@@ -81,6 +88,7 @@ impl MeshConfig {
     pub(crate) fn listen_cert(mut self, certificate: PrivateEncryptKey) -> Self {
         self.cfg_mesh.listen_certificate = Some(certificate.clone());
         self.listen_cert = Some(certificate);
+        self.listen_certs = self.cfg_mesh.server_certificates();
         self
     }
 
@@ -0,0 +1,118 @@
+//! Sample gRPC service that replies with the message it was sent (the code
+//! below is what tonic generates for the following protobuf definition)
+//!
+//! ```text
+//! syntax = "proto3";
+//! package ate.echo;
+//!
+//! message EchoMessage {
+//!     string message = 1;
+//! }
+//!
+//! service Echo {
+//!     rpc Echo (EchoMessage) returns (EchoMessage);
+//! }
+//! ```
+use std::convert::Infallible;
+use std::task::Context;
+use std::task::Poll;
+use tonic::codec::ProstCodec;
+use tonic::codegen::BoxFuture;
+use tonic::codegen::Service;
+use tonic::codegen::StdError;
+use tonic::transport::NamedService;
+
+const ECHO_PATH: &'static str = "/ate.echo.Echo/Echo";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EchoMessage {
+    #[prost(string, tag = "1")]
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EchoServer {}
+
+struct EchoUnary;
+
+impl tonic::server::UnaryService<EchoMessage> for EchoUnary {
+    type Response = EchoMessage;
+    type Future = BoxFuture<tonic::Response<EchoMessage>, tonic::Status>;
+
+    fn call(&mut self, request: tonic::Request<EchoMessage>) -> Self::Future {
+        let reply = request.into_inner();
+        Box::pin(async move { Ok(tonic::Response::new(reply)) })
+    }
+}
+
+impl<B> Service<http::Request<B>> for EchoServer
+where
+    B: http_body::Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        match request.uri().path() {
+            ECHO_PATH => Box::pin(async move {
+                let mut grpc = tonic::server::Grpc::new(ProstCodec::default());
+                Ok(grpc.unary(EchoUnary, request).await)
+            }),
+            _ => Box::pin(async move {
+                Ok(http::Response::builder()
+                    .status(200)
+                    .header("grpc-status", tonic::Code::Unimplemented as i32)
+                    .header("content-type", "application/grpc")
+                    .body(tonic::codegen::empty_body())
+                    .unwrap())
+            }),
+        }
+    }
+}
+
+impl NamedService for EchoServer {
+    const NAME: &'static str = "ate.echo.Echo";
+}
+
+/// Client for the echo service
+#[derive(Debug, Clone)]
+pub struct EchoClient {
+    inner: tonic::client::Grpc<tonic::transport::Channel>,
+}
+
+impl EchoClient {
+    pub async fn connect(dst: String) -> Result<EchoClient, tonic::transport::Error> {
+        let channel = tonic::transport::Endpoint::new(dst)?.connect().await?;
+        Ok(EchoClient::new(channel))
+    }
+
+    pub fn new(channel: tonic::transport::Channel) -> EchoClient {
+        EchoClient {
+            inner: tonic::client::Grpc::new(channel),
+        }
+    }
+
+    pub async fn echo(&mut self, message: &str) -> Result<String, tonic::Status> {
+        self.inner.ready().await.map_err(|err| {
+            tonic::Status::new(
+                tonic::Code::Unknown,
+                format!("service was not ready - {}", err),
+            )
+        })?;
+        let request = tonic::Request::new(EchoMessage {
+            message: message.to_string(),
+        });
+        let path = http::uri::PathAndQuery::from_static(ECHO_PATH);
+        let response = self
+            .inner
+            .unary(request, path, ProstCodec::default())
+            .await?;
+        Ok(response.into_inner().message)
+    }
+}
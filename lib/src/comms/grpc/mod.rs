@@ -0,0 +1,136 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body::Body as HttpBody;
+use hyper_tungstenite::hyper::Body;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::codegen::Service;
+use tonic::transport::NamedService;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use super::GrpcRoute;
+use super::NodeId;
+use super::StreamRouter;
+use crate::engine::TaskEngine;
+
+pub mod echo;
+
+/// Mounts a tonic service (or any other tower service that speaks gRPC) on
+/// the stream router so that it shares the port (and certificate) of the
+/// web server that the router is attached to
+pub struct TonicRoute<S> {
+    service: S,
+}
+
+impl<S> TonicRoute<S> {
+    pub fn new(service: S) -> TonicRoute<S> {
+        TonicRoute { service }
+    }
+}
+
+#[async_trait]
+impl<S, B> GrpcRoute for TonicRoute<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<B>>,
+    S: Clone + Send + Sync + 'static,
+    S::Future: Send,
+    S::Error: std::fmt::Display + Send,
+    B: HttpBody<Data = Bytes> + Send + 'static,
+    B::Error: std::fmt::Display,
+{
+    async fn accepted_grpc_request(
+        &self,
+        request: http::Request<Body>,
+        sock_addr: SocketAddr,
+        _server_id: NodeId,
+    ) -> http::Response<Body> {
+        trace!("grpc request from {} - {}", sock_addr, request.uri().path());
+
+        let mut service = self.service.clone();
+        let ret = match futures::future::poll_fn(|cx| service.poll_ready(cx)).await {
+            Ok(()) => service.call(request).await,
+            Err(err) => Err(err),
+        };
+        match ret {
+            Ok(response) => {
+                let (parts, body) = response.into_parts();
+                http::Response::from_parts(parts, into_body(body))
+            }
+            Err(err) => {
+                debug!("grpc service failed - {}", err);
+                grpc_internal_error(err.to_string())
+            }
+        }
+    }
+}
+
+impl StreamRouter {
+    /// Mounts a tonic generated service on the path of the service
+    /// (e.g. `router.add_tonic_service(MyServiceServer::new(svc)).await`)
+    pub async fn add_tonic_service<S, B>(&mut self, service: S)
+    where
+        S: Service<http::Request<Body>, Response = http::Response<B>>,
+        S: NamedService + Clone + Send + Sync + 'static,
+        S::Future: Send,
+        S::Error: std::fmt::Display + Send,
+        B: HttpBody<Data = Bytes> + Send + 'static,
+        B::Error: std::fmt::Display,
+    {
+        let path = format!("/{}/", S::NAME);
+        self.add_grpc_route(path.as_str(), Arc::new(TonicRoute::new(service)))
+            .await;
+    }
+}
+
+/// Streams the body of the response back to the client including its
+/// trailers (which is where gRPC puts the status of the call)
+fn into_body<B>(body: B) -> Body
+where
+    B: HttpBody<Data = Bytes> + Send + 'static,
+    B::Error: std::fmt::Display,
+{
+    let (mut tx, ret) = Body::channel();
+    TaskEngine::spawn(async move {
+        let mut body = Box::pin(body);
+        while let Some(data) = body.data().await {
+            match data {
+                Ok(data) => {
+                    if tx.send_data(data).await.is_err() {
+                        return;
+                    }
+                }
+                Err(err) => {
+                    debug!("grpc response body failed - {}", err);
+                    tx.abort();
+                    return;
+                }
+            }
+        }
+        match body.trailers().await {
+            Ok(Some(trailers)) => {
+                let _ = tx.send_trailers(trailers).await;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                debug!("grpc response trailers failed - {}", err);
+                tx.abort();
+            }
+        }
+    });
+    ret
+}
+
+fn grpc_internal_error(msg: String) -> http::Response<Body> {
+    let mut ret = http::Response::new(Body::empty());
+    let headers = ret.headers_mut();
+    headers.insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("application/grpc"),
+    );
+    headers.insert("grpc-status", http::HeaderValue::from(tonic::Code::Internal as i32));
+    if let Ok(msg) = http::HeaderValue::from_str(msg.as_str()) {
+        headers.insert("grpc-message", msg);
+    }
+    ret
+}
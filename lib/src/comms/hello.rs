@@ -1,4 +1,9 @@
 pub use ate_comms::mesh_hello_exchange_receiver;
+pub use ate_comms::mesh_hello_receive;
+pub use ate_comms::HelloReceived;
 pub use ate_comms::mesh_hello_exchange_sender;
 pub use ate_comms::HelloMetadata;
 pub use ate_comms::MessageProtocolVersion as StreamProtocolVersion;
+pub use ate_comms::KeepAlive;
+pub use ate_comms::mesh_hello_verify;
+pub use ate_comms::HelloTranscript;
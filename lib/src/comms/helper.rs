@@ -26,10 +26,13 @@ use super::Metrics;
 use super::Packet;
 use super::PacketData;
 use super::PacketWithContext;
-use super::StreamRx;
+use super::StreamReadable;
 use super::Throttle;
 use crate::conf::MeshConnectAddr;
 
+/// Held while a packet that was admitted onto a connection is processed
+pub(crate) type PacketGuard = Box<dyn Send + Sync>;
+
 #[async_trait]
 pub(crate) trait InboxProcessor<M, C>
 where
@@ -37,6 +40,12 @@ where
     M: Send + Sync + Serialize + DeserializeOwned + Clone + Default,
     C: Send + Sync,
 {
+    /// Called with the size of every packet before it is decoded, packets
+    /// that are refused are never decoded and the connection is closed
+    async fn admit(&mut self, _context: &Arc<C>, _bytes: usize) -> Result<Option<PacketGuard>, CommsError> {
+        Ok(None)
+    }
+
     async fn process(&mut self, pck: PacketWithContext<M, C>) -> Result<(), CommsError>;
 
     async fn shutdown(&mut self, addr: MeshConnectAddr);
@@ -50,8 +59,8 @@ pub(super) fn setup_tcp_stream(stream: &TcpStream) -> io::Result<()> {
 
 #[allow(dead_code)]
 #[allow(unused_variables)]
-pub(super) async fn process_inbox<M, C>(
-    mut rx: StreamRx,
+pub(super) async fn process_inbox<M, C, R>(
+    mut rx: R,
     rx_proto: StreamProtocol,
     mut inbox: Box<dyn InboxProcessor<M, C>>,
     metrics: Arc<StdMutex<Metrics>>,
@@ -67,6 +76,7 @@ pub(super) async fn process_inbox<M, C>(
 where
     M: Send + Sync + Serialize + DeserializeOwned + Clone + Default,
     C: Send + Sync,
+    R: StreamReadable + Send,
 {
     let ret = async {
         // Throttling variables
@@ -76,10 +86,26 @@ where
         let mut current_sent = 0u64;
         let mut hickup_count = 0u32;
 
+        // Adaptive throttling variables (packets that are read without any delay
+        // after the previous one was processed were sitting in the queue)
+        let queued_threshold = chrono::Duration::milliseconds(1);
+        let mut queue_depth = 0u32;
+
         // Main read loop
         loop {
             // Read the next request
             let buf = async {
+                // While the reads are paused nothing more is taken off the wire
+                // (resuming the throttle wakes this up, if that happens before
+                // the wait starts then the notification is already stored)
+                loop {
+                    let paused = throttle.lock().unwrap().paused.clone();
+                    match paused {
+                        Some(paused) => paused.notified().await,
+                        None => break,
+                    }
+                }
+
                 // If the throttle has triggered
                 let now = chrono::offset::Utc::now();
                 let delta = now - last_throttle;
@@ -102,11 +128,25 @@ where
                     delta_received /= delta.num_milliseconds();
                     delta_sent /= delta.num_milliseconds();
 
+                    // In adaptive mode the effective rate is recomputed and caps both directions
+                    let effective = throttle.lock().unwrap().adapt(queue_depth);
+                    if effective.is_some() {
+                        metrics.lock().unwrap().throttle_per_second = effective;
+                    }
+
                     // We throttle the connection based off the current metrics and a calculated wait time
                     let wait_time = {
                         let throttle = throttle.lock().unwrap();
-                        let wait1 = throttle
-                            .download_per_second
+                        let download_per_second = match (throttle.download_per_second, effective) {
+                            (Some(a), Some(b)) => Some(a.min(b)),
+                            (a, b) => a.or(b),
+                        };
+                        let upload_per_second = match (throttle.upload_per_second, effective) {
+                            (Some(a), Some(b)) => Some(a.min(b)),
+                            (a, b) => a.or(b),
+                        };
+
+                        let wait1 = download_per_second
                             .map(|limit| limit as i64)
                             .filter(|limit| delta_sent.gt(limit))
                             .map(|limit| {
@@ -114,8 +154,7 @@ where
                                     ((delta_sent - limit) * 1000i64) / limit,
                                 )
                             });
-                        let wait2 = throttle
-                            .upload_per_second
+                        let wait2 = upload_per_second
                             .map(|limit| limit as i64)
                             .filter(|limit| delta_received.gt(limit))
                             .map(|limit| {
@@ -143,7 +182,14 @@ where
                     }
                 }
 
-                rx.read().await
+                let read_start = chrono::offset::Utc::now();
+                let ret = rx.read().await;
+                if chrono::offset::Utc::now() - read_start < queued_threshold {
+                    queue_depth = queue_depth.saturating_add(1);
+                } else {
+                    queue_depth = 0;
+                }
+                ret
             };
             let buf = {
                 select! {
@@ -153,7 +199,8 @@ where
                     },
                     a = buf => a
                 }
-            }?;
+            }
+            .map_err(CommsError::from_wire)?;
 
             // Update the metrics with all this received data
             {
@@ -162,6 +209,10 @@ where
                 metrics.requests += 1u64;
             }
 
+            // The packet is accounted for before it is decoded (which is where
+            // the memory it needs is allocated) and until it has been processed
+            let _admitted = inbox.admit(&context, buf.len()).await?;
+
             // Deserialize it
             let msg: M = wire_format.deserialize_ref(&buf)
                 .map_err(SerializationError::from)?;
@@ -179,9 +230,17 @@ where
                 peer_id,
             };
 
-            // Its time to process the packet
-            let rcv = inbox.process(pck);
-            match rcv.await {
+            // Its time to process the packet (the time it takes is the round trip
+            // that feeds the adaptive throttle)
+            let process_start = chrono::offset::Utc::now();
+            let rcv = inbox.process(pck).await;
+            if let Ok(latency) = (chrono::offset::Utc::now() - process_start).to_std() {
+                let mut throttle = throttle.lock().unwrap();
+                if throttle.adaptive {
+                    throttle.record_latency(latency);
+                }
+            }
+            match rcv {
                 Ok(a) => {
                     if hickup_count > 0 {
                         debug!("inbox-recovered: recovered from hickups {}", hickup_count);
@@ -1,2 +1,5 @@
 pub use ate_comms::mesh_key_exchange_receiver;
 pub use ate_comms::mesh_key_exchange_sender;
+pub use ate_comms::mesh_key_exchange_sender_ext;
+pub use ate_comms::mesh_key_exchange_receiver_select;
+pub use ate_comms::mesh_key_exchange_sender_select;
@@ -5,6 +5,7 @@ use crate::spec::*;
 use async_trait::async_trait;
 use error_chain::bail;
 use serde::{de::DeserializeOwned, Serialize};
+use std::collections::VecDeque;
 use std::marker::PhantomData;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -14,8 +15,10 @@ use tokio::net::TcpListener;
 use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
+use tokio::sync::watch;
 use tokio::sync::Mutex;
 use tokio::time::Duration;
+use tokio_rustls::TlsAcceptor;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use tracing_futures::{Instrument, WithSubscriber};
 
@@ -27,17 +30,30 @@ use tokio_tungstenite::WebSocketStream;
 
 use super::conf::*;
 use super::helper::InboxProcessor;
+use super::helper::PacketGuard;
 use super::helper::*;
 use super::key_exchange;
+use super::proxy_protocol::read_proxy_header;
 use super::rx_tx::*;
 use super::stream::*;
+use super::Multiplexer;
+use super::StreamReadable;
+use super::Throttle;
+use super::ConnectionPeer;
+use super::UpstreamOutbox;
 use super::router::*;
+use super::Packet;
 use super::PacketWithContext;
 use super::StreamProtocol;
 use super::StreamRouter;
+use super::PacketTapHook;
+use super::ServerCertificates;
+use super::tls_acceptor;
 use super::hello::HelloMetadata;
+use super::hello::KeepAlive;
+use super::hello::StreamProtocolVersion;
+use super::metrics::*;
 use crate::comms::NodeId;
-use crate::crypto::PrivateEncryptKey;
 use crate::crypto::EncryptKey;
 use crate::engine::TaskEngine;
 
@@ -45,6 +61,111 @@ use crate::engine::TaskEngine;
 struct ListenerNode {
     #[allow(dead_code)]
     path: String,
+    allow_plaintext: bool,
+}
+
+/// Connection that is tracked by the listener along with what is needed to
+/// describe it (e.g. on a dashboard)
+struct TrackedConnection {
+    upstream: Weak<Mutex<Upstream>>,
+    summary: ConnectionSummary,
+    metrics: Arc<StdMutex<Metrics>>,
+}
+
+/// Keeps track of the connections that a listener is still serving so that
+/// a drain can report how many remain and reach them with a hint
+#[derive(Clone)]
+struct ListenerConnections {
+    next_id: Arc<std::sync::atomic::AtomicU64>,
+    upstreams: Arc<StdMutex<fxhash::FxHashMap<u64, TrackedConnection>>>,
+    errors: Arc<StdMutex<VecDeque<ConnectionError>>>,
+    count_tx: Arc<watch::Sender<usize>>,
+    count_rx: watch::Receiver<usize>,
+}
+
+impl Default for ListenerConnections {
+    fn default() -> ListenerConnections {
+        let (count_tx, count_rx) = watch::channel(0usize);
+        ListenerConnections {
+            next_id: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            upstreams: Arc::new(StdMutex::new(fxhash::FxHashMap::default())),
+            errors: Arc::new(StdMutex::new(VecDeque::new())),
+            count_tx: Arc::new(count_tx),
+            count_rx,
+        }
+    }
+}
+
+impl ListenerConnections {
+    fn register(
+        &self,
+        upstream: &Arc<Mutex<Upstream>>,
+        summary: ConnectionSummary,
+        metrics: &Arc<StdMutex<Metrics>>,
+    ) -> ListenerConnection {
+        let id = self.next_id.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
+        let mut guard = self.upstreams.lock().unwrap();
+        guard.insert(
+            id,
+            TrackedConnection {
+                upstream: Arc::downgrade(upstream),
+                summary,
+                metrics: Arc::clone(metrics),
+            },
+        );
+        let _ = self.count_tx.send(guard.len());
+        ListenerConnection {
+            id,
+            connections: self.clone(),
+        }
+    }
+
+    fn upstreams(&self) -> Vec<Arc<Mutex<Upstream>>> {
+        let guard = self.upstreams.lock().unwrap();
+        guard.values().filter_map(|a| a.upstream.upgrade()).collect()
+    }
+
+    fn summaries(&self) -> Vec<ConnectionSummary> {
+        let guard = self.upstreams.lock().unwrap();
+        guard
+            .values()
+            .map(|a| {
+                let metrics = a.metrics.lock().unwrap();
+                let mut ret = a.summary.clone();
+                ret.received = metrics.received;
+                ret.sent = metrics.sent;
+                ret.requests = metrics.requests;
+                ret.inflight_bytes = metrics.inflight_bytes;
+                ret
+            })
+            .collect()
+    }
+
+    fn record_error(&self, addr: SocketAddr, error: String) {
+        let mut guard = self.errors.lock().unwrap();
+        while guard.len() >= METRICS_MAX_RECENT_ERRORS {
+            guard.pop_front();
+        }
+        guard.push_back(ConnectionError {
+            when: chrono::Utc::now(),
+            addr,
+            error,
+        });
+    }
+}
+
+/// Removes the connection from the listener when the connection ends
+struct ListenerConnection {
+    id: u64,
+    connections: ListenerConnections,
+}
+
+impl Drop for ListenerConnection {
+    fn drop(&mut self) {
+        let mut guard = self.connections.upstreams.lock().unwrap();
+        guard.remove(&self.id);
+        let _ = self.connections.count_tx.send(guard.len());
+    }
 }
 
 pub(crate) struct Listener<M, C>
@@ -55,11 +176,23 @@ where
     server_id: NodeId,
     wire_format: SerializationFormat,
     min_encryption: Option<KeySize>,
-    server_cert: Option<PrivateEncryptKey>,
+    server_certs: ServerCertificates,
     timeout: Duration,
+    handshake_timeouts: HandshakeTimeouts,
+    handshake_limiter: HandshakeLimiter,
+    proxy_protocol: bool,
+    tls_acceptor: Option<TlsAcceptor>,
+    packet_tap: Option<PacketTapHook>,
+    max_protocol_version: StreamProtocolVersion,
+    keepalive: Option<KeepAlive>,
+    throttle: Throttle,
+    outbox_high_water: usize,
     handler: Arc<dyn ServerProcessor<M, C>>,
     routes: fxhash::FxHashMap<String, ListenerNode>,
     exit: broadcast::Sender<()>,
+    draining_tx: watch::Sender<bool>,
+    draining_rx: watch::Receiver<bool>,
+    connections: ListenerConnections,
 }
 
 #[async_trait]
@@ -76,6 +209,23 @@ where
     ) -> Result<(), CommsError>;
 
     async fn shutdown(&self, addr: SocketAddr);
+
+    /// Called with the size of every packet before it is decoded, refusing
+    /// it closes the connection (see `InboxProcessor::admit`)
+    async fn admit<'a, 'b>(
+        &'a self,
+        _context: &Arc<C>,
+        _bytes: usize,
+        _tx: &'b mut Tx,
+    ) -> Result<Option<PacketGuard>, CommsError> {
+        Ok(None)
+    }
+
+    /// Message that is sent to the connected clients when the listener is
+    /// drained to ask them to reconnect elsewhere (if the protocol has one)
+    fn drain_hint(&self) -> Option<M> {
+        None
+    }
 }
 
 pub(crate) struct ServerProcessorFascade<M, C>
@@ -94,6 +244,10 @@ where
     M: Send + Sync + Serialize + DeserializeOwned + Clone + Default,
     C: Send + Sync,
 {
+    async fn admit(&mut self, context: &Arc<C>, bytes: usize) -> Result<Option<PacketGuard>, CommsError> {
+        self.handler.admit(context, bytes, &mut self.tx).await
+    }
+
     async fn process(&mut self, pck: PacketWithContext<M, C>) -> Result<(), CommsError> {
         self.handler.process(pck, &mut self.tx).await
     }
@@ -114,28 +268,53 @@ where
         inbox: Arc<dyn ServerProcessor<M, C>>,
         exit: broadcast::Sender<()>,
     ) -> Result<Arc<StdMutex<Listener<M, C>>>, CommsError> {
+        // Streams that run over TLS are accepted with the certificate of the
+        // server (and may skip the wire encryption if so configured)
+        let wire_protocol = conf.cfg_mesh.wire_protocol;
+        let tls_acceptor = match wire_protocol.is_tls() {
+            true => Some(tls_acceptor(&conf.cfg_mesh)?),
+            false => None,
+        };
+        let min_encryption = match wire_protocol.is_tls() && conf.cfg_mesh.tls_wire_encryption == false {
+            true => None,
+            false => conf.listen_min_encryption.clone(),
+        };
+
         // Create the node state and initialize it
+        let (draining_tx, draining_rx) = watch::channel(false);
         let listener = {
             Arc::new(StdMutex::new(Listener {
                 server_id: server_id.clone(),
                 wire_format: conf.cfg_mesh.wire_format,
-                min_encryption: conf.listen_min_encryption.clone(),
-                server_cert: conf.listen_cert.clone(),
+                min_encryption,
+                server_certs: conf.listen_certs.clone(),
                 timeout: conf.cfg_mesh.accept_timeout,
+                handshake_timeouts: conf.cfg_mesh.handshake_timeouts,
+                handshake_limiter: HandshakeLimiter::new(conf.cfg_mesh.max_pre_hello_per_ip),
+                proxy_protocol: conf.cfg_mesh.proxy_protocol,
+                tls_acceptor,
+                packet_tap: conf.cfg_mesh.packet_tap(),
+                max_protocol_version: conf.cfg_mesh.max_protocol_version,
+                keepalive: conf.cfg_mesh.keepalive(),
+                throttle: conf.cfg_mesh.listen_throttle.clone(),
+                outbox_high_water: conf.cfg_mesh.outbox_high_water,
                 handler: Arc::clone(&inbox),
                 routes: fxhash::FxHashMap::default(),
                 exit: exit.clone(),
+                draining_tx,
+                draining_rx: draining_rx.clone(),
+                connections: ListenerConnections::default(),
             }))
         };
 
         // If wire encryption is required then make sure a certificate of sufficient size was supplied
         if let Some(size) = &conf.cfg_mesh.wire_encryption {
-            match conf.listen_cert.as_ref() {
+            match conf.listen_certs.max_size(chrono::Utc::now()) {
                 None => {
                     bail!(CommsErrorKind::MissingCertificate);
                 }
-                Some(a) if a.size() < *size => {
-                    bail!(CommsErrorKind::CertificateTooWeak(size.clone(), a.size()));
+                Some(a) if a < *size => {
+                    bail!(CommsErrorKind::CertificateTooWeak(size.clone(), a));
                 }
                 _ => {}
             }
@@ -147,8 +326,9 @@ where
                 target.clone(),
                 server_id.clone(),
                 Arc::downgrade(&listener),
-                conf.cfg_mesh.wire_protocol,
+                wire_protocol,
                 exit.clone(),
+                draining_rx.clone(),
             )
             .await;
         }
@@ -157,11 +337,17 @@ where
     }
 
     pub(crate) fn add_route(&mut self, path: &str) -> Result<(), CommsError> {
+        self.add_route_ext(path, false)
+    }
+
+    /// Adds a route which may optionally be connected to without wire encryption
+    pub(crate) fn add_route_ext(&mut self, path: &str, allow_plaintext: bool) -> Result<(), CommsError> {
         // Add the node to the lookup
         self.routes.insert(
             path.to_string(),
             ListenerNode {
                 path: path.to_string(),
+                allow_plaintext,
             },
         );
 
@@ -169,12 +355,20 @@ where
         Ok(())
     }
 
+    pub(crate) fn allows_plaintext(&self, path: &str) -> bool {
+        self.routes
+            .get(path)
+            .map(|a| a.allow_plaintext)
+            .unwrap_or(false)
+    }
+
     async fn listen_on(
         addr: SocketAddr,
         server_id: NodeId,
         listener: Weak<StdMutex<Listener<M, C>>>,
         wire_protocol: StreamProtocol,
         exit: broadcast::Sender<()>,
+        mut draining: watch::Receiver<bool>,
     ) {
         let tcp_listener = TcpListener::bind(addr.clone()).await.expect(&format!(
             "Failed to bind listener to address ({})",
@@ -186,7 +380,18 @@ where
         let mut exp_backoff = Duration::from_millis(100);
         TaskEngine::spawn(async move {
             loop {
-                let result = tcp_listener.accept().await;
+                // Once the listener is draining the socket is closed so that
+                // new connections go elsewhere (existing ones keep running)
+                let result = tokio::select! {
+                    a = tcp_listener.accept() => a,
+                    _ = draining.changed() => {
+                        if *draining.borrow() {
+                            info!("stopped listening on: {} (draining)", addr);
+                            break;
+                        }
+                        continue;
+                    }
+                };
 
                 let (stream, sock_addr) = match result {
                     Ok(a) => a,
@@ -218,15 +423,29 @@ where
                 let (
                     wire_format,
                     min_encryption,
-                    server_cert,
+                    server_certs,
                     timeout,
+                    handshake_timeouts,
+                    handshake_limiter,
+                    proxy_protocol,
+                    tls_acceptor,
+                    packet_tap,
+                    max_protocol_version,
+                    keepalive,
                 ) = {
                     let listener = listener.lock().unwrap();
                     (
                         listener.wire_format.clone(),
                         listener.min_encryption.clone(),
-                        listener.server_cert.clone(),
+                        listener.server_certs.clone(),
                         listener.timeout.clone(),
+                        listener.handshake_timeouts.clone(),
+                        listener.handshake_limiter.clone(),
+                        listener.proxy_protocol,
+                        listener.tls_acceptor.clone(),
+                        listener.packet_tap.clone(),
+                        listener.max_protocol_version,
+                        listener.keepalive,
                     )
                 };
 
@@ -234,52 +453,78 @@ where
                     wire_format,
                     wire_protocol,
                     min_encryption,
-                    server_cert,
+                    None,
                     server_id,
                     timeout.clone()
                 );
+                router.set_certificates(server_certs);
+                router.set_handshake(handshake_timeouts, handshake_limiter);
+                router.set_packet_tap(packet_tap);
+                router.set_max_protocol_version(max_protocol_version);
+                router.set_keepalive(keepalive);
                 let adapter = Arc::new(ListenerAdapter {
                     listener,
                     exit: exit.clone(),
                 });
                 router.set_default_route(adapter);
 
-                // Upgrade and split the stream
-                let (rx, tx) = match wire_protocol
-                    .upgrade_server_and_split(stream, timeout)
-                    .await {
-                    Ok(a) => a,
-                    Err(err) => {
-                        warn!("connection-failed(accept): {}", err.to_string());
-                        continue;
+                // The handshake runs in its own task so that a client that
+                // stalls it does not hold up the clients behind it
+                TaskEngine::spawn(async move {
+                    // Behind a load balancer the address of the client is
+                    // carried in the PROXY header that precedes the protocol
+                    let mut stream = stream;
+                    let mut sock_addr = sock_addr;
+                    if proxy_protocol {
+                        match read_proxy_header(&mut stream, handshake_timeouts.hello_read).await {
+                            Ok(Some(a)) => {
+                                trace!("proxied connection from {} (via {})", a, sock_addr);
+                                sock_addr = a;
+                            }
+                            Ok(None) => {}
+                            Err(err) => {
+                                warn!("connection-failed(proxy) from {}: {}", sock_addr, err.to_string());
+                                return;
+                            }
+                        }
                     }
-                };
 
-                match router.accept_socket(rx, tx, sock_addr, None, None)
-                    .instrument(tracing::info_span!(
-                        "server-accept",
-                        id = server_id.to_short_string().as_str()
-                    ))
-                    .await
-                {
-                    Ok(a) => a,
-                    Err(CommsError(CommsErrorKind::IO(err), _))
-                        if err.kind() == std::io::ErrorKind::UnexpectedEof
-                            || err.kind() == std::io::ErrorKind::ConnectionReset
-                            || err.kind() == std::io::ErrorKind::ConnectionAborted
-                            || err.kind() == std::io::ErrorKind::BrokenPipe
-                            || err
-                                .to_string()
-                                .to_lowercase()
-                                .contains("connection reset without closing handshake") =>
+                    // Upgrade and split the stream
+                    let (rx, tx, peer_identity) = match wire_protocol
+                        .upgrade_server_and_split(stream, timeout, tls_acceptor.as_ref())
+                        .await {
+                        Ok(a) => a,
+                        Err(err) => {
+                            warn!("connection-failed(accept): {}", err.to_string());
+                            return;
+                        }
+                    };
+
+                    match router.accept_socket_ext(rx, tx, sock_addr, None, None, peer_identity)
+                        .instrument(tracing::info_span!(
+                            "server-accept",
+                            id = server_id.to_short_string().as_str()
+                        ))
+                        .await
                     {
-                        debug!("{:?}(accept)", err.kind())
-                    }
-                    Err(err) => {
-                        warn!("connection-failed(accept): {}", err.to_string());
-                        continue;
+                        Ok(a) => a,
+                        Err(CommsError(CommsErrorKind::IO(err), _))
+                            if err.kind() == std::io::ErrorKind::UnexpectedEof
+                                || err.kind() == std::io::ErrorKind::ConnectionReset
+                                || err.kind() == std::io::ErrorKind::ConnectionAborted
+                                || err.kind() == std::io::ErrorKind::BrokenPipe
+                                || err
+                                    .to_string()
+                                    .to_lowercase()
+                                    .contains("connection reset without closing handshake") =>
+                        {
+                            debug!("{:?}(accept)", err.kind())
+                        }
+                        Err(err) => {
+                            warn!("connection-failed(accept): {}", err.to_string());
+                        }
                     }
-                }
+                });
             }
         });
     }
@@ -300,42 +545,192 @@ where
         let (
             server_id,
             wire_format,
-            handler
+            throttle,
+            outbox_high_water,
+            handler,
+            connections,
+            is_draining,
         ) = {
             let listener = listener.lock().unwrap();
             (
                 listener.server_id.clone(),
                 listener.wire_format.clone(),
+                listener.throttle.clone(),
+                listener.outbox_high_water,
                 listener.handler.clone(),
+                listener.connections.clone(),
+                listener.is_draining(),
             )
         };
         let node_id = hello.client_id;
+        let version = hello.version;
 
-        let context = Arc::new(C::default());
+        // A draining listener does not take on any new connections (this also
+        // covers web sockets that arrive through a shared web server)
+        if is_draining {
+            debug!("refused connection from {} (draining)", sock_addr);
+            bail!(CommsErrorKind::Refused);
+        }
 
-        // Create an upstream from the tx
-        let tx = Arc::new(Mutex::new(tx));
+        // Multiplexed streams carry many sessions which each get their own
+        // context (and hence their own chain) on this server
+        if hello.multiplex {
+            let outbox = match tx.outbox {
+                UpstreamOutbox::Stream(a) => a,
+                UpstreamOutbox::Channel(_) => {
+                    bail!(CommsErrorKind::InternalError(
+                        "multiplexed channels can not be multiplexed again".to_string()
+                    ));
+                }
+            };
+            let hello_path = hello.path.clone();
+            let draining = listener.lock().unwrap().draining_rx.clone();
+            let mux = Multiplexer::new(outbox, false);
+            TaskEngine::spawn(async move {
+                let accept = |mux: &Arc<Multiplexer>, channel: u32| {
+                    if *draining.borrow() {
+                        debug!("refused channel (id={}) as the listener is draining", channel);
+                        return false;
+                    }
+                    trace!("accepting channel (id={})", channel);
+                    let (tx, rx) = mux.open_channel(channel);
+                    let tx = Upstream {
+                        id: node_id,
+                        outbox: UpstreamOutbox::Channel(tx),
+                        wire_format,
+                    };
+                    Self::spawn_inbox(
+                        Arc::clone(&handler),
+                        rx,
+                        rx_proto,
+                        tx,
+                        hello_path.clone(),
+                        server_id,
+                        node_id,
+                        sock_addr,
+                        wire_format,
+                        version,
+                        wire_encryption.clone(),
+                        throttle.clone(),
+                        outbox_high_water,
+                        connections.clone(),
+                        mux.exit.subscribe(),
+                    );
+                    true
+                };
+                if let Err(err) = mux.run(rx, accept, exit).await {
+                    debug!("multiplexed stream closed: {}", err);
+                }
+            });
+            return Ok(());
+        }
+
+        Self::spawn_inbox(
+            handler,
+            rx,
+            rx_proto,
+            tx,
+            hello.path.clone(),
+            server_id,
+            node_id,
+            sock_addr,
+            wire_format,
+            version,
+            wire_encryption,
+            throttle,
+            outbox_high_water,
+            connections,
+            exit,
+        );
+
+        // Happy days
+        Ok(())
+    }
+
+    fn spawn_inbox<R>(
+        handler: Arc<dyn ServerProcessor<M, C>>,
+        rx: R,
+        rx_proto: StreamProtocol,
+        tx: Upstream,
+        hello_path: String,
+        server_id: NodeId,
+        node_id: NodeId,
+        sock_addr: SocketAddr,
+        wire_format: SerializationFormat,
+        version: StreamProtocolVersion,
+        wire_encryption: Option<EncryptKey>,
+        throttle: Throttle,
+        outbox_high_water: usize,
+        connections: ListenerConnections,
+        exit: broadcast::Receiver<()>,
+    ) where
+        R: StreamReadable + Send + 'static,
+    {
+        let context = Arc::new(C::default());
 
         // Create the metrics and throttles
-        let metrics = Arc::new(StdMutex::new(super::metrics::Metrics::default()));
-        let throttle = Arc::new(StdMutex::new(super::throttle::Throttle::default()));
+        let metrics = Arc::new(StdMutex::new(super::metrics::Metrics {
+            protocol_version: Some(version),
+            ..Default::default()
+        }));
+
+        // Create an upstream from the tx (which is tracked until the inbox exits)
+        let tx = Arc::new(Mutex::new(tx));
+        let summary = ConnectionSummary {
+            node_id,
+            addr: sock_addr,
+            path: hello_path.clone(),
+            connected: chrono::Utc::now(),
+            protocol_version: Some(version),
+            received: 0,
+            sent: 0,
+            requests: 0,
+            inflight_bytes: 0,
+        };
+        let connection = connections.register(&tx, summary, &metrics);
+        let throttle = Arc::new(StdMutex::new(throttle));
+
+        // The connection is closed when the listener exits or when it falls
+        // too far behind on the broadcasts it is sent
+        let (disconnect, _) = broadcast::channel(1);
+        let exit = {
+            let mut exit = exit;
+            let mut stop = disconnect.subscribe();
+            let ret = disconnect.subscribe();
+            let disconnect = disconnect.clone();
+            TaskEngine::spawn(async move {
+                tokio::select! {
+                    _ = exit.recv() => {
+                        let _ = disconnect.send(());
+                    }
+                    _ = stop.recv() => {}
+                }
+            });
+            ret
+        };
+        let member = TxGroupMember::new(&tx, outbox_high_water, disconnect.clone());
 
         // Now lets build a Tx object that is not connected to any of transmit pipes for now
         // (later we will add other ones to create a broadcast group)
         let mut group = TxGroup::default();
-        group.all.insert(node_id, Arc::downgrade(&tx));
+        group.all.insert(node_id, member.clone());
         let tx = Tx {
-            hello_path: hello.path.clone(),
+            hello_path,
             wire_format,
             direction: TxDirection::Downcast(TxGroupSpecific {
                 me_id: node_id,
                 me_tx: Arc::clone(&tx),
+                me_member: member,
                 group: Arc::new(Mutex::new(group)),
             }),
             relay: None,
             metrics: Arc::clone(&metrics),
             throttle: Arc::clone(&throttle),
             exit_dependencies: Vec::new(),
+            peer: Some(ConnectionPeer {
+                node_id,
+                addr: sock_addr,
+            }),
         };
 
         // The fascade makes the transmit object available
@@ -383,18 +778,93 @@ where
                 {
                     debug!("{:?}(inbox)", err.kind())
                 }
-                Err(CommsError(CommsErrorKind::IO(err), _)) => warn!(
-                    "connection-failed (inbox): due to an IO error(kind={:?}) - {}",
-                    err.kind(),
-                    err
-                ),
-                Err(err) => warn!("connection-failed (inbox): {}", err),
+                Err(CommsError(CommsErrorKind::IO(err), _)) => {
+                    warn!(
+                        "connection-failed (inbox): due to an IO error(kind={:?}) - {}",
+                        err.kind(),
+                        err
+                    );
+                    connections.record_error(sock_addr, err.to_string());
+                }
+                Err(err) => {
+                    warn!("connection-failed (inbox): {}", err);
+                    connections.record_error(sock_addr, err.to_string());
+                }
             };
             debug!("disconnected");
+            let _ = disconnect.send(());
+            drop(connection);
         });
+    }
 
-        // Happy days
-        Ok(())
+    /// Number of connections that were aborted while they were handshaking
+    pub(crate) fn dropped_connections(&self) -> u64 {
+        self.handshake_limiter.dropped_connections()
+    }
+
+    /// Certificates that new connections are accepted with
+    pub(crate) fn certificates(&self) -> ServerCertificates {
+        self.server_certs.clone()
+    }
+
+    /// Replaces the certificates that new connections are accepted with (the
+    /// connections that are already established are not affected)
+    pub(crate) fn set_certificates(&mut self, certs: ServerCertificates) {
+        self.server_certs = certs;
+    }
+
+    /// Returns true if the listener has been told to drain
+    pub(crate) fn is_draining(&self) -> bool {
+        *self.draining_rx.borrow()
+    }
+
+    /// Number of connections that this listener is still serving
+    pub(crate) fn connections(&self) -> watch::Receiver<usize> {
+        self.connections.count_rx.clone()
+    }
+
+    /// Describes the connections that this listener is still serving
+    pub(crate) fn connection_summaries(&self) -> Vec<ConnectionSummary> {
+        self.connections.summaries()
+    }
+
+    /// Most recent connections that failed with an error (oldest first)
+    pub(crate) fn recent_errors(&self) -> Vec<ConnectionError> {
+        let guard = self.connections.errors.lock().unwrap();
+        guard.iter().cloned().collect()
+    }
+
+    /// Stops accepting new connections while leaving the existing ones to run
+    /// to completion. When `hint` is set then the connected clients are told
+    /// (if the protocol supports it) to reconnect elsewhere. The returned
+    /// receiver tracks the number of connections that remain.
+    pub(crate) fn drain(&self, hint: bool) -> watch::Receiver<usize> {
+        if self.is_draining() == false {
+            info!("listener is draining ({} connections)", *self.connections.count_rx.borrow());
+        }
+        let _ = self.draining_tx.send(true);
+
+        if let Some(msg) = hint.then(|| self.handler.drain_hint()).flatten() {
+            let upstreams = self.connections.upstreams();
+            let wire_format = self.wire_format;
+            TaskEngine::spawn(async move {
+                let pck = match Packet::from(msg).to_packet_data(wire_format) {
+                    Ok(a) => a,
+                    Err(err) => {
+                        warn!("failed to serialize the drain hint - {}", err);
+                        return;
+                    }
+                };
+                for upstream in upstreams {
+                    let mut upstream = upstream.lock().await;
+                    if let Err(err) = upstream.outbox.write(&pck.bytes[..]).await {
+                        debug!("failed to send the drain hint - {}", err);
+                    }
+                }
+            });
+        }
+
+        self.connections.count_rx.clone()
     }
 }
 
@@ -443,4 +913,13 @@ where M: Send + Sync + Serialize + DeserializeOwned + Clone + Default + 'static,
             self.exit.subscribe(),
         ).await
     }
+
+    fn supports_multiplex(&self) -> bool {
+        true
+    }
+
+    fn allows_plaintext(&self, path: &str) -> bool {
+        let listener = self.listener.lock().unwrap();
+        listener.allows_plaintext(path)
+    }
 }
\ No newline at end of file
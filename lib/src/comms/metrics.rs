@@ -1,7 +1,67 @@
+use chrono::DateTime;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+use crate::comms::NodeId;
+
 #[derive(Debug, Clone, Default)]
 pub struct Metrics {
     pub received: u64,
     pub sent: u64,
     pub requests: u64,
     pub chain_size: u64,
+    pub compactions: u64,
+    pub last_compaction: Option<crate::compact::CompactionStats>,
+    // Rate (bytes/second) currently enforced by an adaptive throttle
+    pub throttle_per_second: Option<u64>,
+    // Number of times the watchdog recovered the chain after it got stuck
+    pub watchdog_recoveries: u64,
+    // Version of the stream protocol that was negotiated with the peer
+    pub protocol_version: Option<crate::comms::StreamProtocolVersion>,
+    // Bytes of transactions received on the connection that are still in flight
+    pub inflight_bytes: u64,
+    // Bytes used and the limit of the storage quota of the chain when the
+    // root last reported that it is nearly full (cleared once it is not)
+    pub quota_warning: Option<(u64, u64)>,
+}
+
+/// Most label values (e.g. chains or peers) that are reported as separate
+/// series by anything that exports the metrics, beyond this the busiest are
+/// kept and the rest are only counted so that huge deployments stay readable
+pub const METRICS_MAX_SERIES: usize = 200;
+
+/// Most recent connection errors that a listener remembers
+pub const METRICS_MAX_RECENT_ERRORS: usize = 50;
+
+/// Connection that a listener is currently serving
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConnectionSummary {
+    pub node_id: NodeId,
+    pub addr: SocketAddr,
+    pub path: String,
+    pub connected: DateTime<Utc>,
+    pub protocol_version: Option<crate::comms::StreamProtocolVersion>,
+    pub received: u64,
+    pub sent: u64,
+    pub requests: u64,
+    /// Bytes of transactions received on the connection that are still in flight
+    #[serde(default)]
+    pub inflight_bytes: u64,
+}
+
+/// Client on the other end of a connection, chains that are opened on behalf
+/// of a connection are told who it was opened for
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConnectionPeer {
+    pub node_id: NodeId,
+    pub addr: SocketAddr,
+}
+
+/// Connection that failed with an error (other than the peer going away)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConnectionError {
+    pub when: DateTime<Utc>,
+    pub addr: SocketAddr,
+    pub error: String,
 }
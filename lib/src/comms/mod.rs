@@ -5,17 +5,24 @@ mod certificate_validation;
 #[cfg(feature = "enable_client")]
 mod client;
 mod conf;
+#[cfg(feature = "enable_grpc")]
+pub mod grpc;
 pub mod hello;
 mod helper;
 pub mod key_exchange;
 #[cfg(feature = "enable_server")]
 mod listener;
 mod metrics;
+mod multiplex;
 mod packet;
+#[cfg(feature = "enable_server")]
+mod proxy_protocol;
 mod rx_tx;
 mod stream;
 mod test;
 mod throttle;
+#[cfg(feature = "enable_full")]
+mod tls;
 mod router;
 
 pub use ate_crypto::NodeId;
@@ -26,7 +33,8 @@ pub(crate) use packet::PacketData;
 pub(crate) use packet::PacketWithContext;
 
 #[allow(unused_imports)]
-pub(crate) use rx_tx::{Tx, TxDirection, TxGroup, TxGroupSpecific};
+pub(crate) use rx_tx::{Tx, TxDirection, TxGroup, TxGroupMember, TxGroupSpecific};
+pub use rx_tx::OUTBOX_DEFAULT_HIGH_WATER;
 
 #[cfg(feature = "enable_client")]
 #[allow(unused_imports)]
@@ -37,6 +45,11 @@ pub(crate) use listener::Listener;
 pub use super::conf::MeshConnectAddr;
 pub use certificate_validation::*;
 pub use metrics::Metrics;
+pub use metrics::ConnectionSummary;
+pub use metrics::ConnectionPeer;
+pub use metrics::ConnectionError;
+pub use metrics::METRICS_MAX_SERIES;
+pub use metrics::METRICS_MAX_RECENT_ERRORS;
 pub use stream::StreamProtocol;
 pub use stream::StreamRx;
 pub use stream::StreamTx;
@@ -45,14 +58,28 @@ pub use stream::StreamWritable;
 pub use stream::MessageProtocolVersion;
 pub use stream::StreamClient;
 pub use stream::StreamSecurity;
+pub use stream::PacketTap;
+pub use stream::PacketTapHook;
+pub use stream::PcapngTap;
+pub use stream::HexDumpTap;
 #[cfg(feature = "enable_dns")]
 pub use stream::Dns;
 pub use conf::Upstream;
+pub use multiplex::UpstreamOutbox;
+pub use multiplex::ChannelTx;
+pub(crate) use multiplex::Multiplexer;
 pub use throttle::Throttle;
+#[cfg(feature = "enable_full")]
+pub use tls::TlsClient;
+#[cfg(all(feature = "enable_full", feature = "enable_server"))]
+pub(crate) use tls::{tls_acceptor, peer_identity as tls_peer_identity};
 pub use router::*;
 pub use hello::HelloMetadata;
+pub use hello::StreamProtocolVersion;
+pub use hello::KeepAlive;
 
 pub(crate) use helper::InboxProcessor;
+pub(crate) use helper::PacketGuard;
 #[cfg(feature = "server")]
 pub(crate) use listener::ServerProcessor;
 #[cfg(feature = "server")]
@@ -0,0 +1,290 @@
+use async_trait::async_trait;
+use fxhash::FxHashMap;
+use std::io;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc;
+use tokio::sync::Mutex;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::crypto::EncryptKey;
+use crate::engine::TaskEngine;
+use crate::error::*;
+
+use super::StreamReadable;
+use super::StreamRx;
+use super::StreamTx;
+
+/// Number of bytes at the front of every multiplexed frame that hold
+/// the channel id (big-endian)
+const CHANNEL_PREFIX_LEN: usize = 4;
+
+/// Number of frames that are buffered for a particular channel before
+/// the shared receive loop waits for it to catch up
+const CHANNEL_BUFFER_SIZE: usize = 256;
+
+fn decode_frame(mut buf: Vec<u8>) -> io::Result<(u32, Vec<u8>)> {
+    if buf.len() < CHANNEL_PREFIX_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "multiplexed frame is missing its channel id",
+        ));
+    }
+    let mut channel = [0u8; CHANNEL_PREFIX_LEN];
+    channel.copy_from_slice(&buf[..CHANNEL_PREFIX_LEN]);
+    let data = buf.split_off(CHANNEL_PREFIX_LEN);
+    Ok((u32::from_be_bytes(channel), data))
+}
+
+/// Shares a single stream between many virtual sessions (channels). Every
+/// frame written to the stream is prefixed with the id of the channel it
+/// belongs to and a frame that holds nothing but the prefix closes that channel.
+#[derive(Debug)]
+pub(crate) struct Multiplexer {
+    outbox: Mutex<StreamTx>,
+    wire_encryption: Option<EncryptKey>,
+    channels: StdMutex<FxHashMap<u32, mpsc::Sender<Vec<u8>>>>,
+    next_channel: AtomicU32,
+    close_when_idle: bool,
+    closing: AtomicBool,
+    pub(crate) exit: broadcast::Sender<()>,
+}
+
+impl Multiplexer {
+    /// Clients close the shared stream once the last channel is released while
+    /// servers keep it open until the client disconnects
+    pub(crate) fn new(outbox: StreamTx, close_when_idle: bool) -> Arc<Multiplexer> {
+        let (exit, _) = broadcast::channel(1);
+        Arc::new(Multiplexer {
+            wire_encryption: outbox.wire_encryption(),
+            outbox: Mutex::new(outbox),
+            channels: StdMutex::new(FxHashMap::default()),
+            next_channel: AtomicU32::new(1),
+            close_when_idle,
+            closing: AtomicBool::new(false),
+            exit,
+        })
+    }
+
+    pub(crate) fn is_closing(&self) -> bool {
+        self.closing.load(Ordering::Acquire)
+    }
+
+    /// Allocates a new channel on this stream (used by the side that initiates sessions)
+    pub(crate) fn alloc_channel(self: &Arc<Self>) -> (ChannelTx, ChannelRx) {
+        let channel = self.next_channel.fetch_add(1, Ordering::AcqRel);
+        self.open_channel(channel)
+    }
+
+    /// Opens a specific channel on this stream (used by the side that accepts sessions)
+    pub(crate) fn open_channel(self: &Arc<Self>, channel: u32) -> (ChannelTx, ChannelRx) {
+        let (tx, rx) = mpsc::channel(CHANNEL_BUFFER_SIZE);
+        {
+            let mut guard = self.channels.lock().unwrap();
+            guard.insert(channel, tx);
+        }
+        trace!("channel opened (id={})", channel);
+        (
+            ChannelTx {
+                channel,
+                closed: false,
+                mux: Arc::clone(self),
+            },
+            ChannelRx { rx },
+        )
+    }
+
+    fn release(self: &Arc<Self>, channel: u32) {
+        let idle = {
+            let mut guard = self.channels.lock().unwrap();
+            guard.remove(&channel);
+            guard.is_empty()
+        };
+        trace!("channel released (id={})", channel);
+
+        // Let the other side know that the channel is gone (this may be
+        // invoked from a drop outside of the runtime hence the check)
+        if self.is_closing() == false && tokio::runtime::Handle::try_current().is_ok() {
+            let mux = Arc::clone(self);
+            TaskEngine::spawn(async move {
+                let mut outbox = mux.outbox.lock().await;
+                let _ = outbox.write(&channel.to_be_bytes()[..]).await;
+            });
+        }
+
+        if idle && self.close_when_idle {
+            debug!("multiplexed stream is idle - closing");
+            self.closing.store(true, Ordering::Release);
+            let _ = self.exit.send(());
+        }
+    }
+
+    /// Reads frames from the shared stream and dispatches them to the channel
+    /// they belong to. Frames for unknown channels are offered to the `accept`
+    /// callback which may open the channel (servers) or ignore it (clients).
+    pub(crate) async fn run<F>(
+        self: Arc<Self>,
+        mut rx: StreamRx,
+        mut accept: F,
+        mut exit: broadcast::Receiver<()>,
+    ) -> Result<(), CommsError>
+    where
+        F: FnMut(&Arc<Multiplexer>, u32) -> bool + Send,
+    {
+        let mut exit_mux = self.exit.subscribe();
+        let ret: Result<(), CommsError> = async {
+            loop {
+                let buf = select! {
+                    _ = exit.recv() => { break; }
+                    _ = exit_mux.recv() => { break; }
+                    a = rx.read() => a.map_err(CommsError::from_wire)?
+                };
+                let (channel, data) = decode_frame(buf)?;
+
+                // Empty frames are used to close channels
+                if data.len() <= 0 {
+                    let mut guard = self.channels.lock().unwrap();
+                    guard.remove(&channel);
+                    trace!("channel closed by peer (id={})", channel);
+                    continue;
+                }
+
+                let mut sender = {
+                    let guard = self.channels.lock().unwrap();
+                    guard.get(&channel).map(|a| a.clone())
+                };
+                if sender.is_none() && accept(&self, channel) {
+                    let guard = self.channels.lock().unwrap();
+                    sender = guard.get(&channel).map(|a| a.clone());
+                }
+                let sender = match sender {
+                    Some(a) => a,
+                    None => {
+                        trace!("frame dropped for unknown channel (id={})", channel);
+                        continue;
+                    }
+                };
+
+                if sender.send(data).await.is_err() {
+                    let mut guard = self.channels.lock().unwrap();
+                    guard.remove(&channel);
+                }
+            }
+            Ok(())
+        }
+        .await;
+
+        // Closing the channels will cause all the virtual sessions to disconnect
+        self.closing.store(true, Ordering::Release);
+        {
+            let mut guard = self.channels.lock().unwrap();
+            guard.clear();
+        }
+        let _ = self.exit.send(());
+        ret
+    }
+}
+
+/// Transmit half of a single channel on a multiplexed stream
+#[derive(Debug)]
+pub struct ChannelTx {
+    channel: u32,
+    closed: bool,
+    mux: Arc<Multiplexer>,
+}
+
+impl ChannelTx {
+    pub fn channel(&self) -> u32 {
+        self.channel
+    }
+
+    pub async fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        // The channel prefix is written in front of the data without
+        // copying them both into a new frame
+        let prefix = self.channel.to_be_bytes();
+        let mut outbox = self.mux.outbox.lock().await;
+        outbox.write_parts(&[&prefix[..], data]).await
+    }
+
+    pub async fn close(&mut self) -> io::Result<()> {
+        if self.closed == false {
+            self.closed = true;
+            self.mux.release(self.channel);
+        }
+        Ok(())
+    }
+
+    pub fn wire_encryption(&self) -> Option<EncryptKey> {
+        self.mux.wire_encryption.clone()
+    }
+}
+
+impl Drop for ChannelTx {
+    fn drop(&mut self) {
+        if self.closed == false {
+            self.closed = true;
+            self.mux.release(self.channel);
+        }
+    }
+}
+
+/// Receive half of a single channel on a multiplexed stream
+pub struct ChannelRx {
+    rx: mpsc::Receiver<Vec<u8>>,
+}
+
+#[async_trait]
+impl StreamReadable for ChannelRx {
+    async fn read(&mut self) -> io::Result<Vec<u8>> {
+        match self.rx.recv().await {
+            Some(a) => Ok(a),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "multiplexed channel was closed",
+            )),
+        }
+    }
+}
+
+/// The outbox of an upstream is either a dedicated stream or a channel
+/// that shares its stream with other sessions
+#[derive(Debug)]
+pub enum UpstreamOutbox {
+    Stream(StreamTx),
+    Channel(ChannelTx),
+}
+
+impl UpstreamOutbox {
+    pub async fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        match self {
+            UpstreamOutbox::Stream(a) => a.write(data).await,
+            UpstreamOutbox::Channel(a) => a.write(data).await,
+        }
+    }
+
+    pub async fn close(&mut self) -> io::Result<()> {
+        match self {
+            UpstreamOutbox::Stream(a) => a.close().await,
+            UpstreamOutbox::Channel(a) => a.close().await,
+        }
+    }
+
+    pub fn wire_encryption(&self) -> Option<EncryptKey> {
+        match self {
+            UpstreamOutbox::Stream(a) => a.wire_encryption(),
+            UpstreamOutbox::Channel(a) => a.wire_encryption(),
+        }
+    }
+}
+
+impl From<StreamTx> for UpstreamOutbox {
+    fn from(tx: StreamTx) -> UpstreamOutbox {
+        UpstreamOutbox::Stream(tx)
+    }
+}
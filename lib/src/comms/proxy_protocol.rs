@@ -0,0 +1,105 @@
+#![allow(unused_imports)]
+use error_chain::bail;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio::time::Duration;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::error::*;
+
+/// Signature that starts every PROXY protocol v2 header
+pub(crate) const PROXY_V2_SIGNATURE: [u8; 12] = [
+    0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+];
+
+const PROXY_V2_CMD_LOCAL: u8 = 0x20;
+const PROXY_V2_CMD_PROXY: u8 = 0x21;
+
+const PROXY_V2_FAMILY_UNSPEC: u8 = 0x00;
+const PROXY_V2_FAMILY_TCP4: u8 = 0x11;
+const PROXY_V2_FAMILY_TCP6: u8 = 0x21;
+
+/// Reads the PROXY protocol v2 header that a load balancer puts in front of
+/// the connection and returns the address of the client it conveys. `None`
+/// is returned for health checks made by the load balancer itself (the
+/// LOCAL command) in which case the address of the socket should be used.
+pub(crate) async fn read_proxy_header(
+    stream: &mut TcpStream,
+    timeout: Duration,
+) -> Result<Option<SocketAddr>, CommsError> {
+    let read = async {
+        let mut header = [0u8; 16];
+        stream.read_exact(&mut header).await?;
+        let len = parse_proxy_header(&header)?;
+
+        let mut body = vec![0u8; len];
+        stream.read_exact(&mut body[..]).await?;
+        parse_proxy_addresses(&header, &body[..])
+    };
+    match crate::engine::timeout(timeout, read).await {
+        Ok(a) => a,
+        Err(_) => bail!(CommsErrorKind::Timeout),
+    }
+}
+
+/// Validates the fixed part of the header and returns the length of the
+/// variable part that follows it
+pub(crate) fn parse_proxy_header(header: &[u8; 16]) -> Result<usize, CommsError> {
+    if header[..12] != PROXY_V2_SIGNATURE {
+        bail!(CommsErrorKind::ProxyHeaderInvalid(
+            "signature is missing".to_string()
+        ));
+    }
+    match header[12] {
+        PROXY_V2_CMD_LOCAL | PROXY_V2_CMD_PROXY => {}
+        a => bail!(CommsErrorKind::ProxyHeaderInvalid(format!(
+            "unsupported version or command ({:#04x})",
+            a
+        ))),
+    }
+    Ok(u16::from_be_bytes([header[14], header[15]]) as usize)
+}
+
+/// Extracts the source address from the variable part of the header (any
+/// TLVs that follow the addresses are ignored)
+pub(crate) fn parse_proxy_addresses(
+    header: &[u8; 16],
+    body: &[u8],
+) -> Result<Option<SocketAddr>, CommsError> {
+    if header[12] == PROXY_V2_CMD_LOCAL {
+        return Ok(None);
+    }
+    match header[13] {
+        PROXY_V2_FAMILY_TCP4 => {
+            if body.len() < 12 {
+                bail!(CommsErrorKind::ProxyHeaderInvalid(
+                    "IPv4 addresses are truncated".to_string()
+                ));
+            }
+            let mut ip = [0u8; 4];
+            ip.copy_from_slice(&body[0..4]);
+            let port = u16::from_be_bytes([body[8], body[9]]);
+            Ok(Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::from(ip)), port)))
+        }
+        PROXY_V2_FAMILY_TCP6 => {
+            if body.len() < 36 {
+                bail!(CommsErrorKind::ProxyHeaderInvalid(
+                    "IPv6 addresses are truncated".to_string()
+                ));
+            }
+            let mut ip = [0u8; 16];
+            ip.copy_from_slice(&body[0..16]);
+            let port = u16::from_be_bytes([body[32], body[33]]);
+            Ok(Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(ip)), port)))
+        }
+        PROXY_V2_FAMILY_UNSPEC => Ok(None),
+        a => bail!(CommsErrorKind::ProxyHeaderInvalid(format!(
+            "unsupported address family ({:#04x})",
+            a
+        ))),
+    }
+}
@@ -1,7 +1,12 @@
 use async_trait::async_trait;
 use error_chain::bail;
+use std::future::Future;
+use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::ops::DerefMut;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex as StdMutex;
 use tokio::sync::Mutex;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncWrite;
@@ -21,15 +26,21 @@ use crate::comms::{
     Upstream,
     StreamProtocol,
     NodeId,
+    PacketTapHook,
+    TlsPeerIdentity,
+    ServerCertificates,
     hello::{
         HelloMetadata,
+        KeepAlive,
+        StreamProtocolVersion,
     },
     key_exchange,
 };
 #[cfg(feature = "enable_server")]
 use crate::comms::{
     hello::{
-        mesh_hello_exchange_receiver
+        mesh_hello_receive,
+        mesh_hello_verify,
     },
 };
 use crate::spec::SerializationFormat;
@@ -56,6 +67,18 @@ where Self: Send + Sync
         sock_addr: SocketAddr,
         wire_encryption: Option<EncryptKey>,
     ) -> Result<(), CommsError>;
+
+    /// Routes that understand frames prefixed with a channel id allow
+    /// clients to share a single connection between many chains
+    fn supports_multiplex(&self) -> bool {
+        false
+    }
+
+    /// Routes that only serve public data may let clients connect to the
+    /// path without wire encryption (even if the server has a certificate)
+    fn allows_plaintext(&self, _path: &str) -> bool {
+        false
+    }
 }
 
 #[async_trait]
@@ -94,6 +117,156 @@ where Self: Send + Sync
         server_id: NodeId,
         body: Vec<u8>,
     ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)>;
+
+    async fn accepted_raw_get_request(
+        &self,
+        _uri: http::Uri,
+        _headers: http::HeaderMap,
+        _sock_addr: SocketAddr,
+        _server_id: NodeId,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        let msg = format!("Bad Request (Not Implemented)").as_bytes().to_vec();
+        Err((msg, StatusCode::BAD_REQUEST))
+    }
+}
+
+/// Checks that the request carries the access token of an admin route as a
+/// bearer token, the comparison takes the same time wherever the supplied
+/// token first differs so that it can not be guessed one byte at a time
+pub fn is_bearer_token(headers: &http::HeaderMap, access_token: &str) -> bool {
+    let auth = match headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|a| a.to_str().ok())
+    {
+        Some(a) => a,
+        None => return false,
+    };
+    let auth = auth.strip_prefix("Bearer ").unwrap_or(auth).as_bytes();
+    let token = access_token.as_bytes();
+
+    let mut diff = auth.len() ^ token.len();
+    for (n, b) in token.iter().enumerate() {
+        diff |= (auth.get(n).copied().unwrap_or(0) ^ *b) as usize;
+    }
+    diff == 0
+}
+
+/// Routes gRPC calls (HTTP/2 requests with an `application/grpc` content
+/// type) whose path starts with the prefix the route was registered on, the
+/// status of failed calls is returned in the trailers of the response
+#[cfg(feature = "enable_server")]
+#[async_trait]
+pub trait GrpcRoute
+where Self: Send + Sync
+{
+    async fn accepted_grpc_request(
+        &self,
+        request: http::Request<hyper_tungstenite::hyper::Body>,
+        sock_addr: SocketAddr,
+        server_id: NodeId,
+    ) -> http::Response<hyper_tungstenite::hyper::Body>;
+}
+
+/// Amount of time that a connecting client is given to complete each stage
+/// of the handshake before the server gives up on it
+#[derive(Debug, Clone, Copy)]
+pub struct HandshakeTimeouts {
+    /// Time to wait for the client to send its hello
+    pub hello_read: Duration,
+    /// Time to wait for the hello of the server to be sent to the client
+    pub hello_write: Duration,
+    /// Time to wait for the exchange of the wire encryption secrets
+    pub key_exchange: Duration,
+}
+
+impl Default for HandshakeTimeouts {
+    fn default() -> HandshakeTimeouts {
+        HandshakeTimeouts {
+            hello_read: Duration::from_secs(5),
+            hello_write: Duration::from_secs(5),
+            key_exchange: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Default number of connections from the same address that may be
+/// waiting to say hello at the same time
+pub const HANDSHAKE_DEFAULT_MAX_PER_IP: usize = 16;
+
+/// Limits the number of connections from a single address that have not yet
+/// said hello and counts the connections that were dropped during the
+/// handshake (the limiter is shared by all the routers of a listener)
+#[derive(Debug, Clone)]
+pub struct HandshakeLimiter {
+    max_per_ip: usize,
+    pending: Arc<StdMutex<FxHashMap<IpAddr, usize>>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Default for HandshakeLimiter {
+    fn default() -> HandshakeLimiter {
+        HandshakeLimiter::new(HANDSHAKE_DEFAULT_MAX_PER_IP)
+    }
+}
+
+impl HandshakeLimiter {
+    pub fn new(max_per_ip: usize) -> HandshakeLimiter {
+        HandshakeLimiter {
+            max_per_ip,
+            pending: Arc::new(StdMutex::new(FxHashMap::default())),
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Reserves a pre-hello slot for the address (or returns None if the
+    /// address already has too many connections waiting to say hello)
+    pub fn try_enter(&self, ip: IpAddr) -> Option<HandshakeSlot> {
+        let mut guard = self.pending.lock().unwrap();
+        let cnt = guard.entry(ip).or_insert(0);
+        if *cnt >= self.max_per_ip {
+            return None;
+        }
+        *cnt += 1;
+        Some(HandshakeSlot {
+            ip,
+            pending: Arc::clone(&self.pending),
+        })
+    }
+
+    /// Records a connection that was aborted during the handshake
+    pub fn record_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Replaces the certificates that the key exchanges are performed with,
+    /// all the valid certificates are advertised so that clients which pinned
+    /// any one of them are able to connect while a certificate is rotated
+    pub fn set_certificates(&mut self, certs: ServerCertificates) {
+        self.server_certs = certs;
+    }
+
+    /// Number of connections that were aborted during the handshake
+    pub fn dropped_connections(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Pre-hello slot of a connection which is released when dropped
+pub struct HandshakeSlot {
+    ip: IpAddr,
+    pending: Arc<StdMutex<FxHashMap<IpAddr, usize>>>,
+}
+
+impl Drop for HandshakeSlot {
+    fn drop(&mut self) {
+        let mut guard = self.pending.lock().unwrap();
+        if let Some(cnt) = guard.get_mut(&self.ip) {
+            *cnt -= 1;
+            if *cnt <= 0 {
+                guard.remove(&self.ip);
+            }
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -101,12 +274,20 @@ pub struct StreamRouter {
     wire_format: SerializationFormat,
     wire_protocol: StreamProtocol,
     min_encryption: Option<KeySize>,
-    server_cert: Option<PrivateEncryptKey>,
+    server_certs: ServerCertificates,
     server_id: NodeId,
     timeout: Duration,
+    handshake_timeouts: HandshakeTimeouts,
+    handshake_limiter: HandshakeLimiter,
+    packet_tap: Option<PacketTapHook>,
+    max_protocol_version: StreamProtocolVersion,
+    keepalive: Option<KeepAlive>,
     post_routes: Mutex<FxHashMap<String, Arc<dyn RawWebRoute>>>,
     put_routes: Mutex<FxHashMap<String, Arc<dyn RawWebRoute>>>,
+    get_routes: Mutex<FxHashMap<String, Arc<dyn RawWebRoute>>>,
     raw_routes: Mutex<FxHashMap<String, Arc<dyn RawStreamRoute>>>,
+    #[cfg(feature = "enable_server")]
+    grpc_routes: Mutex<FxHashMap<String, Arc<dyn GrpcRoute>>>,
     routes: Mutex<FxHashMap<String, Arc<dyn StreamRoute>>>,
     default_route: Option<Arc<dyn StreamRoute>>,
 }
@@ -117,17 +298,55 @@ impl StreamRouter {
             wire_format: format,
             wire_protocol: protocol,
             min_encryption,
-            server_cert,
+            server_certs: ServerCertificates::from(server_cert),
             server_id,
             timeout,
+            handshake_timeouts: HandshakeTimeouts::default(),
+            handshake_limiter: HandshakeLimiter::default(),
+            packet_tap: None,
+            max_protocol_version: StreamProtocolVersion::default(),
+            keepalive: None,
             post_routes: Mutex::new(FxHashMap::default()),
             put_routes: Mutex::new(FxHashMap::default()),
+            get_routes: Mutex::new(FxHashMap::default()),
             raw_routes: Mutex::new(FxHashMap::default()),
+            #[cfg(feature = "enable_server")]
+            grpc_routes: Mutex::new(FxHashMap::default()),
             routes: Mutex::new(FxHashMap::default()),
             default_route: None,
         }
     }
 
+    /// Sets the timeouts of the handshake and the limiter that bounds the
+    /// connections that are waiting to say hello
+    pub fn set_handshake(&mut self, timeouts: HandshakeTimeouts, limiter: HandshakeLimiter) {
+        self.handshake_timeouts = timeouts;
+        self.handshake_limiter = limiter;
+    }
+
+    /// Number of connections that were aborted during the handshake
+    pub fn dropped_connections(&self) -> u64 {
+        self.handshake_limiter.dropped_connections()
+    }
+
+    /// Pins the highest version of the stream protocol that will be
+    /// negotiated with clients (e.g. while a new version is rolled out)
+    pub fn set_max_protocol_version(&mut self, version: StreamProtocolVersion) {
+        self.max_protocol_version = version;
+    }
+
+    /// Offers a keepalive to the clients that connect to this router, peers
+    /// that stop answering its pings are disconnected
+    pub fn set_keepalive(&mut self, keepalive: Option<KeepAlive>) {
+        self.keepalive = keepalive;
+    }
+
+    /// Installs a tap on every connection accepted by this router so that
+    /// the frames they carry can be captured
+    pub fn set_packet_tap(&mut self, hook: Option<PacketTapHook>) {
+        self.packet_tap = hook;
+    }
+
     pub fn set_default_route(&mut self, route: Arc<dyn StreamRoute>) {
         self.default_route = Some(route);
     }
@@ -152,6 +371,20 @@ impl StreamRouter {
         guard.insert(path.to_string(), web_route);
     }
 
+    /// GET requests that do not match a route are left to the web server
+    /// (e.g. to serve static files)
+    pub async fn add_get_route(&mut self, path: &str, web_route: Arc<dyn RawWebRoute>) {
+        let mut guard = self.get_routes.lock().await;
+        guard.insert(path.to_string(), web_route);
+    }
+
+    /// gRPC services are mounted on the path of the service (e.g. "/my.package.MyService/")
+    #[cfg(feature = "enable_server")]
+    pub async fn add_grpc_route(&mut self, path: &str, grpc_route: Arc<dyn GrpcRoute>) {
+        let mut guard = self.grpc_routes.lock().await;
+        guard.insert(path.to_string(), grpc_route);
+    }
+
     #[cfg(feature = "enable_server")]
     pub async fn try_web_request(
         &self,
@@ -188,6 +421,21 @@ impl StreamRouter {
         uri: Option<http::Uri>,
         headers: Option<http::HeaderMap>
     ) -> Result<(), CommsError>
+    {
+        self.accept_socket_ext(rx, tx, sock_addr, uri, headers, None).await
+    }
+
+    /// Accepts a socket that runs inside a TLS tunnel, the identity that the
+    /// client proved with its certificate is passed to the route in the hello
+    pub async fn accept_socket_ext(
+        &self,
+        rx: Box<dyn AsyncRead + Send + Sync + Unpin + 'static>,
+        tx: Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>,
+        sock_addr: SocketAddr,
+        uri: Option<http::Uri>,
+        headers: Option<http::HeaderMap>,
+        peer_identity: Option<TlsPeerIdentity>,
+    ) -> Result<(), CommsError>
     {
         // Attempt to open it with as a raw stream (if a URI is supplied)
         if let (Some(uri), Some(headers)) = (uri, headers)
@@ -211,43 +459,168 @@ impl StreamRouter {
             }
         }
 
-        // Say hello
-        let (mut proto, hello_meta) = mesh_hello_exchange_receiver(
-            rx,
-            tx,
-            self.server_id,
-            self.min_encryption.clone(),
-            self.wire_format,
+        // Multiplexing is only offered to clients if the route they are
+        // connecting to supports it
+        let multiplex = {
+            let routes = self.routes.lock().await;
+            let routes = routes
+                .iter()
+                .map(|(test, route)| (test.clone(), route.supports_multiplex()))
+                .collect::<Vec<_>>();
+            let default = self
+                .default_route
+                .as_ref()
+                .map(|a| a.supports_multiplex())
+                .unwrap_or(false);
+            move |path: &str| {
+                routes
+                    .iter()
+                    .filter(|(test, _)| path.starts_with(test.as_str()))
+                    .map(|(_, multiplex)| *multiplex)
+                    .next()
+                    .unwrap_or(default)
+            }
+        };
+
+        // Routes that serve public data may accept clients without encryption
+        let plaintext = {
+            let routes = self.routes.lock().await;
+            let routes = routes
+                .iter()
+                .map(|(test, route)| (test.clone(), Arc::clone(route)))
+                .collect::<Vec<_>>();
+            let default = self.default_route.clone();
+            move |path: &str| {
+                match routes
+                    .iter()
+                    .filter(|(test, _)| path.starts_with(test.as_str()))
+                    .map(|(_, route)| route)
+                    .next()
+                    .or(default.as_ref())
+                {
+                    Some(route) => route.allows_plaintext(path),
+                    None => false,
+                }
+            }
+        };
+
+        // Say hello (each stage is bounded so that clients which stall the
+        // handshake can not tie up the server)
+        let slot = match self.handshake_limiter.try_enter(sock_addr.ip()) {
+            Some(a) => a,
+            None => {
+                debug!("refused connection from {} (too many pending handshakes)", sock_addr);
+                self.handshake_limiter.record_dropped();
+                bail!(CommsErrorKind::Refused);
+            }
+        };
+        let hello = self.handshake_stage(
+            "hello-read",
+            self.handshake_timeouts.hello_read,
+            mesh_hello_receive(rx, tx),
         )
         .await?;
+        drop(slot);
+
+        let min_encryption = match plaintext(hello.path()) {
+            true => None,
+            false => self.min_encryption.clone(),
+        };
+        let now = chrono::Utc::now();
+        let (mut proto, mut hello_meta) = self.handshake_stage(
+            "hello-write",
+            self.handshake_timeouts.hello_write,
+            hello
+                .with_certificates(self.server_certs.advertise(now))
+                .with_keepalive(self.keepalive)
+                .reply(
+                    self.server_id,
+                    min_encryption,
+                    self.wire_format,
+                    multiplex,
+                    self.max_protocol_version,
+                ),
+        )
+        .await?;
+        hello_meta.peer_identity = peer_identity;
         let wire_encryption = hello_meta.encryption;
         let node_id = hello_meta.client_id;
 
         // If wire encryption is required then make sure a certificate of sufficient size was supplied
         let ek = match &wire_encryption {
             Some(size) => {
-                match self.server_cert.as_ref() {
+                match self.server_certs.max_size(now) {
                     None => {
                         return Err(CommsError::from(CommsErrorKind::MissingCertificate).into());
                     }
-                    Some(a) if a.size() < *size => {
-                        return Err(CommsError::from(CommsErrorKind::CertificateTooWeak(size.clone(), a.size())).into());
+                    Some(a) if a < *size => {
+                        return Err(CommsError::from(CommsErrorKind::CertificateTooWeak(size.clone(), a)).into());
+                    }
+                    Some(_) if hello_meta.select_certificate => {
+                        // The client selects which of the advertised certificates to use
+                        let certs = self.server_certs
+                            .valid(now)
+                            .into_iter()
+                            .filter(|a| a.key.size() >= *size)
+                            .map(|a| a.key.clone())
+                            .collect::<Vec<_>>();
+                        let ek = self.handshake_stage(
+                            "key-exchange",
+                            self.handshake_timeouts.key_exchange,
+                            key_exchange::mesh_key_exchange_receiver_select(proto.deref_mut(), &certs[..]),
+                        )
+                        .await?;
+                        Some(ek)
                     }
-                    Some(server_key) =>
-                    {
+                    Some(_) => {
+                        // Otherwise the oldest certificate is used (which is the
+                        // one that older clients will have pinned)
+                        let server_key = match self.server_certs.primary(now) {
+                            Some(a) if a.key.size() >= *size => a.key.clone(),
+                            Some(a) => {
+                                return Err(CommsError::from(CommsErrorKind::CertificateTooWeak(size.clone(), a.key.size())).into());
+                            }
+                            None => {
+                                return Err(CommsError::from(CommsErrorKind::MissingCertificate).into());
+                            }
+                        };
+
                         // If we are using wire encryption then exchange secrets
-                        let ek = key_exchange::mesh_key_exchange_receiver(proto.deref_mut(), server_key.clone())
-                            .await?;
+                        let ek = self.handshake_stage(
+                            "key-exchange",
+                            self.handshake_timeouts.key_exchange,
+                            key_exchange::mesh_key_exchange_receiver(proto.deref_mut(), server_key),
+                        )
+                        .await?;
                         Some(ek)
                     }
                 }
             }
             None => None
         };
-        let (rx, tx) = proto.split(ek);
+
+        // Make sure that no one tampered with the hello messages (for instance
+        // to downgrade the key size) before the connection was encrypted
+        if let (Some(ek), Some(transcript)) = (ek.as_ref(), hello_meta.transcript.as_ref()) {
+            let verified = self.handshake_stage(
+                "hello-verify",
+                self.handshake_timeouts.key_exchange,
+                mesh_hello_verify(proto.deref_mut(), ek, transcript, false),
+            )
+            .await?;
+            if verified == false {
+                warn!("connection from {} dropped as its hello was tampered with", sock_addr);
+                bail!(CommsErrorKind::HelloTampered);
+            }
+        }
+        let (mut rx, mut tx) = proto.split(ek);
+        if let Some(hook) = self.packet_tap.clone() {
+            rx.set_tap(hook.clone(), node_id);
+            tx.set_tap(hook, node_id);
+        }
         let tx = Upstream {
             id: node_id,
-            outbox: tx,
+            outbox: tx.into(),
             wire_format: self.wire_format,
         };
 
@@ -285,6 +658,29 @@ impl StreamRouter {
         return Ok(());
     }
 
+    /// Runs a stage of the handshake and aborts the connection (counting it
+    /// as dropped) if the stage does not complete in time
+    #[cfg(feature = "enable_server")]
+    async fn handshake_stage<F, R, E>(
+        &self,
+        stage: &str,
+        timeout: Duration,
+        future: F,
+    ) -> Result<R, CommsError>
+    where
+        F: Future<Output = Result<R, E>>,
+        CommsError: From<E>,
+    {
+        match crate::engine::timeout(timeout, future).await {
+            Ok(ret) => Ok(ret?),
+            Err(_) => {
+                debug!("connection dropped as the handshake stalled ({})", stage);
+                self.handshake_limiter.record_dropped();
+                bail!(CommsErrorKind::Timeout);
+            }
+        }
+    }
+
     #[cfg(feature = "enable_server")]
     pub async fn post_request(
         &self,
@@ -352,4 +748,64 @@ impl StreamRouter {
         let msg = format!("Bad Request (No Route)").as_bytes().to_vec();
         return Err((msg, StatusCode::BAD_REQUEST));
     }
-}
\ No newline at end of file
+
+    /// Returns None if no route is registered for the path of the request
+    #[cfg(feature = "enable_server")]
+    pub async fn get_request(
+        &self,
+        sock_addr: SocketAddr,
+        uri: http::Uri,
+        headers: http::HeaderMap,
+    ) -> Option<Result<Vec<u8>, (Vec<u8>, StatusCode)>> {
+        // Get the path
+        let path = uri.path();
+
+        // Look for a registered route for this path
+        let route = {
+            let routes = self.get_routes.lock().await;
+            routes
+                .iter()
+                .filter(|(test, _)| path.starts_with(test.as_str()))
+                .map(|(_, route)| Arc::clone(route))
+                .next()
+        };
+
+        // Execute the accept command
+        match route {
+            Some(route) => Some(
+                route
+                    .accepted_raw_get_request(uri, headers, sock_addr, self.server_id)
+                    .await,
+            ),
+            None => None,
+        }
+    }
+
+    /// Hands the request back if no gRPC route is registered for its path so
+    /// that it can be processed as a normal web request
+    #[cfg(feature = "enable_server")]
+    pub async fn grpc_request(
+        &self,
+        request: http::Request<hyper_tungstenite::hyper::Body>,
+        sock_addr: SocketAddr,
+    ) -> Result<http::Response<hyper_tungstenite::hyper::Body>, http::Request<hyper_tungstenite::hyper::Body>> {
+        // Look for a registered route for this path
+        let route = {
+            let path = request.uri().path();
+            let routes = self.grpc_routes.lock().await;
+            routes
+                .iter()
+                .filter(|(test, _)| path.starts_with(test.as_str()))
+                .map(|(_, route)| Arc::clone(route))
+                .next()
+        };
+
+        // Execute the accept command
+        match route {
+            Some(route) => Ok(route
+                .accepted_grpc_request(request, sock_addr, self.server_id)
+                .await),
+            None => Err(request),
+        }
+    }
+}
@@ -1,19 +1,27 @@
+use bytes::Bytes;
+use error_chain::bail;
 use fxhash::FxHashMap;
 use rand::seq::SliceRandom;
 use serde::{de::DeserializeOwned, Serialize};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex as StdMutex;
 use std::sync::Weak;
 use tokio::sync::broadcast;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
 use tokio::sync::Mutex;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use crate::crypto::EncryptKey;
+use crate::engine::TaskEngine;
 use crate::error::*;
 use crate::prelude::SerializationFormat;
 
 use super::conf::Upstream;
+use super::ConnectionPeer;
 use super::Metrics;
 use super::NodeId;
 use super::Packet;
@@ -21,6 +29,10 @@ use super::PacketData;
 use super::PacketWithContext;
 use super::Throttle;
 
+/// Default number of broadcast packets that may be queued for a member of a
+/// group before it is disconnected
+pub const OUTBOX_DEFAULT_HIGH_WATER: usize = 4096;
+
 #[derive(Debug)]
 pub(crate) enum TxDirection {
     #[cfg(feature = "enable_server")]
@@ -46,6 +58,8 @@ pub(crate) struct Tx {
     pub metrics: Arc<StdMutex<Metrics>>,
     pub throttle: Arc<StdMutex<Throttle>>,
     pub(crate) exit_dependencies: Vec<broadcast::Sender<()>>,
+    /// Client that this transmitter replies to (only known by servers)
+    pub peer: Option<ConnectionPeer>,
 }
 
 impl Tx {
@@ -142,7 +156,7 @@ impl Tx {
             TxDirection::Downcast(tx) => {
                 {
                     let mut new_group = new_group.lock().await;
-                    new_group.all.insert(tx.me_id, Arc::downgrade(&tx.me_tx));
+                    new_group.all.insert(tx.me_id, tx.me_member.clone());
                 }
 
                 let old_group = tx.replace_group(new_group);
@@ -169,6 +183,7 @@ impl Tx {
             metrics: Arc::clone(&self.metrics),
             throttle: Arc::clone(&self.throttle),
             exit_dependencies: Vec::new(),
+            peer: self.peer.clone(),
         };
         ret
     }
@@ -222,6 +237,7 @@ pub(crate) struct TxGroupSpecific {
     #[allow(dead_code)]
     pub me_id: NodeId,
     pub me_tx: Arc<Mutex<Upstream>>,
+    pub me_member: TxGroupMember,
     #[allow(dead_code)]
     pub group: Arc<Mutex<TxGroup>>,
 }
@@ -229,9 +245,7 @@ pub(crate) struct TxGroupSpecific {
 impl TxGroupSpecific {
     #[cfg(feature = "enable_server")]
     pub async fn send_reply(&mut self, pck: PacketData) -> Result<u64, CommsError> {
-        let mut tx = self.me_tx.lock().await;
-        let total_sent = tx.outbox.write(&pck.bytes[..]).await? as u64;
-        Ok(total_sent)
+        self.me_member.reply(&pck.bytes).await
     }
 
     #[cfg(feature = "enable_server")]
@@ -258,29 +272,146 @@ impl TxGroupSpecific {
     }
 }
 
+/// Packet that is waiting to be written to a member, replies carry a
+/// sender that is told once the write has finished
+type TxGroupPacket = (Bytes, Option<oneshot::Sender<u64>>);
+
+/// Member of a broadcast group, packets that are broadcast to the member
+/// are queued and written to its outbox in the background so that a member
+/// that stops reading can not hold up the others. Replies go through the
+/// same queue so they are never written ahead of the broadcasts that were
+/// sent before them.
+#[derive(Debug, Clone)]
+pub(crate) struct TxGroupMember {
+    queue: mpsc::Sender<TxGroupPacket>,
+    high_water: usize,
+    disconnect: broadcast::Sender<()>,
+    overflowed: Arc<AtomicBool>,
+}
+
+impl TxGroupMember {
+    /// Once the queue reaches the high-water mark the member is disconnected
+    /// by sending on the `disconnect` channel (which also stops the writer)
+    pub(crate) fn new(
+        upstream: &Arc<Mutex<Upstream>>,
+        high_water: usize,
+        disconnect: broadcast::Sender<()>,
+    ) -> TxGroupMember {
+        let high_water = high_water.max(1);
+        let (queue, mut rx) = mpsc::channel::<TxGroupPacket>(high_water);
+        let upstream = Arc::downgrade(upstream);
+        let mut exit = disconnect.subscribe();
+        TaskEngine::spawn(async move {
+            loop {
+                let data = tokio::select! {
+                    a = rx.recv() => a,
+                    _ = exit.recv() => None,
+                };
+                let (data, ack) = match data {
+                    Some(a) => a,
+                    None => break,
+                };
+                let upstream = match Weak::upgrade(&upstream) {
+                    Some(a) => a,
+                    None => break,
+                };
+
+                // The write is abandoned if the member is disconnected while
+                // it is blocked (otherwise it would hold the outbox forever)
+                let ret = tokio::select! {
+                    ret = async {
+                        let mut upstream = upstream.lock().await;
+                        upstream.outbox.write(&data[..]).await
+                    } => ret,
+                    _ = exit.recv() => break,
+                };
+                match ret {
+                    Ok(amt) => {
+                        if let Some(ack) = ack {
+                            let _ = ack.send(amt as u64);
+                        }
+                    }
+                    Err(err) => {
+                        debug!("write to group member failed - {}", err);
+                        break;
+                    }
+                }
+            }
+        });
+
+        TxGroupMember {
+            queue,
+            high_water,
+            disconnect,
+            overflowed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Number of packets that are waiting to be written to the member
+    #[allow(dead_code)]
+    pub(crate) fn queued(&self) -> usize {
+        self.high_water - self.queue.capacity()
+    }
+
+    /// Writes a reply to this member behind anything that is already queued
+    /// for it, replies wait for room in the queue rather than being dropped
+    pub(crate) async fn reply(&self, bytes: &Bytes) -> Result<u64, CommsError> {
+        let (tx, rx) = oneshot::channel();
+        if self.queue.send((bytes.clone(), Some(tx))).await.is_err() {
+            bail!(CommsErrorKind::Disconnected);
+        }
+        match rx.await {
+            Ok(amt) => Ok(amt),
+            Err(_) => bail!(CommsErrorKind::Disconnected),
+        }
+    }
+
+    /// Queues the packet for this member, returns false if the member has
+    /// gone or has been disconnected for exceeding its high-water mark
+    fn send(&self, bytes: &Bytes) -> bool {
+        match self.queue.try_send((bytes.clone(), None)) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                if self.overflowed.swap(true, Ordering::AcqRel) == false {
+                    warn!("disconnecting a client that is not reading its broadcasts (high-water mark reached)");
+                    let _ = self.disconnect.send(());
+                }
+                false
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct TxGroup {
     #[allow(dead_code)]
-    pub all: FxHashMap<NodeId, Weak<Mutex<Upstream>>>,
+    pub all: FxHashMap<NodeId, TxGroupMember>,
 }
 
 impl TxGroup {
+    /// Queues the packet for all the members of the group (members that
+    /// are gone or wedged are removed from the group), the members share
+    /// the buffer of the packet rather than each being given a copy of it
     #[cfg(feature = "enable_server")]
     pub(crate) async fn send(
         &mut self,
         pck: PacketData,
         skip: Option<NodeId>,
     ) -> u64 {
+        let bytes = pck.bytes;
+        let len = bytes.len() as u64;
         let mut total_sent = 0u64;
-        let all = self.all.values().filter_map(|a| Weak::upgrade(a));
-        for tx in all {
-            let mut tx = tx.lock().await;
-            if Some(tx.id) != skip {
-                if let Ok(amt) = tx.outbox.write(&pck.bytes[..]).await {
-                    total_sent += amt as u64;
-                }
+        self.all.retain(|id, member| {
+            if Some(*id) == skip {
+                return true;
             }
-        }
+            if member.send(&bytes) {
+                total_sent += len;
+                return true;
+            }
+            false
+        });
         total_sent
     }
 }
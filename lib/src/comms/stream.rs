@@ -36,6 +36,10 @@ use crate::comms::PacketData;
 use crate::crypto::EncryptKey;
 #[cfg(feature = "enable_full")]
 use super::helper::setup_tcp_stream;
+#[cfg(feature = "enable_full")]
+use super::TlsClient;
+#[cfg(all(feature = "enable_full", feature = "enable_server"))]
+use super::TlsPeerIdentity;
 
 pub use ate_comms::StreamRx;
 pub use ate_comms::StreamTx;
@@ -44,6 +48,10 @@ pub use ate_comms::StreamWritable;
 pub use ate_comms::MessageProtocolVersion;
 pub use ate_comms::StreamClient;
 pub use ate_comms::StreamSecurity;
+pub use ate_comms::PacketTap;
+pub use ate_comms::PacketTapHook;
+pub use ate_comms::PcapngTap;
+pub use ate_comms::HexDumpTap;
 #[cfg(feature = "enable_dns")]
 pub use ate_comms::Dns;
 
@@ -69,6 +77,8 @@ use ate_comms::MessageProtocolApi;
 #[derive(Debug, Clone, Copy)]
 pub enum StreamProtocol {
     Tcp,
+    /// Raw TCP stream that runs inside a TLS tunnel (rustls)
+    Tls,
     WebSocket,
     SecureWebSocket,
 }
@@ -79,6 +89,7 @@ impl std::str::FromStr for StreamProtocol {
     fn from_str(s: &str) -> Result<StreamProtocol, CommsError> {
         let ret = match s {
             "tcp" => StreamProtocol::Tcp,
+            "tls" => StreamProtocol::Tls,
             "ws" => StreamProtocol::WebSocket,
             "wss" => StreamProtocol::SecureWebSocket,
             _ => {
@@ -93,6 +104,7 @@ impl StreamProtocol {
     pub fn to_scheme(&self) -> String {
         let ret = match self {
             StreamProtocol::Tcp => "tcp",
+            StreamProtocol::Tls => "tls",
             StreamProtocol::WebSocket => "ws",
             StreamProtocol::SecureWebSocket => "wss",
         };
@@ -106,6 +118,7 @@ impl StreamProtocol {
     pub fn default_port(&self) -> u16 {
         match self {
             StreamProtocol::Tcp => 5000,
+            StreamProtocol::Tls => 5443,
             StreamProtocol::WebSocket => 80,
             StreamProtocol::SecureWebSocket => 443,
         }
@@ -114,14 +127,23 @@ impl StreamProtocol {
     pub fn is_tcp(&self) -> bool {
         match self {
             StreamProtocol::Tcp => true,
+            StreamProtocol::Tls => true,
             StreamProtocol::WebSocket => false,
             StreamProtocol::SecureWebSocket => false,
         }
     }
 
+    pub fn is_tls(&self) -> bool {
+        match self {
+            StreamProtocol::Tls => true,
+            _ => false,
+        }
+    }
+
     pub fn is_web_socket(&self) -> bool {
         match self {
             StreamProtocol::Tcp => false,
+            StreamProtocol::Tls => false,
             StreamProtocol::WebSocket => true,
             StreamProtocol::SecureWebSocket => true,
         }
@@ -161,7 +183,7 @@ impl StreamProtocol {
     }
 
     #[cfg(feature = "enable_full")]
-    pub async fn upgrade_client_and_split(&self, stream: TcpStream) -> Result<
+    pub async fn upgrade_client_and_split(&self, stream: TcpStream, tls: Option<&TlsClient>) -> Result<
         (
             Box<dyn AsyncRead + Send + Sync + Unpin + 'static>,
             Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>
@@ -179,6 +201,24 @@ impl StreamProtocol {
                     Box::new(tx)
                 ))
             },
+            StreamProtocol::Tls => {
+                let tls = match tls {
+                    Some(a) => a,
+                    None => {
+                        bail!(CommsErrorKind::TlsError("the client has no TLS configuration".to_string()));
+                    }
+                };
+                let stream = tls
+                    .connector
+                    .connect(tls.dns_name()?, stream)
+                    .await
+                    .map_err(|err| CommsErrorKind::TlsError(err.to_string()))?;
+                let (rx, tx) = tokio::io::split(stream);
+                Ok((
+                    Box::new(rx),
+                    Box::new(tx)
+                ))
+            },
             wire_protocol if self.is_web_socket() => {
                 let port = match wire_protocol {
                     StreamProtocol::SecureWebSocket => 443,
@@ -214,11 +254,20 @@ impl StreamProtocol {
         }
     }
 
-    #[cfg(feature = "enable_full")]
-    pub async fn upgrade_server_and_split(&self, stream: TcpStream, timeout: Duration) -> Result<
+    /// Upgrades a stream that was accepted by a server, when the stream runs
+    /// over TLS the identity of the client is also returned (if it presented
+    /// a certificate)
+    #[cfg(all(feature = "enable_full", feature = "enable_server"))]
+    pub async fn upgrade_server_and_split(
+        &self,
+        stream: TcpStream,
+        timeout: Duration,
+        tls: Option<&tokio_rustls::TlsAcceptor>,
+    ) -> Result<
         (
             Box<dyn AsyncRead + Send + Sync + Unpin + 'static>,
-            Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>
+            Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>,
+            Option<TlsPeerIdentity>,
         ), CommsError>
     {    
         // Setup the TCP stream
@@ -230,7 +279,27 @@ impl StreamProtocol {
                 let (rx, tx) = stream.into_split();
                 Ok((
                     Box::new(rx),
-                    Box::new(tx)
+                    Box::new(tx),
+                    None
+                ))
+            },
+            StreamProtocol::Tls => {
+                let tls = match tls {
+                    Some(a) => a,
+                    None => {
+                        bail!(CommsErrorKind::TlsError("the listener has no TLS configuration".to_string()));
+                    }
+                };
+                let wait = tls.accept(stream);
+                let stream = tokio_timeout(timeout, wait)
+                    .await?
+                    .map_err(|err| CommsErrorKind::TlsError(err.to_string()))?;
+                let peer_identity = super::tls_peer_identity(stream.get_ref().1);
+                let (rx, tx) = tokio::io::split(stream);
+                Ok((
+                    Box::new(rx),
+                    Box::new(tx),
+                    peer_identity
                 ))
             },
             StreamProtocol::WebSocket |
@@ -243,7 +312,8 @@ impl StreamProtocol {
                 let (sink, stream) = socket.split();
                 Ok((
                     Box::new(wasmer_bus_ws::ws::RecvHalf::new(stream)),
-                    Box::new(wasmer_bus_ws::ws::SendHalf::new(sink))
+                    Box::new(wasmer_bus_ws::ws::SendHalf::new(sink)),
+                    None
                 ))
             }
         }
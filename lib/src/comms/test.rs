@@ -195,3 +195,822 @@ async fn test_server_client_for_comms(
     }
     .await
 }
+
+#[cfg(all(feature = "enable_server", feature = "enable_client", feature = "enable_dns"))]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_server_client_for_comms_with_multiplex() -> Result<(), AteError> {
+    use crate::comms::helper::InboxProcessor;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::atomic::Ordering;
+
+    crate::utils::bootstrap_test_env();
+
+    let port = 4021;
+    let wire_protocol = StreamProtocol::Tcp;
+    let wire_format = SerializationFormat::MessagePack;
+
+    // Start a server that echos back the text that was sent to it
+    #[derive(Debug, Clone, Default)]
+    struct ServerHandler {}
+    #[async_trait]
+    impl ServerProcessor<TestMessage, DummyContext> for ServerHandler {
+        async fn process(
+            &'_ self,
+            pck: PacketWithContext<TestMessage, DummyContext>,
+            tx: &'_ mut Tx,
+        ) -> Result<(), CommsError> {
+            if let TestMessage::Ping(txt) = pck.packet.msg {
+                tx.send_reply_msg(TestMessage::Pong(txt)).await?;
+            }
+            Ok(())
+        }
+        async fn shutdown(&self, _addr: SocketAddr) {}
+    }
+
+    let mut cfg = mock_test_mesh(port);
+    cfg.wire_protocol = wire_protocol;
+    cfg.wire_format = wire_format;
+    let cfg = MeshConfig::new(cfg).listen_on(IpAddr::from_str("127.0.0.1").unwrap(), port);
+    let (exit_tx, _exit_rx) = broadcast::channel(1);
+    let listener = Listener::new(
+        &cfg,
+        NodeId::generate_server_id(0),
+        Arc::new(ServerHandler::default()),
+        exit_tx,
+    )
+    .await?;
+    {
+        let mut guard = listener.lock().unwrap();
+        guard.add_route("/comm-test")?;
+    }
+
+    // Each client session must only see the replies for its own channel
+    #[derive(Debug, Clone)]
+    struct ClientHandler {
+        expect: String,
+        received: Arc<AtomicU32>,
+    }
+    #[async_trait]
+    impl InboxProcessor<TestMessage, ()> for ClientHandler {
+        async fn process(&mut self, pck: PacketWithContext<TestMessage, ()>) -> Result<(), CommsError> {
+            if let TestMessage::Pong(txt) = pck.packet.msg {
+                assert_eq!(self.expect, txt);
+                self.received.fetch_add(1, Ordering::AcqRel);
+            } else {
+                panic!("Wrong message type returned")
+            }
+            Ok(())
+        }
+        async fn shutdown(&mut self, _addr: SocketAddr) {}
+    }
+
+    let mut cfg = mock_test_mesh(port);
+    cfg.wire_protocol = wire_protocol;
+    cfg.wire_format = wire_format;
+    cfg.multiplex = true;
+    let cfg = MeshConfig::new(cfg).connect_to(MeshAddress {
+        host: IpAddr::from_str("127.0.0.1").unwrap(),
+        port,
+    });
+
+    let client_id = NodeId::generate_client_id();
+    let (exit_tx, _exit_rx) = broadcast::channel(1);
+    let mut sessions = Vec::new();
+    for name in ["first", "second"] {
+        let received = Arc::new(AtomicU32::new(0));
+        let inbox = ClientHandler {
+            expect: name.to_string(),
+            received: Arc::clone(&received),
+        };
+        let tx = super::connect(
+            &cfg,
+            "/comm-test".to_string(),
+            client_id,
+            inbox,
+            Arc::new(StdMutex::new(Metrics::default())),
+            Arc::new(StdMutex::new(Throttle::default())),
+            exit_tx.subscribe(),
+        )
+        .await?;
+        sessions.push((name, tx, received));
+    }
+
+    // Both sessions should be sharing the same connection
+    for (_, tx, _) in sessions.iter() {
+        match &tx.direction {
+            super::TxDirection::Upcast(upstream) => {
+                assert!(matches!(upstream.outbox, super::UpstreamOutbox::Channel(_)));
+            }
+            _ => panic!("the client should be connected upstream"),
+        }
+    }
+
+    for (name, tx, _) in sessions.iter_mut() {
+        for _ in 0..10 {
+            tx.send_reply_msg(TestMessage::Ping(name.to_string())).await?;
+        }
+    }
+
+    for _ in 0..100 {
+        if sessions.iter().all(|(_, _, a)| a.load(Ordering::Acquire) >= 10) {
+            break;
+        }
+        crate::engine::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    for (_, _, received) in sessions.iter() {
+        assert_eq!(received.load(Ordering::Acquire), 10);
+    }
+    Ok(())
+}
+
+#[cfg(all(feature = "enable_server", feature = "enable_client", feature = "enable_dns"))]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_listener_drain() -> Result<(), AteError> {
+    use crate::comms::helper::InboxProcessor;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::atomic::Ordering;
+
+    crate::utils::bootstrap_test_env();
+
+    let port = 4031;
+    let wire_protocol = StreamProtocol::Tcp;
+    let wire_format = SerializationFormat::MessagePack;
+
+    // Start a server that asks its clients to reconnect when its drained
+    #[derive(Debug, Clone, Default)]
+    struct ServerHandler {}
+    #[async_trait]
+    impl ServerProcessor<TestMessage, DummyContext> for ServerHandler {
+        async fn process(
+            &'_ self,
+            _pck: PacketWithContext<TestMessage, DummyContext>,
+            _tx: &'_ mut Tx,
+        ) -> Result<(), CommsError> {
+            Ok(())
+        }
+        async fn shutdown(&self, _addr: SocketAddr) {}
+        fn drain_hint(&self) -> Option<TestMessage> {
+            Some(TestMessage::Pong("drain".to_string()))
+        }
+    }
+
+    let mut cfg = mock_test_mesh(port);
+    cfg.wire_protocol = wire_protocol;
+    cfg.wire_format = wire_format;
+    cfg.multiplex = false;
+    let cfg = MeshConfig::new(cfg).listen_on(IpAddr::from_str("127.0.0.1").unwrap(), port);
+    let (exit_tx, _exit_rx) = broadcast::channel(1);
+    let listener = Listener::new(
+        &cfg,
+        NodeId::generate_server_id(0),
+        Arc::new(ServerHandler::default()),
+        exit_tx,
+    )
+    .await?;
+    let remaining = {
+        let mut guard = listener.lock().unwrap();
+        guard.add_route("/comm-test")?;
+        guard.connections()
+    };
+
+    #[derive(Debug, Clone)]
+    struct ClientHandler {
+        hints: Arc<AtomicU32>,
+    }
+    #[async_trait]
+    impl InboxProcessor<TestMessage, ()> for ClientHandler {
+        async fn process(&mut self, pck: PacketWithContext<TestMessage, ()>) -> Result<(), CommsError> {
+            if let TestMessage::Pong(txt) = pck.packet.msg {
+                assert_eq!(txt, "drain");
+                self.hints.fetch_add(1, Ordering::AcqRel);
+            }
+            Ok(())
+        }
+        async fn shutdown(&mut self, _addr: SocketAddr) {}
+    }
+
+    let mut cfg = mock_test_mesh(port);
+    cfg.wire_protocol = wire_protocol;
+    cfg.wire_format = wire_format;
+    cfg.multiplex = false;
+    let cfg = MeshConfig::new(cfg).connect_to(MeshAddress {
+        host: IpAddr::from_str("127.0.0.1").unwrap(),
+        port,
+    });
+
+    let connect = |inbox: ClientHandler, exit: broadcast::Receiver<()>| {
+        super::connect(
+            &cfg,
+            "/comm-test".to_string(),
+            NodeId::generate_client_id(),
+            inbox,
+            Arc::new(StdMutex::new(Metrics::default())),
+            Arc::new(StdMutex::new(Throttle::default())),
+            exit,
+        )
+    };
+
+    let hints = Arc::new(AtomicU32::new(0));
+    let (client_exit_tx, _) = broadcast::channel(1);
+    let client = connect(
+        ClientHandler { hints: Arc::clone(&hints) },
+        client_exit_tx.subscribe(),
+    )
+    .await?;
+
+    // The listener should be tracking the connection
+    for _ in 0..100 {
+        if *remaining.borrow() == 1 {
+            break;
+        }
+        crate::engine::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert_eq!(*remaining.borrow(), 1);
+
+    // Draining sends the hint to the client but leaves it connected
+    let remaining = listener.lock().unwrap().drain(true);
+    for _ in 0..100 {
+        if hints.load(Ordering::Acquire) >= 1 {
+            break;
+        }
+        crate::engine::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert_eq!(hints.load(Ordering::Acquire), 1);
+    assert_eq!(*remaining.borrow(), 1);
+
+    // New connections are no longer accepted
+    let (other_exit_tx, _) = broadcast::channel(1);
+    let other = crate::engine::timeout(
+        std::time::Duration::from_secs(5),
+        connect(
+            ClientHandler { hints: Arc::new(AtomicU32::new(0)) },
+            other_exit_tx.subscribe(),
+        ),
+    )
+    .await;
+    assert!(!matches!(other, Ok(Ok(_))), "a draining listener accepted a connection");
+
+    // Once the client goes away there is nothing left to drain
+    drop(client);
+    let _ = client_exit_tx.send(());
+    for _ in 0..100 {
+        if *remaining.borrow() == 0 {
+            break;
+        }
+        crate::engine::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert_eq!(*remaining.borrow(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_adaptive_throttle() {
+    crate::utils::bootstrap_test_env();
+
+    let mut throttle = Throttle::adaptive(1000, 101000);
+    assert_eq!(throttle.effective_per_second(), Some(101000));
+
+    // Healthy round trips keep the rate at the ceiling
+    for _ in 0..10 {
+        throttle.record_latency(std::time::Duration::from_millis(2));
+        assert_eq!(throttle.adapt(0), Some(101000));
+    }
+
+    // A deep packet queue halves the rate but never drops below the floor
+    assert_eq!(throttle.adapt(1000), Some(50500));
+    for _ in 0..20 {
+        throttle.adapt(1000);
+    }
+    assert_eq!(throttle.effective_per_second(), Some(1000));
+
+    // Slow round trips are also treated as congestion
+    throttle.set_adaptive(1000, 101000);
+    throttle.record_latency(std::time::Duration::from_millis(2));
+    for _ in 0..10 {
+        throttle.record_latency(std::time::Duration::from_millis(200));
+    }
+    assert_eq!(throttle.adapt(0), Some(50500));
+
+    // Once the congestion clears the rate climbs back additively
+    for _ in 0..100 {
+        throttle.record_latency(std::time::Duration::from_millis(2));
+    }
+    assert_eq!(throttle.adapt(0), Some(51500));
+
+    // Fixed throttles do not adapt
+    let mut fixed = Throttle::default();
+    assert_eq!(fixed.adapt(1000), None);
+}
+
+#[cfg(all(feature = "enable_server", feature = "enable_dns"))]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_listener_handshake_timeout() -> Result<(), AteError> {
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpStream;
+
+    crate::utils::bootstrap_test_env();
+
+    let port = 4051;
+
+    #[derive(Debug, Clone, Default)]
+    struct ServerHandler {}
+    #[async_trait]
+    impl ServerProcessor<TestMessage, DummyContext> for ServerHandler {
+        async fn process(
+            &'_ self,
+            _pck: PacketWithContext<TestMessage, DummyContext>,
+            _tx: &'_ mut Tx,
+        ) -> Result<(), CommsError> {
+            Ok(())
+        }
+        async fn shutdown(&self, _addr: SocketAddr) {}
+    }
+
+    // Start a server that gives clients very little time to say hello and
+    // only lets one connection per address wait to do so
+    let mut cfg = mock_test_mesh(port);
+    cfg.wire_protocol = StreamProtocol::Tcp;
+    cfg.handshake_timeouts.hello_read = Duration::from_millis(500);
+    cfg.max_pre_hello_per_ip = 1;
+    let cfg = MeshConfig::new(cfg).listen_on(IpAddr::from_str("127.0.0.1").unwrap(), port);
+    let (exit_tx, _exit_rx) = broadcast::channel(1);
+    let listener = Listener::new(
+        &cfg,
+        NodeId::generate_server_id(0),
+        Arc::new(ServerHandler::default()),
+        exit_tx,
+    )
+    .await?;
+    listener.lock().unwrap().add_route("/comm-test")?;
+
+    // Open a socket and then say nothing
+    let mut stalled = TcpStream::connect(("127.0.0.1", port)).await?;
+    crate::engine::sleep(Duration::from_millis(100)).await;
+
+    // Another connection from the same address is turned away straight away
+    let mut other = TcpStream::connect(("127.0.0.1", port)).await?;
+    let mut buf = [0u8; 16];
+    let read = crate::engine::timeout(Duration::from_millis(400), other.read(&mut buf)).await;
+    assert!(matches!(read, Ok(Ok(0)) | Ok(Err(_))), "the second pre-hello connection was not closed");
+
+    // The stalled connection is closed by the server once the hello times out
+    let read = crate::engine::timeout(Duration::from_secs(5), stalled.read(&mut buf)).await;
+    assert!(matches!(read, Ok(Ok(0)) | Ok(Err(_))), "the server did not close the stalled connection");
+    assert_eq!(listener.lock().unwrap().dropped_connections(), 2);
+    Ok(())
+}
+
+#[cfg(all(feature = "enable_server", feature = "enable_client", feature = "enable_dns"))]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_listener_hello_tampered() -> Result<(), AteError> {
+    use crate::comms::helper::InboxProcessor;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    crate::utils::bootstrap_test_env();
+
+    let port = 4061;
+    let proxy_port = 4062;
+    let cert = PrivateEncryptKey::generate(KeySize::Bit192);
+
+    #[derive(Debug, Clone, Default)]
+    struct ServerHandler {}
+    #[async_trait]
+    impl ServerProcessor<TestMessage, DummyContext> for ServerHandler {
+        async fn process(
+            &'_ self,
+            _pck: PacketWithContext<TestMessage, DummyContext>,
+            _tx: &'_ mut Tx,
+        ) -> Result<(), CommsError> {
+            Ok(())
+        }
+        async fn shutdown(&self, _addr: SocketAddr) {}
+    }
+
+    // Start an encrypted server
+    let mut cfg = mock_test_mesh(port);
+    cfg.wire_protocol = StreamProtocol::Tcp;
+    cfg.wire_encryption = Some(KeySize::Bit192);
+    let cfg = MeshConfig::new(cfg)
+        .listen_on(IpAddr::from_str("127.0.0.1").unwrap(), port)
+        .listen_cert(cert.clone());
+    let (exit_tx, _exit_rx) = broadcast::channel(1);
+    let listener = Listener::new(
+        &cfg,
+        NodeId::generate_server_id(0),
+        Arc::new(ServerHandler::default()),
+        exit_tx,
+    )
+    .await?;
+    listener.lock().unwrap().add_route("/comm-test")?;
+
+    // Put a man-in-the-middle in front of it that rewrites the hello from
+    // the client (without changing what it means) and relays everything else
+    let proxy = TcpListener::bind(("127.0.0.1", proxy_port)).await?;
+    TaskEngine::spawn(async move {
+        let (mut client, _) = proxy.accept().await.unwrap();
+        let mut server = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+        let len = client.read_u16().await.unwrap() as usize;
+        let mut hello = vec![0u8; len];
+        client.read_exact(&mut hello[..]).await.unwrap();
+        hello.insert(1, b' ');
+        server.write_u16(hello.len() as u16).await.unwrap();
+        server.write_all(&hello[..]).await.unwrap();
+
+        let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+    });
+
+    #[derive(Debug, Clone, Default)]
+    struct ClientHandler {}
+    #[async_trait]
+    impl InboxProcessor<TestMessage, ()> for ClientHandler {
+        async fn process(
+            &mut self,
+            _pck: PacketWithContext<TestMessage, ()>,
+        ) -> Result<(), CommsError> {
+            Ok(())
+        }
+        async fn shutdown(&mut self, _addr: SocketAddr) {}
+    }
+
+    // Connecting through the proxy must fail once the keys are exchanged
+    let mut cfg = mock_test_mesh(proxy_port);
+    cfg.wire_protocol = StreamProtocol::Tcp;
+    cfg.wire_encryption = Some(KeySize::Bit192);
+    cfg.fail_fast = true;
+    cfg.certificate_validation = CertificateValidation::AllowedCertificates(vec![cert.hash()]);
+    let cfg = MeshConfig::new(cfg).connect_to(MeshAddress {
+        host: IpAddr::from_str("127.0.0.1").unwrap(),
+        port: proxy_port,
+    });
+    let (_exit_tx, exit_rx) = broadcast::channel(1);
+    let ret = super::connect(
+        &cfg,
+        "/comm-test".to_string(),
+        NodeId::generate_client_id(),
+        ClientHandler::default(),
+        Arc::new(StdMutex::new(Metrics::default())),
+        Arc::new(StdMutex::new(Throttle::default())),
+        exit_rx,
+    )
+    .await;
+    assert!(
+        matches!(ret, Err(CommsError(CommsErrorKind::HelloTampered, _))),
+        "the tampered hello was not detected"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "enable_server")]
+#[test]
+fn test_proxy_protocol_header() {
+    use super::proxy_protocol::*;
+
+    let mut header = [0u8; 16];
+    header[..12].copy_from_slice(&PROXY_V2_SIGNATURE);
+    header[12] = 0x21;
+    header[13] = 0x11;
+    header[14..16].copy_from_slice(&12u16.to_be_bytes());
+    let body = [10, 1, 2, 3, 10, 0, 0, 1, 0x1F, 0x90, 0x13, 0x89];
+    assert_eq!(parse_proxy_header(&header).unwrap(), 12);
+    assert_eq!(
+        parse_proxy_addresses(&header, &body).unwrap(),
+        Some(SocketAddr::from_str("10.1.2.3:8080").unwrap())
+    );
+
+    // Truncated addresses are rejected
+    parse_proxy_addresses(&header, &body[..8]).expect_err("truncated addresses should be rejected");
+
+    // Health checks from the load balancer keep the address of the socket
+    header[12] = 0x20;
+    assert_eq!(parse_proxy_addresses(&header, &body).unwrap(), None);
+
+    // Anything that is not a v2 header is rejected
+    header[12] = 0x11;
+    parse_proxy_header(&header).expect_err("unknown versions should be rejected");
+    let mut plain = [0u8; 16];
+    plain.copy_from_slice(b"GET / HTTP/1.1\r\n");
+    parse_proxy_header(&plain).expect_err("missing signatures should be rejected");
+}
+
+#[cfg(feature = "enable_server")]
+fn duplex_upstream(id: NodeId) -> (Arc<tokio::sync::Mutex<super::Upstream>>, super::StreamRx) {
+    let (local, remote) = tokio::io::duplex(1024);
+    let (local_rx, local_tx) = tokio::io::split(local);
+    let (remote_rx, remote_tx) = tokio::io::split(remote);
+    let (_, tx) = super::MessageProtocolVersion::default()
+        .create(Some(Box::new(local_rx)), Some(Box::new(local_tx)))
+        .split(None);
+    let (rx, _) = super::MessageProtocolVersion::default()
+        .create(Some(Box::new(remote_rx)), Some(Box::new(remote_tx)))
+        .split(None);
+    let upstream = super::Upstream {
+        id,
+        outbox: tx.into(),
+        wire_format: SerializationFormat::Bincode,
+    };
+    (Arc::new(tokio::sync::Mutex::new(upstream)), rx)
+}
+
+#[cfg(feature = "enable_server")]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_wedged_group_member_is_disconnected() -> Result<(), AteError> {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    crate::utils::bootstrap_test_env();
+
+    const HIGH_WATER: usize = 32;
+    const PACKETS: u32 = 2000;
+
+    // One member never reads anything that it is sent while the others do
+    let mut group = super::TxGroup::default();
+    let wedged_id = NodeId::generate_client_id();
+    let (wedged_disconnect, mut wedged_exit) = broadcast::channel(1);
+    let _wedged = duplex_upstream(wedged_id);
+    group.all.insert(
+        wedged_id,
+        super::TxGroupMember::new(&_wedged.0, HIGH_WATER, wedged_disconnect),
+    );
+    let mut healthy = Vec::new();
+    for _ in 0..2 {
+        let id = NodeId::generate_client_id();
+        let (disconnect, _) = broadcast::channel(1);
+        let (upstream, mut rx) = duplex_upstream(id);
+        let member = super::TxGroupMember::new(&upstream, HIGH_WATER, disconnect);
+        group.all.insert(id, member.clone());
+        let received = Arc::new(AtomicU32::new(0));
+        {
+            let received = Arc::clone(&received);
+            TaskEngine::spawn(async move {
+                while rx.read().await.is_ok() {
+                    received.fetch_add(1, Ordering::AcqRel);
+                }
+            });
+        }
+        healthy.push((upstream, member, received));
+    }
+
+    // Broadcast far more than the high-water mark, the healthy members are
+    // given the time to drain their queues between batches
+    let data = vec![0u8; 512];
+    for n in 0..PACKETS {
+        let pck = PacketData {
+            bytes: bytes::Bytes::from(data.clone()),
+            wire_format: SerializationFormat::Bincode,
+        };
+        group.send(pck, None).await;
+
+        // The memory held for the wedged member never grows beyond its mark
+        if let Some(wedged) = group.all.get(&wedged_id) {
+            assert!(wedged.queued() <= HIGH_WATER);
+        }
+        if n % 8 == 7 {
+            while healthy.iter().any(|(_, member, _)| member.queued() > 0) {
+                crate::engine::sleep(std::time::Duration::from_millis(1)).await;
+            }
+        }
+    }
+
+    // The wedged member was disconnected and dropped from the group
+    assert!(wedged_exit.try_recv().is_ok(), "the wedged member was not disconnected");
+    assert!(group.all.contains_key(&wedged_id) == false);
+    assert_eq!(group.all.len(), 2);
+
+    // While the others received everything
+    for _ in 0..200 {
+        if healthy.iter().all(|(_, _, a)| a.load(Ordering::Acquire) >= PACKETS) {
+            break;
+        }
+        crate::engine::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    for (_, _, received) in healthy.iter() {
+        assert_eq!(received.load(Ordering::Acquire), PACKETS);
+    }
+    Ok(())
+}
+
+/// Certificates for the TLS tests, the server and client certificates are
+/// both signed by the same test authority
+#[cfg(all(feature = "enable_server", feature = "enable_client", feature = "enable_dns"))]
+struct TestTlsFiles {
+    ca: String,
+    server_cert: String,
+    server_key: String,
+    client_cert: String,
+    client_key: String,
+}
+
+#[cfg(all(feature = "enable_server", feature = "enable_client", feature = "enable_dns"))]
+fn generate_test_tls(name: &str) -> TestTlsFiles {
+    let dir = std::env::temp_dir().join(format!("ate-tls-{}-{}", name, fastrand::u64(..)));
+    std::fs::create_dir_all(&dir).unwrap();
+    let write = |file: &str, data: String| {
+        let path = dir.join(file);
+        std::fs::write(&path, data).unwrap();
+        path.to_string_lossy().to_string()
+    };
+
+    let mut params = rcgen::CertificateParams::new(Vec::new());
+    params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+    let ca = rcgen::Certificate::from_params(params).unwrap();
+    let server = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let client = rcgen::generate_simple_self_signed(vec!["client".to_string()]).unwrap();
+
+    TestTlsFiles {
+        ca: write("ca.pem", ca.serialize_pem().unwrap()),
+        server_cert: write("server.pem", server.serialize_pem_with_signer(&ca).unwrap()),
+        server_key: write("server.key", server.serialize_private_key_pem()),
+        client_cert: write("client.pem", client.serialize_pem_with_signer(&ca).unwrap()),
+        client_key: write("client.key", client.serialize_private_key_pem()),
+    }
+}
+
+#[cfg(all(feature = "enable_server", feature = "enable_client", feature = "enable_dns"))]
+async fn test_tls_connect(
+    port: u16,
+    server: &TestTlsFiles,
+    client: &TestTlsFiles,
+    client_cert: bool,
+) -> Result<(), AteError> {
+    use crate::comms::helper::InboxProcessor;
+
+    #[derive(Debug, Clone, Default)]
+    struct ServerHandler {}
+    #[async_trait]
+    impl ServerProcessor<TestMessage, DummyContext> for ServerHandler {
+        async fn process(
+            &'_ self,
+            pck: PacketWithContext<TestMessage, DummyContext>,
+            tx: &'_ mut Tx,
+        ) -> Result<(), CommsError> {
+            if let TestMessage::Ping(txt) = pck.packet.msg {
+                tx.send_reply_msg(TestMessage::Pong(txt)).await?;
+            }
+            Ok(())
+        }
+        async fn shutdown(&self, _addr: SocketAddr) {}
+    }
+
+    // The server only accepts clients with a certificate from its authority
+    // and relies on the TLS tunnel alone to protect the data
+    let mut cfg = mock_test_mesh(port);
+    cfg.wire_protocol = StreamProtocol::Tls;
+    cfg.tls_wire_encryption = false;
+    cfg.listen_tls_certificate = Some(server.server_cert.clone());
+    cfg.listen_tls_key = Some(server.server_key.clone());
+    cfg.listen_tls_client_ca = Some(server.ca.clone());
+    let cfg = MeshConfig::new(cfg).listen_on(IpAddr::from_str("127.0.0.1").unwrap(), port);
+    let (exit_tx, _exit_rx) = broadcast::channel(1);
+    let listener = Listener::new(
+        &cfg,
+        NodeId::generate_server_id(0),
+        Arc::new(ServerHandler::default()),
+        exit_tx,
+    )
+    .await?;
+    listener.lock().unwrap().add_route("/comm-test")?;
+
+    #[derive(Debug, Clone)]
+    struct ClientHandler {
+        pong: Arc<tokio::sync::Notify>,
+    }
+    #[async_trait]
+    impl InboxProcessor<TestMessage, ()> for ClientHandler {
+        async fn process(&mut self, pck: PacketWithContext<TestMessage, ()>) -> Result<(), CommsError> {
+            if let TestMessage::Pong(txt) = pck.packet.msg {
+                assert_eq!("hello", txt.as_str());
+                self.pong.notify_one();
+            }
+            Ok(())
+        }
+        async fn shutdown(&mut self, _addr: SocketAddr) {}
+    }
+    let pong = Arc::new(tokio::sync::Notify::new());
+
+    let mut cfg = mock_test_mesh(port);
+    cfg.wire_protocol = StreamProtocol::Tls;
+    cfg.tls_wire_encryption = false;
+    cfg.fail_fast = true;
+    cfg.tls_ca = Some(client.ca.clone());
+    if client_cert {
+        cfg.tls_client_certificate = Some(client.client_cert.clone());
+        cfg.tls_client_key = Some(client.client_key.clone());
+    }
+    let cfg = MeshConfig::new(cfg).connect_to(MeshAddress {
+        host: IpAddr::from_str("127.0.0.1").unwrap(),
+        port,
+    });
+    let (_exit_tx, exit_rx) = broadcast::channel(1);
+    let mut client_tx = super::connect(
+        &cfg,
+        "/comm-test".to_string(),
+        NodeId::generate_client_id(),
+        ClientHandler { pong: pong.clone() },
+        Arc::new(StdMutex::new(Metrics::default())),
+        Arc::new(StdMutex::new(Throttle::default())),
+        exit_rx,
+    )
+    .await?;
+
+    client_tx
+        .send_reply_msg(TestMessage::Ping("hello".to_string()))
+        .await?;
+    crate::engine::timeout(std::time::Duration::from_secs(5), pong.notified())
+        .await
+        .map_err(|_| CommsError::from(CommsErrorKind::Timeout))?;
+    Ok(())
+}
+
+#[cfg(all(feature = "enable_server", feature = "enable_client", feature = "enable_dns"))]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_server_client_for_comms_with_tls() -> Result<(), AteError> {
+    crate::utils::bootstrap_test_env();
+
+    let tls = generate_test_tls("ok");
+    test_tls_connect(4071, &tls, &tls, true).await
+}
+
+#[cfg(all(feature = "enable_server", feature = "enable_client", feature = "enable_dns"))]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_tls_rejects_bad_certificates() -> Result<(), AteError> {
+    crate::utils::bootstrap_test_env();
+
+    // A client that does not trust the authority of the server refuses it
+    let server = generate_test_tls("server");
+    let other = generate_test_tls("other");
+    assert!(test_tls_connect(4081, &server, &other, true).await.is_err());
+
+    // A server that requires client certificates refuses clients without one
+    assert!(test_tls_connect(4082, &server, &server, false).await.is_err());
+    Ok(())
+}
+
+#[cfg(test)]
+#[test]
+fn test_bearer_token() {
+    use crate::comms::is_bearer_token;
+
+    let headers = |auth: Option<&str>| {
+        let mut ret = http::HeaderMap::new();
+        if let Some(auth) = auth {
+            ret.insert(http::header::AUTHORIZATION, auth.parse().unwrap());
+        }
+        ret
+    };
+
+    assert!(is_bearer_token(&headers(Some("Bearer secret")), "secret"));
+    assert!(is_bearer_token(&headers(Some("secret")), "secret"));
+    assert!(!is_bearer_token(&headers(None), "secret"));
+    assert!(!is_bearer_token(&headers(Some("Bearer secreT")), "secret"));
+    assert!(!is_bearer_token(&headers(Some("Bearer secre")), "secret"));
+    assert!(!is_bearer_token(&headers(Some("Bearer secrets")), "secret"));
+    assert!(!is_bearer_token(&headers(Some("Bearer ")), "secret"));
+}
+
+#[cfg(feature = "enable_server")]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_group_member_reply_order() -> Result<(), AteError> {
+    crate::utils::bootstrap_test_env();
+
+    let id = NodeId::generate_client_id();
+    let (disconnect, _) = broadcast::channel(1);
+    let (upstream, mut rx) = duplex_upstream(id);
+    let member = super::TxGroupMember::new(&upstream, 64, disconnect);
+    let mut group = super::TxGroup::default();
+    group.all.insert(id, member.clone());
+
+    // Replies are written behind the broadcasts that were sent before them
+    let reader = TaskEngine::spawn(async move {
+        let mut ret = Vec::new();
+        while ret.len() < 11 {
+            match rx.read().await {
+                Ok(a) => ret.push(a[0]),
+                Err(_) => break,
+            }
+        }
+        ret
+    });
+    for n in 0..10u8 {
+        let pck = PacketData {
+            bytes: bytes::Bytes::from(vec![n; 16]),
+            wire_format: SerializationFormat::Bincode,
+        };
+        group.send(pck, None).await;
+    }
+    member.reply(&bytes::Bytes::from(vec![99u8; 16])).await?;
+
+    let received = reader.await.unwrap();
+    let mut expected = (0..10u8).collect::<Vec<_>>();
+    expected.push(99);
+    assert_eq!(received, expected);
+    Ok(())
+}
@@ -1,6 +1,135 @@
-#[derive(Debug, Clone, Default)]
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Number of packets that may be queued up on a connection before it is
+/// considered to be congested (when adaptive throttling is enabled)
+const ADAPTIVE_MAX_QUEUE_DEPTH: u32 = 32;
+
+/// Commit round trips that are this many times slower than the fastest
+/// round trip observed on the connection indicate congestion
+const ADAPTIVE_LATENCY_FACTOR: u32 = 4;
+
+/// Number of healthy intervals it takes to climb from the floor to the ceiling
+const ADAPTIVE_INCREASE_STEPS: u64 = 100;
+
+#[derive(Debug, Clone)]
 pub struct Throttle {
     pub download_per_second: Option<u64>,
     pub upload_per_second: Option<u64>,
     pub delete_only: bool,
+    // When enabled the rate is adjusted between the floor and ceiling based
+    // on the latency of commit round trips and the depth of the packet queue
+    pub adaptive: bool,
+    pub adaptive_floor_per_second: u64,
+    pub adaptive_ceiling_per_second: u64,
+    pub(crate) effective_per_second: u64,
+    pub(crate) latency: Option<Duration>,
+    pub(crate) min_latency: Option<Duration>,
+    // Reads of the connection are paused while the server is applying
+    // backpressure (e.g. too many bytes of transactions are in flight), the
+    // read loop waits on the notification until they are resumed
+    pub(crate) paused: Option<Arc<Notify>>,
+}
+
+impl Default for Throttle {
+    fn default() -> Throttle {
+        Throttle {
+            download_per_second: None,
+            upload_per_second: None,
+            delete_only: false,
+            adaptive: false,
+            adaptive_floor_per_second: 64 * 1024,
+            adaptive_ceiling_per_second: 64 * 1024 * 1024,
+            effective_per_second: 64 * 1024 * 1024,
+            latency: None,
+            min_latency: None,
+            paused: None,
+        }
+    }
+}
+
+impl Throttle {
+    /// Creates a throttle that adapts its rate (AIMD) between the floor and ceiling
+    pub fn adaptive(floor_per_second: u64, ceiling_per_second: u64) -> Throttle {
+        let mut ret = Throttle::default();
+        ret.set_adaptive(floor_per_second, ceiling_per_second);
+        ret
+    }
+
+    pub fn set_adaptive(&mut self, floor_per_second: u64, ceiling_per_second: u64) {
+        let floor_per_second = floor_per_second.max(1);
+        self.adaptive = true;
+        self.adaptive_floor_per_second = floor_per_second;
+        self.adaptive_ceiling_per_second = ceiling_per_second.max(floor_per_second);
+        self.effective_per_second = self.adaptive_ceiling_per_second;
+        self.latency = None;
+        self.min_latency = None;
+    }
+
+    /// Returns the rate that is currently being enforced by the adaptive mode
+    pub fn effective_per_second(&self) -> Option<u64> {
+        match self.adaptive {
+            true => Some(self.effective_per_second),
+            false => None,
+        }
+    }
+
+    /// Returns true while the reads of the connection are paused
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Stops the reads of the connection until it is resumed
+    pub(crate) fn pause(&mut self) {
+        if self.paused.is_none() {
+            self.paused = Some(Arc::new(Notify::new()));
+        }
+    }
+
+    /// Wakes up the reads of the connection if they were paused
+    pub(crate) fn resume(&mut self) {
+        if let Some(paused) = self.paused.take() {
+            paused.notify_one();
+        }
+    }
+
+    /// Records the round trip time of a commit that was processed on this connection
+    pub fn record_latency(&mut self, sample: Duration) {
+        self.latency = Some(match self.latency {
+            Some(a) => (a * 7 + sample) / 8,
+            None => sample,
+        });
+        self.min_latency = Some(match self.min_latency {
+            Some(a) => a.min(sample),
+            None => sample,
+        });
+    }
+
+    /// Adjusts the effective rate (additive increase, multiplicative decrease)
+    /// based on the rolling latency and the current depth of the packet queue
+    pub fn adapt(&mut self, queue_depth: u32) -> Option<u64> {
+        if self.adaptive == false {
+            return None;
+        }
+        let floor = self.adaptive_floor_per_second;
+        let ceiling = self.adaptive_ceiling_per_second.max(floor);
+
+        let congested = queue_depth > ADAPTIVE_MAX_QUEUE_DEPTH
+            || match (self.latency, self.min_latency) {
+                (Some(latency), Some(min)) => {
+                    latency > (min * ADAPTIVE_LATENCY_FACTOR).max(Duration::from_millis(5))
+                }
+                _ => false,
+            };
+
+        self.effective_per_second = if congested {
+            self.effective_per_second / 2
+        } else {
+            let step = ((ceiling - floor) / ADAPTIVE_INCREASE_STEPS).max(1);
+            self.effective_per_second.saturating_add(step)
+        }
+        .clamp(floor, ceiling);
+        Some(self.effective_per_second)
+    }
 }
@@ -0,0 +1,153 @@
+use error_chain::bail;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use tokio_rustls::rustls;
+use tokio_rustls::webpki;
+#[cfg(feature = "enable_server")]
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::TlsConnector;
+
+use crate::conf::ConfMesh;
+use crate::error::*;
+
+use super::TlsPeerIdentity;
+
+/// Everything a client needs to open a TLS tunnel to a server (the name is
+/// the one the certificate of the server must have been issued for)
+#[derive(Clone)]
+pub struct TlsClient {
+    pub(crate) connector: TlsConnector,
+    pub(crate) server_name: String,
+}
+
+impl std::fmt::Debug for TlsClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "tls-client(server_name={})", self.server_name)
+    }
+}
+
+impl TlsClient {
+    /// Builds the TLS client for a mesh, servers are verified against the
+    /// authorities in `tls_ca` (or the well known web roots if none are given)
+    #[cfg(feature = "enable_client")]
+    pub fn new(cfg: &ConfMesh, server_name: &str) -> Result<TlsClient, CommsError> {
+        let mut tls = rustls::ClientConfig::new();
+        match cfg.tls_ca.as_ref() {
+            Some(path) => {
+                tls.root_store = load_roots(path)?;
+            }
+            None => {
+                tls.root_store
+                    .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+            }
+        }
+
+        // Servers that authenticate their clients need a certificate from us
+        match (cfg.tls_client_certificate.as_ref(), cfg.tls_client_key.as_ref()) {
+            (Some(cert), Some(key)) => {
+                tls.set_single_client_cert(load_certs(cert)?, load_private_key(key)?)
+                    .map_err(|err| CommsErrorKind::TlsError(err.to_string()))?;
+            }
+            (None, None) => {}
+            _ => {
+                bail!(CommsErrorKind::TlsError(
+                    "the client certificate and key must be supplied together".to_string()
+                ));
+            }
+        }
+
+        Ok(TlsClient {
+            connector: TlsConnector::from(Arc::new(tls)),
+            server_name: server_name.to_string(),
+        })
+    }
+
+    pub(crate) fn dns_name(&self) -> Result<webpki::DNSNameRef<'_>, CommsError> {
+        webpki::DNSNameRef::try_from_ascii_str(self.server_name.as_str())
+            .map_err(|_| CommsErrorKind::InvalidDomainName.into())
+    }
+}
+
+/// Builds the acceptor that servers listening with the `tls` protocol use,
+/// when `listen_tls_client_ca` is set clients must present a certificate that
+/// was signed by one of those authorities
+#[cfg(feature = "enable_server")]
+pub(crate) fn tls_acceptor(cfg: &ConfMesh) -> Result<TlsAcceptor, CommsError> {
+    let (cert, key) = match (
+        cfg.listen_tls_certificate.as_ref(),
+        cfg.listen_tls_key.as_ref(),
+    ) {
+        (Some(a), Some(b)) => (a, b),
+        _ => {
+            bail!(CommsErrorKind::TlsError(
+                "listening with TLS requires a certificate and key".to_string()
+            ));
+        }
+    };
+
+    let verifier = match cfg.listen_tls_client_ca.as_ref() {
+        Some(path) => rustls::AllowAnyAuthenticatedClient::new(load_roots(path)?),
+        None => rustls::NoClientAuth::new(),
+    };
+    let mut tls = rustls::ServerConfig::new(verifier);
+    tls.set_single_cert(load_certs(cert)?, load_private_key(key)?)
+        .map_err(|err| CommsErrorKind::TlsError(err.to_string()))?;
+    Ok(TlsAcceptor::from(Arc::new(tls)))
+}
+
+/// Returns the certificates that the client presented during the handshake
+#[cfg(feature = "enable_server")]
+pub(crate) fn peer_identity(session: &rustls::ServerSession) -> Option<TlsPeerIdentity> {
+    use rustls::Session;
+    session
+        .get_peer_certificates()
+        .filter(|a| a.is_empty() == false)
+        .map(|certs| TlsPeerIdentity {
+            certificates: certs.into_iter().map(|a| a.0).collect(),
+        })
+}
+
+fn open_pem(path: &str) -> Result<BufReader<File>, CommsError> {
+    let path = shellexpand::tilde(path).to_string();
+    let file = File::open(path.as_str()).map_err(|err| {
+        CommsErrorKind::TlsError(format!("failed to open {} - {}", path, err))
+    })?;
+    Ok(BufReader::new(file))
+}
+
+fn load_certs(path: &str) -> Result<Vec<rustls::Certificate>, CommsError> {
+    let ret = rustls::internal::pemfile::certs(&mut open_pem(path)?).map_err(|_| {
+        CommsErrorKind::TlsError(format!("the certificates in {} are invalid", path))
+    })?;
+    if ret.is_empty() {
+        bail!(CommsErrorKind::TlsError(format!("no certificates found in {}", path)));
+    }
+    Ok(ret)
+}
+
+fn load_private_key(path: &str) -> Result<rustls::PrivateKey, CommsError> {
+    let invalid = || CommsErrorKind::TlsError(format!("the private key in {} is invalid", path));
+    let mut keys = rustls::internal::pemfile::pkcs8_private_keys(&mut open_pem(path)?)
+        .map_err(|_| invalid())?;
+    if keys.is_empty() {
+        keys = rustls::internal::pemfile::rsa_private_keys(&mut open_pem(path)?)
+            .map_err(|_| invalid())?;
+    }
+    match keys.into_iter().next() {
+        Some(a) => Ok(a),
+        None => bail!(CommsErrorKind::TlsError(format!("no private key found in {}", path))),
+    }
+}
+
+fn load_roots(path: &str) -> Result<rustls::RootCertStore, CommsError> {
+    let mut ret = rustls::RootCertStore::empty();
+    for cert in load_certs(path)? {
+        ret.add(&cert).map_err(|err| {
+            CommsErrorKind::TlsError(format!("the authority in {} is invalid - {}", path, err))
+        })?;
+    }
+    Ok(ret)
+}
@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+/// # Compaction Policy
+///
+/// Policy that is evaluated periodically by the mesh root against every
+/// open chain to decide if the chain should be compacted. A compaction is
+/// triggered when any of the configured triggers are exceeded and (if set)
+/// the current time falls within the maintenance window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactionPolicy {
+    // Compaction will be triggered when the redo log exceeds this size in bytes
+    pub max_log_size: Option<u64>,
+    // Compaction will be triggered when the ratio of tombstones to events exceeds this value
+    pub max_tombstone_ratio: Option<f32>,
+    // Compaction will be triggered when this much time has passed since the last compaction
+    pub max_age: Option<Duration>,
+    // Compactions will only be performed within this maintenance window (UTC)
+    pub window: Option<CompactionWindow>,
+    // How frequently the policy is evaluated against the chain
+    pub check_interval: Duration,
+}
+
+impl Default for CompactionPolicy {
+    fn default() -> CompactionPolicy {
+        CompactionPolicy {
+            max_log_size: None,
+            max_tombstone_ratio: None,
+            max_age: None,
+            window: None,
+            check_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl CompactionPolicy {
+    pub fn with_max_log_size(mut self, val: u64) -> Self {
+        self.max_log_size = Some(val);
+        self
+    }
+
+    pub fn with_max_tombstone_ratio(mut self, val: f32) -> Self {
+        self.max_tombstone_ratio = Some(val);
+        self
+    }
+
+    pub fn with_max_age(mut self, val: Duration) -> Self {
+        self.max_age = Some(val);
+        self
+    }
+
+    pub fn with_window(mut self, val: CompactionWindow) -> Self {
+        self.window = Some(val);
+        self
+    }
+
+    pub fn with_check_interval(mut self, val: Duration) -> Self {
+        self.check_interval = val;
+        self
+    }
+
+    /// Returns true if any triggers have actually been configured
+    pub fn is_active(&self) -> bool {
+        self.max_log_size.is_some() || self.max_tombstone_ratio.is_some() || self.max_age.is_some()
+    }
+
+    /// Evaluates the policy and returns the trigger that fired (if any)
+    ///
+    /// When the last compaction could not bring the log under the maximum
+    /// size the live data alone exceeds it, hence the size trigger waits
+    /// until the log has grown by the maximum again (rather than compacting
+    /// on every check)
+    pub fn evaluate(
+        &self,
+        log_size: u64,
+        size_after_last: Option<u64>,
+        events: u64,
+        tombstones: u64,
+        since_last: Duration,
+        time_of_day_secs: u32,
+    ) -> Option<CompactionTrigger> {
+        if let Some(window) = &self.window {
+            if window.contains(time_of_day_secs) == false {
+                return None;
+            }
+        }
+        if let Some(max) = self.max_log_size {
+            let max = match size_after_last {
+                Some(after) if after > max => after.saturating_add(max),
+                _ => max,
+            };
+            if log_size > max {
+                return Some(CompactionTrigger::LogSize);
+            }
+        }
+        if let Some(max) = self.max_tombstone_ratio {
+            if events > 0 && (tombstones as f32 / events as f32) > max {
+                return Some(CompactionTrigger::TombstoneRatio);
+            }
+        }
+        if let Some(max) = self.max_age {
+            if since_last > max {
+                return Some(CompactionTrigger::Age);
+            }
+        }
+        None
+    }
+}
+
+impl std::fmt::Display for CompactionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "policy(")?;
+        if let Some(a) = self.max_log_size {
+            write!(f, "size={},", a)?;
+        }
+        if let Some(a) = self.max_tombstone_ratio {
+            write!(f, "tombstones={},", a)?;
+        }
+        if let Some(a) = self.max_age {
+            write!(f, "age={}s,", a.as_secs())?;
+        }
+        if let Some(a) = &self.window {
+            write!(f, "window={},", a)?;
+        }
+        write!(f, "interval={}s)", self.check_interval.as_secs())
+    }
+}
+
+/// Maintenance window expressed as seconds since midnight (UTC), windows
+/// that wrap past midnight (e.g. 23:00-01:00) are supported
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompactionWindow {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl CompactionWindow {
+    pub fn contains(&self, time_of_day_secs: u32) -> bool {
+        let t = time_of_day_secs % 86400;
+        if self.start <= self.end {
+            t >= self.start && t < self.end
+        } else {
+            t >= self.start || t < self.end
+        }
+    }
+}
+
+impl std::fmt::Display for CompactionWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 3600,
+            (self.start % 3600) / 60,
+            self.end / 3600,
+            (self.end % 3600) / 60
+        )
+    }
+}
+
+impl std::str::FromStr for CompactionWindow {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERR: &'static str = "maintenance windows must be in the format 'HH:MM-HH:MM' (UTC)";
+        let parse = |s: &str| -> Result<u32, &'static str> {
+            let mut parts = s.trim().split(':');
+            let h = parts.next().and_then(|a| a.parse::<u32>().ok()).ok_or(ERR)?;
+            let m = parts.next().and_then(|a| a.parse::<u32>().ok()).ok_or(ERR)?;
+            if parts.next().is_some() || h > 24 || m > 59 || (h == 24 && m != 0) {
+                return Err(ERR);
+            }
+            Ok(h * 3600 + m * 60)
+        };
+        let mut parts = s.split('-');
+        let start = parse(parts.next().ok_or(ERR)?)?;
+        let end = parse(parts.next().ok_or(ERR)?)?;
+        if parts.next().is_some() {
+            return Err(ERR);
+        }
+        Ok(CompactionWindow { start, end })
+    }
+}
+
+/// Reason that a particular compaction was performed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompactionTrigger {
+    LogSize,
+    TombstoneRatio,
+    Age,
+    Manual,
+}
+
+impl std::fmt::Display for CompactionTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CompactionTrigger::LogSize => write!(f, "log-size"),
+            CompactionTrigger::TombstoneRatio => write!(f, "tombstone-ratio"),
+            CompactionTrigger::Age => write!(f, "age"),
+            CompactionTrigger::Manual => write!(f, "manual"),
+        }
+    }
+}
+
+/// Statistics captured from the most recent compaction of a chain
+#[derive(Debug, Clone)]
+pub struct CompactionStats {
+    pub trigger: CompactionTrigger,
+    pub when_ms: u64,
+    pub duration: Duration,
+    pub size_before: u64,
+    pub size_after: u64,
+    pub events_before: u64,
+    pub events_after: u64,
+}
@@ -1,10 +1,13 @@
 pub mod compact_mode;
+pub mod compact_policy;
 pub mod compact_state;
 pub mod cut_off_compactor;
 pub mod event_compactor;
 pub mod indecisive_compactor;
+pub mod patch_compactor;
 pub mod public_key_compactor;
 pub mod remove_duplicates;
+pub mod retention_compactor;
 pub mod sig_compactor;
 mod tests;
 pub mod tombstone_compactor;
@@ -12,10 +15,13 @@ pub mod tombstone_compactor;
 pub(crate) use compact_state::*;
 
 pub use compact_mode::*;
+pub use compact_policy::*;
 pub use cut_off_compactor::*;
 pub use event_compactor::*;
 pub use indecisive_compactor::*;
+pub use patch_compactor::*;
 pub use public_key_compactor::*;
 pub use remove_duplicates::*;
+pub use retention_compactor::*;
 pub use sig_compactor::*;
 pub use tombstone_compactor::*;
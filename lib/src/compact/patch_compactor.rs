@@ -0,0 +1,42 @@
+use fxhash::FxHashSet;
+
+use crate::crypto::AteHash;
+use crate::event::*;
+
+use super::*;
+
+/// Keeps the earlier versions of a row that a kept patch event is applied
+/// on top of. Once a row is rewritten in full (which happens when its chain
+/// of patches gets too long, or when `Chain::fold_patches` rewrites it ahead
+/// of a compaction) the older chain is no longer referenced and folds away
+/// like any other old version of the row.
+#[derive(Default, Clone)]
+pub struct PatchCompactor {
+    bases: FxHashSet<AteHash>,
+}
+
+impl EventCompactor for PatchCompactor {
+    fn clone_compactor(&self) -> Option<Box<dyn EventCompactor>> {
+        Some(Box::new(Self::default()))
+    }
+
+    fn relevance(&self, header: &EventHeader) -> EventRelevance {
+        match self.bases.contains(&header.raw.event_hash) {
+            true => EventRelevance::Keep,
+            false => EventRelevance::Abstain,
+        }
+    }
+
+    fn feed(&mut self, header: &EventHeader, keep: bool) {
+        // Events are fed newest first so a whole chain is resolved in one pass
+        if let Some(patch) = header.meta.get_patch() {
+            if keep || self.bases.contains(&header.raw.event_hash) {
+                self.bases.insert(patch.base);
+            }
+        }
+    }
+
+    fn name(&self) -> &str {
+        "patch-compactor"
+    }
+}
@@ -0,0 +1,128 @@
+use fxhash::FxHashMap;
+use fxhash::FxHashSet;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::event::*;
+use crate::header::*;
+use crate::meta::*;
+
+use super::*;
+
+#[derive(Debug, Clone)]
+struct RetainedEntry {
+    vec: MetaCollection,
+    /// Position of the entry in its collection (the most recently written is zero)
+    rank: u64,
+    updated: u64,
+}
+
+/// Drops the rows of collections that have fallen outside the retention
+/// policy of the collection (either because there are too many newer
+/// entries or because they have not been written to for too long)
+#[derive(Default, Clone)]
+pub struct RetentionCompactor {
+    now: u64,
+    seen: FxHashSet<PrimaryKey>,
+    policies: FxHashMap<MetaCollection, MetaCollectionPolicy>,
+    counts: FxHashMap<MetaCollection, u64>,
+    entries: FxHashMap<PrimaryKey, RetainedEntry>,
+}
+
+impl RetentionCompactor {
+    pub fn new() -> RetentionCompactor {
+        RetentionCompactor {
+            now: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|a| a.as_millis() as u64)
+                .unwrap_or(0),
+            ..Default::default()
+        }
+    }
+
+    fn is_expired(&self, key: &PrimaryKey) -> bool {
+        let entry = match self.entries.get(key) {
+            Some(a) => a,
+            None => {
+                return false;
+            }
+        };
+        let policy = match self.policies.get(&entry.vec) {
+            Some(a) => a,
+            None => {
+                return false;
+            }
+        };
+        if let Some(max_entries) = policy.max_entries {
+            if entry.rank >= max_entries {
+                return true;
+            }
+        }
+        if let Some(max_age) = policy.max_age {
+            if entry.updated.saturating_add(max_age) < self.now {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl EventCompactor for RetentionCompactor {
+    fn clone_compactor(&self) -> Option<Box<dyn EventCompactor>> {
+        Some(Box::new(Self::new()))
+    }
+
+    fn relevance(&self, header: &EventHeader) -> EventRelevance {
+        match header.meta.get_data_key() {
+            Some(key) if self.is_expired(&key) => EventRelevance::ForceDrop,
+            _ => EventRelevance::Abstain,
+        }
+    }
+
+    fn feed(&mut self, header: &EventHeader, _keep: bool) {
+        // Events arrive newest first thus only the first event of each row
+        // (its latest version or its tombstone) decides where it stands
+        if let Some(key) = header.meta.get_tombstone() {
+            self.seen.insert(key);
+            return;
+        }
+        let key = match header.meta.get_data_key() {
+            Some(a) => a,
+            None => {
+                return;
+            }
+        };
+        if self.seen.insert(key.clone()) == false {
+            return;
+        }
+
+        for policy in header.meta.get_collection_policies() {
+            let vec = MetaCollection {
+                parent_id: key.clone(),
+                collection_id: policy.collection_id,
+            };
+            self.policies.insert(vec, policy);
+        }
+
+        if let Some(parent) = header.meta.get_parent() {
+            let count = self.counts.entry(parent.vec.clone()).or_default();
+            self.entries.insert(
+                key,
+                RetainedEntry {
+                    vec: parent.vec.clone(),
+                    rank: *count,
+                    updated: header
+                        .meta
+                        .get_timestamp()
+                        .map(|a| a.time_since_epoch_ms)
+                        .unwrap_or(0),
+                },
+            );
+            *count += 1;
+        }
+    }
+
+    fn name(&self) -> &str {
+        "retention-compactor"
+    }
+}
@@ -155,3 +155,68 @@ async fn test_compact_state_machine() -> Result<(), AteError> {
     }
     .await
 }
+
+#[test]
+fn test_compaction_policy() {
+    crate::utils::bootstrap_test_env();
+
+    // Parse a maintenance window and make sure its bounds are honoured
+    let window: CompactionWindow = "02:00-04:00".parse().unwrap();
+    assert!(window.contains(2 * 3600));
+    assert!(window.contains(3 * 3600 + 59 * 60));
+    assert!(window.contains(4 * 3600) == false);
+    assert!(window.contains(1 * 3600) == false);
+    assert_eq!(window.to_string(), "02:00-04:00");
+
+    // Windows may wrap around midnight
+    let window: CompactionWindow = "23:00-01:00".parse().unwrap();
+    assert!(window.contains(23 * 3600 + 30 * 60));
+    assert!(window.contains(30 * 60));
+    assert!(window.contains(12 * 3600) == false);
+    assert!("25:00-01:00".parse::<CompactionWindow>().is_err());
+    assert!("02:00".parse::<CompactionWindow>().is_err());
+
+    // A policy with no triggers never fires
+    let policy = CompactionPolicy::default();
+    assert!(policy.is_active() == false);
+    assert_eq!(policy.evaluate(u64::MAX, None, 100, 100, Duration::from_secs(u64::MAX / 2), 0), None);
+
+    // Each of the triggers fires independently
+    let policy = CompactionPolicy::default()
+        .with_max_log_size(1000)
+        .with_max_tombstone_ratio(0.5)
+        .with_max_age(Duration::from_secs(3600));
+    assert_eq!(policy.evaluate(100, None, 100, 10, Duration::from_secs(10), 0), None);
+    assert_eq!(
+        policy.evaluate(2000, None, 100, 10, Duration::from_secs(10), 0),
+        Some(CompactionTrigger::LogSize)
+    );
+    assert_eq!(
+        policy.evaluate(100, None, 100, 60, Duration::from_secs(10), 0),
+        Some(CompactionTrigger::TombstoneRatio)
+    );
+    assert_eq!(
+        policy.evaluate(100, None, 100, 10, Duration::from_secs(7200), 0),
+        Some(CompactionTrigger::Age)
+    );
+
+    // Chains whose live data is above the maximum size are only compacted
+    // again once the log has grown by the maximum
+    assert_eq!(
+        policy.evaluate(2000, Some(500), 100, 10, Duration::from_secs(10), 0),
+        Some(CompactionTrigger::LogSize)
+    );
+    assert_eq!(policy.evaluate(2000, Some(1800), 100, 10, Duration::from_secs(10), 0), None);
+    assert_eq!(
+        policy.evaluate(2900, Some(1800), 100, 10, Duration::from_secs(10), 0),
+        Some(CompactionTrigger::LogSize)
+    );
+
+    // Outside the maintenance window nothing will fire
+    let policy = policy.with_window("02:00-04:00".parse().unwrap());
+    assert_eq!(policy.evaluate(2000, None, 100, 60, Duration::from_secs(7200), 0), None);
+    assert_eq!(
+        policy.evaluate(2000, None, 100, 60, Duration::from_secs(7200), 3 * 3600),
+        Some(CompactionTrigger::LogSize)
+    );
+}
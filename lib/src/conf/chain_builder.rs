@@ -5,6 +5,7 @@ use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use crate::anti_replay::AntiReplayPlugin;
 use crate::chain::Chain;
+use crate::comms::ConnectionPeer;
 use crate::comms::Metrics;
 use crate::comms::NodeId;
 use crate::comms::Throttle;
@@ -13,6 +14,7 @@ use crate::crypto::PublicSignKey;
 use crate::error::*;
 use crate::index::*;
 use crate::lint::*;
+use crate::mesh::QuorumPolicy;
 use crate::pipe::*;
 use crate::plugin::*;
 use crate::prelude::CentralizedRole;
@@ -44,11 +46,13 @@ pub struct ChainBuilder {
     pub(crate) tree: Option<TreeAuthorityPlugin>,
     pub(crate) truncate: bool,
     pub(crate) temporal: bool,
+    pub(crate) read_only: bool,
     pub(crate) session: Box<dyn AteSession>,
     pub(crate) metrics: Arc<StdMutex<Metrics>>,
     pub(crate) throttle: Arc<StdMutex<Throttle>>,
     pub(crate) load_integrity: TrustMode,
     pub(crate) idle_integrity: TrustMode,
+    pub(crate) peer: Option<ConnectionPeer>,
 }
 
 impl Clone for ChainBuilder {
@@ -92,10 +96,12 @@ impl Clone for ChainBuilder {
             session: self.session.clone_session(),
             truncate: self.truncate,
             temporal: self.temporal,
+            read_only: self.read_only,
             metrics: Arc::clone(&self.metrics),
             throttle: Arc::clone(&self.throttle),
             load_integrity: self.load_integrity,
             idle_integrity: self.idle_integrity,
+            peer: self.peer.clone(),
         }
     }
 }
@@ -118,10 +124,12 @@ impl ChainBuilder {
             session: AteSessionUser::new().into(),
             truncate: false,
             temporal: false,
+            read_only: false,
             metrics: Arc::new(StdMutex::new(Metrics::default())),
             throttle: Arc::new(StdMutex::new(Throttle::default())),
             load_integrity: TrustMode::Centralized(CentralizedRole::Client),
             idle_integrity: TrustMode::Distributed,
+            peer: None,
         }
         .with_defaults()
         .await
@@ -148,8 +156,12 @@ impl ChainBuilder {
             .push(Box::new(SignatureCompactor::default()));
         self.compactors
             .push(Box::new(RemoveDuplicatesCompactor::default()));
+        self.compactors
+            .push(Box::new(PatchCompactor::default()));
         self.compactors
             .push(Box::new(TombstoneCompactor::default()));
+        self.compactors
+            .push(Box::new(RetentionCompactor::new()));
         self.plugins.push(Box::new(AntiReplayPlugin::default()));
 
         match self.configured_for {
@@ -219,6 +231,18 @@ impl ChainBuilder {
         self
     }
 
+    #[allow(dead_code)]
+    pub fn compact_policy(mut self, policy: Option<CompactionPolicy>) -> Self {
+        self.cfg_ate.compact_policy = policy;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn quorum_policy(mut self, policy: Option<QuorumPolicy>) -> Self {
+        self.cfg_ate.quorum_policy = policy;
+        self
+    }
+
     #[allow(dead_code)]
     pub fn add_compactor(mut self, compactor: Box<dyn EventCompactor>) -> Self {
         self.compactors.push(compactor);
@@ -329,11 +353,31 @@ impl ChainBuilder {
         self
     }
 
+    /// Opens the redo log without write access, any attempt to modify the
+    /// chain will fail immediately with a ChainReadOnly error
+    #[allow(dead_code)]
+    pub fn read_only(mut self, val: bool) -> Self {
+        self.read_only = val;
+        self
+    }
+
     pub fn node_id(mut self, client_id: NodeId) -> Self {
         self.node_id = client_id;
         self
     }
 
+    /// Records the client that the chain is being opened for
+    pub fn with_peer(mut self, peer: ConnectionPeer) -> Self {
+        self.peer = Some(peer);
+        self
+    }
+
+    /// Client that the chain is being opened for (if it was opened by a
+    /// server on behalf of a connection)
+    pub fn peer(&self) -> Option<&ConnectionPeer> {
+        self.peer.as_ref()
+    }
+
     pub fn cfg_ate(&self) -> &ConfAte {
         &self.cfg_ate
     }
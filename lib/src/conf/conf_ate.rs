@@ -3,7 +3,9 @@ use std::time::Duration;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use crate::compact::CompactMode;
+use crate::compact::CompactionPolicy;
 use crate::mesh::BackupMode;
+use crate::mesh::QuorumPolicy;
 use crate::mesh::RecoveryMode;
 use crate::spec::*;
 
@@ -25,11 +27,30 @@ pub struct ConfAte {
     pub compact_bootstrap: bool,
     /// Compacts the redo log on cleanup
     pub compact_cleanup: bool,
+    /// Policy evaluated by mesh roots against open chains that will
+    /// trigger compactions in the background (e.g. on size or age).
+    pub compact_policy: Option<CompactionPolicy>,
+    /// Policy that makes mesh roots relay every commit to a set of replica
+    /// roots and only confirm it once a quorum of them have acknowledged it.
+    pub quorum_policy: Option<QuorumPolicy>,
+    /// (Optional) Ethereal chains hosted by a mesh root that have had no
+    /// subscribers and no writes for this long are evicted from memory.
+    /// Persistent chains are never evicted.
+    pub ethereal_ttl: Option<Duration>,
+    /// (Optional) Maximum number of ethereal chains a mesh root will host
+    /// for a route, when reached the least recently used idle chain is
+    /// evicted to make room for the new one.
+    pub ethereal_max_chains: Option<usize>,
 
     /// Directory path that the redo logs will be stored.
     /// (if this option is none then the logs will be stored in memory)
     #[cfg(feature = "enable_local_fs")]
     pub log_path: Option<String>,
+    /// Commits made by a client that have not yet been confirmed by the root
+    /// are written to a file next to the redo log so that they survive a
+    /// restart and are replayed when the chain next connects.
+    /// (requires a log_path, otherwise this option is ignored)
+    pub outbound_wal: bool,
 
     /// (Optional) List of nodes that make up the mesh, otherwise they will be
     /// built from the DNS A records if not supplied here
@@ -77,6 +98,15 @@ pub struct ConfAte {
     /// they are evicted
     #[cfg(feature = "enable_local_fs")]
     pub load_cache_ttl: u64,
+    /// (Optional) Amount of memory (in bytes) the history index of a single
+    /// chain may use before its oldest entries are moved to an index file on
+    /// disk (the most recent entries always remain in memory)
+    #[cfg(feature = "enable_local_fs")]
+    pub max_index_memory: Option<usize>,
+    /// Processes the events of a chain in batches while they are streamed
+    /// from the redo log rather than after the whole log has been read,
+    /// which avoids holding every event header in memory during the load
+    pub load_streaming: bool,
 
     /// Serialization format of the log files
     pub log_format: MessageFormat,
@@ -90,6 +120,10 @@ pub struct ConfAte {
     /// Flag that indicates if the type name should always be saved in the event log.
     /// Added the type-name consumes space but gives extra debug information
     pub record_type_name: bool,
+
+    /// Maximum number of patch events (see `DaoMut::as_mut_patch`) that may be
+    /// stacked on a row before the next edit rewrites the full row instead
+    pub max_patch_depth: u32,
 }
 
 impl Default for ConfAte {
@@ -106,6 +140,10 @@ impl Default for ConfAte {
             compact_mode: CompactMode::Never,
             compact_bootstrap: false,
             compact_cleanup: false,
+            compact_policy: None,
+            quorum_policy: None,
+            ethereal_ttl: None,
+            ethereal_max_chains: None,
             sync_tolerance: Duration::from_secs(30),
             #[cfg(feature = "enable_ntp")]
             ntp_sync: true,
@@ -118,6 +156,10 @@ impl Default for ConfAte {
             load_cache_size: 1000,
             #[cfg(feature = "enable_local_fs")]
             load_cache_ttl: 30,
+            #[cfg(feature = "enable_local_fs")]
+            max_index_memory: None,
+            load_streaming: false,
+            outbound_wal: false,
             log_format: MessageFormat {
                 meta: SerializationFormat::Bincode,
                 data: SerializationFormat::Json,
@@ -126,6 +168,7 @@ impl Default for ConfAte {
             lock_attempt_timeout: Duration::from_secs(20),
             load_timeout: Duration::from_secs(20),
             record_type_name: false,
+            max_patch_depth: 8,
             nodes: None,
         }
     }
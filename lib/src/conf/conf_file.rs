@@ -0,0 +1,948 @@
+//! Configuration files for the ATE datastore and the mesh
+//!
+//! A configuration file is a TOML, YAML or JSON document (selected by the
+//! extension of the file) with an `ate` section for `ConfAte` and a `mesh`
+//! section for `ConfMesh`. Fields that are not present keep their defaults,
+//! durations are expressed in seconds and `${VAR}` inside any string is
+//! replaced with the value of the environment variable (use `$${` for a
+//! literal `${`).
+//!
+//! ```toml
+//! [ate]
+//! configured_for = "balanced"       # raw, barebone, performance, compatibility, balanced, security
+//! recovery_mode = "readonly-async" # async, readonly-async, readonly-sync, sync
+//! backup_mode = "full"              # none, restore, rotating, full
+//! compact_mode = "factor-or-timer" # never, modified, timer, factor, size, factor-or-timer, size-or-timer
+//! compact_timer = 3600              # seconds
+//! compact_growth_factor = 0.2
+//! compact_growth_size = 104857600   # bytes
+//! compact_bootstrap = false
+//! compact_cleanup = false
+//! log_path = "${HOME}/ate/logs"
+//! outbound_wal = false
+//! backup_path = "/mnt/backup"
+//! nodes = [ "10.0.0.1", "10.0.0.2" ]
+//! ntp_pool = "pool.ntp.org"
+//! ntp_port = 123
+//! ntp_sync = true
+//! dns_sec = false
+//! dns_server = "8.8.8.8"
+//! sync_tolerance = 30
+//! load_cache_size = 1000
+//! load_cache_ttl = 30
+//! max_index_memory = 536870912
+//! load_streaming = false
+//! buffer_size_chain = 1
+//! lock_attempt_timeout = 20
+//! load_timeout = 20
+//! record_type_name = false
+//! max_patch_depth = 8
+//! ethereal_ttl = 600
+//! ethereal_max_chains = 1000
+//!
+//! [ate.log_format]
+//! meta = "bincode"
+//! data = "json"
+//!
+//! [ate.compact_policy]
+//! max_log_size = 1073741824
+//! max_tombstone_ratio = 0.5         # between 0 and 1
+//! max_age = 86400
+//! window = "02:00-04:00"
+//! check_interval = 60
+//!
+//! [ate.quorum_policy]
+//! replicas = [ "ws://replica1.example.com/db", "ws://replica2.example.com/db" ]
+//! required = 1                      # at most the number of replicas
+//! timeout = 10
+//! relay_attempts = 3
+//! repair_interval = 30
+//!
+//! [mesh]
+//! domain_name = "example.com"       # required
+//! remote = "ws://example.com:5000/" # required
+//! roots = [ "10.0.0.1:5000" ]       # ip:port
+//! wire_protocol = "websocket"       # defaults to the protocol of the remote
+//! wire_format = "bincode"
+//! max_protocol_version = "v3"       # v1, v2 or v3 (pin for staged rollouts)
+//! wire_encryption = 128             # 128, 192, 256 or "none"
+//! require_encryption = false        # can not be used when wire_encryption is "none"
+//! tls_wire_encryption = true        # false skips wire_encryption inside "tls" tunnels
+//! tls_ca = "/etc/ate/ca.pem"        # authorities trusted for "tls" servers
+//! tls_client_certificate = "/etc/ate/client.pem"
+//! tls_client_key = "/etc/ate/client.key"
+//! connect_timeout = 30
+//! keepalive_interval = 30           # 0 turns the keepalive off
+//! keepalive_timeout = 10
+//! keepalive_max_missed = 3
+//! fail_fast = false
+//! force_client_only = false         # can not be used with force_listen
+//! force_connect = "10.0.0.1:5000"
+//! buffer_size_client = 2
+//! multiplex = false                 # true shares one connection per server
+//! force_listen = "0.0.0.0:5000"
+//! force_port = 5000                 # 1 to 65535
+//! force_node_id = 1
+//! listen_min_encryption = 128
+//! listen_tls_certificate = "/etc/ate/server.pem" # required when listening with "tls"
+//! listen_tls_key = "/etc/ate/server.key"
+//! listen_tls_client_ca = "/etc/ate/ca.pem"       # clients must present a certificate
+//! accept_timeout = 10
+//! max_pre_hello_per_ip = 16
+//! proxy_protocol = false
+//! outbox_high_water = 4096
+//! buffer_size_server = 10
+//! compact_concurrency = 2
+//! compact_remote_trigger = false
+//! history_cache_size = 33554432    # bytes per route, 0 disables
+//! inflight_connection_limit = 67108864 # bytes per connection, 0 disables
+//! inflight_route_limit = 536870912     # bytes per route, 0 disables
+//! chain_quota = 0                  # bytes per chain, 0 disables
+//! checkpoint_interval = 60
+//!
+//! [mesh.watchdog_policy]             # default for every route of a root
+//! threshold = 60
+//! check_interval = 10
+//! recover = false
+//!
+//! [mesh.handshake_timeouts]
+//! hello_read = 5
+//! hello_write = 5
+//! key_exchange = 10
+//!
+//! [mesh.listen_throttle]
+//! download_per_second = 1048576
+//! upload_per_second = 1048576
+//! delete_only = false
+//! adaptive_floor_per_second = 65536     # at most the ceiling
+//! adaptive_ceiling_per_second = 1048576
+//! ```
+//!
+//! Validation does not stop at the first problem, instead every problem in
+//! the document (unknown fields, values that do not parse, ports that are
+//! out of range and settings that conflict with each other) is reported at
+//! once in a `ConfigErrorKind::Invalid` error.
+use error_chain::bail;
+use serde_json::Map;
+use serde_json::Value;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::comms::StreamProtocol;
+use crate::compact::CompactMode;
+use crate::compact::CompactionPolicy;
+use crate::compact::CompactionWindow;
+use crate::crypto::KeySize;
+use crate::error::*;
+use crate::mesh::QuorumPolicy;
+#[cfg(feature = "enable_server")]
+use crate::mesh::WatchdogPolicy;
+use crate::spec::*;
+
+use super::*;
+
+pub const CONF_SECTION_ATE: &str = "ate";
+pub const CONF_SECTION_MESH: &str = "mesh";
+
+/// Configuration document that has been read and had its environment
+/// variables interpolated, the sections are validated when they are
+/// converted into their configuration objects
+#[derive(Debug, Clone)]
+pub struct ConfDocument {
+    sections: Map<String, Value>,
+}
+
+impl ConfDocument {
+    /// Reads a configuration document from a file, the format is selected
+    /// by the extension of the file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<ConfDocument, ConfigError> {
+        let path = path.as_ref();
+        let format = path
+            .extension()
+            .and_then(|a| a.to_str())
+            .map(|a| a.to_lowercase());
+        let format = match format {
+            Some(a) => a,
+            None => bail!(ConfigErrorKind::UnsupportedFormat(path.display().to_string())),
+        };
+        let text = std::fs::read_to_string(path)?;
+        ConfDocument::parse(text.as_str(), format.as_str())
+    }
+
+    /// Parses a configuration document that is in the supplied format
+    /// (`toml`, `yaml`, `yml` or `json`)
+    pub fn parse(text: &str, format: &str) -> Result<ConfDocument, ConfigError> {
+        let mut value = match format {
+            "toml" => {
+                let value: toml::Value = toml::from_str(text)
+                    .map_err(|err| ConfigErrorKind::ParseError(err.to_string()))?;
+                serde_json::to_value(value)
+                    .map_err(|err| ConfigErrorKind::ParseError(err.to_string()))?
+            }
+            "yaml" | "yml" => serde_yaml::from_str::<Value>(text)
+                .map_err(|err| ConfigErrorKind::ParseError(err.to_string()))?,
+            "json" => serde_json::from_str::<Value>(text)
+                .map_err(|err| ConfigErrorKind::ParseError(err.to_string()))?,
+            a => bail!(ConfigErrorKind::UnsupportedFormat(a.to_string())),
+        };
+
+        let mut problems = Vec::new();
+        interpolate(&mut value, "", &mut problems);
+
+        let sections = match value {
+            Value::Object(a) => a,
+            Value::Null => Map::new(),
+            _ => bail!(ConfigErrorKind::ParseError(
+                "the document must be made up of sections".to_string()
+            )),
+        };
+        for key in sections.keys() {
+            if key != CONF_SECTION_ATE && key != CONF_SECTION_MESH {
+                problems.push(format!(
+                    "[{}] is not a known section (expected [{}] or [{}])",
+                    key, CONF_SECTION_ATE, CONF_SECTION_MESH
+                ));
+            }
+        }
+        if problems.len() > 0 {
+            bail!(ConfigErrorKind::Invalid(problems));
+        }
+        Ok(ConfDocument { sections })
+    }
+
+    /// Implements the `--check-config` mode of the server binaries, the file
+    /// is read and validated and the outcome is printed to the console
+    pub fn check_file(path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        match ConfDocument::from_file(path).and_then(|a| a.check()) {
+            Ok(()) => {
+                println!("{} is valid", path.display());
+                true
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                false
+            }
+        }
+    }
+
+    pub fn has_section(&self, name: &str) -> bool {
+        self.sections.contains_key(name)
+    }
+
+    /// Builds the ATE configuration from the `ate` section (a missing section
+    /// yields the default configuration)
+    pub fn ate(&self) -> Result<ConfAte, ConfigError> {
+        let mut problems = Vec::new();
+        let ret = self.ate_internal(&mut problems);
+        if problems.len() > 0 {
+            bail!(ConfigErrorKind::Invalid(problems));
+        }
+        Ok(ret)
+    }
+
+    /// Builds the mesh configuration from the `mesh` section
+    pub fn mesh(&self) -> Result<ConfMesh, ConfigError> {
+        if self.has_section(CONF_SECTION_MESH) == false {
+            bail!(ConfigErrorKind::MissingSection(CONF_SECTION_MESH.to_string()));
+        }
+        let mut problems = Vec::new();
+        let ret = self.mesh_internal(&mut problems);
+        if problems.len() > 0 {
+            bail!(ConfigErrorKind::Invalid(problems));
+        }
+        Ok(ret)
+    }
+
+    /// Validates every section in the document and reports all the problems
+    /// that were found together
+    pub fn check(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        self.ate_internal(&mut problems);
+        if self.has_section(CONF_SECTION_MESH) {
+            self.mesh_internal(&mut problems);
+        }
+        if problems.len() > 0 {
+            bail!(ConfigErrorKind::Invalid(problems));
+        }
+        Ok(())
+    }
+
+    fn section<'a>(&self, name: &str, problems: &'a mut Vec<String>) -> Section<'a> {
+        let value = self.sections.get(name).cloned().unwrap_or(Value::Null);
+        Section::new(name.to_string(), value, problems)
+    }
+
+    fn ate_internal(&self, problems: &mut Vec<String>) -> ConfAte {
+        let mut sec = self.section(CONF_SECTION_ATE, problems);
+        let mut ret = ConfAte::default();
+
+        if let Some(a) = sec.parse("configured_for") {
+            ret.configured_for(a);
+        }
+        if let Some(a) = sec.parse("recovery_mode") {
+            ret.recovery_mode = a;
+        }
+        if let Some(a) = sec.parse::<CompactMode>("compact_mode") {
+            ret.compact_mode = a;
+        }
+        if let Some(a) = sec.duration("compact_timer") {
+            ret.compact_mode = ret.compact_mode.with_timer_value(a);
+        }
+        if let Some(a) = sec.parse::<f32>("compact_growth_factor") {
+            ret.compact_mode = ret.compact_mode.with_growth_factor(a);
+        }
+        if let Some(a) = sec.parse::<u64>("compact_growth_size") {
+            ret.compact_mode = ret.compact_mode.with_growth_size(a);
+        }
+        if let Some(a) = sec.parse("compact_bootstrap") {
+            ret.compact_bootstrap = a;
+        }
+        if let Some(a) = sec.parse("compact_cleanup") {
+            ret.compact_cleanup = a;
+        }
+        if let Some(mut policy) = sec.section("compact_policy") {
+            ret.compact_policy = Some(compact_policy(&mut policy));
+            policy.finish();
+        }
+        if let Some(mut policy) = sec.section("quorum_policy") {
+            ret.quorum_policy = Some(quorum_policy(&mut policy));
+            policy.finish();
+        }
+        if let Some(a) = sec.duration("ethereal_ttl") {
+            ret.ethereal_ttl = Some(a);
+        }
+        if let Some(a) = sec.parse("ethereal_max_chains") {
+            ret.ethereal_max_chains = Some(a);
+        }
+        if let Some(a) = sec.string("log_path") {
+            ret.log_path = Some(shellexpand::tilde(a.as_str()).to_string());
+        }
+        if let Some(a) = sec.parse("outbound_wal") {
+            ret.outbound_wal = a;
+        }
+        if let Some(a) = sec.string("backup_path") {
+            ret.backup_path = Some(shellexpand::tilde(a.as_str()).to_string());
+        }
+        if let Some(a) = sec.parse("backup_mode") {
+            ret.backup_mode = a;
+        }
+        if let Some(a) = sec.strings("nodes") {
+            ret.nodes = Some(a);
+        }
+        #[cfg(feature = "enable_ntp")]
+        {
+            if let Some(a) = sec.string("ntp_pool") {
+                ret.ntp_pool = a;
+            }
+            if let Some(a) = sec.port("ntp_port") {
+                ret.ntp_port = a;
+            }
+            if let Some(a) = sec.parse("ntp_sync") {
+                ret.ntp_sync = a;
+            }
+        }
+        if let Some(a) = sec.parse("dns_sec") {
+            ret.dns_sec = a;
+        }
+        if let Some(a) = sec.string("dns_server") {
+            ret.dns_server = a;
+        }
+        if let Some(a) = sec.duration("sync_tolerance") {
+            ret.sync_tolerance = a;
+        }
+        if let Some(a) = sec.parse("load_cache_size") {
+            ret.load_cache_size = a;
+        }
+        if let Some(a) = sec.parse("load_cache_ttl") {
+            ret.load_cache_ttl = a;
+        }
+        if let Some(a) = sec.parse("max_index_memory") {
+            ret.max_index_memory = Some(a);
+        }
+        if let Some(a) = sec.parse("load_streaming") {
+            ret.load_streaming = a;
+        }
+        if let Some(mut format) = sec.section("log_format") {
+            if let Some(a) = format.parse("meta") {
+                ret.log_format.meta = a;
+            }
+            if let Some(a) = format.parse("data") {
+                ret.log_format.data = a;
+            }
+            format.finish();
+        }
+        if let Some(a) = sec.parse::<usize>("buffer_size_chain") {
+            if a == 0 {
+                sec.problem("buffer_size_chain", "must be at least 1");
+            } else {
+                ret.buffer_size_chain = a;
+            }
+        }
+        if let Some(a) = sec.duration("lock_attempt_timeout") {
+            ret.lock_attempt_timeout = a;
+        }
+        if let Some(a) = sec.duration("load_timeout") {
+            ret.load_timeout = a;
+        }
+        if let Some(a) = sec.parse("record_type_name") {
+            ret.record_type_name = a;
+        }
+        if let Some(a) = sec.parse("max_patch_depth") {
+            ret.max_patch_depth = a;
+        }
+
+        sec.finish();
+        ret
+    }
+
+    fn mesh_internal(&self, problems: &mut Vec<String>) -> ConfMesh {
+        let mut sec = self.section(CONF_SECTION_MESH, problems);
+
+        let domain_name = sec.string("domain_name");
+        if domain_name.is_none() {
+            sec.missing("domain_name");
+        }
+        let remote = sec.parse::<url::Url>("remote");
+        if remote.is_none() {
+            sec.missing("remote");
+        }
+        let roots = sec.addresses("roots").unwrap_or_default();
+
+        // When the required fields are missing the remaining fields are still
+        // validated against a placeholder so that all the problems are reported
+        let remote = remote.unwrap_or_else(|| url::Url::parse("ws://localhost/").unwrap());
+        let mut ret = ConfMesh::new(
+            domain_name.unwrap_or_default().as_str(),
+            remote.clone(),
+            roots.iter(),
+        );
+        if let Ok(a) = StreamProtocol::parse(&remote) {
+            ret.wire_protocol = a;
+        }
+
+        if let Some(a) = sec.parse("wire_protocol") {
+            ret.wire_protocol = a;
+        }
+        if let Some(a) = sec.parse("wire_format") {
+            ret.wire_format = a;
+        }
+        if let Some(a) = sec.parse("max_protocol_version") {
+            ret.max_protocol_version = a;
+        }
+        if let Some(a) = sec.string("wire_encryption") {
+            match a.trim() {
+                "none" | "off" => ret.wire_encryption = None,
+                a => match KeySize::from_str(a) {
+                    Ok(a) => ret.wire_encryption = Some(a),
+                    Err(err) => sec.problem("wire_encryption", format!("is invalid ({}) - {} or 'none'", a, err)),
+                },
+            }
+        }
+        if let Some(a) = sec.parse("require_encryption") {
+            ret.require_encryption = a;
+        }
+        if let Some(a) = sec.parse("tls_wire_encryption") {
+            ret.tls_wire_encryption = a;
+        }
+        if let Some(a) = sec.duration("connect_timeout") {
+            ret.connect_timeout = a;
+        }
+        if let Some(a) = sec.duration("keepalive_interval") {
+            ret.keepalive_interval = match a.is_zero() {
+                true => None,
+                false => Some(a),
+            };
+        }
+        if let Some(a) = sec.duration("keepalive_timeout") {
+            if a.is_zero() {
+                sec.problem("keepalive_timeout", "must be greater than zero");
+            } else {
+                ret.keepalive_timeout = a;
+            }
+        }
+        if let Some(a) = sec.parse::<u32>("keepalive_max_missed") {
+            if a == 0 {
+                sec.problem("keepalive_max_missed", "must be at least 1");
+            } else {
+                ret.keepalive_max_missed = a;
+            }
+        }
+        if let Some(a) = sec.parse("fail_fast") {
+            ret.fail_fast = a;
+        }
+
+        #[cfg(feature = "enable_client")]
+        {
+            if let Some(a) = sec.parse("force_client_only") {
+                ret.force_client_only = a;
+            }
+            if let Some(a) = sec.address("force_connect") {
+                ret.force_connect = Some(a);
+            }
+            if let Some(a) = sec.parse("buffer_size_client") {
+                ret.buffer_size_client = a;
+            }
+            if let Some(a) = sec.parse("multiplex") {
+                ret.multiplex = a;
+            }
+            ret.tls_ca = sec.string("tls_ca");
+            ret.tls_client_certificate = sec.string("tls_client_certificate");
+            ret.tls_client_key = sec.string("tls_client_key");
+            if ret.tls_client_certificate.is_some() != ret.tls_client_key.is_some() {
+                sec.problem(
+                    "tls_client_certificate",
+                    "and `tls_client_key` must be supplied together",
+                );
+            }
+        }
+
+        #[cfg(feature = "enable_server")]
+        {
+            if let Some(a) = sec.address("force_listen") {
+                ret.force_listen = Some(a);
+            }
+            if let Some(a) = sec.port("force_port") {
+                ret.force_port = Some(a);
+            }
+            if let Some(a) = sec.parse("force_node_id") {
+                ret.force_node_id = Some(a);
+            }
+            if let Some(a) = sec.parse("listen_min_encryption") {
+                ret.listen_min_encryption = Some(a);
+            }
+            ret.listen_tls_certificate = sec.string("listen_tls_certificate");
+            ret.listen_tls_key = sec.string("listen_tls_key");
+            ret.listen_tls_client_ca = sec.string("listen_tls_client_ca");
+            if ret.listen_tls_certificate.is_some() != ret.listen_tls_key.is_some() {
+                sec.problem(
+                    "listen_tls_certificate",
+                    "and `listen_tls_key` must be supplied together",
+                );
+            }
+            if let Some(a) = sec.duration("accept_timeout") {
+                ret.accept_timeout = a;
+            }
+            if let Some(mut timeouts) = sec.section("handshake_timeouts") {
+                if let Some(a) = timeouts.duration("hello_read") {
+                    ret.handshake_timeouts.hello_read = a;
+                }
+                if let Some(a) = timeouts.duration("hello_write") {
+                    ret.handshake_timeouts.hello_write = a;
+                }
+                if let Some(a) = timeouts.duration("key_exchange") {
+                    ret.handshake_timeouts.key_exchange = a;
+                }
+                timeouts.finish();
+            }
+            if let Some(a) = sec.parse("max_pre_hello_per_ip") {
+                ret.max_pre_hello_per_ip = a;
+            }
+            if let Some(a) = sec.parse("proxy_protocol") {
+                ret.proxy_protocol = a;
+            }
+            if let Some(a) = sec.parse("outbox_high_water") {
+                ret.outbox_high_water = a;
+            }
+            if let Some(a) = sec.parse("buffer_size_server") {
+                ret.buffer_size_server = a;
+            }
+            if let Some(a) = sec.parse("compact_concurrency") {
+                ret.compact_concurrency = a;
+            }
+            if let Some(a) = sec.parse("compact_remote_trigger") {
+                ret.compact_remote_trigger = a;
+            }
+            if let Some(a) = sec.parse("history_cache_size") {
+                ret.history_cache_size = a;
+            }
+            if let Some(a) = sec.parse("inflight_connection_limit") {
+                ret.inflight_connection_limit = a;
+            }
+            if let Some(a) = sec.parse("inflight_route_limit") {
+                ret.inflight_route_limit = a;
+            }
+            if let Some(a) = sec.parse("chain_quota") {
+                ret.chain_quota = a;
+            }
+            if let Some(a) = sec.duration("checkpoint_interval") {
+                ret.checkpoint_interval = a;
+            }
+            if let Some(mut policy) = sec.section("watchdog_policy") {
+                ret.watchdog_policy = Some(watchdog_policy(&mut policy));
+                policy.finish();
+            }
+            if let Some(mut throttle) = sec.section("listen_throttle") {
+                if let Some(a) = throttle.parse("download_per_second") {
+                    ret.listen_throttle.download_per_second = Some(a);
+                }
+                if let Some(a) = throttle.parse("upload_per_second") {
+                    ret.listen_throttle.upload_per_second = Some(a);
+                }
+                if let Some(a) = throttle.parse("delete_only") {
+                    ret.listen_throttle.delete_only = a;
+                }
+                let floor = throttle.parse::<u64>("adaptive_floor_per_second");
+                let ceiling = throttle.parse::<u64>("adaptive_ceiling_per_second");
+                match (floor, ceiling) {
+                    (Some(floor), Some(ceiling)) if floor > ceiling => {
+                        throttle.problem(
+                            "adaptive_floor_per_second",
+                            format!("({}) can not be greater than `adaptive_ceiling_per_second` ({})", floor, ceiling),
+                        );
+                    }
+                    (Some(floor), Some(ceiling)) => ret.listen_throttle.set_adaptive(floor, ceiling),
+                    (None, None) => {}
+                    _ => {
+                        throttle.problem(
+                            "adaptive_floor_per_second",
+                            "and `adaptive_ceiling_per_second` must be supplied together",
+                        );
+                    }
+                }
+                throttle.finish();
+            }
+        }
+
+        #[cfg(all(feature = "enable_client", feature = "enable_server"))]
+        if ret.force_client_only && ret.force_listen.is_some() {
+            sec.problem(
+                "force_client_only",
+                "can not be used together with `mesh.force_listen`",
+            );
+        }
+        if ret.require_encryption && ret.wire_encryption.is_none() {
+            sec.problem(
+                "require_encryption",
+                "can not be used when `mesh.wire_encryption` is 'none'",
+            );
+        }
+
+        sec.finish();
+        ret
+    }
+}
+
+fn compact_policy(sec: &mut Section) -> CompactionPolicy {
+    let mut ret = CompactionPolicy::default();
+    if let Some(a) = sec.parse("max_log_size") {
+        ret = ret.with_max_log_size(a);
+    }
+    if let Some(a) = sec.parse::<f32>("max_tombstone_ratio") {
+        if a < 0.0 || a > 1.0 {
+            sec.problem("max_tombstone_ratio", format!("({}) must be between 0 and 1", a));
+        } else {
+            ret = ret.with_max_tombstone_ratio(a);
+        }
+    }
+    if let Some(a) = sec.duration("max_age") {
+        ret = ret.with_max_age(a);
+    }
+    if let Some(a) = sec.parse::<CompactionWindow>("window") {
+        ret = ret.with_window(a);
+    }
+    if let Some(a) = sec.duration("check_interval") {
+        ret = ret.with_check_interval(a);
+    }
+    ret
+}
+
+fn quorum_policy(sec: &mut Section) -> QuorumPolicy {
+    let mut ret = QuorumPolicy::default();
+    for replica in sec.strings("replicas").unwrap_or_default() {
+        match url::Url::parse(replica.as_str()) {
+            Ok(a) => ret = ret.with_replica(a),
+            Err(err) => sec.problem("replicas", format!("contains an invalid url ({}) - {}", replica, err)),
+        }
+    }
+    if let Some(a) = sec.parse("required") {
+        ret = ret.with_required(a);
+    }
+    if let Some(a) = sec.duration("timeout") {
+        ret = ret.with_timeout(a);
+    }
+    if let Some(a) = sec.parse("relay_attempts") {
+        ret = ret.with_relay_attempts(a);
+    }
+    if let Some(a) = sec.duration("repair_interval") {
+        ret = ret.with_repair_interval(a);
+    }
+    if ret.required < 1 {
+        sec.problem("required", "must be at least 1");
+    } else if ret.required > ret.replicas.len() {
+        sec.problem(
+            "required",
+            format!(
+                "({}) can not be more than the number of replicas ({})",
+                ret.required,
+                ret.replicas.len()
+            ),
+        );
+    }
+    ret
+}
+
+#[cfg(feature = "enable_server")]
+fn watchdog_policy(sec: &mut Section) -> WatchdogPolicy {
+    let mut ret = WatchdogPolicy::default();
+    if let Some(a) = sec.duration("threshold") {
+        ret = ret.with_threshold(a);
+    }
+    if let Some(a) = sec.duration("check_interval") {
+        if a.is_zero() {
+            sec.problem("check_interval", "must be greater than zero");
+        } else {
+            ret = ret.with_check_interval(a);
+        }
+    }
+    if let Some(a) = sec.parse("recover") {
+        ret = ret.with_recover(a);
+    }
+    ret
+}
+
+/// Table within the configuration document, fields are taken out of the
+/// table as they are read so that whatever remains at the end is unknown
+struct Section<'a> {
+    path: String,
+    values: Map<String, Value>,
+    problems: &'a mut Vec<String>,
+}
+
+impl<'a> Section<'a> {
+    fn new(path: String, value: Value, problems: &'a mut Vec<String>) -> Section<'a> {
+        let values = match value {
+            Value::Object(a) => a,
+            Value::Null => Map::new(),
+            _ => {
+                problems.push(format!("`{}` must be a table", path));
+                Map::new()
+            }
+        };
+        Section {
+            path,
+            values,
+            problems,
+        }
+    }
+
+    fn field(&self, key: &str) -> String {
+        format!("{}.{}", self.path, key)
+    }
+
+    fn problem(&mut self, key: &str, msg: impl std::fmt::Display) {
+        let field = self.field(key);
+        self.problems.push(format!("`{}` {}", field, msg));
+    }
+
+    fn missing(&mut self, key: &str) {
+        self.problem(key, "is required");
+    }
+
+    fn section(&mut self, key: &str) -> Option<Section<'_>> {
+        let value = self.values.remove(key)?;
+        let path = self.field(key);
+        Some(Section::new(path, value, self.problems))
+    }
+
+    fn string(&mut self, key: &str) -> Option<String> {
+        match self.values.remove(key)? {
+            Value::String(a) => Some(a),
+            Value::Number(a) => Some(a.to_string()),
+            Value::Bool(a) => Some(a.to_string()),
+            _ => {
+                self.problem(key, "must be a string");
+                None
+            }
+        }
+    }
+
+    fn strings(&mut self, key: &str) -> Option<Vec<String>> {
+        match self.values.remove(key)? {
+            Value::Array(a) => {
+                let mut ret = Vec::new();
+                for (n, a) in a.into_iter().enumerate() {
+                    match a {
+                        Value::String(a) => ret.push(a),
+                        _ => self.problem(key, format!("must only contain strings (item {})", n)),
+                    }
+                }
+                Some(ret)
+            }
+            Value::String(a) => Some(vec![a]),
+            _ => {
+                self.problem(key, "must be a list of strings");
+                None
+            }
+        }
+    }
+
+    /// Reads a value using its string form so that values which came from
+    /// environment variables are treated the same as literal values
+    fn parse<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let val = self.string(key)?;
+        match T::from_str(val.trim()) {
+            Ok(a) => Some(a),
+            Err(err) => {
+                self.problem(key, format!("is invalid ({}) - {}", val, err));
+                None
+            }
+        }
+    }
+
+    fn duration(&mut self, key: &str) -> Option<Duration> {
+        let secs = self.parse::<f64>(key)?;
+        if secs.is_finite() == false || secs < 0.0 {
+            self.problem(key, format!("({}) must be a positive number of seconds", secs));
+            return None;
+        }
+        Some(Duration::from_secs_f64(secs))
+    }
+
+    fn port(&mut self, key: &str) -> Option<u16> {
+        let port = self.parse::<i64>(key)?;
+        if port < 1 || port > u16::MAX as i64 {
+            self.problem(key, format!("({}) is out of range - ports must be between 1 and 65535", port));
+            return None;
+        }
+        Some(port as u16)
+    }
+
+    fn address(&mut self, key: &str) -> Option<MeshAddress> {
+        let val = self.string(key)?;
+        match parse_mesh_address(val.trim()) {
+            Ok(a) => Some(a),
+            Err(err) => {
+                self.problem(key, format!("is invalid ({}) - {}", val, err));
+                None
+            }
+        }
+    }
+
+    fn addresses(&mut self, key: &str) -> Option<Vec<MeshAddress>> {
+        let mut ret = Vec::new();
+        for val in self.strings(key)? {
+            match parse_mesh_address(val.trim()) {
+                Ok(a) => ret.push(a),
+                Err(err) => self.problem(key, format!("contains an invalid address ({}) - {}", val, err)),
+            }
+        }
+        Some(ret)
+    }
+
+    /// Reports all the fields that were not read as unknown
+    fn finish(self) {
+        for key in self.values.keys() {
+            self.problems
+                .push(format!("`{}.{}` is not a known field", self.path, key));
+        }
+    }
+}
+
+fn parse_mesh_address(val: &str) -> Result<MeshAddress, String> {
+    let (host, port) = match val.rsplit_once(':') {
+        Some(a) => a,
+        None => return Err("addresses must be in the format 'host:port'".to_string()),
+    };
+    let port = match u16::from_str(port) {
+        Ok(a) if a > 0 => a,
+        _ => return Err("ports must be between 1 and 65535".to_string()),
+    };
+
+    #[cfg(feature = "enable_dns")]
+    {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        match std::net::IpAddr::from_str(host) {
+            Ok(a) => Ok(MeshAddress::new(a, port)),
+            Err(_) => Err("addresses must be in the format 'ip:port'".to_string()),
+        }
+    }
+    #[cfg(not(feature = "enable_dns"))]
+    Ok(MeshAddress::new(host, port))
+}
+
+/// Replaces `${VAR}` in all the strings of the document with the values of
+/// the environment variables
+fn interpolate(value: &mut Value, path: &str, problems: &mut Vec<String>) {
+    match value {
+        Value::String(a) => {
+            *a = interpolate_str(a.as_str(), path, problems);
+        }
+        Value::Array(a) => {
+            for (n, a) in a.iter_mut().enumerate() {
+                interpolate(a, format!("{}[{}]", path, n).as_str(), problems);
+            }
+        }
+        Value::Object(a) => {
+            for (k, a) in a.iter_mut() {
+                let path = match path.len() {
+                    0 => k.clone(),
+                    _ => format!("{}.{}", path, k),
+                };
+                interpolate(a, path.as_str(), problems);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn interpolate_str(val: &str, path: &str, problems: &mut Vec<String>) -> String {
+    let mut ret = String::with_capacity(val.len());
+    let mut rest = val;
+    while let Some(idx) = rest.find('$') {
+        ret.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        if rest.starts_with("$${") {
+            ret.push_str("${");
+            rest = &rest[3..];
+        } else if rest.starts_with("${") {
+            match rest.find('}') {
+                Some(end) => {
+                    let name = &rest[2..end];
+                    match std::env::var(name) {
+                        Ok(a) => ret.push_str(a.as_str()),
+                        Err(_) => problems.push(format!(
+                            "`{}` refers to the environment variable `{}` which is not set",
+                            path, name
+                        )),
+                    }
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    problems.push(format!("`{}` has an unterminated `${{`", path));
+                    ret.push_str(rest);
+                    rest = "";
+                }
+            }
+        } else {
+            ret.push('$');
+            rest = &rest[1..];
+        }
+    }
+    ret.push_str(rest);
+    ret
+}
+
+impl ConfAte {
+    /// Loads the ATE configuration from the `ate` section of a configuration
+    /// file (see `ConfDocument` for the schema)
+    pub fn from_file(path: impl AsRef<Path>) -> Result<ConfAte, ConfigError> {
+        ConfDocument::from_file(path)?.ate()
+    }
+}
+
+impl ConfMesh {
+    /// Loads the mesh configuration from the `mesh` section of a
+    /// configuration file (see `ConfDocument` for the schema)
+    pub fn from_file(path: impl AsRef<Path>) -> Result<ConfMesh, ConfigError> {
+        ConfDocument::from_file(path)?.mesh()
+    }
+}
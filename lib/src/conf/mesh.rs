@@ -2,13 +2,33 @@
 use error_chain::bail;
 use std::iter::Iterator;
 use std::net::IpAddr;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use crate::comms::CertificateValidation;
+#[cfg(feature = "enable_server")]
+use crate::comms::{ServerCertificate, ServerCertificates};
+use crate::comms::KeepAlive;
+use crate::comms::PacketTapHook;
+use crate::comms::StreamProtocolVersion;
+#[cfg(feature = "enable_server")]
+use crate::comms::{HandshakeTimeouts, HANDSHAKE_DEFAULT_MAX_PER_IP, OUTBOX_DEFAULT_HIGH_WATER};
+use crate::chain::InboundBudget;
+use crate::comms::Throttle;
 use crate::conf::ConfAte;
 use crate::crypto::KeySize;
 use crate::mesh::Registry;
+#[cfg(feature = "enable_server")]
+use crate::mesh::DEFAULT_HISTORY_CACHE_SIZE;
+#[cfg(feature = "enable_server")]
+use crate::mesh::DEFAULT_INFLIGHT_CONNECTION_LIMIT;
+#[cfg(feature = "enable_server")]
+use crate::mesh::DEFAULT_INFLIGHT_ROUTE_LIMIT;
+#[cfg(feature = "enable_server")]
+use crate::mesh::DEFAULT_CHAIN_QUOTA;
+#[cfg(feature = "enable_server")]
+use crate::mesh::WatchdogPolicy;
 use crate::prelude::*;
 use crate::{comms::StreamProtocol, error::CommsError};
 
@@ -32,6 +52,10 @@ pub struct ConfMesh {
 
     /// List of all the addresses that the root nodes exists on
     pub roots: Vec<MeshAddress>,
+    /// When the roots were discovered from SRV records this keeps them up
+    /// to date (new connections use the latest set of roots)
+    #[cfg(feature = "enable_dns")]
+    pub discovery: Option<Arc<SrvDiscovery>>,
 
     /// Forces ATE to act as a client even if its local IP address is one
     /// of the node machines in the clusters (normally ATE would automatically
@@ -55,6 +79,24 @@ pub struct ConfMesh {
     /// below when establishing secure connections.
     #[cfg(feature = "enable_server")]
     pub listen_certificate: Option<PrivateEncryptKey>,
+    /// Additional certificates (with the window of time they are valid for)
+    /// that the server accepts secure connections with, all the certificates
+    /// that are valid are advertised to the clients so that a certificate can
+    /// be rotated without clients that pinned the old one being turned away
+    #[cfg(feature = "enable_server")]
+    pub listen_certificates: Vec<ServerCertificate>,
+    /// Path to the PEM certificate chain that the server presents to the
+    /// clients that connect with the `tls` protocol
+    #[cfg(feature = "enable_server")]
+    pub listen_tls_certificate: Option<String>,
+    /// Path to the PEM private key of the TLS certificate above
+    #[cfg(feature = "enable_server")]
+    pub listen_tls_key: Option<String>,
+    /// (Optional) Path to the PEM certificates of the authorities that sign
+    /// client certificates, when set clients connecting over `tls` must
+    /// present one and its identity is passed on to the routes
+    #[cfg(feature = "enable_server")]
+    pub listen_tls_client_ca: Option<String>,
     /// Forces ATE to process all requests related to this particular node_id.
     /// Use this property when the node_id can not be derived from the list
     /// of addresses and your listen address. For instance when behind a load
@@ -74,11 +116,61 @@ pub struct ConfMesh {
     /// which double encrypting your data and the metadata around it is
     /// another defence.
     pub wire_encryption: Option<KeySize>,
+    /// When true the client refuses to use connections where no wire encryption
+    /// was negotiated, regardless of what the server said during the hello
+    /// (this stops a man-in-the-middle from silently stripping the encryption)
+    pub require_encryption: bool,
+    /// When false the wire encryption above is not used on connections that
+    /// already run inside a TLS tunnel (the `tls` protocol), both the clients
+    /// and the servers must agree on this
+    pub tls_wire_encryption: bool,
+    /// (Optional) Path to the PEM certificates of the authorities that are
+    /// trusted to sign the certificate of a `tls` server, by default the well
+    /// known web authorities are trusted
+    #[cfg(feature = "enable_client")]
+    pub tls_ca: Option<String>,
+    /// (Optional) Path to the PEM certificate that the client presents to
+    /// `tls` servers that authenticate their clients
+    #[cfg(feature = "enable_client")]
+    pub tls_client_certificate: Option<String>,
+    /// (Optional) Path to the PEM private key of the client certificate
+    #[cfg(feature = "enable_client")]
+    pub tls_client_key: Option<String>,
     /// Time to wait for a connection to a server before it times out
     pub connect_timeout: Duration,
     /// Time to wait for a connection to be accepted during handshaking
     #[cfg(feature = "enable_server")]
     pub accept_timeout: Duration,
+    /// Time that connecting clients are given for each stage of the
+    /// handshake (hello and key exchange) before the connection is aborted
+    #[cfg(feature = "enable_server")]
+    pub handshake_timeouts: HandshakeTimeouts,
+    /// Maximum number of connections from the same address that may be
+    /// waiting to say hello at the same time
+    #[cfg(feature = "enable_server")]
+    pub max_pre_hello_per_ip: usize,
+    /// When true every accepted TCP connection must start with a PROXY
+    /// protocol v2 header (e.g. from HAProxy or an NLB) and the client
+    /// address it carries is used in place of the address of the socket
+    #[cfg(feature = "enable_server")]
+    pub proxy_protocol: bool,
+    /// Number of broadcast packets that may be queued for a connected client
+    /// that is not reading them before it is disconnected (so that a stalled
+    /// client can not hold up the others or exhaust the memory of the server)
+    #[cfg(feature = "enable_server")]
+    pub outbox_high_water: usize,
+
+    /// Time that a connection may be quiet before the peer is sent a ping to
+    /// check that it is still alive (None turns the keepalive off). The
+    /// keepalive is only used when both sides ask for it and never with
+    /// peers that speak an older version of the stream protocol.
+    pub keepalive_interval: Option<Duration>,
+    /// Time that the peer is given to answer each keepalive ping
+    pub keepalive_timeout: Duration,
+    /// Number of keepalive pings in a row that may go unanswered before the
+    /// peer is considered dead, servers then drop the connection (releasing
+    /// whatever locks it held) and clients reconnect
+    pub keepalive_max_missed: u32,
 
     /// Connection attempts will abort quickly in the scenario that something is wrong rather
     /// than retrying in an exponential backoff
@@ -92,12 +184,81 @@ pub struct ConfMesh {
     /// underlying communication channels
     pub wire_protocol: StreamProtocol,
 
+    /// Highest version of the stream protocol that will be negotiated with
+    /// peers, pinning this to an older version allows for staged rollouts
+    pub max_protocol_version: StreamProtocolVersion,
+
     /// Size of the buffer on mesh clients, tweak this number with care
     #[cfg(feature = "enable_client")]
     pub buffer_size_client: usize,
+    /// When enabled chains that connect to the same server with the same
+    /// protocol and credentials will share a single connection (if the
+    /// server supports it) rather than opening a connection per chain.
+    /// This is off by default as a busy chain will hold up the others
+    /// that share its connection (head-of-line blocking)
+    #[cfg(feature = "enable_client")]
+    pub multiplex: bool,
+    /// Budget for the cache maintenance that clients perform inline for
+    /// events received from the server, beyond it the work is coalesced
+    /// (by default there is no budget)
+    #[cfg(feature = "enable_client")]
+    pub inbound_budget: Option<InboundBudget>,
     /// Size of the buffer on mesh servers, tweak this number with care
     #[cfg(feature = "enable_server")]
     pub buffer_size_server: usize,
+
+    /// Maximum number of chains that will be compacted concurrently by the
+    /// compaction policy of this server
+    #[cfg(feature = "enable_server")]
+    pub compact_concurrency: usize,
+    /// Flag that indicates if clients are allowed to trigger a compaction
+    /// of the chain they are subscribed to (admin use only)
+    #[cfg(feature = "enable_server")]
+    pub compact_remote_trigger: bool,
+    /// Amount of memory (in bytes) that each route may use to cache the
+    /// history batches it streams so that many clients subscribing to the
+    /// same chain share them (zero disables the cache)
+    #[cfg(feature = "enable_server")]
+    pub history_cache_size: usize,
+    /// Amount of memory (in bytes) that the transactions received on one
+    /// connection may use while they are in flight, larger packets are
+    /// refused before they are decoded and the connection is closed (zero
+    /// disables the limit)
+    #[cfg(feature = "enable_server")]
+    pub inflight_connection_limit: usize,
+    /// Amount of memory (in bytes) that the transactions in flight on all
+    /// the connections of a route may use together, beyond it the reads of
+    /// the noisiest connections are paused (zero disables the limit)
+    #[cfg(feature = "enable_server")]
+    pub inflight_route_limit: usize,
+    /// Number of bytes that each chain hosted on a route may occupy on disk,
+    /// commits beyond it are rejected (zero disables the quota). The quota
+    /// can be changed (or overridden for single chains) on the root.
+    #[cfg(feature = "enable_server")]
+    pub chain_quota: u64,
+    /// Policy that makes the routes of a mesh root detect chains whose
+    /// transactions have stopped making progress and optionally recover
+    /// them, the policy can be changed (or removed) per route on the root.
+    #[cfg(feature = "enable_server")]
+    pub watchdog_policy: Option<WatchdogPolicy>,
+    /// Throttle that is applied to every connection accepted by this server,
+    /// in adaptive mode congested servers will automatically slow down
+    /// chatty clients
+    #[cfg(feature = "enable_server")]
+    pub listen_throttle: Throttle,
+    /// (Optional) Key the server uses to periodically sign the root head of
+    /// the hash tree over the history of every chain it hosts, the signed
+    /// heads allow inclusion proofs to be verified offline
+    #[cfg(feature = "enable_server")]
+    pub checkpoint_key: Option<PrivateSignKey>,
+    /// How often the root heads of the hosted chains are checkpointed (only
+    /// chains that have changed since their last checkpoint are signed again)
+    #[cfg(feature = "enable_server")]
+    pub checkpoint_interval: Duration,
+    /// (Optional) Tap that is installed on every connection made or accepted
+    /// with this configuration so the frames can be captured (debug builds only)
+    #[cfg(debug_assertions)]
+    pub packet_tap: Option<PacketTapHook>,
 }
 
 impl ConfMesh {
@@ -212,6 +373,47 @@ impl ConfMesh {
         ConfMesh::solo(cfg_ate, domain, port, node_id)
     }
 
+    /// Locates the roots of the mesh from SRV records (e.g. "_ate._tcp.example.com")
+    /// that are resolved again whenever their TTL expires
+    #[cfg(feature = "enable_dns")]
+    #[cfg(feature = "enable_full")]
+    pub async fn for_srv(cfg_ate: &ConfAte, name: &str) -> Result<ConfMesh, CommsError> {
+        let resolver = Arc::new(DnsSrvResolver::new(cfg_ate).await);
+        ConfMesh::for_srv_with_resolver(name, resolver).await
+    }
+
+    /// Locates the roots of the mesh from SRV records using a specific resolver
+    #[cfg(feature = "enable_dns")]
+    pub async fn for_srv_with_resolver(
+        name: &str,
+        resolver: Arc<dyn SrvResolver>,
+    ) -> Result<ConfMesh, CommsError> {
+        let discovery = SrvDiscovery::new(name, resolver).await?;
+        discovery.spawn_refresher();
+
+        // The domain is whatever follows the service and protocol labels
+        let domain = name
+            .split(".")
+            .skip_while(|a| a.starts_with("_"))
+            .collect::<Vec<_>>()
+            .join(".");
+        let roots = discovery.roots();
+        let port = match roots.first() {
+            Some(a) => a.port,
+            None => {
+                bail!(CommsErrorKind::NoAddress);
+            }
+        };
+        let remote = url::Url::parse(
+            format!("{}://{}:{}/", Registry::guess_schema(port), domain, port).as_str(),
+        )?;
+
+        let mut ret = ConfMesh::new(domain.as_str(), remote, roots.iter());
+        ret.wire_protocol = StreamProtocol::parse(&ret.remote)?;
+        ret.discovery = Some(discovery);
+        Ok(ret)
+    }
+
     pub(crate) fn new<'a, 'b>(
         domain_name: &'a str,
         remote: url::Url,
@@ -219,6 +421,8 @@ impl ConfMesh {
     ) -> ConfMesh {
         ConfMesh {
             roots: roots.map(|a| a.clone()).collect::<Vec<_>>(),
+            #[cfg(feature = "enable_dns")]
+            discovery: None,
             domain_name: domain_name.to_string(),
             remote,
             certificate_validation: CertificateValidation::AllowedCertificates(Vec::new()),
@@ -226,6 +430,14 @@ impl ConfMesh {
             listen_min_encryption: None,
             #[cfg(feature = "enable_server")]
             listen_certificate: None,
+            #[cfg(feature = "enable_server")]
+            listen_certificates: Vec::new(),
+            #[cfg(feature = "enable_server")]
+            listen_tls_certificate: None,
+            #[cfg(feature = "enable_server")]
+            listen_tls_key: None,
+            #[cfg(feature = "enable_server")]
+            listen_tls_client_ca: None,
             #[cfg(feature = "enable_client")]
             force_client_only: false,
             #[cfg(feature = "enable_server")]
@@ -237,16 +449,88 @@ impl ConfMesh {
             #[cfg(feature = "enable_client")]
             force_connect: None,
             wire_encryption: Some(KeySize::Bit128),
+            require_encryption: false,
+            tls_wire_encryption: true,
+            #[cfg(feature = "enable_client")]
+            tls_ca: None,
+            #[cfg(feature = "enable_client")]
+            tls_client_certificate: None,
+            #[cfg(feature = "enable_client")]
+            tls_client_key: None,
             wire_protocol: StreamProtocol::WebSocket,
+            max_protocol_version: StreamProtocolVersion::default(),
             wire_format: SerializationFormat::Bincode,
             connect_timeout: Duration::from_secs(30),
             #[cfg(feature = "enable_server")]
             accept_timeout: Duration::from_secs(10),
+            #[cfg(feature = "enable_server")]
+            handshake_timeouts: HandshakeTimeouts::default(),
+            #[cfg(feature = "enable_server")]
+            max_pre_hello_per_ip: HANDSHAKE_DEFAULT_MAX_PER_IP,
+            #[cfg(feature = "enable_server")]
+            proxy_protocol: false,
+            #[cfg(feature = "enable_server")]
+            outbox_high_water: OUTBOX_DEFAULT_HIGH_WATER,
+            keepalive_interval: Some(Duration::from_secs(30)),
+            keepalive_timeout: Duration::from_secs(10),
+            keepalive_max_missed: 3,
             fail_fast: false,
             #[cfg(feature = "enable_client")]
             buffer_size_client: 2,
+            #[cfg(feature = "enable_client")]
+            multiplex: false,
+            #[cfg(feature = "enable_client")]
+            inbound_budget: None,
             #[cfg(feature = "enable_server")]
             buffer_size_server: 10,
+            #[cfg(feature = "enable_server")]
+            compact_concurrency: 2,
+            #[cfg(feature = "enable_server")]
+            compact_remote_trigger: false,
+            #[cfg(feature = "enable_server")]
+            history_cache_size: DEFAULT_HISTORY_CACHE_SIZE,
+            #[cfg(feature = "enable_server")]
+            inflight_connection_limit: DEFAULT_INFLIGHT_CONNECTION_LIMIT,
+            #[cfg(feature = "enable_server")]
+            inflight_route_limit: DEFAULT_INFLIGHT_ROUTE_LIMIT,
+            #[cfg(feature = "enable_server")]
+            chain_quota: DEFAULT_CHAIN_QUOTA,
+            #[cfg(feature = "enable_server")]
+            watchdog_policy: None,
+            #[cfg(feature = "enable_server")]
+            listen_throttle: Throttle::default(),
+            #[cfg(feature = "enable_server")]
+            checkpoint_key: None,
+            #[cfg(feature = "enable_server")]
+            checkpoint_interval: Duration::from_secs(60),
+            #[cfg(debug_assertions)]
+            packet_tap: None,
         }
     }
+
+    /// Returns all the certificates that the server listens with (the
+    /// `listen_certificate` comes first as it is the oldest)
+    #[cfg(feature = "enable_server")]
+    pub fn server_certificates(&self) -> ServerCertificates {
+        let mut ret = ServerCertificates::from(self.listen_certificate.clone());
+        for cert in self.listen_certificates.iter() {
+            ret.push(cert.clone());
+        }
+        ret
+    }
+
+    /// Returns the keepalive that this side asks for during the hello
+    pub(crate) fn keepalive(&self) -> Option<KeepAlive> {
+        self.keepalive_interval
+            .map(|interval| KeepAlive::new(interval, self.keepalive_timeout, self.keepalive_max_missed))
+    }
+
+    /// Returns the tap that should be installed on new connections (release
+    /// builds never install one)
+    pub(crate) fn packet_tap(&self) -> Option<PacketTapHook> {
+        #[cfg(debug_assertions)]
+        return self.packet_tap.clone();
+        #[cfg(not(debug_assertions))]
+        return None;
+    }
 }
@@ -1,12 +1,20 @@
 pub mod chain_builder;
 pub mod conf_ate;
+#[cfg(feature = "enable_local_fs")]
+pub mod conf_file;
 pub mod configured_for;
 pub mod mesh;
 pub mod mesh_address;
+#[cfg(feature = "enable_dns")]
+pub mod srv;
 pub mod tests;
 
 pub use chain_builder::*;
 pub use conf_ate::*;
+#[cfg(feature = "enable_local_fs")]
+pub use conf_file::*;
 pub use configured_for::*;
 pub use mesh::*;
 pub use mesh_address::*;
+#[cfg(feature = "enable_dns")]
+pub use srv::*;
@@ -0,0 +1,291 @@
+#![allow(unused_imports)]
+use async_trait::async_trait;
+use derivative::*;
+use error_chain::bail;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::RwLock as StdRwLock;
+use std::sync::Weak;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::Mutex;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::conf::ConfAte;
+use crate::dns::*;
+use crate::engine::TaskEngine;
+use crate::error::*;
+use crate::mesh::MeshHashTable;
+
+use super::MeshAddress;
+
+/// Shortest amount of time between two resolutions of the SRV records
+/// (regardless of how low the TTL on the records is)
+pub const SRV_MIN_REFRESH: Duration = Duration::from_secs(30);
+
+/// Longest amount of time the resolved roots are used before the SRV
+/// records are resolved again
+pub const SRV_MAX_REFRESH: Duration = Duration::from_secs(3600);
+
+/// Single SRV record that points at a mesh root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvTarget {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+/// Answer to a SRV query
+#[derive(Debug, Clone, Default)]
+pub struct SrvAnswer {
+    pub targets: Vec<SrvTarget>,
+    /// Lowest TTL of all the records in the answer
+    pub ttl: Duration,
+}
+
+/// Resolves the SRV records (and the addresses of their targets) that
+/// publish the roots of a mesh
+#[async_trait]
+pub trait SrvResolver
+where
+    Self: Send + Sync,
+{
+    async fn resolve_srv(&self, name: &str) -> Result<SrvAnswer, CommsError>;
+
+    async fn resolve_host(&self, host: &str) -> Result<Vec<IpAddr>, CommsError>;
+}
+
+/// Resolver that queries the DNS server in the configuration
+#[cfg(feature = "enable_full")]
+pub struct DnsSrvResolver {
+    dns: Mutex<DnsClient>,
+}
+
+#[cfg(feature = "enable_full")]
+impl DnsSrvResolver {
+    pub async fn new(cfg_ate: &ConfAte) -> DnsSrvResolver {
+        DnsSrvResolver {
+            dns: Mutex::new(DnsClient::connect(cfg_ate).await),
+        }
+    }
+}
+
+#[cfg(feature = "enable_full")]
+#[async_trait]
+impl SrvResolver for DnsSrvResolver {
+    async fn resolve_srv(&self, name: &str) -> Result<SrvAnswer, CommsError> {
+        let name = Name::from_str(name).map_err(|_| CommsErrorKind::InvalidDomainName)?;
+        let mut dns = self.dns.lock().await;
+        let response = dns
+            .query(name, DNSClass::IN, RecordType::SRV)
+            .await
+            .map_err(|err| CommsErrorKind::InternalError(err.to_string()))?;
+
+        let mut ret = SrvAnswer::default();
+        let mut ttl: Option<u32> = None;
+        for answer in response.answers() {
+            if let RData::SRV(ref srv) = *answer.rdata() {
+                ttl = Some(ttl.map_or(answer.ttl(), |a| a.min(answer.ttl())));
+                ret.targets.push(SrvTarget {
+                    priority: srv.priority(),
+                    weight: srv.weight(),
+                    port: srv.port(),
+                    target: srv.target().to_string().trim_end_matches(".").to_string(),
+                });
+            }
+        }
+        ret.ttl = Duration::from_secs(ttl.unwrap_or(0) as u64);
+        Ok(ret)
+    }
+
+    async fn resolve_host(&self, host: &str) -> Result<Vec<IpAddr>, CommsError> {
+        if let Ok(ip) = IpAddr::from_str(host) {
+            return Ok(vec![ip]);
+        }
+
+        let name = Name::from_str(host).map_err(|_| CommsErrorKind::InvalidDomainName)?;
+        let mut dns = self.dns.lock().await;
+        let mut addrs = Vec::new();
+        for record_type in [RecordType::AAAA, RecordType::A] {
+            let response = match dns.query(name.clone(), DNSClass::IN, record_type).await {
+                Ok(a) => a,
+                Err(err) => {
+                    debug!("failed to resolve {} ({}) - {}", host, record_type, err);
+                    continue;
+                }
+            };
+            for answer in response.answers() {
+                match *answer.rdata() {
+                    RData::A(ref address) => addrs.push(IpAddr::V4(address.clone())),
+                    RData::AAAA(ref address) => addrs.push(IpAddr::V6(address.clone())),
+                    _ => {}
+                }
+            }
+        }
+        Ok(addrs)
+    }
+}
+
+/// Orders the SRV targets by priority (lowest first) and then by weight
+/// (highest first), ties are broken by name so that every client ends
+/// up with the same order for the same answer
+pub fn order_srv_targets(mut targets: Vec<SrvTarget>) -> Vec<SrvTarget> {
+    targets.sort_by(|a, b| {
+        a.priority
+            .cmp(&b.priority)
+            .then(b.weight.cmp(&a.weight))
+            .then(a.target.cmp(&b.target))
+            .then(a.port.cmp(&b.port))
+    });
+    targets
+}
+
+/// Roots that were discovered by the last resolution
+#[derive(Debug)]
+pub struct DiscoveredRoots {
+    pub roots: Vec<MeshAddress>,
+    pub lookup: Arc<MeshHashTable>,
+    pub refresh_at: Instant,
+}
+
+/// Discovers the roots of a mesh from SRV records and keeps them up to date
+/// by resolving the records again once their TTL expires. Only the targets
+/// with the best priority that resolve to an address become roots (targets
+/// with a weight of zero are only used when nothing else is available)
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct SrvDiscovery {
+    pub name: String,
+    #[derivative(Debug = "ignore")]
+    resolver: Arc<dyn SrvResolver>,
+    current: StdRwLock<Arc<DiscoveredRoots>>,
+}
+
+impl SrvDiscovery {
+    /// Performs the first resolution of the SRV records, it is an error if
+    /// no roots are found
+    pub async fn new(
+        name: &str,
+        resolver: Arc<dyn SrvResolver>,
+    ) -> Result<Arc<SrvDiscovery>, CommsError> {
+        let current = Self::resolve(name, resolver.as_ref()).await?;
+        if current.roots.is_empty() {
+            bail!(CommsErrorKind::NoAddress);
+        }
+        Ok(Arc::new(SrvDiscovery {
+            name: name.to_string(),
+            resolver,
+            current: StdRwLock::new(Arc::new(current)),
+        }))
+    }
+
+    pub fn current(&self) -> Arc<DiscoveredRoots> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+
+    pub fn roots(&self) -> Vec<MeshAddress> {
+        self.current().roots.clone()
+    }
+
+    pub fn lookup(&self) -> Arc<MeshHashTable> {
+        Arc::clone(&self.current().lookup)
+    }
+
+    async fn resolve(name: &str, resolver: &dyn SrvResolver) -> Result<DiscoveredRoots, CommsError> {
+        let answer = resolver.resolve_srv(name).await?;
+        let refresh = answer.ttl.clamp(SRV_MIN_REFRESH, SRV_MAX_REFRESH);
+
+        let targets = order_srv_targets(answer.targets);
+        let mut priorities = targets.iter().map(|a| a.priority).collect::<Vec<_>>();
+        priorities.dedup();
+
+        let mut roots = Vec::new();
+        for priority in priorities {
+            let group = targets
+                .iter()
+                .filter(|a| a.priority == priority)
+                .collect::<Vec<_>>();
+            let weighted = group.iter().any(|a| a.weight > 0);
+            for target in group {
+                if weighted && target.weight == 0 {
+                    continue;
+                }
+                let addrs = match resolver.resolve_host(target.target.as_str()).await {
+                    Ok(a) => a,
+                    Err(err) => {
+                        debug!("srv target {} did not resolve - {}", target.target, err);
+                        continue;
+                    }
+                };
+                for addr in addrs {
+                    let addr = MeshAddress::new(addr, target.port);
+                    if roots.contains(&addr) == false {
+                        roots.push(addr);
+                    }
+                }
+            }
+            if roots.is_empty() == false {
+                break;
+            }
+        }
+
+        debug!("srv records for {} resolved to {} roots", name, roots.len());
+        Ok(DiscoveredRoots {
+            lookup: Arc::new(MeshHashTable::from_roots(roots.iter())),
+            roots,
+            refresh_at: Instant::now() + refresh,
+        })
+    }
+
+    /// Resolves the SRV records again, the hash table is only rebuilt when
+    /// the set of roots has actually changed (failures keep the old roots)
+    pub async fn refresh(&self) -> Result<bool, CommsError> {
+        let next = Self::resolve(self.name.as_str(), self.resolver.as_ref()).await?;
+        let mut guard = self.current.write().unwrap();
+        if next.roots.is_empty() || next.roots == guard.roots {
+            *guard = Arc::new(DiscoveredRoots {
+                roots: guard.roots.clone(),
+                lookup: Arc::clone(&guard.lookup),
+                refresh_at: next.refresh_at,
+            });
+            return Ok(false);
+        }
+        info!("roots for {} changed ({} roots)", self.name, next.roots.len());
+        *guard = Arc::new(next);
+        Ok(true)
+    }
+
+    /// Keeps resolving the SRV records as their TTL expires until the
+    /// discovery is dropped
+    pub(crate) fn spawn_refresher(self: &Arc<Self>) {
+        let discovery = Arc::downgrade(self);
+        TaskEngine::spawn(async move {
+            loop {
+                let wait = match Weak::upgrade(&discovery) {
+                    Some(a) => a
+                        .current()
+                        .refresh_at
+                        .saturating_duration_since(Instant::now()),
+                    None => break,
+                };
+                crate::engine::sleep(wait).await;
+
+                let discovery = match Weak::upgrade(&discovery) {
+                    Some(a) => a,
+                    None => break,
+                };
+                if let Err(err) = discovery.refresh().await {
+                    warn!("failed to refresh the srv records for {} - {}", discovery.name, err);
+                    let mut guard = discovery.current.write().unwrap();
+                    *guard = Arc::new(DiscoveredRoots {
+                        roots: guard.roots.clone(),
+                        lookup: Arc::clone(&guard.lookup),
+                        refresh_at: Instant::now() + SRV_MIN_REFRESH,
+                    });
+                }
+            }
+        });
+    }
+}
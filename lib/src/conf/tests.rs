@@ -4,6 +4,10 @@ use std::{net::IpAddr, str::FromStr};
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
+#[cfg(feature = "enable_dns")]
+use crate::chain::ChainKey;
+#[cfg(feature = "enable_dns")]
+use crate::error::CommsError;
 use crate::mesh::Registry;
 
 use super::*;
@@ -47,3 +51,204 @@ fn test_config_mocking() {
         "127.0.0.1"
     );
 }
+
+#[cfg(feature = "enable_dns")]
+struct MockSrvResolver {
+    answer: std::sync::Mutex<SrvAnswer>,
+    hosts: std::collections::HashMap<String, IpAddr>,
+}
+
+#[cfg(feature = "enable_dns")]
+#[async_trait::async_trait]
+impl SrvResolver for MockSrvResolver {
+    async fn resolve_srv(&self, _name: &str) -> Result<SrvAnswer, CommsError> {
+        Ok(self.answer.lock().unwrap().clone())
+    }
+
+    async fn resolve_host(&self, host: &str) -> Result<Vec<IpAddr>, CommsError> {
+        Ok(self.hosts.get(host).map(|a| vec![a.clone()]).unwrap_or_default())
+    }
+}
+
+#[cfg(feature = "enable_dns")]
+fn mock_srv_target(target: &str, priority: u16, weight: u16) -> SrvTarget {
+    SrvTarget {
+        priority,
+        weight,
+        port: 5000,
+        target: target.to_string(),
+    }
+}
+
+#[cfg(feature = "enable_dns")]
+fn mock_srv_resolver(targets: Vec<SrvTarget>) -> std::sync::Arc<MockSrvResolver> {
+    let mut hosts = std::collections::HashMap::new();
+    hosts.insert("a.example.com".to_string(), IpAddr::from_str("10.0.0.1").unwrap());
+    hosts.insert("b.example.com".to_string(), IpAddr::from_str("10.0.0.2").unwrap());
+    hosts.insert("c.example.com".to_string(), IpAddr::from_str("10.0.0.3").unwrap());
+    hosts.insert("d.example.com".to_string(), IpAddr::from_str("10.0.0.4").unwrap());
+    std::sync::Arc::new(MockSrvResolver {
+        answer: std::sync::Mutex::new(SrvAnswer {
+            targets,
+            ttl: std::time::Duration::from_secs(5),
+        }),
+        hosts,
+    })
+}
+
+#[cfg(feature = "enable_dns")]
+fn mock_srv_root(ip: &str) -> MeshAddress {
+    MeshAddress::new(IpAddr::from_str(ip).unwrap(), 5000)
+}
+
+#[cfg(feature = "enable_dns")]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_srv_priority_and_weight() {
+    crate::utils::bootstrap_test_env();
+
+    // The best priority wins, heavier targets come first and targets with
+    // no weight are skipped when others in the same priority have weight
+    let resolver = mock_srv_resolver(vec![
+        mock_srv_target("d.example.com", 20, 100),
+        mock_srv_target("c.example.com", 10, 0),
+        mock_srv_target("a.example.com", 10, 5),
+        mock_srv_target("b.example.com", 10, 50),
+    ]);
+    let cfg = ConfMesh::for_srv_with_resolver("_ate._tcp.example.com", resolver)
+        .await
+        .unwrap();
+    assert_eq!(cfg.domain_name, "example.com");
+    assert_eq!(cfg.roots, vec![mock_srv_root("10.0.0.2"), mock_srv_root("10.0.0.1")]);
+
+    // When the best priority does not resolve the next one is used
+    let resolver = mock_srv_resolver(vec![
+        mock_srv_target("missing.example.com", 10, 10),
+        mock_srv_target("d.example.com", 20, 0),
+    ]);
+    let discovery = SrvDiscovery::new("_ate._tcp.example.com", resolver)
+        .await
+        .unwrap();
+    assert_eq!(discovery.roots(), vec![mock_srv_root("10.0.0.4")]);
+}
+
+#[cfg(feature = "enable_dns")]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_srv_reresolution() {
+    crate::utils::bootstrap_test_env();
+
+    let resolver = mock_srv_resolver(vec![
+        mock_srv_target("a.example.com", 10, 10),
+        mock_srv_target("b.example.com", 10, 10),
+    ]);
+    let discovery = SrvDiscovery::new("_ate._tcp.example.com", resolver.clone())
+        .await
+        .unwrap();
+
+    // Low TTLs are clamped so the records are not hammered
+    let wait = discovery
+        .current()
+        .refresh_at
+        .saturating_duration_since(std::time::Instant::now());
+    assert!(wait > std::time::Duration::from_secs(5));
+    assert!(wait <= SRV_MIN_REFRESH);
+
+    // Nothing changed so the hash table is kept as is
+    let lookup = discovery.lookup();
+    assert_eq!(discovery.refresh().await.unwrap(), false);
+    assert!(std::sync::Arc::ptr_eq(&lookup, &discovery.lookup()));
+
+    // A root is added and another removed
+    resolver.answer.lock().unwrap().targets = vec![
+        mock_srv_target("b.example.com", 10, 10),
+        mock_srv_target("c.example.com", 10, 10),
+    ];
+    assert_eq!(discovery.refresh().await.unwrap(), true);
+    assert_eq!(
+        discovery.roots(),
+        vec![mock_srv_root("10.0.0.2"), mock_srv_root("10.0.0.3")]
+    );
+
+    // Every chain maps onto one of the new roots
+    let lookup = discovery.lookup();
+    for n in 0..50 {
+        let key = ChainKey::from(format!("chain-{}", n));
+        let (addr, _) = lookup.lookup(&key).unwrap();
+        assert!(discovery.roots().contains(&addr));
+    }
+
+    // Losing all the records keeps the last known roots
+    resolver.answer.lock().unwrap().targets = Vec::new();
+    assert_eq!(discovery.refresh().await.unwrap(), false);
+    assert_eq!(discovery.roots().len(), 2);
+}
+
+#[cfg(feature = "enable_local_fs")]
+#[test]
+fn test_conf_document() {
+    crate::utils::bootstrap_test_env();
+
+    std::env::set_var("ATE_TEST_CONF_DOMAIN", "example.com");
+    let doc = ConfDocument::parse(
+        r#"
+[ate]
+configured_for = "performance"
+load_timeout = 2.5
+
+[ate.quorum_policy]
+replicas = [ "ws://replica1.example.com/db", "ws://replica2.example.com/db" ]
+required = 2
+
+[mesh]
+domain_name = "${ATE_TEST_CONF_DOMAIN}"
+remote = "ws://${ATE_TEST_CONF_DOMAIN}:5000/"
+wire_encryption = "none"
+"#,
+        "toml",
+    )
+    .unwrap();
+    doc.check().unwrap();
+
+    let ate = doc.ate().unwrap();
+    assert_eq!(ate.load_timeout, std::time::Duration::from_millis(2500));
+    assert_eq!(ate.quorum_policy.unwrap().required, 2);
+
+    let mesh = doc.mesh().unwrap();
+    assert_eq!(mesh.domain_name, "example.com");
+    assert_eq!(mesh.remote.port(), Some(5000));
+    assert!(mesh.wire_encryption.is_none());
+}
+
+#[cfg(feature = "enable_local_fs")]
+#[test]
+fn test_conf_document_problems() {
+    crate::utils::bootstrap_test_env();
+
+    // Every problem in the document is reported rather than just the first
+    let doc = ConfDocument::parse(
+        r#"
+ate:
+  compact_mode: sometimes
+  no_such_field: true
+  compact_policy:
+    max_tombstone_ratio: 1.5
+mesh:
+  remote: "ws://example.com/"
+  wire_encryption: none
+  require_encryption: true
+"#,
+        "yaml",
+    )
+    .unwrap();
+    let problems = match doc.check().map_err(|err| err.0) {
+        Err(crate::error::ConfigErrorKind::Invalid(a)) => a,
+        other => panic!("unexpected result - {:?}", other),
+    };
+    assert!(problems.iter().any(|a| a.contains("ate.compact_mode")));
+    assert!(problems.iter().any(|a| a.contains("ate.no_such_field")));
+    assert!(problems.iter().any(|a| a.contains("ate.compact_policy.max_tombstone_ratio")));
+    assert!(problems.iter().any(|a| a.contains("mesh.domain_name")));
+    assert!(problems.iter().any(|a| a.contains("mesh.require_encryption")));
+    assert_eq!(problems.len(), 5);
+}
@@ -0,0 +1,261 @@
+//! Integrates the servers with the service managers that run them, i.e.
+//! readiness and watchdog notifications for systemd (sd_notify), graceful
+//! shutdown when the process is asked to stop, PID files and (behind the
+//! `enable_windows_service` feature) the Windows service control protocol.
+//!
+//! Servers build a `Daemon` before they start, call `ready` once all their
+//! routes were added and then `run_until_shutdown` which returns after the
+//! mesh root has been shut down.
+#![allow(unused_imports)]
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use std::sync::Weak;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+mod notify;
+mod pid_file;
+#[cfg(all(windows, feature = "enable_windows_service"))]
+mod windows;
+
+pub use notify::*;
+pub use pid_file::*;
+#[cfg(all(windows, feature = "enable_windows_service"))]
+pub use windows::WindowsService;
+
+use crate::engine::TaskEngine;
+use crate::mesh::MeshRoot;
+
+/// Reason that the server was asked to shut down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// SIGTERM (sent by service managers to stop the server)
+    Terminate,
+    /// SIGINT or ctrl-c
+    Interrupt,
+    /// Stop (or system shutdown) requested by the Windows service manager
+    ServiceStop,
+}
+
+impl std::fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShutdownReason::Terminate => write!(f, "SIGTERM"),
+            ShutdownReason::Interrupt => write!(f, "SIGINT"),
+            ShutdownReason::ServiceStop => write!(f, "service stop"),
+        }
+    }
+}
+
+/// Server process that is run by a service manager
+pub struct Daemon {
+    notify: Option<ServiceNotify>,
+    pid_file: Option<PidFile>,
+    #[cfg(all(windows, feature = "enable_windows_service"))]
+    windows: Option<WindowsService>,
+    heartbeat: StdMutex<Option<tokio::task::JoinHandle<()>>>,
+    exit: watch::Sender<bool>,
+}
+
+impl Default for Daemon {
+    fn default() -> Daemon {
+        Daemon {
+            notify: None,
+            pid_file: None,
+            #[cfg(all(windows, feature = "enable_windows_service"))]
+            windows: None,
+            heartbeat: StdMutex::new(None),
+            exit: watch::channel(false).0,
+        }
+    }
+}
+
+impl Daemon {
+    /// Creates a daemon that notifies the service manager that started the
+    /// process (if it was started by one)
+    pub fn new() -> Daemon {
+        Daemon::default().with_notify(ServiceNotify::from_env())
+    }
+
+    pub fn with_notify(mut self, notify: Option<ServiceNotify>) -> Self {
+        self.notify = notify;
+        self
+    }
+
+    /// Writes the process ID to a file which is removed again when the daemon
+    /// is shut down (or dropped)
+    pub fn with_pid_file(mut self, path: impl AsRef<Path>) -> io::Result<Self> {
+        self.pid_file = Some(PidFile::create(path)?);
+        Ok(self)
+    }
+
+    /// Runs the daemon as a Windows service with a particular name
+    #[cfg(all(windows, feature = "enable_windows_service"))]
+    pub fn with_windows_service(mut self, name: &str) -> io::Result<Self> {
+        self.windows = Some(WindowsService::start(name)?);
+        Ok(self)
+    }
+
+    pub fn notify(&self) -> Option<&ServiceNotify> {
+        self.notify.as_ref()
+    }
+
+    /// Tells the service manager that the server is ready, this must only be
+    /// called after all the routes were added to the mesh root (which is
+    /// listening from the moment it was created). When the service manager
+    /// expects watchdog heartbeats they are sent for as long as none of the
+    /// chains of the root are stuck.
+    pub fn ready(&self, root: &Arc<MeshRoot>) {
+        if let Some(notify) = self.notify.as_ref() {
+            if let Err(err) = notify.ready() {
+                warn!("failed to notify the service manager that the server is ready - {}", err);
+            }
+            if let Some(interval) = notify.watchdog_interval() {
+                debug!("watchdog heartbeat every {}ms", interval.as_millis());
+                let heartbeat = TaskEngine::spawn(Daemon::heartbeat_worker(
+                    Arc::downgrade(root),
+                    notify.clone(),
+                    interval,
+                    self.exit.subscribe(),
+                ));
+                self.heartbeat.lock().unwrap().replace(heartbeat);
+            }
+        }
+        #[cfg(all(windows, feature = "enable_windows_service"))]
+        if let Some(windows) = self.windows.as_ref() {
+            windows.set_state(windows_service::service::ServiceState::Running);
+        }
+        info!("server is ready");
+    }
+
+    async fn heartbeat_worker(
+        root: Weak<MeshRoot>,
+        notify: ServiceNotify,
+        interval: Duration,
+        mut exit: watch::Receiver<bool>,
+    ) {
+        let mut stuck = 0usize;
+        loop {
+            let root = match Weak::upgrade(&root) {
+                Some(a) => a,
+                None => {
+                    break;
+                }
+            };
+
+            // Heartbeats stop while chains are stuck so that the service
+            // manager restarts the server if they do not recover in time
+            let now_stuck = root.stuck_chains().await;
+            drop(root);
+            if now_stuck == 0 {
+                if stuck > 0 {
+                    let _ = notify.status("all chains are making progress");
+                }
+                if let Err(err) = notify.watchdog() {
+                    warn!("failed to send the watchdog heartbeat - {}", err);
+                }
+            } else if now_stuck != stuck {
+                warn!("withholding the watchdog heartbeat - {} chains are stuck", now_stuck);
+                let _ = notify.status(format!("{} chains are stuck", now_stuck).as_str());
+            }
+            stuck = now_stuck;
+
+            tokio::select! {
+                _ = crate::engine::sleep(interval) => { },
+                _ = exit.changed() => { break; }
+            }
+        }
+    }
+
+    /// Waits until the process is asked to shut down
+    pub async fn wait_for_shutdown(&self) -> ShutdownReason {
+        #[cfg(all(windows, feature = "enable_windows_service"))]
+        let service_stop = async {
+            let mut stop = WindowsService::stop_requested();
+            while *stop.borrow() == false {
+                if stop.changed().await.is_err() {
+                    futures::future::pending::<()>().await;
+                }
+            }
+        };
+        #[cfg(not(all(windows, feature = "enable_windows_service")))]
+        let service_stop = futures::future::pending::<()>();
+
+        let ret = tokio::select! {
+            ret = wait_for_signal() => ret,
+            _ = service_stop => ShutdownReason::ServiceStop,
+        };
+        info!("received {} - shutting down", ret);
+        ret
+    }
+
+    /// Tells the service manager that the server is stopping, shuts down the
+    /// mesh root and then removes the PID file
+    pub async fn shutdown(mut self, root: &Arc<MeshRoot>) {
+        // No more heartbeats are sent once the server says it is stopping
+        let _ = self.exit.send(true);
+        let heartbeat = self.heartbeat.lock().unwrap().take();
+        if let Some(heartbeat) = heartbeat {
+            let _ = heartbeat.await;
+        }
+
+        if let Some(notify) = self.notify.as_ref() {
+            if let Err(err) = notify.stopping() {
+                warn!("failed to notify the service manager that the server is stopping - {}", err);
+            }
+        }
+        #[cfg(all(windows, feature = "enable_windows_service"))]
+        if let Some(windows) = self.windows.as_ref() {
+            windows.set_state(windows_service::service::ServiceState::StopPending);
+        }
+
+        root.shutdown().await;
+        self.pid_file.take();
+
+        #[cfg(all(windows, feature = "enable_windows_service"))]
+        if let Some(windows) = self.windows.as_ref() {
+            windows.set_state(windows_service::service::ServiceState::Stopped);
+        }
+    }
+
+    /// Waits until the process is asked to shut down and then shuts down the
+    /// mesh root gracefully
+    pub async fn run_until_shutdown(self, root: &Arc<MeshRoot>) -> ShutdownReason {
+        let ret = self.wait_for_shutdown().await;
+        self.shutdown(root).await;
+        ret
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> ShutdownReason {
+    use tokio::signal::unix::signal;
+    use tokio::signal::unix::SignalKind;
+
+    let (mut terminate, mut interrupt) = match (
+        signal(SignalKind::terminate()),
+        signal(SignalKind::interrupt()),
+    ) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(err), _) | (_, Err(err)) => {
+            error!("failed to register the signal handlers - {}", err);
+            return futures::future::pending::<ShutdownReason>().await;
+        }
+    };
+    tokio::select! {
+        _ = terminate.recv() => ShutdownReason::Terminate,
+        _ = interrupt.recv() => ShutdownReason::Interrupt,
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> ShutdownReason {
+    if let Err(err) = tokio::signal::ctrl_c().await {
+        error!("failed to register the ctrl-c handler - {}", err);
+        return futures::future::pending::<ShutdownReason>().await;
+    }
+    ShutdownReason::Interrupt
+}
@@ -0,0 +1,113 @@
+use std::io;
+use std::time::Duration;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+/// Socket that the service manager listens on for notifications
+pub const NOTIFY_SOCKET_ENV: &'static str = "NOTIFY_SOCKET";
+/// Interval in microseconds that the service manager expects heartbeats within
+pub const WATCHDOG_USEC_ENV: &'static str = "WATCHDOG_USEC";
+/// Process that the watchdog heartbeats are expected from
+pub const WATCHDOG_PID_ENV: &'static str = "WATCHDOG_PID";
+
+/// Sends notifications to a service manager that implements the systemd
+/// notify protocol (sd_notify) which tell it when the server is ready, that
+/// it is still healthy and when it is stopping
+#[derive(Debug, Clone)]
+pub struct ServiceNotify {
+    socket: String,
+    watchdog: Option<Duration>,
+}
+
+impl ServiceNotify {
+    /// Returns the notifier of the service manager that started this process
+    /// (or none if the process was not started with a notify socket)
+    pub fn from_env() -> Option<ServiceNotify> {
+        let socket = std::env::var(NOTIFY_SOCKET_ENV)
+            .ok()
+            .filter(|a| a.len() > 0)?;
+
+        // The watchdog only applies to the process that the service manager
+        // started (not to the processes that it starts in turn)
+        let watchdog = match std::env::var(WATCHDOG_PID_ENV).ok() {
+            Some(pid) if pid.trim() != std::process::id().to_string() => None,
+            _ => std::env::var(WATCHDOG_USEC_ENV)
+                .ok()
+                .and_then(|a| a.trim().parse::<u64>().ok())
+                .filter(|a| *a > 0)
+                .map(Duration::from_micros),
+        };
+        Some(ServiceNotify::new(socket, watchdog))
+    }
+
+    pub fn new(socket: String, watchdog: Option<Duration>) -> ServiceNotify {
+        ServiceNotify { socket, watchdog }
+    }
+
+    /// Interval that the watchdog heartbeats are sent at (half of the timeout
+    /// of the service manager so that a late heartbeat does not kill it)
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog.map(|a| a / 2)
+    }
+
+    /// Tells the service manager that the server has started up
+    pub fn ready(&self) -> io::Result<()> {
+        self.send("READY=1")
+    }
+
+    /// Tells the service manager that the server is shutting down
+    pub fn stopping(&self) -> io::Result<()> {
+        self.send("STOPPING=1")
+    }
+
+    /// Tells the service manager that the server is still healthy
+    pub fn watchdog(&self) -> io::Result<()> {
+        self.send("WATCHDOG=1")
+    }
+
+    /// Updates the status line that the service manager shows for the server
+    pub fn status(&self, status: &str) -> io::Result<()> {
+        self.send(format!("STATUS={}", status.replace('\n', " ")).as_str())
+    }
+
+    /// Sends one or more assignments (separated by new lines) to the service manager
+    #[cfg(unix)]
+    pub fn send(&self, state: &str) -> io::Result<()> {
+        use std::os::unix::net::UnixDatagram;
+
+        trace!("sd_notify({}) - {}", self.socket, state);
+        let socket = UnixDatagram::unbound()?;
+
+        // Sockets that start with an '@' are in the abstract namespace
+        if let Some(name) = self.socket.strip_prefix('@') {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            {
+                #[cfg(target_os = "android")]
+                use std::os::android::net::SocketAddrExt;
+                #[cfg(target_os = "linux")]
+                use std::os::linux::net::SocketAddrExt;
+
+                let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+                socket.send_to_addr(state.as_bytes(), &addr)?;
+                return Ok(());
+            }
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            {
+                let _ = name;
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "abstract notify sockets are only supported on linux",
+                ));
+            }
+        }
+        socket.send_to(state.as_bytes(), self.socket.as_str())?;
+        Ok(())
+    }
+
+    /// Sends one or more assignments (separated by new lines) to the service manager
+    #[cfg(not(unix))]
+    pub fn send(&self, state: &str) -> io::Result<()> {
+        trace!("sd_notify({}) - {} (unsupported)", self.socket, state);
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+/// File that holds the process ID of the server while it runs, the file is
+/// removed again when this is dropped
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl PidFile {
+    /// Writes the process ID to the file, fails if the file belongs to
+    /// another process that is still running
+    pub fn create(path: impl AsRef<Path>) -> io::Result<PidFile> {
+        let path = path.as_ref().to_path_buf();
+        let pid = std::process::id();
+
+        if let Some(other) = read_pid(&path) {
+            if other != pid && is_running(other) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("the pid file ({}) belongs to a running process ({})", path.display(), other),
+                ));
+            }
+            debug!("replacing the stale pid file ({}) of process {}", path.display(), other);
+        }
+
+        // The file is renamed into place so that readers never see it half written
+        let tmp = path.with_extension(format!("{}.tmp", pid));
+        fs::write(&tmp, format!("{}\n", pid))?;
+        if let Err(err) = fs::rename(&tmp, &path) {
+            let _ = fs::remove_file(&tmp);
+            return Err(err);
+        }
+        debug!("pid file: {} ({})", path.display(), pid);
+        Ok(PidFile { path, pid })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // Another process may have taken over the file in the meantime
+        if read_pid(&self.path) == Some(self.pid) {
+            if let Err(err) = fs::remove_file(&self.path) {
+                warn!("failed to remove the pid file ({}) - {}", self.path.display(), err);
+            }
+        }
+    }
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse::<u32>().ok()
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    // Signal zero only checks that the process exists (a process that we
+    // are not allowed to signal still exists)
+    let ret = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    ret == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
@@ -0,0 +1,132 @@
+//! Service control handler protocol of the Windows service control manager,
+//! the dispatcher runs on its own thread so that the server keeps running
+//! on the runtime that started it while the service manager is told about
+//! the state that the server is in
+use once_cell::sync::Lazy;
+use std::ffi::OsString;
+use std::io;
+use std::sync::mpsc;
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+use tokio::sync::watch;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use windows_service::define_windows_service;
+use windows_service::service::ServiceControl;
+use windows_service::service::ServiceControlAccept;
+use windows_service::service::ServiceExitCode;
+use windows_service::service::ServiceState;
+use windows_service::service::ServiceStatus;
+use windows_service::service::ServiceType;
+use windows_service::service_control_handler;
+use windows_service::service_control_handler::ServiceControlHandlerResult;
+use windows_service::service_control_handler::ServiceStatusHandle;
+use windows_service::service_dispatcher;
+
+/// Service that the dispatcher will register the control handler for
+static PENDING: Lazy<StdMutex<Option<(String, mpsc::Receiver<ServiceState>)>>> =
+    Lazy::new(|| StdMutex::new(None));
+
+/// Set when the service manager asks the service to stop
+static STOP: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Connection to the service control manager for a server that runs as
+/// a Windows service
+#[derive(Debug)]
+pub struct WindowsService {
+    state: mpsc::Sender<ServiceState>,
+}
+
+impl WindowsService {
+    /// Connects to the service control manager, the service is reported as
+    /// starting until the server tells it that it is running
+    pub fn start(name: &str) -> io::Result<WindowsService> {
+        let (tx, rx) = mpsc::channel();
+        PENDING.lock().unwrap().replace((name.to_string(), rx));
+
+        let name = name.to_string();
+        std::thread::Builder::new()
+            .name("windows-service".to_string())
+            .spawn(move || {
+                if let Err(err) = service_dispatcher::start(name.as_str(), ffi_service_main) {
+                    error!("failed to connect to the service control manager - {}", err);
+                }
+            })?;
+        Ok(WindowsService { state: tx })
+    }
+
+    pub fn set_state(&self, state: ServiceState) {
+        let _ = self.state.send(state);
+    }
+
+    /// Returns a receiver that changes to true when the service manager asks
+    /// the service to stop
+    pub fn stop_requested() -> watch::Receiver<bool> {
+        STOP.subscribe()
+    }
+}
+
+fn service_main(_args: Vec<OsString>) {
+    let (name, rx) = match PENDING.lock().unwrap().take() {
+        Some(a) => a,
+        None => {
+            return;
+        }
+    };
+
+    let handler = move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            info!("the service manager asked the service to stop");
+            let _ = STOP.send(true);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    };
+    let handle = match service_control_handler::register(name.as_str(), handler) {
+        Ok(a) => a,
+        Err(err) => {
+            error!("failed to register the service control handler - {}", err);
+            return;
+        }
+    };
+
+    // Report every state the server moves through until it has stopped
+    report(&handle, ServiceState::StartPending);
+    let mut state = ServiceState::StartPending;
+    while let Ok(next) = rx.recv() {
+        state = next;
+        report(&handle, state);
+        if state == ServiceState::Stopped {
+            break;
+        }
+    }
+    if state != ServiceState::Stopped {
+        report(&handle, ServiceState::Stopped);
+    }
+}
+
+fn report(handle: &ServiceStatusHandle, state: ServiceState) {
+    let controls_accepted = match state {
+        ServiceState::Running => ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        _ => ServiceControlAccept::empty(),
+    };
+    let wait_hint = match state {
+        ServiceState::StartPending | ServiceState::StopPending => Duration::from_secs(30),
+        _ => Duration::ZERO,
+    };
+    let status = ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted,
+        exit_code: ServiceExitCode::Win32(0),
+        checkpoint: 0,
+        wait_hint,
+        process_id: None,
+    };
+    if let Err(err) = handle.set_service_status(status) {
+        warn!("failed to report the service state ({:?}) - {}", state, err);
+    }
+}
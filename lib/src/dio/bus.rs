@@ -3,7 +3,7 @@ use serde::{de::DeserializeOwned, Serialize};
 use std::marker::PhantomData;
 use std::sync::Arc;
 use std::ops::Deref;
-use tokio::sync::mpsc;
+use tokio::sync::broadcast;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use std::fmt;
@@ -150,14 +150,15 @@ pub struct Bus<D> {
     dio: Arc<Dio>,
     chain: Arc<Chain>,
     vec: MetaCollection,
-    receiver: mpsc::Receiver<EventWeakData>,
+    receiver: broadcast::Receiver<EventWeakData>,
+    lagged: u64,
     _marker: PhantomData<D>,
 }
 
 impl<D> Bus<D> {
     pub(crate) async fn new(dio: &Arc<Dio>, vec: MetaCollection) -> Bus<D> {
         let id = fastrand::u64(..);
-        let (tx, rx) = mpsc::channel(100);
+        let (tx, rx) = broadcast::channel(LISTENER_BUFFER_SIZE);
 
         {
             let mut lock = dio.chain().inside_async.write().await;
@@ -170,15 +171,35 @@ impl<D> Bus<D> {
             chain: Arc::clone(dio.chain()),
             vec: vec,
             receiver: rx,
+            lagged: 0,
             _marker: PhantomData,
         }
     }
 
+    /// Number of events that were dropped because this bus fell too far
+    /// behind the chain (the oldest events are dropped first), every time
+    /// this happens the next receive fails with `BusErrorKind::Lagged` and
+    /// the receives after that carry on with the events that were kept
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+
     pub async fn recv(&mut self) -> Result<BusEvent<D>, BusError>
     where
         D: DeserializeOwned,
     {
-        while let Some(evt) = self.receiver.recv().await {
+        loop {
+            let evt = match self.receiver.recv().await {
+                Ok(a) => a,
+                Err(broadcast::error::RecvError::Lagged(cnt)) => {
+                    debug!("bus lagged behind the chain ({} events dropped)", cnt);
+                    self.lagged += cnt;
+                    bail!(BusErrorKind::Lagged(cnt));
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    break;
+                }
+            };
             match self.ret_evt(evt).await? {
                 TryBusEvent::Updated(dao) => {
                     return Ok(BusEvent::Updated(dao));
@@ -211,10 +232,15 @@ impl<D> Bus<D> {
                         }
                     }
                 },
-                Err(mpsc::error::TryRecvError::Empty) => {
+                Err(broadcast::error::TryRecvError::Lagged(cnt)) => {
+                    debug!("bus lagged behind the chain ({} events dropped)", cnt);
+                    self.lagged += cnt;
+                    bail!(BusErrorKind::Lagged(cnt));
+                },
+                Err(broadcast::error::TryRecvError::Empty) => {
                     return Ok(TryBusEvent::NoData);
                 },
-                Err(mpsc::error::TryRecvError::Disconnected) => {
+                Err(broadcast::error::TryRecvError::Closed) => {
                     return Err(BusErrorKind::ChannelClosed.into());
                 }
             }
@@ -266,9 +292,21 @@ impl<D> Bus<D> {
             format: evt.format,
         };
 
+        // Patches are applied on top of the earlier versions of the row
+        let base = self.dio.multi.load_patch_base(&evt.meta).await?;
+
         let session = self.dio.session();
         evt.data_bytes = match evt.data_bytes {
-            Some(data) => Some(self.dio.multi.data_as_overlay(&evt.meta, data, session.deref())?),
+            Some(data) => {
+                let data = self.dio.multi.data_as_overlay(&evt.meta, data, session.deref())?;
+                Some(self.dio.multi.data_with_patches(
+                    &evt.meta,
+                    evt.format.data,
+                    data,
+                    &base[..],
+                    session.deref(),
+                )?)
+            }
             None => None,
         };
 
@@ -0,0 +1,446 @@
+//! Durable cursors for consumers that forward the events of a chain into
+//! another system (e.g. a message queue) and must resume exactly where they
+//! left off when they are restarted.
+//!
+//! A cursor is a named row stored in the chain itself (beneath a system
+//! collection) that records the last event its consumer acknowledged. The
+//! subscription returned by `open_from_cursor` yields the events that change
+//! rows in timeline order starting just after that event, any number of
+//! cursors with different names can follow the same chain independently.
+//!
+//! Acknowledgements are cumulative and are held in memory until a batch of
+//! them has built up, only then is the cursor written to the chain. Consumers
+//! should `flush` the subscription whenever the position must be durable (e.g.
+//! right after committing to the downstream system) as events that were
+//! acknowledged but not flushed are returned again when the cursor is next
+//! opened. Cursors are never removed automatically, one that is no longer
+//! needed must be deleted with `delete_cursor`.
+use bytes::Bytes;
+use error_chain::bail;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use super::dio::*;
+use super::dio_mut::*;
+use crate::chain::*;
+use crate::crypto::AteHash;
+use crate::error::*;
+use crate::event::*;
+use crate::header::PrimaryKey;
+use crate::index::EventLeaf;
+use crate::meta::*;
+use crate::spec::*;
+use crate::time::ChainTimestamp;
+use crate::transaction::TransactionScope;
+use crate::trust::ChainHistory;
+
+/// Collection (beneath a fixed parent) that holds the cursors of a chain
+pub const CURSOR_COLLECTION_ID: u64 = 0x4355_5253;
+/// Number of acknowledgements after which a cursor is written to the chain
+pub const CURSOR_ACK_BATCH: u64 = 100;
+/// Maximum number of events that are read from the history at a time
+const CURSOR_FETCH_BATCH: usize = 100;
+
+fn cursor_parent() -> PrimaryKey {
+    PrimaryKey::from(CURSOR_COLLECTION_ID)
+}
+
+fn cursor_key(name: &str) -> PrimaryKey {
+    PrimaryKey::from(format!("cursor:{}", name))
+}
+
+/// Position of an event within the timeline of a chain
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorPosition {
+    pub timestamp: ChainTimestamp,
+    pub event_hash: AteHash,
+}
+
+/// Row that records how far the consumer of a cursor has got
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChainCursor {
+    pub name: String,
+    /// Last event that was acknowledged (None when the consumer has not
+    /// acknowledged any events yet)
+    pub position: Option<CursorPosition>,
+    /// Total number of events that have been acknowledged
+    pub acked: u64,
+}
+
+/// Event returned by a cursor subscription
+#[derive(Debug, Clone)]
+pub struct CursorEvent {
+    pub position: CursorPosition,
+    pub meta: Metadata,
+    /// Data of the row (after it was decrypted), None when the event
+    /// deletes the row
+    pub data: Option<Bytes>,
+    pub format: MessageFormat,
+}
+
+impl CursorEvent {
+    /// Hash that is passed to `ack` once the event has been processed
+    pub fn event_hash(&self) -> AteHash {
+        self.position.event_hash
+    }
+
+    /// Key of the row that the event changes
+    pub fn key(&self) -> Option<PrimaryKey> {
+        self.meta
+            .get_data_key()
+            .or_else(|| self.meta.get_tombstone())
+    }
+
+    pub fn is_delete(&self) -> bool {
+        self.meta.get_tombstone().is_some()
+    }
+
+    pub fn type_name(&self) -> Option<&str> {
+        self.meta.get_type_name().map(|a| a.type_name.as_str())
+    }
+
+    pub fn data_as<D>(&self) -> Result<Option<D>, SerializationError>
+    where
+        D: DeserializeOwned,
+    {
+        match &self.data {
+            Some(data) => Ok(Some(
+                self.format
+                    .data
+                    .deserialize_ref(&data[..])
+                    .map_err(SerializationError::from)?,
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Returns true for the events that change rows other than the cursors
+fn is_row_event(raw: &EventHeaderRaw) -> bool {
+    let header = match raw.as_header() {
+        Ok(a) => a,
+        Err(err) => {
+            trace!("cursor skipped an event - {}", err);
+            return false;
+        }
+    };
+    let meta = &header.meta;
+    if let Some(parent) = meta.get_parent() {
+        if parent.vec.collection_id == CURSOR_COLLECTION_ID
+            && parent.vec.parent_id == cursor_parent()
+        {
+            return false;
+        }
+    }
+    meta.get_data_key().is_some() || meta.get_tombstone().is_some()
+}
+
+/// Walks the history after a position and returns the events that change
+/// rows (up to the limit) along with the position of the last event walked
+fn scan_after(
+    history: &ChainHistory,
+    after: Option<&CursorPosition>,
+    limit: usize,
+) -> (Vec<CursorPosition>, Option<CursorPosition>) {
+    let iter = match after {
+        Some(a) => history.range(a.timestamp..),
+        None => history.iter(),
+    };
+
+    // Events that share the timestamp of the position are only returned when
+    // they come after it (all of them are skipped if it was compacted away)
+    let mut skipping = after.is_some();
+    let mut ret = Vec::new();
+    let mut last = None;
+    for (timestamp, raw) in iter {
+        if let Some(after) = after {
+            if skipping && timestamp == after.timestamp {
+                if raw.event_hash == after.event_hash {
+                    skipping = false;
+                }
+                continue;
+            }
+            skipping = false;
+        }
+        if ret.len() >= limit {
+            break;
+        }
+
+        let position = CursorPosition {
+            timestamp,
+            event_hash: raw.event_hash,
+        };
+        if is_row_event(&raw) {
+            ret.push(position);
+        }
+        last = Some(position);
+    }
+    (ret, last)
+}
+
+async fn count_after(chain: &Arc<Chain>, after: Option<&CursorPosition>) -> u64 {
+    let guard = chain.inside_async.read().await;
+    scan_after(&guard.chain.timeline.history, after, usize::MAX)
+        .0
+        .len() as u64
+}
+
+/// Subscription that follows a chain from the position of a named cursor
+pub struct CursorSubscription {
+    dio: Arc<Dio>,
+    name: String,
+    receiver: broadcast::Receiver<EventWeakData>,
+    /// Last event that was read from the history
+    scanned: Option<CursorPosition>,
+    /// Events that were read from the history but not yet returned
+    buffer: VecDeque<CursorPosition>,
+    /// Events that were returned but not yet acknowledged
+    unacked: VecDeque<CursorPosition>,
+    acked: Option<CursorPosition>,
+    acked_total: u64,
+    /// Acknowledgements that have not yet been written to the chain
+    pending: u64,
+    ack_batch: u64,
+}
+
+impl CursorSubscription {
+    pub(crate) async fn new(dio: &Arc<Dio>, name: &str) -> Result<CursorSubscription, BusError> {
+        let cursor = dio.cursor(name).await?;
+
+        let (tx, rx) = broadcast::channel(LISTENER_BUFFER_SIZE);
+        {
+            let mut lock = dio.chain().inside_async.write().await;
+            let listener = ChainListener {
+                id: fastrand::u64(..),
+                sender: tx,
+            };
+            lock.cursor_listeners.push(listener);
+        }
+
+        let position = cursor.as_ref().and_then(|a| a.position);
+        debug!("cursor {} opened (position={:?})", name, position);
+        Ok(CursorSubscription {
+            dio: Arc::clone(dio),
+            name: name.to_string(),
+            receiver: rx,
+            scanned: position,
+            buffer: VecDeque::new(),
+            unacked: VecDeque::new(),
+            acked: position,
+            acked_total: cursor.map(|a| a.acked).unwrap_or(0),
+            pending: 0,
+            ack_batch: CURSOR_ACK_BATCH,
+        })
+    }
+
+    /// Sets how many acknowledgements are held in memory before the cursor
+    /// is written to the chain
+    pub fn with_ack_batch(mut self, ack_batch: u64) -> Self {
+        self.ack_batch = ack_batch.max(1);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Last event that was acknowledged (which may not be flushed yet)
+    pub fn position(&self) -> Option<CursorPosition> {
+        self.acked
+    }
+
+    /// Waits for the next event after the ones that were already returned
+    pub async fn recv(&mut self) -> Result<CursorEvent, BusError> {
+        loop {
+            if let Some(evt) = self.try_recv().await? {
+                return Ok(evt);
+            }
+            match self.receiver.recv().await {
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => {
+                    bail!(BusErrorKind::ChannelClosed);
+                }
+            }
+        }
+    }
+
+    /// Returns the next event or None when the subscription has caught up
+    /// with the head of the chain
+    pub async fn try_recv(&mut self) -> Result<Option<CursorEvent>, BusError> {
+        loop {
+            if let Some(position) = self.buffer.pop_front() {
+                if let Some(evt) = self.load(position).await? {
+                    self.unacked.push_back(position);
+                    return Ok(Some(evt));
+                }
+                continue;
+            }
+            if self.fetch().await == false {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Reads the next batch of events from the history, returns false when
+    /// there were no more events to read
+    async fn fetch(&mut self) -> bool {
+        let chain = Arc::clone(self.dio.chain());
+        let guard = chain.inside_async.read().await;
+        let (events, last) = scan_after(
+            &guard.chain.timeline.history,
+            self.scanned.as_ref(),
+            CURSOR_FETCH_BATCH,
+        );
+        if last.is_none() {
+            return false;
+        }
+        self.scanned = last;
+        self.buffer.extend(events);
+        true
+    }
+
+    async fn load(&self, position: CursorPosition) -> Result<Option<CursorEvent>, BusError> {
+        let leaf = EventLeaf {
+            record: position.event_hash,
+            created: 0,
+            updated: 0,
+        };
+        let evt = match self.dio.multi.load(leaf).await {
+            Ok(a) => a,
+            Err(err) => {
+                debug!("cursor {} skipped event {} - {}", self.name, position.event_hash, err);
+                return Ok(None);
+            }
+        };
+
+        let mut data = evt.data;
+        {
+            let session = self.dio.session();
+            self.dio.data_as_overlay(session.as_ref(), &mut data)?;
+            data.data_bytes = match data.data_bytes {
+                Some(bytes) => Some(self.dio.multi.data_with_patches(
+                    &data.meta,
+                    data.format.data,
+                    bytes,
+                    &evt.base[..],
+                    session.as_ref(),
+                )?),
+                None => None,
+            };
+        }
+
+        Ok(Some(CursorEvent {
+            position,
+            meta: data.meta,
+            data: data.data_bytes,
+            format: data.format,
+        }))
+    }
+
+    /// Acknowledges an event and all the events that were returned before
+    /// it, the cursor is written to the chain once enough of them build up
+    pub async fn ack(&mut self, event_hash: AteHash) -> Result<(), BusError> {
+        let index = match self
+            .unacked
+            .iter()
+            .position(|a| a.event_hash == event_hash)
+        {
+            Some(a) => a,
+            None => bail!(BusErrorKind::UnknownEvent(event_hash.to_string())),
+        };
+        let acked = self.unacked.drain(..=index).collect::<Vec<_>>();
+        self.acked = acked.last().cloned();
+        self.acked_total += acked.len() as u64;
+        self.pending += acked.len() as u64;
+
+        if self.pending >= self.ack_batch {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the cursor to the chain (if anything was acknowledged since
+    /// it was last written)
+    pub async fn flush(&mut self) -> Result<(), BusError> {
+        if self.pending == 0 {
+            return Ok(());
+        }
+
+        let cursor = ChainCursor {
+            name: self.name.clone(),
+            position: self.acked,
+            acked: self.acked_total,
+        };
+        let dio = self.dio.trans(TransactionScope::Local).await;
+        {
+            let mut dao = dio.store_with_key(cursor, cursor_key(self.name.as_str()))?;
+            dao.attach_orphaned_ext(&cursor_parent(), CURSOR_COLLECTION_ID)?;
+        }
+        dio.commit().await?;
+
+        trace!("cursor {} flushed ({} acks)", self.name, self.pending);
+        self.pending = 0;
+        Ok(())
+    }
+
+    /// Number of events between the acknowledged position of the cursor and
+    /// the head of the chain
+    pub async fn lag(&self) -> u64 {
+        count_after(self.dio.chain(), self.acked.as_ref()).await
+    }
+}
+
+impl Dio {
+    /// Subscribes to the events of the chain that come after the last event
+    /// acknowledged on the named cursor (or from the start of the chain when
+    /// the cursor does not exist yet)
+    pub async fn open_from_cursor(
+        self: &Arc<Self>,
+        name: &str,
+    ) -> Result<CursorSubscription, BusError> {
+        CursorSubscription::new(self, name).await
+    }
+
+    /// Returns the named cursor as it was last written to the chain
+    pub async fn cursor(self: &Arc<Self>, name: &str) -> Result<Option<ChainCursor>, LoadError> {
+        match self.load_and_take::<ChainCursor>(&cursor_key(name)).await {
+            Ok(a) => Ok(Some(a)),
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns all the cursors that have been written to the chain
+    pub async fn cursors(self: &Arc<Self>) -> Result<Vec<ChainCursor>, LoadError> {
+        Ok(self
+            .children_ext::<ChainCursor>(cursor_parent(), CURSOR_COLLECTION_ID, true, true)
+            .await?
+            .into_iter()
+            .map(|a| a.take())
+            .collect())
+    }
+
+    /// Number of events between the position of the named cursor (as it was
+    /// last written) and the head of the chain
+    pub async fn cursor_lag(self: &Arc<Self>, name: &str) -> Result<u64, LoadError> {
+        let position = self.cursor(name).await?.and_then(|a| a.position);
+        Ok(count_after(self.chain(), position.as_ref()).await)
+    }
+}
+
+impl DioMut {
+    /// Deletes the named cursor so that its consumer starts again from the
+    /// beginning of the chain, returns false if the cursor does not exist
+    pub async fn delete_cursor(self: &Arc<Self>, name: &str) -> Result<bool, SerializationError> {
+        let key = cursor_key(name);
+        if self.exists(&key).await == false {
+            return Ok(false);
+        }
+        self.delete(&key).await?;
+        Ok(true)
+    }
+}
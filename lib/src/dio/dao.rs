@@ -127,6 +127,20 @@ impl<D> Dao<D> {
             .as_ref()
             .map(|a| a.vec.parent_id.clone())
     }
+
+    /// Returns the retention policy of one of the collections of this row
+    pub fn collection_policy(&self, collection_id: u64) -> Option<MetaCollectionPolicy> {
+        self.row
+            .extra_meta
+            .iter()
+            .filter_map(|m| match m {
+                CoreMetadata::CollectionPolicy(a) if a.collection_id == collection_id => {
+                    Some(a.clone())
+                }
+                _ => None,
+            })
+            .next()
+    }
 }
 
 impl<D> DaoObj for Dao<D> {
@@ -26,6 +26,7 @@ use crate::index::*;
 use crate::meta::*;
 use crate::spec::*;
 
+use super::patch::RowPatchBase;
 use super::row::*;
 pub use super::vec::DaoVec;
 
@@ -175,6 +176,25 @@ where
         self.commit(true, true)
     }
 
+    /// Sets the retention policy of one of the collections of this row, the
+    /// policy is removed again when it has no limits
+    pub fn set_collection_policy(
+        &mut self,
+        policy: MetaCollectionPolicy,
+    ) -> std::result::Result<(), SerializationError> {
+        self.inner.row.extra_meta.retain(|m| match m {
+            CoreMetadata::CollectionPolicy(a) => a.collection_id != policy.collection_id,
+            _ => true,
+        });
+        if policy.max_entries.is_some() || policy.max_age.is_some() {
+            self.inner
+                .row
+                .extra_meta
+                .push(CoreMetadata::CollectionPolicy(policy));
+        }
+        self.commit(true, true)
+    }
+
     pub fn is_locked(&self) -> bool {
         match self.state.lock {
             DaoMutLock::Locked | DaoMutLock::LockedThenDelete => true,
@@ -308,6 +328,11 @@ where
     }
 
     pub fn as_mut<'a>(&'a mut self) -> DaoMutGuard<'a, D> {
+        self.inner.row.patch = None;
+        self.as_mut_ext()
+    }
+
+    fn as_mut_ext<'a>(&'a mut self) -> DaoMutGuard<'a, D> {
         {
             let mut state = self.trans.state.lock().unwrap();
             if state.rows.contains_key(self.inner.key()) == false {
@@ -323,6 +348,33 @@ where
         }
     }
 
+    /// Same as `as_mut` except that when the changes are committed only the
+    /// fields that were modified are written to the redo-log as a patch on
+    /// top of the version of the row that was loaded. If the row was changed
+    /// by someone else in the meantime then the commit fails with a conflict.
+    pub fn as_mut_patch<'a>(&'a mut self) -> DaoMutGuard<'a, D> {
+        let version = self.inner.dio().row_version(self.inner.key());
+        let base = self.inner.row.patch.as_ref().map(|a| a.record);
+        if version.map(|a| a.0) != base {
+            self.inner.row.patch = match version {
+                Some((record, depth)) => self
+                    .inner
+                    .row
+                    .format
+                    .data
+                    .serialize(&self.inner.row.data)
+                    .ok()
+                    .map(|data| RowPatchBase {
+                        record,
+                        depth,
+                        data: Bytes::from(data),
+                    }),
+                None => None,
+            };
+        }
+        self.as_mut_ext()
+    }
+
     pub fn as_ref<'a>(&'a self) -> &'a D {
         &self.inner.row.data
     }
@@ -335,7 +387,8 @@ where
         self.inner
     }
 
-    pub fn as_mut_owned(self) -> DaoMutGuardOwned<D> {
+    pub fn as_mut_owned(mut self) -> DaoMutGuardOwned<D> {
+        self.inner.row.patch = None;
         DaoMutGuardOwned {
             dao: self,
             dirty: false,
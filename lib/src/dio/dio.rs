@@ -44,6 +44,9 @@ use crate::{
 #[derive(Debug)]
 pub(crate) struct DioState {
     pub(super) cache_load: FxHashMap<PrimaryKey, (Arc<EventStrongData>, EventLeaf)>,
+    /// Versions of the rows (event hash and patch depth) that were last
+    /// written through this DIO so that further edits can be patched on them
+    pub(super) versions: FxHashMap<PrimaryKey, (AteHash, u32)>,
 }
 
 /// Represents a series of mutations that the user is making on a particular chain-of-trust
@@ -249,13 +252,20 @@ impl Dio {
         let evt = self.multi.load(leaf).await?;
         let session = self.session();
 
-        Ok(self.load_from_event(session.as_ref(), evt.data, evt.header.as_header()?, leaf)?)
+        Ok(self.load_from_event(
+            session.as_ref(),
+            evt.data,
+            &evt.base[..],
+            evt.header.as_header()?,
+            leaf,
+        )?)
     }
 
     pub(crate) fn load_from_event<D>(
         self: &Arc<Self>,
         session: &'_ dyn AteSession,
         mut data: EventStrongData,
+        base: &[EventStrongData],
         header: EventHeader,
         leaf: EventLeaf,
     ) -> Result<Dao<D>, LoadError>
@@ -263,7 +273,16 @@ impl Dio {
         D: DeserializeOwned,
     {
         data.data_bytes = match data.data_bytes {
-            Some(data) => Some(self.multi.data_as_overlay(&header.meta, data, session)?),
+            Some(bytes) => {
+                let bytes = self.multi.data_as_overlay(&header.meta, bytes, session)?;
+                Some(self.multi.data_with_patches(
+                    &header.meta,
+                    data.format.data,
+                    bytes,
+                    base,
+                    session,
+                )?)
+            }
             None => None,
         };
 
@@ -534,6 +553,126 @@ impl Dio {
         Ok(ret)
     }
 
+    /// Loads many rows where the decryption and deserialization of each row
+    /// is spread over the worker pool. The raw events are fetched in batches
+    /// of `max_concurrency` rows which also bounds how many rows are held in
+    /// flight at once. Results are returned in the same order as the keys and
+    /// a row that fails to load only fails its own entry.
+    pub async fn load_many_parallel<D>(
+        self: &Arc<Self>,
+        keys: Vec<PrimaryKey>,
+        max_concurrency: usize,
+    ) -> Vec<Result<Dao<D>, LoadError>>
+    where
+        D: DeserializeOwned + Send + 'static,
+    {
+        self.run_async(self.__load_many_parallel(keys, max_concurrency))
+            .await
+    }
+
+    pub(super) async fn __load_many_parallel<D>(
+        self: &Arc<Self>,
+        keys: Vec<PrimaryKey>,
+        max_concurrency: usize,
+    ) -> Vec<Result<Dao<D>, LoadError>>
+    where
+        D: DeserializeOwned + Send + 'static,
+    {
+        let mut ret = Vec::with_capacity(keys.len());
+        for batch in keys.chunks(max_concurrency.max(1)) {
+            let mut slots: Vec<Option<Result<Dao<D>, LoadError>>> =
+                Vec::with_capacity(batch.len());
+
+            // Rows in the cache are served straight away while the rest are looked up
+            let mut to_load = Vec::new();
+            {
+                let inside_async = self.multi.inside_async.read().await;
+                let state = self.state.lock().unwrap();
+                for key in batch {
+                    if let Some((dao, leaf)) = state.cache_load.get(key) {
+                        slots.push(Some(
+                            Row::from_event(self, dao.deref(), leaf.created, leaf.updated)
+                                .map(|(row_header, row)| Dao::new(self, row_header, row))
+                                .map_err(|err| err.into()),
+                        ));
+                        continue;
+                    }
+                    match inside_async.chain.lookup_primary(key) {
+                        Some(leaf) => {
+                            to_load.push((slots.len(), key.clone(), leaf));
+                            slots.push(None);
+                        }
+                        None => {
+                            slots.push(Some(Err(LoadErrorKind::NotFound(key.clone()).into())));
+                        }
+                    }
+                }
+            }
+
+            // Fetch the raw events in one go, if that fails then they are fetched
+            // one at a time so that a bad row only fails its own entry
+            let leafs = to_load.iter().map(|(_, _, leaf)| leaf.clone()).collect();
+            let evts = match self.multi.load_many(leafs).await {
+                Ok(evts) if evts.len() == to_load.len() => {
+                    evts.into_iter().map(|evt| Ok(evt)).collect::<Vec<_>>()
+                }
+                _ => {
+                    let mut evts = Vec::with_capacity(to_load.len());
+                    for (_, _, leaf) in to_load.iter() {
+                        evts.push(self.multi.load(leaf.clone()).await);
+                    }
+                    evts
+                }
+            };
+
+            // Decrypt and deserialize the rows on the worker pool
+            let mut tasks = Vec::with_capacity(evts.len());
+            for ((slot, key, _), evt) in to_load.into_iter().zip(evts.into_iter()) {
+                let task = evt.map(|evt| {
+                    let dio = Arc::clone(self);
+                    TaskEngine::spawn(async move { dio.__hydrate_row::<D>(key, evt) })
+                });
+                tasks.push((slot, task));
+            }
+            for (slot, task) in tasks {
+                let row = match task {
+                    Ok(task) => match task.await {
+                        Ok(row) => row,
+                        Err(err) => Err(LoadErrorKind::LoadFailed(err.to_string()).into()),
+                    },
+                    Err(err) => Err(err),
+                };
+                slots[slot] = Some(row.map(|(row_header, row)| Dao::new(self, row_header, row)));
+            }
+
+            ret.extend(slots.into_iter().map(|a| a.unwrap()));
+        }
+        ret
+    }
+
+    fn __hydrate_row<D>(
+        self: &Arc<Self>,
+        key: PrimaryKey,
+        mut evt: LoadStrongResult,
+    ) -> Result<(RowHeader, Row<D>), LoadError>
+    where
+        D: DeserializeOwned,
+    {
+        let header = evt.header.as_header()?;
+        let session = self.session();
+        let (row_header, row) =
+            match self.__process_load_row(session.as_ref(), &mut evt, &header.meta, false, false)? {
+                Some(a) => a,
+                None => bail!(LoadErrorKind::NotFound(key)),
+            };
+
+        let mut state = self.state.lock().unwrap();
+        state
+            .cache_load
+            .insert(row.key.clone(), (Arc::new(evt.data), evt.leaf));
+        Ok((row_header, row))
+    }
+
     pub(crate) fn data_as_overlay(
         self: &Arc<Self>,
         session: &'_ dyn AteSession,
@@ -571,7 +710,13 @@ impl Dio {
                         bail!(LoadErrorKind::TransformationError(err.0));
                     }
                 };
-                Some(data)
+                Some(self.multi.data_with_patches(
+                    meta,
+                    evt.data.format.data,
+                    data,
+                    &evt.base[..],
+                    session,
+                )?)
             }
             None => {
                 return Ok(None);
@@ -592,6 +737,17 @@ impl Dio {
         Ok(Some((row_header, row)))
     }
 
+    /// Returns the version of a row (event hash and patch depth) that edits
+    /// made through `DaoMut::as_mut_patch` will be written against
+    pub(crate) fn row_version(&self, key: &PrimaryKey) -> Option<(AteHash, u32)> {
+        let state = self.state.lock().unwrap();
+        if let Some((evt, leaf)) = state.cache_load.get(key) {
+            let depth = evt.meta.get_patch().map(|a| a.depth).unwrap_or(0);
+            return Some((leaf.record, depth));
+        }
+        state.versions.get(key).map(|a| a.clone())
+    }
+
     pub fn session<'a>(&'a self) -> DioSessionGuard<'a> {
         DioSessionGuard::new(self)
     }
@@ -600,6 +756,13 @@ impl Dio {
         DioSessionGuardMut::new(self)
     }
 
+    /// Replaces the session (for instance once it has been refreshed) so that
+    /// everything the DIO loads or writes from now on uses its keys
+    pub fn set_session(&self, session: &'_ dyn AteSession) {
+        let mut guard = self.session.write().unwrap();
+        *guard = session.clone_session();
+    }
+
     pub async fn wait_for_accurate_timing(&self) {
         self.time.wait_for_high_accuracy().await;
     }
@@ -628,7 +791,11 @@ impl Dio {
                     Err(broadcast::error::RecvError::Closed) => {
                         break;
                     }
-                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                    Err(broadcast::error::RecvError::Lagged(cnt)) => {
+                        // Some of the invalidations were dropped so nothing
+                        // that is cached can be trusted anymore
+                        debug!("decache lagged behind the chain ({} dropped)", cnt);
+                        dio.state.lock().unwrap().cache_load.clear();
                         continue;
                     }
                 };
@@ -644,6 +811,9 @@ impl Dio {
                                 state.cache_load.remove(&key);
                             }
                         }
+                        Err(broadcast::error::TryRecvError::Lagged(_)) => {
+                            state.cache_load.clear();
+                        }
                         Err(_) => {
                             break;
                         }
@@ -722,6 +892,7 @@ impl Chain {
             chain: Arc::clone(self),
             state: StdMutex::new(DioState {
                 cache_load: FxHashMap::default(),
+                versions: FxHashMap::default(),
             }),
             session: StdRwLock::new(session.clone_session()),
             log_format: Some(multi.default_format.clone()),
@@ -20,6 +20,7 @@ use tracing_futures::Instrument;
 use super::dao::*;
 use super::dao_mut::*;
 use super::dio::*;
+use super::patch::RowPatch;
 use super::row::*;
 use crate::chain::ChainWork;
 use crate::comms::*;
@@ -33,6 +34,7 @@ use crate::spec::*;
 use crate::time::*;
 use crate::transaction::*;
 use crate::tree::*;
+use crate::trust::ChainOfTrust;
 use crate::trust::LoadStrongResult;
 
 use crate::crypto::{EncryptedPrivateKey, PrivateSignKey};
@@ -328,6 +330,7 @@ impl DioMut {
             created: 0,
             updated: 0,
             extra_meta: Vec::new(),
+            patch: None,
             is_new: true,
         };
 
@@ -367,31 +370,33 @@ impl DioMut {
 impl Chain {
     /// Opens a data access layer that allows mutable changes to data.
     /// Transaction consistency on commit will be guarranted for local redo log files
-    pub async fn dio_mut(self: &Arc<Chain>, session: &'_ dyn AteSession) -> Arc<DioMut> {
+    pub async fn dio_mut(self: &Arc<Chain>, session: &'_ dyn AteSession) -> Result<Arc<DioMut>, CommitError> {
         self.dio_trans(session, TransactionScope::Local).await
     }
 
     /// Opens a data access layer that allows mutable changes to data (in a fire-and-forget mode).
     /// No transaction consistency on commits will be enforced
-    pub async fn dio_fire(self: &Arc<Chain>, session: &'_ dyn AteSession) -> Arc<DioMut> {
+    pub async fn dio_fire(self: &Arc<Chain>, session: &'_ dyn AteSession) -> Result<Arc<DioMut>, CommitError> {
         self.dio_trans(session, TransactionScope::None).await
     }
 
     /// Opens a data access layer that allows mutable changes to data.
     /// Transaction consistency on commit will be guarranted for all remote replicas
-    pub async fn dio_full(self: &Arc<Chain>, session: &'_ dyn AteSession) -> Arc<DioMut> {
+    pub async fn dio_full(self: &Arc<Chain>, session: &'_ dyn AteSession) -> Result<Arc<DioMut>, CommitError> {
         self.dio_trans(session, TransactionScope::Full).await
     }
 
     /// Opens a data access layer that allows mutable changes to data.
-    /// Transaction consistency on commit must be specified
+    /// Transaction consistency on commit must be specified (fails with a
+    /// ChainReadOnly error when the chain can not be modified)
     pub async fn dio_trans(
         self: &Arc<Chain>,
         session: &'_ dyn AteSession,
         scope: TransactionScope,
-    ) -> Arc<DioMut> {
+    ) -> Result<Arc<DioMut>, CommitError> {
+        self.check_writable()?;
         let dio = self.dio(session).await;
-        dio.trans(scope).await
+        Ok(dio.trans(scope).await)
     }
 }
 
@@ -429,13 +434,17 @@ impl DioMut {
     }
 
     pub async fn commit_ext(&self, timeout: Duration) -> Result<(), CommitError> {
-        let (rows, deleted, unlocks) = {
+        let (mut rows, mut deleted, unlocks) = {
             // If we have no dirty records
             let mut state = self.state.lock().unwrap();
             if state.store_ordered.is_empty() && state.deleted.is_empty() {
                 return Ok(());
             }
 
+            // Rows that were queued before the chain became read-only are kept
+            // so that the caller can retry the commit later
+            self.dio.chain.check_writable()?;
+
             // Grab the rows from the state datachain
             let rows = state
                 .store_ordered
@@ -466,8 +475,14 @@ impl DioMut {
             (rows, deleted, unlocks)
         };
 
+        // The secondary indexes are updated within the same transaction
+        let (index_rows, index_deleted) = self.index_changes(&rows, &deleted).await?;
+        rows.extend(index_rows);
+        deleted.extend(index_deleted);
+
         // Declare variables
         let mut evts = Vec::new();
+        let mut versions = Vec::new();
         let mut trans_meta = TransactionMetadata::default();
 
         {
@@ -475,6 +490,16 @@ impl DioMut {
             let multi_lock = self.multi.lock().await;
             let session = self.session();
 
+            // Collections with a retention policy have their oldest entries
+            // tombstoned in the same transaction that pushes the new ones
+            let now = self.time.current_timestamp()?.time_since_epoch_ms;
+            let expired =
+                retention_tombstones(&multi_lock.inside_async.chain, &rows, &deleted, now);
+            if expired.len() > 0 {
+                trace!("commit retention expired={}", expired.len());
+                deleted.extend(expired);
+            }
+
             // Determine the format of the message
             let format = match self.log_format {
                 Some(a) => a,
@@ -487,6 +512,22 @@ impl DioMut {
                 #[cfg(feature = "enable_verbose")]
                 trace!("store: {}@{}", row.type_name, row.key.as_hex_string());
 
+                // Restricted sessions may only write rows that fall within their restriction
+                if let Some(restriction) = session.restriction() {
+                    let reply_to = row.extra_meta.iter().filter_map(|m| match m {
+                        CoreMetadata::Reply(k) => Some(k.clone()),
+                        _ => None,
+                    }).next();
+                    if let Err(err) = restriction.check_row(
+                        &row.key,
+                        row_header.parent.as_ref(),
+                        &row_header.auth,
+                        reply_to.as_ref(),
+                    ) {
+                        bail!(CommitErrorKind::SessionRestricted(err));
+                    }
+                }
+
                 // Build a new clean metadata header
                 let mut meta = Metadata::for_data(row.key);
                 meta.core
@@ -541,10 +582,33 @@ impl DioMut {
                     };
                 }
 
+                // Rows edited with `as_mut_patch` only write the changes unless the
+                // chain of patches is already too long or the patch saves nothing
+                let mut depth = 0u32;
+                let mut data = row.data.clone();
+                match row.patch.as_ref() {
+                    Some(base)
+                        if row.is_new == false
+                            && base.depth < self.dio.chain.cfg_ate.max_patch_depth =>
+                    {
+                        let patch = RowPatch::diff(row.format.data, &base.data[..], &row.data[..])?
+                            .to_bytes()?;
+                        if patch.len() < row.data.len() {
+                            depth = base.depth + 1;
+                            meta.core.push(CoreMetadata::Patch(MetaPatch {
+                                base: base.record,
+                                depth,
+                            }));
+                            data = patch;
+                        }
+                    }
+                    _ => {}
+                }
+
                 // Perform any transformation (e.g. data encryption and compression)
                 let data = multi_lock.data_as_underlay(
                     &mut meta,
-                    row.data.clone(),
+                    data,
                     session.deref(),
                     &trans_meta,
                 )?;
@@ -555,11 +619,19 @@ impl DioMut {
                     data_bytes: MessageBytes::Some(data),
                     format: row.format,
                 };
+                versions.push((row.key, evt.as_header()?.raw.event_hash, depth));
                 evts.push(evt);
             }
 
             // Build events that will represent tombstones on all these records (they will be sent after the writes)
             for key in deleted {
+                let parent = multi_lock.inside_async.chain.lookup_parent(&key);
+                if let Some(restriction) = session.restriction() {
+                    if let Err(err) = restriction.check_delete(&key, parent.as_ref()) {
+                        bail!(CommitErrorKind::SessionRestricted(err));
+                    }
+                }
+
                 let mut meta = Metadata::default();
                 meta.core
                     .push(CoreMetadata::Timestamp(self.time.current_timestamp()?));
@@ -568,7 +640,7 @@ impl DioMut {
                         read: ReadOption::Everyone(None),
                         write: WriteOption::Nobody,
                     }));
-                if let Some(parent) = multi_lock.inside_async.chain.lookup_parent(&key) {
+                if let Some(parent) = parent {
                     meta.core.push(CoreMetadata::Parent(parent))
                 }
                 meta.add_tombstone(key);
@@ -635,12 +707,23 @@ impl DioMut {
                 Some(c) => Some(Arc::clone(c)),
                 None => None,
             },
+            deferrable: false,
         };
         trace!("commit events={}", trans.events.len());
 
         // Process the transaction in the chain using its pipe
         self.multi.pipe.feed(ChainWork { trans: trans }).await?;
 
+        // Remember the versions that were written so that later edits can be
+        // patched on top of them (the cached copies are now out of date)
+        {
+            let mut state = self.dio.state.lock().unwrap();
+            for (key, record, depth) in versions {
+                state.cache_load.remove(&key);
+                state.versions.insert(key, (record, depth));
+            }
+        }
+
         // Last thing we do is kick off an unlock operation using fire and forget
         let unlock_multi = self.multi.clone();
         let unlock_me = unlocks.iter().map(|a| a.clone()).collect::<Vec<_>>();
@@ -653,6 +736,68 @@ impl DioMut {
     }
 }
 
+/// Determines which entries must be removed from the collections that rows
+/// are being pushed into so that their retention policies are honoured (the
+/// oldest entries are those that were written the longest time ago)
+fn retention_tombstones(
+    chain: &ChainOfTrust,
+    rows: &Vec<(RowHeader, RowData)>,
+    deleted: &Vec<PrimaryKey>,
+    now: u64,
+) -> Vec<PrimaryKey> {
+    let mut pushed = FxHashMap::<MetaCollection, u64>::default();
+    for (row_header, row) in rows.iter() {
+        if let (Some(parent), true) = (row_header.parent.as_ref(), row.is_new) {
+            *pushed.entry(parent.vec.clone()).or_default() += 1;
+        }
+    }
+
+    let mut ret = Vec::new();
+    for (vec, pushed) in pushed {
+        // A policy that is changed in this transaction takes effect straight away
+        let policy = match rows.iter().find(|a| a.1.key == vec.parent_id) {
+            Some((_, parent)) => parent
+                .extra_meta
+                .iter()
+                .filter_map(|m| match m {
+                    CoreMetadata::CollectionPolicy(a) if a.collection_id == vec.collection_id => {
+                        Some(a.clone())
+                    }
+                    _ => None,
+                })
+                .next(),
+            None => chain.lookup_collection_policy(&vec),
+        };
+        let policy = match policy {
+            Some(a) => a,
+            None => continue,
+        };
+
+        let existing = chain
+            .lookup_secondary_raw(&vec)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|a| deleted.contains(a) == false)
+            .collect::<Vec<_>>();
+        let excess = match policy.max_entries {
+            Some(max_entries) => (existing.len() as u64 + pushed)
+                .saturating_sub(max_entries)
+                .min(existing.len() as u64) as usize,
+            None => 0usize,
+        };
+        for (n, key) in existing.into_iter().enumerate() {
+            let too_old = match (policy.max_age, chain.lookup_primary(&key)) {
+                (Some(max_age), Some(leaf)) => leaf.updated.saturating_add(max_age) < now,
+                _ => false,
+            };
+            if n < excess || too_old {
+                ret.push(key);
+            }
+        }
+    }
+    ret
+}
+
 impl std::ops::Deref for DioMut {
     type Target = Dio;
 
@@ -755,13 +900,20 @@ impl DioMut {
         let session = self.session();
 
         let _pop1 = DioMutScope::new(self);
-        Ok(self.load_from_event(session.as_ref(), evt.data, evt.header.as_header()?, leaf)?)
+        Ok(self.load_from_event(
+            session.as_ref(),
+            evt.data,
+            &evt.base[..],
+            evt.header.as_header()?,
+            leaf,
+        )?)
     }
 
     pub(crate) fn load_from_event<D>(
         self: &Arc<Self>,
         session: &'_ dyn AteSession,
         mut data: EventStrongData,
+        base: &[EventStrongData],
         header: EventHeader,
         leaf: EventLeaf,
     ) -> Result<DaoMut<D>, LoadError>
@@ -769,7 +921,16 @@ impl DioMut {
         D: Serialize + DeserializeOwned,
     {
         data.data_bytes = match data.data_bytes {
-            Some(data) => Some(self.multi.data_as_overlay(&header.meta, data, session)?),
+            Some(bytes) => {
+                let bytes = self.multi.data_as_overlay(&header.meta, bytes, session)?;
+                Some(self.multi.data_with_patches(
+                    &header.meta,
+                    data.format.data,
+                    bytes,
+                    base,
+                    session,
+                )?)
+            }
             None => None,
         };
 
@@ -1124,7 +1285,7 @@ impl DioMut {
         // Remove anythign thats deleted and return it
         let state = self.state.lock().unwrap();
         let mut ret: Vec<PrimaryKey> = keys.into_iter()
-            .filter(|k| state.deleted.contains(k))
+            .filter(|k| state.deleted.contains(k) == false)
             .collect();
 
         // Build an already loaded list
@@ -0,0 +1,682 @@
+//! Secondary indexes that allow rows to be found by the value of one of
+//! their fields rather than only by their primary key.
+//!
+//! A type opts in by implementing `DaoIndexed` and naming the fields that
+//! are indexed. Whenever a row of the type is written or deleted the DIO
+//! writes (or tombstones) an index entry for each of those fields within
+//! the same transaction. Entries are attached to the same parent as the row
+//! they point at (rows without a parent use a root row for the type) under a
+//! collection per value, numbers are instead all kept in one collection per
+//! field so that they can be queried by range. This means an entry needs
+//! the same access rights as its row and it carries the same authorization.
+//! The entry of a row is stored at a key derived from the key of the row
+//! hence an update moves the existing entry to its new value rather than
+//! leaving the old value behind.
+//!
+//! Entries are only ever treated as hints, every hit is checked against
+//! the row itself before it is returned. A stale or corrupted entry can
+//! thus hide a row from a query but never return the wrong one, when this
+//! is detected `DioMut::repair_index` rebuilds the entries of the rows.
+#![allow(unused_imports)]
+use bytes::Bytes;
+use error_chain::bail;
+use fxhash::FxHashMap;
+use fxhash::FxHashSet;
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::ops::RangeBounds;
+use std::sync::Arc;
+use std::sync::RwLock;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use super::dao::*;
+use super::dao_mut::*;
+use super::dio::*;
+use super::dio_mut::*;
+use super::row::*;
+use crate::crypto::AteHash;
+use crate::error::*;
+use crate::header::*;
+use crate::meta::*;
+use crate::spec::*;
+
+/// Value of an indexed field
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IndexValue {
+    Text(String),
+    Bytes(Vec<u8>),
+    /// Numbers are kept in order and can be queried with `find_range`
+    Number(i64),
+}
+
+/// Collection that holds the entries for a value of an indexed field
+fn index_collection_id(name: &str, field: &str, value: &IndexValue) -> u64 {
+    let prefix = format!("index:{}:{}", name, field);
+    match value {
+        IndexValue::Text(a) => {
+            AteHash::from_bytes_twice(prefix.as_bytes(), format!("text:{}", a).as_bytes()).to_u64()
+        }
+        IndexValue::Bytes(a) => {
+            let mut data = b"bytes:".to_vec();
+            data.extend_from_slice(&a[..]);
+            AteHash::from_bytes_twice(prefix.as_bytes(), &data[..]).to_u64()
+        }
+        IndexValue::Number(_) => index_ordered_collection_id(name, field),
+    }
+}
+
+/// All the numbers of a field share the one collection so that they can be
+/// queried by range
+fn index_ordered_collection_id(name: &str, field: &str) -> u64 {
+    let prefix = format!("index:{}:{}", name, field);
+    AteHash::from_bytes_twice(prefix.as_bytes(), b"ordered").to_u64()
+}
+
+impl From<String> for IndexValue {
+    fn from(val: String) -> IndexValue {
+        IndexValue::Text(val)
+    }
+}
+
+impl From<&str> for IndexValue {
+    fn from(val: &str) -> IndexValue {
+        IndexValue::Text(val.to_string())
+    }
+}
+
+impl From<Vec<u8>> for IndexValue {
+    fn from(val: Vec<u8>) -> IndexValue {
+        IndexValue::Bytes(val)
+    }
+}
+
+impl From<i64> for IndexValue {
+    fn from(val: i64) -> IndexValue {
+        IndexValue::Number(val)
+    }
+}
+
+/// Data objects that can be queried by the value of one or more of their
+/// fields, the type must be registered with `register_index` before any
+/// rows are written for the DIO to maintain its indexes
+pub trait DaoIndexed: Serialize + DeserializeOwned + Clone + Send + Sync + 'static {
+    /// Name of the index within the chain, this must not change once rows
+    /// have been written as the index rows are stored under it
+    const INDEX_NAME: &'static str;
+
+    /// Fields of the type that are indexed
+    const INDEXED_FIELDS: &'static [&'static str];
+
+    /// Returns the value of an indexed field, rows that have no value for
+    /// the field are left out of its index
+    fn index_value(&self, field: &str) -> Option<IndexValue>;
+}
+
+type IndexTermsFn =
+    fn(&Arc<Dio>, &RowData) -> Result<Vec<(&'static str, Option<IndexValue>)>, SerializationError>;
+
+#[derive(Clone)]
+struct IndexedType {
+    name: &'static str,
+    fields: &'static [&'static str],
+    terms: IndexTermsFn,
+}
+
+/// Types that are indexed keyed by the type name of their rows
+static INDEXED_TYPES: Lazy<RwLock<FxHashMap<String, IndexedType>>> =
+    Lazy::new(|| RwLock::new(FxHashMap::default()));
+
+/// Registers a type so that its indexes are maintained on every commit
+pub fn register_index<T>()
+where
+    T: DaoIndexed,
+{
+    let type_name = std::any::type_name::<T>();
+    let mut guard = INDEXED_TYPES.write().unwrap();
+    if guard.contains_key(type_name) == false {
+        guard.insert(
+            type_name.to_string(),
+            IndexedType {
+                name: T::INDEX_NAME,
+                fields: T::INDEXED_FIELDS,
+                terms: index_terms::<T>,
+            },
+        );
+    }
+}
+
+fn index_terms<T>(
+    dio: &Arc<Dio>,
+    row: &RowData,
+) -> Result<Vec<(&'static str, Option<IndexValue>)>, SerializationError>
+where
+    T: DaoIndexed,
+{
+    let (_, row) = Row::<T>::from_row_data(dio, row)?;
+    Ok(T::INDEXED_FIELDS
+        .iter()
+        .map(|field| (*field, row.data.index_value(field)))
+        .collect())
+}
+
+/// Row that the index entries of rows without a parent are attached to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct IndexRoot {
+    name: String,
+}
+
+/// Row in the index of a field that points at a row holding the value
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct IndexEntry {
+    key: PrimaryKey,
+    value: IndexValue,
+}
+
+fn index_root_key(name: &str) -> PrimaryKey {
+    PrimaryKey::from(format!("index:{}", name))
+}
+
+fn index_entry_key(name: &str, field: &str, key: &PrimaryKey) -> PrimaryKey {
+    PrimaryKey::from(format!("index:{}:{}:{}", name, field, key.as_hex_string()))
+}
+
+/// Rows without a parent have their index entries attached to the root
+/// row of their type
+fn index_parent_id<T>(parent_id: Option<PrimaryKey>) -> PrimaryKey
+where
+    T: DaoIndexed,
+{
+    match parent_id {
+        Some(a) => a,
+        None => index_root_key(T::INDEX_NAME),
+    }
+}
+
+/// Returns the name and indexed fields of a registered type
+pub(super) fn indexed_fields(type_name: &str) -> Option<(&'static str, &'static [&'static str])> {
+    let guard = INDEXED_TYPES.read().unwrap();
+    guard.get(type_name).map(|a| (a.name, a.fields))
+}
+
+fn index_field<T>(field: &str) -> Result<&'static str, LoadError>
+where
+    T: DaoIndexed,
+{
+    match T::INDEXED_FIELDS.iter().filter(|a| **a == field).next() {
+        Some(a) => {
+            register_index::<T>();
+            Ok(*a)
+        }
+        None => bail!(LoadErrorKind::IndexNotFound(
+            T::INDEX_NAME.to_string(),
+            field.to_string()
+        )),
+    }
+}
+
+impl Dio {
+    /// Returns all the rows (without a parent) whose indexed field holds
+    /// this value
+    pub async fn find_by<T>(
+        self: &Arc<Self>,
+        field: &str,
+        value: impl Into<IndexValue>,
+    ) -> Result<Vec<Dao<T>>, LoadError>
+    where
+        T: DaoIndexed,
+    {
+        self.find_by_ext(None, field, value).await
+    }
+
+    /// Returns all the rows attached to the parent (or the rows without a
+    /// parent) whose indexed field holds this value
+    pub async fn find_by_ext<T>(
+        self: &Arc<Self>,
+        parent_id: Option<PrimaryKey>,
+        field: &str,
+        value: impl Into<IndexValue>,
+    ) -> Result<Vec<Dao<T>>, LoadError>
+    where
+        T: DaoIndexed,
+    {
+        let value = value.into();
+        let field = index_field::<T>(field)?;
+        let collection_id = index_collection_id(T::INDEX_NAME, field, &value);
+        let hits = self
+            .find_index_hits(index_parent_id::<T>(parent_id), collection_id, |a| *a == value)
+            .await?;
+        self.load_index_hits(field, hits).await
+    }
+
+    /// Returns all the rows (without a parent) whose indexed (numeric) field
+    /// falls within the range, ordered by the value of the field
+    pub async fn find_range<T>(
+        self: &Arc<Self>,
+        field: &str,
+        range: impl RangeBounds<i64>,
+    ) -> Result<Vec<Dao<T>>, LoadError>
+    where
+        T: DaoIndexed,
+    {
+        self.find_range_ext(None, field, range).await
+    }
+
+    /// Returns all the rows attached to the parent (or the rows without a
+    /// parent) whose indexed (numeric) field falls within the range, ordered
+    /// by the value of the field
+    pub async fn find_range_ext<T>(
+        self: &Arc<Self>,
+        parent_id: Option<PrimaryKey>,
+        field: &str,
+        range: impl RangeBounds<i64>,
+    ) -> Result<Vec<Dao<T>>, LoadError>
+    where
+        T: DaoIndexed,
+    {
+        let field = index_field::<T>(field)?;
+        let collection_id = index_ordered_collection_id(T::INDEX_NAME, field);
+        let hits = self
+            .find_index_hits(index_parent_id::<T>(parent_id), collection_id, |a| match a {
+                IndexValue::Number(a) => range.contains(a),
+                _ => false,
+            })
+            .await?;
+        self.load_index_hits(field, hits).await
+    }
+
+    /// Returns the keys of the rows attached to the parent (or the rows
+    /// without a parent) that the index of a registered type says hold this
+    /// value, the rows themselves are not checked
+    pub(super) async fn find_index_keys(
+        self: &Arc<Self>,
+        name: &str,
+        parent_id: Option<PrimaryKey>,
+        field: &str,
+        value: &IndexValue,
+    ) -> Result<Vec<PrimaryKey>, LoadError> {
+        let parent_id = parent_id.unwrap_or_else(|| index_root_key(name));
+        let collection_id = index_collection_id(name, field, value);
+        let hits = self
+            .find_index_hits(parent_id, collection_id, |a| a == value)
+            .await?;
+        Ok(hits.into_iter().map(|a| a.0).collect())
+    }
+
+    /// Returns the keys of the rows that the index of a registered type says
+    /// hold a number within the range (ordered by the number)
+    pub(super) async fn find_index_range_keys(
+        self: &Arc<Self>,
+        name: &str,
+        parent_id: Option<PrimaryKey>,
+        field: &str,
+        range: impl RangeBounds<i64>,
+    ) -> Result<Vec<PrimaryKey>, LoadError> {
+        let parent_id = parent_id.unwrap_or_else(|| index_root_key(name));
+        let collection_id = index_ordered_collection_id(name, field);
+        let hits = self
+            .find_index_hits(parent_id, collection_id, |a| match a {
+                IndexValue::Number(a) => range.contains(a),
+                _ => false,
+            })
+            .await?;
+        Ok(hits.into_iter().map(|a| a.0).collect())
+    }
+
+    /// Reads the entries in a collection of an index and returns the keys of
+    /// the rows that they point at (ordered by their value)
+    pub(super) async fn find_index_hits(
+        self: &Arc<Self>,
+        parent_id: PrimaryKey,
+        collection_id: u64,
+        filter: impl Fn(&IndexValue) -> bool,
+    ) -> Result<Vec<(PrimaryKey, IndexValue)>, LoadError> {
+        let keys = self.children_keys(parent_id, collection_id).await?;
+
+        // Entries that can not be read are skipped rather than failing the
+        // query as they may belong to rows this session can not read
+        let mut hits = self
+            .load_many_ext::<IndexEntry>(keys.into_iter(), true, true)
+            .await?
+            .into_iter()
+            .map(|a| a.take())
+            .filter(|a| filter(&a.value))
+            .map(|a| (a.key, a.value))
+            .collect::<Vec<_>>();
+        hits.sort_by(|a, b| match (&a.1, &b.1) {
+            (IndexValue::Number(a), IndexValue::Number(b)) => a.cmp(b),
+            _ => std::cmp::Ordering::Equal,
+        });
+        let mut already = FxHashSet::default();
+        hits.retain(|a| already.insert(a.0.clone()));
+        Ok(hits)
+    }
+
+    async fn load_index_hits<T>(
+        self: &Arc<Self>,
+        field: &str,
+        hits: Vec<(PrimaryKey, IndexValue)>,
+    ) -> Result<Vec<Dao<T>>, LoadError>
+    where
+        T: DaoIndexed,
+    {
+        let mut ret = Vec::new();
+        for (key, value) in hits {
+            let dao: Dao<T> = match self.load(&key).await {
+                Ok(a) => a,
+                Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                    trace!("index({}.{}) entry for a missing row {}", T::INDEX_NAME, field, key);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            if dao.index_value(field).as_ref() != Some(&value) {
+                trace!("index({}.{}) entry is stale for row {}", T::INDEX_NAME, field, key);
+                continue;
+            }
+            ret.push(dao);
+        }
+        Ok(ret)
+    }
+}
+
+impl DioMut {
+    /// Returns all the rows (without a parent) whose indexed field holds
+    /// this value, rows written within this transaction are only indexed
+    /// once it is committed
+    pub async fn find_by<T>(
+        self: &Arc<Self>,
+        field: &str,
+        value: impl Into<IndexValue>,
+    ) -> Result<Vec<DaoMut<T>>, LoadError>
+    where
+        T: DaoIndexed,
+    {
+        self.find_by_ext(None, field, value).await
+    }
+
+    /// Returns all the rows attached to the parent (or the rows without a
+    /// parent) whose indexed field holds this value
+    pub async fn find_by_ext<T>(
+        self: &Arc<Self>,
+        parent_id: Option<PrimaryKey>,
+        field: &str,
+        value: impl Into<IndexValue>,
+    ) -> Result<Vec<DaoMut<T>>, LoadError>
+    where
+        T: DaoIndexed,
+    {
+        let value = value.into();
+        let field = index_field::<T>(field)?;
+        let collection_id = index_collection_id(T::INDEX_NAME, field, &value);
+        let hits = self
+            .dio
+            .find_index_hits(index_parent_id::<T>(parent_id), collection_id, |a| *a == value)
+            .await?;
+        self.load_index_hits_mut(field, hits).await
+    }
+
+    /// Returns all the rows (without a parent) whose indexed (numeric) field
+    /// falls within the range, ordered by the value of the field
+    pub async fn find_range<T>(
+        self: &Arc<Self>,
+        field: &str,
+        range: impl RangeBounds<i64>,
+    ) -> Result<Vec<DaoMut<T>>, LoadError>
+    where
+        T: DaoIndexed,
+    {
+        self.find_range_ext(None, field, range).await
+    }
+
+    /// Returns all the rows attached to the parent (or the rows without a
+    /// parent) whose indexed (numeric) field falls within the range, ordered
+    /// by the value of the field
+    pub async fn find_range_ext<T>(
+        self: &Arc<Self>,
+        parent_id: Option<PrimaryKey>,
+        field: &str,
+        range: impl RangeBounds<i64>,
+    ) -> Result<Vec<DaoMut<T>>, LoadError>
+    where
+        T: DaoIndexed,
+    {
+        let field = index_field::<T>(field)?;
+        let collection_id = index_ordered_collection_id(T::INDEX_NAME, field);
+        let hits = self
+            .dio
+            .find_index_hits(index_parent_id::<T>(parent_id), collection_id, |a| match a {
+                IndexValue::Number(a) => range.contains(a),
+                _ => false,
+            })
+            .await?;
+        self.load_index_hits_mut(field, hits).await
+    }
+
+    async fn load_index_hits_mut<T>(
+        self: &Arc<Self>,
+        field: &str,
+        hits: Vec<(PrimaryKey, IndexValue)>,
+    ) -> Result<Vec<DaoMut<T>>, LoadError>
+    where
+        T: DaoIndexed,
+    {
+        let mut ret = Vec::new();
+        for (key, value) in hits {
+            let dao: DaoMut<T> = match self.try_load(&key).await? {
+                Some(a) => a,
+                None => {
+                    trace!("index({}.{}) entry for a missing row {}", T::INDEX_NAME, field, key);
+                    continue;
+                }
+            };
+            if dao.index_value(field).as_ref() != Some(&value) {
+                trace!("index({}.{}) entry is stale for row {}", T::INDEX_NAME, field, key);
+                continue;
+            }
+            ret.push(dao);
+        }
+        Ok(ret)
+    }
+
+    /// Rebuilds the index entries of these rows (typically all the rows that
+    /// were found by scanning a collection after a query missed one of them),
+    /// the changes are written when this transaction is committed. Returns
+    /// the number of index rows that had to be written or removed.
+    pub async fn repair_index<T>(
+        self: &Arc<Self>,
+        keys: impl IntoIterator<Item = PrimaryKey>,
+    ) -> Result<usize, CommitError>
+    where
+        T: DaoIndexed,
+    {
+        register_index::<T>();
+
+        let mut rows = Vec::new();
+        for key in keys {
+            let dao: Dao<T> = match self.dio.load(&key).await {
+                Ok(a) => a,
+                Err(LoadError(LoadErrorKind::NotFound(_), _)) => continue,
+                Err(err) => return Err(err.into()),
+            };
+            let row = dao.row.as_row_data(&dao.row_header)?;
+            rows.push((dao.row_header.clone(), row));
+        }
+
+        let (index_rows, index_deleted) = self.index_changes(&rows, &Vec::new()).await?;
+        let ret = index_rows.len() + index_deleted.len();
+        if ret > 0 {
+            debug!("index({}) repaired {} index rows", T::INDEX_NAME, ret);
+        }
+
+        let mut parents = Vec::new();
+        for key in index_deleted {
+            let parent = self.multi.lookup_parent(&key).await;
+            parents.push((key, parent));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        for (header, row) in index_rows {
+            state.dirty_row(row);
+            state.dirty_header(header);
+        }
+        for (key, parent) in parents {
+            if state.deleted.contains(&key) == false {
+                state.add_deleted(key, parent);
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Builds the index rows (and index tombstones) that keep the indexes of
+    /// the registered types in step with the rows that are being committed
+    pub(super) async fn index_changes(
+        &self,
+        rows: &Vec<(RowHeader, RowData)>,
+        deleted: &Vec<PrimaryKey>,
+    ) -> Result<(Vec<(RowHeader, RowData)>, Vec<PrimaryKey>), CommitError> {
+        let types = {
+            let guard = INDEXED_TYPES.read().unwrap();
+            if guard.is_empty() {
+                return Ok((Vec::new(), Vec::new()));
+            }
+            guard.clone()
+        };
+
+        let mut roots = Vec::new();
+        let mut entries = Vec::new();
+        let mut tombstones = Vec::new();
+        let mut seen_roots = FxHashSet::default();
+
+        for (header, row) in rows.iter() {
+            let indexed = match types.get(&row.type_name) {
+                Some(a) => a,
+                None => continue,
+            };
+            let terms = (indexed.terms)(&self.dio, row)?;
+
+            for (field, value) in terms {
+                let entry_key = index_entry_key(indexed.name, field, &row.key);
+                let existing = match self.dio.exists(&entry_key).await {
+                    true => Some(self.dio.load::<IndexEntry>(&entry_key).await.ok()),
+                    false => None,
+                };
+
+                let value = match value {
+                    Some(a) => a,
+                    None => {
+                        if existing.is_some() {
+                            tombstones.push(entry_key);
+                        }
+                        continue;
+                    }
+                };
+
+                // Rows without a parent need the root row of their type
+                let parent_id = match header.parent.as_ref() {
+                    Some(a) => a.vec.parent_id,
+                    None => {
+                        let root_key = index_root_key(indexed.name);
+                        if seen_roots.insert(root_key) && self.dio.exists(&root_key).await == false {
+                            let root = IndexRoot {
+                                name: indexed.name.to_string(),
+                            };
+                            roots.push(self.index_row(
+                                root_key,
+                                None,
+                                MetaAuthorization::default(),
+                                &root,
+                                true,
+                            )?);
+                        }
+                        root_key
+                    }
+                };
+                let parent = MetaParent {
+                    vec: MetaCollection {
+                        parent_id,
+                        collection_id: index_collection_id(indexed.name, field, &value),
+                    },
+                };
+                let entry = IndexEntry {
+                    key: row.key.clone(),
+                    value,
+                };
+
+                // Entries that already point at the right value are left alone
+                if let Some(Some(dao)) = existing.as_ref() {
+                    if dao.row.data == entry
+                        && dao.row_header.auth == header.auth
+                        && dao.row_header.parent.as_ref() == Some(&parent)
+                    {
+                        continue;
+                    }
+                }
+                entries.push(self.index_row(
+                    entry_key,
+                    Some(parent),
+                    header.auth.clone(),
+                    &entry,
+                    existing.is_none(),
+                )?);
+            }
+        }
+
+        // Deleted rows take their index entries with them, the type of the
+        // row is not known hence every registered field is checked
+        for key in deleted.iter() {
+            for indexed in types.values() {
+                for field in indexed.fields.iter() {
+                    let entry_key = index_entry_key(indexed.name, field, key);
+                    if deleted.contains(&entry_key) == false
+                        && tombstones.contains(&entry_key) == false
+                        && self.dio.exists(&entry_key).await
+                    {
+                        tombstones.push(entry_key);
+                    }
+                }
+            }
+        }
+
+        roots.extend(entries);
+        Ok((roots, tombstones))
+    }
+
+    fn index_row<D>(
+        &self,
+        key: PrimaryKey,
+        parent: Option<MetaParent>,
+        auth: MetaAuthorization,
+        data: &D,
+        is_new: bool,
+    ) -> Result<(RowHeader, RowData), SerializationError>
+    where
+        D: Serialize,
+    {
+        let format = match self.dio.log_format {
+            Some(a) => a,
+            None => self.default_format(),
+        };
+        let data = Bytes::from(format.data.serialize(data)?);
+        let header = RowHeader {
+            key: key.clone(),
+            parent: parent.clone(),
+            auth: auth.clone(),
+        };
+        let row = RowData {
+            key,
+            type_name: std::any::type_name::<D>().to_string(),
+            format,
+            data_hash: AteHash::from_bytes(&data[..]),
+            data,
+            collections: FxHashSet::default(),
+            created: 0,
+            updated: 0,
+            extra_meta: Vec::new(),
+            parent,
+            auth,
+            patch: None,
+            is_new,
+        };
+        Ok((header, row))
+    }
+}
@@ -1,11 +1,17 @@
 pub(crate) mod bus;
 pub(crate) mod child;
+pub(crate) mod cursor;
 pub(crate) mod dao;
 pub(crate) mod dao_mut;
 pub(crate) mod dio;
 pub(crate) mod dio_mut;
+pub(crate) mod field_index;
 pub(crate) mod foreign;
 pub(crate) mod map;
+pub(crate) mod multi_chain;
+pub(crate) mod patch;
+pub(crate) mod query;
+pub(crate) mod raw;
 pub(crate) mod row;
 pub(crate) mod test;
 pub(crate) mod vec;
@@ -18,11 +24,32 @@ pub use super::dio::dio::Dio;
 pub use super::dio::dio::DioSessionGuard;
 pub use super::dio::dio::DioSessionGuardMut;
 pub use super::dio::dio_mut::DioMut;
+pub use super::dio::field_index::register_index;
+pub use super::dio::field_index::DaoIndexed;
+pub use super::dio::field_index::IndexValue;
 pub use super::dio::map::DaoMap;
+pub use super::dio::multi_chain::MultiChainIntent;
+pub use super::dio::multi_chain::MultiChainIntentState;
+pub use super::dio::multi_chain::MultiChainParticipant;
+pub use super::dio::multi_chain::MultiChainResolver;
+pub use super::dio::multi_chain::MultiChainTransaction;
+pub use super::dio::multi_chain::MULTI_CHAIN_INTENT_COLLECTION_ID;
+pub use super::dio::query::Query;
+pub use super::dio::query::QueryExpr;
+pub use super::dio::query::QueryOp;
+pub use super::dio::query::DEFAULT_QUERY_SCAN_LIMIT;
+pub use super::dio::raw::RawRow;
+pub use super::dio::raw::SerializerExt;
 pub use crate::dio::bus::Bus;
 pub use crate::dio::bus::BusEvent;
 pub use crate::dio::bus::TryBusEvent;
 pub use crate::dio::child::DaoChild;
+pub use crate::dio::cursor::ChainCursor;
+pub use crate::dio::cursor::CursorEvent;
+pub use crate::dio::cursor::CursorPosition;
+pub use crate::dio::cursor::CursorSubscription;
+pub use crate::dio::cursor::CURSOR_ACK_BATCH;
+pub use crate::dio::cursor::CURSOR_COLLECTION_ID;
 pub use crate::dio::dao::Dao;
 pub use crate::dio::dao::DaoObj;
 pub use crate::dio::dao_mut::DaoMut;
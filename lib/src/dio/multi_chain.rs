@@ -0,0 +1,353 @@
+#![allow(unused_imports)]
+use async_trait::async_trait;
+use error_chain::bail;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use super::dio_mut::*;
+use crate::error::*;
+use crate::prelude::*;
+
+/// Collection (beneath the parent given to the coordinator) that holds the
+/// intents of the multi-chain transactions that have not yet completed
+pub const MULTI_CHAIN_INTENT_COLLECTION_ID: u64 = 0x4d43_5458;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiChainIntentState {
+    /// All the chains were prepared but none of them have been committed
+    Prepared,
+    /// The chains are being committed one after the other
+    Committing,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MultiChainParticipant {
+    /// Name that the resolver uses to open the chain again during recovery
+    pub name: String,
+    /// Rows that the transaction creates on this chain (these are removed
+    /// again if the transaction is rolled back after it was committed)
+    pub created: Vec<PrimaryKey>,
+    pub updated: Vec<PrimaryKey>,
+    pub deleted: Vec<PrimaryKey>,
+    pub committed: bool,
+}
+
+/// Record written to the coordinator chain before any of the chains are
+/// committed so that a crash part way through can be recovered
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MultiChainIntent {
+    pub state: MultiChainIntentState,
+    pub participants: Vec<MultiChainParticipant>,
+    pub started: u64,
+}
+
+/// Opens the chains of the participants when intents are recovered
+#[async_trait]
+pub trait MultiChainResolver: Send + Sync {
+    async fn open(&self, name: &str) -> Result<Arc<DioMut>, MultiChainError>;
+}
+
+/// Commits the changes of several chains-of-trust as a single unit using a
+/// two-phase commit. All the chains are first prepared (validated and the
+/// rows locked), an intent is then recorded on the coordinator chain and
+/// finally the chains are committed one after the other.
+///
+/// If any chain fails to commit then the chains that were already committed
+/// have the rows they created removed again. Rows that were updated or
+/// deleted can not be restored hence these should be kept on the chain that
+/// is committed last.
+///
+/// If the process crashes while committing then `recover` will finish the
+/// transaction the next time it starts when it only deletes rows, otherwise
+/// it is rolled back.
+pub struct MultiChainTransaction {
+    coordinator: Arc<DioMut>,
+    parent: PrimaryKey,
+    participants: Vec<Participant>,
+    intent: Option<PrimaryKey>,
+    #[cfg(test)]
+    pub(crate) crash_after: Option<usize>,
+}
+
+struct Participant {
+    name: String,
+    dio: Arc<DioMut>,
+    locked: Vec<PrimaryKey>,
+    created: Vec<PrimaryKey>,
+    updated: Vec<PrimaryKey>,
+    deleted: Vec<PrimaryKey>,
+    committed: bool,
+}
+
+impl MultiChainTransaction {
+    /// Creates a transaction whose intent records are attached to the parent
+    /// on the coordinator chain (the coordinator must not also be used as one
+    /// of the participants as it is committed separately)
+    pub fn new(coordinator: &Arc<DioMut>, parent: &PrimaryKey) -> MultiChainTransaction {
+        MultiChainTransaction {
+            coordinator: Arc::clone(coordinator),
+            parent: parent.clone(),
+            participants: Vec::new(),
+            intent: None,
+            #[cfg(test)]
+            crash_after: None,
+        }
+    }
+
+    /// Adds the changes of a chain to the transaction, the chains are
+    /// committed in the order that they were added
+    pub fn add(&mut self, name: &str, dio: &Arc<DioMut>) {
+        self.participants.push(Participant {
+            name: name.to_string(),
+            dio: Arc::clone(dio),
+            locked: Vec::new(),
+            created: Vec::new(),
+            updated: Vec::new(),
+            deleted: Vec::new(),
+            committed: false,
+        });
+    }
+
+    /// Validates that all the chains can be written to and locks all the rows
+    /// that will be written before recording the intent to commit them
+    pub async fn prepare(&mut self) -> Result<(), MultiChainError> {
+        if self.intent.is_some() {
+            return Ok(());
+        }
+        if let Err(err) = self.prepare_internal().await {
+            self.unlock_all().await;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    async fn prepare_internal(&mut self) -> Result<(), MultiChainError> {
+        for participant in self.participants.iter_mut() {
+            participant.dio.dio.chain.check_writable()?;
+
+            // Determine which rows are going to be written (rows that the
+            // caller already locked will be unlocked by the commit itself)
+            let (keys, created, updated, deleted) = {
+                let state = participant.dio.state.lock().unwrap();
+                let mut keys = state
+                    .store_ordered
+                    .iter()
+                    .map(|a| a.key.clone())
+                    .chain(state.deleted.iter().map(|a| a.clone()))
+                    .filter(|a| state.pipe_unlock.contains(a) == false)
+                    .collect::<Vec<_>>();
+                keys.sort();
+                keys.dedup();
+                let (created, updated) = state
+                    .rows
+                    .values()
+                    .filter(|a| state.deleted.contains(&a.key) == false)
+                    .partition::<Vec<_>, _>(|a| a.is_new);
+                let created = created.into_iter().map(|a| a.key.clone()).collect();
+                let updated = updated.into_iter().map(|a| a.key.clone()).collect();
+                let deleted = state.deleted.iter().map(|a| a.clone()).collect();
+                (keys, created, updated, deleted)
+            };
+            participant.created = created;
+            participant.updated = updated;
+            participant.deleted = deleted;
+
+            for key in keys {
+                if participant.dio.try_lock(key.clone()).await? == false {
+                    bail!(MultiChainErrorKind::Locked(
+                        participant.name.clone(),
+                        key.as_hex_string()
+                    ));
+                }
+                participant.locked.push(key);
+            }
+        }
+
+        // Record the intent so that we can recover from a crash
+        let intent = MultiChainIntent {
+            state: MultiChainIntentState::Prepared,
+            participants: self
+                .participants
+                .iter()
+                .map(|a| MultiChainParticipant {
+                    name: a.name.clone(),
+                    created: a.created.clone(),
+                    updated: a.updated.clone(),
+                    deleted: a.deleted.clone(),
+                    committed: false,
+                })
+                .collect(),
+            started: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|a| a.as_millis() as u64)
+                .unwrap_or(0),
+        };
+        let key = {
+            let mut dao = self.coordinator.store(intent)?;
+            dao.attach_orphaned_ext(&self.parent, MULTI_CHAIN_INTENT_COLLECTION_ID)?;
+            dao.key().clone()
+        };
+        self.coordinator.commit().await?;
+        self.intent = Some(key);
+
+        debug!(
+            "multi-chain transaction prepared (participants={})",
+            self.participants.len()
+        );
+        Ok(())
+    }
+
+    /// Commits all the chains (preparing them first if this has not already
+    /// been done), if any of them fail then the whole transaction is rolled back
+    pub async fn commit(mut self) -> Result<(), MultiChainError> {
+        self.prepare().await?;
+        let intent = match self.intent.clone() {
+            Some(a) => a,
+            None => {
+                return Ok(());
+            }
+        };
+        self.update_intent(&intent, |a| a.state = MultiChainIntentState::Committing)
+            .await?;
+
+        for n in 0..self.participants.len() {
+            let ret = self.participants[n].dio.commit().await;
+            if let Err(err) = ret {
+                let name = self.participants[n].name.clone();
+                warn!("multi-chain transaction failed on {} - {}", name, err);
+                self.rollback().await?;
+                bail!(MultiChainErrorKind::RolledBack(name, err.to_string()));
+            }
+            self.participants[n].committed = true;
+            self.unlock(n).await;
+
+            #[cfg(test)]
+            if self.crash_after == Some(n + 1) {
+                self.intent.take();
+                bail!(CommitErrorKind::Aborted);
+            }
+
+            self.update_intent(&intent, |a| a.participants[n].committed = true)
+                .await?;
+        }
+
+        // The transaction is complete so the intent is no longer needed
+        self.intent.take();
+        self.coordinator.delete(&intent).await?;
+        self.coordinator.commit().await?;
+        Ok(())
+    }
+
+    /// Abandons the transaction, any chains that were already committed have
+    /// the rows they created removed again
+    pub async fn rollback(mut self) -> Result<(), MultiChainError> {
+        for participant in self.participants.iter() {
+            if participant.committed {
+                remove_rows(&participant.dio, &participant.created).await?;
+            } else {
+                participant.dio.cancel();
+            }
+        }
+        self.unlock_all().await;
+
+        if let Some(intent) = self.intent.take() {
+            self.coordinator.delete(&intent).await?;
+            self.coordinator.commit().await?;
+        }
+        Ok(())
+    }
+
+    async fn update_intent(
+        &self,
+        key: &PrimaryKey,
+        f: impl FnOnce(&mut MultiChainIntent),
+    ) -> Result<(), MultiChainError> {
+        {
+            let mut dao = self.coordinator.load::<MultiChainIntent>(key).await?;
+            let mut intent = dao.as_mut();
+            f(&mut *intent);
+        }
+        self.coordinator.commit().await?;
+        Ok(())
+    }
+
+    async fn unlock(&mut self, n: usize) {
+        let participant = &mut self.participants[n];
+        for key in participant.locked.drain(..) {
+            if let Err(err) = participant.dio.unlock(key).await {
+                debug!("failed to unlock row - {}", err);
+            }
+        }
+    }
+
+    async fn unlock_all(&mut self) {
+        for n in 0..self.participants.len() {
+            self.unlock(n).await;
+        }
+    }
+
+    /// Recovers all the transactions that were interrupted before they
+    /// completed, transactions that only delete rows (or whose chains were all
+    /// committed) are finished while the others are rolled back. Returns the
+    /// number of transactions that were recovered.
+    pub async fn recover(
+        coordinator: &Arc<DioMut>,
+        parent: &PrimaryKey,
+        resolver: &dyn MultiChainResolver,
+    ) -> Result<usize, MultiChainError> {
+        let intents = coordinator
+            .children_ext::<MultiChainIntent>(
+                parent.clone(),
+                MULTI_CHAIN_INTENT_COLLECTION_ID,
+                true,
+                true,
+            )
+            .await?;
+
+        let mut ret = 0usize;
+        for intent in intents {
+            let committed = intent.participants.iter().all(|a| a.committed);
+            let deletes_only = intent
+                .participants
+                .iter()
+                .all(|a| a.created.is_empty() && a.updated.is_empty());
+
+            // A participant may have committed just before the crash without
+            // it being recorded hence the rows are checked on all of them
+            if intent.state == MultiChainIntentState::Committing && committed == false {
+                for participant in intent.participants.iter() {
+                    let dio = resolver.open(participant.name.as_str()).await?;
+                    if deletes_only {
+                        remove_rows(&dio, &participant.deleted).await?;
+                    } else {
+                        remove_rows(&dio, &participant.created).await?;
+                    }
+                }
+                if deletes_only {
+                    info!("multi-chain transaction finished ({})", intent.key());
+                } else {
+                    info!("multi-chain transaction rolled back ({})", intent.key());
+                }
+            }
+
+            coordinator.delete(intent.key()).await?;
+            ret += 1;
+        }
+        coordinator.commit().await?;
+        Ok(ret)
+    }
+}
+
+async fn remove_rows(dio: &Arc<DioMut>, keys: &Vec<PrimaryKey>) -> Result<(), MultiChainError> {
+    dio.cancel();
+    for key in keys {
+        if dio.exists(key).await {
+            dio.delete(key).await?;
+        }
+    }
+    dio.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,238 @@
+#![allow(unused_imports)]
+use error_chain::bail;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, error, trace, warn};
+
+use bytes::Bytes;
+
+use crate::crypto::AteHash;
+use crate::error::*;
+use crate::spec::*;
+
+/// Single change within a patch event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) enum PatchOp {
+    /// Replaces the field at a serde path with a JSON encoded value, or
+    /// removes the field when the value is `None`
+    Field {
+        path: Vec<String>,
+        value: Option<String>,
+    },
+    /// Replaces a range of bytes in the serialized row (used for formats
+    /// that can not be walked field by field)
+    Bytes {
+        offset: u64,
+        remove: u64,
+        insert: Vec<u8>,
+    },
+}
+
+/// Payload of a patch event which is always encoded with bincode
+/// regardless of the serialization format of the row itself
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct RowPatch {
+    pub ops: Vec<PatchOp>,
+}
+
+/// Version of a row that a mutable object is patched against
+#[derive(Debug, Clone)]
+pub(crate) struct RowPatchBase {
+    /// Hash of the event that holds this version of the row
+    pub record: AteHash,
+    /// Number of patches stacked on the last full row
+    pub depth: u32,
+    /// Serialized data of the row at this version
+    pub data: Bytes,
+}
+
+impl RowPatch {
+    /// Computes the changes needed to turn the base row into the new row
+    pub(crate) fn diff(
+        format: SerializationFormat,
+        base: &[u8],
+        new: &[u8],
+    ) -> Result<RowPatch, SerializationError> {
+        let mut ret = RowPatch::default();
+        match format {
+            SerializationFormat::Json => {
+                let base: Value = serde_json::from_slice(base)?;
+                let new: Value = serde_json::from_slice(new)?;
+                diff_value(&mut Vec::new(), &base, &new, &mut ret.ops)?;
+            }
+            _ => {
+                let prefix = base
+                    .iter()
+                    .zip(new.iter())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                let suffix = base[prefix..]
+                    .iter()
+                    .rev()
+                    .zip(new[prefix..].iter().rev())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                let remove = base.len() - prefix - suffix;
+                let insert = new[prefix..(new.len() - suffix)].to_vec();
+                if remove > 0 || insert.len() > 0 {
+                    ret.ops.push(PatchOp::Bytes {
+                        offset: prefix as u64,
+                        remove: remove as u64,
+                        insert,
+                    });
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Applies the changes on top of the base row and returns the new row
+    pub(crate) fn apply(
+        &self,
+        format: SerializationFormat,
+        base: &[u8],
+    ) -> Result<Bytes, SerializationError> {
+        if self.ops.iter().any(|op| matches!(op, PatchOp::Field { .. })) {
+            if format != SerializationFormat::Json {
+                bail!(SerializationErrorKind::SerdeError(format!(
+                    "field patches can not be applied to rows stored as {}",
+                    format
+                )));
+            }
+        }
+
+        match format {
+            SerializationFormat::Json => {
+                let mut ret: Value = serde_json::from_slice(base)?;
+                for op in self.ops.iter() {
+                    match op {
+                        PatchOp::Field { path, value } => {
+                            let value = match value {
+                                Some(a) => Some(serde_json::from_str(a.as_str())?),
+                                None => None,
+                            };
+                            apply_value(&mut ret, &path[..], value)?;
+                        }
+                        PatchOp::Bytes { .. } => {
+                            bail!(SerializationErrorKind::SerdeError(
+                                "byte patches can not be applied to rows stored as json"
+                                    .to_string()
+                            ));
+                        }
+                    }
+                }
+                Ok(Bytes::from(serde_json::to_vec(&ret)?))
+            }
+            _ => {
+                let mut ret = base.to_vec();
+                for op in self.ops.iter() {
+                    if let PatchOp::Bytes {
+                        offset,
+                        remove,
+                        insert,
+                    } = op
+                    {
+                        let start = *offset as usize;
+                        let end = start + *remove as usize;
+                        if end > ret.len() {
+                            bail!(SerializationErrorKind::SerdeError(format!(
+                                "byte patch is out of range ({}..{} of {} bytes)",
+                                start,
+                                end,
+                                ret.len()
+                            )));
+                        }
+                        ret.splice(start..end, insert.iter().cloned());
+                    }
+                }
+                Ok(Bytes::from(ret))
+            }
+        }
+    }
+
+    pub(crate) fn to_bytes(&self) -> Result<Bytes, SerializationError> {
+        Ok(Bytes::from(bincode::serialize(self)?))
+    }
+
+    pub(crate) fn from_bytes(data: &[u8]) -> Result<RowPatch, SerializationError> {
+        Ok(bincode::deserialize(data)?)
+    }
+}
+
+fn diff_value(
+    path: &mut Vec<String>,
+    base: &Value,
+    new: &Value,
+    ops: &mut Vec<PatchOp>,
+) -> Result<(), SerializationError> {
+    match (base, new) {
+        (Value::Object(base), Value::Object(new)) => {
+            for (name, new_val) in new.iter() {
+                path.push(name.clone());
+                match base.get(name) {
+                    Some(base_val) => diff_value(path, base_val, new_val, ops)?,
+                    None => ops.push(PatchOp::Field {
+                        path: path.clone(),
+                        value: Some(serde_json::to_string(new_val)?),
+                    }),
+                }
+                path.pop();
+            }
+            for name in base.keys().filter(|a| new.contains_key(a.as_str()) == false) {
+                path.push(name.clone());
+                ops.push(PatchOp::Field {
+                    path: path.clone(),
+                    value: None,
+                });
+                path.pop();
+            }
+        }
+        (base, new) if base == new => {}
+        (_, new) => ops.push(PatchOp::Field {
+            path: path.clone(),
+            value: Some(serde_json::to_string(new)?),
+        }),
+    }
+    Ok(())
+}
+
+fn apply_value(
+    target: &mut Value,
+    path: &[String],
+    value: Option<Value>,
+) -> Result<(), SerializationError> {
+    let (name, rest) = match path.split_first() {
+        Some(a) => a,
+        None => {
+            *target = value.unwrap_or(Value::Null);
+            return Ok(());
+        }
+    };
+    let obj = match target {
+        Value::Object(a) => a,
+        _ => {
+            bail!(SerializationErrorKind::SerdeError(format!(
+                "field patch expected an object at '{}'",
+                name
+            )));
+        }
+    };
+    if rest.is_empty() {
+        match value {
+            Some(a) => {
+                obj.insert(name.clone(), a);
+            }
+            None => {
+                obj.remove(name);
+            }
+        }
+        return Ok(());
+    }
+    match obj.get_mut(name) {
+        Some(next) => apply_value(next, rest, value),
+        None => bail!(SerializationErrorKind::SerdeError(format!(
+            "field patch refers to the missing field '{}'",
+            name
+        ))),
+    }
+}
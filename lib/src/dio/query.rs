@@ -0,0 +1,620 @@
+//! Small filter language for finding rows by the values of their fields
+//! rather than by their keys, it is mostly meant for ad-hoc queries made by
+//! people (e.g. `export.binary == 'foo' and not (replicas < 2)`).
+//!
+//! Expressions compare the fields of a row (dotted paths into its JSON form,
+//! array elements are addressed by their index) with literal values using
+//! `==`, `!=`, `<`, `<=`, `>`, `>=` and `contains`, and combine them with
+//! `and`, `or` and `not` (or `&&`, `||` and `!`). Strings are quoted with
+//! either single or double quotes, fields that a row does not have are null.
+//!
+//! When the rows are of a type that is registered with `register_index` the
+//! indexed fields that the expression requires (i.e. equality or numeric
+//! ranges that are combined with `and`) are used to find the candidates,
+//! otherwise the rows are scanned. A scan fails rather than reading more
+//! rows than the scan limit of the query allows. Indexes are only of use
+//! to queries when their values are the values of the fields as they are
+//! (rather than e.g. a lowercase copy of them).
+#![allow(unused_imports)]
+use error_chain::bail;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::ops::Bound;
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use super::dao::*;
+use super::dio::*;
+use super::field_index::*;
+use crate::error::*;
+use crate::header::*;
+
+/// Maximum number of rows that a query scans unless it is told otherwise
+pub const DEFAULT_QUERY_SCAN_LIMIT: usize = 10_000;
+
+/// How a field is compared with a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Text contains the (text) value, an array contains an element that
+    /// equals the value or an object has a field named by the value
+    Contains,
+}
+
+impl std::fmt::Display for QueryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryOp::Eq => write!(f, "=="),
+            QueryOp::Ne => write!(f, "!="),
+            QueryOp::Lt => write!(f, "<"),
+            QueryOp::Le => write!(f, "<="),
+            QueryOp::Gt => write!(f, ">"),
+            QueryOp::Ge => write!(f, ">="),
+            QueryOp::Contains => write!(f, "contains"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryExpr {
+    Compare {
+        field: Vec<String>,
+        op: QueryOp,
+        value: Value,
+    },
+    And(Box<QueryExpr>, Box<QueryExpr>),
+    Or(Box<QueryExpr>, Box<QueryExpr>),
+    Not(Box<QueryExpr>),
+}
+
+/// Query that has been parsed and can be matched against rows
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub expr: QueryExpr,
+    /// Maximum number of rows that will be read when the query can not use
+    /// an index to find them
+    pub scan_limit: usize,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, QueryError> {
+        let mut parser = Parser::new(text)?;
+        let expr = parser.parse_or()?;
+        if parser.token != Token::End {
+            return Err(parser.expected("'and', 'or' or the end of the query"));
+        }
+        Ok(Query {
+            expr,
+            scan_limit: DEFAULT_QUERY_SCAN_LIMIT,
+        })
+    }
+
+    pub fn with_scan_limit(mut self, scan_limit: usize) -> Query {
+        self.scan_limit = scan_limit;
+        self
+    }
+
+    /// Checks if the JSON form of a row matches the query
+    pub fn is_match(&self, row: &Value) -> bool {
+        self.expr.is_match(row)
+    }
+
+    /// Finds a lookup in an index (of these fields) that returns every row
+    /// the query could match, equality is preferred over a range
+    fn index_lookup(&self, fields: &[&'static str]) -> Option<(&'static str, IndexLookup)> {
+        let mut terms = Vec::new();
+        self.expr.conjuncts(&mut terms);
+
+        let mut ranges: Vec<(&'static str, Bound<i64>, Bound<i64>)> = Vec::new();
+        for (path, op, value) in terms {
+            let field = match path {
+                [a] => match fields.iter().find(|b| **b == a.as_str()) {
+                    Some(b) => *b,
+                    None => continue,
+                },
+                _ => continue,
+            };
+            let (lower, upper) = match (op, value) {
+                (QueryOp::Eq, Value::String(a)) => {
+                    return Some((field, IndexLookup::Equals(IndexValue::Text(a.clone()))));
+                }
+                (QueryOp::Eq, Value::Number(a)) => match a.as_i64() {
+                    Some(a) => return Some((field, IndexLookup::Equals(IndexValue::Number(a)))),
+                    None => continue,
+                },
+                (op, Value::Number(a)) => match (op, a.as_i64()) {
+                    (QueryOp::Lt, Some(a)) => (Bound::Unbounded, Bound::Excluded(a)),
+                    (QueryOp::Le, Some(a)) => (Bound::Unbounded, Bound::Included(a)),
+                    (QueryOp::Gt, Some(a)) => (Bound::Excluded(a), Bound::Unbounded),
+                    (QueryOp::Ge, Some(a)) => (Bound::Included(a), Bound::Unbounded),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            match ranges.iter_mut().find(|a| a.0 == field) {
+                Some(range) => {
+                    if lower != Bound::Unbounded {
+                        range.1 = lower;
+                    }
+                    if upper != Bound::Unbounded {
+                        range.2 = upper;
+                    }
+                }
+                None => ranges.push((field, lower, upper)),
+            }
+        }
+        ranges
+            .into_iter()
+            .next()
+            .map(|(field, lower, upper)| (field, IndexLookup::Range(lower, upper)))
+    }
+}
+
+impl std::str::FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(text: &str) -> Result<Query, QueryError> {
+        Query::parse(text)
+    }
+}
+
+enum IndexLookup {
+    Equals(IndexValue),
+    Range(Bound<i64>, Bound<i64>),
+}
+
+impl QueryExpr {
+    pub fn is_match(&self, row: &Value) -> bool {
+        match self {
+            QueryExpr::Compare { field, op, value } => {
+                let left = lookup(row, &field[..]).unwrap_or(&Value::Null);
+                compare(left, *op, value)
+            }
+            QueryExpr::And(a, b) => a.is_match(row) && b.is_match(row),
+            QueryExpr::Or(a, b) => a.is_match(row) || b.is_match(row),
+            QueryExpr::Not(a) => a.is_match(row) == false,
+        }
+    }
+
+    /// Collects the comparisons that every matching row must satisfy
+    fn conjuncts<'a>(&'a self, ret: &mut Vec<(&'a [String], QueryOp, &'a Value)>) {
+        match self {
+            QueryExpr::Compare { field, op, value } => ret.push((&field[..], *op, value)),
+            QueryExpr::And(a, b) => {
+                a.conjuncts(ret);
+                b.conjuncts(ret);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn lookup<'a>(row: &'a Value, path: &[String]) -> Option<&'a Value> {
+    let mut ret = row;
+    for name in path {
+        ret = match ret {
+            Value::Object(a) => a.get(name.as_str())?,
+            Value::Array(a) => a.get(name.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(ret)
+}
+
+/// Numbers are equal if they have the same value whatever their
+/// representation (e.g. 1 and 1.0)
+fn is_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (a, b) => a == b,
+    }
+}
+
+fn compare(left: &Value, op: QueryOp, right: &Value) -> bool {
+    let ordering = match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .and_then(|a| b.as_f64().and_then(|b| a.partial_cmp(&b))),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    match op {
+        QueryOp::Eq => is_equal(left, right),
+        QueryOp::Ne => is_equal(left, right) == false,
+        QueryOp::Lt => ordering == Some(Ordering::Less),
+        QueryOp::Le => matches!(ordering, Some(Ordering::Less) | Some(Ordering::Equal)),
+        QueryOp::Gt => ordering == Some(Ordering::Greater),
+        QueryOp::Ge => matches!(ordering, Some(Ordering::Greater) | Some(Ordering::Equal)),
+        QueryOp::Contains => match (left, right) {
+            (Value::String(a), Value::String(b)) => a.contains(b.as_str()),
+            (Value::Array(a), b) => a.iter().any(|a| is_equal(a, b)),
+            (Value::Object(a), Value::String(b)) => a.contains_key(b.as_str()),
+            _ => false,
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Field(Vec<String>),
+    Literal(Value),
+    Op(QueryOp),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    End,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    offset: usize,
+    token: Token,
+    /// Where the current token starts (counted in characters from 1)
+    position: usize,
+    /// Text of the current token as it was written
+    found: String,
+}
+
+impl Parser {
+    fn new(text: &str) -> Result<Parser, QueryError> {
+        let mut ret = Parser {
+            chars: text.chars().collect(),
+            offset: 0,
+            token: Token::End,
+            position: 0,
+            found: String::new(),
+        };
+        ret.advance()?;
+        Ok(ret)
+    }
+
+    fn expected(&self, expected: &str) -> QueryError {
+        let found = match self.token {
+            Token::End => "the end of the query".to_string(),
+            _ => format!("'{}'", self.found),
+        };
+        QueryErrorKind::Parse(self.position, expected.to_string(), found).into()
+    }
+
+    fn advance(&mut self) -> Result<(), QueryError> {
+        while self.chars.get(self.offset).map(|a| a.is_whitespace()) == Some(true) {
+            self.offset += 1;
+        }
+        let start = self.offset;
+        self.position = start + 1;
+
+        let c = match self.chars.get(start) {
+            Some(a) => *a,
+            None => {
+                self.token = Token::End;
+                self.found = String::new();
+                return Ok(());
+            }
+        };
+        let next = self.chars.get(start + 1).cloned();
+        self.token = match (c, next) {
+            ('(', _) => self.symbol(1, Token::Open),
+            (')', _) => self.symbol(1, Token::Close),
+            ('=', Some('=')) => self.symbol(2, Token::Op(QueryOp::Eq)),
+            ('=', _) => self.symbol(1, Token::Op(QueryOp::Eq)),
+            ('!', Some('=')) => self.symbol(2, Token::Op(QueryOp::Ne)),
+            ('!', _) => self.symbol(1, Token::Not),
+            ('<', Some('=')) => self.symbol(2, Token::Op(QueryOp::Le)),
+            ('<', _) => self.symbol(1, Token::Op(QueryOp::Lt)),
+            ('>', Some('=')) => self.symbol(2, Token::Op(QueryOp::Ge)),
+            ('>', _) => self.symbol(1, Token::Op(QueryOp::Gt)),
+            ('&', Some('&')) => self.symbol(2, Token::And),
+            ('|', Some('|')) => self.symbol(2, Token::Or),
+            ('\'', _) | ('"', _) => self.string(c)?,
+            (c, _) if c.is_ascii_digit() || c == '-' || c == '+' => self.number()?,
+            (c, _) if c.is_alphabetic() || c == '_' => self.word()?,
+            (c, _) => {
+                self.offset += 1;
+                self.found = c.to_string();
+                self.token = Token::Literal(Value::Null);
+                return Err(self.expected("a field, a value, an operator or a parenthesis"));
+            }
+        };
+        self.found = self.chars[start..self.offset].iter().collect();
+        Ok(())
+    }
+
+    fn symbol(&mut self, len: usize, token: Token) -> Token {
+        self.offset += len;
+        token
+    }
+
+    fn string(&mut self, quote: char) -> Result<Token, QueryError> {
+        let mut ret = String::new();
+        self.offset += 1;
+        loop {
+            match self.chars.get(self.offset) {
+                Some(c) if *c == quote => {
+                    self.offset += 1;
+                    return Ok(Token::Literal(Value::String(ret)));
+                }
+                Some('\\') => {
+                    match self.chars.get(self.offset + 1) {
+                        Some('n') => ret.push('\n'),
+                        Some('t') => ret.push('\t'),
+                        Some(c) => ret.push(*c),
+                        None => {}
+                    }
+                    self.offset += 2;
+                }
+                Some(c) => {
+                    ret.push(*c);
+                    self.offset += 1;
+                }
+                None => {
+                    self.position = self.chars.len() + 1;
+                    self.token = Token::End;
+                    return Err(self.expected(format!("a closing {}", quote).as_str()));
+                }
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Token, QueryError> {
+        let start = self.offset;
+        self.offset += 1;
+        while let Some(c) = self.chars.get(self.offset) {
+            if c.is_ascii_alphanumeric() == false && *c != '.' && *c != '-' && *c != '+' {
+                break;
+            }
+            self.offset += 1;
+        }
+        let text = self.chars[start..self.offset].iter().collect::<String>();
+        match serde_json::from_str::<serde_json::Number>(text.trim_start_matches('+')) {
+            Ok(a) => Ok(Token::Literal(Value::Number(a))),
+            Err(_) => {
+                self.found = text;
+                self.token = Token::Literal(Value::Null);
+                Err(self.expected("a number"))
+            }
+        }
+    }
+
+    /// Reads a keyword or the (dotted) path of a field
+    fn word(&mut self) -> Result<Token, QueryError> {
+        let start = self.offset;
+        while let Some(c) = self.chars.get(self.offset) {
+            if c.is_alphanumeric() == false && *c != '_' && *c != '.' {
+                break;
+            }
+            self.offset += 1;
+        }
+        let text = self.chars[start..self.offset].iter().collect::<String>();
+        Ok(match text.as_str() {
+            "and" | "AND" => Token::And,
+            "or" | "OR" => Token::Or,
+            "not" | "NOT" => Token::Not,
+            "contains" | "CONTAINS" => Token::Op(QueryOp::Contains),
+            "true" => Token::Literal(Value::Bool(true)),
+            "false" => Token::Literal(Value::Bool(false)),
+            "null" => Token::Literal(Value::Null),
+            _ => {
+                let path = text.split('.').map(|a| a.to_string()).collect::<Vec<_>>();
+                if path.iter().any(|a| a.is_empty()) {
+                    self.found = text;
+                    self.token = Token::Literal(Value::Null);
+                    return Err(self.expected("a field name"));
+                }
+                Token::Field(path)
+            }
+        })
+    }
+
+    fn parse_or(&mut self) -> Result<QueryExpr, QueryError> {
+        let mut ret = self.parse_and()?;
+        while self.token == Token::Or {
+            self.advance()?;
+            let right = self.parse_and()?;
+            ret = QueryExpr::Or(Box::new(ret), Box::new(right));
+        }
+        Ok(ret)
+    }
+
+    fn parse_and(&mut self) -> Result<QueryExpr, QueryError> {
+        let mut ret = self.parse_not()?;
+        while self.token == Token::And {
+            self.advance()?;
+            let right = self.parse_not()?;
+            ret = QueryExpr::And(Box::new(ret), Box::new(right));
+        }
+        Ok(ret)
+    }
+
+    fn parse_not(&mut self) -> Result<QueryExpr, QueryError> {
+        match self.token {
+            Token::Not => {
+                self.advance()?;
+                Ok(QueryExpr::Not(Box::new(self.parse_not()?)))
+            }
+            Token::Open => {
+                self.advance()?;
+                let ret = self.parse_or()?;
+                if self.token != Token::Close {
+                    return Err(self.expected("')'"));
+                }
+                self.advance()?;
+                Ok(ret)
+            }
+            _ => self.parse_compare(),
+        }
+    }
+
+    fn parse_compare(&mut self) -> Result<QueryExpr, QueryError> {
+        let field = match &self.token {
+            Token::Field(a) => a.clone(),
+            _ => return Err(self.expected("a field, 'not' or '('")),
+        };
+        self.advance()?;
+
+        let op = match self.token {
+            Token::Op(a) => a,
+            _ => {
+                return Err(
+                    self.expected("a comparison (==, !=, <, <=, >, >= or contains)")
+                )
+            }
+        };
+        self.advance()?;
+
+        let value = match &self.token {
+            Token::Literal(a) => a.clone(),
+            _ => {
+                return Err(self.expected("a value (a quoted string, a number, true, false or null)"))
+            }
+        };
+        self.advance()?;
+
+        Ok(QueryExpr::Compare { field, op, value })
+    }
+}
+
+impl Dio {
+    /// Returns the rows of a type attached to the parent (or the rows
+    /// without a parent) that match the query expression
+    pub async fn query<T>(
+        self: &Arc<Self>,
+        parent_id: Option<PrimaryKey>,
+        expr: &str,
+    ) -> Result<Vec<Dao<T>>, QueryError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let query = Query::parse(expr)?;
+        self.query_ext(parent_id, &query).await
+    }
+
+    /// Returns the rows of a type attached to the parent (or the rows
+    /// without a parent) that match a query that was already parsed
+    pub async fn query_ext<T>(
+        self: &Arc<Self>,
+        parent_id: Option<PrimaryKey>,
+        query: &Query,
+    ) -> Result<Vec<Dao<T>>, QueryError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let type_name = std::any::type_name::<T>();
+        let (keys, scanned) = self.query_keys(parent_id, Some(type_name), query).await?;
+
+        // Rows of other types (or that this session can not read) are skipped
+        let mut ret = Vec::new();
+        for dao in self.load_many_ext::<T>(keys.into_iter(), true, true).await? {
+            if scanned && dao.row.type_name != type_name {
+                continue;
+            }
+            let row = serde_json::to_value(&dao.row.data)
+                .map_err(|err| {
+                    SerializationError::from(SerializationErrorKind::SerdeError(err.to_string()))
+                })?;
+            if query.is_match(&row) {
+                ret.push(dao);
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the rows attached to the parent (or the rows without a parent)
+    /// that match the query as JSON along with their keys, this works without
+    /// knowing their types but only for rows stored in a self describing
+    /// format (the others are skipped). The type name of the rows may be used
+    /// to limit the query to one type.
+    pub async fn query_rows(
+        self: &Arc<Self>,
+        parent_id: Option<PrimaryKey>,
+        type_name: Option<&str>,
+        query: &Query,
+    ) -> Result<Vec<(PrimaryKey, Value)>, QueryError> {
+        let (keys, _) = self.query_keys(parent_id, type_name, query).await?;
+
+        let mut ret = Vec::new();
+        for key in keys {
+            let row = match self.load_raw_typed(&key).await {
+                Ok(a) => a,
+                Err(err) => {
+                    trace!("query skipped row {} - {}", key, err);
+                    continue;
+                }
+            };
+            if type_name.is_some() && row.type_name.as_deref() != type_name {
+                continue;
+            }
+            let row = match row.format.data.deserialize_ref::<Value>(&row.data[..]) {
+                Ok(a) => a,
+                Err(err) => {
+                    trace!("query skipped row {} - {}", key, err);
+                    continue;
+                }
+            };
+            if query.is_match(&row) {
+                ret.push((key, row));
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the keys of the rows that the query needs to check, these
+    /// come from an index when one can be used or are otherwise all the rows
+    /// under the parent (in which case there may not be more of them than
+    /// the scan limit). Also returns if the rows are scanned (in which case
+    /// they may be of any type).
+    async fn query_keys(
+        self: &Arc<Self>,
+        parent_id: Option<PrimaryKey>,
+        type_name: Option<&str>,
+        query: &Query,
+    ) -> Result<(Vec<PrimaryKey>, bool), QueryError> {
+        if let Some((name, fields)) = type_name.and_then(indexed_fields) {
+            match query.index_lookup(fields) {
+                Some((field, IndexLookup::Equals(value))) => {
+                    debug!("query uses index {}.{}", name, field);
+                    let keys = self.find_index_keys(name, parent_id, field, &value).await?;
+                    return Ok((keys, false));
+                }
+                Some((field, IndexLookup::Range(lower, upper))) => {
+                    debug!("query uses index {}.{}", name, field);
+                    let keys = self
+                        .find_index_range_keys(name, parent_id, field, (lower, upper))
+                        .await?;
+                    return Ok((keys, false));
+                }
+                None => {}
+            }
+        }
+
+        let keys = match parent_id {
+            Some(parent_id) => {
+                let guard = self.multi.inside_async.read().await;
+                let pointers = &guard.chain.timeline.pointers;
+                pointers
+                    .all_keys()
+                    .filter(|key| {
+                        pointers
+                            .lookup_parent(key)
+                            .map(|a| a.vec.parent_id == parent_id)
+                            .unwrap_or(false)
+                    })
+                    .map(|a| a.clone())
+                    .collect::<Vec<_>>()
+            }
+            None => self.root_keys().await,
+        };
+        if keys.len() > query.scan_limit {
+            bail!(QueryErrorKind::ScanLimitExceeded(query.scan_limit));
+        }
+        debug!("query scans {} rows", keys.len());
+        Ok((keys, true))
+    }
+}
@@ -0,0 +1,270 @@
+#![allow(unused_imports)]
+use bytes::Bytes;
+use error_chain::bail;
+use fxhash::FxHashSet;
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use super::dio::*;
+use super::dio_mut::*;
+use super::row::*;
+use crate::crypto::AteHash;
+use crate::error::*;
+use crate::event::*;
+use crate::header::*;
+use crate::meta::*;
+use crate::spec::*;
+
+/// Allows a type to bypass serde when it is stored in or loaded from a
+/// chain, for instance when the payload is already encoded by another
+/// system (e.g. protobuf) and converting it would be wasted effort.
+///
+/// Types that implement this trait are stored with `store_ext` and loaded
+/// with `load_ext` while all other types continue to use serde.
+pub trait SerializerExt: Sized {
+    /// Format that is recorded with the row so that readers know how to decode it
+    fn format() -> SerializationFormat {
+        SerializationFormat::Raw
+    }
+
+    /// Name of the type that is recorded with the row
+    fn type_name() -> String {
+        std::any::type_name::<Self>().to_string()
+    }
+
+    fn encode(&self) -> Result<Bytes, SerializationError>;
+
+    fn decode(data: Bytes, format: SerializationFormat) -> Result<Self, SerializationError>;
+}
+
+/// Row data as it was stored in the chain (after it has been decrypted)
+#[derive(Debug, Clone)]
+pub struct RawRow {
+    pub data: Bytes,
+    pub format: MessageFormat,
+    /// Only present if the writer recorded the type name with the row
+    pub type_name: Option<String>,
+}
+
+impl Dio {
+    /// Loads the bytes of a row without deserializing them, the encryption of
+    /// the row is still removed using the keys in the session
+    pub async fn load_raw_typed(self: &Arc<Self>, key: &PrimaryKey) -> Result<RawRow, LoadError> {
+        {
+            let state = self.state.lock().unwrap();
+            if let Some((evt, _)) = state.cache_load.get(key) {
+                return RawRow::from_event(key, evt.as_ref());
+            }
+        }
+
+        let leaf = match self.multi.lookup_primary(key).await {
+            Some(a) => a,
+            None => bail!(LoadErrorKind::NotFound(key.clone())),
+        };
+        let evt = self.multi.load(leaf).await?;
+        let mut data = evt.data;
+        {
+            let session = self.session();
+            self.data_as_overlay(session.as_ref(), &mut data)?;
+            data.data_bytes = match data.data_bytes {
+                Some(bytes) => Some(self.multi.data_with_patches(
+                    &data.meta,
+                    data.format.data,
+                    bytes,
+                    &evt.base[..],
+                    session.as_ref(),
+                )?),
+                None => None,
+            };
+        }
+
+        let ret = RawRow::from_event(key, &data)?;
+        let mut state = self.state.lock().unwrap();
+        state.cache_load.insert(key.clone(), (Arc::new(data), leaf));
+        Ok(ret)
+    }
+
+    /// Loads a row that was stored by a type with its own serializer
+    pub async fn load_ext<D>(self: &Arc<Self>, key: &PrimaryKey) -> Result<D, LoadError>
+    where
+        D: SerializerExt,
+    {
+        let row = self.load_raw_typed(key).await?;
+        Ok(D::decode(row.data, row.format.data)?)
+    }
+}
+
+impl RawRow {
+    fn from_event(key: &PrimaryKey, evt: &EventStrongData) -> Result<RawRow, LoadError> {
+        let data = match &evt.data_bytes {
+            Some(a) => a.clone(),
+            None => bail!(LoadErrorKind::NotFound(key.clone())),
+        };
+        Ok(RawRow {
+            data,
+            format: evt.format,
+            type_name: evt.meta.get_type_name().map(|a| a.type_name.clone()),
+        })
+    }
+}
+
+impl DioMut {
+    /// Stores bytes that are already serialized as a row, the format and type
+    /// name are recorded with the row and the bytes are encrypted on commit
+    /// just like any other row
+    pub fn store_raw(
+        self: &Arc<Self>,
+        key: PrimaryKey,
+        data: Bytes,
+        type_name: &str,
+        format: SerializationFormat,
+    ) -> Result<(), SerializationError> {
+        let mut state = self.state.lock().unwrap();
+        if state.deleted.contains(&key) {
+            bail!(SerializationErrorKind::AlreadyDeleted(key));
+        }
+
+        let mut extra_meta = Vec::new();
+        extra_meta.push(CoreMetadata::Type(MetaType {
+            type_name: type_name.to_string(),
+        }));
+
+        let auth = MetaAuthorization {
+            read: ReadOption::Inherit,
+            write: WriteOption::Inherit,
+        };
+        state.dirty_header(RowHeader {
+            key,
+            parent: None,
+            auth: auth.clone(),
+        });
+        state.dirty_row(RowData {
+            key,
+            type_name: type_name.to_string(),
+            format: MessageFormat {
+                data: format,
+                meta: self.default_format().meta,
+            },
+            data_hash: AteHash::from_bytes(&data[..]),
+            data,
+            collections: FxHashSet::default(),
+            created: 0,
+            updated: 0,
+            extra_meta,
+            parent: None,
+            auth,
+            patch: None,
+            is_new: true,
+        });
+        Ok(())
+    }
+
+    /// Stores a row using the serializer of the type rather than serde
+    pub fn store_ext<D>(self: &Arc<Self>, key: PrimaryKey, data: &D) -> Result<(), SerializationError>
+    where
+        D: SerializerExt,
+    {
+        let bytes = data.encode()?;
+        self.store_raw(key, bytes, D::type_name().as_str(), D::format())
+    }
+
+    /// Loads the bytes of a row, rows that were stored in this transaction
+    /// but not yet committed are returned as they were stored
+    pub async fn load_raw_typed(self: &Arc<Self>, key: &PrimaryKey) -> Result<RawRow, LoadError> {
+        {
+            let state = self.state.lock().unwrap();
+            if state.deleted.contains(key) {
+                bail!(LoadErrorKind::AlreadyDeleted(key.clone()));
+            }
+            if let Some(row) = state.rows.get(key) {
+                return Ok(RawRow {
+                    data: row.data.clone(),
+                    format: row.format,
+                    type_name: Some(row.type_name.clone()),
+                });
+            }
+        }
+        self.dio.load_raw_typed(key).await
+    }
+
+    /// Loads a row that was stored by a type with its own serializer
+    pub async fn load_ext<D>(self: &Arc<Self>, key: &PrimaryKey) -> Result<D, LoadError>
+    where
+        D: SerializerExt,
+    {
+        let row = self.load_raw_typed(key).await?;
+        Ok(D::decode(row.data, row.format.data)?)
+    }
+
+    /// Rewrites a row whose latest version is a patch as a full row (keeping
+    /// its parent, authorization and collections) so that the patches it was
+    /// built from are no longer referenced and compact away, returns false
+    /// when the row is already a full row
+    pub async fn fold_patches(self: &Arc<Self>, key: &PrimaryKey) -> Result<bool, LoadError> {
+        let leaf = match self.multi.lookup_primary(key).await {
+            Some(a) => a,
+            None => bail!(LoadErrorKind::NotFound(key.clone())),
+        };
+        let evt = self.multi.load(leaf).await?;
+        if evt.data.meta.get_patch().is_none() {
+            return Ok(false);
+        }
+
+        let mut data = evt.data;
+        let bytes = {
+            let session = self.session();
+            self.dio.data_as_overlay(session.as_ref(), &mut data)?;
+            match data.data_bytes.take() {
+                Some(bytes) => self.multi.data_with_patches(
+                    &data.meta,
+                    data.format.data,
+                    bytes,
+                    &evt.base[..],
+                    session.as_ref(),
+                )?,
+                None => bail!(LoadErrorKind::MissingData),
+            }
+        };
+
+        let auth = data.meta.get_authorization().map(|a| a.clone()).unwrap_or_default();
+        let parent = data.meta.get_parent().map(|a| a.clone());
+        let type_name = data.meta.get_type_name().map(|a| a.type_name.clone());
+        let mut extra_meta: Vec<CoreMetadata> = data
+            .meta
+            .get_collection_policies()
+            .into_iter()
+            .map(CoreMetadata::CollectionPolicy)
+            .collect();
+        if let Some(type_name) = type_name.as_ref() {
+            extra_meta.push(CoreMetadata::Type(MetaType {
+                type_name: type_name.clone(),
+            }));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.deleted.contains(key) || state.rows.contains_key(key) {
+            return Ok(false);
+        }
+        state.dirty_header(RowHeader {
+            key: key.clone(),
+            parent: parent.clone(),
+            auth: auth.clone(),
+        });
+        state.dirty_row(RowData {
+            key: key.clone(),
+            type_name: type_name.unwrap_or_default(),
+            format: data.format,
+            data_hash: AteHash::from_bytes(&bytes[..]),
+            data: bytes,
+            collections: data.meta.get_collections().into_iter().collect(),
+            created: leaf.created,
+            updated: leaf.updated,
+            extra_meta,
+            parent,
+            auth,
+            patch: None,
+            is_new: false,
+        });
+        Ok(true)
+    }
+}
@@ -22,6 +22,8 @@ use crate::index::*;
 use crate::meta::*;
 use crate::spec::*;
 
+use super::patch::RowPatchBase;
+
 pub use super::vec::DaoVec;
 
 #[derive(Debug, Clone)]
@@ -40,6 +42,7 @@ pub(super) struct Row<D> {
     pub(super) data: D,
     pub(super) collections: FxHashSet<MetaCollection>,
     pub(super) extra_meta: Vec<CoreMetadata>,
+    pub(super) patch: Option<RowPatchBase>,
     pub(super) is_new: bool,
 }
 
@@ -57,6 +60,7 @@ where
             data: self.data.clone(),
             collections: self.collections.clone(),
             extra_meta: self.extra_meta.clone(),
+            patch: self.patch.clone(),
             is_new: self.is_new.clone(),
         }
     }
@@ -135,7 +139,13 @@ impl<D> Row<D> {
                         collections,
                         created,
                         updated,
-                        extra_meta: Vec::new(),
+                        extra_meta: evt
+                            .meta
+                            .get_collection_policies()
+                            .into_iter()
+                            .map(CoreMetadata::CollectionPolicy)
+                            .collect(),
+                        patch: None,
                         is_new: false,
                     },
                 ))
@@ -174,6 +184,7 @@ impl<D> Row<D> {
                 created: row.created,
                 updated: row.updated,
                 extra_meta: row.extra_meta.clone(),
+                patch: row.patch.clone(),
                 is_new: false,
             },
         ))
@@ -200,6 +211,7 @@ impl<D> Row<D> {
             created: self.created,
             updated: self.updated,
             extra_meta: self.extra_meta.clone(),
+            patch: self.patch.clone(),
             is_new: self.is_new,
         })
     }
@@ -218,5 +230,6 @@ pub(crate) struct RowData {
     pub extra_meta: Vec<CoreMetadata>,
     pub parent: Option<MetaParent>,
     pub auth: MetaAuthorization,
+    pub patch: Option<RowPatchBase>,
     pub is_new: bool,
 }
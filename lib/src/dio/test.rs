@@ -1,11 +1,14 @@
 #![allow(unused_imports)]
+use error_chain::bail;
 use serde::Deserialize;
 use serde::{de::DeserializeOwned, Serialize};
 use std::convert::*;
+use std::sync::Arc;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use crate::crypto::*;
 use crate::dio::*;
+use crate::error::*;
 use crate::prelude::*;
 
 #[cfg(test)]
@@ -86,7 +89,7 @@ async fn test_dio() -> Result<(), AteError> {
 
         // Write a value immediately from chain (this data will remain in the transaction)
         {
-            let dio = chain.dio_mut(&session).await;
+            let dio = chain.dio_mut(&session).await?;
             {
                 info!("storing data object 1");
                 let mut mock_dao = TestStructDao::default();
@@ -113,7 +116,7 @@ async fn test_dio() -> Result<(), AteError> {
 
         {
             info!("new DIO context");
-            let dio = chain.dio_mut(&session).await;
+            let dio = chain.dio_mut(&session).await?;
             {
                 // Load the object again which should load it from the cache
                 info!("loading data object 1");
@@ -183,7 +186,7 @@ async fn test_dio() -> Result<(), AteError> {
 
         {
             info!("new DIO context");
-            let dio = chain.dio_mut(&session).await;
+            let dio = chain.dio_mut(&session).await?;
 
             // The data we saved earlier should be accessible accross DIO scope boundaries
             info!("loading data object 1");
@@ -261,3 +264,264 @@ async fn test_dio() -> Result<(), AteError> {
 
     Ok(())
 }
+
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+struct TestRawDao {
+    val: u32,
+}
+
+#[cfg(test)]
+impl SerializerExt for TestRawDao {
+    fn encode(&self) -> Result<bytes::Bytes, SerializationError> {
+        Ok(bytes::Bytes::from(self.val.to_be_bytes().to_vec()))
+    }
+
+    fn decode(data: bytes::Bytes, format: SerializationFormat) -> Result<Self, SerializationError> {
+        if format != SerializationFormat::Raw || data.len() != 4 {
+            bail!(SerializationErrorKind::InvalidSerializationFormat);
+        }
+        let mut val = [0u8; 4];
+        val.copy_from_slice(&data[..]);
+        Ok(TestRawDao {
+            val: u32::from_be_bytes(val),
+        })
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_dio_raw() -> Result<(), AteError> {
+    crate::utils::bootstrap_test_env();
+
+    let write_key = PrivateSignKey::generate(KeySize::Bit192);
+    let read_key = EncryptKey::generate(KeySize::Bit192);
+    let mut session = AteSessionUser::new();
+    session.user.add_write_key(&write_key);
+    session.user.add_read_key(&read_key);
+
+    let chain_name = format!("test_dio_raw_{}", PrimaryKey::generate().to_string());
+    let mut mock_cfg = crate::conf::tests::mock_test_config();
+    let (chain, _builder) = crate::trust::create_test_chain(
+        &mut mock_cfg,
+        chain_name,
+        false,
+        false,
+        Some(write_key.as_public_key()),
+    )
+    .await;
+
+    let key1 = PrimaryKey::generate();
+    let key2 = PrimaryKey::generate();
+    {
+        let dio = chain.dio_mut(&session).await?;
+        dio.store_raw(
+            key1,
+            bytes::Bytes::from_static(b"opaque"),
+            "test.Opaque",
+            SerializationFormat::Raw,
+        )?;
+        dio.store_ext(key2, &TestRawDao { val: 42 })?;
+
+        // Uncommitted rows are returned as they were stored
+        let row = dio.load_raw_typed(&key1).await?;
+        assert_eq!(&row.data[..], b"opaque");
+        dio.commit().await?;
+    }
+
+    {
+        let dio = chain.dio(&session).await;
+        let row = dio.load_raw_typed(&key1).await?;
+        assert_eq!(&row.data[..], b"opaque");
+        assert_eq!(row.format.data, SerializationFormat::Raw);
+        assert_eq!(row.type_name.as_deref(), Some("test.Opaque"));
+
+        let dao: TestRawDao = dio.load_ext(&key2).await?;
+        assert_eq!(dao, TestRawDao { val: 42 });
+    }
+
+    chain.single().await.destroy().await.unwrap();
+    Ok(())
+}
+
+#[cfg(test)]
+struct TestMultiChainResolver {
+    session: AteSessionUser,
+    chains: Vec<(String, Arc<Chain>)>,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl MultiChainResolver for TestMultiChainResolver {
+    async fn open(&self, name: &str) -> Result<Arc<DioMut>, MultiChainError> {
+        match self.chains.iter().filter(|a| a.0 == name).next() {
+            Some((_, chain)) => Ok(chain.dio_mut(&self.session).await?),
+            None => bail!(MultiChainErrorKind::UnknownParticipant(name.to_string())),
+        }
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_multi_chain_crash_recovery() -> Result<(), AteError> {
+    crate::utils::bootstrap_test_env();
+
+    let write_key = PrivateSignKey::generate(KeySize::Bit192);
+    let mut session = AteSessionUser::new();
+    session.user.add_write_key(&write_key);
+
+    let mut mock_cfg = crate::conf::tests::mock_test_config();
+    let (chain_a, _builder_a) = crate::trust::create_test_chain(
+        &mut mock_cfg,
+        format!("test_multi_chain_a_{}", PrimaryKey::generate().to_string()),
+        true,
+        false,
+        Some(write_key.as_public_key()),
+    )
+    .await;
+    let (chain_b, _builder_b) = crate::trust::create_test_chain(
+        &mut mock_cfg,
+        format!("test_multi_chain_b_{}", PrimaryKey::generate().to_string()),
+        true,
+        false,
+        Some(write_key.as_public_key()),
+    )
+    .await;
+    let resolver = TestMultiChainResolver {
+        session: session.clone(),
+        chains: vec![
+            ("a".to_string(), chain_a.clone()),
+            ("b".to_string(), chain_b.clone()),
+        ],
+    };
+
+    // The intents are attached to a row on the first chain
+    let coordinator = chain_a.dio_mut(&session).await?;
+    let parent = coordinator.store(TestStructDao::default())?.key().clone();
+    coordinator.commit().await?;
+
+    // A transaction that completes leaves no intent behind
+    let key_a1;
+    let key_b1;
+    {
+        let dio_a = chain_a.dio_mut(&session).await?;
+        let dio_b = chain_b.dio_mut(&session).await?;
+        key_a1 = dio_a.store(TestEnumDao::Blah1)?.key().clone();
+        key_b1 = dio_b.store(TestEnumDao::Blah2(1))?.key().clone();
+
+        let mut trans = MultiChainTransaction::new(&coordinator, &parent);
+        trans.add("a", &dio_a);
+        trans.add("b", &dio_b);
+        trans.commit().await?;
+    }
+    {
+        let dio = chain_a.dio_mut(&session).await?;
+        assert!(dio.exists(&key_a1).await);
+        assert_eq!(MultiChainTransaction::recover(&dio, &parent, &resolver).await?, 0);
+        let dio = chain_b.dio(&session).await;
+        assert!(dio.exists(&key_b1).await);
+    }
+
+    // Crash after the first chain committed but before the second did
+    let key_a2;
+    let key_b2;
+    {
+        let dio_a = chain_a.dio_mut(&session).await?;
+        let dio_b = chain_b.dio_mut(&session).await?;
+        key_a2 = dio_a.store(TestEnumDao::Blah3("crash".to_string()))?.key().clone();
+        key_b2 = dio_b.store(TestEnumDao::Blah4)?.key().clone();
+
+        let mut trans = MultiChainTransaction::new(&coordinator, &parent);
+        trans.add("a", &dio_a);
+        trans.add("b", &dio_b);
+        trans.crash_after = Some(1);
+        trans
+            .commit()
+            .await
+            .expect_err("the commit should have crashed");
+
+        // The process died so the second chain never saw its changes
+        dio_b.cancel();
+    }
+    {
+        let dio = chain_a.dio(&session).await;
+        assert!(dio.exists(&key_a2).await);
+    }
+
+    // Recovery rolls back the chain that was committed
+    {
+        let dio = chain_a.dio_mut(&session).await?;
+        assert_eq!(MultiChainTransaction::recover(&dio, &parent, &resolver).await?, 1);
+    }
+    {
+        let dio = chain_a.dio(&session).await;
+        assert!(dio.exists(&key_a1).await);
+        assert!(dio.exists(&key_a2).await == false);
+        let dio = chain_b.dio(&session).await;
+        assert!(dio.exists(&key_b2).await == false);
+    }
+    {
+        let dio = chain_a.dio_mut(&session).await?;
+        assert_eq!(MultiChainTransaction::recover(&dio, &parent, &resolver).await?, 0);
+    }
+
+    chain_a.single().await.destroy().await.unwrap();
+    chain_b.single().await.destroy().await.unwrap();
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_collection_retention() -> Result<(), AteError> {
+    crate::utils::bootstrap_test_env();
+
+    let write_key = PrivateSignKey::generate(KeySize::Bit192);
+    let mut session = AteSessionUser::new();
+    session.user.add_write_key(&write_key);
+
+    let mut mock_cfg = crate::conf::tests::mock_test_config();
+    let (chain, _builder) = crate::trust::create_test_chain(
+        &mut mock_cfg,
+        format!("test_collection_retention_{}", PrimaryKey::generate().to_string()),
+        true,
+        false,
+        Some(write_key.as_public_key()),
+    )
+    .await;
+
+    // The collection only keeps the last three entries
+    let parent = {
+        let dio = chain.dio_mut(&session).await?;
+        let mut dao = dio.store(TestStructDao::default())?;
+        let vec_id = dao.inner.vec_id();
+        dao.set_collection_policy(MetaCollectionPolicy {
+            collection_id: vec_id,
+            max_entries: Some(3),
+            max_age: None,
+        })?;
+        dio.commit().await?;
+        dao.key().clone()
+    };
+
+    let mut pushed = Vec::new();
+    for n in 0..5u32 {
+        let dio = chain.dio_mut(&session).await?;
+        let mut dao = dio.load::<TestStructDao>(&parent).await?;
+        let child = dao.as_mut().inner.push(TestEnumDao::Blah2(n))?;
+        pushed.push(child.key().clone());
+        dio.commit().await?;
+    }
+
+    // The oldest entries are tombstoned as the new ones are pushed
+    let dio = chain.dio(&session).await;
+    let dao = dio.load::<TestStructDao>(&parent).await?;
+    assert!(dao.collection_policy(dao.inner.vec_id()).is_some());
+    let keys = dio.children_keys(parent.clone(), dao.inner.vec_id()).await?;
+    assert_eq!(keys.len(), 3);
+    for key in pushed.iter().skip(2) {
+        assert!(keys.contains(key));
+    }
+    assert_eq!(dio.exists(&pushed[0]).await, false);
+    assert_eq!(dio.exists(&pushed[1]).await, false);
+    Ok(())
+}
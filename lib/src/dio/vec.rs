@@ -236,6 +236,32 @@ impl<D> DaoVec<D> {
         Ok(Iter::new(children))
     }
 
+    /// Loads all the committed rows in this collection with the decryption
+    /// and deserialization spread over the worker pool (at most `max_concurrency`
+    /// rows at a time). Each row carries its own result so that one bad row
+    /// does not fail the whole collection.
+    pub async fn load_all_parallel(
+        &self,
+        max_concurrency: usize,
+    ) -> Result<Vec<Result<Dao<D>, LoadError>>, LoadError>
+    where
+        D: DeserializeOwned + Send + 'static,
+    {
+        let ret = match &self.state {
+            DaoVecState::Unsaved => vec![],
+            DaoVecState::Saved(parent_id) => {
+                let dio = match self.dio() {
+                    Some(a) => a,
+                    None => bail!(LoadErrorKind::WeakDio),
+                };
+
+                let keys = dio.children_keys(parent_id.clone(), self.vec_id).await?;
+                dio.load_many_parallel(keys, max_concurrency).await
+            }
+        };
+        Ok(ret)
+    }
+
     pub async fn iter_mut(&mut self) -> Result<IterMut<D>, LoadError>
     where
         D: Serialize + DeserializeOwned,
@@ -3,6 +3,7 @@ use cooked_waker::*;
 use fxhash::FxHashMap;
 use once_cell::sync::Lazy;
 use pin_project_lite::pin_project;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::future::Future;
@@ -17,26 +18,273 @@ use std::time::Duration;
 use std::time::Instant;
 use tokio::sync::broadcast;
 use tokio::sync::oneshot;
-use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use tracing::{debug, error, info, instrument, span, trace, warn, Instrument, Level};
+
+/// Blocking tasks that are still running after this long are reported along
+/// with where they were spawned (and reported again each time it doubles)
+pub const DEFAULT_BLOCKING_WARN_THRESHOLD: Duration = Duration::from_secs(5);
+
+static BLOCKING_WARN_THRESHOLD_MS: AtomicU64 =
+    AtomicU64::new(DEFAULT_BLOCKING_WARN_THRESHOLD.as_millis() as u64);
+
+static TASK_ID_SEED: AtomicU64 = AtomicU64::new(1);
+
+/// Tasks that were spawned through the engine and have not yet finished
+static TASKS: Lazy<Mutex<FxHashMap<u64, Arc<TaskEntry>>>> =
+    Lazy::new(|| Mutex::new(FxHashMap::default()));
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskKind {
+    Async,
+    Blocking,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    /// Waiting to be woken up (or for a thread in the case of blocking tasks)
+    Idle,
+    /// Being polled or running on a blocking thread
+    Running,
+}
+
+/// Snapshot of a task that is still alive, see `TaskEngine::dump_tasks`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TaskInfo {
+    pub id: u64,
+    pub name: String,
+    /// Source location of the code that spawned the task
+    pub site: String,
+    pub kind: TaskKind,
+    pub state: TaskState,
+    pub age_ms: u64,
+    pub polls: u64,
+}
+
+impl std::fmt::Display for TaskInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:>6} {:<8} {:<7} {:>10}ms {:>8} {} ({})",
+            self.id,
+            format!("{:?}", self.kind).to_lowercase(),
+            format!("{:?}", self.state).to_lowercase(),
+            self.age_ms,
+            self.polls,
+            self.name,
+            self.site
+        )
+    }
+}
+
+struct TaskEntry {
+    id: u64,
+    name: String,
+    site: &'static std::panic::Location<'static>,
+    kind: TaskKind,
+    started: Instant,
+    running: AtomicBool,
+    polls: AtomicU64,
+}
+
+impl TaskEntry {
+    fn info(&self) -> TaskInfo {
+        TaskInfo {
+            id: self.id,
+            name: self.name.clone(),
+            site: self.site.to_string(),
+            kind: self.kind,
+            state: match self.running.load(Ordering::Relaxed) {
+                true => TaskState::Running,
+                false => TaskState::Idle,
+            },
+            age_ms: self.started.elapsed().as_millis() as u64,
+            polls: self.polls.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Keeps a task in the registry for as long as it is alive
+struct TaskGuard {
+    entry: Arc<TaskEntry>,
+}
+
+impl TaskGuard {
+    fn register(
+        name: &str,
+        kind: TaskKind,
+        site: &'static std::panic::Location<'static>,
+    ) -> TaskGuard {
+        let entry = Arc::new(TaskEntry {
+            id: TASK_ID_SEED.fetch_add(1, Ordering::Relaxed),
+            name: name.to_string(),
+            site,
+            kind,
+            started: Instant::now(),
+            running: AtomicBool::new(false),
+            polls: AtomicU64::new(0),
+        });
+        let mut guard = TASKS.lock().unwrap();
+        guard.insert(entry.id, Arc::clone(&entry));
+        TaskGuard { entry }
+    }
+
+    /// Span that the task runs under, it is a child of the span that was
+    /// current when the task was spawned so that the work keeps its parentage
+    #[cfg(feature = "enable_task_spans")]
+    fn span(&self) -> tracing::Span {
+        span!(
+            Level::INFO,
+            "task",
+            task.id = self.entry.id,
+            task.name = self.entry.name.as_str(),
+            task.kind = ?self.entry.kind,
+            spawn.location = %self.entry.site
+        )
+    }
+
+    #[cfg(not(feature = "enable_task_spans"))]
+    fn span(&self) -> tracing::Span {
+        tracing::Span::current()
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        let mut guard = TASKS.lock().unwrap();
+        guard.remove(&self.entry.id);
+    }
+}
+
+pin_project! {
+    /// Records when a task is being polled (and how often)
+    struct TrackedTask<F> {
+        #[pin]
+        inner: F,
+        guard: TaskGuard,
+    }
+}
+
+impl<F> Future for TrackedTask<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let entry = &this.guard.entry;
+        entry.polls.fetch_add(1, Ordering::Relaxed);
+        entry.running.store(true, Ordering::Relaxed);
+        let ret = this.inner.poll(cx);
+        entry.running.store(false, Ordering::Relaxed);
+        ret
+    }
+}
+
+/// Name of a task that was not given one, this is the name of the span
+/// that it was spawned from
+fn current_task_name() -> &'static str {
+    tracing::Span::current()
+        .metadata()
+        .map(|a| a.name())
+        .unwrap_or("task")
+}
 
 pub struct TaskEngine {}
 
 impl TaskEngine {
+    /// Spawns a task that runs under the current span (so that its work is
+    /// attributed to the code that spawned it)
+    #[track_caller]
     pub fn spawn<T>(task: T) -> tokio::task::JoinHandle<T::Output>
     where
         T: Future + Send + 'static,
         T::Output: Send + 'static,
     {
-        tokio::spawn(task)
+        Self::spawn_named(current_task_name(), task)
     }
 
-    pub async fn spawn_blocking<F, R>(f: F) -> R
+    /// Spawns a task with the name that it is listed under by `dump_tasks`
+    #[track_caller]
+    pub fn spawn_named<T>(name: &str, task: T) -> tokio::task::JoinHandle<T::Output>
+    where
+        T: Future + Send + 'static,
+        T::Output: Send + 'static,
+    {
+        let guard = TaskGuard::register(name, TaskKind::Async, std::panic::Location::caller());
+        let span = guard.span();
+        tokio::spawn(TrackedTask { inner: task, guard }.instrument(span))
+    }
+
+    /// Runs a closure on a thread that is allowed to block, while it is
+    /// running longer than the warning threshold a warning is logged with
+    /// the place that it was spawned from
+    #[track_caller]
+    pub fn spawn_blocking<F, R>(f: F) -> impl Future<Output = R>
     where
         F: FnOnce() -> R + Send + 'static,
         R: Send + 'static,
     {
-        let ret = tokio::task::spawn_blocking(f).await;
-        ret.unwrap()
+        let name = current_task_name();
+        let site = std::panic::Location::caller();
+        let guard = TaskGuard::register(name, TaskKind::Blocking, site);
+        let span = guard.span();
+        let mut handle = tokio::task::spawn_blocking(move || {
+            let _span = span.enter();
+            guard.entry.polls.fetch_add(1, Ordering::Relaxed);
+            guard.entry.running.store(true, Ordering::Relaxed);
+            let ret = f();
+            drop(guard);
+            ret
+        });
+
+        async move {
+            let started = Instant::now();
+            let mut threshold = Self::blocking_warn_threshold();
+            loop {
+                let wait = match threshold {
+                    Some(a) => a.saturating_sub(started.elapsed()),
+                    None => return (&mut handle).await.unwrap(),
+                };
+                match timeout(wait, &mut handle).await {
+                    Ok(ret) => return ret.unwrap(),
+                    Err(_) => {
+                        warn!(
+                            "blocking task ({}) spawned at {} is still running after {}ms",
+                            name,
+                            site,
+                            started.elapsed().as_millis()
+                        );
+                        threshold = threshold.map(|a| a * 2);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lists the tasks that were spawned through the engine and are still
+    /// alive, the oldest first (stuck tasks tend to be near the top)
+    pub fn dump_tasks() -> Vec<TaskInfo> {
+        let mut ret = {
+            let guard = TASKS.lock().unwrap();
+            guard.values().map(|a| a.info()).collect::<Vec<_>>()
+        };
+        ret.sort_by(|a, b| b.age_ms.cmp(&a.age_ms).then(a.id.cmp(&b.id)));
+        ret
+    }
+
+    /// Changes how long a blocking task may run before a warning is logged
+    /// (None turns the warnings off)
+    pub fn set_blocking_warn_threshold(threshold: Option<Duration>) {
+        let ms = threshold.map(|a| a.as_millis().max(1) as u64).unwrap_or(0);
+        BLOCKING_WARN_THRESHOLD_MS.store(ms, Ordering::Relaxed);
+    }
+
+    pub fn blocking_warn_threshold() -> Option<Duration> {
+        match BLOCKING_WARN_THRESHOLD_MS.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
     }
 }
 
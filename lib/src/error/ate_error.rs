@@ -10,11 +10,16 @@ error_chain! {
         CommitError(super::CommitError, super::CommitErrorKind);
         CommsError(super::CommsError, super::CommsErrorKind);
         CompactError(super::CompactError, super::CompactErrorKind);
+        ConfigError(super::ConfigError, super::ConfigErrorKind);
         CryptoError(super::CryptoError, super::CryptoErrorKind);
+        InclusionError(super::InclusionError, super::InclusionErrorKind);
         InvokeError(super::InvokeError, super::InvokeErrorKind);
+        KvError(super::KvError, super::KvErrorKind);
         LintError(super::LintError, super::LintErrorKind);
         LoadError(super::LoadError, super::LoadErrorKind);
         LockError(super::LockError, super::LockErrorKind);
+        MultiChainError(super::MultiChainError, super::MultiChainErrorKind);
+        QueryError(super::QueryError, super::QueryErrorKind);
         SerializationError(super::SerializationError, super::SerializationErrorKind);
         SinkError(super::SinkError, super::SinkErrorKind);
         TimeError(super::TimeError, super::TimeErrorKind);
@@ -36,6 +41,14 @@ error_chain! {
             description("service error"),
             display("service error - {}", err)
         }
+        Reentrant {
+            description("blocking call was made from within an async context"),
+            display("blocking call was made from within an async context (use the async API instead)")
+        }
+        Timeout(duration: std::time::Duration) {
+            description("the operation timed out"),
+            display("the operation timed out after {}ms", duration.as_millis())
+        }
     }
 }
 
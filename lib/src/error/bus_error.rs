@@ -9,12 +9,17 @@ error_chain! {
         SerializationError(super::SerializationError, super::SerializationErrorKind);
         LockError(super::LockError, super::LockErrorKind);
         TransformError(super::TransformError, super::TransformErrorKind);
+        CommitError(super::CommitError, super::CommitErrorKind);
     }
     errors {
         ReceiveError(err: String) {
             description("failed to receive event from bus due to an internal error"),
             display("failed to receive event from bus due to an internal error: '{}'", err),
         }
+        Lagged(dropped: u64) {
+            description("the bus fell behind the chain and some of the events were dropped"),
+            display("the bus fell behind the chain and {} events were dropped", dropped),
+        }
         ChannelClosed {
             description("failed to receive event from bus as the channel is closed"),
             display("failed to receive event from bus as the channel is closed"),
@@ -23,6 +28,10 @@ error_chain! {
             description("you must save the parent object before attempting to initiate a bus from this vector"),
             display("you must save the parent object before attempting to initiate a bus from this vector"),
         }
+        UnknownEvent(hash: String) {
+            description("the acknowledged event was not received from this subscription"),
+            display("the acknowledged event ({}) was not received from this subscription", hash),
+        }
         WeakDio {
             description("the dio that created this object has gone out of scope"),
             display("the dio that created this object has gone out of scope"),
@@ -68,6 +68,53 @@ error_chain! {
             description("failed to create chain-of-trust due to a DNS error"),
             display("failed to create chain-of-trust due to a DNS error - {}", err),
         }
+        AliasLoop(alias: String) {
+            description("failed to resolve the chain alias as the aliases form a loop"),
+            display("failed to resolve the chain alias ({}) as the aliases form a loop", alias),
+        }
+        AliasShadowsChain(alias: String) {
+            description("failed to register the chain alias as a chain with the same key already exists"),
+            display("failed to register the chain alias ({}) as a chain with the same key already exists", alias),
+        }
+        TooManyEtherealChains(route: String, max: usize) {
+            description("failed to create chain-of-trust as the route is hosting the maximum number of ethereal chains"),
+            display("failed to create chain-of-trust as the route ({}) is hosting the maximum number of ethereal chains ({}) and none of them are idle", route, max),
+        }
+        MigrationInProgress(chain: String) {
+            description("failed to migrate the chain as a migration of it is already in progress"),
+            display("failed to migrate the chain ({}) as a migration of it is already in progress", chain),
+        }
+        MigrationNotFound(chain: String) {
+            description("the chain is not being migrated by this root"),
+            display("the chain ({}) is not being migrated by this root", chain),
+        }
+        MigrationNotReady(chain: String, phase: String) {
+            description("the migration of the chain can not cut over yet"),
+            display("the migration of the chain ({}) can not cut over while it is {}", chain, phase),
+        }
+        MigrationFailed(err: String) {
+            description("failed to migrate the chain"),
+            display("failed to migrate the chain - {}", err),
+        }
+        ChainMigrated(chain: String, destination: String) {
+            description("the chain has migrated to another root"),
+            display("the chain ({}) has migrated to another root ({})", chain, destination),
+        }
+        ArchiveInvalid(path: String, reason: String) {
+            description("failed to open the chain archive as it is invalid"),
+            display("failed to open the chain archive ({}) as it is invalid - {}", path, reason),
+        }
+        ArchiveTruncated(path: String, reason: String) {
+            description("failed to open the chain archive as it is truncated or incomplete"),
+            display("failed to open the chain archive ({}) as it is truncated or incomplete - {}", path, reason),
+        }
+        LogLocked(path: String, pid: Option<u32>) {
+            description("failed to open the redo log as another process has it locked"),
+            display("failed to open the redo log ({}) as another process has it locked{}", path, match pid {
+                Some(pid) => format!(" (pid={})", pid),
+                None => String::new(),
+            }),
+        }
         InternalError(err: String) {
             description("internal error"),
             display("{}", err),
@@ -75,6 +122,21 @@ error_chain! {
     }
 }
 
+impl ChainCreationError {
+    /// Errors that occur while opening the redo log are serialization errors,
+    /// when it failed as the log is locked by another process that is
+    /// reported as its own kind of error instead
+    pub fn from_redo_log(err: super::SerializationError) -> ChainCreationError {
+        #[cfg(feature = "enable_local_fs")]
+        if let super::SerializationErrorKind::IO(io) = err.kind() {
+            if let Some(locked) = crate::redo::LogLockedError::from_io(io) {
+                return ChainCreationErrorKind::LogLocked(locked.path.clone(), locked.pid).into();
+            }
+        }
+        err.into()
+    }
+}
+
 #[cfg(feature = "enable_dns")]
 impl From<::trust_dns_proto::error::ProtoError> for ChainCreationError {
     fn from(err: ::trust_dns_proto::error::ProtoError) -> ChainCreationError {
@@ -0,0 +1,86 @@
+use serde::*;
+
+/// Stable identifier for a particular kind of error (e.g. "COMMS_0014") that
+/// does not change between releases even when the message text does, hence
+/// callers should match on the code rather than on the displayed text
+pub trait ErrorCode {
+    fn code(&self) -> &'static str;
+}
+
+/// Entry in the table of error codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ErrorCodeInfo {
+    pub code: &'static str,
+    pub kind: &'static str,
+    pub variant: &'static str,
+}
+
+impl std::fmt::Display for ErrorCodeInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {}::{}", self.code, self.kind, self.variant)
+    }
+}
+
+/// JSON body that HTTP surfaces return when a request fails
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorEnvelope {
+    pub code: String,
+    pub error: String,
+}
+
+impl ErrorEnvelope {
+    pub fn new<E>(err: &E) -> ErrorEnvelope
+    where
+        E: ErrorCode + std::fmt::Display,
+    {
+        ErrorEnvelope {
+            code: err.code().to_string(),
+            error: err.to_string(),
+        }
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+}
+
+/// Assigns stable error codes to the variants of an error kind generated by
+/// error_chain, codes must never be reused or renumbered once released
+/// (variants that are not listed fall back to the "_0000" code)
+#[macro_export]
+macro_rules! error_codes {
+    ($kind:ident, $domain:literal, { $($num:literal => $variant:ident,)* }) => {
+        impl $crate::error::ErrorCode for $kind {
+            fn code(&self) -> &'static str {
+                #[allow(unreachable_patterns)]
+                match self {
+                    $( $kind::$variant { .. } => concat!($domain, "_", $num), )*
+                    _ => concat!($domain, "_0000"),
+                }
+            }
+        }
+
+        impl $kind {
+            /// Table of the stable codes assigned to this kind of error
+            pub fn error_codes() -> &'static [$crate::error::ErrorCodeInfo] {
+                &[
+                    $(
+                        $crate::error::ErrorCodeInfo {
+                            code: concat!($domain, "_", $num),
+                            kind: stringify!($kind),
+                            variant: stringify!($variant),
+                        },
+                    )*
+                ]
+            }
+        }
+    };
+}
+
+/// Returns the table of all the error codes that are defined by this crate
+pub fn all() -> Vec<ErrorCodeInfo> {
+    let mut ret = Vec::new();
+    ret.extend_from_slice(super::CommsErrorKind::error_codes());
+    ret.extend_from_slice(super::CommitErrorKind::error_codes());
+    ret
+}
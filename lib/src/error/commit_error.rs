@@ -1,4 +1,5 @@
 use error_chain::error_chain;
+use crate::error_codes;
 
 error_chain! {
     types {
@@ -12,6 +13,7 @@ error_chain! {
         TimeError(super::TimeError, super::TimeErrorKind);
         SinkError(super::SinkError, super::SinkErrorKind);
         SerializationError(super::SerializationError, super::SerializationErrorKind);
+        LoadError(super::LoadError, super::LoadErrorKind);
     }
     foreign_links {
         IO(::tokio::io::Error);
@@ -19,35 +21,80 @@ error_chain! {
     errors {
         Aborted {
             description("the transaction aborted before it could be completed"),
-            display("the transaction aborted before it could be completed"),
+            display("COMMIT_0001: the transaction aborted before it could be completed"),
         }
         Timeout(elapsed: String) {
             description("the transaction has timed out"),
-            display("the transaction has timed out after {}", elapsed),
+            display("COMMIT_0002: the transaction has timed out after {}", elapsed),
         }
         ReadOnly {
             description("the chain of trust is currently read only"),
-            display("the chain of trust is currently read only")
+            display("COMMIT_0003: the chain of trust is currently read only")
+        }
+        ChainReadOnly(key: String) {
+            description("the chain is read-only so it can not be modified"),
+            display("COMMIT_0004: the chain ({}) is read-only so it can not be modified", key),
         }
         LockError(err: super::CommsErrorKind) {
             description("failed to lock the data due to an error in communication"),
-            display("failed to lock the data due to an error in communication - {}", err.to_string()),
+            display("COMMIT_0005: failed to lock the data due to an error in communication - {}", err.to_string()),
         }
         NewRootsAreDisabled {
             description("new root objects are currently not allowed for this chain"),
-            display("new root objects are currently not allowed for this chain"),
+            display("COMMIT_0006: new root objects are currently not allowed for this chain"),
+        }
+        SessionRestricted(err: String) {
+            description("the transaction was rejected as it falls outside of the restrictions placed on the session"),
+            display("COMMIT_0007: the transaction was rejected as it falls outside of the restrictions placed on the session - {}", err),
         }
         PipeError(err: String) {
             description("failed to commit the data due to an error receiving the result in the interprocess pipe"),
-            display("failed to commit the data due to an error receiving the result in the interprocess pipe - {}", err.to_string()),
+            display("COMMIT_0008: failed to commit the data due to an error receiving the result in the interprocess pipe - {}", err.to_string()),
         }
         RootError(err: String) {
             description("failed to commit the data due to an error at the root server while processing the events"),
-            display("failed to commit the data due to an error at the root server while processing the events - {}", err.to_string()),
+            display("COMMIT_0009: failed to commit the data due to an error at the root server while processing the events - {}", err.to_string()),
+        }
+        QuorumNotReached(acks: usize, required: usize, failed: String) {
+            description("the commit was not acknowledged by enough of the replica roots"),
+            display("COMMIT_0010: the commit was acknowledged by {} of the {} required replica roots (failed: {})", acks, required, failed),
+        }
+        ChainMigrated(destination: String) {
+            description("the commit was rejected as the chain has migrated to another root"),
+            display("COMMIT_0011: the commit was rejected as the chain has migrated to another root ({})", destination),
+        }
+        PatchConflict(key: String) {
+            description("the commit was rejected as the row was changed by someone else since the version that was patched"),
+            display("COMMIT_0012: the commit was rejected as row {} was changed by someone else since the version that was patched", key),
+        }
+        TooLarge(size: u64, limit: u64) {
+            description("the commit was rejected as it holds more data than the root accepts from a connection at once"),
+            display("COMMIT_0013: the commit was rejected as it holds more data ({} bytes) than the root accepts from a connection at once ({} bytes)", size, limit),
+        }
+        QuotaExceeded(used: u64, limit: u64) {
+            description("the commit was rejected as the chain would exceed its storage quota"),
+            display("COMMIT_0014: the commit was rejected as the chain would exceed its storage quota (used {} of {} bytes)", used, limit),
         }
     }
 }
 
+error_codes!(CommitErrorKind, "COMMIT", {
+    "0001" => Aborted,
+    "0002" => Timeout,
+    "0003" => ReadOnly,
+    "0004" => ChainReadOnly,
+    "0005" => LockError,
+    "0006" => NewRootsAreDisabled,
+    "0007" => SessionRestricted,
+    "0008" => PipeError,
+    "0009" => RootError,
+    "0010" => QuorumNotReached,
+    "0011" => ChainMigrated,
+    "0012" => PatchConflict,
+    "0013" => TooLarge,
+    "0014" => QuotaExceeded,
+});
+
 impl<T> From<tokio::sync::mpsc::error::SendError<T>> for CommitError {
     fn from(err: tokio::sync::mpsc::error::SendError<T>) -> CommitError {
         CommitErrorKind::PipeError(err.to_string()).into()
@@ -4,6 +4,7 @@ use serde_json::Error as JsonError;
 use tokio::sync::mpsc;
 
 use crate::crypto::KeySize;
+use crate::error_codes;
 
 error_chain! {
     types {
@@ -22,90 +23,158 @@ error_chain! {
     errors {
         SendError(err: String) {
             description("sending error while processing communication"),
-            display("sending error while processing communication - {}", err),
+            display("COMMS_0007: sending error while processing communication - {}", err),
         }
         ReceiveError(err: String) {
             description("receiving error while processing communication"),
-            display("receiving error while processing communication - {}", err),
+            display("COMMS_0008: receiving error while processing communication - {}", err),
         }
         MissingCertificate {
             description("the server requires wire encryption but you did not supply a certificate"),
-            display("the server requires wire encryption but you did not supply a certificate"),
+            display("COMMS_0009: the server requires wire encryption but you did not supply a certificate"),
         }
         CertificateTooWeak(needed: KeySize, actual: KeySize) {
             description("the server requires strong wire encryption then available in the certificate you supplied"),
-            display("the server requires strong wire encryption({}) then available in the certificate you supplied({})", needed, actual),
+            display("COMMS_0010: the server requires strong wire encryption({}) then available in the certificate you supplied({})", needed, actual),
         }
         ServerCertificateValidation {
             description("the server certificate failed the clients validation check"),
-            display("the server certificate failed the clients validation check"),
+            display("COMMS_0011: the server certificate failed the clients validation check"),
         }
         ServerEncryptionWeak {
             description("the server encryption strength is too weak"),
-            display("the server encryption strength is too weak"),
+            display("COMMS_0012: the server encryption strength is too weak"),
         }
         RedirectNotSupported {
             description("redirecting to another address is not supported by this process")
-            display("redirecting to another address is not supported by this process")
+            display("COMMS_0013: redirecting to another address is not supported by this process")
         }
         Disconnected {
             description("channel has been disconnected")
-            display("channel has been disconnected")
+            display("COMMS_0014: channel has been disconnected")
         }
         ReadOnly {
             description("the chain is currently read-only")
-            display("the chain is currently read-only")
+            display("COMMS_0015: the chain is currently read-only")
         }
         Timeout {
             description("io timeout")
-            display("io timeout")
+            display("COMMS_0016: io timeout")
         }
         NoAddress {
             description("no address to connect to")
-            display("no address to connect to")
+            display("COMMS_0017: no address to connect to")
         }
         Refused {
             description("connection was refused by the destination address")
-            display("connection was refused by the destination address")
+            display("COMMS_0018: connection was refused by the destination address")
         }
         ShouldBlock {
             description("operation should have blocked but it didnt")
-            display("operation should have blocked but it didnt")
+            display("COMMS_0019: operation should have blocked but it didnt")
         }
         InvalidDomainName {
             description("the supplied domain name is not valid")
-            display("the supplied domain name is not valid")
+            display("COMMS_0020: the supplied domain name is not valid")
         }
         RequiredExplicitNodeId {
             description("ate is unable to determine the node_id of this root and thus you must explicily specify it in cfg")
-            display("ate is unable to determine the node_id of this root and thus you must explicily specify it in cfg")
+            display("COMMS_0021: ate is unable to determine the node_id of this root and thus you must explicily specify it in cfg")
         }
         ListenAddressInvalid(addr: String) {
             description("could not listen on the address as it is not a valid IPv4/IPv6 address"),
-            display("could not listen on the address ({}) as it is not a valid IPv4/IPv6 address", addr),
+            display("COMMS_0022: could not listen on the address ({}) as it is not a valid IPv4/IPv6 address", addr),
         }
         NotYetSubscribed {
             description("attempted to perform a chain operation on a connection that is not yet subscribed to chain")
+            display("COMMS_0023: attempted to perform a chain operation on a connection that is not yet subscribed to chain")
         }
         FatalError(err: String) {
             description("error at the root server while processing communication which has terminated the connection"),
-            display("error at the root server while processing communication which has terminated the connection - {}", err),
+            display("COMMS_0024: error at the root server while processing communication which has terminated the connection - {}", err),
         }
         InternalError(err: String) {
             description("internal comms error"),
-            display("internal comms error - {}", err),
+            display("COMMS_0025: internal comms error - {}", err),
         }
         WebSocketError(err: String) {
             description("web socket error"),
-            display("web socket error - {}", err),
+            display("COMMS_0026: web socket error - {}", err),
         }
         WebSocketInternalError(err: String) {
             description("web socket internal error"),
-            display("web socket internal error - {}", err),
+            display("COMMS_0027: web socket internal error - {}", err),
         }
         UnsupportedProtocolError(proto: String) {
             description("unsupported wire protocol"),
-            display("unsupported wire protocol ({})", proto),
+            display("COMMS_0028: unsupported wire protocol ({})", proto),
+        }
+        HelloTampered {
+            description("the hello messages were tampered with before the connection was encrypted"),
+            display("COMMS_0029: the hello messages were tampered with before the connection was encrypted"),
+        }
+        ProxyHeaderInvalid(err: String) {
+            description("the PROXY protocol header of the connection is malformed"),
+            display("COMMS_0030: the PROXY protocol header of the connection is malformed - {}", err),
+        }
+        TlsError(err: String) {
+            description("the TLS tunnel could not be established"),
+            display("COMMS_0031: the TLS tunnel could not be established - {}", err),
+        }
+        NotListening {
+            description("the root is not listening for connections"),
+            display("COMMS_0032: the root is not listening for connections"),
+        }
+        ReplayDetected(err: String) {
+            description("a frame was replayed or tampered with on the wire which has terminated the connection"),
+            display("COMMS_0033: a frame was replayed or tampered with on the wire which has terminated the connection - {}", err),
+        }
+    }
+}
+
+error_codes!(CommsErrorKind, "COMMS", {
+    "0001" => SerializationError,
+    "0002" => ValidationError,
+    "0003" => LoadError,
+    "0004" => IO,
+    "0005" => JoinError,
+    "0006" => UrlError,
+    "0007" => SendError,
+    "0008" => ReceiveError,
+    "0009" => MissingCertificate,
+    "0010" => CertificateTooWeak,
+    "0011" => ServerCertificateValidation,
+    "0012" => ServerEncryptionWeak,
+    "0013" => RedirectNotSupported,
+    "0014" => Disconnected,
+    "0015" => ReadOnly,
+    "0016" => Timeout,
+    "0017" => NoAddress,
+    "0018" => Refused,
+    "0019" => ShouldBlock,
+    "0020" => InvalidDomainName,
+    "0021" => RequiredExplicitNodeId,
+    "0022" => ListenAddressInvalid,
+    "0023" => NotYetSubscribed,
+    "0024" => FatalError,
+    "0025" => InternalError,
+    "0026" => WebSocketError,
+    "0027" => WebSocketInternalError,
+    "0028" => UnsupportedProtocolError,
+    "0029" => HelloTampered,
+    "0030" => ProxyHeaderInvalid,
+    "0031" => TlsError,
+    "0032" => NotListening,
+    "0033" => ReplayDetected,
+});
+
+impl CommsError {
+    /// Converts an error that was returned while reading frames off the
+    /// wire, frames rejected by the replay protection get their own error
+    pub fn from_wire(err: std::io::Error) -> CommsError {
+        match ate_comms::ReplayError::find(&err) {
+            Some(replay) => CommsErrorKind::ReplayDetected(replay.to_string()).into(),
+            None => CommsErrorKind::IO(err).into(),
         }
     }
 }
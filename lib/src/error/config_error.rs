@@ -0,0 +1,28 @@
+use error_chain::error_chain;
+
+error_chain! {
+    types {
+        ConfigError, ConfigErrorKind, ResultExt, Result;
+    }
+    foreign_links {
+        IO(std::io::Error);
+    }
+    errors {
+        UnsupportedFormat(path: String) {
+            description("the format of the configuration file is not supported"),
+            display("the format of the configuration file ({}) is not supported - use a .toml, .yaml, .yml or .json file", path),
+        }
+        ParseError(err: String) {
+            description("failed to parse the configuration file"),
+            display("failed to parse the configuration file - {}", err),
+        }
+        MissingSection(name: String) {
+            description("the configuration file is missing a section"),
+            display("the configuration file is missing the [{}] section", name),
+        }
+        Invalid(problems: Vec<String>) {
+            description("the configuration file is invalid"),
+            display("the configuration file is invalid:\n{}", problems.iter().map(|a| format!("  - {}", a)).collect::<Vec<_>>().join("\n")),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use error_chain::error_chain;
+
+use crate::crypto::AteHash;
+
+error_chain! {
+    types {
+        InclusionError, InclusionErrorKind, ResultExt, Result;
+    }
+    errors {
+        NoRootHead {
+            description("the chain has not been checkpointed yet so there is no signed root head to prove against"),
+            display("the chain has not been checkpointed yet so there is no signed root head to prove against"),
+        }
+        EventNotFound(hash: AteHash) {
+            description("the event is not covered by the signed root head of the chain"),
+            display("the event ({}) is not covered by the signed root head of the chain", hash),
+        }
+        StaleRootHead(tree_size: u64) {
+            description("the history of the chain no longer matches its signed root head (it must be checkpointed again)"),
+            display("the history of the chain no longer matches its signed root head of size {} (it must be checkpointed again)", tree_size),
+        }
+        UntrustedRootHead {
+            description("the root head is not signed by any of the trusted public keys"),
+            display("the root head is not signed by any of the trusted public keys"),
+        }
+        RootHeadMismatch {
+            description("the proof was not generated against the trusted root head"),
+            display("the proof was not generated against the trusted root head"),
+        }
+        InvalidProof(reason: String) {
+            description("the inclusion proof is invalid"),
+            display("the inclusion proof is invalid - {}", reason),
+        }
+        SigningFailed(err: String) {
+            description("failed to sign the root head"),
+            display("failed to sign the root head - {}", err),
+        }
+    }
+}
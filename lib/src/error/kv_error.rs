@@ -0,0 +1,22 @@
+use error_chain::error_chain;
+
+error_chain! {
+    types {
+        KvError, KvErrorKind, ResultExt, Result;
+    }
+    links {
+        CommitError(super::CommitError, super::CommitErrorKind);
+        LoadError(super::LoadError, super::LoadErrorKind);
+        SerializationError(super::SerializationError, super::SerializationErrorKind);
+    }
+    errors {
+        LockTimeout(key: String) {
+            description("timed out while waiting for the lock on a key"),
+            display("timed out while waiting for the lock on key '{}'", key),
+        }
+        KeyCollision(key: String, other: String) {
+            description("the key hashed to the same row as another key"),
+            display("the key '{}' hashed to the same row as the key '{}'", key, other),
+        }
+    }
+}
@@ -78,6 +78,10 @@ error_chain! {
             description("the dio that created this object has gone out of scope")
             display("the dio that created this object has gone out of scope")
         }
+        IndexNotFound(name: String, field: String) {
+            description("the field is not indexed hence the rows can not be found by its value"),
+            display("the field ({}.{}) is not indexed hence the rows can not be found by its value", name, field),
+        }
     }
 }
 
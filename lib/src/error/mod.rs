@@ -1,43 +1,64 @@
 pub mod ate_error;
 pub mod bus_error;
 pub mod chain_creation_error;
+pub mod code;
 pub mod commit_error;
 pub mod comms_error;
 pub mod compact_error;
+pub mod config_error;
+pub mod inclusion_error;
 pub mod invoke_error;
+pub mod kv_error;
 pub mod lint_error;
 pub mod load_error;
 pub mod lock_error;
+pub mod multi_chain_error;
 pub mod process_error;
+pub mod query_error;
 pub mod sink_error;
 pub mod time_error;
 pub mod transform_error;
 pub mod trust_error;
 pub mod validation_error;
 
+mod tests;
+
 pub use ate_error::AteError;
 pub use ate_error::AteErrorKind;
 pub use bus_error::BusError;
 pub use bus_error::BusErrorKind;
 pub use chain_creation_error::ChainCreationError;
 pub use chain_creation_error::ChainCreationErrorKind;
+pub use code::ErrorCode;
+pub use code::ErrorCodeInfo;
+pub use code::ErrorEnvelope;
 pub use commit_error::CommitError;
 pub use commit_error::CommitErrorKind;
 pub use comms_error::CommsError;
 pub use comms_error::CommsErrorKind;
 pub use compact_error::CompactError;
 pub use compact_error::CompactErrorKind;
+pub use config_error::ConfigError;
+pub use config_error::ConfigErrorKind;
 pub use ate_crypto::error::CryptoError;
 pub use ate_crypto::error::CryptoErrorKind;
+pub use inclusion_error::InclusionError;
+pub use inclusion_error::InclusionErrorKind;
 pub use invoke_error::InvokeError;
 pub use invoke_error::InvokeErrorKind;
+pub use kv_error::KvError;
+pub use kv_error::KvErrorKind;
 pub use lint_error::LintError;
 pub use lint_error::LintErrorKind;
 pub use load_error::LoadError;
 pub use load_error::LoadErrorKind;
 pub use lock_error::LockError;
 pub use lock_error::LockErrorKind;
+pub use multi_chain_error::MultiChainError;
+pub use multi_chain_error::MultiChainErrorKind;
 pub use process_error::ProcessError;
+pub use query_error::QueryError;
+pub use query_error::QueryErrorKind;
 pub use ate_crypto::error::SerializationError;
 pub use ate_crypto::error::SerializationErrorKind;
 pub use sink_error::SinkError;
@@ -0,0 +1,26 @@
+use error_chain::error_chain;
+
+error_chain! {
+    types {
+        MultiChainError, MultiChainErrorKind, ResultExt, Result;
+    }
+    links {
+        CommitError(super::CommitError, super::CommitErrorKind);
+        LoadError(super::LoadError, super::LoadErrorKind);
+        SerializationError(super::SerializationError, super::SerializationErrorKind);
+    }
+    errors {
+        Locked(participant: String, key: String) {
+            description("the transaction could not be prepared as one of its rows is locked by someone else"),
+            display("the transaction could not be prepared as a row ({}) on {} is locked by someone else", key, participant),
+        }
+        RolledBack(participant: String, err: String) {
+            description("the transaction was rolled back as one of the chains failed to commit"),
+            display("the transaction was rolled back as {} failed to commit - {}", participant, err),
+        }
+        UnknownParticipant(participant: String) {
+            description("the transaction could not be recovered as one of its chains could not be opened"),
+            display("the transaction could not be recovered as the chain of {} could not be opened", participant),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use error_chain::error_chain;
+
+error_chain! {
+    types {
+        QueryError, QueryErrorKind, ResultExt, Result;
+    }
+    links {
+        LoadError(super::LoadError, super::LoadErrorKind);
+        SerializationError(super::SerializationError, super::SerializationErrorKind);
+    }
+    errors {
+        Parse(position: usize, expected: String, found: String) {
+            description("the query could not be parsed"),
+            display("the query could not be parsed at position {} - expected {} but found {}", position, expected, found),
+        }
+        ScanLimitExceeded(limit: usize) {
+            description("the query would scan more rows than it is allowed to"),
+            display("the query would scan more than {} rows (filter on an indexed field or raise the scan limit)", limit),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use error_chain::error_chain;
 
 use crate::crypto::AteHash;
+use crate::crypto::SignatureError;
 
 error_chain! {
     types {
@@ -14,7 +15,7 @@ error_chain! {
             description("the public key for signature could not be found in the chain-of-trust"),
             display("the public key ({}) for signature could not be found in the chain-of-trust", hash.to_string()),
         }
-        InvalidSignature(hash: AteHash, err: Option<pqcrypto_traits_wasi::Error>) {
+        InvalidSignature(hash: AteHash, err: Option<SignatureError>) {
             description("failed verification of hash while using public key"),
             display("failed verification of hash while using public key ({}) - {}", hash.to_string(), err.map(|a| a.to_string()).unwrap_or_else(|| "unknown reason".to_string()))
         }
@@ -0,0 +1,69 @@
+#![cfg(test)]
+use fxhash::FxHashSet;
+
+use super::*;
+
+#[test]
+fn test_error_codes_unique() {
+    crate::utils::bootstrap_test_env();
+
+    let mut codes = FxHashSet::default();
+    for info in code::all() {
+        assert!(
+            codes.insert(info.code),
+            "the error code {} has been assigned more than once",
+            info.code
+        );
+    }
+}
+
+#[test]
+fn test_error_codes_stable() {
+    crate::utils::bootstrap_test_env();
+
+    // These codes are relied upon by external callers and must never change
+    assert_eq!(CommsErrorKind::Disconnected.code(), "COMMS_0014");
+    assert_eq!(CommsErrorKind::Timeout.code(), "COMMS_0016");
+    assert_eq!(CommsErrorKind::Refused.code(), "COMMS_0018");
+    assert_eq!(
+        CommsErrorKind::UnsupportedProtocolError("ws".to_string()).code(),
+        "COMMS_0028"
+    );
+    assert_eq!(CommsErrorKind::Msg("other".to_string()).code(), "COMMS_0000");
+    assert_eq!(CommitErrorKind::QuotaExceeded(1, 2).code(), "COMMIT_0014");
+    assert_eq!(CommitErrorKind::TooLarge(1, 2).code(), "COMMIT_0013");
+}
+
+#[test]
+fn test_error_codes_display() {
+    crate::utils::bootstrap_test_env();
+
+    let errs = vec![
+        CommsErrorKind::Disconnected,
+        CommsErrorKind::NotYetSubscribed,
+        CommsErrorKind::ListenAddressInvalid("nowhere".to_string()),
+        CommsErrorKind::InternalError("oops".to_string()),
+    ];
+    for err in errs {
+        let msg = err.to_string();
+        assert!(
+            msg.starts_with(err.code()),
+            "the error message ({}) does not start with its code",
+            msg
+        );
+    }
+
+    let errs = vec![
+        CommitErrorKind::Aborted,
+        CommitErrorKind::ChainReadOnly("chain".to_string()),
+        CommitErrorKind::QuotaExceeded(10, 8),
+    ];
+    for err in errs {
+        let msg = err.to_string();
+        assert!(
+            msg.starts_with(err.code()),
+            "the error message ({}) does not start with its code",
+            msg
+        );
+    }
+}
@@ -12,11 +12,6 @@ error_chain! {
         IO(std::io::Error);
     }
     errors {
-        #[cfg(feature = "enable_openssl")]
-        EncryptionError(stack: openssl::error::ErrorStack) {
-            description("encryption error while transforming event data"),
-            display("encryption error while transforming event data - {}", err),
-        }
         MissingData {
             description("missing data for this record")
             display("missing data for this record")
@@ -31,10 +26,3 @@ error_chain! {
         }
     }
 }
-
-#[cfg(feature = "enable_openssl")]
-impl From<openssl::error::ErrorStack> for Error {
-    fn from(err: openssl::error::ErrorStack) -> Error {
-        ErrorKind::EncryptionError(err).into()
-    }
-}
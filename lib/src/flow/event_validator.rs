@@ -0,0 +1,57 @@
+use std::sync::Arc;
+use std::sync::RwLock as StdRwLock;
+
+use crate::error::*;
+use crate::event::EventHeader;
+use crate::meta::Metadata;
+use crate::session::AteSession;
+
+/// Validation logic that an application hosting a `MeshRoot` registers on
+/// a route (either by returning it from `OpenFlow::event_validators` when the
+/// route is added or later with `MeshRoot::add_route_validator`), the server
+/// runs it on every event that a client commits before the event is accepted.
+/// Rejections are returned to the client that committed the events as a
+/// `CommitError` that carries the reason.
+///
+/// Validators run synchronously on the feed path hence they must be fast and
+/// must never block. The data of most events is encrypted by the client,
+/// validators that need to read it should return true from `decrypt_data` so
+/// that the server decrypts it with the keys of its own session first - if
+/// the server does not hold the key then the event is denied rather than
+/// handing the validator data that it can not read.
+pub trait EventValidator: Send + Sync {
+    fn validate(
+        &self,
+        header: &EventHeader,
+        meta: &Metadata,
+        data: Option<&[u8]>,
+        session: &'_ dyn AteSession,
+    ) -> Result<(), ValidationError>;
+
+    /// Return true to receive the data after it has been decrypted
+    fn decrypt_data(&self) -> bool {
+        false
+    }
+
+    fn validator_name(&self) -> &str;
+}
+
+/// Validators of a route which are shared with all the sessions on the route
+/// so that validators added later also apply to the clients already connected
+#[derive(Default)]
+pub(crate) struct RouteValidators {
+    validators: StdRwLock<Vec<Arc<dyn EventValidator>>>,
+}
+
+impl RouteValidators {
+    pub(crate) fn add(&self, validator: Arc<dyn EventValidator>) {
+        let mut validators = self.validators.write().unwrap();
+        validators.push(validator);
+    }
+
+    /// Returns the validators as they are right now (taken for every commit)
+    pub(crate) fn list(&self) -> Vec<Arc<dyn EventValidator>> {
+        let validators = self.validators.read().unwrap();
+        validators.clone()
+    }
+}
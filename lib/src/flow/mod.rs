@@ -1,6 +1,7 @@
 #![allow(unused_imports)]
 use async_trait::async_trait;
 pub mod basic;
+mod event_validator;
 use crate::{crypto::EncryptKey, session::AteSessionUser};
 
 use super::chain::Chain;
@@ -14,6 +15,9 @@ use super::spec::TrustMode;
 use crate::crypto::KeySize;
 use std::sync::Arc;
 
+pub use event_validator::EventValidator;
+pub(crate) use event_validator::RouteValidators;
+
 pub type MessageOfTheDay = Option<String>;
 
 pub enum OpenAction {
@@ -54,6 +58,12 @@ where
     ) -> Result<Option<String>, ChainCreationError>;
 
     fn hello_path(&self) -> &str;
+
+    /// Validators that the server runs on every event that clients commit to
+    /// the chains of this flow before they are accepted (see `EventValidator`)
+    fn event_validators(&self) -> Vec<Arc<dyn EventValidator>> {
+        Vec::new()
+    }
 }
 
 pub async fn all_persistent_and_centralized() -> Box<basic::OpenStaticBuilder> {
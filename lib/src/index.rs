@@ -34,6 +34,7 @@ pub(crate) struct BinaryTreeIndexer {
     secondary: MultiMap<MetaCollection, PrimaryKey>,
     parents: FxHashMap<PrimaryKey, MetaParent>,
     uploads: FxHashMap<ChainTimestamp, MetaDelayedUpload>,
+    policies: FxHashMap<MetaCollection, MetaCollectionPolicy>,
 }
 
 impl BinaryTreeIndexer {
@@ -54,6 +55,7 @@ impl BinaryTreeIndexer {
                 CoreMetadata::Tombstone(key) => {
                     self.roots.remove(key);
                     self.primary.remove(&key);
+                    self.policies.retain(|k, _| k.parent_id != *key);
                     if let Some(tree) = self.parents.remove(&key) {
                         if let Some(vec) = self.secondary.get_vec_mut(&tree.vec) {
                             vec.retain(|x| *x != *key);
@@ -75,7 +77,7 @@ impl BinaryTreeIndexer {
                     if entry.raw.data_hash.is_none() {
                         continue;
                     }
-                    if has_parent {
+                    if has_parent == false {
                         self.roots.insert(key.clone());
                     }
                     let when = entry.meta.get_timestamp();
@@ -92,9 +94,20 @@ impl BinaryTreeIndexer {
                         Some(t) => t.time_since_epoch_ms,
                         None => 0,
                     };
+
+                    // The latest version of the row holds the policies of its collections
+                    self.policies.retain(|k, _| k.parent_id != *key);
+                    for policy in entry.meta.get_collection_policies() {
+                        let vec = MetaCollection {
+                            parent_id: key.clone(),
+                            collection_id: policy.collection_id,
+                        };
+                        self.policies.insert(vec, policy);
+                    }
                 }
                 CoreMetadata::Parent(parent) => {
                     if let Some(key) = entry.meta.get_data_key() {
+                        self.roots.remove(&key);
                         if let Some(parent) = self.parents.remove(&key) {
                             if let Some(vec) = self.secondary.get_vec_mut(&parent.vec) {
                                 vec.retain(|x| *x != key);
@@ -154,6 +167,13 @@ impl BinaryTreeIndexer {
         }
     }
 
+    pub(crate) fn lookup_collection_policy(
+        &self,
+        key: &MetaCollection,
+    ) -> Option<MetaCollectionPolicy> {
+        self.policies.get(key).map(|a| a.clone())
+    }
+
     pub(crate) fn roots_raw(&self) -> Vec<PrimaryKey> {
         self.roots
             .iter()
@@ -176,6 +196,22 @@ impl BinaryTreeIndexer {
     pub(crate) fn all_keys(&self) -> impl Iterator<Item = &PrimaryKey> {
         self.primary.keys()
     }
+
+    /// Checksum of the rows in the index (and the events they point to) which
+    /// does not depend on the order that they are held in memory
+    pub(crate) fn checksum(&self) -> super::crypto::AteHash {
+        let mut rows = self.primary.iter().collect::<Vec<_>>();
+        rows.sort_by_key(|(k, _)| **k);
+
+        let mut data = Vec::with_capacity(rows.len() * 40);
+        for (key, leaf) in rows {
+            data.extend_from_slice(&key.as_u64().to_be_bytes());
+            data.extend_from_slice(leaf.record.as_bytes());
+            data.extend_from_slice(&leaf.created.to_be_bytes());
+            data.extend_from_slice(&leaf.updated.to_be_bytes());
+        }
+        super::crypto::AteHash::from_bytes(&data[..])
+    }
 }
 
 #[derive(Default, Debug)]
@@ -0,0 +1,418 @@
+#![allow(unused_imports)]
+use error_chain::bail;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::chain::Chain;
+use crate::crypto::AteHash;
+use crate::dio::DioMut;
+use crate::error::*;
+use crate::header::PrimaryKey;
+use crate::meta::ReadOption;
+use crate::meta::WriteOption;
+use crate::session::AteSession;
+use crate::session::AteSessionUser;
+
+/// The shards of a bucket are attached to this collection on the bucket's
+/// (virtual) parent key
+const KV_COLLECTION_ID: u64 = 0x6b76;
+
+/// Entries are grouped into shards by this many leading characters of their
+/// key, listing a prefix at least this long only reads the one shard
+const KV_SHARD_LEN: usize = 2;
+
+/// Row that holds a single value within a bucket, the key is stored
+/// alongside the value so that the bucket can be listed
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct KvEntry {
+    pub key: String,
+    pub value: Vec<u8>,
+}
+
+/// Row that records a shard of a bucket which holds at least one entry (so
+/// that short prefixes only read the shards that can match)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct KvShard {
+    pub prefix: String,
+}
+
+/// Namespaced key-value store built on top of the DIO, the rows for each
+/// key are stored at a primary key derived from the bucket name and the
+/// key hence reads go straight to the row without any lookups
+///
+/// Every write is performed while holding the chain lock on the row, this
+/// gives compare-and-swap semantics across all the clients of a chain
+/// (note: locks are only available on chains that are connected to a
+/// mesh, a local chain will fail with a lock timeout)
+pub struct KvBucket {
+    chain: Arc<Chain>,
+    name: String,
+    session: Box<dyn AteSession>,
+    read: ReadOption,
+    write: WriteOption,
+    lock_timeout: Duration,
+}
+
+impl Clone for KvBucket {
+    fn clone(&self) -> Self {
+        KvBucket {
+            chain: Arc::clone(&self.chain),
+            name: self.name.clone(),
+            session: self.session.clone_session(),
+            read: self.read.clone(),
+            write: self.write.clone(),
+            lock_timeout: self.lock_timeout,
+        }
+    }
+}
+
+impl std::fmt::Debug for KvBucket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "kv-bucket(chain={}, name={})", self.chain.key(), self.name)
+    }
+}
+
+/// Opens a bucket within a chain using an empty session, use the
+/// builder methods on the bucket to change its settings
+pub fn bucket(chain: &Arc<Chain>, name: &str) -> KvBucket {
+    KvBucket::new(chain, name)
+}
+
+impl KvBucket {
+    pub fn new(chain: &Arc<Chain>, name: &str) -> KvBucket {
+        KvBucket {
+            chain: Arc::clone(chain),
+            name: name.to_string(),
+            session: Box::new(AteSessionUser::default()),
+            read: ReadOption::Inherit,
+            write: WriteOption::Inherit,
+            lock_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Session used to read and write the rows (it must hold the keys
+    /// needed by the read and write options of the bucket)
+    pub fn with_session(mut self, session: &'_ dyn AteSession) -> Self {
+        self.session = session.clone_session();
+        self
+    }
+
+    /// Encryption that is applied to new rows written to the bucket
+    pub fn with_read(mut self, read: ReadOption) -> Self {
+        self.read = read;
+        self
+    }
+
+    /// Write permissions that are applied to new rows written to the bucket
+    pub fn with_write(mut self, write: WriteOption) -> Self {
+        self.write = write;
+        self
+    }
+
+    /// Maximum amount of time a write will wait for the row lock
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    // The length of the name is part of the keys so that the bucket and key
+    // can not run into each other (e.g. bucket "a:b" and bucket "a")
+    fn parent_key(&self) -> PrimaryKey {
+        PrimaryKey::from(format!("kv-bucket:{}:{}", self.name.len(), self.name))
+    }
+
+    fn row_key(&self, key: &str) -> PrimaryKey {
+        PrimaryKey::from(format!("kv:{}:{}:{}", self.name.len(), self.name, key))
+    }
+
+    fn shard_key(&self, shard: &str) -> PrimaryKey {
+        PrimaryKey::from(format!("kv-shard:{}:{}:{}", self.name.len(), self.name, shard))
+    }
+
+    fn shard_collection_id(&self, shard: &str) -> u64 {
+        let prefix = format!("kv-shard:{}:{}", self.name.len(), self.name);
+        AteHash::from_bytes_twice(prefix.as_bytes(), shard.as_bytes()).to_u64()
+    }
+
+    fn shard_of(key: &str) -> String {
+        key.chars().take(KV_SHARD_LEN).collect()
+    }
+
+    /// Keys are hashed into primary keys so the row found at the key might
+    /// belong to another key, in which case it must not be used
+    fn check_entry(key: &str, entry: &KvEntry) -> Result<(), KvError> {
+        if entry.key != key {
+            bail!(KvErrorKind::KeyCollision(key.to_string(), entry.key.clone()));
+        }
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, KvError> {
+        let dio = self.chain.dio(self.session.deref()).await;
+        match dio.load::<KvEntry>(&self.row_key(key)).await {
+            Ok(a) => {
+                let entry = a.take();
+                Self::check_entry(key, &entry)?;
+                Ok(Some(entry.value))
+            }
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub async fn put(&self, key: &str, value: Vec<u8>) -> Result<(), KvError> {
+        self.swap(key, None, Some(value)).await?;
+        Ok(())
+    }
+
+    /// Deletes a key from the bucket, returns false if it did not exist
+    pub async fn delete(&self, key: &str) -> Result<bool, KvError> {
+        Ok(match self.swap(key, None, None).await? {
+            Some(Some(_)) => true,
+            _ => false,
+        })
+    }
+
+    /// Replaces the value of a key only if its current value matches the
+    /// expected value (None means the key does not exist), setting the new
+    /// value to None deletes the key. Returns false if the value did not
+    /// match, in which case nothing is written
+    pub async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<Vec<u8>>,
+    ) -> Result<bool, KvError> {
+        Ok(self.swap(key, Some(expected), new).await?.is_some())
+    }
+
+    /// Returns all the entries whose key starts with the prefix ordered by
+    /// their key, only the shards that can hold such keys are read
+    pub async fn list_prefix(&self, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, KvError> {
+        let dio = self.chain.dio(self.session.deref()).await;
+
+        // Prefixes that cover a whole shard go straight to it, otherwise the
+        // shards of the bucket are listed to find those that could match
+        let shards = if prefix.chars().count() >= KV_SHARD_LEN {
+            vec![Self::shard_of(prefix)]
+        } else {
+            let keys = dio
+                .children_keys(self.parent_key(), KV_COLLECTION_ID)
+                .await?;
+            let mut shards = Vec::new();
+            for shard in dio.load_many_parallel::<KvShard>(keys, 64).await {
+                match shard {
+                    Ok(a) if a.prefix.starts_with(prefix) => shards.push(a.take().prefix),
+                    Ok(_) => {}
+                    Err(LoadError(LoadErrorKind::NotFound(_), _)) => continue,
+                    Err(err) => {
+                        bail!(err);
+                    }
+                }
+            }
+            shards
+        };
+
+        let mut ret = Vec::new();
+        for shard in shards {
+            let keys = dio
+                .children_keys(self.parent_key(), self.shard_collection_id(shard.as_str()))
+                .await?;
+            for entry in dio.load_many_parallel::<KvEntry>(keys, 64).await {
+                let entry = match entry {
+                    Ok(a) => a.take(),
+                    Err(LoadError(LoadErrorKind::NotFound(_), _)) => continue,
+                    Err(err) => {
+                        bail!(err);
+                    }
+                };
+                if entry.key.starts_with(prefix) {
+                    ret.push((entry.key, entry.value));
+                }
+            }
+        }
+        ret.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(ret)
+    }
+
+    pub async fn get_as<T>(&self, key: &str) -> Result<Option<T>, KvError>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(match self.get(key).await? {
+            Some(a) => Some(Self::decode(&a[..])?),
+            None => None,
+        })
+    }
+
+    pub async fn put_as<T>(&self, key: &str, value: &T) -> Result<(), KvError>
+    where
+        T: Serialize,
+    {
+        self.put(key, Self::encode(value)?).await
+    }
+
+    pub async fn compare_and_swap_as<T>(
+        &self,
+        key: &str,
+        expected: Option<&T>,
+        new: Option<&T>,
+    ) -> Result<bool, KvError>
+    where
+        T: Serialize,
+    {
+        let expected = match expected {
+            Some(a) => Some(Self::encode(a)?),
+            None => None,
+        };
+        let new = match new {
+            Some(a) => Some(Self::encode(a)?),
+            None => None,
+        };
+        self.compare_and_swap(key, expected.as_ref().map(|a| &a[..]), new)
+            .await
+    }
+
+    pub async fn list_prefix_as<T>(&self, prefix: &str) -> Result<Vec<(String, T)>, KvError>
+    where
+        T: DeserializeOwned,
+    {
+        self.list_prefix(prefix)
+            .await?
+            .into_iter()
+            .map(|(k, v)| -> Result<(String, T), KvError> { Ok((k, Self::decode(&v[..])?)) })
+            .collect()
+    }
+
+    fn encode<T>(value: &T) -> Result<Vec<u8>, SerializationError>
+    where
+        T: Serialize,
+    {
+        Ok(bincode::serialize(value)?)
+    }
+
+    fn decode<T>(value: &[u8]) -> Result<T, SerializationError>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(bincode::deserialize(value)?)
+    }
+
+    /// Writes a new value for a key while holding its lock, when an expected
+    /// value is supplied the write only happens if the current value matches.
+    /// Returns None if nothing was written otherwise the previous value
+    async fn swap(
+        &self,
+        key: &str,
+        expected: Option<Option<&[u8]>>,
+        new: Option<Vec<u8>>,
+    ) -> Result<Option<Option<Vec<u8>>>, KvError> {
+        // The commit must reach the server before the lock is released
+        // otherwise the next holder of the lock could read a stale value
+        let id = self.row_key(key);
+        let dio = self.chain.dio_full(self.session.deref()).await?;
+        self.lock(&dio, key, &id).await?;
+
+        let ret = self.__swap(&dio, key, &id, expected, new).await;
+        if ret.is_err() {
+            dio.cancel();
+        }
+        dio.unlock(id).await?;
+        ret
+    }
+
+    async fn __swap(
+        &self,
+        dio: &Arc<DioMut>,
+        key: &str,
+        id: &PrimaryKey,
+        expected: Option<Option<&[u8]>>,
+        new: Option<Vec<u8>>,
+    ) -> Result<Option<Option<Vec<u8>>>, KvError> {
+        // The lock is held so this is the latest value of the row
+        let mut current = dio.try_load::<KvEntry>(id).await?;
+        if let Some(entry) = current.as_ref() {
+            Self::check_entry(key, entry)?;
+        }
+        let previous = current.as_ref().map(|a| a.value.clone());
+        if let Some(expected) = expected {
+            if previous.as_ref().map(|a| &a[..]) != expected {
+                trace!("kv-bucket({}) swap of '{}' did not match", self.name, key);
+                return Ok(None);
+            }
+        }
+
+        match (current.as_mut(), new) {
+            (Some(entry), Some(value)) => {
+                entry.as_mut().value = value;
+            }
+            (None, Some(value)) => {
+                let shard = Self::shard_of(key);
+                let mut entry = dio.store_with_key(
+                    KvEntry {
+                        key: key.to_string(),
+                        value,
+                    },
+                    id.clone(),
+                )?;
+                entry.attach_orphaned_ext(&self.parent_key(), self.shard_collection_id(shard.as_str()))?;
+                let mut auth = entry.auth_mut();
+                auth.read = self.read.clone();
+                auth.write = self.write.clone();
+                drop(auth);
+
+                // The first entry of a shard records it in the bucket
+                let shard_key = self.shard_key(shard.as_str());
+                if dio.exists(&shard_key).await == false {
+                    let mut marker = dio.store_with_key(KvShard { prefix: shard }, shard_key)?;
+                    marker.attach_orphaned_ext(&self.parent_key(), KV_COLLECTION_ID)?;
+                    let mut auth = marker.auth_mut();
+                    auth.read = self.read.clone();
+                    auth.write = self.write.clone();
+                }
+            }
+            (Some(_), None) => {
+                dio.delete(id).await?;
+            }
+            (None, None) => {}
+        }
+        drop(current);
+
+        dio.commit().await?;
+        Ok(Some(previous))
+    }
+
+    /// Acquires the chain lock on the row using an exponential backoff
+    async fn lock(&self, dio: &Arc<DioMut>, key: &str, id: &PrimaryKey) -> Result<(), KvError> {
+        let timer = Instant::now();
+        let mut max_wait = 0u64;
+        loop {
+            if dio.try_lock(id.clone()).await? {
+                return Ok(());
+            }
+
+            let remaining = match self.lock_timeout.checked_sub(timer.elapsed()) {
+                Some(a) => a,
+                None => {
+                    bail!(KvErrorKind::LockTimeout(key.to_string()));
+                }
+            };
+
+            max_wait = ((max_wait * 12u64) / 10u64) + 5u64;
+            max_wait = max_wait.min(500u64);
+            let min_wait = max_wait / 2u64;
+
+            let random_wait = fastrand::u64(min_wait..max_wait);
+            let random_wait = Duration::from_millis(random_wait).min(remaining);
+            crate::engine::sleep(random_wait).await;
+        }
+    }
+}
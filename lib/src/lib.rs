@@ -10,11 +10,17 @@
 
 pub const LOG_VERSION: spec::EventVersion = spec::EventVersion::V2;
 
+// Without the 'asymmetric' feature chains can still be read and decrypted
+// with symmetric keys but signing, verifying signatures and exchanging keys
+// fail at runtime with an unsupported error
+
 pub mod anti_replay;
 pub mod chain;
 pub mod comms;
 pub mod compact;
 pub mod conf;
+#[cfg(feature = "enable_server")]
+pub mod daemon;
 pub mod dio;
 #[cfg(feature = "enable_dns")]
 pub mod dns;
@@ -25,6 +31,7 @@ pub mod event;
 pub mod flow;
 pub mod header;
 pub mod index;
+pub mod kv;
 pub mod lint;
 pub mod loader;
 pub mod mesh;
@@ -49,4 +56,5 @@ pub mod utils;
 pub mod validator;
 
 pub use ate_crypto::crypto;
+pub use error::code as errors;
 pub use ate_crypto::utils::log_init;
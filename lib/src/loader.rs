@@ -1,5 +1,7 @@
 use async_trait::async_trait;
+use std::sync::Arc;
 use tokio::sync::mpsc;
+use tokio::sync::watch;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
@@ -19,6 +21,11 @@ pub trait Loader: Send + Sync {
     /// Function invoked when the start of the history is being loaded
     async fn start_of_history(&mut self, _size: usize) {}
 
+    /// Function invoked just before the start of the history with the
+    /// approximate number of data bytes that will be loaded (older servers
+    /// do not report this in which case it is never invoked)
+    fn history_bytes(&mut self, _total_bytes: u64) {}
+
     /// Human message sent from the server to this process
     fn human_message(&mut self, _message: String) {}
 
@@ -59,6 +66,12 @@ impl Loader for CompositionLoader {
         }
     }
 
+    fn history_bytes(&mut self, total_bytes: u64) {
+        for loader in self.loaders.iter_mut() {
+            loader.history_bytes(total_bytes);
+        }
+    }
+
     fn human_message(&mut self, message: String) {
         for loader in self.loaders.iter_mut() {
             loader.human_message(message.clone());
@@ -131,3 +144,108 @@ impl Loader for NotificationLoader {
         None
     }
 }
+
+/// How far the initial synchronization of a chain with its server has got
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncProgress {
+    pub events_received: u64,
+    pub bytes_received: u64,
+    /// What the server said it will send (None until the sync starts)
+    pub total: Option<SyncTotal>,
+    /// Set once the whole history has been received
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncTotal {
+    pub events: u64,
+    /// Approximate number of data bytes (None when the server is too old
+    /// to report it)
+    pub bytes: Option<u64>,
+}
+
+impl SyncProgress {
+    pub fn completed() -> SyncProgress {
+        SyncProgress {
+            complete: true,
+            ..Default::default()
+        }
+    }
+
+    /// Returns how much of the sync has completed between 0.0 and 1.0, when
+    /// the server did not report the size of the data this is None as the
+    /// progress is indeterminate
+    pub fn fraction(&self) -> Option<f64> {
+        if self.complete {
+            return Some(1.0);
+        }
+        let total = self.total.as_ref()?.bytes?;
+        if total == 0 {
+            return Some(1.0);
+        }
+        Some((self.bytes_received as f64 / total as f64).min(1.0))
+    }
+}
+
+/// Loader that publishes the progress of the sync on a watch channel so that
+/// callers can display it while the chain is being opened
+pub struct SyncProgressLoader {
+    progress: SyncProgress,
+    total_bytes: Option<u64>,
+    tx: Arc<watch::Sender<SyncProgress>>,
+}
+
+impl SyncProgressLoader {
+    pub fn new() -> (SyncProgressLoader, watch::Receiver<SyncProgress>) {
+        let (tx, rx) = watch::channel(SyncProgress::default());
+        (SyncProgressLoader::with_sender(Arc::new(tx)), rx)
+    }
+
+    pub(crate) fn with_sender(tx: Arc<watch::Sender<SyncProgress>>) -> SyncProgressLoader {
+        SyncProgressLoader {
+            progress: SyncProgress::default(),
+            total_bytes: None,
+            tx,
+        }
+    }
+
+    fn publish(&self) {
+        let _ = self.tx.send(self.progress.clone());
+    }
+}
+
+#[async_trait]
+impl Loader for SyncProgressLoader {
+    fn history_bytes(&mut self, total_bytes: u64) {
+        self.total_bytes = Some(total_bytes);
+    }
+
+    async fn start_of_history(&mut self, size: usize) {
+        self.progress = SyncProgress {
+            total: Some(SyncTotal {
+                events: size as u64,
+                bytes: self.total_bytes.take(),
+            }),
+            ..Default::default()
+        };
+        self.publish();
+    }
+
+    fn feed_events(&mut self, evts: &Vec<EventWeakData>) {
+        self.progress.events_received += evts.len() as u64;
+        self.progress.bytes_received += evts
+            .iter()
+            .map(|evt| match &evt.data_bytes {
+                MessageBytes::Some(a) => a.len() as u64,
+                MessageBytes::LazySome(l) => l.len as u64,
+                MessageBytes::None => 0,
+            })
+            .sum::<u64>();
+        self.publish();
+    }
+
+    async fn end_of_history(&mut self) {
+        self.progress.complete = true;
+        self.publish();
+    }
+}
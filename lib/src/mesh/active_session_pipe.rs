@@ -79,13 +79,28 @@ impl ActiveSessionPipe {
     pub(super) async fn feed_internal(
         &mut self,
         trans: &mut Transaction,
+        confirm: bool,
     ) -> Result<Option<mpsc::Receiver<Result<u64, CommitError>>>, CommitError> {
         // Convert the event data into message events
         let evts = MessageEvent::convert_to(&trans.events);
 
         // If the scope requires synchronization with the remote server then allocate a commit ID
-        let (commit, receiver) = match &trans.scope {
-            TransactionScope::Full => {
+        let confirm = match &trans.scope {
+            TransactionScope::Full => true,
+            _ => confirm,
+        };
+        self.send_events(evts, confirm).await
+    }
+
+    /// Sends a set of events to the root, if a confirmation is requested then
+    /// a receiver is returned that will complete once the root has committed them
+    pub(super) async fn send_events(
+        &mut self,
+        evts: Vec<MessageEvent>,
+        confirm: bool,
+    ) -> Result<Option<mpsc::Receiver<Result<u64, CommitError>>>, CommitError> {
+        let (commit, receiver) = match confirm {
+            true => {
                 // Generate a sender/receiver pair
                 let (sender, receiver) = mpsc::channel(1);
 
@@ -94,7 +109,7 @@ impl ActiveSessionPipe {
                 self.commit.lock().unwrap().insert(id, sender);
                 (Some(id), Some(receiver))
             }
-            _ => (None, None),
+            false => (None, None),
         };
 
         // Send the same packet to all the transmit nodes (if there is only one then don't clone)
@@ -108,7 +123,7 @@ impl ActiveSessionPipe {
 }
 
 impl ActiveSessionPipe {
-    pub(super) async fn feed(&mut self, trans: &mut Transaction) -> Result<Option<mpsc::Receiver<Result<u64, CommitError>>>, CommitError> {
+    pub(super) async fn feed(&mut self, trans: &mut Transaction, confirm: bool) -> Result<Option<mpsc::Receiver<Result<u64, CommitError>>>, CommitError> {
         // Only transmit the packet if we are meant to
         let ret = if trans.transmit == true {
             // If we are likely in a read only situation then all transactions
@@ -130,7 +145,7 @@ impl ActiveSessionPipe {
             }
 
             // Feed the transaction into the pipe
-            self.feed_internal(trans).await?
+            self.feed_internal(trans, confirm).await?
         } else {
             None
         };
@@ -173,6 +188,28 @@ impl ActiveSessionPipe {
         };
     }
 
+    pub(super) async fn quota_status(&mut self) -> Result<QuotaStatus, LoadError> {
+        // Every request that is waiting receives the next status of the root
+        let (tx, mut rx) = mpsc::channel(1);
+        self.session.quota_requests.lock().unwrap().push(tx);
+
+        self.tx
+            .send_all_msg(Message::QuotaStatus {
+                chain_key: self.key.clone(),
+            })
+            .await
+            .map_err(|err| {
+                trace!("quota status failed: {}", err);
+                LoadErrorKind::Disconnected
+            })?;
+
+        match crate::engine::timeout(self.load_timeout, rx.recv()).await {
+            Ok(Some(a)) => Ok(a),
+            Ok(None) => bail!(LoadErrorKind::Disconnected),
+            Err(_) => bail!(LoadErrorKind::Timeout),
+        }
+    }
+
     pub(super) async fn try_lock(&mut self, key: PrimaryKey) -> Result<bool, CommitError> {
         // If we are still connecting then don't do it
         if self.connected == false {
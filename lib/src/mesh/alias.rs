@@ -0,0 +1,281 @@
+use async_trait::async_trait;
+use error_chain::bail;
+use fxhash::FxHashMap;
+use fxhash::FxHashSet;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use tokio::sync::Mutex;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use super::server::MeshRoot;
+use crate::chain::Chain;
+use crate::chain::ChainKey;
+use crate::comms::NodeId;
+use crate::comms::RawWebRoute;
+use crate::comms::is_bearer_token;
+use crate::conf::ChainBuilder;
+use crate::conf::ConfAte;
+use crate::error::*;
+use crate::header::PrimaryKey;
+use crate::session::AteSessionUser;
+
+/// Name of the system chain (one per route) that holds the aliases
+pub const ALIAS_CHAIN_NAME: &str = "_aliases";
+/// Maximum number of aliases that are followed before giving up
+pub const ALIAS_MAX_DEPTH: usize = 16;
+
+/// Alias that serves the subscriptions for one chain key from another chain
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChainAlias {
+    pub alias: ChainKey,
+    pub canonical: ChainKey,
+}
+
+/// Aliases of a route which are held in memory and persisted in a small
+/// system chain so that they survive restarts of the root
+pub(super) struct AliasTable {
+    chain: Arc<Chain>,
+    lookup: StdMutex<FxHashMap<String, ChainKey>>,
+    write: Mutex<()>,
+}
+
+impl AliasTable {
+    pub(super) async fn open(
+        cfg_ate: &ConfAte,
+        route: &str,
+        node_id: NodeId,
+    ) -> Result<AliasTable, ChainCreationError> {
+        #[allow(unused_mut)]
+        let mut builder = ChainBuilder::new(cfg_ate).await.node_id(node_id);
+        #[cfg(feature = "enable_local_fs")]
+        {
+            builder = builder.postfix_log_path(route);
+        }
+        let chain = builder
+            .build()
+            .open(&ChainKey::from(ALIAS_CHAIN_NAME))
+            .await?;
+
+        let mut lookup = FxHashMap::default();
+        let dio = chain.dio(&AteSessionUser::new()).await;
+        for row in dio.roots::<ChainAlias>().await? {
+            let row = row.take();
+            lookup.insert(row.alias.name, row.canonical);
+        }
+        debug!("loaded {} aliases for route {}", lookup.len(), route);
+
+        Ok(AliasTable {
+            chain,
+            lookup: StdMutex::new(lookup),
+            write: Mutex::new(()),
+        })
+    }
+
+    fn row_key(alias: &ChainKey) -> PrimaryKey {
+        PrimaryKey::from(alias.hash().to_u64())
+    }
+
+    /// Follows the aliases until it reaches the chain that holds the data
+    pub(super) fn resolve(&self, key: &ChainKey) -> Result<ChainKey, ChainCreationError> {
+        let lookup = self.lookup.lock().unwrap();
+        Self::resolve_in(&lookup, key)
+    }
+
+    fn resolve_in(
+        lookup: &FxHashMap<String, ChainKey>,
+        key: &ChainKey,
+    ) -> Result<ChainKey, ChainCreationError> {
+        let mut ret = key.clone();
+        let mut visited = FxHashSet::default();
+        visited.insert(ret.name.clone());
+        while let Some(next) = lookup.get(&ret.name) {
+            if visited.insert(next.name.clone()) == false || visited.len() > ALIAS_MAX_DEPTH {
+                bail!(ChainCreationErrorKind::AliasLoop(key.name.clone()));
+            }
+            ret = next.clone();
+        }
+        Ok(ret)
+    }
+
+    pub(super) fn contains(&self, alias: &ChainKey) -> bool {
+        self.lookup.lock().unwrap().contains_key(&alias.name)
+    }
+
+    pub(super) fn list(&self) -> Vec<ChainAlias> {
+        let lookup = self.lookup.lock().unwrap();
+        let mut ret = lookup
+            .iter()
+            .map(|(alias, canonical)| ChainAlias {
+                alias: ChainKey::from(alias.clone()),
+                canonical: canonical.clone(),
+            })
+            .collect::<Vec<_>>();
+        ret.sort_by(|a, b| a.alias.name.cmp(&b.alias.name));
+        ret
+    }
+
+    pub(super) async fn add(
+        &self,
+        alias: ChainKey,
+        canonical: ChainKey,
+    ) -> Result<(), ChainCreationError> {
+        let _guard = self.write.lock().await;
+
+        // Make sure the new alias does not form a loop with the others
+        {
+            let mut test = self.lookup.lock().unwrap().clone();
+            test.insert(alias.name.clone(), canonical.clone());
+            Self::resolve_in(&test, &alias)?;
+        }
+
+        let dio = self
+            .chain
+            .dio_mut(&AteSessionUser::new())
+            .await
+            .map_err(|err| ChainCreationErrorKind::InternalError(err.to_string()))?;
+        dio.store_with_key(
+            ChainAlias {
+                alias: alias.clone(),
+                canonical: canonical.clone(),
+            },
+            Self::row_key(&alias),
+        )?;
+        dio.commit()
+            .await
+            .map_err(|err| ChainCreationErrorKind::InternalError(err.to_string()))?;
+
+        self.lookup.lock().unwrap().insert(alias.name, canonical);
+        Ok(())
+    }
+
+    pub(super) async fn remove(&self, alias: &ChainKey) -> Result<bool, ChainCreationError> {
+        let _guard = self.write.lock().await;
+        if self.contains(alias) == false {
+            return Ok(false);
+        }
+
+        let dio = self
+            .chain
+            .dio_mut(&AteSessionUser::new())
+            .await
+            .map_err(|err| ChainCreationErrorKind::InternalError(err.to_string()))?;
+        dio.delete(&Self::row_key(alias)).await?;
+        dio.commit()
+            .await
+            .map_err(|err| ChainCreationErrorKind::InternalError(err.to_string()))?;
+
+        self.lookup.lock().unwrap().remove(&alias.name);
+        Ok(true)
+    }
+
+    pub(super) async fn shutdown(&self) {
+        if let Err(err) = self.chain.shutdown().await {
+            error!("failed to shutdown the alias chain - {}", err);
+        }
+    }
+}
+
+/// Request made to the admin alias route, when the canonical chain is
+/// omitted then the alias is removed (the data is never touched)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AliasRequest {
+    pub route: String,
+    pub alias: String,
+    pub canonical: Option<String>,
+}
+
+/// Result of a request made to the admin alias route
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AliasResponse {
+    pub aliases: Vec<ChainAlias>,
+}
+
+/// Admin web route that registers and removes the chain aliases of a mesh
+/// root (mount it with `add_post_route`). The body of the request is an
+/// `AliasRequest` in JSON and the response lists the aliases of the route.
+pub struct AliasRoute {
+    root: Arc<MeshRoot>,
+    access_token: String,
+}
+
+impl AliasRoute {
+    pub fn new(root: &Arc<MeshRoot>, access_token: String) -> AliasRoute {
+        AliasRoute {
+            root: Arc::clone(root),
+            access_token,
+        }
+    }
+
+    fn error(msg: &str, code: StatusCode) -> (Vec<u8>, StatusCode) {
+        (msg.as_bytes().to_vec(), code)
+    }
+}
+
+#[async_trait]
+impl RawWebRoute for AliasRoute {
+    async fn accepted_raw_post_request(
+        &self,
+        _uri: http::Uri,
+        headers: http::HeaderMap,
+        sock_addr: SocketAddr,
+        _server_id: NodeId,
+        body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        if is_bearer_token(&headers, self.access_token.as_str()) == false {
+            warn!("rejected alias request from {}", sock_addr);
+            return Err(Self::error("invalid access token", StatusCode::UNAUTHORIZED));
+        }
+
+        let req: AliasRequest = serde_json::from_slice(&body[..])
+            .map_err(|err| Self::error(err.to_string().as_str(), StatusCode::BAD_REQUEST))?;
+        let alias = ChainKey::from(req.alias.clone());
+
+        let ret = match req.canonical {
+            Some(canonical) => {
+                info!("alias {} -> {} requested by {}", req.alias, canonical, sock_addr);
+                self.root
+                    .add_alias(req.route.as_str(), alias, ChainKey::from(canonical))
+                    .await
+            }
+            None => {
+                info!("alias {} removal requested by {}", req.alias, sock_addr);
+                self.root
+                    .remove_alias(req.route.as_str(), &alias)
+                    .await
+                    .map(|_| ())
+            }
+        };
+        match ret {
+            Ok(()) => {}
+            Err(ChainCreationError(ChainCreationErrorKind::InternalError(err), _)) => {
+                return Err(Self::error(err.as_str(), StatusCode::INTERNAL_SERVER_ERROR));
+            }
+            Err(err) => {
+                return Err(Self::error(err.to_string().as_str(), StatusCode::BAD_REQUEST));
+            }
+        }
+
+        let aliases = self
+            .root
+            .aliases(req.route.as_str())
+            .await
+            .map_err(|err| Self::error(err.to_string().as_str(), StatusCode::INTERNAL_SERVER_ERROR))?;
+        serde_json::to_vec(&AliasResponse { aliases })
+            .map_err(|err| Self::error(err.to_string().as_str(), StatusCode::INTERNAL_SERVER_ERROR))
+    }
+
+    async fn accepted_raw_put_request(
+        &self,
+        _uri: http::Uri,
+        _headers: http::HeaderMap,
+        _sock_addr: SocketAddr,
+        _server_id: NodeId,
+        _body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        Err(Self::error("alias requests must be made with POST", StatusCode::BAD_REQUEST))
+    }
+}
@@ -0,0 +1,312 @@
+use error_chain::bail;
+use serde::{de::DeserializeOwned, Serialize};
+use std::future::Future;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use url::Url;
+
+use crate::chain::ChainKey;
+use crate::conf::ConfAte;
+use crate::dio::DaoObj;
+use crate::dio::DaoVec;
+use crate::dio::DioMut;
+use crate::error::*;
+use crate::header::PrimaryKey;
+use crate::session::AteSession;
+use crate::session::AteSessionUser;
+
+use super::registry::ChainGuard;
+use super::registry::Registry;
+
+/// Default amount of time a blocking call waits for the async operation
+pub const BLOCKING_DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Runtime that runs on its own thread and drives all the async operations
+/// that are dispatched by the blocking facade. It is shared by the registry
+/// and the chains opened from it and shuts down when the last one is dropped.
+struct BlockingRuntime {
+    handle: Handle,
+    exit: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BlockingRuntime {
+    fn new() -> Result<BlockingRuntime, AteError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let handle = runtime.handle().clone();
+
+        let (exit_tx, exit_rx) = oneshot::channel();
+        let thread = std::thread::Builder::new()
+            .name("ate-blocking".to_string())
+            .spawn(move || {
+                let _ = runtime.block_on(exit_rx);
+                runtime.shutdown_timeout(Duration::from_secs(5));
+                debug!("blocking runtime has shut down");
+            })?;
+
+        Ok(BlockingRuntime {
+            handle,
+            exit: Some(exit_tx),
+            thread: Some(thread),
+        })
+    }
+
+    /// Runs the future on the runtime thread and blocks until it completes
+    /// or the timeout is reached. Calling this from within an async context
+    /// would stall the executor (or deadlock it) so its rejected instead.
+    fn block_on<F, R>(&self, timeout: Duration, future: F) -> Result<R, AteError>
+    where
+        F: Future<Output = Result<R, AteError>> + Send + 'static,
+        R: Send + 'static,
+    {
+        if Handle::try_current().is_ok() {
+            bail!(AteErrorKind::Reentrant);
+        }
+
+        let (tx, rx) = mpsc::channel();
+        self.handle.spawn(async move {
+            let ret = crate::engine::timeout(timeout, future).await;
+            let _ = tx.send(ret);
+        });
+
+        match rx.recv() {
+            Ok(Ok(ret)) => ret,
+            Ok(Err(_)) => bail!(AteErrorKind::Timeout(timeout)),
+            Err(_) => bail!(AteErrorKind::IO(tokio::io::Error::new(
+                tokio::io::ErrorKind::BrokenPipe,
+                "the blocking runtime has shut down"
+            ))),
+        }
+    }
+
+    /// Drops the object on the runtime thread (chains and transactions spawn
+    /// background tasks when they are dropped which needs a runtime)
+    fn drop_on<T>(&self, obj: T)
+    where
+        T: Send + 'static,
+    {
+        self.handle.spawn(async move {
+            drop(obj);
+        });
+    }
+}
+
+impl Drop for BlockingRuntime {
+    fn drop(&mut self) {
+        if let Some(exit) = self.exit.take() {
+            let _ = exit.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            if thread.thread().id() != std::thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// Synchronous facade over the `Registry` for consumers that do not run an
+/// async executor. Every call is dispatched to a dedicated runtime thread
+/// and blocks until it completes (or the timeout is reached). The blocking
+/// methods return `AteErrorKind::Reentrant` when called from an async context.
+pub struct BlockingRegistry {
+    runtime: Arc<BlockingRuntime>,
+    registry: Option<Arc<Registry>>,
+    url: Url,
+    session: Arc<dyn AteSession>,
+    timeout: Duration,
+}
+
+impl BlockingRegistry {
+    /// Creates a registry that opens chains from the remote at the URL
+    pub fn new(cfg_ate: &ConfAte, url: Url) -> Result<BlockingRegistry, AteError> {
+        let runtime = Arc::new(BlockingRuntime::new()?);
+
+        let cfg_ate = cfg_ate.clone();
+        let registry = runtime.block_on(BLOCKING_DEFAULT_TIMEOUT, async move {
+            Ok(Registry::new(&cfg_ate).await.cement())
+        })?;
+
+        Ok(BlockingRegistry {
+            runtime,
+            registry: Some(registry),
+            url,
+            session: Arc::new(AteSessionUser::new()),
+            timeout: BLOCKING_DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// Amount of time each blocking call will wait before giving up
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Session used to read and write the chains that are opened
+    pub fn session(mut self, session: &dyn AteSession) -> Self {
+        self.session = Arc::from(session.clone_session());
+        self
+    }
+
+    /// Opens (or reuses) a chain on the remote
+    pub fn open(&self, key: &ChainKey) -> Result<BlockingChainGuard, AteError> {
+        let registry = self.registry();
+        let url = self.url.clone();
+        let key = key.clone();
+        let chain = self.runtime.block_on(self.timeout, async move {
+            Ok(registry.open(&url, &key, false).await?)
+        })?;
+
+        Ok(BlockingChainGuard {
+            runtime: Arc::clone(&self.runtime),
+            chain: Some(chain),
+            session: Arc::clone(&self.session),
+            timeout: self.timeout,
+            pending: StdMutex::new(None),
+        })
+    }
+
+    /// Returns the async registry that sits behind this facade
+    pub fn registry(&self) -> Arc<Registry> {
+        Arc::clone(self.registry.as_ref().unwrap())
+    }
+}
+
+impl Drop for BlockingRegistry {
+    fn drop(&mut self) {
+        if let Some(registry) = self.registry.take() {
+            self.runtime.drop_on(registry);
+        }
+    }
+}
+
+/// Chain that was opened by a `BlockingRegistry`, data that is stored is
+/// held in a pending transaction until `commit` is called
+pub struct BlockingChainGuard {
+    runtime: Arc<BlockingRuntime>,
+    chain: Option<ChainGuard>,
+    session: Arc<dyn AteSession>,
+    timeout: Duration,
+    pending: StdMutex<Option<Arc<DioMut>>>,
+}
+
+impl BlockingChainGuard {
+    /// Returns the async chain that sits behind this facade
+    pub fn chain(&self) -> ChainGuard {
+        self.chain.as_ref().unwrap().clone()
+    }
+
+    /// Loads an object from the chain (uncommitted data is not visible)
+    pub fn load<D>(&self, key: &PrimaryKey) -> Result<D, AteError>
+    where
+        D: DeserializeOwned + Send + 'static,
+    {
+        let chain = self.chain();
+        let session = Arc::clone(&self.session);
+        let key = key.clone();
+        self.runtime.block_on(self.timeout, async move {
+            let dio = chain.dio(&*session).await;
+            Ok(dio.load_and_take::<D>(&key).await?)
+        })
+    }
+
+    /// Stores an object in the pending transaction and returns its key
+    pub fn store<D>(&self, data: D) -> Result<PrimaryKey, AteError>
+    where
+        D: Clone + Serialize + DeserializeOwned + Send + 'static,
+    {
+        let dio = self.pending()?;
+        self.runtime.block_on(self.timeout, async move {
+            let dao = dio.store(data)?;
+            Ok(dao.key().clone())
+        })
+    }
+
+    /// Stores an object in the pending transaction as a child of a parent
+    /// (in the collection that `iter_collection` reads) and returns its key
+    pub fn push<D>(
+        &self,
+        parent_id: &PrimaryKey,
+        collection_id: u64,
+        data: D,
+    ) -> Result<PrimaryKey, AteError>
+    where
+        D: Clone + Serialize + DeserializeOwned + Send + 'static,
+    {
+        let dio = self.pending()?;
+        let parent_id = parent_id.clone();
+        self.runtime.block_on(self.timeout, async move {
+            let vec = DaoVec::<D>::new_orphaned_mut(&dio, parent_id, collection_id);
+            let dao = vec.push_with_dio(&dio, data)?;
+            Ok(dao.key().clone())
+        })
+    }
+
+    /// Commits everything that was stored since the last commit
+    pub fn commit(&self) -> Result<(), AteError> {
+        let dio = match self.pending.lock().unwrap().take() {
+            Some(a) => a,
+            None => {
+                return Ok(());
+            }
+        };
+        self.runtime.block_on(self.timeout, async move {
+            dio.commit().await?;
+            Ok(())
+        })
+    }
+
+    /// Loads all the objects in a collection that is attached to a parent
+    pub fn iter_collection<D>(
+        &self,
+        parent_id: &PrimaryKey,
+        collection_id: u64,
+    ) -> Result<Vec<(PrimaryKey, D)>, AteError>
+    where
+        D: DeserializeOwned + Send + 'static,
+    {
+        let chain = self.chain();
+        let session = Arc::clone(&self.session);
+        let parent_id = parent_id.clone();
+        self.runtime.block_on(self.timeout, async move {
+            let dio = chain.dio(&*session).await;
+            Ok(dio
+                .children::<D>(parent_id, collection_id)
+                .await?
+                .into_iter()
+                .map(|a| (a.key().clone(), a.take()))
+                .collect())
+        })
+    }
+
+    fn pending(&self) -> Result<Arc<DioMut>, AteError> {
+        let mut guard = self.pending.lock().unwrap();
+        if let Some(dio) = guard.as_ref() {
+            return Ok(Arc::clone(dio));
+        }
+
+        let chain = self.chain();
+        let session = Arc::clone(&self.session);
+        let dio = self.runtime.block_on(self.timeout, async move {
+            Ok(chain.dio_mut(&*session).await?)
+        })?;
+        guard.replace(Arc::clone(&dio));
+        Ok(dio)
+    }
+}
+
+impl Drop for BlockingChainGuard {
+    fn drop(&mut self) {
+        let pending = self.pending.lock().unwrap().take();
+        let chain = self.chain.take();
+        self.runtime.drop_on((pending, chain));
+    }
+}
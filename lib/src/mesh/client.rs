@@ -2,9 +2,12 @@ use crate::{header::PrimaryKey, pipe::EventPipe};
 use async_trait::async_trait;
 use error_chain::bail;
 use fxhash::FxHashMap;
+use std::sync::Mutex as StdMutex;
 use std::sync::Weak;
 use std::time::Duration;
 use std::{collections::hash_map::Entry, sync::Arc};
+use tokio::sync::broadcast;
+use tokio::sync::mpsc;
 use tokio::sync::Mutex;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use tracing_futures::{Instrument, WithSubscriber};
@@ -13,7 +16,12 @@ use super::core::*;
 use super::msg::*;
 use super::session::*;
 use crate::chain::*;
+use crate::comms::InboxProcessor;
+use crate::comms::MeshConfig;
+use crate::comms::Metrics;
+use crate::comms::PacketWithContext;
 use crate::comms::StreamProtocol;
+use crate::comms::Throttle;
 use crate::conf::*;
 use crate::error::*;
 use crate::loader::Loader;
@@ -52,12 +60,15 @@ impl MeshClientSession {
         &'a self,
         client: &MeshClient,
         hello_path: String,
-        loader_local: impl Loader + 'static,
-        loader_remote: impl Loader + 'static,
+        mut loader_local: impl Loader + 'static,
+        mut loader_remote: impl Loader + 'static,
     ) -> Result<Arc<Chain>, ChainCreationError> {
         let mut chain = self.chain.lock().await;
         if let Some(chain) = chain.upgrade() {
+            // The chain is already warm so there is no history to wait for
             trace!("reusing chain {}", self.key);
+            loader_local.end_of_history().await;
+            loader_remote.end_of_history().await;
             return Ok(chain);
         }
 
@@ -79,7 +90,7 @@ impl MeshClientSession {
         debug!(key = self.key.to_string().as_str());
         debug!(path = hello_path.as_str());
 
-        let (peer_addr, _) = match client.lookup.lookup(&self.key) {
+        let (peer_addr, _) = match client.lookup(&self.key) {
             Some(a) => a,
             None => {
                 bail!(ChainCreationErrorKind::NoRootFoundInConfig);
@@ -130,6 +141,16 @@ impl MeshClient {
         })
     }
 
+    /// Finds the root that owns a chain, roots that are discovered from SRV
+    /// records use the latest hash table
+    fn lookup(&self, key: &ChainKey) -> Option<(MeshAddress, u32)> {
+        #[cfg(feature = "enable_dns")]
+        if let Some(discovery) = &self.cfg_mesh.discovery {
+            return discovery.lookup().lookup(key);
+        }
+        self.lookup.lookup(key)
+    }
+
     pub async fn try_open_ext<'a>(
         &'a self,
         key: &ChainKey,
@@ -172,12 +193,110 @@ impl MeshClient {
             .await
     }
 
+    /// Asks the root that owns the key which chain it serves for the key
+    /// (this differs from the key when the key is an alias)
+    pub async fn resolve_alias(
+        &self,
+        key: &ChainKey,
+        hello_path: String,
+    ) -> Result<ChainKey, ChainCreationError> {
+        let msg = Message::ResolveAlias {
+            chain_key: key.clone(),
+        };
+        self.request(key, hello_path, msg, |msg| match msg {
+            Message::AliasResolved { chain_key } => Some(chain_key),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Sends a single request to the root that owns the key (on a connection
+    /// of its own) and waits for the reply that the parser accepts
+    async fn request<T>(
+        &self,
+        key: &ChainKey,
+        hello_path: String,
+        msg: Message,
+        parse: fn(Message) -> Option<T>,
+    ) -> Result<T, ChainCreationError>
+    where
+        T: Send + Sync + 'static,
+    {
+        let (peer_addr, _) = match self.lookup(key) {
+            Some(a) => a,
+            None => {
+                bail!(ChainCreationErrorKind::NoRootFoundInConfig);
+            }
+        };
+        let addr = match &self.cfg_mesh.force_connect {
+            Some(a) => a.clone(),
+            None => peer_addr,
+        };
+        let node_cfg = MeshConfig::new(self.cfg_mesh.clone()).connect_to(addr);
+
+        let (reply_tx, mut reply_rx) = mpsc::channel(1);
+        let (exit_tx, exit_rx) = broadcast::channel(1);
+        let mut tx = crate::comms::connect(
+            &node_cfg,
+            hello_path,
+            self.node_id.clone(),
+            RequestReply {
+                reply: reply_tx,
+                parse,
+            },
+            Arc::new(StdMutex::new(Metrics::default())),
+            Arc::new(StdMutex::new(Throttle::default())),
+            exit_rx,
+        )
+        .await?;
+        tx.send_reply_msg(msg).await?;
+
+        let ret = crate::engine::timeout(self.cfg_ate.load_timeout, reply_rx.recv()).await;
+        let _ = exit_tx.send(());
+        match ret {
+            Ok(Some(ret)) => ret,
+            Ok(None) => Err(CommsError::from(CommsErrorKind::Disconnected).into()),
+            Err(_) => Err(CommsError::from(CommsErrorKind::Timeout).into()),
+        }
+    }
+
     pub fn temporal(mut self, val: bool) -> Self {
         self.temporal = val;
         self
     }
 }
 
+/// Receives the answer to a request that was sent on its own connection
+struct RequestReply<T> {
+    reply: mpsc::Sender<Result<T, ChainCreationError>>,
+    parse: fn(Message) -> Option<T>,
+}
+
+#[async_trait]
+impl<T> InboxProcessor<Message, ()> for RequestReply<T>
+where
+    T: Send + Sync + 'static,
+{
+    async fn process(&mut self, pck: PacketWithContext<Message, ()>) -> Result<(), CommsError> {
+        match pck.packet.msg {
+            Message::FatalTerminate(fatal) => {
+                let _ = self
+                    .reply
+                    .send(Err(ChainCreationErrorKind::ServerRejected(fatal).into()))
+                    .await;
+            }
+            msg => {
+                if let Some(ret) = (self.parse)(msg) {
+                    let _ = self.reply.send(Ok(ret)).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&mut self, _addr: MeshConnectAddr) {}
+}
+
 impl Drop for MeshClient {
     fn drop(&mut self) {
         let span = span!(
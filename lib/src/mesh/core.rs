@@ -9,7 +9,7 @@ use tokio::sync::mpsc;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use crate::chain::*;
-use crate::comms::{PacketData, NodeId};
+use crate::comms::{Packet, PacketData, NodeId};
 use crate::comms::StreamTx;
 use crate::comms::Tx;
 use crate::conf::*;
@@ -17,6 +17,7 @@ use crate::crypto::*;
 use crate::error::*;
 use crate::event::*;
 use crate::index::*;
+use crate::mesh::fanout::HistoryCache;
 use crate::mesh::msg::*;
 use crate::mesh::MeshSession;
 use crate::redo::LogLookup;
@@ -146,6 +147,14 @@ pub struct OpenedChain {
     pub chain: Arc<Chain>,
     pub integrity: TrustMode,
     pub message_of_the_day: Option<String>,
+    #[cfg(feature = "enable_server")]
+    pub(crate) quorum: Option<Arc<super::quorum::QuorumRelay>>,
+    #[cfg(feature = "enable_server")]
+    pub(crate) activity: super::server::ChainActivity,
+    #[cfg(feature = "enable_server")]
+    pub(crate) migration: super::migrate::MigrationSlot,
+    #[cfg(feature = "enable_server")]
+    pub(crate) usage: Arc<super::quota::ChainUsage>,
 }
 
 #[derive(Default)]
@@ -234,11 +243,15 @@ impl MeshHashTable {
     }
 
     pub fn new(cfg_mesh: &ConfMesh) -> MeshHashTable {
+        MeshHashTable::from_roots(cfg_mesh.roots.iter())
+    }
+
+    pub fn from_roots<'a>(roots: impl Iterator<Item = &'a MeshAddress>) -> MeshHashTable {
         let mut index: usize = 0;
 
         let mut addresses = Vec::new();
         let mut hash_table = BTreeMap::new();
-        for addr in cfg_mesh.roots.iter() {
+        for addr in roots {
             addresses.push(addr.clone());
             hash_table.insert(addr.hash(), index);
             index = index + 1;
@@ -256,10 +269,17 @@ async fn stream_events<R>(
     tx: &mut Tx,
     strip_signatures: bool,
     strip_data: usize,
+    cache: Option<&HistoryCache>,
 ) -> Result<(), CommsError>
 where
     R: RangeBounds<ChainTimestamp>,
 {
+    // Cached batches of a chain that has since been compacted are dropped
+    if let Some(cache) = cache {
+        let compactions = chain.metrics.lock().unwrap().compactions;
+        cache.check_compactions(chain.key(), compactions);
+    }
+
     // Declare vars
     let multi = chain.multi().await;
     let mut skip = 0usize;
@@ -267,7 +287,7 @@ where
         Bound::Unbounded => {
             let guard = multi.inside_async.read().await;
             let r = match guard.range(..).map(|a| a.0).next() {
-                Some(a) => a.clone(),
+                Some(a) => a,
                 None => return Ok(()),
             };
             drop(guard);
@@ -296,8 +316,8 @@ where
 
             let mut amount = 0usize;
             while let Some((k, v)) = iter.next() {
-                if *k != start {
-                    start = k.clone();
+                if k != start {
+                    start = k;
                     skip = 1;
                 } else {
                     skip = skip + 1;
@@ -320,6 +340,25 @@ where
             }
         }
 
+        // Batches that were recently streamed to another subscriber are sent
+        // from the cache without loading them again
+        let cache_key = cache.map(|_| {
+            HistoryCache::key(
+                chain.key(),
+                &leafs[..],
+                tx.wire_format,
+                strip_signatures,
+                strip_data,
+            )
+        });
+        if let (Some(cache), Some(cache_key)) = (cache, cache_key.as_ref()) {
+            if let Some(pck) = cache.get(cache_key) {
+                trace!("sending {} cached events", leafs.len());
+                tx.send_reply(pck).await?;
+                continue;
+            }
+        }
+
         let mut evts = Vec::new();
         for evt in multi.load_many(leafs).await? {
             let mut meta = evt.data.meta.clone();
@@ -331,14 +370,11 @@ where
                 meta,
                 data: match evt.data.data_bytes {
                     Some(a) if a.len() <= strip_data => MessageData::Some(a.to_vec()),
-                    Some(a) => {
-                        let data = a.to_vec();
-                        MessageData::LazySome(LazyData {
-                            record: evt.leaf.record,
-                            hash: AteHash::from_bytes(&data[..]),
-                            len: data.len(),
-                        })
-                    },
+                    Some(a) => MessageData::LazySome(LazyData {
+                        record: evt.leaf.record,
+                        hash: AteHash::from_bytes(&a[..]),
+                        len: a.len(),
+                    }),
                     None => MessageData::None
                 },
                 format: evt.header.format,
@@ -347,8 +383,12 @@ where
         }
 
         trace!("sending {} events", evts.len());
-        tx.send_reply_msg(Message::Events { commit: None, evts })
-            .await?;
+        let pck = Packet::from(Message::Events { commit: None, evts })
+            .to_packet_data(tx.wire_format)?;
+        if let (Some(cache), Some(cache_key)) = (cache, cache_key) {
+            cache.insert(chain.key(), cache_key, pck.clone());
+        }
+        tx.send_reply(pck).await?;
     }
 }
 
@@ -380,6 +420,7 @@ pub(super) async fn stream_empty_history(
             to,
             root_keys,
             integrity,
+            total_bytes: Some(0),
         },
     )
     .await?;
@@ -396,6 +437,7 @@ pub(super) async fn stream_history_range<R>(
     tx: &mut Tx,
     strip_signatures: bool,
     strip_data: usize,
+    cache: Option<&HistoryCache>,
 ) -> Result<(), CommsError>
 where
     R: RangeBounds<ChainTimestamp>,
@@ -411,17 +453,20 @@ where
         (chain.integrity, root_keys)
     };
 
-    // Determine how many more events are left to sync
-    let size = {
+    // Determine how many more events are left to sync (and roughly how
+    // much data they carry so that clients can show their progress)
+    let (size, total_bytes) = {
         let guard = chain.multi().await;
         let guard = guard.inside_async.read().await;
         guard
             .range((range.start_bound(), range.end_bound()))
-            .count()
+            .fold((0usize, 0u64), |(size, bytes), (_, header)| {
+                (size + 1, bytes + header.data_size as u64)
+            })
     };
 
     // Let the caller know we will be streaming them events
-    trace!("sending start-of-history (size={})", size);
+    trace!("sending start-of-history (size={}, bytes={})", size, total_bytes);
     tx.send_reply_msg(Message::StartOfHistory {
         size,
         from: match range.start_bound() {
@@ -434,6 +479,7 @@ where
         },
         root_keys,
         integrity: integrity.as_client(),
+        total_bytes: Some(total_bytes),
     })
     .await?;
 
@@ -441,7 +487,7 @@ where
     if size > 0 {
         // Sync the events
         trace!("streaming requested events");
-        stream_events(&chain, range, tx, strip_signatures, strip_data).await?;
+        stream_events(&chain, range, tx, strip_signatures, strip_data, cache).await?;
     }
 
     // Let caller know we have sent all the events that were requested
@@ -0,0 +1,269 @@
+use async_trait::async_trait;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::Mutex;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use super::server::MeshRoot;
+use super::server::OpenChain;
+use crate::chain::ChainKey;
+use crate::comms::ConnectionError;
+use crate::comms::ConnectionSummary;
+use crate::comms::NodeId;
+use crate::comms::RawWebRoute;
+use crate::comms::is_bearer_token;
+use crate::comms::METRICS_MAX_SERIES;
+
+static DASHBOARD_HTML: &'static [u8] = include_bytes!("dashboard/index.html");
+static DASHBOARD_JS: &'static [u8] = include_bytes!("dashboard/dashboard.js");
+static DASHBOARD_CSS: &'static [u8] = include_bytes!("dashboard/dashboard.css");
+
+/// Default amount of time that the list of chains is reused for
+pub const DASHBOARD_REFRESH: Duration = Duration::from_secs(10);
+
+/// Totals across the whole root that the dashboard graphs over time
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DashboardSummary {
+    pub server_id: NodeId,
+    pub connections: usize,
+    pub open_chains: usize,
+    pub stuck_chains: usize,
+    pub dropped_connections: u64,
+    pub received: u64,
+    pub sent: u64,
+    pub requests: u64,
+}
+
+/// Size of a chain (the full statistics are served by the stats route)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DashboardChain {
+    pub route: String,
+    pub chain: ChainKey,
+    pub subscribers: usize,
+    pub idle_secs: u64,
+    pub events: u64,
+    pub live_rows: u64,
+    pub bytes: u64,
+}
+
+/// List that was capped at the series limit, `omitted` counts what was cut
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DashboardList<T> {
+    pub items: Vec<T>,
+    pub omitted: usize,
+}
+
+impl<T> DashboardList<T> {
+    fn capped(mut items: Vec<T>, max: usize) -> DashboardList<T> {
+        let omitted = items.len().saturating_sub(max);
+        items.truncate(max);
+        DashboardList { items, omitted }
+    }
+}
+
+/// Read-only web dashboard of the root (mount it with `add_get_route` on
+/// `/admin/dashboard`). The page itself is served from `{prefix}/index.html`
+/// without authentication while the JSON API that backs it (`{prefix}/api/...`)
+/// requires the access token as a bearer token. Chains and connections are
+/// capped at the same number of series as the other metric exporters, the
+/// busiest are kept. Computing the statistics of the chains is expensive so
+/// the list of chains is reused by every request within the refresh interval.
+pub struct DashboardRoute {
+    root: Arc<MeshRoot>,
+    access_token: String,
+    prefix: String,
+    max_series: usize,
+    refresh: Duration,
+    chains: Mutex<Option<(Instant, DashboardList<DashboardChain>)>>,
+}
+
+impl DashboardRoute {
+    pub fn new(root: &Arc<MeshRoot>, access_token: String) -> DashboardRoute {
+        DashboardRoute {
+            root: Arc::clone(root),
+            access_token,
+            prefix: "/admin/dashboard".to_string(),
+            max_series: METRICS_MAX_SERIES,
+            refresh: DASHBOARD_REFRESH,
+            chains: Mutex::new(None),
+        }
+    }
+
+    /// Changes the path that the route is mounted on (defaults to `/admin/dashboard`)
+    pub fn with_prefix(mut self, prefix: &str) -> DashboardRoute {
+        self.prefix = prefix.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Lowers the number of chains and connections that are listed
+    pub fn with_max_series(mut self, max_series: usize) -> DashboardRoute {
+        self.max_series = max_series.min(METRICS_MAX_SERIES);
+        self
+    }
+
+    /// Changes how long the list of chains is reused for (defaults to 10 seconds)
+    pub fn with_refresh(mut self, refresh: Duration) -> DashboardRoute {
+        self.refresh = refresh;
+        self
+    }
+
+    fn error(msg: &str, code: StatusCode) -> (Vec<u8>, StatusCode) {
+        (msg.as_bytes().to_vec(), code)
+    }
+
+    fn to_json<T: Serialize>(val: &T) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        serde_json::to_vec(val)
+            .map_err(|err| Self::error(err.to_string().as_str(), StatusCode::INTERNAL_SERVER_ERROR))
+    }
+
+    async fn summary(&self) -> DashboardSummary {
+        let connections = self.root.connections();
+        DashboardSummary {
+            server_id: self.root.server_id(),
+            connections: connections.len(),
+            open_chains: self.root.open_chains().await.len(),
+            stuck_chains: self.root.stuck_chains().await,
+            dropped_connections: self.root.dropped_connections(),
+            received: connections.iter().map(|a| a.received).sum(),
+            sent: connections.iter().map(|a| a.sent).sum(),
+            requests: connections.iter().map(|a| a.requests).sum(),
+        }
+    }
+
+    async fn chains(&self) -> DashboardList<DashboardChain> {
+        // Requests that arrive while the list is being computed wait for it
+        // rather than computing it again
+        let mut cached = self.chains.lock().await;
+        if let Some((when, chains)) = cached.as_ref() {
+            if when.elapsed() < self.refresh {
+                return chains.clone();
+            }
+        }
+        let chains = self.compute_chains().await;
+        cached.replace((Instant::now(), chains.clone()));
+        chains
+    }
+
+    async fn compute_chains(&self) -> DashboardList<DashboardChain> {
+        // Busiest chains first (the statistics are only computed for the
+        // chains that make the cut)
+        let mut chains = self.root.open_chains().await;
+        chains.sort_by(|a, b| {
+            b.subscribers
+                .cmp(&a.subscribers)
+                .then(a.idle_secs.cmp(&b.idle_secs))
+        });
+        let chains = DashboardList::capped(chains, self.max_series);
+
+        let mut items = Vec::with_capacity(chains.items.len());
+        for OpenChain {
+            route,
+            chain,
+            subscribers,
+            idle_secs,
+        } in chains.items
+        {
+            let stats = self
+                .root
+                .chain_statistics(route.as_str(), &chain)
+                .await
+                .unwrap_or_default();
+            items.push(DashboardChain {
+                route,
+                chain,
+                subscribers,
+                idle_secs,
+                events: stats.events,
+                live_rows: stats.live_rows,
+                bytes: stats.bytes,
+            });
+        }
+        DashboardList {
+            items,
+            omitted: chains.omitted,
+        }
+    }
+
+    fn connections(&self) -> DashboardList<ConnectionSummary> {
+        let mut connections = self.root.connections();
+        connections.sort_by(|a, b| (b.received + b.sent).cmp(&(a.received + a.sent)));
+        DashboardList::capped(connections, self.max_series)
+    }
+
+    fn errors(&self) -> Vec<ConnectionError> {
+        let mut errors = self.root.recent_errors();
+        errors.reverse();
+        errors
+    }
+}
+
+#[async_trait]
+impl RawWebRoute for DashboardRoute {
+    async fn accepted_raw_post_request(
+        &self,
+        _uri: http::Uri,
+        _headers: http::HeaderMap,
+        _sock_addr: SocketAddr,
+        _server_id: NodeId,
+        _body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        Err(Self::error("the dashboard is read-only", StatusCode::METHOD_NOT_ALLOWED))
+    }
+
+    async fn accepted_raw_put_request(
+        &self,
+        _uri: http::Uri,
+        _headers: http::HeaderMap,
+        _sock_addr: SocketAddr,
+        _server_id: NodeId,
+        _body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        Err(Self::error("the dashboard is read-only", StatusCode::METHOD_NOT_ALLOWED))
+    }
+
+    async fn accepted_raw_get_request(
+        &self,
+        uri: http::Uri,
+        headers: http::HeaderMap,
+        sock_addr: SocketAddr,
+        _server_id: NodeId,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        let path = match uri.path().strip_prefix(self.prefix.as_str()) {
+            Some(a) => a.trim_start_matches('/'),
+            None => {
+                return Err(Self::error("not found", StatusCode::NOT_FOUND));
+            }
+        };
+
+        // The assets of the page hold no data so they are served to anyone
+        match path {
+            "index.html" => return Ok(DASHBOARD_HTML.to_vec()),
+            "dashboard.js" => return Ok(DASHBOARD_JS.to_vec()),
+            "dashboard.css" => return Ok(DASHBOARD_CSS.to_vec()),
+            "" => {
+                let msg = format!("the dashboard is served from {}/index.html", self.prefix);
+                return Err(Self::error(msg.as_str(), StatusCode::NOT_FOUND));
+            }
+            _ => {}
+        }
+
+        if is_bearer_token(&headers, self.access_token.as_str()) == false {
+            warn!("rejected dashboard request from {}", sock_addr);
+            return Err(Self::error("invalid access token", StatusCode::UNAUTHORIZED));
+        }
+
+        trace!("dashboard {} requested by {}", path, sock_addr);
+        match path {
+            "api/summary" => Self::to_json(&self.summary().await),
+            "api/chains" => Self::to_json(&self.chains().await),
+            "api/connections" => Self::to_json(&self.connections()),
+            "api/errors" => Self::to_json(&self.errors()),
+            _ => Err(Self::error("not found", StatusCode::NOT_FOUND)),
+        }
+    }
+}
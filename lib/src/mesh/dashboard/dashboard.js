@@ -0,0 +1,165 @@
+// Read-only dashboard of an ATE root, the API is polled with the access
+// token (kept for the browser session) and the totals are graphed here
+(function () {
+  "use strict";
+
+  var POLL_INTERVAL = 5000;
+  var HISTORY = 120;
+
+  var token = sessionStorage.getItem("ate-dashboard-token");
+  var history = [];
+  var timer = null;
+
+  function $(id) {
+    return document.getElementById(id);
+  }
+
+  function status(msg, error) {
+    var el = $("status");
+    el.textContent = msg;
+    el.className = error ? "error" : "";
+  }
+
+  function api(name) {
+    return fetch("api/" + name, {
+      headers: { "Authorization": "Bearer " + token }
+    }).then(function (resp) {
+      if (resp.ok === false) {
+        return resp.text().then(function (msg) {
+          throw new Error(resp.status + " - " + msg);
+        });
+      }
+      return resp.json();
+    });
+  }
+
+  function cell(row, val) {
+    var td = document.createElement("td");
+    td.textContent = val === null || val === undefined ? "" : String(val);
+    row.appendChild(td);
+  }
+
+  function fill(id, items, columns) {
+    var body = $(id);
+    body.textContent = "";
+    items.forEach(function (item) {
+      var row = document.createElement("tr");
+      columns(item).forEach(function (val) {
+        cell(row, val);
+      });
+      body.appendChild(row);
+    });
+  }
+
+  function omitted(id, list) {
+    $(id).textContent = list.omitted > 0 ? "(" + list.omitted + " more not shown)" : "";
+  }
+
+  function chainName(key) {
+    return typeof key === "string" ? key : key.name;
+  }
+
+  function nodeName(id) {
+    return typeof id === "string" ? id : JSON.stringify(id);
+  }
+
+  function graph(id, values) {
+    var canvas = $(id);
+    var ctx = canvas.getContext("2d");
+    ctx.clearRect(0, 0, canvas.width, canvas.height);
+    if (values.length < 2) {
+      return;
+    }
+    var max = Math.max.apply(null, values.concat([1]));
+    var step = canvas.width / (HISTORY - 1);
+    var offset = (HISTORY - values.length) * step;
+    ctx.strokeStyle = "#36c";
+    ctx.beginPath();
+    values.forEach(function (val, n) {
+      var x = offset + n * step;
+      var y = canvas.height - 2 - (val / max) * (canvas.height - 16);
+      if (n === 0) {
+        ctx.moveTo(x, y);
+      } else {
+        ctx.lineTo(x, y);
+      }
+    });
+    ctx.stroke();
+    ctx.fillStyle = "#777";
+    ctx.fillText(String(Math.round(max)), 2, 10);
+  }
+
+  function rate(field) {
+    var ret = [];
+    for (var n = 1; n < history.length; n++) {
+      var secs = (history[n].at - history[n - 1].at) / 1000;
+      var diff = history[n].summary[field] - history[n - 1].summary[field];
+      ret.push(secs > 0 && diff > 0 ? diff / secs : 0);
+    }
+    return ret;
+  }
+
+  function render(summary, chains, peers, errors) {
+    $("server-id").textContent = nodeName(summary.server_id);
+    $("connections").textContent = summary.connections;
+    $("open-chains").textContent = summary.open_chains;
+    $("stuck-chains").textContent = summary.stuck_chains;
+    $("dropped").textContent = summary.dropped_connections;
+
+    history.push({ at: Date.now(), summary: summary });
+    while (history.length > HISTORY) {
+      history.shift();
+    }
+    graph("graph-received", rate("received"));
+    graph("graph-sent", rate("sent"));
+    graph("graph-requests", rate("requests"));
+    graph("graph-connections", history.map(function (a) {
+      return a.summary.connections;
+    }));
+
+    fill("chains", chains.items, function (a) {
+      return [a.route, chainName(a.chain), a.subscribers, a.idle_secs, a.events, a.live_rows, a.bytes];
+    });
+    omitted("chains-omitted", chains);
+    fill("peers", peers.items, function (a) {
+      return [nodeName(a.node_id), a.addr, a.path, a.connected, a.protocol_version, a.received, a.sent, a.requests];
+    });
+    omitted("connections-omitted", peers);
+    fill("errors", errors, function (a) {
+      return [a.when, a.addr, a.error];
+    });
+  }
+
+  function poll() {
+    Promise.all([api("summary"), api("chains"), api("connections"), api("errors")])
+      .then(function (ret) {
+        render(ret[0], ret[1], ret[2], ret[3]);
+        status("updated " + new Date().toLocaleTimeString(), false);
+      })
+      .catch(function (err) {
+        status(err.message, true);
+      });
+  }
+
+  function start() {
+    if (timer !== null) {
+      clearInterval(timer);
+    }
+    history = [];
+    poll();
+    timer = setInterval(poll, POLL_INTERVAL);
+  }
+
+  $("login").addEventListener("submit", function (evt) {
+    evt.preventDefault();
+    token = $("token").value;
+    sessionStorage.setItem("ate-dashboard-token", token);
+    start();
+  });
+
+  if (token) {
+    start();
+  } else {
+    status("enter the access token to connect", false);
+  }
+})();
@@ -0,0 +1,534 @@
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use std::future::Future;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::net::TcpStream;
+use url::Url;
+
+use super::Registry;
+use crate::comms::hello;
+use crate::comms::key_exchange;
+use crate::comms::CertificateInfo;
+use crate::comms::CertificateValidation;
+use crate::comms::HelloMetadata;
+use crate::comms::StreamProtocol;
+use crate::comms::TlsClient;
+use crate::conf::MeshAddress;
+use crate::crypto::KeySize;
+use crate::trust::ChainKey;
+
+/// Stages that are checked (in order) when diagnosing a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoctorStage {
+    Resolve,
+    Connect,
+    Upgrade,
+    Hello,
+    KeyExchange,
+    ChainOpen,
+}
+
+impl std::fmt::Display for DoctorStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DoctorStage::Resolve => write!(f, "dns resolution"),
+            DoctorStage::Connect => write!(f, "tcp connect"),
+            DoctorStage::Upgrade => write!(f, "protocol upgrade"),
+            DoctorStage::Hello => write!(f, "hello exchange"),
+            DoctorStage::KeyExchange => write!(f, "key exchange"),
+            DoctorStage::ChainOpen => write!(f, "chain open"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoctorStatus {
+    Pass,
+    Fail,
+    Skip,
+}
+
+impl std::fmt::Display for DoctorStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DoctorStatus::Pass => write!(f, "pass"),
+            DoctorStatus::Fail => write!(f, "FAIL"),
+            DoctorStatus::Skip => write!(f, "skip"),
+        }
+    }
+}
+
+/// Outcome of a single stage of the diagnostics
+#[derive(Debug, Clone)]
+pub struct DoctorCheck {
+    pub stage: DoctorStage,
+    pub status: DoctorStatus,
+    pub elapsed: Duration,
+    /// What was found when the stage passed, the exact error when it failed
+    /// or the reason it was skipped
+    pub detail: String,
+    /// Suggestion on how to fix a failed stage
+    pub hint: Option<String>,
+}
+
+/// # Doctor Options
+///
+/// Controls how the diagnostics are run, every stage has its own timeout
+/// so that a black-holed port does not hang the whole run.
+#[derive(Debug, Clone)]
+pub struct DoctorOptions {
+    // Maximum amount of time that a single stage may take
+    pub stage_timeout: Duration,
+    // Wire encryption the client will offer the server during the hello
+    pub wire_encryption: Option<KeySize>,
+    // Chain that will be opened (and synchronized) in the final stage
+    pub chain: Option<ChainKey>,
+}
+
+impl Default for DoctorOptions {
+    fn default() -> DoctorOptions {
+        DoctorOptions {
+            stage_timeout: Duration::from_secs(10),
+            wire_encryption: Some(KeySize::Bit128),
+            chain: None,
+        }
+    }
+}
+
+impl DoctorOptions {
+    pub fn with_stage_timeout(mut self, val: Duration) -> Self {
+        self.stage_timeout = val;
+        self
+    }
+
+    pub fn with_wire_encryption(mut self, val: Option<KeySize>) -> Self {
+        self.wire_encryption = val;
+        self
+    }
+
+    pub fn with_chain(mut self, val: ChainKey) -> Self {
+        self.chain = Some(val);
+        self
+    }
+}
+
+/// Results of diagnosing the connection to a particular URL
+#[derive(Debug, Clone)]
+pub struct DoctorReport {
+    pub url: Url,
+    pub checks: Vec<DoctorCheck>,
+    /// Certificates that the server advertised during the hello
+    pub certificates: Vec<CertificateInfo>,
+}
+
+impl DoctorReport {
+    /// Returns true if none of the stages failed
+    pub fn is_healthy(&self) -> bool {
+        self.failed().is_none()
+    }
+
+    /// Returns the stage that failed (if any)
+    pub fn failed(&self) -> Option<&DoctorCheck> {
+        self.checks.iter().filter(|a| a.status == DoctorStatus::Fail).next()
+    }
+}
+
+impl std::fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Diagnostics for {}", self.url)?;
+        writeln!(f, "{:<18} {:<6} {:>8}  {}", "STAGE", "STATUS", "TIME", "DETAIL")?;
+        for check in self.checks.iter() {
+            let elapsed = match check.status {
+                DoctorStatus::Skip => "-".to_string(),
+                _ => format!("{}ms", check.elapsed.as_millis()),
+            };
+            writeln!(
+                f,
+                "{:<18} {:<6} {:>8}  {}",
+                check.stage.to_string(),
+                check.status.to_string(),
+                elapsed,
+                check.detail
+            )?;
+            if let Some(hint) = &check.hint {
+                writeln!(f, "{:<18} {:<6} {:>8}  hint: {}", "", "", "", hint)?;
+            }
+        }
+        match self.failed() {
+            Some(check) => write!(f, "Connection failed at the {} stage", check.stage),
+            None => write!(f, "All checks passed"),
+        }
+    }
+}
+
+struct StageError {
+    error: String,
+    hint: Option<String>,
+}
+
+impl StageError {
+    fn new(error: impl ToString) -> StageError {
+        StageError {
+            error: error.to_string(),
+            hint: None,
+        }
+    }
+
+    fn with_hint(mut self, hint: impl ToString) -> StageError {
+        self.hint = Some(hint.to_string());
+        self
+    }
+}
+
+struct Doctor {
+    checks: Vec<DoctorCheck>,
+    stage_timeout: Duration,
+    failed: bool,
+}
+
+impl Doctor {
+    /// Runs a stage (unless an earlier one failed) and records its outcome
+    async fn run<T, F>(&mut self, stage: DoctorStage, task: F) -> Option<T>
+    where
+        F: Future<Output = Result<(T, String), StageError>>,
+    {
+        if self.failed {
+            self.skip(stage, "skipped after an earlier failure");
+            return None;
+        }
+
+        let start = Instant::now();
+        let ret = match crate::engine::timeout(self.stage_timeout, task).await {
+            Ok(a) => a,
+            Err(_) => Err(StageError::new(format!(
+                "timed out after {}s",
+                self.stage_timeout.as_secs_f32()
+            ))
+            .with_hint(match stage {
+                DoctorStage::Connect => "the port did not answer, a firewall may be silently dropping the traffic",
+                _ => "the server stopped responding part way through, it may be overloaded or speaking a different protocol",
+            })),
+        };
+
+        let elapsed = start.elapsed();
+        match ret {
+            Ok((ret, detail)) => {
+                self.checks.push(DoctorCheck {
+                    stage,
+                    status: DoctorStatus::Pass,
+                    elapsed,
+                    detail,
+                    hint: None,
+                });
+                Some(ret)
+            }
+            Err(err) => {
+                self.failed = true;
+                self.checks.push(DoctorCheck {
+                    stage,
+                    status: DoctorStatus::Fail,
+                    elapsed,
+                    detail: err.error,
+                    hint: err.hint,
+                });
+                None
+            }
+        }
+    }
+
+    fn skip(&mut self, stage: DoctorStage, reason: &str) {
+        self.checks.push(DoctorCheck {
+            stage,
+            status: DoctorStatus::Skip,
+            elapsed: Duration::ZERO,
+            detail: reason.to_string(),
+            hint: None,
+        });
+    }
+}
+
+/// Runs staged checks against the server behind the URL (dns, tcp, protocol
+/// upgrade, hello, key exchange and optionally opening a chain) and reports
+/// exactly which stage failed with hints on how to fix it
+pub async fn doctor(registry: &Registry, url: &Url, options: DoctorOptions) -> DoctorReport {
+    let mut doctor = Doctor {
+        checks: Vec::new(),
+        stage_timeout: options.stage_timeout,
+        failed: false,
+    };
+
+    // Resolve the addresses of the root nodes and the trusted certificates
+    let resolved = doctor
+        .run(DoctorStage::Resolve, async {
+            let protocol = StreamProtocol::parse(url).map_err(|err| {
+                StageError::new(err).with_hint("supported URL schemes are ws://, wss:// and tcp://")
+            })?;
+            let cfg_mesh = registry.cfg_for_url(url).await.map_err(|err| {
+                let err = StageError::new(err);
+                #[cfg(feature = "enable_dns")]
+                let err = err.with_hint(format!(
+                    "check the domain name is correct and resolvable by the DNS server ({}{})",
+                    registry.cfg_ate.dns_server,
+                    match registry.cfg_ate.dns_sec {
+                        true => " with DNSSec",
+                        false => "",
+                    }
+                ));
+                err
+            })?;
+            let root = match cfg_mesh.roots.first() {
+                Some(a) => a.clone(),
+                None => {
+                    return Err(StageError::new("no root nodes were found for the domain")
+                        .with_hint("add address records for the domain or configure the nodes explicitly"));
+                }
+            };
+            let detail = format!(
+                "{} root(s) [{}], {}",
+                cfg_mesh.roots.len(),
+                cfg_mesh
+                    .roots
+                    .iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                match &cfg_mesh.certificate_validation {
+                    CertificateValidation::AllowAll => "certificate validation disabled".to_string(),
+                    CertificateValidation::DenyAll => "all certificates denied".to_string(),
+                    CertificateValidation::AllowedCertificates(a) => format!("{} trusted certificate(s)", a.len()),
+                }
+            );
+            Ok(((protocol, cfg_mesh, root), detail))
+        })
+        .await;
+
+    // Open a TCP connection to the first root
+    let connected = match resolved {
+        Some((protocol, cfg_mesh, root)) => doctor
+            .run(DoctorStage::Connect, async {
+                let stream = tcp_connect(&root).await.map_err(|err| {
+                    let hint = match err.kind() {
+                        std::io::ErrorKind::ConnectionRefused => Some(format!(
+                            "nothing is listening on {}, check the server is running and the port is correct",
+                            root
+                        )),
+                        std::io::ErrorKind::PermissionDenied => {
+                            Some("the connection was blocked by a local firewall".to_string())
+                        }
+                        _ => None,
+                    };
+                    let err = StageError::new(err);
+                    match hint {
+                        Some(hint) => err.with_hint(hint),
+                        None => err,
+                    }
+                })?;
+                Ok(((protocol, cfg_mesh, stream), format!("connected to {}", root)))
+            })
+            .await,
+        None => {
+            doctor.skip(DoctorStage::Connect, "skipped after an earlier failure");
+            None
+        }
+    };
+
+    // Upgrade the connection to the wire protocol
+    let upgraded = match connected {
+        Some((protocol, cfg_mesh, stream)) => doctor
+            .run(DoctorStage::Upgrade, async {
+                let tls = match protocol.is_tls() {
+                    true => Some(
+                        TlsClient::new(&cfg_mesh, cfg_mesh.domain_name.as_str())
+                            .map_err(|err| StageError::new(err))?,
+                    ),
+                    false => None,
+                };
+                let (rx, tx) = protocol
+                    .upgrade_client_and_split(stream, tls.as_ref())
+                    .await
+                    .map_err(|err| {
+                        StageError::new(err).with_hint(format!(
+                            "the server may not be speaking {} on this port (check the URL scheme and port)",
+                            protocol
+                        ))
+                    })?;
+                Ok(((cfg_mesh, rx, tx), format!("upgraded to {}", protocol)))
+            })
+            .await,
+        None => {
+            doctor.skip(DoctorStage::Upgrade, "skipped after an earlier failure");
+            None
+        }
+    };
+
+    // Exchange the hello messages and negotiate the versions and formats
+    let offered = options.wire_encryption;
+    let greeted = match upgraded {
+        Some((cfg_mesh, rx, tx)) => doctor
+            .run(DoctorStage::Hello, async {
+                let (proto, hello) = hello::mesh_hello_exchange_sender(
+                    rx,
+                    tx,
+                    registry.node_id.clone(),
+                    url.path().to_string(),
+                    cfg_mesh.domain_name.clone(),
+                    offered,
+                    false,
+                    cfg_mesh.max_protocol_version,
+                    None,
+                )
+                .await
+                .map_err(|err| {
+                    let hint = match err.kind() {
+                        std::io::ErrorKind::ConnectionRefused => format!(
+                            "the server offered weaker encryption than the {} the client requires",
+                            key_size_str(offered)
+                        ),
+                        std::io::ErrorKind::Unsupported => format!(
+                            "the client and server have no stream protocol version in common, upgrade the older side (max_protocol_version={})",
+                            cfg_mesh.max_protocol_version
+                        ),
+                        _ => format!(
+                            "the server did not complete the hello, check that the path '{}' is served",
+                            url.path()
+                        ),
+                    };
+                    StageError::new(err).with_hint(hint)
+                })?;
+                let detail = hello_detail(&hello);
+                Ok(((cfg_mesh, proto, hello), detail))
+            })
+            .await,
+        None => {
+            doctor.skip(DoctorStage::Hello, "skipped after an earlier failure");
+            None
+        }
+    };
+
+    // Exchange the keys used for wire encryption and check the certificate
+    let certificates = greeted
+        .as_ref()
+        .map(|(_, _, hello)| hello.certificates.clone())
+        .unwrap_or_default();
+    let exchanged = match greeted {
+        Some((_, _, hello)) if hello.encryption.is_none() => {
+            doctor.skip(DoctorStage::KeyExchange, "wire encryption is disabled");
+            Some(())
+        }
+        Some((cfg_mesh, mut proto, hello)) => doctor
+            .run(DoctorStage::KeyExchange, async {
+                // The server picks the stronger of the two key sizes (a weaker
+                // server was already refused during the hello) so the client
+                // uses whatever was negotiated just like a normal connection
+                let negotiated = hello.encryption.unwrap_or(KeySize::Bit128);
+                let selected = hello.selected_certificate(&cfg_mesh.certificate_validation);
+                let (ek, cert) = key_exchange::mesh_key_exchange_sender_select(
+                    proto.as_mut(),
+                    negotiated,
+                    CertificateValidation::AllowAll,
+                    selected,
+                )
+                .await
+                .map_err(|err| StageError::new(err))?;
+
+                let hash = cert.hash();
+                if cfg_mesh.certificate_validation.validate(&hash) == false {
+                    return Err(StageError::new(format!(
+                        "server certificate {} ({} bits) is not trusted",
+                        hash,
+                        cert.size()
+                    ))
+                    .with_hint(
+                        "publish the certificate hash in the DNS TXT records of the domain or ignore certificates",
+                    ));
+                }
+
+                // Make sure no one tampered with the hello messages on the way
+                if let Some(transcript) = hello.transcript.as_ref() {
+                    let verified = hello::mesh_hello_verify(proto.as_mut(), &ek, transcript, true)
+                        .await
+                        .map_err(|err| StageError::new(err))?;
+                    if verified == false {
+                        return Err(StageError::new("the hello messages were altered in transit").with_hint(
+                            "something between the client and server is rewriting the traffic (e.g. a proxy)",
+                        ));
+                    }
+                }
+
+                Ok((
+                    (),
+                    format!(
+                        "{} bits (client offered {}), server certificate {}",
+                        negotiated,
+                        key_size_str(offered),
+                        hash
+                    ),
+                ))
+            })
+            .await,
+        None => {
+            doctor.skip(DoctorStage::KeyExchange, "skipped after an earlier failure");
+            None
+        }
+    };
+
+    // Open the chain (which uses the normal client connection logic)
+    match (exchanged, options.chain) {
+        (Some(_), Some(key)) => {
+            doctor
+                .run(DoctorStage::ChainOpen, async {
+                    let chain = registry
+                        .open(url, &key, true)
+                        .await
+                        .map_err(|err| StageError::new(err))?;
+                    chain.sync().await.map_err(|err| {
+                        StageError::new(err)
+                            .with_hint("the server rejected the chain, check the credentials and trust mode")
+                    })?;
+                    Ok(((), format!("opened {} ({} events)", key.to_string(), chain.count().await)))
+                })
+                .await;
+        }
+        (Some(_), None) => {
+            doctor.skip(DoctorStage::ChainOpen, "no chain was requested");
+        }
+        (None, _) => {
+            doctor.skip(DoctorStage::ChainOpen, "skipped after an earlier failure");
+        }
+    }
+
+    DoctorReport {
+        url: url.clone(),
+        checks: doctor.checks,
+        certificates,
+    }
+}
+
+#[cfg(feature = "enable_dns")]
+async fn tcp_connect(root: &MeshAddress) -> std::io::Result<TcpStream> {
+    TcpStream::connect(std::net::SocketAddr::new(root.host, root.port)).await
+}
+
+#[cfg(not(feature = "enable_dns"))]
+async fn tcp_connect(root: &MeshAddress) -> std::io::Result<TcpStream> {
+    TcpStream::connect((root.host.as_str(), root.port)).await
+}
+
+fn key_size_str(key_size: Option<KeySize>) -> String {
+    match key_size {
+        Some(a) => format!("{}-bit", a),
+        None => "none".to_string(),
+    }
+}
+
+fn hello_detail(hello: &HelloMetadata) -> String {
+    format!(
+        "server {}, protocol {}, format {}, encryption {}",
+        hello.server_id.to_short_string(),
+        hello.version,
+        hello.wire_format,
+        key_size_str(hello.encryption)
+    )
+}
@@ -0,0 +1,104 @@
+use async_trait::async_trait;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use super::server::MeshRoot;
+use crate::comms::NodeId;
+use crate::comms::RawWebRoute;
+use crate::comms::is_bearer_token;
+
+/// Result of a drain request made over the admin web route
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DrainResponse {
+    pub draining: bool,
+    pub connections: usize,
+}
+
+/// Admin web route that drains a mesh root so that it can be restarted
+/// without dropping the connected clients (mount it with `add_post_route`).
+///
+/// The query string accepts `hint=false` to skip asking the clients to
+/// reconnect elsewhere and `wait=<secs>` to wait for the connections to
+/// close before responding.
+pub struct DrainRoute {
+    root: Arc<MeshRoot>,
+    access_token: String,
+}
+
+impl DrainRoute {
+    pub fn new(root: &Arc<MeshRoot>, access_token: String) -> DrainRoute {
+        DrainRoute {
+            root: Arc::clone(root),
+            access_token,
+        }
+    }
+
+    fn error(msg: &str, code: StatusCode) -> (Vec<u8>, StatusCode) {
+        (msg.as_bytes().to_vec(), code)
+    }
+}
+
+#[async_trait]
+impl RawWebRoute for DrainRoute {
+    async fn accepted_raw_post_request(
+        &self,
+        uri: http::Uri,
+        headers: http::HeaderMap,
+        sock_addr: SocketAddr,
+        _server_id: NodeId,
+        _body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        if is_bearer_token(&headers, self.access_token.as_str()) == false {
+            warn!("rejected drain request from {}", sock_addr);
+            return Err(Self::error("invalid access token", StatusCode::UNAUTHORIZED));
+        }
+
+        let mut hint = true;
+        let mut wait = None;
+        for (key, val) in uri.query().unwrap_or("").split('&').filter_map(|a| a.split_once('=')) {
+            match key {
+                "hint" => {
+                    hint = val.parse::<bool>().map_err(|_| {
+                        Self::error("the hint must be true or false", StatusCode::BAD_REQUEST)
+                    })?;
+                }
+                "wait" => {
+                    let secs = val.parse::<u64>().map_err(|_| {
+                        Self::error("the wait must be a number of seconds", StatusCode::BAD_REQUEST)
+                    })?;
+                    wait = Some(Duration::from_secs(secs));
+                }
+                _ => {}
+            }
+        }
+
+        info!("drain requested by {} (hint={})", sock_addr, hint);
+        let connections = match wait {
+            Some(wait) => self.root.drain_and_wait(hint, wait).await,
+            None => self.root.drain(hint).map(|a| *a.borrow()).unwrap_or(0),
+        };
+
+        let ret = DrainResponse {
+            draining: true,
+            connections,
+        };
+        serde_json::to_vec(&ret)
+            .map_err(|err| Self::error(err.to_string().as_str(), StatusCode::INTERNAL_SERVER_ERROR))
+    }
+
+    async fn accepted_raw_put_request(
+        &self,
+        _uri: http::Uri,
+        _headers: http::HeaderMap,
+        _sock_addr: SocketAddr,
+        _server_id: NodeId,
+        _body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        Err(Self::error("drain requests must be made with POST", StatusCode::BAD_REQUEST))
+    }
+}
@@ -0,0 +1,228 @@
+//! Cache of the history batches that the root has recently streamed to its
+//! subscribers. When many clients subscribe to the same chain at once (e.g.
+//! after a deploy) they receive the same batches which are then served from
+//! the serialized bytes rather than being reloaded from the redo log and
+//! serialized again for every one of them.
+//!
+//! Batches are keyed by the hashes of the events they contain (plus the wire
+//! format and the stripping options) so a cached batch always holds exactly
+//! the events that would have been loaded. Wire encryption happens after the
+//! serialization which means encrypted connections share the same entries.
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex as StdMutex;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::chain::ChainKey;
+use crate::comms::PacketData;
+use crate::crypto::AteHash;
+use crate::index::EventLeaf;
+use crate::spec::SerializationFormat;
+
+/// Default amount of memory (in bytes) that the history cache of a route may use
+pub const DEFAULT_HISTORY_CACHE_SIZE: usize = 32 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct HistoryCacheMetrics {
+    /// Batches that were served from the cache
+    pub hits: u64,
+    /// Batches that had to be loaded from the redo log
+    pub misses: u64,
+    /// Batches that were dropped to stay within the capacity
+    pub evictions: u64,
+    /// Batches that were dropped because their chain was compacted
+    pub invalidations: u64,
+    pub entries: usize,
+    pub bytes: usize,
+    pub capacity: usize,
+}
+
+impl HistoryCacheMetrics {
+    /// Fraction of the batches that were served from the cache
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total <= 0 {
+            return 0.0;
+        }
+        self.hits as f64 / total as f64
+    }
+}
+
+struct HistoryCacheEntry {
+    chain: ChainKey,
+    tick: u64,
+    pck: PacketData,
+}
+
+#[derive(Default)]
+struct HistoryCacheProtected {
+    entries: FxHashMap<AteHash, HistoryCacheEntry>,
+    /// Least recently used entries come first
+    order: BTreeMap<u64, AteHash>,
+    /// Number of compactions of each chain when its entries were added
+    compactions: FxHashMap<ChainKey, u64>,
+    tick: u64,
+    metrics: HistoryCacheMetrics,
+}
+
+/// Bounded LRU of serialized history batches that is shared by all the
+/// subscribers of a route
+pub(crate) struct HistoryCache {
+    inside: StdMutex<HistoryCacheProtected>,
+}
+
+impl HistoryCache {
+    pub(crate) fn new(capacity: usize) -> HistoryCache {
+        let mut inside = HistoryCacheProtected::default();
+        inside.metrics.capacity = capacity;
+        HistoryCache {
+            inside: StdMutex::new(inside),
+        }
+    }
+
+    /// A cache with no capacity is disabled, nothing is ever stored in it
+    pub(crate) fn is_enabled(&self) -> bool {
+        let inside = self.inside.lock().unwrap();
+        inside.metrics.capacity > 0
+    }
+
+    /// Computes the key of a batch of events
+    pub(crate) fn key(
+        chain: &ChainKey,
+        leafs: &[EventLeaf],
+        wire_format: SerializationFormat,
+        strip_signatures: bool,
+        strip_data: usize,
+    ) -> AteHash {
+        let mut data = Vec::with_capacity(leafs.len() * 16 + 64);
+        data.extend_from_slice(chain.name.as_bytes());
+        data.push(wire_format as u8);
+        data.push(strip_signatures as u8);
+        data.extend_from_slice(&(strip_data as u64).to_be_bytes());
+        for leaf in leafs {
+            data.extend_from_slice(leaf.record.as_bytes());
+        }
+        AteHash::from_bytes(&data[..])
+    }
+
+    /// Drops all the entries of the chain if it has been compacted since
+    /// they were added (the compaction count comes from the chain metrics)
+    pub(crate) fn check_compactions(&self, chain: &ChainKey, compactions: u64) {
+        let mut inside = self.inside.lock().unwrap();
+        match inside.compactions.insert(chain.clone(), compactions) {
+            Some(a) if a != compactions => {
+                inside.invalidate(chain);
+            }
+            _ => {}
+        }
+    }
+
+    /// Drops all the entries of a chain
+    pub(crate) fn invalidate(&self, chain: &ChainKey) {
+        let mut inside = self.inside.lock().unwrap();
+        inside.invalidate(chain);
+    }
+
+    pub(crate) fn get(&self, key: &AteHash) -> Option<PacketData> {
+        let mut inside = self.inside.lock().unwrap();
+        inside.tick += 1;
+        let tick = inside.tick;
+
+        let (old, pck) = match inside.entries.get_mut(key) {
+            Some(entry) => {
+                let old = entry.tick;
+                entry.tick = tick;
+                (old, entry.pck.clone())
+            }
+            None => {
+                inside.metrics.misses += 1;
+                return None;
+            }
+        };
+        inside.order.remove(&old);
+        inside.order.insert(tick, key.clone());
+        inside.metrics.hits += 1;
+        Some(pck)
+    }
+
+    pub(crate) fn insert(&self, chain: &ChainKey, key: AteHash, pck: PacketData) {
+        let mut inside = self.inside.lock().unwrap();
+        let len = pck.bytes.len();
+        if inside.metrics.capacity <= 0 || len > inside.metrics.capacity {
+            return;
+        }
+        if let Some(old) = inside.entries.remove(&key) {
+            inside.order.remove(&old.tick);
+            inside.metrics.bytes -= old.pck.bytes.len();
+        }
+
+        // Make room by evicting the least recently used batches
+        let limit = inside.metrics.capacity - len;
+        inside.evict(limit);
+
+        inside.tick += 1;
+        let tick = inside.tick;
+        inside.order.insert(tick, key.clone());
+        inside.entries.insert(
+            key,
+            HistoryCacheEntry {
+                chain: chain.clone(),
+                tick,
+                pck,
+            },
+        );
+        inside.metrics.bytes += len;
+        inside.metrics.entries = inside.entries.len();
+    }
+
+    /// Changes how much memory the cache may use (shrinking it evicts the
+    /// least recently used batches)
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        let mut inside = self.inside.lock().unwrap();
+        inside.metrics.capacity = capacity;
+        inside.evict(capacity);
+    }
+
+    pub(crate) fn metrics(&self) -> HistoryCacheMetrics {
+        let inside = self.inside.lock().unwrap();
+        inside.metrics.clone()
+    }
+}
+
+impl HistoryCacheProtected {
+    /// Evicts the least recently used batches until the cache holds no more
+    /// than the limit
+    fn evict(&mut self, limit: usize) {
+        while self.metrics.bytes > limit {
+            let oldest = match self.order.iter().next() {
+                Some((tick, key)) => (*tick, key.clone()),
+                None => break,
+            };
+            self.order.remove(&oldest.0);
+            if let Some(entry) = self.entries.remove(&oldest.1) {
+                self.metrics.bytes -= entry.pck.bytes.len();
+                self.metrics.evictions += 1;
+            }
+        }
+        self.metrics.entries = self.entries.len();
+    }
+
+    fn invalidate(&mut self, chain: &ChainKey) {
+        let keys = self
+            .entries
+            .iter()
+            .filter(|(_, v)| v.chain == *chain)
+            .map(|(k, v)| (k.clone(), v.tick))
+            .collect::<Vec<_>>();
+        for (key, tick) in keys {
+            self.order.remove(&tick);
+            if let Some(entry) = self.entries.remove(&key) {
+                self.metrics.bytes -= entry.pck.bytes.len();
+                self.metrics.invalidations += 1;
+            }
+        }
+        self.metrics.entries = self.entries.len();
+    }
+}
@@ -0,0 +1,186 @@
+//! Accounting of the memory used by the transactions that are in flight on
+//! the root, that is the events of commits that were received from clients
+//! but have not yet been fed into their chain. A client may send a commit
+//! with tens of thousands of events which are all materialized before the
+//! commit is acknowledged, hence the packets are accounted for before they
+//! are decoded. Every route has a budget for the bytes that a single
+//! connection may have in flight (larger packets are refused and the
+//! connection is closed) and a budget for all the connections of the route
+//! together (beyond it the reads of the noisiest connections are paused until
+//! the memory is freed, releasing it wakes them up again).
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use std::sync::Weak;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::comms::Metrics;
+use crate::comms::Throttle;
+
+/// Default amount of memory (in bytes) that one connection may have in flight
+pub const DEFAULT_INFLIGHT_CONNECTION_LIMIT: usize = 64 * 1024 * 1024;
+/// Default amount of memory (in bytes) that all the connections of a route
+/// may have in flight together
+pub const DEFAULT_INFLIGHT_ROUTE_LIMIT: usize = 512 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct InflightMetrics {
+    /// Bytes that are currently in flight on the route
+    pub bytes: usize,
+    /// Most bytes that were ever in flight on the route at once
+    pub peak_bytes: usize,
+    /// Connections that currently have a commit in flight
+    pub connections: usize,
+    /// Commits that were rejected for exceeding the budget of their connection
+    pub rejected: u64,
+    /// Number of times the reads of a connection were paused
+    pub paused: u64,
+    /// Connections whose reads are currently paused
+    pub paused_connections: usize,
+    pub connection_limit: usize,
+    pub route_limit: usize,
+}
+
+#[derive(Default)]
+struct InflightBudgetProtected {
+    connections: FxHashMap<u64, usize>,
+    paused: FxHashMap<u64, Weak<StdMutex<Throttle>>>,
+    metrics: InflightMetrics,
+}
+
+/// Budget for the bytes that are in flight on the connections of a route
+/// (a limit of zero disables that part of the budget)
+pub(crate) struct InflightBudget {
+    inside: StdMutex<InflightBudgetProtected>,
+}
+
+impl InflightBudget {
+    pub(crate) fn new(connection_limit: usize, route_limit: usize) -> InflightBudget {
+        let mut inside = InflightBudgetProtected::default();
+        inside.metrics.connection_limit = connection_limit;
+        inside.metrics.route_limit = route_limit;
+        InflightBudget {
+            inside: StdMutex::new(inside),
+        }
+    }
+
+    /// Accounts for the bytes of a packet that was received on a connection,
+    /// when the connection would exceed its own budget the packet must be
+    /// refused and the limit that it exceeded is returned instead. When the
+    /// route is over its budget the reads of the connection are paused (if it
+    /// is one of the noisiest) until enough of the memory has been released.
+    pub(crate) fn reserve(
+        self: &Arc<Self>,
+        connection: u64,
+        bytes: usize,
+        throttle: &Arc<StdMutex<Throttle>>,
+        metrics: &Arc<StdMutex<Metrics>>,
+    ) -> Result<InflightReservation, usize> {
+        let mut inside = self.inside.lock().unwrap();
+
+        let limit = inside.metrics.connection_limit;
+        let used = inside.connections.get(&connection).map(|a| *a).unwrap_or(0);
+        if limit > 0 && used + bytes > limit {
+            inside.metrics.rejected += 1;
+            return Err(limit);
+        }
+
+        let used = used + bytes;
+        inside.connections.insert(connection, used);
+        inside.metrics.bytes += bytes;
+        inside.metrics.peak_bytes = inside.metrics.peak_bytes.max(inside.metrics.bytes);
+        inside.metrics.connections = inside.connections.len();
+        metrics.lock().unwrap().inflight_bytes = used as u64;
+
+        // Backpressure is applied to the connections that hold at least their
+        // fair share of what is in flight on the route
+        let limit = inside.metrics.route_limit;
+        if limit > 0 && inside.metrics.bytes > limit {
+            let fair_share = inside.metrics.bytes / inside.connections.len().max(1);
+            if used >= fair_share && inside.paused.contains_key(&connection) == false {
+                debug!(
+                    "route over its in-flight budget ({} of {} bytes) - pausing reads of a connection with {} bytes",
+                    inside.metrics.bytes, limit, used
+                );
+                throttle.lock().unwrap().pause();
+                inside.paused.insert(connection, Arc::downgrade(throttle));
+                inside.metrics.paused += 1;
+                inside.metrics.paused_connections = inside.paused.len();
+            }
+        }
+
+        Ok(InflightReservation {
+            budget: Arc::clone(self),
+            connection,
+            bytes,
+            metrics: Arc::clone(metrics),
+        })
+    }
+
+    fn release(&self, connection: u64, bytes: usize, metrics: &Arc<StdMutex<Metrics>>) {
+        let mut inside = self.inside.lock().unwrap();
+        inside.metrics.bytes = inside.metrics.bytes.saturating_sub(bytes);
+        let used = match inside.connections.get_mut(&connection) {
+            Some(used) => {
+                *used = used.saturating_sub(bytes);
+                *used
+            }
+            None => 0,
+        };
+        if used <= 0 {
+            inside.connections.remove(&connection);
+        }
+        inside.metrics.connections = inside.connections.len();
+        metrics.lock().unwrap().inflight_bytes = used as u64;
+
+        // Once the route is back within its budget the reads resume
+        let limit = inside.metrics.route_limit;
+        if limit <= 0 || inside.metrics.bytes <= limit {
+            inside.resume();
+        }
+    }
+
+    /// Changes the budgets (raising them resumes the reads that were paused)
+    pub(crate) fn set_limits(&self, connection_limit: usize, route_limit: usize) {
+        let mut inside = self.inside.lock().unwrap();
+        inside.metrics.connection_limit = connection_limit;
+        inside.metrics.route_limit = route_limit;
+        if route_limit <= 0 || inside.metrics.bytes <= route_limit {
+            inside.resume();
+        }
+    }
+
+    pub(crate) fn metrics(&self) -> InflightMetrics {
+        let inside = self.inside.lock().unwrap();
+        inside.metrics.clone()
+    }
+}
+
+impl InflightBudgetProtected {
+    fn resume(&mut self) {
+        for (_, throttle) in self.paused.drain() {
+            if let Some(throttle) = throttle.upgrade() {
+                throttle.lock().unwrap().resume();
+            }
+        }
+        self.metrics.paused_connections = 0;
+    }
+}
+
+/// Bytes of a commit that are in flight, they are released from the budget
+/// when this is dropped
+pub(crate) struct InflightReservation {
+    budget: Arc<InflightBudget>,
+    connection: u64,
+    bytes: usize,
+    metrics: Arc<StdMutex<Metrics>>,
+}
+
+impl Drop for InflightReservation {
+    fn drop(&mut self) {
+        self.budget
+            .release(self.connection, self.bytes, &self.metrics);
+    }
+}
@@ -0,0 +1,390 @@
+use async_trait::async_trait;
+use error_chain::bail;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use tokio::sync::RwLock;
+use tokio::sync::RwLockReadGuard;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use url::Url;
+
+use super::core::MeshHashTable;
+use super::quorum::QuorumPolicy;
+use super::quorum::QuorumRelay;
+use super::server::MeshRoot;
+use super::server::RouteChain;
+use crate::chain::Chain;
+use crate::chain::ChainKey;
+use crate::comms::NodeId;
+use crate::comms::RawWebRoute;
+use crate::comms::is_bearer_token;
+use crate::conf::ConfAte;
+use crate::conf::MeshAddress;
+use crate::error::*;
+use crate::event::EventWeakData;
+
+/// Shared by a chain hosted on the root and every session subscribed to it
+/// so that a migration started later is also seen by the existing sessions
+pub(crate) type MigrationSlot = Arc<StdMutex<Option<Arc<ChainMigration>>>>;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum MigrationPhase {
+    /// The existing history is being copied to the destination (commits
+    /// are already being forwarded)
+    Copying,
+    /// The history has been copied and the commits are being forwarded,
+    /// the migration is ready to cut over
+    Forwarding,
+    /// Commits are paused while the destination catches up one last time
+    CuttingOver,
+    /// The destination now serves the chain and clients are redirected to it
+    Migrated,
+    /// The copy failed, the source is still authoritative and the cutover
+    /// may be attempted again (it copies whatever is missing)
+    Failed { err: String },
+    /// The migration was aborted and the source remains authoritative
+    Aborted,
+}
+
+impl std::fmt::Display for MigrationPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MigrationPhase::Copying => write!(f, "copying"),
+            MigrationPhase::Forwarding => write!(f, "forwarding"),
+            MigrationPhase::CuttingOver => write!(f, "cutting-over"),
+            MigrationPhase::Migrated => write!(f, "migrated"),
+            MigrationPhase::Failed { err } => write!(f, "failed ({})", err),
+            MigrationPhase::Aborted => write!(f, "aborted"),
+        }
+    }
+}
+
+/// Progress of a chain that is being migrated away from this root
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MigrationStatus {
+    pub route: String,
+    pub chain: ChainKey,
+    pub destination: String,
+    pub phase: MigrationPhase,
+    /// Events copied by the bulk transfer and the cutover
+    pub copied: u64,
+    /// Commits that were forwarded to the destination
+    pub forwarded: u64,
+    /// Commits that could not be forwarded (they are copied at the cutover)
+    pub forward_failures: u64,
+}
+
+/// Live migration of a chain to another root. Commits made on the source
+/// are forwarded to the destination for as long as the migration runs while
+/// the existing history is copied in the background. The cutover pauses the
+/// commits, copies anything the destination is still missing and only then
+/// hands the chain over, if anything fails the source stays authoritative.
+pub(crate) struct ChainMigration {
+    route: RouteChain,
+    destination: Url,
+    /// Root at the destination that the clients are redirected to
+    pub(crate) address: MeshAddress,
+    relay: Arc<QuorumRelay>,
+    phase: StdMutex<MigrationPhase>,
+    writes: RwLock<()>,
+    copied: AtomicU64,
+    forwarded: AtomicU64,
+    forward_failures: AtomicU64,
+}
+
+impl ChainMigration {
+    pub(crate) async fn new(
+        cfg_ate: &ConfAte,
+        route: RouteChain,
+        destination: Url,
+    ) -> Result<Arc<ChainMigration>, ChainCreationError> {
+        // The destination is treated as the one and only replica of the chain
+        let policy = QuorumPolicy::default()
+            .with_replica(destination.clone())
+            .with_required(1);
+        let relay = QuorumRelay::new(cfg_ate, &route.chain, policy).await;
+
+        // Clients are redirected to the same root the relay connects to
+        let cfg_mesh = relay.registry().cfg_for_url(&destination).await?;
+        let address = match MeshHashTable::new(&cfg_mesh).lookup(&route.chain) {
+            Some((a, _)) => a,
+            None => {
+                bail!(ChainCreationErrorKind::NoRootFoundForDomain(
+                    destination.to_string()
+                ));
+            }
+        };
+
+        Ok(Arc::new(ChainMigration {
+            route,
+            destination,
+            address,
+            relay,
+            phase: StdMutex::new(MigrationPhase::Copying),
+            writes: RwLock::new(()),
+            copied: AtomicU64::new(0),
+            forwarded: AtomicU64::new(0),
+            forward_failures: AtomicU64::new(0),
+        }))
+    }
+
+    pub(crate) fn phase(&self) -> MigrationPhase {
+        self.phase.lock().unwrap().clone()
+    }
+
+    pub(crate) fn is_migrated(&self) -> bool {
+        self.phase() == MigrationPhase::Migrated
+    }
+
+    pub(crate) fn status(&self) -> MigrationStatus {
+        MigrationStatus {
+            route: self.route.route.clone(),
+            chain: self.route.chain.clone(),
+            destination: self.destination.to_string(),
+            phase: self.phase(),
+            copied: self.copied.load(Ordering::Relaxed),
+            forwarded: self.forwarded.load(Ordering::Relaxed),
+            forward_failures: self.forward_failures.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Bulk transfer of the history that existed before the migration started
+    pub(crate) async fn copy_history(&self, chain: &Arc<Chain>) {
+        let ret = self.relay.backfill(chain).await;
+        let mut phase = self.phase.lock().unwrap();
+        match ret {
+            Ok(cnt) => {
+                debug!("migration of {} copied {} events", self.route.chain, cnt);
+                self.copied.fetch_add(cnt as u64, Ordering::Relaxed);
+                if *phase == MigrationPhase::Copying {
+                    *phase = MigrationPhase::Forwarding;
+                }
+            }
+            Err(err) => {
+                warn!("migration of {} failed to copy the history - {}", self.route.chain, err);
+                if *phase == MigrationPhase::Copying {
+                    *phase = MigrationPhase::Failed {
+                        err: err.to_string(),
+                    };
+                }
+            }
+        }
+    }
+
+    /// Held by every commit while the chain is migrating so that the cutover
+    /// can wait for the commits that are in flight, fails once the chain has
+    /// been handed over
+    pub(crate) async fn begin_commit(&self) -> Result<RwLockReadGuard<'_, ()>, CommitError> {
+        let guard = self.writes.read().await;
+        if self.is_migrated() {
+            bail!(CommitErrorKind::ChainMigrated(self.destination.to_string()));
+        }
+        Ok(guard)
+    }
+
+    /// Forwards a commit that the source accepted to the destination, failures
+    /// are only counted as the cutover copies whatever is missing
+    pub(crate) async fn forward(&self, evts: &Vec<EventWeakData>) {
+        match self.relay.relay(evts).await {
+            Ok(()) => {
+                self.forwarded.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(err) => {
+                debug!("migration of {} failed to forward a commit - {}", self.route.chain, err);
+                self.forward_failures.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Pauses the commits until the destination has every event of the chain
+    /// and then marks the chain as migrated, on failure the commits resume on
+    /// the source as if nothing happened
+    pub(crate) async fn cutover(&self, chain: &Arc<Chain>) -> Result<(), ChainCreationError> {
+        {
+            let mut phase = self.phase.lock().unwrap();
+            match &*phase {
+                MigrationPhase::Forwarding | MigrationPhase::Failed { .. } => {}
+                other => {
+                    bail!(ChainCreationErrorKind::MigrationNotReady(
+                        self.route.chain.to_string(),
+                        other.to_string()
+                    ));
+                }
+            }
+            *phase = MigrationPhase::CuttingOver;
+        }
+
+        let _writes = self.writes.write().await;
+        match self.relay.backfill(chain).await {
+            Ok(cnt) => {
+                info!("migration of {} cut over to {} ({} events caught up)", self.route.chain, self.destination, cnt);
+                self.copied.fetch_add(cnt as u64, Ordering::Relaxed);
+                *self.phase.lock().unwrap() = MigrationPhase::Migrated;
+                Ok(())
+            }
+            Err(err) => {
+                warn!("migration of {} failed to cut over - {}", self.route.chain, err);
+                *self.phase.lock().unwrap() = MigrationPhase::Failed {
+                    err: err.to_string(),
+                };
+                bail!(ChainCreationErrorKind::MigrationFailed(err.to_string()));
+            }
+        }
+    }
+
+    /// Stops the migration (waiting for a cutover that is in progress), once
+    /// the chain has migrated it can no longer be aborted
+    pub(crate) async fn abort(&self) -> Result<(), ChainCreationError> {
+        let _writes = self.writes.write().await;
+        let mut phase = self.phase.lock().unwrap();
+        if *phase == MigrationPhase::Migrated {
+            bail!(ChainCreationErrorKind::ChainMigrated(
+                self.route.chain.to_string(),
+                self.destination.to_string()
+            ));
+        }
+        *phase = MigrationPhase::Aborted;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum MigrateAction {
+    /// Run on the destination so that it serves the chain before it is
+    /// the root that the chain hashes to
+    Accept,
+    /// Run on the destination after an aborted migration so that it stops
+    /// serving the chain (the copied data is left untouched)
+    Release,
+    /// Run on the source to enter migrate mode and start copying the history
+    Start { destination: String },
+    /// Run on the source to hand the chain over to the destination
+    Cutover,
+    /// Run on the source to stop the migration before it cuts over
+    Abort,
+    /// Reports the migrations of the root
+    Status,
+}
+
+/// Request made to the admin migrate route
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MigrateRequest {
+    pub route: String,
+    pub chain: String,
+    pub action: MigrateAction,
+}
+
+/// Result of a request made to the admin migrate route
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MigrateResponse {
+    /// Chains that this root is migrating (or has migrated) elsewhere
+    pub migrations: Vec<MigrationStatus>,
+    /// Chains that this root accepted from other roots
+    pub accepted: Vec<RouteChain>,
+}
+
+/// Admin web route that migrates chains between roots without taking them
+/// offline (mount it with `add_post_route`). The body of the request is a
+/// `MigrateRequest` in JSON and the response lists the migrations of the root.
+///
+/// A migration is `Accept`ed on the destination, `Start`ed on the source and
+/// once its status reaches `Forwarding` it is `Cutover` on the source. Clients
+/// of a migrated chain are relayed to the destination by the source until the
+/// roots they are configured with are updated.
+pub struct MigrateRoute {
+    root: Arc<MeshRoot>,
+    access_token: String,
+}
+
+impl MigrateRoute {
+    pub fn new(root: &Arc<MeshRoot>, access_token: String) -> MigrateRoute {
+        MigrateRoute {
+            root: Arc::clone(root),
+            access_token,
+        }
+    }
+
+    fn error(msg: &str, code: StatusCode) -> (Vec<u8>, StatusCode) {
+        (msg.as_bytes().to_vec(), code)
+    }
+}
+
+#[async_trait]
+impl RawWebRoute for MigrateRoute {
+    async fn accepted_raw_post_request(
+        &self,
+        _uri: http::Uri,
+        headers: http::HeaderMap,
+        sock_addr: SocketAddr,
+        _server_id: NodeId,
+        body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        if is_bearer_token(&headers, self.access_token.as_str()) == false {
+            warn!("rejected migrate request from {}", sock_addr);
+            return Err(Self::error("invalid access token", StatusCode::UNAUTHORIZED));
+        }
+
+        let req: MigrateRequest = serde_json::from_slice(&body[..])
+            .map_err(|err| Self::error(err.to_string().as_str(), StatusCode::BAD_REQUEST))?;
+        let route = req.route.as_str();
+        let chain = ChainKey::from(req.chain.clone());
+
+        info!("migrate {:?} of {} requested by {}", req.action, req.chain, sock_addr);
+        let ret = match req.action {
+            MigrateAction::Accept => {
+                self.root.accept_migration(route, &chain);
+                Ok(())
+            }
+            MigrateAction::Release => {
+                self.root.release_migration(route, &chain);
+                Ok(())
+            }
+            MigrateAction::Start { destination } => {
+                let destination = Url::parse(destination.as_str())
+                    .map_err(|err| Self::error(err.to_string().as_str(), StatusCode::BAD_REQUEST))?;
+                self.root
+                    .start_migration(route, &chain, destination)
+                    .await
+                    .map(|_| ())
+            }
+            MigrateAction::Cutover => self.root.cutover_migration(route, &chain).await.map(|_| ()),
+            MigrateAction::Abort => self.root.abort_migration(route, &chain).await.map(|_| ()),
+            MigrateAction::Status => Ok(()),
+        };
+        match ret {
+            Ok(()) => {}
+            Err(err @ ChainCreationError(ChainCreationErrorKind::MigrationNotFound(_), _)) => {
+                return Err(Self::error(err.to_string().as_str(), StatusCode::NOT_FOUND));
+            }
+            Err(ChainCreationError(ChainCreationErrorKind::MigrationFailed(err), _)) => {
+                return Err(Self::error(err.as_str(), StatusCode::INTERNAL_SERVER_ERROR));
+            }
+            Err(err) => {
+                return Err(Self::error(err.to_string().as_str(), StatusCode::BAD_REQUEST));
+            }
+        }
+
+        let ret = MigrateResponse {
+            migrations: self.root.migrations(),
+            accepted: self.root.accepted_migrations(),
+        };
+        serde_json::to_vec(&ret)
+            .map_err(|err| Self::error(err.to_string().as_str(), StatusCode::INTERNAL_SERVER_ERROR))
+    }
+
+    async fn accepted_raw_put_request(
+        &self,
+        _uri: http::Uri,
+        _headers: http::HeaderMap,
+        _sock_addr: SocketAddr,
+        _server_id: NodeId,
+        _body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        Err(Self::error("migrate requests must be made with POST", StatusCode::BAD_REQUEST))
+    }
+}
@@ -4,11 +4,31 @@ use tracing::trace;
 use tracing::{debug, error, info};
 
 mod active_session_pipe;
+#[cfg(feature = "enable_server")]
+mod alias;
+#[cfg(feature = "enable_full")]
+mod blocking;
 #[cfg(feature = "enable_client")]
 mod client;
 mod core;
+#[cfg(feature = "enable_dashboard")]
+mod dashboard;
+#[cfg(all(feature = "enable_client", feature = "enable_full"))]
+mod doctor;
+#[cfg(feature = "enable_server")]
+mod drain;
+mod fanout;
+#[cfg(feature = "enable_server")]
+mod inflight;
 mod lock_request;
+#[cfg(feature = "enable_server")]
+mod migrate;
 mod msg;
+mod outbound_wal;
+#[cfg(feature = "enable_server")]
+mod public;
+mod quorum;
+mod quota;
 mod recoverable_session_pipe;
 #[cfg(feature = "enable_server")]
 mod redirect;
@@ -16,7 +36,12 @@ mod registry;
 #[cfg(feature = "enable_server")]
 mod server;
 mod session;
+#[cfg(feature = "enable_server")]
+mod stats;
+#[cfg(feature = "enable_server")]
+mod tasks;
 mod test;
+mod watchdog;
 
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -63,14 +88,50 @@ pub(crate) use crate::mesh::client::MeshClient;
 pub(crate) use session::MeshSession;
 
 pub use crate::mesh::core::MeshHashTable;
+#[cfg(feature = "enable_full")]
+pub use self::blocking::*;
 pub use self::core::BackupMode;
 pub use self::core::RecoveryMode;
+pub use self::fanout::HistoryCacheMetrics;
+pub use self::fanout::DEFAULT_HISTORY_CACHE_SIZE;
+#[cfg(feature = "enable_server")]
+pub use self::inflight::InflightMetrics;
+#[cfg(feature = "enable_server")]
+pub use self::inflight::DEFAULT_INFLIGHT_CONNECTION_LIMIT;
+#[cfg(feature = "enable_server")]
+pub use self::inflight::DEFAULT_INFLIGHT_ROUTE_LIMIT;
+#[cfg(all(feature = "enable_client", feature = "enable_full"))]
+pub use self::doctor::*;
 pub use self::msg::FatalTerminate;
+pub use self::quorum::QuorumPolicy;
+pub use self::quota::ChainQuota;
+pub use self::quota::QuotaStatus;
+pub use self::quota::DEFAULT_CHAIN_QUOTA;
+pub use self::quota::QUOTA_WARN_PERCENT;
+pub use self::watchdog::WatchdogPolicy;
 pub use crate::loader::Loader;
 pub use crate::mesh::registry::ChainGuard;
 pub use crate::mesh::registry::Registry;
 #[cfg(feature = "enable_server")]
 pub use crate::mesh::server::MeshRoot;
+#[cfg(feature = "enable_server")]
+pub use crate::mesh::server::RouteMode;
+#[cfg(feature = "enable_server")]
+pub use crate::mesh::server::OpenChain;
+#[cfg(feature = "enable_dashboard")]
+pub use self::dashboard::*;
+#[cfg(feature = "enable_server")]
+pub use self::drain::*;
+#[cfg(feature = "enable_server")]
+pub use self::alias::*;
+#[cfg(feature = "enable_server")]
+pub use self::migrate::*;
+#[cfg(feature = "enable_server")]
+pub use self::stats::*;
+#[cfg(feature = "enable_server")]
+pub use self::tasks::*;
+#[cfg(feature = "enable_server")]
+pub use self::public::*;
 
 fn create_prepare<'a, 'b>(cfg_mesh: &'b ConfMesh) -> (Vec<MeshAddress>, Vec<MeshAddress>) {
     let mut hash_table = BTreeMap::new();
@@ -91,11 +152,16 @@ fn create_prepare<'a, 'b>(cfg_mesh: &'b ConfMesh) -> (Vec<MeshAddress>, Vec<Mesh
 
     #[cfg(feature = "enable_dns")]
     {
-        let local_ips = pnet::datalink::interfaces()
-            .iter()
-            .flat_map(|i| i.ips.iter())
-            .map(|i| i.ip())
-            .collect::<Vec<_>>();
+        // When the interfaces can not be enumerated (e.g. static musl builds)
+        // the roots that this host can bind to are the ones that are local
+        let local_ips = match local_interface_ips() {
+            Some(a) => a,
+            None => {
+                let ret = bindable_root_ips(cfg_mesh);
+                debug!("found {} bindable root addresses", ret.len());
+                ret
+            }
+        };
         if listen_root_addresses.len() <= 0 && cfg_mesh.force_client_only == false {
             for local_ip in local_ips.iter() {
                 trace!("Found Local IP - {}", local_ip);
@@ -144,6 +210,47 @@ fn create_prepare<'a, 'b>(cfg_mesh: &'b ConfMesh) -> (Vec<MeshAddress>, Vec<Mesh
     (listen_root_addresses, all_root_addresses)
 }
 
+/// Returns the IP addresses of the local network interfaces or `None` if
+/// they could not be enumerated on this platform
+#[cfg(feature = "enable_dns")]
+fn local_interface_ips() -> Option<Vec<IpAddr>> {
+    #[cfg(not(target_env = "musl"))]
+    {
+        let ret = std::panic::catch_unwind(|| {
+            pnet::datalink::interfaces()
+                .iter()
+                .flat_map(|i| i.ips.iter())
+                .map(|i| i.ip())
+                .collect::<Vec<_>>()
+        });
+        match ret {
+            Ok(a) if a.len() > 0 => {
+                return Some(a);
+            }
+            Ok(_) => debug!("no network interfaces were found"),
+            Err(_) => error!("failed to enumerate the network interfaces"),
+        }
+    }
+    None
+}
+
+/// Returns the addresses of the configured roots that this host is able to
+/// bind to (which are the addresses of its own network interfaces)
+#[cfg(feature = "enable_dns")]
+fn bindable_root_ips(cfg_mesh: &ConfMesh) -> Vec<IpAddr> {
+    let mut ret = Vec::new();
+    for root in cfg_mesh.roots.iter() {
+        if ret.contains(&root.host) {
+            continue;
+        }
+        let addr = std::net::SocketAddr::new(root.host, 0);
+        if std::net::TcpListener::bind(addr).is_ok() {
+            ret.push(root.host);
+        }
+    }
+    ret
+}
+
 #[cfg(feature = "enable_server")]
 pub async fn create_persistent_centralized_server(
     cfg_ate: &ConfAte,
@@ -20,6 +20,7 @@ use crate::{
     meta::{CoreMetadata, Metadata},
 };
 
+use super::quota::QuotaStatus;
 use super::NodeId;
 pub type MessageData = LogData;
 pub type MessageDataRef<'a> = LogDataRef<'a>;
@@ -153,6 +154,10 @@ pub(super) enum Message {
         to: Option<ChainTimestamp>,
         integrity: TrustMode,
         root_keys: Vec<PublicSignKey>,
+        /// Approximate number of data bytes that will be streamed (older
+        /// servers do not send this so it is read leniently)
+        #[serde(default, deserialize_with = "deserialize_or_none")]
+        total_bytes: Option<u64>,
     },
     Events {
         commit: Option<u64>,
@@ -165,6 +170,10 @@ pub(super) enum Message {
     CommitError {
         id: u64,
         err: String,
+        /// Reason for the rejection that the client can act upon (older
+        /// servers do not send this so it is read leniently)
+        #[serde(default, deserialize_with = "deserialize_or_none")]
+        rejection: Option<CommitRejection>,
     },
 
     FatalTerminate(FatalTerminate),
@@ -182,6 +191,75 @@ pub(super) enum Message {
     LoadManyFailed {
         id: u64,
         err: String,
+    },
+
+    /// Admin trigger that compacts the subscribed chain immediately
+    CompactNow,
+    CompactResult {
+        err: Option<String>,
+    },
+
+    /// Sent by a server that is draining to ask the client to reconnect
+    /// to another root (the current connection keeps working until then)
+    Reconnect,
+
+    /// Asks the server for the chain that serves the key (following any
+    /// aliases that are registered on the root)
+    ResolveAlias {
+        chain_key: ChainKey,
+    },
+    AliasResolved {
+        chain_key: ChainKey,
+    },
+
+    /// Asks the root how much of its storage quota the subscribed chain is
+    /// using, the root only ever answers for the chain that the connection
+    /// is subscribed to. Once a session has asked it is also sent the status
+    /// unprompted when its commits take the chain close to its quota (older
+    /// clients never ask hence they are never sent it).
+    QuotaStatus {
+        chain_key: ChainKey,
+    },
+    QuotaStatusResult(QuotaStatus),
+}
+
+/// Reason that a root rejected a commit for, it is sent next to the text of
+/// the error so that clients can rebuild the matching error kind
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(super) enum CommitRejection {
+    /// The chain would exceed its storage quota
+    QuotaExceeded {
+        used: u64,
+        limit: u64,
+    },
+    /// A patch was written against a version of the row that is no longer
+    /// the latest version
+    PatchConflict {
+        key: String,
+    },
+}
+
+impl CommitRejection {
+    pub(super) fn to_commit_error(&self) -> CommitError {
+        match self {
+            CommitRejection::QuotaExceeded { used, limit } => {
+                CommitErrorKind::QuotaExceeded(*used, *limit).into()
+            }
+            CommitRejection::PatchConflict { key } => {
+                CommitErrorKind::PatchConflict(key.clone()).into()
+            }
+        }
+    }
+
+    /// Returns the rejection for errors that the client needs to tell apart
+    /// from other failed commits
+    pub(super) fn from_commit_error(err: &CommitError) -> Option<CommitRejection> {
+        match err.kind() {
+            CommitErrorKind::PatchConflict(key) => {
+                Some(CommitRejection::PatchConflict { key: key.clone() })
+            }
+            _ => None,
+        }
     }
 }
 
@@ -216,8 +294,11 @@ impl std::fmt::Display for Message {
                 }
             },
             Message::NewConversation { conversation_id } => write!(f, "new-conversation(id={})", conversation_id),
-            Message::StartOfHistory { size, from, to, integrity, root_keys } => {
+            Message::StartOfHistory { size, from, to, integrity, root_keys, total_bytes } => {
                 write!(f, "start-of-history(size={}", size)?;
+                if let Some(total_bytes) = total_bytes {
+                    write!(f, ", bytes={}", total_bytes)?;
+                }
                 if let Some(from) = from {
                     write!(f, ", from={}", from)?;
                 }
@@ -235,12 +316,22 @@ impl std::fmt::Display for Message {
             },
             Message::EndOfHistory => write!(f, "end-of-history"),
             Message::Confirmed(id) => write!(f, "confirmed({})", id),
-            Message::CommitError { id, err } => write!(f, "commit-error(id={}, err='{}')", id, err),
+            Message::CommitError { id, err, .. } => write!(f, "commit-error(id={}, err='{}')", id, err),
             Message::FatalTerminate(why) => write!(f, "fatal-terminate({})", why),
             Message::SecuredWith(sess) => write!(f, "secured-with({})", sess),
             Message::LoadMany { id, leafs } => write!(f, "load-many(id={}, cnt={})", id, leafs.len()),
             Message::LoadManyResult { id, data } => write!(f, "load-many-result(id={}, cnt={})", id, data.len()),
             Message::LoadManyFailed { id, err } => write!(f, "load-many-failed(id={})-{}", id, err),
+            Message::CompactNow => write!(f, "compact-now"),
+            Message::CompactResult { err } => match err {
+                Some(err) => write!(f, "compact-result(err='{}')", err),
+                None => write!(f, "compact-result(ok)"),
+            },
+            Message::Reconnect => write!(f, "reconnect"),
+            Message::ResolveAlias { chain_key } => write!(f, "resolve-alias(chain_key={})", chain_key),
+            Message::AliasResolved { chain_key } => write!(f, "alias-resolved(chain_key={})", chain_key),
+            Message::QuotaStatus { chain_key } => write!(f, "quota-status(chain_key={})", chain_key),
+            Message::QuotaStatusResult(status) => write!(f, "quota-status-result(chain_key={}, {})", status.chain, status),
         }
     }
 }
@@ -250,3 +341,30 @@ impl Default for Message {
         Message::Noop
     }
 }
+
+/// Reads a field that was appended to a message after it was first released,
+/// binary formats run out of bytes when the peer is older so rather than
+/// failing the whole message the field is treated as absent (any other
+/// decode error means the message is corrupt and is returned as is)
+fn deserialize_or_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    match Option::<T>::deserialize(deserializer) {
+        Ok(a) => Ok(a),
+        Err(err) if is_end_of_input(&err) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns true if the decode error is the serializer running out of bytes,
+/// the deserializer is generic so the only thing left to go on is the message
+/// (bincode reports a bare IO error as its readers only fail at the end)
+fn is_end_of_input(err: &impl std::fmt::Display) -> bool {
+    let err = err.to_string().to_lowercase();
+    err.trim_end() == "io error:"
+        || err.contains("end of file")
+        || err.contains("fill whole buffer")
+        || err.contains("unexpected eof")
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Read;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use super::msg::MessageEvent;
+use crate::conf::ConfAte;
+use crate::crypto::AteHash;
+use crate::trust::ChainKey;
+
+/// Maximum amount of time that a replayed commit may take to be confirmed
+pub(super) const WAL_REPLAY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Size of the header in front of every record (length and checksum)
+const WAL_RECORD_HEADER: usize = 4 + AteHash::LEN;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+enum WalRecord {
+    Commit { id: u64, events: Vec<MessageEvent> },
+    Confirmed { id: u64 },
+}
+
+/// Write-ahead file that holds the commits a client has made that the root
+/// has not yet confirmed, the commits survive a restart of the process and
+/// are replayed (in their original order) the next time the chain connects.
+/// The file is emptied whenever every commit in it has been confirmed.
+pub(super) struct OutboundWal {
+    path: String,
+    state: StdMutex<WalState>,
+}
+
+struct WalState {
+    file: File,
+    next_id: u64,
+    unconfirmed: BTreeMap<u64, Vec<MessageEvent>>,
+}
+
+impl OutboundWal {
+    /// Opens the write-ahead file of a chain (if the configuration asks for
+    /// one and the chain has a log path to put it in)
+    #[allow(unused_variables)]
+    pub(super) fn open(cfg: &ConfAte, key: &ChainKey) -> std::io::Result<Option<Arc<OutboundWal>>> {
+        if cfg.outbound_wal == false {
+            return Ok(None);
+        }
+
+        #[cfg(feature = "enable_local_fs")]
+        {
+            let mut key_name = key.name.clone();
+            if key_name.starts_with("/") {
+                key_name = key_name[1..].to_string();
+            }
+            let path = match cfg.log_path.as_ref() {
+                Some(a) if a.ends_with("/") => format!("{}{}.wal", a, key_name),
+                Some(a) => format!("{}/{}.wal", a, key_name),
+                None => {
+                    warn!("the outbound write-ahead file is disabled as there is no log path");
+                    return Ok(None);
+                }
+            };
+            trace!("wal-path: {}", path);
+            if let Some(parent) = std::path::Path::new(&path).parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            Ok(Some(Arc::new(OutboundWal::open_path(path)?)))
+        }
+        #[cfg(not(feature = "enable_local_fs"))]
+        Ok(None)
+    }
+
+    pub(super) fn open_path(path: String) -> std::io::Result<OutboundWal> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        let (unconfirmed, next_id) = Self::parse(&path, &data[..]);
+        if unconfirmed.len() > 0 {
+            debug!("{} unconfirmed commits found in {}", unconfirmed.len(), path);
+        }
+
+        Ok(OutboundWal {
+            path,
+            state: StdMutex::new(WalState {
+                file,
+                next_id,
+                unconfirmed,
+            }),
+        })
+    }
+
+    /// Reads the records in the file, records that are corrupted are skipped
+    /// and a truncated record at the end (e.g. from a crash) is ignored
+    fn parse(path: &str, mut data: &[u8]) -> (BTreeMap<u64, Vec<MessageEvent>>, u64) {
+        let mut unconfirmed = BTreeMap::new();
+        let mut next_id = 0u64;
+        while data.len() > 0 {
+            if data.len() < WAL_RECORD_HEADER {
+                warn!("ignoring a truncated record at the end of {}", path);
+                break;
+            }
+            let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+            let hash = &data[4..WAL_RECORD_HEADER];
+            data = &data[WAL_RECORD_HEADER..];
+            if data.len() < len {
+                warn!("ignoring a truncated record at the end of {}", path);
+                break;
+            }
+            let (payload, rest) = data.split_at(len);
+            data = rest;
+
+            if AteHash::from_bytes(payload).as_bytes()[..] != hash[..] {
+                warn!("skipping a corrupted record in {} (checksum mismatch)", path);
+                continue;
+            }
+            match bincode::deserialize::<WalRecord>(payload) {
+                Ok(WalRecord::Commit { id, events }) => {
+                    next_id = next_id.max(id + 1);
+                    unconfirmed.insert(id, events);
+                }
+                Ok(WalRecord::Confirmed { id }) => {
+                    unconfirmed.remove(&id);
+                }
+                Err(err) => {
+                    warn!("skipping a corrupted record in {} - {}", path, err);
+                }
+            }
+        }
+        (unconfirmed, next_id)
+    }
+
+    fn write(file: &mut File, record: &WalRecord) -> std::io::Result<()> {
+        let payload = bincode::serialize(record)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+        let mut buf = Vec::with_capacity(WAL_RECORD_HEADER + payload.len());
+        buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        buf.extend_from_slice(AteHash::from_bytes(&payload[..]).as_bytes());
+        buf.extend_from_slice(&payload[..]);
+        file.write_all(&buf[..])?;
+        file.sync_data()
+    }
+
+    /// Records a commit before it is sent to the root and returns its ID, a
+    /// commit with the same events as one that is still unconfirmed (i.e. a
+    /// retry after a timeout) reuses its record rather than adding another
+    pub(super) fn append(&self, events: Vec<MessageEvent>) -> std::io::Result<u64> {
+        let mut state = self.state.lock().unwrap();
+        let digest = Self::digest(&events);
+        if let Some(id) = state
+            .unconfirmed
+            .iter()
+            .filter(|(_, existing)| existing.len() == events.len())
+            .find(|(_, existing)| Self::digest(existing) == digest)
+            .map(|(id, _)| *id)
+        {
+            trace!("commit {} is already in {}", id, self.path);
+            return Ok(id);
+        }
+
+        let id = state.next_id;
+        let record = WalRecord::Commit { id, events };
+        Self::write(&mut state.file, &record)?;
+        if let WalRecord::Commit { events, .. } = record {
+            state.unconfirmed.insert(id, events);
+        }
+        state.next_id += 1;
+        Ok(id)
+    }
+
+    fn digest(events: &Vec<MessageEvent>) -> Option<AteHash> {
+        bincode::serialize(events)
+            .ok()
+            .map(|a| AteHash::from_bytes(&a[..]))
+    }
+
+    /// Removes a commit once the root has confirmed it (or once it is known
+    /// that the commit failed and its error was returned to the caller)
+    pub(super) fn confirm(&self, id: u64) -> std::io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.unconfirmed.remove(&id).is_none() {
+            return Ok(());
+        }
+
+        // Once nothing is left the file is emptied rather than growing forever
+        if state.unconfirmed.is_empty() {
+            trace!("all commits confirmed - truncating {}", self.path);
+            state.file.set_len(0)?;
+            return Ok(());
+        }
+        Self::write(&mut state.file, &WalRecord::Confirmed { id })
+    }
+
+    /// Returns the commits that have not been confirmed in the order they
+    /// were originally made
+    pub(super) fn pending(&self) -> Vec<(u64, Vec<MessageEvent>)> {
+        let state = self.state.lock().unwrap();
+        state
+            .unconfirmed
+            .iter()
+            .map(|(id, events)| (*id, events.clone()))
+            .collect()
+    }
+}
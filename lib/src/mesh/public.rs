@@ -0,0 +1,287 @@
+use async_trait::async_trait;
+use fxhash::FxHashMap;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+use std::time::Instant;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use super::server::MeshRoot;
+use crate::chain::ChainKey;
+use crate::comms::NodeId;
+use crate::comms::RawWebRoute;
+use crate::dio::RawRow;
+use crate::error::*;
+use crate::header::PrimaryKey;
+use crate::session::AteSessionUser;
+use crate::spec::SerializationFormat;
+
+/// Number of addresses tracked by the rate limiter before the addresses
+/// that have been quiet for a while are forgotten
+const PUBLIC_RATE_LIMIT_MAX_TRACKED: usize = 10000;
+
+/// Number of rows of a collection that are returned in one page when the
+/// reader does not ask for a particular limit
+pub const PUBLIC_DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Limits the number of requests that a single address may make to a public
+/// route (as anonymous readers have no identity to throttle on)
+pub struct PublicRateLimit {
+    per_second: f64,
+    burst: f64,
+    buckets: StdMutex<FxHashMap<IpAddr, (f64, Instant)>>,
+}
+
+impl PublicRateLimit {
+    pub fn new(per_second: u32, burst: u32) -> PublicRateLimit {
+        PublicRateLimit {
+            per_second: per_second as f64,
+            burst: burst.max(1) as f64,
+            buckets: StdMutex::new(FxHashMap::default()),
+        }
+    }
+
+    /// Takes a token from the bucket of the address (returns false if the
+    /// address has exhausted its allowance)
+    pub fn try_acquire(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= PUBLIC_RATE_LIMIT_MAX_TRACKED && buckets.contains_key(&ip) == false {
+            let full = Duration::from_secs_f64(self.burst / self.per_second.max(1f64));
+            buckets.retain(|_, (_, last)| now.duration_since(*last) < full);
+        }
+
+        let (tokens, last) = buckets.entry(ip).or_insert((self.burst, now));
+        let refill = now.duration_since(*last).as_secs_f64() * self.per_second;
+        *tokens = (*tokens + refill).min(self.burst);
+        *last = now;
+        if *tokens < 1f64 {
+            return false;
+        }
+        *tokens -= 1f64;
+        true
+    }
+}
+
+impl Default for PublicRateLimit {
+    fn default() -> PublicRateLimit {
+        PublicRateLimit::new(10, 50)
+    }
+}
+
+/// Page of the rows of a collection, `next` is the offset of the page that
+/// follows (or `None` when this is the last one)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PublicPage {
+    pub items: Vec<serde_json::Value>,
+    pub offset: usize,
+    pub total: usize,
+    pub next: Option<usize>,
+}
+
+/// Web route that serves the rows of chains hosted on a public route of the
+/// root as JSON to anyone (mount it with `add_get_route` on `/public`).
+///
+/// `GET /public/{chain}/{key}` returns a single row while
+/// `GET /public/{chain}/{key}/{collection}` returns a page of the rows of a
+/// collection (pass `?offset=` and `?limit=` to walk through the pages).
+/// Keys are given as hex (or as names that are hashed into keys) and only rows
+/// stored as JSON or MessagePack can be served.
+pub struct PublicRoute {
+    root: Arc<MeshRoot>,
+    route: String,
+    prefix: String,
+    limit: PublicRateLimit,
+    max_page_size: usize,
+}
+
+impl PublicRoute {
+    pub fn new(root: &Arc<MeshRoot>, route: &str, limit: PublicRateLimit) -> PublicRoute {
+        PublicRoute {
+            root: Arc::clone(root),
+            route: route.to_string(),
+            prefix: "/public".to_string(),
+            limit,
+            max_page_size: PUBLIC_DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    /// Changes the most rows of a collection that are returned in one page
+    pub fn with_max_page_size(mut self, max_page_size: usize) -> PublicRoute {
+        self.max_page_size = max_page_size.max(1);
+        self
+    }
+
+    /// Changes the path that the route is mounted on (defaults to `/public`)
+    pub fn with_prefix(mut self, prefix: &str) -> PublicRoute {
+        self.prefix = prefix.trim_end_matches('/').to_string();
+        self
+    }
+
+    fn error(msg: &str, code: StatusCode) -> (Vec<u8>, StatusCode) {
+        (msg.as_bytes().to_vec(), code)
+    }
+
+    fn parse_key(val: &str) -> PrimaryKey {
+        match u64::from_str_radix(val, 16) {
+            Ok(a) if val.len() == 16 => PrimaryKey::from(a),
+            _ => PrimaryKey::from(val.to_string()),
+        }
+    }
+
+    fn to_json(row: RawRow) -> Result<serde_json::Value, (Vec<u8>, StatusCode)> {
+        let ret = match row.format.data {
+            SerializationFormat::Json => serde_json::from_slice::<serde_json::Value>(&row.data[..])
+                .map_err(|err| err.to_string()),
+            SerializationFormat::MessagePack => {
+                rmp_serde::from_read_ref::<_, serde_json::Value>(&row.data[..])
+                    .map_err(|err| err.to_string())
+            }
+            format => {
+                return Err(Self::error(
+                    format!("rows stored as {} can not be served as JSON", format).as_str(),
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                ));
+            }
+        };
+        ret.map_err(|err| Self::error(err.as_str(), StatusCode::INTERNAL_SERVER_ERROR))
+    }
+
+    fn load_error(err: LoadError) -> (Vec<u8>, StatusCode) {
+        match err {
+            LoadError(LoadErrorKind::NotFound(_), _) => {
+                Self::error("row not found", StatusCode::NOT_FOUND)
+            }
+            err => Self::error(err.to_string().as_str(), StatusCode::FORBIDDEN),
+        }
+    }
+}
+
+#[async_trait]
+impl RawWebRoute for PublicRoute {
+    async fn accepted_raw_post_request(
+        &self,
+        _uri: http::Uri,
+        _headers: http::HeaderMap,
+        _sock_addr: SocketAddr,
+        _server_id: NodeId,
+        _body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        Err(Self::error("public chains are read-only", StatusCode::METHOD_NOT_ALLOWED))
+    }
+
+    async fn accepted_raw_put_request(
+        &self,
+        _uri: http::Uri,
+        _headers: http::HeaderMap,
+        _sock_addr: SocketAddr,
+        _server_id: NodeId,
+        _body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        Err(Self::error("public chains are read-only", StatusCode::METHOD_NOT_ALLOWED))
+    }
+
+    async fn accepted_raw_get_request(
+        &self,
+        uri: http::Uri,
+        _headers: http::HeaderMap,
+        sock_addr: SocketAddr,
+        _server_id: NodeId,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        if self.limit.try_acquire(sock_addr.ip()) == false {
+            debug!("public request from {} rate limited", sock_addr);
+            return Err(Self::error("too many requests", StatusCode::TOO_MANY_REQUESTS));
+        }
+
+        let path = uri.path().strip_prefix(self.prefix.as_str()).unwrap_or("");
+        let parts = path.split('/').filter(|a| a.len() > 0).collect::<Vec<_>>();
+        let (chain, key, collection) = match parts[..] {
+            [chain, key] => (chain, key, None),
+            [chain, key, collection] => match collection.parse::<u64>() {
+                Ok(a) => (chain, key, Some(a)),
+                Err(_) => {
+                    return Err(Self::error("the collection must be a number", StatusCode::BAD_REQUEST));
+                }
+            },
+            _ => {
+                return Err(Self::error(
+                    format!("the path must be {}/{{chain}}/{{key}}", self.prefix).as_str(),
+                    StatusCode::BAD_REQUEST,
+                ));
+            }
+        };
+        let chain = ChainKey::from(chain.to_string());
+        let key = Self::parse_key(key);
+
+        let mut offset = 0usize;
+        let mut limit = self.max_page_size;
+        for (name, val) in uri.query().unwrap_or("").split('&').filter_map(|a| a.split_once('=')) {
+            let val = match name {
+                "offset" | "limit" => match val.parse::<usize>() {
+                    Ok(a) => a,
+                    Err(_) => {
+                        return Err(Self::error(
+                            format!("the {} must be a number", name).as_str(),
+                            StatusCode::BAD_REQUEST,
+                        ));
+                    }
+                },
+                _ => continue,
+            };
+            match name {
+                "offset" => offset = val,
+                _ => limit = val.clamp(1, self.max_page_size),
+            }
+        }
+
+        debug!("public read of {} in {} by {}", key, chain, sock_addr);
+        let chain = match self.root.open_public(self.route.as_str(), &chain).await {
+            Ok(a) => a,
+            Err(ChainCreationError(ChainCreationErrorKind::NotThisRoot, _)) => {
+                return Err(Self::error("the chain is not hosted on this root", StatusCode::NOT_FOUND));
+            }
+            Err(err) => {
+                return Err(Self::error(err.to_string().as_str(), StatusCode::FORBIDDEN));
+            }
+        };
+
+        // Readers are anonymous so only data that is not encrypted can be read
+        let session = AteSessionUser::default();
+        let dio = chain.dio(&session).await;
+        let ret = match collection {
+            None => {
+                let row = dio.load_raw_typed(&key).await.map_err(Self::load_error)?;
+                Self::to_json(row)?
+            }
+            Some(collection) => {
+                let keys = dio
+                    .children_keys(key, collection)
+                    .await
+                    .map_err(Self::load_error)?;
+                let total = keys.len();
+                let mut items = Vec::new();
+                for key in keys.into_iter().skip(offset).take(limit) {
+                    let row = dio.load_raw_typed(&key).await.map_err(Self::load_error)?;
+                    items.push(Self::to_json(row)?);
+                }
+                let next = Some(offset.saturating_add(limit)).filter(|a| *a < total);
+                let page = PublicPage {
+                    items,
+                    offset,
+                    total,
+                    next,
+                };
+                serde_json::to_value(&page).map_err(|err| {
+                    Self::error(err.to_string().as_str(), StatusCode::INTERNAL_SERVER_ERROR)
+                })?
+            }
+        };
+        serde_json::to_vec(&ret)
+            .map_err(|err| Self::error(err.to_string().as_str(), StatusCode::INTERNAL_SERVER_ERROR))
+    }
+}
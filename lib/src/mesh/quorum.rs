@@ -0,0 +1,411 @@
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+#[cfg(feature = "enable_server")]
+use {
+    fxhash::FxHashSet,
+    std::sync::Mutex as StdMutex,
+    std::sync::Weak,
+    std::time::Instant,
+    tokio::sync::broadcast,
+    tokio::sync::mpsc,
+    tokio::sync::Mutex,
+};
+
+#[cfg(feature = "enable_server")]
+use {
+    super::Registry,
+    crate::chain::*,
+    crate::conf::ConfAte,
+    crate::engine::TaskEngine,
+    crate::error::*,
+    crate::event::*,
+    crate::index::EventLeaf,
+    crate::time::ChainTimestamp,
+    crate::transaction::*,
+};
+
+/// Maximum number of events that are sent to a replica in a single
+/// transaction when it is being repaired
+#[cfg(feature = "enable_server")]
+const REPAIR_BATCH_SIZE: usize = 500;
+
+/// # Quorum Policy
+///
+/// Opt-in policy for critical chains that makes the primary root relay
+/// every commit to a set of replica roots before it is confirmed back to
+/// the client. A commit is only confirmed once `required` replicas have
+/// accepted the events, replicas that miss commits (e.g. because they
+/// were down) are backfilled in the background by a repair job.
+///
+/// The replica roots must not have a quorum policy of their own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuorumPolicy {
+    // Roots that will receive a copy of every commit made on the primary
+    pub replicas: Vec<Url>,
+    // Number of replicas that must acknowledge a commit before its confirmed
+    pub required: usize,
+    // Maximum amount of time to wait for the replicas to acknowledge a commit
+    pub timeout: Duration,
+    // Number of times the relay to a particular replica is attempted
+    pub relay_attempts: u32,
+    // How frequently replicas that have fallen behind are repaired
+    pub repair_interval: Duration,
+}
+
+impl Default for QuorumPolicy {
+    fn default() -> QuorumPolicy {
+        QuorumPolicy {
+            replicas: Vec::new(),
+            required: 1,
+            timeout: Duration::from_secs(10),
+            relay_attempts: 3,
+            repair_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl QuorumPolicy {
+    pub fn with_replica(mut self, url: Url) -> Self {
+        self.replicas.push(url);
+        self
+    }
+
+    pub fn with_required(mut self, val: usize) -> Self {
+        self.required = val;
+        self
+    }
+
+    pub fn with_timeout(mut self, val: Duration) -> Self {
+        self.timeout = val;
+        self
+    }
+
+    pub fn with_relay_attempts(mut self, val: u32) -> Self {
+        self.relay_attempts = val;
+        self
+    }
+
+    pub fn with_repair_interval(mut self, val: Duration) -> Self {
+        self.repair_interval = val;
+        self
+    }
+
+    /// Returns true if there are replicas that commits must be relayed to
+    pub fn is_active(&self) -> bool {
+        self.replicas.len() > 0 && self.required > 0
+    }
+
+    /// Number of acknowledgements needed (never more than the number of replicas)
+    pub fn quorum(&self) -> usize {
+        self.required.min(self.replicas.len())
+    }
+}
+
+impl std::fmt::Display for QuorumPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "quorum({}/{},", self.quorum(), self.replicas.len())?;
+        for replica in self.replicas.iter() {
+            write!(f, "{},", replica)?;
+        }
+        write!(
+            f,
+            "timeout={}ms,attempts={},repair={}s)",
+            self.timeout.as_millis(),
+            self.relay_attempts,
+            self.repair_interval.as_secs()
+        )
+    }
+}
+
+#[cfg(feature = "enable_server")]
+pub(crate) struct QuorumReplica {
+    url: Url,
+    chain: Mutex<Option<Arc<Chain>>>,
+    // Earliest event that this replica may have missed (if any)
+    behind_since: StdMutex<Option<ChainTimestamp>>,
+}
+
+#[cfg(feature = "enable_server")]
+impl QuorumReplica {
+    fn mark_behind(&self, since: ChainTimestamp) {
+        let mut guard = self.behind_since.lock().unwrap();
+        let since = match guard.take() {
+            Some(a) if a < since => a,
+            _ => since,
+        };
+        guard.replace(since);
+    }
+}
+
+/// Relays the commits of a primary chain to its replica roots
+#[cfg(feature = "enable_server")]
+pub(crate) struct QuorumRelay {
+    policy: QuorumPolicy,
+    key: ChainKey,
+    registry: Arc<Registry>,
+    replicas: Vec<Arc<QuorumReplica>>,
+}
+
+#[cfg(feature = "enable_server")]
+impl QuorumRelay {
+    pub(crate) async fn new(cfg_ate: &ConfAte, key: &ChainKey, policy: QuorumPolicy) -> Arc<QuorumRelay> {
+        // The connections to the replicas are plain clients
+        let mut cfg_ate = cfg_ate.clone();
+        cfg_ate.compact_policy = None;
+        cfg_ate.quorum_policy = None;
+        let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+
+        let replicas = policy
+            .replicas
+            .iter()
+            .map(|url| {
+                Arc::new(QuorumReplica {
+                    url: url.clone(),
+                    chain: Mutex::new(None),
+                    behind_since: StdMutex::new(None),
+                })
+            })
+            .collect();
+
+        Arc::new(QuorumRelay {
+            policy,
+            key: key.clone(),
+            registry,
+            replicas,
+        })
+    }
+
+    pub(crate) fn registry(&self) -> &Arc<Registry> {
+        &self.registry
+    }
+
+    async fn connect(&self, replica: &QuorumReplica) -> Result<Arc<Chain>, CommitError> {
+        let mut guard = replica.chain.lock().await;
+        if let Some(chain) = guard.as_ref() {
+            return Ok(Arc::clone(chain));
+        }
+
+        let chain = self
+            .registry
+            .open(&replica.url, &self.key, true)
+            .await
+            .map_err(|err| CommitErrorKind::RootError(err.to_string()))?
+            .as_arc();
+        guard.replace(Arc::clone(&chain));
+        Ok(chain)
+    }
+
+    /// Sends the events to a replica and waits for it to confirm them, failed
+    /// attempts drop the connection so that the next attempt reconnects. All
+    /// the attempts share the time that is left until the deadline.
+    async fn relay_one(
+        &self,
+        replica: &QuorumReplica,
+        evts: Vec<EventWeakData>,
+        deadline: Instant,
+    ) -> Result<(), CommitError> {
+        let attempts = self.policy.relay_attempts.max(1);
+        let mut attempt = 0u32;
+        loop {
+            attempt += 1;
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                replica.chain.lock().await.take();
+                let elapsed = format!("{}ms", self.policy.timeout.as_millis());
+                return Err(CommitErrorKind::Timeout(elapsed).into());
+            }
+
+            let ret = crate::engine::timeout(remaining, async {
+                let chain = self.connect(replica).await?;
+                chain
+                    .pipe
+                    .feed(ChainWork {
+                        trans: Transaction {
+                            scope: TransactionScope::Full,
+                            transmit: true,
+                            events: evts.clone(),
+                            timeout: remaining,
+                            conversation: None,
+                            deferrable: false,
+                        },
+                    })
+                    .await
+            })
+            .await
+            .unwrap_or_else(|elapsed| Err(CommitErrorKind::Timeout(elapsed.to_string()).into()));
+
+            match ret {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt < attempts => {
+                    debug!("quorum relay to {} failed (attempt {}) - {}", replica.url, attempt, err);
+                    replica.chain.lock().await.take();
+                }
+                Err(err) => {
+                    replica.chain.lock().await.take();
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// Relays the events to all the replicas and returns once enough of them
+    /// have acknowledged the commit (or fails naming the replicas that did not).
+    /// The primary must only write the events after the quorum is reached.
+    pub(crate) async fn relay(self: &Arc<Self>, evts: &Vec<EventWeakData>) -> Result<(), CommitError> {
+        let required = self.policy.quorum();
+        let deadline = Instant::now() + self.policy.timeout;
+        let since = evts
+            .iter()
+            .filter_map(|e| e.meta.get_timestamp())
+            .min()
+            .cloned()
+            .unwrap_or(ChainTimestamp::from(0u64));
+
+        let (tx, mut rx) = mpsc::channel(self.replicas.len().max(1));
+        for replica in self.replicas.iter() {
+            let relay = Arc::clone(self);
+            let replica = Arc::clone(replica);
+            let evts = evts.clone();
+            let tx = tx.clone();
+            TaskEngine::spawn(async move {
+                let ret = relay.relay_one(&replica, evts, deadline).await;
+                if let Err(err) = &ret {
+                    warn!("quorum relay to {} failed - {}", replica.url, err);
+                    replica.mark_behind(since);
+                }
+                let _ = tx.send((replica.url.clone(), ret.is_ok())).await;
+            });
+        }
+        drop(tx);
+
+        // Every relay gives up at the deadline so this ends once the quorum
+        // is reached or all the replicas have answered
+        let mut acked = Vec::new();
+        while let Some((url, ok)) = rx.recv().await {
+            if ok {
+                acked.push(url);
+                if acked.len() >= required {
+                    return Ok(());
+                }
+            }
+        }
+
+        let failed = self
+            .replicas
+            .iter()
+            .filter(|r| acked.contains(&r.url) == false)
+            .map(|r| r.url.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Err(CommitErrorKind::QuorumNotReached(acked.len(), required, failed).into())
+    }
+
+    /// Copies any events the replica is missing (from the point it fell
+    /// behind) from the primary chain
+    async fn repair(
+        &self,
+        chain: &Arc<Chain>,
+        replica: &QuorumReplica,
+        since: ChainTimestamp,
+    ) -> Result<usize, CommitError> {
+        let target = self.connect(replica).await?;
+        let existing = {
+            let multi = target.multi().await;
+            let guard = multi.inside_async.read().await;
+            guard
+                .range(since..)
+                .map(|(_, v)| v.event_hash)
+                .collect::<FxHashSet<_>>()
+        };
+
+        let multi = chain.multi().await;
+        let leafs = {
+            let guard = multi.inside_async.read().await;
+            guard
+                .range(since..)
+                .filter(|(_, v)| existing.contains(&v.event_hash) == false)
+                .map(|(_, v)| EventLeaf {
+                    record: v.event_hash,
+                    created: 0,
+                    updated: 0,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut cnt = 0usize;
+        for batch in leafs.chunks(REPAIR_BATCH_SIZE) {
+            let evts = multi
+                .load_many(batch.to_vec())
+                .await
+                .map_err(|err| CommitErrorKind::RootError(err.to_string()))?
+                .into_iter()
+                .map(|evt| EventWeakData {
+                    meta: evt.data.meta,
+                    data_bytes: match evt.data.data_bytes {
+                        Some(a) => MessageBytes::Some(a),
+                        None => MessageBytes::None,
+                    },
+                    format: evt.header.format,
+                })
+                .collect::<Vec<_>>();
+            cnt += evts.len();
+            let deadline = Instant::now() + self.policy.timeout;
+            self.relay_one(replica, evts, deadline).await?;
+        }
+        Ok(cnt)
+    }
+
+    /// Copies every event of the chain that the replicas do not yet have
+    /// (used to bulk transfer a chain to another root), returns the number
+    /// of events that were sent
+    pub(crate) async fn backfill(&self, chain: &Arc<Chain>) -> Result<usize, CommitError> {
+        let mut cnt = 0usize;
+        for replica in self.replicas.iter() {
+            cnt += self.repair(chain, replica, ChainTimestamp::from(0u64)).await?;
+            replica.behind_since.lock().unwrap().take();
+        }
+        Ok(cnt)
+    }
+
+    /// Background job that backfills replicas which missed commits
+    pub(crate) async fn repair_worker(
+        relay: Arc<QuorumRelay>,
+        chain: Weak<Chain>,
+        mut exit: broadcast::Receiver<()>,
+    ) {
+        loop {
+            tokio::select! {
+                _ = crate::engine::sleep(relay.policy.repair_interval) => { },
+                _ = exit.recv() => { break; }
+            }
+
+            let chain = match Weak::upgrade(&chain) {
+                Some(a) => a,
+                None => {
+                    break;
+                }
+            };
+
+            for replica in relay.replicas.iter() {
+                let since = match replica.behind_since.lock().unwrap().take() {
+                    Some(a) => a,
+                    None => continue,
+                };
+                match relay.repair(&chain, replica, since).await {
+                    Ok(cnt) => {
+                        debug!("quorum repair of {} sent {} events", replica.url, cnt);
+                    }
+                    Err(err) => {
+                        warn!("quorum repair of {} failed - {}", replica.url, err);
+                        replica.mark_behind(since);
+                    }
+                }
+            }
+        }
+    }
+}
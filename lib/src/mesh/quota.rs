@@ -0,0 +1,209 @@
+//! Storage quotas of the chains hosted on a root. Every route has a quota
+//! that applies to each of its chains (which may be overridden for single
+//! chains) and the root keeps track of how many bytes each chain that it
+//! hosts occupies on disk, that is its redo log plus the archives. The usage
+//! is measured when the chain is opened and again after it is compacted,
+//! in between it grows with the commits that are fed into it.
+//!
+//! Commits that would take a chain over its quota are rejected, commits that
+//! only delete rows are always accepted so that the owner can free up space
+//! (which is reclaimed when the chain is next compacted).
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::chain::Chain;
+use crate::chain::ChainKey;
+
+/// Default number of bytes that each chain of a route may occupy on disk
+/// (zero means that the chains are not limited)
+pub const DEFAULT_CHAIN_QUOTA: u64 = 0;
+/// Percentage of the quota beyond which the sessions that write to a chain
+/// (and asked for its status) are warned
+pub const QUOTA_WARN_PERCENT: u64 = 80;
+
+/// Quota of a chain on the root that hosts it and how much of it is used
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct QuotaStatus {
+    pub chain: ChainKey,
+    /// Bytes that the chain occupies on disk (zero when the chain is not
+    /// currently open on the root)
+    pub used: u64,
+    /// Bytes that the chain may occupy (None when it is not limited)
+    pub limit: Option<u64>,
+}
+
+impl QuotaStatus {
+    /// Percentage of the quota that is used (None when it is not limited)
+    pub fn percent(&self) -> Option<u64> {
+        self.limit
+            .filter(|a| *a > 0)
+            .map(|limit| self.used.saturating_mul(100) / limit)
+    }
+}
+
+impl std::fmt::Display for QuotaStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match (self.limit, self.percent()) {
+            (Some(limit), Some(percent)) => {
+                write!(f, "{}/{} bytes ({}%)", self.used, limit, percent)
+            }
+            _ => write!(f, "{} bytes (unlimited)", self.used),
+        }
+    }
+}
+
+/// Overrides the quota of a single chain, these are stored as root objects
+/// in a system chain and loaded by the root with `load_chain_quotas`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChainQuota {
+    pub chain: ChainKey,
+    /// Bytes that the chain may occupy on disk (zero means unlimited)
+    pub limit: u64,
+}
+
+#[cfg(feature = "enable_server")]
+struct RouteQuotasProtected {
+    default: u64,
+    overrides: FxHashMap<String, u64>,
+}
+
+/// Quotas of the chains of a route (a limit of zero means unlimited)
+#[cfg(feature = "enable_server")]
+pub(crate) struct RouteQuotas {
+    inside: StdMutex<RouteQuotasProtected>,
+}
+
+#[cfg(feature = "enable_server")]
+impl RouteQuotas {
+    pub(crate) fn new(default: u64) -> RouteQuotas {
+        RouteQuotas {
+            inside: StdMutex::new(RouteQuotasProtected {
+                default,
+                overrides: FxHashMap::default(),
+            }),
+        }
+    }
+
+    pub(crate) fn set_default(&self, limit: u64) {
+        self.inside.lock().unwrap().default = limit;
+    }
+
+    pub(crate) fn set_override(&self, key: &ChainKey, limit: Option<u64>) {
+        let mut inside = self.inside.lock().unwrap();
+        match limit {
+            Some(limit) => inside.overrides.insert(key.name.clone(), limit),
+            None => inside.overrides.remove(&key.name),
+        };
+    }
+
+    /// Replaces all the overrides (e.g. after they were reloaded)
+    pub(crate) fn replace_overrides(&self, quotas: impl Iterator<Item = ChainQuota>) {
+        let mut inside = self.inside.lock().unwrap();
+        inside.overrides = quotas.map(|a| (a.chain.name, a.limit)).collect();
+    }
+
+    /// Returns the quota of a chain (None when it is not limited)
+    pub(crate) fn limit(&self, key: &ChainKey) -> Option<u64> {
+        let inside = self.inside.lock().unwrap();
+        let limit = inside
+            .overrides
+            .get(&key.name)
+            .cloned()
+            .unwrap_or(inside.default);
+        match limit {
+            0 => None,
+            a => Some(a),
+        }
+    }
+}
+
+/// Bytes that a chain hosted by the root occupies on disk, the usage is
+/// tracked approximately as concurrent commits are checked against the
+/// quota before any of them have been written
+#[cfg(feature = "enable_server")]
+pub(crate) struct ChainUsage {
+    used: AtomicU64,
+    /// Flips of the redo log (compactions) when the usage was last measured
+    flips: AtomicU64,
+    warned: AtomicBool,
+}
+
+#[cfg(feature = "enable_server")]
+impl ChainUsage {
+    pub(crate) async fn measure(chain: &Arc<Chain>) -> ChainUsage {
+        let ret = ChainUsage {
+            used: AtomicU64::new(0),
+            flips: AtomicU64::new(0),
+            warned: AtomicBool::new(false),
+        };
+        ret.remeasure(chain).await;
+        ret
+    }
+
+    async fn remeasure(&self, chain: &Arc<Chain>) {
+        let flips = chain.redo_flips().await;
+        match chain.disk_size().await {
+            Ok(used) => {
+                trace!("chain {} occupies {} bytes", chain.key(), used);
+                self.used.store(used, Ordering::Release);
+            }
+            Err(err) => {
+                warn!("failed to measure the size of chain {} - {}", chain.key(), err);
+            }
+        }
+        self.flips.store(flips, Ordering::Release);
+    }
+
+    /// Measures the chain again if it was compacted since it was last
+    /// measured (which reclaims the space of the rows that were deleted)
+    pub(crate) async fn refresh(&self, chain: &Arc<Chain>, limit: Option<u64>) -> u64 {
+        if chain.redo_flips().await != self.flips.load(Ordering::Acquire) {
+            self.remeasure(chain).await;
+            if is_over_warning(self.used(), limit) == false {
+                self.warned.store(false, Ordering::Release);
+            }
+        }
+        self.used()
+    }
+
+    pub(crate) fn used(&self) -> u64 {
+        self.used.load(Ordering::Acquire)
+    }
+
+    /// Checks that a commit of this many bytes fits within the quota, if
+    /// it does not the current usage and the limit are returned instead
+    pub(crate) fn check(&self, size: u64, limit: Option<u64>) -> Result<(), (u64, u64)> {
+        let used = self.used();
+        match limit {
+            Some(limit) if used.saturating_add(size) > limit => Err((used, limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns true if the chain has used most of its quota
+    pub(crate) fn is_warning(&self, limit: Option<u64>) -> bool {
+        is_over_warning(self.used(), limit)
+    }
+
+    /// Accounts for a commit that was fed into the chain, returns true
+    /// the first time the usage crosses the warning threshold
+    pub(crate) fn add(&self, size: u64, limit: Option<u64>) -> bool {
+        let used = self.used.fetch_add(size, Ordering::AcqRel) + size;
+        is_over_warning(used, limit) && self.warned.swap(true, Ordering::AcqRel) == false
+    }
+}
+
+#[cfg(feature = "enable_server")]
+fn is_over_warning(used: u64, limit: Option<u64>) -> bool {
+    match limit {
+        Some(limit) => used.saturating_mul(100) >= limit.saturating_mul(QUOTA_WARN_PERCENT),
+        None => false,
+    }
+}
@@ -2,6 +2,7 @@ use crate::engine::timeout;
 use async_trait::async_trait;
 use error_chain::bail;
 use fxhash::FxHashMap;
+use fxhash::FxHashSet;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::ops::Rem;
@@ -13,12 +14,14 @@ use std::{sync::Arc, sync::Weak};
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::sync::RwLock;
+use tokio::sync::watch;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use super::active_session_pipe::*;
 use super::core::*;
 use super::lock_request::*;
 use super::msg::*;
+use super::outbound_wal::*;
 use super::session::*;
 use super::*;
 use crate::chain::*;
@@ -47,7 +50,7 @@ pub(super) struct RecoverableSessionPipe {
     pub(super) cfg_mesh: ConfMesh,
 
     // Used to create new active pipes
-    pub(super) addr: MeshAddress,
+    pub(super) addr: StdMutex<MeshAddress>,
     pub(super) lazy_data: bool,
     pub(super) hello_path: String,
     pub(super) node_id: NodeId,
@@ -56,11 +59,137 @@ pub(super) struct RecoverableSessionPipe {
     pub(super) exit: broadcast::Sender<()>,
     pub(super) chain: Arc<StdMutex<Option<Weak<Chain>>>>,
     pub(super) loader_remote: StdMutex<Option<Box<dyn Loader + 'static>>>,
+    pub(super) sync: Arc<watch::Sender<SyncProgress>>,
     pub(crate) metrics: Arc<StdMutex<Metrics>>,
     pub(crate) throttle: Arc<StdMutex<Throttle>>,
+    // Commits that have not yet been confirmed by the root (if enabled)
+    pub(super) wal: Option<Arc<OutboundWal>>,
 }
 
 impl RecoverableSessionPipe {
+    pub(super) fn addr(&self) -> MeshAddress {
+        self.addr.lock().unwrap().clone()
+    }
+
+    /// Looks up the root that owns this chain again using the latest SRV
+    /// records (if the roots were discovered that way) so that the next
+    /// connect attempt goes to wherever the chain now lives
+    #[cfg(feature = "enable_dns")]
+    async fn reresolve(&self) {
+        if self.cfg_mesh.force_connect.is_some() {
+            return;
+        }
+        let discovery = match self.cfg_mesh.discovery.as_ref() {
+            Some(a) => a,
+            None => return,
+        };
+        if let Err(err) = discovery.refresh().await {
+            warn!("failed to refresh the roots (using the cached roots) - {}", err);
+        }
+        if let Some((addr, _)) = discovery.lookup().lookup(&self.key) {
+            let mut guard = self.addr.lock().unwrap();
+            if *guard != addr {
+                debug!("chain {} moved from {} to {}", self.key, guard, addr);
+                *guard = addr;
+            }
+        }
+    }
+
+    #[cfg(not(feature = "enable_dns"))]
+    async fn reresolve(&self) {}
+
+    /// Sends the commits in the write-ahead file to the root (in the order they
+    /// were originally made) and adds them to the local chain once confirmed.
+    /// Commits the root rejects are discarded while commits that could not be
+    /// sent (e.g. the connection dropped) are kept for the next connect.
+    async fn replay_wal(&self) -> Result<(), ChainCreationError> {
+        let wal = match self.wal.as_ref() {
+            Some(a) => a,
+            None => return Ok(()),
+        };
+        let pending = wal.pending();
+        if pending.len() <= 0 {
+            return Ok(());
+        }
+        debug!("replaying {} unconfirmed commits for {}", pending.len(), self.key);
+
+        for (id, evts) in pending {
+            let receiver = {
+                let mut lock = self.active.write().await;
+                match lock.as_mut() {
+                    Some(pipe) => pipe.send_events(evts.clone(), true).await,
+                    None => break,
+                }
+            };
+            let mut receiver = match receiver {
+                Ok(Some(a)) => a,
+                Ok(None) => break,
+                Err(err) => {
+                    debug!("failed to replay commit {} - {}", id, err);
+                    break;
+                }
+            };
+
+            match crate::engine::timeout(WAL_REPLAY_TIMEOUT, receiver.recv()).await {
+                Ok(Some(Ok(_))) => {}
+                Ok(Some(Err(err))) => {
+                    warn!("discarding unconfirmed commit {} as it was rejected - {}", id, err);
+                    wal.confirm(id)?;
+                    continue;
+                }
+                Ok(None) | Err(_) => {
+                    debug!("replay of commit {} did not complete - will retry on the next connect", id);
+                    break;
+                }
+            }
+
+            // The events may already be in the local chain (e.g. from the redo
+            // log or because the root streamed them back) so only add the rest
+            let chain = self.chain.lock().unwrap().as_ref().map(|a| a.upgrade()).flatten();
+            if let Some(chain) = chain {
+                let existing = {
+                    let guard = chain.inside_async.read().await;
+                    guard
+                        .chain
+                        .timeline
+                        .history
+                        .iter()
+                        .map(|(_, raw)| raw.event_hash)
+                        .collect::<FxHashSet<_>>()
+                };
+                let evts = MessageEvent::convert_from(evts.into_iter())
+                    .into_iter()
+                    .filter(|evt| match evt.as_header_raw() {
+                        Ok(raw) => existing.contains(&raw.event_hash) == false,
+                        Err(_) => true,
+                    })
+                    .collect::<Vec<_>>();
+                if evts.len() > 0 {
+                    let trans = Transaction::from_events(
+                        evts,
+                        TransactionScope::Local,
+                        false,
+                        WAL_REPLAY_TIMEOUT,
+                    );
+                    if let Err(err) = self.next.feed(ChainWork { trans }).await {
+                        warn!("failed to add replayed commit {} to the local chain - {}", id, err);
+                    }
+                }
+            }
+            wal.confirm(id)?;
+        }
+        Ok(())
+    }
+
+    /// Removes a commit that failed from the write-ahead file
+    fn discard_wal(&self, wal_id: Option<u64>) {
+        if let (Some(wal), Some(wal_id)) = (self.wal.as_ref(), wal_id) {
+            if let Err(err) = wal.confirm(wal_id) {
+                warn!("failed to update the outbound write-ahead file - {}", err);
+            }
+        }
+    }
+
     #[cfg(not(feature = "enable_client"))]
     pub(super) async fn create_active_pipe(
         &self,
@@ -89,13 +218,13 @@ impl RecoverableSessionPipe {
         // Create pipes to all the target root nodes
         trace!("building node cfg connect to");
         let node_cfg = MeshConfig::new(self.cfg_mesh.clone())
-            .connect_to(self.addr.clone());
+            .connect_to(self.addr());
 
         let inbound_conversation = Arc::new(ConversationSession::default());
         let outbound_conversation = Arc::new(ConversationSession::default());
 
         let session = Arc::new(MeshSession {
-            addr: self.addr.clone(),
+            addr: self.addr(),
             key: self.key.clone(),
             sync_tolerance: self.builder.cfg_ate.sync_tolerance,
             commit: Arc::clone(&commit),
@@ -108,13 +237,14 @@ impl RecoverableSessionPipe {
             ),
             lock_requests: Arc::clone(&lock_requests),
             load_requests: Arc::clone(&load_requests),
+            quota_requests: StdMutex::new(Vec::new()),
             inbound_conversation: Arc::clone(&inbound_conversation),
             outbound_conversation: Arc::clone(&outbound_conversation),
             status_tx: status_tx.clone(),
         });
 
         let inbox = MeshSessionProcessor {
-            addr: self.addr.clone(),
+            addr: self.addr(),
             node_id: self.node_id,
             session: Arc::downgrade(&session),
             loader: Some(Box::new(loader)),
@@ -201,6 +331,8 @@ impl RecoverableSessionPipe {
         // Enter a loop
         let mut exp_backoff = 1;
         loop {
+            let mut immediate = false;
+
             // Upgrade to a full reference long enough to get a channel clone
             // if we can not get a full reference then the chain has been destroyed
             // and we should exit
@@ -224,6 +356,12 @@ impl RecoverableSessionPipe {
                     pipe.on_read_only().await?;
                     continue;
                 }
+                Some(ConnectionStatusChange::Reconnect) => {
+                    // The server asked us to go elsewhere so there is no need
+                    // to back off before the first attempt
+                    pipe.on_reconnect_hint().await?;
+                    immediate = true;
+                }
                 None => {
                     break;
                 }
@@ -237,11 +375,14 @@ impl RecoverableSessionPipe {
                 }
 
                 // Wait a fix amount of time to prevent thrashing and increase the exp backoff
-                crate::engine::sleep(Duration::from_secs(exp_backoff)).await;
-                exp_backoff = (exp_backoff * 2) + 4;
-                if exp_backoff > 60 {
-                    exp_backoff = 60;
+                if immediate == false {
+                    crate::engine::sleep(Duration::from_secs(exp_backoff)).await;
+                    exp_backoff = (exp_backoff * 2) + 4;
+                    if exp_backoff > 60 {
+                        exp_backoff = 60;
+                    }
                 }
+                immediate = false;
 
                 // Reconnect
                 status_change = match pipe.connect().await {
@@ -270,7 +411,7 @@ impl RecoverableSessionPipe {
 
 impl Drop for RecoverableSessionPipe {
     fn drop(&mut self) {
-        trace!("drop {} @ {}", self.key.to_string(), self.addr);
+        trace!("drop {} @ {}", self.key.to_string(), self.addr());
     }
 }
 
@@ -300,15 +441,28 @@ impl EventPipe for RecoverableSessionPipe {
         Ok(())
     }
 
+    async fn on_reconnect_hint(&self) -> Result<(), CommsError> {
+        self.reresolve().await;
+        self.on_disconnect().await
+    }
+
     async fn connect(
         &self,
     ) -> Result<mpsc::Receiver<ConnectionStatusChange>, ChainCreationError> {
-        trace!("connecting to {}", self.addr);
+        trace!("connecting to {}", self.addr());
 
         // Remove the pipe which will mean if we are in a particular recovery
         // mode then all write IO will be blocked
         self.active.write().await.take();
 
+        // The root we connect to decides again if the chain is read-only
+        if self.builder.read_only == false {
+            let chain = self.chain.lock().unwrap().as_ref().map(|a| a.upgrade()).flatten();
+            if let Some(chain) = chain {
+                chain.set_read_only(false);
+            }
+        }
+
         // We build a anti replay loader and fill it with the events we already have
         // This is because the sync design has a tolerance in what it replays back
         // to the consumer meaning duplicate events will be received from the remote
@@ -334,6 +488,7 @@ impl EventPipe for RecoverableSessionPipe {
         let mut composite_loader = crate::loader::CompositionLoader::default();
         composite_loader.loaders.push(anti_replay);
         composite_loader.loaders.push(notify_loaded);
+        composite_loader.loaders.push(Box::new(SyncProgressLoader::with_sender(Arc::clone(&self.sync))));
         if let Some(loader) = loader.take() {
             composite_loader.loaders.push(loader);
         }
@@ -406,6 +561,7 @@ impl EventPipe for RecoverableSessionPipe {
                         pipe_tx,
                         false,
                         usize::MAX,
+                        None,
                     )
                     .await?;
                     trace!("perf-checkpoint: streamed events to the server");
@@ -432,6 +588,9 @@ impl EventPipe for RecoverableSessionPipe {
         }
         trace!("local upload complete {}", self.key.to_string());
 
+        // Replay any commits that were made before a restart but never confirmed
+        self.replay_wal().await?;
+
         // Mark the pipe as connected
         {
             let mut lock = self.active.write().await;
@@ -463,6 +622,14 @@ impl EventPipe for RecoverableSessionPipe {
         Ok(ret)
     }
 
+    async fn quota_status(&self) -> Result<Option<QuotaStatus>, LoadError> {
+        let mut lock = self.active.write().await;
+        match lock.as_mut() {
+            Some(active) => Ok(Some(active.quota_status().await?)),
+            None => bail!(LoadErrorKind::Disconnected),
+        }
+    }
+
     async fn prime(&self, _records: Vec<(AteHash, Option<Bytes>)>) -> Result<(), CommsError>
     {
         // We don't do anything here as the server is the one that send it to us in
@@ -477,18 +644,59 @@ impl EventPipe for RecoverableSessionPipe {
             work.trans.scope
         );
 
+        // Commits that will be sent to the root are first written to the
+        // write-ahead file so they are not lost if the process restarts
+        let wal_id = match self.wal.as_ref() {
+            Some(wal) if work.trans.transmit && work.trans.events.len() > 0 => {
+                let evts = MessageEvent::convert_to(&work.trans.events);
+                Some(wal.append(evts)?)
+            }
+            _ => None,
+        };
+
         let timeout = work.trans.timeout.clone();
         let receiver = {
             let mut lock = self.active.write().await;
             if let Some(pipe) = lock.as_mut() {
-                pipe.feed(&mut work.trans).await?
+                pipe.feed(&mut work.trans, wal_id.is_some()).await
             } else if self.mode.should_error_out() {
-                bail!(CommitErrorKind::CommsError(CommsErrorKind::Disconnected));
+                Err(CommitErrorKind::CommsError(CommsErrorKind::Disconnected).into())
             } else if self.mode.should_go_readonly() {
-                bail!(CommitErrorKind::CommsError(CommsErrorKind::ReadOnly));
+                Err(CommitErrorKind::CommsError(CommsErrorKind::ReadOnly).into())
             } else {
+                Ok(None)
+            }
+        };
+
+        // Commits that fail are reported to the caller (who may retry them) so
+        // they must not also be replayed from the write-ahead file
+        let receiver = match receiver {
+            Ok(a) => a,
+            Err(err) => {
+                self.discard_wal(wal_id);
+                return Err(err);
+            }
+        };
+
+        // Asynchronous commits are removed from the write-ahead file in the
+        // background once the root confirms them
+        let receiver = match (receiver, &work.trans.scope, wal_id) {
+            (Some(mut receiver), scope, Some(wal_id)) if *scope != TransactionScope::Full => {
+                let wal = self.wal.clone();
+                TaskEngine::spawn(async move {
+                    // Commits the root rejected will never be accepted so they
+                    // are dropped from the file as well
+                    if let Ok(Some(_)) = crate::engine::timeout(timeout, receiver.recv()).await {
+                        if let Some(wal) = wal {
+                            if let Err(err) = wal.confirm(wal_id) {
+                                warn!("failed to update the outbound write-ahead file - {}", err);
+                            }
+                        }
+                    }
+                });
                 None
             }
+            (receiver, _, _) => receiver,
         };
 
         // If we need to wait for the transaction to commit then do so
@@ -502,6 +710,9 @@ impl EventPipe for RecoverableSessionPipe {
                             pipe.likely_read_only = false;
                         }
                     }
+                    if let (Some(wal), Some(wal_id)) = (self.wal.as_ref(), wal_id) {
+                        wal.confirm(wal_id)?;
+                    }
                     let commit_id = result?;
                     trace!("transaction committed: {}", commit_id);
                 }
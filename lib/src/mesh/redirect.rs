@@ -89,8 +89,9 @@ where
     // Build a configuration that forces connecting to a specific ndoe
     let mut conf = root.cfg_mesh.clone();
     conf.force_connect = Some(node_addr.clone());
-    if let Some(cert) = &root.cfg_mesh.listen_certificate {
-        conf.certificate_validation = CertificateValidation::AllowedCertificates(vec![cert.hash()]);
+    let certs = root.certificates();
+    if certs.is_empty() == false {
+        conf.certificate_validation = CertificateValidation::AllowedCertificates(certs.iter().map(|a| a.hash.clone()).collect());
     } else {
         conf.certificate_validation = CertificateValidation::AllowAll;
     }
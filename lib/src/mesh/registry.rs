@@ -16,6 +16,7 @@ use std::sync::RwLock as StdRwLock;
 use std::time::Duration;
 use std::{net::IpAddr, sync::Arc};
 use tokio::sync::Mutex;
+use tokio::sync::watch;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use url::Url;
 
@@ -26,6 +27,7 @@ use crate::dns::*;
 use crate::engine::TaskEngine;
 use crate::error::*;
 use crate::loader;
+use crate::loader::SyncProgress;
 use crate::mesh::*;
 use crate::prelude::*;
 use crate::service::Service;
@@ -43,6 +45,7 @@ pub struct Registry {
     pub node_id: NodeId,
     pub fail_fast: bool,
     pub keep_alive: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
     pub ignore_certificates: bool,
 
     cmd_key: StdMutex<FxHashMap<url::Url, String>>,
@@ -78,6 +81,7 @@ impl Registry {
             remotes: Mutex::new(FxHashMap::default()),
             services: StdMutex::new(Vec::new()),
             keep_alive: None,
+            connect_timeout: None,
         }
     }
 
@@ -96,6 +100,13 @@ impl Registry {
         self
     }
 
+    /// Overrides the connect timeout of the mesh configuration for every
+    /// remote that is opened through this registry
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
     pub fn ignore_certificates(mut self) -> Self {
         self.ignore_certificates = true;
         self
@@ -178,6 +189,27 @@ impl Registry {
         .into());
     }
 
+    #[cfg(feature = "enable_client")]
+    async fn mesh_client(&self, url: &Url, force_temporal: bool) -> Result<Arc<MeshClient>, ChainCreationError> {
+        let mut lock = self.remotes.lock().await;
+        match lock.get(&url) {
+            Some(a) => Ok(Arc::clone(a)),
+            None => {
+                trace!("perf-checkpoint: creating mesh client");
+                trace!("building mesh client for {}", url);
+                let cfg_mesh = self.cfg_for_url(url).await?;
+                let mesh = MeshClient::new(
+                    &self.cfg_ate,
+                    &cfg_mesh,
+                    self.node_id.clone(),
+                    force_temporal | self.temporal,
+                );
+                lock.insert(url.clone(), Arc::clone(&mesh));
+                Ok(mesh)
+            }
+        }
+    }
+
     #[cfg(feature = "enable_client")]
     pub async fn open_ext(
         &self,
@@ -187,25 +219,7 @@ impl Registry {
         loader_local: impl loader::Loader + 'static,
         loader_remote: impl loader::Loader + 'static,
     ) -> Result<ChainGuard, ChainCreationError> {
-        let client = {
-            let mut lock = self.remotes.lock().await;
-            match lock.get(&url) {
-                Some(a) => Arc::clone(a),
-                None => {
-                    trace!("perf-checkpoint: creating mesh client");
-                    trace!("building mesh client for {}", url);
-                    let cfg_mesh = self.cfg_for_url(url).await?;
-                    let mesh = MeshClient::new(
-                        &self.cfg_ate,
-                        &cfg_mesh,
-                        self.node_id.clone(),
-                        force_temporal | self.temporal,
-                    );
-                    lock.insert(url.clone(), Arc::clone(&mesh));
-                    Arc::clone(&mesh)
-                }
-            }
-        };
+        let client = self.mesh_client(url, force_temporal).await?;
 
         trace!("opening chain ({}) on mesh client for {}", key, url);
 
@@ -221,6 +235,42 @@ impl Registry {
         })
     }
 
+    /// Returns the key of the chain that the remote serves for this key, when
+    /// the key is an alias this is the canonical chain that it points to
+    #[cfg(feature = "enable_client")]
+    pub async fn resolve_alias(&self, url: &Url, key: &ChainKey) -> Result<ChainKey, ChainCreationError> {
+        let client = self.mesh_client(url, false).await?;
+        client.resolve_alias(key, url.path().to_string()).await
+    }
+
+    #[cfg(not(feature = "enable_client"))]
+    pub async fn resolve_alias(&self, _url: &Url, _key: &ChainKey) -> Result<ChainKey, ChainCreationError> {
+        return Err(ChainCreationErrorKind::InternalError(
+            "client connections are unsupported".to_string(),
+        )
+        .into());
+    }
+
+    /// Asks the remote how much of its storage quota the chain is using (the
+    /// root only answers for chains that the caller is subscribed to hence the
+    /// chain is opened first)
+    #[cfg(feature = "enable_client")]
+    pub async fn quota_status(&self, url: &Url, key: &ChainKey) -> Result<QuotaStatus, ChainCreationError> {
+        let chain = self.open(url, key, true).await?;
+        match chain.quota_status().await? {
+            Some(a) => Ok(a),
+            None => bail!(ChainCreationErrorKind::NotImplemented),
+        }
+    }
+
+    #[cfg(not(feature = "enable_client"))]
+    pub async fn quota_status(&self, _url: &Url, _key: &ChainKey) -> Result<QuotaStatus, ChainCreationError> {
+        return Err(ChainCreationErrorKind::InternalError(
+            "client connections are unsupported".to_string(),
+        )
+        .into());
+    }
+
     #[cfg(not(feature = "enable_client"))]
     pub async fn open_ext(
         &self,
@@ -252,8 +302,11 @@ impl Registry {
         ret.remote = url.clone();
         ret.wire_protocol = protocol;
 
-        // Set the fail fast
+        // Set the fail fast (and the connect timeout)
         ret.fail_fast = self.fail_fast;
+        if let Some(timeout) = self.connect_timeout {
+            ret.connect_timeout = timeout;
+        }
 
         // Set the ignore certificates
         if self.ignore_certificates {
@@ -456,6 +509,7 @@ impl Registry {
         match port {
             80 => "ws",
             443 => "wss",
+            5443 => "tls",
             _ => "tcp"
         }
     }
@@ -495,21 +549,38 @@ impl ChainGuard {
         self.chain.dio(session).await
     }
 
+    /// Returns how far the local replica is behind the server (for instance
+    /// when cache maintenance has been deferred under heavy inbound load)
+    pub fn lag(&self) -> ChainLag {
+        self.chain.lag()
+    }
+
+    /// Returns how far the synchronization of the chain from the server has
+    /// got, chains that were already open report completion straight away
+    pub fn sync_progress(&self) -> watch::Receiver<SyncProgress> {
+        self.chain.sync_progress()
+    }
+
+    /// Returns false when the chain can not be modified (see Chain::is_writable)
+    pub fn is_writable(&self) -> bool {
+        self.chain.is_writable()
+    }
+
     /// Opens a data access layer that allows mutable changes to data.
     /// Transaction consistency on commit will be guarranted for local redo log files
-    pub async fn dio_mut(&self, session: &'_ dyn AteSession) -> Arc<DioMut> {
+    pub async fn dio_mut(&self, session: &'_ dyn AteSession) -> Result<Arc<DioMut>, CommitError> {
         self.chain.dio_mut(session).await
     }
 
     /// Opens a data access layer that allows mutable changes to data (in a fire-and-forget mode).
     /// No transaction consistency on commits will be enforced
-    pub async fn dio_fire(&self, session: &'_ dyn AteSession) -> Arc<DioMut> {
+    pub async fn dio_fire(&self, session: &'_ dyn AteSession) -> Result<Arc<DioMut>, CommitError> {
         self.chain.dio_fire(session).await
     }
 
     /// Opens a data access layer that allows mutable changes to data.
     /// Transaction consistency on commit will be guarranted for all remote replicas
-    pub async fn dio_full(&self, session: &'_ dyn AteSession) -> Arc<DioMut> {
+    pub async fn dio_full(&self, session: &'_ dyn AteSession) -> Result<Arc<DioMut>, CommitError> {
         self.chain.dio_full(session).await
     }
 
@@ -519,7 +590,7 @@ impl ChainGuard {
         &self,
         session: &'_ dyn AteSession,
         scope: TransactionScope,
-    ) -> Arc<DioMut> {
+    ) -> Result<Arc<DioMut>, CommitError> {
         self.chain.dio_trans(session, scope).await
     }
 
@@ -10,6 +10,7 @@ use std::net::SocketAddr;
 use std::sync::Mutex as StdMutex;
 use std::sync::Weak;
 use std::time::Duration;
+use std::time::Instant;
 use std::{
     borrow::Borrow,
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
@@ -17,21 +18,32 @@ use std::{
 };
 use std::{collections::hash_map::Entry, sync::Arc};
 use tokio::sync::broadcast;
+use tokio::sync::watch;
 use tokio::sync::mpsc;
 use tokio::sync::Mutex;
+use tokio::sync::Semaphore;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use tracing_futures::{Instrument, WithSubscriber};
 use bytes::Bytes;
+use url::Url;
 
+use super::alias::*;
 use super::client::MeshClient;
 use super::core::*;
+use super::fanout::*;
+use super::inflight::*;
+use super::migrate::*;
 use super::msg::*;
+use super::quorum::*;
+use super::quota::*;
+use super::watchdog::*;
 use super::MeshSession;
 use super::Registry;
 use crate::chain::*;
 use crate::comms::TxDirection;
 use crate::comms::TxGroup;
 use crate::comms::*;
+use crate::compact::*;
 use crate::conf::*;
 use crate::crypto::AteHash;
 use crate::engine::TaskEngine;
@@ -44,6 +56,8 @@ use crate::spec::SerializationFormat;
 use crate::time::ChainTimestamp;
 use crate::transaction::*;
 use crate::trust::*;
+use crate::flow::EventValidator;
+use crate::flow::RouteValidators;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RouteChain {
@@ -51,18 +65,155 @@ pub struct RouteChain {
     pub chain: ChainKey,
 }
 
+/// Chain that is currently open on the root
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenChain {
+    pub route: String,
+    pub chain: ChainKey,
+    /// Number of sessions that are subscribed to the chain
+    pub subscribers: usize,
+    /// Seconds since the chain was last used
+    pub idle_secs: u64,
+}
+
+/// Determines who may use the chains that are hosted on a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteMode {
+    /// Chains are opened with the flow of the route (which decides who may
+    /// access them)
+    Standard,
+    /// Anyone may read the chains of the route without authenticating while
+    /// every commit and lock is rejected as read-only. Private chains are
+    /// never served on these routes.
+    PublicRead {
+        /// Clients may connect without wire encryption (even if the server
+        /// has a certificate)
+        allow_plaintext: bool,
+    },
+}
+
+impl RouteMode {
+    pub fn is_public(&self) -> bool {
+        match self {
+            RouteMode::PublicRead { .. } => true,
+            RouteMode::Standard => false,
+        }
+    }
+}
+
+impl Default for RouteMode {
+    fn default() -> RouteMode {
+        RouteMode::Standard
+    }
+}
+
 pub struct MeshRoute {
     pub hello_path: String,
+    pub mode: RouteMode,
     pub cfg_ate: ConfAte,
     pub cfg_mesh: ConfMesh,
     pub flow: Box<dyn OpenFlow>,
     pub flow_type: String,
 }
 
+/// Everything that the root tracks per route besides the route itself, the
+/// state is created when it is first configured (which may be before the
+/// route is added) and lives for as long as the root
+pub(super) struct RouteState {
+    /// Set once the route has been added
+    mode: StdMutex<Option<RouteMode>>,
+    validators: Arc<RouteValidators>,
+    history_cache: Arc<HistoryCache>,
+    inflight_budget: Arc<InflightBudget>,
+    quotas: Arc<RouteQuotas>,
+    watchdog: StdMutex<Option<WatchdogPolicy>>,
+}
+
+impl RouteState {
+    fn new(cfg_mesh: &ConfMesh) -> RouteState {
+        RouteState {
+            mode: StdMutex::new(None),
+            validators: Arc::new(RouteValidators::default()),
+            history_cache: Arc::new(HistoryCache::new(cfg_mesh.history_cache_size)),
+            inflight_budget: Arc::new(InflightBudget::new(
+                cfg_mesh.inflight_connection_limit,
+                cfg_mesh.inflight_route_limit,
+            )),
+            quotas: Arc::new(RouteQuotas::new(cfg_mesh.chain_quota)),
+            watchdog: StdMutex::new(cfg_mesh.watchdog_policy.clone()),
+        }
+    }
+
+    /// Watchdog policy of the route (if it is active)
+    fn watchdog(&self) -> Option<WatchdogPolicy> {
+        self.watchdog
+            .lock()
+            .unwrap()
+            .clone()
+            .filter(|a| a.is_active())
+    }
+}
+
 pub struct MeshChain {
     chain: Arc<Chain>,
     integrity: TrustMode,
     tx_group: Arc<Mutex<TxGroup>>,
+    quorum: Option<Arc<QuorumRelay>>,
+    activity: ChainActivity,
+    migration: MigrationSlot,
+    usage: Arc<ChainUsage>,
+}
+
+impl MeshChain {
+    /// Ethereal chains are evicted after they have been idle for this long
+    /// (persistent chains never are)
+    fn ethereal_ttl(&self) -> Option<Duration> {
+        match self.chain.is_temporal() {
+            true => self.chain.cfg_ate.ethereal_ttl,
+            false => None,
+        }
+    }
+
+    /// Returns true if no session is subscribed to the chain and nothing
+    /// else on the root (e.g. a public web request) holds a reference to it
+    fn is_unused(&self) -> bool {
+        self.activity.subscribers() == 0 && Arc::strong_count(&self.chain) <= 1
+    }
+
+    /// Returns true if the chain is ethereal, is unused and has not been
+    /// written to for the supplied amount of time
+    fn is_idle_for(&self, ttl: Duration) -> bool {
+        self.chain.is_temporal() && self.is_unused() && self.activity.idle() >= ttl
+    }
+}
+
+/// Tracks the subscribers of a chain hosted by the root and when it was
+/// last used, every session subscribed to the chain holds a clone
+#[derive(Clone)]
+pub(crate) struct ChainActivity {
+    subscribers: Arc<()>,
+    last_used: Arc<StdMutex<Instant>>,
+}
+
+impl ChainActivity {
+    fn new() -> ChainActivity {
+        ChainActivity {
+            subscribers: Arc::new(()),
+            last_used: Arc::new(StdMutex::new(Instant::now())),
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_used.lock().unwrap() = Instant::now();
+    }
+
+    fn subscribers(&self) -> usize {
+        Arc::strong_count(&self.subscribers) - 1
+    }
+
+    fn idle(&self) -> Duration {
+        self.last_used.lock().unwrap().elapsed()
+    }
 }
 
 pub struct MeshRoot {
@@ -74,16 +225,33 @@ pub struct MeshRoot {
     pub(super) chains: Mutex<FxHashMap<RouteChain, MeshChain>>,
     pub(super) listener: StdMutex<Option<Arc<StdMutex<Listener<Message, SessionContext>>>>>,
     pub(super) routes: StdMutex<FxHashMap<String, Arc<Mutex<MeshRoute>>>>,
+    pub(super) route_states: StdMutex<FxHashMap<String, Arc<RouteState>>>,
     pub(super) exit: broadcast::Sender<()>,
+    pub(super) compact_limit: Arc<Semaphore>,
+    pub(super) aliases: Mutex<FxHashMap<String, Arc<AliasTable>>>,
+    pub(super) migrations: StdMutex<FxHashMap<RouteChain, Arc<ChainMigration>>>,
+    pub(super) accepted: StdMutex<FxHashSet<RouteChain>>,
 }
 
 #[derive(Clone)]
 struct SessionContextProtected {
     chain: Option<Arc<Chain>>,
+    quorum: Option<Arc<QuorumRelay>>,
+    activity: Option<ChainActivity>,
+    migration: Option<MigrationSlot>,
+    usage: Option<Arc<ChainUsage>>,
     locks: FxHashSet<PrimaryKey>,
+    read_only: bool,
+    validators: Option<Arc<RouteValidators>>,
+    /// The client asked for the quota status of the chain (only those that
+    /// do are sent it when the chain gets close to its quota)
+    quota_watch: bool,
+    quota_warned: bool,
 }
 
 pub(super) struct SessionContext {
+    /// Identifies the connection in the in-flight budget of its route
+    id: u64,
     inside: StdMutex<SessionContextProtected>,
     conversation: Arc<ConversationSession>,
 }
@@ -91,9 +259,18 @@ pub(super) struct SessionContext {
 impl Default for SessionContext {
     fn default() -> SessionContext {
         SessionContext {
+            id: fastrand::u64(..),
             inside: StdMutex::new(SessionContextProtected {
                 chain: None,
+                quorum: None,
+                activity: None,
+                migration: None,
+                usage: None,
                 locks: FxHashSet::default(),
+                read_only: false,
+                validators: None,
+                quota_watch: false,
+                quota_warned: false,
             }),
             conversation: Arc::new(ConversationSession::default()),
         }
@@ -181,7 +358,12 @@ impl MeshRoot {
             chains: Mutex::new(FxHashMap::default()),
             listener: StdMutex::new(None),
             routes: StdMutex::new(FxHashMap::default()),
+            route_states: StdMutex::new(FxHashMap::default()),
             exit: exit_tx.clone(),
+            compact_limit: Arc::new(Semaphore::new(cfg.cfg_mesh.compact_concurrency.max(1))),
+            aliases: Mutex::new(FxHashMap::default()),
+            migrations: StdMutex::new(FxHashMap::default()),
+            accepted: StdMutex::new(FxHashSet::default()),
         });
 
         let processor = Arc::new(MeshRootProcessor {
@@ -221,18 +403,241 @@ impl MeshRoot {
         }
     }
 
+    async fn compaction_worker(
+        chain: Weak<Chain>,
+        policy: CompactionPolicy,
+        limit: Arc<Semaphore>,
+        mut exit: broadcast::Receiver<()>,
+    ) {
+        let mut last_compact = std::time::Instant::now();
+        let mut last_count = 0u64;
+        loop {
+            tokio::select! {
+                _ = crate::engine::sleep(policy.check_interval) => { },
+                _ = exit.recv() => { break; }
+            }
+
+            let chain = match Weak::upgrade(&chain) {
+                Some(a) => a,
+                None => {
+                    break;
+                }
+            };
+
+            // Compactions that were triggered by other means reset the age
+            let count = chain.metrics.lock().unwrap().compactions;
+            if count != last_count {
+                last_count = count;
+                last_compact = std::time::Instant::now();
+            }
+
+            let trigger = match chain
+                .compaction_trigger(&policy, last_compact.elapsed())
+                .await
+            {
+                Ok(Some(a)) => a,
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!("compaction policy failed - {}", err);
+                    continue;
+                }
+            };
+
+            // Only a limited number of chains may compact at the same time
+            let _permit = match limit.acquire().await {
+                Ok(a) => a,
+                Err(_) => break,
+            };
+            debug!("compaction triggered ({}) for {}", trigger, chain.key());
+            if let Err(err) = chain.compact_with_stats(trigger).await {
+                error!("failed to compact chain - {}", err);
+            }
+            last_count = chain.metrics.lock().unwrap().compactions;
+            last_compact = std::time::Instant::now();
+        }
+    }
+
+    async fn checkpoint_worker(
+        chain: Weak<Chain>,
+        key: PrivateSignKey,
+        interval: Duration,
+        mut exit: broadcast::Receiver<()>,
+    ) {
+        loop {
+            let chain = match Weak::upgrade(&chain) {
+                Some(a) => a,
+                None => {
+                    break;
+                }
+            };
+            if chain.needs_checkpoint().await {
+                if let Err(err) = chain.checkpoint(&key).await {
+                    warn!("failed to checkpoint chain - {}", err);
+                }
+            }
+            drop(chain);
+
+            tokio::select! {
+                _ = crate::engine::sleep(interval) => { },
+                _ = exit.recv() => { break; }
+            }
+        }
+    }
+
+    /// Watches a chain for transactions that never complete, the policy is
+    /// read from the route on every check so that changes to it apply to
+    /// the chains that are already open (the worker stops once the route
+    /// no longer has a policy)
+    async fn watchdog_worker(
+        root: Weak<MeshRoot>,
+        route_chain: RouteChain,
+        chain: Weak<Chain>,
+        state: Arc<RouteState>,
+        mut exit: broadcast::Receiver<()>,
+    ) {
+        let mut reported = false;
+        loop {
+            let policy = match state.watchdog() {
+                Some(a) => a,
+                None => {
+                    break;
+                }
+            };
+            tokio::select! {
+                _ = crate::engine::sleep(policy.check_interval) => { },
+                _ = exit.recv() => { break; }
+            }
+
+            let chain = match Weak::upgrade(&chain) {
+                Some(a) => a,
+                None => {
+                    break;
+                }
+            };
+            if chain.progress.is_stuck(policy.threshold) == false {
+                if reported {
+                    info!("watchdog - chain {} is making progress again", chain.key());
+                    reported = false;
+                }
+                continue;
+            }
+            if reported == false {
+                error!(
+                    "watchdog - chain {} on {} is stuck\n{}",
+                    chain.key(),
+                    route_chain.route,
+                    chain.pipe_diagnostics()
+                );
+                reported = true;
+            }
+            if policy.recover == false {
+                continue;
+            }
+
+            // Abort whatever is pending and evict the chain so that its pipes
+            // are rebuilt from scratch the next time it is opened
+            let aborted = chain.progress.abort_pending();
+            warn!(
+                "watchdog - aborted {} pending transactions on {}",
+                aborted,
+                chain.key()
+            );
+            chain.metrics.lock().unwrap().watchdog_recoveries += 1;
+            if let Some(root) = Weak::upgrade(&root) {
+                root.evict_chain(&route_chain, &chain, policy.threshold).await;
+            }
+            break;
+        }
+    }
+
+    /// Removes a chain from the root (if it is still the one hosted for the
+    /// route) and shuts it down, the shutdown is abandoned after the timeout
+    /// as the chain may be deadlocked
+    async fn evict_chain(&self, route_chain: &RouteChain, chain: &Arc<Chain>, timeout: Duration) {
+        {
+            let mut chains = self.chains.lock().await;
+            match chains.get(route_chain) {
+                Some(a) if Arc::ptr_eq(&a.chain, chain) => {
+                    debug!("evicting chain {} from {}", route_chain.chain, route_chain.route);
+                    chains.remove(route_chain);
+                }
+                _ => return,
+            }
+        }
+        match crate::engine::timeout(timeout, chain.shutdown()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => error!("failed to shutdown chain - {}", err),
+            Err(_) => warn!("shutdown of chain {} timed out", route_chain.chain),
+        }
+    }
+
+    /// Number of chains hosted by this root that the watchdog considers to
+    /// be stuck (only chains on routes that have a watchdog policy are counted)
+    pub async fn stuck_chains(&self) -> usize {
+        let chains = self.chains.lock().await;
+        chains
+            .iter()
+            .filter(|(k, v)| match self.find_route_state(k.route.as_str()).and_then(|a| a.watchdog()) {
+                Some(policy) => v.chain.progress.is_stuck(policy.threshold),
+                None => false,
+            })
+            .count()
+    }
+
+    /// Sets the watchdog policy of a route (which otherwise uses the policy
+    /// of the mesh configuration), chains that were opened while the route
+    /// had no policy are only watched once they are opened again
+    pub fn set_watchdog_policy(&self, route: &str, policy: Option<WatchdogPolicy>) {
+        *self.route_state(route).watchdog.lock().unwrap() = policy;
+    }
+
+    /// Returns the watchdog policy of a route (if it is active)
+    pub fn watchdog_policy(&self, route: &str) -> Option<WatchdogPolicy> {
+        self.find_route_state(route).and_then(|a| a.watchdog())
+    }
+
     pub async fn add_route<F>(
         self: &Arc<Self>,
         open_flow: Box<F>,
         cfg_ate: &ConfAte,
     ) -> Result<(), CommsError>
+    where
+        F: OpenFlow + 'static,
+    {
+        self.add_route_ext(open_flow, cfg_ate, RouteMode::Standard)
+            .await
+    }
+
+    /// Adds a route whose chains anyone may read without an account (or
+    /// even wire encryption if allowed) while all writes are rejected
+    pub async fn add_public_route<F>(
+        self: &Arc<Self>,
+        open_flow: Box<F>,
+        cfg_ate: &ConfAte,
+        allow_plaintext: bool,
+    ) -> Result<(), CommsError>
+    where
+        F: OpenFlow + 'static,
+    {
+        self.add_route_ext(open_flow, cfg_ate, RouteMode::PublicRead { allow_plaintext })
+            .await
+    }
+
+    pub async fn add_route_ext<F>(
+        self: &Arc<Self>,
+        open_flow: Box<F>,
+        cfg_ate: &ConfAte,
+        mode: RouteMode,
+    ) -> Result<(), CommsError>
     where
         F: OpenFlow + 'static,
     {
         let hello_path = open_flow.hello_path().to_string();
+        let event_validators = open_flow.event_validators();
 
         let route = MeshRoute {
             hello_path: hello_path.clone(),
+            mode,
             cfg_ate: cfg_ate.clone(),
             cfg_mesh: self.cfg_mesh.clone(),
             flow: open_flow,
@@ -243,24 +648,223 @@ impl MeshRoot {
             let mut routes = self.routes.lock().unwrap();
             routes.insert(hello_path.clone(), Arc::new(Mutex::new(route)));
         }
+        {
+            let state = self.route_state(hello_path.as_str());
+            *state.mode.lock().unwrap() = Some(mode);
+            for validator in event_validators {
+                state.validators.add(validator);
+            }
+        }
 
         {
+            let allow_plaintext = match mode {
+                RouteMode::PublicRead { allow_plaintext } => allow_plaintext,
+                RouteMode::Standard => false,
+            };
             let listener = self.listener.lock().unwrap();
             if let Some(listener) = listener.deref() {
                 let mut listener = listener.lock().unwrap();
-                listener.add_route(hello_path.as_str())?
+                listener.add_route_ext(hello_path.as_str(), allow_plaintext)?
             }
         };
 
         Ok(())
     }
 
+    /// Registers a validator that runs on every event committed to the route
+    /// before it is accepted, it also applies to the clients that are already
+    /// connected to the route from their next commit onwards
+    pub fn add_route_validator<V>(&self, route: &str, validator: V)
+    where
+        V: EventValidator + 'static,
+    {
+        self.route_state(route).validators.add(Arc::new(validator));
+    }
+
+    /// Returns the state of a route (creating it if it does not exist yet)
+    fn route_state(&self, route: &str) -> Arc<RouteState> {
+        let mut route_states = self.route_states.lock().unwrap();
+        route_states
+            .entry(route.to_string())
+            .or_insert_with(|| Arc::new(RouteState::new(&self.cfg_mesh)))
+            .clone()
+    }
+
+    fn find_route_state(&self, route: &str) -> Option<Arc<RouteState>> {
+        let route_states = self.route_states.lock().unwrap();
+        route_states.get(route).cloned()
+    }
+
+    /// Changes how much memory (in bytes) the history cache of a route may
+    /// use, a size of zero disables the cache for the route
+    pub fn set_history_cache_size(&self, route: &str, size: usize) {
+        self.route_state(route).history_cache.set_capacity(size);
+    }
+
+    /// Returns the hit rate and memory use of the history cache of a route
+    pub fn history_cache_metrics(&self, route: &str) -> Option<HistoryCacheMetrics> {
+        self.find_route_state(route)
+            .map(|a| a.history_cache.metrics())
+    }
+
+    /// Returns the history cache of a route (unless it is disabled)
+    fn history_cache(&self, route: &str) -> Option<Arc<HistoryCache>> {
+        self.find_route_state(route)
+            .map(|a| Arc::clone(&a.history_cache))
+            .filter(|a| a.is_enabled())
+    }
+
+    /// Changes how many bytes of transactions (that have been received but
+    /// not yet committed) a single connection and all the connections of a
+    /// route together may have in flight, a limit of zero disables it
+    pub fn set_inflight_budget(&self, route: &str, connection_limit: usize, route_limit: usize) {
+        self.route_state(route)
+            .inflight_budget
+            .set_limits(connection_limit, route_limit);
+    }
+
+    /// Returns how many bytes of transactions are in flight on a route (and
+    /// how often its budget was exceeded)
+    pub fn inflight_metrics(&self, route: &str) -> Option<InflightMetrics> {
+        self.find_route_state(route)
+            .map(|a| a.inflight_budget.metrics())
+    }
+
+    fn inflight_budget(&self, route: &str) -> Option<Arc<InflightBudget>> {
+        self.find_route_state(route)
+            .map(|a| Arc::clone(&a.inflight_budget))
+    }
+
+    /// Changes how many bytes each chain of a route may occupy on disk (the
+    /// chains whose quota is overridden keep their own), a quota of zero
+    /// means that the chains are not limited
+    pub fn set_chain_quota(&self, route: &str, limit: u64) {
+        self.route_state(route).quotas.set_default(limit);
+    }
+
+    /// Overrides the quota of a single chain on a route (or removes the
+    /// override when the limit is None)
+    pub fn set_chain_quota_override(&self, route: &str, key: &ChainKey, limit: Option<u64>) {
+        self.route_state(route).quotas.set_override(key, limit);
+    }
+
+    /// Replaces the quota overrides of a route with the `ChainQuota` rows
+    /// that are stored in a system chain, returns how many were loaded
+    pub async fn load_chain_quotas(
+        &self,
+        route: &str,
+        chain: &Arc<Chain>,
+        session: &'_ dyn AteSession,
+    ) -> Result<usize, LoadError> {
+        let dio = chain.dio(session).await;
+        let overrides = dio
+            .roots::<ChainQuota>()
+            .await?
+            .into_iter()
+            .map(|a| a.take())
+            .collect::<Vec<_>>();
+        let ret = overrides.len();
+        debug!("loaded {} chain quotas for {}", ret, route);
+
+        self.route_state(route)
+            .quotas
+            .replace_overrides(overrides.into_iter());
+        Ok(ret)
+    }
+
+    fn route_quotas(&self, route: &str) -> Option<Arc<RouteQuotas>> {
+        self.find_route_state(route)
+            .map(|a| Arc::clone(&a.quotas))
+    }
+
+    /// Returns how much of its storage quota a chain is using (chains that
+    /// are not currently open on this root are reported as empty)
+    pub async fn quota_status(&self, route: &str, key: &ChainKey) -> QuotaStatus {
+        let limit = self.route_quotas(route).and_then(|a| a.limit(key));
+        let chain = {
+            let chains = self.chains.lock().await;
+            chains
+                .iter()
+                .filter(|(k, _)| k.route == route && k.chain.name == key.name)
+                .map(|(_, v)| (Arc::clone(&v.chain), Arc::clone(&v.usage)))
+                .next()
+        };
+        let used = match chain {
+            Some((chain, usage)) => usage.refresh(&chain, limit).await,
+            None => 0,
+        };
+        QuotaStatus {
+            chain: key.clone(),
+            used,
+            limit,
+        }
+    }
+
+    /// Returns the mode of a route (if the route exists)
+    pub fn route_mode(&self, route: &str) -> Option<RouteMode> {
+        self.find_route_state(route)
+            .and_then(|a| a.mode.lock().unwrap().clone())
+    }
+
+    /// Opens a chain on a public route for a reader that is not connected to
+    /// this root (e.g. a plain web request)
+    pub(super) async fn open_public(
+        self: &Arc<Self>,
+        route: &str,
+        chain_key: &ChainKey,
+    ) -> Result<Arc<Chain>, ChainCreationError> {
+        match self.route_mode(route) {
+            Some(mode) if mode.is_public() => {}
+            _ => bail!(ChainCreationErrorKind::InvalidRoute(route.to_string())),
+        }
+
+        let chain_key = match self.resolve_alias(route, chain_key).await {
+            Ok(a) => a,
+            Err(ChainCreationError(ChainCreationErrorKind::InvalidRoute(_), _)) => chain_key.clone(),
+            Err(err) => return Err(err),
+        };
+        if chain_key.name == ALIAS_CHAIN_NAME {
+            bail!(ChainCreationErrorKind::ServerRejected(FatalTerminate::Denied {
+                reason: "this chain is reserved by the system".to_string(),
+            }));
+        }
+        match self.lookup.lookup(&chain_key) {
+            Some((_, node_id)) if node_id == self.node_id => {}
+            _ => bail!(ChainCreationErrorKind::NotThisRoot),
+        }
+
+        // There is no connection behind the reader so nothing is broadcast to it
+        let mut tx = Tx {
+            hello_path: route.to_string(),
+            direction: TxDirection::Nullcast,
+            wire_format: self.cfg_mesh.wire_format,
+            relay: None,
+            metrics: Arc::new(StdMutex::new(Metrics::default())),
+            throttle: Arc::new(StdMutex::new(Throttle::default())),
+            exit_dependencies: Vec::new(),
+            peer: None,
+        };
+        let route_chain = RouteChain {
+            route: route.to_string(),
+            chain: chain_key,
+        };
+        let opened = open_internal(Arc::clone(self), route_chain, &mut tx).await?;
+        Ok(opened.chain)
+    }
+
     pub async fn clean(self: &Arc<Self>) {
         let mut shutdown_me = Vec::new();
         {
             let mut guard = self.chains.lock().await;
-            guard.retain(|_k, v| {
-                if Arc::strong_count(&v.chain) <= 1 {
+            guard.retain(|k, v| {
+                // Ethereal chains with a TTL are kept until they have been idle
+                // for long enough, everything else is dropped once it is unused
+                let evict = match v.ethereal_ttl() {
+                    Some(ttl) => v.is_idle_for(ttl),
+                    None => Arc::strong_count(&v.chain) <= 1,
+                };
+                if evict {
+                    debug!("evicting chain {} from {}", k.chain, k.route);
                     shutdown_me.push(Arc::clone(&v.chain));
                     false
                 } else {
@@ -275,8 +879,491 @@ impl MeshRoot {
         }
     }
 
-    pub fn server_id(&self) -> NodeId {
-        self.server_id.clone()
+    /// Makes room for a new ethereal chain on a route that is limited to a
+    /// maximum number of them by evicting the least recently used idle chain
+    fn evict_ethereal_lru(
+        chains: &mut FxHashMap<RouteChain, MeshChain>,
+        route: &str,
+        max: usize,
+    ) -> Result<Option<Arc<Chain>>, ChainCreationError> {
+        let ethereal = chains
+            .iter()
+            .filter(|(k, v)| k.route == route && v.chain.is_temporal())
+            .collect::<Vec<_>>();
+        if ethereal.len() < max {
+            return Ok(None);
+        }
+
+        let lru = ethereal
+            .into_iter()
+            .filter(|(_, v)| v.is_unused())
+            .max_by_key(|(_, v)| v.activity.idle())
+            .map(|(k, _)| k.clone());
+        match lru.and_then(|k| chains.remove(&k).map(|v| (k, v))) {
+            Some((k, v)) => {
+                debug!("evicting least recently used chain {} from {}", k.chain, k.route);
+                Ok(Some(v.chain))
+            }
+            None => {
+                bail!(ChainCreationErrorKind::TooManyEtherealChains(
+                    route.to_string(),
+                    max
+                ));
+            }
+        }
+    }
+
+    pub fn server_id(&self) -> NodeId {
+        self.server_id.clone()
+    }
+
+    /// Returns the aliases of a route (which are loaded the first time
+    /// they are needed)
+    async fn alias_table(&self, route: &str) -> Result<Arc<AliasTable>, ChainCreationError> {
+        let mut guard = self.aliases.lock().await;
+        if let Some(table) = guard.get(route) {
+            return Ok(Arc::clone(table));
+        }
+
+        let cfg_ate = self.route_cfg_ate(route).await?;
+        let table = Arc::new(AliasTable::open(&cfg_ate, route, self.server_id.clone()).await?);
+        guard.insert(route.to_string(), Arc::clone(&table));
+        Ok(table)
+    }
+
+    /// Returns the configuration of the chains hosted on a route
+    async fn route_cfg_ate(&self, route: &str) -> Result<ConfAte, ChainCreationError> {
+        let route = {
+            let routes = self.routes.lock().unwrap();
+            match routes.get(route) {
+                Some(a) => Arc::clone(a),
+                None => {
+                    bail!(ChainCreationErrorKind::InvalidRoute(route.to_string()))
+                }
+            }
+        };
+        let route = route.lock().await;
+        Ok(route.cfg_ate.clone())
+    }
+
+    /// Returns true if a real chain with this key exists under the route
+    async fn chain_exists(&self, route: &str, key: &ChainKey) -> bool {
+        if key.name == ALIAS_CHAIN_NAME {
+            return true;
+        }
+        {
+            let chains = self.chains.lock().await;
+            if chains.keys().any(|a| a.route == route && a.chain.name == key.name) {
+                return true;
+            }
+        }
+
+        #[cfg(feature = "enable_local_fs")]
+        {
+            let route_name = route;
+            let route = {
+                let routes = self.routes.lock().unwrap();
+                routes.get(route).map(Arc::clone)
+            };
+            if let Some(route) = route {
+                let log_path = route.lock().await.cfg_ate.log_path.clone();
+                if let Some(log_path) = log_path {
+                    // Same layout as the redo logs opened by `open_internal`
+                    let path = format!(
+                        "{}/{}/{}.log.0",
+                        log_path.trim_end_matches('/'),
+                        route_name.trim_start_matches('/'),
+                        key.name
+                    );
+                    if std::path::Path::new(path.as_str()).exists() {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Registers an alias under a route so that subscriptions to the alias are
+    /// served from the canonical chain. An alias may not shadow a chain that
+    /// already exists nor form a loop with the other aliases. In a cluster the
+    /// alias must be registered on the root that owns the alias key.
+    pub async fn add_alias(
+        &self,
+        route: &str,
+        alias: ChainKey,
+        canonical: ChainKey,
+    ) -> Result<(), ChainCreationError> {
+        let table = self.alias_table(route).await?;
+        if table.contains(&alias) == false && self.chain_exists(route, &alias).await {
+            bail!(ChainCreationErrorKind::AliasShadowsChain(alias.name));
+        }
+        table.add(alias, canonical).await
+    }
+
+    /// Removes an alias from a route (the canonical chain is left untouched),
+    /// returns false if there was no such alias
+    pub async fn remove_alias(
+        &self,
+        route: &str,
+        alias: &ChainKey,
+    ) -> Result<bool, ChainCreationError> {
+        let table = self.alias_table(route).await?;
+        table.remove(alias).await
+    }
+
+    /// Returns the key of the chain that actually serves this key
+    pub async fn resolve_alias(
+        &self,
+        route: &str,
+        key: &ChainKey,
+    ) -> Result<ChainKey, ChainCreationError> {
+        let table = self.alias_table(route).await?;
+        table.resolve(key)
+    }
+
+    /// Lists all the aliases that are registered under a route
+    pub async fn aliases(&self, route: &str) -> Result<Vec<ChainAlias>, ChainCreationError> {
+        let table = self.alias_table(route).await?;
+        Ok(table.list())
+    }
+
+    /// Returns the statistics of a chain that is currently open on this root
+    /// (or None if the chain is not open)
+    pub async fn chain_statistics(&self, route: &str, key: &ChainKey) -> Option<ChainStatistics> {
+        let chain = {
+            let chains = self.chains.lock().await;
+            chains
+                .iter()
+                .filter(|(k, _)| k.route == route && k.chain.name == key.name)
+                .map(|(_, v)| Arc::clone(&v.chain))
+                .next()
+        };
+        match chain {
+            Some(chain) => Some(chain.statistics().await),
+            None => None,
+        }
+    }
+
+    /// Number of connections that were aborted during the handshake (e.g.
+    /// because the client never said hello)
+    pub fn dropped_connections(&self) -> u64 {
+        let guard = self.listener.lock().unwrap();
+        guard
+            .as_ref()
+            .map(|listener| listener.lock().unwrap().dropped_connections())
+            .unwrap_or(0)
+    }
+
+    /// Lists the chains that are currently open on this root
+    pub async fn open_chains(&self) -> Vec<OpenChain> {
+        let chains = self.chains.lock().await;
+        chains
+            .iter()
+            .map(|(k, v)| OpenChain {
+                route: k.route.clone(),
+                chain: k.chain.clone(),
+                subscribers: v.activity.subscribers(),
+                idle_secs: v.activity.idle().as_secs(),
+            })
+            .collect()
+    }
+
+    /// Lists the connections that this root is currently serving
+    pub fn connections(&self) -> Vec<ConnectionSummary> {
+        let guard = self.listener.lock().unwrap();
+        guard
+            .as_ref()
+            .map(|listener| listener.lock().unwrap().connection_summaries())
+            .unwrap_or_default()
+    }
+
+    /// Most recent connections that failed with an error (oldest first)
+    pub fn recent_errors(&self) -> Vec<ConnectionError> {
+        let guard = self.listener.lock().unwrap();
+        guard
+            .as_ref()
+            .map(|listener| listener.lock().unwrap().recent_errors())
+            .unwrap_or_default()
+    }
+
+    /// Certificates that this root currently accepts new connections with
+    pub fn certificates(&self) -> ServerCertificates {
+        let guard = self.listener.lock().unwrap();
+        guard
+            .as_ref()
+            .map(|listener| listener.lock().unwrap().certificates())
+            .unwrap_or_else(|| self.cfg_mesh.server_certificates())
+    }
+
+    /// Rotates the certificate that this root accepts secure connections
+    /// with. The new certificate is used straight away while the existing
+    /// ones remain valid (and advertised to clients that pinned them) until
+    /// the grace period elapses after which they are removed. Connections
+    /// that are already established are not affected.
+    pub fn rotate_certificate(&self, new_key: PrivateEncryptKey, grace: Duration) -> Result<(), CommsError> {
+        let listener = {
+            let guard = self.listener.lock().unwrap();
+            match guard.as_ref() {
+                Some(a) => Arc::clone(a),
+                None => {
+                    bail!(CommsErrorKind::NotListening);
+                }
+            }
+        };
+
+        let now = chrono::Utc::now();
+        let expires = now + chrono::Duration::from_std(grace).unwrap_or_else(|_| chrono::Duration::zero());
+        {
+            let mut listener = listener.lock().unwrap();
+            let mut certs = listener.certificates();
+            certs.prune(now);
+            let mut rotated = ServerCertificates::default();
+            for cert in certs.iter() {
+                let cert = match cert.not_after {
+                    Some(a) if a <= expires => cert.clone(),
+                    _ => cert.clone().with_not_after(expires),
+                };
+                rotated.push(cert);
+            }
+            rotated.push(ServerCertificate::new(new_key.clone()).with_not_before(now));
+            listener.set_certificates(rotated);
+        }
+        info!("rotated certificate to {} (old certificates expire at {})", new_key.hash(), expires.to_rfc3339());
+
+        // Remove the old certificates once the grace period has passed
+        let listener = Arc::downgrade(&listener);
+        TaskEngine::spawn(async move {
+            crate::engine::sleep(grace).await;
+            if let Some(listener) = listener.upgrade() {
+                let mut listener = listener.lock().unwrap();
+                let mut certs = listener.certificates();
+                certs.prune(chrono::Utc::now());
+                listener.set_certificates(certs);
+            }
+        });
+        Ok(())
+    }
+
+    /// Stops accepting new connections so that this root can be restarted
+    /// without dropping the clients that are still connected. When `hint` is
+    /// set the clients are asked to reconnect to another root. The returned
+    /// receiver tracks the number of connections that remain (or is None if
+    /// the root is not listening)
+    pub fn drain(&self, hint: bool) -> Option<watch::Receiver<usize>> {
+        let guard = self.listener.lock().unwrap();
+        guard.as_ref().map(|listener| {
+            let listener = listener.lock().unwrap();
+            listener.drain(hint)
+        })
+    }
+
+    /// Drains the root and waits until all the connections have closed or
+    /// the timeout elapses, returning the number of connections that remain
+    pub async fn drain_and_wait(&self, hint: bool, timeout: Duration) -> usize {
+        let mut remaining = match self.drain(hint) {
+            Some(a) => a,
+            None => return 0,
+        };
+        let wait = async {
+            while *remaining.borrow() > 0 {
+                if remaining.changed().await.is_err() {
+                    break;
+                }
+            }
+        };
+        let _ = crate::engine::timeout(timeout, wait).await;
+        let ret = *remaining.borrow();
+        ret
+    }
+
+    /// Allows this root to serve a chain that is being migrated to it from
+    /// another root (even though the chain does not hash to this root)
+    pub fn accept_migration(&self, route: &str, key: &ChainKey) {
+        let mut guard = self.accepted.lock().unwrap();
+        guard.insert(RouteChain {
+            route: route.to_string(),
+            chain: key.clone(),
+        });
+    }
+
+    /// Stops serving a chain that was accepted from another root (e.g. after
+    /// the migration was aborted), returns false if it was never accepted
+    pub fn release_migration(&self, route: &str, key: &ChainKey) -> bool {
+        let mut guard = self.accepted.lock().unwrap();
+        guard.remove(&RouteChain {
+            route: route.to_string(),
+            chain: key.clone(),
+        })
+    }
+
+    /// Lists the chains that were accepted from other roots
+    pub fn accepted_migrations(&self) -> Vec<RouteChain> {
+        let guard = self.accepted.lock().unwrap();
+        guard.iter().cloned().collect()
+    }
+
+    fn is_accepted(&self, route: &str, key: &ChainKey) -> bool {
+        let guard = self.accepted.lock().unwrap();
+        guard.contains(&RouteChain {
+            route: route.to_string(),
+            chain: key.clone(),
+        })
+    }
+
+    fn migration(&self, route: &str, key: &ChainKey) -> Option<Arc<ChainMigration>> {
+        let guard = self.migrations.lock().unwrap();
+        guard
+            .get(&RouteChain {
+                route: route.to_string(),
+                chain: key.clone(),
+            })
+            .cloned()
+    }
+
+    /// Returns the root that serves a chain that has migrated away from here
+    fn migrated_to(&self, route: &str, key: &ChainKey) -> Option<MeshAddress> {
+        self.migration(route, key)
+            .filter(|a| a.is_migrated())
+            .map(|a| a.address.clone())
+    }
+
+    /// Lists the chains that are being (or have been) migrated away from this root
+    pub fn migrations(&self) -> Vec<MigrationStatus> {
+        let guard = self.migrations.lock().unwrap();
+        guard.values().map(|a| a.status()).collect()
+    }
+
+    /// Opens a chain for an administrative task rather than for a session
+    async fn open_chain_for_admin(
+        self: &Arc<Self>,
+        route_chain: RouteChain,
+    ) -> Result<OpenedChain, ChainCreationError> {
+        let metrics = Arc::new(StdMutex::new(Metrics::default()));
+        let throttle = Arc::new(StdMutex::new(Throttle::default()));
+        let (opened, _, _) = open_chain(
+            Arc::clone(self),
+            route_chain,
+            &metrics,
+            &throttle,
+            self.cfg_mesh.wire_encryption,
+            None,
+        )
+        .await?;
+        Ok(opened)
+    }
+
+    /// Puts a chain hosted on this root into migrate mode, from then on its
+    /// commits are also forwarded to the destination while its history is
+    /// copied there in the background (the destination must first accept the
+    /// migration). The chain is still served by this root until the cutover.
+    pub async fn start_migration(
+        self: &Arc<Self>,
+        route: &str,
+        key: &ChainKey,
+        destination: Url,
+    ) -> Result<MigrationStatus, ChainCreationError> {
+        if self.migration(route, key).is_some() {
+            bail!(ChainCreationErrorKind::MigrationInProgress(key.to_string()));
+        }
+        match self.lookup.lookup(key) {
+            Some((_, node_id)) if node_id == self.node_id => {}
+            _ if self.is_accepted(route, key) => {}
+            _ => {
+                bail!(ChainCreationErrorKind::NotThisRoot);
+            }
+        }
+
+        let route_chain = RouteChain {
+            route: route.to_string(),
+            chain: key.clone(),
+        };
+        let cfg_ate = self.route_cfg_ate(route).await?;
+        let opened = self.open_chain_for_admin(route_chain.clone()).await?;
+        let migration = ChainMigration::new(&cfg_ate, route_chain.clone(), destination).await?;
+        {
+            let mut guard = self.migrations.lock().unwrap();
+            if guard.contains_key(&route_chain) {
+                bail!(ChainCreationErrorKind::MigrationInProgress(key.to_string()));
+            }
+            guard.insert(route_chain, Arc::clone(&migration));
+        }
+
+        // Commits are forwarded before the copy starts so that nothing falls
+        // between the two
+        opened.migration.lock().unwrap().replace(Arc::clone(&migration));
+        info!("migration of {} to {} has started", key, migration.status().destination);
+        {
+            let migration = Arc::clone(&migration);
+            let chain = Arc::clone(&opened.chain);
+            TaskEngine::spawn(async move {
+                migration.copy_history(&chain).await;
+            });
+        }
+        Ok(migration.status())
+    }
+
+    /// Hands a migrating chain over to its destination. The commits pause
+    /// until the destination has every event of the chain, after which the
+    /// chain is closed on this root and its clients are asked to reconnect
+    /// (they are relayed to the destination from then on). If the cutover
+    /// fails this root remains authoritative and it may be retried.
+    pub async fn cutover_migration(
+        self: &Arc<Self>,
+        route: &str,
+        key: &ChainKey,
+    ) -> Result<MigrationStatus, ChainCreationError> {
+        let migration = match self.migration(route, key) {
+            Some(a) => a,
+            None => {
+                bail!(ChainCreationErrorKind::MigrationNotFound(key.to_string()));
+            }
+        };
+        let route_chain = RouteChain {
+            route: route.to_string(),
+            chain: key.clone(),
+        };
+        let opened = self.open_chain_for_admin(route_chain.clone()).await?;
+        migration.cutover(&opened.chain).await?;
+
+        let evicted = self.chains.lock().await.remove(&route_chain);
+        if let Some(evicted) = evicted {
+            let pck = Packet::from(Message::Reconnect).to_packet_data(self.cfg_mesh.wire_format)?;
+            evicted.tx_group.lock().await.send(pck, None).await;
+            if let Err(err) = evicted.chain.shutdown().await {
+                error!("failed to shutdown chain - {}", err);
+            }
+        }
+        Ok(migration.status())
+    }
+
+    /// Stops a migration before it cuts over, the chain carries on as if the
+    /// migration never happened (whatever was copied to the destination is
+    /// left for it to release)
+    pub async fn abort_migration(
+        &self,
+        route: &str,
+        key: &ChainKey,
+    ) -> Result<MigrationStatus, ChainCreationError> {
+        let migration = match self.migration(route, key) {
+            Some(a) => a,
+            None => {
+                bail!(ChainCreationErrorKind::MigrationNotFound(key.to_string()));
+            }
+        };
+        migration.abort().await?;
+
+        let route_chain = RouteChain {
+            route: route.to_string(),
+            chain: key.clone(),
+        };
+        self.migrations.lock().unwrap().remove(&route_chain);
+        if let Some(chain) = self.chains.lock().await.get(&route_chain) {
+            chain.migration.lock().unwrap().take();
+        }
+        info!("migration of {} was aborted", key);
+        Ok(migration.status())
     }
 
     pub async fn shutdown(self: &Arc<Self>) {
@@ -298,6 +1385,13 @@ impl MeshRoot {
                 }
             }
         }
+
+        {
+            let mut guard = self.aliases.lock().await;
+            for (_, v) in guard.drain() {
+                v.shutdown().await;
+            }
+        }
     }
 }
 
@@ -326,6 +1420,17 @@ for MeshRoot
         Listener::accept_stream(listener, rx, rx_proto, tx, hello, wire_encryption, sock_addr, self.exit.subscribe()).await?;
         Ok(())
     }
+
+    fn supports_multiplex(&self) -> bool {
+        true
+    }
+
+    fn allows_plaintext(&self, path: &str) -> bool {
+        match self.route_mode(path) {
+            Some(RouteMode::PublicRead { allow_plaintext }) => allow_plaintext,
+            _ => false,
+        }
+    }
 }
 
 fn disconnected(mut context: SessionContextProtected) -> Result<(), CommsError> {
@@ -334,6 +1439,9 @@ fn disconnected(mut context: SessionContextProtected) -> Result<(), CommsError>
             chain.pipe.unlock_local(key.clone())?;
         }
     }
+    if let Some(activity) = context.activity.take() {
+        activity.touch();
+    }
     context.chain = None;
 
     Ok(())
@@ -342,6 +1450,7 @@ fn disconnected(mut context: SessionContextProtected) -> Result<(), CommsError>
 struct ServerPipe {
     chain_key: ChainKey,
     tx_group: Arc<Mutex<TxGroup>>,
+    last_used: Arc<StdMutex<Instant>>,
     wire_format: SerializationFormat,
     next: Arc<Box<dyn EventPipe>>,
 }
@@ -349,14 +1458,13 @@ struct ServerPipe {
 #[async_trait]
 impl EventPipe for ServerPipe {
     async fn feed(&self, work: ChainWork) -> Result<(), CommitError> {
+        *self.last_used.lock().unwrap() = Instant::now();
+
         // If this packet is being broadcast then send it to all the other nodes too
         if work.trans.transmit {
             let evts = MessageEvent::convert_to(&work.trans.events);
-            let pck = Packet::from(Message::Events {
-                commit: None,
-                evts: evts.clone(),
-            })
-            .to_packet_data(self.wire_format)?;
+            let pck = Packet::from(Message::Events { commit: None, evts })
+                .to_packet_data(self.wire_format)?;
             
             let mut tx = self.tx_group.lock().await;
             tx.send(pck, None).await;
@@ -405,6 +1513,32 @@ async fn open_internal<'b>(
         route_chain.route, route_chain.chain
     );
 
+    let wire_format = root.cfg_mesh.wire_format;
+    let wire_encryption = tx.wire_encryption().await.map(|a| a.size());
+    let (opened, tx_group, secured_with) =
+        open_chain(root, route_chain, &tx.metrics, &tx.throttle, wire_encryption, tx.peer.clone()).await?;
+
+    // Only the session that opened a private chain is told what it is secured with
+    if let Some(session) = secured_with {
+        let msg = Message::SecuredWith(session);
+        let pck = Packet::from(msg).to_packet_data(wire_format)?;
+        tx.send_reply(pck).await?;
+    }
+    tx.replace_group(tx_group).await;
+    Ok(opened)
+}
+
+/// Opens a chain hosted on the root (or returns the one that is already
+/// open) along with the broadcast group of its sessions
+async fn open_chain(
+    root: Arc<MeshRoot>,
+    route_chain: RouteChain,
+    metrics: &Arc<StdMutex<Metrics>>,
+    throttle: &Arc<StdMutex<Throttle>>,
+    wire_encryption: Option<KeySize>,
+    peer: Option<ConnectionPeer>,
+) -> Result<(OpenedChain, Arc<Mutex<TxGroup>>, Option<AteSessionUser>), ChainCreationError> {
+
     // Perform a clean of any chains that are out of scope
     root.clean().await;
 
@@ -422,28 +1556,36 @@ async fn open_internal<'b>(
     {
         let chains = root.chains.lock().await;
         if let Some(chain) = chains.get(&route_chain) {
-            tx.replace_group(Arc::clone(&chain.tx_group)).await;
+            chain.activity.touch();
             let route = route.lock().await;
-            return Ok(OpenedChain {
+            let opened = OpenedChain {
                 integrity: chain.integrity,
                 message_of_the_day: route.flow.message_of_the_day(&chain.chain).await?,
                 chain: Arc::clone(&chain.chain),
-            });
+                quorum: chain.quorum.clone(),
+                activity: chain.activity.clone(),
+                migration: Arc::clone(&chain.migration),
+                usage: Arc::clone(&chain.usage),
+            };
+            return Ok((opened, Arc::clone(&chain.tx_group), None));
         }
     }
 
     // Get the configuration, metrics and throttle
-    let cfg_ate = {
+    let (cfg_ate, mode) = {
         let route = route.lock().await;
-        route.cfg_ate.clone()
+        (route.cfg_ate.clone(), route.mode)
     };
 
     // Create a chain builder
     let mut builder = ChainBuilder::new(&cfg_ate)
         .await
         .node_id(root.server_id.clone())
-        .with_metrics(&tx.metrics)
-        .with_throttle(&tx.throttle);
+        .with_metrics(metrics)
+        .with_throttle(throttle);
+    if let Some(peer) = peer {
+        builder = builder.with_peer(peer);
+    }
 
     // Postfix the hello_path
     #[cfg(feature = "enable_local_fs")]
@@ -453,11 +1595,13 @@ async fn open_internal<'b>(
 
     // Create the broadcast group
     let new_tx_group = { Arc::new(Mutex::new(TxGroup::default())) };
+    let new_activity = ChainActivity::new();
 
     // Add a pipe that will broadcast message to the connected clients
     let pipe = Box::new(ServerPipe {
         chain_key: route_chain.chain.clone(),
         tx_group: Arc::clone(&new_tx_group),
+        last_used: Arc::clone(&new_activity.last_used),
         wire_format: root.cfg_mesh.wire_format.clone(),
         next: crate::pipe::NullPipe::new(),
     });
@@ -465,7 +1609,7 @@ async fn open_internal<'b>(
 
     // Create the chain using the chain flow builder
     let integrity;
-    let wire_encryption = tx.wire_encryption().await.map(|a| a.size());
+    let mut secured_with = None;
     let new_chain = {
         let route = route.lock().await;
         debug!("open_flow: {}", route.flow_type);
@@ -474,10 +1618,15 @@ async fn open_internal<'b>(
             .open(builder, &route_chain.chain, wire_encryption)
             .await?
         {
+            OpenAction::PrivateChain { .. } if mode.is_public() => {
+                bail!(ChainCreationErrorKind::ServerRejected(
+                    FatalTerminate::Denied {
+                        reason: "private chains can not be served on a public route".to_string()
+                    }
+                ));
+            }
             OpenAction::PrivateChain { chain, session } => {
-                let msg = Message::SecuredWith(session);
-                let pck = Packet::from(msg).to_packet_data(root.cfg_mesh.wire_format)?;
-                tx.send_reply(pck).await?;
+                secured_with = Some(session);
                 integrity = TrustMode::Centralized(CentralizedRole::Server);
                 chain
             }
@@ -498,30 +1647,120 @@ async fn open_internal<'b>(
     };
     new_chain.single().await.set_integrity(integrity);
 
+    // Measure how much of its storage quota the chain is already using
+    let new_usage = Arc::new(ChainUsage::measure(&new_chain).await);
+
     // Insert it into the cache so future requests can reuse the reference to the chain
     let mut chains = root.chains.lock().await;
+
+    // Routes may limit how many ethereal chains they host at the same time
+    let mut evicted = None;
+    if let Some(max) = new_chain.cfg_ate.ethereal_max_chains {
+        if new_chain.is_temporal() && chains.contains_key(&route_chain) == false {
+            evicted = MeshRoot::evict_ethereal_lru(&mut chains, route_chain.route.as_str(), max)?;
+        }
+    }
+
     let new_chain = match chains.entry(route_chain.clone()) {
         Entry::Occupied(o) => {
             let o = o.into_mut();
-            tx.replace_group(Arc::clone(&o.tx_group)).await;
+            o.activity.touch();
             o
         }
         Entry::Vacant(v) => {
-            tx.replace_group(Arc::clone(&new_tx_group)).await;
+
+            // Start the background compaction policy for this chain
+            if let Some(policy) = new_chain.cfg_ate.compact_policy.clone() {
+                if policy.is_active() {
+                    debug!("compact-policy: {}", policy);
+                    TaskEngine::spawn(MeshRoot::compaction_worker(
+                        Arc::downgrade(&new_chain),
+                        policy,
+                        Arc::clone(&root.compact_limit),
+                        root.exit.subscribe(),
+                    ));
+                }
+            }
+
+            // Periodically sign the root head of the history of the chain
+            if let Some(key) = root.cfg_mesh.checkpoint_key.clone() {
+                TaskEngine::spawn(MeshRoot::checkpoint_worker(
+                    Arc::downgrade(&new_chain),
+                    key,
+                    root.cfg_mesh.checkpoint_interval,
+                    root.exit.subscribe(),
+                ));
+            }
+
+            // Watch the chain for transactions that never complete
+            let route_state = root.route_state(route_chain.route.as_str());
+            if let Some(policy) = route_state.watchdog() {
+                debug!("watchdog-policy: {}", policy);
+                TaskEngine::spawn(MeshRoot::watchdog_worker(
+                    Arc::downgrade(&root),
+                    route_chain.clone(),
+                    Arc::downgrade(&new_chain),
+                    route_state,
+                    root.exit.subscribe(),
+                ));
+            }
+
+            // Commits on critical chains are relayed to the replica roots
+            let mut quorum = None;
+            if let Some(policy) = new_chain.cfg_ate.quorum_policy.clone() {
+                if policy.is_active() {
+                    debug!("quorum-policy: {}", policy);
+                    let relay =
+                        QuorumRelay::new(&new_chain.cfg_ate, &route_chain.chain, policy).await;
+                    TaskEngine::spawn(QuorumRelay::repair_worker(
+                        Arc::clone(&relay),
+                        Arc::downgrade(&new_chain),
+                        root.exit.subscribe(),
+                    ));
+                    quorum = Some(relay);
+                }
+            }
+
+            // A chain that is reopened while it is migrating carries on
+            // forwarding its commits
+            let migration = root.migrations.lock().unwrap().get(&route_chain).cloned();
+
             v.insert(MeshChain {
                 integrity,
                 chain: Arc::clone(&new_chain),
                 tx_group: new_tx_group,
+                quorum,
+                activity: new_activity,
+                migration: Arc::new(StdMutex::new(migration)),
+                usage: new_usage,
             })
         }
     };
-
-    let route = route.lock().await;
-    Ok(OpenedChain {
+    let tx_group = Arc::clone(&new_chain.tx_group);
+    let opened = OpenedChain {
         integrity,
-        message_of_the_day: route.flow.message_of_the_day(&new_chain.chain).await?,
+        message_of_the_day: None,
         chain: Arc::clone(&new_chain.chain),
-    })
+        quorum: new_chain.quorum.clone(),
+        activity: new_chain.activity.clone(),
+        migration: Arc::clone(&new_chain.migration),
+        usage: Arc::clone(&new_chain.usage),
+    };
+    drop(chains);
+
+    // The evicted chain is shut down outside of the lock
+    if let Some(evicted) = evicted {
+        if let Err(err) = evicted.shutdown().await {
+            error!("failed to shutdown chain - {}", err);
+        }
+    }
+
+    let route = route.lock().await;
+    let opened = OpenedChain {
+        message_of_the_day: route.flow.message_of_the_day(&opened.chain).await?,
+        ..opened
+    };
+    Ok((opened, tx_group, secured_with))
 }
 
 #[derive(Clone)]
@@ -550,6 +1789,46 @@ impl ServerProcessor<Message, SessionContext> for MeshRootProcessor {
     async fn shutdown(&self, addr: SocketAddr) {
         debug!("disconnected: {}", addr.to_string());
     }
+
+    /// Packets are accounted for in the in-flight budget of the route before
+    /// they are decoded so that a connection can not exhaust the memory of the
+    /// server with huge commits, a packet that is larger than a connection
+    /// may ever have in flight is refused and the connection is closed
+    async fn admit<'a, 'b>(
+        &'a self,
+        context: &Arc<SessionContext>,
+        bytes: usize,
+        tx: &'b mut Tx,
+    ) -> Result<Option<PacketGuard>, CommsError> {
+        let root = match Weak::upgrade(&self.root) {
+            Some(a) => a,
+            None => {
+                return Ok(None);
+            }
+        };
+        let budget = match root.inflight_budget(tx.hello_path.as_str()) {
+            Some(a) => a,
+            None => {
+                return Ok(None);
+            }
+        };
+        match budget.reserve(context.id, bytes, &tx.throttle, &tx.metrics) {
+            Ok(reservation) => Ok(Some(Box::new(reservation))),
+            Err(limit) => {
+                let err = CommitError::from(CommitErrorKind::TooLarge(bytes as u64, limit as u64));
+                debug!("packet refused - {}", err);
+                tx.send_reply_msg(Message::FatalTerminate(FatalTerminate::Other {
+                    err: err.to_string(),
+                }))
+                .await?;
+                bail!(CommsErrorKind::FatalError(err.to_string()));
+            }
+        }
+    }
+
+    fn drain_hint(&self) -> Option<Message> {
+        Some(Message::Reconnect)
+    }
 }
 
 async fn inbox_event<'b>(
@@ -558,6 +1837,7 @@ async fn inbox_event<'b>(
     evts: Vec<MessageEvent>,
     tx: &'b mut Tx,
     pck_data: PacketData,
+    quotas: Option<Arc<RouteQuotas>>,
 ) -> Result<(), CommsError> {
     trace!(evts.cnt = evts.len());
     #[cfg(feature = "enable_verbose")]
@@ -567,7 +1847,16 @@ async fn inbox_event<'b>(
         }
     }
 
-    let chain = context.inside.lock().unwrap().chain.clone();
+    let (chain, quorum, validators, migration, usage) = {
+        let guard = context.inside.lock().unwrap();
+        (
+            guard.chain.clone(),
+            guard.quorum.clone(),
+            guard.validators.as_ref().map(|a| a.list()).unwrap_or_default(),
+            guard.migration.as_ref().and_then(|a| a.lock().unwrap().clone()),
+            guard.usage.clone(),
+        )
+    };
     let chain = match chain {
         Some(a) => a,
         None => {
@@ -578,24 +1867,151 @@ async fn inbox_event<'b>(
     };
     let commit = commit.clone();
 
-    // Feed the events into the chain of trust
+    let size = pck_data.bytes.len();
+
+    // Commits that would take the chain over its storage quota are rejected
+    // unless they carry no data (deleting rows is how space is freed up)
+    let limit = quotas.as_ref().and_then(|a| a.limit(chain.key()));
+    if let Some(usage) = usage.as_ref() {
+        usage.refresh(&chain, limit).await;
+        if evts.iter().any(|a| a.data.is_some()) {
+            if let Err((used, limit)) = usage.check(size as u64, limit) {
+                debug!("event rejected - commit of {} bytes exceeds the quota of the chain ({} of {} bytes used)", size, used, limit);
+                if let Some(id) = commit {
+                    let rejection = CommitRejection::QuotaExceeded { used, limit };
+                    tx.send_reply_msg(Message::CommitError {
+                        id,
+                        err: rejection.to_commit_error().to_string(),
+                        rejection: Some(rejection),
+                    })
+                    .await?;
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    // Feed the events into the chain of trust (after the validators of the
+    // route have had their say)
     let evts = MessageEvent::convert_from(evts.into_iter());
-    let ret = chain
-        .pipe
-        .feed(ChainWork {
-            trans: Transaction {
-                scope: TransactionScope::None,
-                transmit: false,
-                events: evts,
-                timeout: Duration::from_secs(30),
-                conversation: Some(Arc::clone(&context.conversation)),
-            },
-        })
-        .await;
+    if let Err(err) = validate_route_events(&chain, &validators[..], &evts[..]) {
+        debug!("event rejected - {}", err);
+        match commit {
+            Some(id) => {
+                tx.send_reply_msg(Message::CommitError {
+                    id,
+                    err: err.to_string(),
+                    rejection: None,
+                })
+                .await?;
+            }
+            None => {
+                // Without a commit there is nothing to reply to hence the
+                // client is disconnected so that it resyncs with the chain
+                // rather than carrying on with events that were never written
+                let reason = err.to_string();
+                tx.send_reply_msg(Message::FatalTerminate(FatalTerminate::Denied { reason }))
+                    .await?;
+                return Err(err.into());
+            }
+        }
+        return Ok(());
+    }
+
+    // While the chain is migrating the commits are also forwarded to the
+    // destination (the cutover waits for the commits that are in flight and
+    // once the chain has migrated they are rejected rather than lost)
+    let _migrating = match migration.as_ref() {
+        Some(migration) => match migration.begin_commit().await {
+            Ok(guard) => Some(guard),
+            Err(err) => {
+                debug!("event rejected - {}", err);
+                if let Some(id) = commit {
+                    tx.send_reply_msg(Message::CommitError {
+                        id,
+                        err: err.to_string(),
+                        rejection: None,
+                    })
+                    .await?;
+                }
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+    let forward_evts = migration.as_ref().map(|_| evts.clone());
+
+    // Commits on critical chains must be acknowledged by a quorum of the
+    // replicas before the primary writes them, otherwise a commit that is
+    // reported as failed would still be left behind on the primary
+    let relayed = match (quorum, commit) {
+        (Some(quorum), Some(_)) => quorum.relay(&evts).await,
+        _ => Ok(()),
+    };
+    let ret = match relayed {
+        Ok(()) => match chain
+            .pipe
+            .feed(ChainWork {
+                trans: Transaction {
+                    scope: TransactionScope::None,
+                    transmit: false,
+                    events: evts,
+                    timeout: Duration::from_secs(30),
+                    conversation: Some(Arc::clone(&context.conversation)),
+                    deferrable: false,
+                },
+            })
+            .await
+        {
+            // Commits that the client can recover from (e.g. a patch that was
+            // written against an old version of a row) are rejected back to
+            // the client rather than failing the connection
+            Err(err) if CommitRejection::from_commit_error(&err).is_some() => Ok(Err(err)),
+            ret => ret.map(|a| Ok(a)),
+        },
+        Err(err) => Ok(Err(err)),
+    };
+
+    // The commit now occupies space on disk, once the chain has used most
+    // of its quota the sessions that write to it are sent its status (if
+    // they understand it)
+    if let (Ok(Ok(())), Some(usage)) = (&ret, usage.as_ref()) {
+        if usage.add(size as u64, limit) {
+            warn!(
+                "chain {} has used {} of its {} byte quota",
+                chain.key(),
+                usage.used(),
+                limit.unwrap_or_default()
+            );
+        }
+        let warn = {
+            let mut guard = context.inside.lock().unwrap();
+            let warning = usage.is_warning(limit);
+            let warn = guard.quota_watch && warning && guard.quota_warned == false;
+            guard.quota_warned = warning;
+            warn
+        };
+        if warn {
+            tx.send_reply_msg(Message::QuotaStatusResult(QuotaStatus {
+                chain: chain.key().clone(),
+                used: usage.used(),
+                limit,
+            }))
+            .await?;
+        }
+    }
+
+    // Commits are forwarded to the destination of a migration before they
+    // are confirmed to the client
+    if let (Ok(Ok(())), Some(migration), Some(evts)) = (&ret, &migration, forward_evts) {
+        migration.forward(&evts).await;
+    }
 
     // Send the packet down to others
     match ret {
-        Ok(_) => {
+        Ok(ret) => {
+            let written = ret.is_ok();
+
             // If the operation has a commit to transmit the response
             if let Some(id) = commit {
                 match ret {
@@ -605,10 +2021,12 @@ async fn inbox_event<'b>(
                         a
                     }
                     Err(err) => {
+                        let rejection = CommitRejection::from_commit_error(&err);
                         let err = err.to_string();
                         tx.send_reply_msg(Message::CommitError {
                             id: id.clone(),
                             err,
+                            rejection,
                         })
                         .await?;
                     }
@@ -616,7 +2034,10 @@ async fn inbox_event<'b>(
             }
 
             // Send the packet data onto the others in this broadcast group
-            tx.send_others(pck_data).await;
+            // (unless the commit never made it onto the chain)
+            if written {
+                tx.send_others(pck_data).await;
+            }
             Ok(())
         }
         Err(err) => {
@@ -625,6 +2046,59 @@ async fn inbox_event<'b>(
     }
 }
 
+/// Runs the validators that the application registered on the route over
+/// the events, the first rejection stops the whole commit
+fn validate_route_events(
+    chain: &Arc<Chain>,
+    validators: &[Arc<dyn EventValidator>],
+    evts: &[EventWeakData],
+) -> Result<(), ValidationError> {
+    if validators.is_empty() {
+        return Ok(());
+    }
+    let guard = chain.inside_sync.read().unwrap();
+    let session = guard.default_session.deref();
+    for evt in evts {
+        let header = evt
+            .as_header()
+            .map_err(|err| ValidationErrorKind::Denied(err.to_string()))?;
+        let data = evt.data_bytes.as_option();
+
+        // Validators that read the data only ever see it in plain text
+        let decrypted = match (data, validators.iter().any(|a| a.decrypt_data())) {
+            (Some(data), true) => Some(guard.data_as_overlay(&evt.meta, data.clone(), session)),
+            _ => None,
+        };
+        for validator in validators {
+            let data = match (validator.decrypt_data(), decrypted.as_ref()) {
+                (true, Some(Ok(data))) => Some(data),
+                (true, Some(Err(err))) => {
+                    bail!(ValidationErrorKind::Denied(format!(
+                        "the server is unable to decrypt the data - {} (validator={})",
+                        err,
+                        validator.validator_name()
+                    )));
+                }
+                _ => data,
+            };
+            validator
+                .validate(&header, &evt.meta, data.map(|a| &a[..]), session)
+                .map_err(|err| {
+                    let reason = match err {
+                        ValidationError(ValidationErrorKind::Denied(reason), _) => reason,
+                        err => err.to_string(),
+                    };
+                    ValidationErrorKind::Denied(format!(
+                        "{} (validator={})",
+                        reason,
+                        validator.validator_name()
+                    ))
+                })?;
+        }
+    }
+    Ok(())
+}
+
 async fn inbox_lock<'b>(
     context: Arc<SessionContext>,
     key: PrimaryKey,
@@ -743,6 +2217,54 @@ async fn inbox_subscribe<'b>(
         return Ok(());
     }
 
+    // The system chain that holds the aliases is not served to clients
+    if chain_key.name == ALIAS_CHAIN_NAME {
+        trace!("sending Message::FatalTerminate(denied)");
+        tx.send_reply_msg(Message::FatalTerminate(FatalTerminate::Denied {
+            reason: "this chain is reserved by the system".to_string(),
+        }))
+        .await?;
+        return Ok(());
+    }
+
+    // Subscriptions to an alias are served from the canonical chain without
+    // the client being aware of it (the canonical chain may live elsewhere)
+    let chain_key = match root.resolve_alias(hello_path, &chain_key).await {
+        Ok(a) => {
+            if a.name != chain_key.name {
+                debug!("alias {} resolved to {}", chain_key, a);
+            }
+            a
+        }
+        Err(ChainCreationError(ChainCreationErrorKind::InvalidRoute(_), _)) => chain_key,
+        Err(err) => {
+            let err = err.to_string();
+            trace!("sending Message::FatalTerminate(other={})", err);
+            tx.send_reply_msg(Message::FatalTerminate(FatalTerminate::Other { err }))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Chains that have migrated away are relayed to the root that now serves
+    // them (until the clients are configured with the new roots)
+    if let Some(node_addr) = root.migrated_to(hello_path, &chain_key) {
+        if redirect {
+            debug!("chain {} has migrated to {}", chain_key, node_addr);
+            return redirect_subscribe(root, node_addr, omit_data, hello_path, chain_key, from, tx).await;
+        } else {
+            let err = ChainCreationError::from(ChainCreationErrorKind::ChainMigrated(
+                chain_key.to_string(),
+                node_addr.to_string(),
+            ))
+            .to_string();
+            trace!("sending Message::FatalTerminate(other={})", err);
+            tx.send_reply_msg(Message::FatalTerminate(FatalTerminate::Other { err }))
+                .await?;
+            return Ok(());
+        }
+    }
+
     // First lets check if this connection is meant for this group of servers that make
     // up the distributed chain table.
     let (node_addr, node_id) = match root.lookup.lookup(&chain_key) {
@@ -755,26 +2277,11 @@ async fn inbox_subscribe<'b>(
         }
     };
 
-    // Reject the request if its from the wrong machine
-    // Or... if we can perform a redirect then do so
-    if root.node_id != node_id {
+    // Reject the request if its from the wrong machine (unless the chain is
+    // being migrated here) Or... if we can perform a redirect then do so
+    if root.node_id != node_id && root.is_accepted(hello_path, &chain_key) == false {
         if redirect {
-            let (exit_tx, exit_rx) = broadcast::channel(1);
-            let relay_tx = super::redirect::redirect::<SessionContext>(
-                root,
-                node_addr,
-                omit_data,
-                hello_path,
-                chain_key,
-                from,
-                tx.take(),
-                exit_rx,
-            )
-            .await?;
-            tx.set_relay(relay_tx);
-            tx.add_exit_dependency(exit_tx);
-
-            return Ok(());
+            return redirect_subscribe(root, node_addr, omit_data, hello_path, chain_key, from, tx).await;
         } else {
             // Fail to redirect
             trace!("sending Message::FatalTerminate(redirect actual={} expected={})", node_id, root.node_id);
@@ -838,10 +2345,23 @@ async fn inbox_subscribe<'b>(
         .await?;
     }
 
-    // Update the context with the latest chain-key
+    // Update the context with the latest chain-key (sessions on public
+    // routes are anonymous and hence may only read)
+    let read_only = root
+        .route_mode(hello_path)
+        .map(|a| a.is_public())
+        .unwrap_or(false);
     {
         let mut guard = context.inside.lock().unwrap();
         guard.chain.replace(Arc::clone(&chain));
+        guard.read_only = read_only;
+        guard.validators = Some(Arc::clone(&root.route_state(hello_path).validators));
+        guard.quorum = opened_chain.quorum.clone();
+        guard.migration = Some(Arc::clone(&opened_chain.migration));
+        guard.usage = Some(Arc::clone(&opened_chain.usage));
+        if let Some(previous) = guard.activity.replace(opened_chain.activity.clone()) {
+            previous.touch();
+        }
     }
 
     // Stream the data back to the client
@@ -851,8 +2371,45 @@ async fn inbox_subscribe<'b>(
         true => 64usize,
         false => usize::MAX
     };
-    stream_history_range(Arc::clone(&chain), from.., tx, strip_signatures, strip_data).await?;
+    let cache = root.history_cache(hello_path);
+    stream_history_range(
+        Arc::clone(&chain),
+        from..,
+        tx,
+        strip_signatures,
+        strip_data,
+        cache.as_deref(),
+    )
+    .await?;
+
+    Ok(())
+}
 
+/// Relays the subscription (and everything the client sends after it) to
+/// another root
+async fn redirect_subscribe<'b>(
+    root: Arc<MeshRoot>,
+    node_addr: MeshAddress,
+    omit_data: bool,
+    hello_path: &str,
+    chain_key: ChainKey,
+    from: ChainTimestamp,
+    tx: &'b mut Tx,
+) -> Result<(), CommsError> {
+    let (exit_tx, exit_rx) = broadcast::channel(1);
+    let relay_tx = super::redirect::redirect::<SessionContext>(
+        root,
+        node_addr,
+        omit_data,
+        hello_path,
+        chain_key,
+        from,
+        tx.take(),
+        exit_rx,
+    )
+    .await?;
+    tx.set_relay(relay_tx);
+    tx.add_exit_dependency(exit_tx);
     Ok(())
 }
 
@@ -868,11 +2425,109 @@ async fn inbox_unsubscribe<'b>(
     {
         let mut guard = context.inside.lock().unwrap();
         guard.chain.take();
+        if let Some(activity) = guard.activity.take() {
+            activity.touch();
+        }
+        guard.migration.take();
+        guard.usage.take();
     }
 
     Ok(())
 }
 
+async fn inbox_resolve_alias<'b>(
+    root: Arc<MeshRoot>,
+    hello_path: &str,
+    chain_key: ChainKey,
+    tx: &'b mut Tx,
+) -> Result<(), CommsError> {
+    trace!("resolve alias: {}", chain_key);
+
+    match root.resolve_alias(hello_path, &chain_key).await {
+        Ok(chain_key) => {
+            tx.send_reply_msg(Message::AliasResolved { chain_key })
+                .await
+        }
+        Err(err) => {
+            tx.send_reply_msg(Message::FatalTerminate(FatalTerminate::Other {
+                err: err.to_string(),
+            }))
+            .await
+        }
+    }
+}
+
+async fn inbox_quota_status<'b>(
+    root: Arc<MeshRoot>,
+    context: Arc<SessionContext>,
+    chain_key: ChainKey,
+    tx: &'b mut Tx,
+) -> Result<(), CommsError> {
+    trace!("quota status: {}", chain_key);
+
+    // Only the quota of the chain that the session is subscribed to is
+    // reported (which the flow of the route has already authorized)
+    let chain = context.inside.lock().unwrap().chain.clone();
+    let chain = match chain {
+        Some(a) => a,
+        None => {
+            tx.send_reply_msg(Message::FatalTerminate(FatalTerminate::NotYetSubscribed))
+                .await?;
+            bail!(CommsErrorKind::NotYetSubscribed);
+        }
+    };
+    if chain.key() != &chain_key {
+        debug!("quota status of {} asked for on the session of {}", chain_key, chain.key());
+    }
+
+    let status = root.quota_status(tx.hello_path.as_str(), chain.key()).await;
+    {
+        let mut guard = context.inside.lock().unwrap();
+        guard.quota_watch = true;
+        guard.quota_warned = status.percent().unwrap_or_default() >= QUOTA_WARN_PERCENT;
+    }
+    tx.send_reply_msg(Message::QuotaStatusResult(status)).await
+}
+
+async fn inbox_compact_now<'b>(
+    root: Arc<MeshRoot>,
+    context: Arc<SessionContext>,
+    tx: &'b mut Tx,
+) -> Result<(), CommsError> {
+    trace!("compact now");
+
+    let chain = context.inside.lock().unwrap().chain.clone();
+    let chain = match chain {
+        Some(a) => a,
+        None => {
+            tx.send_reply_msg(Message::FatalTerminate(FatalTerminate::NotYetSubscribed))
+                .await?;
+            bail!(CommsErrorKind::NotYetSubscribed);
+        }
+    };
+
+    if root.cfg_mesh.compact_remote_trigger == false {
+        return tx
+            .send_reply_msg(Message::CompactResult {
+                err: Some("remote compaction is not enabled on this server".to_string()),
+            })
+            .await;
+    }
+
+    let err = {
+        let _permit = root.compact_limit.acquire().await;
+        chain
+            .compact_with_stats(CompactionTrigger::Manual)
+            .await
+            .err()
+            .map(|err| err.to_string())
+    };
+    if let Some(cache) = root.history_cache(tx.hello_path.as_str()) {
+        cache.invalidate(chain.key());
+    }
+    tx.send_reply_msg(Message::CompactResult { err }).await
+}
+
 async fn inbox_packet<'b>(
     root: Arc<MeshRoot>,
     pck: PacketWithContext<Message, SessionContext>,
@@ -940,7 +2595,23 @@ async fn inbox_packet<'b>(
                     return Ok(());
                 }
 
-                inbox_event(context, commit, evts, tx, pck_data)
+                // Anonymous sessions on public routes may never write
+                if context.inside.lock().unwrap().read_only {
+                    debug!("event rejected - session is read-only");
+                    if let Some(id) = commit {
+                        tx.send_reply_msg(Message::CommitError {
+                            id,
+                            err: CommitError::from(CommitErrorKind::ReadOnly).to_string(),
+                            rejection: None,
+                        })
+                        .await?;
+                    }
+                    tx.send_reply_msg(Message::ReadOnly).await?;
+                    return Ok(());
+                }
+
+                let quotas = root.route_quotas(tx.hello_path.as_str());
+                inbox_event(context, commit, evts, tx, pck_data, quotas)
                     .instrument(span!(
                         Level::DEBUG,
                         "event",
@@ -949,6 +2620,14 @@ async fn inbox_packet<'b>(
                     ))
                     .await?;
             }
+            Message::Lock { key } if context.inside.lock().unwrap().read_only => {
+                debug!("lock rejected - session is read-only");
+                tx.send_reply_msg(Message::LockResult {
+                    key,
+                    is_locked: false,
+                })
+                .await?;
+            }
             Message::Lock { key } => {
                 inbox_lock(context, key, tx)
                     .instrument(span!(Level::DEBUG, "lock"))
@@ -964,6 +2643,22 @@ async fn inbox_packet<'b>(
                     .instrument(span!(Level::DEBUG, "load-many"))
                     .await?;
             }
+            Message::ResolveAlias { chain_key } => {
+                let hello_path = tx.hello_path.clone();
+                inbox_resolve_alias(root, hello_path.as_str(), chain_key, tx)
+                    .instrument(span!(Level::DEBUG, "resolve-alias"))
+                    .await?;
+            }
+            Message::CompactNow => {
+                inbox_compact_now(root, context, tx)
+                    .instrument(span!(Level::DEBUG, "compact-now"))
+                    .await?;
+            }
+            Message::QuotaStatus { chain_key } => {
+                inbox_quota_status(root, context, chain_key, tx)
+                    .instrument(span!(Level::DEBUG, "quota-status"))
+                    .await?;
+            }
             _ => {}
         };
         Ok(())
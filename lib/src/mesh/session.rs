@@ -3,6 +3,7 @@ use async_trait::async_trait;
 use error_chain::bail;
 use fxhash::FxHashMap;
 use std::net::SocketAddr;
+use std::ops::DerefMut;
 use std::ops::Rem;
 use std::sync::Mutex as StdMutex;
 use std::sync::RwLock as StdRwLock;
@@ -13,6 +14,7 @@ use tokio::select;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::sync::RwLock;
+use tokio::sync::watch;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use tracing_futures::{Instrument, WithSubscriber};
 use bytes::Bytes;
@@ -20,6 +22,8 @@ use bytes::Bytes;
 use super::core::*;
 use super::lock_request::*;
 use super::msg::*;
+use super::outbound_wal::*;
+use super::quota::*;
 use super::recoverable_session_pipe::*;
 use crate::chain::*;
 use crate::conf::MeshConnectAddr;
@@ -53,6 +57,7 @@ pub struct MeshSession {
     pub(super) commit: Arc<StdMutex<FxHashMap<u64, mpsc::Sender<Result<u64, CommitError>>>>>,
     pub(super) lock_requests: Arc<StdMutex<FxHashMap<PrimaryKey, LockRequest>>>,
     pub(super) load_requests: Arc<StdMutex<FxHashMap<u64, LoadRequest>>>,
+    pub(super) quota_requests: StdMutex<Vec<mpsc::Sender<QuotaStatus>>>,
     pub(super) inbound_conversation: Arc<ConversationSession>,
     pub(super) outbound_conversation: Arc<ConversationSession>,
     pub(crate) status_tx: mpsc::Sender<ConnectionStatusChange>,
@@ -110,9 +115,14 @@ impl MeshSession {
 
             chain.remote = Some(remote);
             chain.remote_addr = Some(addr.clone());
+            chain.shedder.set_budget(cfg_mesh.inbound_budget.clone());
             chain
         };
 
+        // The progress of the sync is published to anyone holding the chain
+        let (sync_tx, sync_rx) = watch::channel(SyncProgress::default());
+        chain.sync = sync_rx;
+
         // While we are running offline we run in full distributed mode until
         // we are reconnect as otherwise if the server is in distributed mode
         // it will immediately reject everything
@@ -120,13 +130,14 @@ impl MeshSession {
 
         // Create a session pipe
         let chain_store = Arc::new(StdMutex::new(None));
+        let wal = OutboundWal::open(&builder.cfg_ate, chain_key)?;
         let session = RecoverableSessionPipe {
             cfg_mesh: cfg_mesh.clone(),
             next: NullPipe::new(),
             active: RwLock::new(None),
             lazy_data,
             mode: builder.cfg_ate.recovery_mode,
-            addr,
+            addr: StdMutex::new(addr),
             hello_path,
             node_id: node_id.clone(),
             key: chain_key.clone(),
@@ -134,8 +145,10 @@ impl MeshSession {
             exit: chain.exit.clone(),
             chain: Arc::clone(&chain_store),
             loader_remote: StdMutex::new(Some(Box::new(loader_remote))),
+            sync: Arc::new(sync_tx),
             metrics: Arc::clone(&chain.metrics),
             throttle: Arc::clone(&chain.throttle),
+            wal,
         };
 
         // Add the pipe to the chain and cement it
@@ -206,7 +219,10 @@ impl MeshSession {
 
                 // We only feed the transactions into the local chain otherwise this will
                 // reflect events back into the chain-of-trust running on the server
-                chain
+                // (the cache maintenance of these events may be deferred under load)
+                let cnt = feed_me.len();
+                chain.shedder.begin_inbound(cnt);
+                let ret = chain
                     .pipe
                     .feed(ChainWork {
                         trans: Transaction {
@@ -215,9 +231,12 @@ impl MeshSession {
                             events: feed_me,
                             timeout: Duration::from_secs(30),
                             conversation: Some(Arc::clone(&self.inbound_conversation)),
+                            deferrable: true,
                         },
                     })
-                    .await?;
+                    .await;
+                chain.shedder.end_inbound(cnt);
+                ret?;
             }
             None => {}
         };
@@ -246,7 +265,7 @@ impl MeshSession {
     pub(super) async fn inbox_commit_error(
         self: &Arc<MeshSession>,
         id: u64,
-        err: String,
+        err: CommitError,
     ) -> Result<(), CommsError> {
         trace!("commit_error id={}, err={}", id, err);
 
@@ -256,7 +275,7 @@ impl MeshSession {
         };
         if let Some(result) = r {
             result
-                .send(Err(CommitErrorKind::RootError(err).into()))
+                .send(Err(err))
                 .await?;
         }
         Ok(())
@@ -303,6 +322,37 @@ impl MeshSession {
         Ok(None)
     }
 
+    pub(super) async fn inbox_quota_status(
+        self: &Arc<MeshSession>,
+        status: QuotaStatus,
+    ) -> Result<(), CommsError> {
+        trace!("quota_status {}", status);
+
+        // The root also sends the status unprompted when the commits of this
+        // session take the chain close to its quota
+        let warning = match (status.limit, status.percent()) {
+            (Some(limit), Some(percent)) if percent >= QUOTA_WARN_PERCENT => {
+                warn!(
+                    "chain-of-trust has used {} of its {} byte quota - {}",
+                    status.used,
+                    limit,
+                    self.key.to_string()
+                );
+                Some((status.used, limit))
+            }
+            _ => None,
+        };
+        if let Some(chain) = self.chain.upgrade() {
+            chain.metrics.lock().unwrap().quota_warning = warning;
+        }
+
+        let requests = std::mem::take(self.quota_requests.lock().unwrap().deref_mut());
+        for request in requests {
+            let _ = request.send(status.clone()).await;
+        }
+        Ok(())
+    }
+
     pub(super) async fn record_delayed_upload(
         chain: &Arc<Chain>,
         pivot: ChainTimestamp,
@@ -365,6 +415,7 @@ impl MeshSession {
     pub(super) async fn inbox_start_of_history(
         self: &Arc<MeshSession>,
         size: usize,
+        total_bytes: Option<u64>,
         _from: Option<ChainTimestamp>,
         to: Option<ChainTimestamp>,
         loader: &mut Option<Box<dyn Loader>>,
@@ -405,6 +456,9 @@ impl MeshSession {
 
         // Tell the loader that we will be starting the load process of the history
         if let Some(loader) = loader {
+            if let Some(total_bytes) = total_bytes {
+                loader.history_bytes(total_bytes);
+            }
             loader.start_of_history(size).await;
         }
 
@@ -489,8 +543,9 @@ impl MeshSession {
                 to,
                 root_keys,
                 integrity,
+                total_bytes,
             } => {
-                Self::inbox_start_of_history(self, size, from, to, loader, root_keys, integrity)
+                Self::inbox_start_of_history(self, size, total_bytes, from, to, loader, root_keys, integrity)
                     .instrument(span!(Level::DEBUG, "start-of-history"))
                     .await?;
             }
@@ -505,8 +560,20 @@ impl MeshSession {
                     self.key.to_string()
                 );
                 self.cancel_commits(CommitErrorKind::ReadOnly).await;
+
+                // Further attempts to modify the chain will now fail early
+                if let Some(chain) = self.chain.upgrade() {
+                    chain.set_read_only(true);
+                }
                 let _ = self.status_tx.send(ConnectionStatusChange::ReadOnly).await;
             }
+            Message::Reconnect => {
+                debug!(
+                    "server is draining, will reconnect elsewhere - {}",
+                    self.key.to_string()
+                );
+                let _ = self.status_tx.send(ConnectionStatusChange::Reconnect).await;
+            }
             Message::Events { commit, evts } => {
                 let num_deletes = evts
                     .iter()
@@ -535,11 +602,20 @@ impl MeshSession {
                     .instrument(span!(Level::DEBUG, "commit-confirmed"))
                     .await?;
             }
-            Message::CommitError { id, err } => {
+            Message::CommitError { id, err, rejection } => {
+                let err = match rejection {
+                    Some(rejection) => rejection.to_commit_error(),
+                    None => CommitErrorKind::RootError(err).into(),
+                };
                 Self::inbox_commit_error(self, id, err)
                     .instrument(span!(Level::DEBUG, "commit-error"))
                     .await?;
             }
+            Message::QuotaStatusResult(status) => {
+                Self::inbox_quota_status(self, status)
+                    .instrument(span!(Level::DEBUG, "quota-status"))
+                    .await?;
+            }
             Message::LockResult { key, is_locked } => {
                 async move { Self::inbox_lock_result(self, key, is_locked) }
                     .instrument(span!(Level::DEBUG, "lock_result"))
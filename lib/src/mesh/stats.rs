@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use http::StatusCode;
+use std::net::SocketAddr;
+use std::sync::Arc;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use super::server::MeshRoot;
+use crate::chain::ChainKey;
+use crate::comms::NodeId;
+use crate::comms::RawWebRoute;
+use crate::comms::is_bearer_token;
+
+/// Admin web route that reports the statistics of a chain as JSON (mount it
+/// with `add_post_route`). The query string must contain the `route` and the
+/// `chain` whose statistics are wanted, only chains that are currently open
+/// on the root can be reported.
+pub struct StatsRoute {
+    root: Arc<MeshRoot>,
+    access_token: String,
+}
+
+impl StatsRoute {
+    pub fn new(root: &Arc<MeshRoot>, access_token: String) -> StatsRoute {
+        StatsRoute {
+            root: Arc::clone(root),
+            access_token,
+        }
+    }
+
+    fn error(msg: &str, code: StatusCode) -> (Vec<u8>, StatusCode) {
+        (msg.as_bytes().to_vec(), code)
+    }
+}
+
+#[async_trait]
+impl RawWebRoute for StatsRoute {
+    async fn accepted_raw_post_request(
+        &self,
+        uri: http::Uri,
+        headers: http::HeaderMap,
+        sock_addr: SocketAddr,
+        _server_id: NodeId,
+        _body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        if is_bearer_token(&headers, self.access_token.as_str()) == false {
+            warn!("rejected stats request from {}", sock_addr);
+            return Err(Self::error("invalid access token", StatusCode::UNAUTHORIZED));
+        }
+
+        let mut route = None;
+        let mut chain = None;
+        for (key, val) in uri.query().unwrap_or("").split('&').filter_map(|a| a.split_once('=')) {
+            match key {
+                "route" => route = Some(val.to_string()),
+                "chain" => chain = Some(ChainKey::from(val.to_string())),
+                _ => {}
+            }
+        }
+        let (route, chain) = match (route, chain) {
+            (Some(a), Some(b)) => (a, b),
+            _ => {
+                return Err(Self::error(
+                    "the route and chain must be supplied",
+                    StatusCode::BAD_REQUEST,
+                ));
+            }
+        };
+
+        debug!("stats of {} requested by {}", chain, sock_addr);
+        let stats = match self.root.chain_statistics(route.as_str(), &chain).await {
+            Some(a) => a,
+            None => {
+                return Err(Self::error("the chain is not open on this root", StatusCode::NOT_FOUND));
+            }
+        };
+        serde_json::to_vec(&stats)
+            .map_err(|err| Self::error(err.to_string().as_str(), StatusCode::INTERNAL_SERVER_ERROR))
+    }
+
+    async fn accepted_raw_put_request(
+        &self,
+        _uri: http::Uri,
+        _headers: http::HeaderMap,
+        _sock_addr: SocketAddr,
+        _server_id: NodeId,
+        _body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        Err(Self::error("stats requests must be made with POST", StatusCode::BAD_REQUEST))
+    }
+}
@@ -0,0 +1,90 @@
+use async_trait::async_trait;
+use http::StatusCode;
+use std::net::SocketAddr;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::comms::NodeId;
+use crate::comms::RawWebRoute;
+use crate::comms::is_bearer_token;
+use crate::engine::TaskEngine;
+
+/// Admin web route that lists the tasks which are alive in the process (mount
+/// it with `add_post_route`) so that a server that is stuck can be inspected
+/// without attaching a debugger. The tasks are returned as JSON unless the
+/// query string contains `format=text`, `min_age=<secs>` leaves out the tasks
+/// that are younger than that.
+pub struct TasksRoute {
+    access_token: String,
+}
+
+impl TasksRoute {
+    pub fn new(access_token: String) -> TasksRoute {
+        TasksRoute { access_token }
+    }
+
+    fn error(msg: &str, code: StatusCode) -> (Vec<u8>, StatusCode) {
+        (msg.as_bytes().to_vec(), code)
+    }
+}
+
+#[async_trait]
+impl RawWebRoute for TasksRoute {
+    async fn accepted_raw_post_request(
+        &self,
+        uri: http::Uri,
+        headers: http::HeaderMap,
+        sock_addr: SocketAddr,
+        _server_id: NodeId,
+        _body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        if is_bearer_token(&headers, self.access_token.as_str()) == false {
+            warn!("rejected tasks request from {}", sock_addr);
+            return Err(Self::error("invalid access token", StatusCode::UNAUTHORIZED));
+        }
+
+        let mut text = false;
+        let mut min_age = 0u64;
+        for (key, val) in uri.query().unwrap_or("").split('&').filter_map(|a| a.split_once('=')) {
+            match key {
+                "format" => text = val == "text",
+                "min_age" => {
+                    let secs = val.parse::<u64>().map_err(|_| {
+                        Self::error("the min_age must be a number of seconds", StatusCode::BAD_REQUEST)
+                    })?;
+                    min_age = secs * 1000;
+                }
+                _ => {}
+            }
+        }
+
+        debug!("tasks requested by {}", sock_addr);
+        let tasks = TaskEngine::dump_tasks()
+            .into_iter()
+            .filter(|a| a.age_ms >= min_age)
+            .collect::<Vec<_>>();
+        if text {
+            let mut ret = format!(
+                "{:>6} {:<8} {:<7} {:>12} {:>8} {}\n",
+                "ID", "KIND", "STATE", "AGE", "POLLS", "NAME (SITE)"
+            );
+            for task in tasks {
+                ret.push_str(format!("{}\n", task).as_str());
+            }
+            return Ok(ret.into_bytes());
+        }
+        serde_json::to_vec(&tasks)
+            .map_err(|err| Self::error(err.to_string().as_str(), StatusCode::INTERNAL_SERVER_ERROR))
+    }
+
+    async fn accepted_raw_put_request(
+        &self,
+        _uri: http::Uri,
+        _headers: http::HeaderMap,
+        _sock_addr: SocketAddr,
+        _server_id: NodeId,
+        _body: Vec<u8>,
+    ) -> Result<Vec<u8>, (Vec<u8>, StatusCode)> {
+        Err(Self::error("tasks requests must be made with POST", StatusCode::BAD_REQUEST))
+    }
+}
@@ -193,7 +193,7 @@ async fn test_mesh_internal(centralized: bool, proto: StreamProtocol, wire_encry
 
         let mut dao2;
         {
-            let dio = chain_a.dio_trans(&session_a, TransactionScope::Full).await;
+            let dio = chain_a.dio_trans(&session_a, TransactionScope::Full).await.unwrap();
             dao2 = dio.store(TestData::default()).unwrap();
             dao_key2 = dao2.key().clone();
             let _ = dio.store(TestData::default()).unwrap();
@@ -219,7 +219,7 @@ async fn test_mesh_internal(centralized: bool, proto: StreamProtocol, wire_encry
 
             {
                 info!("start a DIO session for client B");
-                let dio = chain_b.dio_trans(&session_b, TransactionScope::Full).await;
+                let dio = chain_b.dio_trans(&session_b, TransactionScope::Full).await.unwrap();
 
                 info!("store data object 1");
                 dao_key1 = dio.store(TestData::default()).unwrap().key().clone();
@@ -266,7 +266,7 @@ async fn test_mesh_internal(centralized: bool, proto: StreamProtocol, wire_encry
 
         {
             info!("new DIO session for client A");
-            let dio = chain_a.dio_trans(&session_a, TransactionScope::Full).await;
+            let dio = chain_a.dio_trans(&session_a, TransactionScope::Full).await.unwrap();
 
             info!("processing the next event in the BUS (and lock_for_delete it)");
             let task_ret = bus_b
@@ -322,3 +322,707 @@ async fn test_mesh_internal(centralized: bool, proto: StreamProtocol, wire_encry
 
     use crate::dio::bus::BusEvent;
 }
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_mesh_session_read_only() {
+    use super::msg::Message;
+    use super::session::MeshSession;
+    use crate::comms::{Packet, PacketWithContext};
+    use crate::transaction::ConversationSession;
+    use fxhash::FxHashMap;
+    use std::sync::Mutex as StdMutex;
+
+    crate::utils::bootstrap_test_env();
+
+    let mut mock_cfg = crate::conf::tests::mock_test_config();
+    let (chain, _builder) = crate::trust::create_test_chain(
+        &mut mock_cfg,
+        "test_mesh_session_read_only".to_string(),
+        true,
+        true,
+        None,
+    )
+    .await;
+    assert!(chain.is_writable());
+
+    // Feed the session a read-only message as if the server had sent it
+    let (status_tx, mut status_rx) = tokio::sync::mpsc::channel(1);
+    let session = Arc::new(MeshSession {
+        addr: crate::conf::tests::mock_test_mesh(4041).roots[0].clone(),
+        key: chain.key().clone(),
+        sync_tolerance: std::time::Duration::from_secs(2),
+        chain: Arc::downgrade(&chain),
+        commit: Arc::new(StdMutex::new(FxHashMap::default())),
+        lock_requests: Arc::new(StdMutex::new(FxHashMap::default())),
+        load_requests: Arc::new(StdMutex::new(FxHashMap::default())),
+        quota_requests: StdMutex::new(Vec::new()),
+        inbound_conversation: Arc::new(ConversationSession::default()),
+        outbound_conversation: Arc::new(ConversationSession::default()),
+        status_tx,
+    });
+    let pck = PacketWithContext {
+        packet: Packet::from(Message::ReadOnly),
+        data: Packet::from(Message::ReadOnly)
+            .to_packet_data(SerializationFormat::Json)
+            .unwrap(),
+        context: Arc::new(()),
+        id: NodeId::generate_client_id(),
+        peer_id: NodeId::generate_server_id(0),
+    };
+    MeshSession::inbox_packet(&session, &mut None, pck).await.unwrap();
+    assert!(matches!(
+        status_rx.recv().await,
+        Some(crate::pipe::ConnectionStatusChange::ReadOnly)
+    ));
+
+    // The chain can still be read but attempts to modify it fail immediately
+    let user = AteSessionUser::new();
+    assert!(chain.is_writable() == false);
+    let _ = chain.dio(&user).await;
+    match chain.dio_mut(&user).await {
+        Err(CommitError(CommitErrorKind::ChainReadOnly(_), _)) => {}
+        Err(err) => panic!("unexpected error - {}", err),
+        Ok(_) => panic!("a read-only replica allowed a mutable data access layer"),
+    }
+}
+
+#[cfg(feature = "enable_server")]
+#[test]
+fn test_mesh_inflight_budget() {
+    use super::inflight::InflightBudget;
+    use crate::comms::{Metrics, Throttle};
+    use std::sync::Mutex as StdMutex;
+
+    crate::utils::bootstrap_test_env();
+
+    let budget = Arc::new(InflightBudget::new(1000, 1000));
+    let throttle1 = Arc::new(StdMutex::new(Throttle::default()));
+    let throttle2 = Arc::new(StdMutex::new(Throttle::default()));
+    let metrics1 = Arc::new(StdMutex::new(Metrics::default()));
+    let metrics2 = Arc::new(StdMutex::new(Metrics::default()));
+
+    // A commit that exceeds the budget of its connection is rejected
+    assert!(matches!(
+        budget.reserve(1, 1001, &throttle1, &metrics1),
+        Err(1000)
+    ));
+    assert_eq!(budget.metrics().rejected, 1);
+    assert_eq!(budget.metrics().bytes, 0);
+
+    // Going over the budget of the route pauses the noisiest connection
+    let small = budget.reserve(2, 200, &throttle2, &metrics2).unwrap();
+    let large = budget.reserve(1, 900, &throttle1, &metrics1).unwrap();
+    assert!(throttle1.lock().unwrap().is_paused());
+    assert!(throttle2.lock().unwrap().is_paused() == false);
+    assert_eq!(metrics1.lock().unwrap().inflight_bytes, 900);
+    let metrics = budget.metrics();
+    assert_eq!(metrics.bytes, 1100);
+    assert_eq!(metrics.connections, 2);
+    assert_eq!(metrics.paused_connections, 1);
+
+    // Once enough memory is released the reads resume and the read loop that
+    // is waiting on the paused connection is woken up straight away
+    let resumed = throttle1.lock().unwrap().paused.clone().unwrap();
+    drop(small);
+    assert!(throttle1.lock().unwrap().is_paused() == false);
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        tokio::time::timeout(std::time::Duration::from_secs(1), resumed.notified())
+            .await
+            .expect("the paused reads were not woken up");
+    });
+    drop(large);
+    let metrics = budget.metrics();
+    assert_eq!(metrics.bytes, 0);
+    assert_eq!(metrics.peak_bytes, 1100);
+    assert_eq!(metrics.connections, 0);
+    assert_eq!(metrics.paused_connections, 0);
+    assert_eq!(metrics1.lock().unwrap().inflight_bytes, 0);
+}
+
+#[cfg(feature = "enable_server")]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_mesh_chain_quota() {
+    use super::quota::{ChainUsage, RouteQuotas};
+
+    crate::utils::bootstrap_test_env();
+
+    // Chains get the quota of their route unless it is overridden
+    let quotas = RouteQuotas::new(1000);
+    let key = ChainKey::from("quota");
+    assert_eq!(quotas.limit(&key), Some(1000));
+    quotas.set_override(&key, Some(0));
+    assert_eq!(quotas.limit(&key), None);
+    assert_eq!(quotas.limit(&ChainKey::from("other")), Some(1000));
+    quotas.set_override(&key, None);
+    assert_eq!(quotas.limit(&key), Some(1000));
+
+    let mut mock_cfg = crate::conf::tests::mock_test_config();
+    let (chain, _builder) = crate::trust::create_test_chain(
+        &mut mock_cfg,
+        "test_mesh_chain_quota".to_string(),
+        true,
+        true,
+        None,
+    )
+    .await;
+    let usage = ChainUsage::measure(&chain).await;
+    let base = usage.used();
+    let limit = Some(base + 10000);
+
+    // Commits that do not fit are rejected and the warning is only raised
+    // the first time the usage crosses the threshold
+    assert_eq!(usage.check(10001, limit), Err((base, base + 10000)));
+    assert!(usage.check(10000, limit).is_ok());
+    assert!(usage.check(10001, None).is_ok());
+    assert!(usage.add(1000, limit) == false);
+    assert!(usage.is_warning(limit) == false);
+    assert!(usage.add(7500, limit));
+    assert!(usage.is_warning(limit));
+    assert!(usage.add(100, limit) == false);
+    assert_eq!(usage.check(2000, limit), Err((base + 8600, base + 10000)));
+
+    // Compacting the chain measures it again which frees up the quota
+    chain.compact().await.expect("Failed to compact the log");
+    assert!(usage.refresh(&chain, limit).await < base + 8600);
+    assert!(usage.check(2000, limit).is_ok());
+}
+
+#[cfg(test)]
+fn test_history_batch(chain: &ChainKey, id: u8, len: usize) -> (AteHash, crate::comms::PacketData) {
+    use super::fanout::HistoryCache;
+    use crate::index::EventLeaf;
+
+    let leafs = vec![EventLeaf {
+        record: AteHash::from_bytes(&[id]),
+        created: 0,
+        updated: 0,
+    }];
+    let key = HistoryCache::key(chain, &leafs[..], SerializationFormat::Json, false, usize::MAX);
+    let pck = crate::comms::PacketData {
+        bytes: bytes::Bytes::from(vec![id; len]),
+        wire_format: SerializationFormat::Json,
+    };
+    (key, pck)
+}
+
+#[test]
+fn test_mesh_history_cache_eviction() {
+    use super::fanout::HistoryCache;
+
+    let chain = ChainKey::from("test-chain");
+    let cache = HistoryCache::new(300);
+    let (key1, pck1) = test_history_batch(&chain, 1, 100);
+    let (key2, pck2) = test_history_batch(&chain, 2, 100);
+    let (key3, pck3) = test_history_batch(&chain, 3, 100);
+    let (key4, pck4) = test_history_batch(&chain, 4, 100);
+    cache.insert(&chain, key1.clone(), pck1);
+    cache.insert(&chain, key2.clone(), pck2);
+    cache.insert(&chain, key3.clone(), pck3);
+
+    // Reading the first batch makes the second the least recently used, so
+    // it is the one that makes room for the fourth
+    assert!(cache.get(&key1).is_some());
+    cache.insert(&chain, key4.clone(), pck4);
+    assert!(cache.get(&key2).is_none());
+    assert!(cache.get(&key1).is_some());
+    assert!(cache.get(&key3).is_some());
+    assert!(cache.get(&key4).is_some());
+
+    let metrics = cache.metrics();
+    assert_eq!(metrics.entries, 3);
+    assert_eq!(metrics.bytes, 300);
+    assert_eq!(metrics.evictions, 1);
+    assert_eq!(metrics.hits, 4);
+    assert_eq!(metrics.misses, 1);
+
+    // Batches larger than the whole cache are not stored and shrinking
+    // the cache evicts the least recently used batches
+    let (key5, pck5) = test_history_batch(&chain, 5, 301);
+    cache.insert(&chain, key5.clone(), pck5);
+    assert!(cache.get(&key5).is_none());
+    cache.set_capacity(100);
+    assert!(cache.get(&key4).is_some());
+    assert!(cache.get(&key1).is_none());
+    assert!(cache.get(&key3).is_none());
+    assert_eq!(cache.metrics().bytes, 100);
+}
+
+#[test]
+fn test_mesh_history_cache_disabled() {
+    use super::fanout::HistoryCache;
+
+    let chain = ChainKey::from("test-chain");
+    let cache = HistoryCache::new(0);
+    assert!(cache.is_enabled() == false);
+
+    let (key, pck) = test_history_batch(&chain, 1, 0);
+    cache.insert(&chain, key.clone(), pck);
+    assert!(cache.get(&key).is_none());
+    assert_eq!(cache.metrics().entries, 0);
+
+    // It can be turned on (and off again) while running
+    cache.set_capacity(100);
+    assert!(cache.is_enabled());
+    let (key, pck) = test_history_batch(&chain, 2, 10);
+    cache.insert(&chain, key.clone(), pck);
+    assert!(cache.get(&key).is_some());
+    cache.set_capacity(0);
+    assert!(cache.is_enabled() == false);
+    assert!(cache.get(&key).is_none());
+}
+
+#[test]
+fn test_mesh_history_cache_compaction() {
+    use super::fanout::HistoryCache;
+
+    let chain1 = ChainKey::from("test-chain1");
+    let chain2 = ChainKey::from("test-chain2");
+    let cache = HistoryCache::new(1000);
+    let (key1, pck1) = test_history_batch(&chain1, 1, 10);
+    let (key2, pck2) = test_history_batch(&chain2, 2, 10);
+
+    // The same events of different chains are different batches
+    assert_ne!(key1, test_history_batch(&chain2, 1, 10).0);
+
+    cache.check_compactions(&chain1, 0);
+    cache.check_compactions(&chain2, 0);
+    cache.insert(&chain1, key1.clone(), pck1);
+    cache.insert(&chain2, key2.clone(), pck2);
+
+    // Nothing is dropped while the chain has not been compacted again
+    cache.check_compactions(&chain1, 0);
+    assert!(cache.get(&key1).is_some());
+
+    // Once it has been compacted only the entries of that chain are dropped
+    cache.check_compactions(&chain1, 1);
+    assert!(cache.get(&key1).is_none());
+    assert!(cache.get(&key2).is_some());
+    assert_eq!(cache.metrics().invalidations, 1);
+
+    cache.invalidate(&chain2);
+    assert!(cache.get(&key2).is_none());
+    assert_eq!(cache.metrics().invalidations, 2);
+    assert_eq!(cache.metrics().bytes, 0);
+}
+
+#[cfg(test)]
+fn test_wal_events(data: &[u8]) -> Vec<super::msg::MessageEvent> {
+    vec![super::msg::MessageEvent {
+        meta: crate::meta::Metadata::default(),
+        data: super::msg::MessageData::Some(data.to_vec()),
+        format: crate::spec::MessageFormat {
+            meta: SerializationFormat::Json,
+            data: SerializationFormat::Json,
+        },
+    }]
+}
+
+#[test]
+fn test_mesh_outbound_wal() {
+    use super::outbound_wal::OutboundWal;
+
+    let path = std::env::temp_dir().join(format!("ate-wal-{}.wal", fastrand::u64(..)));
+    let path = path.to_string_lossy().to_string();
+
+    let wal = OutboundWal::open_path(path.clone()).unwrap();
+    let first = wal.append(test_wal_events(b"first")).unwrap();
+    let second = wal.append(test_wal_events(b"second")).unwrap();
+    assert_ne!(first, second);
+
+    // Retrying a commit that is still unconfirmed does not record it twice
+    assert_eq!(wal.append(test_wal_events(b"first")).unwrap(), first);
+    assert_eq!(wal.pending().len(), 2);
+
+    // The unconfirmed commits survive a restart in their original order
+    wal.confirm(first).unwrap();
+    drop(wal);
+    let wal = OutboundWal::open_path(path.clone()).unwrap();
+    let pending = wal.pending();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].0, second);
+
+    // New commits never reuse the ID of an older one
+    let third = wal.append(test_wal_events(b"third")).unwrap();
+    assert!(third > second);
+
+    // Once everything is confirmed the file is emptied
+    wal.confirm(second).unwrap();
+    wal.confirm(third).unwrap();
+    assert!(wal.pending().is_empty());
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+    drop(wal);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_mesh_outbound_wal_truncated() {
+    use super::outbound_wal::OutboundWal;
+    use std::io::Write;
+
+    let path = std::env::temp_dir().join(format!("ate-wal-{}.wal", fastrand::u64(..)));
+    let path = path.to_string_lossy().to_string();
+
+    let wal = OutboundWal::open_path(path.clone()).unwrap();
+    let id = wal.append(test_wal_events(b"kept")).unwrap();
+    drop(wal);
+
+    // A record that was only partly written when the process died is ignored
+    let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(&[200, 0, 0, 0, 1, 2, 3]).unwrap();
+    drop(file);
+
+    let wal = OutboundWal::open_path(path.clone()).unwrap();
+    let pending = wal.pending();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].0, id);
+    drop(wal);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_mesh_msg_trailing_field() {
+    use super::msg::*;
+
+    let format = SerializationFormat::Bincode;
+    let rejected = Message::CommitError {
+        id: 1,
+        err: "quota exceeded".to_string(),
+        rejection: Some(CommitRejection::QuotaExceeded { used: 10, limit: 5 }),
+    };
+    let data = format.serialize_ref(&rejected).unwrap();
+    match format.deserialize_ref::<Message>(&data[..]).unwrap() {
+        Message::CommitError {
+            rejection: Some(CommitRejection::QuotaExceeded { used: 10, limit: 5 }),
+            ..
+        } => {}
+        a => panic!("the rejection was lost - {:?}", a),
+    }
+
+    // Older peers never write the field so the message just ends
+    let failed = Message::CommitError {
+        id: 2,
+        err: "failed".to_string(),
+        rejection: None,
+    };
+    let mut data = format.serialize_ref(&failed).unwrap();
+    assert_eq!(data.pop(), Some(0u8));
+    match format.deserialize_ref::<Message>(&data[..]).unwrap() {
+        Message::CommitError {
+            id: 2,
+            rejection: None,
+            ..
+        } => {}
+        a => panic!("the message of the older peer was not read - {:?}", a),
+    }
+
+    // A field that is there but can not be decoded fails the message
+    data.push(7u8);
+    assert!(format.deserialize_ref::<Message>(&data[..]).is_err());
+}
+
+#[cfg(feature = "enable_server")]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_mesh_ethereal_eviction() -> Result<(), AteError> {
+    use std::net::IpAddr;
+    use std::str::FromStr;
+
+    crate::utils::bootstrap_test_env();
+
+    let mut cfg_ate = ConfAte::default();
+    cfg_ate.ethereal_ttl = Some(std::time::Duration::from_secs(0));
+    cfg_ate.ethereal_max_chains = Some(1);
+    let url = url::Url::parse("ws://localhost:5091/").unwrap();
+    let listen = IpAddr::from_str("::").unwrap();
+    let cfg_mesh = ConfMesh::solo_from_url(&cfg_ate, &url, &listen, None, None).await?;
+    let root = super::create_server(&cfg_mesh).await?;
+    root.add_public_route(crate::flow::all_ethereal_centralized().await, &cfg_ate, false)
+        .await?;
+
+    // A chain that is still in use is neither evicted when it has been idle
+    // for longer than the TTL nor to make room for another chain
+    let first = root.open_public("/", &ChainKey::from("first")).await?;
+    root.clean().await;
+    assert_eq!(root.open_chains().await.len(), 1);
+    assert!(matches!(
+        root.open_public("/", &ChainKey::from("second")).await,
+        Err(ChainCreationError(ChainCreationErrorKind::TooManyEtherealChains(..), _))
+    ));
+
+    // Once it is released it makes way for the next chain
+    drop(first);
+    let second = root.open_public("/", &ChainKey::from("second")).await?;
+    let open = root.open_chains().await;
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].chain, ChainKey::from("second"));
+
+    // and idle chains are evicted after the TTL
+    drop(second);
+    root.clean().await;
+    assert!(root.open_chains().await.is_empty());
+
+    root.shutdown().await;
+    Ok(())
+}
+
+#[cfg(feature = "enable_dashboard")]
+async fn test_dashboard_get(
+    dashboard: &super::DashboardRoute,
+    path: &str,
+    token: Option<&str>,
+) -> Result<Vec<u8>, http::StatusCode> {
+    use crate::comms::RawWebRoute;
+
+    let mut headers = http::HeaderMap::new();
+    if let Some(token) = token {
+        headers.insert(
+            http::header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+    }
+    let uri: http::Uri = format!("/admin/dashboard/{}", path).parse().unwrap();
+    dashboard
+        .accepted_raw_get_request(uri, headers, "127.0.0.1:1234".parse().unwrap(), NodeId::default())
+        .await
+        .map_err(|(_, code)| code)
+}
+
+#[cfg(feature = "enable_dashboard")]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_mesh_dashboard() -> Result<(), AteError> {
+    use super::dashboard::*;
+    use crate::comms::RawWebRoute;
+    use http::StatusCode;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    crate::utils::bootstrap_test_env();
+
+    let cfg_ate = ConfAte::default();
+    let url = url::Url::parse("ws://localhost:5092/").unwrap();
+    let listen = IpAddr::from_str("::").unwrap();
+    let cfg_mesh = ConfMesh::solo_from_url(&cfg_ate, &url, &listen, None, None).await?;
+    let root = super::create_server(&cfg_mesh).await?;
+    root.add_public_route(crate::flow::all_ethereal_centralized().await, &cfg_ate, false)
+        .await?;
+    let _first = root.open_public("/", &ChainKey::from("dashboard-1")).await?;
+
+    // The page is public while the data behind it needs the access token
+    let dashboard = DashboardRoute::new(&root, "secret".to_string()).with_max_series(1);
+    assert!(test_dashboard_get(&dashboard, "index.html", None).await.is_ok());
+    assert_eq!(
+        test_dashboard_get(&dashboard, "api/summary", None).await.unwrap_err(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        test_dashboard_get(&dashboard, "api/summary", Some("guess")).await.unwrap_err(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        test_dashboard_get(&dashboard, "api/other", Some("secret")).await.unwrap_err(),
+        StatusCode::NOT_FOUND
+    );
+    let ret = dashboard
+        .accepted_raw_post_request(
+            "/admin/dashboard/api/summary".parse().unwrap(),
+            http::HeaderMap::new(),
+            "127.0.0.1:1234".parse().unwrap(),
+            NodeId::default(),
+            Vec::new(),
+        )
+        .await;
+    assert_eq!(ret.unwrap_err().1, StatusCode::METHOD_NOT_ALLOWED);
+
+    let summary = test_dashboard_get(&dashboard, "api/summary", Some("secret")).await.unwrap();
+    let summary: DashboardSummary = serde_json::from_slice(&summary[..]).unwrap();
+    assert_eq!(summary.open_chains, 1);
+
+    let chains = test_dashboard_get(&dashboard, "api/chains", Some("secret")).await.unwrap();
+    let chains: DashboardList<DashboardChain> = serde_json::from_slice(&chains[..]).unwrap();
+    assert_eq!(chains.items.len(), 1);
+    assert_eq!(chains.omitted, 0);
+
+    // The statistics of the chains are reused until the refresh interval
+    // passes (so repeated requests do not recompute them)
+    let _second = root.open_public("/", &ChainKey::from("dashboard-2")).await?;
+    let chains = test_dashboard_get(&dashboard, "api/chains", Some("secret")).await.unwrap();
+    let chains: DashboardList<DashboardChain> = serde_json::from_slice(&chains[..]).unwrap();
+    assert_eq!(chains.omitted, 0);
+
+    let dashboard = DashboardRoute::new(&root, "secret".to_string())
+        .with_max_series(1)
+        .with_refresh(Duration::from_secs(0));
+    let chains = test_dashboard_get(&dashboard, "api/chains", Some("secret")).await.unwrap();
+    let chains: DashboardList<DashboardChain> = serde_json::from_slice(&chains[..]).unwrap();
+    assert_eq!(chains.items.len(), 1);
+    assert_eq!(chains.omitted, 1);
+
+    root.shutdown().await;
+    Ok(())
+}
+
+#[cfg(feature = "enable_server")]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_mesh_watchdog_route_policy() -> Result<(), AteError> {
+    use super::WatchdogPolicy;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    crate::utils::bootstrap_test_env();
+
+    let cfg_ate = ConfAte::default();
+    let url = url::Url::parse("ws://localhost:5094/").unwrap();
+    let listen = IpAddr::from_str("::").unwrap();
+    let mut cfg_mesh = ConfMesh::solo_from_url(&cfg_ate, &url, &listen, None, None).await?;
+    let default = WatchdogPolicy::default().with_threshold(Duration::from_secs(30));
+    cfg_mesh.watchdog_policy = Some(default.clone());
+    let root = super::create_server(&cfg_mesh).await?;
+    root.add_public_route(crate::flow::all_ethereal_centralized().await, &cfg_ate, false)
+        .await?;
+
+    // Routes start with the policy of the mesh configuration
+    let _chain = root.open_public("/", &ChainKey::from("watchdog-1")).await?;
+    assert_eq!(root.watchdog_policy("/"), Some(default.clone()));
+
+    // Each route can have its own policy (or none at all)
+    let strict = WatchdogPolicy::default()
+        .with_threshold(Duration::from_secs(5))
+        .with_check_interval(Duration::from_secs(1))
+        .with_recover(true);
+    root.set_watchdog_policy("/", Some(strict.clone()));
+    root.set_watchdog_policy("/other", None);
+    assert_eq!(root.watchdog_policy("/"), Some(strict));
+    assert_eq!(root.watchdog_policy("/other"), None);
+
+    // A zero threshold turns the watchdog off for the route
+    root.set_watchdog_policy("/", Some(default.with_threshold(Duration::ZERO)));
+    assert_eq!(root.watchdog_policy("/"), None);
+
+    // Nothing is pending so nothing is stuck
+    assert_eq!(root.stuck_chains().await, 0);
+
+    root.shutdown().await;
+    Ok(())
+}
+
+#[cfg(feature = "enable_server")]
+async fn test_public_get(
+    public: &super::PublicRoute,
+    path: &str,
+) -> Result<serde_json::Value, http::StatusCode> {
+    use crate::comms::RawWebRoute;
+
+    let uri: http::Uri = format!("/public/{}", path).parse().unwrap();
+    let ret = public
+        .accepted_raw_get_request(
+            uri,
+            http::HeaderMap::new(),
+            "127.0.0.1:1234".parse().unwrap(),
+            NodeId::default(),
+        )
+        .await
+        .map_err(|(_, code)| code)?;
+    Ok(serde_json::from_slice(&ret[..]).unwrap())
+}
+
+#[cfg(feature = "enable_server")]
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_mesh_public_collection_pages() -> Result<(), AteError> {
+    use super::public::*;
+    use http::StatusCode;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+
+    crate::utils::bootstrap_test_env();
+
+    let cfg_ate = ConfAte::default();
+    let url = url::Url::parse("ws://localhost:5095/").unwrap();
+    let listen = IpAddr::from_str("::").unwrap();
+    let cfg_mesh = ConfMesh::solo_from_url(&cfg_ate, &url, &listen, None, None).await?;
+    let root = super::create_server(&cfg_mesh).await?;
+    root.add_public_route(crate::flow::all_ethereal_centralized().await, &cfg_ate, false)
+        .await?;
+
+    let chain = root.open_public("/", &ChainKey::from("public-1")).await?;
+    let session = AteSessionUser::new();
+    let (key, collection) = {
+        let dio = chain.dio_mut(&session).await?;
+        let mut parent = dio.store(TestData::default())?;
+        for n in 0..5u32 {
+            parent.as_mut().inner.push(format!("child-{}", n))?;
+        }
+        dio.commit().await?;
+        (parent.key().as_fixed_hex_string(), parent.inner.vec_id())
+    };
+
+    let public = PublicRoute::new(&root, "/", PublicRateLimit::new(1000, 1000))
+        .with_max_page_size(2);
+
+    // Single rows are served as they are
+    let row = test_public_get(&public, format!("public-1/{}", key).as_str())
+        .await
+        .unwrap();
+    assert_eq!(row["data"], serde_json::json!(0));
+
+    // Collections are served one page at a time
+    let path = format!("public-1/{}/{}", key, collection);
+    let page: PublicPage =
+        serde_json::from_value(test_public_get(&public, path.as_str()).await.unwrap()).unwrap();
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(page.total, 5);
+    assert_eq!(page.next, Some(2));
+
+    let mut seen = page.items;
+    let mut next = page.next;
+    while let Some(offset) = next {
+        let page: PublicPage = serde_json::from_value(
+            test_public_get(&public, format!("{}?offset={}", path, offset).as_str())
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(page.items.len() <= 2);
+        assert_eq!(page.offset, offset);
+        seen.extend(page.items);
+        next = page.next;
+    }
+    assert_eq!(seen.len(), 5);
+
+    // Readers may ask for smaller pages but never for larger ones
+    let page: PublicPage = serde_json::from_value(
+        test_public_get(&public, format!("{}?limit=1", path).as_str())
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(page.items.len(), 1);
+    let page: PublicPage = serde_json::from_value(
+        test_public_get(&public, format!("{}?limit=1000&offset=4", path).as_str())
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.next, None);
+
+    assert_eq!(
+        test_public_get(&public, format!("{}?limit=lots", path).as_str())
+            .await
+            .unwrap_err(),
+        StatusCode::BAD_REQUEST
+    );
+
+    root.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+/// Policy that makes mesh roots watch the chains they host for transactions
+/// that never complete (for instance because a pipe in the chain deadlocked).
+/// Stuck chains are reported with diagnostics and, when recovery is enabled,
+/// their pending transactions are aborted with a timeout and the chain is
+/// evicted from the root so that its pipes are rebuilt when it next opens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchdogPolicy {
+    // Chains with pending transactions that have made no progress for this long are stuck
+    pub threshold: Duration,
+    // How frequently the chains are checked
+    pub check_interval: Duration,
+    // Aborts the pending transactions of stuck chains and rebuilds their pipes
+    pub recover: bool,
+}
+
+impl Default for WatchdogPolicy {
+    fn default() -> WatchdogPolicy {
+        WatchdogPolicy {
+            threshold: Duration::from_secs(60),
+            check_interval: Duration::from_secs(10),
+            recover: false,
+        }
+    }
+}
+
+impl WatchdogPolicy {
+    pub fn with_threshold(mut self, val: Duration) -> Self {
+        self.threshold = val;
+        self
+    }
+
+    pub fn with_check_interval(mut self, val: Duration) -> Self {
+        self.check_interval = val;
+        self
+    }
+
+    pub fn with_recover(mut self, val: bool) -> Self {
+        self.recover = val;
+        self
+    }
+
+    /// Returns true if the policy will watch the chains
+    pub fn is_active(&self) -> bool {
+        self.threshold > Duration::ZERO
+    }
+}
+
+impl std::fmt::Display for WatchdogPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "watchdog(threshold={}s,interval={}s,recover={})",
+            self.threshold.as_secs(),
+            self.check_interval.as_secs(),
+            self.recover
+        )
+    }
+}
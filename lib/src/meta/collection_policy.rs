@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// Retention policy of a collection that is attached to the metadata of its
+/// parent row (hence only those who can write to the parent row can change
+/// it). Collections with a policy behave like ring buffers where the oldest
+/// entries are removed once the limits are exceeded.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetaCollectionPolicy {
+    pub collection_id: u64,
+    /// Maximum number of entries that are kept in the collection
+    pub max_entries: Option<u64>,
+    /// Maximum age of the entries in milliseconds
+    pub max_age: Option<u64>,
+}
+
+impl std::fmt::Display for MetaCollectionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.collection_id)?;
+        if let Some(max_entries) = self.max_entries {
+            write!(f, "-max_entries={}", max_entries)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "-max_age={}ms", max_age)?;
+        }
+        Ok(())
+    }
+}
@@ -28,6 +28,8 @@ pub enum CoreMetadata {
     Type(MetaType),
     Reply(PrimaryKey),
     DelayedUpload(MetaDelayedUpload),
+    CollectionPolicy(MetaCollectionPolicy),
+    Patch(MetaPatch),
 }
 
 impl Default for CoreMetadata {
@@ -58,6 +60,8 @@ impl std::fmt::Display for CoreMetadata {
             CoreMetadata::Type(a) => write!(f, "type-{}", a),
             CoreMetadata::Reply(a) => write!(f, "reply-{}", a),
             CoreMetadata::DelayedUpload(a) => write!(f, "delayed_upload-{}", a),
+            CoreMetadata::CollectionPolicy(a) => write!(f, "collection_policy-{}", a),
+            CoreMetadata::Patch(a) => write!(f, "patch-{}", a),
         }
     }
 }
@@ -278,6 +282,26 @@ impl Metadata {
             .next()
     }
 
+    pub fn get_patch(&self) -> Option<MetaPatch> {
+        self.core
+            .iter()
+            .filter_map(|m| match m {
+                CoreMetadata::Patch(a) => Some(a.clone()),
+                _ => None,
+            })
+            .next()
+    }
+
+    pub fn get_collection_policies(&self) -> Vec<MetaCollectionPolicy> {
+        self.core
+            .iter()
+            .filter_map(|m| match m {
+                CoreMetadata::CollectionPolicy(a) => Some(a.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn include_in_history(&self) -> bool {
         if self.get_delayed_upload().is_some() {
             return false;
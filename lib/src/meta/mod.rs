@@ -1,19 +1,23 @@
 mod authorization;
 mod collection;
+mod collection_policy;
 mod confidentiality;
 mod core;
 mod delayed_upload;
 mod meta_type;
 mod parent;
+mod patch;
 mod read_option;
 mod write_option;
 
 pub use self::core::*;
 pub use authorization::*;
 pub use collection::*;
+pub use collection_policy::*;
 pub use confidentiality::*;
 pub use delayed_upload::*;
 pub use meta_type::*;
 pub use parent::*;
+pub use patch::*;
 pub use read_option::*;
 pub use write_option::*;
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::AteHash;
+
+/// Marks an event whose data is a patch against an earlier version of the
+/// same row rather than the full row itself
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MetaPatch {
+    /// Hash of the event that this patch is applied on top of
+    pub base: AteHash,
+    /// Number of patches (including this one) since the last full row
+    pub depth: u32,
+}
+
+impl std::fmt::Display for MetaPatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "base-{}-depth-{}", self.base, self.depth)
+    }
+}
@@ -6,6 +6,7 @@ use tokio::sync::RwLock;
 use derivative::*;
 use error_chain::bail;
 
+use crate::dio::patch::RowPatch;
 use crate::event::EventStrongData;
 use crate::session::AteSession;
 
@@ -17,6 +18,7 @@ use super::lint::*;
 use super::meta::*;
 use super::pipe::*;
 use super::spec::MessageFormat;
+use super::spec::SerializationFormat;
 use super::transaction::*;
 use super::trust::*;
 use super::event::MessageBytes;
@@ -72,6 +74,12 @@ impl ChainMultiUser {
     }
 
     pub async fn load(&self, leaf: EventLeaf) -> Result<LoadStrongResult, LoadError> {
+        let mut ret = self.load_event(leaf).await?;
+        ret.base = self.load_patch_base(&ret.data.meta).await?;
+        Ok(ret)
+    }
+
+    async fn load_event(&self, leaf: EventLeaf) -> Result<LoadStrongResult, LoadError> {
         let ret = self.inside_async.read().await.chain.load(leaf).await?;
         let ret = LoadStrongResult {
             lookup: ret.lookup,
@@ -97,10 +105,76 @@ impl ChainMultiUser {
                 }
             },
             leaf: ret.leaf,
+            base: Vec::new(),
         };
         Ok(ret)
     }
 
+    /// Loads the events that a patch event is applied on top of, ordered from
+    /// the full row through to the patch that directly precedes it
+    pub(crate) async fn load_patch_base(
+        &self,
+        meta: &Metadata,
+    ) -> Result<Vec<EventStrongData>, LoadError> {
+        let mut ret = Vec::new();
+        let mut next = meta.get_patch();
+        while let Some(patch) = next {
+            let evt = self
+                .load_event(EventLeaf {
+                    record: patch.base,
+                    created: 0,
+                    updated: 0,
+                })
+                .await?;
+
+            // Each step back must get closer to the full row or the chain is corrupt
+            next = evt.data.meta.get_patch();
+            if let Some(prev) = next.as_ref() {
+                if prev.depth >= patch.depth {
+                    bail!(LoadErrorKind::LoadFailed(format!(
+                        "the patch chain is broken at event {}",
+                        patch.base
+                    )));
+                }
+            }
+            ret.push(evt.data);
+        }
+        ret.reverse();
+        Ok(ret)
+    }
+
+    /// Rebuilds the full data of a row that was written as a patch by applying
+    /// it (and the patches before it) on top of the full row it is based on,
+    /// the data passed in must already have been decrypted
+    pub(crate) fn data_with_patches(
+        &self,
+        meta: &Metadata,
+        format: SerializationFormat,
+        data: Bytes,
+        base: &[EventStrongData],
+        session: &'_ dyn AteSession,
+    ) -> Result<Bytes, LoadError> {
+        if meta.get_patch().is_none() {
+            return Ok(data);
+        }
+
+        let mut ret: Option<Bytes> = None;
+        for evt in base.iter() {
+            let bytes = match &evt.data_bytes {
+                Some(a) => self.data_as_overlay(&evt.meta, a.clone(), session)?,
+                None => bail!(LoadErrorKind::MissingData),
+            };
+            ret = Some(match ret {
+                Some(prev) => RowPatch::from_bytes(&bytes[..])?.apply(format, &prev[..])?,
+                None => bytes,
+            });
+        }
+        match ret {
+            Some(prev) => Ok(RowPatch::from_bytes(&data[..])?.apply(format, &prev[..])?),
+            None => bail!(LoadErrorKind::MissingData),
+        }
+    }
+
     pub async fn load_many(&self, leafs: Vec<EventLeaf>) -> Result<Vec<LoadStrongResult>, LoadError> {
         let mut data = self.inside_async.read().await.chain.load_many(leafs.clone()).await?;
         
@@ -142,9 +216,13 @@ impl ChainMultiUser {
                         }
                     },
                     leaf: l.leaf,
+                    base: Vec::new(),
                 }
             );
         }
+        for ret in rets.iter_mut() {
+            ret.base = self.load_patch_base(&ret.data.meta).await?;
+        }
         Ok(rets)
     }
 
@@ -168,6 +246,17 @@ impl ChainMultiUser {
         self.inside_async.read().await.chain.lookup_parent(key)
     }
 
+    pub async fn lookup_collection_policy(
+        &self,
+        key: &MetaCollection,
+    ) -> Option<MetaCollectionPolicy> {
+        self.inside_async
+            .read()
+            .await
+            .chain
+            .lookup_collection_policy(key)
+    }
+
     pub async fn roots_raw(&self) -> Vec<PrimaryKey> {
         self.inside_async
             .read()
@@ -246,6 +335,7 @@ impl ChainMultiUser {
             events: Vec::new(),
             timeout,
             conversation: None,
+            deferrable: false,
         };
 
         let work = ChainWork { trans };
@@ -10,10 +10,13 @@ use tokio::sync::mpsc;
 use bytes::Bytes;
 
 use crate::crypto::AteHash;
+use crate::mesh::QuotaStatus;
 
 pub enum ConnectionStatusChange {
     Disconnected,
     ReadOnly,
+    /// The server is draining and asked that we reconnect elsewhere
+    Reconnect,
 }
 
 #[async_trait]
@@ -36,8 +39,18 @@ pub(crate) trait EventPipe: Send + Sync {
         Ok(())
     }
 
+    async fn on_reconnect_hint(&self) -> Result<(), CommsError> {
+        Ok(())
+    }
+
     async fn load_many(&self, leafs: Vec<AteHash>) -> Result<Vec<Option<Bytes>>, LoadError>;
 
+    /// Asks the root that the pipe is connected to how much of its storage
+    /// quota the chain is using (None when there is no root to ask)
+    async fn quota_status(&self) -> Result<Option<QuotaStatus>, LoadError> {
+        Ok(None)
+    }
+
     async fn feed(&self, work: ChainWork) -> Result<(), CommitError>;
 
     async fn prime(&self, records: Vec<(AteHash, Option<Bytes>)>) -> Result<(), CommsError>;
@@ -150,6 +163,27 @@ impl EventPipe for DuelPipe {
         Err(CommsErrorKind::ShouldBlock.into())
     }
 
+    async fn on_reconnect_hint(&self) -> Result<(), CommsError> {
+        let ret1 = self.first.on_reconnect_hint().await;
+        let ret2 = self.second.on_reconnect_hint().await;
+
+        if let Ok(_) = ret1 {
+            return Ok(());
+        }
+        if let Ok(_) = ret2 {
+            return Ok(());
+        }
+
+        Err(CommsErrorKind::ShouldBlock.into())
+    }
+
+    async fn quota_status(&self) -> Result<Option<QuotaStatus>, LoadError> {
+        match self.first.quota_status().await? {
+            Some(a) => Ok(Some(a)),
+            None => self.second.quota_status().await,
+        }
+    }
+
     async fn connect(
         &self,
     ) -> Result<mpsc::Receiver<ConnectionStatusChange>, ChainCreationError> {
@@ -1,6 +1,10 @@
 pub use crate::compact::CompactMode;
 pub use crate::conf::ConfAte as AteConfig;
 pub use crate::conf::ConfAte;
+#[cfg(feature = "enable_local_fs")]
+pub use crate::conf::ConfDocument;
+#[cfg(feature = "enable_local_fs")]
+pub use crate::conf::CONF_SECTION_MESH;
 pub use crate::conf::ConfMesh;
 pub use crate::conf::ConfiguredFor;
 pub use crate::error::*;
@@ -8,6 +12,14 @@ pub use crate::header::PrimaryKey;
 
 pub use crate::comms::Metrics as ChainMetrics;
 pub use crate::comms::Throttle as ChainThrottle;
+pub use crate::chain::ChainLag;
+pub use crate::trust::ChainStatistics;
+pub use crate::trust::IndexUsage;
+pub use crate::trust::verify_inclusion;
+pub use crate::trust::InclusionProof;
+pub use crate::trust::RootHead;
+pub use crate::trust::SignedRootHead;
+pub use crate::chain::InboundBudget;
 pub use crate::conf::MeshConnectAddr;
 pub use crate::crypto::AteHash;
 pub use crate::crypto::DerivedEncryptKey;
@@ -49,18 +61,32 @@ pub use crate::utils::chain_key_16hex;
 pub use crate::utils::chain_key_4hex;
 
 pub use crate::chain::Chain;
+#[cfg(feature = "enable_local_fs")]
+pub use crate::chain::{ReplayOptions, ReplayReport, ReplaySnapshot};
+#[cfg(feature = "enable_local_fs")]
+pub use crate::chain::{ChainArchive, ChainArchiveManifest};
+pub use crate::chain::{GcOptions, GcReport};
 pub use crate::conf::ChainBuilder;
 pub use crate::mesh::ChainGuard;
+#[cfg(feature = "enable_full")]
+pub use crate::mesh::BlockingChainGuard;
+#[cfg(feature = "enable_full")]
+pub use crate::mesh::BlockingRegistry;
 pub use crate::trust::ChainKey;
 pub use crate::trust::ChainRef;
 
 pub use crate::dio::Bus;
 pub use crate::dio::BusEvent;
 pub use crate::dio::TryBusEvent;
+pub use crate::dio::ChainCursor;
+pub use crate::dio::CursorEvent;
+pub use crate::dio::CursorPosition;
+pub use crate::dio::CursorSubscription;
 pub use crate::dio::Dao;
 pub use crate::dio::DaoAuthGuard;
 pub use crate::dio::DaoChild;
 pub use crate::dio::DaoForeign;
+pub use crate::dio::DaoIndexed;
 pub use crate::dio::DaoMap;
 pub use crate::dio::DaoMut;
 pub use crate::dio::DaoMutGuard;
@@ -72,6 +98,13 @@ pub use crate::dio::Dio;
 pub use crate::dio::DioMut;
 pub use crate::dio::DioSessionGuard;
 pub use crate::dio::DioSessionGuardMut;
+pub use crate::dio::IndexValue;
+pub use crate::dio::MultiChainResolver;
+pub use crate::dio::MultiChainTransaction;
+pub use crate::dio::Query;
+pub use crate::dio::RawRow;
+pub use crate::dio::register_index;
+pub use crate::dio::SerializerExt;
 
 pub use crate::multi::ChainMultiUser;
 pub use crate::session::AteGroup;
@@ -92,13 +125,23 @@ pub use crate::transaction::TransactionScope;
 pub use crate::service::ServiceHandler;
 
 pub use crate::comms::CertificateValidation;
+pub use crate::comms::{CertificateInfo, ServerCertificate, ServerCertificates};
 pub use crate::comms::NodeId;
+pub use crate::comms::ConnectionPeer;
 pub use crate::comms::StreamProtocol;
 pub use crate::conf::MeshAddress;
 pub use crate::engine::TaskEngine;
+pub use crate::engine::TaskInfo;
 pub use crate::mesh::BackupMode;
+pub use crate::mesh::QuorumPolicy;
+pub use crate::mesh::ChainQuota;
+pub use crate::mesh::QuotaStatus;
+pub use crate::mesh::QUOTA_WARN_PERCENT;
 pub use crate::mesh::RecoveryMode;
 pub use crate::mesh::Registry;
+pub use crate::flow::EventValidator;
+#[cfg(all(feature = "enable_client", feature = "enable_full"))]
+pub use crate::mesh::{doctor, DoctorOptions, DoctorReport};
 pub use crate::spec::CentralizedRole;
 pub use crate::spec::TrustMode;
 pub use std::{
@@ -36,6 +36,8 @@ pub struct RedoLog {
     #[cfg(feature = "enable_local_fs")]
     log_path: Option<String>,
     flip: Option<RedoLogFlip>,
+    /// Number of times the log was replaced by a compacted copy of itself
+    flips: u64,
     pub(super) log_file: Box<dyn LogFile>,
 }
 
@@ -80,6 +82,7 @@ impl RedoLog {
                 None => LogFileMemDb::new(header_bytes).await?,
             },
             flip: None,
+            flips: 0,
         };
         Ok(ret)
     }
@@ -90,6 +93,7 @@ impl RedoLog {
         let ret = RedoLog {
             log_file: LogFileMemDb::new(header_bytes).await?,
             flip: None,
+            flips: 0,
         };
         Ok(ret)
     }
@@ -156,6 +160,7 @@ impl RedoLog {
 
                 self.log_file = new_log_file;
                 self.flip = None;
+                self.flips += 1;
 
                 Ok(event_summary)
             }
@@ -187,6 +192,14 @@ impl RedoLog {
         self.log_file.offset()
     }
 
+    pub async fn disk_size(&self) -> Result<u64> {
+        self.log_file.disk_size().await
+    }
+
+    pub fn flips(&self) -> u64 {
+        self.flips
+    }
+
     pub fn end(&self) -> LogLookup {
         LogLookup {
             index: self.log_file.index(),
@@ -0,0 +1,291 @@
+//! Advisory locking of the redo log of a chain so that several processes
+//! which open the same log on the local disk do not corrupt it. A process
+//! that opens the log for writing holds an exclusive lock while the ones
+//! that only read it (e.g. the replay and dump tools) share a lock, the lock
+//! is taken on a `.lock` file next to the log which also records the process
+//! that is writing to it.
+//!
+//! On unix the lock is an `flock` which the kernel releases when the process
+//! dies. When `flock` is not available (other platforms or file systems that
+//! do not support it) the existence of the lockfile is the lock instead, in
+//! which case a lockfile left behind by a process that is no longer alive is
+//! considered stale and taken over. Processes on wasm have no identity that
+//! a lockfile could record hence their logs are not locked.
+#![cfg_attr(target_family = "wasm", allow(dead_code, unused_imports))]
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Read;
+use std::io::Write;
+use tokio::io::Error;
+use tokio::io::ErrorKind;
+use tokio::io::Result;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+/// Details of the process that holds the lock, these are written into the
+/// lockfile by the process that opened the log for writing
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct LogLockOwner {
+    pub pid: u32,
+    pub writable: bool,
+    /// Milliseconds since the epoch when the lock was acquired
+    pub since: u64,
+}
+
+impl LogLockOwner {
+    fn current(writable: bool) -> LogLockOwner {
+        LogLockOwner {
+            pid: std::process::id(),
+            writable,
+            since: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|a| a.as_millis() as u64)
+                .unwrap_or(0),
+        }
+    }
+
+    fn read(file: &mut File) -> Option<LogLockOwner> {
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).ok()?;
+        serde_json::from_slice(&data[..]).ok()
+    }
+
+    fn write(&self, file: &mut File) -> Result<()> {
+        let data = serde_json::to_vec(self)?;
+        file.set_len(0)?;
+        file.write_all(&data[..])?;
+        file.sync_data()
+    }
+}
+
+/// Error returned when the redo log can not be opened as another process
+/// holds a lock on it that conflicts (the PID is only known when the other
+/// process is writing to the log)
+#[derive(Debug, Clone)]
+pub struct LogLockedError {
+    pub path: String,
+    pub pid: Option<u32>,
+}
+
+impl std::fmt::Display for LogLockedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.pid {
+            Some(pid) => write!(f, "the redo log ({}) is locked by another process (pid={})", self.path, pid),
+            None => write!(f, "the redo log ({}) is locked by another process", self.path),
+        }
+    }
+}
+
+impl std::error::Error for LogLockedError {}
+
+impl LogLockedError {
+    fn into_io(self) -> Error {
+        Error::new(ErrorKind::WouldBlock, self)
+    }
+
+    /// Returns the locking error that caused an IO error (if that was the cause)
+    pub fn from_io(err: &Error) -> Option<&LogLockedError> {
+        err.get_ref()
+            .and_then(|a| a.downcast_ref::<LogLockedError>())
+    }
+}
+
+/// Lock that is held on a redo log for as long as it is open, the lock is
+/// released when this is dropped
+#[derive(Debug)]
+pub(super) struct LogFileLock {
+    path: String,
+    file: Option<File>,
+    writable: bool,
+    fallback: bool,
+}
+
+impl LogFileLock {
+    /// Locks the redo log at this path (exclusively when it is writable),
+    /// fails immediately rather than waiting when another process holds a
+    /// lock that conflicts
+    pub(super) fn acquire(path_log: &str, writable: bool) -> Result<LogFileLock> {
+        let path = format!("{}.lock", path_log);
+
+        #[cfg(unix)]
+        {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .open(path.as_str())?;
+            match flock(&file, writable) {
+                Ok(true) => {
+                    if writable {
+                        LogLockOwner::current(writable).write(&mut file)?;
+                    }
+                    debug!("locked {} (writable={})", path, writable);
+                    return Ok(LogFileLock {
+                        path,
+                        file: Some(file),
+                        writable,
+                        fallback: false,
+                    });
+                }
+                Ok(false) => {
+                    let pid = LogLockOwner::read(&mut file).map(|a| a.pid);
+                    return Err(LogLockedError {
+                        path: path_log.to_string(),
+                        pid,
+                    }
+                    .into_io());
+                }
+                Err(err) if is_unsupported(&err) => {
+                    debug!("flock is not supported for {} - {}", path, err);
+                }
+                Err(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        Self::acquire_fallback(path_log, path, writable)
+    }
+
+    /// Uses the existence of the lockfile as the lock, only writers create
+    /// it while readers just check that no live process is writing
+    #[cfg(not(target_family = "wasm"))]
+    fn acquire_fallback(path_log: &str, path: String, writable: bool) -> Result<LogFileLock> {
+        for _ in 0..2 {
+            if writable {
+                match OpenOptions::new().write(true).create_new(true).open(path.as_str()) {
+                    Ok(mut file) => {
+                        LogLockOwner::current(writable).write(&mut file)?;
+                        debug!("locked {} with a lockfile", path);
+                        return Ok(LogFileLock {
+                            path,
+                            file: Some(file),
+                            writable,
+                            fallback: true,
+                        });
+                    }
+                    Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+                    Err(err) => return Err(err),
+                }
+            }
+
+            let owner = match File::open(path.as_str()) {
+                Ok(mut file) => LogLockOwner::read(&mut file),
+                Err(err) if err.kind() == ErrorKind::NotFound => {
+                    if writable {
+                        continue;
+                    }
+                    break;
+                }
+                Err(err) => return Err(err),
+            };
+            match owner {
+                Some(owner) if is_process_alive(owner.pid) => {
+                    return Err(LogLockedError {
+                        path: path_log.to_string(),
+                        pid: Some(owner.pid),
+                    }
+                    .into_io());
+                }
+                owner => {
+                    warn!(
+                        "removing a stale lockfile ({}) left by a process that is no longer running (pid={:?})",
+                        path,
+                        owner.map(|a| a.pid)
+                    );
+                    let _ = std::fs::remove_file(path.as_str());
+                    if writable == false {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if writable {
+            return Err(LogLockedError {
+                path: path_log.to_string(),
+                pid: None,
+            }
+            .into_io());
+        }
+        Ok(LogFileLock {
+            path,
+            file: None,
+            writable,
+            fallback: true,
+        })
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn acquire_fallback(_path_log: &str, path: String, writable: bool) -> Result<LogFileLock> {
+        Ok(LogFileLock {
+            path,
+            file: None,
+            writable,
+            fallback: true,
+        })
+    }
+}
+
+impl Drop for LogFileLock {
+    fn drop(&mut self) {
+        if let Some(file) = self.file.take() {
+            // The details of the writer are cleared before the lock is released
+            // so that they are not reported for the processes that lock it next
+            if self.writable {
+                let _ = file.set_len(0);
+            }
+            drop(file);
+            if self.fallback && self.writable {
+                let _ = std::fs::remove_file(self.path.as_str());
+            }
+            debug!("unlocked {}", self.path);
+        }
+    }
+}
+
+/// Takes the lock without waiting, returns false if another process holds it
+#[cfg(unix)]
+fn flock(file: &File, exclusive: bool) -> Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    let op = match exclusive {
+        true => libc::LOCK_EX,
+        false => libc::LOCK_SH,
+    };
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), op | libc::LOCK_NB) } == 0 {
+            return Ok(true);
+        }
+        let err = Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EINTR) => continue,
+            Some(libc::EWOULDBLOCK) => return Ok(false),
+            _ => return Err(err),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_unsupported(err: &Error) -> bool {
+    matches!(
+        err.raw_os_error(),
+        Some(libc::ENOLCK) | Some(libc::EOPNOTSUPP) | Some(libc::ENOSYS)
+    )
+}
+
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> bool {
+    if unsafe { libc::kill(pid as libc::pid_t, 0) } == 0 {
+        return true;
+    }
+    Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Other processes can not be checked on these platforms hence a lockfile
+/// is never considered stale (it must be removed by hand)
+#[cfg(not(unix))]
+fn is_process_alive(_pid: u32) -> bool {
+    true
+}
@@ -11,6 +11,7 @@ use cached::Cached;
 #[cfg(feature = "enable_caching")]
 use cached::*;
 use fxhash::FxHashMap;
+use std::sync::Arc;
 #[cfg(feature = "enable_caching")]
 use std::sync::Mutex as MutexSync;
 use tokio::io::ErrorKind;
@@ -24,6 +25,7 @@ use crate::{crypto::*, redo::LogLookup};
 
 use super::appender::*;
 use super::archive::*;
+use super::lock::*;
 use super::magic::*;
 use super::*;
 
@@ -38,6 +40,7 @@ pub(super) struct LogFileLocalFs {
     pub(crate) log_path: String,
     pub(crate) backup_path: Option<String>,
     pub(crate) temp: bool,
+    pub(crate) lock: Option<Arc<LogFileLock>>,
     pub(crate) lookup: FxHashMap<AteHash, LogLookup>,
     pub(crate) appender: LogAppender,
     pub(crate) archives: FxHashMap<u32, LogArchive>,
@@ -56,6 +59,45 @@ impl LogFileLocalFs {
         _cache_size: usize,
         _cache_ttl: u64,
         header_bytes: Vec<u8>,
+    ) -> Result<Box<LogFileLocalFs>> {
+        // Temporary logs are deleted as soon as they are opened so no other
+        // process could ever open them
+        let lock = match temp_file {
+            true => None,
+            false => Some(Arc::new(LogFileLock::acquire(
+                path_log.as_str(),
+                read_only == false,
+            )?)),
+        };
+
+        LogFileLocalFs::new_ext(
+            lock,
+            temp_file,
+            read_only,
+            path_log,
+            backup_path,
+            restore_path,
+            truncate,
+            _cache_size,
+            _cache_ttl,
+            header_bytes,
+        )
+        .await
+    }
+
+    /// Opens the log under a lock that is already held (e.g. the lock of
+    /// the log that a flipped log will replace)
+    async fn new_ext(
+        lock: Option<Arc<LogFileLock>>,
+        temp_file: bool,
+        read_only: bool,
+        path_log: String,
+        backup_path: Option<String>,
+        restore_path: Option<String>,
+        truncate: bool,
+        _cache_size: usize,
+        _cache_ttl: u64,
+        header_bytes: Vec<u8>,
     ) -> Result<Box<LogFileLocalFs>> {
         debug!("open at {}", path_log);
 
@@ -144,6 +186,7 @@ impl LogFileLocalFs {
             log_path: path_log,
             backup_path: backup_path,
             temp: temp_file,
+            lock,
             lookup: FxHashMap::default(),
             appender,
             #[cfg(feature = "enable_caching")]
@@ -383,6 +426,7 @@ impl LogFile for LogFileLocalFs {
             log_path: self.log_path.clone(),
             backup_path: self.backup_path.clone(),
             temp: self.temp,
+            lock: self.lock.clone(),
             lookup: self.lookup.clone(),
             appender: self.appender.clone().await?,
             #[cfg(feature = "enable_caching")]
@@ -646,6 +690,16 @@ impl LogFile for LogFileLocalFs {
         self.appender.offset() - self.appender.header().len() as u64
     }
 
+    async fn disk_size(&self) -> Result<u64> {
+        let mut ret = self.appender.offset();
+        for archive in self.archives.values() {
+            if archive.index != self.appender.index {
+                ret += archive.len().await?;
+            }
+        }
+        Ok(ret)
+    }
+
     fn index(&self) -> u32 {
         self.appender.index
     }
@@ -695,7 +749,9 @@ impl LogFile for LogFileLocalFs {
             #[cfg(not(feature = "enable_caching"))]
             let (cache_size, cache_ttl) = { (0, u64::MAX) };
 
-            LogFileLocalFs::new(
+            // The flipped log shares the lock of this log as it will replace it
+            LogFileLocalFs::new_ext(
+                self.lock.clone(),
                 self.temp,
                 false,
                 path_flip,
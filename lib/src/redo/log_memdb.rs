@@ -204,6 +204,10 @@ impl LogFile for LogFileMemDb {
         self.offset as u64
     }
 
+    async fn disk_size(&self) -> Result<u64> {
+        Ok(self.offset as u64)
+    }
+
     fn index(&self) -> u32 {
         0u32
     }
@@ -51,6 +51,9 @@ where
 
     fn size(&self) -> u64;
 
+    /// Number of bytes that the log occupies on disk including its archives
+    async fn disk_size(&self) -> Result<u64>;
+
     fn index(&self) -> u32;
 
     fn offset(&self) -> u64;
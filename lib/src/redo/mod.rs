@@ -11,17 +11,25 @@ mod flags;
 mod flip;
 mod loader;
 #[cfg(feature = "enable_local_fs")]
+mod lock;
+#[cfg(feature = "enable_local_fs")]
 mod log_localfs;
 mod log_memdb;
 mod log_traits;
 mod magic;
+#[cfg(feature = "enable_local_fs")]
+mod spill;
 mod test;
 
 pub use self::core::RedoLog;
 pub use api::LogWritable;
 pub use flags::OpenFlags;
 pub use loader::RedoLogLoader;
+#[cfg(feature = "enable_local_fs")]
+pub use lock::LogLockedError;
 
 pub(crate) use api::LogLookup;
+#[cfg(feature = "enable_local_fs")]
+pub(crate) use spill::SpillFile;
 
 pub use log_traits::*;
@@ -0,0 +1,248 @@
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use bytes::Bytes;
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use tokio::io::Error;
+use tokio::io::ErrorKind;
+use tokio::io::Result;
+
+use crate::conf::ConfAte;
+use crate::crypto::AteHash;
+use crate::event::EventHeaderRaw;
+use crate::spec::MessageFormat;
+use crate::time::ChainTimestamp;
+use crate::trust::ChainKey;
+
+/// Number of index records that are written to disk as a single block
+pub(crate) const SPILL_BLOCK_RECORDS: usize = 256;
+
+/// Number of blocks that are read back from disk and kept in memory
+pub(crate) const SPILL_CACHE_BLOCKS: usize = 64;
+
+/// Entry in the history index of a chain
+pub(crate) type SpillRecord = (ChainTimestamp, EventHeaderRaw);
+
+#[derive(Serialize, Deserialize)]
+struct SpillRecordRaw {
+    timestamp: ChainTimestamp,
+    meta_hash: AteHash,
+    meta_bytes: Vec<u8>,
+    data_hash: Option<AteHash>,
+    data_size: usize,
+    event_hash: AteHash,
+    format: MessageFormat,
+}
+
+impl SpillRecordRaw {
+    fn new(record: &SpillRecord) -> SpillRecordRaw {
+        SpillRecordRaw {
+            timestamp: record.0,
+            meta_hash: record.1.meta_hash,
+            meta_bytes: record.1.meta_bytes.to_vec(),
+            data_hash: record.1.data_hash,
+            data_size: record.1.data_size,
+            event_hash: record.1.event_hash,
+            format: record.1.format,
+        }
+    }
+
+    fn into_record(self) -> SpillRecord {
+        (
+            self.timestamp,
+            EventHeaderRaw {
+                meta_hash: self.meta_hash,
+                meta_bytes: Bytes::from(self.meta_bytes),
+                data_hash: self.data_hash,
+                data_size: self.data_size,
+                event_hash: self.event_hash,
+                format: self.format,
+            },
+        )
+    }
+}
+
+/// Location of a block of index records within the spill file
+#[derive(Debug, Clone)]
+pub(crate) struct SpillBlock {
+    pub(crate) first: ChainTimestamp,
+    pub(crate) last: ChainTimestamp,
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+}
+
+#[derive(Default)]
+struct SpillCache {
+    blocks: FxHashMap<usize, Arc<Vec<SpillRecord>>>,
+    order: VecDeque<usize>,
+}
+
+impl SpillCache {
+    fn get(&mut self, idx: usize) -> Option<Arc<Vec<SpillRecord>>> {
+        let ret = self.blocks.get(&idx).map(Arc::clone)?;
+        self.order.retain(|a| *a != idx);
+        self.order.push_back(idx);
+        Some(ret)
+    }
+
+    fn insert(&mut self, idx: usize, block: Arc<Vec<SpillRecord>>) {
+        while self.order.len() >= SPILL_CACHE_BLOCKS {
+            if let Some(old) = self.order.pop_front() {
+                self.blocks.remove(&old);
+            }
+        }
+        self.blocks.insert(idx, block);
+        self.order.push_back(idx);
+    }
+}
+
+/// On-disk tail of the history index of a chain. Records are appended in
+/// timestamp order in blocks, only the location of each block is kept in
+/// memory along with a small LRU cache of the blocks that were read back.
+/// The file is derived from the redo log and hence is deleted when dropped.
+pub(crate) struct SpillFile {
+    path: String,
+    file: StdMutex<File>,
+    cache: StdMutex<SpillCache>,
+    blocks: Vec<SpillBlock>,
+    end: u64,
+    count: usize,
+}
+
+impl SpillFile {
+    /// Determines where the spilled index of a chain will be stored, its
+    /// next to the redo log when there is one otherwise in the temp folder
+    pub(crate) fn prefix_for(cfg: &ConfAte, key: &ChainKey, temporal: bool) -> String {
+        let mut key_name = key.name.clone();
+        if key_name.starts_with("/") {
+            key_name = key_name[1..].to_string();
+        }
+
+        match (temporal, cfg.log_path.as_ref()) {
+            (false, Some(a)) if a.ends_with("/") => format!("{}{}", a, key_name),
+            (false, Some(a)) => format!("{}/{}", a, key_name),
+            _ => {
+                let key_name = key_name.replace("/", "_");
+                format!("{}/ate-{}", std::env::temp_dir().display(), key_name)
+            }
+        }
+    }
+
+    /// Creates a new (empty) spill file, every file gets a unique name so that
+    /// a compaction can build a new index while the old one is still in use
+    pub(crate) fn create(prefix: &str) -> Result<SpillFile> {
+        let path = format!("{}.{:x}.idx", prefix, fastrand::u64(..));
+        trace!("index-spill-path: {}", path);
+        if let Some(parent) = std::path::Path::new(&path).parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+
+        Ok(SpillFile {
+            path,
+            file: StdMutex::new(file),
+            cache: StdMutex::new(SpillCache::default()),
+            blocks: Vec::new(),
+            end: 0,
+            count: 0,
+        })
+    }
+
+    /// Appends records to the end of the file, they must be sorted and must
+    /// not be older than the records that were already spilled
+    pub(crate) fn append(&mut self, records: Vec<SpillRecord>) -> Result<()> {
+        if let (Some(last), Some(first)) = (self.last(), records.first()) {
+            if first.0 < last {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "spilled index records must be appended in order",
+                ));
+            }
+        }
+
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(self.end))?;
+        for chunk in records.chunks(SPILL_BLOCK_RECORDS) {
+            let raw = chunk.iter().map(SpillRecordRaw::new).collect::<Vec<_>>();
+            let data = bincode::serialize(&raw)
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+            file.write_all(&data[..])?;
+
+            self.blocks.push(SpillBlock {
+                first: chunk.first().unwrap().0,
+                last: chunk.last().unwrap().0,
+                offset: self.end,
+                size: data.len() as u64,
+            });
+            self.end += data.len() as u64;
+            self.count += chunk.len();
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Reads a block of records (from the cache if its been read recently)
+    pub(crate) fn block(&self, idx: usize) -> Result<Arc<Vec<SpillRecord>>> {
+        if let Some(ret) = self.cache.lock().unwrap().get(idx) {
+            return Ok(ret);
+        }
+        let block = match self.blocks.get(idx) {
+            Some(a) => a,
+            None => return Err(Error::new(ErrorKind::NotFound, "spilled index block is missing")),
+        };
+
+        let mut data = vec![0u8; block.size as usize];
+        {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(block.offset))?;
+            file.read_exact(&mut data[..])?;
+        }
+        let raw: Vec<SpillRecordRaw> = bincode::deserialize(&data[..])
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+        let ret = Arc::new(raw.into_iter().map(SpillRecordRaw::into_record).collect::<Vec<_>>());
+
+        self.cache.lock().unwrap().insert(idx, Arc::clone(&ret));
+        Ok(ret)
+    }
+
+    pub(crate) fn blocks(&self) -> &[SpillBlock] {
+        &self.blocks[..]
+    }
+
+    /// Index of the first block that could hold records at or after the timestamp
+    pub(crate) fn seek(&self, from: &ChainTimestamp) -> usize {
+        self.blocks.partition_point(|a| a.last < *from)
+    }
+
+    pub(crate) fn first(&self) -> Option<ChainTimestamp> {
+        self.blocks.first().map(|a| a.first)
+    }
+
+    pub(crate) fn last(&self) -> Option<ChainTimestamp> {
+        self.blocks.last().map(|a| a.last)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.count
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            debug!("failed to remove the index spill file ({}) - {}", self.path, err);
+        }
+    }
+}
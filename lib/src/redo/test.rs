@@ -306,3 +306,97 @@ fn test_redo_log() {
         }
     });
 }
+
+// Readers only exclude writers where flock is available
+#[cfg(all(unix, feature = "enable_local_fs"))]
+#[test]
+fn test_redo_log_lock() {
+    crate::utils::bootstrap_test_env();
+
+    let rt = Runtime::new().unwrap();
+    rt.block_on(async {
+        let mock_cfg = crate::conf::tests::mock_test_config();
+        let mock_chain_key = ChainKey::default().with_temp_name("test_redo_lock".to_string());
+        let read_only = OpenFlags {
+            read_only: true,
+            ..OpenFlags::open_centralized_server()
+        };
+
+        let locked = |err: crate::error::SerializationError| match err.kind() {
+            crate::error::SerializationErrorKind::IO(err) => super::LogLockedError::from_io(err).cloned(),
+            _ => None,
+        };
+
+        {
+            // While the log is open for writing nobody else may open it
+            println!("test_redo_log_lock - opening the redo log for writing");
+            let (mut rl, _) = RedoLog::open(
+                &mock_cfg,
+                &mock_chain_key,
+                OpenFlags::create_centralized_server(),
+                Vec::new(),
+            )
+            .await
+            .expect("Failed to load the redo log");
+
+            println!("test_redo_log_lock - opening it for writing again");
+            let err = RedoLog::open(
+                &mock_cfg,
+                &mock_chain_key,
+                OpenFlags::open_centralized_server(),
+                Vec::new(),
+            )
+            .await
+            .err()
+            .and_then(locked)
+            .expect("The second writer should have been refused");
+            assert_eq!(err.pid, Some(std::process::id()));
+
+            println!("test_redo_log_lock - opening it read-only");
+            let err = RedoLog::open(&mock_cfg, &mock_chain_key, read_only, Vec::new())
+                .await
+                .err()
+                .and_then(locked)
+                .expect("The reader should have been refused");
+            assert_eq!(err.pid, Some(std::process::id()));
+
+            rl.flush().await.unwrap();
+        }
+
+        {
+            // Readers share the lock but exclude writers
+            println!("test_redo_log_lock - opening the redo log read-only twice");
+            let (_rl1, _) = RedoLog::open(&mock_cfg, &mock_chain_key, read_only, Vec::new())
+                .await
+                .expect("Failed to load the redo log");
+            let (_rl2, _) = RedoLog::open(&mock_cfg, &mock_chain_key, read_only, Vec::new())
+                .await
+                .expect("Readers should share the lock");
+
+            println!("test_redo_log_lock - opening it for writing while it is being read");
+            let err = RedoLog::open(
+                &mock_cfg,
+                &mock_chain_key,
+                OpenFlags::open_centralized_server(),
+                Vec::new(),
+            )
+            .await
+            .err()
+            .and_then(locked)
+            .expect("The writer should have been refused");
+            assert_eq!(err.pid, None);
+        }
+
+        // Once everyone has closed it the log can be written again
+        println!("test_redo_log_lock - reopening the redo log for writing");
+        let (mut rl, _) = RedoLog::open(
+            &mock_cfg,
+            &mock_chain_key,
+            OpenFlags::open_centralized_server(),
+            Vec::new(),
+        )
+        .await
+        .expect("Failed to load the redo log");
+        rl.destroy().unwrap();
+    });
+}
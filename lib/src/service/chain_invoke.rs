@@ -58,7 +58,7 @@ impl Chain {
         };
 
         // Build the command object
-        let dio = self.dio_trans(session, TransactionScope::None).await;
+        let dio = self.dio_trans(session, TransactionScope::None).await?;
         let (join_res, join_err) = {
             dio.auto_cancel();
 
@@ -11,6 +11,7 @@ use crate::event::*;
 use crate::header::*;
 use crate::service::ServiceHook;
 use crate::session::AteSession;
+use crate::session::AteSessionRestriction;
 
 use super::*;
 
@@ -44,12 +45,43 @@ impl Chain {
         self.add_generic_service(session.clone_session(), &svr)
     }
 
+    /// Adds a service whose hook may only write rows that fall within
+    /// the supplied restriction (in addition to the request and its reply)
+    pub fn add_restricted_service<CTX, REQ, RES, ERR, C, F>(
+        self: &Arc<Self>,
+        session: &'_ dyn AteSession,
+        restriction: AteSessionRestriction,
+        context: Arc<CTX>,
+        callback: C,
+    ) -> Arc<ServiceHook>
+    where
+        CTX: Send + Sync + 'static,
+        REQ: DeserializeOwned + Send + Sync + Sized + 'static,
+        RES: Serialize + Send + Sync + Sized + 'static,
+        ERR: Serialize + Send + Sync + Sized + 'static,
+        C: Fn(Arc<CTX>, REQ) -> F + Send + 'static,
+        F: Future<Output = Result<RES, ERR>> + Send + 'static,
+    {
+        let svr = ServiceHandler::new(context, callback);
+        let svr: Arc<dyn ServiceInvoker> = svr;
+        self.add_generic_service_ext(session.clone_session(), &svr, Some(restriction))
+    }
+
     pub fn add_generic_service(
         self: &Arc<Self>,
         session: Box<dyn AteSession>,
         handler: &Arc<dyn ServiceInvoker>,
     ) -> Arc<ServiceHook> {
-        let ret = Arc::new(ServiceHook::new(self, session, handler));
+        self.add_generic_service_ext(session, handler, None)
+    }
+
+    pub fn add_generic_service_ext(
+        self: &Arc<Self>,
+        session: Box<dyn AteSession>,
+        handler: &Arc<dyn ServiceInvoker>,
+        restriction: Option<AteSessionRestriction>,
+    ) -> Arc<ServiceHook> {
+        let ret = Arc::new(ServiceHook::new(self, session, handler, restriction));
 
         {
             let svr = Arc::clone(&ret);
@@ -2,7 +2,6 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use error_chain::bail;
 use fxhash::FxHashSet;
-use std::ops::Deref;
 use std::sync::{Arc, Weak};
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
@@ -22,24 +21,43 @@ use super::*;
 
 pub struct ServiceHook {
     pub session: Box<dyn AteSession>,
+    pub restriction: Option<AteSessionRestriction>,
     pub scope: TransactionScope,
     handler: Arc<dyn ServiceInvoker>,
     chain: Weak<Chain>,
 }
 
 impl ServiceHook {
+    /// Creates a hook that will process requests using the supplied session. If no
+    /// restriction is supplied then the hook is only able to reply to, delete and
+    /// attach rows to the request that it is currently processing.
     pub(crate) fn new(
         chain: &Arc<Chain>,
         session: Box<dyn AteSession>,
         handler: &Arc<dyn ServiceInvoker>,
+        restriction: Option<AteSessionRestriction>,
     ) -> ServiceHook {
         ServiceHook {
             chain: Arc::downgrade(chain),
             session,
+            restriction,
             handler: Arc::clone(handler),
             scope: TransactionScope::None,
         }
     }
+
+    /// Derives the least-privilege session used to process a particular request
+    fn scoped_session(&self, key: PrimaryKey) -> AteSessionRestricted {
+        let mut restriction = self.restriction.clone().unwrap_or_default();
+        if restriction.parent.is_none() && restriction.write_keys.is_none() {
+            restriction.parent = Some(key);
+        }
+        self.session
+            .restrict()
+            .with_restriction(restriction)
+            .with_key(key)
+            .build()
+    }
 }
 
 #[async_trait]
@@ -60,8 +78,9 @@ impl Service for ServiceHook {
             }
         };
 
-        // Build the data access layer
-        let dio = chain.dio_trans(self.session.deref(), self.scope).await;
+        // Build the data access layer using a session that is scoped to this request
+        let session = self.scoped_session(key);
+        let dio = chain.dio_trans(&session, self.scope).await?;
         dio.auto_cancel();
 
         // Lock the data row
@@ -74,7 +93,7 @@ impl Service for ServiceHook {
         let mut evt = dio.load_raw(&key).await?;
 
         // Convert the data using the encryption and decryption routines
-        dio.data_as_overlay(self.session.deref(), &mut evt)?;
+        dio.data_as_overlay(&session, &mut evt)?;
         let req = match evt.data_bytes {
             Some(a) => a,
             None => {
@@ -180,6 +199,7 @@ impl ServiceHook {
             extra_meta,
             parent: None,
             auth,
+            patch: None,
             is_new: true,
         });
         Ok(())
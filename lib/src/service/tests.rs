@@ -63,3 +63,65 @@ async fn test_service() -> Result<(), AteError> {
     info!("received pong with msg [{}]", pong.msg);
     Ok(())
 }
+
+#[test]
+fn test_service_restriction() {
+    crate::utils::bootstrap_test_env();
+
+    use crate::crypto::*;
+    use crate::header::PrimaryKey;
+    use crate::meta::*;
+
+    let write_key = PrivateSignKey::generate(KeySize::Bit192);
+    let other_key = PrivateSignKey::generate(KeySize::Bit192);
+    let parent = PrimaryKey::generate();
+
+    let session = AteSessionUser::new()
+        .restrict()
+        .with_write_key(write_key.as_public_key())
+        .with_parent(parent)
+        .build();
+    let restriction = session.restriction().expect("the session should be restricted");
+
+    let attached = MetaParent {
+        vec: MetaCollection {
+            parent_id: parent,
+            collection_id: 1,
+        },
+    };
+    let detached = MetaParent {
+        vec: MetaCollection {
+            parent_id: PrimaryKey::generate(),
+            collection_id: 1,
+        },
+    };
+
+    let mut auth = MetaAuthorization::default();
+    auth.write = WriteOption::Specific(write_key.hash());
+    assert!(restriction
+        .check_row(&PrimaryKey::generate(), Some(&attached), &auth, None)
+        .is_ok());
+    assert!(restriction
+        .check_row(&PrimaryKey::generate(), Some(&detached), &auth, None)
+        .is_err());
+    assert!(restriction
+        .check_row(&PrimaryKey::generate(), None, &auth, None)
+        .is_err());
+
+    auth.write = WriteOption::Specific(other_key.hash());
+    assert!(restriction
+        .check_row(&PrimaryKey::generate(), Some(&attached), &auth, None)
+        .is_err());
+
+    auth.write = WriteOption::Everyone;
+    assert!(restriction
+        .check_row(&PrimaryKey::generate(), Some(&attached), &auth, None)
+        .is_err());
+
+    assert!(restriction
+        .check_delete(&PrimaryKey::generate(), Some(&detached))
+        .is_err());
+    assert!(restriction
+        .check_delete(&PrimaryKey::generate(), Some(&attached))
+        .is_ok());
+}
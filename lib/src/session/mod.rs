@@ -4,6 +4,7 @@ pub mod role_purpose;
 pub mod session_group;
 pub mod session_inner;
 pub mod session_property;
+pub mod session_restriction;
 pub mod session_sudo;
 pub mod session_trait;
 pub mod session_type;
@@ -15,6 +16,7 @@ pub use role_purpose::*;
 pub use session_group::*;
 pub use session_inner::*;
 pub use session_property::*;
+pub use session_restriction::*;
 pub use session_sudo::*;
 pub use session_trait::*;
 pub use session_type::*;
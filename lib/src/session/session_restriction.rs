@@ -0,0 +1,282 @@
+#[allow(unused_imports)]
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::crypto::*;
+use crate::header::PrimaryKey;
+use crate::meta::*;
+
+use super::*;
+
+/// Restrictions limit what a derived session is allowed to write
+/// into a chain-of-trust. They are enforced locally by the `DioMut`
+/// at commit time so that events which fall outside of the restriction
+/// are rejected before they are ever transmitted.
+///
+/// An empty restriction allows everything that the inner session
+/// would otherwise allow.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AteSessionRestriction {
+    /// When set only rows protected by one of these write keys may be written
+    pub write_keys: Option<Vec<AteHash>>,
+    /// When set only rows that are attached to this parent may be written
+    pub parent: Option<PrimaryKey>,
+    /// Rows that are explicitly allowed regardless of the other rules
+    pub keys: Vec<PrimaryKey>,
+}
+
+impl AteSessionRestriction {
+    pub fn is_empty(&self) -> bool {
+        self.write_keys.is_none() && self.parent.is_none()
+    }
+
+    pub fn allows_key(&self, key: &PrimaryKey) -> bool {
+        self.keys.iter().any(|k| k == key)
+    }
+
+    /// Checks if a row that is about to be written falls within this restriction
+    pub fn check_row(
+        &self,
+        key: &PrimaryKey,
+        parent: Option<&MetaParent>,
+        auth: &MetaAuthorization,
+        reply_to: Option<&PrimaryKey>,
+    ) -> Result<(), String> {
+        if self.allows_key(key) {
+            return Ok(());
+        }
+        if let Some(reply_to) = reply_to {
+            if self.allows_key(reply_to) {
+                return Ok(());
+            }
+        }
+        if let Some(write_keys) = &self.write_keys {
+            match &auth.write {
+                WriteOption::Inherit => {}
+                WriteOption::Specific(hash) => {
+                    if write_keys.contains(hash) == false {
+                        return Err(format!(
+                            "row {} is protected by write key {} which is outside the session restriction",
+                            key, hash
+                        ));
+                    }
+                }
+                WriteOption::Any(hashes) => {
+                    if let Some(hash) = hashes.iter().filter(|h| write_keys.contains(h) == false).next() {
+                        return Err(format!(
+                            "row {} is protected by write key {} which is outside the session restriction",
+                            key, hash
+                        ));
+                    }
+                }
+                a => {
+                    return Err(format!(
+                        "row {} has a write option ({}) that is not allowed by the session restriction",
+                        key, a
+                    ));
+                }
+            }
+        }
+        self.check_parent(key, parent)
+    }
+
+    /// Checks if a row that is about to be deleted falls within this restriction
+    pub fn check_delete(&self, key: &PrimaryKey, parent: Option<&MetaParent>) -> Result<(), String> {
+        if self.allows_key(key) {
+            return Ok(());
+        }
+        self.check_parent(key, parent)
+    }
+
+    fn check_parent(&self, key: &PrimaryKey, parent: Option<&MetaParent>) -> Result<(), String> {
+        if let Some(restrict) = &self.parent {
+            match parent {
+                Some(parent) if parent.vec.parent_id == *restrict => {}
+                Some(parent) => {
+                    return Err(format!(
+                        "row {} is attached to parent {} which is outside the session restriction",
+                        key, parent.vec.parent_id
+                    ));
+                }
+                None if key == restrict => {}
+                None => {
+                    return Err(format!(
+                        "row {} has no parent however the session is restricted to parent {}",
+                        key, restrict
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builder that produces a derived session which is limited to specific
+/// write keys and/or a particular parent collection
+pub struct AteSessionRestrictionBuilder {
+    inner: Box<dyn AteSession>,
+    restriction: AteSessionRestriction,
+}
+
+impl AteSessionRestrictionBuilder {
+    pub fn new(inner: Box<dyn AteSession>) -> AteSessionRestrictionBuilder {
+        AteSessionRestrictionBuilder {
+            inner,
+            restriction: AteSessionRestriction::default(),
+        }
+    }
+
+    pub fn with_restriction(mut self, restriction: AteSessionRestriction) -> Self {
+        self.restriction = restriction;
+        self
+    }
+
+    pub fn with_write_key(mut self, key: &PublicSignKey) -> Self {
+        self.restriction
+            .write_keys
+            .get_or_insert_with(|| Vec::new())
+            .push(key.hash());
+        self
+    }
+
+    pub fn with_parent(mut self, parent: PrimaryKey) -> Self {
+        self.restriction.parent = Some(parent);
+        self
+    }
+
+    pub fn with_key(mut self, key: PrimaryKey) -> Self {
+        self.restriction.keys.push(key);
+        self
+    }
+
+    pub fn build(self) -> AteSessionRestricted {
+        AteSessionRestricted {
+            inner: self.inner,
+            restriction: self.restriction,
+        }
+    }
+}
+
+/// Restricted sessions wrap another session and limit the rows that
+/// it may write to based on an `AteSessionRestriction`
+///
+/// Sessions are never cached and only exist in memory for the
+/// duration that you use them for security reasons.
+pub struct AteSessionRestricted {
+    pub inner: Box<dyn AteSession>,
+    pub restriction: AteSessionRestriction,
+}
+
+impl Clone for AteSessionRestricted {
+    fn clone(&self) -> Self {
+        AteSessionRestricted {
+            inner: self.inner.clone_session(),
+            restriction: self.restriction.clone(),
+        }
+    }
+}
+
+impl AteSession for AteSessionRestricted {
+    fn role<'a>(&'a self, purpose: &AteRolePurpose) -> Option<&'a AteGroupRole> {
+        self.inner.role(purpose)
+    }
+
+    fn read_keys<'a>(
+        &'a self,
+        category: AteSessionKeyCategory,
+    ) -> Box<dyn Iterator<Item = &'a EncryptKey> + 'a> {
+        self.inner.read_keys(category)
+    }
+
+    fn write_keys<'a>(
+        &'a self,
+        category: AteSessionKeyCategory,
+    ) -> Box<dyn Iterator<Item = &'a PrivateSignKey> + 'a> {
+        let ret = self.inner.write_keys(category);
+        match &self.restriction.write_keys {
+            Some(allowed) => Box::new(ret.filter(move |k| allowed.contains(&k.hash()))),
+            None => ret,
+        }
+    }
+
+    fn public_read_keys<'a>(
+        &'a self,
+        category: AteSessionKeyCategory,
+    ) -> Box<dyn Iterator<Item = &'a PublicEncryptKey> + 'a> {
+        self.inner.public_read_keys(category)
+    }
+
+    fn private_read_keys<'a>(
+        &'a self,
+        category: AteSessionKeyCategory,
+    ) -> Box<dyn Iterator<Item = &'a PrivateEncryptKey> + 'a> {
+        self.inner.private_read_keys(category)
+    }
+
+    fn broker_read<'a>(&'a self) -> Option<&'a PrivateEncryptKey> {
+        self.inner.broker_read()
+    }
+
+    fn broker_write<'a>(&'a self) -> Option<&'a PrivateSignKey> {
+        let ret = self.inner.broker_write();
+        match &self.restriction.write_keys {
+            Some(allowed) => ret.filter(|k| allowed.contains(&k.hash())),
+            None => ret,
+        }
+    }
+
+    fn identity<'a>(&'a self) -> &'a str {
+        self.inner.identity()
+    }
+
+    fn user<'a>(&'a self) -> &'a AteSessionUser {
+        self.inner.user()
+    }
+
+    fn user_mut<'a>(&'a mut self) -> &'a mut AteSessionUser {
+        self.inner.user_mut()
+    }
+
+    fn uid<'a>(&'a self) -> Option<u32> {
+        self.inner.uid()
+    }
+
+    fn gid<'a>(&'a self) -> Option<u32> {
+        self.inner.gid()
+    }
+
+    fn properties<'a>(&'a self) -> Box<dyn Iterator<Item = &'a AteSessionProperty> + 'a> {
+        self.inner.properties()
+    }
+
+    fn append<'a, 'b>(
+        &'a mut self,
+        properties: Box<dyn Iterator<Item = &'b AteSessionProperty> + 'b>,
+    ) {
+        self.inner.append(properties);
+    }
+
+    fn clone_session(&self) -> Box<dyn AteSession> {
+        Box::new(self.clone())
+    }
+
+    fn clone_inner(&self) -> AteSessionInner {
+        self.inner.clone_inner()
+    }
+
+    fn restriction<'a>(&'a self) -> Option<&'a AteSessionRestriction> {
+        Some(&self.restriction)
+    }
+}
+
+impl std::fmt::Display for AteSessionRestricted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[restricted={}", self.inner)?;
+        if let Some(parent) = &self.restriction.parent {
+            write!(f, ",parent={}", parent)?;
+        }
+        if let Some(write_keys) = &self.restriction.write_keys {
+            write!(f, ",write_keys={}", write_keys.len())?;
+        }
+        write!(f, "]")
+    }
+}
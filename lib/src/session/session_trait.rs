@@ -8,6 +8,9 @@ use super::AteRolePurpose;
 use super::AteSessionGroup;
 use super::AteSessionInner;
 use super::AteSessionProperty;
+use super::AteSessionRestricted;
+use super::AteSessionRestriction;
+use super::AteSessionRestrictionBuilder;
 use super::AteSessionType;
 use crate::crypto::*;
 
@@ -75,6 +78,17 @@ pub trait AteSession: Send + Sync + std::fmt::Display {
     fn clone_session(&self) -> Box<dyn AteSession>;
 
     fn clone_inner(&self) -> AteSessionInner;
+
+    /// Returns the restriction that limits what this session may write (if any)
+    fn restriction<'a>(&'a self) -> Option<&'a AteSessionRestriction> {
+        None
+    }
+
+    /// Starts building a derived session that is limited to specific
+    /// write keys and/or a parent collection
+    fn restrict(&self) -> AteSessionRestrictionBuilder {
+        AteSessionRestrictionBuilder::new(self.clone_session())
+    }
 }
 
 impl std::fmt::Debug
@@ -109,6 +123,12 @@ impl From<AteSessionInner> for Box<dyn AteSession> {
     }
 }
 
+impl From<AteSessionRestricted> for Box<dyn AteSession> {
+    fn from(session: AteSessionRestricted) -> Self {
+        Box::new(session)
+    }
+}
+
 impl From<AteSessionType> for Box<dyn AteSession> {
     fn from(session: AteSessionType) -> Self {
         Box::new(session)
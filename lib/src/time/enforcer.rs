@@ -98,6 +98,10 @@ impl EventValidator for TimestampEnforcer {
         Box::new(self.clone())
     }
 
+    fn set_replay_clock(&mut self, clock: Option<super::ReplayClock>) {
+        self.keeper.replay = clock;
+    }
+
     fn validate(
         &self,
         header: &EventHeader,
@@ -7,15 +7,36 @@ use crate::error::*;
 #[cfg(feature = "enable_ntp")]
 use super::worker::NtpWorker;
 use super::ChainTimestamp;
-#[cfg(feature = "enable_ntp")]
 use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::time::Duration;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
+/// Clock that is driven by the timestamps of the events that are being
+/// replayed (rather than the wall clock) so that a replay is deterministic
+#[derive(Debug, Clone, Default)]
+pub struct ReplayClock {
+    now_ms: Arc<AtomicU64>,
+}
+
+impl ReplayClock {
+    /// Moves the clock forward to the time of an event (it never goes back)
+    pub fn advance(&self, time_since_epoch_ms: u64) {
+        self.now_ms.fetch_max(time_since_epoch_ms, Ordering::AcqRel);
+    }
+
+    pub fn now(&self) -> ChainTimestamp {
+        ChainTimestamp::from(self.now_ms.load(Ordering::Acquire))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TimeKeeper {
     pub tolerance: Duration,
+    /// When set the time is read from the replay rather than the wall clock
+    pub(crate) replay: Option<ReplayClock>,
     #[cfg(feature = "enable_ntp")]
     pub ntp_pool: String,
     #[cfg(feature = "enable_ntp")]
@@ -31,6 +52,7 @@ impl TimeKeeper {
         let tolerance = Duration::from_millis(tolerance_ms as u64);
         Ok(TimeKeeper {
             tolerance: tolerance,
+            replay: None,
             #[cfg(feature = "enable_ntp")]
             ntp_pool: cfg.ntp_pool.clone(),
             #[cfg(feature = "enable_ntp")]
@@ -51,6 +73,9 @@ impl TimeKeeper {
     }
 
     pub fn has_converged(&self) -> bool {
+        if self.replay.is_some() {
+            return true;
+        }
         #[cfg(feature = "enable_ntp")]
         if let Some(worker) = &self.ntp_worker {
             return worker.is_accurate();
@@ -59,6 +84,9 @@ impl TimeKeeper {
     }
 
     pub fn current_timestamp_as_duration(&self) -> Result<Duration, TimeError> {
+        if let Some(replay) = &self.replay {
+            return Ok(Duration::from_millis(replay.now().time_since_epoch_ms));
+        }
         #[cfg(not(feature = "enable_ntp"))]
         {
             let start = SystemTime::now();
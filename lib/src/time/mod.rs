@@ -7,6 +7,7 @@ mod timestamp;
 mod worker;
 
 pub use enforcer::TimestampEnforcer;
+pub use keeper::ReplayClock;
 pub use keeper::TimeKeeper;
 pub use timestamp::ChainTimestamp;
 #[cfg(feature = "enable_ntp")]
@@ -67,6 +67,8 @@ pub(crate) struct Transaction {
     pub(crate) events: Vec<EventWeakData>,
     pub(crate) timeout: Duration,
     pub(crate) conversation: Option<Arc<ConversationSession>>,
+    // Cache maintenance for these events may be deferred when under load
+    pub(crate) deferrable: bool,
 }
 
 impl Transaction {
@@ -83,6 +85,7 @@ impl Transaction {
             events,
             timeout,
             conversation: None,
+            deferrable: false,
         }
     }
 }
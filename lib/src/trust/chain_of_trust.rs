@@ -92,6 +92,13 @@ impl<'a> ChainOfTrust {
         self.timeline.lookup_secondary_raw(key)
     }
 
+    pub(crate) fn lookup_collection_policy(
+        &self,
+        key: &MetaCollection,
+    ) -> Option<MetaCollectionPolicy> {
+        self.timeline.lookup_collection_policy(key)
+    }
+
     pub(crate) fn roots_raw(&self) -> Vec<PrimaryKey> {
         self.timeline.roots_raw()
     }
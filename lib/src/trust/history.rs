@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::ops::Bound;
+use std::ops::RangeBounds;
+#[cfg(feature = "enable_local_fs")]
+use std::sync::Arc;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::conf::ConfAte;
+use crate::event::*;
+#[cfg(feature = "enable_local_fs")]
+use crate::redo::SpillFile;
+use crate::time::*;
+
+use super::ChainKey;
+
+/// Settings that allow the cold tail of the history to be moved to disk
+#[cfg(feature = "enable_local_fs")]
+struct HistorySpill {
+    max_memory: usize,
+    prefix: String,
+    file: Option<SpillFile>,
+}
+
+/// # Chain History
+///
+/// Index of every event in the chain ordered by its timestamp. By default
+/// the whole index is held in memory however when the `max_index_memory`
+/// limit is configured the oldest part of the index is moved to an index
+/// file on disk once the limit is exceeded, leaving a window of the most
+/// recent events in memory. Ranges that reach into the older events read
+/// them back from disk transparently (with recently read blocks cached).
+pub(crate) struct ChainHistory {
+    hot: BTreeMap<ChainTimestamp, Vec<EventHeaderRaw>>,
+    hot_len: usize,
+    hot_bytes: usize,
+    #[cfg(feature = "enable_local_fs")]
+    spill: Option<HistorySpill>,
+}
+
+impl Default for ChainHistory {
+    fn default() -> ChainHistory {
+        ChainHistory {
+            hot: BTreeMap::new(),
+            hot_len: 0,
+            hot_bytes: 0,
+            #[cfg(feature = "enable_local_fs")]
+            spill: None,
+        }
+    }
+}
+
+/// Reports how much of the history index of a chain is held in memory
+/// and how much of it has been moved to disk
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexUsage {
+    /// Number of events that are indexed in memory
+    pub events_in_memory: usize,
+    /// Number of events that have been moved to the index file on disk
+    pub events_spilled: usize,
+    /// Approximate amount of memory used by the in-memory part of the index
+    pub memory_bytes: usize,
+}
+
+/// Approximate amount of memory an entry in the history uses
+fn footprint(raw: &EventHeaderRaw) -> usize {
+    std::mem::size_of::<ChainTimestamp>()
+        + std::mem::size_of::<EventHeaderRaw>()
+        + raw.meta_bytes.len()
+}
+
+impl ChainHistory {
+    /// Creates a history that will spill to disk if the configuration
+    /// limits the memory used by the indexes
+    #[allow(unused_variables)]
+    pub(crate) fn new(cfg: &ConfAte, key: &ChainKey, temporal: bool) -> ChainHistory {
+        #[allow(unused_mut)]
+        let mut ret = ChainHistory::default();
+        #[cfg(feature = "enable_local_fs")]
+        if let Some(max_memory) = cfg.max_index_memory {
+            ret.spill = Some(HistorySpill {
+                max_memory,
+                prefix: SpillFile::prefix_for(cfg, key, temporal),
+                file: None,
+            });
+        }
+        ret
+    }
+
+    /// Creates an empty history with the same memory limits as this one
+    pub(crate) fn fresh(&self) -> ChainHistory {
+        #[allow(unused_mut)]
+        let mut ret = ChainHistory::default();
+        #[cfg(feature = "enable_local_fs")]
+        if let Some(spill) = self.spill.as_ref() {
+            ret.spill = Some(HistorySpill {
+                max_memory: spill.max_memory,
+                prefix: spill.prefix.clone(),
+                file: None,
+            });
+        }
+        ret
+    }
+
+    pub(crate) fn insert(&mut self, timestamp: ChainTimestamp, raw: EventHeaderRaw) {
+        self.hot_bytes += footprint(&raw);
+        self.hot_len += 1;
+        self.hot.entry(timestamp).or_insert_with(Vec::new).push(raw);
+
+        #[cfg(feature = "enable_local_fs")]
+        if let Some(max_memory) = self.spill.as_ref().map(|a| a.max_memory) {
+            if self.hot_bytes > max_memory {
+                self.spill_cold(max_memory / 2);
+            }
+        }
+    }
+
+    /// Moves the oldest events to the spill file until the memory used by
+    /// the history is below the target. Events that are older than what was
+    /// already spilled (late arrivals) stay in memory so the file is sorted.
+    #[cfg(feature = "enable_local_fs")]
+    fn spill_cold(&mut self, target: usize) {
+        let spill = match self.spill.as_mut() {
+            Some(a) => a,
+            None => return,
+        };
+        if spill.file.is_none() {
+            match SpillFile::create(spill.prefix.as_str()) {
+                Ok(a) => spill.file = Some(a),
+                Err(err) => {
+                    warn!("failed to create the index spill file, keeping the history in memory - {}", err);
+                    spill.max_memory = usize::MAX;
+                    return;
+                }
+            }
+        }
+        let file = spill.file.as_mut().unwrap();
+        let cold_last = file.last();
+
+        let mut keys = Vec::new();
+        let mut freed = 0usize;
+        for (k, v) in self.hot.iter() {
+            if self.hot_bytes - freed <= target {
+                break;
+            }
+            if let Some(cold_last) = cold_last.as_ref() {
+                if k < cold_last {
+                    continue;
+                }
+            }
+            freed += v.iter().map(footprint).sum::<usize>();
+            keys.push(k.clone());
+        }
+        if keys.is_empty() {
+            return;
+        }
+
+        let records = keys
+            .iter()
+            .flat_map(|k| {
+                self.hot
+                    .get(k)
+                    .into_iter()
+                    .flat_map(move |v| v.iter().map(move |a| (k.clone(), a.clone())))
+            })
+            .collect::<Vec<_>>();
+        let cnt = records.len();
+
+        if let Err(err) = file.append(records) {
+            warn!("failed to spill the history index to disk, keeping it in memory - {}", err);
+            spill.max_memory = usize::MAX;
+            return;
+        }
+        for k in keys {
+            self.hot.remove(&k);
+        }
+        self.hot_len -= cnt;
+        self.hot_bytes -= freed;
+        trace!("spilled {} history entries to disk ({} remain in memory)", cnt, self.hot_len);
+    }
+
+    #[cfg(feature = "enable_local_fs")]
+    fn cold(&self) -> Option<&SpillFile> {
+        self.spill.as_ref().and_then(|a| a.file.as_ref())
+    }
+
+    /// Returns all the events within the range in timestamp order (events
+    /// with the same timestamp are returned in the order they were added)
+    pub(crate) fn range<'a, R>(&'a self, range: R) -> HistoryRange<'a>
+    where
+        R: RangeBounds<ChainTimestamp>,
+    {
+        let start = range.start_bound().cloned();
+        let end = range.end_bound().cloned();
+
+        let hot: Box<dyn Iterator<Item = (ChainTimestamp, EventHeaderRaw)> + 'a> = Box::new(
+            self.hot
+                .range((start.clone(), end.clone()))
+                .flat_map(|(k, v)| v.iter().map(move |a| (k.clone(), a.clone()))),
+        );
+
+        HistoryRange {
+            hot: hot.peekable(),
+            #[cfg(feature = "enable_local_fs")]
+            cold: self.cold().map(|file| ColdRange::new(file, start, end)),
+        }
+    }
+
+    pub(crate) fn iter<'a>(&'a self) -> HistoryRange<'a> {
+        self.range(..)
+    }
+
+    pub(crate) fn first_key(&self) -> Option<ChainTimestamp> {
+        let hot = self.hot.keys().next().cloned();
+        #[cfg(feature = "enable_local_fs")]
+        if let Some(cold) = self.cold().and_then(|a| a.first()) {
+            return Some(hot.map_or(cold, |a| a.min(cold)));
+        }
+        hot
+    }
+
+    pub(crate) fn last_key(&self) -> Option<ChainTimestamp> {
+        let hot = self.hot.keys().next_back().cloned();
+        #[cfg(feature = "enable_local_fs")]
+        if let Some(cold) = self.cold().and_then(|a| a.last()) {
+            return Some(hot.map_or(cold, |a| a.max(cold)));
+        }
+        hot
+    }
+
+    /// Number of events in the history (both in memory and on disk)
+    #[allow(dead_code)]
+    pub(crate) fn len(&self) -> usize {
+        self.hot_len + self.usage().events_spilled
+    }
+
+    pub(crate) fn usage(&self) -> IndexUsage {
+        #[allow(unused_mut)]
+        let mut ret = IndexUsage {
+            events_in_memory: self.hot_len,
+            events_spilled: 0,
+            memory_bytes: self.hot_bytes,
+        };
+        #[cfg(feature = "enable_local_fs")]
+        if let Some(cold) = self.cold() {
+            ret.events_spilled = cold.len();
+        }
+        ret
+    }
+}
+
+/// Reads the events in a range from the spill file one block at a time
+#[cfg(feature = "enable_local_fs")]
+struct ColdRange<'a> {
+    file: &'a SpillFile,
+    end: Bound<ChainTimestamp>,
+    start: Bound<ChainTimestamp>,
+    next_block: usize,
+    block: Option<Arc<Vec<(ChainTimestamp, EventHeaderRaw)>>>,
+    pos: usize,
+    peeked: Option<(ChainTimestamp, EventHeaderRaw)>,
+    done: bool,
+}
+
+#[cfg(feature = "enable_local_fs")]
+impl<'a> ColdRange<'a> {
+    fn new(file: &'a SpillFile, start: Bound<ChainTimestamp>, end: Bound<ChainTimestamp>) -> ColdRange<'a> {
+        let next_block = match &start {
+            Bound::Included(a) | Bound::Excluded(a) => file.seek(a),
+            Bound::Unbounded => 0,
+        };
+        ColdRange {
+            file,
+            end,
+            start,
+            next_block,
+            block: None,
+            pos: 0,
+            peeked: None,
+            done: false,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&(ChainTimestamp, EventHeaderRaw)> {
+        if self.peeked.is_none() {
+            self.peeked = self.advance();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn take(&mut self) -> Option<(ChainTimestamp, EventHeaderRaw)> {
+        self.peek();
+        self.peeked.take()
+    }
+
+    fn advance(&mut self) -> Option<(ChainTimestamp, EventHeaderRaw)> {
+        while self.done == false {
+            if let Some(block) = self.block.as_ref() {
+                if let Some(record) = block.get(self.pos) {
+                    self.pos += 1;
+                    if after_start(&self.start, &record.0) == false {
+                        continue;
+                    }
+                    if before_end(&self.end, &record.0) == false {
+                        self.done = true;
+                        break;
+                    }
+                    return Some(record.clone());
+                }
+            }
+
+            if self.next_block >= self.file.blocks().len() {
+                self.done = true;
+                break;
+            }
+            match self.file.block(self.next_block) {
+                Ok(block) => {
+                    self.block = Some(block);
+                    self.pos = 0;
+                    self.next_block += 1;
+                }
+                Err(err) => {
+                    error!("failed to read the history index from disk - {}", err);
+                    self.done = true;
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(feature = "enable_local_fs")]
+fn after_start(start: &Bound<ChainTimestamp>, val: &ChainTimestamp) -> bool {
+    match start {
+        Bound::Included(a) => val >= a,
+        Bound::Excluded(a) => val > a,
+        Bound::Unbounded => true,
+    }
+}
+
+#[cfg(feature = "enable_local_fs")]
+fn before_end(end: &Bound<ChainTimestamp>, val: &ChainTimestamp) -> bool {
+    match end {
+        Bound::Included(a) => val <= a,
+        Bound::Excluded(a) => val < a,
+        Bound::Unbounded => true,
+    }
+}
+
+/// Iterator over a range of the history that merges the events that are
+/// held in memory with those that were moved to disk
+pub(crate) struct HistoryRange<'a> {
+    hot: Peekable<Box<dyn Iterator<Item = (ChainTimestamp, EventHeaderRaw)> + 'a>>,
+    #[cfg(feature = "enable_local_fs")]
+    cold: Option<ColdRange<'a>>,
+}
+
+impl<'a> Iterator for HistoryRange<'a> {
+    type Item = (ChainTimestamp, EventHeaderRaw);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        #[cfg(feature = "enable_local_fs")]
+        if let Some(cold) = self.cold.as_mut() {
+            let use_cold = match (cold.peek(), self.hot.peek()) {
+                (Some(c), Some(h)) => c.0 <= h.0,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            if use_cold {
+                return cold.take();
+            }
+        }
+        self.hot.next()
+    }
+}
@@ -0,0 +1,234 @@
+use error_chain::bail;
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::crypto::AteHash;
+use crate::crypto::PrivateSignKey;
+use crate::crypto::PublicSignKey;
+use crate::error::*;
+
+use super::ChainHistory;
+use super::ChainKey;
+
+/// Domain separation prefixes so that a leaf can never be passed off as
+/// an interior node of the tree (and vice versa)
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Head of the hash tree over the history of a chain at a particular size
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RootHead {
+    /// Name of the chain the tree was built from
+    pub chain: String,
+    /// Number of events (leaves) covered by the tree
+    pub tree_size: u64,
+    /// Hash at the root of the tree
+    pub root_hash: AteHash,
+    /// Time (milliseconds since the epoch) when the head was checkpointed
+    pub timestamp: u64,
+}
+
+impl RootHead {
+    /// Bytes that are signed, the layout is fixed so that heads remain
+    /// verifiable regardless of the serialization format they travel in
+    fn signing_bytes(&self) -> Vec<u8> {
+        let mut ret = Vec::new();
+        ret.extend_from_slice(b"ate-root-head-v1");
+        ret.extend_from_slice(&(self.chain.len() as u64).to_be_bytes());
+        ret.extend_from_slice(self.chain.as_bytes());
+        ret.extend_from_slice(&self.tree_size.to_be_bytes());
+        ret.extend_from_slice(&self.root_hash.val[..]);
+        ret.extend_from_slice(&self.timestamp.to_be_bytes());
+        ret
+    }
+}
+
+/// Root head that has been signed by the server that hosts the chain
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SignedRootHead {
+    pub head: RootHead,
+    /// Hash of the public key that verifies the signature
+    pub public_key_hash: AteHash,
+    pub signature: Vec<u8>,
+}
+
+impl SignedRootHead {
+    pub fn sign(head: RootHead, key: &PrivateSignKey) -> Result<SignedRootHead, InclusionError> {
+        let signature = key
+            .sign(&head.signing_bytes()[..])
+            .map_err(|err| InclusionErrorKind::SigningFailed(err.to_string()))?;
+        Ok(SignedRootHead {
+            head,
+            public_key_hash: key.hash(),
+            signature,
+        })
+    }
+
+    /// Checks that the head was signed by one of the trusted public keys
+    pub fn verify(&self, public_keys: &[PublicSignKey]) -> Result<(), InclusionError> {
+        let key = match public_keys
+            .iter()
+            .find(|k| k.hash() == self.public_key_hash)
+        {
+            Some(a) => a,
+            None => bail!(InclusionErrorKind::UntrustedRootHead),
+        };
+        match key.verify(&self.head.signing_bytes()[..], &self.signature[..]) {
+            Ok(true) => Ok(()),
+            _ => bail!(InclusionErrorKind::UntrustedRootHead),
+        }
+    }
+}
+
+/// Proves that a single event is part of the history of a chain without
+/// needing the rest of the chain.
+///
+/// The proof holds the sibling hashes on the path from the leaf of the event
+/// up to the root of the tree (RFC 6962 style) plus the signed root head it
+/// was generated against. A tree of `n` events needs `ceil(log2(n))` hashes
+/// of 16 bytes each (e.g. 320 bytes for a million events) plus the signature
+/// of the head (~0.7KB for Falcon512, ~1.3KB for Falcon1024). Verifying it
+/// takes the same number of hash operations and a single signature check.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+    pub event_hash: AteHash,
+    /// Position of the event in the history of the chain
+    pub leaf_index: u64,
+    /// Sibling hashes from the leaf up to the root
+    pub path: Vec<AteHash>,
+    pub root_head: SignedRootHead,
+}
+
+/// Verifies an inclusion proof against a root head that the caller already
+/// trusts (e.g. one that was published by the server), this does not need
+/// access to the chain itself
+pub fn verify_inclusion(
+    proof: &InclusionProof,
+    trusted_root_head: &SignedRootHead,
+    public_keys: &[PublicSignKey],
+) -> Result<(), InclusionError> {
+    trusted_root_head.verify(public_keys)?;
+    if proof.root_head.head != trusted_root_head.head {
+        bail!(InclusionErrorKind::RootHeadMismatch);
+    }
+
+    let head = &trusted_root_head.head;
+    let root = root_from_path(
+        leaf_hash(&proof.event_hash),
+        proof.leaf_index,
+        head.tree_size,
+        &proof.path[..],
+    )?;
+    if root != head.root_hash {
+        bail!(InclusionErrorKind::InvalidProof(
+            "the path does not lead to the root hash".to_string()
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn leaf_hash(event_hash: &AteHash) -> AteHash {
+    let mut data = [0u8; 1 + AteHash::LEN];
+    data[0] = LEAF_PREFIX;
+    data[1..].copy_from_slice(&event_hash.val[..]);
+    AteHash::from_bytes(&data[..])
+}
+
+fn node_hash(left: &AteHash, right: &AteHash) -> AteHash {
+    let mut data = [0u8; 1 + 2 * AteHash::LEN];
+    data[0] = NODE_PREFIX;
+    data[1..1 + AteHash::LEN].copy_from_slice(&left.val[..]);
+    data[1 + AteHash::LEN..].copy_from_slice(&right.val[..]);
+    AteHash::from_bytes(&data[..])
+}
+
+/// Largest power of two that is smaller than `n` (where `n` > 1)
+fn split(n: usize) -> usize {
+    let mut k = 1usize;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+/// Leaves of the hash tree in the order of the history of the chain
+pub(crate) fn history_leaves(history: &ChainHistory) -> Vec<AteHash> {
+    history.iter().map(|(_, h)| leaf_hash(&h.event_hash)).collect()
+}
+
+pub(crate) fn tree_root(leaves: &[AteHash]) -> AteHash {
+    match leaves.len() {
+        0 => AteHash::from_bytes(&[]),
+        1 => leaves[0],
+        n => {
+            let k = split(n);
+            node_hash(&tree_root(&leaves[..k]), &tree_root(&leaves[k..]))
+        }
+    }
+}
+
+pub(crate) fn tree_path(index: usize, leaves: &[AteHash]) -> Vec<AteHash> {
+    let n = leaves.len();
+    if n <= 1 {
+        return Vec::new();
+    }
+    let k = split(n);
+    let (mut ret, sibling) = match index < k {
+        true => (tree_path(index, &leaves[..k]), tree_root(&leaves[k..])),
+        false => (tree_path(index - k, &leaves[k..]), tree_root(&leaves[..k])),
+    };
+    ret.push(sibling);
+    ret
+}
+
+fn root_from_path(
+    leaf: AteHash,
+    index: u64,
+    tree_size: u64,
+    path: &[AteHash],
+) -> Result<AteHash, InclusionError> {
+    if index >= tree_size {
+        bail!(InclusionErrorKind::InvalidProof(
+            "the leaf index is outside of the tree".to_string()
+        ));
+    }
+
+    let mut f = index;
+    let mut s = tree_size - 1;
+    let mut ret = leaf;
+    for sibling in path {
+        if s == 0 {
+            bail!(InclusionErrorKind::InvalidProof(
+                "the path is longer than the tree is deep".to_string()
+            ));
+        }
+        if f & 1 == 1 || f == s {
+            ret = node_hash(sibling, &ret);
+            while f & 1 == 0 && f != 0 {
+                f >>= 1;
+                s >>= 1;
+            }
+        } else {
+            ret = node_hash(&ret, sibling);
+        }
+        f >>= 1;
+        s >>= 1;
+    }
+    if s != 0 {
+        bail!(InclusionErrorKind::InvalidProof(
+            "the path is shorter than the tree is deep".to_string()
+        ));
+    }
+    Ok(ret)
+}
+
+/// Builds the head of the hash tree over the history of a chain
+pub(crate) fn root_head(key: &ChainKey, leaves: &[AteHash], timestamp: u64) -> RootHead {
+    RootHead {
+        chain: key.to_string(),
+        tree_size: leaves.len() as u64,
+        root_hash: tree_root(leaves),
+        timestamp,
+    }
+}
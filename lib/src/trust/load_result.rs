@@ -10,6 +10,9 @@ pub struct LoadStrongResult {
     pub header: EventHeaderRaw,
     pub data: EventStrongData,
     pub leaf: EventLeaf,
+    /// When the event is a patch these are the events it is applied on top
+    /// of, starting with the full row
+    pub base: Vec<EventStrongData>,
 }
 
 #[derive(Debug, Clone)]
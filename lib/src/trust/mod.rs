@@ -1,7 +1,10 @@
 pub mod chain_of_trust;
 pub mod chain_ref;
 pub mod header;
+pub mod history;
+pub mod inclusion;
 pub mod load_result;
+pub mod statistics;
 pub mod tests;
 pub mod timeline;
 
@@ -10,10 +13,18 @@ pub mod timeline;
 pub(crate) use tests::*;
 
 pub(crate) use chain_of_trust::*;
+pub(crate) use history::*;
+pub(crate) use statistics::StatisticsTracker;
 pub(crate) use timeline::*;
 
 pub use chain_ref::*;
 pub use header::*;
+pub use history::IndexUsage;
+pub use statistics::*;
+pub use inclusion::verify_inclusion;
+pub use inclusion::InclusionProof;
+pub use inclusion::RootHead;
+pub use inclusion::SignedRootHead;
 pub use load_result::*;
 
 pub use ate_crypto::ChainKey;
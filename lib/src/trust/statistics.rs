@@ -0,0 +1,267 @@
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::event::*;
+use crate::header::*;
+use crate::meta::*;
+
+/// Number of type names that are reported individually, the remainder are
+/// added together under `STATISTICS_OTHER_TYPE`
+pub const STATISTICS_MAX_TYPES: usize = 32;
+/// Number of the largest rows that are reported
+pub const STATISTICS_MAX_LARGEST_ROWS: usize = 10;
+/// Number of days of growth that are reported (older days are added to the
+/// first day that is reported)
+pub const STATISTICS_MAX_DAYS: usize = 90;
+/// Name under which the type names that did not make the cut are reported
+pub const STATISTICS_OTHER_TYPE: &str = "(other)";
+/// Name under which events that did not record their type name are reported
+pub const STATISTICS_UNKNOWN_TYPE: &str = "(unknown)";
+
+/// Upper bounds of the buckets in the histogram of event sizes (the last
+/// bucket holds everything that is bigger)
+const STATISTICS_SIZE_BUCKETS: [u64; 8] = [
+    64,
+    256,
+    1024,
+    4 * 1024,
+    16 * 1024,
+    64 * 1024,
+    256 * 1024,
+    1024 * 1024,
+];
+
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// Summary of what is stored inside a chain which is computed from the
+/// headers of the events (hence the data does not need to be decrypted)
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ChainStatistics {
+    /// Number of events in the history of the chain
+    pub events: u64,
+    /// Number of rows that currently exist in the chain
+    pub live_rows: u64,
+    /// Number of events that deleted a row
+    pub tombstones: u64,
+    /// Number of bytes (metadata and data) of all the events
+    pub bytes: u64,
+    /// Events and bytes for each type name, largest first (type names are
+    /// only known when the writer records them)
+    pub types: Vec<TypeStatistics>,
+    /// The largest rows in the chain, largest first
+    pub largest_rows: Vec<RowStatistics>,
+    /// Number of events that fall within each size range
+    pub size_histogram: Vec<SizeBucket>,
+    /// Events and bytes added to the chain on each day (UTC)
+    pub growth: Vec<GrowthStatistics>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TypeStatistics {
+    pub type_name: String,
+    pub events: u64,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RowStatistics {
+    pub key: PrimaryKey,
+    pub type_name: String,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SizeBucket {
+    /// Largest event size that falls in this bucket (none for the last bucket)
+    pub max_bytes: Option<u64>,
+    pub events: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GrowthStatistics {
+    /// Number of days since the unix epoch
+    pub day: u64,
+    pub events: u64,
+    pub bytes: u64,
+}
+
+impl std::fmt::Display for ChainStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "events:     {}", self.events)?;
+        writeln!(f, "live rows:  {}", self.live_rows)?;
+        writeln!(f, "tombstones: {}", self.tombstones)?;
+        writeln!(f, "bytes:      {}", self.bytes)?;
+        writeln!(f, "types:")?;
+        for t in self.types.iter() {
+            writeln!(f, "  {} - {} events, {} bytes", t.type_name, t.events, t.bytes)?;
+        }
+        writeln!(f, "largest rows:")?;
+        for r in self.largest_rows.iter() {
+            writeln!(f, "  {} ({}) - {} bytes", r.key, r.type_name, r.bytes)?;
+        }
+        writeln!(f, "event sizes:")?;
+        for b in self.size_histogram.iter() {
+            match b.max_bytes {
+                Some(max) => writeln!(f, "  <= {} bytes - {} events", max, b.events)?,
+                None => writeln!(f, "  larger - {} events", b.events)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Maintains the statistics of a chain as events are added to it so that
+/// they can be read cheaply (memory is bounded regardless of the number of
+/// type names in the chain)
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StatisticsTracker {
+    events: u64,
+    tombstones: u64,
+    bytes: u64,
+    types: FxHashMap<String, TypeStatistics>,
+    other: TypeStatistics,
+    largest: Vec<RowStatistics>,
+    histogram: [u64; STATISTICS_SIZE_BUCKETS.len() + 1],
+    growth: BTreeMap<u64, GrowthStatistics>,
+}
+
+impl StatisticsTracker {
+    pub(crate) fn feed(&mut self, header: &EventHeader) {
+        let size = header.raw.meta_bytes.len() as u64 + header.raw.data_size as u64;
+        self.events += 1;
+        self.bytes += size;
+
+        let bucket = STATISTICS_SIZE_BUCKETS
+            .iter()
+            .position(|a| size <= *a)
+            .unwrap_or(STATISTICS_SIZE_BUCKETS.len());
+        self.histogram[bucket] += 1;
+
+        if let Some(timestamp) = header.meta.get_timestamp() {
+            let day = timestamp.time_since_epoch_ms / MS_PER_DAY;
+            let entry = self.growth.entry(day).or_insert_with(|| GrowthStatistics {
+                day,
+                ..Default::default()
+            });
+            entry.events += 1;
+            entry.bytes += size;
+            if self.growth.len() > STATISTICS_MAX_DAYS {
+                let oldest = self.growth.keys().next().cloned();
+                if let Some(oldest) = oldest.and_then(|a| self.growth.remove(&a)) {
+                    if let Some((_, next)) = self.growth.iter_mut().next() {
+                        next.events += oldest.events;
+                        next.bytes += oldest.bytes;
+                    }
+                }
+            }
+        }
+
+        let tombstone = header.meta.core.iter().find_map(|a| match a {
+            CoreMetadata::Tombstone(key) => Some(key.clone()),
+            _ => None,
+        });
+        if let Some(key) = tombstone {
+            self.tombstones += 1;
+            self.largest.retain(|a| a.key != key);
+            return;
+        }
+
+        let type_name = header
+            .meta
+            .get_type_name()
+            .map(|a| a.type_name.as_str())
+            .unwrap_or(STATISTICS_UNKNOWN_TYPE);
+        self.feed_type(type_name, size);
+
+        if let Some(key) = header.meta.get_data_key() {
+            self.feed_row(key, type_name, header.raw.data_size as u64);
+        }
+    }
+
+    fn feed_type(&mut self, type_name: &str, size: u64) {
+        if let Some(entry) = self.types.get_mut(type_name) {
+            entry.events += 1;
+            entry.bytes += size;
+            return;
+        }
+        self.types.insert(
+            type_name.to_string(),
+            TypeStatistics {
+                type_name: type_name.to_string(),
+                events: 1,
+                bytes: size,
+            },
+        );
+
+        // Once there are too many type names the smallest half of them are
+        // folded into the 'other' bucket so that memory stays bounded
+        if self.types.len() > STATISTICS_MAX_TYPES * 2 {
+            let mut types = self.types.drain().map(|(_, v)| v).collect::<Vec<_>>();
+            types.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+            for t in types.split_off(STATISTICS_MAX_TYPES) {
+                self.other.events += t.events;
+                self.other.bytes += t.bytes;
+            }
+            self.types = types.into_iter().map(|a| (a.type_name.clone(), a)).collect();
+        }
+    }
+
+    fn feed_row(&mut self, key: PrimaryKey, type_name: &str, size: u64) {
+        // A row that is written again replaces its earlier size
+        self.largest.retain(|a| a.key != key);
+        if self.largest.len() >= STATISTICS_MAX_LARGEST_ROWS {
+            match self.largest.last() {
+                Some(a) if a.bytes >= size => return,
+                _ => {}
+            }
+            self.largest.pop();
+        }
+        let row = RowStatistics {
+            key,
+            type_name: type_name.to_string(),
+            bytes: size,
+        };
+        let pos = self.largest.iter().position(|a| a.bytes < size).unwrap_or(self.largest.len());
+        self.largest.insert(pos, row);
+    }
+
+    pub(crate) fn snapshot(&self, live_rows: u64) -> ChainStatistics {
+        let mut types = self.types.values().cloned().collect::<Vec<_>>();
+        types.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.type_name.cmp(&b.type_name)));
+        let mut other = self.other.clone();
+        if types.len() > STATISTICS_MAX_TYPES {
+            for t in types.split_off(STATISTICS_MAX_TYPES) {
+                other.events += t.events;
+                other.bytes += t.bytes;
+            }
+        }
+        if other.events > 0 {
+            other.type_name = STATISTICS_OTHER_TYPE.to_string();
+            types.push(other);
+        }
+
+        let size_histogram = self
+            .histogram
+            .iter()
+            .enumerate()
+            .map(|(n, events)| SizeBucket {
+                max_bytes: STATISTICS_SIZE_BUCKETS.get(n).cloned(),
+                events: *events,
+            })
+            .collect();
+
+        ChainStatistics {
+            events: self.events,
+            live_rows,
+            tombstones: self.tombstones,
+            bytes: self.bytes,
+            types,
+            largest_rows: self.largest.clone(),
+            size_histogram,
+            growth: self.growth.values().cloned().collect(),
+        }
+    }
+}
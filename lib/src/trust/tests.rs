@@ -13,7 +13,9 @@ use crate::error::*;
 use crate::event::*;
 use crate::header::*;
 use crate::lint::*;
+use crate::meta::*;
 use crate::spec::*;
+use crate::time::ChainTimestamp;
 use crate::transaction::*;
 use crate::transform::*;
 use crate::validator::*;
@@ -364,3 +366,243 @@ async fn test_chain() -> Result<(), AteError> {
 
     Ok(())
 }
+
+#[cfg(feature = "enable_local_fs")]
+#[test]
+fn test_history_spill() {
+    crate::utils::bootstrap_test_env();
+
+    let raw = |n: u64| EventHeaderRaw {
+        meta_hash: AteHash::from_bytes(&n.to_be_bytes()),
+        meta_bytes: Bytes::from(vec![1u8; 64]),
+        data_hash: None,
+        data_size: 0,
+        event_hash: AteHash::from_bytes(&n.to_be_bytes()),
+        format: MessageFormat {
+            meta: SerializationFormat::Bincode,
+            data: SerializationFormat::Json,
+        },
+    };
+
+    let mut cfg = crate::conf::tests::mock_test_config();
+    cfg.max_index_memory = Some(16 * 1024);
+    let mut history = ChainHistory::new(&cfg, &ChainKey::from("spill-test"), true);
+
+    // Every event gets its own timestamp except every tenth one which
+    // shares the timestamp of the event before it
+    let mut expected = Vec::new();
+    for n in 0..5000u64 {
+        let ts = ChainTimestamp::from(n - (n % 10 == 9) as u64);
+        history.insert(ts, raw(n));
+        expected.push((ts, raw(n).event_hash));
+    }
+
+    // A late arrival that is older than what was already spilled stays in memory
+    history.insert(ChainTimestamp::from(5u64), raw(99999));
+    expected.insert(6, (ChainTimestamp::from(5u64), raw(99999).event_hash));
+
+    let usage = history.usage();
+    assert!(usage.events_spilled > 0, "the history should have been spilled");
+    assert!(usage.memory_bytes <= 16 * 1024);
+    assert_eq!(history.len(), expected.len());
+
+    // Reading the whole history merges what is on disk with what is in memory
+    let all = history
+        .iter()
+        .map(|(k, v)| (k, v.event_hash))
+        .collect::<Vec<_>>();
+    assert_eq!(all, expected);
+
+    // Ranges that start in the spilled part of the history
+    let from = ChainTimestamp::from(1000u64);
+    let to = ChainTimestamp::from(1100u64);
+    let some = history
+        .range(from..to)
+        .map(|(k, v)| (k, v.event_hash))
+        .collect::<Vec<_>>();
+    let check = expected
+        .iter()
+        .filter(|(k, _)| *k >= from && *k < to)
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(some, check);
+
+    assert_eq!(history.first_key(), Some(ChainTimestamp::from(0u64)));
+    assert_eq!(history.last_key(), Some(ChainTimestamp::from(4999u64)));
+}
+
+#[test]
+fn test_inclusion_tree() {
+    crate::utils::bootstrap_test_env();
+
+    let key = PrivateSignKey::generate(KeySize::Bit128);
+    let public_keys = vec![key.as_public_key().clone()];
+    let chain_key = ChainKey::from("inclusion-tree");
+
+    // Every position in trees that are (and are not) powers of two
+    for size in 1..=17u64 {
+        let events = (0..size)
+            .map(|n| AteHash::from_bytes(&n.to_be_bytes()))
+            .collect::<Vec<_>>();
+        let leaves = events.iter().map(inclusion::leaf_hash).collect::<Vec<_>>();
+        let head = inclusion::root_head(&chain_key, &leaves[..], 0);
+        let head = SignedRootHead::sign(head, &key).unwrap();
+
+        for (index, event_hash) in events.iter().enumerate() {
+            let proof = InclusionProof {
+                event_hash: *event_hash,
+                leaf_index: index as u64,
+                path: inclusion::tree_path(index, &leaves[..]),
+                root_head: head.clone(),
+            };
+            verify_inclusion(&proof, &head, &public_keys[..]).unwrap();
+
+            if size > 1 {
+                let mut wrong = proof.clone();
+                wrong.leaf_index = (wrong.leaf_index + 1) % size;
+                assert!(verify_inclusion(&wrong, &head, &public_keys[..]).is_err());
+            }
+        }
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_inclusion_proof() -> Result<(), AteError> {
+    crate::utils::bootstrap_test_env();
+
+    let mut mock_cfg = crate::conf::tests::mock_test_config();
+    mock_cfg.compact_mode = CompactMode::Never;
+    let (chain, _builder) =
+        create_test_chain(&mut mock_cfg, "test_inclusion".to_string(), true, true, None).await;
+
+    let key = PrivateSignKey::generate(KeySize::Bit128);
+    let public_keys = vec![key.as_public_key().clone()];
+
+    // Nothing can be proven until the chain is checkpointed
+    assert!(chain.prove_inclusion(AteHash::generate()).await.is_err());
+
+    {
+        let lock = chain.multi().await;
+        let evts = (0..7u8)
+            .map(|n| {
+                EventWeakData::new(
+                    PrimaryKey::generate(),
+                    Bytes::from(vec![n; 1]),
+                    mock_cfg.log_format,
+                )
+            })
+            .collect::<Vec<_>>();
+        let trans = Transaction::from_events(
+            evts,
+            TransactionScope::Local,
+            false,
+            Duration::from_secs(30),
+        );
+        lock.pipe
+            .feed(ChainWork { trans })
+            .await
+            .expect("The event failed to be accepted");
+    }
+    let head = chain.checkpoint(&key).await?;
+    assert_eq!(head.head.tree_size, 7);
+
+    let event_hashes = {
+        let guard = chain.inside_async.read().await;
+        guard.range(..).map(|(_, h)| h.event_hash).collect::<Vec<_>>()
+    };
+    for event_hash in event_hashes.iter() {
+        let proof = chain.prove_inclusion(*event_hash).await?;
+        verify_inclusion(&proof, &head, &public_keys[..])?;
+    }
+    let proof = chain.prove_inclusion(event_hashes[3]).await?;
+
+    // Events that are not in the chain have no proof
+    assert!(chain.prove_inclusion(AteHash::generate()).await.is_err());
+
+    // Tampering with any part of the proof makes it fail
+    let mut tampered = proof.clone();
+    tampered.path[0] = AteHash::generate();
+    assert!(verify_inclusion(&tampered, &head, &public_keys[..]).is_err());
+
+    let mut tampered = proof.clone();
+    tampered.event_hash = AteHash::generate();
+    assert!(verify_inclusion(&tampered, &head, &public_keys[..]).is_err());
+
+    let mut tampered = proof.clone();
+    tampered.path.pop();
+    assert!(verify_inclusion(&tampered, &head, &public_keys[..]).is_err());
+
+    let mut tampered = proof.clone();
+    tampered.path.push(AteHash::generate());
+    assert!(verify_inclusion(&tampered, &head, &public_keys[..]).is_err());
+
+    // A root head that was altered after it was signed is not trusted
+    let mut forged = head.clone();
+    forged.head.root_hash = AteHash::generate();
+    let mut tampered = proof.clone();
+    tampered.root_head = forged.clone();
+    assert!(verify_inclusion(&tampered, &forged, &public_keys[..]).is_err());
+
+    // Neither is one signed by a key that is not trusted
+    let other = PrivateSignKey::generate(KeySize::Bit128);
+    let untrusted = SignedRootHead::sign(head.head.clone(), &other)?;
+    let mut tampered = proof.clone();
+    tampered.root_head = untrusted.clone();
+    assert!(verify_inclusion(&tampered, &untrusted, &public_keys[..]).is_err());
+
+    // Proofs are only valid against the head they were generated for
+    let mut newer = head.head.clone();
+    newer.timestamp += 1;
+    let newer = SignedRootHead::sign(newer, &key)?;
+    assert!(verify_inclusion(&proof, &newer, &public_keys[..]).is_err());
+    verify_inclusion(&proof, &head, &public_keys[..])?;
+
+    chain.single().await.destroy().await?;
+    Ok(())
+}
+
+#[test]
+fn test_statistics_tracker() {
+    crate::utils::bootstrap_test_env();
+
+    let format = MessageFormat {
+        meta: SerializationFormat::Bincode,
+        data: SerializationFormat::Json,
+    };
+    let header = |key: PrimaryKey, type_name: String, size: usize| {
+        let mut evt = EventWeakData::new(key, Bytes::from(vec![0u8; size]), format);
+        evt.meta.core.push(CoreMetadata::Type(MetaType { type_name }));
+        evt.meta.core.push(CoreMetadata::Timestamp(ChainTimestamp::from(1000u64)));
+        evt.as_header().unwrap()
+    };
+
+    // Many distinct type names must not grow the tracker without bound
+    let mut tracker = StatisticsTracker::default();
+    for n in 0..(STATISTICS_MAX_TYPES * 10) {
+        tracker.feed(&header(PrimaryKey::generate(), format!("type{}", n), 10 + n));
+    }
+    let big = PrimaryKey::generate();
+    tracker.feed(&header(big, "big".to_string(), 100000));
+
+    let mut tombstone = Metadata::default();
+    tombstone.core.push(CoreMetadata::Tombstone(big));
+    tracker.feed(&EventWeakData {
+        meta: tombstone,
+        data_bytes: MessageBytes::None,
+        format,
+    }.as_header().unwrap());
+
+    let stats = tracker.snapshot(1);
+    assert_eq!(stats.events, STATISTICS_MAX_TYPES as u64 * 10 + 2);
+    assert_eq!(stats.tombstones, 1);
+    assert_eq!(stats.types.len(), STATISTICS_MAX_TYPES + 1);
+    assert_eq!(stats.types[0].type_name, "big");
+    assert_eq!(stats.types.last().unwrap().type_name, STATISTICS_OTHER_TYPE);
+    assert_eq!(stats.types.iter().map(|a| a.events).sum::<u64>(), stats.events - 1);
+    // Deleted rows are no longer reported as the largest
+    assert_eq!(stats.largest_rows.len(), STATISTICS_MAX_LARGEST_ROWS - 1);
+    assert!(stats.largest_rows.iter().all(|a| a.key != big));
+    assert_eq!(stats.size_histogram.iter().map(|a| a.events).sum::<u64>(), stats.events);
+    assert_eq!(stats.growth.len(), 1);
+}
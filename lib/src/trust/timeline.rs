@@ -1,4 +1,3 @@
-use btreemultimap::BTreeMultiMap;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
@@ -9,10 +8,19 @@ use crate::index::*;
 use crate::meta::*;
 use crate::time::*;
 
+use super::history::*;
+use super::statistics::*;
+
 pub(crate) struct ChainTimeline {
-    pub(crate) history: BTreeMultiMap<ChainTimestamp, EventHeaderRaw>,
+    pub(crate) history: ChainHistory,
     pub(crate) pointers: BinaryTreeIndexer,
     pub(crate) compactors: Vec<Box<dyn EventCompactor>>,
+    /// Statistics that are kept up to date as events are added (only once
+    /// they have been asked for at least once)
+    pub(crate) statistics: Option<StatisticsTracker>,
+    /// Number of tombstones in the history (kept up to date as events are
+    /// added so that compaction policies do not need to scan the history)
+    pub(crate) tombstones: u64,
 }
 
 impl<'a> ChainTimeline {
@@ -32,6 +40,13 @@ impl<'a> ChainTimeline {
         self.pointers.lookup_secondary_raw(key)
     }
 
+    pub(crate) fn lookup_collection_policy(
+        &self,
+        key: &MetaCollection,
+    ) -> Option<MetaCollectionPolicy> {
+        self.pointers.lookup_collection_policy(key)
+    }
+
     pub(crate) fn roots_raw(&self) -> Vec<PrimaryKey> {
         self.pointers.roots_raw()
     }
@@ -40,6 +55,14 @@ impl<'a> ChainTimeline {
 
     pub(crate) fn add_history(&mut self, header: EventHeader) {
         self.pointers.feed(&header);
+        if header.meta.include_in_history() {
+            if let Some(statistics) = self.statistics.as_mut() {
+                statistics.feed(&header);
+            }
+            if header.meta.get_tombstone().is_some() {
+                self.tombstones += 1;
+            }
+        }
 
         let raw = header.raw;
 
@@ -48,10 +71,10 @@ impl<'a> ChainTimeline {
 
         let timestamp = match header.meta.get_timestamp() {
             Some(a) => a.clone(),
-            None => match self.history.iter().next_back() {
-                Some(a) => a.0.clone(),
-                None => ChainTimestamp::from(0u64),
-            },
+            None => self
+                .history
+                .last_key()
+                .unwrap_or_else(|| ChainTimestamp::from(0u64)),
         };
 
         if header.meta.include_in_history() {
@@ -61,18 +84,14 @@ impl<'a> ChainTimeline {
 
     #[allow(dead_code)]
     pub(crate) fn start(&self) -> ChainTimestamp {
-        let last = self.history.iter().next();
-        match last {
-            Some(a) => a.0.clone(),
-            None => ChainTimestamp::from(0u64),
-        }
+        self.history
+            .first_key()
+            .unwrap_or_else(|| ChainTimestamp::from(0u64))
     }
 
     pub(crate) fn end(&self) -> ChainTimestamp {
-        let last = self.history.iter().next_back();
-        match last {
-            Some(a) => a.0.clone(),
-            None => ChainTimestamp::from(0u64),
-        }
+        self.history
+            .last_key()
+            .unwrap_or_else(|| ChainTimestamp::from(0u64))
     }
 }
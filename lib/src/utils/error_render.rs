@@ -0,0 +1,162 @@
+//! Renders errors for the people running the command line tools, rather
+//! than the single line dump of the whole error chain. The message of the
+//! error comes first followed by each of its causes (indented one level
+//! deeper than the error they caused) and then the hints for the stable
+//! error codes (e.g. "COMMIT_0014") found anywhere in the chain.
+//!
+//! In verbose mode the debug output of every error in the chain is added
+//! beneath, errors created by error_chain include their backtrace in it
+//! when one was captured (i.e. RUST_BACKTRACE is set).
+use std::error::Error;
+
+/// Hint that is shown when an error carries the stable error code
+pub type ErrorHint = (&'static str, &'static str);
+
+/// Hints for the errors of ate itself, the tools add their own hints in
+/// front of these with `with_hints`
+pub const ATE_ERROR_HINTS: &'static [ErrorHint] = &[
+    ("COMMS_0011", "the certificate of the server could not be validated, check the system clock and the URL of the server"),
+    ("COMMS_0014", "the connection was lost, check your network connection and try again"),
+    ("COMMS_0016", "the server did not respond in time, check your network connection and try again"),
+    ("COMMS_0018", "the server refused the connection, check that the URL is correct and the server is running"),
+    ("COMMS_0020", "the domain name is not valid, check the URL that was given"),
+    ("COMMS_0033", "someone on the network path replayed or altered the traffic, use a trusted network or TLS"),
+    ("COMMIT_0004", "the chain is read-only, it can not be changed until it is opened for writing again"),
+    ("COMMIT_0011", "the chain has moved to another root, run the command again to follow it"),
+    ("COMMIT_0012", "someone else changed the row at the same time, load it again and retry"),
+    ("COMMIT_0013", "split the change into several smaller commits"),
+    ("COMMIT_0014", "delete data that is no longer needed or ask for a larger quota"),
+];
+
+#[derive(Debug, Clone)]
+pub struct ErrorRenderer {
+    hints: Vec<ErrorHint>,
+    verbose: bool,
+}
+
+impl Default for ErrorRenderer {
+    fn default() -> ErrorRenderer {
+        ErrorRenderer {
+            hints: ATE_ERROR_HINTS.to_vec(),
+            verbose: false,
+        }
+    }
+}
+
+impl ErrorRenderer {
+    pub fn new() -> ErrorRenderer {
+        ErrorRenderer::default()
+    }
+
+    /// Adds hints that take precedence over the ones already added
+    pub fn with_hints(mut self, hints: &[ErrorHint]) -> Self {
+        let mut ret = hints.to_vec();
+        ret.extend(self.hints.drain(..));
+        self.hints = ret;
+        self
+    }
+
+    /// Adds the debug output of the whole error chain
+    pub fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    pub fn render(&self, err: &(dyn Error + 'static)) -> String {
+        // Causes whose message was already included in the message of the
+        // error they caused are not repeated
+        let mut messages = vec![err.to_string()];
+        let mut chain = vec![err];
+        let mut source = err.source();
+        while let Some(cause) = source {
+            let msg = cause.to_string();
+            if messages.iter().any(|a| a.contains(msg.as_str())) == false {
+                messages.push(msg);
+            }
+            chain.push(cause);
+            source = cause.source();
+        }
+
+        let mut ret = String::new();
+        for (depth, msg) in messages.iter().enumerate() {
+            let prefix = match depth {
+                0 => "error: ".to_string(),
+                n => format!("{}caused by: ", "  ".repeat(n)),
+            };
+            push_indented(&mut ret, prefix.as_str(), msg.as_str());
+        }
+
+        let mut hints = Vec::new();
+        for code in messages.iter().flat_map(|a| error_codes(a.as_str())) {
+            if let Some((_, hint)) = self.hints.iter().find(|a| a.0 == code) {
+                if hints.contains(hint) == false {
+                    hints.push(*hint);
+                }
+            }
+        }
+        for hint in hints {
+            push_indented(&mut ret, "hint: ", hint);
+        }
+
+        if self.verbose {
+            ret.push_str("\ndebug:\n");
+            for cause in chain {
+                push_indented(&mut ret, "  ", format!("{:?}", cause).as_str());
+            }
+        }
+        ret
+    }
+
+    /// Writes the rendered error to stderr
+    pub fn eprint(&self, err: &(dyn Error + 'static)) {
+        eprint!("{}", self.render(err));
+    }
+}
+
+/// Appends a line with a prefix, lines that follow it within the text are
+/// aligned beneath the first one
+fn push_indented(ret: &mut String, prefix: &str, text: &str) {
+    let indent = " ".repeat(prefix.len());
+    for (n, line) in text.lines().enumerate() {
+        match n {
+            0 => ret.push_str(prefix),
+            _ => ret.push_str(indent.as_str()),
+        }
+        ret.push_str(line);
+        ret.push('\n');
+    }
+}
+
+/// Returns the stable error codes (e.g. "COMMS_0014") within a message
+pub fn error_codes(msg: &str) -> Vec<&str> {
+    let bytes = msg.as_bytes();
+    let mut ret = Vec::new();
+    let mut start = 0usize;
+    while start < bytes.len() {
+        let word = bytes[start..]
+            .iter()
+            .take_while(|a| a.is_ascii_uppercase())
+            .count();
+        let digits = start + word + 1;
+        let is_code = word > 0
+            && bytes.get(start + word) == Some(&b'_')
+            && bytes.len() >= digits + 4
+            && bytes[digits..digits + 4].iter().all(|a| a.is_ascii_digit())
+            && bytes
+                .get(digits + 4)
+                .map(|a| a.is_ascii_alphanumeric() == false)
+                .unwrap_or(true);
+        if is_code {
+            ret.push(&msg[start..digits + 4]);
+            start = digits + 4;
+            continue;
+        }
+
+        // Skip to the start of the next word
+        start += word.max(1);
+        while start < bytes.len() && bytes[start - 1].is_ascii_alphanumeric() {
+            start += 1;
+        }
+    }
+    ret
+}
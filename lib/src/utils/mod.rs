@@ -2,9 +2,11 @@
 #![allow(unused_imports)]
 use tracing::{debug, error, info};
 
+mod error_render;
 mod key;
 mod progress;
 mod io;
+mod tests;
 
 use ate_crypto::utils;
 pub use ate_crypto::utils::b64;
@@ -20,6 +22,10 @@ pub use utils::b32_deserialize;
 pub use utils::b32_serialize;
 pub use utils::vec_deserialize;
 pub use utils::vec_serialize;
+pub use error_render::error_codes;
+pub use error_render::ErrorHint;
+pub use error_render::ErrorRenderer;
+pub use error_render::ATE_ERROR_HINTS;
 pub use key::chain_key_16hex;
 pub use key::chain_key_4hex;
 pub use log::log_init;
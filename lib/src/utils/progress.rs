@@ -19,6 +19,10 @@ where
     pub units: pbr::Units,
     pub bar: Option<ProgressBar<T>>,
     pub writer: Option<T>,
+    /// When false no bar is drawn (e.g. when the output is not a terminal)
+    pub draw_bar: bool,
+    total_bytes: Option<u64>,
+    by_bytes: bool,
 }
 
 impl<T> LoadProgress<T>
@@ -31,6 +35,9 @@ where
             units: pbr::Units::Default,
             bar: None,
             writer: Some(writer),
+            draw_bar: true,
+            total_bytes: None,
+            by_bytes: false,
         }
     }
 }
@@ -50,12 +57,23 @@ where
         }
     }
 
+    fn history_bytes(&mut self, total_bytes: u64) {
+        self.total_bytes = Some(total_bytes);
+    }
+
     async fn start_of_history(&mut self, size: usize) {
+        if self.draw_bar == false {
+            return;
+        }
         if let Some(writer) = self.writer.take() {
-            let mut pb = ProgressBar::on(writer, size as u64);
-            match &self.units {
-                Units::Default => pb.set_units(Units::Default),
-                Units::Bytes => pb.set_units(Units::Bytes),
+            // Servers that report the size of the data are tracked by bytes
+            // otherwise we fall back to counting the events
+            let total_bytes = self.total_bytes.take();
+            self.by_bytes = total_bytes.is_some();
+            let mut pb = ProgressBar::on(writer, total_bytes.unwrap_or(size as u64));
+            match (&self.units, self.by_bytes) {
+                (Units::Bytes, _) | (_, true) => pb.set_units(Units::Bytes),
+                (Units::Default, false) => pb.set_units(Units::Default),
             }
             pb.format("╢█▌░╟");
             self.bar.replace(pb);
@@ -64,7 +82,19 @@ where
 
     fn feed_events(&mut self, evts: &Vec<EventWeakData>) {
         if let Some(pb) = &mut self.bar {
-            pb.add(evts.len() as u64);
+            if self.by_bytes {
+                let total: u64 = evts
+                    .iter()
+                    .map(|evt| match &evt.data_bytes {
+                        MessageBytes::Some(a) => a.len() as u64,
+                        MessageBytes::LazySome(l) => l.len as u64,
+                        MessageBytes::None => 0,
+                    })
+                    .sum();
+                pb.add(total);
+            } else {
+                pb.add(evts.len() as u64);
+            }
         }
     }
 
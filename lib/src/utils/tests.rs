@@ -0,0 +1,99 @@
+#![cfg(test)]
+use std::error::Error;
+
+use super::*;
+use crate::error::*;
+
+/// Error with an optional cause whose debug output does not depend on the
+/// environment (unlike the backtraces of error_chain)
+#[derive(Debug)]
+struct TestError {
+    msg: &'static str,
+    cause: Option<Box<dyn Error + 'static>>,
+}
+
+impl TestError {
+    fn new(msg: &'static str, cause: Option<Box<dyn Error + 'static>>) -> Box<dyn Error + 'static> {
+        Box::new(TestError { msg, cause })
+    }
+}
+
+impl std::fmt::Display for TestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl Error for TestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.cause.as_deref()
+    }
+}
+
+/// Compares the output with the snapshot of it that is checked in, so that
+/// changes to the wording are deliberate (set UPDATE_SNAPSHOTS=1 to accept
+/// the new output)
+fn assert_snapshot(name: &str, actual: &str) {
+    let path = format!("{}/src/utils/snapshots/{}.txt", env!("CARGO_MANIFEST_DIR"), name);
+    if std::env::var("UPDATE_SNAPSHOTS").is_ok() {
+        std::fs::write(path.as_str(), actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(path.as_str())
+        .unwrap_or_else(|err| panic!("failed to read the snapshot {} - {}", path, err));
+    assert_eq!(expected, actual, "the output does not match the snapshot {}", path);
+}
+
+#[test]
+fn test_error_codes() {
+    crate::utils::bootstrap_test_env();
+
+    assert_eq!(
+        error_codes("COMMS_0014: lost - COMMIT_0014: quota (see X_0001a, ab_0001)"),
+        vec!["COMMS_0014", "COMMIT_0014"]
+    );
+    assert!(error_codes("COMMS_001 and COMMS_00145").is_empty());
+}
+
+#[test]
+fn test_error_render_causes_and_hints() {
+    crate::utils::bootstrap_test_env();
+
+    let quota: CommitError = CommitErrorKind::QuotaExceeded(900, 1000).into();
+    let err = TestError::new(
+        "failed to update the instance",
+        Some(TestError::new(
+            "failed to save the instance configuration",
+            Some(Box::new(quota)),
+        )),
+    );
+
+    let renderer = ErrorRenderer::new();
+    assert_snapshot("render_quota", renderer.render(err.as_ref()).as_str());
+
+    let renderer = renderer.with_hints(&[("COMMIT_0014", "run 'tool details' to see the usage")]);
+    assert_snapshot("render_quota_tool", renderer.render(err.as_ref()).as_str());
+}
+
+#[test]
+fn test_error_render_repeated_causes() {
+    crate::utils::bootstrap_test_env();
+
+    let err = TestError::new(
+        "failed to login - COMMS_0018: the connection was refused\nby the server",
+        Some(TestError::new("COMMS_0018: the connection was refused", None)),
+    );
+    assert_snapshot("render_repeated", ErrorRenderer::new().render(err.as_ref()).as_str());
+}
+
+#[test]
+fn test_error_render_verbose() {
+    crate::utils::bootstrap_test_env();
+
+    let err = TestError::new(
+        "failed to open the chain",
+        Some(TestError::new("COMMIT_0004: the chain (test) is read-only", None)),
+    );
+    let renderer = ErrorRenderer::new().with_verbose(true);
+    assert_snapshot("render_verbose", renderer.render(err.as_ref()).as_str());
+}
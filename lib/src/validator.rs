@@ -27,6 +27,10 @@ pub trait EventValidator: Send + Sync {
 
     fn set_integrity_mode(&mut self, _mode: TrustMode) {}
 
+    /// Validators that check the time of events against the current time
+    /// read it from this clock while a chain is being replayed
+    fn set_replay_clock(&mut self, _clock: Option<crate::time::ReplayClock>) {}
+
     fn clone_validator(&self) -> Box<dyn EventValidator>;
 
     fn validator_name(&self) -> &str;
@@ -0,0 +1,98 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::prelude::*;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+#[cfg(all(feature = "enable_server", feature = "enable_client"))]
+#[test]
+fn alias_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let url = url::Url::parse("ws://localhost:5041/").unwrap();
+        let cfg_ate = ConfAte::default();
+        let cfg_mesh = ConfMesh::solo_from_url(
+            &cfg_ate,
+            &url,
+            &IpAddr::from_str("::").unwrap(),
+            None,
+            None,
+        )
+        .await?;
+        let root = create_ethereal_centralized_server(&cfg_ate, &cfg_mesh).await?;
+
+        // Aliases are persisted so clear out anything left by a previous run
+        for alias in ["alias-new", "alias-loop1", "alias-loop2"] {
+            root.remove_alias("/", &ChainKey::from(alias)).await?;
+        }
+
+        // Write some data into the chain that will be renamed
+        let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+        let session = AteSessionUser::new();
+        let old = registry.open(&url, &ChainKey::from("alias-old"), false).await?;
+        let key = {
+            let dio = old.dio_mut(&session).await?;
+            let key = dio.store("tenant data".to_string())?.key().clone();
+            dio.commit().await?;
+            key
+        };
+
+        // Subscribing to the alias is transparently served by the canonical chain
+        root.add_alias("/", ChainKey::from("alias-new"), ChainKey::from("alias-old"))
+            .await?;
+        {
+            let new = registry.open(&url, &ChainKey::from("alias-new"), false).await?;
+            let dio = new.dio(&session).await;
+            assert_eq!(*dio.load::<String>(&key).await?, "tenant data".to_string());
+        }
+        assert_eq!(
+            registry
+                .resolve_alias(&url, &ChainKey::from("alias-new"))
+                .await?
+                .name,
+            "alias-old".to_string()
+        );
+
+        // An alias can not shadow a chain that already exists
+        assert!(matches!(
+            root.add_alias("/", ChainKey::from("alias-old"), ChainKey::from("alias-other"))
+                .await,
+            Err(ChainCreationError(ChainCreationErrorKind::AliasShadowsChain(_), _))
+        ));
+
+        // Nor can the aliases form a loop
+        root.add_alias("/", ChainKey::from("alias-loop1"), ChainKey::from("alias-loop2"))
+            .await?;
+        assert!(matches!(
+            root.add_alias("/", ChainKey::from("alias-loop2"), ChainKey::from("alias-loop1"))
+                .await,
+            Err(ChainCreationError(ChainCreationErrorKind::AliasLoop(_), _))
+        ));
+        assert!(root.remove_alias("/", &ChainKey::from("alias-loop1")).await?);
+
+        // Removing the alias leaves the data where it was
+        assert!(root.remove_alias("/", &ChainKey::from("alias-new")).await?);
+        assert_eq!(
+            root.resolve_alias("/", &ChainKey::from("alias-new"))
+                .await?
+                .name,
+            "alias-new".to_string()
+        );
+        let dio = old.dio(&session).await;
+        assert_eq!(*dio.load::<String>(&key).await?, "tenant data".to_string());
+        assert!(root.aliases("/").await?.is_empty());
+
+        root.shutdown().await;
+        Ok(())
+    })
+}
@@ -0,0 +1,71 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::prelude::*;
+
+#[cfg(feature = "enable_local_fs")]
+#[test]
+fn archive_round_trip() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let mut conf = ConfAte::default();
+        conf.log_path = Some("/tmp/ate".to_string());
+        conf.configured_for(ConfiguredFor::BestPerformance);
+        let key = ChainKey::from("archive");
+        let session = AteSessionUser::new();
+        let path = "/tmp/ate-archive.bin";
+
+        // Write some rows and export the chain
+        let (row_key, manifest) = {
+            let builder = ChainBuilder::new(&conf).await.truncate(true).build();
+            let chain = builder.open(&key).await?;
+            let dio = chain.dio_mut(&session).await?;
+            let mut row_key = None;
+            for n in 0..20u32 {
+                row_key = Some(dio.store(format!("row-{}", n))?.key().clone());
+            }
+            dio.commit().await?;
+            chain.flush().await?;
+
+            let manifest = chain.export_archive(path).await?;
+            chain.single().await.destroy().await.unwrap();
+            (row_key.unwrap(), manifest)
+        };
+        assert!(manifest.events >= 20);
+
+        // Import it into another folder and read the rows back
+        let mut import_conf = conf.clone();
+        import_conf.log_path = Some("/tmp/ate-import".to_string());
+        let archive = ChainBuilder::new(&import_conf)
+            .await
+            .open_archive(path)
+            .await?;
+        assert_eq!(archive.manifest, manifest);
+        assert_eq!(archive.head, manifest.head);
+        let dio = archive.chain.dio(&session).await;
+        assert_eq!(*dio.load::<String>(&row_key).await?, "row-19".to_string());
+        drop(dio);
+        drop(archive);
+
+        // A truncated archive is refused
+        let data = std::fs::read(path)?;
+        std::fs::write(path, &data[..data.len() - 10])?;
+        match ChainBuilder::new(&import_conf).await.open_archive(path).await {
+            Err(ChainCreationError(ChainCreationErrorKind::ArchiveTruncated(..), _)) => {}
+            Err(err) => panic!("unexpected error - {}", err),
+            Ok(_) => panic!("the truncated archive was opened"),
+        }
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    })
+}
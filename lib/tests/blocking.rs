@@ -0,0 +1,89 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TestRow {
+    name: String,
+}
+
+#[cfg(feature = "enable_server")]
+#[test]
+fn blocking_registry_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    // The server runs on its own runtime while this thread stays synchronous
+    let url = url::Url::parse("ws://localhost:5031/").unwrap();
+    let (exit_tx, exit_rx) = tokio::sync::oneshot::channel::<()>();
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    let server_url = url.clone();
+    let server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let cfg_ate = ConfAte::default();
+            let cfg_mesh = ConfMesh::solo_from_url(
+                &cfg_ate,
+                &server_url,
+                &IpAddr::from_str("::").unwrap(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+            let server = create_ethereal_centralized_server(&cfg_ate, &cfg_mesh)
+                .await
+                .unwrap();
+            ready_tx.send(()).unwrap();
+            let _ = exit_rx.await;
+            server.shutdown().await;
+        });
+    });
+    ready_rx.recv().unwrap();
+
+    {
+        let registry =
+            BlockingRegistry::new(&ConfAte::default(), url)?.timeout(Duration::from_secs(20));
+        let chain = registry.open(&ChainKey::from("blocking-chain"))?;
+
+        // Store an object and read it back once its committed
+        let parent = chain.store(TestRow {
+            name: "parent".to_string(),
+        })?;
+        chain.commit()?;
+        assert_eq!(chain.load::<TestRow>(&parent)?.name, "parent".to_string());
+
+        // Children are attached to the parent and read back as a collection
+        chain.push(&parent, 1, TestRow { name: "child1".to_string() })?;
+        chain.push(&parent, 1, TestRow { name: "child2".to_string() })?;
+        chain.commit()?;
+        let mut names = chain
+            .iter_collection::<TestRow>(&parent, 1)?
+            .into_iter()
+            .map(|(_, row)| row.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["child1".to_string(), "child2".to_string()]);
+
+        // Calling the facade from an async context is rejected rather than deadlocking
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        rt.block_on(async {
+            assert!(matches!(
+                chain.load::<TestRow>(&parent),
+                Err(AteError(AteErrorKind::Reentrant, _))
+            ));
+        });
+    }
+
+    exit_tx.send(()).unwrap();
+    server.join().unwrap();
+    Ok(())
+}
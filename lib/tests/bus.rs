@@ -0,0 +1,58 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Table {
+    balls: DaoVec<u32>,
+}
+
+#[test]
+fn bus_lagged_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let mut conf = ConfAte::default();
+        conf.configured_for(ConfiguredFor::BestPerformance);
+        let builder = ChainBuilder::new(&conf).await.temporal(true).build();
+        let chain = builder.open(&ChainKey::from("bus-lagged")).await?;
+        let session = AteSessionUser::new();
+
+        let dio = chain.dio_mut(&session).await?;
+        let mut table = dio.store(Table {
+            balls: DaoVec::default(),
+        })?;
+        dio.commit().await?;
+        let mut bus = table.balls.bus().await?;
+
+        // A bus that is not read falls behind and is told about it once
+        for n in 0..250u32 {
+            table.as_mut().balls.push(n)?;
+            dio.commit().await?;
+        }
+        match bus.recv().await {
+            Err(BusError(BusErrorKind::Lagged(dropped), _)) => assert!(dropped > 0),
+            other => panic!("expected the bus to report the lag - {:?}", other.map(|_| ())),
+        }
+        assert!(bus.lagged() > 0);
+
+        // After which it carries on with the events that were kept
+        match bus.recv().await? {
+            BusEvent::Updated(ball) => assert!(*ball >= bus.lagged() as u32),
+            BusEvent::Deleted(_) => panic!("nothing was deleted"),
+        }
+
+        chain.single().await.destroy().await.unwrap();
+        Ok(())
+    })
+}
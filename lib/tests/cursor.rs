@@ -0,0 +1,108 @@
+#![cfg(any(feature = "enable_server", feature = "enable_client"))]
+use ate::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Order {
+    id: u32,
+}
+
+async fn store(chain: &std::sync::Arc<Chain>, session: &AteSessionUser, ids: &[u32]) -> Result<(), AteError> {
+    let dio = chain.dio_mut(session).await?;
+    for id in ids {
+        dio.store(Order { id: *id })?;
+    }
+    dio.commit().await?;
+    Ok(())
+}
+
+async fn recv_id(sub: &mut CursorSubscription) -> Result<(u32, AteHash), AteError> {
+    let evt = sub.recv().await?;
+    let order = evt.data_as::<Order>()?.expect("the event should carry data");
+    Ok((order.id, evt.event_hash()))
+}
+
+#[test]
+fn cursor_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let mut conf = ConfAte::default();
+        conf.configured_for(ConfiguredFor::BestPerformance);
+        let builder = ChainBuilder::new(&conf).await.temporal(true).build();
+        let chain = builder.open(&ChainKey::from("cursor")).await?;
+        let session = AteSessionUser::new();
+
+        store(&chain, &session, &[1, 2, 3]).await?;
+
+        // Acknowledging the second event also acknowledges the first one
+        // which fills the batch and writes the cursor
+        {
+            let dio = chain.dio(&session).await;
+            let mut sub = dio.open_from_cursor("kafka").await?.with_ack_batch(2);
+            let (id1, _) = recv_id(&mut sub).await?;
+            let (id2, hash2) = recv_id(&mut sub).await?;
+            let (id3, hash3) = recv_id(&mut sub).await?;
+            assert_eq!((id1, id2, id3), (1, 2, 3));
+            assert_eq!(sub.lag().await, 3);
+
+            sub.ack(hash2).await?;
+            assert_eq!(sub.lag().await, 1);
+            assert!(sub.try_recv().await?.is_none());
+
+            // Acks that are not flushed are lost when the subscription ends
+            sub.ack(hash3).await?;
+            assert!(sub.ack(hash3).await.is_err());
+        }
+
+        // The cursor resumes right after the last event that was flushed
+        // while other cursors follow the chain independently
+        {
+            let dio = chain.dio(&session).await;
+            let cursor = dio.cursor("kafka").await?.expect("the cursor should exist");
+            assert_eq!(cursor.acked, 2);
+            assert_eq!(dio.cursor_lag("kafka").await?, 1);
+            assert!(dio.cursor("audit").await?.is_none());
+
+            let mut sub = dio.open_from_cursor("kafka").await?;
+            assert_eq!(recv_id(&mut sub).await?.0, 3);
+            let mut audit = dio.open_from_cursor("audit").await?;
+            assert_eq!(recv_id(&mut audit).await?.0, 1);
+
+            // Events that arrive later wake up the subscription
+            store(&chain, &session, &[4]).await?;
+            let (id, hash) = recv_id(&mut sub).await?;
+            assert_eq!(id, 4);
+            sub.ack(hash).await?;
+            sub.flush().await?;
+            assert_eq!(sub.lag().await, 0);
+        }
+
+        // Cursors are only removed when they are deleted explicitly
+        {
+            let dio = chain.dio(&session).await;
+            assert_eq!(dio.cursors().await?.len(), 1);
+            assert_eq!(dio.cursor_lag("kafka").await?, 0);
+
+            let dio = chain.dio_mut(&session).await?;
+            assert!(dio.delete_cursor("kafka").await?);
+            assert!(dio.delete_cursor("kafka").await? == false);
+            dio.commit().await?;
+
+            let dio = chain.dio(&session).await;
+            assert!(dio.cursor("kafka").await?.is_none());
+            assert_eq!(dio.cursor_lag("kafka").await?, 4);
+        }
+
+        Ok(())
+    })
+}
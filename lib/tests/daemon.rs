@@ -0,0 +1,91 @@
+#![cfg(all(unix, feature = "enable_server", feature = "enable_full"))]
+use ate::daemon::*;
+use ate::prelude::*;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::UnixDatagram;
+
+async fn recv(socket: &UnixDatagram) -> String {
+    let mut buf = [0u8; 1024];
+    let len = tokio::time::timeout(Duration::from_secs(10), socket.recv(&mut buf))
+        .await
+        .expect("the service manager was not notified in time")
+        .unwrap();
+    String::from_utf8_lossy(&buf[..len]).to_string()
+}
+
+#[test]
+fn daemon_notify_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let dir = std::env::temp_dir().join(format!("ate-daemon-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let socket_path = dir.join("notify.sock");
+        let pid_path = dir.join("atedb.pid");
+        let _ = std::fs::remove_file(&socket_path);
+
+        // A fake service manager hands the notify socket and the watchdog
+        // timeout to the process through its environment
+        let socket = UnixDatagram::bind(&socket_path)?;
+        std::env::set_var(NOTIFY_SOCKET_ENV, &socket_path);
+        std::env::set_var(WATCHDOG_USEC_ENV, "200000");
+        std::env::remove_var(WATCHDOG_PID_ENV);
+
+        let daemon = Daemon::new().with_pid_file(&pid_path)?;
+        assert_eq!(
+            daemon.notify().and_then(|a| a.watchdog_interval()),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            std::fs::read_to_string(&pid_path)?.trim(),
+            std::process::id().to_string()
+        );
+
+        let url = url::Url::parse("ws://localhost:5052/").unwrap();
+        let cfg_ate = ConfAte::default();
+        let cfg_mesh = ConfMesh::solo_from_url(
+            &cfg_ate,
+            &url,
+            &IpAddr::from_str("::").unwrap(),
+            None,
+            None,
+        )
+        .await?;
+        let server = create_ethereal_centralized_server(&cfg_ate, &cfg_mesh).await?;
+
+        // Nothing is signalled until the server says that it is ready and
+        // then the heartbeats follow (none of the chains are stuck)
+        let mut buf = [0u8; 64];
+        assert!(socket.try_recv(&mut buf).is_err());
+        daemon.ready(&server);
+        assert_eq!(recv(&socket).await, "READY=1");
+        assert_eq!(recv(&socket).await, "WATCHDOG=1");
+        assert_eq!(recv(&socket).await, "WATCHDOG=1");
+
+        // The heartbeats stop before the server says that it is stopping
+        // and the pid file is gone once it has shut down
+        daemon.shutdown(&server).await;
+        let mut last = recv(&socket).await;
+        while last == "WATCHDOG=1" {
+            last = recv(&socket).await;
+        }
+        assert_eq!(last, "STOPPING=1");
+        assert!(pid_path.exists() == false);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(socket.try_recv(&mut buf).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    })
+}
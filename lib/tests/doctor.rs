@@ -0,0 +1,64 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::mesh::DoctorStage;
+use ate::mesh::DoctorStatus;
+use ate::prelude::*;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[cfg(all(feature = "enable_server", feature = "enable_client"))]
+#[test]
+fn doctor_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let listen = IpAddr::from_str("::").unwrap();
+        let cfg_ate = ConfAte::default();
+        let url = url::Url::parse("ws://localhost:5096/").unwrap();
+        let mut cfg_mesh = ConfMesh::solo_from_url(&cfg_ate, &url, &listen, None, None).await?;
+        cfg_mesh.wire_encryption = Some(KeySize::Bit256);
+        let server = create_ethereal_centralized_server(&cfg_ate, &cfg_mesh).await?;
+        let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+
+        let stage = |report: &DoctorReport, stage: DoctorStage| {
+            report
+                .checks
+                .iter()
+                .filter(|a| a.stage == stage)
+                .map(|a| a.status)
+                .next()
+        };
+
+        // Clients that offer weaker (or no) encryption are upgraded by the
+        // server so the key exchange passes just like a real connection
+        for offered in vec![None, Some(KeySize::Bit128), Some(KeySize::Bit256)] {
+            let options = DoctorOptions::default()
+                .with_wire_encryption(offered)
+                .with_chain(ChainKey::from("doctor"));
+            let report = doctor(&registry, &url, options).await;
+            assert!(report.is_healthy(), "{}", report);
+            assert_eq!(stage(&report, DoctorStage::KeyExchange), Some(DoctorStatus::Pass));
+            assert_eq!(stage(&report, DoctorStage::ChainOpen), Some(DoctorStatus::Pass));
+        }
+
+        // Nothing is listening so the connection fails and the rest is skipped
+        let closed = url::Url::parse("ws://localhost:5097/").unwrap();
+        let options = DoctorOptions::default().with_stage_timeout(Duration::from_secs(5));
+        let report = doctor(&registry, &closed, options).await;
+        assert_eq!(report.failed().map(|a| a.stage), Some(DoctorStage::Connect));
+        assert_eq!(stage(&report, DoctorStage::KeyExchange), Some(DoctorStatus::Skip));
+
+        server.shutdown().await;
+        Ok(())
+    })
+}
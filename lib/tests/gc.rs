@@ -0,0 +1,118 @@
+#![cfg(any(feature = "enable_server", feature = "enable_client"))]
+use ate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct Car {
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct Garage {
+    cars: DaoVec<Car>,
+}
+
+#[test]
+fn gc_orphans_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let mut conf = ConfAte::default();
+        conf.configured_for(ConfiguredFor::BestPerformance);
+        let builder = ChainBuilder::new(&conf).await.truncate(true).build();
+        let chain = builder.open(&ChainKey::from("gc-orphans")).await?;
+        let session = AteSessionUser::new();
+
+        // Two garages full of cars of which one is deleted
+        let (kept, deleted) = {
+            let dio = chain.dio_mut(&session).await?;
+            let mut kept = dio.store(Garage::default())?;
+            let mut deleted = dio.store(Garage::default())?;
+            for n in 0..10 {
+                kept.as_mut().cars.push(Car {
+                    name: format!("Kept {}", n),
+                })?;
+                deleted.as_mut().cars.push(Car {
+                    name: format!("Deleted {}", n),
+                })?;
+            }
+
+            // Rows can also hang off a virtual parent that never exists as a
+            // row of its own (e.g. buckets) and these are not orphans
+            for n in 0..5 {
+                let mut car = dio.store(Car {
+                    name: format!("Virtual {}", n),
+                })?;
+                car.attach_orphaned(&PrimaryKey::from("gc-virtual-parent"))?;
+            }
+            dio.commit().await?;
+            (kept.key().clone(), deleted.key().clone())
+        };
+        {
+            let dio = chain.dio_mut(&session).await?;
+            dio.delete(&deleted).await?;
+            dio.commit().await?;
+        }
+
+        let (scanned, orphans) = chain.find_orphans().await;
+        assert_eq!(scanned, 26);
+        assert_eq!(orphans.len(), 10);
+
+        // An interrupted collection picks up where it left off
+        let report = chain
+            .gc_orphans_ext(
+                &session,
+                GcOptions {
+                    batch_size: 3,
+                    max_batches: Some(2),
+                    ..GcOptions::default()
+                },
+            )
+            .await?;
+        assert_eq!(report.collected, 6);
+        assert_eq!(report.complete, false);
+
+        let stop = Arc::new(AtomicBool::new(true));
+        let report = chain
+            .gc_orphans_ext(
+                &session,
+                GcOptions {
+                    stop: Some(stop),
+                    ..GcOptions::default()
+                },
+            )
+            .await?;
+        assert_eq!(report.orphaned, 4);
+        assert_eq!(report.collected, 0);
+
+        let report = chain.gc_orphans(&session).await?;
+        assert_eq!(report.orphaned, 4);
+        assert_eq!(report.collected, 4);
+        assert_eq!(report.complete, true);
+
+        // Everything under the garage that was kept and the virtual parent
+        // is still there
+        let report = chain.gc_orphans(&session).await?;
+        assert_eq!(report.orphaned, 0);
+        {
+            let dio = chain.dio(&session).await;
+            let garage = dio.load::<Garage>(&kept).await?;
+            assert_eq!(garage.cars.iter().await?.count(), 10);
+            assert_eq!(dio.all_keys().await.len(), 16);
+        }
+
+        chain.single().await.destroy().await.unwrap();
+        Ok(())
+    })
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "enable_grpc")]
+#![allow(unused_imports)]
+use ate::comms::grpc::echo::EchoClient;
+use ate::comms::grpc::echo::EchoServer;
+use ate::comms::NodeId;
+use ate::comms::StreamProtocol;
+use ate::comms::StreamRouter;
+use ate::prelude::*;
+use ate::spec::SerializationFormat;
+use hyper::service::{make_service_fn, service_fn};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn grpc_echo_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        // The echo service is mounted on a router that is served by hyper
+        // in the same way the web server serves it
+        let mut router = StreamRouter::new(
+            SerializationFormat::MessagePack,
+            StreamProtocol::WebSocket,
+            None,
+            None,
+            NodeId::generate_server_id(0),
+            Duration::from_secs(30),
+        );
+        router.add_tonic_service(EchoServer::default()).await;
+        let router = Arc::new(router);
+
+        let make_service = make_service_fn(move |conn: &hyper::server::conn::AddrStream| {
+            let addr = conn.remote_addr();
+            let router = router.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let router = router.clone();
+                    async move {
+                        let resp = match router.grpc_request(req, addr).await {
+                            Ok(resp) => resp,
+                            Err(_) => {
+                                let mut resp = hyper::Response::new(hyper::Body::empty());
+                                *resp.status_mut() = hyper::StatusCode::NOT_FOUND;
+                                resp
+                            }
+                        };
+                        Ok::<_, Infallible>(resp)
+                    }
+                }))
+            }
+        });
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let server = hyper::Server::bind(&addr).serve(make_service);
+        let addr = server.local_addr();
+        TaskEngine::spawn(server);
+
+        // Call it using a tonic client
+        let mut client = EchoClient::connect(format!("http://{}", addr))
+            .await
+            .expect("failed to connect to the echo service");
+        let reply = client.echo("hello gRPC").await.expect("echo call failed");
+        assert_eq!(reply, "hello gRPC");
+        let reply = client.echo("again").await.expect("echo call failed");
+        assert_eq!(reply, "again");
+
+        Ok(())
+    })
+}
@@ -0,0 +1,155 @@
+#![cfg(any(feature = "enable_server", feature = "enable_client"))]
+use ate::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Person {
+    name: String,
+    age: i64,
+}
+
+impl DaoIndexed for Person {
+    const INDEX_NAME: &'static str = "person";
+    const INDEXED_FIELDS: &'static [&'static str] = &["name", "age"];
+
+    fn index_value(&self, field: &str) -> Option<IndexValue> {
+        match field {
+            "name" => Some(IndexValue::Text(self.name.clone())),
+            "age" => Some(IndexValue::Number(self.age)),
+            _ => None,
+        }
+    }
+}
+
+/// Same layout as a person but it is not registered hence its rows are
+/// written without any index entries
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct UnindexedPerson {
+    name: String,
+    age: i64,
+}
+
+fn names(rows: &Vec<Dao<Person>>) -> Vec<String> {
+    rows.iter().map(|a| a.name.clone()).collect()
+}
+
+#[test]
+fn index_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let mut conf = ConfAte::default();
+        conf.configured_for(ConfiguredFor::BestPerformance);
+        let builder = ChainBuilder::new(&conf).await.temporal(true).build();
+        let chain = builder.open(&ChainKey::from("index")).await?;
+        let session = AteSessionUser::new();
+        register_index::<Person>();
+
+        let (joe, _) = {
+            let dio = chain.dio_mut(&session).await?;
+            let mut keys = Vec::new();
+            for (name, age) in [("joe", 30), ("jane", 25), ("jim", 40)] {
+                let obj = dio.store(Person {
+                    name: name.to_string(),
+                    age,
+                })?;
+                keys.push(obj.key().clone());
+            }
+            dio.commit().await?;
+            (keys[0].clone(), keys)
+        };
+
+        // Rows are found by the value of a field or by a range of numbers
+        {
+            let dio = chain.dio(&session).await;
+            let found = dio.find_by::<Person>("name", "jane").await?;
+            assert_eq!(names(&found), vec!["jane".to_string()]);
+            assert!(dio.find_by::<Person>("name", "nobody").await?.is_empty());
+
+            let found = dio.find_range::<Person>("age", 26..=40).await?;
+            assert_eq!(names(&found), vec!["joe".to_string(), "jim".to_string()]);
+
+            assert!(dio.find_by::<Person>("missing", "joe").await.is_err());
+        }
+
+        // Updates unindex the old value and index the new one
+        {
+            let dio = chain.dio_mut(&session).await?;
+            let mut obj = dio.load::<Person>(&joe).await?;
+            obj.as_mut().name = "joseph".to_string();
+            obj.as_mut().age = 20;
+            drop(obj);
+            dio.commit().await?;
+        }
+        {
+            let dio = chain.dio(&session).await;
+            assert!(dio.find_by::<Person>("name", "joe").await?.is_empty());
+            assert_eq!(
+                names(&dio.find_by::<Person>("name", "joseph").await?),
+                vec!["joseph".to_string()]
+            );
+            assert_eq!(
+                names(&dio.find_range::<Person>("age", ..30).await?),
+                vec!["joseph".to_string(), "jane".to_string()]
+            );
+        }
+
+        // Deletes remove the row from the index
+        {
+            let dio = chain.dio_mut(&session).await?;
+            dio.delete(&joe).await?;
+            dio.commit().await?;
+        }
+        {
+            let dio = chain.dio(&session).await;
+            assert!(dio.find_by::<Person>("name", "joseph").await?.is_empty());
+            assert_eq!(
+                names(&dio.find_range::<Person>("age", ..).await?),
+                vec!["jane".to_string(), "jim".to_string()]
+            );
+        }
+
+        // Rows that were written without their index entries are picked up
+        // again once the index is repaired
+        let lost = {
+            let dio = chain.dio_mut(&session).await?;
+            let key = dio
+                .store(UnindexedPerson {
+                    name: "lost".to_string(),
+                    age: 50,
+                })?
+                .key()
+                .clone();
+            dio.commit().await?;
+            key
+        };
+        {
+            let dio = chain.dio(&session).await;
+            assert!(dio.find_by::<Person>("name", "lost").await?.is_empty());
+        }
+        {
+            let dio = chain.dio_mut(&session).await?;
+            assert_eq!(dio.repair_index::<Person>(vec![lost.clone()]).await?, 2);
+            dio.commit().await?;
+        }
+        {
+            let dio = chain.dio_mut(&session).await?;
+            assert_eq!(dio.repair_index::<Person>(vec![lost.clone()]).await?, 0);
+
+            let found = dio.find_by::<Person>("name", "lost").await?;
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].key(), &lost);
+        }
+
+        Ok(())
+    })
+}
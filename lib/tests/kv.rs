@@ -0,0 +1,145 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::kv::KvBucket;
+use ate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Counter {
+    value: u64,
+}
+
+#[cfg(all(feature = "enable_server", feature = "enable_client"))]
+#[test]
+fn kv_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let url = url::Url::parse("ws://localhost:5051/").unwrap();
+        let cfg_ate = ConfAte::default();
+        let cfg_mesh = ConfMesh::solo_from_url(
+            &cfg_ate,
+            &url,
+            &IpAddr::from_str("::").unwrap(),
+            None,
+            None,
+        )
+        .await?;
+        let server = create_ethereal_centralized_server(&cfg_ate, &cfg_mesh).await?;
+
+        // Two clients that each have their own connection to the server
+        let registry1 = Registry::new(&cfg_ate).await.temporal(true).cement();
+        let registry2 = Registry::new(&cfg_ate).await.temporal(true).cement();
+        let chain1 = registry1.open(&url, &ChainKey::from("kv-test"), false).await?;
+        let chain2 = registry2.open(&url, &ChainKey::from("kv-test"), false).await?;
+        let bucket1 = ate::kv::bucket(&chain1.as_arc(), "test");
+        let bucket2 = ate::kv::bucket(&chain2.as_arc(), "test");
+
+        // Basic operations
+        assert_eq!(bucket1.get("missing").await?, None);
+        bucket1.put("a/1", b"one".to_vec()).await?;
+        bucket1.put("a/2", b"two".to_vec()).await?;
+        bucket1.put("b/1", b"three".to_vec()).await?;
+        assert_eq!(bucket1.get("a/1").await?, Some(b"one".to_vec()));
+        bucket1.put("a/1", b"uno".to_vec()).await?;
+        assert_eq!(bucket1.get("a/1").await?, Some(b"uno".to_vec()));
+
+        // Listing only returns the keys with the prefix (and nothing from
+        // other buckets on the same chain)
+        ate::kv::bucket(&chain1.as_arc(), "other")
+            .put("a/3", b"four".to_vec())
+            .await?;
+        let list = bucket1.list_prefix("a/").await?;
+        assert_eq!(
+            list,
+            vec![
+                ("a/1".to_string(), b"uno".to_vec()),
+                ("a/2".to_string(), b"two".to_vec())
+            ]
+        );
+
+        // Short prefixes only read the shards that can match
+        assert_eq!(
+            bucket1
+                .list_prefix("")
+                .await?
+                .into_iter()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>(),
+            vec!["a/1".to_string(), "a/2".to_string(), "b/1".to_string()]
+        );
+        assert_eq!(bucket1.list_prefix("b").await?.len(), 1);
+        assert_eq!(bucket1.list_prefix("a/2").await?.len(), 1);
+
+        // Bucket names and keys can not run into each other
+        let nested = ate::kv::bucket(&chain1.as_arc(), "test:a");
+        nested.put("1", b"nested".to_vec()).await?;
+        bucket1.put("a:1", b"flat".to_vec()).await?;
+        assert_eq!(nested.get("1").await?, Some(b"nested".to_vec()));
+        assert_eq!(bucket1.get("a:1").await?, Some(b"flat".to_vec()));
+        assert!(bucket1.delete("a:1").await?);
+
+        // Deleting removes the key from the listing
+        assert!(bucket1.delete("a/2").await?);
+        assert!(bucket1.delete("a/2").await? == false);
+        assert_eq!(bucket1.list_prefix("a/").await?.len(), 1);
+
+        // Compare-and-swap only writes when the value matches
+        assert!(bucket1.compare_and_swap("cas", None, Some(b"x".to_vec())).await?);
+        assert!(bucket1.compare_and_swap("cas", None, Some(b"y".to_vec())).await? == false);
+        assert!(
+            bucket1
+                .compare_and_swap("cas", Some(b"y"), Some(b"z".to_vec()))
+                .await?
+                == false
+        );
+        assert!(bucket1.compare_and_swap("cas", Some(b"x"), None).await?);
+        assert_eq!(bucket1.get("cas").await?, None);
+
+        // Both clients increment the same counter concurrently, every
+        // increment must land exactly once
+        bucket1.put_as("counter", &Counter { value: 0 }).await?;
+        let increment = |bucket: KvBucket, times: u64| async move {
+            let mut conflicts = 0u64;
+            for _ in 0..times {
+                loop {
+                    let current = bucket.get_as::<Counter>("counter").await?.unwrap();
+                    let next = Counter {
+                        value: current.value + 1,
+                    };
+                    if bucket
+                        .compare_and_swap_as("counter", Some(&current), Some(&next))
+                        .await?
+                    {
+                        break;
+                    }
+                    conflicts += 1;
+                }
+            }
+            Result::<u64, KvError>::Ok(conflicts)
+        };
+        let (conflicts1, conflicts2) = futures::join!(
+            increment(bucket1.clone(), 20),
+            increment(bucket2.clone(), 20)
+        );
+        tracing::info!("kv conflicts {} and {}", conflicts1?, conflicts2?);
+        assert_eq!(
+            bucket1.get_as::<Counter>("counter").await?,
+            Some(Counter { value: 40 })
+        );
+
+        server.shutdown().await;
+        Ok(())
+    })
+}
@@ -0,0 +1,110 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use tracing::{debug, error, info, warn};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct Car {
+    name: String,
+    data: Vec<u128>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct Garage {
+    cars: DaoVec<Car>,
+}
+
+#[cfg(any(feature = "enable_server", feature = "enable_client"))]
+#[test]
+fn load_parallel_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let read_key = EncryptKey::generate(KeySize::Bit256);
+        let mut session = AteSessionUser::new();
+        session
+            .user
+            .properties
+            .push(AteSessionProperty::ReadKey(read_key.clone()));
+
+        let mut conf = ConfAte::default();
+        conf.configured_for(ConfiguredFor::BestPerformance);
+        let builder = ChainBuilder::new(&conf).await.truncate(true).build();
+        let chain = builder.open(&ChainKey::from("load-parallel")).await?;
+
+        // Fill a collection with encrypted rows
+        info!("create::running");
+        let garage_key = {
+            let dio = chain.dio_mut(&session).await?;
+            let mut garage = dio.store(Garage::default())?;
+            garage.auth_mut().read = ReadOption::from_key(&read_key);
+            for n in 0..10000 {
+                garage.as_mut().cars.push(Car {
+                    name: format!("Car {}", n),
+                    data: vec![n as u128; 32],
+                })?;
+            }
+            dio.commit().await?;
+            garage.key().clone()
+        };
+        info!("create::finished");
+
+        // Load them one at a time
+        let keys = {
+            let dio = chain.dio(&session).await;
+            let garage = dio.load::<Garage>(&garage_key).await?;
+            let start = Instant::now();
+            let cars = garage.cars.iter().await?.count();
+            assert_eq!(cars, 10000);
+            info!("load::sequential took {}ms", start.elapsed().as_millis());
+            dio.children_keys(garage_key.clone(), garage.cars.vec_id())
+                .await?
+        };
+
+        // Load them in parallel from a fresh DIO (so nothing is cached)
+        {
+            let dio = chain.dio(&session).await;
+            let garage = dio.load::<Garage>(&garage_key).await?;
+            let start = Instant::now();
+            let cars = garage.cars.load_all_parallel(64).await?;
+            info!("load::parallel took {}ms", start.elapsed().as_millis());
+            assert_eq!(cars.len(), 10000);
+            for (car, key) in cars.iter().zip(keys.iter()) {
+                assert_eq!(car.as_ref().unwrap().key(), key);
+            }
+        }
+
+        // Rows that are missing fail on their own and the order is kept
+        {
+            let dio = chain.dio(&session).await;
+            let missing = PrimaryKey::generate();
+            let ret = dio
+                .load_many_parallel::<Car>(
+                    vec![keys[1].clone(), missing.clone(), keys[0].clone()],
+                    2,
+                )
+                .await;
+            assert_eq!(ret.len(), 3);
+            assert_eq!(ret[0].as_ref().unwrap().key(), &keys[1]);
+            assert!(matches!(
+                ret[1],
+                Err(LoadError(LoadErrorKind::NotFound(_), _))
+            ));
+            assert_eq!(ret[2].as_ref().unwrap().key(), &keys[0]);
+        }
+
+        chain.single().await.destroy().await.unwrap();
+        Ok(())
+    })
+}
@@ -53,7 +53,7 @@ fn load_test() -> Result<(), AteError> {
             // Do a whole let of work
             info!("create::running");
             for _ in 0..100 {
-                let dio = chain.dio_mut(&session).await;
+                let dio = chain.dio_mut(&session).await?;
                 for _ in 0..100 {
                     dio.store(test_obj.clone())?;
                 }
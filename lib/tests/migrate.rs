@@ -0,0 +1,119 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::prelude::*;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[cfg(all(feature = "enable_server", feature = "enable_client"))]
+#[test]
+fn migrate_test() -> Result<(), AteError> {
+    use ate::mesh::MigrationPhase;
+
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let cfg_ate = ConfAte::default();
+        let listen = IpAddr::from_str("::").unwrap();
+        let src_url = url::Url::parse("ws://localhost:5061/").unwrap();
+        let dst_url = url::Url::parse("ws://localhost:5062/").unwrap();
+        let cfg_src = ConfMesh::solo_from_url(&cfg_ate, &src_url, &listen, None, None).await?;
+        let cfg_dst = ConfMesh::solo_from_url(&cfg_ate, &dst_url, &listen, None, None).await?;
+        let src = create_ethereal_centralized_server(&cfg_ate, &cfg_src).await?;
+        let dst = create_ethereal_centralized_server(&cfg_ate, &cfg_dst).await?;
+
+        // Write some data into the chain on the source root
+        let key = ChainKey::from("migrate-tenant");
+        let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+        let session = AteSessionUser::new();
+        let chain = registry.open(&src_url, &key, false).await?;
+        let before = {
+            let dio = chain.dio_mut(&session).await?;
+            let key = dio.store("before".to_string())?.key().clone();
+            dio.commit().await?;
+            key
+        };
+
+        // Enter migrate mode (only one migration may run at a time)
+        dst.accept_migration("/", &key);
+        src.start_migration("/", &key, dst_url.clone()).await?;
+        assert!(matches!(
+            src.start_migration("/", &key, dst_url.clone()).await,
+            Err(ChainCreationError(ChainCreationErrorKind::MigrationInProgress(_), _))
+        ));
+
+        // Writes are still accepted by the source while it is migrating
+        let during = {
+            let dio = chain.dio_mut(&session).await?;
+            let key = dio.store("during".to_string())?.key().clone();
+            dio.commit().await?;
+            key
+        };
+
+        // Wait for the history to be copied and then cut over
+        let mut attempts = 0;
+        loop {
+            let phase = src.migrations().into_iter().next().unwrap().phase;
+            match phase {
+                MigrationPhase::Forwarding => break,
+                MigrationPhase::Copying if attempts < 100 => {
+                    attempts += 1;
+                    ate::engine::sleep(Duration::from_millis(100)).await;
+                }
+                phase => panic!("the migration did not copy the history ({})", phase),
+            }
+        }
+        let status = src.cutover_migration("/", &key).await?;
+        assert_eq!(status.phase, MigrationPhase::Migrated);
+
+        // Every commit that was acknowledged is served by the destination
+        {
+            let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+            let moved = registry.open(&dst_url, &key, false).await?;
+            let dio = moved.dio(&session).await;
+            assert_eq!(*dio.load::<String>(&before).await?, "before".to_string());
+            assert_eq!(*dio.load::<String>(&during).await?, "during".to_string());
+        }
+
+        // Clients that still connect to the source are relayed to the destination
+        {
+            let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+            let relayed = registry.open(&src_url, &key, false).await?;
+            let dio = relayed.dio(&session).await;
+            assert_eq!(*dio.load::<String>(&during).await?, "during".to_string());
+        }
+
+        // Once migrated the chain can no longer be aborted
+        assert!(matches!(
+            src.abort_migration("/", &key).await,
+            Err(ChainCreationError(ChainCreationErrorKind::ChainMigrated(..), _))
+        ));
+
+        // An aborted migration leaves the source authoritative
+        let other = ChainKey::from("migrate-aborted");
+        let chain = registry.open(&src_url, &other, false).await?;
+        dst.accept_migration("/", &other);
+        src.start_migration("/", &other, dst_url.clone()).await?;
+        src.abort_migration("/", &other).await?;
+        assert!(dst.release_migration("/", &other));
+        assert!(src.migrations().iter().all(|a| a.chain != other));
+        {
+            let dio = chain.dio_mut(&session).await?;
+            dio.store("after".to_string())?;
+            dio.commit().await?;
+        }
+
+        src.shutdown().await;
+        dst.shutdown().await;
+        Ok(())
+    })
+}
@@ -0,0 +1,199 @@
+#![cfg(any(feature = "enable_server", feature = "enable_client"))]
+use ate::prelude::*;
+use serde::{Deserialize, Serialize};
+#[cfg(all(feature = "enable_server", feature = "enable_client"))]
+use std::net::IpAddr;
+#[cfg(all(feature = "enable_server", feature = "enable_client"))]
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct MyTestObject {
+    firstname: String,
+    lastname: String,
+    data: Vec<u32>,
+}
+
+#[test]
+fn patch_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let mut conf = ConfAte::default();
+        conf.configured_for(ConfiguredFor::BestPerformance);
+        conf.max_patch_depth = 3;
+        conf.sync_tolerance = Duration::from_secs(0);
+        let builder = ChainBuilder::new(&conf).await.temporal(true).build();
+        let chain = builder.open(&ChainKey::from("patch")).await?;
+        let session = AteSessionUser::new();
+
+        let key = {
+            let dio = chain.dio_mut(&session).await?;
+            let obj = dio.store(MyTestObject {
+                firstname: "Joe".to_string(),
+                lastname: "Blogs".to_string(),
+                data: (0..100).collect(),
+            })?;
+            let key = obj.key().clone();
+            dio.commit().await?;
+            key
+        };
+
+        // Several edits in a row go past the maximum depth and get consolidated
+        for n in 0..5u32 {
+            let dio = chain.dio_mut(&session).await?;
+            let mut obj = dio.load::<MyTestObject>(&key).await?;
+            obj.as_mut_patch().data[0] = n;
+            dio.commit().await?;
+        }
+        {
+            let dio = chain.dio(&session).await;
+            let obj = dio.load::<MyTestObject>(&key).await?;
+            assert_eq!(obj.firstname, "Joe");
+            assert_eq!(obj.data[0], 4);
+            assert_eq!(obj.data.len(), 100);
+        }
+
+        // Repeated commits of the same object patch on top of what was written
+        {
+            let dio = chain.dio_mut(&session).await?;
+            let mut obj = dio.load::<MyTestObject>(&key).await?;
+            obj.as_mut_patch().firstname = "John".to_string();
+            dio.commit().await?;
+            obj.as_mut_patch().lastname = "Smith".to_string();
+            dio.commit().await?;
+        }
+        {
+            let dio = chain.dio(&session).await;
+            let obj = dio.load::<MyTestObject>(&key).await?;
+            assert_eq!(obj.firstname, "John");
+            assert_eq!(obj.lastname, "Smith");
+        }
+
+        // Two writers patching the same version conflict with each other
+        let dio1 = chain.dio_mut(&session).await?;
+        let dio2 = chain.dio_mut(&session).await?;
+        let mut obj1 = dio1.load::<MyTestObject>(&key).await?;
+        let mut obj2 = dio2.load::<MyTestObject>(&key).await?;
+        obj1.as_mut_patch().firstname = "Jane".to_string();
+        obj2.as_mut_patch().lastname = "Doe".to_string();
+        dio1.commit().await?;
+        assert!(matches!(
+            dio2.commit().await,
+            Err(CommitError(CommitErrorKind::PatchConflict(_), _))
+        ));
+        drop(dio1);
+        drop(dio2);
+
+        // Folding rewrites the row in full so compacting the chain removes
+        // the patches behind it without losing any of the edits
+        assert!(chain.fold_patches(&session).await? >= 1);
+        assert_eq!(chain.fold_patches(&session).await?, 0);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let before = chain.count().await;
+        chain.compact().await?;
+        assert!(chain.count().await < before);
+        {
+            let dio = chain.dio(&session).await;
+            let obj = dio.load::<MyTestObject>(&key).await?;
+            assert_eq!(obj.firstname, "Jane");
+            assert_eq!(obj.lastname, "Smith");
+            assert_eq!(obj.data[0], 4);
+            assert_eq!(obj.data.len(), 100);
+        }
+
+        // Patches still work on top of the folded row
+        {
+            let dio = chain.dio_mut(&session).await?;
+            let mut obj = dio.load::<MyTestObject>(&key).await?;
+            obj.as_mut_patch().data[1] = 42;
+            dio.commit().await?;
+        }
+        {
+            let dio = chain.dio(&session).await;
+            let obj = dio.load::<MyTestObject>(&key).await?;
+            assert_eq!(obj.firstname, "Jane");
+            assert_eq!(obj.data[1], 42);
+        }
+
+        chain.single().await.destroy().await.unwrap();
+        Ok(())
+    })
+}
+
+#[cfg(all(feature = "enable_server", feature = "enable_client"))]
+#[test]
+fn patch_conflict_remote_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let listen = IpAddr::from_str("::").unwrap();
+        let session = AteSessionUser::new();
+        let chain_key = ChainKey::from("patch-conflict");
+
+        let cfg_ate = ConfAte::default();
+        let url = url::Url::parse("ws://localhost:5093/").unwrap();
+        let cfg_mesh = ConfMesh::solo_from_url(&cfg_ate, &url, &listen, None, None).await?;
+        let server = create_ethereal_centralized_server(&cfg_ate, &cfg_mesh).await?;
+
+        let registry1 = Registry::new(&cfg_ate).await.temporal(true).cement();
+        let chain1 = registry1.open(&url, &chain_key, false).await?;
+        let key = {
+            let dio = chain1.dio_mut(&session).await?;
+            let obj = dio.store(MyTestObject {
+                firstname: "Joe".to_string(),
+                lastname: "Blogs".to_string(),
+                data: (0..100).collect(),
+            })?;
+            let key = obj.key().clone();
+            dio.commit().await?;
+            key
+        };
+
+        let registry2 = Registry::new(&cfg_ate).await.temporal(true).cement();
+        let chain2 = registry2.open(&url, &chain_key, false).await?;
+
+        // Two clients patching the same version of a row are told about the
+        // conflict whether it is found by the client or by the root
+        let dio1 = chain1.dio_mut(&session).await?;
+        let dio2 = chain2.dio_mut(&session).await?;
+        let mut obj1 = dio1.load::<MyTestObject>(&key).await?;
+        let mut obj2 = dio2.load::<MyTestObject>(&key).await?;
+        obj1.as_mut_patch().firstname = "Jane".to_string();
+        obj2.as_mut_patch().lastname = "Doe".to_string();
+        dio1.commit().await?;
+        assert!(matches!(
+            dio2.commit().await,
+            Err(CommitError(CommitErrorKind::PatchConflict(_), _))
+        ));
+
+        // The rejection does not break the connection of the second client
+        {
+            let dio = chain2.dio_mut(&session).await?;
+            let mut obj = dio.load::<MyTestObject>(&key).await?;
+            obj.as_mut().lastname = "Doe".to_string();
+            dio.commit().await?;
+        }
+
+        server.shutdown().await;
+        Ok(())
+    })
+}
@@ -0,0 +1,143 @@
+#![cfg(any(feature = "enable_server", feature = "enable_client"))]
+use ate::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Export {
+    binary: String,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Instance {
+    name: String,
+    replicas: i64,
+    export: Export,
+}
+
+impl DaoIndexed for Instance {
+    const INDEX_NAME: &'static str = "instance";
+    const INDEXED_FIELDS: &'static [&'static str] = &["name", "replicas"];
+
+    fn index_value(&self, field: &str) -> Option<IndexValue> {
+        match field {
+            "name" => Some(IndexValue::Text(self.name.clone())),
+            "replicas" => Some(IndexValue::Number(self.replicas)),
+            _ => None,
+        }
+    }
+}
+
+/// Rows that are not indexed hence they can only be queried by scanning
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Note {
+    text: String,
+}
+
+fn names(rows: &Vec<Dao<Instance>>) -> Vec<String> {
+    let mut ret = rows.iter().map(|a| a.name.clone()).collect::<Vec<_>>();
+    ret.sort();
+    ret
+}
+
+#[test]
+fn query_parse_test() {
+    assert!(Query::parse("export.binary == 'foo' and not (replicas < 2)").is_ok());
+    assert!(Query::parse("name contains \"web\" || tags.0 != null").is_ok());
+
+    match Query::parse("replicas >").map_err(|a| a.0) {
+        Err(QueryErrorKind::Parse(position, expected, found)) => {
+            assert_eq!(position, 11);
+            assert!(expected.starts_with("a value"));
+            assert_eq!(found, "the end of the query");
+        }
+        ret => panic!("unexpected result - {:?}", ret),
+    }
+    match Query::parse("(name == 'a' replicas").map_err(|a| a.0) {
+        Err(QueryErrorKind::Parse(position, expected, found)) => {
+            assert_eq!(position, 14);
+            assert_eq!(expected, "')'");
+            assert_eq!(found, "'replicas'");
+        }
+        ret => panic!("unexpected result - {:?}", ret),
+    }
+    assert!(Query::parse("name == 'unterminated").is_err());
+    assert!(Query::parse("name ~ 'a'").is_err());
+}
+
+#[test]
+fn query_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let mut conf = ConfAte::default();
+        conf.configured_for(ConfiguredFor::BestPerformance);
+        let builder = ChainBuilder::new(&conf).await.temporal(true).build();
+        let chain = builder.open(&ChainKey::from("query")).await?;
+        let session = AteSessionUser::new();
+        register_index::<Instance>();
+
+        {
+            let dio = chain.dio_mut(&session).await?;
+            for (name, replicas, binary) in [("web", 3, "nginx"), ("api", 1, "foo"), ("db", 2, "foo")] {
+                dio.store(Instance {
+                    name: name.to_string(),
+                    replicas,
+                    export: Export {
+                        binary: binary.to_string(),
+                        tags: vec![format!("{}-tag", name)],
+                    },
+                })?;
+            }
+            dio.store(Note {
+                text: "foo".to_string(),
+            })?;
+            dio.commit().await?;
+        }
+
+        let dio = chain.dio(&session).await;
+
+        // Indexed fields are looked up and the rest of the query is checked
+        // against the rows that they return
+        let found = dio.query::<Instance>(None, "name == 'db' and replicas > 1").await?;
+        assert_eq!(names(&found), vec!["db".to_string()]);
+        let found = dio.query::<Instance>(None, "replicas >= 2 and replicas < 3").await?;
+        assert_eq!(names(&found), vec!["db".to_string()]);
+
+        // Fields that are not indexed are found by scanning
+        let found = dio.query::<Instance>(None, "export.binary == 'foo'").await?;
+        assert_eq!(names(&found), vec!["api".to_string(), "db".to_string()]);
+        let found = dio
+            .query::<Instance>(None, "export.tags contains 'web-tag' or not (replicas != 1)")
+            .await?;
+        assert_eq!(names(&found), vec!["api".to_string(), "web".to_string()]);
+
+        // Scans that would read more rows than allowed fail
+        let query = Query::parse("export.binary == 'foo'")?.with_scan_limit(2);
+        match dio.query_ext::<Instance>(None, &query).await.map_err(|a| a.0) {
+            Err(QueryErrorKind::ScanLimitExceeded(2)) => {}
+            ret => panic!("unexpected result - {:?}", ret.map(|a| a.len())),
+        }
+
+        // Rows can be queried without knowing their type
+        let query = Query::parse("text contains 'fo'")?;
+        let rows = dio.query_rows(None, None, &query).await?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].1["text"], "foo");
+        let rows = dio
+            .query_rows(None, Some(std::any::type_name::<Instance>()), &query)
+            .await?;
+        assert!(rows.is_empty());
+
+        Ok(())
+    })
+}
@@ -0,0 +1,108 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::mesh::QuorumPolicy;
+use ate::prelude::*;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+
+#[test]
+fn quorum_policy_test() {
+    let policy = QuorumPolicy::default();
+    assert!(!policy.is_active());
+
+    let replica = url::Url::parse("ws://localhost:5082/").unwrap();
+    let policy = policy.with_replica(replica).with_required(3);
+    assert!(policy.is_active());
+    assert_eq!(policy.quorum(), 1);
+    assert!(!policy.clone().with_required(0).is_active());
+}
+
+#[cfg(all(feature = "enable_server", feature = "enable_client"))]
+#[test]
+fn quorum_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let listen = IpAddr::from_str("::").unwrap();
+        let session = AteSessionUser::new();
+        let key = ChainKey::from("quorum-critical");
+
+        // A primary whose replica is up confirms the commits that the
+        // replica has accepted
+        let cfg_ate = ConfAte::default();
+        let replica_url = url::Url::parse("ws://localhost:5082/").unwrap();
+        let cfg_replica = ConfMesh::solo_from_url(&cfg_ate, &replica_url, &listen, None, None).await?;
+        let replica = create_ethereal_centralized_server(&cfg_ate, &cfg_replica).await?;
+
+        let mut cfg_primary = cfg_ate.clone();
+        cfg_primary.quorum_policy = Some(
+            QuorumPolicy::default()
+                .with_replica(replica_url.clone())
+                .with_timeout(Duration::from_secs(5)),
+        );
+        let primary_url = url::Url::parse("ws://localhost:5081/").unwrap();
+        let cfg_mesh = ConfMesh::solo_from_url(&cfg_primary, &primary_url, &listen, None, None).await?;
+        let primary = create_ethereal_centralized_server(&cfg_primary, &cfg_mesh).await?;
+
+        let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+        let chain = registry.open(&primary_url, &key, false).await?;
+        let accepted = {
+            let dio = chain.dio_mut(&session).await?;
+            let key = dio.store("accepted".to_string())?.key().clone();
+            dio.commit().await?;
+            key
+        };
+        {
+            let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+            let copy = registry.open(&replica_url, &key, false).await?;
+            let dio = copy.dio(&session).await;
+            assert_eq!(*dio.load::<String>(&accepted).await?, "accepted".to_string());
+        }
+
+        // A primary that can not reach a quorum rejects the commit within
+        // the timeout (even with retries) and does not keep the write
+        let timeout = Duration::from_secs(2);
+        let mut cfg_orphan = cfg_ate.clone();
+        cfg_orphan.quorum_policy = Some(
+            QuorumPolicy::default()
+                .with_replica(url::Url::parse("ws://localhost:5084/").unwrap())
+                .with_relay_attempts(3)
+                .with_timeout(timeout),
+        );
+        let orphan_url = url::Url::parse("ws://localhost:5083/").unwrap();
+        let cfg_mesh = ConfMesh::solo_from_url(&cfg_orphan, &orphan_url, &listen, None, None).await?;
+        let orphan = create_ethereal_centralized_server(&cfg_orphan, &cfg_mesh).await?;
+
+        let chain = registry.open(&orphan_url, &key, false).await?;
+        let rejected = {
+            let dio = chain.dio_mut(&session).await?;
+            let key = dio.store("rejected".to_string())?.key().clone();
+            let start = Instant::now();
+            assert!(dio.commit().await.is_err());
+            assert!(start.elapsed() < timeout * 2);
+            key
+        };
+        {
+            let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+            let reopened = registry.open(&orphan_url, &key, false).await?;
+            let dio = reopened.dio(&session).await;
+            assert!(dio.load::<String>(&rejected).await.is_err());
+        }
+
+        primary.shutdown().await;
+        replica.shutdown().await;
+        orphan.shutdown().await;
+        Ok(())
+    })
+}
@@ -0,0 +1,84 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::prelude::*;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+#[cfg(all(feature = "enable_server", feature = "enable_client"))]
+#[test]
+fn chain_quota_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let url = url::Url::parse("ws://localhost:5072/").unwrap();
+        let cfg_ate = ConfAte::default();
+        let cfg_mesh = ConfMesh::solo_from_url(
+            &cfg_ate,
+            &url,
+            &IpAddr::from_str("::").unwrap(),
+            None,
+            None,
+        )
+        .await?;
+        let root = create_ethereal_centralized_server(&cfg_ate, &cfg_mesh).await?;
+
+        let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+        let session = AteSessionUser::new();
+        let key = ChainKey::from("quota-test");
+        let chain = registry.open(&url, &key, false).await?;
+
+        // The status is reported for the chain that the client subscribed to
+        let status = chain
+            .quota_status()
+            .await?
+            .expect("the chain is not connected to a root");
+        assert_eq!(status.limit, None);
+        let limit = status.used + 10000;
+        root.set_chain_quota("/", limit);
+
+        // Crossing the warning threshold sends the status to the session
+        // that asked for it before
+        {
+            let dio = chain.dio_mut(&session).await?;
+            dio.store("a".repeat(8500))?;
+            dio.commit().await?;
+        }
+        let warning = chain.metrics().lock().unwrap().quota_warning.clone();
+        match warning {
+            Some((used, warn_limit)) => {
+                assert!(used >= status.used + 8500);
+                assert_eq!(warn_limit, limit);
+            }
+            None => panic!("the root did not warn that the chain is nearly full"),
+        }
+
+        // Commits that do not fit are rejected with the typed error
+        {
+            let dio = chain.dio_mut(&session).await?;
+            dio.store("b".repeat(5000))?;
+            match dio.commit().await {
+                Ok(_) => panic!("the commit exceeded the quota but was accepted"),
+                Err(err) => {
+                    let err = err.to_string();
+                    assert!(err.contains("COMMIT_0014"), "unexpected error - {}", err);
+                }
+            }
+        }
+
+        let status = registry.quota_status(&url, &key).await?;
+        assert_eq!(status.limit, Some(limit));
+        assert!(status.percent().unwrap() >= QUOTA_WARN_PERCENT);
+
+        root.shutdown().await;
+        Ok(())
+    })
+}
@@ -0,0 +1,66 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::prelude::*;
+
+#[cfg(feature = "enable_local_fs")]
+#[test]
+fn read_only_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let mut conf = ConfAte::default();
+        conf.log_path = Some("/tmp/ate".to_string());
+        conf.configured_for(ConfiguredFor::BestPerformance);
+        let key = ChainKey::from("read-only");
+        let session = AteSessionUser::new();
+
+        // Write a test object while the chain is writable
+        let key1 = {
+            let builder = ChainBuilder::new(&conf).await.truncate(true).build();
+            let chain = builder.open(&key).await?;
+            assert!(chain.is_writable());
+
+            let dio = chain.dio_mut(&session).await?;
+            let key1 = dio.store("blah!".to_string())?.key().clone();
+            dio.commit().await?;
+            chain.flush().await?;
+            key1
+        };
+
+        // Reopen it read-only which still allows the data to be read
+        {
+            let builder = ChainBuilder::new(&conf).await.read_only(true).build();
+            let chain = builder.open(&key).await?;
+            assert!(chain.is_writable() == false);
+
+            let dio = chain.dio(&session).await;
+            assert_eq!(*dio.load::<String>(&key1).await?, "blah!".to_string());
+
+            // Any attempt to modify it fails before anything is queued
+            match chain.dio_mut(&session).await {
+                Err(CommitError(CommitErrorKind::ChainReadOnly(_), _)) => {}
+                Err(err) => panic!("unexpected error - {}", err),
+                Ok(_) => panic!("a read-only chain allowed a mutable data access layer"),
+            }
+            assert!(matches!(
+                chain.dio_trans(&session, TransactionScope::Full).await,
+                Err(CommitError(CommitErrorKind::ChainReadOnly(_), _))
+            ));
+        }
+
+        // Clean up
+        let builder = ChainBuilder::new(&conf).await.build();
+        let chain = builder.open(&key).await?;
+        chain.single().await.destroy().await.unwrap();
+        Ok(())
+    })
+}
@@ -0,0 +1,128 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::event::EventHeader;
+use ate::prelude::*;
+use ate::transaction::ConversationSession;
+use ate::validator::ValidationResult;
+use std::sync::Arc;
+
+/// Denies the events of one row so that a replay has something to reject
+#[derive(Clone)]
+struct DenyRowValidator {
+    key: PrimaryKey,
+}
+
+impl ate::validator::EventValidator for DenyRowValidator {
+    fn validate(
+        &self,
+        header: &EventHeader,
+        _conversation: Option<&Arc<ConversationSession>>,
+    ) -> Result<ValidationResult, ValidationError> {
+        match header.meta.get_data_key() {
+            Some(key) if key == self.key => Ok(ValidationResult::Deny),
+            _ => Ok(ValidationResult::Abstain),
+        }
+    }
+
+    fn clone_validator(&self) -> Box<dyn ate::validator::EventValidator> {
+        Box::new(self.clone())
+    }
+
+    fn validator_name(&self) -> &str {
+        "deny-row-validator"
+    }
+}
+
+#[cfg(feature = "enable_local_fs")]
+#[test]
+fn replay_is_deterministic() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let mut conf = ConfAte::default();
+        conf.log_path = Some("/tmp/ate".to_string());
+        conf.configured_for(ConfiguredFor::BestPerformance);
+        let key = ChainKey::from("replay");
+        let session = AteSessionUser::new();
+
+        // Write some rows (and delete a few) so the log has some history
+        let denied = {
+            let builder = ChainBuilder::new(&conf).await.truncate(true).build();
+            let chain = builder.open(&key).await?;
+            let dio = chain.dio_mut(&session).await?;
+            let mut rows = Vec::new();
+            for n in 0..50u32 {
+                rows.push(dio.store(format!("row-{}", n))?);
+            }
+            dio.commit().await?;
+            let denied = rows[1].key().clone();
+            for row in rows.into_iter().step_by(5) {
+                row.delete()?;
+            }
+            dio.commit().await?;
+            chain.flush().await?;
+            denied
+        };
+
+        // Two replays of the same log end up in exactly the same state
+        let builder = ChainBuilder::new(&conf).await.build();
+        let options = || ReplayOptions {
+            snapshot_every: 10,
+            ..Default::default()
+        };
+        let first = Chain::replay_from(&builder, &key, None, options()).await?;
+        let second = Chain::replay_from(&builder, &key, None, options()).await?;
+        assert!(first.snapshots.len() > 1);
+        assert_eq!(first.hooks, second.hooks);
+        assert_eq!(first.snapshots, second.snapshots);
+
+        assert_eq!(first.rejected, second.rejected);
+        assert!(first.snapshots.last().unwrap().rows >= 40);
+
+        // Stopping early reproduces the earlier snapshots
+        let partial = Chain::replay_from(
+            &builder,
+            &key,
+            None,
+            ReplayOptions {
+                snapshot_every: 10,
+                stop_after: Some(20),
+                ..Default::default()
+            },
+        )
+        .await?;
+        assert_eq!(partial.snapshots[..], first.snapshots[..2]);
+
+        // Rejected events are left out of the state just like on a live chain
+        let strict = ChainBuilder::new(&conf)
+            .await
+            .add_validator(Box::new(DenyRowValidator {
+                key: denied.clone(),
+            }))
+            .build();
+        let report = Chain::replay_from(&strict, &key, None, options()).await?;
+        assert_eq!(report.rejected.len(), first.rejected.len() + 1);
+        assert_eq!(
+            report.snapshots.last().unwrap().rows,
+            first.snapshots.last().unwrap().rows - 1
+        );
+        assert_eq!(
+            report.snapshots.last().unwrap().events,
+            first.snapshots.last().unwrap().events
+        );
+
+        // Clean up
+        let chain = builder.open(&key).await?;
+        chain.single().await.destroy().await.unwrap();
+        Ok(())
+    })
+}
@@ -36,7 +36,7 @@ fn rotate_test() -> Result<(), AteError> {
 
             {
                 // Write a test object
-                let dio = chain.dio_mut(&session).await;
+                let dio = chain.dio_mut(&session).await?;
                 key1 = dio.store("blah!".to_string())?.key().clone();
                 dio.commit().await?;
             }
@@ -46,7 +46,7 @@ fn rotate_test() -> Result<(), AteError> {
 
             {
                 // Write a test object
-                let dio = chain.dio_mut(&session).await;
+                let dio = chain.dio_mut(&session).await?;
                 key2 = dio.store("haha!".to_string())?.key().clone();
                 dio.commit().await?;
             }
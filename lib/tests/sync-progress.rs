@@ -0,0 +1,145 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::event::*;
+use ate::loader::*;
+use ate::meta::Metadata;
+use ate::prelude::*;
+use ate::spec::MessageFormat;
+use bytes::Bytes;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+fn test_event(len: usize) -> EventWeakData {
+    EventWeakData {
+        meta: Metadata::default(),
+        data_bytes: MessageBytes::Some(Bytes::from(vec![0u8; len])),
+        format: MessageFormat {
+            meta: SerializationFormat::Json,
+            data: SerializationFormat::Json,
+        },
+    }
+}
+
+#[test]
+fn sync_progress_loader_test() {
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    rt.block_on(async {
+        // Servers that report the size of the history give a fraction
+        let (mut loader, rx) = SyncProgressLoader::new();
+        assert!(rx.borrow().total.is_none());
+        assert_eq!(rx.borrow().fraction(), None);
+
+        loader.history_bytes(100);
+        loader.start_of_history(4).await;
+        assert_eq!(
+            rx.borrow().total,
+            Some(SyncTotal {
+                events: 4,
+                bytes: Some(100)
+            })
+        );
+        assert_eq!(rx.borrow().fraction(), Some(0.0));
+
+        loader.feed_events(&vec![test_event(25), test_event(25)]);
+        assert_eq!(rx.borrow().events_received, 2);
+        assert_eq!(rx.borrow().bytes_received, 50);
+        assert_eq!(rx.borrow().fraction(), Some(0.5));
+
+        // The reported size is only approximate so it never goes past the end
+        loader.feed_events(&vec![test_event(80), test_event(0)]);
+        assert_eq!(rx.borrow().fraction(), Some(1.0));
+        assert!(rx.borrow().complete == false);
+
+        loader.end_of_history().await;
+        assert!(rx.borrow().complete);
+        assert_eq!(rx.borrow().events_received, 4);
+
+        // Older servers do not report the size so the progress is indeterminate
+        // until the history has been received
+        let (mut loader, rx) = SyncProgressLoader::new();
+        loader.start_of_history(1).await;
+        assert_eq!(
+            rx.borrow().total,
+            Some(SyncTotal {
+                events: 1,
+                bytes: None
+            })
+        );
+        loader.feed_events(&vec![test_event(10)]);
+        assert_eq!(rx.borrow().fraction(), None);
+        loader.end_of_history().await;
+        assert_eq!(rx.borrow().fraction(), Some(1.0));
+
+        // The size of one sync is not carried over to the next
+        let (mut loader, rx) = SyncProgressLoader::new();
+        loader.history_bytes(10);
+        loader.start_of_history(0).await;
+        loader.start_of_history(0).await;
+        assert_eq!(rx.borrow().total.unwrap().bytes, None);
+    });
+}
+
+#[cfg(all(feature = "enable_server", feature = "enable_client"))]
+#[test]
+fn sync_progress_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let listen = IpAddr::from_str("::").unwrap();
+        let session = AteSessionUser::new();
+        let key = ChainKey::from("sync-progress");
+
+        let cfg_ate = ConfAte::default();
+        let url = url::Url::parse("ws://localhost:5101/").unwrap();
+        let cfg_mesh = ConfMesh::solo_from_url(&cfg_ate, &url, &listen, None, None).await?;
+        let server = create_ethereal_centralized_server(&cfg_ate, &cfg_mesh).await?;
+
+        {
+            let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+            let chain = registry.open(&url, &key, false).await?;
+            let dio = chain.dio_mut(&session).await?;
+            for n in 0..10u32 {
+                dio.store(n)?;
+            }
+            dio.commit().await?;
+        }
+
+        // A fresh client receives the whole history and reports it as done
+        // once the chain is open
+        let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+        let chain = registry.open(&url, &key, false).await?;
+        {
+            let progress = chain.sync_progress();
+            let progress = progress.borrow();
+            assert!(progress.complete);
+            assert!(progress.events_received >= 10);
+            assert_eq!(progress.fraction(), Some(1.0));
+        }
+
+        // Chains that are already open have nothing left to sync
+        let again = registry.open(&url, &key, false).await?;
+        assert!(again.sync_progress().borrow().complete);
+
+        server.shutdown().await;
+        Ok(())
+    })
+}
@@ -0,0 +1,39 @@
+use ate::prelude::*;
+
+#[test]
+fn task_dump_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        // Tasks are listed for as long as they are alive along with the
+        // place that they were spawned from
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let task = TaskEngine::spawn_named("waiting-for-test", async move {
+            let _ = rx.await;
+        });
+        tokio::task::yield_now().await;
+
+        let found = TaskEngine::dump_tasks()
+            .into_iter()
+            .filter(|a| a.name == "waiting-for-test")
+            .collect::<Vec<_>>();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].site.contains("tasks.rs"));
+        assert!(found[0].polls >= 1);
+
+        let _ = tx.send(());
+        task.await.unwrap();
+        assert!(TaskEngine::dump_tasks()
+            .iter()
+            .all(|a| a.name != "waiting-for-test"));
+
+        // Blocking tasks return their result like any other
+        let ret = TaskEngine::spawn_blocking(|| 42).await;
+        assert_eq!(ret, 42);
+        Ok(())
+    })
+}
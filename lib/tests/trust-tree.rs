@@ -71,7 +71,7 @@ fn test_trust_tree_persistent() -> Result<(), AteError> {
             let chain = builder.open(&ChainKey::from("trust")).await?;
 
             info!("add the objects to the DIO");
-            let dio = chain.dio_mut(&session).await;
+            let dio = chain.dio_mut(&session).await?;
             let mut garage = dio.store(Garage::default())?;
             garage.auth_mut().read = ReadOption::from_key(&read_key);
             garage.auth_mut().write = WriteOption::Specific(write_key2.hash());
@@ -182,7 +182,7 @@ fn test_trust_tree_memory() -> Result<(), AteError> {
             let chain = builder.open(&ChainKey::from("trust")).await?;
 
             info!("add the objects to the DIO");
-            let dio = chain.dio_mut(&session).await;
+            let dio = chain.dio_mut(&session).await?;
             let mut garage = dio.store(Garage::default())?;
             garage.auth_mut().read = ReadOption::from_key(&read_key);
             garage.auth_mut().write = WriteOption::Specific(write_key2.hash());
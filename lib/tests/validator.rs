@@ -0,0 +1,94 @@
+#![cfg(any(feature = "enable_full"))]
+#![allow(unused_imports)]
+use ate::event::EventHeader;
+use ate::meta::Metadata;
+use ate::prelude::*;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// Rejects any event whose data mentions a forbidden word
+struct ForbiddenWordValidator {}
+
+impl EventValidator for ForbiddenWordValidator {
+    fn validate(
+        &self,
+        _header: &EventHeader,
+        _meta: &Metadata,
+        data: Option<&[u8]>,
+        _session: &'_ dyn AteSession,
+    ) -> Result<(), ValidationError> {
+        let word = b"forbidden";
+        if let Some(data) = data {
+            if data.windows(word.len()).any(|a| a == word) {
+                return Err(ValidationErrorKind::Denied("the data holds a forbidden word".to_string()).into());
+            }
+        }
+        Ok(())
+    }
+
+    fn validator_name(&self) -> &str {
+        "forbidden-word"
+    }
+}
+
+#[cfg(all(feature = "enable_server", feature = "enable_client"))]
+#[test]
+fn route_validator_test() -> Result<(), AteError> {
+    ate::utils::bootstrap_test_env();
+
+    #[cfg(feature = "enable_mt")]
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    #[cfg(not(feature = "enable_mt"))]
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    rt.block_on(async {
+        let url = url::Url::parse("ws://localhost:5071/").unwrap();
+        let cfg_ate = ConfAte::default();
+        let cfg_mesh = ConfMesh::solo_from_url(
+            &cfg_ate,
+            &url,
+            &IpAddr::from_str("::").unwrap(),
+            None,
+            None,
+        )
+        .await?;
+        let root = create_ethereal_centralized_server(&cfg_ate, &cfg_mesh).await?;
+
+        // The client subscribes before the validator is registered
+        let registry = Registry::new(&cfg_ate).await.temporal(true).cement();
+        let session = AteSessionUser::new();
+        let chain = registry.open(&url, &ChainKey::from("validator-test"), false).await?;
+        {
+            let dio = chain.dio_mut(&session).await?;
+            dio.store("forbidden but not yet validated".to_string())?;
+            dio.commit().await?;
+        }
+
+        // Validators that are added later still apply to the existing session
+        root.add_route_validator("/", ForbiddenWordValidator {});
+        {
+            let dio = chain.dio_mut(&session).await?;
+            dio.store("perfectly fine".to_string())?;
+            dio.commit().await?;
+        }
+        {
+            let dio = chain.dio_mut(&session).await?;
+            dio.store("this is forbidden".to_string())?;
+            match dio.commit().await {
+                Ok(_) => panic!("the validator of the route did not reject the commit"),
+                Err(err) => {
+                    let err = err.to_string();
+                    assert!(err.contains("forbidden word"), "unexpected error - {}", err);
+                    assert!(err.contains("forbidden-word"), "unexpected error - {}", err);
+                }
+            }
+        }
+
+        root.shutdown().await;
+        Ok(())
+    })
+}
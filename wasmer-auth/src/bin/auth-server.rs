@@ -1,3 +1,4 @@
+use ate::daemon::Daemon;
 use ate::prelude::*;
 use ate::utils::load_node_list;
 use clap::Parser;
@@ -6,6 +7,10 @@ use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use wasmer_auth::helper::*;
 use wasmer_auth::prelude::*;
+use wasmer_auth::service::AuthService;
+use wasmer_auth::work::LoginPolicy;
+use wasmer_auth::work::LoginThrottle;
+use std::sync::Arc;
 
 #[derive(Parser)]
 #[clap(version = "1.5", author = "John S. <johnathan.sharratt@gmail.com>")]
@@ -16,8 +21,16 @@ struct Opts {
     /// Logs debug info to the console
     #[clap(short, long)]
     debug: bool,
+    /// Configuration file (TOML, YAML or JSON) with an [ate] section - when supplied
+    /// the settings in the file are used instead of the defaults (the chains are
+    /// still stored in the JSON log format)
+    #[clap(long)]
+    config: Option<String>,
+    /// Validates the configuration file, reports every problem that it finds and exits
+    #[clap(long, requires = "config")]
+    check_config: bool,
     #[clap(subcommand)]
-    subcmd: SubCommand,
+    subcmd: Option<SubCommand>,
 }
 
 #[derive(Parser)]
@@ -26,6 +39,8 @@ enum SubCommand {
     Run(Run),
     #[clap()]
     Generate(Generate),
+    #[clap()]
+    Unlock(Unlock),
 }
 
 /// Runs the login authentication and authorization server
@@ -66,6 +81,43 @@ struct Run {
     /// Ensures that this authentication server runs as a specific node_id
     #[clap(short, long)]
     node_id: Option<u32>,
+    /// Number of days after an account deletion is requested before the account is
+    /// permanently erased (during this grace period the deletion can be cancelled)
+    #[clap(long, default_value = "30")]
+    delete_grace_days: i64,
+    /// Number of consecutive failed logins (for an account or a source) before
+    /// further logins are temporarily refused
+    #[clap(long, default_value = "5")]
+    login_max_failures: u32,
+    /// Number of seconds that logins are refused for after too many failures
+    #[clap(long, default_value = "900")]
+    login_lockout_secs: u64,
+    /// File that the process ID is written to while the server runs
+    #[clap(long)]
+    pid_file: Option<String>,
+}
+
+/// Lifts the temporary lockout of an account caused by too many failed logins
+#[derive(Parser)]
+struct Unlock {
+    /// Email address of the account to be unlocked
+    #[clap(index = 1)]
+    email: String,
+    /// Path to the secret key that helps protect key operations like creating users and resetting passwords
+    #[clap(long, default_value = "~/wasmer/auth.key")]
+    auth_key_path: String,
+    /// Path to the secret key that grants access to the WebServer role within groups
+    #[clap(long, default_value = "~/wasmer/web.key")]
+    web_key_path: String,
+    /// Path to the secret key that grants access to the EdgeCompute role within groups
+    #[clap(long, default_value = "~/wasmer/edge.key")]
+    edge_key_path: String,
+    /// Path to the secret key that grants access to the contracts
+    #[clap(long, default_value = "~/wasmer/contract.key")]
+    contract_key_path: String,
+    /// Address of the authentication server that holds the account
+    #[clap(short, long, default_value = "ws://localhost:5001/auth")]
+    url: url::Url,
 }
 
 /// Generates the secret key that helps protect key operations like creating users and resetting passwords
@@ -79,23 +131,29 @@ struct Generate {
     strength: KeySize,
 }
 
-fn ctrl_channel() -> tokio::sync::watch::Receiver<bool> {
-    let (sender, receiver) = tokio::sync::watch::channel(false);
-    ctrlc_async::set_handler(move || {
-        let _ = sender.send(true);
-    })
-    .unwrap();
-    receiver
-}
-
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> Result<(), AteError> {
     let opts: Opts = Opts::parse();
 
     ate::log_init(opts.verbose, opts.debug);
 
+    let config = opts.config.map(|a| shellexpand::tilde(&a).to_string());
+    if opts.check_config {
+        match ConfDocument::check_file(config.unwrap_or_default()) {
+            true => return Ok(()),
+            false => std::process::exit(1),
+        }
+    }
+    let subcmd = match opts.subcmd {
+        Some(a) => a,
+        None => {
+            eprintln!("a subcommand is required (see --help)");
+            std::process::exit(2);
+        }
+    };
+
     // Determine what we need to do
-    match opts.subcmd {
+    match subcmd {
         SubCommand::Run(run) => {
             // Open the key file
             let root_write_key: PrivateSignKey = load_key(run.auth_key_path.clone(), ".write");
@@ -106,7 +164,7 @@ async fn main() -> Result<(), AteError> {
             let contract_key: EncryptKey = load_key(run.contract_key_path.clone(), ".read");
 
             // Build a session for service
-            let mut cfg_ate = conf_auth();
+            let mut cfg_ate = load_conf(config)?;
             cfg_ate.log_path = Some(shellexpand::tilde(&run.logs_path).to_string());
             if let Some(backup_path) = run.backup_path {
                 cfg_ate.backup_path = Some(shellexpand::tilde(&backup_path).to_string());
@@ -119,34 +177,95 @@ async fn main() -> Result<(), AteError> {
             session.user.add_write_key(&root_write_key);
 
             // Create the server and listen
+            let delete_grace_period = chrono::Duration::days(run.delete_grace_days);
             let mut flow = ChainFlow::new(
                 &cfg_ate,
                 root_write_key,
-                session,
-                web_key,
-                edge_key,
-                contract_key,
+                session.clone(),
+                web_key.clone(),
+                edge_key.clone(),
+                contract_key.clone(),
                 &run.url,
             );
             flow.terms_and_conditions = Some(wasmer_auth::GENERIC_TERMS_AND_CONDITIONS.to_string());
+            flow.delete_grace_period = delete_grace_period;
+            let mut login_policy = LoginPolicy::default();
+            login_policy.max_failures = run.login_max_failures;
+            login_policy.lockout = std::time::Duration::from_secs(run.login_lockout_secs);
+            flow.login_throttle = Arc::new(LoginThrottle::new(login_policy));
+            let login_throttle = flow.login_throttle.clone();
             let mut cfg_mesh =
                 ConfMesh::solo_from_url(&cfg_ate, &run.url, &run.listen, None, run.node_id).await?;
             cfg_mesh.wire_protocol = StreamProtocol::parse(&run.url)?;
             cfg_mesh.listen_certificate = Some(root_cert_key);
 
+            let mut daemon = Daemon::new();
+            if let Some(pid_file) = run.pid_file.as_ref() {
+                daemon = daemon.with_pid_file(shellexpand::tilde(pid_file).as_ref())?;
+            }
+
             let server = create_server(&cfg_mesh).await?;
             server.add_route(Box::new(flow), &cfg_ate).await?;
 
-            // Wait for ctrl-c
-            let mut exit = ctrl_channel();
-            while *exit.borrow() == false {
-                exit.changed().await.unwrap();
-            }
-            println!("Shutting down...");
-            server.shutdown().await;
+            // Periodically erase any accounts whose deletion grace period has expired
+            let sweeper = AuthService::new(
+                &cfg_ate,
+                run.url.clone(),
+                session,
+                web_key,
+                edge_key,
+                contract_key,
+                None,
+                delete_grace_period,
+                login_throttle,
+                None,
+                None,
+            )
+            .await?;
+            TaskEngine::spawn(sweeper.run_deletion_sweeper(std::time::Duration::from_secs(3600)));
+
+            daemon.ready(&server);
+
+            // Wait for ctrl-c (or for the service manager to stop the server)
+            daemon.run_until_shutdown(&server).await;
             println!("Goodbye!");
         }
 
+        SubCommand::Unlock(unlock) => {
+            let root_write_key: PrivateSignKey = load_key(unlock.auth_key_path.clone(), ".write");
+            let root_read_key: EncryptKey = load_key(unlock.auth_key_path.clone(), ".read");
+            let web_key: EncryptKey = load_key(unlock.web_key_path.clone(), ".read");
+            let edge_key: EncryptKey = load_key(unlock.edge_key_path.clone(), ".read");
+            let contract_key: EncryptKey = load_key(unlock.contract_key_path.clone(), ".read");
+
+            let mut session = AteSessionUser::new();
+            session.user.add_read_key(&root_read_key);
+            session.user.add_write_key(&root_write_key);
+
+            let service = AuthService::new(
+                &load_conf(config)?,
+                unlock.url.clone(),
+                session,
+                web_key,
+                edge_key,
+                contract_key,
+                None,
+                chrono::Duration::days(30),
+                Arc::new(LoginThrottle::default()),
+                None,
+                None,
+            )
+            .await?;
+            match service.unlock_login(unlock.email.as_str()).await {
+                Ok(true) => println!("Account unlocked ({})", unlock.email),
+                Ok(false) => println!("Account was not locked ({})", unlock.email),
+                Err(err) => {
+                    eprintln!("Failed to unlock the account - {:?}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+
         SubCommand::Generate(generate) => {
             let mut key_path = generate.key_path.clone();
             if key_path.ends_with("/") == false {
@@ -176,3 +295,16 @@ async fn main() -> Result<(), AteError> {
     // We are done
     Ok(())
 }
+
+/// Builds the configuration from the configuration file (when there is one)
+/// while keeping the log format that the authentication chains are stored in
+fn load_conf(config: Option<String>) -> Result<ConfAte, AteError> {
+    let mut cfg_ate = match config {
+        Some(path) => ConfAte::from_file(path)?,
+        None => return Ok(conf_auth()),
+    };
+    cfg_ate.log_format.meta = SerializationFormat::Json;
+    cfg_ate.log_format.data = SerializationFormat::Json;
+    cfg_ate.record_type_name = true;
+    Ok(cfg_ate)
+}
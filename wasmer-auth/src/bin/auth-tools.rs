@@ -9,10 +9,23 @@ use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use url::Url;
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<(), AteError> {
+async fn main() {
     let opts: Opts = Opts::parse();
+    let verbose = opts.verbose > 0;
 
+    if let Err(err) = main_run(opts).await {
+        ate::utils::ErrorRenderer::new()
+            .with_verbose(verbose)
+            .eprint(&err);
+        std::process::exit(1);
+    }
+}
+
+async fn main_run(opts: Opts) -> Result<(), AteError> {
     ate::log_init(opts.verbose, opts.debug);
+    if opts.no_cache {
+        group_cache().set_enabled(false);
+    }
 
     // Determine what we need to do
     let auth = wasmer_auth::prelude::origin_url(&opts.auth, "auth");
@@ -26,6 +39,10 @@ async fn main() -> Result<(), AteError> {
         SubCommand::Token(opts_token) => {
             main_opts_token(opts_token, opts.token, opts.token_path, auth, "Group").await?;
         }
+        SubCommand::ServiceAccount(opts_service_account) => {
+            main_opts_service_account(opts_service_account, opts.token, opts.token_path, auth)
+                .await?;
+        }
     }
 
     // We are done
@@ -0,0 +1,107 @@
+#![allow(unused_imports)]
+use ate::prelude::*;
+use error_chain::bail;
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use url::Url;
+
+use crate::cmd::*;
+use crate::error::*;
+use crate::helper::*;
+use crate::model::*;
+use crate::opt::*;
+use crate::prelude::*;
+use crate::request::*;
+
+pub async fn security_activity_command(
+    registry: &Registry,
+    session: &AteSessionUser,
+    limit: usize,
+    auth: Url,
+) -> Result<SecurityActivityResponse, SecurityActivityError> {
+    // Open a command chain
+    let chain = registry.open_cmd(&auth).await?;
+
+    // Ask the authentication server for the recent events
+    let query = SecurityActivityRequest {
+        session: session.clone(),
+        limit,
+    };
+
+    let response: Result<SecurityActivityResponse, SecurityActivityFailed> =
+        chain.invoke(query).await?;
+    let result = response?;
+    debug!("events: {}", result.events.len());
+    Ok(result)
+}
+
+pub async fn security_webhook_command(
+    registry: &Registry,
+    session: &AteSessionSudo,
+    webhook: Option<String>,
+    auth: Url,
+) -> Result<SecurityWebhookResponse, SecurityActivityError> {
+    // Open a command chain
+    let chain = registry.open_cmd(&auth).await?;
+
+    // Update the webhook using the elevated rights of the session
+    let update = SecurityWebhookRequest {
+        session: session.clone(),
+        webhook,
+    };
+
+    let response: Result<SecurityWebhookResponse, SecurityActivityFailed> =
+        chain.invoke(update).await?;
+    let result = response?;
+    Ok(result)
+}
+
+pub async fn main_user_activity(
+    session: AteSessionUser,
+    limit: usize,
+    auth: Url,
+) -> Result<(), SecurityActivityError> {
+    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+    let result = security_activity_command(&registry, &session, limit, auth).await?;
+
+    println!("# Security Activity");
+    println!("");
+    if let Some(webhook) = &result.webhook {
+        println!("Webhook: {}", webhook);
+        println!("");
+    }
+    if result.events.len() <= 0 {
+        println!("No recent activity");
+        return Ok(());
+    }
+    println!(
+        "{:<20} {:<6} {:<8} {:<40} {}",
+        "WHEN", "KIND", "RESULT", "ADDRESS", "USER AGENT"
+    );
+    for event in result.events.iter() {
+        println!(
+            "{:<20} {:<6} {:<8} {:<40} {}",
+            event.when.format("%Y-%m-%d %H:%M:%S"),
+            event.kind.to_string(),
+            if event.success { "ok" } else { "failed" },
+            event.addr.clone().unwrap_or_else(|| "-".to_string()),
+            event.user_agent.clone().unwrap_or_else(|| "-".to_string())
+        );
+    }
+    Ok(())
+}
+
+pub async fn main_user_webhook(
+    session: AteSessionSudo,
+    webhook: Option<String>,
+    auth: Url,
+) -> Result<(), SecurityActivityError> {
+    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+    let result = security_webhook_command(&registry, &session, webhook, auth).await?;
+
+    match result.webhook {
+        Some(webhook) => println!("Logins from new locations will be posted to {}", webhook),
+        None => println!("The security webhook has been removed"),
+    }
+    Ok(())
+}
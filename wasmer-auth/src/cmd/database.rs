@@ -49,9 +49,11 @@ pub async fn main_opts_db(
     .await?;
     let registry = ate::mesh::Registry::new(&conf).await.temporal(true);
 
-    // Create a progress bar loader
-    let progress_local = LoadProgress::new(std::io::stderr());
-    let progress_remote = LoadProgress::new(std::io::stderr());
+    // Create a progress bar loader (only drawn when a terminal is attached)
+    let mut progress_local = LoadProgress::new(std::io::stderr());
+    let mut progress_remote = LoadProgress::new(std::io::stderr());
+    progress_local.draw_bar = is_tty_stderr();
+    progress_remote.draw_bar = is_tty_stderr();
 
     // Load the chain
     let remote = crate::prelude::origin_url(&opts_db.remote, "db");
@@ -78,7 +80,7 @@ pub async fn main_opts_db(
         }
         DatabaseAction::Truncate(_action) => {
             print!("Deleting all events...");
-            let dio = db.dio_full(&session).await;
+            let dio = db.dio_full(&session).await?;
             let mut ids = dio.dio.all_keys().await;
             while ids.is_empty() == false {
                 print!(".");
@@ -0,0 +1,128 @@
+#![allow(unused_imports)]
+use ate::prelude::*;
+use error_chain::bail;
+use std::io::stdout;
+use std::io::Write;
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use url::Url;
+
+use crate::cmd::*;
+use crate::error::*;
+use crate::helper::*;
+use crate::opt::*;
+use crate::prelude::*;
+use crate::request::*;
+
+pub async fn delete_user_command(
+    registry: &Registry,
+    session: &AteSessionSudo,
+    auth: Url,
+) -> Result<DeleteUserResponse, DeleteUserError> {
+    // Open a command chain
+    let chain = registry.open_cmd(&auth).await?;
+
+    // Make the delete request and fire it over to the authentication server
+    let delete = DeleteUserRequest {
+        session: session.clone(),
+    };
+
+    let response: Result<DeleteUserResponse, DeleteUserFailed> = chain.invoke(delete).await?;
+    let result = response?;
+    debug!("key: {}", result.key);
+    Ok(result)
+}
+
+pub async fn cancel_delete_user_command(
+    registry: &Registry,
+    session: &AteSessionSudo,
+    auth: Url,
+) -> Result<CancelDeleteUserResponse, DeleteUserError> {
+    // Open a command chain
+    let chain = registry.open_cmd(&auth).await?;
+
+    // Make the cancel request and fire it over to the authentication server
+    let cancel = CancelDeleteUserRequest {
+        session: session.clone(),
+    };
+
+    let response: Result<CancelDeleteUserResponse, DeleteUserFailed> =
+        chain.invoke(cancel).await?;
+    let result = response?;
+    debug!("key: {}", result.key);
+    Ok(result)
+}
+
+pub async fn main_delete_user(
+    session: AteSessionSudo,
+    force: bool,
+    auth: Url,
+) -> Result<(), DeleteUserError> {
+    let identity = session.identity().to_string();
+
+    // Deleting an account is a big deal so make sure the user really wants it
+    if force == false {
+        if !is_tty_stdin() {
+            bail!(DeleteUserErrorKind::InvalidArguments);
+        }
+
+        eprintln!(
+            r#"# Account Deletion
+
+Your account and all the personal data held about it will be permanently
+erased once the grace period expires. Until then you can still log in and
+cancel the deletion with 'user cancel-delete'.
+"#
+        );
+        eprint!("Type your username ({}) to confirm: ", identity);
+        stdout().lock().flush()?;
+        let mut s = String::new();
+        std::io::stdin()
+            .read_line(&mut s)
+            .expect("Did not enter a valid username");
+        if s.trim() != identity.as_str() {
+            eprintln!("Account deletion aborted");
+            std::process::exit(1);
+        }
+    }
+
+    // Request the deletion using the authentication server
+    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+    let result = delete_user_command(&registry, &session, auth).await?;
+
+    println!(
+        "Account {} will be permanently deleted on {}",
+        identity,
+        result.erase_after.format("%Y-%m-%d %H:%M UTC")
+    );
+    Ok(())
+}
+
+pub async fn main_cancel_delete_user(
+    session: AteSessionSudo,
+    auth: Url,
+) -> Result<(), DeleteUserError> {
+    let identity = session.identity().to_string();
+
+    // Cancel the deletion using the authentication server
+    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+    let response = cancel_delete_user_command(&registry, &session, auth).await;
+
+    match response {
+        Ok(_) => {}
+        Err(DeleteUserError(DeleteUserErrorKind::NotPending, _)) => {
+            eprintln!("There is no pending deletion for this account ({})", identity);
+            std::process::exit(1);
+        }
+        Err(DeleteUserError(DeleteUserErrorKind::NotFound(_), _)) => {
+            eprintln!("The account ({}) has already been erased", identity);
+            std::process::exit(1);
+        }
+        Err(err) => {
+            bail!(err);
+        }
+    }
+
+    println!("Deletion of account {} has been cancelled", identity);
+    Ok(())
+}
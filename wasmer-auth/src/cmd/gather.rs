@@ -41,18 +41,31 @@ pub async fn gather_command(
         bail!(GatherErrorKind::NotFound(group));
     }
 
+    // Groups rarely change so the results are cached (as long as the server
+    // agrees that the cached version is still the current one)
+    if group_cache()
+        .get_gather(&auth, group.as_str(), &session)
+        .is_some()
+    {
+        revalidate_group_cache(registry, &auth, group.as_str()).await;
+        if let Some(ret) = group_cache().get_gather(&auth, group.as_str(), &session) {
+            return Ok(ret);
+        }
+    }
+
     // Open a command chain
     let chain = registry.open_cmd(&auth).await?;
 
     // Create the gather command
     let gather = GatherRequest {
         group: group.clone(),
-        session,
+        session: session.clone(),
     };
 
     // Attempt the gather request with a 10 second timeout
     let response: Result<GatherResponse, GatherFailed> = chain.invoke(gather).await?;
     let result = response?;
+    group_cache().put_gather(&auth, group.as_str(), &session, &result.authority, result.version);
     Ok(result.authority)
 }
 
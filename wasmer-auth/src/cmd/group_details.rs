@@ -19,21 +19,68 @@ pub async fn group_details_command(
     auth: Url,
     session: Option<&AteSessionGroup>,
 ) -> Result<GroupDetailsResponse, GroupDetailsError> {
+    // Groups rarely change so the results are cached (as long as the server
+    // agrees that the cached version is still the current one)
+    if group_cache()
+        .get_details(&auth, group.as_str(), session)
+        .is_some()
+    {
+        revalidate_group_cache(registry, &auth, group.as_str()).await;
+        if let Some(ret) = group_cache().get_details(&auth, group.as_str(), session) {
+            return Ok(ret);
+        }
+    }
+
     // Open a command chain
     let chain = registry.open_cmd(&auth).await?;
 
     // Make the create request and fire it over to the authentication server
     let create = GroupDetailsRequest {
-        group,
+        group: group.clone(),
         session: session.map(|s| s.clone()),
     };
 
     let response: Result<GroupDetailsResponse, GroupDetailsFailed> = chain.invoke(create).await?;
     let result = response?;
     debug!("key: {}", result.key);
+    group_cache().put_details(&auth, group.as_str(), session, &result);
     Ok(result)
 }
 
+/// Asks the server for the current version of a group so that any cached
+/// entries for an older version are dropped before they are used
+pub(crate) async fn revalidate_group_cache(registry: &Registry, auth: &Url, group: &str) {
+    let chain = match registry.open_cmd(auth).await {
+        Ok(a) => a,
+        Err(err) => {
+            debug!("group cache revalidation skipped - {}", err);
+            return;
+        }
+    };
+
+    let request = GroupVersionRequest {
+        group: group.to_string(),
+    };
+    let response: Result<Result<GroupVersionResponse, GroupDetailsFailed>, InvokeError> = chain
+        .invoke_ext(None, request, GROUP_CACHE_REVALIDATE_TIMEOUT)
+        .await;
+    match response {
+        Ok(Ok(a)) => {
+            group_cache().observe_version(auth, group, a.version);
+        }
+        Ok(Err(GroupDetailsFailed::GroupNotFound)) => {
+            group_cache().invalidate_group(auth, group);
+        }
+        Ok(Err(err)) => {
+            debug!("group cache revalidation failed - {:?}", err);
+        }
+        Err(err) => {
+            // Older servers do not know this request so the TTL still applies
+            debug!("group cache revalidation failed - {}", err);
+        }
+    }
+}
+
 pub async fn main_group_details(
     group: Option<String>,
     auth: Url,
@@ -77,7 +124,11 @@ pub async fn main_group_details(
         } else {
             println!("[membership]");
             for member in role.members {
-                println!("- {}", member);
+                if role.service_accounts.contains(&member) {
+                    println!("- {} (service account)", member);
+                } else {
+                    println!("- {}", member);
+                }
             }
         }
         println!("");
@@ -25,11 +25,12 @@ pub async fn group_remove_command(
 
     // Make the remove request and fire it over to the authentication server
     let create = GroupRemoveRequest {
-        group,
+        group: group.clone(),
         session: session.clone(),
     };
 
     let response: Result<GroupRemoveResponse, GroupRemoveFailed> = chain.invoke(create).await?;
+    group_cache().invalidate_group(&auth, group.as_str());
     let result = response?;
     debug!("key: {}", result.key);
     Ok(result)
@@ -26,7 +26,7 @@ pub async fn group_user_add_command(
     let chain = registry.open_cmd(&auth).await?;
 
     // First we query the user that needs to be added so that we can get their public encrypt key
-    let query = query_command(registry, username.clone(), auth).await?;
+    let query = query_command(registry, username.clone(), auth.clone()).await?;
 
     // Determine what level of authentication we will associate the role with
     let who_key = match purpose {
@@ -36,7 +36,7 @@ pub async fn group_user_add_command(
 
     // Make the create request and fire it over to the authentication server
     let create = GroupUserAddRequest {
-        group,
+        group: group.clone(),
         session: session.clone(),
         who_name: username.clone(),
         who_key,
@@ -44,6 +44,7 @@ pub async fn group_user_add_command(
     };
 
     let response: Result<GroupUserAddResponse, GroupUserAddFailed> = chain.invoke(create).await?;
+    group_cache().invalidate_group(&auth, group.as_str());
     let result = response?;
     debug!("key: {}", result.key);
     Ok(result)
@@ -26,7 +26,7 @@ pub async fn group_user_remove_command(
     let chain = registry.open_cmd(&auth).await?;
 
     // First we query the user that needs to be removed so that we can get their public encrypt key
-    let query = query_command(registry, username, auth).await?;
+    let query = query_command(registry, username, auth.clone()).await?;
 
     // Determine what level of authentication we will associate the role with
     let who = match purpose {
@@ -36,7 +36,7 @@ pub async fn group_user_remove_command(
 
     // Make the create request and fire it over to the authentication server
     let create = GroupUserRemoveRequest {
-        group,
+        group: group.clone(),
         session: session.clone(),
         who: who.hash(),
         purpose,
@@ -44,6 +44,7 @@ pub async fn group_user_remove_command(
 
     let response: Result<GroupUserRemoveResponse, GroupUserRemoveFailed> =
         chain.invoke(create).await?;
+    group_cache().invalidate_group(&auth, group.as_str());
     let result = response?;
     debug!("key: {}", result.key);
     Ok(result)
@@ -22,6 +22,28 @@ pub async fn login_command(
     auth: Url,
     print_message_of_the_day: bool,
 ) -> Result<AteSessionUser, LoginError> {
+    let response = login_command_ext(
+        registry,
+        username,
+        password,
+        verification_code,
+        auth,
+        print_message_of_the_day,
+    )
+    .await?;
+    Ok(response.authority)
+}
+
+/// Same as the login command but returns the whole response (which
+/// includes the refresh token for the session)
+pub async fn login_command_ext(
+    registry: &Registry,
+    username: String,
+    password: String,
+    verification_code: Option<String>,
+    auth: Url,
+    print_message_of_the_day: bool,
+) -> Result<LoginResponse, LoginError> {
     // Open a command chain
     let chain = registry.open_cmd(&auth).await?;
 
@@ -36,6 +58,7 @@ pub async fn login_command(
         email: username.clone(),
         secret: read_key,
         verification_code,
+        user_agent: Some(user_agent()),
     };
 
     // Attempt the login request with a 10 second timeout
@@ -45,7 +68,7 @@ pub async fn login_command(
 
     // Display the message of the day
     if print_message_of_the_day {
-        if let Some(message_of_the_day) = result.message_of_the_day {
+        if let Some(message_of_the_day) = result.message_of_the_day.as_ref() {
             if is_tty_stderr() {
                 eprintln!("{}", message_of_the_day);
             }
@@ -53,7 +76,7 @@ pub async fn login_command(
     }
 
     // Success
-    Ok(result.authority)
+    Ok(result)
 }
 
 pub(crate) async fn main_session_start(
@@ -143,6 +166,26 @@ pub async fn main_login(
     password: Option<String>,
     auth: Url,
 ) -> Result<AteSessionUser, LoginError> {
+    Ok(main_login_ext(username, password, auth).await?.authority)
+}
+
+pub async fn main_login_ext(
+    username: Option<String>,
+    password: Option<String>,
+    auth: Url,
+) -> Result<LoginResponse, LoginError> {
+    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+    main_login_with_registry(&registry, username, password, auth).await
+}
+
+/// Same as `main_login_ext` but the login is performed through the supplied
+/// registry (so that callers can control how the auth server is connected to)
+pub async fn main_login_with_registry(
+    registry: &Registry,
+    username: Option<String>,
+    password: Option<String>,
+    auth: Url,
+) -> Result<LoginResponse, LoginError> {
     let username = match username {
         Some(a) => a,
         None => {
@@ -175,9 +218,8 @@ pub async fn main_login(
     };
 
     // Login using the authentication server which will give us a session with all the tokens
-    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
-    let response = login_command(
-        &registry,
+    let response = login_command_ext(
+        registry,
         username.clone(),
         password.clone(),
         None,
@@ -185,17 +227,33 @@ pub async fn main_login(
         true,
     )
     .await;
-    let ret = handle_login_response(&registry, response, username, password, auth).await?;
+    let ret = handle_login_response_ext(registry, response, username, password, auth).await?;
     Ok(ret)
 }
 
 pub(crate) async fn handle_login_response(
     registry: &Registry,
-    mut response: Result<AteSessionUser, LoginError>,
+    response: Result<AteSessionUser, LoginError>,
     username: String,
     password: String,
     auth: Url,
 ) -> Result<AteSessionUser, LoginError> {
+    match response {
+        Ok(a) => Ok(a),
+        Err(err) => {
+            let response = handle_login_response_ext(registry, Err(err), username, password, auth).await?;
+            Ok(response.authority)
+        }
+    }
+}
+
+pub(crate) async fn handle_login_response_ext(
+    registry: &Registry,
+    mut response: Result<LoginResponse, LoginError>,
+    username: String,
+    password: String,
+    auth: Url,
+) -> Result<LoginResponse, LoginError> {
     // If we are currently unverified then prompt for the verification code
     let mut was_unverified = false;
     if let Err(LoginError(LoginErrorKind::Unverified(_), _)) = &response {
@@ -216,7 +274,7 @@ pub(crate) async fn handle_login_response(
         let verification_code = s.trim().to_string();
 
         // Perform the login again but also supply the verification code
-        response = login_command(
+        response = login_command_ext(
             registry,
             username,
             password,
@@ -253,6 +311,20 @@ pub(crate) async fn handle_login_response(
             }
             std::process::exit(1);
         }
+        Err(LoginError(LoginErrorKind::TemporarilyLocked(retry_after), _)) => {
+            if retry_after > Duration::minutes(1).to_std().unwrap() {
+                eprintln!(
+                    "Too many failed login attempts - try again in {} minutes",
+                    (retry_after.as_secs() as u64 + 59u64) / 60u64
+                );
+            } else {
+                eprintln!(
+                    "Too many failed login attempts - try again in {} seconds",
+                    (retry_after.as_secs() as u64).max(1)
+                );
+            }
+            std::process::exit(1);
+        }
         Err(LoginError(LoginErrorKind::WrongPassword, _)) => {
             if was_unverified {
                 eprintln!("Either the password or verification code was incorrect");
@@ -1,6 +1,8 @@
+pub mod activity;
 pub mod create_group;
 pub mod create_user;
 pub mod database;
+pub mod delete_user;
 pub mod gather;
 pub mod group;
 pub mod group_details;
@@ -10,13 +12,16 @@ pub mod group_user_remove;
 pub mod login;
 pub mod query;
 pub mod reset;
+pub mod service_account;
 pub mod sudo;
 pub mod token;
 pub mod user;
 
+pub use activity::*;
 pub use create_group::*;
 pub use create_user::*;
 pub use database::*;
+pub use delete_user::*;
 pub use gather::*;
 pub use group::*;
 pub use group_details::*;
@@ -26,6 +31,7 @@ pub use group_user_remove::*;
 pub use login::*;
 pub use query::*;
 pub use reset::*;
+pub use service_account::*;
 pub use sudo::*;
 pub use token::*;
 pub use user::*;
@@ -0,0 +1,273 @@
+#![allow(unused_imports)]
+use ate::prelude::*;
+use error_chain::bail;
+use std::io::stdout;
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use url::Url;
+
+use crate::cmd::*;
+use crate::error::*;
+use crate::helper::*;
+use crate::model::*;
+use crate::opt::*;
+use crate::prelude::*;
+use crate::request::*;
+
+pub async fn main_opts_service_account(
+    opts: OptsServiceAccount,
+    token: Option<String>,
+    token_path: Option<String>,
+    auth: url::Url,
+) -> Result<(), AteError> {
+    match opts.action {
+        ServiceAccountAction::Create(action) => {
+            let session =
+                main_session_sudo(token.clone(), token_path.clone(), action.code, Some(auth.clone()))
+                    .await?;
+            main_create_service_account(session, action.name, action.key_path, auth).await?;
+        }
+        ServiceAccountAction::List => {
+            let session =
+                main_session_user(token.clone(), token_path.clone(), Some(auth.clone())).await?;
+            main_list_service_accounts(session, auth).await?;
+        }
+        ServiceAccountAction::Revoke(action) => {
+            let session =
+                main_session_sudo(token.clone(), token_path.clone(), action.code, Some(auth.clone()))
+                    .await?;
+            main_revoke_service_account(session, action.identity, auth).await?;
+        }
+        ServiceAccountAction::Login(action) => {
+            let session = main_login_service_account(action.key_path, auth).await?;
+
+            if is_tty_stdout() {
+                eprintln!("The token string below can be used to secure your file system.\n");
+            }
+
+            let session: AteSessionType = session.into();
+            println!("{}", session_to_b64(session).unwrap());
+        }
+    }
+    Ok(())
+}
+
+pub async fn create_service_account_command(
+    registry: &Registry,
+    session: &AteSessionSudo,
+    name: String,
+    public_key: PublicSignKey,
+    auth: Url,
+) -> Result<CreateServiceAccountResponse, ServiceAccountError> {
+    // Open a command chain
+    let chain = registry.open_cmd(&auth).await?;
+
+    // Make the create request and fire it over to the authentication server
+    let create = CreateServiceAccountRequest {
+        session: session.clone(),
+        name,
+        public_key,
+    };
+
+    let response: Result<CreateServiceAccountResponse, ServiceAccountFailed> =
+        chain.invoke(create).await?;
+    let result = response?;
+    debug!("key: {}", result.key);
+    Ok(result)
+}
+
+pub async fn list_service_accounts_command(
+    registry: &Registry,
+    session: &AteSessionUser,
+    auth: Url,
+) -> Result<ListServiceAccountsResponse, ServiceAccountError> {
+    // Open a command chain
+    let chain = registry.open_cmd(&auth).await?;
+
+    // Make the list request and fire it over to the authentication server
+    let list = ListServiceAccountsRequest {
+        session: session.clone(),
+    };
+
+    let response: Result<ListServiceAccountsResponse, ServiceAccountFailed> =
+        chain.invoke(list).await?;
+    Ok(response?)
+}
+
+pub async fn revoke_service_account_command(
+    registry: &Registry,
+    session: &AteSessionSudo,
+    identity: String,
+    auth: Url,
+) -> Result<RevokeServiceAccountResponse, ServiceAccountError> {
+    // Open a command chain
+    let chain = registry.open_cmd(&auth).await?;
+
+    // Make the revoke request and fire it over to the authentication server
+    let revoke = RevokeServiceAccountRequest {
+        session: session.clone(),
+        identity,
+    };
+
+    let response: Result<RevokeServiceAccountResponse, ServiceAccountFailed> =
+        chain.invoke(revoke).await?;
+    Ok(response?)
+}
+
+/// Logs in as a service account by signing a challenge from the
+/// authentication server with the private key of the account
+pub async fn service_login_command(
+    registry: &Registry,
+    key: &ServiceAccountKey,
+    auth: Url,
+) -> Result<LoginResponse, LoginError> {
+    // Open a command chain
+    let chain = registry.open_cmd(&auth).await?;
+
+    // Get a challenge that proves the signature is fresh
+    let challenge = ServiceLoginChallengeRequest {
+        identity: key.identity.clone(),
+    };
+    let response: Result<ServiceLoginChallengeResponse, LoginFailed> =
+        chain.invoke(challenge).await?;
+    let challenge = response?;
+
+    // Answer it with the key of the account
+    let login = ServiceLoginRequest::new(key.identity.as_str(), challenge.challenge, &key.key)?;
+    trace!("invoking service login (identity={})", login.identity);
+    let response: Result<LoginResponse, LoginFailed> = chain.invoke(login).await?;
+    Ok(response?)
+}
+
+pub fn save_service_account_key(
+    key: &ServiceAccountKey,
+    key_path: String,
+) -> Result<(), ServiceAccountError> {
+    let data = service_account_key_to_b64(key)?;
+    let path = shellexpand::tilde(key_path.as_str()).to_string();
+    let path = std::path::Path::new(&path);
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+
+    // Never overwrite an existing key as that would lock out its account
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+
+    // Set the permissions so no one else can read it but the current user
+    #[cfg(unix)]
+    {
+        let mut perms = file.metadata()?.permissions();
+        perms.set_mode(0o600);
+        std::fs::set_permissions(path, perms)?;
+    }
+
+    file.write_all(data.as_bytes())?;
+    Ok(())
+}
+
+pub fn load_service_account_key(key_path: String) -> Result<ServiceAccountKey, ServiceAccountError> {
+    let path = shellexpand::tilde(key_path.as_str()).to_string();
+    let data = std::fs::read_to_string(path)?;
+    match b64_to_service_account_key(data) {
+        Some(a) => Ok(a),
+        None => bail!(ServiceAccountErrorKind::InvalidKeyFile(key_path)),
+    }
+}
+
+pub async fn main_login_service_account(
+    key_path: String,
+    auth: Url,
+) -> Result<AteSessionUser, ServiceAccountError> {
+    let key = load_service_account_key(key_path)?;
+
+    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+    let response = service_login_command(&registry, &key, auth).await?;
+    Ok(response.authority)
+}
+
+pub async fn main_create_service_account(
+    session: AteSessionSudo,
+    name: String,
+    key_path: Option<String>,
+    auth: Url,
+) -> Result<(), ServiceAccountError> {
+    let key_path = key_path.unwrap_or_else(|| format!("{}.key", name));
+
+    // The private key is generated here and never leaves this machine
+    let key = PrivateSignKey::generate(KeySize::Bit192);
+
+    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+    let result = create_service_account_command(
+        &registry,
+        &session,
+        name,
+        key.as_public_key().clone(),
+        auth,
+    )
+    .await?;
+
+    let key = ServiceAccountKey {
+        identity: result.identity.clone(),
+        key,
+    };
+    save_service_account_key(&key, key_path.clone())?;
+
+    println!("Service account created ({})", result.identity);
+    println!("");
+    println!("The private key of the account has been written to {}", key_path);
+    println!("(keep it safe - anyone who holds it can login as the account)");
+    Ok(())
+}
+
+pub async fn main_list_service_accounts(
+    session: AteSessionUser,
+    auth: Url,
+) -> Result<(), ServiceAccountError> {
+    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+    let result = list_service_accounts_command(&registry, &session, auth).await?;
+
+    println!("# Service Accounts");
+    println!("");
+    if result.accounts.is_empty() {
+        println!("[none]");
+    }
+    for account in result.accounts {
+        match account.revoked {
+            Some(revoked) => println!(
+                "- {} (created {}, revoked {})",
+                account.identity,
+                account.created.format("%Y-%m-%d %H:%M UTC"),
+                revoked.format("%Y-%m-%d %H:%M UTC")
+            ),
+            None => println!(
+                "- {} (created {}, key {})",
+                account.identity,
+                account.created.format("%Y-%m-%d %H:%M UTC"),
+                account.public_key
+            ),
+        }
+    }
+    Ok(())
+}
+
+pub async fn main_revoke_service_account(
+    session: AteSessionSudo,
+    identity: String,
+    auth: Url,
+) -> Result<(), ServiceAccountError> {
+    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+    let result = revoke_service_account_command(&registry, &session, identity, auth).await?;
+
+    println!(
+        "Service account {} was revoked on {}",
+        result.identity,
+        result.revoked.format("%Y-%m-%d %H:%M UTC")
+    );
+    Ok(())
+}
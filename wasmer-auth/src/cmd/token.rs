@@ -1,9 +1,12 @@
 #![allow(unused_imports)]
 use ate::prelude::*;
 use error_chain::bail;
+use once_cell::sync::Lazy;
 use std::io::stdout;
 use std::io::Write;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use url::Url;
 
@@ -68,6 +71,17 @@ pub async fn main_opts_token(
                 println!("{}", session_to_b64(session).unwrap());
             }
         }
+        TokenAction::Refresh(_action) => {
+            let token_path = match token_path {
+                Some(a) => a,
+                None => {
+                    eprintln!("The session can only be refreshed when it is stored in a token file!");
+                    std::process::exit(1);
+                }
+            };
+            let session = refresh(token_path, auth).await?;
+            eprintln!("The session for {} has been refreshed.", session.identity());
+        }
         TokenAction::View(_action) => {
             let session =
                 main_session_user(token.clone(), token_path.clone(), Some(auth.clone())).await?;
@@ -77,3 +91,182 @@ pub async fn main_opts_token(
     }
     Ok(())
 }
+
+/// How often the auto refresh renews the session (well within the lifetime
+/// of the refresh token so that it never expires while the daemon runs)
+const AUTO_REFRESH_INTERVAL: Duration = Duration::from_secs(12 * 3600);
+/// How long the auto refresh waits before it tries again after a failure
+const AUTO_REFRESH_RETRY: Duration = Duration::from_secs(60);
+
+static REFRESH_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// Path of the file that holds the refresh token of the session that is
+/// saved at the token path
+pub fn refresh_token_path(token_path: &str) -> String {
+    format!("{}.refresh", token_path)
+}
+
+pub fn save_refresh_token(token: &RefreshToken, token_path: String) -> Result<(), AteError> {
+    let refresh = refresh_token_to_b64(token)?;
+    save_token(refresh, refresh_token_path(token_path.as_str()))
+}
+
+pub fn load_refresh_token(token_path: String) -> Option<RefreshToken> {
+    let path = refresh_token_path(token_path.as_str());
+    let refresh = std::fs::read_to_string(path).ok()?;
+    b64_to_refresh_token(refresh)
+}
+
+pub fn clear_refresh_token(token_path: String) {
+    let path = refresh_token_path(token_path.as_str());
+    if let Ok(old) = std::fs::canonicalize(path.clone()) {
+        let _ = std::fs::remove_file(old);
+    }
+    let _ = std::fs::remove_file(path);
+}
+
+pub async fn refresh_command(
+    registry: &Registry,
+    token: RefreshToken,
+    auth: Url,
+) -> Result<RefreshResponse, RefreshError> {
+    // Open a command chain
+    let chain = registry.open_cmd(&auth).await?;
+
+    // Exchange the refresh token for a new session (the token is rotated)
+    trace!("invoking refresh (email={})", token.email);
+    let response: Result<RefreshResponse, RefreshFailed> =
+        chain.invoke(RefreshRequest { token }).await?;
+    Ok(response?)
+}
+
+pub async fn revoke_refresh_command(
+    registry: &Registry,
+    token: RefreshToken,
+    auth: Url,
+) -> Result<(), RefreshError> {
+    let chain = registry.open_cmd(&auth).await?;
+    let response: Result<RevokeRefreshResponse, RefreshFailed> =
+        chain.invoke(RevokeRefreshRequest { token }).await?;
+    response?;
+    Ok(())
+}
+
+/// Exchanges the refresh token saved next to the token file for a fresh
+/// session, both the token file and the refresh token file are updated
+pub async fn refresh(token_path: String, auth: Url) -> Result<AteSessionUser, RefreshError> {
+    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+    refresh_token_file(&registry, token_path, auth).await
+}
+
+async fn refresh_token_file(
+    registry: &Registry,
+    token_path: String,
+    auth: Url,
+) -> Result<AteSessionUser, RefreshError> {
+    // Refreshes within this process are done one at a time as each of them
+    // rotates the token that the next one will read
+    let _guard = REFRESH_LOCK.lock().await;
+
+    let token_path = shellexpand::tilde(&token_path).to_string();
+    let mut token = match load_refresh_token(token_path.clone()) {
+        Some(a) => a,
+        None => {
+            bail!(RefreshErrorKind::MissingRefreshToken);
+        }
+    };
+    let mut attempts = 0u32;
+    let response = loop {
+        match refresh_command(registry, token.clone(), auth.clone()).await {
+            Ok(a) => break a,
+            // Another process may have rotated the token after it was read, in
+            // which case the token that it saved is used instead
+            Err(RefreshError(RefreshErrorKind::Revoked, _)) if attempts < 3 => {
+                match load_refresh_token(token_path.clone()) {
+                    Some(a) if a.id != token.id => {
+                        debug!("refresh token was rotated by another process");
+                        token = a;
+                        attempts += 1;
+                    }
+                    _ => bail!(RefreshErrorKind::Revoked),
+                }
+            }
+            Err(err) => {
+                return Err(err);
+            }
+        }
+    };
+
+    // The old refresh token is now revoked so the new one is saved first
+    save_refresh_token(&response.refresh_token, token_path.clone())?;
+    let session = session_to_b64(AteSessionType::User(response.authority.clone()))?;
+    save_token(session, token_path)?;
+    Ok(response.authority)
+}
+
+/// Refreshes the session saved at the token path and then keeps refreshing
+/// it in the background before the refresh token can expire. The receiver
+/// always holds the latest session, DIOs that are opened later should use
+/// it while those that are already open are kept up to date by passing them
+/// to `rekey_on_refresh`. The background task stops once all the receivers
+/// are dropped or when the refresh token has been revoked.
+pub async fn spawn_auto_refresh(
+    registry: &Arc<Registry>,
+    token_path: String,
+    auth: Url,
+) -> Result<watch::Receiver<AteSessionUser>, RefreshError> {
+    let session = refresh_token_file(registry, token_path.clone(), auth.clone()).await?;
+    let (tx, rx) = watch::channel(session);
+
+    let registry = Arc::clone(registry);
+    TaskEngine::spawn(async move {
+        let mut wait = AUTO_REFRESH_INTERVAL;
+        loop {
+            ate::engine::sleep(wait).await;
+
+            match refresh_token_file(&registry, token_path.clone(), auth.clone()).await {
+                Ok(session) => {
+                    debug!("session refreshed ({})", session.identity());
+                    wait = AUTO_REFRESH_INTERVAL;
+                    if tx.send(session).is_err() {
+                        break;
+                    }
+                }
+                Err(RefreshError(RefreshErrorKind::Revoked, _))
+                | Err(RefreshError(RefreshErrorKind::Expired, _))
+                | Err(RefreshError(RefreshErrorKind::MissingRefreshToken, _))
+                | Err(RefreshError(RefreshErrorKind::NotFound(_), _)) => {
+                    error!("session can no longer be refreshed - login again");
+                    break;
+                }
+                Err(err) => {
+                    warn!("failed to refresh the session - {}", err);
+                    wait = AUTO_REFRESH_RETRY;
+                }
+            }
+        }
+    });
+
+    Ok(rx)
+}
+
+/// Keeps the session of a DIO that stays open (such as the one behind a
+/// mounted file system) up to date with the sessions that the auto refresh
+/// hands out, this stops once the DIO is dropped or the refresh stops
+pub fn rekey_on_refresh(sessions: &watch::Receiver<AteSessionUser>, dio: &Arc<Dio>) {
+    let mut sessions = sessions.clone();
+    let dio = Arc::downgrade(dio);
+    TaskEngine::spawn(async move {
+        while sessions.changed().await.is_ok() {
+            let dio = match dio.upgrade() {
+                Some(a) => a,
+                None => {
+                    break;
+                }
+            };
+            let session = sessions.borrow().clone();
+            debug!("dio rekeyed with the refreshed session ({})", session.identity());
+            dio.set_session(&session);
+        }
+    });
+}
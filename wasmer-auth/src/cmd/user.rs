@@ -40,6 +40,35 @@ pub async fn main_opts_user(
             )
             .await?;
         }
+        UserAction::Delete(action) => {
+            let session =
+                main_session_sudo(token.clone(), token_path.clone(), action.code, Some(auth.clone()))
+                    .await?;
+            main_delete_user(session, action.force, auth).await?;
+        }
+        UserAction::CancelDelete(action) => {
+            let session =
+                main_session_sudo(token.clone(), token_path.clone(), action.code, Some(auth.clone()))
+                    .await?;
+            main_cancel_delete_user(session, auth).await?;
+        }
+        UserAction::Activity(action) => {
+            if action.webhook.is_some() || action.clear_webhook {
+                let session = main_session_sudo(
+                    token.clone(),
+                    token_path.clone(),
+                    action.code,
+                    Some(auth.clone()),
+                )
+                .await?;
+                main_user_webhook(session, action.webhook, auth).await?;
+            } else {
+                let session =
+                    main_session_user(token.clone(), token_path.clone(), Some(auth.clone()))
+                        .await?;
+                main_user_activity(session, action.limit, auth).await?;
+            }
+        }
     }
     Ok(())
 }
@@ -0,0 +1,74 @@
+use error_chain::error_chain;
+
+use crate::request::*;
+use ::ate::prelude::*;
+
+error_chain! {
+    types {
+        DeleteUserError, DeleteUserErrorKind, ResultExt, Result;
+    }
+    links {
+        AteError(::ate::error::AteError, ::ate::error::AteErrorKind);
+        ChainCreationError(::ate::error::ChainCreationError, ::ate::error::ChainCreationErrorKind);
+        SerializationError(::ate::error::SerializationError, ::ate::error::SerializationErrorKind);
+        InvokeError(::ate::error::InvokeError, ::ate::error::InvokeErrorKind);
+        SudoError(super::SudoError, super::SudoErrorKind);
+    }
+    foreign_links {
+        IO(tokio::io::Error);
+    }
+    errors {
+        NoMasterKey {
+            description("account deletion failed as the server has not been properly initialized")
+            display("account deletion failed as the server has not been properly initialized")
+        }
+        InvalidArguments {
+            description("you did not provide the right type or quantity of arguments")
+            display("you did not provide the right type or quantity of arguments")
+        }
+        MissingToken {
+            description("account deletion failed as the token was missing"),
+            display("account deletion failed as the token was missing"),
+        }
+        NotElevated {
+            description("account deletion failed as the session does not have elevated (sudo) rights"),
+            display("account deletion failed as the session does not have elevated (sudo) rights"),
+        }
+        NotPending {
+            description("account deletion could not be cancelled as no deletion is pending"),
+            display("account deletion could not be cancelled as no deletion is pending"),
+        }
+        NotFound(username: String) {
+            description("account deletion failed as the account does not exist"),
+            display("account deletion failed for {} as the account does not exist", username),
+        }
+        RevokeFailed(group: String) {
+            description("account deletion failed as the access to a group could not be revoked"),
+            display("account deletion failed as the access to group {} could not be revoked", group),
+        }
+        InternalError(code: u16) {
+            description("account deletion failed as the server experienced an internal error")
+            display("account deletion failed as the server experienced an internal error - code={}", code)
+        }
+    }
+}
+
+impl From<DeleteUserError> for AteError {
+    fn from(err: DeleteUserError) -> AteError {
+        AteErrorKind::ServiceError(err.to_string()).into()
+    }
+}
+
+impl From<DeleteUserFailed> for DeleteUserError {
+    fn from(err: DeleteUserFailed) -> DeleteUserError {
+        match err {
+            DeleteUserFailed::MissingToken => DeleteUserErrorKind::MissingToken.into(),
+            DeleteUserFailed::NoMasterKey => DeleteUserErrorKind::NoMasterKey.into(),
+            DeleteUserFailed::NotElevated => DeleteUserErrorKind::NotElevated.into(),
+            DeleteUserFailed::NotPending => DeleteUserErrorKind::NotPending.into(),
+            DeleteUserFailed::UserNotFound(username) => DeleteUserErrorKind::NotFound(username).into(),
+            DeleteUserFailed::RevokeFailed(group) => DeleteUserErrorKind::RevokeFailed(group).into(),
+            DeleteUserFailed::InternalError(code) => DeleteUserErrorKind::InternalError(code).into(),
+        }
+    }
+}
@@ -38,10 +38,18 @@ error_chain! {
             description("login failed as the account is locked"),
             display("login failed as the account is locked for {} hours", (duration.as_secs() as f32 / 3600f32)),
         }
+        TemporarilyLocked(retry_after: Duration) {
+            description("login failed as there were too many failed attempts"),
+            display("login failed as there were too many failed attempts - try again in {} seconds", retry_after.as_secs().max(1)),
+        }
         Unverified(username: String) {
             description("login failed as the account is not yet verified")
             display("login failed for {} as the account is not yet verified", username)
         }
+        Revoked(username: String) {
+            description("login failed as the service account has been revoked")
+            display("login failed for {} as the service account has been revoked", username)
+        }
         WrongPassword {
             description("login failed due to an incorrect password")
             display("login failed due to an incorrect password")
@@ -68,6 +76,10 @@ impl From<LoginFailed> for LoginError {
             LoginFailed::UserNotFound(username) => LoginErrorKind::NotFound(username).into(),
             LoginFailed::WrongPassword => LoginErrorKind::WrongPassword.into(),
             LoginFailed::InternalError(code) => LoginErrorKind::InternalError(code).into(),
+            LoginFailed::TemporarilyLocked(retry_after) => {
+                LoginErrorKind::TemporarilyLocked(retry_after).into()
+            }
+            LoginFailed::Revoked(username) => LoginErrorKind::Revoked(username).into(),
         }
     }
 }
@@ -1,4 +1,5 @@
 mod create_error;
+mod delete_user_error;
 mod gather_error;
 mod group_details_error;
 mod group_remove_error;
@@ -6,11 +7,16 @@ mod group_user_add_error;
 mod group_user_remove_error;
 mod login_error;
 mod query_error;
+mod refresh_error;
 mod reset_error;
+mod security_activity_error;
+mod service_account_error;
 mod sudo_error;
 
 pub use create_error::CreateError;
 pub use create_error::CreateErrorKind;
+pub use delete_user_error::DeleteUserError;
+pub use delete_user_error::DeleteUserErrorKind;
 pub use gather_error::GatherError;
 pub use gather_error::GatherErrorKind;
 pub use group_details_error::GroupDetailsError;
@@ -25,7 +31,13 @@ pub use login_error::LoginError;
 pub use login_error::LoginErrorKind;
 pub use query_error::QueryError;
 pub use query_error::QueryErrorKind;
+pub use refresh_error::RefreshError;
+pub use refresh_error::RefreshErrorKind;
 pub use reset_error::ResetError;
 pub use reset_error::ResetErrorKind;
+pub use security_activity_error::SecurityActivityError;
+pub use security_activity_error::SecurityActivityErrorKind;
+pub use service_account_error::ServiceAccountError;
+pub use service_account_error::ServiceAccountErrorKind;
 pub use sudo_error::SudoError;
 pub use sudo_error::SudoErrorKind;
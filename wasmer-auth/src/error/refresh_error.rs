@@ -0,0 +1,79 @@
+use error_chain::error_chain;
+use std::time::Duration;
+
+use crate::request::*;
+use ::ate::prelude::*;
+
+error_chain! {
+    types {
+        RefreshError, RefreshErrorKind, ResultExt, Result;
+    }
+    links {
+        AteError(::ate::error::AteError, ::ate::error::AteErrorKind);
+        ChainCreationError(::ate::error::ChainCreationError, ::ate::error::ChainCreationErrorKind);
+        SerializationError(::ate::error::SerializationError, ::ate::error::SerializationErrorKind);
+        InvokeError(::ate::error::InvokeError, ::ate::error::InvokeErrorKind);
+    }
+    foreign_links {
+        IO(tokio::io::Error);
+    }
+    errors {
+        NoMasterKey {
+            description("refresh failed as the server has not been properly initialized")
+            display("refresh failed as the server has not been properly initialized")
+        }
+        MissingRefreshToken {
+            description("refresh failed as there is no refresh token for this session - login again")
+            display("refresh failed as there is no refresh token for this session - login again")
+        }
+        Revoked {
+            description("refresh failed as the refresh token has been revoked - login again")
+            display("refresh failed as the refresh token has been revoked - login again")
+        }
+        Expired {
+            description("refresh failed as the refresh token has expired - login again")
+            display("refresh failed as the refresh token has expired - login again")
+        }
+        NotFound(username: String) {
+            description("refresh failed as the account does not exist"),
+            display("refresh failed for {} as the account does not exist", username),
+        }
+        AccountLocked(duration: Duration) {
+            description("refresh failed as the account is locked"),
+            display("refresh failed as the account is locked for {} hours", (duration.as_secs() as f32 / 3600f32)),
+        }
+        Unverified(username: String) {
+            description("refresh failed as the account is not yet verified")
+            display("refresh failed for {} as the account is not yet verified", username)
+        }
+        Busy {
+            description("refresh failed as another refresh of the same account is still underway")
+            display("refresh failed as another refresh of the same account is still underway")
+        }
+        InternalError(code: u16) {
+            description("refresh failed as the server experienced an internal error")
+            display("refresh failed as the server experienced an internal error - code={}", code)
+        }
+    }
+}
+
+impl From<RefreshError> for AteError {
+    fn from(err: RefreshError) -> AteError {
+        AteErrorKind::ServiceError(err.to_string()).into()
+    }
+}
+
+impl From<RefreshFailed> for RefreshError {
+    fn from(err: RefreshFailed) -> RefreshError {
+        match err {
+            RefreshFailed::AccountLocked(duration) => RefreshErrorKind::AccountLocked(duration).into(),
+            RefreshFailed::Expired => RefreshErrorKind::Expired.into(),
+            RefreshFailed::NoMasterKey => RefreshErrorKind::NoMasterKey.into(),
+            RefreshFailed::Busy => RefreshErrorKind::Busy.into(),
+            RefreshFailed::Revoked => RefreshErrorKind::Revoked.into(),
+            RefreshFailed::Unverified(username) => RefreshErrorKind::Unverified(username).into(),
+            RefreshFailed::UserNotFound(username) => RefreshErrorKind::NotFound(username).into(),
+            RefreshFailed::InternalError(code) => RefreshErrorKind::InternalError(code).into(),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use error_chain::error_chain;
+
+use crate::request::*;
+use ::ate::prelude::*;
+
+error_chain! {
+    types {
+        SecurityActivityError, SecurityActivityErrorKind, ResultExt, Result;
+    }
+    links {
+        AteError(::ate::error::AteError, ::ate::error::AteErrorKind);
+        ChainCreationError(::ate::error::ChainCreationError, ::ate::error::ChainCreationErrorKind);
+        SerializationError(::ate::error::SerializationError, ::ate::error::SerializationErrorKind);
+        InvokeError(::ate::error::InvokeError, ::ate::error::InvokeErrorKind);
+        SudoError(super::SudoError, super::SudoErrorKind);
+    }
+    foreign_links {
+        IO(tokio::io::Error);
+    }
+    errors {
+        NoMasterKey {
+            description("security activity failed as the server has not been properly initialized")
+            display("security activity failed as the server has not been properly initialized")
+        }
+        MissingToken {
+            description("security activity failed as the token was missing"),
+            display("security activity failed as the token was missing"),
+        }
+        NotElevated {
+            description("security activity failed as the session does not have elevated (sudo) rights"),
+            display("security activity failed as the session does not have elevated (sudo) rights"),
+        }
+        InvalidWebhook(url: String) {
+            description("security activity failed as the webhook is not a valid http(s) URL"),
+            display("security activity failed as the webhook ({}) is not a valid http(s) URL", url),
+        }
+        NotFound(username: String) {
+            description("security activity failed as the account does not exist"),
+            display("security activity failed for {} as the account does not exist", username),
+        }
+        InternalError(code: u16) {
+            description("security activity failed as the server experienced an internal error")
+            display("security activity failed as the server experienced an internal error - code={}", code)
+        }
+    }
+}
+
+impl From<SecurityActivityError> for AteError {
+    fn from(err: SecurityActivityError) -> AteError {
+        AteErrorKind::ServiceError(err.to_string()).into()
+    }
+}
+
+impl From<SecurityActivityFailed> for SecurityActivityError {
+    fn from(err: SecurityActivityFailed) -> SecurityActivityError {
+        match err {
+            SecurityActivityFailed::MissingToken => SecurityActivityErrorKind::MissingToken.into(),
+            SecurityActivityFailed::NoMasterKey => SecurityActivityErrorKind::NoMasterKey.into(),
+            SecurityActivityFailed::NotElevated => SecurityActivityErrorKind::NotElevated.into(),
+            SecurityActivityFailed::InvalidWebhook(url) => {
+                SecurityActivityErrorKind::InvalidWebhook(url).into()
+            }
+            SecurityActivityFailed::UserNotFound(username) => {
+                SecurityActivityErrorKind::NotFound(username).into()
+            }
+            SecurityActivityFailed::InternalError(code) => {
+                SecurityActivityErrorKind::InternalError(code).into()
+            }
+        }
+    }
+}
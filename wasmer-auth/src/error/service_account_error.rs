@@ -0,0 +1,79 @@
+use error_chain::error_chain;
+
+use crate::request::*;
+use ::ate::prelude::*;
+
+error_chain! {
+    types {
+        ServiceAccountError, ServiceAccountErrorKind, ResultExt, Result;
+    }
+    links {
+        AteError(::ate::error::AteError, ::ate::error::AteErrorKind);
+        ChainCreationError(::ate::error::ChainCreationError, ::ate::error::ChainCreationErrorKind);
+        SerializationError(::ate::error::SerializationError, ::ate::error::SerializationErrorKind);
+        InvokeError(::ate::error::InvokeError, ::ate::error::InvokeErrorKind);
+        LoginError(super::LoginError, super::LoginErrorKind);
+        SudoError(super::SudoError, super::SudoErrorKind);
+    }
+    foreign_links {
+        IO(tokio::io::Error);
+    }
+    errors {
+        NoMasterKey {
+            description("service account operation failed as the server has not been properly initialized")
+            display("service account operation failed as the server has not been properly initialized")
+        }
+        InvalidArguments {
+            description("you did not provide the right type or quantity of arguments")
+            display("you did not provide the right type or quantity of arguments")
+        }
+        InvalidName {
+            description("service account names may only contain lowercase letters, numbers, dashes and underscores"),
+            display("service account names may only contain lowercase letters, numbers, dashes and underscores"),
+        }
+        InvalidKeyFile(path: String) {
+            description("the file does not hold a service account key"),
+            display("the file ({}) does not hold a service account key", path),
+        }
+        AlreadyExists(identity: String) {
+            description("service account creation failed as the account already exists"),
+            display("service account creation failed as {} already exists", identity),
+        }
+        NotFound(identity: String) {
+            description("service account operation failed as the account does not exist"),
+            display("service account operation failed as {} does not exist", identity),
+        }
+        MissingToken {
+            description("service account operation failed as the token was missing"),
+            display("service account operation failed as the token was missing"),
+        }
+        NotElevated {
+            description("service account operation failed as the session does not have elevated (sudo) rights"),
+            display("service account operation failed as the session does not have elevated (sudo) rights"),
+        }
+        InternalError(code: u16) {
+            description("service account operation failed as the server experienced an internal error")
+            display("service account operation failed as the server experienced an internal error - code={}", code)
+        }
+    }
+}
+
+impl From<ServiceAccountError> for AteError {
+    fn from(err: ServiceAccountError) -> AteError {
+        AteErrorKind::ServiceError(err.to_string()).into()
+    }
+}
+
+impl From<ServiceAccountFailed> for ServiceAccountError {
+    fn from(err: ServiceAccountFailed) -> ServiceAccountError {
+        match err {
+            ServiceAccountFailed::AlreadyExists(identity) => ServiceAccountErrorKind::AlreadyExists(identity).into(),
+            ServiceAccountFailed::InvalidName => ServiceAccountErrorKind::InvalidName.into(),
+            ServiceAccountFailed::NotFound(identity) => ServiceAccountErrorKind::NotFound(identity).into(),
+            ServiceAccountFailed::MissingToken => ServiceAccountErrorKind::MissingToken.into(),
+            ServiceAccountFailed::NotElevated => ServiceAccountErrorKind::NotElevated.into(),
+            ServiceAccountFailed::NoMasterKey => ServiceAccountErrorKind::NoMasterKey.into(),
+            ServiceAccountFailed::InternalError(code) => ServiceAccountErrorKind::InternalError(code).into(),
+        }
+    }
+}
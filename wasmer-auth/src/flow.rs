@@ -9,6 +9,7 @@ use ate::{error::ChainCreationError, prelude::*};
 use regex::Regex;
 
 use crate::service::*;
+use crate::work::LoginThrottle;
 
 pub struct ChainFlow {
     cfg: ConfAte,
@@ -21,6 +22,10 @@ pub struct ChainFlow {
     regex_cmd: Regex,
     session: AteSessionUser,
     pub terms_and_conditions: Option<String>,
+    pub delete_grace_period: chrono::Duration,
+    /// Shared by all the command chains so that failed logins are counted
+    /// across them
+    pub login_throttle: Arc<LoginThrottle>,
 }
 
 impl ChainFlow {
@@ -44,6 +49,8 @@ impl ChainFlow {
             edge_key,
             contract_key,
             terms_and_conditions: None,
+            delete_grace_period: chrono::Duration::days(30),
+            login_throttle: Arc::new(LoginThrottle::default()),
         }
     }
 }
@@ -100,6 +107,7 @@ impl OpenFlow for ChainFlow {
             };
 
             // Build the chain
+            let peer = builder.peer().cloned();
             builder = builder
                 .set_session(cmd_session.clone_session())
                 .temporal(true);
@@ -119,6 +127,9 @@ impl OpenFlow for ChainFlow {
                 self.edge_key.clone(),
                 self.contract_key.clone(),
                 self.terms_and_conditions.clone(),
+                self.delete_grace_period,
+                self.login_throttle.clone(),
+                peer,
                 &Arc::clone(&chain),
             )
             .await?;
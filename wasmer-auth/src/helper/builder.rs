@@ -125,6 +125,6 @@ impl DioBuilder {
         let key = ChainKey::new(self.generate_key(name));
         let registry = self.get_registry().await;
         let chain = registry.open(&self.url_db, &key, true).await?;
-        Ok(chain.dio_mut(self.session.deref()).await)
+        Ok(chain.dio_mut(self.session.deref()).await.map_err(AteError::from)?)
     }
 }
@@ -0,0 +1,286 @@
+#![allow(unused_imports)]
+use ate::prelude::*;
+use fxhash::FxHashMap;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use url::Url;
+
+use crate::request::*;
+
+/// Default amount of time that gathered group sessions and group details are cached
+pub const DEFAULT_GROUP_CACHE_TTL: Duration = Duration::from_secs(300);
+/// Default maximum number of entries held in each of the group caches
+pub const DEFAULT_GROUP_CACHE_MAX_ENTRIES: usize = 256;
+/// How long a cache hit waits for the server to confirm the version of the group
+pub const GROUP_CACHE_REVALIDATE_TIMEOUT: Duration = Duration::from_secs(5);
+
+static GROUP_CACHE: Lazy<GroupCache> = Lazy::new(|| GroupCache::new());
+
+/// Returns the cache used by the client helpers for the results of the gather
+/// and group details commands
+pub fn group_cache() -> &'static GroupCache {
+    &GROUP_CACHE
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct GroupCacheKey {
+    auth: String,
+    group: String,
+    user: String,
+}
+
+impl GroupCacheKey {
+    fn new(auth: &Url, group: &str, session: Option<&AteSessionInner>) -> GroupCacheKey {
+        // Sudo sessions gather more rights than normal sessions hence they are
+        // cached separately even though they share the same identity
+        let user = match session {
+            Some(AteSessionInner::User(a)) => format!("user:{}", a.identity()),
+            Some(AteSessionInner::Sudo(a)) => format!("sudo:{}", a.identity()),
+            Some(AteSessionInner::Nothing) | None => "nobody".to_string(),
+        };
+        GroupCacheKey {
+            auth: auth.to_string(),
+            group: group.to_string(),
+            user,
+        }
+    }
+
+    fn is_group(&self, auth: &str, group: &str) -> bool {
+        self.auth == auth && self.group == group
+    }
+}
+
+struct GroupCacheEntry<T> {
+    value: T,
+    version: u64,
+    when: Instant,
+}
+
+struct GroupCacheConfig {
+    enabled: bool,
+    ttl: Duration,
+    max_entries: usize,
+}
+
+#[derive(Default)]
+struct GroupCacheState {
+    gather: FxHashMap<GroupCacheKey, GroupCacheEntry<AteSessionGroup>>,
+    details: FxHashMap<GroupCacheKey, GroupCacheEntry<GroupDetailsResponse>>,
+    versions: FxHashMap<(String, String), u64>,
+}
+
+/// Caches the group sessions returned by gather and the results of group details
+/// keyed by the authentication server, group and user. Entries expire after the
+/// TTL, are cleared locally when the group is changed by this process and are
+/// dropped early when the server reports a newer version of the group
+pub struct GroupCache {
+    config: Mutex<GroupCacheConfig>,
+    state: Mutex<GroupCacheState>,
+}
+
+impl GroupCache {
+    fn new() -> GroupCache {
+        GroupCache {
+            config: Mutex::new(GroupCacheConfig {
+                enabled: true,
+                ttl: DEFAULT_GROUP_CACHE_TTL,
+                max_entries: DEFAULT_GROUP_CACHE_MAX_ENTRIES,
+            }),
+            state: Mutex::new(GroupCacheState::default()),
+        }
+    }
+
+    /// Changes how long entries are cached for and how many are kept
+    pub fn configure(&self, ttl: Duration, max_entries: usize) {
+        {
+            let mut config = self.config.lock().unwrap();
+            config.ttl = ttl;
+            config.max_entries = max_entries;
+        }
+        self.clear();
+    }
+
+    /// Enables or disables the cache (when disabled every call goes to the server)
+    pub fn set_enabled(&self, enabled: bool) {
+        self.config.lock().unwrap().enabled = enabled;
+        if enabled == false {
+            self.clear();
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.gather.clear();
+        state.details.clear();
+    }
+
+    /// Removes all the entries for a group (for every user), this is called
+    /// whenever the group is modified or removed
+    pub fn invalidate_group(&self, auth: &Url, group: &str) {
+        let auth = auth.to_string();
+        let mut state = self.state.lock().unwrap();
+        state.gather.retain(|k, _| k.is_group(auth.as_str(), group) == false);
+        state.details.retain(|k, _| k.is_group(auth.as_str(), group) == false);
+        debug!("group cache invalidated (group={})", group);
+    }
+
+    /// Records the latest version of a group reported by the server, any
+    /// entries that were cached from an older version are dropped
+    pub fn observe_version(&self, auth: &Url, group: &str, version: u64) {
+        let auth = auth.to_string();
+        let mut state = self.state.lock().unwrap();
+        let known = state
+            .versions
+            .entry((auth.clone(), group.to_string()))
+            .or_default();
+        if version <= *known {
+            return;
+        }
+        *known = version;
+
+        state
+            .gather
+            .retain(|k, v| k.is_group(auth.as_str(), group) == false || v.version >= version);
+        state
+            .details
+            .retain(|k, v| k.is_group(auth.as_str(), group) == false || v.version >= version);
+    }
+
+    pub fn get_gather(
+        &self,
+        auth: &Url,
+        group: &str,
+        session: &AteSessionInner,
+    ) -> Option<AteSessionGroup> {
+        let key = GroupCacheKey::new(auth, group, Some(session));
+        let ret = self.get(&key, |s| &mut s.gather)?;
+
+        // The group rights are cached but the session they are attached to is
+        // always the one supplied by the caller
+        Some(AteSessionGroup {
+            inner: session.clone(),
+            group: ret.group,
+        })
+    }
+
+    pub fn put_gather(
+        &self,
+        auth: &Url,
+        group: &str,
+        session: &AteSessionInner,
+        value: &AteSessionGroup,
+        version: u64,
+    ) {
+        self.observe_version(auth, group, version);
+        let key = GroupCacheKey::new(auth, group, Some(session));
+        self.put(key, value.clone(), version, |s| &mut s.gather);
+    }
+
+    pub fn get_details(
+        &self,
+        auth: &Url,
+        group: &str,
+        session: Option<&AteSessionGroup>,
+    ) -> Option<GroupDetailsResponse> {
+        let key = GroupCacheKey::new(auth, group, session.map(|a| &a.inner));
+        self.get(&key, |s| &mut s.details)
+    }
+
+    pub fn put_details(
+        &self,
+        auth: &Url,
+        group: &str,
+        session: Option<&AteSessionGroup>,
+        value: &GroupDetailsResponse,
+    ) {
+        self.observe_version(auth, group, value.version);
+        let key = GroupCacheKey::new(auth, group, session.map(|a| &a.inner));
+        self.put(key, value.clone(), value.version, |s| &mut s.details);
+    }
+
+    fn get<T, F>(&self, key: &GroupCacheKey, map: F) -> Option<T>
+    where
+        T: Clone,
+        F: Fn(&mut GroupCacheState) -> &mut FxHashMap<GroupCacheKey, GroupCacheEntry<T>>,
+    {
+        let (enabled, ttl) = {
+            let config = self.config.lock().unwrap();
+            (config.enabled, config.ttl)
+        };
+        if enabled == false {
+            return None;
+        }
+
+        // Entries cached from an older version than the server has since
+        // reported are stale even if they have not yet expired
+        let mut state = self.state.lock().unwrap();
+        let known = state
+            .versions
+            .get(&(key.auth.clone(), key.group.clone()))
+            .map(|a| *a)
+            .unwrap_or_default();
+        let map = map(&mut state);
+        match map.get(key) {
+            Some(a) if a.when.elapsed() < ttl && a.version >= known => {
+                trace!("group cache hit (group={}, user={})", key.group, key.user);
+                Some(a.value.clone())
+            }
+            Some(_) => {
+                map.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn put<T, F>(&self, key: GroupCacheKey, value: T, version: u64, map: F)
+    where
+        F: Fn(&mut GroupCacheState) -> &mut FxHashMap<GroupCacheKey, GroupCacheEntry<T>>,
+    {
+        let (enabled, max_entries) = {
+            let config = self.config.lock().unwrap();
+            (config.enabled, config.max_entries)
+        };
+        if enabled == false || max_entries == 0 {
+            return;
+        }
+
+        // Responses that arrive after a newer version was seen are not kept
+        let mut state = self.state.lock().unwrap();
+        let known = state
+            .versions
+            .get(&(key.auth.clone(), key.group.clone()))
+            .map(|a| *a)
+            .unwrap_or_default();
+        if version < known {
+            return;
+        }
+        let map = map(&mut state);
+
+        // Make room by evicting the oldest entries
+        while map.len() >= max_entries && map.contains_key(&key) == false {
+            let oldest = map
+                .iter()
+                .min_by_key(|(_, v)| v.when)
+                .map(|(k, _)| k.clone());
+            match oldest {
+                Some(a) => {
+                    map.remove(&a);
+                }
+                None => break,
+            }
+        }
+
+        map.insert(
+            key,
+            GroupCacheEntry {
+                value,
+                version,
+                when: Instant::now(),
+            },
+        );
+    }
+}
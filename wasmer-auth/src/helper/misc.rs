@@ -4,6 +4,9 @@ use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use ::ate::crypto::EncryptKey;
 use ::ate::prelude::*;
 
+use crate::request::RefreshToken;
+use crate::request::ServiceAccountKey;
+
 pub fn password_to_read_key(
     seed: &String,
     password: &String,
@@ -44,6 +47,43 @@ pub fn b64_to_session(val: String) -> AteSessionType {
     format.deserialize(bytes).unwrap()
 }
 
+pub fn refresh_token_to_b64(token: &RefreshToken) -> Result<String, SerializationError> {
+    let format = SerializationFormat::MessagePack;
+    let bytes = format.serialize(token)?;
+    Ok(base64::encode(bytes))
+}
+
+pub fn b64_to_refresh_token(val: String) -> Option<RefreshToken> {
+    let val = val.trim().to_string();
+    let format = SerializationFormat::MessagePack;
+    let bytes = base64::decode(val).ok()?;
+    format.deserialize(bytes).ok()
+}
+
+pub fn service_account_key_to_b64(key: &ServiceAccountKey) -> Result<String, SerializationError> {
+    let format = SerializationFormat::MessagePack;
+    let bytes = format.serialize(key)?;
+    Ok(base64::encode(bytes))
+}
+
+pub fn b64_to_service_account_key(val: String) -> Option<ServiceAccountKey> {
+    let val = val.trim().to_string();
+    let format = SerializationFormat::MessagePack;
+    let bytes = base64::decode(val).ok()?;
+    format.deserialize(bytes).ok()
+}
+
+/// Describes this client to the authentication server (shown to the user
+/// in their account activity)
+pub fn user_agent() -> String {
+    format!(
+        "{}/{} ({})",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS
+    )
+}
+
 #[allow(dead_code)]
 pub fn is_public_domain(domain: &str) -> bool {
     match domain {
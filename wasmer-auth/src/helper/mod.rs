@@ -1,11 +1,13 @@
 mod auth;
 mod builder;
 mod conf;
+mod group_cache;
 mod keys;
 mod misc;
 
 pub use auth::*;
 pub use builder::*;
 pub use conf::*;
+pub use group_cache::*;
 pub use keys::*;
 pub use misc::*;
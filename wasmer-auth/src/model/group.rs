@@ -14,3 +14,15 @@ pub struct Group {
     pub broker_read: PrivateEncryptKey,
     pub broker_write: PrivateSignKey,
 }
+
+/// Groups that a user has been added to, stored next to the user in the auth
+/// chain where only the authentication server can read it so that all the
+/// access of the user can be revoked when their account is erased
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GroupMemberships {
+    pub groups: Vec<String>,
+}
+
+pub fn group_memberships_key(email: &str) -> PrimaryKey {
+    PrimaryKey::from(format!("group-memberships:{}", email))
+}
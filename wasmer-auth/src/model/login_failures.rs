@@ -0,0 +1,21 @@
+use serde::*;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use ate::prelude::*;
+
+/// Failed login attempts against an account, this is stored next to the user
+/// in the auth chain (even for accounts that do not exist so that they behave
+/// the same) which means lockouts survive restarts of the server
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LoginFailures {
+    /// Number of consecutive failed attempts
+    pub failures: u32,
+    pub last_failure: Option<chrono::DateTime<chrono::Utc>>,
+    /// Logins are refused until this time even with the right password
+    pub locked_until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+pub fn login_failures_key(email: &str) -> PrimaryKey {
+    PrimaryKey::from(format!("login-failures:{}", email))
+}
@@ -6,12 +6,17 @@ mod company;
 mod email_verification;
 mod gender;
 mod group;
+mod login_failures;
 mod person;
+mod refresh_grant;
 mod role;
+mod security_event;
+mod service_account;
 mod sms_verification;
 mod ssh_key_type;
 mod sudo;
 mod user;
+mod user_deletion;
 mod user_recovery;
 mod user_role;
 mod user_status;
@@ -24,12 +29,17 @@ pub use company::*;
 pub use email_verification::*;
 pub use gender::*;
 pub use group::*;
+pub use login_failures::*;
 pub use person::*;
+pub use refresh_grant::*;
 pub use role::*;
+pub use security_event::*;
+pub use service_account::*;
 pub use sms_verification::*;
 pub use ssh_key_type::*;
 pub use sudo::*;
 pub use user::*;
+pub use user_deletion::*;
 pub use user_recovery::*;
 pub use user_role::*;
 pub use user_status::*;
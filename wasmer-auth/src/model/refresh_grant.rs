@@ -0,0 +1,37 @@
+use serde::*;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use ate::prelude::*;
+
+/// Number of days a refresh token can be used for before the user must
+/// login again with their password
+pub const REFRESH_TOKEN_LIFETIME_DAYS: i64 = 30;
+/// Maximum number of refresh tokens a user can hold at once (one per
+/// device or daemon), the oldest is revoked when another is issued
+pub const MAX_REFRESH_GRANTS: usize = 16;
+/// How long a refresh waits for another refresh of the same user to finish
+/// rotating its token before it gives up
+pub const REFRESH_LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Refresh token that was issued to a user, only the hash of the secret is
+/// kept while the token lets the authentication server rebuild the session
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefreshGrant {
+    pub id: PrimaryKey,
+    pub secret_hash: AteHash,
+    pub issued: chrono::DateTime<chrono::Utc>,
+    pub expires: chrono::DateTime<chrono::Utc>,
+    pub token: EncryptedSecureData<EncryptKey>,
+}
+
+/// All the refresh tokens of a user which are stored next to the user in
+/// the auth chain, removing a grant revokes the refresh token immediately
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RefreshGrants {
+    pub grants: Vec<RefreshGrant>,
+}
+
+pub fn refresh_grants_key(email: &str) -> PrimaryKey {
+    PrimaryKey::from(format!("refresh-grants:{}", email))
+}
@@ -0,0 +1,186 @@
+use serde::*;
+use std::net::IpAddr;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use ate::prelude::*;
+
+/// Maximum number of security events that are kept for a user, the oldest
+/// are dropped as new ones are recorded
+pub const MAX_SECURITY_EVENTS: usize = 100;
+/// Maximum number of sources that are remembered for a user when deciding
+/// if a login came from somewhere new
+pub const MAX_SEEN_SOURCES: usize = 32;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SecurityEventKind {
+    Login,
+    Sudo,
+}
+
+impl std::fmt::Display for SecurityEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecurityEventKind::Login => write!(f, "login"),
+            SecurityEventKind::Sudo => write!(f, "sudo"),
+        }
+    }
+}
+
+/// Attempt to authenticate as a user that is shown to the user so that they
+/// can spot logins that they did not make themselves
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecurityEvent {
+    pub when: chrono::DateTime<chrono::Utc>,
+    pub kind: SecurityEventKind,
+    pub success: bool,
+    /// Address of the client that made the attempt (if it is known)
+    pub addr: Option<String>,
+    pub node_id: Option<NodeId>,
+    /// Description of the client supplied by the client itself
+    pub user_agent: Option<String>,
+}
+
+impl SecurityEvent {
+    /// Hash that identifies where the attempt came from, the port of the
+    /// address is left out as it changes with every connection
+    pub fn source(&self) -> AteHash {
+        let ip = self
+            .addr
+            .as_ref()
+            .map(|a| match a.parse::<std::net::SocketAddr>() {
+                Ok(a) => a.ip().to_string(),
+                Err(_) => a.clone(),
+            })
+            .unwrap_or_default();
+        let user_agent = self.user_agent.clone().unwrap_or_default();
+        AteHash::from_bytes(format!("{}:{}", ip, user_agent).as_bytes())
+    }
+}
+
+/// Security events of a user which are stored next to the user in the auth
+/// chain (encrypted with the master key so that failed logins can be added)
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SecurityEvents {
+    pub events: Vec<SecurityEvent>,
+    /// Sources that the user has successfully logged in from before
+    pub seen_sources: Vec<AteHash>,
+    /// Notified when there is a successful login from a new source
+    pub webhook: Option<String>,
+}
+
+impl SecurityEvents {
+    /// Adds an event and returns true if it was a successful login from a
+    /// source that has not been seen before
+    pub fn record(&mut self, event: SecurityEvent) -> bool {
+        let mut is_new = false;
+        if event.success && event.kind == SecurityEventKind::Login {
+            let source = event.source();
+            match self.seen_sources.iter().position(|a| *a == source) {
+                Some(n) => {
+                    self.seen_sources.remove(n);
+                }
+                None => is_new = true,
+            }
+            self.seen_sources.push(source);
+            while self.seen_sources.len() > MAX_SEEN_SOURCES {
+                self.seen_sources.remove(0);
+            }
+        }
+
+        self.events.push(event);
+        while self.events.len() > MAX_SECURITY_EVENTS {
+            self.events.remove(0);
+        }
+        is_new
+    }
+}
+
+/// Body of the webhook that is posted when a user logs in from a new source,
+/// the signature of the body is in the `X-Ate-Signature` header (base64) and
+/// is made with the key of the authentication server
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecurityNotification {
+    pub email: String,
+    pub event: SecurityEvent,
+}
+
+impl SecurityNotification {
+    /// Checks that a webhook body was signed by the authentication server
+    pub fn verify(body: &[u8], signature: &str, server_key: &PublicSignKey) -> bool {
+        let signature = match base64::decode(signature.trim()) {
+            Ok(a) => a,
+            Err(_) => return false,
+        };
+        server_key.verify(body, &signature[..]).unwrap_or(false)
+    }
+}
+
+/// Checks that notifications can be posted to a webhook, it must use https
+/// and must not name a host on the loopback or a private network (the
+/// addresses it resolves to are checked again when it is notified)
+pub fn is_allowed_webhook(webhook: &str) -> bool {
+    let url = match url::Url::parse(webhook) {
+        Ok(a) => a,
+        Err(_) => return false,
+    };
+    if url.scheme() != "https" {
+        return false;
+    }
+    match url.host() {
+        Some(url::Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            domain != "localhost"
+                && domain.ends_with(".localhost") == false
+                && domain.ends_with(".local") == false
+                && domain.ends_with(".internal") == false
+        }
+        Some(url::Host::Ipv4(ip)) => is_public_ip(&IpAddr::V4(ip)),
+        Some(url::Host::Ipv6(ip)) => is_public_ip(&IpAddr::V6(ip)),
+        None => false,
+    }
+}
+
+/// Returns true if the address is reachable on the public internet (rather
+/// than being a loopback, private, link local or otherwise reserved address)
+pub fn is_public_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let o = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || o[0] == 0
+                || (o[0] == 100 && (o[1] & 0xc0) == 64)
+                || (o[0] == 192 && o[1] == 0 && o[2] == 0)
+                || (o[0] == 198 && (o[1] & 0xfe) == 18)
+                || o[0] >= 240)
+        }
+        IpAddr::V6(ip) => {
+            let s = ip.segments();
+            if s[..5] == [0u16; 5] && s[5] == 0xffff {
+                let v4 = std::net::Ipv4Addr::new(
+                    (s[6] >> 8) as u8,
+                    s[6] as u8,
+                    (s[7] >> 8) as u8,
+                    s[7] as u8,
+                );
+                return is_public_ip(&IpAddr::V4(v4));
+            }
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                || (s[0] & 0xfe00) == 0xfc00
+                || (s[0] & 0xffc0) == 0xfe80
+                || (s[0] == 0x2001 && s[1] == 0x0db8))
+        }
+    }
+}
+
+pub fn security_events_key(email: &str) -> PrimaryKey {
+    PrimaryKey::from(format!("security-events:{}", email))
+}
@@ -0,0 +1,52 @@
+use serde::*;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use ate::prelude::*;
+
+/// Domain that the identities of all service accounts belong to, people can
+/// not register accounts in it so service accounts are always recognisable
+pub const SERVICE_ACCOUNT_DOMAIN: &str = "service.ate";
+
+/// Service account (an identity used by automation) which authenticates by
+/// signing a challenge with its private key rather than with a password.
+/// The record is stored next to the user that represents the account.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServiceAccount {
+    pub identity: String,
+    /// User that created the account (only they may revoke it)
+    pub owner: String,
+    pub public_key: PublicSignKey,
+    pub created: chrono::DateTime<chrono::Utc>,
+    /// Logins are refused once the account has been revoked
+    pub revoked: Option<chrono::DateTime<chrono::Utc>>,
+    /// Super key of the user record wrapped by the master key (the account
+    /// has no password to derive it from)
+    pub token: EncryptedSecureData<EncryptKey>,
+}
+
+/// Service accounts that a user has created, stored next to the user
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ServiceAccounts {
+    #[serde(default)]
+    pub identities: Vec<String>,
+}
+
+pub fn service_account_identity(name: &str) -> String {
+    format!("{}@{}", name, SERVICE_ACCOUNT_DOMAIN)
+}
+
+pub fn is_service_account(identity: &str) -> bool {
+    identity
+        .rsplit_once('@')
+        .map(|(_, domain)| domain == SERVICE_ACCOUNT_DOMAIN)
+        .unwrap_or(false)
+}
+
+pub fn service_account_key(identity: &str) -> PrimaryKey {
+    PrimaryKey::from(format!("service-account:{}", identity))
+}
+
+pub fn service_accounts_key(owner: &str) -> PrimaryKey {
+    PrimaryKey::from(format!("service-accounts:{}", owner))
+}
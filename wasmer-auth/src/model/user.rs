@@ -1,6 +1,8 @@
 use ate::crypto::*;
+use ate::error::LoadError;
 use ate::prelude::*;
 use serde::*;
+use std::sync::Arc;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
@@ -30,3 +32,40 @@ pub struct User {
     pub broker_read: PrivateEncryptKey,
     pub broker_write: PrivateSignKey,
 }
+
+/// Users are looked up by their email address (which is case-insensitive)
+impl DaoIndexed for User {
+    const INDEX_NAME: &'static str = "user";
+    const INDEXED_FIELDS: &'static [&'static str] = &["email"];
+
+    fn index_value(&self, field: &str) -> Option<IndexValue> {
+        match field {
+            "email" => Some(IndexValue::Text(self.email.to_lowercase())),
+            _ => None,
+        }
+    }
+}
+
+/// Finds a user by their email address through the index, users that were
+/// written before the index existed are loaded by their key instead (their
+/// index entries are rebuilt when the transaction is committed)
+pub async fn find_user(dio: &Arc<DioMut>, email: &str) -> Result<DaoMut<User>, LoadError> {
+    register_index::<User>();
+
+    let found = dio
+        .find_by::<User>("email", email.to_lowercase())
+        .await
+        .map_err(|err| debug!("user index lookup failed ({}) - {}", email, err))
+        .ok()
+        .and_then(|a| a.into_iter().next());
+    if let Some(user) = found {
+        return Ok(user);
+    }
+
+    let user_key = PrimaryKey::from(email.to_string());
+    let user = dio.load::<User>(&user_key).await?;
+    if let Err(err) = dio.repair_index::<User>(vec![user_key]).await {
+        debug!("failed to repair the user index ({}) - {}", email, err);
+    }
+    Ok(user)
+}
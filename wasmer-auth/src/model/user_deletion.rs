@@ -0,0 +1,29 @@
+use serde::*;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use ate::prelude::*;
+
+/// Key of the row (and chain) that holds the list of accounts that are
+/// waiting to be erased once their grace period expires
+pub const DELETION_SCHEDULE_KEY: &'static str = "deletion-schedule";
+
+/// Pending deletion of a user account which is stored next to the user in the
+/// auth chain. The token lets the authentication server finish the erasure
+/// once the grace period expires even if the user never returns.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserDeletion {
+    pub email: String,
+    pub requested: chrono::DateTime<chrono::Utc>,
+    pub erase_after: chrono::DateTime<chrono::Utc>,
+    pub token: EncryptedSecureData<EncryptKey>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DeletionSchedule {
+    pub pending: Vec<String>,
+}
+
+pub fn user_deletion_key(email: &str) -> PrimaryKey {
+    PrimaryKey::from(format!("deletion:{}", email))
+}
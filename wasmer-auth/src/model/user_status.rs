@@ -7,6 +7,7 @@ pub enum UserStatus {
     Nominal,
     Unverified,
     Locked(chrono::DateTime<chrono::Utc>),
+    PendingDeletion(chrono::DateTime<chrono::Utc>),
 }
 
 impl Default for UserStatus {
@@ -0,0 +1,9 @@
+use clap::Parser;
+
+/// Cancels a pending deletion of your account (only possible within the grace period)
+#[derive(Parser)]
+pub struct CancelDeleteUser {
+    /// The authenticator code from your mobile authenticator
+    #[clap(long)]
+    pub code: Option<String>,
+}
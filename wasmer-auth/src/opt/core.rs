@@ -23,6 +23,9 @@ pub struct Opts {
     /// Logs debug info to the console
     #[clap(short, long)]
     pub debug: bool,
+    /// Bypasses the local cache of group sessions and details (useful for debugging)
+    #[clap(long)]
+    pub no_cache: bool,
     #[clap(subcommand)]
     pub subcmd: SubCommand,
 }
@@ -38,4 +41,7 @@ pub enum SubCommand {
     /// Tokens are stored authentication and authorization secrets used by other processes
     #[clap()]
     Token(OptsToken),
+    /// Service accounts are identities used by automation that login with a key
+    #[clap()]
+    ServiceAccount(OptsServiceAccount),
 }
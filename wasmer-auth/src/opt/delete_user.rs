@@ -0,0 +1,12 @@
+use clap::Parser;
+
+/// Requests that your account and all the personal data held about it is permanently erased
+#[derive(Parser)]
+pub struct DeleteUser {
+    /// The authenticator code from your mobile authenticator
+    #[clap(long)]
+    pub code: Option<String>,
+    /// Skips the confirmation prompt
+    #[clap(short, long)]
+    pub force: bool,
+}
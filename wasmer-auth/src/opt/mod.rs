@@ -1,9 +1,11 @@
+mod cancel_delete_user;
 mod core;
 mod create_group;
 mod create_user;
 mod database;
 mod database_details;
 mod database_truncate;
+mod delete_user;
 mod gather_permissions;
 mod generate_token;
 mod generate_token_sudo;
@@ -12,17 +14,22 @@ mod group_add_user;
 mod group_details;
 mod group_remove;
 mod group_remove_user;
+mod refresh_token;
 mod reset_user;
+mod service_account;
 mod token;
 mod user;
+mod user_activity;
 mod view_token;
 
 pub use self::core::*;
+pub use cancel_delete_user::*;
 pub use create_group::*;
 pub use create_user::*;
 pub use database::*;
 pub use database_details::*;
 pub use database_truncate::*;
+pub use delete_user::*;
 pub use gather_permissions::*;
 pub use generate_token::*;
 pub use generate_token_sudo::*;
@@ -31,7 +38,10 @@ pub use group_add_user::*;
 pub use group_details::*;
 pub use group_remove::*;
 pub use group_remove_user::*;
+pub use refresh_token::*;
 pub use reset_user::*;
+pub use service_account::*;
 pub use token::*;
 pub use user::*;
+pub use user_activity::*;
 pub use view_token::*;
@@ -0,0 +1,5 @@
+use clap::Parser;
+
+/// Exchanges the refresh token saved next to the token file for a fresh session
+#[derive(Parser)]
+pub struct RefreshSessionToken {}
@@ -0,0 +1,58 @@
+use clap::Parser;
+
+/// Service accounts are identities used by automation that login with a key instead of a password
+#[derive(Parser)]
+#[clap()]
+pub struct OptsServiceAccount {
+    #[clap(subcommand)]
+    pub action: ServiceAccountAction,
+}
+
+#[derive(Parser)]
+pub enum ServiceAccountAction {
+    /// Creates a new service account and writes its private key to a file
+    #[clap()]
+    Create(CreateServiceAccount),
+    /// Lists all the service accounts that you have created
+    #[clap()]
+    List,
+    /// Revokes a service account so that it can no longer login
+    #[clap()]
+    Revoke(RevokeServiceAccount),
+    /// Logs in as a service account using its key file and prints the token of the session
+    #[clap()]
+    Login(LoginServiceAccount),
+}
+
+/// Creates a new service account owned by you
+#[derive(Parser)]
+pub struct CreateServiceAccount {
+    /// Name of the service account (lowercase letters, numbers, dashes and underscores)
+    #[clap(index = 1)]
+    pub name: String,
+    /// Path of the file that the private key of the account will be written to
+    #[clap(short, long)]
+    pub key_path: Option<String>,
+    /// The authenticator code from your mobile authenticator
+    #[clap(long)]
+    pub code: Option<String>,
+}
+
+/// Revokes one of your service accounts
+#[derive(Parser)]
+pub struct RevokeServiceAccount {
+    /// Identity of the service account (as shown by 'list')
+    #[clap(index = 1)]
+    pub identity: String,
+    /// The authenticator code from your mobile authenticator
+    #[clap(long)]
+    pub code: Option<String>,
+}
+
+/// Logs in as a service account
+#[derive(Parser)]
+pub struct LoginServiceAccount {
+    /// Path of the file that holds the private key of the account
+    #[clap(index = 1)]
+    pub key_path: String,
+}
@@ -21,6 +21,9 @@ pub enum TokenAction {
     /// Gather the permissions needed to access a specific group into the token using either another supplied token or the prompted credentials
     #[clap()]
     Gather(GatherPermissions),
+    /// Refreshes the session in the token file without logging in again
+    #[clap()]
+    Refresh(RefreshSessionToken),
     /// Views the contents of the supplied token
     #[clap()]
     View(ViewToken),
@@ -20,4 +20,13 @@ pub enum UserAction {
     /// Recovers a lost account using your recovery code
     #[clap()]
     Recover(ResetUser),
+    /// Requests that your account is permanently deleted after a grace period
+    #[clap()]
+    Delete(DeleteUser),
+    /// Cancels a pending deletion of your account
+    #[clap()]
+    CancelDelete(CancelDeleteUser),
+    /// Lists recent logins and other security events of your account
+    #[clap()]
+    Activity(UserActivity),
 }
@@ -0,0 +1,19 @@
+use clap::Parser;
+
+/// Lists the recent logins (and other security events) of your account
+#[derive(Parser)]
+pub struct UserActivity {
+    /// Maximum number of events to list (most recent first)
+    #[clap(short, long, default_value = "20")]
+    pub limit: usize,
+    /// Sets a webhook that is notified (with a signed JSON body) whenever your
+    /// account is logged into from somewhere new
+    #[clap(long)]
+    pub webhook: Option<String>,
+    /// Removes the webhook
+    #[clap(long, conflicts_with = "webhook")]
+    pub clear_webhook: bool,
+    /// The authenticator code from your mobile authenticator (needed to change the webhook)
+    #[clap(long)]
+    pub code: Option<String>,
+}
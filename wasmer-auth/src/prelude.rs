@@ -10,6 +10,7 @@ pub use crate::cmd::main_session_sudo;
 pub use crate::cmd::main_session_user;
 pub use crate::helper::conf_auth;
 pub use crate::helper::conf_cmd;
+pub use crate::helper::group_cache;
 pub use crate::helper::DioBuilder;
 pub use crate::util::origin_url;
 pub use crate::util::origin_url_ext;
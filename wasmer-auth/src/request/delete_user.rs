@@ -0,0 +1,45 @@
+#![allow(unused_imports)]
+use ate::prelude::*;
+use serde::*;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeleteUserRequest {
+    pub session: AteSessionSudo,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeleteUserResponse {
+    pub key: PrimaryKey,
+    pub erase_after: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CancelDeleteUserRequest {
+    pub session: AteSessionSudo,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CancelDeleteUserResponse {
+    pub key: PrimaryKey,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum DeleteUserFailed {
+    UserNotFound(String),
+    MissingToken,
+    NotElevated,
+    NotPending,
+    NoMasterKey,
+    RevokeFailed(String),
+    InternalError(u16),
+}
+
+impl<E> From<E> for DeleteUserFailed
+where
+    E: std::error::Error + Sized,
+{
+    fn from(err: E) -> Self {
+        DeleteUserFailed::InternalError(ate::utils::obscure_error(err))
+    }
+}
@@ -15,6 +15,9 @@ pub struct GatherResponse {
     pub gid: u32,
     pub group_key: PrimaryKey,
     pub authority: AteSessionGroup,
+    /// Changes whenever the group is modified (used to detect stale caches)
+    #[serde(default)]
+    pub version: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
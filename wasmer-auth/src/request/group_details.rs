@@ -18,6 +18,9 @@ pub struct GroupDetailsRoleResponse {
     pub write: PublicSignKey,
     pub hidden: bool,
     pub members: Vec<String>,
+    /// Members that are service accounts rather than people
+    #[serde(default)]
+    pub service_accounts: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -26,6 +29,21 @@ pub struct GroupDetailsResponse {
     pub name: String,
     pub roles: Vec<GroupDetailsRoleResponse>,
     pub gid: u32,
+    /// Changes whenever the group is modified (used to detect stale caches)
+    #[serde(default)]
+    pub version: u64,
+}
+
+/// Asks for the current version of a group only, which is much cheaper than
+/// the details and lets clients check that what they have cached is current
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GroupVersionRequest {
+    pub group: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GroupVersionResponse {
+    pub version: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -4,11 +4,60 @@ use serde::*;
 use std::time::Duration;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
+use super::RefreshToken;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LoginRequest {
     pub email: String,
     pub secret: EncryptKey,
     pub verification_code: Option<String>,
+    /// Describes the client that is logging in (recorded in the security
+    /// events of the user)
+    #[serde(default)]
+    pub user_agent: Option<String>,
+}
+
+/// Asks for a challenge that a service account must sign to login
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServiceLoginChallengeRequest {
+    pub identity: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServiceLoginChallengeResponse {
+    pub challenge: AteHash,
+    pub expires: chrono::DateTime<chrono::Utc>,
+}
+
+/// Login of a service account which proves who it is by signing the
+/// challenge it was given with its private key (instead of a password)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServiceLoginRequest {
+    pub identity: String,
+    pub challenge: AteHash,
+    pub signature: Vec<u8>,
+}
+
+impl ServiceLoginRequest {
+    pub fn new(
+        identity: &str,
+        challenge: AteHash,
+        key: &PrivateSignKey,
+    ) -> Result<ServiceLoginRequest, std::io::Error> {
+        let signature = key.sign(&service_login_data(identity, &challenge)[..])?;
+        Ok(ServiceLoginRequest {
+            identity: identity.to_string(),
+            challenge,
+            signature,
+        })
+    }
+}
+
+/// Data that is signed by a service account to answer a challenge
+pub fn service_login_data(identity: &str, challenge: &AteHash) -> Vec<u8> {
+    format!("service-login:{}:{}", identity, challenge)
+        .as_bytes()
+        .to_vec()
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,6 +69,7 @@ pub struct LoginResponse {
     pub sudo_write: PublicSignKey,
     pub authority: AteSessionUser,
     pub message_of_the_day: Option<String>,
+    pub refresh_token: Option<RefreshToken>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -30,6 +80,8 @@ pub enum LoginFailed {
     Unverified(String),
     NoMasterKey,
     InternalError(u16),
+    TemporarilyLocked(Duration),
+    Revoked(String),
 }
 
 impl<E> From<E> for LoginFailed
@@ -1,5 +1,6 @@
 mod create_group;
 mod create_user;
+mod delete_user;
 mod gather;
 mod group_details;
 mod group_remove;
@@ -7,11 +8,15 @@ mod group_user_add;
 mod group_user_remove;
 mod login;
 mod query;
+mod refresh;
 mod reset;
+mod security_activity;
+mod service_account;
 mod sudo;
 
 pub use create_group::*;
 pub use create_user::*;
+pub use delete_user::*;
 pub use gather::*;
 pub use group_details::*;
 pub use group_remove::*;
@@ -19,5 +24,8 @@ pub use group_user_add::*;
 pub use group_user_remove::*;
 pub use login::*;
 pub use query::*;
+pub use refresh::*;
 pub use reset::*;
+pub use security_activity::*;
+pub use service_account::*;
 pub use sudo::*;
@@ -0,0 +1,55 @@
+#![allow(unused_imports)]
+use ate::prelude::*;
+use serde::*;
+use std::time::Duration;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+/// Long lived token that is exchanged for a fresh session without the
+/// password, a new token is issued (and the old one revoked) on every use
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefreshToken {
+    pub email: String,
+    pub id: PrimaryKey,
+    pub secret: AteHash,
+    pub expires: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefreshRequest {
+    pub token: RefreshToken,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefreshResponse {
+    pub authority: AteSessionUser,
+    pub refresh_token: RefreshToken,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevokeRefreshRequest {
+    pub token: RefreshToken,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevokeRefreshResponse {}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum RefreshFailed {
+    UserNotFound(String),
+    Revoked,
+    Expired,
+    AccountLocked(Duration),
+    Unverified(String),
+    NoMasterKey,
+    Busy,
+    InternalError(u16),
+}
+
+impl<E> From<E> for RefreshFailed
+where
+    E: std::error::Error + Sized,
+{
+    fn from(err: E) -> Self {
+        RefreshFailed::InternalError(ate::utils::obscure_error(err))
+    }
+}
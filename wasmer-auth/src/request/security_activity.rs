@@ -0,0 +1,52 @@
+#![allow(unused_imports)]
+use ate::prelude::*;
+use serde::*;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use crate::model::SecurityEvent;
+
+/// Lists the recent security events (e.g. logins) of the user
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecurityActivityRequest {
+    pub session: AteSessionUser,
+    pub limit: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecurityActivityResponse {
+    /// Most recent events first
+    pub events: Vec<SecurityEvent>,
+    pub webhook: Option<String>,
+}
+
+/// Sets (or clears) the webhook that is notified about logins from sources
+/// that the user has not logged in from before
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecurityWebhookRequest {
+    pub session: AteSessionSudo,
+    pub webhook: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecurityWebhookResponse {
+    pub webhook: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum SecurityActivityFailed {
+    UserNotFound(String),
+    MissingToken,
+    NotElevated,
+    InvalidWebhook(String),
+    NoMasterKey,
+    InternalError(u16),
+}
+
+impl<E> From<E> for SecurityActivityFailed
+where
+    E: std::error::Error + Sized,
+{
+    fn from(err: E) -> Self {
+        SecurityActivityFailed::InternalError(ate::utils::obscure_error(err))
+    }
+}
@@ -0,0 +1,75 @@
+#![allow(unused_imports)]
+use ate::prelude::*;
+use serde::*;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+/// Credentials of a service account which are kept in a file by whatever
+/// runs as the account (the server only ever sees the public half)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServiceAccountKey {
+    pub identity: String,
+    pub key: PrivateSignKey,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateServiceAccountRequest {
+    pub session: AteSessionSudo,
+    pub name: String,
+    pub public_key: PublicSignKey,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateServiceAccountResponse {
+    pub key: PrimaryKey,
+    pub identity: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ListServiceAccountsRequest {
+    pub session: AteSessionUser,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServiceAccountSummary {
+    pub identity: String,
+    pub public_key: AteHash,
+    pub created: chrono::DateTime<chrono::Utc>,
+    pub revoked: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ListServiceAccountsResponse {
+    pub accounts: Vec<ServiceAccountSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevokeServiceAccountRequest {
+    pub session: AteSessionSudo,
+    pub identity: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevokeServiceAccountResponse {
+    pub identity: String,
+    pub revoked: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum ServiceAccountFailed {
+    AlreadyExists(String),
+    InvalidName,
+    NotFound(String),
+    MissingToken,
+    NotElevated,
+    NoMasterKey,
+    InternalError(u16),
+}
+
+impl<E> From<E> for ServiceAccountFailed
+where
+    E: std::error::Error + Sized,
+{
+    fn from(err: E) -> Self {
+        ServiceAccountFailed::InternalError(ate::utils::obscure_error(err))
+    }
+}
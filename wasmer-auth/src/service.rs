@@ -12,6 +12,8 @@ use ::ate::time::TimeKeeper;
 use crate::helper::*;
 use crate::model::*;
 use crate::request::*;
+use crate::work::LoginThrottle;
+use crate::work::ServiceChallenges;
 
 pub struct AuthService {
     pub auth_url: url::Url,
@@ -21,7 +23,17 @@ pub struct AuthService {
     pub contract_key: EncryptKey,
     pub time_keeper: TimeKeeper,
     pub terms_and_conditions: Option<String>,
+    pub delete_grace_period: chrono::Duration,
     pub registry: Arc<Registry>,
+    pub login_throttle: Arc<LoginThrottle>,
+    /// Identifies where the requests of this service come from (the IP
+    /// address of the client) so that failed logins can be counted per source
+    pub login_source: Option<String>,
+    /// Address and node of the client that opened the command chain which is
+    /// recorded against the security events of the users
+    pub login_peer: Option<ConnectionPeer>,
+    /// Challenges handed out to service accounts that are trying to login
+    pub service_challenges: ServiceChallenges,
 }
 
 impl AuthService {
@@ -33,6 +45,10 @@ impl AuthService {
         edge_key: EncryptKey,
         contract_key: EncryptKey,
         terms_and_conditions: Option<String>,
+        delete_grace_period: chrono::Duration,
+        login_throttle: Arc<LoginThrottle>,
+        login_source: Option<String>,
+        login_peer: Option<ConnectionPeer>,
     ) -> Result<Arc<AuthService>, TimeError> {
         let service = Arc::new(AuthService {
             auth_url,
@@ -47,6 +63,11 @@ impl AuthService {
                 .keep_alive(Duration::from_secs(60))
                 .cement(),
             terms_and_conditions,
+            delete_grace_period,
+            login_throttle,
+            login_source,
+            login_peer,
+            service_challenges: ServiceChallenges::default(),
         });
         Ok(service)
     }
@@ -61,6 +82,9 @@ pub async fn service_auth_handlers(
     edge_key: EncryptKey,
     contract_key: EncryptKey,
     terms_and_conditions: Option<String>,
+    delete_grace_period: chrono::Duration,
+    login_throttle: Arc<LoginThrottle>,
+    peer: Option<ConnectionPeer>,
     chain: &Arc<Chain>,
 ) -> Result<(), TimeError> {
     let service = AuthService::new(
@@ -71,10 +95,30 @@ pub async fn service_auth_handlers(
         edge_key,
         contract_key,
         terms_and_conditions,
+        delete_grace_period,
+        login_throttle,
+        peer.as_ref().map(crate::work::login_source),
+        peer,
     )
     .await?;
     chain.add_service(&cmd_session, service.clone(), AuthService::process_login);
+    chain.add_service(
+        &cmd_session,
+        service.clone(),
+        AuthService::process_service_login_challenge,
+    );
+    chain.add_service(
+        &cmd_session,
+        service.clone(),
+        AuthService::process_service_login,
+    );
     chain.add_service(&cmd_session, service.clone(), AuthService::process_sudo);
+    chain.add_service(&cmd_session, service.clone(), AuthService::process_refresh);
+    chain.add_service(
+        &cmd_session,
+        service.clone(),
+        AuthService::process_revoke_refresh,
+    );
     chain.add_service(&cmd_session, service.clone(), AuthService::process_reset);
     chain.add_service(
         &cmd_session,
@@ -103,10 +147,50 @@ pub async fn service_auth_handlers(
         service.clone(),
         AuthService::process_group_details,
     );
+    chain.add_service(
+        &cmd_session,
+        service.clone(),
+        AuthService::process_group_version,
+    );
     chain.add_service(
         &cmd_session,
         service.clone(),
         AuthService::process_group_remove,
     );
+    chain.add_service(
+        &cmd_session,
+        service.clone(),
+        AuthService::process_delete_user,
+    );
+    chain.add_service(
+        &cmd_session,
+        service.clone(),
+        AuthService::process_cancel_delete_user,
+    );
+    chain.add_service(
+        &cmd_session,
+        service.clone(),
+        AuthService::process_create_service_account,
+    );
+    chain.add_service(
+        &cmd_session,
+        service.clone(),
+        AuthService::process_list_service_accounts,
+    );
+    chain.add_service(
+        &cmd_session,
+        service.clone(),
+        AuthService::process_revoke_service_account,
+    );
+    chain.add_service(
+        &cmd_session,
+        service.clone(),
+        AuthService::process_security_activity,
+    );
+    chain.add_service(
+        &cmd_session,
+        service.clone(),
+        AuthService::process_security_webhook,
+    );
     Ok(())
 }
@@ -7,6 +7,7 @@ use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use url::Url;
 
 use crate::cmd::*;
+use crate::error::*;
 use crate::prelude::*;
 
 #[tokio::main(flavor = "current_thread")]
@@ -139,12 +140,165 @@ pub async fn test_create_user_and_group() {
         }
     }
 
+    // Refresh the session using the refresh token (which is rotated on every use)
+    {
+        let registry = ate::mesh::Registry::new(&conf_cmd())
+            .await
+            .keep_alive(Duration::from_secs(30))
+            .cement();
+        info!("login request with refresh token for joe.blogs");
+        let response = login_command_ext(
+            &registry,
+            username.clone(),
+            password.clone(),
+            None,
+            auth.clone(),
+            false,
+        )
+        .await
+        .unwrap();
+        let refresh_token = response.refresh_token.expect("Should have a refresh token");
+
+        info!("refresh the session for joe.blogs");
+        let refreshed = refresh_command(&registry, refresh_token.clone(), auth.clone())
+            .await
+            .unwrap();
+        assert_eq!(refreshed.authority.identity(), username.as_str());
+        assert!(refreshed.authority.token.is_some());
+
+        info!("reusing a rotated refresh token must fail");
+        match refresh_command(&registry, refresh_token, auth.clone()).await {
+            Err(RefreshError(RefreshErrorKind::Revoked, _)) => {}
+            a => panic!("the rotated refresh token should have been revoked - {:?}", a.map(|_| ())),
+        }
+
+        info!("concurrent refreshes with the same token must not both succeed");
+        let (a, b) = futures::join!(
+            refresh_command(&registry, refreshed.refresh_token.clone(), auth.clone()),
+            refresh_command(&registry, refreshed.refresh_token.clone(), auth.clone()),
+        );
+        let refreshed = match (a, b) {
+            (Ok(a), Err(RefreshError(RefreshErrorKind::Revoked, _))) => a,
+            (Err(RefreshError(RefreshErrorKind::Revoked, _)), Ok(b)) => b,
+            (a, b) => panic!(
+                "exactly one of the refreshes should succeed - {:?} {:?}",
+                a.map(|_| ()),
+                b.map(|_| ())
+            ),
+        };
+
+        info!("revoked refresh tokens must fail immediately");
+        let refresh_token = refreshed.refresh_token;
+        revoke_refresh_command(&registry, refresh_token.clone(), auth.clone())
+            .await
+            .unwrap();
+        match refresh_command(&registry, refresh_token, auth.clone()).await {
+            Err(RefreshError(RefreshErrorKind::Revoked, _)) => {}
+            a => panic!("the refresh token should have been revoked - {:?}", a.map(|_| ())),
+        }
+    }
+
     // Login to the main user and gather the rights to the group (full sudo rights)
     info!("sudo login for 'joe.blogs'");
     let session = main_login(Some(username.clone()), Some(password.clone()), auth.clone())
         .await
         .unwrap();
     let session = main_sudo(session, Some(code), auth.clone()).await.unwrap();
+
+    // Create service accounts that login with their key instead of a password
+    {
+        use crate::request::ServiceAccountKey;
+
+        let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+        let key = PrivateSignKey::generate(KeySize::Bit192);
+
+        info!("create service accounts for 'joe.blogs'");
+        let created = create_service_account_command(
+            &registry,
+            &session,
+            "deploy-bot".to_string(),
+            key.as_public_key().clone(),
+            auth.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(created.identity, crate::model::service_account_identity("deploy-bot"));
+        match create_service_account_command(
+            &registry,
+            &session,
+            "deploy-bot".to_string(),
+            key.as_public_key().clone(),
+            auth.clone(),
+        )
+        .await
+        {
+            Err(ServiceAccountError(ServiceAccountErrorKind::AlreadyExists(_), _)) => {}
+            a => panic!("the service account name should already be taken - {:?}", a.map(|_| ())),
+        }
+
+        // Concurrent creates must all end up in the list of the owner
+        let (a, b) = futures::join!(
+            create_service_account_command(
+                &registry,
+                &session,
+                "build-bot".to_string(),
+                key.as_public_key().clone(),
+                auth.clone(),
+            ),
+            create_service_account_command(
+                &registry,
+                &session,
+                "test-bot".to_string(),
+                key.as_public_key().clone(),
+                auth.clone(),
+            )
+        );
+        a.unwrap();
+        b.unwrap();
+        let mut listed = list_service_accounts_command(&registry, &session.inner, auth.clone())
+            .await
+            .unwrap()
+            .accounts
+            .into_iter()
+            .map(|a| a.identity)
+            .collect::<Vec<_>>();
+        listed.sort();
+        assert_eq!(
+            listed,
+            vec![
+                crate::model::service_account_identity("build-bot"),
+                crate::model::service_account_identity("deploy-bot"),
+                crate::model::service_account_identity("test-bot"),
+            ]
+        );
+
+        info!("login as the service account");
+        let service_key = ServiceAccountKey {
+            identity: created.identity.clone(),
+            key: key.clone(),
+        };
+        let response = service_login_command(&registry, &service_key, auth.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.authority.identity(), created.identity.as_str());
+        let wrong_key = ServiceAccountKey {
+            identity: created.identity.clone(),
+            key: PrivateSignKey::generate(KeySize::Bit192),
+        };
+        assert!(service_login_command(&registry, &wrong_key, auth.clone())
+            .await
+            .is_err());
+
+        info!("revoked service accounts can not login");
+        revoke_service_account_command(&registry, &session, created.identity.clone(), auth.clone())
+            .await
+            .unwrap();
+        match service_login_command(&registry, &service_key, auth.clone()).await {
+            Err(LoginError(LoginErrorKind::Revoked(_), _)) => {}
+            a => panic!("the service account should have been revoked - {:?}", a.map(|_| ())),
+        }
+    }
+
     info!("gather permissions for group 'mygroup'");
     let session = main_gather(Some(group.clone()), session.into(), auth.clone(), "Group")
         .await
@@ -198,6 +352,7 @@ pub async fn test_create_user_and_group() {
     )
     .await
     .unwrap();
+    let friend_qr_secret = friend.qr_secret.clone();
     let friend_session = friend.authority;
 
     info!("add friend to the group 'mygroup'");
@@ -273,4 +428,294 @@ pub async fn test_create_user_and_group() {
             .is_none(),
         "The user should have had this role removed"
     );
+
+    // Request that the friend account is deleted and then cancel it again
+    info!("request deletion of the 'friend' account");
+    timer.wait_for_high_accuracy().await;
+    let friend_code = google_auth
+        .get_code(
+            friend_qr_secret.as_str(),
+            timer.current_timestamp_as_duration().unwrap().as_secs() / 30,
+        )
+        .unwrap();
+    let friend_sudo = main_sudo(friend_session.clone(), Some(friend_code), auth.clone())
+        .await
+        .unwrap();
+    main_delete_user(friend_sudo.clone(), true, auth.clone())
+        .await
+        .unwrap();
+
+    info!("cancel the deletion of the 'friend' account");
+    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+    cancel_delete_user_command(&registry, &friend_sudo, auth.clone())
+        .await
+        .unwrap();
+    let ret = cancel_delete_user_command(&registry, &friend_sudo, auth.clone()).await;
+    assert!(
+        matches!(
+            ret,
+            Err(crate::error::DeleteUserError(
+                crate::error::DeleteUserErrorKind::NotPending,
+                _
+            ))
+        ),
+        "The deletion should no longer be pending"
+    );
+}
+
+#[test]
+pub fn test_group_cache() {
+    let cache = group_cache();
+    let auth = Url::parse("ws://localhost/auth").unwrap();
+    let group = "cache.example.com";
+
+    let session = AteSessionInner::User(AteSessionUser::default());
+    let gathered = AteSessionGroup::new(session.clone(), group.to_string());
+    assert!(cache.get_gather(&auth, group, &session).is_none());
+
+    // Results are returned from the cache until the group is invalidated
+    cache.put_gather(&auth, group, &session, &gathered, 10);
+    assert!(cache.get_gather(&auth, group, &session).is_some());
+    cache.invalidate_group(&auth, group);
+    assert!(
+        cache.get_gather(&auth, group, &session).is_none(),
+        "Invalidating the group should clear its entries"
+    );
+
+    // A newer version reported by the server drops the older entries
+    cache.put_gather(&auth, group, &session, &gathered, 20);
+    cache.observe_version(&auth, group, 21);
+    assert!(
+        cache.get_gather(&auth, group, &session).is_none(),
+        "Entries from an older version of the group should be dropped"
+    );
+
+    // Late responses from an older version are not cached
+    cache.put_gather(&auth, group, &session, &gathered, 15);
+    assert!(cache.get_gather(&auth, group, &session).is_none());
+}
+
+#[test]
+pub fn test_service_account_challenge() {
+    use crate::model::*;
+    use crate::request::*;
+    use crate::work::ServiceChallenges;
+
+    let identity = service_account_identity("deploy-bot");
+    assert!(is_service_account(identity.as_str()));
+    assert!(is_service_account("someone@example.com") == false);
+
+    // Challenges can only be answered once and only by who they were issued to
+    let challenges = ServiceChallenges::default();
+    let challenge = challenges.issue(identity.as_str());
+    assert!(challenges.take("other-bot@service.ate", &challenge) == false);
+    let challenge = challenges.issue(identity.as_str());
+    assert!(challenges.take(identity.as_str(), &challenge));
+    assert!(
+        challenges.take(identity.as_str(), &challenge) == false,
+        "Challenges should not be reusable"
+    );
+
+    // Only the holder of the private key can answer the challenge
+    let key = PrivateSignKey::generate(KeySize::Bit192);
+    let other = PrivateSignKey::generate(KeySize::Bit192);
+    let challenge = challenges.issue(identity.as_str());
+    let login = ServiceLoginRequest::new(identity.as_str(), challenge.clone(), &key).unwrap();
+    let data = service_login_data(identity.as_str(), &challenge);
+    assert!(key.as_public_key().verify(&data[..], &login.signature[..]).unwrap());
+    assert!(other.as_public_key().verify(&data[..], &login.signature[..]).unwrap() == false);
+}
+
+#[test]
+pub fn test_service_account_defaults() {
+    use crate::model::*;
+    use crate::request::*;
+
+    // Rows written before service accounts existed must still load
+    let role = GroupDetailsRoleResponse {
+        purpose: AteRolePurpose::Owner,
+        name: "owner".to_string(),
+        read: AteHash::generate(),
+        private_read: PrivateEncryptKey::generate(KeySize::Bit192).as_public_key().clone(),
+        write: PrivateSignKey::generate(KeySize::Bit192).as_public_key().clone(),
+        hidden: false,
+        members: vec!["joe.blogs@nowhere.com".to_string()],
+        service_accounts: vec!["deploy-bot@service.ate".to_string()],
+    };
+    let mut json = serde_json::to_value(&role).unwrap();
+    json.as_object_mut().unwrap().remove("service_accounts");
+    let role: GroupDetailsRoleResponse = serde_json::from_value(json).unwrap();
+    assert!(role.service_accounts.is_empty());
+    assert_eq!(role.members, vec!["joe.blogs@nowhere.com".to_string()]);
+
+    let accounts: ServiceAccounts = serde_json::from_str("{}").unwrap();
+    assert!(accounts.identities.is_empty());
+}
+
+#[test]
+pub fn test_login_throttle_source() {
+    use crate::work::*;
+    use std::sync::Arc;
+
+    let throttle = Arc::new(LoginThrottle::new(LoginPolicy {
+        max_failures: 3,
+        ..LoginPolicy::default()
+    }));
+
+    // The source is the address of the client so opening new command
+    // chains (or connecting from another port) does not reset the count
+    let peer1 = ConnectionPeer {
+        node_id: NodeId::generate_client_id(),
+        addr: "10.1.2.3:40001".parse().unwrap(),
+    };
+    let peer2 = ConnectionPeer {
+        node_id: NodeId::generate_client_id(),
+        addr: "10.1.2.3:40002".parse().unwrap(),
+    };
+    assert_eq!(login_source(&peer1), login_source(&peer2));
+
+    for n in 0..3 {
+        let peer = if n % 2 == 0 { &peer1 } else { &peer2 };
+        let (failures, mut attempt) = throttle.begin_attempt(login_source(peer).as_str()).unwrap();
+        assert_eq!(failures, n);
+        attempt.failed();
+    }
+    assert!(throttle.begin_attempt(login_source(&peer2).as_str()).is_err());
+
+    // Other sources are not affected
+    let (failures, _) = throttle.begin_attempt("10.9.9.9").unwrap();
+    assert_eq!(failures, 0);
+}
+
+#[test]
+pub fn test_login_throttle_concurrent_attempts() {
+    use crate::work::*;
+    use std::sync::Arc;
+
+    let throttle = Arc::new(LoginThrottle::new(LoginPolicy {
+        max_failures: 3,
+        ..LoginPolicy::default()
+    }));
+
+    // Attempts that are still in progress count towards the limit so that
+    // a burst of concurrent guesses can not exceed it
+    let (_, mut a1) = throttle.begin_attempt("10.1.2.3").unwrap();
+    let (_, a2) = throttle.begin_attempt("10.1.2.3").unwrap();
+    let (_, a3) = throttle.begin_attempt("10.1.2.3").unwrap();
+    assert!(throttle.begin_attempt("10.1.2.3").is_err());
+
+    // Attempts that end without an outcome free up their slot while
+    // failures keep counting
+    drop(a2);
+    a1.failed();
+    drop(a1);
+    let (failures, mut a4) = throttle.begin_attempt("10.1.2.3").unwrap();
+    assert_eq!(failures, 1);
+    assert!(throttle.begin_attempt("10.1.2.3").is_err());
+
+    // A successful login clears the failures of the source
+    a4.succeeded();
+    drop(a4);
+    drop(a3);
+    let (failures, _) = throttle.begin_attempt("10.1.2.3").unwrap();
+    assert_eq!(failures, 0);
+}
+
+#[test]
+pub fn test_security_events_record() {
+    use crate::model::*;
+
+    let event = |addr: &str, kind: SecurityEventKind, success: bool| SecurityEvent {
+        when: chrono::Utc::now(),
+        kind,
+        success,
+        addr: Some(addr.to_string()),
+        node_id: None,
+        user_agent: Some("ate/1.0".to_string()),
+    };
+
+    // The port of the client changes with every connection so it is not
+    // part of the source
+    assert_eq!(
+        event("10.1.2.3:40001", SecurityEventKind::Login, true).source(),
+        event("10.1.2.3:40002", SecurityEventKind::Login, true).source()
+    );
+    assert_ne!(
+        event("10.1.2.3:40001", SecurityEventKind::Login, true).source(),
+        event("10.1.2.4:40001", SecurityEventKind::Login, true).source()
+    );
+
+    // Only successful logins from sources that were not seen before are new
+    let mut record = SecurityEvents::default();
+    assert!(record.record(event("10.1.2.3:40001", SecurityEventKind::Login, true)));
+    assert!(record.record(event("10.1.2.3:40002", SecurityEventKind::Login, true)) == false);
+    assert!(record.record(event("10.9.9.9:40001", SecurityEventKind::Login, false)) == false);
+    assert!(record.record(event("10.9.9.9:40001", SecurityEventKind::Sudo, true)) == false);
+    assert!(record.record(event("10.9.9.9:40001", SecurityEventKind::Login, true)));
+    assert_eq!(record.events.len(), 5);
+    assert_eq!(record.seen_sources.len(), 2);
+
+    // The events and sources are capped, the oldest are dropped first
+    for n in 0..MAX_SECURITY_EVENTS {
+        let addr = format!("10.0.{}.{}:40001", n / 256, n % 256);
+        record.record(event(addr.as_str(), SecurityEventKind::Login, true));
+    }
+    assert_eq!(record.events.len(), MAX_SECURITY_EVENTS);
+    assert_eq!(record.seen_sources.len(), MAX_SEEN_SOURCES);
+    assert!(
+        record.record(event("10.1.2.3:40001", SecurityEventKind::Login, true)),
+        "Sources that were dropped should be new again"
+    );
+}
+
+#[test]
+pub fn test_security_notification_verify() {
+    use crate::model::*;
+
+    let key = PrivateSignKey::generate(KeySize::Bit192);
+    let other = PrivateSignKey::generate(KeySize::Bit192);
+    let notification = SecurityNotification {
+        email: "someone@example.com".to_string(),
+        event: SecurityEvent {
+            when: chrono::Utc::now(),
+            kind: SecurityEventKind::Login,
+            success: true,
+            addr: Some("10.1.2.3:40001".to_string()),
+            node_id: None,
+            user_agent: None,
+        },
+    };
+    let body = serde_json::to_vec(&notification).unwrap();
+    let signature = base64::encode(key.sign(&body[..]).unwrap());
+
+    let server_key = key.as_public_key();
+    assert!(SecurityNotification::verify(&body[..], signature.as_str(), &server_key));
+    assert!(SecurityNotification::verify(&body[..], signature.as_str(), &other.as_public_key()) == false);
+    assert!(SecurityNotification::verify(&body[1..], signature.as_str(), &server_key) == false);
+    assert!(SecurityNotification::verify(&body[..], "not base64!", &server_key) == false);
+}
+
+#[test]
+pub fn test_security_webhook_allowed() {
+    use crate::model::*;
+
+    assert!(is_allowed_webhook("https://hooks.example.com/ate"));
+    assert!(is_allowed_webhook("https://8.8.8.8/ate"));
+
+    // Plain http and hosts on internal networks are refused
+    assert!(is_allowed_webhook("http://hooks.example.com/ate") == false);
+    assert!(is_allowed_webhook("https://localhost/ate") == false);
+    assert!(is_allowed_webhook("https://admin.localhost/ate") == false);
+    assert!(is_allowed_webhook("https://127.0.0.1/ate") == false);
+    assert!(is_allowed_webhook("https://10.1.2.3/ate") == false);
+    assert!(is_allowed_webhook("https://172.16.0.1/ate") == false);
+    assert!(is_allowed_webhook("https://192.168.1.1/ate") == false);
+    assert!(is_allowed_webhook("https://169.254.169.254/latest") == false);
+    assert!(is_allowed_webhook("https://100.64.0.1/ate") == false);
+    assert!(is_allowed_webhook("https://0.0.0.0/ate") == false);
+    assert!(is_allowed_webhook("https://[::1]/ate") == false);
+    assert!(is_allowed_webhook("https://[fd00::1]/ate") == false);
+    assert!(is_allowed_webhook("https://[fe80::1]/ate") == false);
+    assert!(is_allowed_webhook("https://[::ffff:127.0.0.1]/ate") == false);
+    assert!(is_allowed_webhook("not a url") == false);
 }
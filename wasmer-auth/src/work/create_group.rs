@@ -96,7 +96,7 @@ impl AuthService {
         // Compute which chain the group should exist within
         let group_chain_key = chain_key_4hex(&request.group, Some("redo"));
         let chain = self.registry.open(&self.auth_url, &group_chain_key, true).await?;
-        let dio = chain.dio_mut(&self.master_session).await;
+        let dio = chain.dio_mut(&self.master_session).await?;
 
         // Try and find a free GID
         let gid_offset = u32::MAX as u64;
@@ -190,7 +190,7 @@ impl AuthService {
             .add_private_read_key(&delegate_private_read);
         super_session.user.add_write_key(&owner_write);
         super_session.user.add_write_key(&delegate_write);
-        let dio = chain.dio_full(&super_session).await;
+        let dio = chain.dio_full(&super_session).await?;
 
         // Create the group and save it
         let group = Group {
@@ -30,6 +30,11 @@ impl AuthService {
         self: Arc<Self>,
         request: CreateUserRequest,
     ) -> Result<CreateUserResponse, CreateUserFailed> {
+        // Service accounts can only be created by their owners
+        if is_service_account(request.email.as_str()) {
+            warn!("reserved email address - {}", request.email);
+            return Err(CreateUserFailed::InvalidEmail);
+        }
         Ok(self
             .process_create_user_internal(request, UserStatus::Nominal)
             .await?
@@ -129,7 +134,7 @@ impl AuthService {
         // Compute which chain the user should exist within
         let user_chain_key = chain_key_4hex(&request.email, Some("redo"));
         let chain = self.registry.open(&self.auth_url, &user_chain_key, true).await?;
-        let dio = chain.dio_full(&super_session).await;
+        let dio = chain.dio_full(&super_session).await?;
 
         // Try and find a free UID
         let mut uid = None;
@@ -0,0 +1,438 @@
+#![allow(unused_imports)]
+use error_chain::bail;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use ate::error::LoadError;
+use ate::error::TransformError;
+use ate::prelude::*;
+use ate::utils::chain_key_4hex;
+
+use crate::error::*;
+use crate::helper::*;
+use crate::model::*;
+use crate::prelude::*;
+use crate::request::*;
+use crate::service::AuthService;
+
+impl AuthService {
+    pub async fn process_delete_user(
+        self: Arc<Self>,
+        request: DeleteUserRequest,
+    ) -> Result<DeleteUserResponse, DeleteUserFailed> {
+        info!("delete user: {}", request.session.identity());
+
+        // Open the user using the elevated rights of the caller
+        let identity = request.session.identity().to_string();
+        let (dio, mut user, token) = self.load_user_for_delete(&request.session).await?;
+
+        // If a deletion is already pending then we either finish it (when the grace
+        // period has expired) or just tell the caller when it will happen
+        let deletion_key = user_deletion_key(identity.as_str());
+        match dio.load::<UserDeletion>(&deletion_key).await {
+            Ok(deletion) => {
+                if deletion.erase_after <= utc_now() {
+                    self.erase_user(identity.as_str()).await?;
+                    return Err(DeleteUserFailed::UserNotFound(identity));
+                }
+                return Ok(DeleteUserResponse {
+                    key: user.key().clone(),
+                    erase_after: deletion.erase_after,
+                });
+            }
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {}
+            Err(err) => {
+                bail!(err);
+            }
+        }
+
+        // Get the master write key
+        let master_write_key = match self.master_session.user.write_keys().next() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(DeleteUserFailed::NoMasterKey);
+            }
+        };
+        let master_key = match self.master_key() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(DeleteUserFailed::NoMasterKey);
+            }
+        };
+
+        // Record the deletion next to the user so that it can be completed later
+        let requested = utc_now();
+        let erase_after = requested + self.delete_grace_period;
+        let mut deletion = dio.store_with_key(
+            UserDeletion {
+                email: identity.clone(),
+                requested,
+                erase_after,
+                token,
+            },
+            deletion_key,
+        )?;
+        deletion.auth_mut().read = ReadOption::from_key(&master_key);
+        deletion.auth_mut().write = WriteOption::Specific(master_write_key.hash());
+
+        user.as_mut().status = UserStatus::PendingDeletion(erase_after);
+        dio.commit().await?;
+
+        // Add the account to the schedule so it is erased even if the user never returns
+        self.update_deletion_schedule(identity.as_str(), true)
+            .await?;
+
+        info!(
+            "user deletion scheduled ({}) - erase after {}",
+            identity, erase_after
+        );
+        Ok(DeleteUserResponse {
+            key: user.key().clone(),
+            erase_after,
+        })
+    }
+
+    pub async fn process_cancel_delete_user(
+        self: Arc<Self>,
+        request: CancelDeleteUserRequest,
+    ) -> Result<CancelDeleteUserResponse, DeleteUserFailed> {
+        info!("cancel delete user: {}", request.session.identity());
+
+        // Open the user using the elevated rights of the caller
+        let identity = request.session.identity().to_string();
+        let (dio, mut user, _) = self.load_user_for_delete(&request.session).await?;
+
+        // The deletion record is the source of truth as the status of the user
+        // may have been overwritten by an account lock
+        let deletion_key = user_deletion_key(identity.as_str());
+        let deletion = match dio.load::<UserDeletion>(&deletion_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                return Err(DeleteUserFailed::NotPending);
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        // Once the grace period has expired the deletion is irreversible
+        if deletion.erase_after <= utc_now() {
+            self.erase_user(identity.as_str()).await?;
+            return Err(DeleteUserFailed::UserNotFound(identity));
+        }
+
+        dio.delete(&deletion_key).await?;
+        user.as_mut().status = UserStatus::Nominal;
+        dio.commit().await?;
+
+        self.update_deletion_schedule(identity.as_str(), false)
+            .await?;
+
+        info!("user deletion cancelled ({})", identity);
+        Ok(CancelDeleteUserResponse {
+            key: user.key().clone(),
+        })
+    }
+
+    /// Permanently erases a user account if its grace period has expired. All the
+    /// access the user has been given to groups is revoked and the personal rows
+    /// are tombstoned so that they are purged on the next compaction of the chain.
+    /// Returns true if the account was erased.
+    pub async fn erase_user(&self, email: &str) -> Result<bool, DeleteUserFailed> {
+        let master_key = match self.master_key() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(DeleteUserFailed::NoMasterKey);
+            }
+        };
+
+        // Load the deletion record (which only the master key can read)
+        let chain_key = chain_key_4hex(email, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let deletion_key = user_deletion_key(email);
+        let deletion = {
+            let dio = chain.dio(&self.master_session).await;
+            match dio.load::<UserDeletion>(&deletion_key).await {
+                Ok(a) => a.take(),
+                Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                    self.update_deletion_schedule(email, false).await?;
+                    return Ok(false);
+                }
+                Err(err) => {
+                    bail!(err);
+                }
+            }
+        };
+        if deletion.erase_after > utc_now() {
+            return Ok(false);
+        }
+
+        // Recover the keys of the user from the token that was stored with the deletion
+        let super_key = deletion.token.unwrap(&master_key)?;
+        let (super_super_key, _) = match self.compute_master_key(&super_key) {
+            Some(a) => a,
+            None => {
+                return Err(DeleteUserFailed::NoMasterKey);
+            }
+        };
+        let mut super_session = self.master_session.clone();
+        super_session.user.add_read_key(&super_key);
+        super_session.user.add_read_key(&super_super_key);
+
+        let dio = chain.dio_full(&super_session).await?;
+        let user_key = PrimaryKey::from(email.to_string());
+        let mut user = match dio.load::<User>(&user_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                dio.delete(&deletion_key).await?;
+                dio.commit().await?;
+                self.update_deletion_schedule(email, false).await?;
+                return Ok(false);
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        // Revoke all the access this user was given to groups (the memberships are
+        // recorded by the server as the user is added to each group)
+        let memberships_key = group_memberships_key(email);
+        let groups = match dio.load::<GroupMemberships>(&memberships_key).await {
+            Ok(a) => a.take().groups,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => Vec::new(),
+            Err(err) => {
+                bail!(err);
+            }
+        };
+        let who = vec![user.nominal_public_read.hash(), user.sudo_public_read.hash()];
+        for group in groups.iter() {
+            match self.group_remove_members(group.as_str(), &who[..]).await {
+                Ok(_) => {}
+                Err(GroupUserRemoveFailed::GroupNotFound) => {}
+                Err(GroupUserRemoveFailed::NoMasterKey) => {
+                    return Err(DeleteUserFailed::NoMasterKey);
+                }
+                Err(_) => {
+                    return Err(DeleteUserFailed::RevokeFailed(group.clone()));
+                }
+            }
+        }
+
+        // Revoke the refresh tokens and service accounts so that nothing can log in
+        // as (or on behalf of) the user anymore
+        let accounts_key = service_accounts_key(email);
+        let accounts = match dio.load::<ServiceAccounts>(&accounts_key).await {
+            Ok(a) => a.take().identities,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => Vec::new(),
+            Err(err) => {
+                bail!(err);
+            }
+        };
+        for identity in accounts.iter() {
+            self.revoke_service_account_of(email, identity.as_str()).await?;
+        }
+        for key in vec![refresh_grants_key(email), accounts_key, memberships_key] {
+            if dio.exists(&key).await {
+                dio.delete(&key).await?;
+            }
+        }
+
+        // Tombstone all the personal data held about the user
+        {
+            let mut user = user.as_mut();
+            user.person.clear().await?;
+            user.accepted_terms.clear().await?;
+            user.sudo.clear().await?;
+            user.advert.clear().await?;
+        }
+        let recovery_key = PrimaryKey::from(format!("recovery:{}", email));
+        if dio.exists(&recovery_key).await {
+            dio.delete(&recovery_key).await?;
+        }
+        dio.delete(&deletion_key).await?;
+        user.delete()?;
+        dio.commit().await?;
+
+        self.update_deletion_schedule(email, false).await?;
+
+        info!("user erased ({})", email);
+        Ok(true)
+    }
+
+    /// Periodically erases all the accounts whose grace period has expired
+    pub async fn run_deletion_sweeper(self: Arc<Self>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+
+            let pending = match self.load_deletion_schedule().await {
+                Ok(a) => a,
+                Err(err) => {
+                    warn!("failed to load the deletion schedule - {:?}", err);
+                    continue;
+                }
+            };
+            for email in pending {
+                if let Err(err) = self.erase_user(email.as_str()).await {
+                    warn!("failed to erase user {} - {:?}", email, err);
+                }
+            }
+        }
+    }
+
+    async fn load_user_for_delete(
+        &self,
+        session: &AteSessionSudo,
+    ) -> Result<(Arc<DioMut>, DaoMut<User>, EncryptedSecureData<EncryptKey>), DeleteUserFailed>
+    {
+        // Get token
+        let identity = session.identity().to_string();
+        let token = match &session.inner.token {
+            Some(a) => a.clone(),
+            None => {
+                return Err(DeleteUserFailed::MissingToken);
+            }
+        };
+
+        // Extract the original super key that was used to access the user
+        let master_key = match self.master_key() {
+            Some(a) => a,
+            None => {
+                return Err(DeleteUserFailed::NoMasterKey);
+            }
+        };
+        let super_key = token.unwrap(&master_key)?;
+
+        // Create the super session
+        let mut super_session = self.master_session.clone();
+        super_session.user.add_read_key(&super_key);
+        let (super_super_key, _) = match self.compute_master_key(&super_key) {
+            Some(a) => a,
+            None => {
+                return Err(DeleteUserFailed::NoMasterKey);
+            }
+        };
+        super_session.user.add_read_key(&super_super_key);
+
+        // Compute which chain the user should exist within
+        let chain_key = chain_key_4hex(identity.as_str(), Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio_full(&super_session).await?;
+
+        let user_key = PrimaryKey::from(identity.clone());
+        let user = match dio.load::<User>(&user_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                return Err(DeleteUserFailed::UserNotFound(identity));
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        // Deleting an account requires elevated rights (sudo)
+        let sudo_write = user.sudo_write.hash();
+        if session
+            .write_keys(AteSessionKeyCategory::UpperKeys)
+            .any(|k| k.as_public_key().hash() == sudo_write)
+            == false
+        {
+            warn!("delete user denied ({}) - not elevated", identity);
+            return Err(DeleteUserFailed::NotElevated);
+        }
+
+        Ok((dio, user, token))
+    }
+
+    async fn revoke_service_account_of(
+        &self,
+        owner: &str,
+        identity: &str,
+    ) -> Result<(), DeleteUserFailed> {
+        let chain_key = chain_key_4hex(identity, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio_full(&self.master_session).await?;
+        let mut account = match dio.load::<ServiceAccount>(&service_account_key(identity)).await {
+            Ok(a) if a.owner == owner => a,
+            Ok(_) | Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                return Ok(());
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+        if account.revoked.is_none() {
+            account.as_mut().revoked = Some(utc_now());
+            dio.commit().await?;
+        }
+        Ok(())
+    }
+
+    async fn load_deletion_schedule(&self) -> Result<Vec<String>, DeleteUserFailed> {
+        let chain_key = chain_key_4hex(DELETION_SCHEDULE_KEY, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio(&self.master_session).await;
+
+        let schedule_key = PrimaryKey::from(DELETION_SCHEDULE_KEY.to_string());
+        match dio.load::<DeletionSchedule>(&schedule_key).await {
+            Ok(a) => Ok(a.take().pending),
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => Ok(Vec::new()),
+            Err(err) => {
+                bail!(err);
+            }
+        }
+    }
+
+    async fn update_deletion_schedule(
+        &self,
+        email: &str,
+        pending: bool,
+    ) -> Result<(), DeleteUserFailed> {
+        let master_write_key = match self.master_session.user.write_keys().next() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(DeleteUserFailed::NoMasterKey);
+            }
+        };
+        let master_key = match self.master_key() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(DeleteUserFailed::NoMasterKey);
+            }
+        };
+
+        let chain_key = chain_key_4hex(DELETION_SCHEDULE_KEY, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio_full(&self.master_session).await?;
+
+        let schedule_key = PrimaryKey::from(DELETION_SCHEDULE_KEY.to_string());
+        let mut schedule = match dio.load::<DeletionSchedule>(&schedule_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                let mut schedule =
+                    dio.store_with_key(DeletionSchedule::default(), schedule_key)?;
+                schedule.auth_mut().read = ReadOption::from_key(&master_key);
+                schedule.auth_mut().write = WriteOption::Specific(master_write_key.hash());
+                schedule
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        {
+            let mut schedule = schedule.as_mut();
+            schedule.pending.retain(|a| a != email);
+            if pending {
+                schedule.pending.push(email.to_string());
+            }
+        }
+        dio.commit().await?;
+        Ok(())
+    }
+}
+
+fn utc_now() -> chrono::DateTime<chrono::Utc> {
+    let local_now = chrono::Local::now();
+    local_now.with_timezone(&chrono::Utc)
+}
@@ -72,6 +72,7 @@ impl AuthService {
             gid: group.gid,
             group_key: group.key().clone(),
             authority: session,
+            version: group.when_updated(),
         })
     }
 }
@@ -80,6 +80,19 @@ impl AuthService {
         // Build the list of roles in this group
         let mut roles = Vec::new();
         for role in group.roles.iter() {
+            let members = match has_access {
+                true => role
+                    .access
+                    .meta_list()
+                    .map(|m| m.clone())
+                    .collect::<Vec<_>>(),
+                false => Vec::new(),
+            };
+            let service_accounts = members
+                .iter()
+                .filter(|m| is_service_account(m.as_str()))
+                .map(|m| m.clone())
+                .collect::<Vec<_>>();
             roles.push(GroupDetailsRoleResponse {
                 purpose: role.purpose.clone(),
                 name: role.purpose.to_string(),
@@ -87,14 +100,8 @@ impl AuthService {
                 private_read: role.private_read.clone(),
                 write: role.write.clone(),
                 hidden: has_access == false,
-                members: match has_access {
-                    true => role
-                        .access
-                        .meta_list()
-                        .map(|m| m.clone())
-                        .collect::<Vec<_>>(),
-                    false => Vec::new(),
-                },
+                members,
+                service_accounts,
             });
         }
 
@@ -104,6 +111,40 @@ impl AuthService {
             name: group.name.clone(),
             gid: group.gid,
             roles,
+            version: group.when_updated(),
+        })
+    }
+
+    pub async fn process_group_version(
+        self: Arc<Self>,
+        request: GroupVersionRequest,
+    ) -> Result<GroupVersionResponse, GroupDetailsFailed> {
+        debug!("group ({}) version", request.group);
+
+        let group_chain_key = chain_key_4hex(&request.group, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &group_chain_key, true).await?;
+
+        // Only the header of the group is needed for its version
+        let group_key = PrimaryKey::from(request.group.clone());
+        let dio = chain.dio(&self.master_session).await;
+        let group = match dio.load::<Group>(&group_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                return Err(GroupDetailsFailed::GroupNotFound);
+            }
+            Err(LoadError(
+                LoadErrorKind::TransformationError(TransformErrorKind::MissingReadKey(_)),
+                _,
+            )) => {
+                return Err(GroupDetailsFailed::NoMasterKey);
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        Ok(GroupVersionResponse {
+            version: group.when_updated(),
         })
     }
 }
@@ -42,7 +42,7 @@ impl AuthService {
 
         // Delete the group
         let group_key = PrimaryKey::from(request.group.clone());
-        let dio = chain.dio_full(&super_session).await;
+        let dio = chain.dio_full(&super_session).await?;
         dio.delete(&group_key).await?;
 
         // Delete the advert
@@ -77,7 +77,7 @@ impl AuthService {
 
         // Load the group
         let group_key = PrimaryKey::from(request.group.clone());
-        let dio = chain.dio_full(&super_session).await;
+        let dio = chain.dio_full(&super_session).await?;
         let mut group = match dio.load::<Group>(&group_key).await {
             Ok(a) => a,
             Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
@@ -137,6 +137,10 @@ impl AuthService {
             })
         }
 
+        // Remember the membership so that it can be revoked if the account is erased
+        self.record_group_membership(request.who_name.as_str(), request.group.as_str())
+            .await?;
+
         // Perform the operation that will add the other user to the specific group role
         for role in group
             .as_mut()
@@ -156,4 +160,48 @@ impl AuthService {
             key: group.key().clone(),
         })
     }
+
+    async fn record_group_membership(
+        &self,
+        who: &str,
+        group: &str,
+    ) -> Result<(), GroupUserAddFailed> {
+        let master_write_key = match self.master_session.user.write_keys().next() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(GroupUserAddFailed::NoMasterKey);
+            }
+        };
+        let master_key = match self.master_key() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(GroupUserAddFailed::NoMasterKey);
+            }
+        };
+
+        let chain_key = chain_key_4hex(who, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio_full(&self.master_session).await?;
+
+        let memberships_key = group_memberships_key(who);
+        let mut memberships = match dio.load::<GroupMemberships>(&memberships_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                let mut memberships =
+                    dio.store_with_key(GroupMemberships::default(), memberships_key)?;
+                memberships.auth_mut().read = ReadOption::from_key(&master_key);
+                memberships.auth_mut().write = WriteOption::Specific(master_write_key.hash());
+                memberships
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+        if memberships.groups.iter().any(|a| a == group) {
+            return Ok(());
+        }
+        memberships.as_mut().groups.push(group.to_string());
+        dio.commit().await?;
+        Ok(())
+    }
 }
@@ -33,32 +33,14 @@ impl AuthService {
         let request_purpose = request.purpose;
         let request_session = request.session;
 
-        // Compute which chain the group should exist within
-        let group_chain_key = chain_key_4hex(&request.group, Some("redo"));
-        let chain = self.registry.open(&self.auth_url, &group_chain_key, true).await?;
-
         // Create the super session that has all the rights we need
         let mut super_session = self.master_session.clone();
         super_session.append(request_session.properties());
 
         // Load the group
-        let group_key = PrimaryKey::from(request.group.clone());
-        let dio = chain.dio_full(&super_session).await;
-        let mut group = match dio.load::<Group>(&group_key).await {
-            Ok(a) => a,
-            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
-                return Err(GroupUserRemoveFailed::GroupNotFound);
-            }
-            Err(LoadError(
-                LoadErrorKind::TransformationError(TransformErrorKind::MissingReadKey(_)),
-                _,
-            )) => {
-                return Err(GroupUserRemoveFailed::NoMasterKey);
-            }
-            Err(err) => {
-                bail!(err);
-            }
-        };
+        let (dio, mut group) = self
+            .load_group_for_remove(&request.group, &super_session)
+            .await?;
 
         // Determine what role is needed to adjust the group
         let needed_role = match request_purpose {
@@ -113,4 +95,60 @@ impl AuthService {
             key: group.key().clone(),
         })
     }
+
+    /// Removes the members from every role of a group without checking the rights of a
+    /// referrer - this is used when an account is erased and its access must be revoked
+    pub(crate) async fn group_remove_members(
+        &self,
+        group: &str,
+        who: &[AteHash],
+    ) -> Result<bool, GroupUserRemoveFailed> {
+        let (dio, mut group) = self
+            .load_group_for_remove(group, &self.master_session)
+            .await?;
+
+        let mut removed = false;
+        {
+            let mut group = group.as_mut();
+            for role in group.roles.iter_mut() {
+                for who in who.iter() {
+                    removed |= role.access.remove(who);
+                }
+            }
+        }
+
+        // Commit
+        dio.commit().await?;
+        Ok(removed)
+    }
+
+    async fn load_group_for_remove(
+        &self,
+        group: &str,
+        super_session: &AteSessionUser,
+    ) -> Result<(Arc<DioMut>, DaoMut<Group>), GroupUserRemoveFailed> {
+        // Compute which chain the group should exist within
+        let group_chain_key = chain_key_4hex(group, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &group_chain_key, true).await?;
+
+        // Load the group
+        let group_key = PrimaryKey::from(group.to_string());
+        let dio = chain.dio_full(super_session).await?;
+        let group = match dio.load::<Group>(&group_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                return Err(GroupUserRemoveFailed::GroupNotFound);
+            }
+            Err(LoadError(
+                LoadErrorKind::TransformationError(TransformErrorKind::MissingReadKey(_)),
+                _,
+            )) => {
+                return Err(GroupUserRemoveFailed::NoMasterKey);
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+        Ok((dio, group))
+    }
 }
@@ -29,7 +29,93 @@ impl AuthService {
         request: LoginRequest,
     ) -> Result<LoginResponse, LoginFailed> {
         debug!("login attempt: {}", request.email);
+        let email = request.email.clone();
+        let user_agent = request.user_agent.clone();
+        let login = self.clone().process_login_inner(request);
+        self.throttle_login(email, user_agent, login).await
+    }
+
+    /// Runs a login attempt while counting (and delaying) the failures so
+    /// that accounts can not be brute forced, the outcome is also added to
+    /// the security events of the user
+    pub(crate) async fn throttle_login<F>(
+        self: Arc<Self>,
+        email: String,
+        user_agent: Option<String>,
+        login: F,
+    ) -> Result<LoginResponse, LoginFailed>
+    where
+        F: std::future::Future<Output = Result<LoginResponse, LoginFailed>>,
+    {
+        let started = std::time::Instant::now();
+
+        // Attempts on the same account are serialized so that no failures
+        // are lost, then the login is refused while the account (or source)
+        // is locked out
+        let _account = self.login_throttle.lock_account(email.as_str()).await;
+        let (failures, mut attempt) = match self.check_login_throttle(email.as_str()).await {
+            Ok(a) => a,
+            Err(err) => {
+                self.pad_failed_login(started, 0).await;
+                return Err(err);
+            }
+        };
+
+        let ret = login.await;
+        match &ret {
+            Ok(_) => {
+                if let Some(attempt) = attempt.as_mut() {
+                    attempt.succeeded();
+                }
+                if let Err(err) = self.reset_login_failures(email.as_str()).await {
+                    warn!("failed to reset login failures ({}) - {:?}", email, err);
+                }
+                self.record_login_event(email.as_str(), true, user_agent)
+                    .await;
+            }
+            // Unknown users are counted and delayed exactly like wrong passwords
+            // so that the responses can not be used to find accounts
+            Err(LoginFailed::WrongPassword) | Err(LoginFailed::UserNotFound(_)) => {
+                if let Some(attempt) = attempt.as_mut() {
+                    attempt.failed();
+                }
+                if let Err(err) = self.record_login_failure(email.as_str()).await {
+                    warn!("failed to record login failure ({}) - {:?}", email, err);
+                }
+                if let Err(LoginFailed::WrongPassword) = &ret {
+                    self.record_login_event(email.as_str(), false, user_agent)
+                        .await;
+                }
+                self.pad_failed_login(started, failures + 1).await;
+            }
+            Err(_) => {}
+        }
+        ret
+    }
 
+    async fn record_login_event(&self, email: &str, success: bool, user_agent: Option<String>) {
+        if let Err(err) = self
+            .record_security_event(email, SecurityEventKind::Login, success, user_agent)
+            .await
+        {
+            warn!("failed to record security event ({}) - {:?}", email, err);
+        }
+    }
+
+    /// Holds back the response of a failed login until the backoff for the
+    /// number of failures has passed since the request started
+    async fn pad_failed_login(&self, started: std::time::Instant, failures: u32) {
+        let delay = self.login_throttle.policy.backoff(failures);
+        let elapsed = started.elapsed();
+        if delay > elapsed {
+            tokio::time::sleep(delay - elapsed).await;
+        }
+    }
+
+    async fn process_login_inner(
+        self: Arc<Self>,
+        request: LoginRequest,
+    ) -> Result<LoginResponse, LoginFailed> {
         // Create the super key and token
         let (super_key, token) = match self.compute_master_key(&request.secret) {
             Some(a) => a,
@@ -46,11 +132,10 @@ impl AuthService {
         // Compute which chain the user should exist within
         let chain_key = chain_key_4hex(request.email.as_str(), Some("redo"));
         let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
-        let dio = chain.dio_full(&super_session).await;
+        let dio = chain.dio_full(&super_session).await?;
 
-        // Attempt to load the object (if it fails we will tell the caller)
-        let user_key = PrimaryKey::from(request.email.clone());
-        let mut user = match dio.load::<User>(&user_key).await {
+        // Attempt to find the user (if it fails we will tell the caller)
+        let mut user = match find_user(&dio, request.email.as_str()).await {
             Ok(a) => a,
             Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
                 warn!("login attempt denied ({}) - not found", request.email);
@@ -68,6 +153,7 @@ impl AuthService {
                 bail!(err);
             }
         };
+        let user_key = user.key().clone();
 
         // Check if the account is locked or not yet verified
         match user.status.clone() {
@@ -101,10 +187,42 @@ impl AuthService {
                     return Err(LoginFailed::Unverified(request.email));
                 }
             },
+            UserStatus::PendingDeletion(erase_after) => {
+                let local_now = chrono::Local::now();
+                let utc_now = local_now.with_timezone(&chrono::Utc);
+                if erase_after <= utc_now {
+                    warn!("login attempt denied ({}) - account erased", request.email);
+                    if let Err(err) = self.erase_user(request.email.as_str()).await {
+                        warn!("failed to erase user {} - {:?}", request.email, err);
+                    }
+                    return Err(LoginFailed::UserNotFound(request.email));
+                }
+            }
             UserStatus::Nominal => {}
         };
+
+        // Issue a refresh token so the session can be renewed without the password
+        let refresh_token = match self
+            .issue_refresh_token(&dio, request.email.as_str(), token.clone(), None)
+            .await
+        {
+            Ok(a) => Some(a),
+            Err(err) => {
+                warn!("failed to issue refresh token ({}) - {:?}", request.email, err);
+                None
+            }
+        };
         dio.commit().await?;
 
+        // Warn the user if their account is about to be deleted
+        let message_of_the_day = match user.status.clone() {
+            UserStatus::PendingDeletion(erase_after) => Some(format!(
+                "This account is scheduled for deletion on {} - use 'cancel-delete' to keep it.",
+                erase_after.format("%Y-%m-%d %H:%M UTC")
+            )),
+            _ => None,
+        };
+
         // Add all the authorizations
         let mut session = compute_user_auth(&user);
         session.token = Some(token.clone());
@@ -119,7 +237,8 @@ impl AuthService {
             sudo_read: user.sudo_read,
             sudo_write: user.sudo_write,
             authority: session,
-            message_of_the_day: None,
+            message_of_the_day,
+            refresh_token,
         })
     }
 
@@ -0,0 +1,406 @@
+#![allow(unused_imports)]
+use error_chain::bail;
+use fxhash::FxHashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+use std::time::Duration;
+use std::time::Instant;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use ate::error::LoadError;
+use ate::prelude::*;
+use ate::utils::chain_key_4hex;
+
+use crate::error::*;
+use crate::helper::*;
+use crate::model::*;
+use crate::prelude::*;
+use crate::request::*;
+use crate::service::AuthService;
+
+/// Default number of consecutive failed logins before logins are refused
+pub const DEFAULT_LOGIN_MAX_FAILURES: u32 = 5;
+/// Default amount of time that logins are refused for once the limit is hit
+pub const DEFAULT_LOGIN_LOCKOUT: Duration = Duration::from_secs(900);
+/// Default delay after the first failed login, it doubles with each failure
+pub const DEFAULT_LOGIN_BACKOFF_BASE: Duration = Duration::from_millis(250);
+/// Default upper bound of the delay between failed logins
+pub const DEFAULT_LOGIN_BACKOFF_MAX: Duration = Duration::from_secs(8);
+/// Default minimum time that a failed login takes to be answered
+pub const DEFAULT_LOGIN_MIN_FAILURE_TIME: Duration = Duration::from_secs(1);
+/// Default maximum number of sources whose failures are tracked in memory
+pub const DEFAULT_LOGIN_MAX_SOURCES: usize = 10000;
+
+/// Limits on failed logins that protect accounts from brute force attacks
+#[derive(Debug, Clone)]
+pub struct LoginPolicy {
+    /// Number of consecutive failures (per account or per source) that
+    /// trigger a temporary lockout
+    pub max_failures: u32,
+    /// How long the lockout lasts, failures older than this are forgotten
+    pub lockout: Duration,
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    /// Failed logins are padded to at least this long so that a wrong
+    /// password and an unknown user can not be told apart by timing
+    pub min_failure_time: Duration,
+    pub max_sources: usize,
+}
+
+impl Default for LoginPolicy {
+    fn default() -> LoginPolicy {
+        LoginPolicy {
+            max_failures: DEFAULT_LOGIN_MAX_FAILURES,
+            lockout: DEFAULT_LOGIN_LOCKOUT,
+            backoff_base: DEFAULT_LOGIN_BACKOFF_BASE,
+            backoff_max: DEFAULT_LOGIN_BACKOFF_MAX,
+            min_failure_time: DEFAULT_LOGIN_MIN_FAILURE_TIME,
+            max_sources: DEFAULT_LOGIN_MAX_SOURCES,
+        }
+    }
+}
+
+impl LoginPolicy {
+    /// Time a failed login takes to be answered after this many consecutive failures
+    pub fn backoff(&self, failures: u32) -> Duration {
+        if failures == 0 {
+            return self.min_failure_time;
+        }
+        let shift = (failures - 1).min(16);
+        self.backoff_base
+            .saturating_mul(1u32 << shift)
+            .min(self.backoff_max)
+            .max(self.min_failure_time)
+    }
+}
+
+struct SourceFailures {
+    failures: u32,
+    /// Attempts that are currently in progress, they count towards the
+    /// limit so that concurrent attempts can not exceed it
+    pending: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Returns the source that failed logins are counted against, this is the
+/// IP address of the client as anything else (e.g. the command chain or
+/// the port) is chosen by the client itself
+pub fn login_source(peer: &ConnectionPeer) -> String {
+    peer.addr.ip().to_string()
+}
+
+/// Applies the login policy, the failures of each account are kept in the
+/// auth chain while the failures of each source (the IP address of the
+/// client) are kept in a bounded in-memory table that is shared by all the
+/// command chains of the server
+pub struct LoginThrottle {
+    pub policy: LoginPolicy,
+    sources: Mutex<FxHashMap<String, SourceFailures>>,
+    accounts: Mutex<FxHashMap<String, Weak<tokio::sync::Mutex<()>>>>,
+}
+
+impl Default for LoginThrottle {
+    fn default() -> LoginThrottle {
+        LoginThrottle::new(LoginPolicy::default())
+    }
+}
+
+impl LoginThrottle {
+    pub fn new(policy: LoginPolicy) -> LoginThrottle {
+        LoginThrottle {
+            policy,
+            sources: Mutex::new(FxHashMap::default()),
+            accounts: Mutex::new(FxHashMap::default()),
+        }
+    }
+
+    /// Logins to the same account are processed one at a time so that the
+    /// check of its failures and the recording of a new failure can not be
+    /// interleaved with another attempt (which would lose counts), the same
+    /// lock guards the security events of the account
+    pub async fn lock_account(&self, email: &str) -> tokio::sync::OwnedMutexGuard<()> {
+        let lock = {
+            let mut accounts = self.accounts.lock().unwrap();
+            accounts.retain(|_, v| v.strong_count() > 0);
+            match accounts.get(email).and_then(|a| a.upgrade()) {
+                Some(a) => a,
+                None => {
+                    let ret = Arc::new(tokio::sync::Mutex::new(()));
+                    accounts.insert(email.to_string(), Arc::downgrade(&ret));
+                    ret
+                }
+            }
+        };
+        lock.lock_owned().await
+    }
+
+    /// Starts a login attempt from a source, it returns the number of recent
+    /// failures from the source or how long it remains locked out for
+    pub fn begin_attempt(self: &Arc<Self>, source: &str) -> Result<(u32, SourceAttempt), Duration> {
+        let mut sources = self.sources.lock().unwrap();
+
+        // Make room by evicting the sources that failed the longest time ago
+        // (sources with attempts in progress are never evicted)
+        while sources.len() >= self.policy.max_sources && sources.contains_key(source) == false {
+            let oldest = sources
+                .iter()
+                .filter(|(_, v)| v.pending == 0)
+                .min_by_key(|(_, v)| v.last_failure)
+                .map(|(k, _)| k.clone());
+            match oldest {
+                Some(a) => {
+                    sources.remove(&a);
+                }
+                None => return Err(self.policy.min_failure_time),
+            }
+        }
+
+        let now = Instant::now();
+        let entry = sources
+            .entry(source.to_string())
+            .or_insert_with(|| SourceFailures {
+                failures: 0,
+                pending: 0,
+                last_failure: now,
+                locked_until: None,
+            });
+        if let Some(until) = entry.locked_until {
+            if until > now {
+                return Err(until - now);
+            }
+            entry.locked_until = None;
+        }
+        if now.duration_since(entry.last_failure) >= self.policy.lockout {
+            entry.failures = 0;
+        }
+        if entry.failures + entry.pending >= self.policy.max_failures {
+            return Err(self.policy.backoff(entry.failures + entry.pending));
+        }
+        entry.pending += 1;
+
+        Ok((
+            entry.failures,
+            SourceAttempt {
+                throttle: Arc::clone(self),
+                source: source.to_string(),
+                failed: None,
+            },
+        ))
+    }
+
+    fn end_attempt(&self, source: &str, failed: Option<bool>) {
+        let mut sources = self.sources.lock().unwrap();
+        let entry = match sources.get_mut(source) {
+            Some(a) => a,
+            None => return,
+        };
+        entry.pending = entry.pending.saturating_sub(1);
+        match failed {
+            Some(true) => {
+                let now = Instant::now();
+                if now.duration_since(entry.last_failure) >= self.policy.lockout {
+                    entry.failures = 0;
+                }
+                entry.failures += 1;
+                entry.last_failure = now;
+                if entry.failures >= self.policy.max_failures {
+                    entry.locked_until = Some(now + self.policy.lockout);
+                }
+            }
+            Some(false) => {
+                entry.failures = 0;
+                entry.locked_until = None;
+            }
+            None => {}
+        }
+        if entry.pending == 0 && entry.failures == 0 && entry.locked_until.is_none() {
+            sources.remove(source);
+        }
+    }
+}
+
+/// Login attempt from a source that is in progress, it counts towards the
+/// limit of the source until it is dropped at which point its outcome (if
+/// there was one) is recorded
+pub struct SourceAttempt {
+    throttle: Arc<LoginThrottle>,
+    source: String,
+    failed: Option<bool>,
+}
+
+impl SourceAttempt {
+    pub fn failed(&mut self) {
+        self.failed = Some(true);
+    }
+
+    pub fn succeeded(&mut self) {
+        self.failed = Some(false);
+    }
+}
+
+impl Drop for SourceAttempt {
+    fn drop(&mut self) {
+        self.throttle.end_attempt(self.source.as_str(), self.failed);
+    }
+}
+
+impl AuthService {
+    /// Refuses the login while the account or source is locked out, otherwise
+    /// it returns the number of recent failures (used for the backoff) and
+    /// the attempt of the source (which must be told about the outcome)
+    pub(crate) async fn check_login_throttle(
+        &self,
+        email: &str,
+    ) -> Result<(u32, Option<SourceAttempt>), LoginFailed> {
+        let (source_failures, attempt) = match &self.login_source {
+            Some(source) => match self.login_throttle.begin_attempt(source.as_str()) {
+                Ok((failures, attempt)) => (failures, Some(attempt)),
+                Err(retry_after) => {
+                    warn!("login attempt denied ({}) - source locked out", email);
+                    return Err(LoginFailed::TemporarilyLocked(retry_after));
+                }
+            },
+            None => (0, None),
+        };
+
+        let chain_key = chain_key_4hex(email, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio(&self.master_session).await;
+        let record = match dio.load::<LoginFailures>(&login_failures_key(email)).await {
+            Ok(a) => a.take(),
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => LoginFailures::default(),
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        let now = utc_now();
+        if let Some(until) = record.locked_until {
+            if until > now {
+                warn!(
+                    "login attempt denied ({}) - locked out until {}",
+                    email, until
+                );
+                let retry_after = (until - now).to_std().unwrap_or_default();
+                return Err(LoginFailed::TemporarilyLocked(retry_after));
+            }
+        }
+        let account_failures = match record.last_failure {
+            Some(a) if is_recent(&self.login_throttle.policy, a, now) => record.failures,
+            _ => 0,
+        };
+        Ok((account_failures.max(source_failures), attempt))
+    }
+
+    /// Counts a failed login against the account, once it reaches the limit
+    /// further logins are refused for a while (the caller must hold the lock
+    /// of the account)
+    pub(crate) async fn record_login_failure(&self, email: &str) -> Result<(), LoginFailed> {
+        let master_write_key = match self.master_session.user.write_keys().next() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(LoginFailed::NoMasterKey);
+            }
+        };
+        let master_key = match self.master_key() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(LoginFailed::NoMasterKey);
+            }
+        };
+
+        let chain_key = chain_key_4hex(email, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio_full(&self.master_session).await?;
+        let failures_key = login_failures_key(email);
+        let mut record = match dio.load::<LoginFailures>(&failures_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                let mut record = dio.store_with_key(LoginFailures::default(), failures_key)?;
+                record.auth_mut().read = ReadOption::from_key(&master_key);
+                record.auth_mut().write = WriteOption::Specific(master_write_key.hash());
+                record
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        let policy = &self.login_throttle.policy;
+        let now = utc_now();
+        {
+            let mut record = record.as_mut();
+            match record.last_failure {
+                Some(a) if is_recent(policy, a, now) => {}
+                _ => record.failures = 0,
+            }
+            record.failures += 1;
+            record.last_failure = Some(now);
+            if record.failures >= policy.max_failures {
+                record.locked_until = Some(now + chrono_duration(policy.lockout));
+                warn!(
+                    "account locked out for {}s after {} failed logins ({})",
+                    policy.lockout.as_secs(),
+                    record.failures,
+                    email
+                );
+            }
+        }
+        dio.commit().await?;
+        Ok(())
+    }
+
+    /// Clears the failed logins of the account after a successful login
+    pub(crate) async fn reset_login_failures(&self, email: &str) -> Result<(), LoginFailed> {
+        self.clear_login_failures(email).await?;
+        Ok(())
+    }
+
+    /// Lifts a lockout of an account that was caused by failed logins (used
+    /// by administrators), returns false if the account had no failures
+    pub async fn unlock_login(&self, email: &str) -> Result<bool, LoginFailed> {
+        let ret = self.clear_login_failures(email).await?;
+        if ret {
+            info!("login failures cleared ({})", email);
+        }
+        Ok(ret)
+    }
+
+    async fn clear_login_failures(&self, email: &str) -> Result<bool, LoginFailed> {
+        let chain_key = chain_key_4hex(email, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio_full(&self.master_session).await?;
+        let failures_key = login_failures_key(email);
+        match dio.load::<LoginFailures>(&failures_key).await {
+            Ok(_) => {}
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                return Ok(false);
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        }
+        dio.delete(&failures_key).await?;
+        dio.commit().await?;
+        Ok(true)
+    }
+}
+
+fn is_recent(
+    policy: &LoginPolicy,
+    when: chrono::DateTime<chrono::Utc>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    when + chrono_duration(policy.lockout) > now
+}
+
+fn chrono_duration(duration: Duration) -> chrono::Duration {
+    chrono::Duration::from_std(duration).unwrap_or_else(|_| chrono::Duration::days(365))
+}
+
+fn utc_now() -> chrono::DateTime<chrono::Utc> {
+    let local_now = chrono::Local::now();
+    local_now.with_timezone(&chrono::Utc)
+}
@@ -1,23 +1,33 @@
 mod create_group;
 mod create_user;
+mod delete_user;
 mod gather;
 mod group_details;
 mod group_remove;
 mod group_user_add;
 mod group_user_remove;
 mod login;
+mod login_throttle;
 mod query;
+mod refresh;
 mod reset;
+mod security_activity;
+mod service_account;
 mod sudo;
 
 pub use create_group::*;
 pub use create_user::*;
+pub use delete_user::*;
 pub use gather::*;
 pub use group_details::*;
 pub use group_remove::*;
 pub use group_user_add::*;
 pub use group_user_remove::*;
 pub use login::*;
+pub use login_throttle::*;
 pub use query::*;
+pub use refresh::*;
 pub use reset::*;
+pub use security_activity::*;
+pub use service_account::*;
 pub use sudo::*;
@@ -0,0 +1,314 @@
+#![allow(unused_imports)]
+use chrono::Duration;
+use error_chain::bail;
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use ate::error::LoadError;
+use ate::prelude::*;
+use ate::utils::chain_key_4hex;
+
+use crate::error::*;
+use crate::helper::*;
+use crate::model::*;
+use crate::prelude::*;
+use crate::request::*;
+use crate::service::AuthService;
+
+impl AuthService {
+    pub async fn process_refresh(
+        self: Arc<Self>,
+        request: RefreshRequest,
+    ) -> Result<RefreshResponse, RefreshFailed> {
+        let email = request.token.email.clone();
+        debug!("refresh attempt: {}", email);
+
+        // The grants are locked while the token is rotated so that two refreshes
+        // with the same token can never both succeed
+        let chain_key = chain_key_4hex(email.as_str(), Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let locker = chain.dio_full(&self.master_session).await?;
+        let lock_key = refresh_grants_key(email.as_str());
+        lock_refresh_grants(&locker, lock_key).await?;
+        let ret = self.refresh_locked(&chain, request).await;
+        locker.unlock(lock_key).await?;
+        ret
+    }
+
+    async fn refresh_locked(
+        &self,
+        chain: &ChainGuard,
+        request: RefreshRequest,
+    ) -> Result<RefreshResponse, RefreshFailed> {
+        let email = request.token.email.clone();
+        let master_key = match self.master_key() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(RefreshFailed::NoMasterKey);
+            }
+        };
+
+        // Find the grant that matches the token (a token that was rotated or
+        // revoked no longer has one)
+        let grant = {
+            let dio = chain.dio(&self.master_session).await;
+            let grants = match dio.load::<RefreshGrants>(&refresh_grants_key(email.as_str())).await {
+                Ok(a) => a.take(),
+                Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                    warn!("refresh attempt denied ({}) - revoked", email);
+                    return Err(RefreshFailed::Revoked);
+                }
+                Err(err) => {
+                    bail!(err);
+                }
+            };
+            match grants.grants.into_iter().find(|a| refresh_grant_matches(a, &request.token)) {
+                Some(a) => a,
+                None => {
+                    warn!("refresh attempt denied ({}) - revoked", email);
+                    return Err(RefreshFailed::Revoked);
+                }
+            }
+        };
+        if grant.expires <= utc_now() {
+            warn!("refresh attempt denied ({}) - expired", email);
+            self.revoke_refresh_grant(&email, &grant.id).await?;
+            return Err(RefreshFailed::Expired);
+        }
+
+        // Extract the original super key that was used to access the user
+        let super_key = grant.token.unwrap(&master_key)?;
+        let mut super_session = self.master_session.clone();
+        super_session.user.add_read_key(&super_key);
+        let dio = chain.dio_full(&super_session).await?;
+
+        let user_key = PrimaryKey::from(email.clone());
+        let user = match dio.load::<User>(&user_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                warn!("refresh attempt denied ({}) - not found", email);
+                return Err(RefreshFailed::UserNotFound(email));
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        // Check if the account is locked or not yet verified
+        match user.status.clone() {
+            UserStatus::Locked(until) => {
+                let utc_now = utc_now();
+                if until > utc_now {
+                    let duration = until - utc_now;
+                    warn!(
+                        "refresh attempt denied ({}) - account locked until {}",
+                        email, until
+                    );
+                    return Err(RefreshFailed::AccountLocked(duration.to_std().unwrap()));
+                }
+            }
+            UserStatus::Unverified => {
+                warn!("refresh attempt denied ({}) - unverified", email);
+                return Err(RefreshFailed::Unverified(email));
+            }
+            UserStatus::PendingDeletion(erase_after) => {
+                if erase_after <= utc_now() {
+                    warn!("refresh attempt denied ({}) - account erased", email);
+                    if let Err(err) = self.erase_user(email.as_str()).await {
+                        warn!("failed to erase user {} - {:?}", email, err);
+                    }
+                    return Err(RefreshFailed::UserNotFound(email));
+                }
+            }
+            UserStatus::Nominal => {}
+        };
+
+        // Rotate the refresh token so that it can only ever be used once
+        let refresh_token = self
+            .issue_refresh_token(&dio, email.as_str(), grant.token.clone(), Some(&grant.id))
+            .await?;
+        dio.commit().await?;
+
+        // Add all the authorizations
+        let mut session = compute_user_auth(&user);
+        session.token = Some(grant.token);
+
+        info!("refresh attempt accepted ({})", email);
+        Ok(RefreshResponse {
+            authority: session,
+            refresh_token,
+        })
+    }
+
+    pub async fn process_revoke_refresh(
+        self: Arc<Self>,
+        request: RevokeRefreshRequest,
+    ) -> Result<RevokeRefreshResponse, RefreshFailed> {
+        let email = request.token.email.clone();
+        debug!("revoke refresh: {}", email);
+
+        // Only the holder of the token (i.e. who knows its secret) may revoke it,
+        // this waits for any refresh that is rotating the token to finish first
+        let chain_key = chain_key_4hex(email.as_str(), Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let locker = chain.dio_full(&self.master_session).await?;
+        let lock_key = refresh_grants_key(email.as_str());
+        lock_refresh_grants(&locker, lock_key).await?;
+        let ret = self.revoke_refresh_locked(&chain, request).await;
+        locker.unlock(lock_key).await?;
+        ret
+    }
+
+    async fn revoke_refresh_locked(
+        &self,
+        chain: &ChainGuard,
+        request: RevokeRefreshRequest,
+    ) -> Result<RevokeRefreshResponse, RefreshFailed> {
+        let email = request.token.email.clone();
+        let dio = chain.dio(&self.master_session).await;
+        let known = match dio.load::<RefreshGrants>(&refresh_grants_key(email.as_str())).await {
+            Ok(a) => a
+                .grants
+                .iter()
+                .any(|a| refresh_grant_matches(a, &request.token)),
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => false,
+            Err(err) => {
+                bail!(err);
+            }
+        };
+        if known {
+            self.revoke_refresh_grant(&email, &request.token.id).await?;
+            info!("refresh token revoked ({})", email);
+        }
+        Ok(RevokeRefreshResponse {})
+    }
+
+    /// Issues a new refresh token for the user (within the supplied DIO which
+    /// the caller must commit), the grant being replaced (if any) is removed
+    /// along with any that have expired
+    pub(crate) async fn issue_refresh_token(
+        &self,
+        dio: &Arc<DioMut>,
+        email: &str,
+        token: EncryptedSecureData<EncryptKey>,
+        replaces: Option<&PrimaryKey>,
+    ) -> Result<RefreshToken, RefreshFailed> {
+        let master_write_key = match self.master_session.user.write_keys().next() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(RefreshFailed::NoMasterKey);
+            }
+        };
+        let master_key = match self.master_key() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(RefreshFailed::NoMasterKey);
+            }
+        };
+
+        let grants_key = refresh_grants_key(email);
+        let mut grants = match dio.load::<RefreshGrants>(&grants_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                let mut grants = dio.store_with_key(RefreshGrants::default(), grants_key)?;
+                grants.auth_mut().read = ReadOption::from_key(&master_key);
+                grants.auth_mut().write = WriteOption::Specific(master_write_key.hash());
+                grants
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        let now = utc_now();
+        let ret = RefreshToken {
+            email: email.to_string(),
+            id: PrimaryKey::generate(),
+            secret: AteHash::generate(),
+            expires: now + Duration::days(REFRESH_TOKEN_LIFETIME_DAYS),
+        };
+
+        let mut grants = grants.as_mut();
+        grants
+            .grants
+            .retain(|a| a.expires > now && Some(&a.id) != replaces);
+        while grants.grants.len() >= MAX_REFRESH_GRANTS {
+            grants.grants.remove(0);
+        }
+        grants.grants.push(RefreshGrant {
+            id: ret.id.clone(),
+            secret_hash: AteHash::from_bytes(ret.secret.as_bytes()),
+            issued: now,
+            expires: ret.expires,
+            token,
+        });
+        Ok(ret)
+    }
+
+    /// Revokes all the refresh tokens of a user, the sessions that were
+    /// already handed out stay valid but they can no longer be refreshed
+    pub async fn revoke_refresh_tokens(&self, email: &str) -> Result<(), RefreshFailed> {
+        let chain_key = chain_key_4hex(email, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio_full(&self.master_session).await?;
+        let grants_key = refresh_grants_key(email);
+        match dio.load::<RefreshGrants>(&grants_key).await {
+            Ok(_) => {}
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                return Ok(());
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        }
+        dio.delete(&grants_key).await?;
+        dio.commit().await?;
+        Ok(())
+    }
+
+    async fn revoke_refresh_grant(&self, email: &str, id: &PrimaryKey) -> Result<(), RefreshFailed> {
+        let chain_key = chain_key_4hex(email, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio_full(&self.master_session).await?;
+        let mut grants = match dio.load::<RefreshGrants>(&refresh_grants_key(email)).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                return Ok(());
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+        grants.as_mut().grants.retain(|a| &a.id != id);
+        dio.commit().await?;
+        Ok(())
+    }
+}
+
+/// Waits (with an exponential backoff) for the lock on the refresh grants of
+/// a user, it is only ever held for the duration of one refresh
+async fn lock_refresh_grants(dio: &Arc<DioMut>, key: PrimaryKey) -> Result<(), RefreshFailed> {
+    let timer = std::time::Instant::now();
+    let mut max_wait = 0u64;
+    while dio.try_lock(key).await? == false {
+        if timer.elapsed() > REFRESH_LOCK_TIMEOUT {
+            warn!("refresh grants are still locked after {:?}", REFRESH_LOCK_TIMEOUT);
+            return Err(RefreshFailed::Busy);
+        }
+        max_wait = ((max_wait * 12u64) / 10u64) + 5u64;
+        max_wait = max_wait.min(500u64);
+        let min_wait = max_wait / 2u64;
+        let random_wait = fastrand::u64(min_wait..max_wait);
+        ate::engine::sleep(std::time::Duration::from_millis(random_wait)).await;
+    }
+    Ok(())
+}
+
+fn refresh_grant_matches(grant: &RefreshGrant, token: &RefreshToken) -> bool {
+    grant.id == token.id && grant.secret_hash == AteHash::from_bytes(token.secret.as_bytes())
+}
+
+fn utc_now() -> chrono::DateTime<chrono::Utc> {
+    let local_now = chrono::Local::now();
+    local_now.with_timezone(&chrono::Utc)
+}
@@ -56,7 +56,7 @@ impl AuthService {
         // Compute which chain the user should exist within
         let chain_key = chain_key_4hex(request.email.as_str(), Some("redo"));
         let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
-        let dio = chain.dio_full(&super_session).await;
+        let dio = chain.dio_full(&super_session).await?;
 
         // Check if the user exists
         let user_key = PrimaryKey::from(request.email.clone());
@@ -219,6 +219,11 @@ impl AuthService {
         // Commit the transaction
         dio.commit().await?;
 
+        // Sessions issued with the old credentials must not be refreshed anymore
+        if let Err(err) = self.revoke_refresh_tokens(request.email.as_str()).await {
+            warn!("failed to revoke refresh tokens ({}) - {:?}", request.email, err);
+        }
+
         // Create the authorizations and return them
         let mut session = compute_user_auth(user.deref());
         session.token = Some(token);
@@ -0,0 +1,295 @@
+#![allow(unused_imports)]
+use error_chain::bail;
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use ate::error::LoadError;
+use ate::prelude::*;
+use ate::utils::chain_key_4hex;
+
+use crate::error::*;
+use crate::helper::*;
+use crate::model::*;
+use crate::prelude::*;
+use crate::request::*;
+use crate::service::AuthService;
+
+impl AuthService {
+    pub async fn process_security_activity(
+        self: Arc<Self>,
+        request: SecurityActivityRequest,
+    ) -> Result<SecurityActivityResponse, SecurityActivityFailed> {
+        info!("security activity: {}", request.session.identity());
+
+        let identity = request.session.identity().to_string();
+        self.load_user_for_activity(identity.as_str(), &request.session.token)
+            .await?;
+
+        let chain_key = chain_key_4hex(identity.as_str(), Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio(&self.master_session).await;
+        let record = match dio
+            .load::<SecurityEvents>(&security_events_key(identity.as_str()))
+            .await
+        {
+            Ok(a) => a.take(),
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => SecurityEvents::default(),
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        let events = record
+            .events
+            .into_iter()
+            .rev()
+            .take(request.limit)
+            .collect();
+        Ok(SecurityActivityResponse {
+            events,
+            webhook: record.webhook,
+        })
+    }
+
+    pub async fn process_security_webhook(
+        self: Arc<Self>,
+        request: SecurityWebhookRequest,
+    ) -> Result<SecurityWebhookResponse, SecurityActivityFailed> {
+        info!("security webhook: {}", request.session.identity());
+
+        // Only https endpoints on the public internet can be notified
+        if let Some(webhook) = &request.webhook {
+            if is_allowed_webhook(webhook.as_str()) == false {
+                return Err(SecurityActivityFailed::InvalidWebhook(webhook.clone()));
+            }
+        }
+
+        // Changing where the notifications go requires elevated rights (sudo)
+        let identity = request.session.identity().to_string();
+        let user = self
+            .load_user_for_activity(identity.as_str(), &request.session.inner.token)
+            .await?;
+        let sudo_write = user.sudo_write.hash();
+        if request
+            .session
+            .write_keys(AteSessionKeyCategory::UpperKeys)
+            .any(|k| k.as_public_key().hash() == sudo_write)
+            == false
+        {
+            warn!("security webhook denied ({}) - not elevated", identity);
+            return Err(SecurityActivityFailed::NotElevated);
+        }
+
+        let _account = self.login_throttle.lock_account(identity.as_str()).await;
+        let (dio, mut record) = self
+            .load_security_events(identity.as_str())
+            .await?;
+        record.as_mut().webhook = request.webhook.clone();
+        dio.commit().await?;
+
+        Ok(SecurityWebhookResponse {
+            webhook: request.webhook,
+        })
+    }
+
+    /// Adds a login or sudo attempt to the security events of the user, when
+    /// its a successful login from a new source then the webhook of the user
+    /// (if they have one) is notified. The caller must hold the lock of the
+    /// account so that concurrent attempts do not overwrite each others events
+    pub(crate) async fn record_security_event(
+        &self,
+        email: &str,
+        kind: SecurityEventKind,
+        success: bool,
+        user_agent: Option<String>,
+    ) -> Result<(), SecurityActivityFailed> {
+        let event = SecurityEvent {
+            when: utc_now(),
+            kind,
+            success,
+            addr: self.login_peer.as_ref().map(|a| a.addr.to_string()),
+            node_id: self.login_peer.as_ref().map(|a| a.node_id),
+            user_agent,
+        };
+
+        let (dio, mut record) = self.load_security_events(email).await?;
+        let is_new = record.as_mut().record(event.clone());
+        let webhook = record.webhook.clone();
+        dio.commit().await?;
+
+        if is_new {
+            if let Some(webhook) = webhook {
+                debug!("login from a new source ({})", email);
+                self.notify_security_webhook(
+                    webhook,
+                    SecurityNotification {
+                        email: email.to_string(),
+                        event,
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Posts the notification to the webhook in the background, delivery is
+    /// best effort and failures are only logged
+    fn notify_security_webhook(
+        &self,
+        webhook: String,
+        notification: SecurityNotification,
+    ) -> Result<(), SecurityActivityFailed> {
+        let master_write_key = match self.master_session.user.write_keys().next() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(SecurityActivityFailed::NoMasterKey);
+            }
+        };
+        let body = serde_json::to_vec(&notification)?;
+        let signature = base64::encode(master_write_key.sign(&body[..])?);
+
+        #[cfg(feature = "server")]
+        TaskEngine::spawn(async move {
+            // The host is resolved here and the request is pinned to the
+            // address that was checked (and redirects are not followed) so
+            // that the webhook can not be pointed at the internal network
+            let client = match webhook_client(webhook.as_str()).await {
+                Some(a) => a,
+                None => {
+                    warn!("security webhook refused ({}) - not a public address", webhook);
+                    return;
+                }
+            };
+            let ret = client
+                .post(webhook.as_str())
+                .header("Content-Type", "application/json")
+                .header("X-Ate-Signature", signature)
+                .timeout(std::time::Duration::from_secs(10))
+                .body(body)
+                .send()
+                .await
+                .and_then(|a| a.error_for_status());
+            match ret {
+                Ok(_) => debug!("security webhook delivered ({})", webhook),
+                Err(err) => warn!("security webhook failed ({}) - {}", webhook, err),
+            }
+        });
+        #[cfg(not(feature = "server"))]
+        {
+            let _ = (body, signature);
+            warn!("security webhook skipped ({}) - not supported by this build", webhook);
+        }
+        Ok(())
+    }
+
+    async fn load_security_events(
+        &self,
+        email: &str,
+    ) -> Result<(Arc<DioMut>, DaoMut<SecurityEvents>), SecurityActivityFailed> {
+        let master_write_key = match self.master_session.user.write_keys().next() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(SecurityActivityFailed::NoMasterKey);
+            }
+        };
+        let master_key = match self.master_key() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(SecurityActivityFailed::NoMasterKey);
+            }
+        };
+
+        let chain_key = chain_key_4hex(email, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio_full(&self.master_session).await?;
+        let events_key = security_events_key(email);
+        let record = match dio.load::<SecurityEvents>(&events_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                let mut record = dio.store_with_key(SecurityEvents::default(), events_key)?;
+                record.auth_mut().read = ReadOption::from_key(&master_key);
+                record.auth_mut().write = WriteOption::Specific(master_write_key.hash());
+                record
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+        Ok((dio, record))
+    }
+
+    /// Loads the user with the token that was handed out when they logged in
+    /// which proves that the caller is the owner of the account
+    async fn load_user_for_activity(
+        &self,
+        identity: &str,
+        token: &Option<EncryptedSecureData<EncryptKey>>,
+    ) -> Result<User, SecurityActivityFailed> {
+        let token = match token {
+            Some(a) => a,
+            None => {
+                return Err(SecurityActivityFailed::MissingToken);
+            }
+        };
+        let master_key = match self.master_key() {
+            Some(a) => a,
+            None => {
+                return Err(SecurityActivityFailed::NoMasterKey);
+            }
+        };
+        let super_key = token.unwrap(&master_key)?;
+
+        let mut super_session = self.master_session.clone();
+        super_session.user.add_read_key(&super_key);
+        let (super_super_key, _) = match self.compute_master_key(&super_key) {
+            Some(a) => a,
+            None => {
+                return Err(SecurityActivityFailed::NoMasterKey);
+            }
+        };
+        super_session.user.add_read_key(&super_super_key);
+
+        let chain_key = chain_key_4hex(identity, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio(&super_session).await;
+        let user_key = PrimaryKey::from(identity.to_string());
+        match dio.load::<User>(&user_key).await {
+            Ok(a) => Ok(a.take()),
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                Err(SecurityActivityFailed::UserNotFound(identity.to_string()))
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        }
+    }
+}
+
+/// Builds the client that posts to a webhook, it returns None if the webhook
+/// is not allowed or any of the addresses of its host are not public
+#[cfg(feature = "server")]
+async fn webhook_client(webhook: &str) -> Option<reqwest::Client> {
+    if is_allowed_webhook(webhook) == false {
+        return None;
+    }
+    let url = url::Url::parse(webhook).ok()?;
+    let host = url.host_str()?.to_string();
+    let port = url.port_or_known_default()?;
+    let addrs = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .ok()?
+        .collect::<Vec<_>>();
+    if addrs.is_empty() || addrs.iter().any(|a| is_public_ip(&a.ip()) == false) {
+        return None;
+    }
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve(host.as_str(), addrs[0])
+        .build()
+        .ok()
+}
+
+fn utc_now() -> chrono::DateTime<chrono::Utc> {
+    let local_now = chrono::Local::now();
+    local_now.with_timezone(&chrono::Utc)
+}
@@ -0,0 +1,508 @@
+#![allow(unused_imports)]
+use error_chain::bail;
+use fxhash::FxHashMap;
+use regex::Regex;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+
+use ate::error::LoadError;
+use ate::prelude::*;
+use ate::utils::chain_key_4hex;
+
+use crate::error::*;
+use crate::helper::*;
+use crate::model::*;
+use crate::prelude::*;
+use crate::request::*;
+use crate::service::AuthService;
+
+/// Amount of time a service account has to answer a login challenge
+pub const SERVICE_LOGIN_CHALLENGE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Maximum number of challenges that can be outstanding at once
+const MAX_SERVICE_LOGIN_CHALLENGES: usize = 10000;
+
+/// Challenges that have been handed out to service accounts and not yet
+/// answered, each challenge can only be used once
+#[derive(Default)]
+pub struct ServiceChallenges {
+    outstanding: Mutex<FxHashMap<AteHash, (String, Instant)>>,
+}
+
+impl ServiceChallenges {
+    pub fn issue(&self, identity: &str) -> AteHash {
+        let now = Instant::now();
+        let challenge = AteHash::generate();
+
+        let mut outstanding = self.outstanding.lock().unwrap();
+        outstanding.retain(|_, (_, issued)| now.duration_since(*issued) < SERVICE_LOGIN_CHALLENGE_TIMEOUT);
+        if outstanding.len() >= MAX_SERVICE_LOGIN_CHALLENGES {
+            let oldest = outstanding
+                .iter()
+                .min_by_key(|(_, (_, issued))| *issued)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                outstanding.remove(&oldest);
+            }
+        }
+        outstanding.insert(challenge.clone(), (identity.to_string(), now));
+        challenge
+    }
+
+    /// Removes the challenge and returns true if it was issued to this
+    /// identity and has not yet expired
+    pub fn take(&self, identity: &str, challenge: &AteHash) -> bool {
+        let mut outstanding = self.outstanding.lock().unwrap();
+        match outstanding.remove(challenge) {
+            Some((a, issued)) => {
+                a == identity && issued.elapsed() < SERVICE_LOGIN_CHALLENGE_TIMEOUT
+            }
+            None => false,
+        }
+    }
+}
+
+impl AuthService {
+    pub async fn process_service_login_challenge(
+        self: Arc<Self>,
+        request: ServiceLoginChallengeRequest,
+    ) -> Result<ServiceLoginChallengeResponse, LoginFailed> {
+        debug!("service login challenge: {}", request.identity);
+
+        // Challenges are handed out even for accounts that do not exist so
+        // that they can not be used to find accounts
+        let challenge = self.service_challenges.issue(request.identity.as_str());
+        let expires = utc_now()
+            + chrono::Duration::from_std(SERVICE_LOGIN_CHALLENGE_TIMEOUT).unwrap();
+        Ok(ServiceLoginChallengeResponse { challenge, expires })
+    }
+
+    pub async fn process_service_login(
+        self: Arc<Self>,
+        request: ServiceLoginRequest,
+    ) -> Result<LoginResponse, LoginFailed> {
+        debug!("service login attempt: {}", request.identity);
+        let identity = request.identity.clone();
+        let login = self.clone().process_service_login_inner(request);
+        self.throttle_login(identity, None, login).await
+    }
+
+    async fn process_service_login_inner(
+        self: Arc<Self>,
+        request: ServiceLoginRequest,
+    ) -> Result<LoginResponse, LoginFailed> {
+        let identity = request.identity.clone();
+        let master_key = match self.master_key() {
+            Some(a) => a.clone(),
+            None => {
+                warn!("service login attempt denied ({}) - no master key", identity);
+                return Err(LoginFailed::NoMasterKey);
+            }
+        };
+
+        // The challenge must be one that we issued to this account
+        if self
+            .service_challenges
+            .take(identity.as_str(), &request.challenge)
+            == false
+        {
+            warn!("service login attempt denied ({}) - unknown challenge", identity);
+            return Err(LoginFailed::WrongPassword);
+        }
+
+        // Load the account (the revocation is checked on every login)
+        let chain_key = chain_key_4hex(identity.as_str(), Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let account = {
+            let dio = chain.dio(&self.master_session).await;
+            match dio.load::<ServiceAccount>(&service_account_key(identity.as_str())).await {
+                Ok(a) => a.take(),
+                Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                    warn!("service login attempt denied ({}) - not found", identity);
+                    return Err(LoginFailed::UserNotFound(identity));
+                }
+                Err(err) => {
+                    bail!(err);
+                }
+            }
+        };
+        if account.revoked.is_some() {
+            warn!("service login attempt denied ({}) - revoked", identity);
+            return Err(LoginFailed::Revoked(identity));
+        }
+
+        // Check the signature of the challenge
+        let data = service_login_data(identity.as_str(), &request.challenge);
+        match account.public_key.verify(&data[..], &request.signature[..]) {
+            Ok(true) => {}
+            _ => {
+                warn!("service login attempt denied ({}) - wrong signature", identity);
+                return Err(LoginFailed::WrongPassword);
+            }
+        }
+
+        // Extract the super key that was used to create the account
+        let super_key = account.token.unwrap(&master_key)?;
+        let mut super_session = self.master_session.clone();
+        super_session.user.add_read_key(&super_key);
+        let dio = chain.dio(&super_session).await;
+
+        let user_key = PrimaryKey::from(identity.clone());
+        let user = match dio.load::<User>(&user_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                warn!("service login attempt denied ({}) - not found", identity);
+                return Err(LoginFailed::UserNotFound(identity));
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+        if let UserStatus::Locked(until) = user.status.clone() {
+            let utc_now = utc_now();
+            if until > utc_now {
+                let duration = until - utc_now;
+                warn!(
+                    "service login attempt denied ({}) - account locked until {}",
+                    identity, until
+                );
+                return Err(LoginFailed::AccountLocked(duration.to_std().unwrap()));
+            }
+        }
+
+        // Add all the authorizations (service accounts hold on to their key
+        // so they are not given a refresh token)
+        let mut session = compute_user_auth(&user);
+        session.token = Some(account.token.clone());
+
+        let user = user.take();
+        info!("service login attempt accepted ({})", identity);
+        Ok(LoginResponse {
+            user_key,
+            nominal_read: user.nominal_read,
+            nominal_write: user.nominal_write,
+            sudo_read: user.sudo_read,
+            sudo_write: user.sudo_write,
+            authority: session,
+            message_of_the_day: None,
+            refresh_token: None,
+        })
+    }
+
+    pub async fn process_create_service_account(
+        self: Arc<Self>,
+        request: CreateServiceAccountRequest,
+    ) -> Result<CreateServiceAccountResponse, ServiceAccountFailed> {
+        info!(
+            "create service account: {} (owner={})",
+            request.name,
+            request.session.identity()
+        );
+        let owner = self
+            .load_service_account_owner(&request.session.inner, Some(&request.session))
+            .await?;
+
+        let master_write_key = match self.master_session.user.write_keys().next() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(ServiceAccountFailed::NoMasterKey);
+            }
+        };
+        let master_key = match self.master_key() {
+            Some(a) => a.clone(),
+            None => {
+                return Err(ServiceAccountFailed::NoMasterKey);
+            }
+        };
+
+        // The name becomes part of the identity of the account
+        let regex = Regex::new("^[a-z0-9][a-z0-9_-]{0,39}$").unwrap();
+        if regex.is_match(request.name.as_str()) == false {
+            warn!("invalid service account name - {}", request.name);
+            return Err(ServiceAccountFailed::InvalidName);
+        }
+        let identity = service_account_identity(request.name.as_str());
+
+        // Creates of the same name are serialized so that only one of them
+        // can pass the existence check below
+        let _account = self.login_throttle.lock_account(identity.as_str()).await;
+
+        // Accounts that were revoked keep their name so that it can not be
+        // taken over by someone else
+        let chain_key = chain_key_4hex(identity.as_str(), Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        if chain
+            .dio(&self.master_session)
+            .await
+            .exists(&service_account_key(identity.as_str()))
+            .await
+        {
+            warn!("service account already exists: {}", identity);
+            return Err(ServiceAccountFailed::AlreadyExists(identity));
+        }
+
+        // The account is a normal user (so groups treat it identically) except
+        // that nobody ever knows its secret as it logs in with its key instead
+        let create = CreateUserRequest {
+            auth: self.auth_url.to_string(),
+            email: identity.clone(),
+            secret: EncryptKey::generate(KeySize::Bit192),
+            accepted_terms: self.terms_and_conditions.clone(),
+        };
+        let (response, mut user) = match self
+            .clone()
+            .process_create_user_internal(create, UserStatus::Nominal)
+            .await
+        {
+            Ok(a) => a,
+            Err(CreateUserFailed::AlreadyExists(_)) => {
+                return Err(ServiceAccountFailed::AlreadyExists(identity));
+            }
+            Err(CreateUserFailed::InvalidEmail) => {
+                return Err(ServiceAccountFailed::InvalidName);
+            }
+            Err(CreateUserFailed::NoMasterKey) => {
+                return Err(ServiceAccountFailed::NoMasterKey);
+            }
+            Err(CreateUserFailed::InternalError(code)) => {
+                return Err(ServiceAccountFailed::InternalError(code));
+            }
+            Err(err) => {
+                warn!("failed to create service account {} - {:?}", identity, err);
+                return Err(ServiceAccountFailed::InternalError(0));
+            }
+        };
+        let token = match response.authority.token.clone() {
+            Some(a) => a,
+            None => {
+                return Err(ServiceAccountFailed::MissingToken);
+            }
+        };
+        user.as_mut().role = UserRole::Robot;
+
+        let dio = user.dio_mut();
+        let mut account = dio.store_with_key(
+            ServiceAccount {
+                identity: identity.clone(),
+                owner: owner.clone(),
+                public_key: request.public_key.clone(),
+                created: utc_now(),
+                revoked: None,
+                token,
+            },
+            service_account_key(identity.as_str()),
+        )?;
+        account.auth_mut().read = ReadOption::from_key(&master_key);
+        account.auth_mut().write = WriteOption::Specific(master_write_key.hash());
+        dio.commit().await?;
+
+        // Record the account against its owner so that it can be listed (the
+        // list is shared by all the accounts of the owner so the update of it
+        // is serialized otherwise concurrent creates would lose entries)
+        let _owner = self.login_throttle.lock_account(owner.as_str()).await;
+        let chain_key = chain_key_4hex(owner.as_str(), Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio_full(&self.master_session).await?;
+        let accounts_key = service_accounts_key(owner.as_str());
+        let mut accounts = match dio.load::<ServiceAccounts>(&accounts_key).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                let mut accounts = dio.store_with_key(ServiceAccounts::default(), accounts_key)?;
+                accounts.auth_mut().read = ReadOption::from_key(&master_key);
+                accounts.auth_mut().write = WriteOption::Specific(master_write_key.hash());
+                accounts
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+        if accounts.identities.contains(&identity) == false {
+            accounts.as_mut().identities.push(identity.clone());
+        }
+        dio.commit().await?;
+
+        info!("service account created ({}) - owner={}", identity, owner);
+        Ok(CreateServiceAccountResponse {
+            key: response.key,
+            identity,
+        })
+    }
+
+    pub async fn process_list_service_accounts(
+        self: Arc<Self>,
+        request: ListServiceAccountsRequest,
+    ) -> Result<ListServiceAccountsResponse, ServiceAccountFailed> {
+        debug!("list service accounts: {}", request.session.identity());
+        let owner = self
+            .load_service_account_owner(&request.session, None)
+            .await?;
+
+        let chain_key = chain_key_4hex(owner.as_str(), Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let identities = match chain
+            .dio(&self.master_session)
+            .await
+            .load::<ServiceAccounts>(&service_accounts_key(owner.as_str()))
+            .await
+        {
+            Ok(a) => a.take().identities,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => Vec::new(),
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        let mut accounts = Vec::new();
+        for identity in identities {
+            let account = match self.load_service_account(identity.as_str()).await {
+                Ok(a) => a,
+                Err(ServiceAccountFailed::NotFound(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            accounts.push(ServiceAccountSummary {
+                identity,
+                public_key: account.public_key.hash(),
+                created: account.created,
+                revoked: account.revoked,
+            });
+        }
+        Ok(ListServiceAccountsResponse { accounts })
+    }
+
+    pub async fn process_revoke_service_account(
+        self: Arc<Self>,
+        request: RevokeServiceAccountRequest,
+    ) -> Result<RevokeServiceAccountResponse, ServiceAccountFailed> {
+        info!(
+            "revoke service account: {} (owner={})",
+            request.identity,
+            request.session.identity()
+        );
+        let owner = self
+            .load_service_account_owner(&request.session.inner, Some(&request.session))
+            .await?;
+
+        // Accounts of other users are reported as not found so that they can
+        // not be discovered
+        let identity = request.identity.clone();
+        let _account = self.login_throttle.lock_account(identity.as_str()).await;
+        let chain_key = chain_key_4hex(identity.as_str(), Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio_full(&self.master_session).await?;
+        let mut account = match dio.load::<ServiceAccount>(&service_account_key(identity.as_str())).await {
+            Ok(a) if a.owner == owner => a,
+            Ok(_) | Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                return Err(ServiceAccountFailed::NotFound(identity));
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        let revoked = match account.revoked {
+            Some(a) => a,
+            None => {
+                let now = utc_now();
+                account.as_mut().revoked = Some(now);
+                dio.commit().await?;
+                now
+            }
+        };
+
+        info!("service account revoked ({}) - owner={}", identity, owner);
+        Ok(RevokeServiceAccountResponse { identity, revoked })
+    }
+
+    async fn load_service_account(&self, identity: &str) -> Result<ServiceAccount, ServiceAccountFailed> {
+        let chain_key = chain_key_4hex(identity, Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio(&self.master_session).await;
+        match dio.load::<ServiceAccount>(&service_account_key(identity)).await {
+            Ok(a) => Ok(a.take()),
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                Err(ServiceAccountFailed::NotFound(identity.to_string()))
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        }
+    }
+
+    /// Checks that the session really belongs to the user it claims to be
+    /// (and that it is elevated when a sudo session is given) and returns
+    /// the identity of that user
+    async fn load_service_account_owner(
+        &self,
+        session: &AteSessionUser,
+        sudo: Option<&AteSessionSudo>,
+    ) -> Result<String, ServiceAccountFailed> {
+        let identity = session.identity().to_string();
+
+        // Service accounts can not create (or manage) other service accounts
+        if is_service_account(identity.as_str()) {
+            warn!("service account management denied ({}) - not a person", identity);
+            return Err(ServiceAccountFailed::NotElevated);
+        }
+
+        let token = match &session.token {
+            Some(a) => a.clone(),
+            None => {
+                return Err(ServiceAccountFailed::MissingToken);
+            }
+        };
+
+        // Extract the original super key that was used to access the user
+        let master_key = match self.master_key() {
+            Some(a) => a,
+            None => {
+                return Err(ServiceAccountFailed::NoMasterKey);
+            }
+        };
+        let super_key = token.unwrap(&master_key)?;
+        let mut super_session = self.master_session.clone();
+        super_session.user.add_read_key(&super_key);
+
+        let chain_key = chain_key_4hex(identity.as_str(), Some("redo"));
+        let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
+        let dio = chain.dio(&super_session).await;
+        let user = match dio.load::<User>(&PrimaryKey::from(identity.clone())).await {
+            Ok(a) => a,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                return Err(ServiceAccountFailed::NotFound(identity));
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        // The session must hold the keys of the user
+        let nominal_write = user.nominal_write.hash();
+        if session
+            .write_keys(AteSessionKeyCategory::AllKeys)
+            .any(|k| k.as_public_key().hash() == nominal_write)
+            == false
+        {
+            warn!("service account management denied ({}) - wrong session", identity);
+            return Err(ServiceAccountFailed::MissingToken);
+        }
+        if let Some(sudo) = sudo {
+            let sudo_write = user.sudo_write.hash();
+            if sudo
+                .write_keys(AteSessionKeyCategory::UpperKeys)
+                .any(|k| k.as_public_key().hash() == sudo_write)
+                == false
+            {
+                warn!("service account management denied ({}) - not elevated", identity);
+                return Err(ServiceAccountFailed::NotElevated);
+            }
+        }
+        Ok(identity)
+    }
+}
+
+fn utc_now() -> chrono::DateTime<chrono::Utc> {
+    let local_now = chrono::Local::now();
+    local_now.with_timezone(&chrono::Utc)
+}
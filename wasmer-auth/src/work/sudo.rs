@@ -25,6 +25,29 @@ impl AuthService {
     pub async fn process_sudo(
         self: Arc<Self>,
         request: SudoRequest,
+    ) -> Result<SudoResponse, SudoFailed> {
+        let identity = request.session.identity().to_string();
+        let ret = self.process_sudo_inner(request).await;
+
+        // Attempts with a valid session are added to the security events of the user
+        let success = match &ret {
+            Ok(_) => true,
+            Err(SudoFailed::WrongCode) => false,
+            Err(_) => return ret,
+        };
+        let _account = self.login_throttle.lock_account(identity.as_str()).await;
+        if let Err(err) = self
+            .record_security_event(identity.as_str(), SecurityEventKind::Sudo, success, None)
+            .await
+        {
+            warn!("failed to record security event ({}) - {:?}", identity, err);
+        }
+        ret
+    }
+
+    async fn process_sudo_inner(
+        &self,
+        request: SudoRequest,
     ) -> Result<SudoResponse, SudoFailed> {
         info!("sudo attempt: {}", request.session.identity());
 
@@ -66,7 +89,7 @@ impl AuthService {
         // Compute which chain the user should exist within
         let chain_key = chain_key_4hex(identity.as_str(), Some("redo"));
         let chain = self.registry.open(&self.auth_url, &chain_key, true).await?;
-        let dio = chain.dio_full(&super_session).await;
+        let dio = chain.dio_full(&super_session).await?;
 
         // Attempt to load the object (if it fails we will tell the caller)
         let user_key = PrimaryKey::from(identity.clone());
@@ -96,6 +119,17 @@ impl AuthService {
                 warn!("login attempt denied ({}) - unverified", identity);
                 return Err(SudoFailed::Unverified(identity));
             }
+            UserStatus::PendingDeletion(erase_after) => {
+                let local_now = chrono::Local::now();
+                let utc_now = local_now.with_timezone(&chrono::Utc);
+                if erase_after <= utc_now {
+                    warn!("login attempt denied ({}) - account erased", identity);
+                    if let Err(err) = self.erase_user(identity.as_str()).await {
+                        warn!("failed to erase user {} - {:?}", identity, err);
+                    }
+                    return Err(SudoFailed::UserNotFound(identity));
+                }
+            }
             UserStatus::Nominal => {}
         };
 
@@ -160,7 +194,9 @@ impl AuthService {
                 if sudo.failed_attempts > 0 {
                     sudo.as_mut().failed_attempts = 0;
                 }
-                user.status = UserStatus::Nominal;
+                if let UserStatus::Locked(_) = user.status {
+                    user.status = UserStatus::Nominal;
+                }
 
                 // Add the extra authentication objects from the sudo
                 compute_sudo_auth(&sudo.take(), request.session.clone())
@@ -43,6 +43,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 async fn find(fs: &FileSystem, path: &str, file: &str) -> FsResult<()> {
+    // Backends that can search by themselves save us walking every directory
+    if let Some(mut task) = fs.search(Path::new(path), file, None, true).await? {
+        while let Some(batch) = task.next().await {
+            for entry in batch {
+                if entry.metadata.map(|a| a.ft.file).unwrap_or(false) {
+                    println!("{}", entry.path);
+                }
+            }
+        }
+        task.wait().await?;
+        return Ok(());
+    }
+
     let mut work = VecDeque::new();
     work.push_back(path.to_string());
 
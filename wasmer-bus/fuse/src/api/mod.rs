@@ -1,12 +1,16 @@
 use serde::*;
 use std::io;
 use std::sync::Arc;
+use std::time::Duration;
+use wasmer_bus::abi::ApplicationError;
+use wasmer_bus::abi::BusError;
 #[allow(unused_imports)]
 use wasmer_bus::macros::*;
 
 #[wasmer_bus(format = "json")]
 pub trait Fuse {
     async fn mount(&self, name: String) -> Arc<dyn FileSystem>;
+    async fn mount_with_options(&self, name: String, options: MountOptions) -> Arc<dyn FileSystem>;
 }
 
 #[wasmer_bus(format = "json")]
@@ -20,6 +24,48 @@ pub trait FileSystem {
     async fn read_metadata(&self, path: String) -> FsResult<Metadata>;
     async fn read_symlink_metadata(&self, path: String) -> FsResult<Metadata>;
     async fn open(&self, path: String, options: OpenOptions) -> Arc<dyn OpenedFile>;
+    async fn copy(
+        &self,
+        from: String,
+        to: String,
+        recursive: bool,
+        progress: impl Fn(CopyProgress),
+        finished: impl Fn(FsResult<CopyProgress>),
+    ) -> Arc<dyn FileCopy>;
+    async fn search(
+        &self,
+        root: String,
+        glob: String,
+        max_results: Option<u64>,
+        return_metadata: bool,
+        found: impl Fn(Vec<DirEntry>),
+        finished: impl Fn(FsResult<SearchSummary>),
+    ) -> Arc<dyn FileSearch>;
+    async fn search_content(
+        &self,
+        root: String,
+        glob: String,
+        pattern: String,
+        regex: bool,
+        max_matches: Option<u64>,
+        found: impl Fn(Vec<SearchMatch>),
+        finished: impl Fn(FsResult<SearchSummary>),
+    ) -> Arc<dyn FileSearch>;
+}
+
+/// Copy that is running on the other side of the bus (dropping it or
+/// calling `cancel` stops the copy at the next block)
+#[wasmer_bus(format = "json")]
+pub trait FileCopy {
+    async fn cancel(&self);
+}
+
+/// Search that is walking the file system on the other side of the bus,
+/// the results are streamed back in batches that are ordered by path
+/// (dropping it or calling `cancel` stops the walk)
+#[wasmer_bus(format = "json")]
+pub trait FileSearch {
+    async fn cancel(&self);
 }
 
 #[wasmer_bus(format = "json")]
@@ -27,6 +73,8 @@ pub trait OpenedFile {
     async fn meta(&self) -> FsResult<Metadata>;
     async fn unlink(&self) -> FsResult<()>;
     async fn set_len(&self, len: u64) -> FsResult<()>;
+    async fn lock(&self, options: LockOptions) -> FsResult<()>;
+    async fn unlock(&self) -> FsResult<()>;
     async fn io(&self) -> Arc<dyn FileIO>;
 }
 
@@ -38,6 +86,44 @@ pub trait FileIO {
     async fn read(&self, len: u64) -> FsResult<Vec<u8>>;
 }
 
+/// Options that change how a file system behaves for a particular mount
+/// (the defaults are what `mount` uses)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MountOptions {
+    /// Names that differ only by case refer to the same entry
+    #[serde(default)]
+    pub casefold: bool,
+}
+
+/// Progress of a copy, it is reported periodically while the copy runs and
+/// once more when it finishes
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CopyProgress {
+    pub files: u64,
+    pub dirs: u64,
+    pub bytes: u64,
+    /// File that is currently being copied
+    pub current: Option<String>,
+}
+
+/// Line of a file that matched a content search
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub path: String,
+    /// Line number of the match (starting from one)
+    pub line_number: u64,
+    pub line: String,
+}
+
+/// Outcome of a search, it is reported once the search finishes
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SearchSummary {
+    /// Number of paths (or lines for a content search) that matched
+    pub matches: u64,
+    /// Set when the search stopped early as it reached its maximum
+    pub truncated: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenOptions {
     pub read: bool,
@@ -48,6 +134,34 @@ pub struct OpenOptions {
     pub truncate: bool,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LockKind {
+    Shared,
+    Exclusive,
+}
+
+/// Advisory lock (flock/fcntl) that is shared by every mount of the same
+/// file system. When `wait` is false a contended lock returns
+/// `FsError::WouldBlock`, otherwise the call waits until the lock is
+/// acquired or the timeout elapses (the file system picks a timeout when
+/// none is given so a lock never waits forever).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockOptions {
+    pub kind: LockKind,
+    pub wait: bool,
+    pub timeout: Option<Duration>,
+}
+
+impl Default for LockOptions {
+    fn default() -> Self {
+        LockOptions {
+            kind: LockKind::Exclusive,
+            wait: false,
+            timeout: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SeekFrom {
     Start(u64),
@@ -69,6 +183,7 @@ pub struct FileType {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Metadata {
     pub ft: FileType,
+    /// Timestamps are in nanoseconds since the epoch
     pub accessed: u64,
     pub created: u64,
     pub modified: u64,
@@ -112,6 +227,7 @@ pub enum FsError {
     WouldBlock,
     WriteZero,
     DirectoryNotEmpty,
+    StorageFull,
     UnknownError,
 }
 
@@ -142,6 +258,7 @@ impl std::fmt::Display for FsError {
             FsError::WouldBlock => write!(f, "call would block"),
             FsError::WriteZero => write!(f, "write zero"),
             FsError::DirectoryNotEmpty => write!(f, "directory is not empty"),
+            FsError::StorageFull => write!(f, "no space left on device"),
             FsError::UnknownError => write!(f, "unknown error"),
         }
     }
@@ -167,6 +284,7 @@ impl From<io::Error> for FsError {
             io::ErrorKind::UnexpectedEof => FsError::UnexpectedEof,
             io::ErrorKind::WouldBlock => FsError::WouldBlock,
             io::ErrorKind::WriteZero => FsError::WriteZero,
+            io::ErrorKind::StorageFull => FsError::StorageFull,
             io::ErrorKind::Other => FsError::IOError,
             _ => FsError::UnknownError,
         }
@@ -193,6 +311,7 @@ impl Into<io::ErrorKind> for FsError {
             FsError::UnexpectedEof => io::ErrorKind::UnexpectedEof,
             FsError::WouldBlock => io::ErrorKind::WouldBlock,
             FsError::WriteZero => io::ErrorKind::WriteZero,
+            FsError::StorageFull => io::ErrorKind::StorageFull,
             FsError::IOError => io::ErrorKind::Other,
             _ => io::ErrorKind::Other,
         }
@@ -214,6 +333,23 @@ impl Into<Box<dyn std::error::Error>> for FsError {
     }
 }
 
+impl From<BusError> for FsError {
+    fn from(_err: BusError) -> FsError {
+        FsError::IOError
+    }
+}
+
+impl From<ApplicationError> for FsError {
+    /// Backends may fail a call with the file system error itself (as an
+    /// application error), anything else is a failure of the bus
+    fn from(err: ApplicationError) -> FsError {
+        match err.downcast::<FsError>() {
+            Some(err) => err,
+            None => FsError::IOError,
+        }
+    }
+}
+
 pub type FsResult<T> = Result<T, FsError>;
 
 /*
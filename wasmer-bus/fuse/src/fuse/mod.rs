@@ -2,15 +2,29 @@
 use std::io;
 use std::path::Path;
 use std::sync::Arc;
+use tokio::sync::watch;
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
 
 use crate::api;
 
+mod search;
+#[cfg(test)]
+mod tests;
+pub use search::*;
+
+/// Name of the bus that the operating system itself listens on
+const HOST_WAPM: &'static str = "os";
+
+pub use crate::api::CopyProgress;
 pub use crate::api::Dir;
+pub use crate::api::DirEntry;
 pub use crate::api::FsError;
 pub use crate::api::FsResult;
 pub use crate::api::Metadata;
+pub use crate::api::MountOptions;
+pub use crate::api::SearchMatch;
+pub use crate::api::SearchSummary;
 
 #[derive(Clone)]
 pub struct FileSystem {
@@ -24,11 +38,30 @@ impl FileSystem {
             .await
             .map_err(|err| {
                 debug!("mount failed - {}", err);
-                FsError::IOError
+                FsError::from(err)
             })?;
         fs.init().await.map_err(|err| {
             debug!("mount init failed - {}", err);
-            FsError::IOError
+            FsError::from(err)
+        })??;
+        Ok(FileSystem { fs })
+    }
+
+    pub async fn mount_with_options(
+        wapm: &str,
+        name: &str,
+        options: MountOptions,
+    ) -> FsResult<FileSystem> {
+        let fs = api::FuseClient::new(wapm)
+            .mount_with_options(name.to_string(), options)
+            .await
+            .map_err(|err| {
+                debug!("mount_with_options failed - {}", err);
+                FsError::from(err)
+            })?;
+        fs.init().await.map_err(|err| {
+            debug!("mount init failed - {}", err);
+            FsError::from(err)
         })??;
         Ok(FileSystem { fs })
     }
@@ -44,11 +77,11 @@ impl FileSystem {
             .await
             .map_err(|err| {
                 debug!("mount_instance failed - {}", err);
-                FsError::IOError
+                FsError::from(err)
             })?;
         fs.init().await.map_err(|err| {
             debug!("mount init failed - {}", err);
-            FsError::IOError
+            FsError::from(err)
         })??;
         Ok(FileSystem { fs })
     }
@@ -61,7 +94,7 @@ impl FileSystem {
             .await
             .map_err(|err| {
                 debug!("read_dir failed - {}", err);
-                FsError::IOError
+                FsError::from(err)
             })?
     }
 
@@ -73,7 +106,7 @@ impl FileSystem {
             .await
             .map_err(|err| {
                 debug!("create_dir failed - {}", err);
-                FsError::IOError
+                FsError::from(err)
             })?
     }
 
@@ -85,7 +118,7 @@ impl FileSystem {
             .await
             .map_err(|err| {
                 debug!("remove_dir failed - {}", err);
-                FsError::IOError
+                FsError::from(err)
             })?
     }
 
@@ -100,7 +133,7 @@ impl FileSystem {
             .await
             .map_err(|err| {
                 debug!("rename failed - {}", err);
-                FsError::IOError
+                FsError::from(err)
             })?
     }
 
@@ -112,7 +145,7 @@ impl FileSystem {
             .await
             .map_err(|err| {
                 debug!("metadata failed - {}", err);
-                FsError::IOError
+                FsError::from(err)
             })?
     }
 
@@ -124,7 +157,7 @@ impl FileSystem {
             .await
             .map_err(|err| {
                 debug!("symlink_metadata failed - {}", err);
-                FsError::IOError
+                FsError::from(err)
             })?
     }
 
@@ -136,15 +169,104 @@ impl FileSystem {
             .await
             .map_err(|err| {
                 debug!("remove_file failed - {}", err);
-                FsError::IOError
+                FsError::from(err)
             })?
     }
 
+    /// Asks the backend to copy a file (or a whole directory when recursive)
+    /// to another path on the same mount without moving the data over the bus
+    pub async fn copy(
+        &self,
+        from: &Path,
+        to: &Path,
+        recursive: bool,
+        progress: impl Fn(CopyProgress) + Send + Sync + 'static,
+    ) -> FsResult<CopyTask> {
+        trace!("copy: from={}, to={}, recursive={}", from.display(), to.display(), recursive);
+
+        CopyTask::start(self.fs.clone(), from, to, recursive, progress).await
+    }
+
     pub fn new_open_options(&self) -> OpenOptions {
         OpenOptions::new(self.clone())
     }
 }
 
+/// Asks the operating system to copy between two paths that this process
+/// can see, even when they are on different mounts (paths must be absolute).
+/// Hosts that do not support copies fail the call, in which case the files
+/// should be copied the normal way.
+pub async fn host_copy(
+    from: &Path,
+    to: &Path,
+    recursive: bool,
+    progress: impl Fn(CopyProgress) + Send + Sync + 'static,
+) -> FsResult<CopyTask> {
+    trace!("host_copy: from={}, to={}, recursive={}", from.display(), to.display(), recursive);
+
+    let fs: Arc<dyn api::FileSystem> = Arc::new(api::FileSystemClient::new(HOST_WAPM));
+    CopyTask::start(fs, from, to, recursive, progress).await
+}
+
+/// Copy that is running on the host or on the backend of a mount, dropping
+/// it before it finishes will cancel the copy
+pub struct CopyTask {
+    task: Arc<dyn api::FileCopy>,
+    finished: watch::Receiver<Option<FsResult<CopyProgress>>>,
+}
+
+impl CopyTask {
+    async fn start(
+        fs: Arc<dyn api::FileSystem>,
+        from: &Path,
+        to: &Path,
+        recursive: bool,
+        progress: impl Fn(CopyProgress) + Send + Sync + 'static,
+    ) -> FsResult<CopyTask> {
+        let (finished_tx, finished_rx) = watch::channel(None);
+        let on_finished = Box::new(move |result: FsResult<CopyProgress>| {
+            let _ = finished_tx.send(Some(result));
+        });
+
+        let task = fs
+            .copy(
+                from.to_string_lossy().to_string(),
+                to.to_string_lossy().to_string(),
+                recursive,
+                Box::new(progress),
+                on_finished,
+            )
+            .await
+            .map_err(|err| {
+                debug!("copy failed - {}", err);
+                FsError::from(err)
+            })?;
+
+        Ok(CopyTask {
+            task,
+            finished: finished_rx,
+        })
+    }
+
+    /// Stops the copy (the files that were already copied are left behind)
+    pub async fn cancel(&self) -> FsResult<()> {
+        self.task.cancel().await.map_err(FsError::from)
+    }
+
+    /// Waits for the copy to finish and returns the final progress
+    pub async fn wait(mut self) -> FsResult<CopyProgress> {
+        loop {
+            if let Some(ret) = self.finished.borrow().clone() {
+                return ret;
+            }
+            if self.finished.changed().await.is_err() {
+                debug!("copy aborted before it finished");
+                return Err(FsError::ConnectionAborted);
+            }
+        }
+    }
+}
+
 pub struct OpenOptionsConfig {
     read: bool,
     write: bool,
@@ -229,13 +351,13 @@ impl OpenOptions {
             .await
             .map_err(|err| {
                 debug!("open failed - {}", err);
-                FsError::IOError
+                FsError::from(err)
             })?;
 
-        let meta = fd.meta().await.map_err(|_| FsError::IOError)??;
+        let meta = fd.meta().await.map_err(FsError::from)??;
 
         Ok(VirtualFile {
-            io: fd.io().await.map_err(|_| FsError::IOError)?,
+            io: fd.io().await.map_err(FsError::from)?,
             fs: self.fs.clone(),
             fd,
             meta,
@@ -323,7 +445,7 @@ impl VirtualFile {
             .fd
             .set_len(new_size)
             .await
-            .map_err(|_| FsError::IOError)?;
+            .map_err(FsError::from)?;
         result?;
 
         self.meta.len = new_size;
@@ -331,7 +453,15 @@ impl VirtualFile {
     }
 
     pub async fn unlink(&mut self) -> FsResult<()> {
-        self.fd.unlink().await.map_err(|_| FsError::IOError)?
+        self.fd.unlink().await.map_err(FsError::from)?
+    }
+
+    pub async fn lock(&mut self, options: api::LockOptions) -> FsResult<()> {
+        self.fd.lock(options).await.map_err(FsError::from)?
+    }
+
+    pub async fn unlock(&mut self) -> FsResult<()> {
+        self.fd.unlock().await.map_err(FsError::from)?
     }
 
     pub async fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
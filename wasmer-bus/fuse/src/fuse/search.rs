@@ -0,0 +1,465 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+use wasmer_bus::abi::BusError;
+
+use super::DirEntry;
+use super::FileSystem;
+use super::FsError;
+use super::FsResult;
+use super::SearchMatch;
+use super::SearchSummary;
+use super::HOST_WAPM;
+use crate::api;
+
+/// Number of results that are sent back to the caller at a time
+pub const SEARCH_BATCH_SIZE: usize = 256;
+
+/// Number of bytes that are read at a time when searching the content of a file
+pub const SEARCH_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Longest line that is kept when data is split into lines, the rest of a
+/// longer line is dropped (so it can not be matched) rather than buffered
+pub const LINE_MAX_LEN: usize = 1024 * 1024;
+
+/// Pattern that the paths of a search are matched against. `*` and `?`
+/// match within one component of a path, `**` matches any number of
+/// components and `[...]` matches one character of a set (or of a range).
+/// Patterns without a `/` are matched against the name of the entry (like
+/// `find -name`) while the others are matched against the path relative to
+/// the root of the search, an empty pattern matches everything.
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pattern: Vec<char>,
+    anchored: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Glob {
+        let pattern = pattern.trim_start_matches('/');
+        Glob {
+            pattern: pattern.chars().collect(),
+            anchored: pattern.contains('/'),
+        }
+    }
+
+    /// Checks the path of an entry (relative to the root of the search)
+    pub fn is_match(&self, path: &str) -> bool {
+        if self.pattern.is_empty() {
+            return true;
+        }
+        let path = path.trim_start_matches('/');
+        let path = match self.anchored {
+            true => path,
+            false => path.rsplit('/').next().unwrap_or(path),
+        };
+        let path = path.chars().collect::<Vec<_>>();
+        GlobMatcher::new(&self.pattern[..], &path[..]).matches(0, 0)
+    }
+}
+
+/// Matches a pattern against a path, the outcome of every position in the
+/// pattern and the path is remembered so that patterns with many wildcards
+/// take polynomial rather than exponential time
+struct GlobMatcher<'a> {
+    p: &'a [char],
+    s: &'a [char],
+    memo: Vec<Option<bool>>,
+}
+
+impl<'a> GlobMatcher<'a> {
+    fn new(p: &'a [char], s: &'a [char]) -> GlobMatcher<'a> {
+        GlobMatcher {
+            p,
+            s,
+            memo: vec![None; (p.len() + 1) * (s.len() + 1)],
+        }
+    }
+
+    fn matches(&mut self, pi: usize, si: usize) -> bool {
+        let idx = pi * (self.s.len() + 1) + si;
+        if let Some(ret) = self.memo[idx] {
+            return ret;
+        }
+        let ret = self.matches_inner(pi, si);
+        self.memo[idx] = Some(ret);
+        ret
+    }
+
+    fn matches_inner(&mut self, pi: usize, si: usize) -> bool {
+        let (p, s) = (self.p, self.s);
+        match p.get(pi) {
+            None => si >= s.len(),
+            Some('*') if p.get(pi + 1) == Some(&'*') => {
+                // `**/` may also match no directories at all
+                let rest = pi + 2;
+                if p.get(rest) == Some(&'/') && self.matches(rest + 1, si) {
+                    return true;
+                }
+                (si..=s.len()).any(|i| self.matches(rest, i))
+            }
+            Some('*') => {
+                for i in si..=s.len() {
+                    if self.matches(pi + 1, i) {
+                        return true;
+                    }
+                    if i < s.len() && s[i] == '/' {
+                        break;
+                    }
+                }
+                false
+            }
+            Some('?') => match s.get(si) {
+                Some(c) if *c != '/' => self.matches(pi + 1, si + 1),
+                _ => false,
+            },
+            Some('[') => match (s.get(si), glob_class(&p[pi + 1..], s.get(si).cloned())) {
+                (Some(_), Some((true, len))) => self.matches(pi + 1 + len, si + 1),
+                (_, Some(_)) => false,
+                // An unterminated set is just a literal bracket
+                (Some('['), None) => self.matches(pi + 1, si + 1),
+                (_, None) => false,
+            },
+            Some('\\') if pi + 1 < p.len() => {
+                s.get(si) == Some(&p[pi + 1]) && self.matches(pi + 2, si + 1)
+            }
+            Some(c) => s.get(si) == Some(c) && self.matches(pi + 1, si + 1),
+        }
+    }
+}
+
+/// Matches a character against a set (the pattern starts after the opening
+/// bracket), returns if it matched and how much of the pattern the set used
+fn glob_class(p: &[char], c: Option<char>) -> Option<(bool, usize)> {
+    let mut i = 0;
+    let negate = matches!(p.first(), Some('!') | Some('^'));
+    if negate {
+        i += 1;
+    }
+    let mut found = false;
+    let mut first = true;
+    loop {
+        let a = *p.get(i)?;
+        if a == ']' && first == false {
+            break;
+        }
+        first = false;
+        match (p.get(i + 1), p.get(i + 2)) {
+            (Some('-'), Some(b)) if *b != ']' => {
+                if let Some(c) = c {
+                    found |= a <= c && c <= *b;
+                }
+                i += 3;
+            }
+            _ => {
+                found |= Some(a) == c;
+                i += 1;
+            }
+        }
+    }
+    let found = found != negate && c.is_some() && c != Some('/');
+    Some((found, i + 1))
+}
+
+/// Order in which a search visits the entries under its root, that is depth
+/// first with the children of every directory in order of their names so
+/// that the results come out sorted by path (component by component). The
+/// file systems that search by themselves walk their directories with this
+/// so that they all return the results in the same order.
+#[derive(Debug)]
+pub struct SearchWalk<M> {
+    root: PathBuf,
+    glob: Glob,
+    todo: Vec<(PathBuf, M)>,
+}
+
+impl<M> SearchWalk<M> {
+    /// Starts a walk from the root, the entries are either the children of
+    /// the root (when it is a directory) or the root itself
+    pub fn new(root: &Path, glob: &str, entries: Vec<(PathBuf, M)>) -> SearchWalk<M> {
+        let mut ret = SearchWalk {
+            root: root.to_path_buf(),
+            glob: Glob::new(glob),
+            todo: Vec::new(),
+        };
+        ret.descend(entries);
+        ret
+    }
+
+    /// Returns the next entry to visit and whether it matches the glob
+    pub fn next(&mut self) -> Option<(PathBuf, M, bool)> {
+        let (path, meta) = self.todo.pop()?;
+        let relative = path.strip_prefix(&self.root).unwrap_or(path.as_path());
+        let relative = match relative.as_os_str().is_empty() {
+            true => path.file_name().map(Path::new).unwrap_or(relative),
+            false => relative,
+        };
+        let is_match = self.glob.is_match(relative.to_string_lossy().as_ref());
+        Some((path, meta, is_match))
+    }
+
+    /// Visits the children of the directory that was just returned by `next`
+    /// before anything else (the same name listed twice is visited once)
+    pub fn descend(&mut self, mut children: Vec<(PathBuf, M)>) {
+        children.sort_by(|a, b| a.0.cmp(&b.0));
+        children.dedup_by(|a, b| a.0 == b.0);
+        children.reverse();
+        self.todo.append(&mut children);
+    }
+}
+
+/// Splits the data of a file into lines as it is read block by block (so
+/// that its content can be searched without holding all of it in memory),
+/// lines longer than `LINE_MAX_LEN` are cut short
+#[derive(Debug, Default)]
+pub struct LineSplitter {
+    pending: Vec<u8>,
+    line_number: u64,
+}
+
+impl LineSplitter {
+    pub fn new() -> LineSplitter {
+        LineSplitter::default()
+    }
+
+    /// Returns the lines that are complete so far along with their line
+    /// numbers, an empty block marks the end of the file (which completes
+    /// the last line)
+    pub fn push(&mut self, data: &[u8]) -> Vec<(u64, String)> {
+        let mut ret = Vec::new();
+        if data.is_empty() {
+            if self.pending.len() > 0 {
+                ret.push(self.complete());
+            }
+            return ret;
+        }
+
+        let mut data = data;
+        while let Some(n) = data.iter().position(|a| *a == b'\n') {
+            self.append(&data[..n]);
+            ret.push(self.complete());
+            data = &data[n + 1..];
+        }
+        self.append(data);
+        ret
+    }
+
+    fn append(&mut self, data: &[u8]) {
+        let room = LINE_MAX_LEN.saturating_sub(self.pending.len());
+        self.pending.extend_from_slice(&data[..data.len().min(room)]);
+    }
+
+    fn complete(&mut self) -> (u64, String) {
+        self.line_number += 1;
+        let line = String::from_utf8_lossy(&self.pending[..]);
+        let line = line.trim_end_matches('\r').to_string();
+        self.pending.clear();
+        (self.line_number, line)
+    }
+}
+
+enum SearchEvent<T> {
+    Found(Vec<T>),
+    Finished(FsResult<SearchSummary>),
+}
+
+/// Search that is running on the host or on the backend of a mount, the
+/// results arrive in batches that are ordered by path (component by
+/// component) and dropping it before it finishes will cancel the search
+pub struct SearchTask<T> {
+    task: Arc<dyn api::FileSearch>,
+    events: mpsc::UnboundedReceiver<SearchEvent<T>>,
+    summary: Option<FsResult<SearchSummary>>,
+}
+
+impl<T> SearchTask<T>
+where
+    T: Send + 'static,
+{
+    fn channel() -> (
+        Box<dyn Fn(Vec<T>) + Send + Sync + 'static>,
+        Box<dyn Fn(FsResult<SearchSummary>) + Send + Sync + 'static>,
+        mpsc::UnboundedReceiver<SearchEvent<T>>,
+    ) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let on_found = {
+            let tx = tx.clone();
+            Box::new(move |batch: Vec<T>| {
+                let _ = tx.send(SearchEvent::Found(batch));
+            })
+        };
+        let on_finished = Box::new(move |result: FsResult<SearchSummary>| {
+            let _ = tx.send(SearchEvent::Finished(result));
+        });
+        (on_found, on_finished, rx)
+    }
+
+    /// Backends that can not search by themselves return None (in which
+    /// case the caller should walk the directories the normal way)
+    fn started(
+        ret: Result<Arc<dyn api::FileSearch>, BusError>,
+        events: mpsc::UnboundedReceiver<SearchEvent<T>>,
+    ) -> FsResult<Option<SearchTask<T>>> {
+        match ret {
+            Ok(task) => Ok(Some(SearchTask {
+                task,
+                events,
+                summary: None,
+            })),
+            Err(BusError::InvalidTopic) | Err(BusError::Unsupported) => {
+                debug!("search is not supported by the backend");
+                Ok(None)
+            }
+            Err(err) => {
+                debug!("search failed - {}", err);
+                Err(FsError::from(err))
+            }
+        }
+    }
+
+    /// Returns the next batch of results or None once the search finished
+    pub async fn next(&mut self) -> Option<Vec<T>> {
+        if self.summary.is_some() {
+            return None;
+        }
+        match self.events.recv().await {
+            Some(SearchEvent::Found(batch)) => Some(batch),
+            Some(SearchEvent::Finished(ret)) => {
+                self.summary = Some(ret);
+                None
+            }
+            None => {
+                debug!("search aborted before it finished");
+                self.summary = Some(Err(FsError::ConnectionAborted));
+                None
+            }
+        }
+    }
+
+    /// Stops the search (the results already sent may still be read)
+    pub async fn cancel(&self) -> FsResult<()> {
+        self.task.cancel().await.map_err(FsError::from)
+    }
+
+    /// Waits for the search to finish and returns its summary, the results
+    /// that were not yet read are discarded
+    pub async fn wait(mut self) -> FsResult<SearchSummary> {
+        while self.next().await.is_some() {}
+        self.summary
+            .take()
+            .unwrap_or(Err(FsError::ConnectionAborted))
+    }
+}
+
+async fn start_search(
+    fs: Arc<dyn api::FileSystem>,
+    root: &Path,
+    glob: &str,
+    max_results: Option<u64>,
+    return_metadata: bool,
+) -> FsResult<Option<SearchTask<DirEntry>>> {
+    let (on_found, on_finished, events) = SearchTask::channel();
+    let ret = fs
+        .search(
+            root.to_string_lossy().to_string(),
+            glob.to_string(),
+            max_results,
+            return_metadata,
+            on_found,
+            on_finished,
+        )
+        .await;
+    SearchTask::started(ret, events)
+}
+
+async fn start_search_content(
+    fs: Arc<dyn api::FileSystem>,
+    root: &Path,
+    glob: &str,
+    pattern: &str,
+    regex: bool,
+    max_matches: Option<u64>,
+) -> FsResult<Option<SearchTask<SearchMatch>>> {
+    let (on_found, on_finished, events) = SearchTask::channel();
+    let ret = fs
+        .search_content(
+            root.to_string_lossy().to_string(),
+            glob.to_string(),
+            pattern.to_string(),
+            regex,
+            max_matches,
+            on_found,
+            on_finished,
+        )
+        .await;
+    SearchTask::started(ret, events)
+}
+
+impl FileSystem {
+    /// Asks the backend to find the paths under the root that match the
+    /// glob without walking the directories over the bus, returns None if
+    /// the backend can not search by itself
+    pub async fn search(
+        &self,
+        root: &Path,
+        glob: &str,
+        max_results: Option<u64>,
+        return_metadata: bool,
+    ) -> FsResult<Option<SearchTask<DirEntry>>> {
+        trace!("search: root={}, glob={}", root.display(), glob);
+
+        start_search(self.fs.clone(), root, glob, max_results, return_metadata).await
+    }
+
+    /// Asks the backend to find the lines of the files under the root (that
+    /// match the glob) which contain the pattern, the pattern is either
+    /// literal text or a regular expression. Returns None if the backend can
+    /// not search by itself.
+    pub async fn search_content(
+        &self,
+        root: &Path,
+        glob: &str,
+        pattern: &str,
+        regex: bool,
+        max_matches: Option<u64>,
+    ) -> FsResult<Option<SearchTask<SearchMatch>>> {
+        trace!("search_content: root={}, glob={}, pattern={}", root.display(), glob, pattern);
+
+        start_search_content(self.fs.clone(), root, glob, pattern, regex, max_matches).await
+    }
+}
+
+/// Asks the operating system to search under a path that this process can
+/// see (the path must be absolute), the mounts that can search by themselves
+/// do so while the others are walked by the host. Returns None if the host
+/// does not support searches, in which case the directories should be
+/// walked the normal way.
+pub async fn host_search(
+    root: &Path,
+    glob: &str,
+    max_results: Option<u64>,
+    return_metadata: bool,
+) -> FsResult<Option<SearchTask<DirEntry>>> {
+    trace!("host_search: root={}, glob={}", root.display(), glob);
+
+    let fs: Arc<dyn api::FileSystem> = Arc::new(api::FileSystemClient::new(HOST_WAPM));
+    start_search(fs, root, glob, max_results, return_metadata).await
+}
+
+/// Asks the operating system to search the content of the files under a
+/// path that this process can see (see `host_search`)
+pub async fn host_search_content(
+    root: &Path,
+    glob: &str,
+    pattern: &str,
+    regex: bool,
+    max_matches: Option<u64>,
+) -> FsResult<Option<SearchTask<SearchMatch>>> {
+    trace!("host_search_content: root={}, glob={}, pattern={}", root.display(), glob, pattern);
+
+    let fs: Arc<dyn api::FileSystem> = Arc::new(api::FileSystemClient::new(HOST_WAPM));
+    start_search_content(fs, root, glob, pattern, regex, max_matches).await
+}
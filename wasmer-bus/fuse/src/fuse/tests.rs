@@ -0,0 +1,139 @@
+#![cfg(test)]
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use super::*;
+
+#[test]
+fn test_glob_name() {
+    let glob = Glob::new("*.rs");
+    assert!(glob.is_match("main.rs"));
+    assert!(glob.is_match("src/fuse/search.rs"));
+    assert!(!glob.is_match("main.rs.bak"));
+    assert!(!glob.is_match("src/main.rs/child"));
+
+    let glob = Glob::new("");
+    assert!(glob.is_match("anything/at/all"));
+}
+
+#[test]
+fn test_glob_anchored() {
+    let glob = Glob::new("src/*.rs");
+    assert!(glob.is_match("src/main.rs"));
+    assert!(glob.is_match("/src/main.rs"));
+    assert!(!glob.is_match("src/fuse/search.rs"));
+    assert!(!glob.is_match("other/src/main.rs"));
+
+    let glob = Glob::new("src/**/*.rs");
+    assert!(glob.is_match("src/main.rs"));
+    assert!(glob.is_match("src/fuse/search.rs"));
+    assert!(glob.is_match("src/a/b/c/d.rs"));
+    assert!(!glob.is_match("lib/main.rs"));
+}
+
+#[test]
+fn test_glob_class() {
+    let glob = Glob::new("file[0-9].txt");
+    assert!(glob.is_match("file1.txt"));
+    assert!(!glob.is_match("filea.txt"));
+
+    let glob = Glob::new("file[!0-9].txt");
+    assert!(glob.is_match("filea.txt"));
+    assert!(!glob.is_match("file1.txt"));
+
+    let glob = Glob::new("a?c");
+    assert!(glob.is_match("abc"));
+    assert!(!glob.is_match("ac"));
+
+    let glob = Glob::new("[abc");
+    assert!(glob.is_match("[abc"));
+
+    let glob = Glob::new("\\*.txt");
+    assert!(glob.is_match("*.txt"));
+    assert!(!glob.is_match("a.txt"));
+}
+
+#[test]
+fn test_glob_backtracking() {
+    let glob = Glob::new("a*a*a*a*a*a*a*a*a*a*b");
+    let path = "a".repeat(200);
+
+    let start = Instant::now();
+    assert!(!glob.is_match(path.as_str()));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    let glob = Glob::new("**/**/**/**/**/**/x");
+    let path = vec!["a"; 50].join("/");
+    let start = Instant::now();
+    assert!(!glob.is_match(path.as_str()));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_line_splitter() {
+    let mut lines = LineSplitter::new();
+    assert_eq!(lines.push(b"first\r\nsec"), vec![(1, "first".to_string())]);
+    assert_eq!(lines.push(b"ond\n\nthi"), vec![(2, "second".to_string()), (3, "".to_string())]);
+    assert_eq!(lines.push(b"rd"), vec![]);
+    assert_eq!(lines.push(b""), vec![(4, "third".to_string())]);
+    assert_eq!(lines.push(b""), vec![]);
+}
+
+#[test]
+fn test_line_splitter_long_line() {
+    let mut lines = LineSplitter::new();
+    let block = vec![b'a'; LINE_MAX_LEN / 2 + 1];
+    assert_eq!(lines.push(&block[..]), vec![]);
+    assert_eq!(lines.push(&block[..]), vec![]);
+    assert_eq!(lines.push(&block[..]), vec![]);
+
+    let ret = lines.push(b"b\nnext\n");
+    assert_eq!(ret.len(), 2);
+    assert_eq!(ret[0].0, 1);
+    assert_eq!(ret[0].1.len(), LINE_MAX_LEN);
+    assert_eq!(ret[1], (2, "next".to_string()));
+}
+
+#[test]
+fn test_search_walk() {
+    let dirs = |path: &str| -> Vec<(PathBuf, bool)> {
+        let names: &[(&str, bool)] = match path {
+            "/root" => &[("b", true), ("a.rs", false), ("c.rs", false), ("a.rs", false)],
+            "/root/b" => &[("z.rs", false), ("y", false)],
+            _ => &[],
+        };
+        names
+            .iter()
+            .map(|(name, dir)| (Path::new(path).join(name), *dir))
+            .collect()
+    };
+
+    let mut walk = SearchWalk::new(Path::new("/root"), "*.rs", dirs("/root"));
+    let mut visited = Vec::new();
+    while let Some((path, is_dir, is_match)) = walk.next() {
+        visited.push((path.to_string_lossy().to_string(), is_match));
+        if is_dir {
+            walk.descend(dirs(path.to_string_lossy().as_ref()));
+        }
+    }
+    assert_eq!(
+        visited,
+        vec![
+            ("/root/a.rs".to_string(), true),
+            ("/root/b".to_string(), false),
+            ("/root/b/y".to_string(), false),
+            ("/root/b/z.rs".to_string(), true),
+            ("/root/c.rs".to_string(), true),
+        ]
+    );
+
+    let mut walk = SearchWalk::new(
+        Path::new("/root/a.rs"),
+        "a.rs",
+        vec![(PathBuf::from("/root/a.rs"), ())],
+    );
+    assert_eq!(walk.next(), Some((PathBuf::from("/root/a.rs"), (), true)));
+    assert_eq!(walk.next(), None);
+}
@@ -1,10 +1,26 @@
+pub use crate::fuse::host_copy;
+pub use crate::fuse::host_search;
+pub use crate::fuse::host_search_content;
+pub use crate::fuse::CopyProgress;
+pub use crate::fuse::CopyTask;
 pub use crate::fuse::Dir;
+pub use crate::fuse::DirEntry;
 pub use crate::fuse::FileSystem;
 pub use crate::fuse::FsError;
 pub use crate::fuse::FsResult;
+pub use crate::fuse::Glob;
+pub use crate::fuse::LineSplitter;
 pub use crate::fuse::Metadata;
+pub use crate::fuse::MountOptions;
 pub use crate::fuse::OpenOptions;
 pub use crate::fuse::OpenOptionsConfig;
+pub use crate::fuse::SearchMatch;
+pub use crate::fuse::SearchSummary;
+pub use crate::fuse::SearchTask;
+pub use crate::fuse::SearchWalk;
+pub use crate::fuse::LINE_MAX_LEN;
+pub use crate::fuse::SEARCH_BATCH_SIZE;
+pub use crate::fuse::SEARCH_BLOCK_SIZE;
 pub use crate::fuse::VirtualFile;
 pub use async_trait::async_trait;
 pub use wasmer_bus;
@@ -50,6 +50,7 @@ pub(crate) struct CallResult
 #[derive(Debug)]
 pub struct CallState {
     pub(crate) result: Option<Result<CallResult, BusError>>,
+    pub(crate) application: Option<ApplicationError>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -140,6 +141,7 @@ impl Call {
             ctx: CallContext::NewBusCall { wapm, instance },
             state: Arc::new(Mutex::new(CallState {
                 result: None,
+                application: None,
             })),
             callbacks: Default::default(),
             handle: None,
@@ -155,6 +157,7 @@ impl Call {
             ctx: CallContext::SubCall { parent },
             state: Arc::new(Mutex::new(CallState {
                 result: None,
+                application: None,
             })),
             callbacks: Default::default(),
             handle: None,
@@ -165,10 +168,22 @@ impl Call {
     pub fn handle(&self) -> Option<CallHandle> {
         self.handle.clone()
     }
+
+    /// Returns the error raised by the application when the call failed
+    /// with `BusError::Application`
+    pub fn application_error(&self) -> Option<ApplicationError> {
+        let state = self.state.lock().unwrap();
+        state.application.clone()
+    }
 }
 
 impl CallOps for Call {
     fn data(&self, data: Vec<u8>, format: SerializationFormat) {
+        if let Some(err) = ApplicationError::from_reply(&data[..]) {
+            self.state.lock().unwrap().application = Some(err);
+            self.error(BusError::Application);
+            return;
+        }
         let mut state = self.state.lock().unwrap();
         state.result = Some(Ok(CallResult {
             data,
@@ -414,6 +429,25 @@ where
         panic!("spawning of calls is not supported on this platform");
     }
 
+    /// Waits for the call to complete and deserializes the error raised by
+    /// the application (if it was of the type `E`) so that it can be
+    /// returned to the caller rather than a `BusError::Application`
+    pub async fn join_application<E>(self) -> Result<Result<T, E>, BusError>
+    where
+        E: de::DeserializeOwned,
+    {
+        let call = self.call.clone();
+        match self.await {
+            Ok(res) => Ok(Ok(res)),
+            Err(BusError::Application) => call
+                .application_error()
+                .and_then(|err| err.downcast::<E>())
+                .map(|err| Ok(Err(err)))
+                .unwrap_or(Err(BusError::Application)),
+            Err(err) => Err(err),
+        }
+    }
+
     /// Tries to get the result of the call to the server but will not
     /// block the execution
     pub fn try_wait(&mut self) -> Result<Option<T>, BusError>
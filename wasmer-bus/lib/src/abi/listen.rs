@@ -6,6 +6,7 @@ use std::sync::Arc;
 use tracing::{debug, error, info, trace, warn};
 use wasmer_bus_types::SerializationFormat;
 
+use crate::abi::ApplicationError;
 use crate::abi::BusError;
 use super::CallHandle;
 
@@ -13,6 +14,8 @@ pub enum ListenAction
 {
     Response(Vec<u8>),
     Fault(BusError),
+    /// Fails the call with an error raised by the application
+    Application(ApplicationError),
     Detach
 }
 
@@ -20,6 +23,8 @@ pub enum ListenActionTyped<T>
 {
     Response(T),
     Fault(BusError),
+    /// Fails the call with an error raised by the application
+    Application(ApplicationError),
     Detach
 }
 
@@ -69,6 +74,9 @@ impl ListenService {
             ListenAction::Fault(err) => {
                 crate::abi::syscall::call_fault(handle, err);
             }
+            ListenAction::Application(err) => {
+                crate::abi::syscall::call_fault_application(handle, &err);
+            }
             ListenAction::Detach => {
                 leak = true;
             }
@@ -8,6 +8,7 @@ use std::sync::Mutex;
 use tracing::{debug, error, info, trace, warn};
 use wasmer_bus_types::SerializationFormat;
 
+use crate::abi::ApplicationError;
 use crate::abi::BusError;
 use crate::abi::CallHandle;
 
@@ -15,6 +16,8 @@ pub enum RespondAction
 {
     Response(Vec<u8>),
     Fault(BusError),
+    /// Fails the call with an error raised by the application
+    Application(ApplicationError),
     Detach
 }
 
@@ -22,6 +25,8 @@ pub enum RespondActionTyped<T>
 {
     Response(T),
     Fault(BusError),
+    /// Fails the call with an error raised by the application
+    Application(ApplicationError),
     Detach
 }
 
@@ -89,6 +94,9 @@ impl RespondToService {
             RespondAction::Fault(err) => {
                 crate::abi::syscall::call_fault(handle, err);
             }
+            RespondAction::Application(err) => {
+                crate::abi::syscall::call_fault_application(handle, &err);
+            }
             RespondAction::Detach => {
                 leak = true;
             }
@@ -76,6 +76,7 @@ fn convert_err_back(val: BusError) -> wasi::BusError {
         BusInvocationFailed => wasi::BUS_ERROR_BUS_INVOCATION_FAILED,
         AlreadyConsumed => wasi::BUS_ERROR_ALREADY_CONSUMED,
         MemoryAccessViolation => wasi::BUS_ERROR_MEMORY_ACCESS_VIOLATION,
+        Application => wasi::BUS_ERROR_UNKNOWN_ERROR,
         Unknown => wasi::BUS_ERROR_UNKNOWN_ERROR
     }
 }
@@ -323,6 +324,12 @@ pub fn call_fault(handle: CallHandle, error: BusError) {
     }
 }
 
+/// Application errors can not be expressed as an error code so they are
+/// sent as a reply that the caller will decode back into the error
+pub fn call_fault_application(handle: CallHandle, error: &ApplicationError) {
+    call_reply(handle, &error.to_reply()[..], error.format);
+}
+
 pub fn call_reply(
     handle: CallHandle,
     response: &[u8],
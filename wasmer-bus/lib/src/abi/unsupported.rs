@@ -52,6 +52,10 @@ pub fn call_fault(handle: CallHandle, error: BusError) {
     panic!("unsupported on this platform");
 }
 
+pub fn call_fault_application(handle: CallHandle, error: &ApplicationError) {
+    panic!("unsupported on this platform");
+}
+
 pub fn call_reply(
     handle: CallHandle,
     response: &[u8],
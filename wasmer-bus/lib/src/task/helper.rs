@@ -179,6 +179,9 @@ where
                     ListenActionTyped::Fault(err) => {
                         ListenAction::Fault(err)
                     }
+                    ListenActionTyped::Application(err) => {
+                        ListenAction::Application(err)
+                    }
                     ListenActionTyped::Detach => {
                         ListenAction::Detach
                     }
@@ -233,6 +236,7 @@ pub fn respond_to<RES, REQ, F, Fut>(
                         }
                     },
                     RespondActionTyped::Fault(err) => RespondAction::Fault(err),
+                    RespondActionTyped::Application(err) => RespondAction::Application(err),
                     RespondActionTyped::Detach => RespondAction::Detach
                 }
             })
@@ -5,6 +5,7 @@ use std::net::Ipv4Addr;
 use std::net::Ipv6Addr;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use std::ops::Deref;
 use std::collections::BTreeMap;
 #[cfg(feature = "crypto")]
@@ -17,6 +18,7 @@ use derivative::*;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
+use crate::model::AcceptFilter;
 use crate::model::HardwareAddress;
 use crate::model::IpCidr;
 use crate::model::IpRoute;
@@ -42,6 +44,7 @@ pub struct SocketState
     recv_from: mpsc::Sender<EventRecvFrom>,
     error: mpsc::Sender<EventError>,
     accept: mpsc::Sender<EventAccept>,
+    accept_stats: Option<Arc<AcceptStats>>,
 }
 
 #[derive(Debug, Default)]
@@ -100,7 +103,7 @@ impl Port
         Ok(port)
     }
 
-    async fn new_socket(&self, proto: Option<IpProtocol>) -> Socket {
+    async fn new_socket(&self, proto: Option<IpProtocol>, accept_stats: Option<Arc<AcceptStats>>) -> Socket {
         let mut state = self.state.lock().await;
         let sockets = &mut state.sockets;
         let handle = sockets.iter()
@@ -121,6 +124,7 @@ impl Port
             recv_from: tx_recv_from,
             error: tx_error,
             accept: tx_accept,
+            accept_stats: accept_stats.clone(),
         });
 
         let handle = SocketHandle(handle);
@@ -136,11 +140,12 @@ impl Port
             recv_from: rx_recv_from,
             error: rx_error,
             accept: rx_accept,
+            accept_stats,
         }
     }
 
     pub async fn bind_raw(&self) -> io::Result<Socket> {
-        let mut socket = self.new_socket(None).await;
+        let mut socket = self.new_socket(None, None).await;
 
         socket.tx(PortCommand::BindRaw {
             handle: socket.handle,
@@ -151,7 +156,7 @@ impl Port
     }
 
     pub async fn bind_udp(&self, local_addr: SocketAddr) -> io::Result<Socket> {
-        let mut socket = self.new_socket(Some(IpProtocol::Udp)).await;
+        let mut socket = self.new_socket(Some(IpProtocol::Udp), None).await;
 
         socket.tx(PortCommand::BindUdp {
             handle: socket.handle,
@@ -164,7 +169,7 @@ impl Port
     }
 
     pub async fn bind_icmp(&self, local_addr: IpAddr) -> io::Result<Socket> {
-        let mut socket = self.new_socket(Some(IpProtocol::Icmp)).await;
+        let mut socket = self.new_socket(Some(IpProtocol::Icmp), None).await;
 
         socket.tx(PortCommand::BindIcmp {
             handle: socket.handle,
@@ -177,7 +182,7 @@ impl Port
     }
 
     pub async fn bind_dhcp(&self) -> io::Result<Socket> {
-        let mut socket = self.new_socket(Some(IpProtocol::Icmp)).await;
+        let mut socket = self.new_socket(Some(IpProtocol::Icmp), None).await;
 
         socket.tx(PortCommand::BindDhcp {
             handle: socket.handle,
@@ -190,7 +195,7 @@ impl Port
     }
 
     pub async fn connect_tcp(&self, local_addr: SocketAddr, peer_addr: SocketAddr) -> io::Result<Socket> {
-        let mut socket = self.new_socket(Some(IpProtocol::Tcp)).await;
+        let mut socket = self.new_socket(Some(IpProtocol::Tcp), None).await;
 
         socket.tx(PortCommand::ConnectTcp {
             handle: socket.handle,
@@ -205,8 +210,13 @@ impl Port
         Ok(socket)
     }
 
-    pub async fn listen_tcp(&self, listen_addr: SocketAddr) -> io::Result<Socket> {
-        let mut socket = self.new_socket(Some(IpProtocol::Tcp)).await;
+    pub async fn listen_tcp(&self, listen_addr: SocketAddr, filter: &AcceptFilter, accept_stats: Arc<AcceptStats>) -> io::Result<Socket> {
+        let mut socket = self.new_socket(Some(IpProtocol::Tcp), Some(accept_stats)).await;
+
+        // The filter goes first so that no connection slips past it
+        if filter.is_empty() == false {
+            self.set_accept_filter(socket.handle, filter.clone()).await?;
+        }
 
         socket.tx(PortCommand::Listen {
             handle: socket.handle,
@@ -218,6 +228,15 @@ impl Port
         Ok(socket)
     }
 
+    /// Filters the connections that the host will accept on a listening
+    /// socket, rejected connections are reset and never reach the guest
+    pub async fn set_accept_filter(&self, handle: SocketHandle, filter: AcceptFilter) -> io::Result<()> {
+        self.tx(PortCommand::SetAcceptFilter {
+            handle,
+            filter,
+        }).await
+    }
+
     pub async fn dhcp_acquire(&self) -> io::Result<(Ipv4Addr, Ipv4Addr)> {
         let mut socket = self.bind_dhcp().await?;
         socket.nop(PortNopType::DhcpAcquire).await?;
@@ -445,9 +464,23 @@ impl Port
                     peer_addr,
                 } => {
                     if let Some(socket) = state.sockets.get(&handle.0) {
+                        if let Some(stats) = socket.accept_stats.as_ref() {
+                            stats.pending.fetch_add(1, Ordering::AcqRel);
+                        }
                         let _ = socket.accept.send(EventAccept { peer_addr }).await;
                     }
                 }
+                PortResponse::TcpFiltered {
+                    handle,
+                    peer_addr,
+                } => {
+                    trace!("connection from {} was filtered", peer_addr);
+                    if let Some(socket) = state.sockets.get(&handle.0) {
+                        if let Some(stats) = socket.accept_stats.as_ref() {
+                            stats.filtered.fetch_add(1, Ordering::AcqRel);
+                        }
+                    }
+                }
                 PortResponse::SocketError {
                     handle,
                     error,
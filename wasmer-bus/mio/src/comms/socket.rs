@@ -1,5 +1,8 @@
 use std::io;
 use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::net::SocketAddr;
 #[cfg(feature = "crypto")]
 use ate_crypto::EncryptKey;
@@ -17,6 +20,17 @@ use crate::model::IpProtocol;
 use super::port::StreamTx;
 use super::evt::*;
 
+/// Counters that are shared by all the sockets that listen on behalf of
+/// the same listener
+#[derive(Debug, Default)]
+pub struct AcceptStats
+{
+    /// Connections that were accepted by the host but not yet by the guest
+    pub pending: AtomicUsize,
+    /// Connections that were rejected by the accept filter
+    pub filtered: AtomicU64,
+}
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct Socket
@@ -33,6 +47,7 @@ pub struct Socket
     pub(super) recv_from: mpsc::Receiver<EventRecvFrom>,
     pub(super) error: mpsc::Receiver<EventError>,
     pub(super) accept: mpsc::Receiver<EventAccept>,
+    pub(super) accept_stats: Option<Arc<AcceptStats>>,
 }
 
 impl Socket
@@ -209,6 +224,9 @@ impl Socket
         tokio::select! {
             evt = self.accept.recv() => {
                 let evt = evt.ok_or_else(|| io::Error::from(io::ErrorKind::ConnectionAborted))?;
+                if let Some(stats) = self.accept_stats.as_ref() {
+                    let _ = stats.pending.fetch_update(Ordering::AcqRel, Ordering::Acquire, |a| a.checked_sub(1));
+                }
                 self.peer_addr.replace(evt.peer_addr.clone());
                 Ok(evt.peer_addr)
             },
@@ -336,6 +354,10 @@ impl Socket
         }
     }
 
+    pub fn handle(&self) -> SocketHandle {
+        self.handle
+    }
+
     pub fn peer_addr(&self) -> Option<&SocketAddr> {
         self.peer_addr.as_ref()
     }
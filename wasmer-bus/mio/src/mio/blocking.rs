@@ -2,6 +2,8 @@ use std::io;
 use std::net::IpAddr;
 use std::net::SocketAddr;
 use wasmer_bus::task::block_on;
+
+use crate::model::IpCidr;
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
 
@@ -52,6 +54,23 @@ impl TcpListener {
         Ok(TcpStream::new(block_on(self.inner.accept())?))
     }
 
+    pub fn accept_with_addr(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        let (stream, addr) = block_on(self.inner.accept_with_addr())?;
+        Ok((TcpStream::new(stream), addr))
+    }
+
+    pub fn set_accept_filter(&self, allow: Vec<IpCidr>, deny: Vec<IpCidr>) -> io::Result<()> {
+        block_on(self.inner.set_accept_filter(allow, deny))
+    }
+
+    pub fn pending_connections(&self) -> usize {
+        self.inner.pending_connections()
+    }
+
+    pub fn filtered_connections(&self) -> u64 {
+        self.inner.filtered_connections()
+    }
+
     pub fn local_addr(&self) -> SocketAddr {
         self.inner.local_addr()
     }
@@ -1,5 +1,8 @@
 use std::io;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use std::sync::atomic::Ordering;
 use tokio::sync::Mutex;
 use derivative::*;
 #[allow(unused_imports, dead_code)]
@@ -8,6 +11,9 @@ use tracing::{debug, error, info, trace, warn};
 use crate::comms::*;
 use super::*;
 use crate::comms::Port;
+use crate::model::AcceptFilter;
+use crate::model::IpCidr;
+use crate::model::SocketHandle;
 
 #[derive(Debug)]
 struct State
@@ -16,6 +22,14 @@ struct State
     ttl: u8,
 }
 
+/// State that is read without waiting for an accept to finish
+#[derive(Debug, Default)]
+struct Shared
+{
+    filter: AcceptFilter,
+    handle: Option<SocketHandle>,
+}
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct AsyncTcpListener
@@ -24,32 +38,61 @@ pub struct AsyncTcpListener
     port: Port,
     addr: SocketAddr,
     state: Mutex<State>,
+    shared: StdMutex<Shared>,
+    stats: Arc<AcceptStats>,
 }
 
 impl AsyncTcpListener {
     pub(crate) async fn new(port: Port, addr: SocketAddr) -> io::Result<AsyncTcpListener> {
-        let socket = port
-            .listen_tcp(addr)
+        let ret = Self {
+            port,
+            addr,
+            state: Mutex::new(State {
+                socket: None,
+                ttl: 64,
+            }),
+            shared: StdMutex::new(Shared::default()),
+            stats: Arc::new(AcceptStats::default()),
+        };
+        let socket = ret._create_socket()
             .await?;
-        Ok(
-            Self {
-                port,
-                addr,
-                state: Mutex::new(State {
-                    socket: Some(socket),
-                    ttl: 64,
-                })
-            }
-        )
+        ret.state.lock().await.socket.replace(socket);
+        Ok(ret)
     }
 
     async fn _create_socket(&self) -> io::Result<Socket> {
-        self.port
-            .listen_tcp(self.addr)
-            .await
+        let filter = self.shared.lock().unwrap().filter.clone();
+        let socket = self.port
+            .listen_tcp(self.addr, &filter, self.stats.clone())
+            .await?;
+
+        // If the filter was changed while the socket was being created then
+        // it needs to be sent again
+        let latest = {
+            let mut shared = self.shared.lock().unwrap();
+            shared.handle.replace(socket.handle());
+            if shared.filter != filter {
+                Some(shared.filter.clone())
+            } else {
+                None
+            }
+        };
+        if let Some(latest) = latest {
+            self.port
+                .set_accept_filter(socket.handle(), latest)
+                .await?;
+        }
+        Ok(socket)
     }
 
     pub async fn accept(&self) -> io::Result<AsyncTcpStream> {
+        let (stream, _) = self.accept_with_addr().await?;
+        Ok(stream)
+    }
+
+    /// Accepts a connection and returns it along with the address of the
+    /// peer that connected
+    pub async fn accept_with_addr(&self) -> io::Result<(AsyncTcpStream, SocketAddr)> {
         let mut guard = self.state.lock().await;
         if let Some(mut socket) = guard.socket.take() {
             let peer = socket
@@ -60,7 +103,7 @@ impl AsyncTcpListener {
                 socket.set_ttl(guard.ttl as u8)
                     .await?;
             }
-            Ok(AsyncTcpStream::new(socket, self.addr, peer))
+            Ok((AsyncTcpStream::new(socket, self.addr, peer), peer))
         } else {
             Err(io::Error::new(io::ErrorKind::BrokenPipe, "no listening socket"))
         }
@@ -73,6 +116,39 @@ impl AsyncTcpListener {
         Ok(())
     }
 
+    /// Sets the source addresses that connections are accepted from, the
+    /// filter is applied by the host hence connections that fail it are
+    /// reset without ever being seen by the guest. Deny takes precedence
+    /// over allow and an empty allow list allows all addresses
+    pub async fn set_accept_filter(&self, allow: Vec<IpCidr>, deny: Vec<IpCidr>) -> io::Result<()> {
+        let filter = AcceptFilter {
+            allow,
+            deny,
+        };
+        let handle = {
+            let mut shared = self.shared.lock().unwrap();
+            shared.filter = filter.clone();
+            shared.handle.clone()
+        };
+        if let Some(handle) = handle {
+            self.port
+                .set_accept_filter(handle, filter)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Number of connections that have been accepted by the host but
+    /// not yet returned by `accept`
+    pub fn pending_connections(&self) -> usize {
+        self.stats.pending.load(Ordering::Acquire)
+    }
+
+    /// Number of connections that were rejected by the accept filter
+    pub fn filtered_connections(&self) -> u64 {
+        self.stats.filtered.load(Ordering::Acquire)
+    }
+
     pub fn local_addr(&self) -> SocketAddr {
         self.addr.clone()
     }
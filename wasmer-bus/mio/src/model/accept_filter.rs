@@ -0,0 +1,44 @@
+use std::net::IpAddr;
+use std::fmt;
+use serde::*;
+
+use super::*;
+
+/// Filter applied to inbound TCP connections before they are accepted,
+/// a connection is rejected if its source address is in any of the deny
+/// ranges or (when there are allow ranges) it is not in any of them
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AcceptFilter
+{
+    pub allow: Vec<IpCidr>,
+    pub deny: Vec<IpCidr>,
+}
+
+impl AcceptFilter
+{
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        if self.deny.iter().any(|cidr| cidr.contains(ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|cidr| cidr.contains(ip))
+    }
+}
+
+impl fmt::Display
+for AcceptFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "accept-filter(allow=[")?;
+        for cidr in self.allow.iter() {
+            write!(f, "{}/{},", cidr.ip, cidr.prefix)?;
+        }
+        write!(f, "],deny=[")?;
+        for cidr in self.deny.iter() {
+            write!(f, "{}/{},", cidr.ip, cidr.prefix)?;
+        }
+        write!(f, "])")
+    }
+}
@@ -39,6 +39,22 @@ impl IpCidr
         }
     }
 
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.ip, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let prefix = self.prefix.min(32) as u32;
+                let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+                (u32::from(net) & mask) == (u32::from(ip) & mask)
+            },
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let prefix = self.prefix.min(128) as u32;
+                let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+                (u128::from(net) & mask) == (u128::from(ip) & mask)
+            },
+            _ => false
+        }
+    }
+
     pub fn broadcast(&self) -> IpAddr {
         match self.ip {
             IpAddr::V4(ip) => {
@@ -1,3 +1,4 @@
+mod accept_filter;
 mod hardware_address;
 mod ip_cidr;
 mod ip_protocol;
@@ -13,6 +14,7 @@ mod shutdown;
 mod token;
 mod switch_hello;
 
+pub use accept_filter::*;
 pub use hardware_address::*;
 pub use ip_cidr::*;
 pub use ip_protocol::*;
@@ -103,6 +103,10 @@ pub enum PortCommand {
         promiscuous: bool,
     },
     Init,
+    SetAcceptFilter {
+        handle: SocketHandle,
+        filter: AcceptFilter,
+    },
 }
 
 impl fmt::Display
@@ -158,6 +162,7 @@ for PortCommand
             PortCommand::SetAddresses { addrs: ips } => write!(f, "set-ip-addresses({:?})", ips),
             PortCommand::SetRoutes { routes } => write!(f, "set-routes({:?})", routes),
             PortCommand::Init => write!(f ,"init"),
+            PortCommand::SetAcceptFilter { handle, filter } => write!(f, "set-accept-filter(handle={},filter={})", handle, filter),
         }
     }
 }
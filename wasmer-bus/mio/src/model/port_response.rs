@@ -46,6 +46,10 @@ pub enum PortResponse {
     Inited {
         mac: HardwareAddress,
     },
+    TcpFiltered {
+        handle: SocketHandle,
+        peer_addr: SocketAddr,
+    },
 }
 
 impl fmt::Display
@@ -118,7 +122,11 @@ for PortResponse
                 mac
             } => {
                 write!(f, "initialized (mac={})", mac)
-            }
+            },
+            PortResponse::TcpFiltered {
+                handle,
+                peer_addr,
+            } => write!(f, "tcp_filtered(handle={},peer_addr={})", handle, peer_addr),
         }
     }
 }
@@ -2,6 +2,8 @@ use std::error::Error;
 use std::io;
 use serde::*;
 use std::fmt;
+use wasmer_bus::abi::ApplicationError;
+use wasmer_bus::abi::BusError;
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum SocketErrorKind {
@@ -122,6 +124,7 @@ for SocketErrorKind
             io::ErrorKind::Unsupported => SocketErrorKind::Unsupported,
             io::ErrorKind::UnexpectedEof => SocketErrorKind::UnexpectedEof,
             io::ErrorKind::OutOfMemory => SocketErrorKind::OutOfMemory,
+            io::ErrorKind::StorageFull => SocketErrorKind::StorageFull,
             io::ErrorKind::Other => SocketErrorKind::Other,
             _ => SocketErrorKind::Other,
         }
@@ -160,6 +163,7 @@ for SocketErrorKind
             SocketErrorKind::Unsupported => io::ErrorKind::Unsupported,
             SocketErrorKind::UnexpectedEof => io::ErrorKind::UnexpectedEof,
             SocketErrorKind::OutOfMemory => io::ErrorKind::OutOfMemory,
+            SocketErrorKind::StorageFull => io::ErrorKind::StorageFull,
             SocketErrorKind::Other => io::ErrorKind::Other,
             _ => io::ErrorKind::Other,
         }
@@ -198,6 +202,30 @@ for SocketError
     }
 }
 
+impl From<BusError>
+for SocketError
+{
+    fn from(err: BusError) -> SocketError {
+        err.into_io_error().into()
+    }
+}
+
+impl From<ApplicationError>
+for SocketError
+{
+    /// Errors raised by the other side of the bus are passed through as is,
+    /// anything else is reported as an application failure
+    fn from(err: ApplicationError) -> SocketError {
+        if let Some(err) = err.downcast::<SocketError>() {
+            return err;
+        }
+        if let Some(kind) = err.downcast::<SocketErrorKind>() {
+            return SocketError::Simple(kind);
+        }
+        SocketError::SimpleMessage(SocketErrorKind::Other, err.to_string())
+    }
+}
+
 impl Into<io::Error>
 for SocketError
 {
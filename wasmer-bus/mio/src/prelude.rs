@@ -20,6 +20,7 @@ pub use super::mio::Port;
 pub use ate_comms::StreamSecurity;
 pub use super::mio::TokenSource;
 pub use super::model::NetworkToken;
+pub use super::model::IpCidr;
 pub use ate_crypto::ChainKey;
 
 pub use super::mio::clear_access_token;
@@ -21,6 +21,7 @@ pub trait Process {
     async fn close_stdin(&self);
     async fn kill(&self);
     async fn flush(&self);
+    async fn resize(&self, rect: PtyRect);
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -33,6 +34,16 @@ pub struct Spawn {
     pub stdout_mode: StdioMode,
     pub stderr_mode: StdioMode,
     pub pre_open: Vec<String>,
+    /// Initial dimensions of the pty (only used when one of the stdio
+    /// modes is `Tty`, otherwise the dimensions of the parent are used)
+    #[serde(default)]
+    pub pty_rect: Option<PtyRect>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PtyRect {
+    pub cols: u32,
+    pub rows: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -41,6 +52,9 @@ pub enum StdioMode {
     Inherit,
     Null,
     Log,
+    /// The stream is attached to a pseudo terminal so that the child
+    /// process behaves as if its running interactively
+    Tty,
 }
 
 impl Display for StdioMode {
@@ -50,6 +64,7 @@ impl Display for StdioMode {
             StdioMode::Inherit => write!(f, "inherit"),
             StdioMode::Null => write!(f, "null"),
             StdioMode::Log => write!(f, "log"),
+            StdioMode::Tty => write!(f, "tty"),
         }
     }
 }
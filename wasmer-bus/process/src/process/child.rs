@@ -100,14 +100,14 @@ impl Child {
         stderr_mode: StdioMode,
         pre_open: Vec<String>,
     ) -> Result<Child> {
-        let (stdout, stdout_tx) = if stdout_mode == StdioMode::Piped {
+        let (stdout, stdout_tx) = if stdout_mode == StdioMode::Piped || stdout_mode == StdioMode::Tty {
             let (a, b) = ChildStdout::new();
             (Some(a), Some(b))
         } else {
             (None, None)
         };
 
-        let (stderr, stderr_tx) = if stderr_mode == StdioMode::Piped {
+        let (stderr, stderr_tx) = if stderr_mode == StdioMode::Piped || stderr_mode == StdioMode::Tty {
             let (a, b) = ChildStdout::new();
             (Some(a), Some(b))
         } else {
@@ -123,6 +123,7 @@ impl Child {
             stdout_mode,
             stderr_mode,
             pre_open: pre_open.clone(),
+            pty_rect: cmd.pty_rect.clone(),
         };
 
         let stdout_tx = stdout_tx.map(|a| Mutex::new(a));
@@ -158,7 +159,7 @@ impl Child {
             .as_client()
             .unwrap();
 
-        let stdin = if stdin_mode == StdioMode::Piped {
+        let stdin = if stdin_mode == StdioMode::Piped || stdin_mode == StdioMode::Tty {
             let stdin = ChildStdin::new(context.clone());
             Some(stdin)
         } else {
@@ -208,6 +209,19 @@ impl Child {
         Ok(())
     }
 
+    /// Informs the child process that the dimensions of its pseudo terminal
+    /// have changed (this only has an effect on streams in [`tty`] mode)
+    ///
+    /// [`tty`]: Stdio::tty
+    pub fn resize(&mut self, cols: u32, rows: u32) -> io::Result<()> {
+        if let Some(context) = self.context.as_ref() {
+            context
+                .blocking_resize(api::PtyRect { cols, rows })
+                .map_err(|err| err.into_io_error())?
+        }
+        Ok(())
+    }
+
     /// Returns the OS-assigned process identifier associated with this child.
     ///
     /// # Examples
@@ -1,6 +1,7 @@
 use std::{ffi::OsStr, io};
 
 use super::*;
+use crate::api::PtyRect;
 
 /// A process builder, providing fine-grained control
 /// over how a new process should be spawned.
@@ -63,6 +64,7 @@ pub struct Command {
     pub(super) stdout: Option<Stdio>,
     pub(super) stderr: Option<Stdio>,
     pub(super) pre_open: Vec<String>,
+    pub(super) pty_rect: Option<PtyRect>,
     pub(super) instance: Option<CommandInstance>,
 }
 
@@ -109,6 +111,7 @@ impl Command {
             stdout: None,
             stderr: None,
             pre_open: Vec::new(),
+            pty_rect: None,
             instance: None,
         }
     }
@@ -349,6 +352,16 @@ impl Command {
         self
     }
 
+    /// Sets the initial dimensions of the pseudo terminal that is allocated
+    /// for any stdio streams that are in [`tty`] mode (if not set then the
+    /// dimensions of the parent terminal are used)
+    ///
+    /// [`tty`]: Stdio::tty
+    pub fn pty_size(&mut self, cols: u32, rows: u32) -> &mut Command {
+        self.pty_rect = Some(PtyRect { cols, rows });
+        self
+    }
+
     async fn prep(&self) -> io::Result<Child> {
         let stdin = self
             .stdin
@@ -143,4 +143,28 @@ impl Stdio {
             mode: StdioMode::Null,
         }
     }
+
+    /// The stream is attached to a pseudo terminal that is shared with the
+    /// parent, which allows interactive programs (e.g. `vim`) to detect that
+    /// they are running on a terminal. When the parent has no terminal the
+    /// stream is piped back to the parent instead (while still reporting
+    /// itself as a terminal to the child).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use wasi_net::{Command, Stdio};
+    ///
+    /// Command::new("vim")
+    ///     .stdin(Stdio::tty())
+    ///     .stdout(Stdio::tty())
+    ///     .stderr(Stdio::tty())
+    ///     .status()
+    ///     .expect("Failed to execute command");
+    /// ```
+    pub fn tty() -> Stdio {
+        Stdio {
+            mode: StdioMode::Tty,
+        }
+    }
 }
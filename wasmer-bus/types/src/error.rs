@@ -2,6 +2,11 @@ use serde::*;
 use std::fmt;
 use std::io;
 
+use crate::SerializationFormat;
+
+/// Prefix that marks a reply as an application error rather than a response
+const APPLICATION_ERROR_MAGIC: &'static [u8] = b"\0wasm-bus-app-error\0";
+
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum BusError {
@@ -24,6 +29,10 @@ pub enum BusError {
     AccessDenied = 18,
     AlreadyConsumed = 19,
     MemoryAccessViolation = 20,
+    /// The call completed with an error that was raised by the application
+    /// itself (rather than by the bus), the error itself is carried by an
+    /// `ApplicationError` that can be retrieved from the call
+    Application = 21,
     Unknown = u32::MAX,
 }
 
@@ -51,6 +60,7 @@ for BusError {
             18 => AccessDenied,
             19 => AlreadyConsumed,
             20 => MemoryAccessViolation,
+            21 => Application,
             _ => Unknown
         }
     }
@@ -62,6 +72,102 @@ impl BusError {
     }
 }
 
+/// Error that was raised by the application on the other side of a call,
+/// it is serialized so that callers that know its type can turn it back
+/// into a concrete error
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ApplicationError {
+    pub type_name: String,
+    pub format: SerializationFormat,
+    pub data: Vec<u8>,
+}
+
+impl ApplicationError {
+    /// Creates an application error that carries a serialized copy of
+    /// the error that was raised
+    pub fn new<T>(format: SerializationFormat, err: &T) -> Result<ApplicationError, BusError>
+    where
+        T: Serialize,
+    {
+        Ok(ApplicationError {
+            type_name: std::any::type_name::<T>().to_string(),
+            format,
+            data: format.serialize_ref(err)?,
+        })
+    }
+
+    /// Returns true if this application error is of a particular type
+    pub fn is<T>(&self) -> bool {
+        self.type_name == std::any::type_name::<T>()
+    }
+
+    /// Deserializes the application error into a concrete type, if the
+    /// error is not of that type then nothing is returned
+    pub fn downcast<T>(&self) -> Option<T>
+    where
+        T: de::DeserializeOwned,
+    {
+        match self.is::<T>() {
+            true => self.format.deserialize_ref(&self.data[..]).ok(),
+            false => None,
+        }
+    }
+
+    /// Encodes the error so that it can be sent as the reply of a call
+    /// (the bus ABI itself can only carry error codes)
+    pub fn to_reply(&self) -> Vec<u8> {
+        let mut ret = Vec::with_capacity(
+            APPLICATION_ERROR_MAGIC.len() + 5 + self.type_name.len() + self.data.len(),
+        );
+        ret.extend_from_slice(APPLICATION_ERROR_MAGIC);
+        ret.push(self.format as u8);
+        ret.extend_from_slice(&(self.type_name.len() as u32).to_le_bytes());
+        ret.extend_from_slice(self.type_name.as_bytes());
+        ret.extend_from_slice(&self.data[..]);
+        ret
+    }
+
+    /// Decodes a reply that was encoded with `to_reply`, replies that are
+    /// normal responses will return nothing
+    pub fn from_reply(reply: &[u8]) -> Option<ApplicationError> {
+        let reply = reply.strip_prefix(APPLICATION_ERROR_MAGIC)?;
+        let (format, reply) = reply.split_first()?;
+        let format = SerializationFormat::iter().find(|a| *a as u8 == *format)?;
+        if reply.len() < 4 {
+            return None;
+        }
+        let (len, reply) = reply.split_at(4);
+        let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        if reply.len() < len {
+            return None;
+        }
+        let (type_name, data) = reply.split_at(len);
+        let type_name = String::from_utf8(type_name.to_vec()).ok()?;
+        Some(ApplicationError {
+            type_name,
+            format,
+            data: data.to_vec(),
+        })
+    }
+}
+
+impl From<ApplicationError> for BusError {
+    fn from(_: ApplicationError) -> BusError {
+        BusError::Application
+    }
+}
+
+impl fmt::Display for ApplicationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "application error ({})", self.type_name)
+    }
+}
+
+impl std::error::Error
+for ApplicationError
+{
+}
+
 impl Into<io::Error> for BusError {
     fn into(self) -> io::Error {
         match self {
@@ -112,6 +218,7 @@ impl fmt::Display for BusError {
             BusError::BusInvocationFailed => write!(f, "bus invocation has failed"),
             BusError::AlreadyConsumed => write!(f, "result already consumed"),
             BusError::MemoryAccessViolation => write!(f, "memory access violation"),
+            BusError::Application => write!(f, "the application raised an error"),
             BusError::Unknown => write!(f, "unknown error."),
         }
     }
@@ -1,5 +1,6 @@
 mod error;
 mod format;
+mod tests;
 
 pub use error::*;
 pub use format::*;
\ No newline at end of file
@@ -0,0 +1,35 @@
+#![cfg(test)]
+use serde::*;
+
+use super::*;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+enum TestError {
+    StorageFull,
+    Code(u32),
+}
+
+#[test]
+fn test_application_error_reply() {
+    let err = ApplicationError::new(SerializationFormat::Json, &TestError::Code(28)).unwrap();
+    assert!(err.is::<TestError>());
+    assert!(err.is::<String>() == false);
+
+    let reply = err.to_reply();
+    let err = ApplicationError::from_reply(&reply[..]).unwrap();
+    assert_eq!(err.downcast::<TestError>(), Some(TestError::Code(28)));
+    assert_eq!(err.downcast::<String>(), None);
+
+    let err = ApplicationError::new(SerializationFormat::Bincode, &TestError::StorageFull).unwrap();
+    let reply = err.to_reply();
+    let err = ApplicationError::from_reply(&reply[..]).unwrap();
+    assert_eq!(err.downcast::<TestError>(), Some(TestError::StorageFull));
+    assert!(matches!(BusError::from(err), BusError::Application));
+}
+
+#[test]
+fn test_application_error_normal_reply() {
+    let reply = SerializationFormat::Json.serialize("hello").unwrap();
+    assert!(ApplicationError::from_reply(&reply[..]).is_none());
+    assert!(matches!(BusError::from(21u32), BusError::Application));
+}
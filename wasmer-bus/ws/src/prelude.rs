@@ -6,6 +6,16 @@ pub use crate::ws::SendHalf;
 pub use crate::ws::SocketBuilder;
 #[cfg(any(feature = "sys", target_family = "wasm"))]
 pub use crate::ws::WebSocket;
+#[cfg(any(feature = "sys", target_family = "wasm"))]
+pub use crate::ws::ReconnectPolicy;
+#[cfg(any(feature = "sys", target_family = "wasm"))]
+pub use crate::ws::ReconnectingWebSocket;
+#[cfg(any(feature = "sys", target_family = "wasm"))]
+pub use crate::ws::ReconnectingSendHalf;
+#[cfg(any(feature = "sys", target_family = "wasm"))]
+pub use crate::ws::ReconnectingRecvHalf;
+#[cfg(any(feature = "sys", target_family = "wasm"))]
+pub use crate::ws::SocketEvent;
 #[cfg(target_family = "wasm")]
 pub use wasmer_bus;
 #[cfg(target_family = "wasm")]
@@ -3,9 +3,13 @@ mod wasm;
 #[cfg(feature = "sys")]
 #[cfg(not(target_family = "wasm"))]
 mod sys;
+#[cfg(any(feature = "sys", target_family = "wasm"))]
+mod reconnect;
 
 #[cfg(target_family = "wasm")]
 pub use wasm::*;
 #[cfg(feature = "sys")]
 #[cfg(not(target_family = "wasm"))]
-pub use sys::*;
\ No newline at end of file
+pub use sys::*;
+#[cfg(any(feature = "sys", target_family = "wasm"))]
+pub use reconnect::*;
\ No newline at end of file
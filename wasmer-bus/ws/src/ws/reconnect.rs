@@ -0,0 +1,295 @@
+use std::io;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::sync::Notify;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+
+use super::SocketBuilder;
+
+/// Controls how a reconnecting web socket re-dials the server after the
+/// connection drops
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Delay before the first attempt to reconnect (it doubles after every failure)
+    pub initial_backoff: Duration,
+    /// Upper limit of the delay between attempts
+    pub max_backoff: Duration,
+    /// Number of failed attempts in a row before the socket gives up (None retries forever)
+    pub max_attempts: Option<u32>,
+    /// Number of outbound frames that are buffered while disconnected, when the
+    /// buffer is full the sends will fail
+    pub max_buffered: usize,
+    /// When set the buffered frames are held back after a reconnect until
+    /// `resume` is called, which gives the consumer the chance to send a
+    /// handshake (with `send_handshake`) before anything else
+    pub hold_after_reconnect: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: None,
+            max_buffered: 1024,
+            hold_after_reconnect: false,
+        }
+    }
+}
+
+/// Events that are received from a reconnecting web socket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SocketEvent {
+    /// A connection was established (the first one has generation zero)
+    Connected { generation: u64 },
+    /// Data was received on the current connection
+    Data(Vec<u8>),
+    /// The connection was lost and the socket is now trying to reconnect
+    Disconnected { generation: u64, reason: String },
+}
+
+#[derive(Debug, Default)]
+struct ReconnectState {
+    generation: AtomicU64,
+    held: AtomicBool,
+    resume: Notify,
+}
+
+impl SocketBuilder {
+    /// Opens a web socket that re-dials the same URL whenever the connection
+    /// drops. Only the first connection must succeed (so that a bad address
+    /// is still reported), afterwards the consumer sees explicit
+    /// `Connected` and `Disconnected` events and every reconnect increments
+    /// the generation counter. The plain `open` never reconnects.
+    pub async fn open_reconnecting(
+        self,
+        policy: ReconnectPolicy,
+    ) -> Result<ReconnectingWebSocket, io::Error> {
+        let url = self.url.clone();
+        let ws = self.open().await?;
+
+        let (tx_out, rx_out) = mpsc::channel(policy.max_buffered.max(1));
+        let (tx_handshake, rx_handshake) = mpsc::channel(16);
+        let (tx_event, rx_event) = mpsc::channel(1024);
+        let state = Arc::new(ReconnectState::default());
+
+        {
+            let state = state.clone();
+            wasmer_bus::task::spawn(async move {
+                let mut ws = ws;
+                let mut rx_out = rx_out;
+                let mut rx_handshake = rx_handshake;
+                let mut backlog = None;
+                loop {
+                    let generation = state.generation.load(Ordering::Acquire);
+                    let (mut tx, mut rx) = ws.split();
+                    if tx_event.send(SocketEvent::Connected { generation }).await.is_err() {
+                        return;
+                    }
+
+                    // Pump the frames until the connection fails (if the
+                    // consumer has gone away then the socket is closed)
+                    let reason = loop {
+                        let held = state.held.load(Ordering::Acquire);
+                        if held == false {
+                            if let Some(data) = backlog.take() {
+                                if let Err(err) = tx.send(data).await {
+                                    break err.to_string();
+                                }
+                                continue;
+                            }
+                        }
+                        tokio::select! {
+                            data = rx_handshake.recv() => {
+                                let data = match data {
+                                    Some(a) => a,
+                                    None => {
+                                        let _ = tx.close().await;
+                                        return;
+                                    }
+                                };
+                                if let Err(err) = tx.send(data).await {
+                                    break err.to_string();
+                                }
+                            }
+                            data = rx_out.recv(), if held == false => {
+                                let data = match data {
+                                    Some(a) => a,
+                                    None => {
+                                        let _ = tx.close().await;
+                                        return;
+                                    }
+                                };
+                                let len = data.len();
+                                if let Err(err) = tx.send(data.clone()).await {
+                                    // The frame is sent again on the next connection
+                                    trace!("web socket send of {} bytes failed", len);
+                                    backlog.replace(data);
+                                    break err.to_string();
+                                }
+                            }
+                            _ = state.resume.notified(), if held => {
+                            }
+                            data = rx.recv() => {
+                                let data = match data {
+                                    Some(a) => a,
+                                    None => break "connection closed".to_string(),
+                                };
+                                if tx_event.send(SocketEvent::Data(data)).await.is_err() {
+                                    let _ = tx.close().await;
+                                    return;
+                                }
+                            }
+                        }
+                    };
+                    debug!("web socket disconnected (generation={}) - {}", generation, reason);
+                    if tx_event
+                        .send(SocketEvent::Disconnected { generation, reason })
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+
+                    // Re-dial the server with an exponential backoff
+                    let mut backoff = policy.initial_backoff;
+                    let mut attempt = 0u32;
+                    ws = loop {
+                        wasmer_bus_time::prelude::sleep(backoff).await;
+                        match SocketBuilder::new(url.clone()).open().await {
+                            Ok(a) => break a,
+                            Err(err) => {
+                                attempt += 1;
+                                debug!("web socket reconnect failed (attempt={}) - {}", attempt, err);
+                                if policy.max_attempts.map(|a| attempt >= a).unwrap_or(false) {
+                                    warn!("web socket gave up reconnecting to {} after {} attempts", url, attempt);
+                                    return;
+                                }
+                                backoff = (backoff * 2).min(policy.max_backoff);
+                            }
+                        }
+                    };
+                    if policy.hold_after_reconnect {
+                        state.held.store(true, Ordering::Release);
+                    }
+                    state.generation.fetch_add(1, Ordering::AcqRel);
+                }
+            });
+        }
+
+        Ok(ReconnectingWebSocket {
+            tx: tx_out,
+            tx_handshake,
+            rx: rx_event,
+            state,
+        })
+    }
+}
+
+/// Web socket that reconnects itself (see `SocketBuilder::open_reconnecting`)
+#[derive(Debug)]
+pub struct ReconnectingWebSocket {
+    tx: mpsc::Sender<Vec<u8>>,
+    tx_handshake: mpsc::Sender<Vec<u8>>,
+    rx: mpsc::Receiver<SocketEvent>,
+    state: Arc<ReconnectState>,
+}
+
+impl ReconnectingWebSocket {
+    pub fn generation(&self) -> u64 {
+        self.state.generation.load(Ordering::Acquire)
+    }
+
+    pub fn split(self) -> (ReconnectingSendHalf, ReconnectingRecvHalf) {
+        (
+            ReconnectingSendHalf {
+                tx: self.tx,
+                tx_handshake: self.tx_handshake,
+                state: self.state.clone(),
+            },
+            ReconnectingRecvHalf {
+                rx: self.rx,
+                state: self.state,
+            },
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReconnectingSendHalf {
+    tx: mpsc::Sender<Vec<u8>>,
+    tx_handshake: mpsc::Sender<Vec<u8>>,
+    state: Arc<ReconnectState>,
+}
+
+impl ReconnectingSendHalf {
+    /// Number of times the socket has reconnected
+    pub fn generation(&self) -> u64 {
+        self.state.generation.load(Ordering::Acquire)
+    }
+
+    /// Queues a frame to be sent on the current connection (or the next one
+    /// if the socket is currently disconnected)
+    pub async fn send(&mut self, data: Vec<u8>) -> io::Result<usize> {
+        let len = data.len();
+        match self.tx.try_send(data) {
+            Ok(()) => Ok(len),
+            Err(mpsc::error::TrySendError::Full(_)) => Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "web socket send buffer is full while reconnecting",
+            )),
+            Err(mpsc::error::TrySendError::Closed(_)) => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "web socket has given up reconnecting",
+            )),
+        }
+    }
+
+    pub fn blocking_send(&mut self, data: Vec<u8>) -> io::Result<usize> {
+        wasmer_bus::task::block_on(self.send(data))
+    }
+
+    /// Sends a frame ahead of any that are buffered, used to redo the
+    /// handshake of a higher layer after a reconnect
+    pub async fn send_handshake(&mut self, data: Vec<u8>) -> io::Result<usize> {
+        let len = data.len();
+        self.tx_handshake
+            .send(data)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "web socket has given up reconnecting"))?;
+        Ok(len)
+    }
+
+    /// Releases the frames that were held back after a reconnect
+    pub fn resume(&self) {
+        self.state.held.store(false, Ordering::Release);
+        self.state.resume.notify_one();
+    }
+}
+
+#[derive(Debug)]
+pub struct ReconnectingRecvHalf {
+    rx: mpsc::Receiver<SocketEvent>,
+    state: Arc<ReconnectState>,
+}
+
+impl ReconnectingRecvHalf {
+    /// Number of times the socket has reconnected
+    pub fn generation(&self) -> u64 {
+        self.state.generation.load(Ordering::Acquire)
+    }
+
+    /// Returns the next event or None once the socket has given up reconnecting
+    pub async fn recv(&mut self) -> Option<SocketEvent> {
+        self.rx.recv().await
+    }
+
+    pub fn blocking_recv(&mut self) -> Option<SocketEvent> {
+        wasmer_bus::task::block_on(self.rx.recv())
+    }
+}
@@ -13,6 +13,14 @@ pub struct DeployApi {
     pub db_url: Option<url::Url>,
     pub registry: Arc<Registry>,
     pub lock_timeout: Duration,
+    /// Set when the wallet is a sub-wallet that draws its coins from a parent
+    pub sub_wallet: Option<SubWalletContext>,
+}
+
+#[derive(Clone)]
+pub struct SubWalletContext {
+    pub record: DaoMut<SubWallet>,
+    pub parent: DaoMut<Wallet>,
 }
 
 pub async fn build_api_accessor(
@@ -22,6 +30,9 @@ pub async fn build_api_accessor(
     db_url: Option<url::Url>,
     registry: &Arc<Registry>,
 ) -> DeployApi {
+    // Instances are written with their index entries so they can be found by name
+    register_index::<WalletInstance>();
+
     DeployApi {
         dio: Arc::clone(dio),
         wallet,
@@ -30,6 +41,7 @@ pub async fn build_api_accessor(
         db_url,
         registry: Arc::clone(&registry),
         lock_timeout: Duration::from_millis(500),
+        sub_wallet: None,
     }
 }
 
@@ -51,6 +51,51 @@ impl DeployApi {
             bail!(WalletErrorKind::CoreError(CoreErrorKind::InternalError(ate::utils::obscure_error_str("unable to cave a bag of coins when there are uncommitted transactions on the DIO"))));
         }
 
+        // Sub-wallets hold a lock on their record from the moment that the
+        // spend is checked against their limit until it is committed so that
+        // concurrent spends can not together exceed the limit
+        let mut sub_wallet = self.sub_wallet.clone();
+        if let Some(ctx) = sub_wallet.as_mut() {
+            if ctx.record.try_lock_with_timeout(self.lock_timeout).await? == false {
+                bail!(WalletErrorKind::WalletLocked);
+            }
+        }
+        let ret = self
+            .__carve_bag_sub_wallet(currency, needed_total_amount, auto_recover_coins)
+            .await;
+        if let Some(ctx) = sub_wallet.as_mut() {
+            ctx.record.unlock().await?;
+        }
+        ret
+    }
+
+    async fn __carve_bag_sub_wallet(
+        &mut self,
+        currency: NationalCurrency,
+        needed_total_amount: Decimal,
+        auto_recover_coins: bool,
+    ) -> Result<BagOfCoins, WalletError> {
+        // Sub-wallets draw the coins they are missing from their parent, if
+        // the carve then fails those coins are handed back to the parent
+        let topped_up = self.__sub_wallet_top_up(currency, needed_total_amount).await?;
+        let ret = self
+            .__carve_bag_inner(currency, needed_total_amount, auto_recover_coins)
+            .await;
+        if ret.is_err() && topped_up > Decimal::zero() {
+            self.dio.cancel();
+            if let Err(err) = self.__sub_wallet_return(currency, topped_up).await {
+                error!("Error returning the top-up to the parent wallet: {}", err);
+            }
+        }
+        ret
+    }
+
+    async fn __carve_bag_inner(
+        &mut self,
+        currency: NationalCurrency,
+        needed_total_amount: Decimal,
+        auto_recover_coins: bool,
+    ) -> Result<BagOfCoins, WalletError> {
         // Loop a limited number of times
         for n in 1..=50 {
             trace!("carve: attempt={}", n);
@@ -62,6 +107,11 @@ impl DeployApi {
 
             // Now do a quick check to make sure we actually have enough coins of this currency
             let currency_summary = self.__wallet_currency_summary(currency).await?;
+            if currency_summary.total <= Decimal::zero() {
+                let held = self.wallet_summary().await?.held_currencies();
+                trace!("carve: currency not held - currency={} held={}", currency, held);
+                bail!(WalletErrorKind::CurrencyNotHeld(currency, held));
+            }
             if needed_total_amount > currency_summary.total {
                 trace!(
                     "carve: insufficient coins-needed={} available={}",
@@ -215,6 +265,13 @@ impl DeployApi {
                 }
             }
 
+            // Spending by a sub-wallet is recorded against its limit in the same
+            // commit (which is also where a freeze takes effect)
+            if let Err(err) = self.__sub_wallet_spend(currency, needed_total_amount).await {
+                self.dio.cancel();
+                return Err(err);
+            }
+
             // Now we commit the transaction which gets us ready for the rotates
             self.dio.commit().await?;
 
@@ -17,6 +17,12 @@ impl DeployApi {
         service: AdvertisedService,
         force: bool,
     ) -> Result<ContractCreateResponse, ContractError> {
+        // Contracts are charged by the provider directly from the wallet which
+        // would bypass the spending limit of a sub-wallet
+        if let Some(ctx) = self.sub_wallet.as_ref() {
+            bail!(ContractErrorKind::SubWalletNotSupported(ctx.record.name.clone()));
+        }
+
         // Make the session
         let session = self.dio.session().clone_session();
 
@@ -40,8 +40,8 @@ impl DeployApi {
         chain_session.add_group_read_key(&AteRolePurpose::Observer, &master_authority.read);
         chain_session.add_group_write_key(&AteRolePurpose::Contributor, &master_authority.write);
         
-        // Load the instance
-        let chain_dio = chain.dio_full(&chain_session).await;
+        // Load the instance (which also works when the chain is read-only)
+        let chain_dio = chain.dio(&chain_session).await.trans(TransactionScope::Full).await;
         chain_dio.load::<ServiceInstance>(&PrimaryKey::from(INSTANCE_ROOT_ID)).await
     }
 
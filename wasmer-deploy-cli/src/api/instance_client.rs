@@ -3,7 +3,9 @@ use ate::comms::{StreamTx, StreamRx, StreamSecurity};
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
 
-use crate::model::{InstanceCommand, InstanceHello, InstanceReply};
+use ate_crypto::SerializationFormat;
+
+use crate::model::{InstanceCall, InstanceCommand, InstanceHello, InstanceReply};
 
 pub struct InstanceClient
 {
@@ -65,6 +67,25 @@ impl InstanceClient
         Ok(())
     }
 
+    /// Opens a call session against an exported binary that is held open so
+    /// that multiple messages can be exchanged with the same running handler
+    pub fn open_call(self, format: SerializationFormat, binary: &str, topic: &str) -> CallSession {
+        CallSession {
+            tx: CallSessionTx {
+                tx: self.tx,
+                handle: fastrand::u64(..),
+                format,
+                binary: binary.to_string(),
+                topic: topic.to_string(),
+                started: false,
+            },
+            rx: CallSessionRx {
+                rx: self.rx,
+                exit_code: None,
+            },
+        }
+    }
+
     pub async fn run_shell(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let mut stdin = Tty::stdin().await?;
         let mut stdout = Tty::stdout().await?;
@@ -154,3 +175,142 @@ impl InstanceClient
         Ok(())
     }
 }
+
+/// Bidirectional call session with a running handler in an instance, the
+/// first message starts the handler and subsequent messages are routed to it
+pub struct CallSession
+{
+    tx: CallSessionTx,
+    rx: CallSessionRx,
+}
+
+impl CallSession
+{
+    pub fn handle(&self) -> u64 {
+        self.tx.handle
+    }
+
+    pub async fn send(&mut self, data: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
+        self.tx.send(data).await
+    }
+
+    pub async fn recv(&mut self) -> Option<InstanceReply> {
+        self.rx.recv().await
+    }
+
+    pub async fn close(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.tx.close().await
+    }
+
+    pub fn split(self) -> (CallSessionTx, CallSessionRx) {
+        (self.tx, self.rx)
+    }
+}
+
+pub struct CallSessionTx
+{
+    tx: StreamTx,
+    handle: u64,
+    format: SerializationFormat,
+    binary: String,
+    topic: String,
+    started: bool,
+}
+
+impl CallSessionTx
+{
+    pub fn handle(&self) -> u64 {
+        self.handle
+    }
+
+    async fn send_cmd(&mut self, cmd: InstanceCommand) -> Result<(), Box<dyn std::error::Error>> {
+        let data = serde_json::to_vec(&cmd)?;
+        self.tx.write(&data[..]).await?;
+        Ok(())
+    }
+
+    pub async fn send(&mut self, data: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
+        // The first message starts the handler while all the others are
+        // sent to the session that it holds open
+        let call = match self.started {
+            false => InstanceCall {
+                parent: None,
+                handle: self.handle,
+                format: self.format,
+                binary: self.binary.clone(),
+                topic: self.topic.clone(),
+                keepalive: true,
+            },
+            true => InstanceCall {
+                parent: Some(self.handle),
+                handle: fastrand::u64(..),
+                format: self.format,
+                binary: self.binary.clone(),
+                topic: self.topic.clone(),
+                keepalive: false,
+            },
+        };
+        self.started = true;
+
+        self.send_cmd(InstanceCommand::Call(call)).await?;
+        self.tx.write(&data[..]).await?;
+        Ok(())
+    }
+
+    pub async fn close(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.started == false {
+            self.tx.close().await?;
+            return Ok(());
+        }
+        let handle = self.handle;
+        self.send_cmd(InstanceCommand::Close(handle)).await
+    }
+}
+
+pub struct CallSessionRx
+{
+    rx: StreamRx,
+    exit_code: Option<i32>,
+}
+
+impl CallSessionRx
+{
+    /// Returns the next reply from the instance or `None` when the
+    /// call session has ended
+    pub async fn recv(&mut self) -> Option<InstanceReply> {
+        if self.exit_code.is_some() {
+            return None;
+        }
+        let data = match self.rx.read().await {
+            Ok(data) if data.len() > 0 => data,
+            _ => {
+                self.exit_code = Some(1);
+                return None;
+            }
+        };
+        let reply: InstanceReply = match bincode::deserialize(&data[..]) {
+            Ok(a) => a,
+            Err(err) => {
+                debug!("failed to deserialize the instance reply - {}", err);
+                self.exit_code = Some(1);
+                return None;
+            }
+        };
+        match &reply {
+            InstanceReply::Error { .. } => {
+                self.exit_code = Some(1);
+            }
+            InstanceReply::Terminate { .. } |
+            InstanceReply::Exit => {
+                self.exit_code = Some(0);
+            }
+            _ => { }
+        }
+        Some(reply)
+    }
+
+    /// Exit code of the call session once it has ended
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+}
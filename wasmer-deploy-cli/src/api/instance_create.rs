@@ -24,6 +24,7 @@ impl DeployApi {
         db_url: url::Url,
         instance_authority: String,
         force: bool,
+        template: Option<String>,
     ) -> Result<WalletInstance, InstanceError>
     {
         // Get the sudo rights from the session (as we will use these for the wallet)
@@ -48,6 +49,11 @@ impl DeployApi {
         };
         let all_write_keys = self.session().write_keys(AteSessionKeyCategory::AllKeys).map(|a| a.clone()).collect::<Vec<_>>();
 
+        // Clean up after any instances that were only partly created
+        if let Err(err) = self.recover_transactions().await {
+            warn!("failed to recover interrupted transactions - {}", err);
+        }
+
         // If it already exists then fail
         let instance_key_entropy = format!("instance://{}/{}", self.session_identity(), name);
         let instance_key = PrimaryKey::from(instance_key_entropy);
@@ -63,6 +69,13 @@ impl DeployApi {
             }
         }
 
+        // Load the template before anything is created so that templates
+        // which are missing or not valid leave nothing behind
+        let template = match template {
+            Some(template) => Some(self.instance_template(template.as_str(), &db_url, instance_authority.as_str()).await?),
+            None => None,
+        };
+
         // Generate encryption keys and modify the root of the tree so that it
         // uses them
         let key_size = sudo_read.size();
@@ -116,9 +129,17 @@ impl DeployApi {
         // Output what we are encrypting with
         debug!("using instance authority ({}) public encryption key ({})", instance_authority, master_public.hash());
 
+        // Everything else is written in one transaction that is committed
+        // together with the wallet, starting with the content of the template
+        // (nothing is left behind if any of it fails)
+        let dio = chain_api.dio_mut_meta().await;
+        if let Some((template, template_ref)) = template.as_ref() {
+            debug!("seeding template: {}@{}", template_ref.name, template_ref.version);
+            DeployApi::instance_template_seed(&chain_api, &dio, root.key().as_u64(), root.dentry.mode, template).await?;
+        }
+
         // Add the master authority record so that the master servers can read this
         let admin_token = AteHash::generate().to_hex_string();
-        let dio = chain_api.dio_mut_meta().await;
         let mut master_authority = dio.store_with_key(
            MasterAuthority {
                inner_broker: PublicEncryptedSecureData::new(&master_public, MasterAuthorityInner {
@@ -146,6 +167,10 @@ impl DeployApi {
             prefix: 64
         }];
 
+        // The key that signs the capability tokens of the exports is kept
+        // in the wallet so that only the owner can mint them
+        let export_key = self.instance_export_key_create(instance_id)?;
+
         // Add the object directly to the chain        
         let mut instance_dao = dio.store_with_key(
             ServiceInstance {
@@ -159,12 +184,16 @@ impl DeployApi {
                 admin_token,
                 exports: DaoVec::new(),
                 mesh_nodes: DaoVec::new(),
+                export_public_key: Some(export_key.as_public_key().clone()),
+                template: template.as_ref().map(|(_, a)| a.clone()),
+                env: template.as_ref().map(|(a, _)| a.env.clone()).unwrap_or_default(),
             },
             PrimaryKey::from(INSTANCE_ROOT_ID),
         )?;
         instance_dao.attach_orphaned(root.key())?;
-        chain_api.commit().await?;
-        dio.commit().await?;
+        if let Some((template, _)) = template.as_ref() {
+            DeployApi::instance_template_exports(&mut instance_dao, template)?;
+        }
 
         // Create the instance and add it to the identities collection
         debug!("adding service instance: {}", name);
@@ -196,7 +225,13 @@ impl DeployApi {
         {
             error!("Error writing activity: {}", err);
         }
-        self.dio.commit().await?;
+
+        // The instance chain and the wallet are committed together so that a
+        // crash in between does not leave a dangling instance behind
+        let mut trans = self.multi_chain_transaction().await?;
+        trans.add(DeployApi::instance_participant(&key).as_str(), &dio);
+        trans.add("wallet", &self.dio);
+        trans.commit().await?;
 
         Ok(instance)
     }
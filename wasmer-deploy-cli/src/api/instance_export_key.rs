@@ -0,0 +1,58 @@
+use ate::prelude::*;
+use error_chain::bail;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::error::*;
+use crate::model::{InstanceExportKey, ServiceInstance, WalletInstance};
+
+use super::*;
+
+impl DeployApi {
+    /// Returns the key that signs the capability tokens of an instance (older
+    /// instances are given one the first time it is needed), the caller must
+    /// commit both the wallet and the instance afterwards
+    pub async fn instance_export_key(
+        &mut self,
+        wallet_instance: &WalletInstance,
+        service_instance: &mut DaoMut<ServiceInstance>,
+    ) -> Result<PrivateSignKey, InstanceError> {
+        let key = match self
+            .dio
+            .load::<InstanceExportKey>(&InstanceExportKey::id(wallet_instance.id))
+            .await
+        {
+            Ok(a) => a.take().key,
+            Err(LoadError(LoadErrorKind::NotFound(_), _)) => {
+                self.instance_export_key_create(wallet_instance.id)?
+            }
+            Err(err) => {
+                bail!(err);
+            }
+        };
+
+        let public_key = key.as_public_key().clone();
+        if service_instance.export_public_key.as_ref().map(|a| a.hash()) != Some(public_key.hash()) {
+            service_instance.as_mut().export_public_key = Some(public_key);
+        }
+        Ok(key)
+    }
+
+    /// Generates the key that signs the capability tokens of an instance and
+    /// stores it in the wallet where only the owner can read it
+    pub(super) fn instance_export_key_create(&self, instance_id: u128) -> Result<PrivateSignKey, InstanceError> {
+        let sudo_read = match self.session().read_keys(AteSessionKeyCategory::SudoKeys).next() {
+            Some(a) => a.clone(),
+            None => bail!(InstanceErrorKind::Unauthorized),
+        };
+
+        let key = PrivateSignKey::generate(KeySize::Bit192);
+        let mut dao = self.dio.store_with_key(
+            InstanceExportKey { key: key.clone() },
+            InstanceExportKey::id(instance_id),
+        )?;
+        dao.auth_mut().read = ReadOption::from_key(&sudo_read);
+        dao.auth_mut().write = WriteOption::Inherit;
+        Ok(key)
+    }
+}
@@ -49,15 +49,27 @@ impl DeployApi {
         )
     }
 
+    /// Returns true if the instance belongs to the wallet of this API
+    fn is_own_instance(&self, instance: &DaoMut<WalletInstance>) -> bool {
+        instance.parent_id() == self.wallet.parent_id()
+            && instance.parent().map(|a| a.collection_id) == Some(INSTANCE_COLLECTION_ID)
+    }
+
     pub async fn instance_find_exact(&self, name: &str) -> Result<DaoMut<WalletInstance>, InstanceError>
     {
-        let instance = self.instances()
-            .await
-            .iter_mut_ext(true, true)
+        // Instances are indexed by name so the wallet is only scanned when
+        // the index does not know about the instance
+        let instance = self.dio
+            .find_by_ext::<WalletInstance>(self.wallet.parent_id(), "name", WalletInstance::index_name(name))
             .await?
-            .filter(|i| i.name.eq_ignore_ascii_case(name))
+            .into_iter()
+            .filter(|i| self.is_own_instance(i))
             .next();
 
+        let instance = match instance {
+            Some(a) => Some(a),
+            None => self.instance_scan_exact(name).await?,
+        };
         let instance = match instance {
             Some(a) => a,
             None => {
@@ -68,6 +80,40 @@ impl DeployApi {
         Ok(instance)
     }
 
+    /// Scans all the instances of the wallet for the name, an instance that
+    /// is found this way was missed by the index hence the index is repaired
+    async fn instance_scan_exact(&self, name: &str) -> Result<Option<DaoMut<WalletInstance>>, InstanceError>
+    {
+        let mut instances = self.instances().await;
+        let instances = instances
+            .iter_mut_ext(true, true)
+            .await?
+            .collect::<Vec<_>>();
+        let keys = instances
+            .iter()
+            .map(|i| i.key().clone())
+            .collect::<Vec<_>>();
+        let instance = instances
+            .into_iter()
+            .filter(|i| i.name.eq_ignore_ascii_case(name))
+            .next();
+
+        if instance.is_some() {
+            warn!("instance index is missing '{}' - rebuilding it", name);
+            let dio = self.dio.dio.trans(TransactionScope::Local).await;
+            let repaired = match dio.repair_index::<WalletInstance>(keys).await {
+                Ok(_) => dio.commit().await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = repaired {
+                // Read-only chains (such as snapshots) can not be repaired
+                dio.cancel();
+                debug!("failed to repair the instance index - {}", err);
+            }
+        }
+        Ok(instance)
+    }
+
     pub async fn instance_find(&self, name: &str) -> Result<DaoMut<WalletInstance>, InstanceError>
     {
         // If the name supplied is not good enough then fail
@@ -77,7 +123,18 @@ impl DeployApi {
         }
         let name = name.as_str();
 
-        // Find the instance that best matches the name supplied
+        // An instance with exactly this name is found through the index
+        let exact = self.dio
+            .find_by_ext::<WalletInstance>(self.wallet.parent_id(), "name", WalletInstance::index_name(name))
+            .await?
+            .into_iter()
+            .filter(|i| self.is_own_instance(i))
+            .next();
+        if let Some(instance) = exact {
+            return Ok(instance);
+        }
+
+        // Otherwise find the instance that best matches the name supplied
         let mut instances = self.instances().await;
         let instances = instances
             .iter_mut_ext(true, true)
@@ -108,4 +165,13 @@ impl DeployApi {
         let chain = self.registry.open(&db_url?, &instance_key, true).await?;
         Ok(chain.as_arc())
     }
+
+    /// Asks the root that hosts the chain of an instance how much of its
+    /// storage quota the instance is using
+    pub async fn instance_quota(&self, instance: &WalletInstance) -> Result<QuotaStatus, InstanceError> {
+        let instance_key = ChainKey::from(instance.chain.clone());
+        let db_url: Result<_, InstanceError> = self.db_url.clone().ok_or_else(|| InstanceErrorKind::Unsupported.into());
+        let status = self.registry.quota_status(&db_url?, &instance_key).await?;
+        Ok(status)
+    }
 }
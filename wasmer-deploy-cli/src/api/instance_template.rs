@@ -0,0 +1,201 @@
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+use error_chain::bail;
+use ate_files::accessor::FileAccessor;
+use ate_files::accessor::RequestContext;
+use ate::crypto::AteHash;
+use std::path::Path;
+use ate::prelude::*;
+use ate_files::prelude::*;
+use fxhash::FxHashMap;
+use std::sync::Arc;
+
+use crate::error::*;
+use crate::model::*;
+
+use super::*;
+
+impl DeployApi {
+    /// Loads a template from a local file (JSON or YAML) or, when no such
+    /// file exists, from the templates published by the instance authority
+    /// (a particular version is selected with name@version)
+    pub async fn instance_template(
+        &self,
+        template: &str,
+        db_url: &url::Url,
+        instance_authority: &str,
+    ) -> Result<(InstanceTemplate, InstanceTemplateRef), InstanceError>
+    {
+        let path = Path::new(template);
+        let ext = path.extension()
+            .map(|a| a.to_string_lossy().to_lowercase());
+        let yaml = ext.as_ref().map(|a| a == "yaml" || a == "yml").unwrap_or(false);
+        if path.is_file() || yaml || ext.as_ref().map(|a| a == "json").unwrap_or(false) {
+            debug!("loading template from file: {}", template);
+            let data = match std::fs::read_to_string(path) {
+                Ok(a) => a,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    bail!(InstanceErrorKind::TemplateNotFound(template.to_string()));
+                }
+                Err(err) => {
+                    bail!(err);
+                }
+            };
+            let ret = InstanceTemplate::parse(data.as_str(), yaml)?;
+            let ret_ref = InstanceTemplateRef {
+                name: ret.name.clone(),
+                version: ret.version.clone(),
+                source: template.to_string(),
+            };
+            return Ok((ret, ret_ref));
+        }
+
+        // Published templates are readable by everyone
+        let (name, version) = match template.split_once("@") {
+            Some((name, version)) => (name, Some(version)),
+            None => (template, None),
+        };
+        let chain_key = InstanceTemplate::chain_key(instance_authority);
+        debug!("loading template ({}) from chain: {}", template, chain_key);
+        let chain = self.registry.open(db_url, &chain_key, false).await?;
+        let session = AteSessionUser::default();
+        let dio = chain.dio(&session).await;
+        let key = InstanceTemplate::primary_key(name, version);
+        if dio.exists(&key).await == false {
+            bail!(InstanceErrorKind::TemplateNotFound(template.to_string()));
+        }
+        let ret = dio.load::<InstanceTemplate>(&key).await?.take();
+        ret.validate()?;
+
+        let ret_ref = InstanceTemplateRef {
+            name: ret.name.clone(),
+            version: ret.version.clone(),
+            source: chain_key.to_string(),
+        };
+        Ok((ret, ret_ref))
+    }
+
+    /// Seeds the directories and files of a template into the file system of
+    /// an instance, they are all written into the transaction supplied so that
+    /// they are committed together with the rest of the instance
+    pub(crate) async fn instance_template_seed(
+        chain_api: &FileAccessor,
+        dio: &Arc<DioMut>,
+        root: u64,
+        root_mode: u32,
+        template: &InstanceTemplate,
+    ) -> Result<(), InstanceError>
+    {
+        let req = chain_api.session_context();
+        let dir_mode = root_mode & 0o777;
+        let file_mode = dir_mode & 0o666;
+
+        let mut dirs = TemplateDirs {
+            chain_api,
+            req: &req,
+            mode: dir_mode,
+            root: dio.load::<Inode>(&PrimaryKey::from(root)).await?,
+            staged: FxHashMap::default(),
+        };
+
+        for dir in template.directories.iter() {
+            let path = template_path(dir.as_str())?;
+            dirs.mkdir_all(&path[..]).await?;
+        }
+
+        for file in template.files.iter() {
+            let path = template_path(file.path.as_str())?;
+            let (name, path) = match path.split_last() {
+                Some(a) => a,
+                None => {
+                    bail!(InstanceErrorKind::InvalidTemplate(format!("the file path ({}) is not valid", file.path)));
+                }
+            };
+            debug!("writing file: {}", file.path);
+            let mode = file.mode.unwrap_or(file_mode);
+            let parent = dirs.mkdir_all(path).await?;
+            chain_api
+                .stage_node(&req, parent, name, mode, FileKind::RegularFile, file.contents.as_bytes())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Exports the binaries of a template from an instance that is being created
+    pub(crate) fn instance_template_exports(
+        service_instance: &mut DaoMut<ServiceInstance>,
+        template: &InstanceTemplate,
+    ) -> Result<(), InstanceError>
+    {
+        for export in template.exports.iter() {
+            debug!("exporting binary: {}", export.binary);
+            service_instance.as_mut().exports.push(InstanceExport {
+                access_token: AteHash::generate().to_hex_string(),
+                binary: export.binary.clone(),
+                distributed: export.pinned == false,
+                http: export.http,
+                https: export.https,
+                bus: export.bus,
+                pinned: None,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Directories of an instance that a template is being seeded into, the
+/// ones that are created by the template are only written when the whole
+/// transaction is committed so they are tracked here until then
+struct TemplateDirs<'a> {
+    chain_api: &'a FileAccessor,
+    req: &'a RequestContext,
+    mode: u32,
+    root: DaoMut<Inode>,
+    staged: FxHashMap<String, DaoMut<Inode>>,
+}
+
+impl<'a> TemplateDirs<'a> {
+    /// Creates a directory and any of its parents that do not yet exist
+    async fn mkdir_all(&mut self, path: &[&str]) -> Result<&mut DaoMut<Inode>, InstanceError> {
+        for n in 1..=path.len() {
+            let key = path[..n].join("/");
+            if self.staged.contains_key(&key) {
+                continue;
+            }
+            let name = path[n - 1];
+            let parent = match n {
+                1 => &mut self.root,
+                _ => self.staged.get_mut(&path[..n - 1].join("/")).unwrap(),
+            };
+
+            // Directories that already exist are loaded into the transaction
+            let existing = parent
+                .children
+                .iter()
+                .await?
+                .filter(|c| self.chain_api.name_matches(c.dentry.name.as_str(), name))
+                .map(|c| c.key().clone())
+                .next();
+            let dir = match existing {
+                Some(existing) => {
+                    let dir = parent.trans().load::<Inode>(&existing).await?;
+                    if dir.kind != FileKind::Directory {
+                        bail!(InstanceErrorKind::InvalidTemplate(format!("the path ({}) is not a directory", key)));
+                    }
+                    dir
+                }
+                None => {
+                    debug!("creating directory: {}", key);
+                    self.chain_api
+                        .stage_node(self.req, parent, name, self.mode, FileKind::Directory, &[])
+                        .await?
+                }
+            };
+            self.staged.insert(key, dir);
+        }
+        Ok(match path.len() {
+            0 => &mut self.root,
+            _ => self.staged.get_mut(&path.join("/")).unwrap(),
+        })
+    }
+}
@@ -12,6 +12,7 @@ mod delete_wallet;
 mod deposit;
 mod history;
 mod reconcile;
+mod rotate;
 mod transfer;
 mod wallet_summary;
 mod withdraw;
@@ -19,6 +20,10 @@ mod instance_create;
 mod instance_summary;
 mod instance_action;
 mod instance_client;
+mod instance_export_key;
+mod instance_template;
+mod multi_chain;
+mod sub_wallet;
 
 pub use accessor::*;
 pub use bag::*;
@@ -34,10 +39,15 @@ pub use delete_wallet::*;
 pub use deposit::*;
 pub use history::*;
 pub use reconcile::*;
+pub use rotate::*;
 pub use transfer::*;
 pub use wallet_summary::*;
 pub use withdraw::*;
 pub use instance_create::*;
 pub use instance_summary::*;
 pub use instance_action::*;
-pub use instance_client::*;
\ No newline at end of file
+pub use instance_client::*;
+pub use instance_export_key::*;
+pub use instance_template::*;
+pub use multi_chain::*;
+pub use sub_wallet::*;
\ No newline at end of file
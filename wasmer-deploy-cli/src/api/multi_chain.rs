@@ -0,0 +1,83 @@
+use async_trait::async_trait;
+use ate::prelude::*;
+use std::ops::Deref;
+use std::sync::Arc;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::error::*;
+
+use super::*;
+
+/// Prefix of the participant names that refer to the chain of an instance
+const INSTANCE_PARTICIPANT_PREFIX: &'static str = "instance:";
+
+/// Opens the chains that take part in transactions that span the wallet
+/// and the chains of its instances
+pub struct DeployResolver {
+    registry: Arc<Registry>,
+    db_url: Option<url::Url>,
+    wallet: Arc<Chain>,
+    session: Box<dyn AteSession>,
+}
+
+#[async_trait]
+impl MultiChainResolver for DeployResolver {
+    async fn open(&self, name: &str) -> Result<Arc<DioMut>, MultiChainError> {
+        if name == "wallet" {
+            return Ok(self.wallet.dio_mut(self.session.deref()).await?);
+        }
+
+        let key = match (name.strip_prefix(INSTANCE_PARTICIPANT_PREFIX), self.db_url.as_ref()) {
+            (Some(key), Some(db_url)) => (ChainKey::from(key.to_string()), db_url),
+            _ => {
+                return Err(MultiChainErrorKind::UnknownParticipant(name.to_string()).into());
+            }
+        };
+        let chain = self
+            .registry
+            .open(key.1, &key.0, true)
+            .await
+            .map_err(|err| {
+                debug!("failed to open the chain ({}) - {}", key.0, err);
+                MultiChainErrorKind::UnknownParticipant(name.to_string())
+            })?;
+        Ok(chain.as_arc().dio_mut(self.session.deref()).await?)
+    }
+}
+
+impl DeployApi {
+    /// Name of the participant for the chain of an instance
+    pub fn instance_participant(chain: &ChainKey) -> String {
+        format!("{}{}", INSTANCE_PARTICIPANT_PREFIX, chain.name)
+    }
+
+    pub fn multi_chain_resolver(&self) -> DeployResolver {
+        DeployResolver {
+            registry: self.registry.clone(),
+            db_url: self.db_url.clone(),
+            wallet: self.dio.chain().clone(),
+            session: self.session().clone_session(),
+        }
+    }
+
+    /// Starts a transaction whose intent is recorded against the wallet (the
+    /// changes to the wallet itself should be added as the "wallet" participant)
+    pub async fn multi_chain_transaction(&self) -> Result<MultiChainTransaction, InstanceError> {
+        let session = self.session().clone_session();
+        let coordinator = self.dio.chain().dio_mut(session.deref()).await?;
+        Ok(MultiChainTransaction::new(&coordinator, self.wallet.key()))
+    }
+
+    /// Finishes or rolls back any transactions that were interrupted
+    pub async fn recover_transactions(&self) -> Result<usize, InstanceError> {
+        let session = self.session().clone_session();
+        let coordinator = self.dio.chain().dio_mut(session.deref()).await?;
+        let resolver = self.multi_chain_resolver();
+        let ret = MultiChainTransaction::recover(&coordinator, self.wallet.key(), &resolver).await?;
+        if ret > 0 {
+            info!("recovered {} interrupted transactions", ret);
+        }
+        Ok(ret)
+    }
+}
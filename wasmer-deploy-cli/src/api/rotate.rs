@@ -0,0 +1,248 @@
+use chrono::Utc;
+use error_chain::bail;
+use fxhash::FxHashSet;
+use std::ops::Deref;
+use std::time::Duration;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::cmd::*;
+use crate::error::*;
+use crate::model::*;
+use ate::crypto::SignedProtectedData;
+use ate::prelude::*;
+
+use super::*;
+
+/// Default number of coins that are rotated within the same transaction
+pub const COIN_ROTATE_DEFAULT_BATCH_SIZE: usize = 50;
+
+/// Number of times in a row that a batch is retried after it collided with
+/// another operation on the wallet before the rotation gives up
+const COIN_ROTATE_MAX_ATTEMPTS: u32 = 10;
+
+#[derive(Debug, Clone, Default)]
+pub struct CoinRotateSummary {
+    pub rotated: usize,
+    pub batches: usize,
+    pub retries: usize,
+}
+
+impl DeployApi {
+    fn coin_rotation_vec(&self) -> DaoVec<CoinRotation> {
+        DaoVec::<CoinRotation>::new_orphaned_mut(&self.dio, self.wallet.key().clone(), COIN_ROTATION_COLLECTION_ID)
+    }
+
+    /// Rotates the ownership keys of all the coins in the wallet (or only those
+    /// of a particular currency) in transactions of at most `batch_size` coins,
+    /// batches that collide with a concurrent spend are retried
+    pub async fn rotate_coins(
+        &mut self,
+        currency: Option<NationalCurrency>,
+        batch_size: usize,
+    ) -> Result<CoinRotateSummary, WalletError> {
+        // If anything has been left on the DIO then we need to fail
+        // as this loop will invoke cancel during its recovery process
+        if self.dio.has_uncommitted() {
+            bail!(WalletErrorKind::CoreError(CoreErrorKind::InternalError(ate::utils::obscure_error_str("unable to rotate the coins of the wallet when there are uncommitted transactions on the DIO"))));
+        }
+
+        let batch_size = batch_size.max(1);
+        let mut ret = CoinRotateSummary::default();
+        let mut rotated = FxHashSet::default();
+        let mut attempt = 0u32;
+        loop {
+            match self.rotate_coin_batch(currency, batch_size, &mut rotated).await {
+                Ok(0) => break,
+                Ok(n) => {
+                    trace!("rotate: batch of {} coins rotated", n);
+                    ret.rotated += n;
+                    ret.batches += 1;
+                    attempt = 0;
+                }
+                Err(err) if is_rotate_retryable(&err) && attempt < COIN_ROTATE_MAX_ATTEMPTS => {
+                    debug!("rotate: batch collided with another operation (attempt={}) - {}", attempt, err);
+                    attempt += 1;
+                    ret.retries += 1;
+                    ate::engine::sleep(Duration::from_millis(100 * (1 << attempt.min(5)))).await;
+                }
+                Err(err) => {
+                    return Err(err);
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    async fn rotate_coin_batch(
+        &mut self,
+        currency: Option<NationalCurrency>,
+        batch_size: usize,
+        rotated: &mut FxHashSet<PrimaryKey>,
+    ) -> Result<usize, WalletError> {
+        // Lock the wallet so that its coins are not spent while we rotate them
+        let lock = self.wallet.try_lock_with_timeout(self.lock_timeout).await?;
+        if lock == false {
+            bail!(WalletErrorKind::WalletLocked);
+        }
+        let ret = self.__rotate_coin_batch(currency, batch_size, rotated).await;
+        if ret.is_err() {
+            self.dio.cancel();
+        }
+
+        // Commit unlock and return the result
+        self.dio.commit().await?;
+        self.wallet.unlock().await?;
+        ret
+    }
+
+    async fn __rotate_coin_batch(
+        &mut self,
+        currency: Option<NationalCurrency>,
+        batch_size: usize,
+        rotated: &mut FxHashSet<PrimaryKey>,
+    ) -> Result<usize, WalletError> {
+        // The linkage rows are signed by the owner of the wallet
+        let session = self.dio.session().clone_session();
+        let sign_key = match session
+            .write_keys(AteSessionKeyCategory::NonGroupKeys)
+            .next()
+        {
+            Some(a) => a.clone(),
+            None => {
+                bail!(WalletErrorKind::CoreError(CoreErrorKind::MissingTokenKey));
+            }
+        };
+
+        // Pick the coins that make up this batch (skipping any that were
+        // already rotated by an earlier batch)
+        let mut bags = self
+            .wallet
+            .as_mut()
+            .bags
+            .iter_mut()
+            .await?
+            .filter(|(k, _)| currency.map(|c| c == k.currency).unwrap_or(true))
+            .collect::<Vec<_>>();
+        let batch = bags
+            .iter()
+            .flat_map(|(_, bag)| bag.coins.iter())
+            .filter(|a| rotated.contains(&a.coin) == false)
+            .take(batch_size)
+            .map(|a| a.clone())
+            .collect::<Vec<_>>();
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        // Before the coins are rotated the new ownerships are added to the
+        // inbox so that if something goes wrong they can be recovered on the
+        // next reconcile
+        let new_token = EncryptKey::generate(KeySize::Bit192);
+        let mut pending = Vec::new();
+        for mut new_owner in (BagOfCoins { coins: batch.clone() }).to_ownerships().await {
+            new_owner.token = new_token.clone();
+            pending.push(self.wallet.inbox.push_with_dio(&self.dio, new_owner)?);
+        }
+        self.dio.commit().await?;
+
+        // Rotate the ownership of the coins
+        let result = coin_rotate_command(
+            &self.registry,
+            batch.clone(),
+            new_token.clone(),
+            session.deref(),
+            self.auth.clone(),
+            None,
+        )
+        .await;
+        if let Err(err) = result {
+            for p in pending {
+                p.delete()?;
+            }
+            self.dio.commit().await?;
+            return Err(err.into());
+        }
+
+        // Update the coins in the wallet and record the linkage between the
+        // old and new owners
+        let batch_keys = batch.iter().map(|a| a.coin).collect::<FxHashSet<_>>();
+        for (_, bag) in bags.iter_mut() {
+            let mut bag = bag.as_mut();
+            for coin in bag.coins.iter_mut() {
+                if batch_keys.contains(&coin.coin) {
+                    coin.owner.token = new_token.clone();
+                }
+            }
+        }
+        let new_owner = new_token.hash();
+        for coin in batch.iter() {
+            let link = CoinRotationLink {
+                coin: coin.coin,
+                old_owner: coin.owner.token.hash(),
+                new_owner: new_owner.clone(),
+                when: Utc::now(),
+            };
+            let mut row = self.dio.store(CoinRotation {
+                link: SignedProtectedData::new(&sign_key, link)?,
+                signer: sign_key.as_public_key().clone(),
+            })?;
+            row.attach_orphaned_ext(self.wallet.key(), COIN_ROTATION_COLLECTION_ID)?;
+            rotated.insert(coin.coin);
+        }
+        for p in pending {
+            p.delete()?;
+        }
+        Ok(batch.len())
+    }
+
+    /// Returns the verified rotations of a coin (oldest first) which together
+    /// link the original owner of the coin to its current owner, only records
+    /// signed by the owner of this wallet (the current session) are trusted
+    pub async fn coin_rotations(&self, coin: &PrimaryKey) -> Result<Vec<CoinRotationLink>, WalletError> {
+        let owners = self
+            .dio
+            .session()
+            .write_keys(AteSessionKeyCategory::NonGroupKeys)
+            .map(|a| a.as_public_key().clone())
+            .collect::<Vec<_>>();
+        self.coin_rotations_ext(coin, &owners[..]).await
+    }
+
+    /// Returns the rotations of a coin (oldest first) that were signed by one
+    /// of the supplied owner keys, this is used by auditors who were given the
+    /// read key of the wallet but not its write keys
+    pub async fn coin_rotations_ext(
+        &self,
+        coin: &PrimaryKey,
+        owners: &[PublicSignKey],
+    ) -> Result<Vec<CoinRotationLink>, WalletError> {
+        let mut ret = self
+            .coin_rotation_vec()
+            .iter()
+            .await?
+            .filter(|a| a.link.coin == *coin)
+            .filter(|a| {
+                let verified = a.verify(owners);
+                if verified == false {
+                    warn!("ignoring coin rotation not signed by the wallet owner (coin={})", coin);
+                }
+                verified
+            })
+            .map(|a| a.take().link.deref().clone())
+            .collect::<Vec<_>>();
+        ret.sort_by(|a, b| a.when.cmp(&b.when));
+        Ok(ret)
+    }
+}
+
+/// Failures caused by another operation on the wallet (or its coins) that
+/// will likely succeed when the batch is built again - a coin that was spent
+/// by a concurrent operation is reported by the server as no longer owned
+fn is_rotate_retryable(err: &WalletError) -> bool {
+    match err.kind() {
+        WalletErrorKind::WalletLocked => true,
+        WalletErrorKind::CoinError(CoinErrorKind::NoOwnership) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,299 @@
+use chrono::Utc;
+use error_chain::bail;
+use num_traits::*;
+use std::ops::Deref;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::cmd::*;
+use crate::error::*;
+use crate::model::*;
+use ate::prelude::*;
+
+use super::*;
+
+impl DeployApi {
+    fn sub_wallet_vec(&self) -> DaoVec<SubWallet> {
+        DaoVec::<SubWallet>::new_orphaned_mut(&self.dio, self.wallet.key().clone(), SUB_WALLET_COLLECTION_ID)
+    }
+
+    pub async fn list_sub_wallets(&self) -> Result<Vec<DaoMut<SubWallet>>, WalletError> {
+        let mut ret = self
+            .sub_wallet_vec()
+            .iter_mut()
+            .await?
+            .collect::<Vec<_>>();
+        ret.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(ret)
+    }
+
+    pub async fn find_sub_wallet(&self, name: &str) -> Result<DaoMut<SubWallet>, WalletError> {
+        self.sub_wallet_vec()
+            .iter_mut()
+            .await?
+            .filter(|a| a.name.eq_ignore_ascii_case(name))
+            .next()
+            .ok_or_else(|| WalletErrorKind::SubWalletNotFound(name.to_string()).into())
+    }
+
+    /// Registers a wallet (which must already be attached to this wallet) as
+    /// a sub-wallet that may spend up to the limit from this wallet
+    pub async fn create_sub_wallet(
+        &mut self,
+        name: &str,
+        wallet: &DaoMut<Wallet>,
+        limit: SpendingLimit,
+    ) -> Result<DaoMut<SubWallet>, WalletError> {
+        if self.find_sub_wallet(name).await.is_ok() {
+            bail!(WalletErrorKind::SubWalletAlreadyExists(name.to_string()));
+        }
+
+        let mut ret = self.dio.store(SubWallet {
+            name: name.to_lowercase(),
+            wallet: wallet.key().clone(),
+            limit,
+            spent: Vec::new(),
+            frozen: false,
+        })?;
+        ret.attach_orphaned_ext(self.wallet.key(), SUB_WALLET_COLLECTION_ID)?;
+        self.dio.commit().await?;
+        Ok(ret)
+    }
+
+    /// Freezing a sub-wallet stops it from spending anything, operations that
+    /// are already underway are stopped when they next commit
+    pub async fn freeze_sub_wallet(&mut self, name: &str, frozen: bool) -> Result<(), WalletError> {
+        let mut record = self.find_sub_wallet(name).await?;
+
+        // Spends hold the lock on the record while they are checked against
+        // the limit so the freeze waits for them to finish
+        if record.try_lock_with_timeout(self.lock_timeout).await? == false {
+            bail!(WalletErrorKind::WalletLocked);
+        }
+        record.as_mut().frozen = frozen;
+        let ret = self.dio.commit().await;
+        record.unlock().await?;
+        ret?;
+        Ok(())
+    }
+
+    /// Removes a sub-wallet, unless forced the sub-wallet must first be empty
+    pub async fn remove_sub_wallet(&mut self, name: &str, force: bool) -> Result<(), WalletError> {
+        let sub_api = self.sub_wallet_api(name).await?;
+        if sub_api.is_wallet_empty().await? == false && force == false {
+            bail!(WalletErrorKind::WalletNotEmpty);
+        }
+        if let Some(ctx) = sub_api.sub_wallet.clone() {
+            ctx.record.delete()?;
+        }
+        sub_api.delete_wallet(force).await?;
+        Ok(())
+    }
+
+    /// Returns an API for one of the sub-wallets of this wallet, spending from
+    /// this API draws coins from this wallet within the limits of the sub-wallet
+    pub async fn sub_wallet_api(&self, name: &str) -> Result<DeployApi, WalletError> {
+        let record = self.find_sub_wallet(name).await?;
+        let wallet = self.dio.load::<Wallet>(&record.wallet).await?;
+        let mut ret = build_api_accessor(
+            &self.dio,
+            wallet,
+            self.auth.clone(),
+            self.db_url.clone(),
+            &self.registry,
+        )
+        .await;
+        ret.sub_wallet = Some(SubWalletContext {
+            record,
+            parent: self.wallet.clone(),
+        });
+        Ok(ret)
+    }
+
+    /// Checks that the sub-wallet may spend this amount against the latest
+    /// copy of its record (so that a freeze or concurrent spend is seen)
+    async fn __sub_wallet_check(
+        &self,
+        ctx: &SubWalletContext,
+        currency: NationalCurrency,
+        amount: Decimal,
+    ) -> Result<SubWallet, WalletError> {
+        let session = self.dio.session().clone_session();
+        let dio = self.dio.chain().dio(session.deref()).await;
+        let record = dio.load::<SubWallet>(ctx.record.key()).await?.take();
+
+        if record.frozen {
+            bail!(WalletErrorKind::SubWalletFrozen(record.name));
+        }
+        if record.limit.currency != currency {
+            bail!(WalletErrorKind::CurrencyNotHeld(currency, record.limit.currency.to_string()));
+        }
+        let remaining = record.remaining(Utc::now());
+        if amount > remaining {
+            bail!(WalletErrorKind::SpendingLimitExceeded(
+                record.name,
+                record.limit.to_string(),
+                remaining
+            ));
+        }
+        Ok(record)
+    }
+
+    /// Makes sure the sub-wallet holds enough coins for an amount that it is
+    /// about to spend by carving whatever is missing from its parent, the
+    /// amount that was carved is returned. The caller must hold the lock on
+    /// the record of the sub-wallet so that the limit check is authoritative
+    pub(super) async fn __sub_wallet_top_up(
+        &mut self,
+        currency: NationalCurrency,
+        amount: Decimal,
+    ) -> Result<Decimal, WalletError> {
+        let ctx = match self.sub_wallet.clone() {
+            Some(a) => a,
+            None => {
+                return Ok(Decimal::zero());
+            }
+        };
+        self.__sub_wallet_check(&ctx, currency, amount).await?;
+
+        // Carve the shortfall out of the parent wallet
+        let held = self.__wallet_currency_summary(currency).await?.total;
+        if held >= amount {
+            return Ok(Decimal::zero());
+        }
+        let shortfall = amount - held;
+        trace!("sub-wallet: topping up {} {} from the parent", shortfall, currency);
+        let mut parent = self.__sub_wallet_parent_api(&ctx).await;
+        let carved = parent.carve_bag(currency, shortfall, true).await?;
+        let wallet = self.wallet.clone();
+        self.__sub_wallet_move(carved, &wallet).await?;
+
+        // Record the movement of the funds in both wallets
+        let activity = activities::FundsTransferred {
+            when: Utc::now(),
+            by: self.user_identity(),
+            amount: shortfall,
+            currency,
+            from: ctx.parent.name.clone(),
+            to: format!("{}/{}", ctx.parent.name, ctx.record.name),
+        };
+        if let Err(err) = parent
+            .record_activity(HistoricActivity::TransferOut(activity.clone()))
+            .await
+        {
+            error!("Error writing activity: {}", err);
+        }
+        if let Err(err) = self
+            .record_activity(HistoricActivity::TransferIn(activity))
+            .await
+        {
+            error!("Error writing activity: {}", err);
+        }
+        self.dio.commit().await?;
+        Ok(shortfall)
+    }
+
+    /// Hands coins that were topped up for a spend that then failed back to
+    /// the parent wallet so that they are not left outside of the limit
+    pub(super) async fn __sub_wallet_return(
+        &mut self,
+        currency: NationalCurrency,
+        amount: Decimal,
+    ) -> Result<(), WalletError> {
+        let ctx = match self.sub_wallet.take() {
+            Some(a) => a,
+            None => {
+                return Ok(());
+            }
+        };
+        trace!("sub-wallet: returning {} {} to the parent", amount, currency);
+
+        // The coins are carved without the limit as they are not being spent
+        let carved = self.__carve_bag(currency, amount, true).await;
+        self.sub_wallet = Some(ctx.clone());
+        let parent = ctx.parent.clone();
+        self.__sub_wallet_move(carved?, &parent).await?;
+        self.dio.commit().await?;
+        Ok(())
+    }
+
+    async fn __sub_wallet_parent_api(&self, ctx: &SubWalletContext) -> DeployApi {
+        build_api_accessor(
+            &self.dio,
+            ctx.parent.clone(),
+            self.auth.clone(),
+            self.db_url.clone(),
+            &self.registry,
+        )
+        .await
+    }
+
+    /// Moves a bag of coins into the inbox of another wallet (they are
+    /// collected into its bags on its next reconcile) and rotates them so
+    /// that only that wallet holds the new ownership
+    async fn __sub_wallet_move(
+        &mut self,
+        coins: BagOfCoins,
+        to: &DaoMut<Wallet>,
+    ) -> Result<(), WalletError> {
+        let new_token = EncryptKey::generate(KeySize::Bit192);
+        for mut new_owner in coins.clone().to_ownerships().await.into_iter() {
+            new_owner.token = new_token.clone();
+            to.inbox.push_with_dio(&self.dio, new_owner)?;
+        }
+        self.dio.commit().await?;
+
+        let session = self.dio.session().clone_session();
+        coin_rotate_command(
+            &self.registry,
+            coins.coins,
+            new_token,
+            session.deref(),
+            self.auth.clone(),
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Records an amount that the sub-wallet spent, this is committed along
+    /// with the coins that were carved so that the limit is updated atomically
+    pub(super) async fn __sub_wallet_spend(
+        &mut self,
+        currency: NationalCurrency,
+        amount: Decimal,
+    ) -> Result<(), WalletError> {
+        let mut ctx = match self.sub_wallet.clone() {
+            Some(a) => a,
+            None => {
+                return Ok(());
+            }
+        };
+        let mut record = self.__sub_wallet_check(&ctx, currency, amount).await?;
+        record.record_spend(Utc::now(), amount);
+        *ctx.record.as_mut() = record;
+
+        // The parent attributes the spending to the sub-wallet
+        let mut parent = self.__sub_wallet_parent_api(&ctx).await;
+        parent
+            .record_activity(HistoricActivity::SubWalletSpent(activities::SubWalletSpent {
+                when: Utc::now(),
+                by: self.user_identity(),
+                sub_wallet: ctx.record.name.clone(),
+                amount,
+                currency,
+            }))
+            .await?;
+        Ok(())
+    }
+}
+
+/// Sub-wallets are addressed as `<wallet>/<sub-wallet>`, this splits the
+/// name into its two halves
+pub fn split_sub_wallet_name(name: &str) -> (&str, Option<&str>) {
+    match name.split_once('/') {
+        Some((wallet, sub)) if sub.is_empty() == false => (wallet, Some(sub)),
+        Some((wallet, _)) => (wallet, None),
+        None => (name, None),
+    }
+}
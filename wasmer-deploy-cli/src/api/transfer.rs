@@ -123,7 +123,7 @@ impl DeployApi {
             } else {
                 session.into()
             };
-            let mut dio = chain.dio_trans(&session, TransactionScope::Full).await;
+            let mut dio = chain.dio_trans(&session, TransactionScope::Full).await?;
             let wallet = get_wallet(destination, &mut dio, &identity).await?;
             build_api_accessor(&dio, wallet, self.auth.clone(), self.db_url.clone(), &registry).await
         };
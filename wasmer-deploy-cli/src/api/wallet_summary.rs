@@ -27,6 +27,20 @@ pub struct WalletSummary {
     pub currencies: BTreeMap<NationalCurrency, CurrencySummary>,
 }
 
+impl WalletSummary {
+    /// Comma separated list of the currencies held in the wallet
+    pub fn held_currencies(&self) -> String {
+        if self.currencies.len() <= 0 {
+            return "none".to_string();
+        }
+        self.currencies
+            .keys()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
 impl DeployApi {
     pub async fn wallet_summary(&mut self) -> Result<WalletSummary, WalletError> {
         // Determine what currencies are currently stored within the wallet
@@ -46,6 +46,21 @@ struct Opts {
     /// Logs debug info to the console
     #[clap(short, long)]
     pub debug: bool,
+    /// Bypasses the local cache of group sessions and details (useful for debugging)
+    #[clap(long)]
+    pub no_cache: bool,
+    /// Format that the results of the command will be written in ('text' or 'json')
+    #[clap(short, long, default_value = "text")]
+    pub output: OutputFormat,
+    /// Runs the command without connecting to anything - only read-only commands
+    /// (wallet history and balance, instance list and details) are supported
+    /// and they are answered from the snapshot
+    #[clap(long, requires = "snapshot")]
+    pub offline: bool,
+    /// Path to a chain snapshot (created with 'wallet export') that offline
+    /// commands will read from
+    #[clap(long, requires = "offline")]
+    pub snapshot: Option<String>,
     #[clap(subcommand)]
     pub subcmd: SubCommand,
 }
@@ -105,6 +120,33 @@ enum SubCommand {
     /// Logout of the account by deleting the local token.
     #[clap()]
     Logout(OptsLogout),
+    /// Interactive wizard that walks through the first-time setup (account, login,
+    /// group and wallet) - running it again resumes from the steps not yet done.
+    #[clap()]
+    Setup(OptsSetup),
+}
+
+impl SubCommand {
+    /// Name of the sub command as it is typed on the command line
+    fn command(&self) -> &'static str {
+        match self {
+            SubCommand::User(..) => "user",
+            SubCommand::Domain(..) => "domain",
+            SubCommand::Db(..) => "db",
+            #[cfg(feature = "bus")]
+            SubCommand::Bus(..) => "bus",
+            #[cfg(not(feature_os = "wasi"))]
+            SubCommand::Token(..) => "token",
+            SubCommand::Service(..) => "service",
+            SubCommand::Contract(..) => "contract",
+            SubCommand::Instance(..) => "instance",
+            SubCommand::Network(..) => "network",
+            SubCommand::Wallet(..) => "wallet",
+            SubCommand::Login(..) => "login",
+            SubCommand::Logout(..) => "logout",
+            SubCommand::Setup(..) => "setup",
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -169,6 +211,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 async fn main_async() -> Result<(), Box<dyn std::error::Error>> {
+    let mut output = OutputFormat::Text;
+    let mut verbose = false;
+    match main_run(&mut output, &mut verbose).await {
+        Ok(()) => Ok(()),
+        Err(err) if output.is_json() => {
+            wasmer_deploy_cli::output::emit_error(output, &err);
+            std::process::exit(1);
+        }
+        Err(err) => {
+            // Failures to connect are shown as a plain message rather than the error chain
+            if let Some(failure) = wasmer_deploy_cli::helper::connect_failure(err.as_ref()) {
+                wasmer_deploy_cli::output::emit_error(output, failure);
+                std::process::exit(1);
+            }
+            ate::utils::ErrorRenderer::new()
+                .with_hints(ERROR_HINTS)
+                .with_verbose(verbose)
+                .eprint(err.as_ref());
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn main_run(output: &mut OutputFormat, verbose: &mut bool) -> Result<(), Box<dyn std::error::Error>> {
     // Initialize the logging and panic hook
     #[cfg(target_os = "wasi")]
     init_wasi_hook().await;
@@ -199,6 +265,7 @@ async fn main_async() -> Result<(), Box<dyn std::error::Error>> {
             "wallet" => Some(SubCommand::Wallet(OptsWallet::parse())),
             "login" => Some(SubCommand::Login(OptsLogin::parse())),
             "logout" => Some(SubCommand::Logout(OptsLogout::parse())),
+            "setup" => Some(SubCommand::Setup(OptsSetup::parse())),
             _ => None,
         };
         match cmd {
@@ -214,12 +281,23 @@ async fn main_async() -> Result<(), Box<dyn std::error::Error>> {
                 dns_sec: false,
                 dns_server: "8.8.8.8".to_string(),
                 debug: false,
+                no_cache: false,
+                output: OutputFormat::Text,
+                offline: false,
+                snapshot: None,
                 subcmd: cmd,
             },
-            None => Opts::parse(),
+            None => {
+                let opts = Opts::parse();
+                *verbose = opts.verbose > 0;
+                opts
+            }
         }
     };
 
+    // Record the output format so that errors can be rendered in the same format
+    *output = opts.output;
+
     // We upgrade the verbosity for certain commands by default
     opts.verbose = opts.verbose.max(match &opts.subcmd {
         #[cfg(feature = "bus")]
@@ -228,6 +306,9 @@ async fn main_async() -> Result<(), Box<dyn std::error::Error>> {
     });
 
     ate::log_init(opts.verbose, opts.debug);
+    if opts.no_cache {
+        wasmer_auth::helper::group_cache().set_enabled(false);
+    }
     let auth = wasmer_auth::prelude::origin_url(&opts.auth_url, "auth");
 
     // Build the ATE configuration object
@@ -257,6 +338,7 @@ async fn main_async() -> Result<(), Box<dyn std::error::Error>> {
     // Do we need a token
     let needs_token = match &opts.subcmd {
         SubCommand::Login(..) => false,
+        SubCommand::Setup(..) => false,
         SubCommand::Token(..) => false,
         #[cfg(feature = "bus")]
         SubCommand::Bus(..) => false,
@@ -285,6 +367,26 @@ async fn main_async() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    // Offline commands are answered from a snapshot of the chain
+    if let Some(snapshot) = opts.snapshot {
+        let command = opts.subcmd.command();
+        match opts.subcmd {
+            SubCommand::Wallet(opts_wallet) => {
+                main_offline_wallet(opts_wallet.source, opts.token_path, auth, snapshot, opts.output).await?
+            }
+            SubCommand::Instance(opts_instance) => {
+                main_offline_instance(opts_instance.purpose, opts.token_path, auth, snapshot, opts.output).await?
+            }
+            _ => {
+                return Err(CoreError::from_kind(CoreErrorKind::OfflineUnsupported(
+                    command.to_string(),
+                ))
+                .into());
+            }
+        }
+        return Ok(());
+    }
+
     // Determine what we need to do
     match opts.subcmd {
         SubCommand::User(opts_user) => {
@@ -305,10 +407,10 @@ async fn main_async() -> Result<(), Box<dyn std::error::Error>> {
             main_opts_token(opts_token, None, Some(opts.token_path), auth, "Domain name").await?;
         }
         SubCommand::Wallet(opts_wallet) => {
-            main_opts_wallet(opts_wallet.source, opts.token_path, auth).await?
+            main_opts_wallet(opts_wallet.source, opts.token_path, auth, opts.output).await?
         }
         SubCommand::Contract(opts_contract) => {
-            main_opts_contract(opts_contract.purpose, opts.token_path, auth).await?;
+            main_opts_contract(opts_contract.purpose, opts.token_path, auth, opts.output).await?;
         }
         SubCommand::Service(opts_service) => {
             main_opts_service(opts_service.purpose, opts.token_path, auth).await?;
@@ -319,13 +421,16 @@ async fn main_async() -> Result<(), Box<dyn std::error::Error>> {
         SubCommand::Instance(opts_instance) => {
             let db_url = wasmer_auth::prelude::origin_url(&opts_instance.db_url, "db");
             let inst_url = wasmer_auth::prelude::origin_url(&opts_instance.inst_url, "inst");
-            main_opts_instance(opts_instance.purpose, opts.token_path, auth, db_url, inst_url, opts_instance.security).await?;
+            main_opts_instance(opts_instance.purpose, opts.token_path, auth, db_url, inst_url, opts_instance.security, opts.output).await?;
         }
         SubCommand::Login(opts_login) => {
             main_opts_login(opts_login, opts.token_path, auth).await?
         },
         SubCommand::Logout(opts_logout) => {
-            main_opts_logout(opts_logout, opts.token_path).await?
+            main_opts_logout(opts_logout, opts.token_path, auth).await?
+        },
+        SubCommand::Setup(opts_setup) => {
+            main_opts_setup(opts_setup, opts.token_path, auth).await?
         },
     }
 
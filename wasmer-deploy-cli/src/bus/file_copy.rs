@@ -0,0 +1,238 @@
+use async_trait::async_trait;
+use ate_files::codes::*;
+use ate_files::prelude::*;
+use derivative::*;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+use wasmer_bus_fuse::api;
+use wasmer_bus_fuse::prelude::*;
+
+use super::conv_err;
+
+/// Number of bytes that are read and written at a time
+const COPY_BLOCK_SIZE: u32 = 1024 * 1024;
+
+/// Progress is reported after every file and after this many bytes
+const COPY_PROGRESS_INTERVAL: u64 = 8 * 1024 * 1024;
+
+/// Copy that runs entirely within the file system so that none of the
+/// data has to travel over the bus
+#[derive(Debug)]
+pub struct FileCopy {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl FileCopy {
+    pub fn start(
+        accessor: Arc<FileAccessor>,
+        context: RequestContext,
+        from: String,
+        to: String,
+        recursive: bool,
+        progress: Box<dyn Fn(api::CopyProgress) + Send + Sync + 'static>,
+        finished: Box<dyn Fn(FsResult<api::CopyProgress>) + Send + Sync + 'static>,
+    ) -> FileCopy {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut copier = Copier {
+            accessor,
+            context,
+            cancelled: cancelled.clone(),
+            progress: api::CopyProgress::default(),
+            last_report: 0,
+            on_progress: progress,
+        };
+        wasmer_bus::task::spawn(async move {
+            let ret = copier
+                .copy(Path::new(&from), Path::new(&to), recursive)
+                .await
+                .map(|_| copier.progress.clone());
+            if let Err(err) = ret.as_ref() {
+                debug!("copy failed (from={}, to={}) - {}", from, to, err);
+            }
+            finished(ret);
+        });
+        FileCopy { cancelled }
+    }
+}
+
+impl Drop for FileCopy {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+}
+
+#[async_trait]
+impl api::FileCopySimplified for FileCopy {
+    async fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct Copier {
+    #[derivative(Debug = "ignore")]
+    accessor: Arc<FileAccessor>,
+    context: RequestContext,
+    cancelled: Arc<AtomicBool>,
+    progress: api::CopyProgress,
+    last_report: u64,
+    #[derivative(Debug = "ignore")]
+    on_progress: Box<dyn Fn(api::CopyProgress) + Send + Sync + 'static>,
+}
+
+impl Copier {
+    fn check_cancelled(&self) -> FsResult<()> {
+        if self.cancelled.load(Ordering::Acquire) {
+            return Err(FsError::Interrupted);
+        }
+        Ok(())
+    }
+
+    fn report(&mut self) {
+        self.last_report = self.progress.bytes;
+        (self.on_progress)(self.progress.clone());
+    }
+
+    async fn search(&self, path: &Path) -> FsResult<Option<FileAttr>> {
+        self.accessor
+            .search(&self.context, path.to_string_lossy().as_ref())
+            .await
+            .map_err(conv_err)
+    }
+
+    /// Splits the path into the inode of its parent and its name
+    async fn parent_of(&self, path: &Path) -> FsResult<(FileAttr, String)> {
+        let name = path.file_name().ok_or_else(|| FsError::InvalidInput)?;
+        let parent = path.parent().ok_or_else(|| FsError::InvalidInput)?;
+        let parent = self.search(parent).await?.ok_or_else(|| FsError::EntityNotFound)?;
+        Ok((parent, name.to_string_lossy().to_string()))
+    }
+
+    async fn copy(&mut self, from: &Path, to: &Path, recursive: bool) -> FsResult<()> {
+        // A directory copied into itself would keep finding the copies that
+        // it just made (and a file copied onto itself would be truncated)
+        if to.starts_with(from) {
+            return Err(FsError::InvalidInput);
+        }
+
+        let mut todo = vec![(from.to_path_buf(), to.to_path_buf())];
+        while let Some((from, to)) = todo.pop() {
+            self.check_cancelled()?;
+
+            let src = self.search(&from).await?.ok_or_else(|| FsError::EntityNotFound)?;
+            if src.kind != FileKind::Directory {
+                self.copy_file(&src, &from, &to).await?;
+                continue;
+            }
+            if recursive == false {
+                return Err(FsError::NotAFile);
+            }
+
+            // Create the destination directory (unless its already there)
+            match self.search(&to).await? {
+                Some(dst) if dst.kind == FileKind::Directory => {}
+                Some(_) => {
+                    return Err(FsError::AlreadyExists);
+                }
+                None => {
+                    let (parent, name) = self.parent_of(&to).await?;
+                    self.accessor
+                        .mkdir(&self.context, parent.ino, name.as_str(), src.mode)
+                        .await
+                        .map_err(conv_err)?;
+                }
+            }
+            self.progress.dirs += 1;
+
+            // Queue all the children
+            let dir = self
+                .accessor
+                .opendir(&self.context, src.ino, O_RDONLY as u32)
+                .await
+                .map_err(conv_err)?;
+            for child in dir.children.iter() {
+                if child.name == "." || child.name == ".." {
+                    continue;
+                }
+                todo.push((from.join(child.name.as_str()), to.join(child.name.as_str())));
+            }
+            let _ = self
+                .accessor
+                .releasedir(&self.context, src.ino, dir.fh, 0)
+                .await;
+        }
+        Ok(())
+    }
+
+    async fn copy_file(&mut self, src: &FileAttr, from: &Path, to: &Path) -> FsResult<()> {
+        self.progress.current = Some(from.to_string_lossy().to_string());
+
+        // Open (or create) the destination file
+        let dst = match self.search(to).await? {
+            Some(dst) => self
+                .accessor
+                .open(&self.context, dst.ino, (O_WRONLY | O_TRUNC) as u32)
+                .await
+                .map_err(conv_err)?,
+            None => {
+                let (parent, name) = self.parent_of(to).await?;
+                self.accessor
+                    .create(&self.context, parent.ino, name.as_str(), src.mode)
+                    .await
+                    .map_err(conv_err)?
+            }
+        };
+        let src = self
+            .accessor
+            .open(&self.context, src.ino, O_RDONLY as u32)
+            .await
+            .map_err(conv_err)?;
+
+        let ret = self.copy_data(&src, &dst).await;
+
+        let _ = self
+            .accessor
+            .release(&self.context, src.inode, src.fh, 0, 0, false)
+            .await;
+        let _ = self
+            .accessor
+            .release(&self.context, dst.inode, dst.fh, 0, 0, false)
+            .await;
+        ret?;
+
+        self.progress.files += 1;
+        self.report();
+        Ok(())
+    }
+
+    async fn copy_data(&mut self, src: &OpenHandle, dst: &OpenHandle) -> FsResult<()> {
+        let mut offset = 0u64;
+        loop {
+            self.check_cancelled()?;
+
+            let data = self
+                .accessor
+                .read(&self.context, src.inode, src.fh, offset, COPY_BLOCK_SIZE)
+                .await
+                .map_err(conv_err)?;
+            if data.len() <= 0 {
+                return Ok(());
+            }
+            self.accessor
+                .write(&self.context, dst.inode, dst.fh, offset, &data[..], 0)
+                .await
+                .map_err(conv_err)?;
+
+            offset += data.len() as u64;
+            self.progress.bytes += data.len() as u64;
+            if self.progress.bytes - self.last_report >= COPY_PROGRESS_INTERVAL {
+                self.report();
+            }
+        }
+    }
+}
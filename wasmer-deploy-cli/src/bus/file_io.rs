@@ -74,7 +74,7 @@ impl api::FileIOSimplified for FileIo {
         let file = self.handle.as_ref();
         file.spec.commit().await.map_err(|err| {
             debug!("flush failed - {}", err);
-            FsError::IOError
+            super::conv_err(err)
         })
     }
 
@@ -90,7 +90,7 @@ impl api::FileIOSimplified for FileIo {
 
         let written = file.spec.write(offset, &data[..]).await.map_err(|err| {
             debug!("write failed - {}", err);
-            FsError::IOError
+            super::conv_err(err)
         })?;
         {
             let mut guard = self.offset.lock().unwrap();
@@ -108,7 +108,7 @@ impl api::FileIOSimplified for FileIo {
             .await
             .map_err(|err| {
                 debug!("read failed - {}", err);
-                FsError::IOError
+                super::conv_err(err)
             })
             .map(|a| a.to_vec())?;
         {
@@ -0,0 +1,305 @@
+use async_trait::async_trait;
+use ate_files::codes::*;
+use ate_files::prelude::*;
+use derivative::*;
+use regex::Regex;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+use wasmer_bus_fuse::api;
+use wasmer_bus_fuse::prelude::*;
+
+use super::conv_err;
+
+/// What a search is looking for and where the results are sent
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub enum SearchQuery {
+    Paths {
+        return_metadata: bool,
+        #[derivative(Debug = "ignore")]
+        found: Box<dyn Fn(Vec<api::DirEntry>) + Send + Sync + 'static>,
+    },
+    Content {
+        pattern: String,
+        regex: bool,
+        #[derivative(Debug = "ignore")]
+        found: Box<dyn Fn(Vec<api::SearchMatch>) + Send + Sync + 'static>,
+    },
+}
+
+/// Search that walks the directories of the file system directly (rather
+/// than the caller walking them one call at a time over the bus)
+#[derive(Debug)]
+pub struct FileSearch {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl FileSearch {
+    pub fn start(
+        accessor: Arc<FileAccessor>,
+        context: RequestContext,
+        root: String,
+        glob: String,
+        max_matches: Option<u64>,
+        query: SearchQuery,
+        finished: Box<dyn Fn(FsResult<api::SearchSummary>) + Send + Sync + 'static>,
+    ) -> FileSearch {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut searcher = Searcher {
+            accessor,
+            context,
+            cancelled: cancelled.clone(),
+            glob: glob.clone(),
+            max_matches,
+            summary: api::SearchSummary::default(),
+            paths: Vec::new(),
+            lines: Vec::new(),
+        };
+        wasmer_bus::task::spawn(async move {
+            let ret = searcher
+                .search(Path::new(&root), &query)
+                .await
+                .map(|_| searcher.summary.clone());
+            if let Err(err) = ret.as_ref() {
+                debug!("search failed (root={}, glob={}) - {}", root, glob, err);
+            }
+            searcher.flush(&query);
+            finished(ret);
+        });
+        FileSearch { cancelled }
+    }
+}
+
+impl Drop for FileSearch {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+}
+
+#[async_trait]
+impl api::FileSearchSimplified for FileSearch {
+    async fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct Searcher {
+    #[derivative(Debug = "ignore")]
+    accessor: Arc<FileAccessor>,
+    context: RequestContext,
+    cancelled: Arc<AtomicBool>,
+    glob: String,
+    max_matches: Option<u64>,
+    summary: api::SearchSummary,
+    paths: Vec<api::DirEntry>,
+    lines: Vec<api::SearchMatch>,
+}
+
+impl Searcher {
+    fn check_cancelled(&self) -> FsResult<()> {
+        if self.cancelled.load(Ordering::Acquire) {
+            return Err(FsError::Interrupted);
+        }
+        Ok(())
+    }
+
+    /// Counts another match, unless the search already has as many as
+    /// it was asked for (in which case the results are truncated)
+    fn accept(&mut self) -> bool {
+        if let Some(max) = self.max_matches {
+            if self.summary.matches >= max {
+                self.summary.truncated = true;
+                return false;
+            }
+        }
+        self.summary.matches += 1;
+        true
+    }
+
+    fn flush(&mut self, query: &SearchQuery) {
+        match query {
+            SearchQuery::Paths { found, .. } if self.paths.len() > 0 => {
+                found(self.paths.drain(..).collect());
+            }
+            SearchQuery::Content { found, .. } if self.lines.len() > 0 => {
+                found(self.lines.drain(..).collect());
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the children of a directory
+    async fn children(&self, path: &Path, dir: &FileAttr) -> Vec<(PathBuf, FileAttr)> {
+        let fh = match self
+            .accessor
+            .opendir(&self.context, dir.ino, O_RDONLY as u32)
+            .await
+        {
+            Ok(a) => a,
+            Err(err) => {
+                debug!("search skipped a directory (path={}) - {}", path.display(), err);
+                return Vec::new();
+            }
+        };
+        let mut ret = fh
+            .children
+            .iter()
+            .filter(|a| a.name != "." && a.name != "..")
+            .map(|a| (path.join(a.name.as_str()), a.attr.clone()))
+            .collect::<Vec<_>>();
+        let _ = self
+            .accessor
+            .releasedir(&self.context, dir.ino, fh.fh, 0)
+            .await;
+        ret
+    }
+
+    async fn search(&mut self, root: &Path, query: &SearchQuery) -> FsResult<()> {
+        let pattern = match query {
+            SearchQuery::Content { pattern, regex: is_regex, .. } => {
+                let pattern = match is_regex {
+                    true => Regex::new(pattern.as_str()),
+                    false => Regex::new(regex::escape(pattern.as_str()).as_str()),
+                };
+                Some(pattern.map_err(|err| {
+                    debug!("search pattern is invalid - {}", err);
+                    FsError::InvalidInput
+                })?)
+            }
+            SearchQuery::Paths { .. } => None,
+        };
+
+        let attr = self
+            .accessor
+            .search(&self.context, root.to_string_lossy().as_ref())
+            .await
+            .map_err(conv_err)?
+            .ok_or_else(|| FsError::EntityNotFound)?;
+
+        let entries = match attr.kind {
+            FileKind::Directory => self.children(root, &attr).await,
+            _ => vec![(root.to_path_buf(), attr)],
+        };
+        let mut walk = SearchWalk::new(root, self.glob.as_str(), entries);
+        while let Some((path, attr, is_match)) = walk.next() {
+            self.check_cancelled()?;
+
+            if is_match {
+                let full = match (query, pattern.as_ref()) {
+                    (SearchQuery::Paths { return_metadata, .. }, _) => {
+                        self.found_path(&path, &attr, *return_metadata)
+                    }
+                    (SearchQuery::Content { .. }, Some(pattern)) => {
+                        self.search_file(&path, &attr, pattern, query).await?
+                    }
+                    _ => false,
+                };
+                if full {
+                    return Ok(());
+                }
+                if self.paths.len() >= SEARCH_BATCH_SIZE || self.lines.len() >= SEARCH_BATCH_SIZE {
+                    self.flush(query);
+                }
+            }
+
+            if attr.kind == FileKind::Directory {
+                walk.descend(self.children(&path, &attr).await);
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a path that matched, returns true if the search is now full
+    fn found_path(&mut self, path: &Path, attr: &FileAttr, return_metadata: bool) -> bool {
+        if self.accept() == false {
+            return true;
+        }
+        self.paths.push(api::DirEntry {
+            path: path.to_string_lossy().to_string(),
+            metadata: match return_metadata {
+                true => Some(super::conv_meta(attr.clone())),
+                false => None,
+            },
+        });
+        false
+    }
+
+    /// Searches the lines of a file for the pattern, returns true if the
+    /// search is now full
+    async fn search_file(
+        &mut self,
+        path: &Path,
+        attr: &FileAttr,
+        pattern: &Regex,
+        query: &SearchQuery,
+    ) -> FsResult<bool> {
+        if attr.kind != FileKind::RegularFile && attr.kind != FileKind::FixedFile {
+            return Ok(false);
+        }
+        let fh = match self
+            .accessor
+            .open(&self.context, attr.ino, O_RDONLY as u32)
+            .await
+        {
+            Ok(a) => a,
+            Err(err) => {
+                debug!("search skipped a file (path={}) - {}", path.display(), err);
+                return Ok(false);
+            }
+        };
+        let ret = self.search_data(path, &fh, pattern, query).await;
+        let _ = self
+            .accessor
+            .release(&self.context, fh.inode, fh.fh, 0, 0, false)
+            .await;
+        ret
+    }
+
+    async fn search_data(
+        &mut self,
+        path: &Path,
+        fh: &OpenHandle,
+        pattern: &Regex,
+        query: &SearchQuery,
+    ) -> FsResult<bool> {
+        let mut offset = 0u64;
+        let mut lines = LineSplitter::new();
+        loop {
+            self.check_cancelled()?;
+
+            let data = self
+                .accessor
+                .read(&self.context, fh.inode, fh.fh, offset, SEARCH_BLOCK_SIZE as u32)
+                .await
+                .map_err(conv_err)?;
+            offset += data.len() as u64;
+
+            for (line_number, line) in lines.push(&data[..]) {
+                if pattern.is_match(line.as_str()) == false {
+                    continue;
+                }
+                if self.accept() == false {
+                    return Ok(true);
+                }
+                self.lines.push(api::SearchMatch {
+                    path: path.to_string_lossy().to_string(),
+                    line_number,
+                    line,
+                });
+                if self.lines.len() >= SEARCH_BATCH_SIZE {
+                    self.flush(query);
+                }
+            }
+            if data.len() <= 0 {
+                return Ok(false);
+            }
+        }
+    }
+}
@@ -10,6 +10,8 @@ use wasmer_bus_fuse::api;
 use wasmer_bus_fuse::prelude::*;
 
 use super::conv_err;
+use super::file_copy::*;
+use super::file_search::*;
 use super::opened_file::*;
 
 #[derive(Derivative, Clone)]
@@ -150,6 +152,28 @@ impl FileSystem {
             self.accessor.clone(),
         ))
     }
+
+    /// Has the chain remove the entries of a directory that have not been
+    /// written to within the maximum age (see `FileAccessor::set_retention`)
+    pub async fn set_retention(
+        &self,
+        path: String,
+        max_age: Option<std::time::Duration>,
+    ) -> FsResult<()> {
+        let dir = match self.accessor.search(&self.context, path.as_str()).await {
+            Ok(Some(a)) => a,
+            Ok(None) => return Err(FsError::EntityNotFound),
+            Err(err) => return Err(conv_err(err)),
+        };
+        let max_age = max_age.map(|a| a.as_millis() as u64);
+        self.accessor
+            .set_retention(&self.context, dir.ino, max_age)
+            .await
+            .map_err(|err| {
+                debug!("set_retention failed - {}", err);
+                conv_err(err)
+            })
+    }
 }
 
 #[async_trait]
@@ -333,4 +357,74 @@ impl api::FileSystemSimplified for FileSystem {
         let ret = FileSystem::open(self, path, options).await;
         Ok(ret)
     }
+
+    async fn copy(
+        &self,
+        from: String,
+        to: String,
+        recursive: bool,
+        progress: Box<dyn Fn(api::CopyProgress) + Send + Sync + 'static>,
+        finished: Box<dyn Fn(FsResult<api::CopyProgress>) + Send + Sync + 'static>,
+    ) -> Result<Arc<dyn api::FileCopy>, BusError> {
+        debug!("copy (from={}, to={}, recursive={})", from, to, recursive);
+        Ok(Arc::new(FileCopy::start(
+            self.accessor.clone(),
+            self.context.clone(),
+            from,
+            to,
+            recursive,
+            progress,
+            finished,
+        )))
+    }
+
+    async fn search(
+        &self,
+        root: String,
+        glob: String,
+        max_results: Option<u64>,
+        return_metadata: bool,
+        found: Box<dyn Fn(Vec<api::DirEntry>) + Send + Sync + 'static>,
+        finished: Box<dyn Fn(FsResult<api::SearchSummary>) + Send + Sync + 'static>,
+    ) -> Result<Arc<dyn api::FileSearch>, BusError> {
+        debug!("search (root={}, glob={})", root, glob);
+        Ok(Arc::new(FileSearch::start(
+            self.accessor.clone(),
+            self.context.clone(),
+            root,
+            glob,
+            max_results,
+            SearchQuery::Paths {
+                return_metadata,
+                found,
+            },
+            finished,
+        )))
+    }
+
+    async fn search_content(
+        &self,
+        root: String,
+        glob: String,
+        pattern: String,
+        regex: bool,
+        max_matches: Option<u64>,
+        found: Box<dyn Fn(Vec<api::SearchMatch>) + Send + Sync + 'static>,
+        finished: Box<dyn Fn(FsResult<api::SearchSummary>) + Send + Sync + 'static>,
+    ) -> Result<Arc<dyn api::FileSearch>, BusError> {
+        debug!("search_content (root={}, glob={}, pattern={})", root, glob, pattern);
+        Ok(Arc::new(FileSearch::start(
+            self.accessor.clone(),
+            self.context.clone(),
+            root,
+            glob,
+            max_matches,
+            SearchQuery::Content {
+                pattern,
+                regex,
+                found,
+            },
+            finished,
+        )))
+    }
 }
\ No newline at end of file
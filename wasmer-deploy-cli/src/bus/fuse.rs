@@ -59,6 +59,14 @@ impl api::FuseSimplified for FuseServer {
     async fn mount(
         &self,
         name: String,
+    ) -> Result<Arc<dyn api::FileSystem>, wasmer_bus_deploy::prelude::BusError> {
+        api::FuseSimplified::mount_with_options(self, name, api::MountOptions::default()).await
+    }
+
+    async fn mount_with_options(
+        &self,
+        name: String,
+        options: api::MountOptions,
     ) -> Result<Arc<dyn api::FileSystem>, wasmer_bus_deploy::prelude::BusError> {
         // Derive the group from the mount address
         let mut group = None;
@@ -95,7 +103,8 @@ impl api::FuseSimplified for FuseServer {
 
         // Create a progress bar loader
         let progress_local = DummyLoader::default();
-        let progress_remote = LoadProgress::new(std::io::stdout());
+        let mut progress_remote = LoadProgress::new(std::io::stdout());
+        progress_remote.draw_bar = wasmer_auth::helper::is_tty_stdout();
         println!("Loading the chain-of-trust");
 
         // Load the chain
@@ -122,7 +131,8 @@ impl api::FuseSimplified for FuseServer {
                 false,
                 false,
             )
-            .await,
+            .await
+            .with_casefold(options.casefold),
         );
         let _ = std::io::stdout().flush();
 
@@ -88,12 +88,14 @@ fn conv_file_type(kind: ate_files::api::FileKind) -> api::FileType {
     ret
 }
 
+/// The chain keeps its timestamps in milliseconds while the file systems
+/// report them in nanoseconds
 fn conv_meta(file: ate_files::attr::FileAttr) -> api::Metadata {
     api::Metadata {
         ft: conv_file_type(file.kind),
-        accessed: file.accessed,
-        created: file.created,
-        modified: file.updated,
+        accessed: file.accessed.saturating_mul(1_000_000),
+        created: file.created.saturating_mul(1_000_000),
+        modified: file.updated.saturating_mul(1_000_000),
         len: file.size,
     }
 }
@@ -1,4 +1,6 @@
+pub mod file_copy;
 pub mod file_io;
+pub mod file_search;
 pub mod file_system;
 mod fuse;
 mod main;
@@ -28,12 +30,14 @@ fn conv_file_type(kind: ate_files::api::FileKind) -> api::FileType {
     ret
 }
 
+/// The chain keeps its timestamps in milliseconds while the file systems
+/// report them in nanoseconds
 fn conv_meta(file: ate_files::attr::FileAttr) -> api::Metadata {
     api::Metadata {
         ft: conv_file_type(file.kind),
-        accessed: file.accessed,
-        created: file.created,
-        modified: file.updated,
+        accessed: file.accessed.saturating_mul(1_000_000),
+        created: file.created.saturating_mul(1_000_000),
+        modified: file.updated.saturating_mul(1_000_000),
         len: file.size,
     }
 }
@@ -53,6 +57,7 @@ fn conv_err(err: FileSystemError) -> api::FsError {
         FileSystemError(FileSystemErrorKind::InvalidArguments, _) => api::FsError::InvalidInput,
         FileSystemError(FileSystemErrorKind::NoEntry, _) => api::FsError::EntityNotFound,
         FileSystemError(FileSystemErrorKind::NotImplemented, _) => api::FsError::NoDevice,
+        FileSystemError(FileSystemErrorKind::IO(err), _) => err.into(),
         FileSystemError(_, _) => api::FsError::IOError,
     }
 }
@@ -4,6 +4,7 @@ use ate_files::prelude::*;
 use derivative::*;
 use std::sync::Arc;
 use std::sync::Mutex;
+use tokio::sync::Mutex as AsyncMutex;
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
 use wasmer_bus_fuse::api;
@@ -20,6 +21,7 @@ pub struct OpenedFile {
     context: RequestContext,
     #[derivative(Debug = "ignore")]
     accessor: Arc<FileAccessor>,
+    lock: Arc<AsyncMutex<Option<FileLock>>>,
 }
 
 impl OpenedFile {
@@ -38,6 +40,7 @@ impl OpenedFile {
             append,
             path,
             accessor,
+            lock: Arc::new(AsyncMutex::new(None)),
         }
     }
 
@@ -93,6 +96,55 @@ impl api::OpenedFileSimplified for OpenedFile {
         }
     }
 
+    async fn lock(&self, options: api::LockOptions) -> FsResult<()> {
+        let file = self.handle.clone()?;
+        let kind = match options.kind {
+            api::LockKind::Shared => FileLockKind::Shared,
+            api::LockKind::Exclusive => FileLockKind::Exclusive,
+        };
+
+        // Converting an existing lock (e.g. shared to exclusive) releases it first
+        let mut guard = self.lock.lock().await;
+        if let Some(existing) = guard.take() {
+            existing.unlock().await.map_err(|err| {
+                debug!("unlock failed - {}", err);
+                FsError::Lock
+            })?;
+        }
+
+        let ret = if options.wait {
+            let timeout = options.timeout.unwrap_or(FILE_LOCK_DEFAULT_TIMEOUT);
+            self.accessor
+                .lock_with_timeout(file.inode, kind, timeout)
+                .await
+        } else {
+            self.accessor.try_lock(file.inode, kind).await
+        };
+        match ret {
+            Ok(Some(lock)) => {
+                guard.replace(lock);
+                Ok(())
+            }
+            Ok(None) if options.wait => Err(FsError::TimedOut),
+            Ok(None) => Err(FsError::WouldBlock),
+            Err(err) => {
+                debug!("lock failed - {}", err);
+                Err(FsError::Lock)
+            }
+        }
+    }
+
+    async fn unlock(&self) -> FsResult<()> {
+        let mut guard = self.lock.lock().await;
+        if let Some(lock) = guard.take() {
+            lock.unlock().await.map_err(|err| {
+                debug!("unlock failed - {}", err);
+                FsError::Lock
+            })?;
+        }
+        Ok(())
+    }
+
     async fn io(&self) -> Result<Arc<dyn api::FileIO>, BusError> {
         let ret = OpenedFile::io(self).await?;
         Ok(ret)
@@ -3,46 +3,41 @@ use ate::prelude::*;
 use tracing::{debug, error, info};
 
 use crate::api::*;
+use crate::cmd::*;
 use crate::error::*;
 use crate::opt::*;
+use crate::output::*;
 
-pub async fn main_opts_balance(opts: OptsBalance, api: &mut DeployApi) -> Result<(), WalletError> {
+pub async fn balance_output(opts: &OptsBalance, api: &mut DeployApi) -> Result<BalanceOutput, WalletError> {
     if opts.no_reconcile == false {
         api.reconcile().await?;
     }
 
     let result = api.wallet_summary().await?;
-
-    let short_form = opts.coins == false;
-    if short_form == true {
-        println!("Currency Balance for {}", api.wallet.key());
-    }
-
-    let mut first = true;
-    for currency in result.currencies.values() {
-        // Display this currency summary to the user
-        if short_form == false {
-            if first == false {
-                println!("");
-            }
-            println!("Currency Balance");
-        }
-
-        println!("{:8} {}", currency.currency, currency.total);
-
-        if opts.coins {
-            println!("");
-            println!("Denomination Quantity Total ({})", currency.currency);
-            for denomination in currency.denominations.values() {
-                println!(
-                    "{:12} {:8} {}",
-                    denomination.denomination, denomination.cnt, denomination.total
-                );
-            }
-        }
-
-        first = false;
+    let mut ret = BalanceOutput::new(api.wallet.key().to_string(), &result, opts.coins);
+
+    // Estimate the total in a single currency using the current rates
+    if let Some(target) = opts.convert {
+        let currencies = result
+            .currencies
+            .keys()
+            .filter(|a| **a != target)
+            .map(|a| a.clone())
+            .collect::<Vec<_>>();
+        let rates = match currencies.len() {
+            0 => None,
+            _ => Some(
+                currency_rates_command(&api.registry, currencies, target, api.auth.clone())
+                    .await?,
+            ),
+        };
+        ret.estimate = Some(BalanceEstimate::new(&result, target, rates.as_ref()));
     }
+    Ok(ret)
+}
 
+pub async fn main_opts_balance(opts: OptsBalance, api: &mut DeployApi, output: OutputFormat) -> Result<(), WalletError> {
+    let result = balance_output(&opts, api).await?;
+    emit(output, &result);
     Ok(())
 }
@@ -0,0 +1,32 @@
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::api::*;
+use crate::error::*;
+use crate::opt::*;
+
+pub async fn main_opts_coin(opts: OptsCoin, api: &mut DeployApi) -> Result<(), WalletError> {
+    match opts.action {
+        OptsCoinAction::Rotate(opts) => {
+            let currency = match opts.all {
+                true => None,
+                false => opts.currency,
+            };
+            let summary = api.rotate_coins(currency, opts.batch_size).await?;
+            eprintln!(
+                "Rotated {} coins in {} batches ({} retries).",
+                summary.rotated, summary.batches, summary.retries
+            );
+        }
+        OptsCoinAction::History(opts) => {
+            let rotations = api.coin_rotations(&opts.coin).await?;
+            if rotations.is_empty() {
+                eprintln!("No rotations have been recorded for coin {}.", opts.coin);
+            }
+            for link in rotations {
+                println!("{} {} -> {}", link.when.to_rfc3339(), link.old_owner, link.new_owner);
+            }
+        }
+    }
+    Ok(())
+}
@@ -3,6 +3,7 @@ use tracing::{debug, error, info, trace, warn};
 use url::Url;
 
 use crate::error::*;
+use crate::helper::SessionConnector;
 use crate::opt::*;
 
 use super::*;
@@ -11,13 +12,15 @@ pub async fn main_opts_contract(
     opts: OptsContractFor,
     token_path: String,
     auth_url: Url,
+    output: OutputFormat,
 ) -> Result<(), ContractError> {
-    let mut context = PurposeContext::new(&opts, token_path.as_str(), &auth_url, None, true).await?;
+    let connector = SessionConnector::new(auth_url);
+    let mut context = PurposeContext::new(&opts, token_path.as_str(), &connector, true).await?;
     let identity = context.identity.clone();
 
     match context.action.clone() {
         OptsContractAction::List => {
-            main_opts_contract_list(&mut context.api).await?;
+            main_opts_contract_list(&mut context.api, output).await?;
         }
         OptsContractAction::Details(opts) => {
             main_opts_contract_details(opts, &mut context.api).await?;
@@ -3,17 +3,14 @@ use tracing::{debug, error, info, trace, warn};
 
 use crate::api::*;
 use crate::error::*;
+use crate::opt::*;
+use crate::output::*;
 
-pub async fn main_opts_contract_list(api: &mut DeployApi) -> Result<(), ContractError> {
+pub async fn main_opts_contract_list(api: &mut DeployApi, output: OutputFormat) -> Result<(), ContractError> {
     let result = api.contract_summary().await?;
 
-    println!("|-----code-----|-------------reference------------|---status---");
-    for contract in result {
-        println!(
-            "- {:12} - {:16} - {}",
-            contract.service.code, contract.reference_number, contract.status
-        );
-    }
+    let result = ContractListOutput::new(&result);
+    emit(output, &result);
 
     Ok(())
 }
@@ -9,6 +9,7 @@ use ate::prelude::*;
 
 use crate::api::*;
 use crate::error::*;
+use crate::helper::*;
 use crate::model::*;
 use crate::opt::*;
 
@@ -20,6 +21,21 @@ pub async fn session_with_permissions<A>(
     auth_url: &url::Url,
     sudo: bool,
 ) -> Result<(A, AteSessionType), AteError>
+where
+    A: Clone,
+{
+    session_with_permissions_ext(purpose, token_path, Some(auth_url), sudo).await
+}
+
+/// Builds the session from the token file, when no authentication server
+/// is supplied the session is built offline from the keys already in the
+/// token (nothing is gathered or elevated)
+pub async fn session_with_permissions_ext<A>(
+    purpose: &dyn OptsPurpose<A>,
+    token_path: &str,
+    auth_url: Option<&url::Url>,
+    sudo: bool,
+) -> Result<(A, AteSessionType), AteError>
 where
     A: Clone,
 {
@@ -36,7 +52,7 @@ where
             group_name,
             sudo,
             None,
-            Some(auth_url.clone()),
+            auth_url.cloned(),
             "Domain name",
         )
         .await?
@@ -46,12 +62,12 @@ where
             None,
             Some(token_path.to_string()),
             None,
-            Some(auth_url.clone()),
+            auth_url.cloned(),
         )
         .await?
         .into();
     } else {
-        session = main_session_user(None, Some(token_path.to_string()), Some(auth_url.clone()))
+        session = main_session_user(None, Some(token_path.to_string()), auth_url.cloned())
             .await?
             .into();
     }
@@ -214,6 +230,40 @@ where
     Ok(wallet)
 }
 
+/// Opens the chain-of-trust that holds the data of a user or group
+pub(crate) async fn open_identity_chain(
+    identity: &str,
+    auth_url: &url::Url,
+) -> Result<(Arc<Registry>, ChainKey, ChainGuard), ChainCreationError> {
+    let registry = ate::mesh::Registry::new(&wasmer_auth::helper::conf_auth())
+        .await
+        .keep_alive(Duration::from_secs(10))
+        .cement();
+    let (chain_key, chain) = open_identity_chain_ext(&registry, identity, auth_url).await?;
+    Ok((registry, chain_key, chain))
+}
+
+/// Opens the chain-of-trust of a user or group using an existing registry
+pub(crate) async fn open_identity_chain_ext(
+    registry: &Arc<Registry>,
+    identity: &str,
+    auth_url: &url::Url,
+) -> Result<(ChainKey, ChainGuard), ChainCreationError> {
+    let chain_key = chain_key_4hex(identity, Some("redo"));
+    debug!("chain_url={}", auth_url);
+    debug!("chain_key={}", chain_key);
+
+    // Large chains take a while to sync so show a progress bar (on stderr
+    // so that it does not mix with the output of the command)
+    let mut progress = ate::utils::LoadProgress::new(std::io::stderr());
+    progress.draw_bar = wasmer_auth::helper::is_tty_stderr();
+    progress.msg_done = String::new();
+    let chain = registry
+        .open_ext(&auth_url, &chain_key, true, ate::loader::DummyLoader::default(), progress)
+        .await?;
+    Ok((chain_key, chain))
+}
+
 pub(crate) struct PurposeContextPrelude<A>
 where
     A: Clone,
@@ -265,28 +315,40 @@ where
     pub async fn new(
         purpose: &dyn OptsPurpose<A>,
         token_path: &str,
-        auth_url: &url::Url,
+        connector: &SessionConnector,
         sudo: bool,
     ) -> Result<PurposeContextPrelude<A>, CoreError> {
-        // Build a session with all the needed permissions
-        let (action, session) =
-            session_with_permissions(purpose, token_path, auth_url, sudo).await?;
+        let auth_url = connector.auth_url();
+
+        // Build a session with all the needed permissions (this may prompt
+        // the user hence it is not repeated when it fails)
+        let (action, session) = connector
+            .request(auth_url, async {
+                session_with_permissions(purpose, token_path, auth_url, sudo)
+                    .await
+                    .map_err(CoreError::from)
+            })
+            .await?;
 
         // Compute the identity of the requesting user or group
         let identity = get_identity(purpose, &session).await?;
 
         // Open the chain
-        let registry = ate::mesh::Registry::new(&wasmer_auth::helper::conf_auth())
-            .await
-            .keep_alive(Duration::from_secs(10))
-            .cement();
-        let chain_key = chain_key_4hex(&identity, Some("redo"));
-        debug!("chain_url={}", auth_url);
-        debug!("chain_key={}", chain_key);
-        let chain = registry.open(&auth_url, &chain_key, true).await?;
+        let registry = connector.registry(&wasmer_auth::helper::conf_auth()).await;
+        let (chain_key, chain) = {
+            let registry = &registry;
+            let identity = identity.as_str();
+            connector
+                .handshake(auth_url, move || async move {
+                    open_identity_chain_ext(registry, identity, auth_url)
+                        .await
+                        .map_err(CoreError::from)
+                })
+                .await?
+        };
 
         // Open the DIO
-        let dio = chain.dio_trans(&session, TransactionScope::Full).await;
+        let dio = chain.dio_trans(&session, TransactionScope::Full).await?;
 
         Ok(PurposeContextPrelude {
             action,
@@ -307,15 +369,21 @@ where
     pub async fn new(
         purpose: &dyn OptsPurpose<A>,
         token_path: &str,
-        auth_url: &url::Url,
-        db_url: Option<&url::Url>,
+        connector: &SessionConnector,
         sudo: bool,
     ) -> Result<PurposeContext<A>, CoreError> {
-        let inner = PurposeContextPrelude::new(purpose, token_path, auth_url, sudo).await?;
+        let inner = PurposeContextPrelude::new(purpose, token_path, connector, sudo).await?;
 
         // Create the API to the wallet
         let wallet = get_wallet(purpose, &inner.dio, &inner.identity).await?;
-        let api = build_api_accessor(&inner.dio, wallet, auth_url.clone(), db_url.map(|a| a.clone()), &inner.registry).await;
+        let api = build_api_accessor(
+            &inner.dio,
+            wallet,
+            connector.auth_url().clone(),
+            connector.db_url().map(|a| a.clone()),
+            &inner.registry,
+        )
+        .await;
 
         Ok(PurposeContext { inner, api })
     }
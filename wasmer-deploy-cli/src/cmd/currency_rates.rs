@@ -0,0 +1,28 @@
+use std::sync::Arc;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use ate::prelude::*;
+
+use crate::error::*;
+use crate::model::*;
+use crate::request::*;
+
+pub async fn currency_rates_command(
+    registry: &Arc<Registry>,
+    currencies: Vec<NationalCurrency>,
+    target: NationalCurrency,
+    auth: url::Url,
+) -> Result<CurrencyRatesResponse, CoreError> {
+    // Open a command chain
+    let chain = registry.open_cmd(&auth).await?;
+
+    // Create the query
+    let query = CurrencyRatesRequest { currencies, target };
+
+    // Query the current exchange rates
+    let response: Result<CurrencyRatesResponse, CurrencyRatesFailed> =
+        chain.invoke(query).await?;
+    let result = response?;
+    Ok(result)
+}
@@ -1,63 +1,43 @@
-use chrono::Datelike;
-
 use crate::api::*;
 use crate::cmd::*;
 use crate::error::*;
 use crate::opt::*;
+use crate::output::*;
+
+pub async fn history_output(
+    opts: &OptsTransactionHistory,
+    api: &mut DeployApi,
+) -> Result<HistoryOutput, WalletError> {
+    // We first get the wallet summary (if it was requested)
+    let balance = if opts.balance {
+        Some(
+            balance_output(
+                &OptsBalance {
+                    coins: false,
+                    no_reconcile: opts.no_reconcile,
+                    convert: None,
+                },
+                api,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    // Read all the history
+    let activities = api.read_activity(opts.year, opts.month, opts.day).await?;
+    Ok(HistoryOutput::new(balance, activities, opts.details))
+}
 
 #[allow(unreachable_code)]
 pub async fn main_opts_transaction_history(
     opts: OptsTransactionHistory,
     api: &mut DeployApi,
+    output: OutputFormat,
 ) -> Result<(), WalletError> {
-    // We first get the wallet summary and output it
-    if opts.balance {
-        main_opts_balance(
-            OptsBalance {
-                coins: false,
-                no_reconcile: opts.no_reconcile,
-            },
-            api,
-        )
-        .await?;
-    }
-
-    // Loop through all the history and display it
-    let mut cur_year = 0i32;
-    let mut cur_month = 0u32;
-    let mut cur_day = 0u32;
-
-    for event in api.read_activity(opts.year, opts.month, opts.day).await? {
-        if cur_year != event.when().year()
-            || cur_month != event.when().month()
-            || cur_day != event.when().day()
-        {
-            cur_year = event.when().year();
-            cur_month = event.when().month();
-            cur_day = event.when().day();
-
-            println!("");
-            println!("[{}]", event.when().date());
-        }
-
-        match event.financial() {
-            Some(a) => {
-                let mut amount = a.amount;
-                amount.rescale(a.currency.decimal_points() as u32);
-                println!("{:11} {:3}: {}", amount, a.currency, event.summary());
-            }
-            None => {
-                println!("            ...: {}", event.summary());
-            }
-        }
-
-        if opts.details {
-            match event.details() {
-                Ok(a) => println!("{}", a),
-                Err(err) => println!("details error - {}", err),
-            };
-        }
-    }
+    let result = history_output(&opts, api).await?;
+    emit(output, &result);
 
     Ok(())
 }
@@ -1,7 +1,7 @@
 use std::ops::Deref;
+use std::sync::Arc;
 use std::io::Read;
 use ate::prelude::*;
-use chrono::NaiveDateTime;
 use error_chain::bail;
 use async_stream::stream;
 use futures_util::pin_mut;
@@ -9,16 +9,21 @@ use futures_util::stream::StreamExt;
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
 use ate_comms::StreamSecurity;
+use wasmer_bus_tty::prelude::*;
 
 use crate::error::*;
-use crate::model::{HistoricActivity, activities, InstanceHello, InstanceCommand, InstanceExport, InstanceCall};
+use crate::helper::SessionConnector;
+use crate::model::{HistoricActivity, activities, InstanceHello, InstanceCommand, InstanceExport, InstanceCall, InstanceReply};
+use crate::model::{ExportCapability, ExportToken};
+use crate::model::{InstanceLog, INSTANCE_ROOT_ID, INSTANCE_LOG_COLLECTION_ID};
+use crate::model::{InstanceMetricsWindow, ServiceInstance, INSTANCE_METRICS_COLLECTION_ID};
 use crate::opt::*;
-use crate::api::{DeployApi, InstanceClient};
+use crate::output::*;
+use crate::api::DeployApi;
 
 use super::*;
 
-pub async fn main_opts_instance_list(api: &mut DeployApi) -> Result<(), InstanceError> {
-    println!("|-------name-------|-------created-------|-exports");
+pub async fn main_opts_instance_list(api: &mut DeployApi, output: OutputFormat) -> Result<(), InstanceError> {
     let instances = api.instances().await;
 
     let instances = instances.iter_ext(true, true).await?;
@@ -39,45 +44,36 @@ pub async fn main_opts_instance_list(api: &mut DeployApi) -> Result<(), Instance
     };
     pin_mut!(instances_ext);
 
+    let mut result = InstanceListOutput::default();
     while let Some((res, name)) = instances_ext.next().await {
         let (wallet_instance, _) = match res {
             Ok(a) => a,
             Err(err) => {
                 debug!("error loading wallet instance - {} - {}", name, err);
-                println!(
-                    "- {:<16} - {:<19} - {}",
-                    name, "error", err
-                );
+                result.instances.push(InstanceListEntry::error(name, err.to_string()));
                 continue;
             }
         };
         match api.instance_load(&wallet_instance).await {
             Ok(instance) => {
-                let secs = instance.when_created() / 1000;
-                let nsecs = (instance.when_created() % 1000) * 1000 * 1000;
-                let when = NaiveDateTime::from_timestamp(secs as i64, nsecs as u32);
-                let mut exports = String::new();
-                for export in instance.exports.iter().await? {
-                    if exports.len() > 0 { exports.push_str(","); }
-                    exports.push_str(export.binary.as_str());
-                    if export.distributed == false {
-                        exports.push_str("*");
-                    }
-                }
-                println!(
-                    "- {:<16} - {:<19} - {}",
-                    wallet_instance.name, when.format("%Y-%m-%d %H:%M:%S"), exports
-                );
+                let exports = instance.exports
+                    .iter()
+                    .await?
+                    .map(|export| InstanceListExport {
+                        binary: export.binary.clone(),
+                        distributed: export.distributed,
+                    })
+                    .collect();
+                result.instances.push(InstanceListEntry::new(wallet_instance.name.clone(), instance.when_created(), exports));
             }
             Err(err) => {
                 debug!("error loading service chain - {}", err);
-                println!(
-                    "- {:<16} - {:<19} - {}",
-                    wallet_instance.name, "error", err
-                );
+                result.instances.push(InstanceListEntry::error(wallet_instance.name.clone(), err.to_string()));
             }
         }
     }
+
+    emit(output, &result);
     Ok(())
 }
 
@@ -85,13 +81,14 @@ pub async fn main_opts_instance_details(
     api: &mut DeployApi,
     inst_url: url::Url,
     opts: OptsInstanceDetails,
+    output: OutputFormat,
 ) -> Result<(), InstanceError> {
     let instance = api.instance_find(opts.name.as_str())
         .await;
     let instance = match instance {
         Ok(a) => a,
         Err(InstanceError(InstanceErrorKind::InvalidInstance, _)) => {
-            eprintln!("An instance does not exist for this token.");
+            emit_error(output, "An instance does not exist for this token.");
             std::process::exit(1);
         }
         Err(err) => {
@@ -99,26 +96,43 @@ pub async fn main_opts_instance_details(
         }
     };
 
-    println!("Instance");
-    println!("{}", serde_json::to_string_pretty(instance.deref()).unwrap());
+    let mut result = InstanceDetailsOutput {
+        instance: instance.deref().clone(),
+        subnet: None,
+        id: None,
+        exports: Vec::new(),
+        stats: Vec::new(),
+        quota: None,
+        template: None,
+    };
 
     if let Ok(service_instance) = api.instance_load(instance.deref()).await {
-        println!("{}", serde_json::to_string_pretty(&service_instance.subnet).unwrap());
+        result.subnet = Some(service_instance.subnet.clone());
+        result.template = service_instance.template.clone();
+        if let Ok(stats) = load_instance_stats(&service_instance, None, None).await {
+            result.stats = stats.exports;
+        }
 
         if service_instance.exports.len().await? > 0 {
-            let id = service_instance.id_str();
             let chain = ChainKey::from(service_instance.chain.clone());
-            println!("ID: {}", id);
-            println!("");
-            println!("Exports");
+            result.id = Some(service_instance.id_str());
             for export in service_instance.exports.iter().await? {
-                let url = compute_export_url(&inst_url, &chain, export.binary.as_str());
-                println!("POST {}", url);
-                println!("{}", serde_json::to_string_pretty(export.deref()).unwrap());
+                result.exports.push(InstanceDetailsExport {
+                    url: compute_export_url(&inst_url, &chain, export.binary.as_str()),
+                    export: export.deref().clone(),
+                });
             }
         }
     }
 
+    // The chain is open on the root now that it has been loaded hence the
+    // root knows how much of its storage quota it uses
+    match api.instance_quota(instance.deref()).await {
+        Ok(quota) => result.quota = Some(quota),
+        Err(err) => debug!("failed to query the quota of the instance - {}", err),
+    }
+
+    emit(output, &result);
     Ok(())
 }
 
@@ -129,6 +143,7 @@ pub async fn main_opts_instance_create(
     db_url: url::Url,
     instance_authority: String,
     force: bool,
+    template: Option<String>,
 ) -> Result<(), InstanceError> {
 
     let name = match name {
@@ -138,11 +153,15 @@ pub async fn main_opts_instance_create(
         }
     };
 
-    if let Err(err) = api.instance_create(name.clone(), group, db_url, instance_authority, force).await {
+    let template_name = template.clone();
+    if let Err(err) = api.instance_create(name.clone(), group, db_url, instance_authority, force, template).await {
         bail!(err);
     };
 
-    println!("Instance created ({})", name);
+    match template_name {
+        Some(template) => println!("Instance created ({}) from template ({})", name, template),
+        None => println!("Instance created ({})", name),
+    }
     Ok(())
 }
 
@@ -151,27 +170,53 @@ pub async fn main_opts_instance_kill(
     name: &str,
     force: bool,
 ) -> Result<(), InstanceError> {
+    // Finish killing any instances that were interrupted part way
+    if let Err(err) = api.recover_transactions().await {
+        warn!("failed to recover interrupted transactions - {}", err);
+    }
+
     let (service_instance, wallet_instance) = api.instance_action(name).await?;
 
-    let name = match service_instance {
+    // The instance chain and the wallet are committed together so that a
+    // crash in between does not leave a dangling instance behind
+    let mut trans = api.multi_chain_transaction().await?;
+    let (name, gc) = match service_instance {
         Ok(service_instance) => {
             let dio = service_instance.dio_mut();
             let name = service_instance.id_str();
             debug!("deleting all the roots in the chain");
             dio.delete_all_roots().await?;
-            dio.commit().await?;
-            drop(dio);
-            name
+            trans.add(DeployApi::instance_participant(&wallet_instance.chain).as_str(), &dio);
+            let gc = (Arc::clone(dio.chain()), dio.session().clone_session());
+            (name, Some(gc))
         }
         Err(err) if force => {
             warn!("failed to read service instance data - forcing through - {}", err);
-            name.to_string()
+            (name.to_string(), None)
         }
         Err(err) => {
             bail!(err);
         }
     };
 
+    debug!("deleting the instance from the user/group");
+    let _ = wallet_instance.delete()?;
+    trans.add("wallet", &api.dio);
+    trans.commit().await?;
+
+    // Deleting the roots leaves everything below them orphaned so they are
+    // now collected in order to release the storage of the instance
+    if let Some((chain, session)) = gc {
+        match chain.gc_orphans(session.deref()).await {
+            Ok(report) => {
+                debug!("collected {} of {} orphaned rows ({} denied)", report.collected, report.orphaned, report.denied);
+            }
+            Err(err) => {
+                warn!("failed to collect the orphaned rows of the instance - {}", err);
+            }
+        }
+    }
+
     // Now add the history
     if let Err(err) = api
         .record_activity(HistoricActivity::InstanceDestroyed(
@@ -185,9 +230,6 @@ pub async fn main_opts_instance_kill(
     {
         error!("Error writing activity: {}", err);
     }
-
-    debug!("deleting the instance from the user/group");
-    let _ = wallet_instance.delete()?;
     api.dio.commit().await?;
 
     println!("Instance ({}) has been killed", name);
@@ -196,14 +238,13 @@ pub async fn main_opts_instance_kill(
 
 pub async fn main_opts_instance_shell(
     api: &mut DeployApi,
-    inst_url: url::Url,
+    connector: &SessionConnector,
     name: &str,
     security: StreamSecurity
 ) -> Result<(), InstanceError> {
     let (instance, _) = api.instance_action(name).await?;
     let instance = instance?;
-    let mut client = InstanceClient::new_ext(inst_url, InstanceClient::PATH_INST, security).await
-        .unwrap();
+    let mut client = connector.instance_client(security).await?;
 
     client.send_hello(InstanceHello {
         access_token: instance.admin_token.clone(),
@@ -224,7 +265,7 @@ pub async fn main_opts_instance_shell(
 
 pub async fn main_opts_instance_call(
     api: &mut DeployApi,
-    inst_url: url::Url,
+    connector: &SessionConnector,
     name: &str,
     format: SerializationFormat,
     binary: &str,
@@ -241,8 +282,7 @@ pub async fn main_opts_instance_call(
 
     let (instance, _) = api.instance_action(name).await?;
     let instance = instance?;
-    let mut client = InstanceClient::new_ext(inst_url, InstanceClient::PATH_INST, security).await
-        .unwrap();
+    let mut client = connector.instance_client(security).await?;
 
     // Search for an export that matches this binary
     let export = instance.exports
@@ -263,6 +303,7 @@ pub async fn main_opts_instance_call(
         format,
         binary: binary.to_string(),
         topic: topic.to_string(),
+        keepalive: false,
     })).await.unwrap();
 
     client.send_data(request).await.unwrap();
@@ -276,6 +317,137 @@ pub async fn main_opts_instance_call(
     Ok(())
 }
 
+pub async fn main_opts_instance_call_interactive(
+    api: &mut DeployApi,
+    connector: &SessionConnector,
+    name: &str,
+    format: SerializationFormat,
+    binary: &str,
+    topic: &str,
+    binary_frames: bool,
+    security: StreamSecurity
+) -> Result<i32, InstanceError>
+{
+    let (instance, _) = api.instance_action(name).await?;
+    let instance = instance?;
+    let mut client = connector.instance_client(security).await?;
+
+    // Search for an export that matches this binary
+    let export = instance.exports
+        .iter()
+        .await?
+        .filter(|e| e.binary.eq_ignore_ascii_case(binary))
+        .next()
+        .ok_or_else(|| InstanceErrorKind::NotExported)?;
+
+    client.send_hello(InstanceHello {
+        access_token: export.access_token.clone(),
+        chain: ChainKey::from(instance.chain.clone()),
+    }).await
+    .map_err(|err| InstanceErrorKind::InternalError(ate::utils::obscure_error_str(err.to_string().as_str())))?;
+
+    let session = client.open_call(format, binary, topic);
+    let (mut tx, mut rx) = session.split();
+
+    let mut stdin = Tty::stdin().await
+        .map_err(|_| InstanceErrorKind::NoInput)?;
+    let mut stdout = Tty::stdout().await
+        .map_err(|err| InstanceErrorKind::InternalError(ate::utils::obscure_error_str(err.to_string().as_str())))?;
+    let mut stderr = Tty::stderr().await
+        .map_err(|err| InstanceErrorKind::InternalError(ate::utils::obscure_error_str(err.to_string().as_str())))?;
+
+    // Each message is either a line of text or a length-prefixed frame
+    let mut buffer = Vec::new();
+    let mut stdin_open = true;
+    loop {
+        tokio::select! {
+            data = stdin.read(), if stdin_open => {
+                match data {
+                    Some(data) => {
+                        buffer.extend_from_slice(&data[..]);
+                        while let Some(msg) = next_call_message(&mut buffer, binary_frames) {
+                            tx.send(msg).await
+                                .map_err(|err| InstanceErrorKind::InternalError(ate::utils::obscure_error_str(err.to_string().as_str())))?;
+                        }
+                    }
+                    None => {
+                        // Any trailing text without a new line is still a message
+                        if binary_frames == false && buffer.len() > 0 {
+                            let msg = buffer.drain(..).collect::<Vec<_>>();
+                            tx.send(msg).await
+                                .map_err(|err| InstanceErrorKind::InternalError(ate::utils::obscure_error_str(err.to_string().as_str())))?;
+                        }
+
+                        // Closing stdin ends the call
+                        stdin_open = false;
+                        tx.close().await
+                            .map_err(|err| InstanceErrorKind::InternalError(ate::utils::obscure_error_str(err.to_string().as_str())))?;
+                    }
+                }
+            }
+            reply = rx.recv() => {
+                let reply = match reply {
+                    Some(a) => a,
+                    None => break,
+                };
+                let _ = match reply {
+                    InstanceReply::FeedBytes { data, .. } |
+                    InstanceReply::Stdout { data } => {
+                        if binary_frames {
+                            let _ = stdout.write((data.len() as u32).to_be_bytes().to_vec()).await;
+                            let _ = stdout.write(data).await;
+                        } else {
+                            let _ = stdout.write(data).await;
+                            let _ = stdout.write("\r\n".as_bytes().to_vec()).await;
+                        }
+                        stdout.flush().await
+                    }
+                    InstanceReply::Stderr { data } => {
+                        let _ = stderr.write(data).await;
+                        let _ = stderr.write("\r\n".as_bytes().to_vec()).await;
+                        stderr.flush().await
+                    }
+                    InstanceReply::Error { error, .. } => {
+                        let error = format!("error: {}\r\n", error);
+                        let _ = stderr.write(error.into_bytes()).await;
+                        stderr.flush().await
+                    }
+                    InstanceReply::Terminate { .. } |
+                    InstanceReply::Exit => {
+                        break;
+                    }
+                };
+            }
+        }
+    }
+
+    Ok(rx.exit_code().unwrap_or(0))
+}
+
+/// Extracts the next complete message from the stdin buffer (if one is available)
+fn next_call_message(buffer: &mut Vec<u8>, binary_frames: bool) -> Option<Vec<u8>>
+{
+    if binary_frames {
+        if buffer.len() < 4 {
+            return None;
+        }
+        let len = u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+        if buffer.len() < 4 + len {
+            return None;
+        }
+        let msg = buffer.drain(..4 + len).skip(4).collect::<Vec<_>>();
+        Some(msg)
+    } else {
+        let pos = buffer.iter().position(|a| *a == b'\n')?;
+        let mut msg = buffer.drain(..=pos).collect::<Vec<_>>();
+        msg.pop();
+        if msg.last() == Some(&b'\r') {
+            msg.pop();
+        }
+        Some(msg)
+    }
+}
+
 pub async fn main_opts_instance_export(
     api: &mut DeployApi,
     inst_url: url::Url,
@@ -286,15 +458,15 @@ pub async fn main_opts_instance_export(
     no_https: bool,
     no_bus: bool,
 ) -> Result<(), InstanceError> {
-    let (service_instance, _wallet_instance) = api.instance_action(name).await?;
+    let (service_instance, wallet_instance) = api.instance_action(name).await?;
 
     let access_token = AteHash::generate().to_hex_string();
-    let (chain, id_str) = match service_instance {
+    let (chain, id_str, capability_token) = match service_instance {
         Ok(mut service_instance) => {
             let dio = service_instance.dio_mut();
             let chain = service_instance.chain.clone();
             let id_str = service_instance.id_str();
-            service_instance.as_mut().exports.push(InstanceExport {
+            let export = InstanceExport {
                 access_token: access_token.clone(),
                 binary: binary.to_string(),
                 distributed: pinned == false,
@@ -302,10 +474,14 @@ pub async fn main_opts_instance_export(
                 https: no_https == false,
                 bus: no_bus == false,
                 pinned: None,
-            })?;
+            };
+            let export_key = api.instance_export_key(wallet_instance.deref(), &mut service_instance).await?;
+            let capability = ExportCapability::new(&ChainKey::from(chain.clone()), &export);
+            let capability_token = ExportToken::mint(&export_key, capability)?;
+            service_instance.as_mut().exports.push(export)?;
             dio.commit().await?;
             drop(dio);
-            (chain, id_str)
+            (chain, id_str, capability_token)
         }
         Err(err) => {
             bail!(err);
@@ -333,12 +509,53 @@ pub async fn main_opts_instance_export(
     api.dio.commit().await?;
 
     println!("Instance ({}) has exported binary ({})", id_str, binary);
-    println!("Authorization: {}", access_token);
+    println!("Authorization: {}", capability_token);
+    println!("Export token (needed to deport): {}", access_token);
     println!("POST: {}arg0/arg1/...", url);
     println!("PUT: {}[request]", url);
     Ok(())
 }
 
+pub async fn main_opts_instance_token(
+    api: &mut DeployApi,
+    name: &str,
+    action: OptsTokenAction,
+) -> Result<(), InstanceError> {
+    match action {
+        OptsTokenAction::Mint(opts) => {
+            let (service_instance, wallet_instance) = api.instance_action(name).await?;
+            let mut service_instance = service_instance?;
+            let chain = ChainKey::from(service_instance.chain.clone());
+
+            // Narrower tokens start from the full capability of the export
+            let export = service_instance.exports
+                .iter()
+                .await?
+                .filter(|e| e.binary.eq_ignore_ascii_case(opts.binary.as_str()))
+                .next()
+                .ok_or_else(|| InstanceErrorKind::NotExported)?;
+            let mut capability = ExportCapability::new(&chain, export.deref());
+            capability.http &= opts.no_http == false;
+            capability.bus &= opts.no_bus == false;
+            capability.topics = opts.topics.clone();
+            capability.expires = opts.expire;
+            capability.rate_limit = opts.rate_limit;
+            if capability.http == false && capability.bus == false {
+                bail!(InstanceErrorKind::InvalidRequest("the token would not allow any access".to_string()));
+            }
+
+            let dio = service_instance.dio_mut();
+            let export_key = api.instance_export_key(wallet_instance.deref(), &mut service_instance).await?;
+            dio.commit().await?;
+            api.dio.commit().await?;
+            let token = ExportToken::mint(&export_key, capability)?;
+
+            println!("{}", token);
+        }
+    }
+    Ok(())
+}
+
 fn compute_export_url(inst_url: &url::Url, chain: &ChainKey, binary: &str) -> String
 {
     // Build the URL that can be used to access this binary
@@ -360,10 +577,33 @@ pub async fn main_opts_instance_deport(
             let dio = service_instance.dio_mut();
             let id = service_instance.id_str();
 
+            // Either the raw token of the export or a capability token that
+            // carries the full capability of the export may be used to deport it
+            let chain = ChainKey::from(service_instance.chain.clone());
+            let capability = match ExportToken::is_capability(access_token) {
+                true => {
+                    let export_key = service_instance.export_public_key.clone()
+                        .ok_or(InstanceErrorKind::InvalidAccessToken)?;
+                    let token = ExportToken::decode(access_token)
+                        .ok_or(InstanceErrorKind::InvalidAccessToken)?;
+                    let capability = token.verify(&export_key)
+                        .ok_or(InstanceErrorKind::InvalidAccessToken)?
+                        .clone();
+                    Some(capability)
+                }
+                false => None,
+            };
+
             let export = service_instance.as_mut().exports.iter_mut().await?
-                .filter(|e| e.access_token.eq_ignore_ascii_case(access_token))
+                .filter(|e| match capability.as_ref() {
+                    Some(capability) => capability.is_for(e),
+                    None => e.access_token.eq_ignore_ascii_case(access_token.trim()),
+                })
                 .next()
                 .ok_or(InstanceErrorKind::InvalidAccessToken)?;
+            if let Some(capability) = capability.as_ref() {
+                capability.check_deport(&chain, export.deref())?;
+            }
 
             let binary = export.binary.clone();
 
@@ -456,13 +696,129 @@ pub async fn main_opts_instance_reset(
     Ok(())
 }
 
+pub async fn main_opts_instance_logs(
+    api: &mut DeployApi,
+    name: &str,
+    opts: OptsInstanceLogs,
+    output: OutputFormat,
+) -> Result<(), InstanceError> {
+    let (instance, _) = api.instance_action(name).await?;
+    let instance = instance?;
+
+    let dio = instance.trans().dio.clone();
+    let logs = DaoVec::<InstanceLog>::new_orphaned(&dio, PrimaryKey::from(INSTANCE_ROOT_ID), INSTANCE_LOG_COLLECTION_ID);
+    let is_match = |log: &InstanceLog| {
+        if let Some(export) = opts.export.as_ref() {
+            if log.binary.eq_ignore_ascii_case(export.as_str()) == false {
+                return false;
+            }
+        }
+        if let Some(since) = opts.since.as_ref() {
+            if log.when < *since {
+                return false;
+            }
+        }
+        true
+    };
+
+    // Subscribe before the existing records are read so that nothing
+    // recorded in between is missed
+    let mut bus = match opts.follow {
+        true => Some(logs.bus().await.map_err(AteError::from)?),
+        false => None,
+    };
+
+    // Display the records that have already been recorded (oldest first)
+    let mut existing = logs.iter().await?.collect::<Vec<_>>();
+    existing.sort_by_key(|a| a.when);
+    let mut shown = std::collections::HashSet::new();
+    for log in existing {
+        shown.insert(log.key().clone());
+        if is_match(log.deref()) {
+            emit(output, log.deref());
+        }
+    }
+
+    // Stream new records as they are appended to the chain
+    if let Some(bus) = bus.as_mut() {
+        loop {
+            match bus.recv().await {
+                Ok(BusEvent::Updated(log)) => {
+                    if shown.insert(log.key().clone()) && is_match(log.deref()) {
+                        emit(output, log.deref());
+                    }
+                }
+                Ok(BusEvent::Deleted(_)) => { }
+                Err(ate::error::BusError(ate::error::BusErrorKind::Lagged(_), _)) => {
+                    // Records were dropped while following so the ones that
+                    // were missed are read from the chain instead
+                    let mut missed = logs
+                        .iter()
+                        .await?
+                        .filter(|a| shown.contains(a.key()) == false)
+                        .collect::<Vec<_>>();
+                    missed.sort_by_key(|a| a.when);
+                    for log in missed {
+                        shown.insert(log.key().clone());
+                        if is_match(log.deref()) {
+                            emit(output, log.deref());
+                        }
+                    }
+                }
+                Err(err) => return Err(AteError::from(err).into()),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the windows of invocation metrics that the instance hosts wrote
+/// to the instance chain and summarizes them per exported binary
+async fn load_instance_stats(
+    instance: &DaoMut<ServiceInstance>,
+    export: Option<&str>,
+    since: Option<&chrono::DateTime<chrono::Utc>>,
+) -> Result<InstanceStatsOutput, InstanceError> {
+    let dio = instance.trans().dio.clone();
+    let windows = DaoVec::<InstanceMetricsWindow>::new_orphaned(&dio, PrimaryKey::from(INSTANCE_ROOT_ID), INSTANCE_METRICS_COLLECTION_ID);
+
+    let mut windows = windows
+        .iter()
+        .await?
+        .map(|a| a.take())
+        .filter(|a| since.map_or(true, |since| a.end() > *since))
+        .collect::<Vec<_>>();
+    if let Some(export) = export {
+        for window in windows.iter_mut() {
+            window.exports.retain(|a| a.binary.eq_ignore_ascii_case(export));
+        }
+    }
+    Ok(InstanceStatsOutput::from_windows(&windows[..]))
+}
+
+pub async fn main_opts_instance_stats(
+    api: &mut DeployApi,
+    name: &str,
+    opts: OptsInstanceStats,
+    output: OutputFormat,
+) -> Result<(), InstanceError> {
+    let (instance, _) = api.instance_action(name).await?;
+    let instance = instance?;
+
+    let result = load_instance_stats(&instance, opts.export.as_ref().map(|a| a.as_str()), opts.since.as_ref()).await?;
+    emit(output, &result);
+    Ok(())
+}
+
 pub async fn main_opts_instance(
     opts: OptsInstanceFor,
     token_path: String,
     auth_url: url::Url,
     db_url: url::Url,
     inst_url: url::Url,
-    security: StreamSecurity
+    security: StreamSecurity,
+    output: OutputFormat,
 ) -> Result<(), InstanceError>
 {
     // Check if sudo is needed
@@ -482,19 +838,22 @@ pub async fn main_opts_instance(
 
     // Perform the action
     let name = opts.action().name();
-    let mut context = PurposeContext::new(&opts, token_path.as_str(), &auth_url, Some(&db_url), needs_sudo).await?;
+    let connector = SessionConnector::new(auth_url)
+        .with_db_url(db_url.clone())
+        .with_inst_url(inst_url.clone());
+    let mut context = PurposeContext::new(&opts, token_path.as_str(), &connector, needs_sudo).await?;
     
     // Determine what we need to do
     let purpose: &dyn OptsPurpose<OptsInstanceAction> = &opts;
     match purpose.action() {
         OptsInstanceAction::List => {
-            main_opts_instance_list(&mut context.api).await?;
+            main_opts_instance_list(&mut context.api, output).await?;
         }
         OptsInstanceAction::Details(opts) => {
-            main_opts_instance_details(&mut context.api, inst_url, opts).await?;
+            main_opts_instance_details(&mut context.api, inst_url, opts, output).await?;
         }
         OptsInstanceAction::Create(opts) => {
-            main_opts_instance_create(&mut context.api, opts.name, purpose.group_name(), db_url, instance_authority, opts.force).await?;
+            main_opts_instance_create(&mut context.api, opts.name, purpose.group_name(), db_url, instance_authority, opts.force, opts.template).await?;
         }
         OptsInstanceAction::Kill(opts_kill) => {
             if name.is_none() { bail!(InstanceErrorKind::InvalidInstance); }
@@ -504,12 +863,19 @@ pub async fn main_opts_instance(
         OptsInstanceAction::Shell(_opts_exec) => {
             if name.is_none() { bail!(InstanceErrorKind::InvalidInstance); }
             let name = name.unwrap();
-            main_opts_instance_shell(&mut context.api, inst_url, name.as_str(), security).await?;
+            main_opts_instance_shell(&mut context.api, &connector, name.as_str(), security).await?;
         }
         OptsInstanceAction::Call(opts_call) => {
             if name.is_none() { bail!(InstanceErrorKind::InvalidInstance); }
             let name = name.unwrap();
-            main_opts_instance_call(&mut context.api, inst_url, name.as_str(), opts_call.format, opts_call.data.as_str(), opts_call.topic.as_str(), security).await?;
+            if opts_call.interactive {
+                let code = main_opts_instance_call_interactive(&mut context.api, &connector, name.as_str(), opts_call.format, opts_call.data.as_str(), opts_call.topic.as_str(), opts_call.binary_frames, security).await?;
+                if code != 0 {
+                    std::process::exit(code);
+                }
+            } else {
+                main_opts_instance_call(&mut context.api, &connector, name.as_str(), opts_call.format, opts_call.data.as_str(), opts_call.topic.as_str(), security).await?;
+            }
         }
         OptsInstanceAction::Export(opts_export) => {
             if name.is_none() { bail!(InstanceErrorKind::InvalidInstance); }
@@ -521,6 +887,11 @@ pub async fn main_opts_instance(
             let name = name.unwrap();
             main_opts_instance_deport(&mut context.api, name.as_str(), opts_deport.token.as_str()).await?;
         }
+        OptsInstanceAction::Token(opts_token) => {
+            if name.is_none() { bail!(InstanceErrorKind::InvalidInstance); }
+            let name = name.unwrap();
+            main_opts_instance_token(&mut context.api, name.as_str(), opts_token.action).await?;
+        }
         OptsInstanceAction::Clone(_opts_clone) => {
             if name.is_none() { bail!(InstanceErrorKind::InvalidInstance); }
             let name = name.unwrap();
@@ -546,6 +917,16 @@ pub async fn main_opts_instance(
             let name = name.unwrap();
             main_opts_instance_reset(&mut context.api, name.as_str()).await?;
         }
+        OptsInstanceAction::Logs(opts_logs) => {
+            if name.is_none() { bail!(InstanceErrorKind::InvalidInstance); }
+            let name = name.unwrap();
+            main_opts_instance_logs(&mut context.api, name.as_str(), opts_logs, output).await?;
+        }
+        OptsInstanceAction::Stats(opts_stats) => {
+            if name.is_none() { bail!(InstanceErrorKind::InvalidInstance); }
+            let name = name.unwrap();
+            main_opts_instance_stats(&mut context.api, name.as_str(), opts_stats, output).await?;
+        }
     }
 
     Ok(())
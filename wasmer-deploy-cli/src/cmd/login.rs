@@ -1,4 +1,3 @@
-use ate::error::AteError;
 use ate::prelude::*;
 
 use wasmer_auth::cmd::*;
@@ -7,26 +6,50 @@ use wasmer_auth::helper::*;
 #[cfg(target_os = "wasi")]
 use wasmer_bus_process::prelude::*;
 
+use crate::error::*;
+use crate::helper::SessionConnector;
 use crate::opt::*;
 
 pub async fn main_opts_login(
     action: OptsLogin,
     token_path: String,
     auth: url::Url,
-) -> Result<(), AteError> {
+) -> Result<(), CoreError> {
     // Convert the token path to a real path
     let token_path = shellexpand::tilde(&token_path).to_string();
+    let connector = SessionConnector::new(auth.clone());
 
     // If a token was supplied then just use it, otherwise we need to get one
+    // (only normal sessions are refreshed, sudo rights require a new login)
+    let mut refresh_token = None;
     let token = if let Some(token) = action.token {
         token
     } else {
+        // Make sure the authentication server is reachable before prompting
+        // for the credentials (this also absorbs transient failures)
+        let registry = connector.probe_auth(&conf_cmd()).await?;
+
         // Get the token session
-        let session = main_login(action.email, action.password, auth.clone()).await?;
+        let (email, password) = (action.email, action.password);
+        let response = connector
+            .request(&auth, async {
+                main_login_with_registry(&registry, email, password, auth.clone())
+                    .await
+                    .map_err(login_error)
+            })
+            .await?;
         let session: AteSessionType = if action.sudo {
-            main_sudo(session, None, auth).await?.into()
+            connector
+                .request(&auth, async {
+                    main_sudo(response.authority, None, auth.clone())
+                        .await
+                        .map_err(sudo_error)
+                })
+                .await?
+                .into()
         } else {
-            session.into()
+            refresh_token = response.refresh_token;
+            response.authority.into()
         };
         session_to_b64(session).unwrap()
     };
@@ -37,7 +60,11 @@ pub async fn main_opts_login(
     let identity = session.identity();
 
     // Save the token
-    save_token(token, token_path)?;
+    save_token(token, token_path.clone())?;
+    match refresh_token {
+        Some(refresh_token) => save_refresh_token(&refresh_token, token_path)?,
+        None => clear_refresh_token(token_path),
+    }
 
     // If we are in WASM mode and there is a login script then run it
     #[cfg(target_os = "wasi")]
@@ -67,3 +94,26 @@ pub async fn main_opts_login(
 
     Ok(())
 }
+
+/// Keeps the kind of the errors raised by ate (e.g. a connection that failed)
+/// so that the connector can recognise them, the rest become a message
+fn login_error(err: LoginError) -> CoreError {
+    match err {
+        LoginError(LoginErrorKind::AteError(err), _) => CoreErrorKind::AteError(err).into(),
+        LoginError(LoginErrorKind::ChainCreationError(err), _) => {
+            CoreErrorKind::ChainCreationError(err).into()
+        }
+        err => CoreError::from(AteError::from(err)),
+    }
+}
+
+fn sudo_error(err: SudoError) -> CoreError {
+    match err {
+        SudoError(SudoErrorKind::AteError(err), _) => CoreErrorKind::AteError(err).into(),
+        SudoError(SudoErrorKind::ChainCreationError(err), _) => {
+            CoreErrorKind::ChainCreationError(err).into()
+        }
+        SudoError(SudoErrorKind::LoginError(err), _) => login_error(err.into()),
+        err => CoreError::from(AteError::from(err)),
+    }
+}
@@ -1,4 +1,6 @@
 use ate::error::AteError;
+use wasmer_auth::cmd::*;
+use wasmer_auth::helper::*;
 #[cfg(target_os = "wasi")]
 use wasmer_bus_process::prelude::*;
 
@@ -7,11 +9,21 @@ use crate::opt::*;
 pub async fn main_opts_logout(
     _opts_logout: OptsLogout,
     token_path: String,
+    auth: url::Url,
 ) -> Result<(), AteError> {
     // Convert the token path to a real path
     let token_network_path = format!("{}.network", token_path);
     let token_network_path = shellexpand::tilde(&token_network_path).to_string();
     let token_path = shellexpand::tilde(&token_path).to_string();
+
+    // Revoke the refresh token so the session can not be renewed anymore
+    if let Some(refresh_token) = load_refresh_token(token_path.clone()) {
+        let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+        if let Err(err) = revoke_refresh_command(&registry, refresh_token, auth).await {
+            eprintln!("Failed to revoke the refresh token - {}", err);
+        }
+    }
+    clear_refresh_token(token_path.clone());
     
     // Remove any old paths
     if let Ok(old) = std::fs::canonicalize(token_network_path.clone()) {
@@ -1,5 +1,6 @@
 mod balance;
 mod cancel_deposit;
+mod coin;
 mod coin_carve;
 mod coin_collect;
 mod coin_combine;
@@ -12,14 +13,19 @@ mod contract_details;
 mod contract_elevate;
 mod contract_list;
 mod core;
+mod currency_rates;
 mod deposit;
 mod history;
 mod login;
 mod logout;
+mod offline;
 mod service;
 mod service_find;
+mod setup;
+mod sub_wallet;
 mod transfer;
 mod wallet;
+mod watch;
 mod withdraw;
 mod instance;
 mod cidr;
@@ -31,6 +37,7 @@ pub use wasmer_auth::cmd::*;
 pub use self::core::*;
 pub use balance::*;
 pub use cancel_deposit::*;
+pub use coin::*;
 pub use coin_carve::*;
 pub use coin_collect::*;
 pub use coin_combine::*;
@@ -42,14 +49,19 @@ pub use contract_create::*;
 pub use contract_details::*;
 pub use contract_elevate::*;
 pub use contract_list::*;
+pub use currency_rates::*;
 pub use deposit::*;
 pub use history::*;
 pub use login::*;
 pub use logout::*;
+pub use offline::*;
 pub use service::*;
 pub use service_find::*;
+pub use setup::*;
+pub use sub_wallet::*;
 pub use transfer::*;
 pub use wallet::*;
+pub use watch::*;
 pub use withdraw::*;
 pub use instance::*;
 pub use cidr::*;
@@ -26,6 +26,7 @@ use ate::prelude::*;
 use wasmer_bus_mio::prelude::*;
 
 use crate::error::*;
+use crate::helper::SessionConnector;
 use crate::model::NetworkToken;
 #[allow(unused_imports)]
 use crate::model::HardwareAddress;
@@ -46,7 +47,8 @@ pub async fn main_opts_network(
     {
         OptsNetworkCommand::For(opts) => {
             let purpose: &dyn OptsPurpose<OptsNetworkAction> = &opts.purpose;
-            let mut context = PurposeContext::new(purpose, token_path.as_str(), &auth_url, Some(&db_url), true).await?;
+            let connector = SessionConnector::new(auth_url.clone()).with_db_url(db_url.clone());
+            let mut context = PurposeContext::new(purpose, token_path.as_str(), &connector, true).await?;
             match context.action.clone() {
                 OptsNetworkAction::List => {
                     main_opts_network_list(&mut context.api).await
@@ -272,7 +274,7 @@ pub async fn main_opts_network_create(
     instance_authority: String,
     force: bool,
 ) -> Result<(), InstanceError> {
-    main_opts_instance_create(api, network_name, group, db_url, instance_authority, force).await
+    main_opts_instance_create(api, network_name, group, db_url, instance_authority, force, None).await
 }
 
 pub async fn main_opts_network_kill(
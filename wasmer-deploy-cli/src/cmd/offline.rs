@@ -0,0 +1,260 @@
+//! Read-only commands that run against a snapshot of a chain (written by
+//! `wallet export`) instead of the remote database. The snapshot is imported
+//! into a temporary local chain, the session comes straight from the token
+//! file (nothing is sent to the authentication server) and every result is
+//! labelled with the time of the newest event in the snapshot.
+use chrono::prelude::*;
+use error_chain::bail;
+use std::ops::Deref;
+#[allow(unused_imports)]
+use tracing::{debug, error, info};
+
+use ate::prelude::*;
+use ate::utils::chain_key_4hex;
+
+use crate::api::*;
+use crate::error::*;
+use crate::opt::*;
+use crate::output::*;
+
+use super::*;
+
+/// Snapshot of a chain that has been imported into a temporary folder
+pub struct OfflineSnapshot {
+    /// Path of the archive the snapshot was read from
+    pub path: String,
+    pub archive: ChainArchive,
+    /// Time of the newest event in the snapshot
+    pub as_of: DateTime<Utc>,
+    temp_path: std::path::PathBuf,
+}
+
+impl OfflineSnapshot {
+    /// Imports the archive and makes sure it holds the chain that is expected
+    pub async fn open(path: &str, expected: &ChainKey) -> Result<OfflineSnapshot, CoreError> {
+        let temp_path = std::env::temp_dir().join(format!(
+            "wasmer-snapshot-{}",
+            PrimaryKey::generate().as_hex_string()
+        ));
+
+        let mut conf = wasmer_auth::helper::conf_auth();
+        conf.log_path = Some(temp_path.to_string_lossy().to_string());
+        conf.ntp_sync = false;
+
+        let archive = ChainBuilder::new(&conf).await.open_archive(path).await;
+        let archive = match archive {
+            Ok(a) => a,
+            Err(err) => {
+                let _ = std::fs::remove_dir_all(&temp_path);
+                bail!(err);
+            }
+        };
+        let ret = OfflineSnapshot {
+            path: path.to_string(),
+            as_of: Self::to_utc(archive.head.time_since_epoch_ms),
+            archive,
+            temp_path,
+        };
+
+        if ret.archive.manifest.key != *expected {
+            bail!(ChainCreationError::from(ChainCreationErrorKind::ArchiveInvalid(
+                path.to_string(),
+                format!(
+                    "it holds the chain {} rather than {}",
+                    ret.archive.manifest.key, expected
+                )
+            )));
+        }
+        debug!(
+            "opened snapshot {} ({} events as of {})",
+            path, ret.archive.manifest.events, ret.as_of
+        );
+        Ok(ret)
+    }
+
+    fn to_utc(time_since_epoch_ms: u64) -> DateTime<Utc> {
+        let secs = time_since_epoch_ms / 1000;
+        let nsecs = (time_since_epoch_ms % 1000) * 1000 * 1000;
+        let when = NaiveDateTime::from_timestamp(secs as i64, nsecs as u32);
+        DateTime::<Utc>::from_utc(when, Utc)
+    }
+
+    /// Labels the result with the point in time the snapshot reflects
+    pub fn output<T>(&self, result: T) -> SnapshotOutput<T> {
+        SnapshotOutput::new(self.as_of.clone(), self.path.clone(), result)
+    }
+
+    /// Opens the API against the snapshot for the wallet of the purpose
+    async fn api<A>(
+        &self,
+        purpose: &dyn OptsPurpose<A>,
+        session: &AteSessionType,
+        identity: &String,
+        auth_url: url::Url,
+    ) -> Result<DeployApi, CoreError>
+    where
+        A: Clone,
+    {
+        // The chain is read-only hence the transaction is never committed
+        let dio = self
+            .archive
+            .chain
+            .dio(session)
+            .await
+            .trans(TransactionScope::None)
+            .await;
+        let wallet = get_wallet(purpose, &dio, identity).await?;
+        let registry = Registry::new(&wasmer_auth::helper::conf_auth())
+            .await
+            .cement();
+        Ok(build_api_accessor(&dio, wallet, auth_url, None, &registry).await)
+    }
+}
+
+impl Drop for OfflineSnapshot {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.temp_path);
+    }
+}
+
+fn offline_unsupported(command: &str) -> CoreError {
+    CoreErrorKind::OfflineUnsupported(command.to_string()).into()
+}
+
+/// Writes a snapshot of the chain that holds the wallet (which includes its
+/// history and the records of its instances) so it can be read offline
+pub async fn main_opts_wallet_export(
+    opts: OptsExportWallet,
+    chain: &ChainGuard,
+    output: OutputFormat,
+) -> Result<(), WalletError> {
+    let manifest = chain.export_archive(opts.path.as_str()).await?;
+    let result = SnapshotExportOutput {
+        snapshot: opts.path,
+        chain: manifest.key.to_string(),
+        events: manifest.events,
+        as_of: OfflineSnapshot::to_utc(manifest.head.time_since_epoch_ms),
+    };
+    emit(output, &result);
+    Ok(())
+}
+
+/// Builds the session from the token and opens the snapshot of the chain
+/// that belongs to the user or group of the purpose
+async fn open_offline<A>(
+    purpose: &dyn OptsPurpose<A>,
+    token_path: &str,
+    snapshot: &str,
+    sudo: bool,
+) -> Result<(AteSessionType, String, OfflineSnapshot), CoreError>
+where
+    A: Clone,
+{
+    let (_, session) = session_with_permissions_ext(purpose, token_path, None, sudo).await?;
+    let identity = get_identity(purpose, &session).await?;
+    let chain_key = chain_key_4hex(identity.as_str(), Some("redo"));
+    let snapshot = OfflineSnapshot::open(snapshot, &chain_key).await?;
+    Ok((session, identity, snapshot))
+}
+
+pub async fn main_offline_wallet(
+    opts_wallet: OptsWalletSource,
+    token_path: String,
+    auth_url: url::Url,
+    snapshot: String,
+    output: OutputFormat,
+) -> Result<(), WalletError> {
+    // Only the commands that read the last-known state may run offline
+    match opts_wallet.action() {
+        OptWalletAction::Balance(opts) if opts.convert.is_none() => {}
+        OptWalletAction::Balance(_) => bail!(offline_unsupported("wallet balance --in")),
+        OptWalletAction::History(_) => {}
+        action => bail!(offline_unsupported(format!("wallet {}", action.command()).as_str())),
+    }
+
+    let (session, identity, snapshot) =
+        open_offline(&opts_wallet, token_path.as_str(), snapshot.as_str(), true).await?;
+    let mut api = snapshot
+        .api(&opts_wallet, &session, &identity, auth_url)
+        .await?;
+
+    // When a sub-wallet was named then it is read instead
+    let wallet_name = get_wallet_name(&opts_wallet)?;
+    if let (_, Some(sub_wallet)) = split_sub_wallet_name(wallet_name.as_str()) {
+        api = api.sub_wallet_api(sub_wallet).await?;
+    }
+
+    // The wallet can not be reconciled as that would change it
+    match opts_wallet.action().clone() {
+        OptWalletAction::Balance(mut opts_balance) => {
+            opts_balance.no_reconcile = true;
+            let result = balance_output(&opts_balance, &mut api).await?;
+            emit(output, &snapshot.output(result));
+        }
+        OptWalletAction::History(mut opts_history) => {
+            opts_history.no_reconcile = true;
+            let result = history_output(&opts_history, &mut api).await?;
+            emit(output, &snapshot.output(result));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub async fn main_offline_instance(
+    opts: OptsInstanceFor,
+    token_path: String,
+    auth_url: url::Url,
+    snapshot: String,
+    output: OutputFormat,
+) -> Result<(), InstanceError> {
+    // Only the instance records held in the wallet are part of the snapshot
+    // (the chains of the instances themselves are not)
+    match opts.action() {
+        OptsInstanceAction::List => {}
+        OptsInstanceAction::Details(_) => {}
+        action => bail!(offline_unsupported(format!("instance {}", action.command()).as_str())),
+    }
+
+    let (session, identity, snapshot) =
+        open_offline(&opts, token_path.as_str(), snapshot.as_str(), opts.action().needs_sudo()).await?;
+    let api = snapshot.api(&opts, &session, &identity, auth_url).await?;
+
+    match opts.action() {
+        OptsInstanceAction::List => {
+            let mut result = InstanceListOutput::default();
+            for instance in api.instances().await.iter().await? {
+                result.instances.push(InstanceListEntry {
+                    name: instance.name.clone(),
+                    created: None,
+                    exports: Vec::new(),
+                    error: None,
+                });
+            }
+            emit(output, &snapshot.output(result));
+        }
+        OptsInstanceAction::Details(opts) => {
+            let instance = match api.instance_find(opts.name.as_str()).await {
+                Ok(a) => a,
+                Err(InstanceError(InstanceErrorKind::InvalidInstance, _)) => {
+                    emit_error(output, "An instance does not exist in this snapshot.");
+                    std::process::exit(1);
+                }
+                Err(err) => {
+                    bail!(err);
+                }
+            };
+            let result = InstanceDetailsOutput {
+                instance: instance.deref().clone(),
+                subnet: None,
+                id: Some(instance.id_str()),
+                exports: Vec::new(),
+                stats: Vec::new(),
+                quota: None,
+            };
+            emit(output, &snapshot.output(result));
+        }
+        _ => {}
+    }
+    Ok(())
+}
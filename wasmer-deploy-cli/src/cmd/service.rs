@@ -6,6 +6,7 @@ use tracing::{debug, error, info};
 
 use crate::api::*;
 use crate::error::*;
+use crate::helper::SessionConnector;
 use crate::opt::*;
 
 use super::*;
@@ -139,8 +140,9 @@ pub async fn main_opts_service(
             main_opts_service_details(opts, &auth_url).await?;
         }
         OptsServiceAction::Subscribe(opts) => {
+            let connector = SessionConnector::new(auth_url.clone());
             let mut context =
-                PurposeContext::new(purpose, token_path.as_str(), &auth_url, None, true).await?;
+                PurposeContext::new(purpose, token_path.as_str(), &connector, true).await?;
             main_opts_service_subscribe(opts, &mut context.api).await?;
             context.api.commit().await?;
         }
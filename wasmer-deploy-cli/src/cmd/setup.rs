@@ -0,0 +1,436 @@
+use std::io::stdout;
+use std::io::Write;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, warn};
+
+use ate::prelude::*;
+use wasmer_auth::error::*;
+use wasmer_auth::helper::*;
+
+use crate::model::*;
+use crate::opt::*;
+
+use super::core::*;
+use super::*;
+
+/// Name of the personal wallet that the setup wizard initializes
+const SETUP_WALLET_NAME: &str = "default";
+
+/// What happened during one of the steps of the setup wizard
+enum SetupOutcome {
+    Created(String),
+    Existing(String),
+    Skipped(String),
+    Failed(String),
+}
+
+struct SetupStep {
+    name: &'static str,
+    outcome: SetupOutcome,
+    manage: Vec<String>,
+}
+
+impl SetupStep {
+    fn new(name: &'static str, outcome: SetupOutcome) -> SetupStep {
+        SetupStep {
+            name,
+            outcome,
+            manage: Vec::new(),
+        }
+    }
+
+    fn manage(mut self, cmd: &str) -> SetupStep {
+        self.manage.push(cmd.to_string());
+        self
+    }
+}
+
+/// Gathers the values that the wizard needs, either by prompting the user or
+/// (in defaults mode) from the environment so that it can run in CI
+struct SetupPrompt {
+    defaults: bool,
+}
+
+impl SetupPrompt {
+    fn value(&self, prompt: &str, env: &str) -> Result<Option<String>, AteError> {
+        if self.defaults {
+            return Ok(std::env::var(env).ok().filter(|a| a.trim().len() > 0));
+        }
+        if !is_tty_stdin() {
+            return Ok(None);
+        }
+
+        eprint!("{}: ", prompt);
+        stdout().lock().flush()?;
+        let mut s = String::new();
+        std::io::stdin().read_line(&mut s)?;
+        let s = s.trim().to_string();
+        Ok(if s.len() > 0 { Some(s) } else { None })
+    }
+
+    /// Secrets are read without echoing them to the terminal
+    fn secret(&self, prompt: &str, env: &str) -> Result<Option<String>, AteError> {
+        if self.defaults {
+            return Ok(std::env::var(env).ok().filter(|a| a.len() > 0));
+        }
+        if !is_tty_stdin() {
+            return Ok(None);
+        }
+
+        let s = rpassword_wasi::prompt_password(format!("{}: ", prompt).as_str())?;
+        Ok(if s.len() > 0 { Some(s) } else { None })
+    }
+
+    fn required(&self, prompt: &str, env: &str) -> Result<String, AteError> {
+        match self.value(prompt, env)? {
+            Some(a) => Ok(a),
+            None => Err(LoginError::from(LoginErrorKind::InvalidArguments).into()),
+        }
+    }
+}
+
+pub async fn main_opts_setup(
+    opts: OptsSetup,
+    token_path: String,
+    auth: url::Url,
+) -> Result<(), AteError> {
+    let token_path = shellexpand::tilde(&token_path).to_string();
+    let prompt = SetupPrompt {
+        defaults: opts.defaults,
+    };
+    let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+    let mut summary = Vec::new();
+
+    // Every step first checks if it was already completed so that the wizard
+    // can be run again to resume from where it left off
+    let session = setup_account(&prompt, &registry, &opts, &token_path, &auth, &mut summary).await?;
+
+    summary.push(if opts.skip_group {
+        SetupStep::new("Group", SetupOutcome::Skipped("not requested".to_string()))
+    } else {
+        setup_group(&prompt, &registry, &opts, &session, &auth).await?
+    });
+
+    summary.push(if opts.skip_wallet {
+        SetupStep::new("Wallet", SetupOutcome::Skipped("not requested".to_string()))
+    } else {
+        setup_wallet(&prompt, &registry, &opts, &session, &auth).await?
+    });
+
+    println!("");
+    println!("# Setup Summary");
+    println!("");
+    for step in summary.iter() {
+        let (state, detail) = match &step.outcome {
+            SetupOutcome::Created(a) => ("created", a),
+            SetupOutcome::Existing(a) => ("already done", a),
+            SetupOutcome::Skipped(a) => ("skipped", a),
+            SetupOutcome::Failed(a) => ("failed", a),
+        };
+        println!("{}: {} - {}", step.name, state, detail);
+        for cmd in step.manage.iter() {
+            println!("  $ {}", cmd);
+        }
+    }
+    if summary
+        .iter()
+        .any(|a| matches!(a.outcome, SetupOutcome::Failed(_)))
+    {
+        println!("");
+        println!("Run 'wasmer-deploy setup' again to retry the steps that failed.");
+    }
+
+    Ok(())
+}
+
+async fn setup_account(
+    prompt: &SetupPrompt,
+    registry: &Registry,
+    opts: &OptsSetup,
+    token_path: &String,
+    auth: &url::Url,
+    summary: &mut Vec<SetupStep>,
+) -> Result<AteSessionUser, AteError> {
+    // A saved token means that we are already logged in
+    if std::path::Path::new(token_path).exists() {
+        let session = main_session_user(None, Some(token_path.clone()), None).await?;
+        if session.identity.len() > 0 {
+            summary.push(
+                SetupStep::new("Account", SetupOutcome::Existing(session.identity.clone()))
+                    .manage("wasmer-deploy user details")
+                    .manage("wasmer-deploy logout"),
+            );
+            summary.push(
+                SetupStep::new("Token", SetupOutcome::Existing(token_path.clone()))
+                    .manage("wasmer-deploy login"),
+            );
+            return Ok(session);
+        }
+    }
+
+    let email = match opts.email.clone() {
+        Some(a) => a,
+        None => prompt.required("Email", "WASMER_EMAIL")?,
+    };
+
+    // If the account does not exist then we will create it
+    let exists = match query_command(registry, email.clone(), auth.clone()).await {
+        Ok(_) => true,
+        Err(QueryError(QueryErrorKind::NotFound, _)) => false,
+        Err(err) => return Err(err.into()),
+    };
+
+    let password = match prompt.secret("Password", "WASMER_PASSWORD")? {
+        Some(a) => a,
+        None => return Err(LoginError::from(LoginErrorKind::InvalidArguments).into()),
+    };
+    let outcome = if exists {
+        SetupOutcome::Existing(email.clone())
+    } else {
+        if prompt.defaults == false {
+            let again = prompt.secret("Password Again", "WASMER_PASSWORD")?;
+            if again.as_ref() != Some(&password) {
+                return Err(CreateError::from(CreateErrorKind::PasswordMismatch).into());
+            }
+        }
+        setup_create_user(prompt, registry, &email, &password, auth).await?;
+        SetupOutcome::Created(email.clone())
+    };
+
+    // Login (verifying the email address if this has not been done yet)
+    let mut response = login_command_ext(
+        registry,
+        email.clone(),
+        password.clone(),
+        None,
+        auth.clone(),
+        true,
+    )
+    .await;
+    if let Err(LoginError(LoginErrorKind::Unverified(_), _)) = &response {
+        if prompt.defaults == false {
+            eprintln!("Check your email for a verification code and enter it below");
+        }
+        let code = prompt.required("Verification Code", "WASMER_VERIFY_CODE")?;
+        response = login_command_ext(
+            registry,
+            email.clone(),
+            password.clone(),
+            Some(code),
+            auth.clone(),
+            true,
+        )
+        .await;
+    }
+    let response = response?;
+    let session = response.authority;
+    summary.push(
+        SetupStep::new("Account", outcome)
+            .manage("wasmer-deploy user details")
+            .manage("wasmer-deploy logout"),
+    );
+
+    // Save the token so that the other commands can use it
+    let token = session_to_b64(AteSessionType::User(session.clone()))?;
+    save_token(token, token_path.clone())?;
+    if let Some(refresh_token) = response.refresh_token.as_ref() {
+        save_refresh_token(refresh_token, token_path.clone())?;
+    }
+    summary.push(
+        SetupStep::new("Token", SetupOutcome::Created(token_path.clone()))
+            .manage("wasmer-deploy login"),
+    );
+
+    Ok(session)
+}
+
+async fn setup_create_user(
+    prompt: &SetupPrompt,
+    registry: &Registry,
+    email: &String,
+    password: &String,
+    auth: &url::Url,
+) -> Result<(), AteError> {
+    let result = match create_user_command(
+        registry,
+        email.clone(),
+        password.clone(),
+        auth.clone(),
+        None,
+    )
+    .await
+    {
+        Ok(a) => a,
+        Err(CreateError(CreateErrorKind::TermsAndConditions(terms), _)) => {
+            // We need an agreement to the terms and conditions from the caller
+            let agreement = if prompt.defaults {
+                std::env::var("WASMER_ACCEPT_TERMS").unwrap_or_default()
+            } else {
+                println!("");
+                println!("{}", terms);
+                println!("");
+                println!(
+                    "If you agree to the above terms and conditions then type the word 'agree' below"
+                );
+                prompt
+                    .value("Agreement", "WASMER_ACCEPT_TERMS")?
+                    .unwrap_or_default()
+            };
+            if agreement.to_lowercase() != "agree" {
+                eprintln!("You may only create an account by specifically agreeing to the terms");
+                eprintln!("and conditions (or setting WASMER_ACCEPT_TERMS=agree with --defaults).");
+                return Err(CreateError::from(CreateErrorKind::InvalidArguments).into());
+            }
+            create_user_command(
+                registry,
+                email.clone(),
+                password.clone(),
+                auth.clone(),
+                Some(terms),
+            )
+            .await?
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    if is_tty_stdout() {
+        println!("User created (id={})", result.key);
+        println!("");
+        println!("Below is your Google Authenticator QR code - scan it on your phone and");
+        println!("save it as this code is the only way you can recover the account.");
+        println!("");
+        println!("{}", result.qr_code);
+    }
+    Ok(())
+}
+
+async fn setup_group(
+    prompt: &SetupPrompt,
+    registry: &Registry,
+    opts: &OptsSetup,
+    session: &AteSessionUser,
+    auth: &url::Url,
+) -> Result<SetupStep, AteError> {
+    let group = match opts.group.clone() {
+        Some(a) => a,
+        None => match prompt.value("Domain group to create (leave blank to skip)", "WASMER_GROUP")? {
+            Some(a) => a,
+            None => {
+                return Ok(SetupStep::new(
+                    "Group",
+                    SetupOutcome::Skipped("no group name was given".to_string()),
+                )
+                .manage("wasmer-deploy domain create <name>"));
+            }
+        },
+    };
+
+    let outcome = match group_details_command(registry, group.clone(), auth.clone(), None).await {
+        Ok(_) => SetupOutcome::Existing(group.clone()),
+        Err(GroupDetailsError(GroupDetailsErrorKind::GroupNotFound, _)) => {
+            match create_group_command(registry, group.clone(), auth.clone(), session.identity.clone())
+                .await
+            {
+                Ok(result) => SetupOutcome::Created(format!("{} (id={})", group, result.key)),
+                Err(err) => SetupOutcome::Failed(err.to_string()),
+            }
+        }
+        Err(err) => SetupOutcome::Failed(err.to_string()),
+    };
+
+    Ok(SetupStep::new("Group", outcome)
+        .manage(format!("wasmer-deploy domain details {}", group).as_str())
+        .manage(format!("wasmer-deploy wallet domain {} default create <country>", group).as_str()))
+}
+
+async fn setup_wallet(
+    prompt: &SetupPrompt,
+    registry: &Registry,
+    opts: &OptsSetup,
+    session: &AteSessionUser,
+    auth: &url::Url,
+) -> Result<SetupStep, AteError> {
+    let wallet_name = SETUP_WALLET_NAME.to_string();
+    let with_manage = |step: SetupStep| {
+        step.manage("wasmer-deploy wallet personal default balance")
+            .manage("wasmer-deploy wallet personal default deposit")
+            .manage("wasmer-deploy instance personal create")
+    };
+
+    // The wallet key is derived from its name hence we can check if it exists
+    // without needing the sudo keys that protect it
+    let identity = session.identity.clone();
+    let wallet_key = PrimaryKey::from(format!("wallet://{}/{}", identity, wallet_name));
+    let (chain_registry, _, chain) = open_identity_chain(&identity, auth).await?;
+    if chain.dio(session).await.exists(&wallet_key).await {
+        return Ok(with_manage(SetupStep::new(
+            "Wallet",
+            SetupOutcome::Existing(wallet_name),
+        )));
+    }
+
+    let country = match opts.country.clone() {
+        Some(a) => a,
+        None => match prompt.value(
+            "Country of residence for tax purposes (e.g. USA, leave blank to skip)",
+            "WASMER_COUNTRY",
+        )? {
+            Some(a) => match a.parse::<Country>() {
+                Ok(a) => a,
+                Err(err) => {
+                    return Ok(with_manage(SetupStep::new(
+                        "Wallet",
+                        SetupOutcome::Failed(err.to_string()),
+                    )));
+                }
+            },
+            None => {
+                return Ok(with_manage(SetupStep::new(
+                    "Wallet",
+                    SetupOutcome::Skipped("no country was given".to_string()),
+                ))
+                .manage("wasmer-deploy wallet personal default create <country>"));
+            }
+        },
+    };
+
+    // Wallets are protected by the sudo keys which need the authenticator code
+    let code = match prompt.secret("Authenticator Code", "WASMER_SUDO_CODE")? {
+        Some(a) => a,
+        None => {
+            return Ok(with_manage(SetupStep::new(
+                "Wallet",
+                SetupOutcome::Skipped("no authenticator code was given".to_string()),
+            ))
+            .manage("wasmer-deploy wallet personal default create <country>"));
+        }
+    };
+    let sudo = match sudo_command(registry, session, code, auth.clone()).await {
+        Ok(a) => a,
+        Err(err) => {
+            return Ok(with_manage(SetupStep::new(
+                "Wallet",
+                SetupOutcome::Failed(err.to_string()),
+            )));
+        }
+    };
+
+    let dio = chain.dio_trans(&sudo, TransactionScope::Full).await?;
+    let parent_key = PrimaryKey::from(identity.clone());
+    create_wallet(
+        &dio,
+        auth,
+        &chain_registry,
+        &identity,
+        &wallet_name,
+        &parent_key,
+        country,
+    )
+    .await?;
+    dio.commit().await?;
+
+    Ok(with_manage(SetupStep::new(
+        "Wallet",
+        SetupOutcome::Created(wallet_name),
+    )))
+}
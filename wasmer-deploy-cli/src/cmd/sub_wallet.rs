@@ -0,0 +1,84 @@
+use std::sync::Arc;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use ate::prelude::*;
+
+use crate::api::*;
+use crate::error::*;
+use crate::model::*;
+use crate::opt::*;
+use crate::output::*;
+
+use super::core::*;
+
+pub async fn main_opts_sub_wallet(
+    opts: OptsSubWallet,
+    api: &mut DeployApi,
+    identity: &String,
+    registry: &Arc<Registry>,
+    output: OutputFormat,
+) -> Result<(), WalletError> {
+    match opts.action {
+        OptsSubWalletAction::Create(opts) => {
+            let name = opts.name.to_lowercase();
+            if api.find_sub_wallet(name.as_str()).await.is_ok() {
+                return Err(WalletErrorKind::SubWalletAlreadyExists(name).into());
+            }
+
+            // The coins of the sub-wallet are held in a wallet of its own
+            // that is attached to this one
+            let wallet_name = format!("{}/{}", api.wallet.name, name);
+            let wallet = create_wallet(
+                &api.dio,
+                &api.auth,
+                registry,
+                identity,
+                &wallet_name,
+                api.wallet.key(),
+                api.wallet.gst_country,
+            )
+            .await?;
+
+            let limit = SpendingLimit {
+                amount: opts.limit.amount,
+                currency: opts.currency,
+                period: opts.limit.period,
+            };
+            api.create_sub_wallet(name.as_str(), &wallet, limit.clone()).await?;
+            eprintln!(
+                "Sub-wallet ({}) created with a limit of {}.",
+                wallet_name, limit
+            );
+        }
+        OptsSubWalletAction::List => {
+            let subs = api
+                .list_sub_wallets()
+                .await?
+                .into_iter()
+                .map(|a| a.take())
+                .collect::<Vec<_>>();
+            emit(output, &SubWalletListOutput::new(&subs));
+        }
+        OptsSubWalletAction::Freeze(opts) => {
+            api.freeze_sub_wallet(opts.name.as_str(), true).await?;
+            eprintln!("Sub-wallet ({}) is now frozen.", opts.name);
+        }
+        OptsSubWalletAction::Unfreeze(opts) => {
+            api.freeze_sub_wallet(opts.name.as_str(), false).await?;
+            eprintln!("Sub-wallet ({}) is no longer frozen.", opts.name);
+        }
+        OptsSubWalletAction::Remove(opts) => {
+            match api.remove_sub_wallet(opts.name.as_str(), opts.force).await {
+                Ok(_) => {}
+                Err(WalletError(WalletErrorKind::WalletNotEmpty, _)) => {
+                    eprintln!("The sub-wallet is not empty and thus can not be removed.");
+                    std::process::exit(1);
+                }
+                Err(err) => return Err(err),
+            }
+            eprintln!("Sub-wallet ({}) removed.", opts.name);
+        }
+    }
+    Ok(())
+}
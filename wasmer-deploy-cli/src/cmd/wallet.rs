@@ -5,6 +5,7 @@ use tracing::{debug, error, info};
 use ate::prelude::*;
 
 use crate::error::*;
+use crate::helper::SessionConnector;
 
 use crate::api::*;
 use crate::cmd::*;
@@ -32,8 +33,16 @@ pub(super) async fn get_or_create_wallet(
         std::process::exit(1);
     }
 
-    // Grab a reference to the wallet
+    // Grab a reference to the wallet (sub-wallets are loaded from their parent)
     let wallet_name = get_wallet_name(purpose)?;
+    let (wallet_name, sub_wallet) = split_sub_wallet_name(wallet_name.as_str());
+    let wallet_name = wallet_name.to_string();
+    if sub_wallet.is_some() {
+        if let OptWalletAction::Create(_) | OptWalletAction::Sub(_) = action {
+            eprintln!("Sub-wallets are managed from their parent wallet with the 'sub' action.");
+            std::process::exit(1);
+        }
+    }
     let mut wallet_vec = DaoVec::<Wallet>::new_orphaned_mut(dio, parent_key, WALLET_COLLECTION_ID);
     let wallet = wallet_vec
         .iter_mut()
@@ -90,22 +99,28 @@ pub async fn main_opts_wallet(
     opts_wallet: OptsWalletSource,
     token_path: String,
     auth_url: url::Url,
+    output: OutputFormat,
 ) -> Result<(), WalletError> {
     let sudo = match opts_wallet.action() {
         OptWalletAction::Balance(_) => true,
         OptWalletAction::History(_) => true,
+        OptWalletAction::Watch(_) => true,
         OptWalletAction::Create(_) => true,
         OptWalletAction::Remove(_) => true,
         OptWalletAction::Deposit(_) => true,
         OptWalletAction::Transfer(_) => true,
         OptWalletAction::Withdraw(_) => true,
+        OptWalletAction::Sub(_) => true,
+        OptWalletAction::Coin(_) => true,
+        OptWalletAction::Export(_) => true,
         #[allow(unreachable_patterns)]
         _ => false,
     };
 
     // Create the API to the wallet
+    let connector = SessionConnector::new(auth_url.clone());
     let inner =
-        PurposeContextPrelude::new(&opts_wallet, token_path.as_str(), &auth_url, sudo).await?;
+        PurposeContextPrelude::new(&opts_wallet, token_path.as_str(), &connector, sudo).await?;
     let wallet = get_or_create_wallet(
         &opts_wallet,
         &inner.dio,
@@ -114,12 +129,18 @@ pub async fn main_opts_wallet(
         &inner.identity,
     )
     .await?;
-    let api = crate::api::build_api_accessor(&inner.dio, wallet, auth_url, None, &inner.registry).await;
+    let mut api = crate::api::build_api_accessor(&inner.dio, wallet, auth_url, None, &inner.registry).await;
+
+    // When a sub-wallet was named then the actions are performed on it instead
+    let wallet_name = get_wallet_name(&opts_wallet)?;
+    if let (_, Some(sub_wallet)) = split_sub_wallet_name(wallet_name.as_str()) {
+        api = api.sub_wallet_api(sub_wallet).await?;
+    }
 
     let mut context = PurposeContext::<OptWalletAction> { inner, api };
 
     // Determine what we need to do
-    match context.inner.action {
+    match context.inner.action.clone() {
         OptWalletAction::Create(_) => {
             context.api.commit().await?;
 
@@ -131,10 +152,14 @@ pub async fn main_opts_wallet(
             return Ok(());
         }
         OptWalletAction::Balance(opts_balance) => {
-            main_opts_balance(opts_balance, &mut context.api).await?;
+            main_opts_balance(opts_balance, &mut context.api, output).await?;
         }
         OptWalletAction::History(opts_history) => {
-            main_opts_transaction_history(opts_history, &mut context.api).await?;
+            main_opts_transaction_history(opts_history, &mut context.api, output).await?;
+        }
+        OptWalletAction::Watch(opts_watch) => {
+            main_opts_wallet_watch(opts_watch, &opts_wallet, context, &connector, output).await?;
+            return Ok(());
         }
         OptWalletAction::Deposit(opts_deposit) => {
             main_opts_deposit(opts_deposit, &mut context.api).await?;
@@ -145,6 +170,23 @@ pub async fn main_opts_wallet(
         OptWalletAction::Withdraw(opts_withdraw) => {
             main_opts_withdraw(opts_withdraw, &opts_wallet, &mut context.api).await?;
         }
+        OptWalletAction::Sub(opts_sub_wallet) => {
+            main_opts_sub_wallet(
+                opts_sub_wallet,
+                &mut context.api,
+                &context.inner.identity,
+                &context.inner.registry,
+                output,
+            )
+            .await?;
+        }
+        OptWalletAction::Coin(opts_coin) => {
+            main_opts_coin(opts_coin, &mut context.api).await?;
+        }
+        OptWalletAction::Export(opts_export) => {
+            main_opts_wallet_export(opts_export, &context.inner.chain, output).await?;
+            return Ok(());
+        }
     }
 
     context.api.commit().await?;
@@ -0,0 +1,287 @@
+use fxhash::FxHashMap;
+use std::time::Duration;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, warn};
+
+use ate::prelude::*;
+
+use crate::api::*;
+use crate::cmd::*;
+use crate::error::*;
+use crate::helper::SessionConnector;
+use crate::model::*;
+use crate::opt::*;
+use crate::output::*;
+
+use super::core::*;
+use super::wallet::get_or_create_wallet;
+
+/// Activities that have already been seen for each day of history, the
+/// day is written again every time an activity is appended to it hence only
+/// the activities past the ones already seen are new
+#[derive(Debug, Default)]
+struct WatchCursor {
+    seen: FxHashMap<PrimaryKey, usize>,
+}
+
+impl WatchCursor {
+    fn advance(&mut self, day: &Dao<HistoricDay>) -> Vec<HistoricActivity> {
+        let seen = self.seen.entry(day.key().clone()).or_default();
+        let ret = day.activities.iter().skip(*seen).cloned().collect::<Vec<_>>();
+        *seen = (*seen).max(day.activities.len());
+        ret
+    }
+}
+
+/// Subscriptions to the history of the wallet, the months are watched for
+/// a new month starting while the days are watched for new activities
+struct WatchSubscription {
+    months: Bus<HistoricMonth>,
+    days: Option<(i32, u32, Bus<HistoricDay>)>,
+}
+
+impl WatchSubscription {
+    fn lagged(&self) -> u64 {
+        self.months.lagged() + self.days.as_ref().map_or(0, |a| a.2.lagged())
+    }
+
+    /// Watches the days of this month if it is the latest month seen so far
+    async fn follow_month(&mut self, month: &Dao<HistoricMonth>) -> Result<bool, AteError> {
+        if let Some((year, num, _)) = self.days.as_ref() {
+            if (month.year, month.month) <= (*year, *num) {
+                return Ok(false);
+            }
+        }
+        let days = month.days.bus().await?;
+        self.days = Some((month.year, month.month, days));
+        Ok(true)
+    }
+}
+
+/// Opens the wallet again with the session that was already established
+/// (so that the user is not prompted again after the connection drops)
+async fn watch_reopen(
+    purpose: &OptsWalletSource,
+    connector: &SessionConnector,
+    session: &AteSessionType,
+    identity: &String,
+) -> Result<(ChainGuard, DeployApi), WalletError> {
+    let auth_url = connector.auth_url();
+    let registry = connector.registry(&wasmer_auth::helper::conf_auth()).await;
+    let (_, chain) = {
+        let registry = &registry;
+        let identity = identity.as_str();
+        connector
+            .handshake(auth_url, move || async move {
+                open_identity_chain_ext(registry, identity, auth_url)
+                    .await
+                    .map_err(CoreError::from)
+            })
+            .await?
+    };
+    let dio = chain.dio_trans(session, TransactionScope::Full).await?;
+
+    let wallet = get_or_create_wallet(purpose, &dio, auth_url, &registry, identity).await?;
+    let mut api = build_api_accessor(&dio, wallet, auth_url.clone(), None, &registry).await;
+    let wallet_name = get_wallet_name(purpose)?;
+    if let (_, Some(sub_wallet)) = split_sub_wallet_name(wallet_name.as_str()) {
+        api = api.sub_wallet_api(sub_wallet).await?;
+    }
+    Ok((chain, api))
+}
+
+/// Subscribes to the history of the wallet and then reads whatever is
+/// already there (in that order so that nothing recorded in between is
+/// missed), the activities that were not seen before are returned
+async fn watch_subscribe(
+    api: &DeployApi,
+    cursor: &mut WatchCursor,
+) -> Result<(WatchSubscription, Vec<HistoricActivity>), WalletError> {
+    let mut sub = WatchSubscription {
+        months: api.wallet.history.bus().await.map_err(AteError::from)?,
+        days: None,
+    };
+
+    let mut unseen = Vec::new();
+    let mut months = api.wallet.history.iter().await?.collect::<Vec<_>>();
+    months.sort_by_key(|a| (a.year, a.month));
+    if let Some(month) = months.last() {
+        sub.follow_month(month).await?;
+    }
+    for month in months {
+        for day in month.days.iter().await? {
+            unseen.append(&mut cursor.advance(&day));
+        }
+    }
+    unseen.sort_by(|a, b| a.when().cmp(b.when()));
+    Ok((sub, unseen))
+}
+
+/// Catches up on the days of a month that was only just seen
+async fn watch_month(
+    month: &Dao<HistoricMonth>,
+    cursor: &mut WatchCursor,
+) -> Result<Vec<HistoricActivity>, WalletError> {
+    let mut unseen = Vec::new();
+    for day in month.days.iter().await? {
+        unseen.append(&mut cursor.advance(&day));
+    }
+    unseen.sort_by(|a, b| a.when().cmp(b.when()));
+    Ok(unseen)
+}
+
+fn is_match(opts: &OptsWatchWallet, event: &WatchEvent) -> bool {
+    if let Some(currency) = opts.currency {
+        if event.currency != Some(currency) {
+            return false;
+        }
+    }
+    if let Some(min_amount) = opts.min_amount {
+        match event.amount {
+            Some(amount) if amount.abs() >= min_amount => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+fn watch_emit(
+    opts: &OptsWatchWallet,
+    activities: Vec<HistoricActivity>,
+    replayed: bool,
+    output: OutputFormat,
+) {
+    for activity in activities {
+        let event = WatchEvent::new(&activity, replayed);
+        if is_match(opts, &event) {
+            emit_line(output, &event);
+        }
+    }
+}
+
+/// Receives the next event from the days of the month being watched (if
+/// no month is being watched yet then this waits forever)
+async fn recv_day(
+    days: &mut Option<(i32, u32, Bus<HistoricDay>)>,
+) -> Result<BusEvent<HistoricDay>, ate::error::BusError> {
+    match days.as_mut() {
+        Some((_, _, bus)) => bus.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+pub async fn main_opts_wallet_watch(
+    opts: OptsWatchWallet,
+    purpose: &OptsWalletSource,
+    mut context: PurposeContext<OptWalletAction>,
+    connector: &SessionConnector,
+    output: OutputFormat,
+) -> Result<(), WalletError> {
+    // Display the balance that the events will be applied to
+    let balance = balance_output(
+        &OptsBalance {
+            coins: false,
+            no_reconcile: opts.no_reconcile,
+            convert: None,
+        },
+        &mut context.api,
+    )
+    .await?;
+    emit_line(output, &balance);
+
+    // Everything that is already in the history is taken as seen
+    let mut cursor = WatchCursor::default();
+    let (mut sub, _) = watch_subscribe(&context.api, &mut cursor).await?;
+    let mut _chain = None;
+    let mut api = context.api;
+
+    let initial_backoff = Duration::from_millis(500);
+    let max_backoff = Duration::from_secs(30);
+    loop {
+        // Stream the events as they are committed to the chain
+        let mut lagged = sub.lagged();
+        let err = loop {
+            let ret: Result<Vec<HistoricActivity>, WalletError> = tokio::select! {
+                evt = sub.months.recv() => match evt {
+                    Ok(BusEvent::Updated(month)) => match sub.follow_month(&month).await {
+                        Ok(true) => watch_month(&month, &mut cursor).await,
+                        Ok(false) => Ok(Vec::new()),
+                        Err(err) => Err(err.into()),
+                    },
+                    Ok(BusEvent::Deleted(_)) => Ok(Vec::new()),
+                    Err(ate::error::BusError(ate::error::BusErrorKind::Lagged(_), _)) => Ok(Vec::new()),
+                    Err(err) => Err(AteError::from(err).into()),
+                },
+                evt = recv_day(&mut sub.days) => match evt {
+                    Ok(BusEvent::Updated(day)) => Ok(cursor.advance(&day)),
+                    Ok(BusEvent::Deleted(_)) => Ok(Vec::new()),
+                    Err(ate::error::BusError(ate::error::BusErrorKind::Lagged(_), _)) => Ok(Vec::new()),
+                    Err(err) => Err(AteError::from(err).into()),
+                },
+            };
+            match ret {
+                Ok(activities) => watch_emit(&opts, activities, false, output),
+                Err(err) => break err,
+            }
+
+            // When the subscription fell behind the chain then events were
+            // dropped, the history is read again to find the ones missed
+            if sub.lagged() > lagged {
+                emit_warning(
+                    output,
+                    "the watch fell behind the wallet and events may have been missed - reading the history again",
+                );
+                match watch_subscribe(&api, &mut cursor).await {
+                    Ok((a, activities)) => {
+                        sub = a;
+                        watch_emit(&opts, activities, true, output);
+                    }
+                    Err(err) => break err,
+                }
+                lagged = sub.lagged();
+            }
+        };
+
+        // Reconnect to the wallet (with a backoff) and replay whatever was
+        // recorded while the watch was disconnected
+        emit_warning(
+            output,
+            format!(
+                "lost the connection to the wallet ({}) - reconnecting, events recorded while disconnected will be replayed",
+                err
+            )
+            .as_str(),
+        );
+        let mut backoff = initial_backoff;
+        loop {
+            ate::engine::sleep(backoff).await;
+            let ret = match watch_reopen(
+                purpose,
+                connector,
+                &context.inner.session,
+                &context.inner.identity,
+            )
+            .await
+            {
+                Ok((chain, a)) => match watch_subscribe(&a, &mut cursor).await {
+                    Ok((b, activities)) => Ok((chain, a, b, activities)),
+                    Err(err) => Err(err),
+                },
+                Err(err) => Err(err),
+            };
+            match ret {
+                Ok((chain, a, b, activities)) => {
+                    _chain = Some(chain);
+                    api = a;
+                    sub = b;
+                    watch_emit(&opts, activities, true, output);
+                    break;
+                }
+                Err(err) => {
+                    debug!("reconnect failed - {}", err);
+                    backoff = (backoff * 2).min(max_backoff);
+                }
+            }
+        }
+    }
+}
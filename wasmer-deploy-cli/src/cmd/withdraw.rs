@@ -77,8 +77,10 @@ where
         OptsBalance {
             coins: false,
             no_reconcile: false,
+            convert: None,
         },
         api,
+        OutputFormat::Text,
     )
     .await?;
 
@@ -0,0 +1,25 @@
+use error_chain::error_chain;
+
+error_chain! {
+    types {
+        ConnectError, ConnectErrorKind, ResultExt, Result;
+    }
+    errors {
+        Unreachable(host: String, reason: String) {
+            description("could not reach the server")
+            display("could not reach {} ({})", host, reason)
+        }
+        TlsError(host: String) {
+            description("could not reach the server as the TLS handshake failed")
+            display("could not reach {} (TLS handshake failed)", host)
+        }
+        AuthRequired(host: String) {
+            description("the server requires you to login")
+            display("{} requires you to login (the session is missing or has expired)", host)
+        }
+        ServerError(host: String, code: u16) {
+            description("the server failed to process the request")
+            display("{} failed to process the request (code={})", host, code)
+        }
+    }
+}
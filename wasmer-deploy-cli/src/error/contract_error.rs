@@ -34,6 +34,10 @@ error_chain! {
             description("invalid reference number"),
             display("invalid reference number ({})", reference_number),
         }
+        SubWalletNotSupported(name: String) {
+            description("contracts can not be created on a sub-wallet"),
+            display("contracts can not be created on a sub-wallet ({}) as they are charged outside of its spending limit", name),
+        }
     }
 }
 
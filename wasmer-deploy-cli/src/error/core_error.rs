@@ -15,6 +15,8 @@ error_chain! {
         LoadError(::ate::error::LoadError, ::ate::error::LoadErrorKind);
         CommitError(::ate::error::CommitError, ::ate::error::CommitErrorKind);
         LockError(::ate::error::LockError, ::ate::error::LockErrorKind);
+        MultiChainError(::ate::error::MultiChainError, ::ate::error::MultiChainErrorKind);
+        ConnectError(super::ConnectError, super::ConnectErrorKind);
     }
     foreign_links {
         IO(tokio::io::Error);
@@ -64,6 +66,10 @@ error_chain! {
             description("one of the saftey and security failsafes was triggered"),
             display("one of the saftey and security failsafes was triggered"),
         }
+        OfflineUnsupported(command: String) {
+            description("the command can not be run in offline mode"),
+            display("the '{}' command can not be run in offline mode as it changes state or needs the network - only the wallet history and balance and the instance list and details can be read from a snapshot", command),
+        }
         InternalError(code: u16) {
             description("the server experienced an internal error")
             display("the server experienced an internal error - code={}", code)
@@ -75,6 +81,19 @@ error_chain! {
     }
 }
 
+impl From<CurrencyRatesFailed> for CoreError {
+    fn from(err: CurrencyRatesFailed) -> CoreError {
+        match err {
+            CurrencyRatesFailed::UnsupportedCurrency(currency) => {
+                CoreErrorKind::Other(format!("no exchange rate is available for {}", currency))
+                    .into()
+            }
+            CurrencyRatesFailed::Forbidden => CoreErrorKind::Forbidden.into(),
+            CurrencyRatesFailed::InternalError(code) => CoreErrorKind::InternalError(code).into(),
+        }
+    }
+}
+
 impl From<ServiceFindFailed> for CoreError {
     fn from(err: ServiceFindFailed) -> CoreError {
         match err {
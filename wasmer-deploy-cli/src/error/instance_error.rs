@@ -1,3 +1,4 @@
+use ate::error_codes;
 use error_chain::error_chain;
 
 use super::*;
@@ -18,39 +19,90 @@ error_chain! {
     errors {
         Unauthorized {
             description("insufficient access rights - login with sudo")
-            display("insufficient access rights")
+            display("INSTANCE_0006: insufficient access rights")
         }
         AlreadyExists {
             description("an instance with this name already exists")
-            display("an instance with this name already exists")
+            display("INSTANCE_0007: an instance with this name already exists")
         }
         InvalidInstance {
             description("the instance was this name could not be found")
-            display("the instance was this name could not be found")
+            display("INSTANCE_0008: the instance was this name could not be found")
         }
         InvalidAccessToken {
             description("the access token supplied was not valid")
-            display("the access token supplied was not valid")
+            display("INSTANCE_0009: the access token supplied was not valid")
         }
         NotExported {
             description("the binary has not been exported on this channel")
-            display("the binary has not been exported on this channel")
+            display("INSTANCE_0010: the binary has not been exported on this channel")
         }
         InternalError(code: u16) {
             description("an internal error has occured")
-            display("an internal error has occured - code={}", code)
+            display("INSTANCE_0011: an internal error has occured - code={}", code)
         }
         NoInput {
             description("no input was supplied to the command")
-            display("no input was supplied to the command")
+            display("INSTANCE_0012: no input was supplied to the command")
         }
         Unsupported {
             description("the operation is not yet supported")
-            display("the operation is not yet supported")
+            display("INSTANCE_0013: the operation is not yet supported")
+        }
+        InvalidRequest(reason: String) {
+            description("the request is not valid")
+            display("INSTANCE_0014: the request is not valid - {}", reason)
+        }
+        CallFailed(err: String) {
+            description("the instance call failed")
+            display("INSTANCE_0015: the instance call failed - {}", err)
+        }
+        CallAborted {
+            description("the instance call was aborted before it finished")
+            display("INSTANCE_0016: the instance call was aborted before it finished")
+        }
+        AccessTokenExpired {
+            description("the access token supplied has expired")
+            display("INSTANCE_0017: the access token supplied has expired")
+        }
+        RateLimited {
+            description("the access token has exceeded its rate limit")
+            display("INSTANCE_0018: the access token has exceeded its rate limit")
+        }
+        TemplateNotFound(name: String) {
+            description("the template could not be found")
+            display("INSTANCE_0019: the template could not be found - {}", name)
+        }
+        InvalidTemplate(reason: String) {
+            description("the template is not valid")
+            display("INSTANCE_0020: the template is not valid - {}", reason)
         }
     }
 }
 
+error_codes!(InstanceErrorKind, "INSTANCE", {
+    "0001" => CoreError,
+    "0002" => QueryError,
+    "0003" => ContractError,
+    "0004" => FileSystemError,
+    "0005" => IO,
+    "0006" => Unauthorized,
+    "0007" => AlreadyExists,
+    "0008" => InvalidInstance,
+    "0009" => InvalidAccessToken,
+    "0010" => NotExported,
+    "0011" => InternalError,
+    "0012" => NoInput,
+    "0013" => Unsupported,
+    "0014" => InvalidRequest,
+    "0015" => CallFailed,
+    "0016" => CallAborted,
+    "0017" => AccessTokenExpired,
+    "0018" => RateLimited,
+    "0019" => TemplateNotFound,
+    "0020" => InvalidTemplate,
+});
+
 impl From<::ate::error::AteError> for InstanceError {
     fn from(err: ::ate::error::AteError) -> Self {
         InstanceErrorKind::CoreError(CoreErrorKind::AteError(err.0)).into()
@@ -145,4 +197,27 @@ impl From<::ate::error::LockErrorKind> for InstanceErrorKind {
     fn from(err: ::ate::error::LockErrorKind) -> Self {
         InstanceErrorKind::CoreError(CoreErrorKind::LockError(err))
     }
-}
\ No newline at end of file
+}
+impl From<::ate::error::MultiChainError> for InstanceError {
+    fn from(err: ::ate::error::MultiChainError) -> Self {
+        InstanceErrorKind::CoreError(CoreErrorKind::MultiChainError(err.0)).into()
+    }
+}
+
+impl From<::ate::error::MultiChainErrorKind> for InstanceErrorKind {
+    fn from(err: ::ate::error::MultiChainErrorKind) -> Self {
+        InstanceErrorKind::CoreError(CoreErrorKind::MultiChainError(err))
+    }
+}
+
+impl From<super::ConnectError> for InstanceError {
+    fn from(err: super::ConnectError) -> Self {
+        InstanceErrorKind::CoreError(CoreErrorKind::ConnectError(err.0)).into()
+    }
+}
+
+impl From<super::ConnectErrorKind> for InstanceErrorKind {
+    fn from(err: super::ConnectErrorKind) -> Self {
+        InstanceErrorKind::CoreError(CoreErrorKind::ConnectError(err))
+    }
+}
@@ -1,5 +1,6 @@
 pub mod bus_error;
 pub mod coin_error;
+pub mod connect_error;
 pub mod contract_error;
 pub mod core_error;
 pub mod wallet_error;
@@ -11,6 +12,8 @@ pub use bus_error::BusError;
 pub use bus_error::BusErrorKind;
 pub use coin_error::CoinError;
 pub use coin_error::CoinErrorKind;
+pub use connect_error::ConnectError;
+pub use connect_error::ConnectErrorKind;
 pub use contract_error::ContractError;
 pub use contract_error::ContractErrorKind;
 pub use core_error::CoreError;
@@ -18,4 +21,22 @@ pub use core_error::CoreErrorKind;
 pub use wallet_error::WalletError;
 pub use wallet_error::WalletErrorKind;
 pub use instance_error::InstanceError;
-pub use instance_error::InstanceErrorKind;
\ No newline at end of file
+pub use instance_error::InstanceErrorKind;
+
+/// Hints that are shown beneath the errors of the command line tool
+pub const ERROR_HINTS: &'static [ate::utils::ErrorHint] = &[
+    ("COMMIT_0014", "run 'wasmer-deploy instance details <name>' to see the storage usage of the instance"),
+    ("INSTANCE_0006", "run 'wasmer-deploy login' to login with an account that has access to the instance"),
+    ("INSTANCE_0008", "run 'wasmer-deploy instance list' to see the instances that exist"),
+    ("INSTANCE_0009", "mint a new token with 'wasmer-deploy instance token <name> mint <binary>'"),
+    ("INSTANCE_0017", "mint a new token with 'wasmer-deploy instance token <name> mint <binary>'"),
+    ("INSTANCE_0018", "wait a minute before calling the instance again or mint a token with a higher rate limit"),
+    ("INSTANCE_0019", "give the path to a local template file (.json or .yaml) or check the name of the published template"),
+];
+
+/// Returns the table of all the error codes (including those of ate)
+pub fn all() -> Vec<ate::error::ErrorCodeInfo> {
+    let mut ret = ate::errors::all();
+    ret.extend_from_slice(InstanceErrorKind::error_codes());
+    ret
+}
@@ -1,4 +1,6 @@
 use super::*;
+use crate::model::Decimal;
+use crate::model::NationalCurrency;
 use crate::request::*;
 use error_chain::error_chain;
 
@@ -35,6 +37,10 @@ error_chain! {
             description("insufficient coins"),
             display("insufficient coins"),
         }
+        CurrencyNotHeld(currency: NationalCurrency, held: String) {
+            description("the wallet does not hold any coins of this currency"),
+            display("the wallet does not hold any {} (currencies held: {})", currency, held),
+        }
         TooSmall {
             description("the withdrawl amount is too small"),
             display("the withdrawl amount is too small"),
@@ -47,6 +53,22 @@ error_chain! {
             description("the wallet is currently locked for modification due to a concurrent operation"),
             display("the wallet is currently locked for modification due to a concurrent operation"),
         }
+        SubWalletNotFound(name: String) {
+            description("the sub-wallet does not exist"),
+            display("the sub-wallet ({}) does not exist", name),
+        }
+        SubWalletAlreadyExists(name: String) {
+            description("a sub-wallet with this name already exists"),
+            display("a sub-wallet with this name ({}) already exists", name),
+        }
+        SubWalletFrozen(name: String) {
+            description("the sub-wallet is frozen"),
+            display("the sub-wallet ({}) is frozen", name),
+        }
+        SpendingLimitExceeded(name: String, limit: String, remaining: Decimal) {
+            description("the spending limit of the sub-wallet would be exceeded"),
+            display("the spending limit of the sub-wallet ({}) would be exceeded - limit is {} with {} remaining", name, limit, remaining),
+        }
         EmailError(err: String) {
             description("failed to send email"),
             display("failed to send email - {}", err),
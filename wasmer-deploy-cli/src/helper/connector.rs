@@ -0,0 +1,330 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use ate::comms::StreamSecurity;
+use ate::prelude::*;
+
+use crate::api::InstanceClient;
+use crate::error::*;
+
+/// Time allowed for a connection to one of the session servers to be established
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+/// Number of times a handshake is attempted before giving up on the server
+pub const DEFAULT_CONNECT_ATTEMPTS: u32 = 4;
+
+/// Owns the URLs of the servers that the commands talk to (auth, db and the
+/// instance sessions) and connects to them in the same way for every command,
+/// that is with the same timeouts, retrying the handshakes that are safe to
+/// repeat when the failure is transient (e.g. a 502 from a load balancer) and
+/// reporting the failures as a `ConnectError` that makes sense to the user
+#[derive(Debug, Clone)]
+pub struct SessionConnector {
+    auth_url: url::Url,
+    db_url: Option<url::Url>,
+    inst_url: Option<url::Url>,
+    pub connect_timeout: Duration,
+    pub attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl SessionConnector {
+    pub fn new(auth_url: url::Url) -> SessionConnector {
+        SessionConnector {
+            auth_url,
+            db_url: None,
+            inst_url: None,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            attempts: DEFAULT_CONNECT_ATTEMPTS,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_db_url(mut self, db_url: url::Url) -> Self {
+        self.db_url = Some(db_url);
+        self
+    }
+
+    pub fn with_inst_url(mut self, inst_url: url::Url) -> Self {
+        self.inst_url = Some(inst_url);
+        self
+    }
+
+    pub fn auth_url(&self) -> &url::Url {
+        &self.auth_url
+    }
+
+    pub fn db_url(&self) -> Option<&url::Url> {
+        self.db_url.as_ref()
+    }
+
+    pub fn inst_url(&self) -> Option<&url::Url> {
+        self.inst_url.as_ref()
+    }
+
+    /// Creates a registry whose connections use the timeout of this connector
+    pub async fn registry(&self, cfg_ate: &ConfAte) -> Arc<Registry> {
+        Registry::new(cfg_ate)
+            .await
+            .keep_alive(Duration::from_secs(10))
+            .connect_timeout(self.connect_timeout)
+            .cement()
+    }
+
+    /// Runs a stage of the handshake with a server, the stage must be safe to
+    /// repeat as it is attempted again (with a backoff) whenever it fails for
+    /// a transient reason. Failures of the connection itself are converted to
+    /// a `ConnectError` while all other errors are returned untouched.
+    pub async fn handshake<T, E, F, Fut>(&self, url: &url::Url, mut stage: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: From<ConnectError> + std::fmt::Display + AsRef<dyn std::error::Error + 'static>,
+    {
+        let host = host_of(url);
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1u32;
+        loop {
+            let failure = match stage().await {
+                Ok(a) => return Ok(a),
+                Err(err) => match classify(host.as_str(), err.as_ref()) {
+                    Some((kind, true)) if attempt < self.attempts => kind,
+                    Some((kind, _)) => {
+                        debug!("connection to {} failed - {}", host, err);
+                        return Err(E::from(ConnectError::from_kind(kind)));
+                    }
+                    None => return Err(err),
+                },
+            };
+            debug!("connection to {} failed (attempt={}) - {}", host, attempt, failure);
+            ate::engine::sleep(backoff).await;
+            backoff = (backoff * 2).min(self.max_backoff);
+            attempt += 1;
+        }
+    }
+
+    /// Runs a request against a server that is not safe to repeat (or that
+    /// interacts with the user) hence it is only attempted once, failures of
+    /// the connection are still converted to a `ConnectError`
+    pub async fn request<T, E, Fut>(&self, url: &url::Url, request: Fut) -> Result<T, E>
+    where
+        Fut: Future<Output = Result<T, E>>,
+        E: From<ConnectError> + std::fmt::Display + AsRef<dyn std::error::Error + 'static>,
+    {
+        request.await.map_err(|err| {
+            match classify(host_of(url).as_str(), err.as_ref()) {
+                Some((kind, _)) => E::from(ConnectError::from_kind(kind)),
+                None => err,
+            }
+        })
+    }
+
+    /// Makes sure the authentication server can be reached before the user
+    /// is asked for anything
+    pub async fn probe_auth(&self, cfg_ate: &ConfAte) -> Result<Arc<Registry>, CoreError> {
+        let registry = self.registry(cfg_ate).await;
+        {
+            let registry = &registry;
+            let auth_url = &self.auth_url;
+            self.handshake(auth_url, move || async move {
+                registry.open_cmd(auth_url).await.map_err(CoreError::from)
+            })
+            .await?;
+        }
+        Ok(registry)
+    }
+
+    /// Connects to the instance session server
+    pub async fn instance_client(
+        &self,
+        security: StreamSecurity,
+    ) -> Result<InstanceClient, ConnectError> {
+        let url = match self.inst_url.as_ref() {
+            Some(a) => a,
+            None => {
+                return Err(ConnectErrorKind::Unreachable(
+                    "the instance server".to_string(),
+                    "no address was supplied".to_string(),
+                )
+                .into());
+            }
+        };
+
+        let ret = self
+            .handshake(url, move || async move {
+                let connect = InstanceClient::new_ext(url.clone(), InstanceClient::PATH_INST, security);
+                match ate::engine::timeout(self.connect_timeout, connect).await {
+                    Ok(ret) => ret,
+                    Err(_) => Err(Box::new(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        "connect timed out",
+                    )) as Box<dyn std::error::Error>),
+                }
+            })
+            .await;
+        ret.map_err(|err| match err.downcast::<ConnectError>() {
+            Ok(err) => *err,
+            Err(err) => {
+                let code = ate::utils::obscure_error_str(err.to_string().as_str());
+                ConnectErrorKind::Unreachable(host_of(url), format!("code={}", code)).into()
+            }
+        })
+    }
+}
+
+/// Returns the connection failure that caused a command to fail (if that is
+/// why it failed) so that it can be shown to the user without the error chain
+pub fn connect_failure<'a>(
+    err: &'a (dyn std::error::Error + 'static),
+) -> Option<&'a ConnectErrorKind> {
+    if let Some(err) = err.downcast_ref::<ConnectError>() {
+        return Some(err.kind());
+    }
+    if let Some(CoreError(CoreErrorKind::ConnectError(kind), _)) = err.downcast_ref::<CoreError>() {
+        return Some(kind);
+    }
+    if let Some(InstanceError(InstanceErrorKind::CoreError(CoreErrorKind::ConnectError(kind)), _)) =
+        err.downcast_ref::<InstanceError>()
+    {
+        return Some(kind);
+    }
+    None
+}
+
+fn host_of(url: &url::Url) -> String {
+    url.host_str()
+        .map(|a| a.to_string())
+        .unwrap_or_else(|| url.to_string())
+}
+
+impl AsRef<dyn std::error::Error + 'static> for CoreError {
+    fn as_ref(&self) -> &(dyn std::error::Error + 'static) {
+        self
+    }
+}
+
+/// Load balancers reject the web socket upgrade with a plain HTTP error
+/// (e.g. "HTTP error: 502 Bad Gateway") which only reaches us as the
+/// message of the web socket error
+pub(super) fn http_status(err: &str) -> Option<u16> {
+    let idx = err.find("HTTP error: ")?;
+    err[idx + "HTTP error: ".len()..].get(..3)?.parse().ok()
+}
+
+/// Works out if an error is a failure of the connection to a server and if
+/// so whether it is worth trying again, the error is matched on its kind
+/// (following the chain of errors that caused it) rather than its message
+pub(super) fn classify(
+    host: &str,
+    err: &(dyn std::error::Error + 'static),
+) -> Option<(ConnectErrorKind, bool)> {
+    let mut next = Some(err);
+    while let Some(err) = next {
+        let ret = if let Some(err) = err.downcast_ref::<CoreError>() {
+            classify_core(host, err.kind())
+        } else if let Some(err) = err.downcast_ref::<AteError>() {
+            classify_ate(host, err.kind())
+        } else if let Some(err) = err.downcast_ref::<ChainCreationError>() {
+            classify_chain_creation(host, err.kind())
+        } else if let Some(err) = err.downcast_ref::<CommsError>() {
+            classify_comms(host, err.kind())
+        } else if let Some(err) = err.downcast_ref::<std::io::Error>() {
+            classify_io(host, err)
+        } else {
+            None
+        };
+        if ret.is_some() {
+            return ret;
+        }
+        next = err.source();
+    }
+    None
+}
+
+fn classify_core(host: &str, err: &CoreErrorKind) -> Option<(ConnectErrorKind, bool)> {
+    match err {
+        CoreErrorKind::AteError(err) => classify_ate(host, err),
+        CoreErrorKind::ChainCreationError(err) => classify_chain_creation(host, err),
+        CoreErrorKind::IO(err) => classify_io(host, err),
+        _ => None,
+    }
+}
+
+fn classify_ate(host: &str, err: &AteErrorKind) -> Option<(ConnectErrorKind, bool)> {
+    match err {
+        AteErrorKind::CommsError(err) => classify_comms(host, err),
+        AteErrorKind::ChainCreationError(err) => classify_chain_creation(host, err),
+        AteErrorKind::IO(err) => classify_io(host, err),
+        _ => None,
+    }
+}
+
+fn classify_chain_creation(
+    host: &str,
+    err: &ChainCreationErrorKind,
+) -> Option<(ConnectErrorKind, bool)> {
+    match err {
+        ChainCreationErrorKind::CommsError(err) => classify_comms(host, err),
+        _ => None,
+    }
+}
+
+/// The certificate errors of the wire encryption (COMMS_0009 to COMMS_0012)
+/// are not failures of the connection hence they are returned untouched
+fn classify_comms(host: &str, err: &CommsErrorKind) -> Option<(ConnectErrorKind, bool)> {
+    let host = host.to_string();
+    let reason = match err {
+        CommsErrorKind::IO(err) => return classify_io(host.as_str(), err),
+        CommsErrorKind::WebSocketError(err) => return classify_http(host, err.as_str()),
+        CommsErrorKind::TlsError(_) => return Some((ConnectErrorKind::TlsError(host), false)),
+        CommsErrorKind::Timeout => "timed out",
+        CommsErrorKind::Refused => "connection refused",
+        CommsErrorKind::NoAddress | CommsErrorKind::InvalidDomainName => {
+            "the address could not be resolved"
+        }
+        CommsErrorKind::Disconnected => "the connection was lost",
+        _ => return None,
+    };
+    Some((ConnectErrorKind::Unreachable(host, reason.to_string()), true))
+}
+
+fn classify_http(host: String, err: &str) -> Option<(ConnectErrorKind, bool)> {
+    let code = http_status(err)?;
+    Some(match code {
+        401 | 403 => (ConnectErrorKind::AuthRequired(host), false),
+        502 | 503 | 504 => (ConnectErrorKind::ServerError(host, code), true),
+        code => (ConnectErrorKind::ServerError(host, code), false),
+    })
+}
+
+/// The web socket client hands back the failures of its handshake (HTTP
+/// and TLS) wrapped in an `std::io::Error` with the original as its source
+fn classify_io(host: &str, err: &std::io::Error) -> Option<(ConnectErrorKind, bool)> {
+    use std::io::ErrorKind;
+
+    let host = host.to_string();
+    let reason = match err.kind() {
+        ErrorKind::TimedOut => "timed out",
+        ErrorKind::ConnectionRefused => "connection refused",
+        ErrorKind::ConnectionReset
+        | ErrorKind::ConnectionAborted
+        | ErrorKind::BrokenPipe
+        | ErrorKind::UnexpectedEof => "the connection was lost",
+        ErrorKind::Other => {
+            let inner = err.get_ref()?.to_string();
+            if inner.starts_with("TLS error") {
+                return Some((ConnectErrorKind::TlsError(host), false));
+            } else if inner.starts_with("failed to lookup address") {
+                "the address could not be resolved"
+            } else {
+                return classify_http(host, inner.as_str());
+            }
+        }
+        _ => return None,
+    };
+    Some((ConnectErrorKind::Unreachable(host, reason.to_string()), true))
+}
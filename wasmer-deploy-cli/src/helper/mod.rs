@@ -1,5 +1,8 @@
 mod coins;
+mod connector;
 mod session;
+mod tests;
 
 pub use coins::*;
+pub use connector::*;
 pub use session::*;
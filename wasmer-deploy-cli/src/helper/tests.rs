@@ -0,0 +1,89 @@
+#![cfg(test)]
+use ate::prelude::*;
+
+use crate::error::*;
+
+use super::*;
+
+fn classify_err<E>(err: E) -> Option<(ConnectErrorKind, bool)>
+where
+    E: std::error::Error + 'static,
+{
+    classify("example.com", &err)
+}
+
+#[test]
+fn test_http_status() {
+    assert_eq!(http_status("HTTP error: 502 Bad Gateway"), Some(502));
+    assert_eq!(
+        http_status("web socket error - HTTP error: 401 Unauthorized"),
+        Some(401)
+    );
+    assert_eq!(http_status("HTTP error: 5"), None);
+    assert_eq!(http_status("HTTP error: abc"), None);
+    assert_eq!(http_status("connection refused"), None);
+}
+
+#[test]
+fn test_classify_comms() {
+    match classify_err(CommsError::from_kind(CommsErrorKind::Refused)) {
+        Some((ConnectErrorKind::Unreachable(host, reason), true)) => {
+            assert_eq!(host, "example.com");
+            assert_eq!(reason, "connection refused");
+        }
+        other => panic!("unexpected classification - {:?}", other),
+    }
+    match classify_err(CommsError::from_kind(CommsErrorKind::TlsError("bad".to_string()))) {
+        Some((ConnectErrorKind::TlsError(_), false)) => {}
+        other => panic!("unexpected classification - {:?}", other),
+    }
+
+    // The certificate checks of the wire encryption are not connection failures
+    assert!(classify_err(CommsError::from_kind(CommsErrorKind::MissingCertificate)).is_none());
+    assert!(
+        classify_err(CommsError::from_kind(CommsErrorKind::ServerEncryptionWeak)).is_none()
+    );
+}
+
+#[test]
+fn test_classify_http() {
+    let err = |msg: &str| CommsError::from_kind(CommsErrorKind::WebSocketError(msg.to_string()));
+    match classify_err(err("HTTP error: 503 Service Unavailable")) {
+        Some((ConnectErrorKind::ServerError(_, 503), true)) => {}
+        other => panic!("unexpected classification - {:?}", other),
+    }
+    match classify_err(err("HTTP error: 403 Forbidden")) {
+        Some((ConnectErrorKind::AuthRequired(_), false)) => {}
+        other => panic!("unexpected classification - {:?}", other),
+    }
+    match classify_err(err("HTTP error: 500 Internal Server Error")) {
+        Some((ConnectErrorKind::ServerError(_, 500), false)) => {}
+        other => panic!("unexpected classification - {:?}", other),
+    }
+    assert!(classify_err(err("protocol error")).is_none());
+}
+
+#[test]
+fn test_classify_linked() {
+    // Failures are found through the errors that link to them
+    let err = CoreError::from(AteError::from(CommsError::from_kind(CommsErrorKind::Timeout)));
+    match classify_err(err) {
+        Some((ConnectErrorKind::Unreachable(_, reason), true)) => assert_eq!(reason, "timed out"),
+        other => panic!("unexpected classification - {:?}", other),
+    }
+
+    let err = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset by peer");
+    match classify_err(err) {
+        Some((ConnectErrorKind::Unreachable(_, reason), true)) => {
+            assert_eq!(reason, "the connection was lost")
+        }
+        other => panic!("unexpected classification - {:?}", other),
+    }
+
+    // Only the kind matters, not words that happen to be in the message
+    let err = CoreError::from(AteError::from_kind(AteErrorKind::ServiceError(
+        "the tls settings timed out".to_string(),
+    )));
+    assert!(classify_err(err).is_none());
+    assert!(classify_err(CoreError::from_kind(CoreErrorKind::Forbidden)).is_none());
+}
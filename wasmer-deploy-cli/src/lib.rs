@@ -5,5 +5,7 @@ pub mod error;
 pub mod helper;
 pub mod model;
 pub mod opt;
+pub mod output;
 pub mod prelude;
 pub mod request;
+pub mod service;
@@ -0,0 +1,37 @@
+use ate::crypto::SignedProtectedData;
+use ate::prelude::*;
+use chrono::prelude::*;
+use serde::*;
+
+/// Links the ownership key that a coin had before it was rotated to the key
+/// that it was given, only hashes of the keys are kept
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CoinRotationLink {
+    pub coin: PrimaryKey,
+    pub old_owner: AteHash,
+    pub new_owner: AteHash,
+    pub when: DateTime<Utc>,
+}
+
+/// Signed record of the rotation of a coin, these are attached to the wallet
+/// (and hence encrypted under its read key) so that an auditor who has been
+/// given the read key can reconstruct the history of the ownership of the
+/// coins without anyone else being able to link coins across wallets
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CoinRotation {
+    pub link: SignedProtectedData<CoinRotationLink>,
+    pub signer: PublicSignKey,
+}
+
+impl CoinRotation {
+    /// Returns true if the link was signed by one of the owners of the wallet,
+    /// the key named by the record itself is only used to pick which owner
+    /// key to check against as anyone could have written it
+    pub fn verify(&self, owners: &[PublicSignKey]) -> bool {
+        let signer = self.signer.hash();
+        owners
+            .iter()
+            .filter(|owner| owner.hash() == signer)
+            .any(|owner| self.link.verify(owner).unwrap_or(false))
+    }
+}
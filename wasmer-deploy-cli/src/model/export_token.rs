@@ -0,0 +1,179 @@
+use ate::crypto::SignedProtectedData;
+use ate::prelude::*;
+use chrono::prelude::*;
+use serde::*;
+use std::ops::Deref;
+
+use crate::error::*;
+
+use super::InstanceExport;
+
+/// Prefix that distinguishes capability tokens from the raw hex tokens
+/// that exports were originally given
+pub const EXPORT_TOKEN_PREFIX: &'static str = "cap1.";
+
+/// Scope of what the holder of a capability token may do with an export
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportCapability {
+    /// Instance chain that the token is valid for
+    pub chain: ChainKey,
+    /// Exported binary that the token is valid for
+    pub binary: String,
+    /// Hash of the raw access token of the export, deporting the export
+    /// thus revokes every capability that was minted for it
+    pub export: AteHash,
+    /// Allows the binary to be invoked with HTTP calls
+    pub http: bool,
+    /// Allows the binary to be invoked over the wasmer-bus
+    pub bus: bool,
+    /// Topics that may be called (when empty all topics are allowed)
+    pub topics: Vec<String>,
+    /// Point in time after which the token is no longer valid
+    pub expires: Option<DateTime<Utc>>,
+    /// Maximum number of invocations per minute
+    pub rate_limit: Option<u32>,
+    /// Unique identifier of this token (the rate limit is tracked against it)
+    pub id: AteHash,
+}
+
+/// Request that is being made with a capability token
+#[derive(Debug, Clone, Copy)]
+pub enum ExportRequest<'a> {
+    /// HTTP call of a topic (or an evaluation of the binary when there is none)
+    Http { topic: Option<&'a str> },
+    /// Call of a topic over the wasmer-bus
+    Bus { topic: &'a str },
+}
+
+/// Capability token that is handed out instead of the raw access token of
+/// an export, it is signed by the export key of the instance so that it can
+/// be validated without any lookups beyond the instance itself
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportToken {
+    pub capability: SignedProtectedData<ExportCapability>,
+}
+
+impl ExportCapability {
+    /// Full access to an export (used when the binary is first exported)
+    pub fn new(chain: &ChainKey, export: &InstanceExport) -> ExportCapability {
+        ExportCapability {
+            chain: chain.clone(),
+            binary: export.binary.clone(),
+            export: AteHash::from(export.access_token.to_lowercase()),
+            http: export.http || export.https,
+            bus: export.bus,
+            topics: Vec::new(),
+            expires: None,
+            rate_limit: None,
+            id: AteHash::generate(),
+        }
+    }
+
+    /// Returns true if this capability was minted for the export
+    pub fn is_for(&self, export: &InstanceExport) -> bool {
+        self.binary.eq_ignore_ascii_case(export.binary.as_str())
+            && self.export == AteHash::from(export.access_token.to_lowercase())
+    }
+
+    /// Checks the constraints of the token (other than the rate limit which
+    /// needs to be tracked by whoever serves the requests)
+    pub fn check(&self, chain: &ChainKey, binary: &str, request: ExportRequest) -> Result<(), InstanceErrorKind> {
+        self.check_valid(chain, binary)?;
+        let topic = match request {
+            ExportRequest::Http { topic } if self.http => topic,
+            ExportRequest::Bus { topic } if self.bus => Some(topic),
+            _ => {
+                return Err(InstanceErrorKind::InvalidAccessToken);
+            }
+        };
+        if self.topics.is_empty() == false {
+            match topic {
+                Some(topic) if self.topics.iter().any(|a| a.eq_ignore_ascii_case(topic)) => {}
+                _ => {
+                    return Err(InstanceErrorKind::InvalidAccessToken);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the token may deport the export, only tokens that carry
+    /// the full capability of the export (and have not expired) may remove it
+    pub fn check_deport(&self, chain: &ChainKey, export: &InstanceExport) -> Result<(), InstanceErrorKind> {
+        self.check_valid(chain, export.binary.as_str())?;
+        if self.is_for(export) == false
+            || self.http != (export.http || export.https)
+            || self.bus != export.bus
+            || self.topics.is_empty() == false
+            || self.rate_limit.is_some()
+        {
+            return Err(InstanceErrorKind::InvalidAccessToken);
+        }
+        Ok(())
+    }
+
+    fn check_valid(&self, chain: &ChainKey, binary: &str) -> Result<(), InstanceErrorKind> {
+        if self.chain != *chain || self.binary.eq_ignore_ascii_case(binary) == false {
+            return Err(InstanceErrorKind::InvalidAccessToken);
+        }
+        if let Some(expires) = self.expires {
+            if Utc::now() >= expires {
+                return Err(InstanceErrorKind::AccessTokenExpired);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Private half of the key that signs the capability tokens of an instance,
+/// it is kept in the wallet of the owner (the instance itself only holds the
+/// public half) so that the servers that check the tokens can not mint them
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstanceExportKey {
+    pub key: PrivateSignKey,
+}
+
+impl InstanceExportKey {
+    /// Key of the row in the wallet that holds the export key of an instance
+    pub fn id(instance_id: u128) -> PrimaryKey {
+        PrimaryKey::from(format!("instance-export-key://{}", hex::encode(&instance_id.to_be_bytes())))
+    }
+}
+
+impl ExportToken {
+    pub fn mint(sign_key: &PrivateSignKey, capability: ExportCapability) -> Result<String, SerializationError> {
+        let token = ExportToken {
+            capability: SignedProtectedData::new(sign_key, capability)
+                .map_err(SerializationError::from)?,
+        };
+        token.encode()
+    }
+
+    pub fn encode(&self) -> Result<String, SerializationError> {
+        let bytes = SerializationFormat::MessagePack.serialize_ref(self)?;
+        Ok(format!(
+            "{}{}",
+            EXPORT_TOKEN_PREFIX,
+            base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+        ))
+    }
+
+    /// Returns true if the token is a capability token (rather than a raw hex token)
+    pub fn is_capability(token: &str) -> bool {
+        token.trim().starts_with(EXPORT_TOKEN_PREFIX)
+    }
+
+    pub fn decode(token: &str) -> Option<ExportToken> {
+        let token = token.trim().strip_prefix(EXPORT_TOKEN_PREFIX)?;
+        let bytes = base64::decode_config(token, base64::URL_SAFE_NO_PAD).ok()?;
+        SerializationFormat::MessagePack.deserialize(bytes).ok()
+    }
+
+    /// Returns the capability if the token was signed by the export key of the instance
+    pub fn verify(&self, export_key: &PublicSignKey) -> Option<&ExportCapability> {
+        match self.capability.verify(export_key) {
+            Ok(true) => Some(self.capability.deref()),
+            _ => None,
+        }
+    }
+}
@@ -94,6 +94,15 @@ pub mod activities {
         pub alias: Option<String>,
         pub binary: String,
     }
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    pub struct SubWalletSpent {
+        pub when: DateTime<Utc>,
+        pub by: String,
+        pub sub_wallet: String,
+        pub amount: Decimal,
+        pub currency: NationalCurrency,
+    }
 }
 
 use chrono::prelude::*;
@@ -119,6 +128,7 @@ pub enum HistoricActivity {
     InstanceDestroyed(InstanceDestroyed),
     InstanceExported(InstanceExported),
     InstanceDeported(InstanceDeported),
+    SubWalletSpent(SubWalletSpent),
 }
 
 impl HistoricActivity {
@@ -137,6 +147,7 @@ impl HistoricActivity {
             HistoricActivity::InstanceDestroyed(a) => &a.when,
             HistoricActivity::InstanceExported(a) => &a.when,
             HistoricActivity::InstanceDeported(a) => &a.when,
+            HistoricActivity::SubWalletSpent(a) => &a.when,
         }
     }
 
@@ -155,6 +166,27 @@ impl HistoricActivity {
             HistoricActivity::InstanceDestroyed(a) => a.by.as_str(),
             HistoricActivity::InstanceExported(a) => a.by.as_str(),
             HistoricActivity::InstanceDeported(a) => a.by.as_str(),
+            HistoricActivity::SubWalletSpent(a) => a.by.as_str(),
+        }
+    }
+
+    /// Short name of the kind of activity (as it appears in structured output)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HistoricActivity::WalletCreated(_) => "wallet-created",
+            HistoricActivity::DepositCreated(_) => "deposit-created",
+            HistoricActivity::DepositCompleted(_) => "deposit-completed",
+            HistoricActivity::TransferIn(_) => "transfer-in",
+            HistoricActivity::TransferOut(_) => "transfer-out",
+            HistoricActivity::FundsWithdrawn(_) => "funds-withdrawn",
+            HistoricActivity::ContractCreated(_) => "contract-created",
+            HistoricActivity::ContractCharge(_) => "contract-charge",
+            HistoricActivity::ContractIncome(_) => "contract-income",
+            HistoricActivity::InstanceCreated(_) => "instance-created",
+            HistoricActivity::InstanceDestroyed(_) => "instance-destroyed",
+            HistoricActivity::InstanceExported(_) => "instance-exported",
+            HistoricActivity::InstanceDeported(_) => "instance-deported",
+            HistoricActivity::SubWalletSpent(_) => "sub-wallet-spent",
         }
     }
 
@@ -166,6 +198,7 @@ impl HistoricActivity {
             HistoricActivity::InstanceDestroyed(_) => None,
             HistoricActivity::InstanceExported(_) => None,
             HistoricActivity::InstanceDeported(_) => None,
+            HistoricActivity::SubWalletSpent(_) => None,
             HistoricActivity::ContractCreated(_) => None,
             HistoricActivity::ContractCharge(a) => Some(HistoricFinancialActivity {
                 activity: self,
@@ -257,6 +290,9 @@ impl HistoricActivity {
                     format!("Instance deported binary ({})", a.binary)
                 }
             }
+            HistoricActivity::SubWalletSpent(a) => {
+                format!("Sub-wallet ({}) spent {} {}", a.sub_wallet, a.amount, a.currency)
+            }
         }
     }
 
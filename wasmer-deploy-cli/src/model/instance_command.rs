@@ -12,6 +12,11 @@ pub struct InstanceCall {
     pub format: SerializationFormat,
     pub binary: String,
     pub topic: String,
+    /// When set the call session is held open after the first response so
+    /// that further calls can be made against it using this handle as
+    /// their parent
+    #[serde(default)]
+    pub keepalive: bool,
 }
 
 impl fmt::Display
@@ -22,6 +27,9 @@ for InstanceCall
         if let Some(parent) = self.parent {
             write!(f, "parent={},", parent)?;
         }
+        if self.keepalive {
+            write!(f, "keepalive,")?;
+        }
         write!(f, ")")
     }
 }
@@ -30,6 +38,8 @@ for InstanceCall
 pub enum InstanceCommand {
     Shell,
     Call(InstanceCall),
+    /// Closes a call session that was previously opened with keepalive
+    Close(u64),
 }
 
 impl fmt::Display
@@ -39,6 +49,7 @@ for InstanceCommand
         match self {
             InstanceCommand::Shell => write!(f, "shell"),
             InstanceCommand::Call(call) => write!(f, "call({})", call),
+            InstanceCommand::Close(handle) => write!(f, "close(handle={})", handle),
         }
     }
 }
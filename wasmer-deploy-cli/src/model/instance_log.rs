@@ -0,0 +1,164 @@
+use chrono::DateTime;
+use chrono::Utc;
+use serde::*;
+use std::fmt;
+
+/// Maximum number of invocation records that are kept for an instance
+pub const INSTANCE_LOG_MAX_RECORDS: usize = 500usize;
+/// Maximum total size of all the invocation records kept for an instance
+pub const INSTANCE_LOG_MAX_BYTES: usize = 4194304usize;
+/// Maximum amount of stdout (and separately stderr) captured per invocation
+pub const INSTANCE_LOG_MAX_OUTPUT: usize = 16384usize;
+
+/// Outcome of an invocation of an exported binary
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum InstanceLogStatus {
+    /// The process ran to completion and exited with this code
+    Exited(u32),
+    /// The call returned a response to the caller
+    Responded,
+    /// The call failed with this error
+    Failed(String),
+    /// The call was aborted before it finished
+    Aborted,
+}
+
+impl InstanceLogStatus {
+    pub fn is_success(&self) -> bool {
+        match self {
+            InstanceLogStatus::Exited(code) => *code == 0,
+            InstanceLogStatus::Responded => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display
+for InstanceLogStatus
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceLogStatus::Exited(code) => write!(f, "exit({})", code),
+            InstanceLogStatus::Responded => write!(f, "responded"),
+            InstanceLogStatus::Failed(err) => write!(f, "failed({})", err),
+            InstanceLogStatus::Aborted => write!(f, "aborted"),
+        }
+    }
+}
+
+/// Record of a single invocation of an exported binary, these are kept in a
+/// bounded collection on the instance chain so that failed invocations can
+/// be investigated after the fact
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstanceLog {
+    /// When the invocation started
+    pub when: DateTime<Utc>,
+    /// Name of the exported binary that was invoked
+    pub binary: String,
+    /// Address of the caller that made the invocation
+    pub caller: String,
+    /// Amount of time the invocation took to complete
+    pub duration_ms: u64,
+    /// Outcome of the invocation
+    pub status: InstanceLogStatus,
+    /// Output written to stdout (up to INSTANCE_LOG_MAX_OUTPUT bytes)
+    pub stdout: String,
+    /// Output written to stderr (up to INSTANCE_LOG_MAX_OUTPUT bytes)
+    pub stderr: String,
+    /// Indicates if some of the output was dropped as it exceeded the limit
+    pub truncated: bool,
+}
+
+impl InstanceLog {
+    pub fn new(binary: &str, caller: String) -> InstanceLog {
+        InstanceLog {
+            when: Utc::now(),
+            binary: binary.to_string(),
+            caller,
+            duration_ms: 0,
+            status: InstanceLogStatus::Aborted,
+            stdout: String::new(),
+            stderr: String::new(),
+            truncated: false,
+        }
+    }
+
+    pub fn append_stdout(&mut self, data: &[u8]) {
+        self.truncated |= Self::append(&mut self.stdout, data);
+    }
+
+    pub fn append_stderr(&mut self, data: &[u8]) {
+        self.truncated |= Self::append(&mut self.stderr, data);
+    }
+
+    /// Appends the output up to the limit and returns true if any was dropped
+    fn append(output: &mut String, data: &[u8]) -> bool {
+        let remaining = INSTANCE_LOG_MAX_OUTPUT.saturating_sub(output.len());
+        let data = String::from_utf8_lossy(data);
+        if data.len() <= remaining {
+            output.push_str(&data);
+            return false;
+        }
+
+        let mut end = remaining;
+        while data.is_char_boundary(end) == false {
+            end -= 1;
+        }
+        output.push_str(&data[..end]);
+        true
+    }
+
+    /// Approximate number of bytes this record occupies on the chain
+    pub fn size(&self) -> usize {
+        std::mem::size_of::<InstanceLog>()
+            + self.binary.len()
+            + self.caller.len()
+            + self.stdout.len()
+            + self.stderr.len()
+    }
+}
+
+/// Determines how many of the oldest records (sizes supplied oldest first)
+/// must be deleted so that a new record of `incoming` bytes can be appended
+/// without exceeding the retention limits of the instance log
+pub fn instance_log_evictions(sizes: &[usize], incoming: usize) -> usize {
+    let mut count = sizes.len() + 1;
+    let mut total = sizes.iter().sum::<usize>() + incoming;
+
+    let mut ret = 0usize;
+    for size in sizes.iter() {
+        if count <= INSTANCE_LOG_MAX_RECORDS && total <= INSTANCE_LOG_MAX_BYTES {
+            break;
+        }
+        count -= 1;
+        total -= *size;
+        ret += 1;
+    }
+    ret
+}
+
+impl fmt::Display
+for InstanceLog
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "[{}] {} caller={} status={} duration={}ms",
+            self.when.format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.binary,
+            self.caller,
+            self.status,
+            self.duration_ms
+        )?;
+        for line in self.stdout.lines() {
+            writeln!(f, "  stdout: {}", line)?;
+        }
+        for line in self.stderr.lines() {
+            writeln!(f, "  stderr: {}", line)?;
+        }
+        if self.truncated {
+            writeln!(f, "  (output truncated)")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::TimeZone;
+use chrono::Utc;
+use serde::*;
+
+/// Length of each metrics window in seconds
+pub const INSTANCE_METRICS_WINDOW_SECS: i64 = 300i64;
+/// Maximum number of windows that are kept for an instance (one day)
+pub const INSTANCE_METRICS_MAX_WINDOWS: usize = 288usize;
+/// Number of buckets in the duration histogram, bucket N holds the
+/// invocations that took less than 2^N milliseconds
+pub const INSTANCE_METRICS_BUCKETS: usize = 24usize;
+
+/// Metrics of a particular exported binary over a window of time
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstanceExportMetrics {
+    /// Name of the exported binary
+    pub binary: String,
+    /// Number of times the binary was invoked
+    pub invocations: u64,
+    /// Number of invocations that did not complete successfully
+    pub errors: u64,
+    /// Total number of bytes passed into the invocations
+    pub bytes_in: u64,
+    /// Total number of bytes returned by the invocations
+    pub bytes_out: u64,
+    /// Histogram of the invocation durations (log2 milliseconds)
+    pub durations: Vec<u64>,
+    /// Number of invocations whose binary was already loaded and compiled
+    #[serde(default)]
+    pub warm: u64,
+    /// Number of invocations that had to load and compile the binary first
+    #[serde(default)]
+    pub cold: u64,
+}
+
+impl InstanceExportMetrics {
+    pub fn new(binary: &str) -> InstanceExportMetrics {
+        InstanceExportMetrics {
+            binary: binary.to_string(),
+            durations: vec![0u64; INSTANCE_METRICS_BUCKETS],
+            ..Default::default()
+        }
+    }
+
+    pub fn record(&mut self, duration_ms: u64, success: bool, bytes_in: u64, bytes_out: u64) {
+        self.invocations += 1;
+        if success == false {
+            self.errors += 1;
+        }
+        self.bytes_in += bytes_in;
+        self.bytes_out += bytes_out;
+
+        let bucket = (u64::BITS - duration_ms.leading_zeros()) as usize;
+        let bucket = bucket.min(INSTANCE_METRICS_BUCKETS - 1);
+        if self.durations.len() <= bucket {
+            self.durations.resize(INSTANCE_METRICS_BUCKETS, 0);
+        }
+        self.durations[bucket] += 1;
+    }
+
+    /// Records whether the binary had to be loaded for an invocation
+    pub fn record_load(&mut self, warm: bool) {
+        if warm {
+            self.warm += 1;
+        } else {
+            self.cold += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &InstanceExportMetrics) {
+        self.invocations += other.invocations;
+        self.errors += other.errors;
+        self.bytes_in += other.bytes_in;
+        self.bytes_out += other.bytes_out;
+        self.warm += other.warm;
+        self.cold += other.cold;
+        if self.durations.len() < other.durations.len() {
+            self.durations.resize(other.durations.len(), 0);
+        }
+        for (a, b) in self.durations.iter_mut().zip(other.durations.iter()) {
+            *a += *b;
+        }
+    }
+
+    /// Returns the duration (upper bound of the histogram bucket) that the
+    /// supplied percentage of invocations completed within
+    pub fn percentile_ms(&self, percent: u64) -> u64 {
+        let total = self.durations.iter().sum::<u64>();
+        if total == 0 {
+            return 0;
+        }
+
+        let target = ((total * percent) + 99) / 100;
+        let mut seen = 0u64;
+        for (bucket, count) in self.durations.iter().enumerate() {
+            seen += *count;
+            if seen >= target.max(1) {
+                return 1u64 << bucket;
+            }
+        }
+        1u64 << (self.durations.len() - 1)
+    }
+
+    pub fn p50_ms(&self) -> u64 {
+        self.percentile_ms(50)
+    }
+
+    pub fn p95_ms(&self) -> u64 {
+        self.percentile_ms(95)
+    }
+}
+
+/// Metrics of all the exported binaries of an instance for a fixed window
+/// of time, these are kept in a bounded collection on the instance chain
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InstanceMetricsWindow {
+    /// Start of the window
+    pub start: DateTime<Utc>,
+    /// Metrics of each of the exports that were invoked during the window
+    pub exports: Vec<InstanceExportMetrics>,
+}
+
+impl InstanceMetricsWindow {
+    /// Creates an empty window that covers the supplied point in time
+    pub fn new(when: DateTime<Utc>) -> InstanceMetricsWindow {
+        let start = when.timestamp() - when.timestamp().rem_euclid(INSTANCE_METRICS_WINDOW_SECS);
+        InstanceMetricsWindow {
+            start: Utc.timestamp(start, 0),
+            exports: Vec::new(),
+        }
+    }
+
+    pub fn current() -> InstanceMetricsWindow {
+        Self::new(Utc::now())
+    }
+
+    pub fn end(&self) -> DateTime<Utc> {
+        self.start + Duration::seconds(INSTANCE_METRICS_WINDOW_SECS)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exports.is_empty()
+    }
+
+    pub fn export_mut(&mut self, binary: &str) -> &mut InstanceExportMetrics {
+        let index = match self
+            .exports
+            .iter()
+            .position(|a| a.binary.eq_ignore_ascii_case(binary))
+        {
+            Some(a) => a,
+            None => {
+                self.exports.push(InstanceExportMetrics::new(binary));
+                self.exports.len() - 1
+            }
+        };
+        &mut self.exports[index]
+    }
+
+    pub fn merge(&mut self, other: &InstanceMetricsWindow) {
+        for export in other.exports.iter() {
+            self.export_mut(export.binary.as_str()).merge(export);
+        }
+    }
+}
+
+/// Determines how many of the oldest windows (supplied oldest first) must
+/// be deleted so that a new window can be added within the retention limit
+pub fn instance_metrics_evictions(windows: usize) -> usize {
+    (windows + 1).saturating_sub(INSTANCE_METRICS_MAX_WINDOWS)
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use ate::prelude::*;
+use error_chain::bail;
+use serde::*;
+
+use crate::error::*;
+
+/// Templates describe the initial content of an instance (the files, the
+/// exported binaries and the environment variables) which is seeded into
+/// it when it is created
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InstanceTemplate {
+    /// Name of the template (e.g. wordpress)
+    pub name: String,
+    /// Version of the template
+    #[serde(default)]
+    pub version: String,
+    /// Description of what the template is for
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Directories that will be created (parent directories are created as well)
+    #[serde(default)]
+    pub directories: Vec<String>,
+    /// Files that will be written into the instance
+    #[serde(default)]
+    pub files: Vec<TemplateFile>,
+    /// Binaries that will be exported from the instance
+    #[serde(default)]
+    pub exports: Vec<TemplateExport>,
+    /// Environment variables that the processes of the instance start with
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// File that is seeded into the instance by a template
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemplateFile {
+    /// Absolute path of the file within the instance
+    pub path: String,
+    /// Contents of the file
+    #[serde(default)]
+    pub contents: String,
+    /// Permissions of the file (if the 'other' bits are set then the file
+    /// can be read or written without the keys of the instance)
+    #[serde(default)]
+    pub mode: Option<u32>,
+}
+
+/// Binary that is exported from the instance by a template
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemplateExport {
+    /// Name of the binary that can be invoked
+    pub binary: String,
+    /// Pins the binary to a particular location rather than distributing it
+    #[serde(default)]
+    pub pinned: bool,
+    /// Can be accessed via HTTP calls
+    #[serde(default = "default_true")]
+    pub http: bool,
+    /// Can be accessed via HTTPS calls
+    #[serde(default = "default_true")]
+    pub https: bool,
+    /// Can be accessed using the wasmer-bus
+    #[serde(default = "default_true")]
+    pub bus: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Records which template an instance was created from
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct InstanceTemplateRef {
+    /// Name of the template
+    pub name: String,
+    /// Version of the template
+    pub version: String,
+    /// Where the template was loaded from (a local path or the chain it was published on)
+    pub source: String,
+}
+
+impl InstanceTemplate {
+    /// Chain that the templates are published on by an instance authority
+    pub fn chain_key(instance_authority: &str) -> ChainKey {
+        ChainKey::from(format!("{}/templates", instance_authority))
+    }
+
+    /// Key of a published template, the latest version of a template is
+    /// published without the version
+    pub fn primary_key(name: &str, version: Option<&str>) -> PrimaryKey {
+        let name = name.to_lowercase();
+        match version {
+            Some(version) => PrimaryKey::from(format!("template://{}@{}", name, version)),
+            None => PrimaryKey::from(format!("template://{}", name)),
+        }
+    }
+
+    /// Parses a template from either JSON or YAML
+    pub fn parse(data: &str, yaml: bool) -> Result<InstanceTemplate, InstanceError> {
+        let ret: InstanceTemplate = if yaml {
+            serde_yaml::from_str(data)
+                .map_err(|err| InstanceErrorKind::InvalidTemplate(err.to_string()))?
+        } else {
+            serde_json::from_str(data)
+                .map_err(|err| InstanceErrorKind::InvalidTemplate(err.to_string()))?
+        };
+        ret.validate()?;
+        Ok(ret)
+    }
+
+    /// Checks the template before any of it is applied to the instance
+    pub fn validate(&self) -> Result<(), InstanceError> {
+        if self.name.trim().len() <= 0 {
+            bail!(InstanceErrorKind::InvalidTemplate("the template has no name".to_string()));
+        }
+        let mut dirs = Vec::new();
+        for dir in self.directories.iter() {
+            let path = template_path(dir)?;
+            for n in 1..=path.len() {
+                dirs.push(path[..n].join("/"));
+            }
+        }
+        for file in self.files.iter() {
+            let path = template_path(file.path.as_str())?;
+            if path.len() <= 0 {
+                bail!(InstanceErrorKind::InvalidTemplate(format!("the file path ({}) is not valid", file.path)));
+            }
+            for n in 1..path.len() {
+                dirs.push(path[..n].join("/"));
+            }
+        }
+
+        // The files are all written in one go so they can not be written
+        // twice nor be written where a directory will be
+        let mut files = Vec::new();
+        for file in self.files.iter() {
+            let path = template_path(file.path.as_str())?.join("/");
+            if files.contains(&path) || dirs.contains(&path) {
+                bail!(InstanceErrorKind::InvalidTemplate(format!("the file path ({}) is used more than once", file.path)));
+            }
+            files.push(path);
+            if let Some(mode) = file.mode {
+                if mode & !0o777 != 0 {
+                    bail!(InstanceErrorKind::InvalidTemplate(format!("the mode of file ({}) is not valid - {:o}", file.path, mode)));
+                }
+            }
+        }
+        for (n, export) in self.exports.iter().enumerate() {
+            if export.binary.trim().len() <= 0 {
+                bail!(InstanceErrorKind::InvalidTemplate("an export has no binary".to_string()));
+            }
+            if self.exports[..n].iter().any(|a| a.binary.eq_ignore_ascii_case(export.binary.as_str())) {
+                bail!(InstanceErrorKind::InvalidTemplate(format!("the binary ({}) is exported more than once", export.binary)));
+            }
+        }
+        for key in self.env.keys() {
+            if key.len() <= 0 || key.contains('=') || key.contains('\0') {
+                bail!(InstanceErrorKind::InvalidTemplate(format!("the environment variable ({}) is not valid", key)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits a path within the template into its components
+pub fn template_path(path: &str) -> Result<Vec<&str>, InstanceError> {
+    let mut ret = Vec::new();
+    for comp in path.split("/") {
+        match comp {
+            "" | "." => continue,
+            ".." => {
+                bail!(InstanceErrorKind::InvalidTemplate(format!("the path ({}) leaves the root of the instance", path)));
+            }
+            a => ret.push(a),
+        }
+    }
+    Ok(ret)
+}
@@ -2,6 +2,7 @@ mod advertised_service;
 mod automation_time;
 mod bag_of_coins;
 mod carved_coin;
+mod coin_rotation;
 mod charge;
 mod charge_frequency;
 mod commodity_category;
@@ -14,6 +15,7 @@ mod decimal;
 mod denomination;
 mod digital_asset;
 mod digital_service;
+mod export_token;
 mod historic_activity;
 mod historic_day;
 mod historic_month;
@@ -28,13 +30,19 @@ mod wallet_instance;
 mod instance_command;
 mod instance_hello;
 mod instance_export;
+mod instance_log;
+mod instance_metrics;
 mod instance_subnet;
+mod instance_template;
 mod mesh_node;
+mod sub_wallet;
+mod tests;
 
 pub use advertised_service::*;
 pub use automation_time::*;
 pub use bag_of_coins::*;
 pub use carved_coin::*;
+pub use coin_rotation::*;
 pub use charge::*;
 pub use charge_frequency::*;
 pub use commodity_category::*;
@@ -47,6 +55,7 @@ pub use decimal::*;
 pub use denomination::*;
 pub use digital_asset::*;
 pub use digital_service::*;
+pub use export_token::*;
 pub use historic_activity::*;
 pub use historic_day::*;
 pub use historic_month::*;
@@ -61,8 +70,12 @@ pub use wallet_instance::*;
 pub use instance_command::*;
 pub use instance_hello::*;
 pub use instance_export::*;
+pub use instance_log::*;
+pub use instance_metrics::*;
 pub use instance_subnet::*;
+pub use instance_template::*;
 pub use mesh_node::*;
+pub use sub_wallet::*;
 
 pub use wasmer_bus_mio::model::*;
 
@@ -72,5 +85,9 @@ pub const INSTANCE_COLLECTION_ID: u64 = 5476918267819474034u64;
 pub const INVOICE_COLLECTION_ID: u64 = 1234960345778345782u64;
 pub const MASTER_AUTHORITY_ID: u64 = 12743381463764637636u64;
 pub const INSTANCE_ROOT_ID: u64 = 9384758237459681256u64;
+pub const INSTANCE_LOG_COLLECTION_ID: u64 = 6412935587203374921u64;
+pub const INSTANCE_METRICS_COLLECTION_ID: u64 = 1873104592276450618u64;
+pub const SUB_WALLET_COLLECTION_ID: u64 = 7391836410925537261u64;
+pub const COIN_ROTATION_COLLECTION_ID: u64 = 4127305981463920817u64;
 
 pub const COINS_PER_STACK_TO_BE_COMBINED: usize = 10usize;
@@ -1,7 +1,11 @@
-use ate::{prelude::DaoVec};
+use std::collections::BTreeMap;
+use ate::prelude::*;
+use error_chain::bail;
 use serde::*;
 
-use super::{InstanceExport, InstanceSubnet, MeshNode};
+use crate::error::*;
+
+use super::{ExportCapability, ExportRequest, ExportToken, InstanceExport, InstanceSubnet, InstanceTemplateRef, MeshNode};
 
 /// Running instance of a particular web assembly application
 /// within the hosting environment
@@ -20,6 +24,17 @@ pub struct ServiceInstance {
     pub exports: DaoVec<InstanceExport>,
     /// List of active nodes currently partipating in the mesh
     pub mesh_nodes: DaoVec<MeshNode>,
+    /// Key that checks the capability tokens of the exports, the private half
+    /// is held by the owner (instances that were created before capability
+    /// tokens are given one on their next export)
+    #[serde(default)]
+    pub export_public_key: Option<PublicSignKey>,
+    /// Template that the instance was created from (if any)
+    #[serde(default)]
+    pub template: Option<InstanceTemplateRef>,
+    /// Environment variables that the processes of this instance start with
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
 }
 
 impl ServiceInstance
@@ -27,4 +42,49 @@ impl ServiceInstance
     pub fn id_str(&self) -> String {
         hex::encode(&self.id.to_be_bytes())
     }
-}
\ No newline at end of file
+
+    /// Checks that a token grants access to a request made against an exported
+    /// binary, the token is either a capability token (in which case the
+    /// capability is returned) or the raw access token of the export
+    pub async fn authorize_export(
+        &self,
+        binary: &str,
+        token: &str,
+        request: ExportRequest<'_>,
+    ) -> Result<Option<ExportCapability>, InstanceError> {
+        let chain = ChainKey::from(self.chain.clone());
+
+        // Raw access tokens keep working until the export is deported
+        if ExportToken::is_capability(token) == false {
+            let token = token.trim();
+            if self
+                .exports
+                .iter()
+                .await?
+                .filter(|e| e.binary.eq_ignore_ascii_case(binary))
+                .any(|e| e.access_token.eq_ignore_ascii_case(token))
+            {
+                return Ok(None);
+            }
+            bail!(InstanceErrorKind::InvalidAccessToken);
+        }
+
+        let export_key = match self.export_public_key.as_ref() {
+            Some(a) => a,
+            None => {
+                bail!(InstanceErrorKind::InvalidAccessToken);
+            }
+        };
+        let token = ExportToken::decode(token).ok_or(InstanceErrorKind::InvalidAccessToken)?;
+        let capability = token
+            .verify(export_key)
+            .ok_or(InstanceErrorKind::InvalidAccessToken)?;
+        capability.check(&chain, binary, request)?;
+
+        // The export that the token was minted for must still exist
+        if self.exports.iter().await?.any(|e| capability.is_for(&e)) == false {
+            bail!(InstanceErrorKind::InvalidAccessToken);
+        }
+        Ok(Some(capability.clone()))
+    }
+}
@@ -0,0 +1,107 @@
+use ate::prelude::*;
+use chrono::prelude::*;
+use num_traits::*;
+use serde::*;
+use std::fmt;
+use std::str::FromStr;
+
+use super::*;
+
+/// Maximum amount that a sub-wallet may spend within a rolling period
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpendingLimit {
+    pub amount: Decimal,
+    pub currency: NationalCurrency,
+    pub period: ChargeFrequency,
+}
+
+impl fmt::Display for SpendingLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.amount, self.currency, self.period)
+    }
+}
+
+/// Amount and period of a spending limit as it is entered on the command
+/// line (e.g. 100/month)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpendingAllowance {
+    pub amount: Decimal,
+    pub period: ChargeFrequency,
+}
+
+impl FromStr for SpendingAllowance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (amount, period) = match s.split_once('/') {
+            Some(a) => a,
+            None => {
+                return Err(format!("the limit must be in the form <amount>/<period> (e.g. 100/month)"));
+            }
+        };
+        let amount = Decimal::from_str(amount.trim())
+            .map_err(|err| format!("invalid limit amount ({}) - {}", amount, err))?;
+        if amount <= Decimal::zero() {
+            return Err(format!("the limit amount must be greater than zero"));
+        }
+        let period = match period.trim().to_lowercase().as_str() {
+            "hour" => ChargeFrequency::PerHour,
+            "day" => ChargeFrequency::PerDay,
+            "week" => ChargeFrequency::PerWeek,
+            "month" => ChargeFrequency::PerMonth,
+            "year" => ChargeFrequency::PerYear,
+            a => {
+                return Err(format!("invalid limit period ({}) - expected hour, day, week, month or year", a));
+            }
+        };
+        Ok(SpendingAllowance { amount, period })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubWalletSpend {
+    pub when: DateTime<Utc>,
+    pub amount: Decimal,
+}
+
+/// Sub-wallets are wallets that are attached to another wallet (the parent)
+/// and that draw their coins from it on demand, the amount that they may
+/// spend is capped over a rolling period
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubWallet {
+    /// Name of the sub-wallet (unique within the parent)
+    pub name: String,
+    /// Key of the wallet that holds the coins of this sub-wallet
+    pub wallet: PrimaryKey,
+    /// Maximum that may be spent within the rolling period
+    pub limit: SpendingLimit,
+    /// Amounts spent by the sub-wallet that still fall within the period
+    pub spent: Vec<SubWalletSpend>,
+    /// Frozen sub-wallets may not spend anything until they are thawed
+    pub frozen: bool,
+}
+
+impl SubWallet {
+    /// Amount spent within the rolling period that ends now
+    pub fn consumed(&self, now: DateTime<Utc>) -> Decimal {
+        let since = now - self.limit.period.as_duration();
+        self.spent
+            .iter()
+            .filter(|a| a.when > since)
+            .map(|a| a.amount)
+            .sum()
+    }
+
+    /// Amount that may still be spent within the rolling period
+    pub fn remaining(&self, now: DateTime<Utc>) -> Decimal {
+        (self.limit.amount - self.consumed(now)).max(Decimal::zero())
+    }
+
+    /// Records an amount that was spent and forgets anything that has
+    /// fallen outside of the rolling period
+    pub fn record_spend(&mut self, now: DateTime<Utc>, amount: Decimal) {
+        let since = now - self.limit.period.as_duration();
+        self.spent.retain(|a| a.when > since);
+        self.spent.push(SubWalletSpend { when: now, amount });
+    }
+}
@@ -0,0 +1,290 @@
+#![cfg(test)]
+use ate::crypto::SignedProtectedData;
+use ate::prelude::*;
+use chrono::prelude::*;
+use num_traits::*;
+
+use super::*;
+
+fn test_rotation(sign_key: &PrivateSignKey, signer: &PublicSignKey) -> CoinRotation {
+    let link = CoinRotationLink {
+        coin: PrimaryKey::from(1234u64),
+        old_owner: AteHash::from("old-owner"),
+        new_owner: AteHash::from("new-owner"),
+        when: Utc::now(),
+    };
+    CoinRotation {
+        link: SignedProtectedData::new(sign_key, link).unwrap(),
+        signer: signer.clone(),
+    }
+}
+
+#[test]
+fn test_coin_rotation_signed_by_owner() {
+    let owner = PrivateSignKey::generate(KeySize::Bit192);
+    let other = PrivateSignKey::generate(KeySize::Bit192);
+    let rotation = test_rotation(&owner, owner.as_public_key());
+
+    assert!(rotation.verify(&[owner.as_public_key().clone()]));
+    assert!(rotation.verify(&[other.as_public_key().clone(), owner.as_public_key().clone()]));
+    assert!(rotation.verify(&[]) == false);
+}
+
+#[test]
+fn test_coin_rotation_self_signed_is_rejected() {
+    // A record that names its own signer must not be trusted just because
+    // the signature matches the key in the record
+    let owner = PrivateSignKey::generate(KeySize::Bit192);
+    let forger = PrivateSignKey::generate(KeySize::Bit192);
+    let rotation = test_rotation(&forger, forger.as_public_key());
+
+    assert!(rotation.verify(&[owner.as_public_key().clone()]) == false);
+}
+
+#[test]
+fn test_coin_rotation_claimed_owner_is_rejected() {
+    // Naming the owner as the signer does not help if the owner never signed it
+    let owner = PrivateSignKey::generate(KeySize::Bit192);
+    let forger = PrivateSignKey::generate(KeySize::Bit192);
+    let rotation = test_rotation(&forger, owner.as_public_key());
+
+    assert!(rotation.verify(&[owner.as_public_key().clone()]) == false);
+}
+
+#[test]
+fn test_sub_wallet_spends_accumulate() {
+    // Spends are checked one at a time (under the lock of the record) so each
+    // one must see everything that was spent before it
+    let now = Utc::now();
+    let mut sub = SubWallet {
+        name: "ci".to_string(),
+        wallet: PrimaryKey::from(1u64),
+        limit: SpendingLimit {
+            amount: Decimal::from(100),
+            currency: NationalCurrency::USD,
+            period: ChargeFrequency::PerDay,
+        },
+        spent: Vec::new(),
+        frozen: false,
+    };
+    sub.record_spend(now, Decimal::from(60));
+    assert_eq!(sub.remaining(now), Decimal::from(40));
+    sub.record_spend(now, Decimal::from(40));
+    assert_eq!(sub.remaining(now), Decimal::zero());
+
+    // Once the period has rolled over the limit is available again
+    let later = now + chrono::Duration::days(1) + chrono::Duration::seconds(1);
+    assert_eq!(sub.remaining(later), Decimal::from(100));
+}
+
+#[test]
+fn test_wallet_instance_index_name() {
+    let instance = WalletInstance {
+        name: "MyInstance".to_string(),
+        id: 1,
+        chain: ChainKey::from("me/myinstance"),
+    };
+
+    // Lookups match the index regardless of the case of the name
+    assert_eq!(
+        instance.index_value("name"),
+        Some(WalletInstance::index_name("myinstance"))
+    );
+    assert_eq!(
+        WalletInstance::index_name("MYINSTANCE"),
+        WalletInstance::index_name("myInstance")
+    );
+    assert_eq!(instance.index_value("id"), None);
+}
+
+#[test]
+fn test_instance_template_parse() {
+    let json = r#"{
+        "name": "wordpress",
+        "version": "1.0",
+        "directories": ["/var/www"],
+        "files": [{"path": "/etc/wordpress.conf", "contents": "debug=false", "mode": 420}],
+        "exports": [{"binary": "php", "pinned": true}],
+        "env": {"WP_HOME": "/var/www"}
+    }"#;
+    let template = InstanceTemplate::parse(json, false).unwrap();
+    assert_eq!(template.name, "wordpress");
+    assert_eq!(template.files[0].mode, Some(0o644));
+    assert!(template.exports[0].pinned);
+    assert!(template.exports[0].http && template.exports[0].https && template.exports[0].bus);
+    assert_eq!(template.env.get("WP_HOME").map(|a| a.as_str()), Some("/var/www"));
+
+    let yaml = "name: static\nfiles:\n  - path: /www/index.html\n    contents: hello\n";
+    let template = InstanceTemplate::parse(yaml, true).unwrap();
+    assert_eq!(template.name, "static");
+    assert_eq!(template.version, "");
+    assert_eq!(template.files[0].contents, "hello");
+
+    assert!(InstanceTemplate::parse("{ not json", false).is_err());
+    assert!(InstanceTemplate::parse(r#"{"name": " "}"#, false).is_err());
+}
+
+#[test]
+fn test_instance_template_validate() {
+    let template = |files: &[&str], dirs: &[&str]| InstanceTemplate {
+        name: "test".to_string(),
+        version: "1.0".to_string(),
+        description: None,
+        directories: dirs.iter().map(|a| a.to_string()).collect(),
+        files: files
+            .iter()
+            .map(|a| TemplateFile {
+                path: a.to_string(),
+                contents: String::new(),
+                mode: None,
+            })
+            .collect(),
+        exports: Vec::new(),
+        env: Default::default(),
+    };
+    assert!(template(&["/etc/a.conf", "/etc/b.conf"], &["/etc", "/var/www"]).validate().is_ok());
+
+    // Paths may not leave the root, be empty, be written twice or collide
+    // with a directory
+    assert!(template(&["/etc/../../passwd"], &[]).validate().is_err());
+    assert!(template(&[], &["/var/../.."]).validate().is_err());
+    assert!(template(&["/"], &[]).validate().is_err());
+    assert!(template(&["/etc/a.conf", "etc/./a.conf"], &[]).validate().is_err());
+    assert!(template(&["/var/www"], &["/var/www/html"]).validate().is_err());
+    assert!(template(&["/etc", "/etc/a.conf"], &[]).validate().is_err());
+
+    let mut bad_mode = template(&["/etc/a.conf"], &[]);
+    bad_mode.files[0].mode = Some(0o4755);
+    assert!(bad_mode.validate().is_err());
+
+    let mut bad_exports = template(&[], &[]);
+    bad_exports.exports = vec![
+        TemplateExport { binary: "php".to_string(), pinned: false, http: true, https: true, bus: true },
+        TemplateExport { binary: "PHP".to_string(), pinned: false, http: true, https: true, bus: true },
+    ];
+    assert!(bad_exports.validate().is_err());
+
+    let mut bad_env = template(&[], &[]);
+    bad_env.env.insert("A=B".to_string(), "C".to_string());
+    assert!(bad_env.validate().is_err());
+}
+
+#[test]
+fn test_instance_template_path() {
+    assert_eq!(template_path("/var/www/html").unwrap(), vec!["var", "www", "html"]);
+    assert_eq!(template_path("./var//www/").unwrap(), vec!["var", "www"]);
+    assert_eq!(template_path("/").unwrap(), Vec::<&str>::new());
+    assert!(template_path("/var/../etc").is_err());
+    assert!(template_path("..").is_err());
+}
+
+#[test]
+fn test_instance_template_primary_key() {
+    // Names are case-insensitive and each version has its own key
+    assert_eq!(
+        InstanceTemplate::primary_key("WordPress", Some("1.0")),
+        InstanceTemplate::primary_key("wordpress", Some("1.0"))
+    );
+    assert_ne!(
+        InstanceTemplate::primary_key("wordpress", Some("1.0")),
+        InstanceTemplate::primary_key("wordpress", Some("2.0"))
+    );
+    assert_ne!(
+        InstanceTemplate::primary_key("wordpress", None),
+        InstanceTemplate::primary_key("wordpress", Some("1.0"))
+    );
+}
+
+fn test_export(access_token: &str) -> InstanceExport {
+    InstanceExport {
+        access_token: access_token.to_string(),
+        binary: "sh".to_string(),
+        distributed: true,
+        http: true,
+        https: true,
+        bus: true,
+        pinned: None,
+    }
+}
+
+#[test]
+fn test_export_token_mint_and_verify() {
+    let sign_key = PrivateSignKey::generate(KeySize::Bit192);
+    let chain = ChainKey::from("me/myinstance");
+    let export = test_export("abcdef");
+
+    let capability = ExportCapability::new(&chain, &export);
+    let token = ExportToken::mint(&sign_key, capability).unwrap();
+    assert!(ExportToken::is_capability(token.as_str()));
+    assert!(ExportToken::is_capability(export.access_token.as_str()) == false);
+
+    // Only the key of the instance can have signed the token
+    let decoded = ExportToken::decode(token.as_str()).unwrap();
+    let capability = decoded.verify(sign_key.as_public_key()).unwrap();
+    assert!(capability.is_for(&export));
+    let other = PrivateSignKey::generate(KeySize::Bit192);
+    assert!(decoded.verify(other.as_public_key()).is_none());
+
+    // Tokens that were tampered with are rejected
+    let forged = ExportToken::mint(&other, ExportCapability::new(&chain, &export)).unwrap();
+    let forged = ExportToken::decode(forged.as_str()).unwrap();
+    assert!(forged.verify(sign_key.as_public_key()).is_none());
+    assert!(ExportToken::decode("cap1.not-a-token").is_none());
+
+    // Deporting (or replacing) the export revokes its tokens
+    assert!(capability.is_for(&test_export("123456")) == false);
+}
+
+#[test]
+fn test_export_token_check() {
+    let chain = ChainKey::from("me/myinstance");
+    let export = test_export("abcdef");
+    let full = ExportCapability::new(&chain, &export);
+    assert!(full.check(&chain, "SH", ExportRequest::Http { topic: None }).is_ok());
+    assert!(full.check(&chain, "sh", ExportRequest::Bus { topic: "run" }).is_ok());
+    assert!(full.check(&ChainKey::from("me/other"), "sh", ExportRequest::Http { topic: None }).is_err());
+    assert!(full.check(&chain, "bash", ExportRequest::Http { topic: None }).is_err());
+
+    // Narrower tokens only allow what they were minted for
+    let mut narrow = full.clone();
+    narrow.bus = false;
+    narrow.topics = vec!["run".to_string()];
+    assert!(narrow.check(&chain, "sh", ExportRequest::Http { topic: Some("RUN") }).is_ok());
+    assert!(narrow.check(&chain, "sh", ExportRequest::Http { topic: Some("stop") }).is_err());
+    assert!(narrow.check(&chain, "sh", ExportRequest::Http { topic: None }).is_err());
+    assert!(narrow.check(&chain, "sh", ExportRequest::Bus { topic: "run" }).is_err());
+
+    let mut expired = full.clone();
+    expired.expires = Some(Utc::now() - chrono::Duration::seconds(1));
+    match expired.check(&chain, "sh", ExportRequest::Http { topic: None }) {
+        Err(crate::error::InstanceErrorKind::AccessTokenExpired) => {}
+        a => panic!("the token should have expired - {:?}", a),
+    }
+    expired.expires = Some(Utc::now() + chrono::Duration::hours(1));
+    assert!(expired.check(&chain, "sh", ExportRequest::Http { topic: None }).is_ok());
+}
+
+#[test]
+fn test_export_token_check_deport() {
+    let chain = ChainKey::from("me/myinstance");
+    let export = test_export("abcdef");
+    let full = ExportCapability::new(&chain, &export);
+    assert!(full.check_deport(&chain, &export).is_ok());
+    assert!(full.check_deport(&chain, &test_export("123456")).is_err());
+    assert!(full.check_deport(&ChainKey::from("me/other"), &export).is_err());
+
+    // Tokens that were handed out with less access can not remove the export
+    let mut narrow = full.clone();
+    narrow.topics = vec!["run".to_string()];
+    assert!(narrow.check_deport(&chain, &export).is_err());
+    let mut narrow = full.clone();
+    narrow.bus = false;
+    assert!(narrow.check_deport(&chain, &export).is_err());
+    let mut narrow = full.clone();
+    narrow.rate_limit = Some(10);
+    assert!(narrow.check_deport(&chain, &export).is_err());
+
+    let mut expired = full.clone();
+    expired.expires = Some(Utc::now() - chrono::Duration::seconds(1));
+    assert!(expired.check_deport(&chain, &export).is_err());
+}
@@ -1,5 +1,7 @@
 use serde::*;
 use ate::prelude::ChainKey;
+use ate::prelude::DaoIndexed;
+use ate::prelude::IndexValue;
 
 /// Running instance of a particular web assembly application
 /// within the hosting environment
@@ -18,4 +20,24 @@ impl WalletInstance
     pub fn id_str(&self) -> String {
         hex::encode(&self.id.to_be_bytes())
     }
-}
\ No newline at end of file
+
+    /// Value that the index holds for the name of an instance, lookups must
+    /// use this as well so that they match regardless of the case
+    pub fn index_name(name: &str) -> IndexValue {
+        IndexValue::Text(name.to_lowercase())
+    }
+}
+
+/// Instances are looked up by their name (which is case-insensitive)
+impl DaoIndexed for WalletInstance
+{
+    const INDEX_NAME: &'static str = "wallet-instance";
+    const INDEXED_FIELDS: &'static [&'static str] = &["name"];
+
+    fn index_value(&self, field: &str) -> Option<IndexValue> {
+        match field {
+            "name" => Some(WalletInstance::index_name(self.name.as_str())),
+            _ => None,
+        }
+    }
+}
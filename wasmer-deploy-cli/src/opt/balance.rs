@@ -1,5 +1,7 @@
 use clap::Parser;
 
+use crate::model::NationalCurrency;
+
 #[derive(Parser, Clone)]
 #[clap()]
 pub struct OptsBalance {
@@ -9,4 +11,7 @@ pub struct OptsBalance {
     /// When reading the balance the wallet is first reconciled - to prevent this happening then set this flag
     #[clap(long)]
     pub no_reconcile: bool,
+    /// Shows an estimate of the total balance converted into this currency using the current rates
+    #[clap(long = "in", value_name = "CURRENCY")]
+    pub convert: Option<NationalCurrency>,
 }
@@ -0,0 +1,50 @@
+use ate::prelude::*;
+use clap::Parser;
+
+use crate::model::NationalCurrency;
+
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsCoin {
+    /// Action to perform on the coins held in this wallet
+    #[clap(subcommand)]
+    pub action: OptsCoinAction,
+}
+
+#[derive(Parser, Clone)]
+pub enum OptsCoinAction {
+    /// Rotates the ownership keys of the coins (the old keys no longer grant access to them)
+    #[clap()]
+    Rotate(OptsCoinRotate),
+    /// Lists the rotations of a coin that were signed by the owner of this wallet
+    #[clap()]
+    History(OptsCoinHistory),
+}
+
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsCoinRotate {
+    /// Rotates every coin held in the wallet
+    #[clap(long, required_unless_present = "currency")]
+    pub all: bool,
+    /// Only rotates the coins of this national currency (e.g. aud,eur,gbp,usd,hkd)
+    #[clap(long, conflicts_with = "all")]
+    pub currency: Option<NationalCurrency>,
+    /// Maximum number of coins that are rotated within a single transaction
+    #[clap(long, default_value = "50")]
+    pub batch_size: usize,
+}
+
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsCoinHistory {
+    /// Key of the coin (in hex) whose ownership rotations will be listed
+    #[clap(parse(try_from_str = parse_coin_key))]
+    pub coin: PrimaryKey,
+}
+
+fn parse_coin_key(val: &str) -> Result<PrimaryKey, String> {
+    u64::from_str_radix(val.trim_start_matches("0x"), 16)
+        .map(PrimaryKey::from)
+        .map_err(|_| format!("invalid coin key [{}]", val))
+}
@@ -0,0 +1,9 @@
+use clap::Parser;
+
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsExportWallet {
+    /// Path of the archive file that the snapshot will be written to
+    #[clap(index = 1)]
+    pub path: String,
+}
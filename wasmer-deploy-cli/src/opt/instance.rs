@@ -1,6 +1,9 @@
 use std::net::IpAddr;
 
 use ate_crypto::SerializationFormat;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
 use clap::Parser;
 use url::Url;
 
@@ -95,6 +98,9 @@ pub enum OptsInstanceAction {
     /// Deports a previously exposed interface for a particular instance
     #[clap()]
     Deport(OptsInstanceDeport),
+    /// Mints capability tokens that grant scoped access to an exported interface
+    #[clap()]
+    Token(OptsInstanceToken),
     /// Kills are particular instance - killed instances are totally destroyed
     #[clap()]
     Kill(OptsInstanceKill),
@@ -119,6 +125,12 @@ pub enum OptsInstanceAction {
     /// Resets an instance
     #[clap()]
     Reset(OptsInstanceReset),
+    /// Shows the log of invocations made against the exported binaries of an instance
+    #[clap()]
+    Logs(OptsInstanceLogs),
+    /// Shows the invocation statistics of the exported binaries of an instance
+    #[clap()]
+    Stats(OptsInstanceStats),
 }
 
 impl OptsInstanceAction
@@ -149,10 +161,35 @@ impl OptsInstanceAction
             OptsInstanceAction::Call(opts) => Some(opts.name.clone()),
             OptsInstanceAction::Export(opts) => Some(opts.name.clone()),
             OptsInstanceAction::Deport(opts) => Some(opts.name.clone()),
+            OptsInstanceAction::Token(opts) => Some(opts.name.clone()),
             OptsInstanceAction::Mount(opts) => Some(opts.name.clone()),
             OptsInstanceAction::Cidr(opts) => Some(opts.name.clone()),
             OptsInstanceAction::Peering(opts) => Some(opts.name.clone()),
             OptsInstanceAction::Reset(opts) => Some(opts.name.clone()),
+            OptsInstanceAction::Logs(opts) => Some(opts.name.clone()),
+            OptsInstanceAction::Stats(opts) => Some(opts.name.clone()),
+        }
+    }
+
+    /// Name of the sub command as it is typed on the command line
+    pub fn command(&self) -> &'static str {
+        match self {
+            OptsInstanceAction::List => "list",
+            OptsInstanceAction::Details(_) => "details",
+            OptsInstanceAction::Create(_) => "create",
+            OptsInstanceAction::Export(_) => "export",
+            OptsInstanceAction::Deport(_) => "deport",
+            OptsInstanceAction::Token(_) => "token",
+            OptsInstanceAction::Kill(_) => "kill",
+            OptsInstanceAction::Shell(_) => "shell",
+            OptsInstanceAction::Call(_) => "call",
+            OptsInstanceAction::Mount(_) => "mount",
+            OptsInstanceAction::Clone(_) => "clone",
+            OptsInstanceAction::Cidr(_) => "cidr",
+            OptsInstanceAction::Peering(_) => "peering",
+            OptsInstanceAction::Reset(_) => "reset",
+            OptsInstanceAction::Logs(_) => "logs",
+            OptsInstanceAction::Stats(_) => "stats",
         }
     }
 }
@@ -174,6 +211,11 @@ pub struct OptsInstanceCreate {
     /// Forces the creation of this instance even if there is a duplicate
     #[clap(short, long)]
     pub force: bool,
+    /// Template that seeds the files, exports and environment variables of the
+    /// instance, either a local file (.json or .yaml) or the name of a published
+    /// template (e.g. wordpress or wordpress@1.0)
+    #[clap(short, long)]
+    pub template: Option<String>,
 }
 
 #[derive(Parser, Clone)]
@@ -208,11 +250,55 @@ pub struct OptsInstanceDeport {
     /// Name of the instance to export an interface from
     #[clap(index = 1)]
     pub name: String,
-    /// Token of the exported interface to be deleted
+    /// Token of the exported interface to be deleted (either the export
+    /// token or a capability token that was minted for it)
     #[clap(index = 2)]
     pub token: String,
 }
 
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsInstanceToken {
+    /// Name of the instance
+    #[clap(index = 1)]
+    pub name: String,
+    /// Action to perform on the tokens
+    #[clap(subcommand)]
+    pub action: OptsTokenAction,
+}
+
+#[derive(Parser, Clone)]
+#[clap()]
+pub enum OptsTokenAction {
+    /// Mints a new capability token for an exported binary
+    #[clap()]
+    Mint(OptsTokenMint),
+}
+
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsTokenMint {
+    /// Name of the exported binary that the token grants access to
+    #[clap(index = 1)]
+    pub binary: String,
+    /// Point in time when the token expires, either as a timestamp
+    /// (e.g. 2022-06-01T12:00:00Z) or relative to now (e.g. 30m, 2h, 1d)
+    #[clap(long, parse(try_from_str = parse_expire))]
+    pub expire: Option<DateTime<Utc>>,
+    /// Topics that the token may call (all topics when none are supplied)
+    #[clap(long, use_value_delimiter = true)]
+    pub topics: Vec<String>,
+    /// Maximum number of invocations per minute
+    #[clap(long)]
+    pub rate_limit: Option<u32>,
+    /// The token may not be used for http calls
+    #[clap(long)]
+    pub no_http: bool,
+    /// The token may not be used for wasmer-bus calls
+    #[clap(long)]
+    pub no_bus: bool,
+}
+
 #[derive(Parser, Clone)]
 #[clap()]
 pub struct OptsInstanceKill {
@@ -257,6 +343,14 @@ pub struct OptsInstanceCall {
     /// Format of the data passed into this call
     #[clap(short, long, default_value = "json")]
     pub format: SerializationFormat,
+    /// Holds the call open so that each line read from stdin is sent as a
+    /// separate message to the same running handler
+    #[clap(long)]
+    pub interactive: bool,
+    /// When in interactive mode stdin and stdout carry length-prefixed binary
+    /// frames (32-bit big endian length) rather than lines of text
+    #[clap(long = "binary", requires = "interactive")]
+    pub binary_frames: bool,
 }
 
 #[derive(Parser, Clone)]
@@ -333,6 +427,68 @@ pub struct OptsInstanceReset {
     pub name: String,
 }
 
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsInstanceLogs {
+    /// Name of the instance
+    #[clap(index = 1)]
+    pub name: String,
+    /// Keeps running and displays new invocations as they are recorded
+    #[clap(short, long)]
+    pub follow: bool,
+    /// Only shows the invocations of this exported binary
+    #[clap(short, long)]
+    pub export: Option<String>,
+    /// Only shows invocations after this point in time, either as a timestamp
+    /// (e.g. 2022-06-01T12:00:00Z) or relative to now (e.g. 30s, 15m, 2h, 1d)
+    #[clap(long, parse(try_from_str = parse_since))]
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsInstanceStats {
+    /// Name of the instance
+    #[clap(index = 1)]
+    pub name: String,
+    /// Only shows the statistics of this exported binary
+    #[clap(short, long)]
+    pub export: Option<String>,
+    /// Only includes the statistics after this point in time, either as a timestamp
+    /// (e.g. 2022-06-01T12:00:00Z) or relative to now (e.g. 30m, 2h, 1d)
+    #[clap(long, parse(try_from_str = parse_since))]
+    pub since: Option<DateTime<Utc>>,
+}
+
+fn parse_since(val: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(when) = DateTime::parse_from_rfc3339(val) {
+        return Ok(when.with_timezone(&Utc));
+    }
+    Ok(Utc::now() - parse_duration(val)?)
+}
+
+fn parse_expire(val: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(when) = DateTime::parse_from_rfc3339(val) {
+        return Ok(when.with_timezone(&Utc));
+    }
+    Ok(Utc::now() + parse_duration(val)?)
+}
+
+fn parse_duration(val: &str) -> Result<Duration, String> {
+    let digits = val.len() - val.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let (amount, unit) = val.split_at(digits);
+    let amount = amount.parse::<i64>()
+        .map_err(|_| format!("invalid point in time [{}]", val))?;
+    let duration = match unit {
+        "s" | "" => Duration::seconds(amount),
+        "m" => Duration::minutes(amount),
+        "h" => Duration::hours(amount),
+        "d" => Duration::days(amount),
+        _ => return Err(format!("invalid time unit [{}] (valid units are s, m, h and d)", unit)),
+    };
+    Ok(duration)
+}
+
 #[derive(Parser, Clone)]
 #[clap()]
 pub enum OptsPeeringAction {
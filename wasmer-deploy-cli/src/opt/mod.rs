@@ -1,19 +1,25 @@
 mod balance;
 mod bus;
+mod coin;
 mod contract;
 mod create_wallet;
 mod deposit;
 mod destination;
+mod export_wallet;
 mod history;
 mod login;
 mod logout;
+mod output;
 mod purpose;
 mod remove_wallet;
 mod service;
+mod setup;
 mod source;
+mod sub_wallet;
 mod transfer;
 mod wallet;
 mod wallet_action;
+mod watch_wallet;
 mod withdraw;
 mod instance;
 mod network;
@@ -22,20 +28,26 @@ pub use wasmer_auth::opt::*;
 
 pub use balance::*;
 pub use bus::*;
+pub use coin::*;
 pub use contract::*;
 pub use create_wallet::*;
 pub use deposit::*;
 pub use destination::*;
+pub use export_wallet::*;
 pub use history::*;
 pub use login::*;
 pub use logout::*;
+pub use output::*;
 pub use purpose::*;
 pub use remove_wallet::*;
 pub use service::*;
+pub use setup::*;
 pub use source::*;
+pub use sub_wallet::*;
 pub use transfer::*;
 pub use wallet::*;
 pub use wallet_action::*;
+pub use watch_wallet::*;
 pub use withdraw::*;
 pub use instance::*;
 pub use network::*;
\ No newline at end of file
@@ -0,0 +1,50 @@
+/// Determines how the results of a command are written to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable text
+    Text,
+    /// A single machine-readable JSON document
+    Json,
+}
+
+impl Default
+for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Text
+    }
+}
+
+impl OutputFormat {
+    pub fn is_json(&self) -> bool {
+        *self == OutputFormat::Json
+    }
+}
+
+impl std::fmt::Display
+for OutputFormat
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl std::str::FromStr
+for OutputFormat
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(
+            match s {
+                "text" | "txt" | "" => OutputFormat::Text,
+                "json" => OutputFormat::Json,
+                a => {
+                    return Err(format!("output format ({}) is not valid - try: 'text' or 'json'", a))
+                }
+            }
+        )
+    }
+}
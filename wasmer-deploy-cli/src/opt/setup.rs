@@ -0,0 +1,31 @@
+use clap::Parser;
+
+use crate::model::*;
+
+#[allow(dead_code)]
+#[derive(Parser, Clone)]
+#[clap(version = "1.5", author = "Wasmer Inc <info@wasmer.io>")]
+pub struct OptsSetup {
+    /// Runs without prompting - any values that are not supplied as arguments are
+    /// read from the environment (WASMER_EMAIL, WASMER_PASSWORD, WASMER_VERIFY_CODE,
+    /// WASMER_ACCEPT_TERMS, WASMER_GROUP, WASMER_COUNTRY and WASMER_SUDO_CODE) and
+    /// optional steps without a value are skipped
+    #[clap(long)]
+    pub defaults: bool,
+    /// Email address of the account to login with (or to create)
+    #[clap(long)]
+    pub email: Option<String>,
+    /// Name of a domain group to create (optional)
+    #[clap(long)]
+    pub group: Option<String>,
+    /// Country of residence for tax purposes (ISO 3166) that the personal wallet
+    /// will be created with - this is the alpha-3 letter code (e.g. USA)
+    #[clap(long)]
+    pub country: Option<Country>,
+    /// Skips the creation of the domain group
+    #[clap(long)]
+    pub skip_group: bool,
+    /// Skips the initialization of the personal wallet
+    #[clap(long)]
+    pub skip_wallet: bool,
+}
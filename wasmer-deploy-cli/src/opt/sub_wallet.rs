@@ -0,0 +1,64 @@
+use clap::Parser;
+
+use crate::model::NationalCurrency;
+use crate::model::SpendingAllowance;
+
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsSubWallet {
+    /// Action to perform on the sub-wallets of this wallet
+    #[clap(subcommand)]
+    pub action: OptsSubWalletAction,
+}
+
+#[derive(Parser, Clone)]
+pub enum OptsSubWalletAction {
+    /// Creates a sub-wallet that can spend from this wallet up to a limit
+    #[clap()]
+    Create(OptsSubWalletCreate),
+    /// Lists all the sub-wallets of this wallet
+    #[clap()]
+    List,
+    /// Stops a sub-wallet from spending anything
+    #[clap()]
+    Freeze(OptsSubWalletName),
+    /// Allows a frozen sub-wallet to spend again
+    #[clap()]
+    Unfreeze(OptsSubWalletName),
+    /// Removes an empty sub-wallet
+    #[clap()]
+    Remove(OptsSubWalletRemove),
+}
+
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsSubWalletCreate {
+    /// Name of the sub-wallet (it is then addressed as <wallet>/<name>)
+    #[clap(index = 1)]
+    pub name: String,
+    /// Most that the sub-wallet may spend within a rolling period (e.g. 100/month)
+    #[clap(long)]
+    pub limit: SpendingAllowance,
+    /// National currency of the limit (e.g. aud,eur,gbp,usd,hkd)
+    #[clap(long, default_value = "usd")]
+    pub currency: NationalCurrency,
+}
+
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsSubWalletName {
+    /// Name of the sub-wallet
+    #[clap(index = 1)]
+    pub name: String,
+}
+
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsSubWalletRemove {
+    /// Name of the sub-wallet
+    #[clap(index = 1)]
+    pub name: String,
+    /// Forces the sub-wallet to be destroyed even if it has commodities in it
+    #[clap(short, long)]
+    pub force: bool,
+}
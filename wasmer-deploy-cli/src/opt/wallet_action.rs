@@ -1,11 +1,15 @@
 use clap::Parser;
 
 use super::OptsBalance;
+use super::OptsCoin;
 use super::OptsCreateWallet;
 use super::OptsDeposit;
+use super::OptsExportWallet;
 use super::OptsRemoveWallet;
+use super::OptsSubWallet;
 use super::OptsTransactionHistory;
 use super::OptsTransfer;
+use super::OptsWatchWallet;
 use super::OptsWithdraw;
 
 #[derive(Parser, Clone)]
@@ -22,6 +26,9 @@ pub enum OptWalletAction {
     /// Displays the transaction history
     #[clap()]
     History(OptsTransactionHistory),
+    /// Displays the current balance and then streams the events of the wallet as they happen
+    #[clap()]
+    Watch(OptsWatchWallet),
     /// Transfers a commodity (e.g. money) between two wallets
     #[clap()]
     Transfer(OptsTransfer),
@@ -31,4 +38,33 @@ pub enum OptWalletAction {
     /// Withdraws from the wallet to an external destination (e.g. PayPal Account)
     #[clap()]
     Withdraw(OptsWithdraw),
+    /// Manages the sub-wallets of this wallet (each can spend from it up to a limit)
+    #[clap()]
+    Sub(OptsSubWallet),
+    /// Performs maintenance on the coins held in this wallet
+    #[clap()]
+    Coin(OptsCoin),
+    /// Exports a snapshot of the chain that holds this wallet which can later be
+    /// read with the --offline and --snapshot options
+    #[clap()]
+    Export(OptsExportWallet),
+}
+
+impl OptWalletAction {
+    /// Name of the sub command as it is typed on the command line
+    pub fn command(&self) -> &'static str {
+        match self {
+            OptWalletAction::Create(_) => "create",
+            OptWalletAction::Remove(_) => "remove",
+            OptWalletAction::Balance(_) => "balance",
+            OptWalletAction::History(_) => "history",
+            OptWalletAction::Watch(_) => "watch",
+            OptWalletAction::Transfer(_) => "transfer",
+            OptWalletAction::Deposit(_) => "deposit",
+            OptWalletAction::Withdraw(_) => "withdraw",
+            OptWalletAction::Sub(_) => "sub",
+            OptWalletAction::Coin(_) => "coin",
+            OptWalletAction::Export(_) => "export",
+        }
+    }
 }
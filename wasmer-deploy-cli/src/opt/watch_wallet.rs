@@ -0,0 +1,18 @@
+use clap::Parser;
+
+use crate::model::Decimal;
+use crate::model::NationalCurrency;
+
+#[derive(Parser, Clone)]
+#[clap()]
+pub struct OptsWatchWallet {
+    /// Only shows the events that moved at least this amount (in or out of the wallet)
+    #[clap(long, value_name = "AMOUNT")]
+    pub min_amount: Option<Decimal>,
+    /// Only shows the events in this national currency (e.g. aud,eur,gbp,usd,hkd)
+    #[clap(long)]
+    pub currency: Option<NationalCurrency>,
+    /// When reading the balance the wallet is first reconciled - to prevent this happening then set this flag
+    #[clap(long)]
+    pub no_reconcile: bool,
+}
@@ -0,0 +1,194 @@
+use chrono::DateTime;
+use chrono::Utc;
+use num_traits::*;
+use serde::*;
+
+use crate::api::WalletSummary;
+use crate::model::*;
+use crate::request::CurrencyRatesResponse;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DenominationBalance {
+    pub denomination: Decimal,
+    pub quantity: usize,
+    pub total: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurrencyBalance {
+    pub currency: NationalCurrency,
+    pub total: Decimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub denominations: Option<Vec<DenominationBalance>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversionRate {
+    pub currency: NationalCurrency,
+    pub rate: Decimal,
+}
+
+/// Estimated value of the whole wallet in a single currency, this is not a
+/// quote as the rates move between the query and any actual exchange
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceEstimate {
+    pub currency: NationalCurrency,
+    pub total: Decimal,
+    pub rates: Vec<ConversionRate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rates_when: Option<DateTime<Utc>>,
+    /// Currencies that could not be converted (no rate was available)
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub excluded: Vec<NationalCurrency>,
+}
+
+impl BalanceEstimate {
+    pub fn new(
+        summary: &WalletSummary,
+        currency: NationalCurrency,
+        rates: Option<&CurrencyRatesResponse>,
+    ) -> BalanceEstimate {
+        let mut ret = BalanceEstimate {
+            currency,
+            total: Decimal::zero(),
+            rates: Vec::new(),
+            rates_when: rates.map(|a| a.when),
+            excluded: Vec::new(),
+        };
+        for held in summary.currencies.values() {
+            if held.currency == currency {
+                ret.total += held.total;
+                continue;
+            }
+            let rate = rates
+                .iter()
+                .flat_map(|a| a.rates.iter())
+                .filter(|a| a.currency == held.currency)
+                .map(|a| a.rate)
+                .next();
+            match rate {
+                Some(rate) => {
+                    ret.total += held.total * rate;
+                    ret.rates.push(ConversionRate {
+                        currency: held.currency,
+                        rate,
+                    });
+                }
+                None => ret.excluded.push(held.currency),
+            }
+        }
+        ret.total = ret.total.round_dp(currency.decimal_points().max(0) as u32);
+        ret
+    }
+}
+
+impl std::fmt::Display for BalanceEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Estimated Total (not a quote)")?;
+        writeln!(f, "{:8} ~{}", self.currency, self.total)?;
+        if self.rates.len() > 0 {
+            match &self.rates_when {
+                Some(when) => writeln!(f, "Rates as of {}", when.format("%Y-%m-%d %H:%M:%S UTC"))?,
+                None => writeln!(f, "Rates")?,
+            }
+            for rate in self.rates.iter() {
+                writeln!(f, "1 {:6} = {} {}", rate.currency, rate.rate, self.currency)?;
+            }
+        }
+        if self.excluded.len() > 0 {
+            let excluded = self
+                .excluded
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(f, "Excludes {} (no rate available)", excluded)?;
+        }
+        Ok(())
+    }
+}
+
+/// Balance of a wallet broken down by currency (and optionally by coin)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceOutput {
+    pub wallet: String,
+    pub currencies: Vec<CurrencyBalance>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub estimate: Option<BalanceEstimate>,
+}
+
+impl BalanceOutput {
+    pub fn new(wallet: String, summary: &WalletSummary, coins: bool) -> BalanceOutput {
+        BalanceOutput {
+            wallet,
+            currencies: summary
+                .currencies
+                .values()
+                .map(|currency| CurrencyBalance {
+                    currency: currency.currency,
+                    total: currency.total,
+                    denominations: match coins {
+                        true => Some(
+                            currency
+                                .denominations
+                                .values()
+                                .map(|d| DenominationBalance {
+                                    denomination: d.denomination,
+                                    quantity: d.cnt,
+                                    total: d.total,
+                                })
+                                .collect(),
+                        ),
+                        false => None,
+                    },
+                })
+                .collect(),
+            estimate: None,
+        }
+    }
+
+    fn short_form(&self) -> bool {
+        self.currencies.iter().all(|a| a.denominations.is_none())
+    }
+}
+
+impl std::fmt::Display for BalanceOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let short_form = self.short_form();
+        if short_form == true {
+            writeln!(f, "Currency Balance for {}", self.wallet)?;
+        }
+
+        let mut first = true;
+        for currency in self.currencies.iter() {
+            if short_form == false {
+                if first == false {
+                    writeln!(f, "")?;
+                }
+                writeln!(f, "Currency Balance")?;
+            }
+
+            writeln!(f, "{:8} {}", currency.currency, currency.total)?;
+
+            if let Some(denominations) = &currency.denominations {
+                writeln!(f, "")?;
+                writeln!(f, "Denomination Quantity Total ({})", currency.currency)?;
+                for denomination in denominations.iter() {
+                    writeln!(
+                        f,
+                        "{:12} {:8} {}",
+                        denomination.denomination, denomination.quantity, denomination.total
+                    )?;
+                }
+            }
+
+            first = false;
+        }
+
+        if let Some(estimate) = &self.estimate {
+            writeln!(f, "")?;
+            write!(f, "{}", estimate)?;
+        }
+        Ok(())
+    }
+}
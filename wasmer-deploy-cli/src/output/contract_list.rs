@@ -0,0 +1,46 @@
+use serde::*;
+
+use crate::api::ContractSummary;
+use crate::model::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractListEntry {
+    pub code: String,
+    pub reference_number: String,
+    pub status: ContractStatus,
+}
+
+/// List of all the contracts attached to a wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractListOutput {
+    pub contracts: Vec<ContractListEntry>,
+}
+
+impl ContractListOutput {
+    pub fn new(contracts: &Vec<ContractSummary>) -> ContractListOutput {
+        ContractListOutput {
+            contracts: contracts
+                .iter()
+                .map(|contract| ContractListEntry {
+                    code: contract.service.code.clone(),
+                    reference_number: contract.reference_number.clone(),
+                    status: contract.status.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for ContractListOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "|-----code-----|-------------reference------------|---status---")?;
+        for contract in self.contracts.iter() {
+            writeln!(
+                f,
+                "- {:12} - {:16} - {}",
+                contract.code, contract.reference_number, contract.status
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::prelude::*;
+use serde::*;
+
+use crate::model::*;
+
+use super::BalanceOutput;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub when: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<NationalCurrency>,
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<HistoricActivity>,
+}
+
+/// Transaction history of a wallet (optionally with its current balance)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<BalanceOutput>,
+    pub events: Vec<HistoryEntry>,
+}
+
+impl HistoryOutput {
+    pub fn new(balance: Option<BalanceOutput>, activities: Vec<HistoricActivity>, details: bool) -> HistoryOutput {
+        HistoryOutput {
+            balance,
+            events: activities
+                .into_iter()
+                .map(|event| {
+                    let (amount, currency) = match event.financial() {
+                        Some(a) => {
+                            let mut amount = a.amount;
+                            amount.rescale(a.currency.decimal_points() as u32);
+                            (Some(amount), Some(a.currency))
+                        }
+                        None => (None, None),
+                    };
+                    HistoryEntry {
+                        when: event.when().clone(),
+                        amount,
+                        currency,
+                        summary: event.summary(),
+                        details: match details {
+                            true => Some(event),
+                            false => None,
+                        },
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for HistoryOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(balance) = &self.balance {
+            balance.fmt(f)?;
+        }
+
+        let mut cur_year = 0i32;
+        let mut cur_month = 0u32;
+        let mut cur_day = 0u32;
+
+        for event in self.events.iter() {
+            if cur_year != event.when.year()
+                || cur_month != event.when.month()
+                || cur_day != event.when.day()
+            {
+                cur_year = event.when.year();
+                cur_month = event.when.month();
+                cur_day = event.when.day();
+
+                writeln!(f, "")?;
+                writeln!(f, "[{}]", event.when.date())?;
+            }
+
+            match (&event.amount, &event.currency) {
+                (Some(amount), Some(currency)) => {
+                    writeln!(f, "{:11} {:3}: {}", amount, currency, event.summary)?;
+                }
+                _ => {
+                    writeln!(f, "            ...: {}", event.summary)?;
+                }
+            }
+
+            if let Some(details) = &event.details {
+                match details.details() {
+                    Ok(a) => writeln!(f, "{}", a)?,
+                    Err(err) => writeln!(f, "details error - {}", err)?,
+                };
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use ate::prelude::QuotaStatus;
+use serde::*;
+
+use crate::model::*;
+
+use super::InstanceExportStats;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceDetailsExport {
+    pub url: String,
+    pub export: InstanceExport,
+}
+
+/// Details of a particular instance including its subnet and exports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceDetailsOutput {
+    pub instance: WalletInstance,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subnet: Option<InstanceSubnet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub exports: Vec<InstanceDetailsExport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stats: Vec<InstanceExportStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<QuotaStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<InstanceTemplateRef>,
+}
+
+impl std::fmt::Display for InstanceDetailsOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Instance")?;
+        writeln!(f, "{}", serde_json::to_string_pretty(&self.instance).unwrap())?;
+
+        if let Some(subnet) = &self.subnet {
+            writeln!(f, "{}", serde_json::to_string_pretty(subnet).unwrap())?;
+        }
+
+        if let Some(quota) = &self.quota {
+            writeln!(f, "Storage: {}", quota)?;
+        }
+
+        if let Some(template) = &self.template {
+            match template.version.len() {
+                0 => writeln!(f, "Template: {} ({})", template.name, template.source)?,
+                _ => writeln!(f, "Template: {}@{} ({})", template.name, template.version, template.source)?,
+            }
+        }
+
+        if let Some(id) = &self.id {
+            if self.exports.len() > 0 {
+                writeln!(f, "ID: {}", id)?;
+                writeln!(f, "")?;
+                writeln!(f, "Exports")?;
+                for export in self.exports.iter() {
+                    writeln!(f, "POST {}", export.url)?;
+                    writeln!(f, "{}", serde_json::to_string_pretty(&export.export).unwrap())?;
+                }
+            }
+        }
+
+        if self.stats.len() > 0 {
+            writeln!(f, "")?;
+            writeln!(f, "Stats")?;
+            for stats in self.stats.iter() {
+                writeln!(
+                    f,
+                    "{} invocations={} errors={} p50={}ms p95={}ms in={}B out={}B",
+                    stats.binary,
+                    stats.invocations,
+                    stats.errors,
+                    stats.p50_ms,
+                    stats.p95_ms,
+                    stats.bytes_in,
+                    stats.bytes_out
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use chrono::prelude::*;
+use serde::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceListExport {
+    pub binary: String,
+    pub distributed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceListEntry {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
+    pub exports: Vec<InstanceListExport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl InstanceListEntry {
+    pub fn new(name: String, when_created: u64, exports: Vec<InstanceListExport>) -> InstanceListEntry {
+        let secs = when_created / 1000;
+        let nsecs = (when_created % 1000) * 1000 * 1000;
+        let when = NaiveDateTime::from_timestamp(secs as i64, nsecs as u32);
+        InstanceListEntry {
+            name,
+            created: Some(DateTime::<Utc>::from_utc(when, Utc)),
+            exports,
+            error: None,
+        }
+    }
+
+    pub fn error(name: String, err: String) -> InstanceListEntry {
+        InstanceListEntry {
+            name,
+            created: None,
+            exports: Vec::new(),
+            error: Some(err),
+        }
+    }
+}
+
+/// List of all the instances attached to a wallet
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct InstanceListOutput {
+    pub instances: Vec<InstanceListEntry>,
+}
+
+impl std::fmt::Display for InstanceListOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "|-------name-------|-------created-------|-exports")?;
+        for instance in self.instances.iter() {
+            if let Some(err) = &instance.error {
+                writeln!(f, "- {:<16} - {:<19} - {}", instance.name, "error", err)?;
+                continue;
+            }
+
+            let mut exports = String::new();
+            for export in instance.exports.iter() {
+                if exports.len() > 0 { exports.push_str(","); }
+                exports.push_str(export.binary.as_str());
+                if export.distributed == false {
+                    exports.push_str("*");
+                }
+            }
+            let when = instance
+                .created
+                .map(|a| a.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default();
+            writeln!(f, "- {:<16} - {:<19} - {}", instance.name, when, exports)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use chrono::prelude::*;
+use serde::*;
+
+use crate::model::*;
+
+/// Summary of the invocations of an exported binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceExportStats {
+    pub binary: String,
+    pub invocations: u64,
+    pub errors: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub warm: u64,
+    pub cold: u64,
+}
+
+impl From<&InstanceExportMetrics> for InstanceExportStats {
+    fn from(metrics: &InstanceExportMetrics) -> InstanceExportStats {
+        InstanceExportStats {
+            binary: metrics.binary.clone(),
+            invocations: metrics.invocations,
+            errors: metrics.errors,
+            p50_ms: metrics.p50_ms(),
+            p95_ms: metrics.p95_ms(),
+            bytes_in: metrics.bytes_in,
+            bytes_out: metrics.bytes_out,
+            warm: metrics.warm,
+            cold: metrics.cold,
+        }
+    }
+}
+
+/// Invocation statistics of the exported binaries of an instance over
+/// the windows of metrics that were recorded
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct InstanceStatsOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+    pub exports: Vec<InstanceExportStats>,
+}
+
+impl InstanceStatsOutput {
+    /// Combines the windows (which may have been written by many hosts
+    /// or restarts of the same host) into a single summary per export
+    pub fn from_windows(windows: &[InstanceMetricsWindow]) -> InstanceStatsOutput {
+        let mut total: Vec<InstanceExportMetrics> = Vec::new();
+        for export in windows.iter().flat_map(|a| a.exports.iter()) {
+            match total
+                .iter_mut()
+                .find(|a| a.binary.eq_ignore_ascii_case(export.binary.as_str()))
+            {
+                Some(a) => a.merge(export),
+                None => total.push(export.clone()),
+            }
+        }
+        total.sort_by(|a, b| a.binary.cmp(&b.binary));
+
+        InstanceStatsOutput {
+            from: windows.iter().map(|a| a.start).min(),
+            to: windows.iter().map(|a| a.end()).max(),
+            exports: total.iter().map(|a| a.into()).collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for InstanceStatsOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let (Some(from), Some(to)) = (self.from.as_ref(), self.to.as_ref()) {
+            writeln!(
+                f,
+                "Between {} and {}",
+                from.format("%Y-%m-%d %H:%M"),
+                to.format("%Y-%m-%d %H:%M")
+            )?;
+        }
+        writeln!(
+            f,
+            "|-------binary-------|-invocations-|--errors--|--p50--|--p95--|--bytes in--|--bytes out-|--cold--|"
+        )?;
+        for export in self.exports.iter() {
+            writeln!(
+                f,
+                "| {:<18} | {:>11} | {:>8} | {:>3}ms | {:>3}ms | {:>10} | {:>10} | {:>6} |",
+                export.binary,
+                export.invocations,
+                export.errors,
+                export.p50_ms,
+                export.p95_ms,
+                export.bytes_in,
+                export.bytes_out,
+                export.cold
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+mod balance;
+mod contract_list;
+mod history;
+mod instance_details;
+mod instance_list;
+mod instance_stats;
+mod snapshot;
+mod sub_wallet_list;
+mod tests;
+mod watch;
+
+pub use balance::*;
+pub use contract_list::*;
+pub use history::*;
+pub use instance_details::*;
+pub use instance_list::*;
+pub use instance_stats::*;
+pub use snapshot::*;
+pub use sub_wallet_list::*;
+pub use watch::*;
+
+use serde::Serialize;
+
+use crate::opt::OutputFormat;
+
+/// Writes the result of a command to stdout in the requested format. Text
+/// output uses the human readable renderer of the result while JSON output
+/// emits the result as a single document.
+pub fn emit<T>(format: OutputFormat, result: &T)
+where
+    T: Serialize + std::fmt::Display,
+{
+    match format {
+        OutputFormat::Text => print!("{}", result),
+        OutputFormat::Json => match serde_json::to_string_pretty(result) {
+            Ok(a) => println!("{}", a),
+            Err(err) => emit_error(format, &err),
+        },
+    }
+}
+
+/// Writes one result of a stream of results to stdout, every result takes
+/// up a single line when JSON output is selected so that the stream can be
+/// consumed line by line
+pub fn emit_line<T>(format: OutputFormat, result: &T)
+where
+    T: Serialize + std::fmt::Display,
+{
+    match format {
+        OutputFormat::Text => println!("{}", result.to_string().trim_end()),
+        OutputFormat::Json => match serde_json::to_string(result) {
+            Ok(a) => println!("{}", a),
+            Err(err) => emit_error(format, &err),
+        },
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorOutput {
+    pub error: ErrorOutputInner,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorOutputInner {
+    pub message: String,
+}
+
+/// Writes an error to stderr - when JSON output is selected the error is
+/// wrapped in an envelope so that scripts can parse it
+pub fn emit_error<E>(format: OutputFormat, err: &E)
+where
+    E: std::fmt::Display + ?Sized,
+{
+    match format {
+        OutputFormat::Text => eprintln!("{}", err),
+        OutputFormat::Json => {
+            let envelope = ErrorOutput {
+                error: ErrorOutputInner {
+                    message: err.to_string(),
+                },
+            };
+            eprintln!(
+                "{}",
+                serde_json::to_string(&envelope)
+                    .unwrap_or_else(|_| "{\"error\":{}}".to_string())
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WarningOutput {
+    pub warning: ErrorOutputInner,
+}
+
+/// Writes a warning to stderr (in the same envelope as errors when JSON
+/// output is selected) without interrupting the command
+pub fn emit_warning<E>(format: OutputFormat, msg: &E)
+where
+    E: std::fmt::Display + ?Sized,
+{
+    match format {
+        OutputFormat::Text => eprintln!("warning: {}", msg),
+        OutputFormat::Json => {
+            let envelope = WarningOutput {
+                warning: ErrorOutputInner {
+                    message: msg.to_string(),
+                },
+            };
+            eprintln!(
+                "{}",
+                serde_json::to_string(&envelope)
+                    .unwrap_or_else(|_| "{\"warning\":{}}".to_string())
+            );
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use chrono::prelude::*;
+use serde::*;
+
+/// Result of a command that was read from an offline snapshot of a chain
+/// rather than from the live database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotOutput<T> {
+    /// Time of the newest event in the snapshot (the result reflects the
+    /// state of the chain at this point in time)
+    pub as_of: DateTime<Utc>,
+    /// Path of the archive that the snapshot was read from
+    pub snapshot: String,
+    #[serde(flatten)]
+    pub result: T,
+}
+
+impl<T> SnapshotOutput<T> {
+    pub fn new(as_of: DateTime<Utc>, snapshot: String, result: T) -> SnapshotOutput<T> {
+        SnapshotOutput {
+            as_of,
+            snapshot,
+            result,
+        }
+    }
+}
+
+impl<T> std::fmt::Display for SnapshotOutput<T>
+where
+    T: std::fmt::Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "As of {} (offline snapshot {})",
+            self.as_of.format("%Y-%m-%d %H:%M:%S UTC"),
+            self.snapshot
+        )?;
+        writeln!(f, "")?;
+        write!(f, "{}", self.result)
+    }
+}
+
+/// Summary of a snapshot that was exported
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotExportOutput {
+    pub snapshot: String,
+    pub chain: String,
+    pub events: u64,
+    pub as_of: DateTime<Utc>,
+}
+
+impl std::fmt::Display for SnapshotExportOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Exported {} events of {} as of {} to {}",
+            self.events,
+            self.chain,
+            self.as_of.format("%Y-%m-%d %H:%M:%S UTC"),
+            self.snapshot
+        )
+    }
+}
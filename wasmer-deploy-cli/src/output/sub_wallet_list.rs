@@ -0,0 +1,55 @@
+use chrono::Utc;
+use serde::*;
+
+use crate::model::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubWalletListEntry {
+    pub name: String,
+    pub limit: SpendingLimit,
+    pub consumed: Decimal,
+    pub remaining: Decimal,
+    pub frozen: bool,
+}
+
+/// List of all the sub-wallets of a wallet and how much of their limit
+/// they have used in the current period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubWalletListOutput {
+    pub sub_wallets: Vec<SubWalletListEntry>,
+}
+
+impl SubWalletListOutput {
+    pub fn new(sub_wallets: &Vec<SubWallet>) -> SubWalletListOutput {
+        let now = Utc::now();
+        SubWalletListOutput {
+            sub_wallets: sub_wallets
+                .iter()
+                .map(|sub| SubWalletListEntry {
+                    name: sub.name.clone(),
+                    limit: sub.limit.clone(),
+                    consumed: sub.consumed(now),
+                    remaining: sub.remaining(now),
+                    frozen: sub.frozen,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for SubWalletListOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "|-----name-----|----------limit----------|--remaining--|---status---")?;
+        for sub in self.sub_wallets.iter() {
+            writeln!(
+                f,
+                "- {:12} - {:23} - {:11} - {}",
+                sub.name,
+                sub.limit.to_string(),
+                sub.remaining.to_string(),
+                if sub.frozen { "frozen" } else { "active" }
+            )?;
+        }
+        Ok(())
+    }
+}
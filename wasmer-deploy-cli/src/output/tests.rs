@@ -0,0 +1,308 @@
+#![cfg(test)]
+use chrono::prelude::*;
+use std::str::FromStr;
+
+use crate::model::*;
+
+use super::*;
+
+fn snapshot<T>(result: &T) -> String
+where
+    T: serde::Serialize,
+{
+    serde_json::to_string(result).unwrap()
+}
+
+fn test_balance() -> BalanceOutput {
+    BalanceOutput {
+        wallet: "1234".to_string(),
+        currencies: vec![CurrencyBalance {
+            currency: NationalCurrency::USD,
+            total: Decimal::from_str("12.50").unwrap(),
+            denominations: Some(vec![DenominationBalance {
+                denomination: Decimal::from_str("2.50").unwrap(),
+                quantity: 5,
+                total: Decimal::from_str("12.50").unwrap(),
+            }]),
+        }],
+        estimate: None,
+    }
+}
+
+#[test]
+fn test_output_balance() {
+    assert_eq!(
+        snapshot(&test_balance()),
+        r#"{"wallet":"1234","currencies":[{"currency":"USD","total":"12.50","denominations":[{"denomination":"2.50","quantity":5,"total":"12.50"}]}]}"#
+    );
+}
+
+#[test]
+fn test_output_balance_estimate() {
+    let mut summary = crate::api::WalletSummary::default();
+    for (currency, total) in [
+        (NationalCurrency::USD, "10.00"),
+        (NationalCurrency::EUR, "20.00"),
+        (NationalCurrency::GBP, "5.00"),
+    ] {
+        summary.currencies.insert(
+            currency,
+            crate::api::CurrencySummary {
+                currency,
+                total: Decimal::from_str(total).unwrap(),
+                denominations: Default::default(),
+            },
+        );
+    }
+    let rates = crate::request::CurrencyRatesResponse {
+        target: NationalCurrency::USD,
+        rates: vec![crate::request::CurrencyRate {
+            currency: NationalCurrency::EUR,
+            rate: Decimal::from_str("1.105").unwrap(),
+        }],
+        when: Utc.ymd(2021, 3, 4).and_hms(5, 6, 7),
+    };
+
+    let estimate = BalanceEstimate::new(&summary, NationalCurrency::USD, Some(&rates));
+    assert_eq!(estimate.total, Decimal::from_str("32.10").unwrap());
+    assert_eq!(estimate.excluded, vec![NationalCurrency::GBP]);
+    assert_eq!(
+        snapshot(&estimate),
+        r#"{"currency":"USD","total":"32.10","rates":[{"currency":"EUR","rate":"1.105"}],"rates_when":"2021-03-04T05:06:07Z","excluded":["GBP"]}"#
+    );
+}
+
+#[test]
+fn test_output_snapshot() {
+    let result = SnapshotOutput::new(
+        Utc.ymd(2021, 3, 4).and_hms(5, 6, 7),
+        "wallet.snapshot".to_string(),
+        test_balance(),
+    );
+    assert_eq!(
+        snapshot(&result),
+        r#"{"as_of":"2021-03-04T05:06:07Z","snapshot":"wallet.snapshot","wallet":"1234","currencies":[{"currency":"USD","total":"12.50","denominations":[{"denomination":"2.50","quantity":5,"total":"12.50"}]}]}"#
+    );
+    assert!(result
+        .to_string()
+        .starts_with("As of 2021-03-04 05:06:07 UTC (offline snapshot wallet.snapshot)\n"));
+}
+
+#[test]
+fn test_output_contract_list() {
+    let result = ContractListOutput {
+        contracts: vec![ContractListEntry {
+            code: "compute".to_string(),
+            reference_number: "REF-1".to_string(),
+            status: ContractStatus::Nominal {
+                throttle: ThrottleTriggers::default(),
+            },
+        }],
+    };
+    assert_eq!(
+        snapshot(&result),
+        r#"{"contracts":[{"code":"compute","reference_number":"REF-1","status":{"Nominal":{"throttle":{"download_per_second":null,"upload_per_second":null,"delete_only_threshold":null}}}}]}"#
+    );
+}
+
+#[test]
+fn test_output_history() {
+    let result = HistoryOutput {
+        balance: None,
+        events: vec![HistoryEntry {
+            when: Utc.ymd(2021, 3, 4).and_hms(5, 6, 7),
+            amount: Some(Decimal::from_str("10.00").unwrap()),
+            currency: Some(NationalCurrency::USD),
+            summary: "Deposit".to_string(),
+            details: None,
+        }],
+    };
+    assert_eq!(
+        snapshot(&result),
+        r#"{"events":[{"when":"2021-03-04T05:06:07Z","amount":"10.00","currency":"USD","summary":"Deposit"}]}"#
+    );
+}
+
+#[test]
+fn test_output_instance_list() {
+    let result = InstanceListOutput {
+        instances: vec![
+            InstanceListEntry::new(
+                "myinst".to_string(),
+                1614834367000,
+                vec![InstanceListExport {
+                    binary: "sh".to_string(),
+                    distributed: true,
+                }],
+            ),
+            InstanceListEntry::error("broken".to_string(), "access denied".to_string()),
+        ],
+    };
+    assert_eq!(
+        snapshot(&result),
+        r#"{"instances":[{"name":"myinst","created":"2021-03-04T05:06:07Z","exports":[{"binary":"sh","distributed":true}]},{"name":"broken","exports":[],"error":"access denied"}]}"#
+    );
+}
+
+#[test]
+fn test_output_instance_details() {
+    let mut result = InstanceDetailsOutput {
+        instance: WalletInstance {
+            name: "myinst".to_string(),
+            id: 1,
+            chain: ate::prelude::ChainKey::from("me/myinst".to_string()),
+        },
+        subnet: None,
+        id: Some("01".to_string()),
+        exports: vec![InstanceDetailsExport {
+            url: "https://wasmer.sh/inst/me/myinst/sh/".to_string(),
+            export: InstanceExport {
+                access_token: "token".to_string(),
+                binary: "sh".to_string(),
+                distributed: true,
+                http: true,
+                https: true,
+                bus: false,
+                pinned: None,
+            },
+        }],
+        stats: Vec::new(),
+        quota: None,
+        template: None,
+    };
+    assert_eq!(
+        snapshot(&result),
+        r#"{"instance":{"name":"myinst","id":1,"chain":{"name":"me/myinst"}},"id":"01","exports":[{"url":"https://wasmer.sh/inst/me/myinst/sh/","export":{"access_token":"token","binary":"sh","distributed":true,"http":true,"https":true,"bus":false,"pinned":null}}]}"#
+    );
+
+    result.quota = Some(ate::prelude::QuotaStatus {
+        chain: ate::prelude::ChainKey::from("me/myinst".to_string()),
+        used: 800,
+        limit: Some(1000),
+    });
+    assert!(result.to_string().contains("Storage: 800/1000 bytes (80%)\n"));
+
+    result.template = Some(InstanceTemplateRef {
+        name: "wordpress".to_string(),
+        version: "1.0".to_string(),
+        source: "wasmer.sh/templates".to_string(),
+    });
+    assert!(snapshot(&result).ends_with(r#""template":{"name":"wordpress","version":"1.0","source":"wasmer.sh/templates"}}"#));
+    assert!(result.to_string().contains("Template: wordpress@1.0 (wasmer.sh/templates)\n"));
+}
+
+#[test]
+fn test_output_instance_log() {
+    let mut log = InstanceLog::new("sh", "127.0.0.1:4000".to_string());
+    log.when = Utc.ymd(2021, 3, 4).and_hms(5, 6, 7);
+    log.duration_ms = 12;
+    log.status = InstanceLogStatus::Exited(1);
+    log.append_stderr(b"oops\n");
+    assert_eq!(
+        snapshot(&log),
+        r#"{"when":"2021-03-04T05:06:07Z","binary":"sh","caller":"127.0.0.1:4000","duration_ms":12,"status":{"Exited":1},"stdout":"","stderr":"oops\n","truncated":false}"#
+    );
+    assert_eq!(
+        log.to_string(),
+        "[2021-03-04 05:06:07.000] sh caller=127.0.0.1:4000 status=exit(1) duration=12ms\n  stderr: oops\n"
+    );
+
+    log.append_stdout(&vec![b'a'; INSTANCE_LOG_MAX_OUTPUT + 10][..]);
+    assert_eq!(log.stdout.len(), INSTANCE_LOG_MAX_OUTPUT);
+    assert!(log.truncated);
+}
+
+#[test]
+fn test_instance_log_evictions() {
+    assert_eq!(instance_log_evictions(&[10, 10, 10], 10), 0);
+
+    let full = vec![1usize; INSTANCE_LOG_MAX_RECORDS];
+    assert_eq!(instance_log_evictions(&full[..], 1), 1);
+
+    let large = vec![INSTANCE_LOG_MAX_BYTES / 2, 10, 10];
+    assert_eq!(instance_log_evictions(&large[..], INSTANCE_LOG_MAX_BYTES / 2), 1);
+}
+
+#[test]
+fn test_output_instance_stats() {
+    let start = Utc.ymd(2021, 3, 4).and_hms(5, 6, 7);
+    let mut first = InstanceMetricsWindow::new(start);
+    assert_eq!(first.start, Utc.ymd(2021, 3, 4).and_hms(5, 5, 0));
+    for duration in [1, 2, 3, 100] {
+        first.export_mut("sh").record(duration, true, 10, 20);
+    }
+    let mut second = InstanceMetricsWindow::new(first.end());
+    second.export_mut("SH").record(3, false, 5, 0);
+    second.export_mut("echo").record(0, true, 0, 0);
+    first.export_mut("sh").record_load(false);
+    second.export_mut("sh").record_load(true);
+    second.export_mut("echo").record_load(true);
+
+    let result = InstanceStatsOutput::from_windows(&[first, second]);
+    assert_eq!(
+        snapshot(&result),
+        r#"{"from":"2021-03-04T05:05:00Z","to":"2021-03-04T05:15:00Z","exports":[{"binary":"echo","invocations":1,"errors":0,"p50_ms":1,"p95_ms":1,"bytes_in":0,"bytes_out":0,"warm":1,"cold":0},{"binary":"sh","invocations":5,"errors":1,"p50_ms":4,"p95_ms":128,"bytes_in":45,"bytes_out":80,"warm":1,"cold":1}]}"#
+    );
+}
+
+#[test]
+fn test_instance_metrics_evictions() {
+    assert_eq!(instance_metrics_evictions(0), 0);
+    assert_eq!(instance_metrics_evictions(INSTANCE_METRICS_MAX_WINDOWS - 1), 0);
+    assert_eq!(instance_metrics_evictions(INSTANCE_METRICS_MAX_WINDOWS), 1);
+}
+
+#[test]
+fn test_sub_wallet_limit() {
+    let allowance = SpendingAllowance::from_str("100/month").unwrap();
+    assert_eq!(allowance.period, ChargeFrequency::PerMonth);
+    assert!(SpendingAllowance::from_str("100").is_err());
+    assert!(SpendingAllowance::from_str("0/day").is_err());
+    assert!(SpendingAllowance::from_str("10/fortnight").is_err());
+
+    let now = Utc::now();
+    let mut sub = SubWallet {
+        name: "ci".to_string(),
+        wallet: ate::prelude::PrimaryKey::from(1u64),
+        limit: SpendingLimit {
+            amount: allowance.amount,
+            currency: NationalCurrency::USD,
+            period: allowance.period,
+        },
+        spent: Vec::new(),
+        frozen: false,
+    };
+    sub.record_spend(now - chrono::Duration::days(40), Decimal::from_str("90").unwrap());
+    sub.record_spend(now, Decimal::from_str("30").unwrap());
+    assert_eq!(sub.spent.len(), 1);
+    assert_eq!(sub.consumed(now), Decimal::from_str("30").unwrap());
+    assert_eq!(sub.remaining(now), Decimal::from_str("70").unwrap());
+
+    let result = SubWalletListOutput::new(&vec![sub]);
+    assert_eq!(result.sub_wallets[0].remaining, Decimal::from_str("70").unwrap());
+    assert!(result.to_string().contains("active"));
+}
+
+#[test]
+fn test_output_watch_event() {
+    let activity = HistoricActivity::TransferIn(activities::FundsTransferred {
+        when: Utc.ymd(2021, 3, 4).and_hms(5, 6, 7),
+        by: "joe@example.com".to_string(),
+        amount: Decimal::from_str("10").unwrap(),
+        currency: NationalCurrency::USD,
+        from: "joe@example.com/default".to_string(),
+        to: "jane@example.com/default".to_string(),
+    });
+    let event = WatchEvent::new(&activity, false);
+    assert_eq!(
+        snapshot(&event),
+        r#"{"when":"2021-03-04T05:06:07Z","kind":"transfer-in","amount":"10.00","currency":"USD","summary":"Transfer from joe@example.com/default"}"#
+    );
+    assert_eq!(
+        event.to_string(),
+        "[2021-03-04 05:06:07]       10.00 USD: Transfer from joe@example.com/default"
+    );
+
+    let event = WatchEvent::new(&activity, true);
+    assert!(snapshot(&event).ends_with(r#","replayed":true}"#));
+}
@@ -0,0 +1,63 @@
+use chrono::prelude::*;
+use serde::*;
+
+use crate::model::*;
+
+/// Activity that was recorded in a wallet while it was being watched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchEvent {
+    pub when: DateTime<Utc>,
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<NationalCurrency>,
+    pub summary: String,
+    /// Set when the event was recorded while the watch was disconnected
+    /// (it is only seen now that the watch caught up again)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub replayed: bool,
+}
+
+fn is_false(val: &bool) -> bool {
+    *val == false
+}
+
+impl WatchEvent {
+    pub fn new(activity: &HistoricActivity, replayed: bool) -> WatchEvent {
+        let (amount, currency) = match activity.financial() {
+            Some(a) => {
+                let mut amount = a.amount;
+                amount.rescale(a.currency.decimal_points() as u32);
+                (Some(amount), Some(a.currency))
+            }
+            None => (None, None),
+        };
+        WatchEvent {
+            when: activity.when().clone(),
+            kind: activity.kind().to_string(),
+            amount,
+            currency,
+            summary: activity.summary(),
+            replayed,
+        }
+    }
+}
+
+impl std::fmt::Display for WatchEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] ", self.when.format("%Y-%m-%d %H:%M:%S"))?;
+        match (&self.amount, &self.currency) {
+            (Some(amount), Some(currency)) => {
+                write!(f, "{:11} {:3}: {}", amount, currency, self.summary)?;
+            }
+            _ => {
+                write!(f, "            ...: {}", self.summary)?;
+            }
+        }
+        if self.replayed {
+            write!(f, " (replayed)")?;
+        }
+        Ok(())
+    }
+}
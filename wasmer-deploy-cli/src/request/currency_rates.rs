@@ -0,0 +1,61 @@
+use chrono::DateTime;
+use chrono::Utc;
+use serde::*;
+
+use crate::model::*;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CurrencyRatesRequest {
+    pub currencies: Vec<NationalCurrency>,
+    pub target: NationalCurrency,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CurrencyRate {
+    pub currency: NationalCurrency,
+    /// Amount of the target currency that one unit of this currency is worth
+    pub rate: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CurrencyRatesResponse {
+    pub target: NationalCurrency,
+    pub rates: Vec<CurrencyRate>,
+    pub when: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum CurrencyRatesFailed {
+    UnsupportedCurrency(NationalCurrency),
+    Forbidden,
+    InternalError(u16),
+}
+
+impl<E> From<E> for CurrencyRatesFailed
+where
+    E: std::error::Error + Sized,
+{
+    fn from(err: E) -> Self {
+        CurrencyRatesFailed::InternalError(ate::utils::obscure_error(err))
+    }
+}
+
+impl std::fmt::Display for CurrencyRatesFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CurrencyRatesFailed::UnsupportedCurrency(currency) => {
+                write!(f, "There is no exchange rate available for this currency ({})", currency)
+            }
+            CurrencyRatesFailed::Forbidden => {
+                write!(f, "This operation is forbidden")
+            }
+            CurrencyRatesFailed::InternalError(a) => {
+                write!(
+                    f,
+                    "An internal error occured while processing the currency rates request (code={})",
+                    a
+                )
+            }
+        }
+    }
+}
@@ -6,6 +6,7 @@ mod coin_proof;
 mod coin_rotate;
 mod contract_action;
 mod contract_create;
+mod currency_rates;
 mod deposit;
 mod service_find;
 mod withdraw;
@@ -19,6 +20,7 @@ pub use coin_proof::*;
 pub use coin_rotate::*;
 pub use contract_action::*;
 pub use contract_create::*;
+pub use currency_rates::*;
 pub use deposit::*;
 pub use service_find::*;
 pub use withdraw::*;
\ No newline at end of file
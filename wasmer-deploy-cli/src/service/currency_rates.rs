@@ -0,0 +1,104 @@
+use chrono::DateTime;
+use chrono::Utc;
+use num_traits::CheckedDiv;
+use num_traits::One;
+use num_traits::Zero;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::RwLock;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use ate::prelude::*;
+
+use crate::model::*;
+use crate::request::*;
+
+/// Answers the currency rates requests that wallets make to estimate what
+/// their balances are worth, the rates are supplied by the operator of the
+/// service (e.g. from a feed it polls) as the worth of one unit of each
+/// currency in a common base currency
+pub struct CurrencyRatesService {
+    base: NationalCurrency,
+    table: RwLock<CurrencyRateTable>,
+}
+
+struct CurrencyRateTable {
+    rates: HashMap<NationalCurrency, Decimal>,
+    when: DateTime<Utc>,
+}
+
+impl CurrencyRatesService {
+    pub fn new(base: NationalCurrency) -> Arc<CurrencyRatesService> {
+        Arc::new(CurrencyRatesService {
+            base,
+            table: RwLock::new(CurrencyRateTable {
+                rates: HashMap::default(),
+                when: Utc::now(),
+            }),
+        })
+    }
+
+    /// Replaces the rates with a fresh set taken at a point in time, rates
+    /// that are not positive are ignored
+    pub fn update(
+        &self,
+        rates: impl IntoIterator<Item = (NationalCurrency, Decimal)>,
+        when: DateTime<Utc>,
+    ) {
+        let rates = rates
+            .into_iter()
+            .filter(|(_, rate)| *rate > Decimal::zero())
+            .collect();
+        let mut guard = self.table.write().unwrap();
+        guard.rates = rates;
+        guard.when = when;
+    }
+
+    /// Worth of one unit of a currency in the base currency
+    fn base_rate(&self, table: &CurrencyRateTable, currency: NationalCurrency) -> Option<Decimal> {
+        match currency == self.base {
+            true => Some(Decimal::one()),
+            false => table.rates.get(&currency).cloned(),
+        }
+    }
+
+    pub async fn process_currency_rates(
+        self: Arc<Self>,
+        request: CurrencyRatesRequest,
+    ) -> Result<CurrencyRatesResponse, CurrencyRatesFailed> {
+        debug!("currency rates: {} currencies to {}", request.currencies.len(), request.target);
+
+        let table = self.table.read().unwrap();
+        let target = self
+            .base_rate(&table, request.target)
+            .ok_or_else(|| CurrencyRatesFailed::UnsupportedCurrency(request.target))?;
+
+        let mut rates = Vec::new();
+        for currency in request.currencies {
+            let rate = match currency == request.target {
+                true => Decimal::one(),
+                false => self
+                    .base_rate(&table, currency)
+                    .and_then(|a| a.checked_div(&target))
+                    .ok_or_else(|| CurrencyRatesFailed::UnsupportedCurrency(currency))?,
+            };
+            rates.push(CurrencyRate { currency, rate });
+        }
+
+        Ok(CurrencyRatesResponse {
+            target: request.target,
+            rates,
+            when: table.when,
+        })
+    }
+}
+
+/// Registers the currency rates service on a command chain
+pub fn service_currency_rates_handlers(
+    cmd_session: &AteSessionUser,
+    service: Arc<CurrencyRatesService>,
+    chain: &Arc<Chain>,
+) {
+    chain.add_service(cmd_session, service, CurrencyRatesService::process_currency_rates);
+}
@@ -0,0 +1,4 @@
+mod currency_rates;
+mod tests;
+
+pub use currency_rates::*;
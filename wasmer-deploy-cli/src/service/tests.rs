@@ -0,0 +1,69 @@
+#![cfg(test)]
+use chrono::prelude::*;
+use std::str::FromStr;
+
+use super::*;
+use crate::model::*;
+use crate::request::*;
+
+fn test_rates() -> std::sync::Arc<CurrencyRatesService> {
+    let service = CurrencyRatesService::new(NationalCurrency::USD);
+    service.update(
+        vec![
+            (NationalCurrency::EUR, Decimal::from_str("1.25").unwrap()),
+            (NationalCurrency::AUD, Decimal::from_str("0.5").unwrap()),
+            (NationalCurrency::GBP, Decimal::from_str("0").unwrap()),
+        ],
+        Utc.timestamp(1_600_000_000, 0),
+    );
+    service
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_currency_rates_converts() {
+    let service = test_rates();
+    let ret = service
+        .process_currency_rates(CurrencyRatesRequest {
+            currencies: vec![NationalCurrency::EUR, NationalCurrency::USD, NationalCurrency::AUD],
+            target: NationalCurrency::AUD,
+        })
+        .await
+        .unwrap();
+    assert_eq!(ret.target, NationalCurrency::AUD);
+    assert_eq!(ret.when, Utc.timestamp(1_600_000_000, 0));
+    let rates = ret
+        .rates
+        .iter()
+        .map(|a| (a.currency, a.rate.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rates,
+        vec![
+            (NationalCurrency::EUR, "2.5".to_string()),
+            (NationalCurrency::USD, "2".to_string()),
+            (NationalCurrency::AUD, "1".to_string()),
+        ]
+    );
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_currency_rates_unsupported() {
+    let service = test_rates();
+
+    // Currencies without a (positive) rate can not be converted from or to
+    for (currency, target) in [
+        (NationalCurrency::GBP, NationalCurrency::USD),
+        (NationalCurrency::EUR, NationalCurrency::CNY),
+    ] {
+        let ret = service
+            .clone()
+            .process_currency_rates(CurrencyRatesRequest {
+                currencies: vec![currency],
+                target,
+            })
+            .await;
+        assert!(matches!(ret, Err(CurrencyRatesFailed::UnsupportedCurrency(_))));
+    }
+}
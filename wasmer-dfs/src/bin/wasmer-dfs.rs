@@ -11,6 +11,7 @@ use wasmer_auth::helper::*;
 use wasmer_deploy_cli::cmd::{
     main_opts_contract, main_opts_login, main_opts_logout, main_opts_service, main_opts_wallet,
 };
+use wasmer_deploy_cli::opt::OutputFormat;
 
 use clap::Parser;
 
@@ -65,10 +66,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             main_opts_group(opts_group, None, token_path, opts.auth, "Domain name").await?;
         }
         SubCommand::Wallet(opts_wallet) => {
-            main_opts_wallet(opts_wallet.source, opts.token_path, opts.auth).await?
+            main_opts_wallet(opts_wallet.source, opts.token_path, opts.auth, OutputFormat::Text).await?
         }
         SubCommand::Contract(opts_contract) => {
-            main_opts_contract(opts_contract.purpose, opts.token_path, opts.auth).await?;
+            main_opts_contract(opts_contract.purpose, opts.token_path, opts.auth, OutputFormat::Text).await?;
         }
         SubCommand::Service(opts_service) => {
             main_opts_service(opts_service.purpose, opts.token_path, opts.auth).await?;
@@ -76,7 +77,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         SubCommand::Login(opts_login) => {
             main_opts_login(opts_login, opts.token_path, opts.auth).await?
         }
-        SubCommand::Logout(opts_logout) => main_opts_logout(opts_logout, opts.token_path).await?,
+        SubCommand::Logout(opts_logout) => {
+            main_opts_logout(opts_logout, opts.token_path, opts.auth).await?
+        }
         SubCommand::Mount(mount) => {
             // Derive the group from the mount address
             let mut group = None;
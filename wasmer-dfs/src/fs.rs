@@ -40,6 +40,7 @@ where
 {
     accessor: FileAccessor,
     umask: u32,
+    chunking: Option<u32>,
 }
 
 pub fn conv_attr(attr: &FileAttr) -> fuse::FileAttr {
@@ -83,6 +84,7 @@ impl AteFS {
         no_auth: bool,
         impersonate_uid: bool,
         umask: u32,
+        chunking: Option<u32>,
     ) -> AteFS {
         AteFS {
             accessor: FileAccessor::new(
@@ -96,6 +98,7 @@ impl AteFS {
             )
             .await,
             umask,
+            chunking,
         }
     }
 
@@ -143,11 +146,19 @@ impl fuse::Filesystem for AteFS {
     async fn init(&self, req: fuse::Request) -> fuse::Result<()> {
         let req = req_ctx(&req);
         conv_result(self.accessor.init(&req).await)?;
+        if let Some(avg_size) = self.chunking {
+            conv_result(self.accessor.enable_chunking(avg_size).await)?;
+        }
         Ok(())
     }
 
     async fn destroy(&self, req: fuse::Request) {
         let _req = req_ctx(&req);
+
+        let stats = &self.accessor.chunk_stats;
+        if stats.chunks.load(std::sync::atomic::Ordering::Relaxed) > 0 {
+            info!("chunk dedupe ratio: {:.2}", stats.dedupe_ratio());
+        }
     }
 
     async fn getattr(
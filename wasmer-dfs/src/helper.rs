@@ -191,6 +191,7 @@ pub async fn main_mount(
             no_auth,
             mount.impersonate_uid,
             mount.umask,
+            mount.chunking,
         )
         .await,
         mount.mount_path,
@@ -167,6 +167,11 @@ pub struct OptsMount {
     /// For files and directories that the authenticated user owns, translate the UID and GID to the local machine ids instead of the global ones.
     #[clap(short, long)]
     pub impersonate_uid: bool,
+    /// Enables content-defined chunking on this file-system using the supplied average chunk size
+    /// in bytes (e.g. 65536) so that data which is identical across files is only stored once. Once
+    /// enabled it stays enabled for the file-system while files written beforehand remain readable.
+    #[clap(long)]
+    pub chunking: Option<u32>,
     /// Configure the log file for <raw>, <barebone>, <speed>, <compatibility>, <balanced> or <security>
     #[clap(long, default_value = "speed")]
     pub configured_for: ate::conf::ConfiguredFor,
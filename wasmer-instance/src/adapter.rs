@@ -105,6 +105,17 @@ for FileAccessorAdapter
         let mut guard = self.ctx.lock().unwrap();
         guard.replace(ctx.clone());
     }
+
+    fn set_retention(&self, path: &Path, max_age: Option<std::time::Duration>) -> Option<Result<()>> {
+        let path = path.to_string_lossy().to_string();
+        let inner = self.inner.clone();
+
+        let ret = self.block_on(async move {
+            inner.set_retention(path, max_age).await
+        })
+        .and_then(|ret| ret.map_err(conv_fs_err));
+        Some(ret)
+    }
 }
 
 impl FileSystem
@@ -437,6 +448,9 @@ fn conv_fs_err(err: wasmer_bus_fuse::api::FsError) -> FsError {
         E::WouldBlock => FsError::WouldBlock,
         E::WriteZero => FsError::WriteZero,
         E::DirectoryNotEmpty => FsError::DirectoryNotEmpty,
+        // the virtual file system reports a full device as a write of zero
+        // bytes (which the guest sees as ENOSPC)
+        E::StorageFull => FsError::WriteZero,
         E::UnknownError => FsError::UnknownError,
     }
 }
\ No newline at end of file
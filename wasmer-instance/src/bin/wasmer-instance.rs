@@ -2,6 +2,7 @@
 use ate::mesh::MeshHashTable;
 use ate::utils::load_node_list;
 use wasmer_instance::server::Server;
+use wasmer_instance::prefetch::Prefetcher;
 use tokio::sync::watch;
 #[allow(unused_imports, dead_code)]
 use tracing::{info, error, debug, trace, warn};
@@ -13,6 +14,7 @@ use tokio::select;
 use tokio::runtime::Builder;
 use wasmer_ssh::wasmer_os;
 use wasmer_os::bin_factory::CachedCompiledModules;
+use wasmer_os::fs::SessionTmp;
 use wasmer_ssh::native_files::NativeFileType;
 
 use ate::comms::StreamRouter;
@@ -37,7 +39,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>>
     let opts: Opts = Opts::parse();
     //let opts = main_debug();
     ate::log_init(opts.verbose, opts.debug);
-        
+
+    let config = opts.config.map(|a| shellexpand::tilde(&a).to_string());
+    if opts.check_config {
+        match ConfDocument::check_file(config.unwrap_or_default()) {
+            true => return Ok(()),
+            false => std::process::exit(1),
+        }
+    }
+    let subcmd = match opts.subcmd {
+        Some(a) => a,
+        None => {
+            eprintln!("a subcommand is required (see --help)");
+            std::process::exit(2);
+        }
+    };
+
     let cert: PrivateEncryptKey = match try_load_key(opts.cert_path.clone()) {
         Some(a) => a,
         None => {
@@ -47,12 +64,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>>
     };
     ate::mesh::add_global_certificate(&cert.hash());
 
-    let mut conf = AteConfig::default();
-    conf.dns_sec = opts.dns_sec.clone();
-    conf.dns_server = opts.dns_server.clone();
+    let mut conf = match config {
+        Some(path) => ConfAte::from_file(path)?,
+        None => {
+            let mut conf = AteConfig::default();
+            conf.dns_sec = opts.dns_sec.clone();
+            conf.dns_server = opts.dns_server.clone();
+            conf
+        }
+    };
     
+    let wire_encryption = opts.wire_encryption;
     let ret = runtime.clone().block_on(async move {
-        match opts.subcmd {
+        match subcmd {
             SubCommand::Run(solo) => {
                 conf.nodes = load_node_list(solo.nodes_list);
 
@@ -64,7 +88,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>>
 
                 let mut cfg_mesh = ConfMesh::skeleton(&conf, domain, port, solo.node_id).await?;
                 cfg_mesh.wire_protocol = protocol;
-                cfg_mesh.wire_encryption = Some(opts.wire_encryption);
+                cfg_mesh.wire_encryption = Some(wire_encryption);
                 cfg_mesh.listen_certificate = Some(cert);
 
                 let table = MeshHashTable::new(&cfg_mesh);
@@ -103,6 +127,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>>
                     solo.compiler.clone(),
                     compiled_modules.clone(),
                     ttl,
+                    Prefetcher::new(solo.no_prefetch == false, solo.prefetch_concurrency),
+                    match solo.tmp_persistent {
+                        true => SessionTmp::Persistent {
+                            ttl: solo.tmp_ttl.map(std::time::Duration::from_secs),
+                        },
+                        false => SessionTmp::Ephemeral {
+                            max_size: solo.tmp_max_size,
+                        },
+                    },
                 ).await?;
 
                 let mut router = ate::comms::StreamRouter::new(
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use ate::prelude::*;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use wasmer_deploy_cli::model::InstanceLog;
+use wasmer_deploy_cli::model::InstanceLogStatus;
+use wasmer_deploy_cli::model::ServiceInstance;
+use wasmer_deploy_cli::model::INSTANCE_ROOT_ID;
+use wasmer_deploy_cli::model::INSTANCE_LOG_COLLECTION_ID;
+use wasmer_deploy_cli::model::instance_log_evictions;
+use wasmer_ssh::wasmer_os;
+use wasmer_os::api::System;
+use wasmer_os::api::SystemAbiExt;
+use wasmer_os::fd::Fd;
+use wasmer_os::fd::FdMsg;
+use wasmer_os::pipe::pipe_out;
+
+use crate::invocation_metrics::InvocationMeter;
+
+/// Gathers the details of an invocation of an exported binary (including
+/// its output) and writes them to the log collection on the instance chain
+/// when the invocation finishes (it is also added to the metrics of the
+/// instance)
+#[derive(Clone)]
+pub struct InvocationRecorder
+{
+    service_instance: DaoMut<ServiceInstance>,
+    meter: InvocationMeter,
+    started: Instant,
+    warm: bool,
+    log: Arc<Mutex<InstanceLog>>,
+    bytes_in: Arc<AtomicU64>,
+    bytes_out: Arc<AtomicU64>,
+}
+
+impl InvocationRecorder
+{
+    pub fn new(service_instance: &DaoMut<ServiceInstance>, meter: &InvocationMeter, binary: &str, caller: String) -> InvocationRecorder {
+        InvocationRecorder {
+            service_instance: service_instance.clone(),
+            meter: meter.clone(),
+            started: Instant::now(),
+            warm: meter.is_warm(binary),
+            log: Arc::new(Mutex::new(InstanceLog::new(binary, caller))),
+            bytes_in: Arc::new(AtomicU64::new(0)),
+            bytes_out: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Counts the data that was passed into the invocation
+    pub fn input(&self, len: usize) {
+        self.bytes_in.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    /// Counts the data that was returned by the invocation (other than
+    /// what it wrote to stdout and stderr)
+    pub fn output(&self, len: usize) {
+        self.bytes_out.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    pub fn stdout(&self, data: &[u8]) {
+        self.output(data.len());
+        let mut log = self.log.lock().unwrap();
+        log.append_stdout(data);
+    }
+
+    pub fn stderr(&self, data: &[u8]) {
+        self.output(data.len());
+        let mut log = self.log.lock().unwrap();
+        log.append_stderr(data);
+    }
+
+    /// Creates a file descriptor that captures everything written to it
+    /// while still passing it through to the supplied file descriptor
+    pub fn tee(&self, mut forward: Fd, is_err: bool) -> Fd {
+        let (fd, mut rx) = pipe_out(forward.flag());
+        let recorder = self.clone();
+        System::default().fork_shared(move || async move {
+            while let Some(msg) = rx.recv().await {
+                match msg {
+                    FdMsg::Data { data, .. } => {
+                        if is_err {
+                            recorder.stderr(&data[..]);
+                        } else {
+                            recorder.stdout(&data[..]);
+                        }
+                        let _ = forward.write_vec(data).await;
+                    }
+                    FdMsg::Flush { .. } => {
+                        let _ = forward.flush_async().await;
+                    }
+                }
+            }
+        });
+        fd
+    }
+
+    /// Writes the record of this invocation to the instance chain in the
+    /// background (failing to do so does not affect the invocation itself)
+    pub fn finish(&self, status: InstanceLogStatus) {
+        let duration = self.started.elapsed();
+        let log = {
+            let mut log = self.log.lock().unwrap();
+            log.duration_ms = duration.as_millis() as u64;
+            log.status = status;
+            log.clone()
+        };
+        self.meter.record(
+            log.binary.as_str(),
+            duration,
+            log.status.is_success(),
+            self.warm,
+            self.bytes_in.load(Ordering::Relaxed),
+            self.bytes_out.load(Ordering::Relaxed),
+        );
+
+        let service_instance = self.service_instance.clone();
+        System::default().fork_shared(move || async move {
+            let binary = log.binary.clone();
+            if let Err(err) = append_log(&service_instance, log).await {
+                warn!("failed to write the invocation log for {} - {}", binary, err);
+            }
+        });
+    }
+}
+
+/// Appends a record to the log collection of the instance and deletes the
+/// oldest records (in the same transaction) so it stays within its limits
+async fn append_log(service_instance: &DaoMut<ServiceInstance>, log: InstanceLog) -> Result<(), AteError>
+{
+    let dio = service_instance.trans().dio.as_mut().await;
+    let mut logs = DaoVec::<InstanceLog>::new_orphaned_mut(&dio, PrimaryKey::from(INSTANCE_ROOT_ID), INSTANCE_LOG_COLLECTION_ID);
+
+    let mut existing = logs.iter_mut().await?.collect::<Vec<_>>();
+    existing.sort_by_key(|a| a.when);
+    let sizes = existing.iter().map(|a| a.size()).collect::<Vec<_>>();
+    let evict = instance_log_evictions(&sizes[..], log.size());
+    for old in existing.into_iter().take(evict) {
+        old.delete()?;
+    }
+
+    logs.push(log)?;
+    dio.commit().await?;
+    Ok(())
+}
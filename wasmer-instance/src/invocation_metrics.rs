@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use ate::prelude::*;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use wasmer_deploy_cli::model::InstanceMetricsWindow;
+use wasmer_deploy_cli::model::ServiceInstance;
+use wasmer_deploy_cli::model::INSTANCE_ROOT_ID;
+use wasmer_deploy_cli::model::INSTANCE_METRICS_COLLECTION_ID;
+use wasmer_deploy_cli::model::instance_metrics_evictions;
+use wasmer_ssh::wasmer_os;
+use wasmer_os::api::System;
+use wasmer_os::api::SystemAbiExt;
+
+/// How often the metrics gathered in memory are written to the instance chain
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Meters the invocations of the exported binaries of an instance, the
+/// metrics are gathered in memory and periodically added to the windows
+/// stored on the instance chain (one write per flush rather than per call)
+#[derive(Clone)]
+pub struct InvocationMeter
+{
+    pending: Arc<Mutex<Vec<InstanceMetricsWindow>>>,
+    /// Binaries that are already loaded and compiled (either prefetched or
+    /// invoked before) so that invocations can be counted as warm or cold
+    warm: Arc<Mutex<HashSet<String>>>,
+    _alive: Arc<()>,
+}
+
+impl InvocationMeter
+{
+    pub fn new(service_instance: &DaoMut<ServiceInstance>) -> InvocationMeter {
+        let pending = Arc::new(Mutex::new(Vec::new()));
+        let alive = Arc::new(());
+
+        // The flush loop runs until all the clones of the meter are dropped
+        // (i.e. the session basics expire) and then writes one last time
+        let service_instance = service_instance.clone();
+        let flush_pending = pending.clone();
+        let flush_alive = Arc::downgrade(&alive);
+        System::default().fork_shared(move || async move {
+            loop {
+                tokio::time::sleep(FLUSH_INTERVAL).await;
+                let finished = flush_alive.strong_count() == 0;
+                flush(&service_instance, &flush_pending).await;
+                if finished {
+                    break;
+                }
+            }
+        });
+
+        InvocationMeter {
+            pending,
+            warm: Arc::new(Mutex::new(HashSet::new())),
+            _alive: alive,
+        }
+    }
+
+    pub fn is_warm(&self, binary: &str) -> bool {
+        self.warm.lock().unwrap().contains(&binary.to_lowercase())
+    }
+
+    pub fn mark_warm(&self, binary: &str) {
+        self.warm.lock().unwrap().insert(binary.to_lowercase());
+    }
+
+    pub fn record(&self, binary: &str, duration: Duration, success: bool, warm: bool, bytes_in: u64, bytes_out: u64) {
+        self.mark_warm(binary);
+        let current = InstanceMetricsWindow::current();
+        let mut pending = self.pending.lock().unwrap();
+        let index = match pending.iter().position(|a| a.start == current.start) {
+            Some(a) => a,
+            None => {
+                pending.push(current);
+                pending.len() - 1
+            }
+        };
+        let export = pending[index].export_mut(binary);
+        export.record(duration.as_millis() as u64, success, bytes_in, bytes_out);
+        export.record_load(warm);
+    }
+}
+
+/// Writes the pending metrics to the instance chain, if this fails they are
+/// kept so that the next flush tries again
+async fn flush(service_instance: &DaoMut<ServiceInstance>, pending: &Mutex<Vec<InstanceMetricsWindow>>)
+{
+    let windows = std::mem::take(&mut *pending.lock().unwrap());
+    if windows.is_empty() {
+        return;
+    }
+
+    if let Err(err) = write_windows(service_instance, &windows[..]).await {
+        warn!("failed to write the invocation metrics - {}", err);
+        let mut pending = pending.lock().unwrap();
+        for window in windows {
+            match pending.iter_mut().find(|a| a.start == window.start) {
+                Some(a) => a.merge(&window),
+                None => pending.push(window),
+            }
+        }
+    }
+}
+
+/// Adds the metrics to the windows already persisted on the instance chain
+/// (which may have been written by another host or before a restart) and
+/// deletes the oldest windows so the collection stays within its limit
+async fn write_windows(service_instance: &DaoMut<ServiceInstance>, windows: &[InstanceMetricsWindow]) -> Result<(), AteError>
+{
+    let dio = service_instance.trans().dio.as_mut().await;
+    let mut stored = DaoVec::<InstanceMetricsWindow>::new_orphaned_mut(&dio, PrimaryKey::from(INSTANCE_ROOT_ID), INSTANCE_METRICS_COLLECTION_ID);
+
+    let mut existing = stored.iter_mut().await?.collect::<Vec<_>>();
+    existing.sort_by_key(|a| a.start);
+    for window in windows {
+        if let Some(a) = existing.iter_mut().find(|a| a.start == window.start) {
+            a.as_mut().merge(window);
+            continue;
+        }
+
+        let evict = instance_metrics_evictions(existing.len());
+        for old in existing.drain(..evict) {
+            old.delete()?;
+        }
+        existing.push(stored.push(window.clone())?);
+    }
+
+    dio.commit().await?;
+    Ok(())
+}
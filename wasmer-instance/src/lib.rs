@@ -5,6 +5,10 @@ pub mod handler;
 pub mod relay;
 pub mod adapter;
 pub mod fixed_reader;
+pub mod invocation_log;
+pub mod invocation_metrics;
+pub mod prefetch;
+pub mod token_limiter;
 
 pub use wasmer_term;
 pub use wasmer_auth;
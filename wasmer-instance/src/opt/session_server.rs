@@ -49,4 +49,22 @@ pub struct OptsSessionServer {
     /// Time-to-live for sessions that are initiated
     #[clap(long, default_value = "300")]
     pub ttl: u64,
+    /// Disables the prefetching of exported binaries when an instance is loaded
+    #[clap(long)]
+    pub no_prefetch: bool,
+    /// Maximum number of exported binaries that are prefetched at the same time
+    #[clap(long, default_value = "2")]
+    pub prefetch_concurrency: usize,
+    /// Keeps the temporary files (/tmp) of the sessions in the instance chain
+    /// rather than in memory so that they persist
+    #[clap(long)]
+    pub tmp_persistent: bool,
+    /// Maximum size in bytes of the temporary files (/tmp) that a session may keep
+    /// in memory
+    #[clap(long, default_value = "67108864")]
+    pub tmp_max_size: u64,
+    /// Number of seconds after which persistent temporary files that have not been
+    /// modified are removed (this happens as sessions end)
+    #[clap(long)]
+    pub tmp_ttl: Option<u64>,
 }
\ No newline at end of file
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::sync::Semaphore;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use wasmer_deploy_cli::model::InstanceExport;
+use wasmer_ssh::wasmer_os;
+use wasmer_os::api::ConsoleRect;
+use wasmer_os::api::System;
+use wasmer_os::api::SystemAbiExt;
+use wasmer_os::console::Console;
+use wasmer_os::eval::prefetch_bin;
+
+use crate::handler::SessionHandler;
+use crate::handler::SessionTx;
+use crate::server::SessionBasics;
+
+/// Warms the binaries referenced by the exports of an instance when its
+/// chain is loaded so that the first invocation after a restart does not
+/// pay for the download and compile. Prefetching runs on dedicated threads
+/// with bounded concurrency and a failure simply leaves the binary cold.
+#[derive(Clone)]
+pub struct Prefetcher
+{
+    enabled: bool,
+    limit: Arc<Semaphore>,
+}
+
+impl Prefetcher
+{
+    pub fn new(enabled: bool, concurrency: usize) -> Prefetcher {
+        Prefetcher {
+            enabled,
+            limit: Arc::new(Semaphore::new(concurrency.max(1))),
+        }
+    }
+
+    pub fn disabled() -> Prefetcher {
+        Prefetcher::new(false, 1)
+    }
+
+    /// Starts prefetching the exported binaries of the instance in the
+    /// background (pinned exports are warmed first)
+    pub async fn prefetch(&self, basics: &SessionBasics, compiler: wasmer_os::eval::Compiler) {
+        if self.enabled == false {
+            return;
+        }
+
+        let exports = match basics.service_instance.exports.iter().await {
+            Ok(a) => a.map(|a| a.take()).collect::<Vec<_>>(),
+            Err(err) => {
+                debug!("prefetch skipped - failed to read the exports - {}", err);
+                return;
+            }
+        };
+        for binary in prefetch_order(exports) {
+            let limit = self.limit.clone();
+            let basics = basics.clone();
+            System::default().fork_dedicated_async(move || async move {
+                let _permit = match limit.acquire_owned().await {
+                    Ok(a) => a,
+                    Err(_) => return,
+                };
+                if basics.meter.is_warm(binary.as_str()) {
+                    return;
+                }
+                if prefetch_binary(&basics, compiler, &binary).await {
+                    debug!("prefetched {}", binary);
+                    basics.meter.mark_warm(binary.as_str());
+                }
+            });
+        }
+    }
+}
+
+/// Returns the binaries to prefetch without duplicates with the binaries of
+/// pinned exports first
+fn prefetch_order(mut exports: Vec<InstanceExport>) -> Vec<String> {
+    exports.sort_by_key(|a| a.pinned.is_none());
+    let mut ret: Vec<String> = Vec::new();
+    for export in exports {
+        if ret.iter().any(|a| a.eq_ignore_ascii_case(export.binary.as_str())) == false {
+            ret.push(export.binary);
+        }
+    }
+    ret
+}
+
+async fn prefetch_binary(basics: &SessionBasics, compiler: wasmer_os::eval::Compiler, binary: &String) -> bool {
+    // Output of the prefetch is discarded
+    let (exit_tx, _exit_rx) = mpsc::channel(1);
+    let handler = Arc::new(SessionHandler {
+        tx: AsyncMutex::new(SessionTx::None),
+        rect: Arc::new(Mutex::new(ConsoleRect { cols: 80, rows: 25 })),
+        exit: exit_tx,
+    });
+
+    let mut console = Console::new_ext(
+        "ssh://wasmer.sh/?no_welcome".to_string(),
+        "noagent".to_string(),
+        compiler,
+        handler,
+        None,
+        basics.fs.clone(),
+        basics.bins.clone(),
+        basics.reactor.clone(),
+    );
+    console.prepare().await;
+
+    let job = match console.new_job().await {
+        Some(a) => a,
+        None => {
+            debug!("prefetch of {} skipped - no more job space", binary);
+            return false;
+        }
+    };
+    let ctx = console.exec_factory().create_context(console.new_spawn_context(&job));
+    prefetch_bin(&ctx, binary).await
+}
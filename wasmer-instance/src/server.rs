@@ -23,19 +23,23 @@ use ate_files::repo::RepositorySessionFactory;
 use wasmer_ssh::wasmer_os::api::System;
 use wasmer_ssh::wasmer_os::api::SystemAbiExt;
 use wasmer_deploy_cli::model::InstanceHello;
+use wasmer_deploy_cli::error::InstanceErrorKind;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 use url::Url;
 use percent_encoding::{percent_decode};
+use wasmer_deploy_cli::model::ExportRequest;
 use wasmer_deploy_cli::model::MasterAuthority;
 use wasmer_deploy_cli::model::ServiceInstance;
 use wasmer_deploy_cli::model::InstanceReply;
+use wasmer_deploy_cli::model::InstanceLogStatus;
 use wasmer_deploy_cli::model::INSTANCE_ROOT_ID;
 use wasmer_deploy_cli::model::MASTER_AUTHORITY_ID;
 #[allow(unused_imports)]
 use wasmer_deploy_cli::model::InstanceCall;
 use wasmer_ssh::wasmer_os;
 use wasmer_os::api::ConsoleRect;
+use wasmer_os::fs::SessionTmp;
 use wasmer_os::fs::UnionFileSystem;
 use wasmer_os::bin_factory::*;
 use wasmer_os::reactor::Reactor;
@@ -57,6 +61,10 @@ use http::StatusCode;
 use crate::adapter::FileAccessorAdapter;
 use crate::session::Session;
 use crate::fixed_reader::FixedReader;
+use crate::invocation_log::InvocationRecorder;
+use crate::invocation_metrics::InvocationMeter;
+use crate::prefetch::Prefetcher;
+use crate::token_limiter::TokenLimiter;
 
 #[derive(Clone)]
 pub struct SessionBasics {
@@ -64,7 +72,9 @@ pub struct SessionBasics {
     pub bins: BinFactory,
     pub reactor: Arc<RwLock<Reactor>>,
     pub service_instance: DaoMut<ServiceInstance>,
-    pub multiplexer: SubProcessMultiplexer
+    pub multiplexer: SubProcessMultiplexer,
+    pub meter: InvocationMeter,
+    pub limiter: TokenLimiter,
 }
 
 pub struct Server
@@ -80,6 +90,8 @@ pub struct Server
     pub instance_authority: String,
     pub sessions: RwLock<TtlCache<ChainKey, SessionBasics>>,
     pub ttl: Duration,
+    pub prefetch: Prefetcher,
+    pub tmp: SessionTmp,
 }
 
 impl Server
@@ -93,6 +105,8 @@ impl Server
         compiler: wasmer_os::eval::Compiler,
         compiled_modules: Arc<CachedCompiledModules>,
         ttl: Duration,
+        prefetch: Prefetcher,
+        tmp: SessionTmp,
     ) -> Result<Self, Box<dyn std::error::Error>>
     {
         // Build a session factory that will load the session for this instance using the broker key
@@ -129,6 +143,8 @@ impl Server
             instance_authority,
             sessions,
             ttl,
+            prefetch,
+            tmp,
         })
     }
 
@@ -165,6 +181,7 @@ impl Server
         let bins = BinFactory::new(self.compiled_modules.clone());
         let reactor = Arc::new(RwLock::new(Reactor::new()));
         let multiplexer = SubProcessMultiplexer::new();
+        let meter = InvocationMeter::new(&service_instance);
         
         // Build the basics
         let basics = SessionBasics {
@@ -173,11 +190,17 @@ impl Server
             reactor,
             service_instance,
             multiplexer,
+            meter,
+            limiter: TokenLimiter::default(),
         };
 
         // Cache and and return it
         let ret = basics.clone();
         guard.insert(key.clone(), basics, self.ttl);
+        drop(guard);
+
+        // Warm the exported binaries in the background
+        self.prefetch.prefetch(&ret, self.compiler).await;
         Ok((ret, true))
     }
 
@@ -208,7 +231,8 @@ impl Server
             self.engine.clone(),
             self.compiler.clone(),
             basics.clone(),
-            first_init
+            first_init,
+            self.tmp,
         ).await;
 
         Ok(ret)
@@ -295,7 +319,7 @@ for Server
             .split(None);
         let tx = Upstream {
             id: NodeId::generate_client_id(),
-            outbox: tx,
+            outbox: tx.into(),
             wire_format: SerializationFormat::Json,
         };
 
@@ -327,6 +351,14 @@ for Server
             path: path.to_string_lossy().to_string(),
             encryption: None,
             wire_format: tx.wire_format,
+            multiplex: false,
+            version: MessageProtocolVersion::V3,
+            certificates: Vec::new(),
+            select_certificate: false,
+            keepalive: None,
+            replay_protection: false,
+            transcript: None,
+            peer_identity: None,
         };
         let hello_instance = InstanceHello {
             access_token: auth.to_str().unwrap().to_string(),
@@ -371,8 +403,7 @@ for Server
             let topic = path_iter.next();
 
             if identity.is_none() || db.is_none() || binary.is_none() || topic.is_none() {
-                let msg = format!("The URL path is malformed").as_bytes().to_vec();
-                return Err((msg, StatusCode::BAD_REQUEST));
+                return Err(error_response(InstanceErrorKind::InvalidRequest("the URL path is malformed".to_string()), StatusCode::BAD_REQUEST));
             }
 
             let identity = identity.unwrap();
@@ -387,15 +418,13 @@ for Server
         
         // Get the authorization
         if headers.contains_key(http::header::AUTHORIZATION) == false {
-            let msg = format!("Missing the Authorization header").as_bytes().to_vec();
-            return Err((msg, StatusCode::UNAUTHORIZED));
+            return Err(error_response(InstanceErrorKind::InvalidAccessToken, StatusCode::UNAUTHORIZED));
         }
         let auth = headers[http::header::AUTHORIZATION].clone();
 
         // Get and check the data format
         if headers.contains_key(http::header::CONTENT_TYPE) == false {
-            let msg = format!("Must supply a content type in the request").as_bytes().to_vec();
-            return Err((msg, StatusCode::BAD_REQUEST));
+            return Err(error_response(InstanceErrorKind::InvalidRequest("must supply a content type in the request".to_string()), StatusCode::BAD_REQUEST));
         }
         let format = match headers[http::header::CONTENT_TYPE].to_str().unwrap() {
             "text/xml" |
@@ -416,8 +445,7 @@ for Server
             "application/yaml" |
             "application/yml" => SerializationFormat::Yaml,
             a => {
-                let msg = format!("Unsupported http content type [{}]", a).as_bytes().to_vec();
-                return Err((msg, StatusCode::BAD_REQUEST));
+                return Err(error_response(InstanceErrorKind::InvalidRequest(format!("unsupported http content type [{}]", a)), StatusCode::BAD_REQUEST));
             }
         };
 
@@ -430,6 +458,14 @@ for Server
             path: path.to_string_lossy().to_string(),
             encryption: None,
             wire_format: SerializationFormat::Json,
+            multiplex: false,
+            version: MessageProtocolVersion::V3,
+            certificates: Vec::new(),
+            select_certificate: false,
+            keepalive: None,
+            replay_protection: false,
+            transcript: None,
+            peer_identity: None,
         };
         let hello_instance = InstanceHello {
             access_token: auth.to_str().unwrap().to_string(),
@@ -441,8 +477,7 @@ for Server
         let (basics, first_init) = self.get_or_create_session_basics(key.clone())
             .await
             .map_err(|err| {
-                let msg = format!("instance call failed - {}", err).as_bytes().to_vec();
-                (msg, StatusCode::INTERNAL_SERVER_ERROR)
+                error_response(InstanceErrorKind::CallFailed(err.to_string()), StatusCode::INTERNAL_SERVER_ERROR)
             })?;
 
         // Create a fixed reader
@@ -460,13 +495,14 @@ for Server
             self.engine.clone(),
             self.compiler.clone(),
             basics.clone(),
-            first_init
+            first_init,
+            self.tmp,
         ).await;
 
         // Validate we can access this binary
-        if session.can_access_binary(binary.as_str(), auth.to_str().unwrap()).await == false {
-            let msg = format!("Access Denied (Invalid Token)").as_bytes().to_vec();
-            return Err((msg, StatusCode::UNAUTHORIZED));
+        let request = ExportRequest::Http { topic: Some(topic.as_str()) };
+        if let Err(err) = session.authorize(binary.as_str(), auth.to_str().unwrap(), request).await {
+            return Err(access_denied_response(err));
         }
         
         // Invoke the call
@@ -477,14 +513,14 @@ for Server
                 format,
                 binary,
                 topic,
+                keepalive: false,
             },
             body,
             tx_reply,
             )
             .await
             .map_err(|err: Box<dyn std::error::Error>| {
-                let msg = format!("instance call failed - {}", err).as_bytes().to_vec();
-                (msg, StatusCode::INTERNAL_SERVER_ERROR)
+                error_response(InstanceErrorKind::CallFailed(err.to_string()), StatusCode::INTERNAL_SERVER_ERROR)
             })?;
 
         // Read the result and pump it
@@ -504,8 +540,7 @@ for Server
                                 trace!("{}", String::from_utf8_lossy(&data[..]));
                             },
                             err => {
-                                let msg = format!("Instance call failed - {}", err).as_bytes().to_vec();
-                                return Err((msg, StatusCode::BAD_GATEWAY));
+                                return Err(error_response(InstanceErrorKind::CallFailed(err.to_string()), StatusCode::BAD_GATEWAY));
                             }
                         }
                     } else {
@@ -515,8 +550,7 @@ for Server
                 _ = invocations => { }
             }
         }
-        let msg = format!("Instance call aborted before finishing").as_bytes().to_vec();
-        Err((msg, StatusCode::NOT_ACCEPTABLE))
+        Err(error_response(InstanceErrorKind::CallAborted, StatusCode::NOT_ACCEPTABLE))
     }
 
     #[allow(unused_variables)]
@@ -542,8 +576,7 @@ for Server
             let binary = path_iter.next();
 
             if identity.is_none() || db.is_none() || binary.is_none() {
-                let msg = format!("The URL path is malformed").as_bytes().to_vec();
-                return Err((msg, StatusCode::BAD_REQUEST));
+                return Err(error_response(InstanceErrorKind::InvalidRequest("the URL path is malformed".to_string()), StatusCode::BAD_REQUEST));
             }
 
             let identity = identity.unwrap();
@@ -584,8 +617,7 @@ for Server
         
         // Get the authorization
         if headers.contains_key(http::header::AUTHORIZATION) == false {
-            let msg = format!("Missing the Authorization header").as_bytes().to_vec();
-            return Err((msg, StatusCode::UNAUTHORIZED));
+            return Err(error_response(InstanceErrorKind::InvalidAccessToken, StatusCode::UNAUTHORIZED));
         }
         let auth = headers[http::header::AUTHORIZATION].clone();
 
@@ -596,6 +628,14 @@ for Server
             path: path.to_string_lossy().to_string(),
             encryption: None,
             wire_format: SerializationFormat::Json,
+            multiplex: false,
+            version: MessageProtocolVersion::V3,
+            certificates: Vec::new(),
+            select_certificate: false,
+            keepalive: None,
+            replay_protection: false,
+            transcript: None,
+            peer_identity: None,
         };
         let hello_instance = InstanceHello {
             access_token: auth.to_str().unwrap().to_string(),
@@ -608,7 +648,7 @@ for Server
             .await
             .map_err(|err| {
                 debug!("instance eval failed - {}", err);
-                (Vec::new(), StatusCode::INTERNAL_SERVER_ERROR)
+                error_response(InstanceErrorKind::CallFailed(err.to_string()), StatusCode::INTERNAL_SERVER_ERROR)
             })?;
 
         // Build the session
@@ -624,13 +664,14 @@ for Server
             self.engine.clone(),
             self.compiler.clone(),
             basics.clone(),
-            first_init
+            first_init,
+            self.tmp,
         ).await;
 
         // Validate we can access this binary
-        if session.can_access_binary(binary.as_str(), auth.to_str().unwrap()).await == false {
-            let msg = format!("Access Denied (Invalid Token)").as_bytes().to_vec();
-            return Err((msg, StatusCode::UNAUTHORIZED));
+        let request = ExportRequest::Http { topic: None };
+        if let Err(err) = session.authorize(binary.as_str(), auth.to_str().unwrap(), request).await {
+            return Err(access_denied_response(err));
         }
 
         debug!("accept-raw-post-request: uri: {}", uri);
@@ -644,6 +685,7 @@ for Server
         }
 
         // Create the stdin pipe
+        let body_len = body.len();
         let (stdin, body_tx) = pipe_in(ReceiverMode::Stream, FdFlag::Stdin(false));
         let _ = body_tx.send(FdMsg::Data { data: body, flag: FdFlag::Stdin(false) }).await;
         let _ = body_tx.send(FdMsg::Data { data: Vec::new(), flag: FdFlag::Stdin(false) }).await;
@@ -656,29 +698,52 @@ for Server
         stderr.set_ignore_flush(true);
 
         // Evaluate the binary until its finished
+        let recorder = InvocationRecorder::new(&basics.service_instance, &basics.meter, binary.as_str(), sock_addr.to_string());
+        recorder.input(body_len);
         let exit_code = session.eval(binary, env, args, redirects, stdin, stdout, stderr)
             .await
             .map_err(|err: Box<dyn std::error::Error>| {
-                let msg = format!("instance eval failed - {}", err).as_bytes().to_vec();
-                (msg, StatusCode::INTERNAL_SERVER_ERROR)
+                recorder.finish(InstanceLogStatus::Failed(err.to_string()));
+                error_response(InstanceErrorKind::CallFailed(err.to_string()), StatusCode::INTERNAL_SERVER_ERROR)
             })?;
         drop(session);
 
         // Read all the data
         let ret = read_to_end(ret_rx).await;
+        let err = read_to_end(err_rx).await;
         debug!("eval returned {} bytes", ret.len());
+
+        // Record the invocation in the instance log
+        recorder.stdout(&ret[..]);
+        recorder.stderr(&err[..]);
+        recorder.finish(InstanceLogStatus::Exited(exit_code));
         
         // Convert the error code to a status code
         match exit_code {
             0 => Ok(ret),
-            _ => {
-                let err = read_to_end(err_rx).await;
-                Err((err, StatusCode::INTERNAL_SERVER_ERROR))
-            }
+            _ => Err((err, StatusCode::INTERNAL_SERVER_ERROR))
         }
     }
 }
 
+/// Builds the JSON error envelope (with its stable error code) that is
+/// returned to HTTP callers when a request fails
+fn error_response(err: InstanceErrorKind, status: StatusCode) -> (Vec<u8>, StatusCode)
+{
+    (ErrorEnvelope::new(&err).to_json(), status)
+}
+
+fn access_denied_response(err: InstanceErrorKind) -> (Vec<u8>, StatusCode)
+{
+    let status = match &err {
+        InstanceErrorKind::InvalidAccessToken |
+        InstanceErrorKind::AccessTokenExpired => StatusCode::UNAUTHORIZED,
+        InstanceErrorKind::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    error_response(err, status)
+}
+
 async fn read_to_end(mut rx: mpsc::Receiver<FdMsg>) -> Vec<u8>
 {
     let mut ret = Vec::new();
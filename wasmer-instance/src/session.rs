@@ -5,9 +5,12 @@ use std::collections::HashMap;
 use tokio::sync::Mutex as AsyncMutex;
 use ate::prelude::*;
 use ate::comms::*;
+use wasmer_deploy_cli::error::InstanceErrorKind;
+use wasmer_deploy_cli::model::ExportRequest;
 use wasmer_deploy_cli::model::InstanceCall;
 use wasmer_deploy_cli::model::InstanceCommand;
 use wasmer_deploy_cli::model::InstanceHello;
+use wasmer_deploy_cli::model::InstanceLogStatus;
 use wasmer_deploy_cli::model::InstanceReply;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
@@ -19,6 +22,7 @@ use std::pin::Pin;
 use std::task::Context;
 use std::task::Poll;
 use wasmer_os::api::ConsoleRect;
+use wasmer_os::fs::SessionTmp;
 use wasmer_os::bus;
 use wasmer_os::bus::*;
 use wasmer_os::bus::BusError;
@@ -34,6 +38,7 @@ use wasmer_os::grammar::ast::Redirect;
 
 use super::handler::SessionHandler;
 use super::handler::SessionTx;
+use super::invocation_log::InvocationRecorder;
 use super::server::SessionBasics;
 
 pub struct Session
@@ -69,6 +74,7 @@ impl Session
         compiler: wasmer_os::eval::Compiler,
         basics: SessionBasics,
         first_init: bool,
+        tmp: SessionTmp,
     ) -> Session
     {
         // Create the handler
@@ -96,6 +102,9 @@ impl Session
             basics.reactor.clone(),
         );
 
+        // The temporary files must be in place before anything runs
+        console.set_tmp(tmp);
+
         // If its the first init
         if first_init {
             console.init().await;
@@ -182,10 +191,19 @@ impl Session
                                 *guard = SessionTx::Feeder(tx_reply.clone());
                             }
 
-                            // Invoke the call
+                            // Invoke the call (calls that have a parent are routed to
+                            // the session of the handler that is already running)
                             let req = self.rx.read().await?;
                             self.call(call, req, tx_reply.clone()).await?;
                         }
+                        InstanceCommand::Close(handle) => {
+                            // Close the call session (if it is still open) and let the
+                            // client know that it has been terminated
+                            self.close(handle);
+                            let _ = tx_reply.send(InstanceReply::Terminate {
+                                handle: handle.into(),
+                            }).await;
+                        }
                     }
                 }
                 reply = rx_reply.recv() => {
@@ -231,8 +249,7 @@ impl Session
                 data = self.rx.read() => {
                     match data {
                         Ok(data) => {
-                            let data = String::from_utf8_lossy(&data[..]);
-                            self.console.on_data(data.into()).await;
+                            self.console.on_data_bytes(&data[..]).await;
                         }
                         Err(err) => {
                             info!("exiting from session ({}) - {}", self.basics.service_instance.id_str(), err);
@@ -266,6 +283,14 @@ impl Session
         ctx.stdout = stdout;
         ctx.stderr = stderr;
         ctx.env = env;
+        // Variables of the instance (e.g. seeded by its template) sit beneath
+        // the ones that came with the request
+        for (key, val) in self.basics.service_instance.env.iter() {
+            if ctx.env.get(key.as_str()).is_none() {
+                ctx.env.set_var(key.as_str(), val.clone());
+                ctx.env.export(key.as_str());
+            }
+        }
         ctx.extra_args = args;
         ctx.extra_redirects = redirects;
         let exec = self.console.exec_factory();
@@ -290,20 +315,31 @@ impl Session
         Ok(ret)
     }
 
-    pub async fn can_access_binary(&self, binary: &str, access_token: &str) -> bool
+    /// Checks that the access token grants the request against an exported
+    /// binary (raw export tokens and capability tokens are both accepted)
+    pub async fn authorize(&self, binary: &str, access_token: &str, request: ExportRequest<'_>) -> Result<(), InstanceErrorKind>
     {
-        // Check the access code matches what was passed in
-        self.basics
+        let capability = self.basics
             .service_instance
-            .exports
-            .iter()
+            .authorize_export(binary, access_token, request)
             .await
-            .map_or(false, |iter| {
-                iter
-                    .filter(|e| e.binary.eq_ignore_ascii_case(binary))
-                    .any(|e| e.access_token.eq_ignore_ascii_case(access_token))
-            })
-        
+            .map_err(|err| err.0)?;
+        if let Some(capability) = capability {
+            if self.basics.limiter.allow(&capability) == false {
+                return Err(InstanceErrorKind::RateLimited);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn close(&mut self, handle: u64)
+    {
+        let handle: CallHandle = handle.into();
+        for factory in self.factories.values_mut() {
+            if factory.close(handle).is_some() {
+                debug!("call session closed (handle={})", handle);
+            }
+        }
     }
 
     pub async fn call(&mut self, call: InstanceCall, request: Vec<u8>, tx_reply: mpsc::Sender<InstanceReply>) -> Result<(), Box<dyn std::error::Error>>
@@ -316,30 +352,33 @@ impl Session
         };
         let feeder = this_callback.clone();
 
-        // Check the access code matches what was passed in
-        if self.basics.service_instance
-            .exports
-            .iter()
-            .await?
-            .filter(|e| e.binary.eq_ignore_ascii_case(call.binary.as_str()))
-            .any(|e| e.access_token.eq_ignore_ascii_case(self.hello_instance.access_token.as_str()))
-            == false
-        {
-            warn!("access denied to {}@{} from {}", call.binary, self.hello_instance.chain, self.sock_addr);
-            this_callback.error(BusError::AccessDenied);
-            return Ok(());
+        // Check the access code matches what was passed in (sessions without
+        // an upstream are HTTP calls which the web route has already authorized)
+        if self.tx.is_some() {
+            let request = ExportRequest::Bus { topic: call.topic.as_str() };
+            if let Err(err) = self.authorize(call.binary.as_str(), self.hello_instance.access_token.as_str(), request).await {
+                warn!("access denied to {}@{} from {} - {}", call.binary, self.hello_instance.chain, self.sock_addr, err);
+                this_callback.error(BusError::AccessDenied);
+                return Ok(());
+            }
         }
 
         // Create the context
         let caller_ctx = WasmCallerContext::default();
 
+        // The output of the call is captured in the invocation log of the instance
+        let recorder = InvocationRecorder::new(&self.basics.service_instance, &self.basics.meter, call.binary.as_str(), self.sock_addr.to_string());
+        recorder.input(request.len());
+        let stdout = recorder.tee(self.console.stdout_fd(), false);
+        let stderr = recorder.tee(self.console.stderr_fd(), true);
+
         // Create the job and context
         let launch_env = LaunchEnvironment {
             abi: self.console.abi(),
             inherit_stdin: WeakFd::null(),
-            inherit_stderr: self.console.stderr_fd().downgrade(),
-            inherit_stdout: self.console.stdout_fd().downgrade(),
-            inherit_log: self.console.stderr_fd().downgrade(),
+            inherit_stderr: stderr.downgrade(),
+            inherit_stdout: stdout.downgrade(),
+            inherit_log: stderr.downgrade(),
         };
         
         // Invoke a call with using the console object
@@ -351,6 +390,7 @@ impl Session
             })?;
 
         // Now invoke it on the bus factory
+        let keepalive = call.keepalive;
         let mut invoke = bus_factory.start(
             call.parent.map(|a| a.into()),
             call.handle.into(),
@@ -384,6 +424,10 @@ impl Session
             feeder,
             result,
             sessions,
+            keepalive,
+            recorder,
+            _stdout: stdout,
+            _stderr: stderr,
             _abort_tx: abort_tx,
         });
         Ok(())
@@ -395,9 +439,42 @@ pub struct SessionInvocation
     feeder: SessionFeeder,
     result: AsyncResult<Result<InvokeResult, BusError>>,
     sessions: Arc<Mutex<HashMap<CallHandle, Box<dyn bus::Session>>>>,
+    keepalive: bool,
+    recorder: InvocationRecorder,
+    _stdout: Fd,
+    _stderr: Fd,
     _abort_tx: mpsc::Sender<()>,
 }
 
+impl SessionInvocation {
+    fn process(&self, result: Result<InvokeResult, BusError>) {
+        let status = match &result {
+            Ok(_) => InstanceLogStatus::Responded,
+            Err(BusError::Aborted) => InstanceLogStatus::Aborted,
+            Err(err) => InstanceLogStatus::Failed(err.to_string()),
+        };
+        match &result {
+            Ok(InvokeResult::Response(_, response))
+            | Ok(InvokeResult::ResponseThenWork(_, response, _))
+            | Ok(InvokeResult::ResponseThenLeak(_, response)) => {
+                self.recorder.output(response.len());
+            }
+            Err(_) => { }
+        }
+        self.recorder.finish(status);
+
+        // Keepalive calls hold their session open after the response
+        // until the client explicitly closes it
+        let result = match result {
+            Ok(InvokeResult::Response(format, response)) if self.keepalive => {
+                Ok(InvokeResult::ResponseThenLeak(format, response))
+            }
+            a => a,
+        };
+        BusFeederUtils::process(&self.feeder, result, &self.sessions);
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct SessionInvocations {
     pub running: Arc<Mutex<Vec<SessionInvocation>>>,
@@ -421,10 +498,10 @@ impl Future for SessionInvocations {
             let mut rx = Pin::new(&mut invoke.result.rx);
             match rx.poll_recv(cx) {
                 Poll::Ready(Some(result)) => {
-                    BusFeederUtils::process(&invoke.feeder, result, &invoke.sessions);
+                    invoke.process(result);
                 }
                 Poll::Ready(None) => {
-                    BusFeederUtils::process(&invoke.feeder, Err(BusError::Aborted), &invoke.sessions);
+                    invoke.process(Err(BusError::Aborted));
                 }
                 Poll::Pending => {
                     carry.push(invoke);
@@ -0,0 +1,42 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use ate::prelude::*;
+use std::time::SystemTime;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use wasmer_deploy_cli::model::ExportCapability;
+
+/// Tracks the number of invocations made with each rate limited capability
+/// token during the current minute (the counts are local to this server)
+#[derive(Clone, Default)]
+pub struct TokenLimiter
+{
+    windows: Arc<Mutex<HashMap<AteHash, (u64, u32)>>>,
+}
+
+impl TokenLimiter
+{
+    /// Counts an invocation against the token and returns false if the
+    /// token has exceeded its rate limit
+    pub fn allow(&self, capability: &ExportCapability) -> bool {
+        let limit = match capability.rate_limit {
+            Some(a) => a,
+            None => { return true; }
+        };
+        let minute = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|a| a.as_secs() / 60)
+            .unwrap_or_default();
+
+        let mut windows = self.windows.lock().unwrap();
+        windows.retain(|_, (m, _)| *m == minute);
+        let (_, count) = windows.entry(capability.id.clone()).or_insert((minute, 0));
+        if *count >= limit {
+            debug!("rate limit reached for token {} ({} per minute)", capability.id, limit);
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
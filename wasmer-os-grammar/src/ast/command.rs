@@ -8,12 +8,24 @@ pub enum Command<'a> {
         args: Vec<Arg<'a>>,
         redirect: Vec<Redirect>,
     },
+    /// { list; }
+    Group(CompleteCommand<'a>),
+    /// if list; then list; [elif list; then list;]... [else list;] fi
+    If(IfClause<'a>),
+    /// while list; do list; done (or until)
+    Loop(LoopClause<'a>),
+    /// for name [in word...]; do list; done
+    For(ForClause<'a>),
+    /// name() compound-command
+    Function(FunctionDefinition<'a>),
 }
 
 impl<'a> Command<'a> {
-    pub fn redirect(&mut self) -> &mut Vec<Redirect> {
+    /// Redirects that apply to the command (only simple commands have them)
+    pub fn redirect(&mut self) -> Option<&mut Vec<Redirect>> {
         match self {
-            Command::Simple { redirect, .. } => redirect,
+            Command::Simple { redirect, .. } => Some(redirect),
+            _ => None,
         }
     }
 }
@@ -0,0 +1,10 @@
+use super::*;
+
+#[derive(Debug, PartialEq)]
+pub struct ForClause<'a> {
+    /// Name of the variable that is set for each iteration
+    pub name: &'a str,
+    /// Words that are iterated over (None means the positional parameters)
+    pub words: Option<Vec<Arg<'a>>>,
+    pub body: CompleteCommand<'a>,
+}
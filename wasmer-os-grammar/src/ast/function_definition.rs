@@ -0,0 +1,10 @@
+use super::*;
+
+#[derive(Debug, PartialEq)]
+pub struct FunctionDefinition<'a> {
+    pub name: &'a str,
+    pub body: Box<Command<'a>>,
+    /// Text of the body, functions outlive the program that defined them
+    /// so they are parsed again from this when they are invoked
+    pub source: &'a str,
+}
@@ -0,0 +1,34 @@
+use super::*;
+
+#[derive(Debug, PartialEq)]
+pub struct IfClause<'a> {
+    /// Each condition (if and elif) with the list that runs when it succeeds
+    pub branches: Vec<(CompleteCommand<'a>, CompleteCommand<'a>)>,
+    /// List that runs when none of the conditions succeed
+    pub otherwise: Option<CompleteCommand<'a>>,
+}
+
+impl<'a> IfClause<'a> {
+    pub fn new(
+        condition: CompleteCommand<'a>,
+        body: CompleteCommand<'a>,
+        rest: Option<IfClause<'a>>,
+    ) -> IfClause<'a> {
+        let mut ret = IfClause {
+            branches: vec![(condition, body)],
+            otherwise: None,
+        };
+        if let Some(rest) = rest {
+            ret.branches.extend(rest.branches.into_iter());
+            ret.otherwise = rest.otherwise;
+        }
+        ret
+    }
+
+    pub fn otherwise(body: CompleteCommand<'a>) -> IfClause<'a> {
+        IfClause {
+            branches: Vec::new(),
+            otherwise: Some(body),
+        }
+    }
+}
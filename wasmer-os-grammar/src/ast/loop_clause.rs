@@ -0,0 +1,9 @@
+use super::*;
+
+#[derive(Debug, PartialEq)]
+pub struct LoopClause<'a> {
+    /// When true the loop runs until the condition succeeds (rather than while)
+    pub until: bool,
+    pub condition: CompleteCommand<'a>,
+    pub body: CompleteCommand<'a>,
+}
@@ -3,6 +3,10 @@ mod arg;
 mod command;
 mod complete_command;
 mod complete_commands;
+mod for_clause;
+mod function_definition;
+mod if_clause;
+mod loop_clause;
 mod pipeline;
 mod program;
 mod redirect;
@@ -13,6 +17,10 @@ pub use arg::*;
 pub use command::*;
 pub use complete_command::*;
 pub use complete_commands::*;
+pub use for_clause::*;
+pub use function_definition::*;
+pub use if_clause::*;
+pub use loop_clause::*;
 pub use pipeline::*;
 pub use program::*;
 pub use redirect::*;
@@ -8,6 +8,7 @@ lalrpop_mod!(
 );
 
 pub mod ast;
+mod tests;
 
 pub use grammar::*;
 pub use lalrpop_util::*;
@@ -0,0 +1,128 @@
+#![cfg(test)]
+
+use crate::ast::*;
+use crate::*;
+
+fn parse(text: &str) -> Program<'_> {
+    match programParser::new().parse(text, text) {
+        Ok(a) => a,
+        Err(err) => panic!("failed to parse {:?} - {:?}", text, err),
+    }
+}
+
+fn first_command<'a>(program: &'a Program<'a>) -> &'a Command<'a> {
+    &program.commands.complete_commands[0].and_ors[0].1.pipelines[0].1.commands[0]
+}
+
+#[test]
+fn test_multi_line_script() {
+    let script = "#!/bin/sh\n# comment\necho a # trailing\n\necho b\r\necho c\n";
+    let program = parse(script);
+    assert_eq!(program.commands.complete_commands.len(), 3);
+}
+
+#[test]
+fn test_reserved_words_as_arguments() {
+    let program = parse("echo if then fi done");
+    match first_command(&program) {
+        Command::Simple { args, .. } => assert_eq!(args.len(), 4),
+        a => panic!("unexpected command {:?}", a),
+    }
+}
+
+#[test]
+fn test_if_clause() {
+    let program = parse("if true\nthen\n  echo yes\nelif false; then echo no\nelse\n  echo maybe\nfi");
+    match first_command(&program) {
+        Command::If(clause) => {
+            assert_eq!(clause.branches.len(), 2);
+            assert!(clause.otherwise.is_some());
+        }
+        a => panic!("unexpected command {:?}", a),
+    }
+
+    let program = parse("if true; then if false; then echo a; fi; fi");
+    match first_command(&program) {
+        Command::If(clause) => {
+            assert_eq!(clause.branches.len(), 1);
+            assert!(clause.otherwise.is_none());
+        }
+        a => panic!("unexpected command {:?}", a),
+    }
+}
+
+#[test]
+fn test_loops() {
+    let program = parse("while true; do echo a; break; done");
+    match first_command(&program) {
+        Command::Loop(clause) => {
+            assert_eq!(clause.until, false);
+            assert_eq!(clause.body.and_ors.len(), 2);
+        }
+        a => panic!("unexpected command {:?}", a),
+    }
+
+    let program = parse("until false\ndo\n  echo a\ndone | cat");
+    match first_command(&program) {
+        Command::Loop(clause) => assert_eq!(clause.until, true),
+        a => panic!("unexpected command {:?}", a),
+    }
+
+    let program = parse("for x in a b c; do echo $x; done");
+    match first_command(&program) {
+        Command::For(clause) => {
+            assert_eq!(clause.name, "x");
+            assert_eq!(clause.words.as_ref().map(|a| a.len()), Some(3));
+        }
+        a => panic!("unexpected command {:?}", a),
+    }
+
+    let program = parse("for x; do echo $x; done");
+    match first_command(&program) {
+        Command::For(clause) => assert!(clause.words.is_none()),
+        a => panic!("unexpected command {:?}", a),
+    }
+}
+
+#[test]
+fn test_function_definition() {
+    let program = parse("greet() {\n  echo hello $1\n  return 3\n}\ngreet world\n");
+    assert_eq!(program.commands.complete_commands.len(), 2);
+    match first_command(&program) {
+        Command::Function(def) => {
+            assert_eq!(def.name, "greet");
+            assert_eq!(def.source, "{\n  echo hello $1\n  return 3\n}");
+            assert!(matches!(def.body.as_ref(), Command::Group(_)));
+        }
+        a => panic!("unexpected command {:?}", a),
+    }
+
+    let program = parse("function greet { echo hello; }");
+    match first_command(&program) {
+        Command::Function(def) => assert_eq!(def.source, "{ echo hello; }"),
+        a => panic!("unexpected command {:?}", a),
+    }
+}
+
+#[test]
+fn test_syntax_errors() {
+    // Incomplete commands need more input
+    let text = "if true; then\n  echo a\n";
+    assert!(matches!(
+        programParser::new().parse(text, text),
+        Err(ParseError::UnrecognizedEOF { .. })
+    ));
+
+    // Mismatched reserved words are reported where they are found
+    let text = "if true; then\n  echo a\ndone\n";
+    match programParser::new().parse(text, text) {
+        Err(ParseError::UnrecognizedToken {
+            token: (location, token, _),
+            ..
+        }) => {
+            assert_eq!(token.1, "done");
+            assert_eq!(location, text.find("done").unwrap());
+        }
+        a => panic!("unexpected result {:?}", a),
+    }
+}
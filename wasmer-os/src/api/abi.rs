@@ -114,6 +114,25 @@ where
     /// Puts the current thread to sleep for a fixed number of milliseconds
     fn sleep(&self, ms: u128) -> AsyncResult<()>;
 
+    /// Returns the number of nanoseconds on a monotonic clock that started at
+    /// an arbitrary (but fixed) point in time shared by all the threads of
+    /// this host. The value never goes backwards.
+    fn now_monotonic_nanos(&self) -> u128;
+
+    /// Resolution of the monotonic clock in nanoseconds. Natively this is
+    /// the resolution of `std::time::Instant` while browsers coarsen
+    /// `performance.now()` to between 5us (cross-origin isolated) and 100us
+    fn monotonic_resolution_nanos(&self) -> u128;
+
+    /// Puts the current thread to sleep until the monotonic clock (see
+    /// `now_monotonic_nanos`) reaches the deadline. Deadlines that have
+    /// already passed return straight away.
+    fn sleep_until(&self, deadline_nanos: u128) -> AsyncResult<()>;
+
+    /// Fills the buffer with random bytes from the cryptographically
+    /// secure random number generator of the host
+    fn random(&self, buf: &mut [u8]) -> std::io::Result<()>;
+
     /// Fetches a data file from the local context of the process
     fn fetch_file(&self, path: &str) -> AsyncResult<Result<Vec<u8>, u32>>;
 
@@ -132,6 +151,14 @@ where
 
     /// Open the WebGL
     async fn webgl(&self) -> Option<Box<dyn WebGlAbi>>;
+
+    /// Offers a file to the user as a download (only possible in the browser),
+    /// the data arrives in chunks so large files are never held in full
+    fn download(
+        &self,
+        name: &str,
+        data: mpsc::Receiver<Vec<u8>>,
+    ) -> AsyncResult<Result<(), String>>;
 }
 
 #[derive(Debug)]
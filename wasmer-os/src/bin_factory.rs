@@ -39,6 +39,9 @@ pub struct BinaryPackage {
     pub fs: TmpFileSystem,
     pub mappings: Vec<String>,
     pub envs: HashMap<String, String>,
+    /// Path of the file that the binary was loaded from (None when it was
+    /// fetched rather than read from the file system)
+    pub path: Option<String>,
 }
 
 impl BinaryPackage {
@@ -54,6 +57,7 @@ impl BinaryPackage {
             fs: TmpFileSystem::new(),
             mappings: Vec::new(),
             envs: HashMap::default(),
+            path: None,
         }
     }
 }
@@ -4,6 +4,7 @@ use std::pin::Pin;
 
 use crate::eval::EvalContext;
 use crate::eval::ExecResponse;
+use crate::fs::normalize_path;
 use crate::fs::AsyncifyFileSystem;
 use crate::stdio::*;
 
@@ -25,13 +26,13 @@ pub(super) fn cd(
         });
     }
 
-    let mut print_path = false;
-    let mut dir = if args.len() == 1 {
-        home(&ctx)
+    // Each candidate is tried in order and the path is printed when it was
+    // not obvious which directory would be selected
+    let candidates = if args.len() == 1 {
+        vec![(home(&ctx), false)]
     } else if args[1] == "-" {
         if let Some(v) = ctx.env.get("OLDPWD") {
-            print_path = true;
-            v
+            vec![(v, true)]
         } else {
             return Box::pin(async move {
                 let _ = stdio
@@ -42,30 +43,33 @@ pub(super) fn cd(
             });
         }
     } else {
-        let mut dir = args[1].clone();
-        if dir.starts_with("/") == false {
-            dir.insert_str(0, current(&ctx).as_str());
-        }
-
-        canonicalize(dir.as_str())
+        let cdpath = ctx.env.get("CDPATH");
+        cd_candidates(args[1].as_str(), current(&ctx).as_str(), cdpath.as_deref())
     };
 
-    if dir.ends_with("/") == false {
-        dir += "/";
-    }
-
     Box::pin(async move {
-        if AsyncifyFileSystem::new(ctx.root.clone())
-            .read_dir(Path::new(dir.as_str()))
-            .await
-            .is_err()
-        {
-            let _ = stdio
-                .stderr
-                .write(format!("cd: {}: No such directory\r\n", dir).as_bytes())
-                .await;
-            return ExecResponse::Immediate(ctx, 0);
+        let fs = AsyncifyFileSystem::new(ctx.root.clone());
+        let mut found = None;
+        for (mut dir, print_path) in candidates.iter().cloned() {
+            if dir.ends_with("/") == false {
+                dir += "/";
+            }
+            if fs.read_dir(Path::new(dir.as_str())).await.is_ok() {
+                found = Some((dir, print_path));
+                break;
+            }
         }
+        let (dir, print_path) = match found {
+            Some(a) => a,
+            None => {
+                let dir = candidates.last().map(|a| a.0.clone()).unwrap_or_default();
+                let _ = stdio
+                    .stderr
+                    .write(format!("cd: {}: No such directory\r\n", dir).as_bytes())
+                    .await;
+                return ExecResponse::Immediate(ctx, 0);
+            }
+        };
 
         ctx.env.set_var("OLDPWD", current(&ctx));
         set_current(&mut ctx, dir.as_str());
@@ -78,34 +82,37 @@ pub(super) fn cd(
     })
 }
 
-fn canonicalize(path: &str) -> String {
-    let mut ret = String::with_capacity(path.len());
+/// Returns the directories that `cd` tries in order for a target that is
+/// not `-` or empty, along with whether the path is printed once selected
+pub(super) fn cd_candidates(dir: &str, current: &str, cdpath: Option<&str>) -> Vec<(String, bool)> {
+    let mut candidates = Vec::new();
+    if dir.starts_with("/") {
+        candidates.push((normalize_path(dir), false));
+        return candidates;
+    }
 
-    let mut comps = Vec::new();
-    for comp in path.split("/") {
-        if comp.len() <= 0 {
-            continue;
-        }
-        if comp == "." {
-            continue;
-        };
-        if comp == ".." {
-            if comps.len() > 0 {
-                comps.remove(comps.len() - 1);
+    let relative = dir == "." || dir == ".." || dir.starts_with("./") || dir.starts_with("../");
+    if relative == false {
+        if let Some(cdpath) = cdpath {
+            for base in cdpath.split(":") {
+                // An empty entry refers to the current directory
+                if base.len() <= 0 {
+                    candidates.push((join(current, dir), false));
+                } else if base.starts_with("/") {
+                    candidates.push((join(base, dir), true));
+                } else {
+                    let base = join(current, base);
+                    candidates.push((join(base.as_str(), dir), true));
+                }
             }
-            continue;
         }
-        comps.push(comp);
     }
+    candidates.push((join(current, dir), false));
+    candidates
+}
 
-    ret += "/";
-    for comp in comps {
-        if ret.ends_with("/") == false {
-            ret += "/";
-        }
-        ret += comp;
-    }
-    ret
+fn join(base: &str, path: &str) -> String {
+    normalize_path(format!("{}/{}", base, path).as_str())
 }
 
 fn home(ctx: &EvalContext) -> String {
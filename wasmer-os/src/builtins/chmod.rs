@@ -0,0 +1,68 @@
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::eval::EvalContext;
+use crate::eval::ExecResponse;
+use crate::fs::AsyncifyFileSystem;
+use crate::stdio::*;
+use crate::tty::*;
+
+pub(super) fn chmod(
+    args: &[String],
+    ctx: EvalContext,
+    mut stdio: Stdio,
+) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
+    // Only the executable bits are kept (the file systems have no others)
+    let executable = match args.get(1).map(|a| a.as_str()) {
+        Some("+x") | Some("a+x") | Some("u+x") => Some(true),
+        Some("-x") | Some("a-x") | Some("u-x") => Some(false),
+        Some(mode) => u32::from_str_radix(mode, 8)
+            .ok()
+            .filter(|a| *a <= 0o7777)
+            .map(|a| a & 0o111 != 0),
+        None => None,
+    };
+    let executable = match executable {
+        Some(a) if args.len() > 2 => a,
+        _ => {
+            return Box::pin(async move {
+                print(Tty::CHMOD_USAGE.to_string(), &mut stdio).await;
+                ExecResponse::Immediate(ctx, 1)
+            });
+        }
+    };
+
+    let files = args
+        .iter()
+        .skip(2)
+        .map(|file| {
+            if file.starts_with("/") {
+                file.clone()
+            } else {
+                format!("{}{}", ctx.working_dir, file)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Box::pin(async move {
+        let fs = AsyncifyFileSystem::new(ctx.root.clone());
+        let mut ret = 0;
+        for file in files {
+            if let Err(err) = fs.metadata(Path::new(file.as_str())).await {
+                print(format!("chmod: {}: {}\r\n", file, err), &mut stdio).await;
+                ret = 1;
+                continue;
+            }
+            ctx.root.exec.set(file.as_str(), executable);
+        }
+        ExecResponse::Immediate(ctx, ret)
+    })
+}
+
+async fn print(msg: String, stdio: &mut Stdio) {
+    let _ = stdio.stderr.write(msg.as_bytes()).await;
+    let _ = stdio.stderr.flush_async().await;
+}
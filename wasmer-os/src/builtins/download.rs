@@ -0,0 +1,103 @@
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use tokio::sync::mpsc;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::api::*;
+use crate::err;
+use crate::eval::EvalContext;
+use crate::eval::ExecResponse;
+use crate::fs::AsyncifyFileSystem;
+use crate::stdio::*;
+
+/// Size of the chunks that are read from the file and handed to the browser
+const DOWNLOAD_CHUNK_SIZE: usize = 262144;
+/// Number of chunks that may be waiting to be handed to the browser
+const DOWNLOAD_BUFFER: usize = 4;
+
+pub(super) fn download(
+    args: &[String],
+    ctx: EvalContext,
+    mut stdio: Stdio,
+) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
+    if args.len() != 2 {
+        return Box::pin(async move {
+            let _ = stdio
+                .stderr
+                .write(format!("usage: download <file>\r\n").as_bytes())
+                .await;
+            ExecResponse::Immediate(ctx, err::ERR_EINVAL)
+        });
+    }
+
+    let mut path = args[1].clone();
+    if path.starts_with("/") == false {
+        path.insert_str(0, ctx.working_dir.as_str());
+    }
+    let name = match Path::new(path.as_str()).file_name() {
+        Some(a) => a.to_string_lossy().to_string(),
+        None => {
+            return Box::pin(async move {
+                let _ = stdio
+                    .stderr
+                    .write(format!("download: {}: Is a directory\r\n", path).as_bytes())
+                    .await;
+                ExecResponse::Immediate(ctx, err::ERR_EISDIR)
+            });
+        }
+    };
+
+    Box::pin(async move {
+        let file = AsyncifyFileSystem::new(ctx.root.clone())
+            .new_open_options()
+            .await
+            .read(true)
+            .open(&Path::new(path.as_str()))
+            .await;
+        let file = match file {
+            Ok(a) => a,
+            Err(_) => {
+                let _ = stdio
+                    .stderr
+                    .write(format!("download: {}: No such file\r\n", path).as_bytes())
+                    .await;
+                return ExecResponse::Immediate(ctx, err::ERR_ENOENT);
+            }
+        };
+
+        // The file is streamed to the browser one chunk at a time
+        let (tx, rx) = mpsc::channel(DOWNLOAD_BUFFER);
+        let result = System::default().download(name.as_str(), rx);
+        loop {
+            let chunk = match file.read(DOWNLOAD_CHUNK_SIZE).await {
+                Ok(a) if a.len() > 0 => a,
+                Ok(_) => break,
+                Err(e) => {
+                    let _ = stdio
+                        .stderr
+                        .write(format!("download: {}: {}\r\n", path, e).as_bytes())
+                        .await;
+                    return ExecResponse::Immediate(ctx, err::ERR_EIO);
+                }
+            };
+            if tx.send(chunk).await.is_err() {
+                break;
+            }
+        }
+        drop(tx);
+
+        match result.await {
+            Some(Ok(())) => ExecResponse::Immediate(ctx, err::ERR_OK),
+            Some(Err(e)) => {
+                let _ = stdio
+                    .stderr
+                    .write(format!("download: {}\r\n", e).as_bytes())
+                    .await;
+                ExecResponse::Immediate(ctx, err::ERR_ENOSYS)
+            }
+            None => ExecResponse::Immediate(ctx, err::ERR_EIO),
+        }
+    })
+}
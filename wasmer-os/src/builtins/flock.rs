@@ -0,0 +1,187 @@
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::time::Duration;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+use wasmer_bus_fuse::api::LockKind;
+use wasmer_bus_fuse::api::LockOptions;
+use wasmer_vfs::FsError;
+
+use crate::api::*;
+use crate::err;
+use crate::eval::eval;
+use crate::eval::EvalContext;
+use crate::eval::EvalStatus;
+use crate::eval::ExecResponse;
+use crate::fs::MountedFileSystem;
+use crate::stdio::*;
+use crate::tty::*;
+
+#[derive(Debug, Clone)]
+pub(super) struct FlockArgs {
+    pub options: LockOptions,
+    pub file: String,
+    pub command: String,
+}
+
+/// Parses the arguments of flock, the error is the message to show
+pub(super) fn parse_flock_args(args: &[String]) -> Result<FlockArgs, String> {
+    let mut options = LockOptions::default();
+    let mut file = None;
+
+    let mut n = 1usize;
+    while n < args.len() {
+        match args[n].as_str() {
+            "-s" | "--shared" => options.kind = LockKind::Shared,
+            "-x" | "-e" | "--exclusive" => options.kind = LockKind::Exclusive,
+            "-n" | "--nonblock" => {
+                options.wait = false;
+                options.timeout = None;
+            }
+            "-w" | "--timeout" => {
+                n += 1;
+                match args.get(n).and_then(|a| a.parse::<f64>().ok()) {
+                    Some(a) if a >= 0.0 => {
+                        options.wait = true;
+                        options.timeout = Some(Duration::from_secs_f64(a));
+                    }
+                    _ => {
+                        return Err(format!("flock: invalid timeout\r\n"));
+                    }
+                }
+            }
+            a if a.starts_with("-") == false => {
+                file = Some(a.to_string());
+                n += 1;
+                break;
+            }
+            a => {
+                return Err(format!("flock: invalid argument '{}'\r\n", a));
+            }
+        }
+        n += 1;
+    }
+
+    let command = match args.get(n).map(|a| a.as_str()) {
+        Some("-c") | Some("--command") if args.len() == n + 2 => args[n + 1].clone(),
+        Some("-c") | Some("--command") => {
+            return Err(Tty::FLOCK_USAGE.to_string());
+        }
+        Some(_) => args[n..]
+            .iter()
+            .map(|a| quote_arg(a.as_str()))
+            .collect::<Vec<_>>()
+            .join(" "),
+        None => {
+            return Err(Tty::FLOCK_USAGE.to_string());
+        }
+    };
+
+    match file {
+        Some(file) => Ok(FlockArgs {
+            options,
+            file,
+            command,
+        }),
+        None => Err(Tty::FLOCK_USAGE.to_string()),
+    }
+}
+
+/// Quotes an argument so that it is evaluated again as the same word
+fn quote_arg(arg: &str) -> String {
+    let plain = arg.len() > 0
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    match plain {
+        true => arg.to_string(),
+        false => format!("'{}'", arg.replace("'", "'\"'\"'")),
+    }
+}
+
+pub(super) fn flock(
+    args: &[String],
+    mut ctx: EvalContext,
+    mut stdio: Stdio,
+) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
+    let FlockArgs {
+        options,
+        mut file,
+        command,
+    } = match parse_flock_args(args) {
+        Ok(a) => a,
+        Err(msg) => {
+            return Box::pin(async move {
+                print(msg, &mut stdio).await;
+                ExecResponse::Immediate(ctx, 1)
+            });
+        }
+    };
+    if file.starts_with("/") == false {
+        file.insert_str(0, ctx.working_dir.as_str());
+    }
+
+    Box::pin(async move {
+        // The lock belongs to the command (as it would to the file descriptor
+        // it was taken on) hence it is released as soon as the command exits
+        let root = ctx.root.clone();
+        let path = file.clone();
+        let lock = System::default()
+            .spawn_dedicated_async(move || async move {
+                root.lock(Path::new(path.as_str()), options)
+            })
+            .await
+            .unwrap_or(Err(FsError::UnknownError));
+        let lock = match lock {
+            Ok(a) => a,
+            Err(FsError::WouldBlock) | Err(FsError::TimedOut) => {
+                print(format!("flock: {}: lock is held elsewhere\r\n", file), &mut stdio).await;
+                return ExecResponse::Immediate(ctx, 1);
+            }
+            Err(err) => {
+                print(format!("flock: {}: {}\r\n", file, err), &mut stdio).await;
+                return ExecResponse::Immediate(ctx, 1);
+            }
+        };
+
+        ctx.stdio = stdio;
+        let mut stderr = ctx.stdio.stderr.clone();
+        let mut process = eval(command, ctx);
+        let result = process.recv().await;
+        drop(process);
+
+        let unlocked = System::default()
+            .spawn_dedicated_async(move || async move { lock.unlock() })
+            .await;
+        if let Some(Err(err)) = unlocked {
+            debug!("flock: failed to release the lock on {} - {}", file, err);
+        }
+
+        let result = match result {
+            Some(a) => a,
+            None => {
+                let _ = stderr
+                    .write(format!("flock: command failed\r\n").as_bytes())
+                    .await;
+                return ExecResponse::OrphanedImmediate(err::ERR_EINTR);
+            }
+        };
+        match result.status {
+            EvalStatus::Executed { code, .. } => ExecResponse::Immediate(result.ctx, code),
+            EvalStatus::InternalError => {
+                let _ = stderr.write("flock: internal error\r\n".as_bytes()).await;
+                ExecResponse::Immediate(result.ctx, err::ERR_EINTR)
+            }
+            EvalStatus::MoreInput | EvalStatus::Invalid => {
+                let _ = stderr.write("flock: invalid command\r\n".as_bytes()).await;
+                ExecResponse::Immediate(result.ctx, err::ERR_EINVAL)
+            }
+        }
+    })
+}
+
+async fn print(msg: String, stdio: &mut Stdio) {
+    let _ = stdio.stderr.write(msg.as_bytes()).await;
+    let _ = stdio.stderr.flush_async().await;
+}
@@ -0,0 +1,72 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::err;
+use crate::eval::EvalContext;
+use crate::eval::ExecResponse;
+use crate::eval::Flow;
+use crate::stdio::*;
+
+pub(super) fn break_(
+    args: &[String],
+    ctx: EvalContext,
+    stdio: Stdio,
+) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
+    loop_flow("break", args, ctx, stdio, Flow::Break)
+}
+
+pub(super) fn continue_(
+    args: &[String],
+    ctx: EvalContext,
+    stdio: Stdio,
+) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
+    loop_flow("continue", args, ctx, stdio, Flow::Continue)
+}
+
+pub(super) fn return_(
+    args: &[String],
+    mut ctx: EvalContext,
+    mut stdio: Stdio,
+) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
+    // Without an argument the status of the last command is returned
+    let code = match args.get(1).map(|a| a.parse::<u32>()) {
+        Some(Ok(a)) => a,
+        Some(Err(_)) => {
+            return Box::pin(async move {
+                let _ = stdio
+                    .stderr
+                    .write(format!("return: numeric argument required\r\n").as_bytes())
+                    .await;
+                ExecResponse::Immediate(ctx, err::ERR_EINVAL)
+            });
+        }
+        None => ctx.last_return,
+    };
+    ctx.flow = Some(Flow::Return);
+    Box::pin(async move { ExecResponse::Immediate(ctx, code) })
+}
+
+fn loop_flow(
+    name: &'static str,
+    args: &[String],
+    mut ctx: EvalContext,
+    mut stdio: Stdio,
+    flow: fn(u32) -> Flow,
+) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
+    // The argument is the number of enclosing loops that are affected
+    let levels = match args.get(1).map(|a| a.parse::<u32>()) {
+        Some(Ok(a)) if a > 0 => a,
+        None => 1,
+        _ => {
+            return Box::pin(async move {
+                let _ = stdio
+                    .stderr
+                    .write(format!("{}: loop count out of range\r\n", name).as_bytes())
+                    .await;
+                ExecResponse::Immediate(ctx, err::ERR_EINVAL)
+            });
+        }
+    };
+    ctx.flow = Some(flow(levels));
+    Box::pin(async move { ExecResponse::Immediate(ctx, 0) })
+}
@@ -4,7 +4,6 @@ use std::pin::Pin;
 use crate::eval::EvalContext;
 use crate::eval::ExecResponse;
 use crate::stdio::*;
-use crate::tty::Tty;
 
 pub(super) fn help(
     _args: &[String],
@@ -12,7 +11,8 @@ pub(super) fn help(
     mut stdio: Stdio,
 ) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
     Box::pin(async move {
-        let _ = stdio.stdout.write(Tty::HELP.as_bytes()).await;
+        let help = ctx.exec_factory.builtins().help_text();
+        let _ = stdio.stdout.write(help.as_bytes()).await;
         ExecResponse::Immediate(ctx, 0)
     })
 }
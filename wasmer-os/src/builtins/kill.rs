@@ -0,0 +1,75 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::err;
+use crate::eval::EvalContext;
+use crate::eval::ExecResponse;
+use crate::signal::*;
+use crate::stdio::*;
+use crate::tty::Tty;
+
+pub(super) fn kill(
+    args: &[String],
+    ctx: EvalContext,
+    mut stdio: Stdio,
+) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
+    let mut sig = Signal::Term;
+    let mut pids = Vec::new();
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "-l" | "--list" => {
+                let list = Signal::ALL
+                    .iter()
+                    .map(|a| format!("{}) SIG{}", a.number(), a.name()))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                return Box::pin(async move {
+                    let _ = stdio.stdout.write(format!("{}\r\n", list).as_bytes()).await;
+                    ExecResponse::Immediate(ctx, 0)
+                });
+            }
+            "-s" => args
+                .next()
+                .ok_or_else(|| "option requires an argument -- s".to_string())
+                .and_then(|a| Signal::from_str(a))
+                .map(|a| sig = a),
+            a if a.starts_with("-") => Signal::from_str(&a[1..]).map(|a| sig = a),
+            a => u32::from_str(a)
+                .map_err(|_| format!("invalid process id ({})", a))
+                .map(|a| pids.push(a)),
+        };
+        if let Err(err) = parsed {
+            return Box::pin(async move {
+                let _ = stdio.stderr.write(format!("kill: {}\r\n", err).as_bytes()).await;
+                ExecResponse::Immediate(ctx, err::ERR_EINVAL)
+            });
+        }
+    }
+
+    Box::pin(async move {
+        if pids.is_empty() {
+            let _ = stdio.stderr.write(Tty::KILL_USAGE.as_bytes()).await;
+            return ExecResponse::Immediate(ctx, err::ERR_EINVAL);
+        }
+
+        let mut ret = 0;
+        for pid in pids {
+            let result = {
+                let mut reactor = ctx.reactor.write().await;
+                reactor.signal_process(pid, sig)
+            };
+            if let Err(code) = result {
+                let _ = stdio
+                    .stderr
+                    .write(format!("kill: ({}) - {}\r\n", pid, err::exit_code_to_message(code)).as_bytes())
+                    .await;
+                ret = code;
+            }
+        }
+        ExecResponse::Immediate(ctx, ret)
+    })
+}
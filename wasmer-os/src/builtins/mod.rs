@@ -1,28 +1,43 @@
 mod about;
 mod cd;
+mod chmod;
+mod download;
 mod exit;
 mod export;
+mod flock;
+mod flow;
 mod help;
+mod kill;
 mod mount;
 mod pwd;
 mod readonly;
 mod reset;
+mod select;
 mod source;
+mod truth;
 mod umount;
 mod unset;
 mod wax;
 mod call;
+mod tests;
 
 use about::*;
 use cd::*;
+use chmod::*;
+use download::*;
 use exit::*;
 use export::*;
+use flock::*;
+use flow::*;
 use help::*;
+use kill::*;
 use mount::*;
 use pwd::*;
 use readonly::*;
 use reset::*;
+use select::*;
 use source::*;
+use truth::*;
 use umount::*;
 use unset::*;
 use wax::*;
@@ -31,22 +46,74 @@ use call::*;
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 
+use super::err;
 use super::eval::EvalContext;
 use super::eval::ExecResponse;
 use super::stdio::*;
+use super::tty::Tty;
 
 pub type Command = fn(&[String], EvalContext, Stdio) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>>;
 
-#[derive(Default)]
+/// Command that the host application embeds into the console, its future
+/// runs on the shared worker pool
+pub type CustomCommand = Arc<
+    dyn Fn(Vec<String>, EvalContext, Stdio) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Command that the host application embeds into the console whose future
+/// is not Send, it runs on a dedicated thread of the worker pool instead
+pub type CustomCommandLocal = Arc<
+    dyn Fn(Vec<String>, EvalContext, Stdio) -> Pin<Box<dyn Future<Output = ExecResponse>>>
+        + Send
+        + Sync,
+>;
+
+#[derive(Clone)]
+pub enum Builtin {
+    Native(Command),
+    Shared(CustomCommand),
+    Local(CustomCommandLocal),
+}
+
+impl Builtin {
+    pub async fn run(&self, args: &[String], ctx: EvalContext, stdio: Stdio) -> ExecResponse {
+        match self {
+            Builtin::Native(cmd) => cmd(args, ctx, stdio).await,
+            Builtin::Shared(cmd) => cmd(args.to_vec(), ctx, stdio).await,
+            Builtin::Local(cmd) => {
+                // If the dedicated thread goes away without an answer then the
+                // context from before the command is handed back
+                let system = ctx.system;
+                let fallback = ctx.clone();
+                let cmd = cmd.clone();
+                let args = args.to_vec();
+                let ret = system
+                    .spawn_dedicated_async(move || async move { cmd(args, ctx, stdio).await })
+                    .await;
+                ret.unwrap_or_else(|| ExecResponse::Immediate(fallback, err::ERR_ECONNABORTED))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
 pub struct Builtins {
-    commands: HashMap<String, Command>,
+    commands: HashMap<String, Builtin>,
+    /// Custom commands in the order they were added and their descriptions
+    custom: Vec<(String, String)>,
+    /// Replaces the text that `help` shows before the custom commands
+    help: Option<String>,
 }
 
 impl Builtins {
     pub fn new() -> Builtins {
         let mut b: Builtins = Default::default();
         b.insert("cd", cd);
+        b.insert("chmod", chmod);
         b.insert("call", call);
         b.insert("export", export);
         b.insert("readonly", readonly);
@@ -58,18 +125,65 @@ impl Builtins {
         b.insert("reset", reset);
         b.insert("mount", mount);
         b.insert("umount", umount);
+        b.insert("flock", flock);
+        b.insert("kill", kill);
+        b.insert("select", select);
         b.insert("unmount", umount);
         b.insert("wax", wax);
+        b.insert("download", download);
         b.insert("exit", exit);
         b.insert("quit", exit);
+        b.insert("true", true_);
+        b.insert(":", true_);
+        b.insert("false", false_);
+        b.insert("break", break_);
+        b.insert("continue", continue_);
+        b.insert("return", return_);
         b
     }
 
     fn insert(&mut self, key: &str, val: Command) {
-        self.commands.insert(key.to_string(), val);
+        self.commands.insert(key.to_string(), Builtin::Native(val));
     }
 
-    pub fn get(&self, key: &String) -> Option<&Command> {
+    /// Adds a command from the host application, names that are already
+    /// taken by another builtin are rejected with ERR_EEXIST
+    pub fn add(&mut self, name: &str, description: &str, cmd: Builtin) -> Result<(), u32> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(err::ERR_EINVAL);
+        }
+        if self.commands.contains_key(name) {
+            return Err(err::ERR_EEXIST);
+        }
+        self.commands.insert(name.to_string(), cmd);
+        self.custom.push((name.to_string(), description.to_string()));
+        Ok(())
+    }
+
+    pub fn set_help(&mut self, help: Option<String>) {
+        self.help = help;
+    }
+
+    pub fn get(&self, key: &String) -> Option<&Builtin> {
         self.commands.get(key)
     }
+
+    /// Text written by the `help` builtin
+    pub fn help_text(&self) -> String {
+        let mut ret = match &self.help {
+            Some(a) => a.clone(),
+            None => Tty::HELP.to_string(),
+        };
+        if self.custom.is_empty() == false {
+            if ret.ends_with("\n") == false {
+                ret.push_str("\n");
+            }
+            ret.push_str("\n## custom commands:\n\n");
+            let width = self.custom.iter().map(|a| a.0.len()).max().unwrap_or(0);
+            for (name, description) in self.custom.iter() {
+                ret.push_str(format!("    {:width$}  {}\n", name, description, width = width).as_str());
+            }
+        }
+        ret
+    }
 }
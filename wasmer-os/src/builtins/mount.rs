@@ -3,6 +3,7 @@ use std::path::Path;
 use std::pin::Pin;
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
+use wasmer_bus_fuse::api::MountOptions;
 use wasmer_bus_process::prelude::StdioMode;
 use wasmer_vfs::FileSystem;
 
@@ -20,6 +21,40 @@ pub(super) fn mount(
     mut ctx: EvalContext,
     mut stdio: Stdio,
 ) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
+    // Pull out the mount options (e.g. -o casefold) before the positional arguments
+    let mut options = MountOptions::default();
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg.as_str() != "-o" {
+            positional.push(arg.clone());
+            continue;
+        }
+        let opts = match iter.next() {
+            Some(a) => a.clone(),
+            None => {
+                return Box::pin(async move {
+                    print(format!("mount: option -o requires an argument\r\n"), &mut stdio, true).await;
+                    ExecResponse::Immediate(ctx, 1)
+                });
+            }
+        };
+        for opt in opts.split(',').filter(|a| a.len() > 0) {
+            match opt {
+                "casefold" => options.casefold = true,
+                "nocasefold" => options.casefold = false,
+                a => {
+                    let msg = format!("mount: unknown mount option ({})\r\n", a);
+                    return Box::pin(async move {
+                        print(msg, &mut stdio, true).await;
+                        ExecResponse::Immediate(ctx, 1)
+                    });
+                }
+            }
+        }
+    }
+    let args = &positional[..];
+
     let wapm: String;
     let mountpoint: String;
     let target: String;
@@ -112,7 +147,7 @@ pub(super) fn mount(
 
         print(format!("Executing the mount\r\n"), &mut stdio, false).await;
 
-        let fs = match FuseFileSystem::new(sub_process, target.as_str(), stdio.clone()).await {
+        let fs = match FuseFileSystem::new(sub_process, target.as_str(), options, stdio.clone()).await {
             Ok(a) => a,
             Err(err) => {
                 print(
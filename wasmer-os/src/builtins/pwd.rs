@@ -5,24 +5,53 @@ use crate::eval::EvalContext;
 use crate::eval::ExecResponse;
 use crate::stdio::*;
 
+/// Returns true when the physical path is to be printed, the error is the
+/// message to show
+pub(super) fn parse_pwd_args(args: &[String]) -> Result<bool, String> {
+    // -L prints the logical path while -P resolves it through the mounts
+    let mut physical = false;
+    for arg in args.iter().skip(1) {
+        match arg.as_str() {
+            "-L" => physical = false,
+            "-P" => physical = true,
+            arg if arg.starts_with("-") => {
+                return Err(format!("pwd: {}: invalid option\r\n", arg));
+            }
+            _ => {
+                return Err(format!("pwd: too many arguments\r\n"));
+            }
+        }
+    }
+    Ok(physical)
+}
+
 pub(super) fn pwd(
     args: &[String],
     ctx: EvalContext,
     mut stdio: Stdio,
 ) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
-    if args.len() > 1 {
-        return Box::pin(async move {
-            let _ = stdio
-                .stderr
-                .write(format!("pwd: too many arguments\r\n").as_bytes())
-                .await;
-            ExecResponse::Immediate(ctx, 0)
-        });
-    }
+    let physical = match parse_pwd_args(args) {
+        Ok(a) => a,
+        Err(msg) => {
+            return Box::pin(async move {
+                let _ = stdio.stderr.write(msg.as_bytes()).await;
+                ExecResponse::Immediate(ctx, 0)
+            });
+        }
+    };
     Box::pin(async move {
+        let path = if physical {
+            let mut path = ctx.root.physical_path(ctx.working_dir.as_str());
+            if path.ends_with("/") == false {
+                path += "/";
+            }
+            path
+        } else {
+            ctx.working_dir.clone()
+        };
         let _ = stdio
             .stdout
-            .write(format!("{}\r\n", ctx.working_dir).as_bytes())
+            .write(format!("{}\r\n", path).as_bytes())
             .await;
         ExecResponse::Immediate(ctx, 0)
     })
@@ -0,0 +1,104 @@
+use ate::prelude::Query;
+use std::future::Future;
+use std::pin::Pin;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+use wasmer_bus_fuse::prelude::LineSplitter;
+
+use crate::err;
+use crate::eval::EvalContext;
+use crate::eval::ExecResponse;
+use crate::fd::FdMsg;
+use crate::stdio::*;
+use crate::tty::Tty;
+
+/// Filters the rows (JSON lines) that are piped into it with a query and
+/// writes the ones that match to stdout (e.g. the output of `atedb query`)
+pub(super) fn select(
+    args: &[String],
+    ctx: EvalContext,
+    mut stdio: Stdio,
+) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
+    let mut max = None;
+    let mut expr = None;
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "-n" | "--max" => args
+                .next()
+                .and_then(|a| a.parse::<u64>().ok())
+                .map(|a| max = Some(a))
+                .ok_or_else(|| "option requires a number -- n".to_string()),
+            a if expr.is_none() && a.starts_with("-") == false => {
+                Query::parse(a)
+                    .map(|a| expr = Some(a))
+                    .map_err(|err| err.to_string())
+            }
+            a => Err(format!("invalid argument '{}'", a)),
+        };
+        if let Err(err) = parsed {
+            return Box::pin(async move {
+                let _ = stdio.stderr.write(format!("select: {}\r\n", err).as_bytes()).await;
+                ExecResponse::Immediate(ctx, err::ERR_EINVAL)
+            });
+        }
+    }
+
+    Box::pin(async move {
+        let query = match expr {
+            Some(a) => a,
+            None => {
+                let _ = stdio.stderr.write(Tty::SELECT_USAGE.as_bytes()).await;
+                return ExecResponse::Immediate(ctx, err::ERR_EINVAL);
+            }
+        };
+
+        if max == Some(0) {
+            return ExecResponse::Immediate(ctx, 0);
+        }
+
+        let mut lines = LineSplitter::new();
+        let mut matches = 0u64;
+        let mut invalid = 0u64;
+        loop {
+            let data = match stdio.stdin.read_async().await {
+                Ok(FdMsg::Data { data, .. }) => data,
+                Ok(FdMsg::Flush { .. }) => continue,
+                Err(_) => Vec::new(),
+            };
+            let eof = data.is_empty();
+
+            for (_, line) in lines.push(&data[..]) {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let row = match serde_json::from_str::<serde_json::Value>(line.as_str()) {
+                    Ok(a) => a,
+                    Err(_) => {
+                        invalid += 1;
+                        continue;
+                    }
+                };
+                if query.is_match(&row) == false {
+                    continue;
+                }
+                let _ = stdio.stdout.write(format!("{}\r\n", row).as_bytes()).await;
+                matches += 1;
+                if max.map(|a| matches >= a).unwrap_or(false) {
+                    return ExecResponse::Immediate(ctx, 0);
+                }
+            }
+            if eof {
+                break;
+            }
+        }
+
+        if invalid > 0 {
+            let _ = stdio
+                .stderr
+                .write(format!("select: skipped {} lines that are not JSON\r\n", invalid).as_bytes())
+                .await;
+        }
+        ExecResponse::Immediate(ctx, 0)
+    })
+}
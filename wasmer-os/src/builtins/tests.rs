@@ -0,0 +1,155 @@
+#![cfg(test)]
+use super::*;
+
+fn shared() -> Builtin {
+    let cmd: CustomCommand = Arc::new(|_args: Vec<String>, ctx: EvalContext, _stdio: Stdio| {
+        let ret: Pin<Box<dyn Future<Output = ExecResponse> + Send>> =
+            Box::pin(async move { ExecResponse::Immediate(ctx, 0) });
+        ret
+    });
+    Builtin::Shared(cmd)
+}
+
+fn local() -> Builtin {
+    let cmd: CustomCommandLocal = Arc::new(|_args: Vec<String>, ctx: EvalContext, _stdio: Stdio| {
+        let ret: Pin<Box<dyn Future<Output = ExecResponse>>> =
+            Box::pin(async move { ExecResponse::Immediate(ctx, 0) });
+        ret
+    });
+    Builtin::Local(cmd)
+}
+
+#[test]
+fn test_builtins_add() {
+    let mut builtins = Builtins::new();
+    assert_eq!(builtins.add("deploy", "Deploys the app", shared()), Ok(()));
+    assert_eq!(builtins.add("render", "Renders a frame", local()), Ok(()));
+
+    // Each kind of command is dispatched the way it was registered
+    assert!(matches!(builtins.get(&"deploy".to_string()), Some(Builtin::Shared(_))));
+    assert!(matches!(builtins.get(&"render".to_string()), Some(Builtin::Local(_))));
+    assert!(matches!(builtins.get(&"cd".to_string()), Some(Builtin::Native(_))));
+    assert!(builtins.get(&"missing".to_string()).is_none());
+}
+
+#[test]
+fn test_builtins_add_rejected() {
+    let mut builtins = Builtins::new();
+
+    // Names of builtins (or of commands added before) can not be taken over
+    assert_eq!(builtins.add("cd", "Not cd", shared()), Err(err::ERR_EEXIST));
+    assert_eq!(builtins.add("deploy", "Deploys the app", shared()), Ok(()));
+    assert_eq!(builtins.add("deploy", "Deploys again", local()), Err(err::ERR_EEXIST));
+    assert!(matches!(builtins.get(&"cd".to_string()), Some(Builtin::Native(_))));
+    assert!(matches!(builtins.get(&"deploy".to_string()), Some(Builtin::Shared(_))));
+
+    // Names must be a single word
+    assert_eq!(builtins.add("", "Empty", shared()), Err(err::ERR_EINVAL));
+    assert_eq!(builtins.add("two words", "Spaces", shared()), Err(err::ERR_EINVAL));
+    assert_eq!(builtins.add("tab\tname", "Tabs", shared()), Err(err::ERR_EINVAL));
+    let help = builtins.help_text();
+    assert!(help.contains("Deploys again") == false);
+    assert!(help.contains("Spaces") == false);
+}
+
+#[test]
+fn test_builtins_help_text() {
+    let mut builtins = Builtins::new();
+    assert_eq!(builtins.help_text(), Tty::HELP.to_string());
+
+    // Custom commands are listed after the help in the order they were added
+    builtins.add("deploy", "Deploys the app", shared()).unwrap();
+    builtins.add("db-migrate", "Migrates the database", local()).unwrap();
+    let help = builtins.help_text();
+    assert!(help.starts_with(Tty::HELP));
+    let deploy = help.find("    deploy      Deploys the app\n").unwrap();
+    let migrate = help.find("    db-migrate  Migrates the database\n").unwrap();
+    assert!(help.find("## custom commands:").unwrap() < deploy);
+    assert!(deploy < migrate);
+
+    // The text before the custom commands can be replaced
+    builtins.set_help(Some("my app".to_string()));
+    let help = builtins.help_text();
+    assert!(help.starts_with("my app\n\n## custom commands:\n\n"));
+    assert!(help.contains("Deploys the app"));
+}
+
+fn flock_args(args: &[&str]) -> Result<FlockArgs, String> {
+    let args = args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    parse_flock_args(&args[..])
+}
+
+#[test]
+fn test_flock_args() {
+    // Contended locks fail straight away unless a timeout is given
+    let args = flock_args(&["flock", "/www/deploy.lock", "echo", "hi"]).unwrap();
+    assert_eq!(args.options.kind, wasmer_bus_fuse::api::LockKind::Exclusive);
+    assert!(args.options.wait == false);
+    assert_eq!(args.file, "/www/deploy.lock");
+    assert_eq!(args.command, "echo hi");
+
+    let args = flock_args(&["flock", "-s", "-w", "1.5", "a.lock", "ls"]).unwrap();
+    assert_eq!(args.options.kind, wasmer_bus_fuse::api::LockKind::Shared);
+    assert!(args.options.wait);
+    assert_eq!(args.options.timeout, Some(std::time::Duration::from_millis(1500)));
+
+    // Arguments of the command are passed on as they were given
+    let args = flock_args(&["flock", "a.lock", "echo", "two words", "it's", "-n"]).unwrap();
+    assert_eq!(args.command, "echo 'two words' 'it'\"'\"'s' -n");
+    let args = flock_args(&["flock", "-n", "a.lock", "-c", "echo a | wc"]).unwrap();
+    assert_eq!(args.command, "echo a | wc");
+
+    // There is nothing to hold the lock without a command
+    assert_eq!(flock_args(&["flock", "a.lock"]).err(), Some(Tty::FLOCK_USAGE.to_string()));
+    assert_eq!(flock_args(&["flock", "a.lock", "-c"]).err(), Some(Tty::FLOCK_USAGE.to_string()));
+    assert!(flock_args(&["flock", "-w", "soon", "a.lock", "ls"]).is_err());
+    assert!(flock_args(&["flock", "-u", "a.lock", "ls"]).is_err());
+}
+
+fn candidates(dir: &str, cdpath: Option<&str>) -> Vec<(String, bool)> {
+    cd_candidates(dir, "/home/", cdpath)
+}
+
+fn paths(paths: &[(&str, bool)]) -> Vec<(String, bool)> {
+    paths.iter().map(|(a, b)| (a.to_string(), *b)).collect()
+}
+
+#[test]
+fn test_cd_candidates() {
+    // Without CDPATH the directory is relative to the current one
+    assert_eq!(candidates("src", None), paths(&[("/home/src", false)]));
+    assert_eq!(candidates("/opt/../srv", None), paths(&[("/srv", false)]));
+
+    // CDPATH is searched in order (before the current directory) and the
+    // path is printed when it came from one of its entries
+    assert_eq!(
+        candidates("app", Some("/www:lib::/opt")),
+        paths(&[
+            ("/www/app", true),
+            ("/home/lib/app", true),
+            ("/home/app", false),
+            ("/opt/app", true),
+            ("/home/app", false),
+        ])
+    );
+
+    // Explicitly relative and absolute paths skip CDPATH
+    assert_eq!(candidates("./app", Some("/www")), paths(&[("/home/app", false)]));
+    assert_eq!(candidates("..", Some("/www")), paths(&[("/", false)]));
+    assert_eq!(candidates("/app", Some("/www")), paths(&[("/app", false)]));
+}
+
+fn pwd_args(args: &[&str]) -> Result<bool, String> {
+    let args = args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    parse_pwd_args(&args[..])
+}
+
+#[test]
+fn test_pwd_args() {
+    assert_eq!(pwd_args(&["pwd"]), Ok(false));
+    assert_eq!(pwd_args(&["pwd", "-P"]), Ok(true));
+    assert_eq!(pwd_args(&["pwd", "-P", "-L"]), Ok(false));
+    assert_eq!(pwd_args(&["pwd", "-L", "-P"]), Ok(true));
+    assert_eq!(pwd_args(&["pwd", "-x"]), Err("pwd: -x: invalid option\r\n".to_string()));
+    assert_eq!(pwd_args(&["pwd", "/tmp"]), Err("pwd: too many arguments\r\n".to_string()));
+}
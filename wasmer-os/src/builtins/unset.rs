@@ -10,8 +10,15 @@ pub(super) fn unset(
     mut ctx: EvalContext,
     _stdio: Stdio,
 ) -> Pin<Box<dyn Future<Output = ExecResponse> + Send>> {
-    for arg in &args[1..] {
-        ctx.env.unset(arg.as_str());
+    // With -f the names refer to shell functions rather than variables
+    let functions = args.get(1).map(|a| a == "-f").unwrap_or(false);
+    let names = if functions { &args[2..] } else { &args[1..] };
+    for arg in names {
+        if functions {
+            ctx.env.unset_function(arg.as_str());
+        } else {
+            ctx.env.unset(arg.as_str());
+        }
     }
     Box::pin(async move { ExecResponse::Immediate(ctx, 0) })
 }
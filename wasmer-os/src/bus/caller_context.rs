@@ -12,6 +12,9 @@ use std::task::Poll;
 use tokio::sync::mpsc;
 use tracing::trace;
 
+use crate::err;
+use crate::signal::Signal;
+
 #[derive(Debug)]
 pub struct WasmCheckpoint {
     rx: Mutex<Option<mpsc::Receiver<()>>>,
@@ -91,6 +94,10 @@ for WasmCheckpoint
 #[derive(Debug, Clone)]
 pub struct WasmCallerContext {
     forced_exit: Arc<AtomicU32>,
+    // Signals that were delivered to the process but not yet read by it
+    signals: Arc<AtomicU32>,
+    // Set when the process handles signals itself (by opening /dev/signal)
+    signal_handler: Arc<AtomicBool>,
     // The second checkpoint is after the start method completes but before
     // all the background threads exit
     checkpoint2: Arc<WasmCheckpoint>,
@@ -102,6 +109,8 @@ impl WasmCallerContext
     {
         WasmCallerContext {
             forced_exit: Arc::new(AtomicU32::new(0)),
+            signals: Arc::new(AtomicU32::new(0)),
+            signal_handler: Arc::new(AtomicBool::new(false)),
             checkpoint2: checkpoint2.clone(),
         }
     }
@@ -130,6 +139,45 @@ impl WasmCallerContext {
         }
     }
 
+    /// Delivers a signal to the process, returns true if the process will
+    /// handle it or false if the default disposition (terminate) was applied
+    pub fn signal(&self, sig: Signal) -> bool {
+        if sig.can_handle() && self.has_signal_handler() {
+            trace!("signal queued ({})", sig);
+            self.queue_signal(sig);
+            return true;
+        }
+        trace!("signal terminated the process ({})", sig);
+        self.terminate(NonZeroU32::new(err::ERR_TERMINATED).unwrap());
+        false
+    }
+
+    pub(crate) fn queue_signal(&self, sig: Signal) {
+        self.signals.fetch_or(sig.mask(), Ordering::AcqRel);
+    }
+
+    pub fn set_signal_handler(&self, val: bool) {
+        self.signal_handler.store(val, Ordering::Release);
+    }
+
+    pub fn has_signal_handler(&self) -> bool {
+        self.signal_handler.load(Ordering::Acquire)
+    }
+
+    pub fn has_pending_signals(&self) -> bool {
+        self.signals.load(Ordering::Acquire) != 0
+    }
+
+    /// Takes all the signals that are waiting to be read by the process
+    pub fn take_signals(&self) -> Vec<Signal> {
+        let pending = self.signals.swap(0, Ordering::AcqRel);
+        Signal::ALL
+            .iter()
+            .filter(|a| pending & a.mask() != 0)
+            .map(|a| *a)
+            .collect()
+    }
+
     pub fn get_forced_exit(&self) -> Arc<AtomicU32> {
         return self.forced_exit.clone();
     }
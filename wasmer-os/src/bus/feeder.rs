@@ -53,8 +53,8 @@ impl BusFeederUtils {
                 Self::feed_bytes_or_error(feeder, format, Ok(response));
             }
             Err(err) => {
-                Self::feed_error(feeder, err);
                 trace!("closing handle={} - due to an error - {}", handle, err);
+                Self::feed_error(feeder, err);
                 sessions.lock().unwrap().remove(&handle);
             }
         }
@@ -0,0 +1,141 @@
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use tokio::sync::mpsc;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+use wasmer_bus::abi::SerializationFormat;
+use wasmer_bus_fuse::api;
+use wasmer_vbus::BusDataFormat;
+use wasmer_vbus::BusInvocationEvent;
+use wasmer_vbus::InstantInvocation;
+use wasmer_vbus::VirtualBusError;
+use wasmer_vbus::VirtualBusInvocation;
+use wasmer_vbus::VirtualBusInvokable;
+use wasmer_vbus::VirtualBusInvoked;
+
+use super::*;
+use crate::api::*;
+use crate::common::MAX_MPSC;
+use crate::fs::*;
+use crate::wasmer_vfs::FsError;
+
+/// Copies files on behalf of a guest so that the data does not need to
+/// pass through the guest (the copy runs on a dedicated thread as the
+/// mounts are blocking)
+pub fn file_copy(
+    system: System,
+    root: UnionFileSystem,
+    request: api::FileSystemCopyRequest,
+) -> FileCopy {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (tx_progress, rx_progress) = mpsc::channel(MAX_MPSC);
+    let (tx_finished, rx_finished) = mpsc::channel(1);
+
+    let tracker = {
+        let cancelled = cancelled.clone();
+        CopyTracker::new(cancelled, move |progress| {
+            // Progress is only informational so it is dropped if the guest
+            // is not keeping up
+            let _ = tx_progress.try_send(progress);
+        })
+    };
+    system.spawn_dedicated(move || {
+        let from = Path::new(request.from.as_str());
+        let to = Path::new(request.to.as_str());
+        let ret = if from.is_absolute() == false || to.is_absolute() == false {
+            Err(FsError::InvalidInput)
+        } else {
+            MountedFileSystem::copy(&root, from, to, request.recursive, &tracker)
+                .unwrap_or(Err(FsError::UnknownError))
+        };
+        if let Err(err) = ret.as_ref() {
+            debug!("copy failed (from={}, to={}) - {}", request.from, request.to, err);
+        }
+        let ret = ret
+            .map(|_| tracker.progress())
+            .map_err(conv_fs_error_back);
+        let _ = tx_finished.try_send(ret);
+    });
+
+    FileCopy {
+        cancelled,
+        rx_progress,
+        rx_finished,
+    }
+}
+
+#[derive(Debug)]
+pub struct FileCopy {
+    cancelled: Arc<AtomicBool>,
+    rx_progress: mpsc::Receiver<api::CopyProgress>,
+    rx_finished: mpsc::Receiver<Result<api::CopyProgress, api::FsError>>,
+}
+
+impl FileCopy {
+    fn callback<T>(topic_hash: u128, data: T) -> BusInvocationEvent
+    where
+        T: serde::Serialize,
+    {
+        match SerializationFormat::Json.serialize(data) {
+            Ok(data) => BusInvocationEvent::Callback {
+                topic_hash,
+                format: BusDataFormat::Json,
+                data,
+            },
+            Err(err) => {
+                debug!("failed to serialize the copy progress");
+                BusInvocationEvent::Fault {
+                    fault: conv_error_back(err),
+                }
+            }
+        }
+    }
+}
+
+impl VirtualBusInvocation for FileCopy {
+    fn poll_event(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<BusInvocationEvent> {
+        if let Poll::Ready(Some(progress)) = self.rx_progress.poll_recv(cx) {
+            return Poll::Ready(Self::callback(
+                type_name_hash::<api::FileSystemCopyProgressCallback>(),
+                api::FileSystemCopyProgressCallback(progress),
+            ));
+        }
+        match self.rx_finished.poll_recv(cx) {
+            Poll::Ready(Some(ret)) => Poll::Ready(Self::callback(
+                type_name_hash::<api::FileSystemCopyFinishedCallback>(),
+                api::FileSystemCopyFinishedCallback(ret),
+            )),
+            Poll::Ready(None) => Poll::Pending,
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl VirtualBusInvokable for FileCopy {
+    fn invoke(
+        &self,
+        topic_hash: u128,
+        _format: BusDataFormat,
+        _buf: Vec<u8>,
+    ) -> Box<dyn VirtualBusInvoked> {
+        if topic_hash == type_name_hash::<api::FileCopyCancelRequest>() {
+            debug!("copy cancelled");
+            self.cancelled.store(true, Ordering::Release);
+            Box::new(encode_instant_response(BusDataFormat::Json, &()))
+        } else {
+            debug!("file copy invalid topic (hash={})", topic_hash);
+            Box::new(InstantInvocation::fault(VirtualBusError::InvalidTopic))
+        }
+    }
+}
+
+impl Drop for FileCopy {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+}
@@ -0,0 +1,172 @@
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use tokio::sync::mpsc;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+use wasmer_bus::abi::SerializationFormat;
+use wasmer_bus_fuse::api;
+use wasmer_vbus::BusDataFormat;
+use wasmer_vbus::BusInvocationEvent;
+use wasmer_vbus::InstantInvocation;
+use wasmer_vbus::VirtualBusError;
+use wasmer_vbus::VirtualBusInvocation;
+use wasmer_vbus::VirtualBusInvokable;
+use wasmer_vbus::VirtualBusInvoked;
+
+use super::*;
+use crate::api::*;
+use crate::common::MAX_MPSC;
+use crate::fs::*;
+use crate::wasmer_vfs::FsError;
+
+#[derive(Debug)]
+enum SearchBatch {
+    Paths(Vec<api::DirEntry>),
+    Lines(Vec<api::SearchMatch>),
+}
+
+/// Searches the file system on behalf of a guest so that it does not need
+/// to walk the directories one call at a time (the search runs on a
+/// dedicated thread as the mounts are blocking)
+pub fn file_search(
+    system: System,
+    root: UnionFileSystem,
+    path: String,
+    query: SearchQuery,
+) -> FileSearch {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (tx_found, rx_found) = mpsc::channel(MAX_MPSC);
+    let (tx_finished, rx_finished) = mpsc::channel(1);
+    let content = matches!(query.kind, SearchKind::Content { .. });
+
+    // Unlike the progress of a copy the results must all reach the guest
+    // hence the search waits for the guest to catch up
+    let tracker = {
+        let tx_lines = tx_found.clone();
+        SearchTracker::new(
+            cancelled.clone(),
+            query.max_matches,
+            move |batch| {
+                let _ = tx_found.blocking_send(SearchBatch::Paths(batch));
+            },
+            move |batch| {
+                let _ = tx_lines.blocking_send(SearchBatch::Lines(batch));
+            },
+        )
+    };
+    system.spawn_dedicated(move || {
+        let ret = if path.starts_with("/") == false {
+            Err(FsError::InvalidInput)
+        } else {
+            MountedFileSystem::search(&root, Path::new(path.as_str()), &query, &tracker)
+                .unwrap_or(Err(FsError::UnknownError))
+        };
+        if let Err(err) = ret.as_ref() {
+            debug!("search failed (root={}, glob={}) - {}", path, query.glob, err);
+        }
+        tracker.flush();
+        let ret = ret
+            .map(|_| tracker.summary())
+            .map_err(conv_fs_error_back);
+        let _ = tx_finished.try_send(ret);
+    });
+
+    FileSearch {
+        cancelled,
+        content,
+        rx_found,
+        rx_finished,
+    }
+}
+
+#[derive(Debug)]
+pub struct FileSearch {
+    cancelled: Arc<AtomicBool>,
+    content: bool,
+    rx_found: mpsc::Receiver<SearchBatch>,
+    rx_finished: mpsc::Receiver<Result<api::SearchSummary, api::FsError>>,
+}
+
+impl FileSearch {
+    fn callback<T>(topic_hash: u128, data: T) -> BusInvocationEvent
+    where
+        T: serde::Serialize,
+    {
+        match SerializationFormat::Json.serialize(data) {
+            Ok(data) => BusInvocationEvent::Callback {
+                topic_hash,
+                format: BusDataFormat::Json,
+                data,
+            },
+            Err(err) => {
+                debug!("failed to serialize the search results");
+                BusInvocationEvent::Fault {
+                    fault: conv_error_back(err),
+                }
+            }
+        }
+    }
+}
+
+impl VirtualBusInvocation for FileSearch {
+    fn poll_event(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<BusInvocationEvent> {
+        // The batches are sent before the search finishes so they are always
+        // delivered ahead of the summary
+        match self.rx_found.poll_recv(cx) {
+            Poll::Ready(Some(SearchBatch::Paths(batch))) => {
+                return Poll::Ready(Self::callback(
+                    type_name_hash::<api::FileSystemSearchFoundCallback>(),
+                    api::FileSystemSearchFoundCallback(batch),
+                ));
+            }
+            Poll::Ready(Some(SearchBatch::Lines(batch))) => {
+                return Poll::Ready(Self::callback(
+                    type_name_hash::<api::FileSystemSearchContentFoundCallback>(),
+                    api::FileSystemSearchContentFoundCallback(batch),
+                ));
+            }
+            Poll::Ready(None) | Poll::Pending => {}
+        }
+        match self.rx_finished.poll_recv(cx) {
+            Poll::Ready(Some(ret)) if self.content => Poll::Ready(Self::callback(
+                type_name_hash::<api::FileSystemSearchContentFinishedCallback>(),
+                api::FileSystemSearchContentFinishedCallback(ret),
+            )),
+            Poll::Ready(Some(ret)) => Poll::Ready(Self::callback(
+                type_name_hash::<api::FileSystemSearchFinishedCallback>(),
+                api::FileSystemSearchFinishedCallback(ret),
+            )),
+            Poll::Ready(None) => Poll::Pending,
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl VirtualBusInvokable for FileSearch {
+    fn invoke(
+        &self,
+        topic_hash: u128,
+        _format: BusDataFormat,
+        _buf: Vec<u8>,
+    ) -> Box<dyn VirtualBusInvoked> {
+        if topic_hash == type_name_hash::<api::FileSearchCancelRequest>() {
+            debug!("search cancelled");
+            self.cancelled.store(true, Ordering::Release);
+            Box::new(encode_instant_response(BusDataFormat::Json, &()))
+        } else {
+            debug!("file search invalid topic (hash={})", topic_hash);
+            Box::new(InstantInvocation::fault(VirtualBusError::InvalidTopic))
+        }
+    }
+}
+
+impl Drop for FileSearch {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+}
@@ -1,8 +1,11 @@
 mod caller_context;
 mod factory;
 mod feeder;
+mod fs_copy;
+mod fs_search;
 mod invokable;
 mod process;
+mod pty;
 mod reqwest;
 mod standard;
 mod sub_process;
@@ -23,6 +26,7 @@ use util::*;
 pub use factory::BusFactory;
 pub use process::ProcessExecFactory;
 pub use process::LaunchEnvironment;
+pub use pty::Pty;
 pub use feeder::BusStatefulFeeder;
 pub use feeder::BusStatelessFeeder;
 pub use feeder::BusFeederUtils;
@@ -22,6 +22,7 @@ use crate::api::*;
 use crate::err;
 use crate::eval::*;
 use crate::fd::*;
+use crate::fs::UnionFileSystem;
 use crate::stdout::*;
 use crate::pipe::*;
 use crate::reactor::*;
@@ -72,6 +73,13 @@ impl ProcessExecFactory
             }
         }
     }
+
+    /// File system of the console that launched the processes (if it is
+    /// still available)
+    pub fn root_fs(&self) -> Option<UnionFileSystem> {
+        let guard = self.ctx.lock().unwrap();
+        guard.as_ref().map(|ctx| ctx.root.clone())
+    }
 }
 
 pub struct EvalContextTaker {
@@ -101,6 +109,7 @@ pub struct LaunchContext {
     pub(crate) on_stdout: Option<Arc<dyn BusStatefulFeeder + Send + Sync + 'static>>,
     pub(crate) on_stderr: Option<Arc<dyn BusStatefulFeeder + Send + Sync + 'static>>,
     pub(crate) on_exit: Option<Arc<dyn BusStatefulFeeder + Send + Sync + 'static>>,
+    pub(crate) pty: Option<Pty>,
 }
 
 #[derive(Derivative, Clone)]
@@ -126,6 +135,8 @@ pub struct LaunchResult<T>
     pub stdin: Option<mpsc::Sender<FdMsg>>,
     pub stdout: Option<mpsc::Receiver<FdMsg>>,
     pub stderr: Option<mpsc::Receiver<FdMsg>>,
+    // Pseudo terminal that was allocated for the process (if any)
+    pub pty: Option<Pty>,
 }
 
 impl ProcessExecFactory {
@@ -209,6 +220,16 @@ impl ProcessExecFactory {
         let inherit_stderr = env.inherit_stderr.upgrade();
         let inherit_log = env.inherit_log.upgrade();
 
+        // Processes that have any of their stdio in tty mode are allocated a pty
+        let pty = if stdin_mode == StdioMode::Tty
+            || stdout_mode == StdioMode::Tty
+            || stderr_mode == StdioMode::Tty
+        {
+            Some(Pty::new(create.request.spawn.pty_rect.clone()))
+        } else {
+            None
+        };
+
         // Perform hooks back to the main stdio
         let (stdin, mut stdin_tx) = match stdin_mode {
             StdioMode::Null => (stdin, None),
@@ -218,6 +239,10 @@ impl ProcessExecFactory {
             StdioMode::Inherit => (stdin, None),
             StdioMode::Piped => (stdin, Some(stdin_tx)),
             StdioMode::Log => (stdin, None),
+            StdioMode::Tty if inherit_stdin.is_some() => {
+                (with_tty_flag(inherit_stdin.clone().unwrap()), None)
+            }
+            StdioMode::Tty => (with_tty_flag(stdin), Some(stdin_tx)),
         };
         let (stdout, mut stdout_rx) = match stdout_mode {
             StdioMode::Null => (stdout, None),
@@ -231,6 +256,10 @@ impl ProcessExecFactory {
                 (inherit_stdout.clone().unwrap(), None)
             }
             StdioMode::Log => (stdout, None),
+            StdioMode::Tty if inherit_stdout.is_some() => {
+                (with_tty_flag(inherit_stdout.clone().unwrap()), None)
+            }
+            StdioMode::Tty => (with_tty_flag(stdout), Some(stdout_rx)),
         };
         let (stderr, mut stderr_rx) = match stderr_mode {
             StdioMode::Null => (stderr, None),
@@ -242,6 +271,10 @@ impl ProcessExecFactory {
                 (inherit_stderr.clone().unwrap(), None)
             }
             StdioMode::Log => (stderr, None),
+            StdioMode::Tty if inherit_stderr.is_some() => {
+                (with_tty_flag(inherit_stderr.clone().unwrap()), None)
+            }
+            StdioMode::Tty => (with_tty_flag(stderr), Some(stderr_rx)),
         };
 
         // Determine if we are stealing the STDIO hooks
@@ -264,7 +297,11 @@ impl ProcessExecFactory {
             let stdin = stdin.clone();
             let stdout = stdout.clone();
             let stderr = stderr.clone();
-            let abi = env.abi.clone();
+            let abi = match pty.as_ref() {
+                Some(pty) => pty.abi(env.abi.clone()),
+                None => env.abi.clone(),
+            };
+            let launch_pty = pty.clone();
             let ctx = self.ctx.clone();
             let reactor = self.reactor.clone();
             #[cfg(feature = "sys")]
@@ -353,6 +390,7 @@ impl ProcessExecFactory {
                     on_stdout,
                     on_stderr,
                     on_exit,
+                    pty: launch_pty,
                 };
 
                 // Start the process
@@ -367,6 +405,7 @@ impl ProcessExecFactory {
             stdin: stolen_stdin,
             stdout: stolen_stdout,
             stderr: stolen_stderr,
+            pty,
         }
     }
 
@@ -384,9 +423,12 @@ impl ProcessExecFactory {
                 let on_ctx = Box::pin(move |src: EvalContext| {
                     let mut guard = dst.lock().unwrap();
                     if let Some(dst) = guard.as_mut() {
+                        // The working directory of the process is not
+                        // changed by the commands that it evaluates
+                        let scope = dst.working_dir_scope();
                         dst.env = src.env;
                         dst.root = src.root;
-                        dst.working_dir = src.working_dir;
+                        dst.restore_working_dir(&scope);
                     }
                 });
 
@@ -406,6 +448,7 @@ impl ProcessExecFactory {
                     },
                     session: ProcessExecSession {
                         stdin: ctx.stdin_tx,
+                        pty: ctx.pty,
                     },
                 })
             })
@@ -623,6 +666,19 @@ impl Processable for ProcessExecInvokable {
     }
 }
 
+/// Marks a stdio file descriptor as being backed by a terminal so that
+/// `isatty` queries will succeed for the child process
+fn with_tty_flag(mut fd: Fd) -> Fd {
+    let flag = match fd.flag() {
+        FdFlag::Stdin(_) => FdFlag::Stdin(true),
+        FdFlag::Stdout(_) => FdFlag::Stdout(true),
+        FdFlag::Stderr(_) => FdFlag::Stderr(true),
+        a => a,
+    };
+    fd.set_flag(flag);
+    fd
+}
+
 fn encode_eval_response(
     format: SerializationFormat,
     on_ctx: Pin<Box<dyn Fn(EvalContext) + Send + 'static>>,
@@ -654,6 +710,7 @@ fn encode_eval_response(
 #[derive(Clone)]
 pub struct ProcessExecSession {
     stdin: Option<mpsc::Sender<FdMsg>>,
+    pty: Option<Pty>,
 }
 
 impl Session for ProcessExecSession {
@@ -684,6 +741,18 @@ impl Session for ProcessExecSession {
                     };
                 self.stdin.take();
                 ResultInvokable::new(conv_format(format), ())
+            } else if topic_hash == type_name_hash::<api::ProcessResizeRequest>() {
+                let request: api::ProcessResizeRequest =
+                    match decode_request(format, request) {
+                        Ok(a) => a,
+                        Err(err) => {
+                            return Ok((ErrornousInvokable::new(err), None));
+                        }
+                    };
+                if let Some(pty) = self.pty.as_ref() {
+                    pty.resize(request.rect);
+                }
+                ResultInvokable::new(conv_format(format), ())
             } else {
                 ErrornousInvokable::new(BusError::InvalidTopic)
             }
@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::sync::Mutex;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+use wasmer_bus_process::api;
+
+use crate::api::*;
+
+/// Pseudo terminal that is allocated for sub-processes that are spawned
+/// with `StdioMode::Tty`. Until the parent explicitly resizes the pty the
+/// dimensions will follow the console that the parent is attached to.
+#[derive(Debug, Clone, Default)]
+pub struct Pty {
+    rect: Arc<Mutex<Option<ConsoleRect>>>,
+}
+
+impl Pty {
+    pub fn new(rect: Option<api::PtyRect>) -> Pty {
+        let ret = Pty::default();
+        if let Some(rect) = rect {
+            ret.resize(rect);
+        }
+        ret
+    }
+
+    pub fn resize(&self, rect: api::PtyRect) {
+        debug!("pty resize cols={} rows={}", rect.cols, rect.rows);
+        let mut guard = self.rect.lock().unwrap();
+        guard.replace(ConsoleRect {
+            cols: rect.cols,
+            rows: rect.rows,
+        });
+    }
+
+    pub fn rect(&self) -> Option<ConsoleRect> {
+        let guard = self.rect.lock().unwrap();
+        guard.clone()
+    }
+
+    /// Wraps the console of the parent so that the child will see the
+    /// dimensions of this pty rather than the dimensions of the parent
+    pub fn abi(&self, parent: Arc<dyn ConsoleAbi>) -> Arc<dyn ConsoleAbi> {
+        Arc::new(PtyConsole {
+            pty: self.clone(),
+            parent,
+        })
+    }
+}
+
+struct PtyConsole {
+    pty: Pty,
+    parent: Arc<dyn ConsoleAbi>,
+}
+
+#[async_trait]
+impl ConsoleAbi for PtyConsole {
+    async fn stdout(&self, data: Vec<u8>) {
+        self.parent.stdout(data).await
+    }
+
+    async fn stderr(&self, data: Vec<u8>) {
+        self.parent.stderr(data).await
+    }
+
+    async fn flush(&self) {
+        self.parent.flush().await
+    }
+
+    async fn log(&self, text: String) {
+        self.parent.log(text).await
+    }
+
+    async fn console_rect(&self) -> ConsoleRect {
+        match self.pty.rect() {
+            Some(rect) => rect,
+            None => self.parent.console_rect().await,
+        }
+    }
+
+    async fn cls(&self) {
+        self.parent.cls().await
+    }
+
+    async fn exit(&self) {
+        self.parent.exit().await
+    }
+}
@@ -13,6 +13,8 @@ use wasmer_vbus::VirtualBusScope;
 
 use crate::api::System;
 use crate::fd::*;
+use crate::fs::SearchKind;
+use crate::fs::SearchQuery;
 use crate::fs::TtyFile;
 use crate::stdio::*;
 use crate::stdout::*;
@@ -96,6 +98,78 @@ for StandardBus
                     )
                 )
             }
+            h if h == type_name_hash::<wasmer_bus_fuse::api::FileSystemCopyRequest>() => {
+                let request = match format.deserialize(buf) {
+                    Ok(a) => a,
+                    Err(err) => {
+                        return Box::new(InstantInvocation::fault(conv_error_back(err)))
+                    }
+                };
+                let root = match self.process_factory.root_fs() {
+                    Some(a) => a,
+                    None => {
+                        return Box::new(encode_instant_fault(format, &wasmer_bus_fuse::api::FsError::NoDevice))
+                    }
+                };
+                Box::new(
+                    InstantInvocation::call(
+                        Box::new(fs_copy::file_copy(self.system, root, request))
+                    )
+                )
+            }
+            h if h == type_name_hash::<wasmer_bus_fuse::api::FileSystemSearchRequest>() => {
+                let request: wasmer_bus_fuse::api::FileSystemSearchRequest = match format.deserialize(buf) {
+                    Ok(a) => a,
+                    Err(err) => {
+                        return Box::new(InstantInvocation::fault(conv_error_back(err)))
+                    }
+                };
+                let root = match self.process_factory.root_fs() {
+                    Some(a) => a,
+                    None => {
+                        return Box::new(encode_instant_fault(format, &wasmer_bus_fuse::api::FsError::NoDevice))
+                    }
+                };
+                let query = SearchQuery {
+                    glob: request.glob,
+                    max_matches: request.max_results,
+                    kind: SearchKind::Paths {
+                        return_metadata: request.return_metadata,
+                    },
+                };
+                Box::new(
+                    InstantInvocation::call(
+                        Box::new(fs_search::file_search(self.system, root, request.root, query))
+                    )
+                )
+            }
+            h if h == type_name_hash::<wasmer_bus_fuse::api::FileSystemSearchContentRequest>() => {
+                let request: wasmer_bus_fuse::api::FileSystemSearchContentRequest = match format.deserialize(buf) {
+                    Ok(a) => a,
+                    Err(err) => {
+                        return Box::new(InstantInvocation::fault(conv_error_back(err)))
+                    }
+                };
+                let root = match self.process_factory.root_fs() {
+                    Some(a) => a,
+                    None => {
+                        return Box::new(encode_instant_fault(format, &wasmer_bus_fuse::api::FsError::NoDevice))
+                    }
+                };
+                let query = SearchQuery {
+                    glob: request.glob,
+                    max_matches: request.max_matches,
+                    kind: SearchKind::Content {
+                        pattern: request.pattern,
+                        regex: request.regex,
+                    },
+                };
+                Box::new(
+                    InstantInvocation::call(
+                        Box::new(fs_search::file_search(self.system, root, request.root, query))
+                    )
+                )
+            }
             h if h == type_name_hash::<wasmer_bus_time::api::TimeSleepRequest>() => {
                 let request: wasmer_bus_time::api::TimeSleepRequest = match format.deserialize(buf) {
                     Ok(a) => a,
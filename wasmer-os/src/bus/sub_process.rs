@@ -100,6 +100,7 @@ impl SubProcessFactory {
                 stdout_mode: stdout_mode,
                 stderr_mode: stderr_mode,
                 pre_open: Vec::new(),
+                pty_rect: None,
             },
         };
         let (process, finish, runtime, checkpoint2) = self
@@ -307,9 +308,11 @@ for SubProcessHandler
                     let code = result.raw();
                     let mut guard = self.dst.lock().unwrap();
                     if let Some(dst) = guard.as_mut() {
+                        // Sub-processes have their own working directory
+                        let scope = dst.working_dir_scope();
                         dst.env = result.ctx.env;
                         dst.root = result.ctx.root;
-                        dst.working_dir = result.ctx.working_dir;
+                        dst.restore_working_dir(&scope);
                     }
                     code
                 },
@@ -378,6 +381,20 @@ for SubProcessHandler
         } else if topic_hash == type_name_hash::<api::ProcessCloseStdinRequest>() {
             result.stdin.take();
             Box::new(encode_instant_response(BusDataFormat::Bincode, &()))
+        } else if topic_hash == type_name_hash::<api::ProcessResizeRequest>() {
+            let rect = match decode_request::<api::ProcessResizeRequest>(
+                format,
+                buf,
+            ) {
+                Ok(a) => a.rect,
+                Err(err) => {
+                    return Box::new(InstantInvocation::fault(conv_error_back(err)));
+                }
+            };
+            if let Some(pty) = result.pty.as_ref() {
+                pty.resize(rect);
+            }
+            Box::new(encode_instant_response(BusDataFormat::Bincode, &()))
         } else if topic_hash == type_name_hash::<api::ProcessFlushRequest>() {
             Box::new(encode_instant_response(BusDataFormat::Bincode, &()))
         } else if topic_hash == type_name_hash::<api::ProcessIdRequest>() {
@@ -1,6 +1,7 @@
 use serde::*;
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
+use wasmer_bus::abi::ApplicationError;
 use wasmer_bus::abi::BusError;
 use wasmer_bus::abi::SerializationFormat;
 use wasmer_vbus::BusDataFormat;
@@ -53,6 +54,7 @@ pub fn conv_error_back(fault: BusError) -> VirtualBusError {
         BusError::BusInvocationFailed => InvokeFailed,
         BusError::AlreadyConsumed => AlreadyConsumed,
         BusError::MemoryAccessViolation => MemoryAccessViolation,
+        BusError::Application => UnknownError,
         BusError::Unknown => UnknownError,
         BusError::Success => UnknownError,
     }
@@ -112,3 +114,20 @@ where
     }
     
 }
+
+/// Fails the call with an error raised by the application, as these can
+/// not be expressed as a fault they are encoded into a response that the
+/// caller will decode back into the error
+pub fn encode_instant_fault<T>(format: SerializationFormat, err: &T) -> InstantInvocation
+where
+    T: Serialize,
+{
+    match ApplicationError::new(format, err) {
+        Ok(err) => {
+            InstantInvocation::response(conv_format_back(format), err.to_reply())
+        },
+        Err(err) => {
+            InstantInvocation::fault(conv_error_back(err))
+        }
+    }
+}
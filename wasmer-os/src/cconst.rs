@@ -45,6 +45,7 @@ impl Tty {
     pub const TERM_DELETE_ABOVE: &'static str = "\x1b[1J\r";
     pub const TERM_DELETE_ALL: &'static str = "\x1b[2J\r";
     pub const TERM_DELETE_SAVED: &'static str = "\x1b[3J\r";
+    pub const TERM_CLEAR_BELOW: &'static str = "\x1b[0J";
 
     pub const TERM_CURSOR_SAVE: &'static str = "\x1b[s";
     pub const TERM_CURSOR_RESTORE: &'static str = "\x1b[u";
@@ -101,13 +102,16 @@ impl Tty {
  \___/\_____(___/|_|_|_|_____|_|    \x1B[37;1m\r\r\n"#;
 
     pub const MOUNT_USAGE: &'static str = r#"Usage:
- mount [<wapm-name>] <mountpoint> <target>
+ mount [-o <options>] [<wapm-name>] <mountpoint> <target>
 
+ <options>: Comma separated mount options
+            casefold - names that differ only by case refer to the same file
  <wapm-name>: Name of the WAPM program that will serve the file-system (default: tok)
  <mounpoint>: Location where the file-system will be mounted to
  <target>: Target name passed to the WAPM program and is ued for the mounting
 
  Example: mount tok /www wasmer.sh/wasm
+          mount -o casefold /docs wasmer.sh/docs
 "#;
 
     pub const UMOUNT_USAGE: &'static str = r#"Usage:
@@ -116,6 +120,62 @@ umount <mountpoint>
 <mounpoint>: Location where the file-system to be unmounted is currently mounted
 
 Example: umount /www
+"#;
+
+    pub const FLOCK_USAGE: &'static str = r#"Usage:
+flock [-s|-x] [-n|-w <seconds>] <file> <command> [<args>...]
+flock [-s|-x] [-n|-w <seconds>] <file> -c <command>
+
+-s: Acquire a shared lock (multiple holders are allowed)
+-x: Acquire an exclusive lock (default)
+-n: Fail straight away if the lock is held elsewhere (default)
+-w: Wait up to this many seconds for the lock to be released
+-c: Run the command through the shell
+
+The lock is shared by every mount of the same file system and is
+held for as long as the command runs.
+
+Example: flock -w 10 /www/deploy.lock ./deploy.sh
+"#;
+
+    pub const CHMOD_USAGE: &'static str = r#"Usage:
+chmod +x|-x <file>...
+chmod <mode> <file>...
+
++x: Mark the files as executable so that they can be run as commands
+-x: Mark the files as not executable
+<mode>: Octal mode, the files are executable if any execute bit is set
+
+Files in /bin, /usr/bin and /usr/local/bin are executable unless they
+are marked otherwise, scripts elsewhere must be marked before they run.
+
+Example: chmod +x ./deploy.sh
+"#;
+
+    pub const KILL_USAGE: &'static str = r#"Usage:
+kill [-s <signal>|-<signal>] <pid>...
+kill -l
+
+<signal>: Name or number of the signal to send (default: TERM)
+<pid>: Identifier of the process that will receive the signal
+-l: List the signals that can be sent
+
+Processes that do not handle the signal are terminated, SIGKILL
+can never be handled.
+
+Example: kill -INT 4
+"#;
+
+    pub const SELECT_USAGE: &'static str = r#"Usage:
+select [-n <max>] <query>
+
+<query>: Expression that the rows must match, fields are compared with
+         ==, !=, <, <=, >, >= or contains and combined with and, or, not
+-n: Stop after this many rows have matched
+<stdin>: Rows to be filtered as JSON lines
+<stdout>: Rows that matched as JSON lines
+
+Example: cat instances.json | select "export.binary == 'foo' and replicas > 1"
 "#;
 
     pub const CALL_USAGE: &'static str = r#"Usage:
@@ -1,6 +1,7 @@
 #![allow(unused_imports)]
 #![allow(dead_code)]
 use std::collections::HashMap;
+use std::future::Future;
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -25,6 +26,7 @@ use super::fs::*;
 use super::job::*;
 use super::pipe::*;
 use super::reactor::*;
+use super::signal::*;
 use super::state::*;
 use super::stdio::*;
 use super::stdout::*;
@@ -52,6 +54,8 @@ pub struct Console {
     whitelabel: bool,
     bootstrap_token: Option<String>,
     no_welcome: bool,
+    input: TtyInputDecoder,
+    tmp: SessionTmp,
 }
 
 impl Drop
@@ -60,9 +64,31 @@ for Console
     fn drop(&mut self) {
         let state = self.state.clone();
         let reactor = self.reactor.clone();
+        let tmp = self.tmp;
         let system = System::default();
         let work = async move {
-            reactor.write().await.clear();
+            // Closing the console hangs up all its jobs, processes that
+            // handle SIGHUP are given a grace period to exit on their own
+            let handled = reactor.write().await.hangup();
+            if handled {
+                system.sleep(SIGNAL_GRACE_MS).await;
+            }
+            {
+                let mut reactor = reactor.write().await;
+                reactor.terminate_all();
+                reactor.clear();
+            }
+
+            // Clearing the mounts drops the temporary files that were held in
+            // memory while those kept in the chain are expired by their TTL
+            // (on a dedicated thread as the mounts are blocking)
+            if let SessionTmp::Persistent { ttl: Some(_) } = tmp {
+                let mut root = state.lock().unwrap().rootfs.clone();
+                root.solidify();
+                system.spawn_dedicated(move || {
+                    tmp.expire(&root);
+                });
+            }
             state.lock().unwrap().clear_mounts();
         };
         system.fork_shared(move || work);
@@ -127,6 +153,21 @@ impl Console {
         }
         state.env.set_var("LOCATION", location.to_string());
 
+        // The temporary files are mounted before anything runs so that nothing
+        // can write to /tmp before it is in place
+        let tmp = SessionTmp::default();
+        tmp.mount(&mut state.rootfs);
+        state.env.set_var("TMPDIR", "/tmp".to_string());
+        state.env.export("TMPDIR");
+
+        // Guests always see a UTF-8 locale which can be chosen per session
+        let locale = location
+            .query_pairs()
+            .filter(|(key, _)| key == "lang" || key == "locale")
+            .next()
+            .map(|(_, val)| val.to_string());
+        state.env.set_locale(locale.as_ref().map(|a| a.as_str()).unwrap_or(DEFAULT_LOCALE));
+
         let state = Arc::new(Mutex::new(state));
         let tty = Tty::channel(&abi, &unfinished_line, outer);
         
@@ -162,6 +203,8 @@ impl Console {
             whitelabel: false,
             bootstrap_token: None,
             no_welcome: false,
+            input: TtyInputDecoder::new(),
+            tmp,
         };
 
         ret.new_init();
@@ -198,6 +241,13 @@ impl Console {
         self.no_welcome = no_welcome;
     }
 
+    /// Changes where the temporary files of the session are kept, this must
+    /// be called before the session is initialized or prepared
+    pub fn set_tmp(&mut self, tmp: SessionTmp) {
+        tmp.mount(&mut self.state.lock().unwrap().rootfs);
+        self.tmp = tmp;
+    }
+
     pub async fn prepare(&mut self) {
         let rect = self.abi.console_rect().await;
         self.tty.set_bounds(rect.cols, rect.rows).await;
@@ -307,6 +357,33 @@ impl Console {
         self.exec.clone()
     }
 
+    /// Embeds a command of the host application into the shell, see
+    /// `EvalFactory::add_builtin`
+    pub fn add_builtin<F, Fut>(&self, name: &str, description: &str, handler: F) -> Result<(), u32>
+    where
+        F: Fn(Vec<String>, EvalContext, Stdio) -> Fut,
+        F: Send + Sync + 'static,
+        Fut: Future<Output = ExecResponse> + Send + 'static,
+    {
+        self.exec.add_builtin(name, description, handler)
+    }
+
+    /// Embeds a command whose future is not Send, see
+    /// `EvalFactory::add_builtin_local`
+    pub fn add_builtin_local<F, Fut>(&self, name: &str, description: &str, handler: F) -> Result<(), u32>
+    where
+        F: Fn(Vec<String>, EvalContext, Stdio) -> Fut,
+        F: Send + Sync + 'static,
+        Fut: Future<Output = ExecResponse> + 'static,
+    {
+        self.exec.add_builtin_local(name, description, handler)
+    }
+
+    /// Replaces the text that `help` shows before the custom commands
+    pub fn set_help(&self, help: Option<String>) {
+        self.exec.set_help(help);
+    }
+
     pub async fn new_job(&mut self) -> Option<Job> {
         // Generate the job and make it the active version
         let job = {
@@ -473,7 +550,7 @@ impl Console {
                                 tty.record_history(cmd).await;
                             }
 
-                            if code != 0 && show_result {
+                            if code != 0 && code != err::ERR_SIGPIPE && show_result {
                                 let mut chars = String::new();
                                 chars += err::exit_code_to_message(code);
                                 chars += "\r\n";
@@ -582,11 +659,51 @@ impl Console {
                 self.tty.draw_prompt().await;
             }
             TtyMode::StdIn(job) => {
-                {
+                // Ctrl-C delivers SIGINT to the job, if nothing traps it then
+                // the job is closed straight away
+                let handled = {
                     let mut reactor = self.reactor.write().await;
-                    reactor.close_job(job, std::num::NonZeroU32::new(err::ERR_TERMINATED).unwrap());
+                    let handled = reactor.signal_job(&job, Signal::Int);
+                    if handled == false {
+                        reactor.close_job(job.clone(), std::num::NonZeroU32::new(err::ERR_TERMINATED).unwrap());
+                    }
+                    handled
+                };
+                if handled == false {
+                    self.tty.enter_mode(TtyMode::Null, &self.reactor).await;
+                    return;
                 }
-                self.tty.enter_mode(TtyMode::Null, &self.reactor).await;
+
+                // Otherwise the job is given a grace period to clean up
+                // before it is killed the hard way
+                let reactor = self.reactor.clone();
+                let system = System::default();
+                system.fork_shared(move || async move {
+                    system.sleep(SIGNAL_GRACE_MS).await;
+                    let mut reactor = reactor.write().await;
+                    if reactor.get_job(job.id).is_some() {
+                        debug!("job ignored SIGINT - terminating (id={})", job.id);
+                        reactor.close_job(job, std::num::NonZeroU32::new(err::ERR_TERMINATED).unwrap());
+                    }
+                });
+            }
+        }
+    }
+
+    /// Prints a notification (e.g. that a file was uploaded) above the
+    /// prompt without losing the command that is being typed
+    pub async fn on_notify(&mut self, msg: &str) {
+        let mode = self.tty.mode().await;
+        match mode {
+            TtyMode::Console if self.wizard.is_none() => {
+                self.tty
+                    .draw(format!("{}{}\r\n", Tty::TERM_DELETE_LINE, msg).as_str())
+                    .await;
+                self.tty.draw_prompt().await;
+                self.tty.draw_line().await;
+            }
+            _ => {
+                self.tty.draw(format!("\r\n{}\r\n", msg).as_str()).await;
             }
         }
     }
@@ -661,6 +778,19 @@ impl Console {
         }
     }
 
+    /// Overrides the locale of this session (e.g. with the language of the browser)
+    /// unless one was explicitly requested in the location
+    pub fn set_locale(&mut self, locale: &str) {
+        if self
+            .location
+            .query_pairs()
+            .any(|(key, _)| key == "lang" || key == "locale")
+        {
+            return;
+        }
+        self.state.lock().unwrap().env.set_locale(locale);
+    }
+
     pub async fn on_key(
         &mut self,
         _key_code: u32,
@@ -672,6 +802,15 @@ impl Console {
         // Do nothing for now
     }
 
+    /// Feeds raw input into the console, characters that are split across
+    /// the chunks of the input are put back together before being processed
+    pub async fn on_data_bytes(&mut self, data: &[u8]) {
+        let data = self.input.push(data);
+        if data.len() > 0 {
+            self.on_data(data).await;
+        }
+    }
+
     pub async fn on_data(&mut self, mut data: String) {
         let mode = self.tty.mode().await;
         match mode {
@@ -4,6 +4,10 @@
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::env;
+use std::sync::Arc;
+
+/// Locale that guests will see when the session does not ask for a specific one
+pub const DEFAULT_LOCALE: &'static str = "C.UTF-8";
 
 #[derive(Debug, Clone, Default)]
 pub struct Val {
@@ -15,6 +19,9 @@ pub struct Val {
 #[derive(Debug, Clone, Default)]
 pub struct Environment {
     vars: HashMap<String, Val>,
+    /// Shell functions (name to the source of their body), these are never
+    /// passed to processes but they live as long as the variables do
+    functions: HashMap<String, Arc<String>>,
 }
 
 impl Environment {
@@ -44,6 +51,20 @@ impl Environment {
         }
     }
 
+    /// Sets the locale of the session (LANG) and exports it so that all the
+    /// processes see it, the terminal always speaks UTF-8 hence the character
+    /// set of the locale is forced to UTF-8
+    pub fn set_locale(&mut self, locale: &str) {
+        let lang = locale.split('.').next().unwrap_or_default().replace('-', "_");
+        let lang = if lang.len() > 0 {
+            format!("{}.UTF-8", lang)
+        } else {
+            DEFAULT_LOCALE.to_string()
+        };
+        self.set_var("LANG", lang);
+        self.export("LANG");
+    }
+
     pub fn unset(&mut self, key: &str) {
         if let Entry::Occupied(o) = self.vars.entry(key.to_string()) {
             if !o.get().readonly {
@@ -74,7 +95,7 @@ impl Environment {
         let entry = self.vars.get(key)?;
 
         return if let Some(var_eq) = &entry.var_eq {
-            let mut split = var_eq.as_bytes().split(|b| *b == b'=');
+            let mut split = var_eq.as_bytes().splitn(2, |b| *b == b'=');
             let _entry_key = split.next().unwrap();
             if let Some(value) = split.next() {
                 Some(String::from_utf8_lossy(value).to_string())
@@ -98,6 +119,18 @@ impl Environment {
         self.vars.iter()
     }
 
+    pub fn set_function(&mut self, name: &str, source: String) {
+        self.functions.insert(name.to_string(), Arc::new(source));
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<Arc<String>> {
+        self.functions.get(name).cloned()
+    }
+
+    pub fn unset_function(&mut self, name: &str) -> bool {
+        self.functions.remove(name).is_some()
+    }
+
     pub fn parse_key(&self, var_eq: &String) -> String {
         let mut split = var_eq.as_bytes().split(|b| *b == b'=');
         String::from_utf8_lossy(split.next().unwrap()).to_string()
@@ -107,6 +140,7 @@ impl Environment {
 pub fn empty() -> Environment {
     Environment {
         vars: HashMap::new(),
+        functions: HashMap::new(),
     }
 }
 
@@ -130,6 +130,7 @@ pub const ERR_ENOMEDIUM: u32 = 123; /* No medium found */
 pub const ERR_EMEDIUMTYPE: u32 = 124; /* Wrong medium type */
 
 pub const ERR_TERMINATED: u32 = 130; /* Process was terminated */
+pub const ERR_SIGPIPE: u32 = 141; /* Process wrote to a pipe that has no reader */
 pub const ERR_PANIC: u32 = 99999; /* Process has panicked */
 
 pub fn exit_code_to_message(code: u32) -> &'static str {
@@ -259,6 +260,7 @@ pub fn exit_code_to_message(code: u32) -> &'static str {
         ERR_EMEDIUMTYPE => "Wrong medium type",
         ERR_PANIC => "Process has panicked",
         ERR_TERMINATED => "Process was terminated",
+        ERR_SIGPIPE => "Broken pipe",
         _ => "Unknown error",
     }
 }
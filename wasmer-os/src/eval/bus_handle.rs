@@ -154,6 +154,25 @@ impl RuntimeCallOutsideHandle
         }
     }
 
+    /// Processes the callbacks of a call that has already replied until the
+    /// condition is met (or the call is closed)
+    pub fn block_on_callbacks(&mut self, mut until: impl FnMut() -> bool) -> Result<(), BusError> {
+        while until() == false {
+            match self.rx.try_recv() {
+                Ok(msg) => {
+                    self.process_msg(msg)?;
+                },
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    return Err(BusError::Aborted);
+                }
+                Err(mpsc::error::TryRecvError::Empty) => {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn clone_task(&self) -> RuntimeCallOutsideTask {
         self.task.clone()
     }
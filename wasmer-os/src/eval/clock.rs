@@ -0,0 +1,248 @@
+//! The monotonic clock of the WASI imports is serviced by the `SystemAbi` so
+//! that guests see the same clock (and the same sleep precision) regardless
+//! of whether they run natively or in the browser. Everything else is passed
+//! through to the original WASI implementation.
+//!
+//! The achievable resolution is that of `SystemAbi::monotonic_resolution_nanos`,
+//! natively that is a nanosecond (sleeps are accurate to roughly 100us) while
+//! browsers coarsen their clock to 5us when cross-origin isolated or 100us
+//! otherwise (sleeps are accurate to roughly a millisecond).
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::api::*;
+use crate::wasmer::{Extern, Function, FunctionEnv, FunctionEnvMut, Imports, Store, Value};
+use crate::wasmer_wasi::{WasiEnv, WasiError};
+
+const WASI_MODULES: [&'static str; 2] = ["wasi_unstable", "wasi_snapshot_preview1"];
+
+const CLOCK_REALTIME: u32 = 0;
+const CLOCK_MONOTONIC: u32 = 1;
+
+const ERRNO_SUCCESS: u32 = 0;
+const ERRNO_FAULT: u32 = 21;
+
+const EVENTTYPE_CLOCK: u8 = 0;
+const SUBSCRIPTION_CLOCK_ABSTIME: u16 = 1;
+const SUBSCRIPTION_SIZE: u64 = 48;
+const EVENT_SIZE: u64 = 32;
+
+/// Longest that a guest sleeps before checking if it has been asked to exit
+const MAX_SLEEP_SLICE_NANOS: u128 = 50_000_000;
+
+/// Replaces `clock_time_get`, `clock_res_get` and `poll_oneoff` in the imports
+/// (for every WASI version that they were defined for)
+pub fn override_clock_imports(
+    store: &mut Store,
+    env: &FunctionEnv<WasiEnv>,
+    imports: &mut Imports,
+    forced_exit: &Arc<AtomicU32>,
+) {
+    for module in WASI_MODULES {
+        if let Some(inner) = inner_function(imports, module, "clock_time_get") {
+            let func = Function::new_typed_with_env(
+                store,
+                env,
+                move |mut ctx: FunctionEnvMut<'_, WasiEnv>,
+                      clock_id: u32,
+                      precision: u64,
+                      time: u32|
+                      -> Result<u32, WasiError> {
+                    if clock_id != CLOCK_MONOTONIC {
+                        return call_inner(
+                            &mut ctx,
+                            &inner,
+                            &[
+                                Value::I32(clock_id as i32),
+                                Value::I64(precision as i64),
+                                Value::I32(time as i32),
+                            ],
+                        );
+                    }
+                    let now = System::default().now_monotonic_nanos() as u64;
+                    Ok(write_u64(&ctx, time, now))
+                },
+            );
+            imports.define(module, "clock_time_get", func);
+        }
+
+        if let Some(inner) = inner_function(imports, module, "clock_res_get") {
+            let func = Function::new_typed_with_env(
+                store,
+                env,
+                move |mut ctx: FunctionEnvMut<'_, WasiEnv>,
+                      clock_id: u32,
+                      resolution: u32|
+                      -> Result<u32, WasiError> {
+                    if clock_id != CLOCK_MONOTONIC {
+                        return call_inner(
+                            &mut ctx,
+                            &inner,
+                            &[Value::I32(clock_id as i32), Value::I32(resolution as i32)],
+                        );
+                    }
+                    let res = System::default().monotonic_resolution_nanos() as u64;
+                    Ok(write_u64(&ctx, resolution, res))
+                },
+            );
+            imports.define(module, "clock_res_get", func);
+        }
+
+        if let Some(inner) = inner_function(imports, module, "poll_oneoff") {
+            let forced_exit = forced_exit.clone();
+            let func = Function::new_typed_with_env(
+                store,
+                env,
+                move |mut ctx: FunctionEnvMut<'_, WasiEnv>,
+                      in_: u32,
+                      out_: u32,
+                      nsubscriptions: u32,
+                      nevents: u32|
+                      -> Result<u32, WasiError> {
+                    // Only polls that are purely waiting on clocks (i.e. sleeps) are
+                    // handled here, anything that involves a file goes to WASI
+                    let subs = match read_clock_subscriptions(&ctx, in_, nsubscriptions) {
+                        Ok(Some(a)) => a,
+                        Ok(None) => {
+                            return call_inner(
+                                &mut ctx,
+                                &inner,
+                                &[
+                                    Value::I32(in_ as i32),
+                                    Value::I32(out_ as i32),
+                                    Value::I32(nsubscriptions as i32),
+                                    Value::I32(nevents as i32),
+                                ],
+                            );
+                        }
+                        Err(errno) => {
+                            return Ok(errno);
+                        }
+                    };
+                    poll_clocks(&ctx, &subs[..], out_, nevents, &forced_exit)
+                },
+            );
+            imports.define(module, "poll_oneoff", func);
+        }
+    }
+}
+
+fn inner_function(imports: &Imports, module: &str, name: &str) -> Option<Function> {
+    match imports.get_export(module, name) {
+        Some(Extern::Function(a)) => Some(a),
+        _ => None,
+    }
+}
+
+fn call_inner(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    inner: &Function,
+    params: &[Value],
+) -> Result<u32, WasiError> {
+    match inner.call(ctx, params) {
+        Ok(ret) => Ok(ret
+            .get(0)
+            .and_then(|a| a.i32())
+            .map(|a| a as u32)
+            .unwrap_or(ERRNO_FAULT)),
+        Err(err) => match err.downcast::<WasiError>() {
+            Ok(err) => Err(err),
+            Err(err) => {
+                debug!("wasi call failed - {}", err);
+                Ok(ERRNO_FAULT)
+            }
+        },
+    }
+}
+
+fn write_u64(ctx: &FunctionEnvMut<'_, WasiEnv>, ptr: u32, val: u64) -> u32 {
+    let view = ctx.data().memory_view(ctx);
+    match view.write(ptr as u64, &val.to_le_bytes()) {
+        Ok(_) => ERRNO_SUCCESS,
+        Err(_) => ERRNO_FAULT,
+    }
+}
+
+/// Reads the subscriptions and converts them into (userdata, deadline) pairs
+/// on the monotonic clock, returns `None` if any of them is not a clock
+fn read_clock_subscriptions(
+    ctx: &FunctionEnvMut<'_, WasiEnv>,
+    in_: u32,
+    nsubscriptions: u32,
+) -> Result<Option<Vec<(u64, u128)>>, u32> {
+    if nsubscriptions == 0 {
+        return Ok(None);
+    }
+
+    let system = System::default();
+    let now = system.now_monotonic_nanos();
+    let view = ctx.data().memory_view(ctx);
+    let mut ret = Vec::with_capacity(nsubscriptions as usize);
+    for n in 0..(nsubscriptions as u64) {
+        let mut buf = [0u8; SUBSCRIPTION_SIZE as usize];
+        view.read(in_ as u64 + n * SUBSCRIPTION_SIZE, &mut buf[..])
+            .map_err(|_| ERRNO_FAULT)?;
+        if buf[8] != EVENTTYPE_CLOCK {
+            return Ok(None);
+        }
+
+        let userdata = u64::from_le_bytes(buf[0..8].try_into().unwrap());
+        let clock_id = u32::from_le_bytes(buf[16..20].try_into().unwrap());
+        let timeout = u64::from_le_bytes(buf[24..32].try_into().unwrap()) as u128;
+        let flags = u16::from_le_bytes(buf[40..42].try_into().unwrap());
+
+        let deadline = match (flags & SUBSCRIPTION_CLOCK_ABSTIME != 0, clock_id) {
+            (false, _) => now + timeout,
+            (true, CLOCK_MONOTONIC) => timeout,
+            (true, CLOCK_REALTIME) => {
+                let realtime = chrono::Utc::now().timestamp_nanos().max(0) as u128;
+                now + timeout.saturating_sub(realtime)
+            }
+            (true, _) => return Ok(None),
+        };
+        ret.push((userdata, deadline));
+    }
+    Ok(Some(ret))
+}
+
+/// Sleeps until the first of the clocks expires and then writes an event for
+/// every clock that has expired
+fn poll_clocks(
+    ctx: &FunctionEnvMut<'_, WasiEnv>,
+    subs: &[(u64, u128)],
+    out_: u32,
+    nevents: u32,
+    forced_exit: &Arc<AtomicU32>,
+) -> Result<u32, WasiError> {
+    let system = System::default();
+    let deadline = subs.iter().map(|(_, a)| *a).min().unwrap_or_default();
+    let mut now = system.now_monotonic_nanos();
+    while now < deadline {
+        let code = forced_exit.load(Ordering::Acquire);
+        if code != 0 {
+            return Err(WasiError::Exit(code));
+        }
+        let until = deadline.min(now + MAX_SLEEP_SLICE_NANOS);
+        system.sleep_until(until).block_on();
+        now = system.now_monotonic_nanos();
+    }
+
+    let view = ctx.data().memory_view(ctx);
+    let mut cnt = 0u32;
+    for (userdata, _) in subs.iter().filter(|(_, a)| *a <= now) {
+        let mut buf = [0u8; EVENT_SIZE as usize];
+        buf[0..8].copy_from_slice(&userdata.to_le_bytes());
+        buf[10] = EVENTTYPE_CLOCK;
+        if view.write(out_ as u64 + cnt as u64 * EVENT_SIZE, &buf[..]).is_err() {
+            return Ok(ERRNO_FAULT);
+        }
+        cnt += 1;
+    }
+    if view.write(nevents as u64, &cnt.to_le_bytes()).is_err() {
+        return Ok(ERRNO_FAULT);
+    }
+    Ok(ERRNO_SUCCESS)
+}
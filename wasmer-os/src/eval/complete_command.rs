@@ -12,6 +12,9 @@ pub(super) async fn complete_command<'a>(
         let (c, r) = andor_list(ctx, builtins, *op != ast::TermOp::Amp, show_result, list).await;
         ctx = c;
         ret = r;
+        if ctx.flow.is_some() {
+            break;
+        }
     }
     (ctx, ret)
 }
@@ -0,0 +1,166 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use super::*;
+use crate::ast;
+
+/// Change to the flow of a script requested by the break, continue and
+/// return builtins, the lists stop running until it reaches its target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    /// Leaves the N innermost loops
+    Break(u32),
+    /// Starts the next iteration of the Nth innermost loop
+    Continue(u32),
+    /// Leaves the function that is running
+    Return,
+}
+
+pub(super) type EvalFuture<'a> = Pin<Box<dyn Future<Output = (EvalContext, u32)> + Send + 'a>>;
+
+/// Runs a compound command within the shell itself (like a builtin), the
+/// future is boxed as the lists within it may hold more compound commands
+pub(super) fn compound_command<'a>(
+    mut ctx: EvalContext,
+    builtins: &'a Builtins,
+    show_result: &'a mut bool,
+    command: &'a ast::Command<'a>,
+) -> EvalFuture<'a> {
+    Box::pin(async move {
+        match command {
+            ast::Command::Group(list) => complete_command(ctx, builtins, list, show_result).await,
+            ast::Command::If(clause) => if_clause(ctx, builtins, show_result, clause).await,
+            ast::Command::Loop(clause) => loop_clause(ctx, builtins, show_result, clause).await,
+            ast::Command::For(clause) => for_clause(ctx, builtins, show_result, clause).await,
+            ast::Command::Function(def) => {
+                ctx.env.set_function(def.name, def.source.to_string());
+                (ctx, 0)
+            }
+            ast::Command::Simple { .. } => (ctx, err::ERR_EINVAL),
+        }
+    })
+}
+
+async fn if_clause<'a>(
+    mut ctx: EvalContext,
+    builtins: &Builtins,
+    show_result: &mut bool,
+    clause: &'a ast::IfClause<'a>,
+) -> (EvalContext, u32) {
+    for (condition, body) in clause.branches.iter() {
+        let (c, r) = complete_command(ctx, builtins, condition, show_result).await;
+        ctx = c;
+        if ctx.flow.is_some() {
+            return (ctx, r);
+        }
+        if r == 0 {
+            return complete_command(ctx, builtins, body, show_result).await;
+        }
+    }
+    match &clause.otherwise {
+        Some(body) => complete_command(ctx, builtins, body, show_result).await,
+        None => (ctx, 0),
+    }
+}
+
+/// Loops stop once the reader of the pipe they write to has gone away (as
+/// they would when a SIGPIPE kills the subshell they run in) otherwise
+/// `while true; do ...; done | head` never finishes
+async fn loop_clause<'a>(
+    mut ctx: EvalContext,
+    builtins: &Builtins,
+    show_result: &mut bool,
+    clause: &'a ast::LoopClause<'a>,
+) -> (EvalContext, u32) {
+    let mut ret = 0;
+    loop {
+        let (c, r) = complete_command(ctx, builtins, &clause.condition, show_result).await;
+        ctx = c;
+        if ctx.flow.is_some() {
+            if loop_flow(&mut ctx) {
+                break;
+            }
+            continue;
+        }
+        if (r == 0) == clause.until {
+            break;
+        }
+
+        let (c, r) = complete_command(ctx, builtins, &clause.body, show_result).await;
+        ctx = c;
+        ret = r;
+        if loop_flow(&mut ctx) {
+            break;
+        }
+
+        // Loops that only run builtins never wait on anything so give the
+        // other tasks a chance to run
+        tokio::task::yield_now().await;
+        if ctx.stdio.stdout.is_broken() {
+            return (ctx, err::ERR_SIGPIPE);
+        }
+    }
+    (ctx, ret)
+}
+
+async fn for_clause<'a>(
+    mut ctx: EvalContext,
+    builtins: &Builtins,
+    show_result: &mut bool,
+    clause: &'a ast::ForClause<'a>,
+) -> (EvalContext, u32) {
+    let words = match &clause.words {
+        Some(words) => {
+            let mut ret = Vec::new();
+            for word in words.iter() {
+                match word {
+                    ast::Arg::Arg("$@") | ast::Arg::Arg("$*") | ast::Arg::Arg("\"$@\"") => {
+                        ret.extend(positional_params(&ctx.env));
+                    }
+                    ast::Arg::Arg(s) => ret.push(eval_arg(&ctx.env, ctx.last_return, *s)),
+                    ast::Arg::Backquote(_quoted_args) => {}
+                }
+            }
+            ret
+        }
+        None => positional_params(&ctx.env),
+    };
+
+    let mut ret = 0;
+    for word in words {
+        ctx.env.set_var(clause.name, word);
+
+        let (c, r) = complete_command(ctx, builtins, &clause.body, show_result).await;
+        ctx = c;
+        ret = r;
+        if loop_flow(&mut ctx) {
+            break;
+        }
+        tokio::task::yield_now().await;
+        if ctx.stdio.stdout.is_broken() {
+            return (ctx, err::ERR_SIGPIPE);
+        }
+    }
+    (ctx, ret)
+}
+
+/// Consumes the break or continue that targets the innermost loop, returns
+/// true when the loop must stop (which includes leaving outer loops)
+fn loop_flow(ctx: &mut EvalContext) -> bool {
+    match ctx.flow {
+        None => false,
+        Some(Flow::Break(n)) => {
+            ctx.flow = if n > 1 { Some(Flow::Break(n - 1)) } else { None };
+            true
+        }
+        Some(Flow::Continue(n)) if n > 1 => {
+            ctx.flow = Some(Flow::Continue(n - 1));
+            true
+        }
+        Some(Flow::Continue(_)) => {
+            ctx.flow = None;
+            false
+        }
+        Some(Flow::Return) => true,
+    }
+}
@@ -1,16 +1,67 @@
 use super::*;
 
+/// Expands the variables within a word ($NAME, ${NAME}, $?, $# and the
+/// positional parameters), words in single quotes are taken literally
+/// and the quotes around a word are removed
 pub(super) fn eval_arg(env: &Environment, last_return: u32, arg: &str) -> String {
-    if arg.as_bytes()[0] == b'$' {
-        let key: &str = &arg[1..];
-        match key {
-            "?" => format!("{}", last_return),
-            _ => match env.get(key) {
-                Some(v) => v.clone(),
-                None => String::new(),
-            },
-        }
+    if arg.len() >= 2 && arg.starts_with('\'') && arg.ends_with('\'') {
+        return arg[1..arg.len() - 1].to_string();
+    }
+    let arg = if arg.len() >= 2 && arg.starts_with('"') && arg.ends_with('"') {
+        &arg[1..arg.len() - 1]
     } else {
-        arg.to_string()
+        arg
+    };
+
+    let mut ret = String::with_capacity(arg.len());
+    let mut chars = arg.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            ret.push(c);
+            continue;
+        }
+
+        let key = match chars.peek().cloned() {
+            Some('{') => {
+                chars.next();
+                let mut key = String::new();
+                while let Some(c) = chars.next() {
+                    if c == '}' {
+                        break;
+                    }
+                    key.push(c);
+                }
+                key
+            }
+            Some(c) if c == '?' || c == '#' || c == '@' || c == '*' || c.is_ascii_digit() => {
+                chars.next();
+                c.to_string()
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let mut key = String::new();
+                while let Some(c) = chars.peek().cloned() {
+                    if c.is_ascii_alphanumeric() == false && c != '_' {
+                        break;
+                    }
+                    key.push(c);
+                    chars.next();
+                }
+                key
+            }
+            _ => {
+                ret.push('$');
+                continue;
+            }
+        };
+
+        match key.as_str() {
+            "?" => ret.push_str(format!("{}", last_return).as_str()),
+            key => {
+                if let Some(v) = env.get(key) {
+                    ret.push_str(v.as_str());
+                }
+            }
+        }
     }
+    ret
 }
@@ -73,7 +73,7 @@ pub async fn exec(
     // If there is a built in then use it
     if let Some(builtin) = builtins.get(cmd) {
         *show_result = true;
-        return Ok(builtin(&args, ctx, stdio).await);
+        return Ok(builtin.run(&args, ctx, stdio).await);
     }
 
     let (process, process_result, _, _) =
@@ -116,8 +116,8 @@ pub async fn exec_process(
     let mut set_pwd = false;
     let mut base_dir = None;
     let mut chroot = ctx.chroot;
-    let (program_data_hash, program_data, mut fs_private) = match load_bin(&ctx, cmd, &mut stdio).await {
-        Some(a) => {
+    let (program_data_hash, program_data, mut fs_private, cmd, args) = match resolve_bin(&ctx, cmd, args, &mut stdio).await {
+        Ok((a, cmd, args)) => {
             if a.chroot {
                 chroot = true;
             }
@@ -134,10 +134,10 @@ pub async fn exec_process(
                 preopen.push(mapping);
             }
 
-            (a.hash, a.data, a.fs)
+            (a.hash, a.data, a.fs, cmd, args)
         }
-        None => {
-            return on_early_exit(None, err::ERR_ENOENT).await;
+        Err(err) => {
+            return on_early_exit(None, err).await;
         }
     };
     let pwd = ctx.working_dir.clone();
@@ -168,10 +168,23 @@ pub async fn exec_process(
 
     // Create the filesystem
     let (fs, union_base) = {
+        // /dev/tty always reaches the interactive terminal even when the
+        // stdio of this process has been redirected or piped
+        let terminal = Stdio {
+            stdin: ctx.job.stdin.clone(),
+            stdout: stdio.tty.fd_stdout(),
+            stderr: stdio.tty.stderr(),
+            log: stdio.log.clone(),
+            tty: stdio.tty.clone(),
+        };
+        let dev = DevFileSystem::new()
+            .with_terminal(terminal)
+            .with_process(caller_ctx.clone());
+
         let stdio = stdio.clone();
         let mut union = ctx.root.clone();
-        union.mount("proc", "/dev", true, Box::new(ProcFileSystem::new(stdio)), None);
-        union.mount("tmp", "/tmp", true, Box::new(TmpFileSystem::new()), None);
+        // The processes share the /tmp of their session (see `SessionTmp`)
+        union.mount("proc", "/dev", true, Box::new(ProcFileSystem::new(stdio, dev)), None);
         union.mount("private", "/.private", true, Box::new(fs_private), None);
         union.set_ctx(&caller_ctx);
         
@@ -201,6 +214,11 @@ pub async fn exec_process(
             filename = format!("{}{}", ctx.working_dir, filename);
         }
 
+        // Redirects to the terminal remain a terminal (isatty) while the
+        // other devices are endless streams that must not be buffered
+        let is_tty = is_dev_tty(filename.as_str());
+        let is_stream = is_dev_stream(filename.as_str());
+
         // Attempt to open the file
         let file = fs
             .new_open_options()
@@ -225,28 +243,28 @@ pub async fn exec_process(
                         -1 => {
                             if redirect.op.read() {
                                 let mut fd = fd.clone();
-                                flag = fd.set_flag(FdFlag::Stdin(false));
+                                flag = fd.set_flag(FdFlag::Stdin(is_tty));
                                 stdio.stdin = fd;
                             }
                             if redirect.op.write() {
                                 let mut fd = fd.clone();
-                                flag = fd.set_flag(FdFlag::Stdout(false));
+                                flag = fd.set_flag(FdFlag::Stdout(is_tty));
                                 stdio.stdout = fd;
                             }
                         }
                         0 => {
                             let mut fd = fd.clone();
-                            flag = fd.set_flag(FdFlag::Stdin(false));
+                            flag = fd.set_flag(FdFlag::Stdin(is_tty));
                             stdio.stdin = fd
                         }
                         1 => {
                             let mut fd = fd.clone();
-                            flag = fd.set_flag(FdFlag::Stdout(false));
+                            flag = fd.set_flag(FdFlag::Stdout(is_tty));
                             stdio.stdout = fd
                         }
                         2 => {
                             let mut fd = fd.clone();
-                            flag = fd.set_flag(FdFlag::Stderr(false));
+                            flag = fd.set_flag(FdFlag::Stderr(is_tty));
                             stdio.stderr = fd
                         }
                         _ => {
@@ -265,7 +283,13 @@ pub async fn exec_process(
                 system.fork_shared(move || async move {
                     if is_read {
                         while let Ok(read) = file.read(4096).await {
-                            let _ = tx.send(FdMsg::new(read, flag)).await;
+                            // An empty read is the end of the file (e.g. /dev/null)
+                            if read.len() <= 0 {
+                                break;
+                            }
+                            if tx.send(FdMsg::new(read, flag)).await.is_err() {
+                                break;
+                            }
                         }
                     }
                     if is_write {
@@ -273,6 +297,9 @@ pub async fn exec_process(
                             match msg {
                                 FdMsg::Data { data, .. } => {
                                     let _ = file.write_all(data).await;
+                                    if is_stream {
+                                        file.flush().await;
+                                    }
                                 }
                                 FdMsg::Flush { tx } => {
                                     file.flush().await;
@@ -392,7 +419,12 @@ pub async fn exec_process(
         ctx.exec_factory.clone(),
         ctx,
     );
-    
+
+    // Writing to a pipe whose reader has gone away terminates this process
+    // (the equivalent of a SIGPIPE)
+    stdio.stdout.ctx = caller_ctx.clone();
+    stdio.stderr.ctx = caller_ctx.clone();
+
     let forced_exit = caller_ctx.get_forced_exit();
 
     // Create the runtime that will perform terminal specific actions
@@ -498,6 +530,7 @@ pub async fn exec_process(
             
             // Let's instantiate the module with the imports.
             let mut import_object = import_object_for_all_wasi_versions(&mut store, &wasi_env.env);
+            override_clock_imports(&mut store, &wasi_env.env, &mut import_object, &forced_exit);
             if let Some(memory) = memory {
                 import_object.define("env", "memory", Memory::new_from_existing(&mut store, memory));
             }
@@ -575,6 +608,12 @@ pub async fn exec_process(
             // all the background threads have exited
             checkpoint2_tx.send(()).await;
 
+            // A process that failed after its pipe was broken is reported as
+            // if it was killed by the SIGPIPE (however it chose to exit)
+            if forced_exit.load(Ordering::Acquire) == err::ERR_SIGPIPE {
+                ret = err::ERR_SIGPIPE;
+            }
+
             // Force everything to exit (if it has not already)
             forced_exit.compare_exchange(0, err::ERR_EINTR, Ordering::AcqRel, Ordering::Relaxed);
 
@@ -1,4 +1,7 @@
+use std::future::Future;
 use std::ops::Deref;
+use std::pin::Pin;
+use std::task::Poll;
 
 use super::*;
 use crate::ast;
@@ -15,7 +18,15 @@ pub(super) async fn exec_pipeline<'a>(
     pipeline: &'a ast::Pipeline<'a>,
 ) -> (EvalContext, u32) {
     let mut child_list = Vec::new();
+    let mut stages = Vec::new();
+
+    // Like bash the exit code of the pipeline is that of its last command
     let mut final_return: Option<u32> = None;
+
+    // Only a lone command that runs in the foreground may change the working
+    // directory of the shell, all the others run in their own scope
+    let scope = ctx.working_dir_scope();
+    let isolated = pipeline.commands.len() > 1 || exec_sync == false;
     {
         let mut next_stdin = ctx.stdio.stdin.clone();
         let mut cur_stdin = ctx.stdio.stdin.clone();
@@ -26,6 +37,26 @@ pub(super) async fn exec_pipeline<'a>(
         for i in 0..pipeline.commands.len() {
             let is_last = i == pipeline.commands.len() - 1;
             let command = &pipeline.commands[i];
+
+            cur_stdin = next_stdin.clone();
+            if i + 1 < pipeline.commands.len() {
+                let (mut w, mut r) = pipe(ReceiverMode::Stream, end_stdout.flag());
+                r.set_flag(FdFlag::Stdin(false));
+                w.set_flag(FdFlag::Stdout(false));
+                next_stdin = r;
+                cur_stdout = w;
+            } else {
+                cur_stdout = end_stdout.clone();
+            }
+
+            let mut stdio = Stdio {
+                stdin: cur_stdin.clone(),
+                stdout: cur_stdout.clone(),
+                stderr: cur_stderr.clone(),
+                log: ctx.stdio.log.clone(),
+                tty: ctx.stdio.tty.clone(),
+            };
+
             match command {
                 ast::Command::Simple {
                     assign,
@@ -33,6 +64,19 @@ pub(super) async fn exec_pipeline<'a>(
                     args,
                     redirect,
                 } => {
+                    // Assignments without a command set shell variables
+                    if let (ast::Arg::Arg(""), true) = (cmd, args.is_empty()) {
+                        for a in assign.iter() {
+                            let key = ctx.env.parse_key(&a.to_string());
+                            let val = eval_arg(&ctx.env, ctx.last_return, &a[key.len() + 1..]);
+                            ctx.env.set_var(key.as_str(), val);
+                        }
+                        if is_last {
+                            final_return = Some(0);
+                        }
+                        continue;
+                    }
+
                     let parsed_cmd = match cmd {
                         ast::Arg::Arg(s) => eval_arg(&ctx.env, ctx.last_return, *s),
                         ast::Arg::Backquote(_quoted_args) => String::new(),
@@ -48,25 +92,21 @@ pub(super) async fn exec_pipeline<'a>(
                     let mut parsed_redirects = redirect.clone().into_iter().collect::<Vec<_>>();
                     parsed_redirects.extend(ctx.extra_redirects.clone().into_iter());
 
-                    cur_stdin = next_stdin.clone();
-                    if i + 1 < pipeline.commands.len() {
-                        let (mut w, mut r) = pipe(ReceiverMode::Stream, end_stdout.flag());
-                        r.set_flag(FdFlag::Stdin(false));
-                        w.set_flag(FdFlag::Stdout(false));
-                        next_stdin = r;
-                        cur_stdout = w;
-                    } else {
-                        cur_stdout = end_stdout.clone();
+                    // Functions run within the shell, just like the builtins
+                    if let Some(source) = ctx.env.get_function(parsed_cmd.as_str()) {
+                        debug!("call {}", parsed_cmd);
+                        let (c, ret) =
+                            call_function(ctx, builtins, show_result, source, &parsed_args[..], stdio).await;
+                        ctx = c;
+                        if isolated {
+                            ctx.restore_working_dir(&scope);
+                        }
+                        if is_last {
+                            final_return = Some(ret);
+                        }
+                        continue;
                     }
 
-                    let mut stdio = Stdio {
-                        stdin: cur_stdin.clone(),
-                        stdout: cur_stdout.clone(),
-                        stderr: cur_stderr.clone(),
-                        log: ctx.stdio.log.clone(),
-                        tty: ctx.stdio.tty.clone(),
-                    };
-
                     debug!("exec {}", parsed_cmd);
                     match exec::exec(
                         ctx.clone(),
@@ -82,20 +122,55 @@ pub(super) async fn exec_pipeline<'a>(
                     {
                         Ok(ExecResponse::Immediate(c, ret)) => {
                             ctx = c;
-                            final_return = Some(ret);
+                            if isolated {
+                                ctx.restore_working_dir(&scope);
+                            }
+                            if is_last {
+                                final_return = Some(ret);
+                            }
                         }
                         Ok(ExecResponse::OrphanedImmediate(ret)) => {
-                            final_return = Some(ret);
+                            if is_last {
+                                final_return = Some(ret);
+                            }
                         }
                         Ok(ExecResponse::Process(process, process_result)) => {
                             child_list.push((process, process_result, is_last));
                         }
                         Err(err) => {
                             *show_result = true;
-                            final_return = Some(err);
+                            if is_last {
+                                final_return = Some(err);
+                            }
                         }
                     }
                 }
+                command if pipeline.commands.len() > 1 => {
+                    // Like subshells the compound commands in a pipeline run at
+                    // the same time as the rest of it, otherwise the commands
+                    // reading from a loop would only start once it had finished
+                    let mut stage_ctx = ctx.clone();
+                    stage_ctx.stdio = stdio;
+                    let stage: StageFuture<'_> = Box::pin(async move {
+                        let mut show_result = false;
+                        let (_, ret) =
+                            compound_command(stage_ctx, builtins, &mut show_result, command).await;
+                        ret
+                    });
+                    stages.push((stage, is_last));
+                }
+                command => {
+                    let saved_stdio = std::mem::replace(&mut ctx.stdio, stdio);
+                    let (c, ret) = compound_command(ctx, builtins, show_result, command).await;
+                    ctx = c;
+                    ctx.stdio = saved_stdio;
+                    if isolated {
+                        ctx.restore_working_dir(&scope);
+                    }
+                    if is_last {
+                        final_return = Some(ret);
+                    }
+                }
             }
         }
     }
@@ -108,7 +183,10 @@ pub(super) async fn exec_pipeline<'a>(
         ctx.job.job_list_tx.send(child.pid).await;
     }
 
-    if exec_sync {
+    let (stage_results, _) = tokio::join!(join_stages(stages), async {
+        if exec_sync == false {
+            return;
+        }
         for (child, child_result, is_last) in child_list.into_iter().rev() {
             let (c, result) = child_result
                 .await
@@ -118,14 +196,52 @@ pub(super) async fn exec_pipeline<'a>(
                 "process (pid={}) finished (exit_code={})",
                 child.pid, result
             );
-            final_return.get_or_insert(result);
+            if is_last {
+                final_return = Some(result);
+            }
             if let Some(c) = c {
                 if is_last {
+                    // Processes have their own working directory
                     ctx = c;
+                    ctx.restore_working_dir(&scope);
                 }
             }
         }
+    });
+    for (ret, is_last) in stage_results {
+        if is_last {
+            final_return = Some(ret);
+        }
     }
 
     (ctx, final_return.map_or_else(|| 0, |a| a))
 }
+
+type StageFuture<'a> = Pin<Box<dyn Future<Output = u32> + Send + 'a>>;
+
+/// Runs the compound commands of a pipeline side by side and returns their
+/// exit codes (along with whether they were the last command)
+async fn join_stages<'a>(mut stages: Vec<(StageFuture<'a>, bool)>) -> Vec<(u32, bool)> {
+    let mut results: Vec<Option<u32>> = vec![None; stages.len()];
+    std::future::poll_fn(|cx| {
+        let mut finished = true;
+        for ((stage, _), result) in stages.iter_mut().zip(results.iter_mut()) {
+            if result.is_none() {
+                match stage.as_mut().poll(cx) {
+                    Poll::Ready(ret) => *result = Some(ret),
+                    Poll::Pending => finished = false,
+                }
+            }
+        }
+        match finished {
+            true => Poll::Ready(()),
+            false => Poll::Pending,
+        }
+    })
+    .await;
+    results
+        .into_iter()
+        .zip(stages.into_iter())
+        .map(|(ret, (_, is_last))| (ret.unwrap_or_default(), is_last))
+        .collect()
+}
@@ -1,6 +1,11 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::RwLock as StdRwLock;
 use tokio::sync::oneshot;
 
 use crate::api::*;
+use crate::builtins::Builtin;
+use crate::builtins::Builtins;
 use crate::bin_factory::*;
 use crate::bus::WasmCheckpoint;
 use crate::eval::*;
@@ -76,6 +81,7 @@ pub struct EvalFactoryState {
     pub stdout: Stdout,
     pub stderr: Fd,
     pub log: Fd,
+    pub(crate) builtins: StdRwLock<Builtins>,
 }
 
 #[derive(Clone)]
@@ -100,10 +106,56 @@ impl EvalFactory {
                 stdout,
                 stderr,
                 log,
+                builtins: StdRwLock::new(Builtins::new()),
             }),
         }
     }
 
+    /// Adds a command that runs within the shell (like `cd` or `export`)
+    /// rather than being looked up on the PATH, its future runs on the
+    /// shared worker pool. Names that are already taken are rejected with
+    /// ERR_EEXIST and the description is listed by `help`
+    pub fn add_builtin<F, Fut>(&self, name: &str, description: &str, handler: F) -> Result<(), u32>
+    where
+        F: Fn(Vec<String>, EvalContext, Stdio) -> Fut,
+        F: Send + Sync + 'static,
+        Fut: Future<Output = ExecResponse> + Send + 'static,
+    {
+        let cmd = Builtin::Shared(Arc::new(move |args, ctx, stdio| {
+            let ret: Pin<Box<dyn Future<Output = ExecResponse> + Send>> =
+                Box::pin(handler(args, ctx, stdio));
+            ret
+        }));
+        self.state.builtins.write().unwrap().add(name, description, cmd)
+    }
+
+    /// Same as `add_builtin` for handlers whose future is not Send, these
+    /// run on a dedicated thread of the worker pool
+    pub fn add_builtin_local<F, Fut>(&self, name: &str, description: &str, handler: F) -> Result<(), u32>
+    where
+        F: Fn(Vec<String>, EvalContext, Stdio) -> Fut,
+        F: Send + Sync + 'static,
+        Fut: Future<Output = ExecResponse> + 'static,
+    {
+        let cmd = Builtin::Local(Arc::new(move |args, ctx, stdio| {
+            let ret: Pin<Box<dyn Future<Output = ExecResponse>>> =
+                Box::pin(handler(args, ctx, stdio));
+            ret
+        }));
+        self.state.builtins.write().unwrap().add(name, description, cmd)
+    }
+
+    /// Replaces the text that `help` shows (None restores the default one),
+    /// the custom commands are still listed beneath it
+    pub fn set_help(&self, help: Option<String>) {
+        self.state.builtins.write().unwrap().set_help(help);
+    }
+
+    /// Snapshot of the builtins that new evaluations will use
+    pub fn builtins(&self) -> Builtins {
+        self.state.builtins.read().unwrap().clone()
+    }
+
     pub fn tty(&self) -> Tty {
         self.state.tty.clone()
     }
@@ -162,6 +214,7 @@ impl EvalFactory {
             extra_redirects: ctx.extra_redirects,            
             checkpoint1: ctx.checkpoint1,
             checkpoint2: ctx.checkpoint2,
+            flow: None,
         };
 
         ctx
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use super::*;
+
+/// Invokes a shell function, the arguments are seen by its body as the
+/// positional parameters (which are restored when the function returns)
+pub(super) fn call_function<'a>(
+    mut ctx: EvalContext,
+    builtins: &'a Builtins,
+    show_result: &'a mut bool,
+    source: Arc<String>,
+    args: &'a [String],
+    stdio: Stdio,
+) -> EvalFuture<'a> {
+    Box::pin(async move {
+        let parser = grammar::programParser::new();
+        let program = match parser.parse(source.as_str(), source.as_str()) {
+            Ok(a) => a,
+            Err(_) => {
+                return (ctx, err::ERR_EINVAL);
+            }
+        };
+
+        let saved_stdio = std::mem::replace(&mut ctx.stdio, stdio);
+        let saved_params = set_positional_params(&mut ctx.env, args.get(1..).unwrap_or_default());
+
+        let mut ret = 0;
+        for cc in program.commands.complete_commands.iter() {
+            let (c, r) = complete_command(ctx, builtins, cc, show_result).await;
+            ctx = c;
+            ret = r;
+            if ctx.flow.is_some() {
+                break;
+            }
+        }
+
+        // Nothing unwinds beyond the function that is returning
+        ctx.flow = None;
+        restore_positional_params(&mut ctx.env, saved_params);
+        ctx.stdio = saved_stdio;
+        (ctx, ret)
+    })
+}
+
+/// Returns the positional parameters ($1, $2, ...) that are currently set
+pub(super) fn positional_params(env: &Environment) -> Vec<String> {
+    let count = env
+        .get("#")
+        .and_then(|a| a.parse::<usize>().ok())
+        .unwrap_or(0);
+    (1..=count)
+        .map(|n| env.get(n.to_string().as_str()).unwrap_or_default())
+        .collect()
+}
+
+fn set_positional_params(env: &mut Environment, args: &[String]) -> Vec<(String, Option<String>)> {
+    let count = positional_params(env).len().max(args.len());
+
+    let mut keys = vec!["#".to_string(), "@".to_string(), "*".to_string()];
+    keys.extend((1..=count).map(|n| n.to_string()));
+    let saved = keys
+        .into_iter()
+        .map(|k| {
+            let v = env.get(k.as_str());
+            (k, v)
+        })
+        .collect();
+
+    for n in 1..=count {
+        match args.get(n - 1) {
+            Some(a) => env.set_var(n.to_string().as_str(), a.clone()),
+            None => env.unset(n.to_string().as_str()),
+        }
+    }
+    env.set_var("#", args.len().to_string());
+    env.set_var("@", args.join(" "));
+    env.set_var("*", args.join(" "));
+    saved
+}
+
+fn restore_positional_params(env: &mut Environment, saved: Vec<(String, Option<String>)>) {
+    for (key, val) in saved {
+        match val {
+            Some(val) => env.set_var(key.as_str(), val),
+            None => env.unset(key.as_str()),
+        }
+    }
+}
@@ -18,12 +18,157 @@ use crate::stdio::*;
 use crate::wasmer_vfs::FileSystem;
 use crate::pipe::*;
 use crate::fd::FdFlag;
+use crate::wasmer::{Module, Store};
+
+/// Most interpreters that a script may pass through (via its shebang) before
+/// a real binary is reached, this stops scripts that name each other looping
+const SHEBANG_MAX_DEPTH: usize = 4;
+
+/// Interpreter named on the first line of a script (e.g. `#!/bin/sh -e`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shebang {
+    pub interpreter: String,
+    pub arg: Option<String>,
+}
+
+/// Parses the shebang line of a script, like Unix everything after the
+/// interpreter is passed as a single argument. Scripts that use `env` to find
+/// their interpreter (e.g. `#!/usr/bin/env python`) have it looked up by name
+pub fn parse_shebang(data: &[u8]) -> Option<Shebang> {
+    if data.starts_with(b"#!") == false {
+        return None;
+    }
+    let line = &data[2..];
+    let line = match line.iter().position(|a| *a == b'\n') {
+        Some(n) => &line[..n],
+        None => line,
+    };
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim();
+
+    let (interpreter, arg) = match line.split_once(|c: char| c.is_whitespace()) {
+        Some((a, b)) => (a.to_string(), Some(b.trim().to_string()).filter(|a| a.len() > 0)),
+        None => (line.to_string(), None),
+    };
+    if interpreter.len() <= 0 {
+        return None;
+    }
+
+    // The `env` trick finds the interpreter by name
+    if interpreter == "env" || interpreter.ends_with("/env") {
+        let arg = arg?;
+        return Some(match arg.split_once(|c: char| c.is_whitespace()) {
+            Some((a, b)) => Shebang {
+                interpreter: a.to_string(),
+                arg: Some(b.trim().to_string()).filter(|a| a.len() > 0),
+            },
+            None => Shebang {
+                interpreter: arg,
+                arg: None,
+            },
+        });
+    }
+
+    // Interpreters in the standard folders are looked up like any other
+    // command so that those that are fetched or installed from wapm are found
+    for dir in ["/bin/", "/usr/bin/", "/usr/local/bin/"] {
+        if let Some(name) = interpreter.strip_prefix(dir) {
+            if name.len() > 0 && name.contains('/') == false {
+                return Some(Shebang {
+                    interpreter: name.to_string(),
+                    arg,
+                });
+            }
+        }
+    }
+    Some(Shebang { interpreter, arg })
+}
+
+/// Builds the command line that runs a script through the interpreter named
+/// by its shebang, the script is passed by the path that it was loaded from
+/// followed by the arguments that it was given
+pub fn shebang_command(shebang: Shebang, script: String, args: Vec<String>) -> (String, Vec<String>) {
+    let mut next = vec![shebang.interpreter.clone()];
+    next.extend(shebang.arg.into_iter());
+    next.push(script);
+    next.extend(args.into_iter().skip(1));
+    (shebang.interpreter, next)
+}
+
+/// Resolves the command into the binary that will run it, scripts that start
+/// with a shebang are run by their interpreter with the path of the script
+/// inserted into the arguments (returns the command and arguments to run)
+pub async fn resolve_bin(
+    ctx: &EvalContext,
+    cmd: &String,
+    args: &Vec<String>,
+    stdio: &mut Stdio,
+) -> Result<(BinaryPackage, String, Vec<String>), u32> {
+    let mut cmd = cmd.clone();
+    let mut args = args.clone();
+    for _ in 0..=SHEBANG_MAX_DEPTH {
+        let bin = load_bin_ext(ctx, &cmd, stdio).await?;
+        if bin.data.starts_with(b"#!") == false {
+            return Ok((bin, cmd, args));
+        }
+        let shebang = match parse_shebang(&bin.data[..]) {
+            Some(a) => a,
+            None => {
+                debug!("script {} has an invalid shebang", cmd);
+                return Err(ERR_ENOEXEC);
+            }
+        };
+        debug!("script {} is run by {}", cmd, shebang.interpreter);
+
+        let script = bin.path.unwrap_or(cmd);
+        let (next_cmd, next_args) = shebang_command(shebang, script, args);
+        cmd = next_cmd;
+        args = next_args;
+    }
+    debug!("too many interpreters for {}", cmd);
+    Err(ERR_ELOOP)
+}
+
+/// Lists the files that may hold the command in the order that they are
+/// tried, commands with a path are only looked for there while the others
+/// are looked for in the command folders and then along the PATH
+pub fn bin_candidates(name: &str, working_dir: &str, path: Option<&str>) -> Vec<String> {
+    let mut ret = Vec::new();
+    if name.starts_with("/") {
+        ret.push(name.to_string());
+    } else if name.starts_with("./") && name.len() > 2 {
+        ret.push(format!("{}{}", working_dir, &name[2..]));
+    } else {
+        ret.push(format!("/bin/{}", name));
+        ret.push(format!("/usr/bin/{}", name));
+        if let Some(path) = path {
+            for dir in path.split(':').filter(|a| a.len() > 0) {
+                let check = format!("{}/{}", dir.trim_end_matches('/'), name);
+                if ret.contains(&check) == false {
+                    ret.push(check);
+                }
+            }
+        }
+    }
+    ret
+}
 
 pub async fn load_bin(
     ctx: &EvalContext,
     name: &String,
     stdio: &mut Stdio,
 ) -> Option<BinaryPackage> {
+    load_bin_ext(ctx, name, stdio).await.ok()
+}
+
+/// Loads a binary, fails with ERR_ENOENT when it can not be found and with
+/// ERR_EACCES when the only files that were found are not executable (like
+/// Unix the search carries on past the files that are not)
+pub async fn load_bin_ext(
+    ctx: &EvalContext,
+    name: &String,
+    stdio: &mut Stdio,
+) -> Result<BinaryPackage, u32> {
     // Resolve any alias
     let mut chroot = false;
     let mut wapm = None;
@@ -101,24 +246,23 @@ pub async fn load_bin(
         }
     }       
 
-    // Check if there is a file in the /bin and /usr/bin folder
-    let mut file_checks = Vec::new();
-    if name.starts_with("/") {
-        file_checks.push(name.clone());
-    } else if name.starts_with("./") && name.len() > 2 {
-        file_checks.push(format!("{}{}", ctx.working_dir, &name[2..]));
-    } else {
-        file_checks.push(format!("/bin/{}", name));
-        file_checks.push(format!("/usr/bin/{}", name));
-    }
-    for file_check in file_checks {
+    // Check if there is a file in the /bin and /usr/bin folder (or along
+    // the PATH)
+    let path = ctx.env.get("PATH");
+    let mut denied = false;
+    for file_check in bin_candidates(name.as_str(), ctx.working_dir.as_str(), path.as_deref()) {
         if let Ok(mut file) = AsyncifyFileSystem::new(ctx.root.clone())
             .new_open_options()
             .await
             .read(true)
-            .open(file_check)
+            .open(file_check.clone())
             .await
         {
+            if ctx.root.exec.is_executable(file_check.as_str()) == false {
+                debug!("{} is not executable", file_check);
+                denied = true;
+                continue;
+            }
             if let Ok(d) = file.read_to_end().await {
                 let d = Bytes::from(d);
                 let mut ret = BinaryPackage::new(d);
@@ -129,10 +273,14 @@ pub async fn load_bin(
                 ret.base_dir = base_dir;
                 ret.envs = envs;
                 ret.mappings.extend(mappings.into_iter());
-                return Some(ret);
+                ret.path = Some(file_check);
+                return Ok(ret);
             }
         }
     }
+    if denied && name.contains('/') {
+        return Err(ERR_EACCES);
+    }
 
     // Resolve some more alias possibilities using fetch commands (with cached results)
     while let Some(next) = ctx.bins.alias(name.as_str()).await {
@@ -166,7 +314,45 @@ pub async fn load_bin(
         ret.envs = envs;
         ret.mappings.extend(mappings.into_iter());
     }
-    ret
+    match denied {
+        true => ret.ok_or(ERR_EACCES),
+        false => ret.ok_or(ERR_ENOENT),
+    }
+}
+
+/// Loads a binary and compiles it ahead of its first use so that the first
+/// invocation does not pay for the download and compile. Returns false if
+/// either step failed (the binary is then loaded as normal when it is used)
+pub async fn prefetch_bin(ctx: &EvalContext, name: &String) -> bool {
+    let mut stdio = ctx.stdio.clone();
+    let data = match load_bin(ctx, name, &mut stdio).await {
+        Some(a) => a,
+        None => {
+            debug!("prefetch of {} failed - binary not found", name);
+            return false;
+        }
+    };
+
+    #[cfg(feature = "sys")]
+    let store = match ctx.engine.clone() {
+        Some(engine) => Store::new(engine),
+        None => Store::default()
+    };
+    #[cfg(feature = "js")]
+    let store = Store::default();
+
+    if ctx.bins.get_compiled_module(&store, &data.hash, ctx.compiler).await.is_some() {
+        return true;
+    }
+    let module = match Module::new(&store, &data.data[..]) {
+        Ok(a) => a,
+        Err(err) => {
+            debug!("prefetch of {} failed - {}", name, err);
+            return false;
+        }
+    };
+    ctx.bins.set_compiled_module(data.hash.clone(), ctx.compiler, &module).await;
+    true
 }
 
 pub async fn wapm_install(ctx: &EvalContext, name: &String, wapm: String, base_dir: String)
@@ -3,30 +3,37 @@
 
 pub(crate) mod andor_list;
 pub(crate) mod complete_command;
+pub(crate) mod compound_command;
 pub(crate) mod eval_arg;
 pub(crate) mod exec;
 pub(crate) mod exec_pipeline;
 pub(crate) mod factory;
+pub(crate) mod function;
 pub(crate) mod load_bin;
 pub(crate) mod process;
 pub(crate) mod runtime;
 pub(crate) mod bus_feeder;
 pub(crate) mod bus_listener;
 pub(crate) mod bus_handle;
+pub(crate) mod clock;
+mod tests;
 
 pub use andor_list::*;
 pub use complete_command::*;
+pub use compound_command::*;
 use derivative::Derivative;
 pub use eval_arg::*;
 pub use exec::*;
 pub use exec_pipeline::*;
 pub use factory::*;
+pub use function::*;
 pub use load_bin::*;
 pub use process::*;
 pub use runtime::*;
 pub use bus_feeder::*;
 pub use bus_listener::*;
 pub use bus_handle::*;
+pub use clock::*;
 #[cfg(feature = "sys")]
 use wasmer::Engine;
 
@@ -272,6 +279,9 @@ pub struct EvalContext {
     pub checkpoint1: Option<(mpsc::Sender<()>, Arc<WasmCheckpoint>)>,
     #[derivative(Debug = "ignore")]
     pub checkpoint2: Option<(mpsc::Sender<()>, Arc<WasmCheckpoint>)>,
+    /// Set by break, continue and return until the loop or function they
+    /// target consumes it
+    pub flow: Option<Flow>,
 }
 
 impl EvalContext {
@@ -284,18 +294,56 @@ impl EvalContext {
             inherit_log: self.stdio.log.downgrade(),
         }
     }
+
+    /// Captures the working directory so that it can be put back once a
+    /// nested scope (background job, pipeline or subshell) has finished
+    pub fn working_dir_scope(&self) -> WorkingDirScope {
+        WorkingDirScope::new(self.working_dir.as_str(), &self.env)
+    }
+
+    /// Reverts any changes that a nested scope made to the working directory
+    pub fn restore_working_dir(&mut self, scope: &WorkingDirScope) {
+        scope.restore(&mut self.working_dir, &mut self.env);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkingDirScope {
+    working_dir: String,
+    pwd: Option<String>,
+    old_pwd: Option<String>,
+}
+
+impl WorkingDirScope {
+    pub fn new(working_dir: &str, env: &Environment) -> WorkingDirScope {
+        WorkingDirScope {
+            working_dir: working_dir.to_string(),
+            pwd: env.get("PWD"),
+            old_pwd: env.get("OLDPWD"),
+        }
+    }
+
+    pub fn restore(&self, working_dir: &mut String, env: &mut Environment) {
+        *working_dir = self.working_dir.clone();
+        for (key, val) in [("PWD", &self.pwd), ("OLDPWD", &self.old_pwd)] {
+            match val {
+                Some(val) => env.set_var(key, val.clone()),
+                None => env.unset(key),
+            }
+        }
+    }
 }
 
 pub(crate) fn eval(cmd: String, mut ctx: EvalContext) -> mpsc::Receiver<EvalResult> {
     let system = ctx.system;
-    let builtins = Builtins::new();
+    let builtins = ctx.exec_factory.builtins();
     let parser = grammar::programParser::new();
 
     let (tx, rx) = mpsc::channel(1);
 
     let work = {
         async move {
-            match parser.parse(cmd.as_str()) {
+            match parser.parse(cmd.as_str(), cmd.as_str()) {
                 Ok(program) => {
                     let mut show_result = false;
                     let mut ret = 0;
@@ -303,7 +351,11 @@ pub(crate) fn eval(cmd: String, mut ctx: EvalContext) -> mpsc::Receiver<EvalResu
                         let (c, r) = complete_command(ctx, &builtins, &cc, &mut show_result).await;
                         ctx = c;
                         ret = r;
+                        if ctx.flow.is_some() {
+                            break;
+                        }
                     }
+                    ctx.flow = None;
                     tx.send(EvalResult::new(
                         ctx,
                         EvalStatus::Executed {
@@ -314,19 +366,15 @@ pub(crate) fn eval(cmd: String, mut ctx: EvalContext) -> mpsc::Receiver<EvalResu
                     .await;
                 }
                 Err(e) => match e {
-                    grammar::ParseError::UnrecognizedToken {
-                        token: _,
-                        expected: _,
-                    } => {
-                        tx.send(EvalResult::new(ctx, EvalStatus::MoreInput)).await;
-                    }
                     grammar::ParseError::UnrecognizedEOF {
                         location: _,
                         expected: _,
                     } => {
                         tx.send(EvalResult::new(ctx, EvalStatus::MoreInput)).await;
                     }
-                    _ => {
+                    err => {
+                        let msg = format!("sh: {}\r\n", syntax_error(cmd.as_str(), err));
+                        let _ = ctx.stdio.stderr.write(msg.as_bytes()).await;
                         tx.send(EvalResult::new(ctx, EvalStatus::Invalid)).await;
                     }
                 },
@@ -340,3 +388,30 @@ pub(crate) fn eval(cmd: String, mut ctx: EvalContext) -> mpsc::Receiver<EvalResu
     system.fork_shared(move || work);
     rx
 }
+
+/// Describes where a script failed to parse (the line number is one based)
+fn syntax_error<T, E>(text: &str, err: grammar::ParseError<usize, T, E>) -> String
+where
+    T: std::fmt::Display,
+{
+    let line = |location: usize| text[..location.min(text.len())].matches('\n').count() + 1;
+    match err {
+        grammar::ParseError::UnrecognizedToken {
+            token: (location, token, _),
+            expected: _,
+        }
+        | grammar::ParseError::ExtraToken {
+            token: (location, token, _),
+        } => format!(
+            "syntax error near unexpected token `{}' on line {}",
+            token,
+            line(location)
+        ),
+        grammar::ParseError::InvalidToken { location }
+        | grammar::ParseError::UnrecognizedEOF {
+            location,
+            expected: _,
+        } => format!("syntax error on line {}", line(location)),
+        grammar::ParseError::User { error: _ } => format!("syntax error"),
+    }
+}
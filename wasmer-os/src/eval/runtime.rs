@@ -195,6 +195,7 @@ impl RuntimeProcessSpawner
                 stdout_mode: conv_stdio_mode(config.stdout_mode()),
                 stderr_mode: conv_stdio_mode(config.stderr_mode()),
                 pre_open: config.preopen().clone(),
+                pty_rect: None,
             }
         };
 
@@ -0,0 +1,90 @@
+#![cfg(test)]
+use super::*;
+
+#[test]
+fn test_parse_shebang() {
+    assert_eq!(
+        parse_shebang(b"#!/bin/sh\necho hi\n"),
+        Some(Shebang {
+            interpreter: "sh".to_string(),
+            arg: None,
+        })
+    );
+    assert_eq!(
+        parse_shebang(b"#! /usr/bin/python3  -u \nprint()"),
+        Some(Shebang {
+            interpreter: "python3".to_string(),
+            arg: Some("-u".to_string()),
+        })
+    );
+    assert_eq!(
+        parse_shebang(b"#!/usr/bin/env node --harmony\n"),
+        Some(Shebang {
+            interpreter: "node".to_string(),
+            arg: Some("--harmony".to_string()),
+        })
+    );
+
+    // Interpreters outside the command folders keep their path
+    assert_eq!(
+        parse_shebang(b"#!/opt/tools/run"),
+        Some(Shebang {
+            interpreter: "/opt/tools/run".to_string(),
+            arg: None,
+        })
+    );
+
+    assert_eq!(parse_shebang(b"\0asm"), None);
+    assert_eq!(parse_shebang(b"#!\n"), None);
+    assert_eq!(parse_shebang(b"#!/usr/bin/env\n"), None);
+}
+
+#[test]
+fn test_shebang_command() {
+    let shebang = parse_shebang(b"#!/bin/sh -e\n").unwrap();
+    let args = vec!["deploy".to_string(), "--now".to_string()];
+    let (cmd, args) = shebang_command(shebang, "/home/deploy.sh".to_string(), args);
+    assert_eq!(cmd, "sh");
+    assert_eq!(args, vec!["sh", "-e", "/home/deploy.sh", "--now"]);
+}
+
+#[test]
+fn test_bin_candidates() {
+    assert_eq!(bin_candidates("/opt/run", "/home/", None), vec!["/opt/run"]);
+    assert_eq!(
+        bin_candidates("./run.sh", "/home/", Some("/opt")),
+        vec!["/home/run.sh"]
+    );
+
+    // The command folders come first and the PATH is searched in order
+    assert_eq!(
+        bin_candidates("ls", "/home/", Some("/usr/bin:/opt/bin/::/home/bin")),
+        vec!["/bin/ls", "/usr/bin/ls", "/opt/bin/ls", "/home/bin/ls"]
+    );
+}
+
+#[test]
+fn test_working_dir_scope() {
+    let mut env = Environment::default();
+    env.set_var("PWD", "/home/".to_string());
+    let mut working_dir = "/home/".to_string();
+    let scope = WorkingDirScope::new(working_dir.as_str(), &env);
+
+    // A job that changes directory (as `cd /tmp` would) does not move the shell
+    working_dir = "/tmp/".to_string();
+    env.set_var("OLDPWD", "/home/".to_string());
+    env.set_var("PWD", "/tmp/".to_string());
+    scope.restore(&mut working_dir, &mut env);
+    assert_eq!(working_dir, "/home/");
+    assert_eq!(env.get("PWD"), Some("/home/".to_string()));
+    assert_eq!(env.get("OLDPWD"), None, "OLDPWD was not set before the job");
+
+    // Variables that were set before the job are put back as they were
+    env.set_var("OLDPWD", "/srv/".to_string());
+    let scope = WorkingDirScope::new(working_dir.as_str(), &env);
+    env.set_var("OLDPWD", "/home/".to_string());
+    env.unset("PWD");
+    scope.restore(&mut working_dir, &mut env);
+    assert_eq!(env.get("PWD"), Some("/home/".to_string()));
+    assert_eq!(env.get("OLDPWD"), Some("/srv/".to_string()));
+}
@@ -211,6 +211,11 @@ impl Fd {
         self.closed.load(Ordering::Acquire)
     }
 
+    /// Returns true once the reader at the other end of the pipe has gone away
+    pub fn is_broken(&self) -> bool {
+        self.sender.as_ref().map(|a| a.is_closed()).unwrap_or(false)
+    }
+
     pub fn is_readable(&self) -> bool {
         self.receiver.is_some()
     }
@@ -219,6 +224,15 @@ impl Fd {
         self.sender.is_some()
     }
 
+    /// Invoked when the reader of the pipe has gone away, like a SIGPIPE this
+    /// terminates the process that owns the Fd (which is quietly reported as
+    /// exit code 141) while the write itself fails with a broken pipe
+    fn broken_pipe(&self) -> io::Error {
+        trace!("broken pipe (flag={})", self.flag);
+        self.ctx.terminate(NonZeroU32::new(ERR_SIGPIPE).unwrap());
+        std::io::ErrorKind::BrokenPipe.into()
+    }
+
     fn check_closed(&self) -> io::Result<()> {
         if self.is_closed() {
             return Err(std::io::ErrorKind::BrokenPipe.into());
@@ -240,7 +254,7 @@ impl Fd {
             let buf_len = buf.len();
             let msg = FdMsg::new(buf, self.flag);
             if let Err(_err) = sender.send(msg).await {
-                return Err(std::io::ErrorKind::BrokenPipe.into());
+                return Err(self.broken_pipe());
             }
             Ok(buf_len)
         } else {
@@ -371,7 +385,7 @@ impl Fd {
                     return Ok(Some(buf_len));
                 }
                 Err(TrySendError::Closed(_)) => {
-                    return Ok(Some(0));
+                    return Err(self.broken_pipe());
                 }
                 Err(TrySendError::Full(_)) => {
                     // Check for a forced exit
@@ -409,7 +423,7 @@ impl Fd {
                         msg = Some(returned_msg);
                     }
                     Err(TrySendError::Closed(_)) => {
-                        return Ok(0);
+                        return Err(self.broken_pipe());
                     }
                 }
 
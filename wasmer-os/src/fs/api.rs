@@ -1,9 +1,61 @@
+use std::path::Path;
+use std::time::Duration;
+use wasmer_bus_fuse::api::LockOptions;
+
 use crate::bus::WasmCallerContext;
 use crate::wasmer_vfs::*;
 
+use super::CopyTracker;
+use super::SearchQuery;
+use super::SearchTracker;
+
+/// Advisory lock held on a file of a mounted file system
+pub trait FileLockHandle: std::fmt::Debug + Send + Sync {
+    /// Releases the lock straight away rather than when it is dropped
+    fn unlock(self: Box<Self>) -> Result<()>;
+}
+
 pub trait MountedFileSystem
 where
     Self: FileSystem + std::fmt::Debug,
 {
     fn set_ctx(&self, ctx: &WasmCallerContext);
+
+    /// Acquires an advisory lock on a file which, like a lock on an open
+    /// file descriptor, is held until the returned handle is dropped
+    fn lock(&self, _path: &Path, _options: LockOptions) -> Result<Box<dyn FileLockHandle>> {
+        Err(FsError::Lock)
+    }
+
+    /// Copies a file (or a whole directory when recursive) to another path
+    /// on this file system without reading the data out of it, returns None
+    /// if the file system can not do this itself
+    fn copy(
+        &self,
+        _from: &Path,
+        _to: &Path,
+        _recursive: bool,
+        _tracker: &CopyTracker,
+    ) -> Option<Result<()>> {
+        None
+    }
+
+    /// Searches the paths (or the content of the files) under a path of
+    /// this file system without walking it one directory at a time, returns
+    /// None if the file system can not do this itself
+    fn search(
+        &self,
+        _root: &Path,
+        _query: &SearchQuery,
+        _tracker: &SearchTracker,
+    ) -> Option<Result<()>> {
+        None
+    }
+
+    /// Has the file system remove the entries of a directory that have not
+    /// been modified within the maximum age by itself, returns None if the
+    /// file system has no way of doing this
+    fn set_retention(&self, _path: &Path, _max_age: Option<Duration>) -> Option<Result<()>> {
+        None
+    }
 }
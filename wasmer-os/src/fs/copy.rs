@@ -0,0 +1,141 @@
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+use wasmer_bus_fuse::api as backend;
+use wasmer_bus_fuse::api::CopyProgress;
+
+use super::normalize_path;
+use crate::wasmer_vfs::*;
+
+/// Number of bytes that are streamed at a time when copying between mounts
+pub const COPY_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Progress is reported after every file and after this many bytes
+pub const COPY_PROGRESS_INTERVAL: u64 = 8 * 1024 * 1024;
+
+/// Keeps track of how far a copy has progressed and whether it has been
+/// cancelled (copies check this between every block they copy)
+pub struct CopyTracker {
+    progress: Mutex<(CopyProgress, u64)>,
+    cancelled: Arc<AtomicBool>,
+    on_progress: Box<dyn Fn(CopyProgress) + Send + Sync + 'static>,
+}
+
+impl CopyTracker {
+    pub fn new(
+        cancelled: Arc<AtomicBool>,
+        on_progress: impl Fn(CopyProgress) + Send + Sync + 'static,
+    ) -> CopyTracker {
+        CopyTracker {
+            progress: Mutex::new((CopyProgress::default(), 0)),
+            cancelled,
+            on_progress: Box::new(on_progress),
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Fails with `FsError::Interrupted` if the copy was cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(FsError::Interrupted);
+        }
+        Ok(())
+    }
+
+    pub fn progress(&self) -> CopyProgress {
+        let guard = self.progress.lock().unwrap();
+        guard.0.clone()
+    }
+
+    pub fn started_file(&self, path: &str) {
+        let mut guard = self.progress.lock().unwrap();
+        guard.0.current = Some(path.to_string());
+    }
+
+    pub fn copied_bytes(&self, amt: u64) {
+        let report = {
+            let mut guard = self.progress.lock().unwrap();
+            guard.0.bytes += amt;
+            if guard.0.bytes - guard.1 >= COPY_PROGRESS_INTERVAL {
+                guard.1 = guard.0.bytes;
+                Some(guard.0.clone())
+            } else {
+                None
+            }
+        };
+        if let Some(progress) = report {
+            (self.on_progress)(progress);
+        }
+    }
+
+    pub fn copied_file(&self) {
+        let progress = {
+            let mut guard = self.progress.lock().unwrap();
+            guard.0.files += 1;
+            guard.1 = guard.0.bytes;
+            guard.0.clone()
+        };
+        (self.on_progress)(progress);
+    }
+
+    pub fn copied_dir(&self) {
+        let mut guard = self.progress.lock().unwrap();
+        guard.0.dirs += 1;
+    }
+
+    /// Takes the progress reported by a file system that is performing the
+    /// copy itself
+    pub fn update(&self, progress: CopyProgress) {
+        {
+            let mut guard = self.progress.lock().unwrap();
+            guard.1 = progress.bytes;
+            guard.0 = progress.clone();
+        }
+        (self.on_progress)(progress);
+    }
+}
+
+/// Returns true if the destination of a copy is the source itself or is
+/// somewhere beneath it
+pub fn is_copy_into_itself(from: &Path, to: &Path) -> bool {
+    let from = normalize_path(from.to_string_lossy().as_ref());
+    let to = normalize_path(to.to_string_lossy().as_ref());
+    to == from || to.starts_with(format!("{}/", from.trim_end_matches('/')).as_str())
+}
+
+pub fn conv_fs_error_back(err: FsError) -> backend::FsError {
+    match err {
+        FsError::BaseNotDirectory => backend::FsError::BaseNotDirectory,
+        FsError::NotAFile => backend::FsError::NotAFile,
+        FsError::InvalidFd => backend::FsError::InvalidFd,
+        FsError::AlreadyExists => backend::FsError::AlreadyExists,
+        FsError::Lock => backend::FsError::Lock,
+        FsError::IOError => backend::FsError::IOError,
+        FsError::AddressInUse => backend::FsError::AddressInUse,
+        FsError::AddressNotAvailable => backend::FsError::AddressNotAvailable,
+        FsError::BrokenPipe => backend::FsError::BrokenPipe,
+        FsError::ConnectionAborted => backend::FsError::ConnectionAborted,
+        FsError::ConnectionRefused => backend::FsError::ConnectionRefused,
+        FsError::ConnectionReset => backend::FsError::ConnectionReset,
+        FsError::Interrupted => backend::FsError::Interrupted,
+        FsError::InvalidData => backend::FsError::InvalidData,
+        FsError::InvalidInput => backend::FsError::InvalidInput,
+        FsError::NotConnected => backend::FsError::NotConnected,
+        FsError::EntityNotFound => backend::FsError::EntityNotFound,
+        FsError::NoDevice => backend::FsError::NoDevice,
+        FsError::PermissionDenied => backend::FsError::PermissionDenied,
+        FsError::TimedOut => backend::FsError::TimedOut,
+        FsError::UnexpectedEof => backend::FsError::UnexpectedEof,
+        FsError::WouldBlock => backend::FsError::WouldBlock,
+        FsError::WriteZero => backend::FsError::WriteZero,
+        FsError::DirectoryNotEmpty => backend::FsError::DirectoryNotEmpty,
+        _ => backend::FsError::UnknownError,
+    }
+}
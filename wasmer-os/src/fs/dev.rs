@@ -0,0 +1,376 @@
+#![allow(dead_code)]
+#![allow(unused)]
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::io::{self};
+use std::path::{Path, PathBuf};
+use std::result::Result as StdResult;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::wasmer_vfs::Result as FsResult;
+use crate::wasmer_vfs::*;
+use crate::wasmer_vfs::{FileDescriptor, VirtualFile};
+use crate::wasmer_wasi::{types as wasi_types, WasiFile, WasiFsError};
+
+use super::api::*;
+use super::proc::NullFile;
+use super::proc::TtyFile;
+use crate::api::System;
+use crate::bus::WasmCallerContext;
+use crate::signal::Signal;
+use crate::stdio::*;
+
+/// Names of the device files that are served by the device file system
+pub const DEV_DEVICES: [&'static str; 6] = ["null", "zero", "random", "urandom", "tty", "signal"];
+
+/// Returns true if the path refers to the controlling terminal
+pub fn is_dev_tty(path: &str) -> bool {
+    path == "/dev/tty"
+}
+
+/// Returns true if the path refers to a device that never runs dry when
+/// read (or never fills up when written) and hence should not be buffered
+pub fn is_dev_stream(path: &str) -> bool {
+    match path {
+        "/dev/null" | "/dev/zero" | "/dev/random" | "/dev/urandom" | "/dev/tty" | "/dev/signal" => true,
+        _ => false,
+    }
+}
+
+/// Device files (null, zero, random, tty and signal) that are mounted at /dev,
+/// the terminal and signals are only available when the file system is bound
+/// to a session and process respectively
+#[derive(Debug, Clone)]
+pub struct DevFileSystem {
+    type_dir: FileType,
+    type_char: FileType,
+    terminal: Option<Stdio>,
+    process: Option<WasmCallerContext>,
+}
+
+impl DevFileSystem {
+    pub fn new() -> DevFileSystem {
+        let mut ret = DevFileSystem {
+            type_dir: FileType::default(),
+            type_char: FileType::default(),
+            terminal: None,
+            process: None,
+        };
+        ret.type_dir.dir = true;
+        ret.type_char.char_device = true;
+        ret
+    }
+
+    /// Binds /dev/tty to the interactive terminal (which is not necessarily
+    /// the stdio of the process as that may have been redirected)
+    pub fn with_terminal(mut self, terminal: Stdio) -> DevFileSystem {
+        self.terminal = Some(terminal);
+        self
+    }
+
+    /// Binds /dev/signal to the process that will receive the signals
+    pub fn with_process(mut self, process: WasmCallerContext) -> DevFileSystem {
+        self.process = Some(process);
+        self
+    }
+
+    fn default_metadata(type_: &FileType) -> Metadata {
+        Metadata {
+            ft: type_.clone(),
+            accessed: 0,
+            created: 0,
+            modified: 0,
+            len: 0,
+        }
+    }
+
+    fn device_name(path: &Path) -> Option<&'static str> {
+        let path = path.to_string_lossy();
+        let name = path.trim_start_matches("/");
+        DEV_DEVICES.iter().filter(|a| **a == name).map(|a| *a).next()
+    }
+
+    pub(crate) fn entries(&self) -> Vec<DirEntry> {
+        DEV_DEVICES
+            .iter()
+            .map(|name| DirEntry {
+                path: PathBuf::from(name),
+                metadata: Ok(Self::default_metadata(&self.type_char)),
+            })
+            .collect()
+    }
+
+    pub(crate) fn open_device(
+        &self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> FsResult<Box<dyn VirtualFile + Send + Sync>> {
+        match Self::device_name(path) {
+            Some("null") => Ok(Box::new(NullFile::default())),
+            Some("zero") => Ok(Box::new(ZeroFile::default())),
+            Some("random") | Some("urandom") => Ok(Box::new(RandomFile::default())),
+            Some("tty") => match &self.terminal {
+                Some(terminal) => Ok(Box::new(TtyFile::new_ext(terminal, conf.read()))),
+                None => Err(FsError::NoDevice),
+            },
+            Some("signal") => match &self.process {
+                Some(process) => {
+                    // Opening the device means the process will handle signals itself
+                    process.set_signal_handler(true);
+                    Ok(Box::new(SignalFile::new(process.clone())))
+                }
+                None => Err(FsError::NoDevice),
+            },
+            _ => Err(FsError::EntityNotFound),
+        }
+    }
+}
+
+impl MountedFileSystem for DevFileSystem {
+    fn set_ctx(&self, ctx: &WasmCallerContext) {}
+}
+
+impl FileSystem for DevFileSystem {
+    fn read_dir(&self, path: &Path) -> FsResult<ReadDir> {
+        debug!("read_dir: path={}", path.display());
+        match path.to_string_lossy().as_ref() {
+            "/" | "" => Ok(ReadDir::new(self.entries())),
+            _ => Err(FsError::EntityNotFound),
+        }
+    }
+    fn create_dir(&self, path: &Path) -> FsResult<()> {
+        debug!("create_dir: path={}", path.display());
+        Err(FsError::PermissionDenied)
+    }
+    fn remove_dir(&self, path: &Path) -> FsResult<()> {
+        debug!("remove_dir: path={}", path.display());
+        Err(FsError::PermissionDenied)
+    }
+    fn rename(&self, from: &Path, to: &Path) -> FsResult<()> {
+        debug!("rename: from={} to={}", from.display(), to.display());
+        Err(FsError::PermissionDenied)
+    }
+    fn metadata(&self, path: &Path) -> FsResult<Metadata> {
+        debug!("metadata: path={}", path.display());
+        match path.to_string_lossy().as_ref() {
+            "/" | "" => return Ok(Self::default_metadata(&self.type_dir)),
+            _ => {}
+        }
+        match Self::device_name(path) {
+            Some(_) => Ok(Self::default_metadata(&self.type_char)),
+            None => Err(FsError::EntityNotFound),
+        }
+    }
+    fn symlink_metadata(&self, path: &Path) -> FsResult<Metadata> {
+        debug!("symlink_metadata: path={}", path.display());
+        self.metadata(path)
+    }
+    fn remove_file(&self, path: &Path) -> FsResult<()> {
+        debug!("remove_file: path={}", path.display());
+        Err(FsError::PermissionDenied)
+    }
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(DevFileOpener { dev: self.clone() }))
+    }
+}
+
+#[derive(Debug)]
+struct DevFileOpener {
+    dev: DevFileSystem,
+}
+
+impl FileOpener for DevFileOpener {
+    fn open(
+        &mut self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> FsResult<Box<dyn VirtualFile + Send + Sync>> {
+        debug!("open: path={}", path.display());
+        self.dev.open_device(path, conf)
+    }
+}
+
+/// Endless stream of zeros (writes are discarded)
+#[derive(Debug, Default)]
+pub struct ZeroFile {}
+
+impl Seek for ZeroFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        Ok(0)
+    }
+}
+
+impl Write for ZeroFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for ZeroFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        buf.iter_mut().for_each(|a| *a = 0);
+        Ok(buf.len())
+    }
+}
+
+impl VirtualFile for ZeroFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        0
+    }
+    fn set_len(&mut self, new_size: wasi_types::__wasi_filesize_t) -> StdResult<(), WasiFsError> {
+        Ok(())
+    }
+    fn unlink(&mut self) -> StdResult<(), WasiFsError> {
+        Ok(())
+    }
+    fn bytes_available(&self) -> StdResult<usize, WasiFsError> {
+        Ok(usize::MAX)
+    }
+    fn get_fd(&self) -> Option<FileDescriptor> {
+        None
+    }
+}
+
+/// Endless stream of random bytes sourced from the random number generator
+/// of the host system (writes are discarded)
+#[derive(Debug, Default)]
+pub struct RandomFile {}
+
+impl Seek for RandomFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        Ok(0)
+    }
+}
+
+impl Write for RandomFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for RandomFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        System::default().random(buf)?;
+        Ok(buf.len())
+    }
+}
+
+impl VirtualFile for RandomFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        0
+    }
+    fn set_len(&mut self, new_size: wasi_types::__wasi_filesize_t) -> StdResult<(), WasiFsError> {
+        Ok(())
+    }
+    fn unlink(&mut self) -> StdResult<(), WasiFsError> {
+        Ok(())
+    }
+    fn bytes_available(&self) -> StdResult<usize, WasiFsError> {
+        Ok(usize::MAX)
+    }
+    fn get_fd(&self) -> Option<FileDescriptor> {
+        None
+    }
+}
+
+/// Signals delivered to the process, each read returns the numbers of the
+/// pending signals (one byte per signal) and the file can be polled for them
+#[derive(Debug)]
+pub struct SignalFile {
+    process: WasmCallerContext,
+}
+
+impl SignalFile {
+    pub fn new(process: WasmCallerContext) -> SignalFile {
+        SignalFile { process }
+    }
+}
+
+impl Seek for SignalFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        Ok(0)
+    }
+}
+
+impl Write for SignalFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for SignalFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let signals = self.process.take_signals();
+        if signals.is_empty() {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        let signals = signals.iter().map(Signal::number).collect::<Vec<_>>();
+        let n = signals.len().min(buf.len());
+        buf[..n].copy_from_slice(&signals[..n]);
+        // Signals that did not fit in the buffer are queued up again
+        for num in signals[n..].iter() {
+            if let Some(sig) = Signal::from_number(*num) {
+                self.process.queue_signal(sig);
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl VirtualFile for SignalFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        0
+    }
+    fn set_len(&mut self, new_size: wasi_types::__wasi_filesize_t) -> StdResult<(), WasiFsError> {
+        Ok(())
+    }
+    fn unlink(&mut self) -> StdResult<(), WasiFsError> {
+        Ok(())
+    }
+    fn bytes_available(&self) -> StdResult<usize, WasiFsError> {
+        match self.process.has_pending_signals() {
+            true => Ok(1),
+            false => Ok(0),
+        }
+    }
+    fn get_fd(&self) -> Option<FileDescriptor> {
+        None
+    }
+}
@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use super::normalize_path;
+
+/// Folders that hold the commands, their files are executable unless they
+/// have been marked otherwise
+pub const EXEC_DIRS: [&'static str; 3] = ["/bin/", "/usr/bin/", "/usr/local/bin/"];
+
+/// The virtual file systems do not store permission bits hence the executable
+/// bits of the files are kept by the root file system of the shell (and are
+/// shared with every process that it spawns). Files outside of the command
+/// folders must be marked executable (e.g. with `chmod +x`) before they run.
+#[derive(Debug, Clone, Default)]
+pub struct ExecBits {
+    marks: Arc<RwLock<HashMap<String, bool>>>,
+}
+
+impl ExecBits {
+    pub fn set(&self, path: &str, executable: bool) {
+        let path = normalize_path(path);
+        let mut marks = self.marks.write().unwrap();
+        marks.insert(path, executable);
+    }
+
+    pub fn is_executable(&self, path: &str) -> bool {
+        let path = normalize_path(path);
+        let marks = self.marks.read().unwrap();
+        match marks.get(&path) {
+            Some(a) => *a,
+            None => EXEC_DIRS.iter().any(|dir| path.starts_with(dir)),
+        }
+    }
+}
@@ -11,6 +11,7 @@ use std::sync::Mutex;
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
 use wasmer_bus::abi::SerializationFormat;
+use wasmer_bus::prelude::BusError;
 use wasmer_bus_fuse::api as backend;
 use wasmer_vfs::DirEntry;
 use wasmer_vfs::FileOpener;
@@ -24,6 +25,10 @@ use wasmer_vfs::ReadDir;
 use wasmer_vfs::VirtualFile;
 
 use super::api::*;
+use super::CopyTracker;
+use super::SearchKind;
+use super::SearchQuery;
+use super::SearchTracker;
 use crate::api::*;
 use crate::bus::SubProcess;
 use crate::bus::WasmCallerContext;
@@ -47,21 +52,32 @@ impl FuseFileSystem {
     pub async fn new(
         process: Arc<SubProcess>,
         target: &str,
+        options: backend::MountOptions,
         mut stdio: Stdio,
     ) -> Result<FuseFileSystem, FsError> {
-        let task = process
-            .runtime
-            .feeder()
-            .call(
+        // Plain mounts use the original request so that backends which
+        // predate the mount options can still serve them
+        let feeder = process.runtime.feeder();
+        let task = if options == backend::MountOptions::default() {
+            feeder.call(
                 SerializationFormat::Json,
                 backend::FuseMountRequest {
                     name: target.to_string(),
                 },
             )
-            .map_err(|err| {
-                debug!("fuse_file_system::new() - mount call failed(r1) - {}", err);
-                FsError::IOError
-            })?;
+        } else {
+            feeder.call(
+                SerializationFormat::Json,
+                backend::FuseMountWithOptionsRequest {
+                    name: target.to_string(),
+                    options,
+                },
+            )
+        }
+        .map_err(|err| {
+            debug!("fuse_file_system::new() - mount call failed(r1) - {}", err);
+            FsError::IOError
+        })?;
         info!(
             "file system (target={}) opened",
             target,
@@ -127,6 +143,249 @@ impl MountedFileSystem for FuseFileSystem {
         let mut guard = self.ctx.lock().unwrap();
         guard.replace(ctx.clone());
     }
+
+    fn lock(
+        &self,
+        path: &Path,
+        options: backend::LockOptions,
+    ) -> Result<Box<dyn FileLockHandle>, FsError> {
+        debug!("lock: path={}", path.display());
+
+        // Every lock opens the file again so that it belongs to that open
+        // file alone, closing it (by dropping the handle) releases the lock
+        let file = self
+            .task
+            .call(
+                SerializationFormat::Json,
+                backend::FileSystemOpenRequest {
+                    options: backend::OpenOptions {
+                        read: true,
+                        write: false,
+                        create_new: false,
+                        create: false,
+                        append: false,
+                        truncate: false,
+                    },
+                    path: path.to_string_lossy().to_string(),
+                },
+            )
+            .map_err(|_| FsError::IOError)?;
+
+        file.call(
+            SerializationFormat::Json,
+            backend::OpenedFileLockRequest { options },
+        )
+        .map_err(|_| FsError::IOError)?
+        .block_on()
+        .map_err(|_| FsError::IOError)?
+        .value::<Result<(), backend::FsError>>()
+        .map_err(|_| FsError::IOError)?
+        .map_err(conv_fs_error)?;
+
+        Ok(Box::new(FuseFileLock { file }))
+    }
+
+    fn copy(
+        &self,
+        from: &Path,
+        to: &Path,
+        recursive: bool,
+        tracker: &CopyTracker,
+    ) -> Option<Result<(), FsError>> {
+        debug!("copy: from={}, to={}, recursive={}", from.display(), to.display(), recursive);
+
+        let mut call = match self.task.call(
+            SerializationFormat::Json,
+            backend::FileSystemCopyRequest {
+                from: from.to_string_lossy().to_string(),
+                to: to.to_string_lossy().to_string(),
+                recursive,
+            },
+        ) {
+            Ok(a) => a,
+            Err(_) => {
+                return Some(Err(FsError::IOError));
+            }
+        };
+
+        let progress = Arc::new(Mutex::new(None));
+        let finished = Arc::new(Mutex::new(None));
+        {
+            let progress = progress.clone();
+            call.callback(move |data: backend::FileSystemCopyProgressCallback| {
+                let mut guard = progress.lock().unwrap();
+                guard.replace(data.0);
+            });
+        }
+        {
+            let finished = finished.clone();
+            call.callback(move |data: backend::FileSystemCopyFinishedCallback| {
+                let mut guard = finished.lock().unwrap();
+                guard.replace(data.0);
+            });
+        }
+
+        // Backends that can not copy by themselves are streamed by the caller
+        let mut call = match call.block_on() {
+            Ok(a) => a.handle(),
+            Err(BusError::Unsupported) | Err(BusError::InvalidTopic) => {
+                return None;
+            }
+            Err(err) => {
+                debug!("copy failed - {}", err);
+                return Some(Err(FsError::IOError));
+            }
+        };
+
+        let ret = call.block_on_callbacks(|| {
+            let update = progress.lock().unwrap().take();
+            if let Some(update) = update {
+                tracker.update(update);
+            }
+            tracker.is_cancelled() || finished.lock().unwrap().is_some()
+        });
+        if let Err(err) = ret {
+            debug!("copy failed - {}", err);
+            return Some(Err(FsError::IOError));
+        }
+
+        if tracker.is_cancelled() {
+            let _ = call
+                .call(SerializationFormat::Json, backend::FileCopyCancelRequest {})
+                .map(|a| a.block_on().map(|a| a.discard()));
+            return Some(Err(FsError::Interrupted));
+        }
+
+        let finished = finished.lock().unwrap().take();
+        Some(match finished {
+            Some(Ok(progress)) => {
+                tracker.update(progress);
+                Ok(())
+            }
+            Some(Err(err)) => Err(conv_fs_error(err)),
+            None => Err(FsError::IOError),
+        })
+    }
+
+    fn search(
+        &self,
+        root: &Path,
+        query: &SearchQuery,
+        tracker: &SearchTracker,
+    ) -> Option<Result<(), FsError>> {
+        debug!("search: root={}, glob={}", root.display(), query.glob);
+
+        let root_str = root.to_string_lossy().to_string();
+        let call = match &query.kind {
+            SearchKind::Paths { return_metadata } => self.task.call(
+                SerializationFormat::Json,
+                backend::FileSystemSearchRequest {
+                    root: root_str,
+                    glob: query.glob.clone(),
+                    max_results: query.max_matches,
+                    return_metadata: *return_metadata,
+                },
+            ),
+            SearchKind::Content { pattern, regex } => self.task.call(
+                SerializationFormat::Json,
+                backend::FileSystemSearchContentRequest {
+                    root: root_str,
+                    glob: query.glob.clone(),
+                    pattern: pattern.clone(),
+                    regex: *regex,
+                    max_matches: query.max_matches,
+                },
+            ),
+        };
+        let mut call = match call {
+            Ok(a) => a,
+            Err(_) => {
+                return Some(Err(FsError::IOError));
+            }
+        };
+
+        // The batches are relayed in the order they arrive
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let finished = Arc::new(Mutex::new(None));
+        {
+            let paths = paths.clone();
+            call.callback(move |data: backend::FileSystemSearchFoundCallback| {
+                let mut guard = paths.lock().unwrap();
+                guard.push(data.0);
+            });
+        }
+        {
+            let lines = lines.clone();
+            call.callback(move |data: backend::FileSystemSearchContentFoundCallback| {
+                let mut guard = lines.lock().unwrap();
+                guard.push(data.0);
+            });
+        }
+        {
+            let finished = finished.clone();
+            call.callback(move |data: backend::FileSystemSearchFinishedCallback| {
+                let mut guard = finished.lock().unwrap();
+                guard.replace(data.0);
+            });
+        }
+        {
+            let finished = finished.clone();
+            call.callback(move |data: backend::FileSystemSearchContentFinishedCallback| {
+                let mut guard = finished.lock().unwrap();
+                guard.replace(data.0);
+            });
+        }
+        let relay = || {
+            let batches = paths.lock().unwrap().drain(..).collect::<Vec<_>>();
+            for batch in batches {
+                tracker.relay_paths(batch);
+            }
+            let batches = lines.lock().unwrap().drain(..).collect::<Vec<_>>();
+            for batch in batches {
+                tracker.relay_lines(batch);
+            }
+        };
+
+        // Backends that can not search by themselves are walked by the caller
+        let mut call = match call.block_on() {
+            Ok(a) => a.handle(),
+            Err(BusError::Unsupported) | Err(BusError::InvalidTopic) => {
+                return None;
+            }
+            Err(err) => {
+                debug!("search failed - {}", err);
+                return Some(Err(FsError::IOError));
+            }
+        };
+
+        let ret = call.block_on_callbacks(|| {
+            relay();
+            tracker.is_cancelled() || finished.lock().unwrap().is_some()
+        });
+        relay();
+        if let Err(err) = ret {
+            debug!("search failed - {}", err);
+            return Some(Err(FsError::IOError));
+        }
+
+        if tracker.is_cancelled() {
+            let _ = call
+                .call(SerializationFormat::Json, backend::FileSearchCancelRequest {})
+                .map(|a| a.block_on().map(|a| a.discard()));
+            return Some(Err(FsError::Interrupted));
+        }
+
+        let finished = finished.lock().unwrap().take();
+        Some(match finished {
+            Some(Ok(summary)) => {
+                tracker.update(summary);
+                Ok(())
+            }
+            Some(Err(err)) => Err(conv_fs_error(err)),
+            None => Err(FsError::IOError),
+        })
+    }
 }
 
 impl FileSystem for FuseFileSystem {
@@ -368,6 +627,30 @@ impl FileOpener for FuseFileOpener {
     }
 }
 
+/// Advisory lock held through a file that was opened just for it
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct FuseFileLock {
+    #[derivative(Debug = "ignore")]
+    file: RuntimeCallOutsideHandle,
+}
+
+impl FileLockHandle for FuseFileLock {
+    fn unlock(self: Box<Self>) -> Result<(), FsError> {
+        self.file
+            .call(
+                SerializationFormat::Json,
+                backend::OpenedFileUnlockRequest {},
+            )
+            .map_err(|_| FsError::IOError)?
+            .block_on()
+            .map_err(|_| FsError::IOError)?
+            .value::<Result<(), backend::FsError>>()
+            .map_err(|_| FsError::IOError)?
+            .map_err(conv_fs_error)
+    }
+}
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct FuseVirtualFile {
@@ -615,6 +898,9 @@ fn conv_fs_error(err: backend::FsError) -> FsError {
         backend::FsError::WouldBlock => FsError::WouldBlock,
         backend::FsError::WriteZero => FsError::WriteZero,
         backend::FsError::DirectoryNotEmpty => FsError::DirectoryNotEmpty,
+        // the virtual file system reports a full device as a write of zero
+        // bytes (which the guest sees as ENOSPC), just like the tmp file system
+        backend::FsError::StorageFull => FsError::WriteZero,
         backend::FsError::UnknownError => FsError::UnknownError,
     }
 }
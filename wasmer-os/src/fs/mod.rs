@@ -1,17 +1,26 @@
 mod api;
 mod asyncify;
+mod copy;
+mod dev;
+mod exec;
 mod ext;
 mod fuse;
 mod proc;
+mod search;
+mod tests;
 mod tmp;
 mod union;
 mod utils;
 
 pub use api::*;
 pub use asyncify::*;
+pub use copy::*;
+pub use dev::*;
+pub use exec::*;
 pub use ext::*;
 pub use fuse::*;
 pub use proc::*;
+pub use search::*;
 pub use tmp::*;
 pub use union::*;
 pub use utils::*;
\ No newline at end of file
@@ -22,6 +22,7 @@ use crate::wasmer_vfs::{FileDescriptor, VirtualFile};
 use crate::wasmer_wasi::{types as wasi_types, WasiFile, WasiFsError};
 
 use super::api::*;
+use super::dev::DevFileSystem;
 use crate::bus::WasmCallerContext;
 use crate::fd::*;
 use crate::stdio::*;
@@ -33,15 +34,17 @@ pub struct ProcFileSystem {
     type_dir: FileType,
     type_char: FileType,
     stdio: Stdio,
+    dev: DevFileSystem,
 }
 
 impl ProcFileSystem {
-    pub fn new(stdio: Stdio) -> ProcFileSystem {
+    pub fn new(stdio: Stdio, dev: DevFileSystem) -> ProcFileSystem {
         let mut ret = ProcFileSystem {
             type_file: FileType::default(),
             type_dir: FileType::default(),
             type_char: FileType::default(),
             stdio,
+            dev,
         };
         ret.type_file.file = true;
         ret.type_dir.dir = true;
@@ -102,14 +105,7 @@ impl FileSystem for ProcFileSystem {
                     path: PathBuf::from("log"),
                     metadata: Ok(Self::default_metadata(&self.type_file)),
                 });
-                entries.push(DirEntry {
-                    path: PathBuf::from("null"),
-                    metadata: Ok(Self::default_metadata(&self.type_file)),
-                });
-                entries.push(DirEntry {
-                    path: PathBuf::from("tty"),
-                    metadata: Ok(Self::default_metadata(&self.type_file)),
-                });
+                entries.extend(self.dev.entries());
             }
             _ => {
                 return Err(FsError::EntityNotFound);
@@ -139,9 +135,7 @@ impl FileSystem for ProcFileSystem {
             "/stdout" | "stdout" => Ok(Self::default_metadata(&self.type_file)),
             "/stderr" | "stderr" => Ok(Self::default_metadata(&self.type_file)),
             "/log" | "log" => Ok(Self::default_metadata(&self.type_file)),
-            "/null" | "null" => Ok(Self::default_metadata(&self.type_file)),
-            "/tty" | "tty" => Ok(Self::default_metadata(&self.type_file)),
-            _ => Err(FsError::EntityNotFound),
+            _ => self.dev.metadata(Path::new(path)),
         }
     }
     fn symlink_metadata(&self, path: &Path) -> FsResult<Metadata> {
@@ -155,6 +149,7 @@ impl FileSystem for ProcFileSystem {
     fn new_open_options(&self) -> OpenOptions {
         let opener = Box::new(CoreFileOpener {
             stdio: self.stdio.clone(),
+            dev: self.dev.clone(),
         });
         OpenOptions::new(opener)
     }
@@ -163,6 +158,7 @@ impl FileSystem for ProcFileSystem {
 #[derive(Debug)]
 pub struct CoreFileOpener {
     stdio: Stdio,
+    dev: DevFileSystem,
 }
 
 impl FileOpener for CoreFileOpener {
@@ -179,9 +175,7 @@ impl FileOpener for CoreFileOpener {
             "/stdout" | "stdout" => Ok(Box::new(self.stdio.stdout.clone())),
             "/stderr" | "stderr" => Ok(Box::new(self.stdio.stderr.clone())),
             "/log" | "log" => Ok(Box::new(self.stdio.log.clone())),
-            "/null" | "null" => Ok(Box::new(NullFile::default())),
-            "/tty" | "tty" => Ok(Box::new(TtyFile::new(&self.stdio))),
-            _ => Err(FsError::EntityNotFound),
+            _ => self.dev.open_device(Path::new(path), conf),
         }
     }
 }
@@ -191,20 +185,30 @@ pub struct TtyFile {
     fd_stdin: Fd,
     fd_stdout: Fd,
     tty: Tty,
+    unbuffered: bool,
 }
 
 impl TtyFile {
     pub fn new(stdio: &Stdio) -> TtyFile {
+        TtyFile::new_ext(stdio, true)
+    }
+
+    /// Only terminals that are opened for reading switch off the line
+    /// buffering of the input, output only handles leave it untouched
+    pub fn new_ext(stdio: &Stdio, read: bool) -> TtyFile {
         let mut fd_stdin = stdio.stdin.clone();
         let mut fd_stdout = stdio.stdout.clone();
         fd_stdin.set_flag(FdFlag::Stdin(true));
         fd_stdout.set_flag(FdFlag::Stdout(true));
 
-        stdio.tty.set_buffering(false);
+        if read {
+            stdio.tty.set_buffering(false);
+        }
         TtyFile {
             fd_stdin,
             fd_stdout,
             tty: stdio.tty.clone(),
+            unbuffered: read,
         }
     }
 
@@ -220,7 +224,9 @@ impl TtyFile {
 
 impl Drop for TtyFile {
     fn drop(&mut self) {
-        self.tty.set_buffering(true);
+        if self.unbuffered {
+            self.tty.set_buffering(true);
+        }
     }
 }
 
@@ -0,0 +1,266 @@
+use regex::Regex;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+use wasmer_bus_fuse::api as backend;
+use wasmer_bus_fuse::api::SearchSummary;
+use wasmer_bus_fuse::prelude::SEARCH_BATCH_SIZE;
+
+use crate::wasmer_vfs::*;
+
+/// What a search is looking for
+#[derive(Debug, Clone)]
+pub enum SearchKind {
+    Paths { return_metadata: bool },
+    Content { pattern: String, regex: bool },
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub glob: String,
+    pub max_matches: Option<u64>,
+    pub kind: SearchKind,
+}
+
+impl SearchQuery {
+    /// Compiles the pattern of a content search (literal patterns are
+    /// escaped so that they are matched as they are)
+    pub fn pattern(&self) -> Result<Option<Regex>> {
+        match &self.kind {
+            SearchKind::Content { pattern, regex: is_regex } => {
+                let pattern = match is_regex {
+                    true => Regex::new(pattern.as_str()),
+                    false => Regex::new(regex::escape(pattern.as_str()).as_str()),
+                };
+                pattern.map(Some).map_err(|err| {
+                    debug!("search pattern is invalid - {}", err);
+                    FsError::InvalidInput
+                })
+            }
+            SearchKind::Paths { .. } => Ok(None),
+        }
+    }
+}
+
+#[derive(Default)]
+struct SearchState {
+    summary: SearchSummary,
+    paths: Vec<backend::DirEntry>,
+    lines: Vec<backend::SearchMatch>,
+}
+
+struct SearchTrackerInner {
+    state: Mutex<SearchState>,
+    cancelled: Arc<AtomicBool>,
+    max_matches: Option<u64>,
+    on_paths: Box<dyn Fn(Vec<backend::DirEntry>) + Send + Sync + 'static>,
+    on_lines: Box<dyn Fn(Vec<backend::SearchMatch>) + Send + Sync + 'static>,
+}
+
+/// Collects the results of a search into batches and keeps track of how
+/// many matched and whether it has been cancelled (searches check this
+/// between every entry they visit). Results found by a mount are relocated
+/// from the paths within the mount to the paths of the union.
+#[derive(Clone)]
+pub struct SearchTracker {
+    inner: Arc<SearchTrackerInner>,
+    relocate: Option<(PathBuf, PathBuf)>,
+}
+
+impl std::fmt::Debug for SearchTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "search-tracker")
+    }
+}
+
+impl SearchTracker {
+    pub fn new(
+        cancelled: Arc<AtomicBool>,
+        max_matches: Option<u64>,
+        on_paths: impl Fn(Vec<backend::DirEntry>) + Send + Sync + 'static,
+        on_lines: impl Fn(Vec<backend::SearchMatch>) + Send + Sync + 'static,
+    ) -> SearchTracker {
+        SearchTracker {
+            inner: Arc::new(SearchTrackerInner {
+                state: Mutex::new(SearchState::default()),
+                cancelled,
+                max_matches,
+                on_paths: Box::new(on_paths),
+                on_lines: Box::new(on_lines),
+            }),
+            relocate: None,
+        }
+    }
+
+    /// Returns a tracker that reports the paths under `from` as though
+    /// they were under `to` instead
+    pub fn relocate(&self, from: &Path, to: &Path) -> SearchTracker {
+        SearchTracker {
+            inner: self.inner.clone(),
+            relocate: Some((from.to_path_buf(), to.to_path_buf())),
+        }
+    }
+
+    fn path(&self, path: &str) -> String {
+        match &self.relocate {
+            Some((from, to)) => match Path::new(path).strip_prefix(from) {
+                Ok(rest) => to.join(rest).to_string_lossy().to_string(),
+                Err(_) => path.to_string(),
+            },
+            None => path.to_string(),
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Fails with `FsError::Interrupted` if the search was cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(FsError::Interrupted);
+        }
+        Ok(())
+    }
+
+    /// Counts another match, unless the search already has as many as it
+    /// was asked for (in which case the results are truncated)
+    fn accept(&self, state: &mut SearchState) -> bool {
+        if let Some(max) = self.inner.max_matches {
+            if state.summary.matches >= max {
+                state.summary.truncated = true;
+                return false;
+            }
+        }
+        state.summary.matches += 1;
+        true
+    }
+
+    /// Records a path that matched, returns false once the search is full
+    pub fn found_path(&self, path: &Path, metadata: Option<Metadata>) -> bool {
+        let batch = {
+            let mut state = self.inner.state.lock().unwrap();
+            if self.accept(&mut state) == false {
+                return false;
+            }
+            state.paths.push(backend::DirEntry {
+                path: self.path(path.to_string_lossy().as_ref()),
+                metadata: metadata.map(conv_metadata_back),
+            });
+            match state.paths.len() >= SEARCH_BATCH_SIZE {
+                true => Some(state.paths.drain(..).collect::<Vec<_>>()),
+                false => None,
+            }
+        };
+        if let Some(batch) = batch {
+            (self.inner.on_paths)(batch);
+        }
+        true
+    }
+
+    /// Records a line that matched, returns false once the search is full
+    pub fn found_line(&self, path: &Path, line_number: u64, line: String) -> bool {
+        let batch = {
+            let mut state = self.inner.state.lock().unwrap();
+            if self.accept(&mut state) == false {
+                return false;
+            }
+            state.lines.push(backend::SearchMatch {
+                path: self.path(path.to_string_lossy().as_ref()),
+                line_number,
+                line,
+            });
+            match state.lines.len() >= SEARCH_BATCH_SIZE {
+                true => Some(state.lines.drain(..).collect::<Vec<_>>()),
+                false => None,
+            }
+        };
+        if let Some(batch) = batch {
+            (self.inner.on_lines)(batch);
+        }
+        true
+    }
+
+    /// Takes the results that a file system found while it was performing
+    /// the search itself (it already applied the maximum)
+    pub fn relay_paths(&self, batch: Vec<backend::DirEntry>) {
+        let batch = batch
+            .into_iter()
+            .map(|mut a| {
+                a.path = self.path(a.path.as_str());
+                a
+            })
+            .collect::<Vec<_>>();
+        {
+            let mut state = self.inner.state.lock().unwrap();
+            state.summary.matches += batch.len() as u64;
+        }
+        (self.inner.on_paths)(batch);
+    }
+
+    pub fn relay_lines(&self, batch: Vec<backend::SearchMatch>) {
+        let batch = batch
+            .into_iter()
+            .map(|mut a| {
+                a.path = self.path(a.path.as_str());
+                a
+            })
+            .collect::<Vec<_>>();
+        {
+            let mut state = self.inner.state.lock().unwrap();
+            state.summary.matches += batch.len() as u64;
+        }
+        (self.inner.on_lines)(batch);
+    }
+
+    /// Takes the summary reported by a file system that performed the search
+    pub fn update(&self, summary: SearchSummary) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.summary = summary;
+    }
+
+    /// Sends the results that are still waiting in a batch
+    pub fn flush(&self) {
+        let (paths, lines) = {
+            let mut state = self.inner.state.lock().unwrap();
+            (
+                state.paths.drain(..).collect::<Vec<_>>(),
+                state.lines.drain(..).collect::<Vec<_>>(),
+            )
+        };
+        if paths.len() > 0 {
+            (self.inner.on_paths)(paths);
+        }
+        if lines.len() > 0 {
+            (self.inner.on_lines)(lines);
+        }
+    }
+
+    pub fn summary(&self) -> SearchSummary {
+        let state = self.inner.state.lock().unwrap();
+        state.summary.clone()
+    }
+}
+
+pub fn conv_metadata_back(metadata: Metadata) -> backend::Metadata {
+    backend::Metadata {
+        ft: backend::FileType {
+            dir: metadata.ft.dir,
+            file: metadata.ft.file,
+            symlink: metadata.ft.symlink,
+            char_device: metadata.ft.char_device,
+            block_device: metadata.ft.block_device,
+            socket: metadata.ft.socket,
+            fifo: metadata.ft.fifo,
+        },
+        accessed: metadata.accessed,
+        created: metadata.created,
+        modified: metadata.modified,
+        len: metadata.len,
+    }
+}
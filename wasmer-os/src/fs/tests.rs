@@ -0,0 +1,296 @@
+#![cfg(test)]
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::wasmer_vfs::*;
+
+use super::*;
+
+#[test]
+fn test_dev_lists_devices() {
+    let dev = DevFileSystem::new();
+
+    let mut names = dev
+        .read_dir(Path::new("/"))
+        .unwrap()
+        .map(|a| a.unwrap().path.to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["null", "random", "signal", "tty", "urandom", "zero"]);
+
+    assert!(dev.metadata(Path::new("/")).unwrap().is_dir());
+    assert!(dev.metadata(Path::new("/zero")).unwrap().ft.char_device);
+    assert_eq!(dev.metadata(Path::new("/disk")).unwrap_err(), FsError::EntityNotFound);
+    assert_eq!(dev.read_dir(Path::new("/zero")).unwrap_err(), FsError::EntityNotFound);
+}
+
+#[test]
+fn test_dev_is_read_only() {
+    let dev = DevFileSystem::new();
+    assert_eq!(dev.create_dir(Path::new("/disk")).unwrap_err(), FsError::PermissionDenied);
+    assert_eq!(dev.remove_dir(Path::new("/")).unwrap_err(), FsError::PermissionDenied);
+    assert_eq!(dev.remove_file(Path::new("/null")).unwrap_err(), FsError::PermissionDenied);
+    assert_eq!(
+        dev.rename(Path::new("/null"), Path::new("/void")).unwrap_err(),
+        FsError::PermissionDenied
+    );
+}
+
+#[test]
+fn test_dev_null_and_zero() {
+    let dev = DevFileSystem::new();
+
+    let mut null = dev.new_open_options().read(true).write(true).open("/null").unwrap();
+    let mut buf = [1u8; 16];
+    assert_eq!(null.read(&mut buf).unwrap(), 0);
+    assert_eq!(null.write(b"discarded").unwrap(), 9);
+
+    let mut zero = dev.new_open_options().read(true).write(true).open("/zero").unwrap();
+    assert_eq!(zero.read(&mut buf).unwrap(), buf.len());
+    assert_eq!(buf, [0u8; 16]);
+    assert_eq!(zero.write(b"discarded").unwrap(), 9);
+
+    assert!(is_dev_stream("/dev/zero"));
+    assert!(is_dev_tty("/dev/tty"));
+    assert!(is_dev_stream("/dev/sda") == false);
+}
+
+#[test]
+fn test_dev_unbound_devices() {
+    // The terminal and signals only exist once the file system is bound
+    // to a session and a process
+    let dev = DevFileSystem::new();
+    assert_eq!(
+        dev.new_open_options().read(true).open("/tty").unwrap_err(),
+        FsError::NoDevice
+    );
+    assert_eq!(
+        dev.new_open_options().read(true).open("/signal").unwrap_err(),
+        FsError::NoDevice
+    );
+    assert_eq!(
+        dev.new_open_options().read(true).open("/sda").unwrap_err(),
+        FsError::EntityNotFound
+    );
+}
+
+#[test]
+fn test_exec_bits() {
+    let bits = ExecBits::default();
+    assert!(bits.is_executable("/bin/ls"));
+    assert!(bits.is_executable("/usr/local/bin/tool"));
+    assert!(bits.is_executable("/home/deploy.sh") == false);
+
+    // Marks are normalised and shared by every clone
+    let shared = bits.clone();
+    shared.set("/home/./scripts/../deploy.sh", true);
+    shared.set("/bin/ls", false);
+    assert!(bits.is_executable("/home/deploy.sh"));
+    assert!(bits.is_executable("//home/deploy.sh"));
+    assert!(bits.is_executable("/bin/ls") == false);
+}
+
+#[test]
+fn test_tmp_limit() {
+    let tmp = TmpFileSystem::with_limit(10);
+    let mut a = tmp.new_open_options().write(true).create(true).open("/a").unwrap();
+    assert_eq!(a.write(b"12345678").unwrap(), 8);
+    assert!(a.write(b"abc").is_err());
+    assert_eq!(a.write(b"ab").unwrap(), 2);
+
+    // Overwriting bytes that are already held takes no more space
+    a.seek(SeekFrom::Start(0)).unwrap();
+    assert_eq!(a.write(b"abcd").unwrap(), 4);
+    assert_eq!(a.set_len(11).unwrap_err(), FsError::WriteZero);
+    a.set_len(6).unwrap();
+    drop(a);
+
+    let mut b = tmp.new_open_options().write(true).create(true).open("/b").unwrap();
+    assert_eq!(b.write(b"1234").unwrap(), 4);
+    assert!(b.write(b"5").is_err());
+    drop(b);
+
+    // Space is given back when files are removed or truncated
+    tmp.remove_file(Path::new("/a")).unwrap();
+    let mut b = tmp
+        .new_open_options()
+        .write(true)
+        .truncate(true)
+        .open("/b")
+        .unwrap();
+    assert_eq!(b.write(b"0123456789").unwrap(), 10);
+    assert!(b.write(b"x").is_err());
+}
+
+#[test]
+fn test_tmp_is_expired() {
+    let now = 100_000_000_000u64;
+    let ttl = Duration::from_secs(10);
+    assert!(is_expired(now - 11_000_000_000, now, ttl));
+    assert!(is_expired(now - 9_000_000_000, now, ttl) == false);
+    assert!(is_expired(now + 1_000_000_000, now, ttl) == false);
+    assert!(is_expired(0, now, ttl) == false);
+}
+
+#[test]
+fn test_tmp_expire() {
+    let mut root = UnionFileSystem::new();
+    root.mount("root", "/", false, Box::new(TmpFileSystem::new()), None);
+
+    let tmp = SessionTmp::Persistent {
+        ttl: Some(Duration::from_secs(3600)),
+    };
+    tmp.mount(&mut root);
+    root.create_dir(Path::new("/tmp/work")).unwrap();
+    root.new_open_options()
+        .write(true)
+        .create(true)
+        .open("/tmp/work/a")
+        .unwrap();
+    assert_eq!(tmp.expire(&root), 0);
+    assert!(root.metadata(Path::new("/tmp/work/a")).is_ok());
+
+    // Once the files are older than the TTL they go along with the
+    // directories that they leave empty
+    std::thread::sleep(Duration::from_millis(5));
+    let tmp = SessionTmp::Persistent {
+        ttl: Some(Duration::from_millis(1)),
+    };
+    assert_eq!(tmp.expire(&root), 1);
+    assert!(root.metadata(Path::new("/tmp/work/a")).is_err());
+    assert!(root.metadata(Path::new("/tmp/work")).is_err());
+    assert!(root.metadata(Path::new("/tmp")).is_ok());
+
+    // Ephemeral files are dropped with the session rather than expired
+    let tmp = SessionTmp::Ephemeral { max_size: 1024 };
+    assert_eq!(tmp.expire(&root), 0);
+}
+
+fn write_file(fs: &UnionFileSystem, path: &str, data: &[u8]) {
+    let mut file = fs
+        .new_open_options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .unwrap();
+    file.write_all(data).unwrap();
+}
+
+fn read_file(fs: &UnionFileSystem, path: &str) -> String {
+    let mut file = fs.new_open_options().read(true).open(path).unwrap();
+    let mut ret = String::new();
+    file.read_to_string(&mut ret).unwrap();
+    ret
+}
+
+fn copy_union() -> UnionFileSystem {
+    let mut root = UnionFileSystem::new();
+    root.mount("root", "/", false, Box::new(TmpFileSystem::new()), None);
+    root.mount("mnt", "/mnt", false, Box::new(TmpFileSystem::new()), None);
+    root.create_dir(Path::new("/src")).unwrap();
+    root.create_dir(Path::new("/src/sub")).unwrap();
+    write_file(&root, "/src/a", b"hello");
+    write_file(&root, "/src/sub/b", b"world");
+    root
+}
+
+#[test]
+fn test_copy_recursive() {
+    let root = copy_union();
+    let tracker = CopyTracker::new(Arc::new(AtomicBool::new(false)), |_| {});
+    root.copy(Path::new("/src"), Path::new("/mnt/dst"), true, &tracker)
+        .unwrap()
+        .unwrap();
+    assert_eq!(read_file(&root, "/mnt/dst/a"), "hello");
+    assert_eq!(read_file(&root, "/mnt/dst/sub/b"), "world");
+    let progress = tracker.progress();
+    assert_eq!(progress.files, 2);
+    assert_eq!(progress.dirs, 2);
+    assert_eq!(progress.bytes, 10);
+
+    // Copying again merges into the directories that are already there
+    write_file(&root, "/src/a", b"changed");
+    write_file(&root, "/mnt/dst/extra", b"kept");
+    root.copy(Path::new("/src"), Path::new("/mnt/dst"), true, &tracker)
+        .unwrap()
+        .unwrap();
+    assert_eq!(read_file(&root, "/mnt/dst/a"), "changed");
+    assert_eq!(read_file(&root, "/mnt/dst/extra"), "kept");
+
+    // but a file is not replaced by a directory
+    assert_eq!(
+        root.copy(Path::new("/src/sub"), Path::new("/mnt/dst/a"), true, &tracker)
+            .unwrap()
+            .unwrap_err(),
+        FsError::AlreadyExists
+    );
+
+    // Directories are only copied when asked to
+    assert_eq!(
+        root.copy(Path::new("/src"), Path::new("/mnt/other"), false, &tracker)
+            .unwrap()
+            .unwrap_err(),
+        FsError::NotAFile
+    );
+}
+
+#[test]
+fn test_copy_into_itself() {
+    let root = copy_union();
+    let tracker = CopyTracker::new(Arc::new(AtomicBool::new(false)), |_| {});
+    for to in ["/src", "/src/sub/copy", "/src/../src/copy", "/src/a"] {
+        let from = match to {
+            "/src/a" => "/src/a",
+            _ => "/src",
+        };
+        assert_eq!(
+            root.copy(Path::new(from), Path::new(to), true, &tracker)
+                .unwrap()
+                .unwrap_err(),
+            FsError::InvalidInput
+        );
+    }
+    assert_eq!(read_file(&root, "/src/a"), "hello");
+    assert!(root.metadata(Path::new("/src/sub/copy")).is_err());
+
+    assert!(is_copy_into_itself(Path::new("/"), Path::new("/tmp")));
+    assert!(is_copy_into_itself(Path::new("/src/"), Path::new("/src/x")));
+    assert!(is_copy_into_itself(Path::new("/src"), Path::new("/src2")) == false);
+    assert!(is_copy_into_itself(Path::new("/src/sub"), Path::new("/src")) == false);
+}
+
+#[test]
+fn test_copy_cancelled() {
+    let root = copy_union();
+    let cancelled = Arc::new(AtomicBool::new(true));
+    let tracker = CopyTracker::new(cancelled, |_| {});
+    assert_eq!(
+        root.copy(Path::new("/src"), Path::new("/mnt/dst"), true, &tracker)
+            .unwrap()
+            .unwrap_err(),
+        FsError::Interrupted
+    );
+    assert!(root.metadata(Path::new("/mnt/dst")).is_err());
+}
+
+#[test]
+fn test_physical_path() {
+    let mut root = UnionFileSystem::new();
+    root.mount("root", "/", false, Box::new(TmpFileSystem::new()), None);
+    root.mount("tmp", "/tmp", false, Box::new(TmpFileSystem::new()), None);
+    root.mount("www", "/mnt/www", false, Box::new(TmpFileSystem::new()), Some("/srv/www"));
+
+    // Only mounts that map to a different path change what `pwd -P` prints
+    assert_eq!(root.physical_path("/home/user"), "/home/user");
+    assert_eq!(root.physical_path("/tmp/work"), "/tmp/work");
+    assert_eq!(root.physical_path("/mnt/www/site"), "/srv/www/site");
+    assert_eq!(root.physical_path("/mnt/www"), "/srv/www");
+    assert_eq!(root.physical_path("/mnt/www/../other"), "/mnt/other");
+}
@@ -7,8 +7,11 @@ use std::io::{self};
 use std::path::{Path, PathBuf};
 use std::result::Result as StdResult;
 use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 use tokio::sync::mpsc;
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
@@ -16,23 +19,181 @@ use tracing::{debug, error, info, trace, warn};
 use crate::wasmer_vfs::mem_fs;
 use crate::wasmer_vfs::Result as FsResult;
 use crate::wasmer_vfs::*;
+use crate::wasmer_vfs::{FileDescriptor, VirtualFile};
 use crate::wasmer_wasi::{types as wasi_types, WasiFile, WasiFsError};
 
 use super::api::*;
+use super::union::UnionFileSystem;
 use crate::bus::WasmCallerContext;
 use crate::fd::*;
 use crate::stdio::*;
 use crate::tty::*;
 
+/// Default maximum number of bytes that the in-memory /tmp of a session may hold
+pub const DEFAULT_SESSION_TMP_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Where a session keeps its temporary files (i.e. what is mounted at /tmp),
+/// this must be mounted before the first command of the session runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionTmp {
+    /// Kept in memory (up to a maximum number of bytes) and dropped when
+    /// the session ends
+    Ephemeral { max_size: u64 },
+    /// Kept in the /tmp of the root file system (e.g. the mounted chain) so
+    /// that they outlive the session, when there is a TTL the files that were
+    /// not modified within it are removed by the retention of the file system
+    /// (or as sessions end when it has none)
+    Persistent { ttl: Option<Duration> },
+}
+
+impl Default for SessionTmp {
+    fn default() -> Self {
+        SessionTmp::Ephemeral {
+            max_size: DEFAULT_SESSION_TMP_SIZE,
+        }
+    }
+}
+
+impl SessionTmp {
+    /// Mounts the temporary files of the session on its root file system
+    /// (replacing whatever was mounted at /tmp before)
+    pub fn mount(&self, root: &mut UnionFileSystem) {
+        root.unmount("/tmp");
+        match self {
+            SessionTmp::Ephemeral { max_size } => {
+                let fs = TmpFileSystem::with_limit(*max_size);
+                root.mount("tmp", "/tmp", false, Box::new(fs), None);
+            }
+            SessionTmp::Persistent { ttl } => {
+                let _ = root.create_dir(Path::new("/tmp"));
+
+                // File systems with retention policies (e.g. chains) remove
+                // the expired files themselves, even while no session runs
+                if let Some(Err(err)) = root.set_retention(Path::new("/tmp"), *ttl) {
+                    warn!("failed to set the retention of /tmp - {}", err);
+                }
+            }
+        }
+    }
+
+    /// Removes the persistent temporary files that have outlived the TTL
+    /// (and the directories they leave empty) when the file system has no
+    /// retention of its own, returns how many files were removed
+    pub fn expire(&self, root: &UnionFileSystem) -> usize {
+        let ttl = match self {
+            SessionTmp::Persistent { ttl: Some(ttl) } => *ttl,
+            _ => return 0,
+        };
+        if let Some(Ok(())) = root.set_retention(Path::new("/tmp"), Some(ttl)) {
+            return 0;
+        }
+        let now = chrono::Utc::now().timestamp_nanos().max(0) as u64;
+        let removed = expire_dir(root, Path::new("/tmp"), now, ttl);
+        if removed > 0 {
+            debug!("expired {} temporary files (ttl={}s)", removed, ttl.as_secs());
+        }
+        removed
+    }
+}
+
+fn expire_dir(root: &UnionFileSystem, path: &Path, now: u64, ttl: Duration) -> usize {
+    let dir = match root.read_dir(path) {
+        Ok(a) => a,
+        Err(_) => return 0,
+    };
+    let mut removed = 0;
+    for entry in dir.filter_map(|a| a.ok()) {
+        let meta = match entry.metadata {
+            Ok(a) => a,
+            Err(_) => continue,
+        };
+        let path = path.join(entry.file_name());
+        if meta.ft.dir {
+            removed += expire_dir(root, path.as_path(), now, ttl);
+
+            // Directories that were made recently may be about to be used by
+            // a session that is still running hence they are left alone
+            if is_expired(meta.modified, now, ttl) {
+                let _ = root.remove_dir(path.as_path());
+            }
+        } else if is_expired(meta.modified, now, ttl) {
+            if root.remove_file(path.as_path()).is_ok() {
+                removed += 1;
+            }
+        }
+    }
+    removed
+}
+
+/// Timestamps of the file systems are in nanoseconds since the epoch
+pub(super) fn is_expired(modified: u64, now: u64, ttl: Duration) -> bool {
+    if modified <= 0 {
+        return false;
+    }
+    now.saturating_sub(modified) as u128 > ttl.as_nanos()
+}
+
+/// Number of bytes held by a temporary file system that is capped
+#[derive(Debug)]
+struct TmpLimit {
+    max: u64,
+    used: AtomicU64,
+}
+
+impl TmpLimit {
+    fn reserve(&self, amount: u64) -> bool {
+        self.used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                match used.checked_add(amount) {
+                    Some(a) if a <= self.max => Some(a),
+                    _ => None,
+                }
+            })
+            .is_ok()
+    }
+
+    fn release(&self, amount: u64) {
+        let _ = self
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                Some(used.saturating_sub(amount))
+            });
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TmpFileSystem {
     fs: mem_fs::FileSystem,
+    limit: Option<Arc<TmpLimit>>,
 }
 
 impl TmpFileSystem {
     pub fn new() -> Self {
         Self {
             fs: mem_fs::FileSystem::default(),
+            limit: None,
+        }
+    }
+
+    /// Temporary file system that refuses to hold more than a maximum
+    /// number of bytes
+    pub fn with_limit(max_size: u64) -> Self {
+        Self {
+            fs: mem_fs::FileSystem::default(),
+            limit: Some(Arc::new(TmpLimit {
+                max: max_size,
+                used: AtomicU64::new(0),
+            })),
+        }
+    }
+
+    fn release_file(&self, path: &Path) {
+        if let Some(limit) = self.limit.as_ref() {
+            if let Ok(meta) = self.fs.metadata(path) {
+                if meta.ft.file {
+                    limit.release(meta.len);
+                }
+            }
         }
     }
 }
@@ -55,7 +216,16 @@ impl FileSystem for TmpFileSystem {
     }
 
     fn rename(&self, from: &Path, to: &Path) -> Result<()> {
-        self.fs.rename(from, to)
+        // A file that is replaced by the rename no longer takes up space
+        let replaced = match (self.limit.as_ref(), self.fs.metadata(to)) {
+            (Some(_), Ok(meta)) if meta.ft.file => meta.len,
+            _ => 0,
+        };
+        self.fs.rename(from, to)?;
+        if let Some(limit) = self.limit.as_ref() {
+            limit.release(replaced);
+        }
+        Ok(())
     }
 
     fn metadata(&self, path: &Path) -> Result<Metadata> {
@@ -67,10 +237,156 @@ impl FileSystem for TmpFileSystem {
     }
 
     fn remove_file(&self, path: &Path) -> Result<()> {
-        self.fs.remove_file(path)
+        let len = match self.limit.as_ref() {
+            Some(_) => self.fs.metadata(path).map(|a| a.len).unwrap_or(0),
+            None => 0,
+        };
+        self.fs.remove_file(path)?;
+        if let Some(limit) = self.limit.as_ref() {
+            limit.release(len);
+        }
+        Ok(())
     }
 
     fn new_open_options(&self) -> OpenOptions {
-        self.fs.new_open_options()
+        match self.limit.as_ref() {
+            Some(limit) => OpenOptions::new(Box::new(TmpFileOpener {
+                tmp: self.clone(),
+                limit: limit.clone(),
+            })),
+            None => self.fs.new_open_options(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TmpFileOpener {
+    tmp: TmpFileSystem,
+    limit: Arc<TmpLimit>,
+}
+
+impl FileOpener for TmpFileOpener {
+    fn open(
+        &mut self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> FsResult<Box<dyn VirtualFile + Send + Sync>> {
+        if conf.truncate() {
+            self.tmp.release_file(path);
+        }
+        let inner = self
+            .tmp
+            .fs
+            .new_open_options()
+            .read(conf.read())
+            .write(conf.write())
+            .append(conf.append())
+            .truncate(conf.truncate())
+            .create(conf.create())
+            .create_new(conf.create_new())
+            .open(path)?;
+        Ok(Box::new(TmpFile {
+            inner,
+            limit: self.limit.clone(),
+            append: conf.append(),
+        }))
+    }
+}
+
+/// File on a temporary file system that is capped, the bytes it grows by
+/// are accounted for before they are written
+#[derive(Debug)]
+struct TmpFile {
+    inner: Box<dyn VirtualFile + Send + Sync>,
+    limit: Arc<TmpLimit>,
+    append: bool,
+}
+
+impl TmpFile {
+    fn grow(&mut self, len: u64) -> io::Result<()> {
+        let size = self.inner.size();
+        if len > size && self.limit.reserve(len - size) == false {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "the temporary file system is full",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Seek for TmpFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl Write for TmpFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let before = self.inner.size();
+        let pos = match self.append {
+            true => before,
+            false => self.inner.seek(SeekFrom::Current(0))?,
+        };
+        self.grow(pos + buf.len() as u64)?;
+        let ret = self.inner.write(buf);
+
+        // Whatever was reserved but not written is given back
+        let reserved = (pos + buf.len() as u64).max(before);
+        let after = self.inner.size();
+        if reserved > after {
+            self.limit.release(reserved - after);
+        }
+        ret
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Read for TmpFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl VirtualFile for TmpFile {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+    fn set_len(&mut self, new_size: u64) -> FsResult<()> {
+        let size = self.inner.size();
+        self.grow(new_size).map_err(|_| FsError::WriteZero)?;
+        self.inner.set_len(new_size).map_err(|err| {
+            if new_size > size {
+                self.limit.release(new_size - size);
+            }
+            err
+        })?;
+        if size > new_size {
+            self.limit.release(size - new_size);
+        }
+        Ok(())
+    }
+    fn unlink(&mut self) -> FsResult<()> {
+        let size = self.inner.size();
+        self.inner.unlink()?;
+        self.limit.release(size);
+        Ok(())
+    }
+    fn bytes_available(&self) -> FsResult<usize> {
+        self.inner.bytes_available()
+    }
+    fn get_fd(&self) -> Option<FileDescriptor> {
+        self.inner.get_fd()
     }
 }
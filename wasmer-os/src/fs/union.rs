@@ -2,16 +2,27 @@
 #![allow(unused)]
 use crate::wasmer_vfs::*;
 use std::borrow::Cow;
+use std::io::Read;
+use std::io::Write;
 use std::ops::Add;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::Weak;
+use std::time::Duration;
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
 
+use wasmer_bus_fuse::api::LockOptions;
+use wasmer_bus_fuse::prelude::LineSplitter;
+use wasmer_bus_fuse::prelude::SearchWalk;
+use wasmer_bus_fuse::prelude::SEARCH_BLOCK_SIZE;
+
+use super::api::FileLockHandle;
 use super::api::MountedFileSystem;
+use super::copy::*;
+use super::search::*;
 use crate::bus::WasmCallerContext;
 
 #[derive(Debug)]
@@ -86,18 +97,33 @@ pub struct StrongMountPoint {
 #[derive(Debug, Clone)]
 pub struct UnionFileSystem {
     pub mounts: Vec<MountPoint>,
+    /// Executable bits of the files (shared with the processes spawned from it)
+    pub exec: ExecBits,
 }
 
 impl UnionFileSystem {
     pub fn new() -> UnionFileSystem {
         UnionFileSystem {
             mounts: Vec::new(),
+            exec: ExecBits::default(),
         }
     }
 
     pub fn clear(&mut self) {
         self.mounts.clear();
     }
+
+    /// Returns the path as it is seen by the file system that it is mounted
+    /// on (i.e. following any mounts that map to a different path)
+    pub fn physical_path(&self, path: &str) -> String {
+        let path = normalize_path(path);
+        for (mapped, mount) in filter_mounts(&self.mounts, path.as_str()) {
+            if mount.new_path.is_some() {
+                return normalize_path(mapped.as_str());
+            }
+        }
+        path
+    }
 }
 
 impl UnionFileSystem {
@@ -183,6 +209,219 @@ impl UnionFileSystem {
         }
     }
 
+    fn copy_internal(
+        &self,
+        from: &Path,
+        to: &Path,
+        recursive: bool,
+        tracker: &CopyTracker,
+    ) -> Result<()> {
+        // A directory copied into itself would keep finding the copies that
+        // it just made (and a file copied onto itself would be truncated)
+        if is_copy_into_itself(from, to) {
+            return Err(FsError::InvalidInput);
+        }
+
+        // When both sides are on the same mount then it may be able to
+        // copy the data without it ever leaving the file system
+        let src = filter_mounts(&self.mounts, from.to_string_lossy().as_ref()).next();
+        let dst = filter_mounts(&self.mounts, to.to_string_lossy().as_ref()).next();
+        if let (Some((src_path, src)), Some((dst_path, dst))) = (src, dst) {
+            if Arc::ptr_eq(&src.fs, &dst.fs) {
+                let ret = src.fs.copy(
+                    Path::new(src_path.as_str()),
+                    Path::new(dst_path.as_str()),
+                    recursive,
+                    tracker,
+                );
+                if let Some(ret) = ret {
+                    return ret;
+                }
+            }
+        }
+
+        // Otherwise we stream the data between the mounts
+        let mut buf = vec![0u8; COPY_BLOCK_SIZE];
+        let mut todo = vec![(from.to_path_buf(), to.to_path_buf())];
+        while let Some((from, to)) = todo.pop() {
+            tracker.check()?;
+            if self.metadata(from.as_path())?.is_dir() == false {
+                self.copy_file_internal(from.as_path(), to.as_path(), &mut buf[..], tracker)?;
+                continue;
+            }
+            if recursive == false {
+                return Err(FsError::NotAFile);
+            }
+
+            // Directories that already exist are copied into
+            match self.metadata(to.as_path()) {
+                Ok(meta) if meta.is_dir() => {}
+                Ok(_) => return Err(FsError::AlreadyExists),
+                Err(_) => self.create_dir(to.as_path())?,
+            }
+            tracker.copied_dir();
+            for entry in self.read_dir(from.as_path())? {
+                if let Some(name) = entry?.path.file_name() {
+                    todo.push((from.join(name), to.join(name)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn copy_file_internal(
+        &self,
+        from: &Path,
+        to: &Path,
+        buf: &mut [u8],
+        tracker: &CopyTracker,
+    ) -> Result<()> {
+        tracker.started_file(from.to_string_lossy().as_ref());
+
+        let mut src = self.new_open_options().read(true).open(from)?;
+        let mut dst = self
+            .new_open_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(to)?;
+        loop {
+            tracker.check()?;
+            let amt = src.read(buf).map_err(FsError::from)?;
+            if amt == 0 {
+                break;
+            }
+            dst.write_all(&buf[..amt]).map_err(FsError::from)?;
+            tracker.copied_bytes(amt as u64);
+        }
+        dst.flush().map_err(FsError::from)?;
+
+        tracker.copied_file();
+        Ok(())
+    }
+
+    fn search_internal(&self, root: &Path, query: &SearchQuery, tracker: &SearchTracker) -> Result<()> {
+        // When the whole tree is on one mount then it may be able to search
+        // by itself (mounts nested below the root would be missed by it)
+        let root_str = normalize_path(root.to_string_lossy().as_ref());
+        let mut mounts = filter_mounts(&self.mounts, root_str.as_str()).collect::<Vec<_>>();
+        let nested = self.mounts.iter().any(|mount| {
+            let path = mount.path.trim_end_matches('/');
+            path.len() > root_str.trim_end_matches('/').len()
+                && path.starts_with(format!("{}/", root_str.trim_end_matches('/')).as_str())
+        });
+        if mounts.len() == 1 && nested == false {
+            let (mount_path, mount) = mounts.remove(0);
+            let mount_path = Path::new(mount_path.as_str());
+            let ret = mount.fs.search(
+                mount_path,
+                query,
+                &tracker.relocate(mount_path, Path::new(root_str.as_str())),
+            );
+            if let Some(ret) = ret {
+                return ret;
+            }
+        }
+
+        // Otherwise the union walks the directories itself
+        let pattern = query.pattern()?;
+        let root = Path::new(root_str.as_str());
+        let meta = self.metadata(root)?;
+        let entries = match meta.is_dir() {
+            true => self.search_children(root),
+            false => vec![(root.to_path_buf(), meta)],
+        };
+        let mut walk = SearchWalk::new(root, query.glob.as_str(), entries);
+        while let Some((path, meta, is_match)) = walk.next() {
+            tracker.check()?;
+
+            if is_match {
+                let more = match (&query.kind, pattern.as_ref()) {
+                    (SearchKind::Paths { return_metadata }, _) => {
+                        let meta = match return_metadata {
+                            true => Some(meta.clone()),
+                            false => None,
+                        };
+                        tracker.found_path(path.as_path(), meta)
+                    }
+                    (SearchKind::Content { .. }, Some(pattern)) if meta.is_file() => {
+                        self.search_file(path.as_path(), pattern, tracker)?
+                    }
+                    _ => true,
+                };
+                if more == false {
+                    return Ok(());
+                }
+            }
+
+            if meta.is_dir() {
+                walk.descend(self.search_children(path.as_path()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the children of a directory (the same name may be listed by
+    /// several mounts of the union, the walk only visits it once)
+    fn search_children(&self, path: &Path) -> Vec<(PathBuf, Metadata)> {
+        let dir = match self.read_dir(path) {
+            Ok(a) => a,
+            Err(err) => {
+                debug!("search skipped a directory (path={}) - {}", path.display(), err);
+                return Vec::new();
+            }
+        };
+        let mut ret = Vec::new();
+        for entry in dir {
+            let entry = match entry {
+                Ok(a) => a,
+                Err(_) => continue,
+            };
+            let child = match entry.path.file_name() {
+                Some(name) => path.join(name),
+                None => continue,
+            };
+            let meta = match entry.metadata {
+                Ok(a) => a,
+                Err(_) => match self.metadata(child.as_path()) {
+                    Ok(a) => a,
+                    Err(_) => continue,
+                },
+            };
+            ret.push((child, meta));
+        }
+        ret
+    }
+
+    /// Searches the lines of a file for the pattern, returns false once the
+    /// search is full
+    fn search_file(&self, path: &Path, pattern: &regex::Regex, tracker: &SearchTracker) -> Result<bool> {
+        let mut file = match self.new_open_options().read(true).open(path) {
+            Ok(a) => a,
+            Err(err) => {
+                debug!("search skipped a file (path={}) - {}", path.display(), err);
+                return Ok(true);
+            }
+        };
+        let mut buf = vec![0u8; SEARCH_BLOCK_SIZE];
+        let mut lines = LineSplitter::new();
+        loop {
+            tracker.check()?;
+            let amt = file.read(&mut buf[..]).map_err(FsError::from)?;
+            for (line_number, line) in lines.push(&buf[..amt]) {
+                if pattern.is_match(line.as_str()) == false {
+                    continue;
+                }
+                if tracker.found_line(path, line_number, line) == false {
+                    return Ok(false);
+                }
+            }
+            if amt == 0 {
+                return Ok(true);
+            }
+        }
+    }
+
     pub fn sanitize(mut self) -> Self {
         self.solidify();
         self.mounts.retain(|mount| mount.should_sanitize == false);
@@ -205,6 +444,69 @@ impl MountedFileSystem for UnionFileSystem {
             }
         }
     }
+
+    fn lock(&self, path: &Path, options: LockOptions) -> Result<Box<dyn FileLockHandle>> {
+        debug!("lock: path={}", path.display());
+        let mut ret_error = FsError::EntityNotFound;
+        let path = path.to_string_lossy();
+        for (path, mount) in filter_mounts(&self.mounts, path.as_ref()) {
+            match mount.fs.lock(Path::new(path.as_str()), options.clone()) {
+                Ok(ret) => {
+                    return Ok(ret);
+                }
+                // The file was found but someone else holds the lock
+                Err(FsError::WouldBlock) => {
+                    return Err(FsError::WouldBlock);
+                }
+                Err(FsError::TimedOut) => {
+                    return Err(FsError::TimedOut);
+                }
+                Err(err) => {
+                    ret_error = err;
+                }
+            }
+        }
+        Err(ret_error)
+    }
+
+    fn set_retention(&self, path: &Path, max_age: Option<Duration>) -> Option<Result<()>> {
+        debug!("set_retention: path={} max_age={:?}", path.display(), max_age);
+        let path = path.to_string_lossy();
+        for (path, mount) in filter_mounts(&self.mounts, path.as_ref()) {
+            if let Some(ret) = mount.fs.set_retention(Path::new(path.as_str()), max_age) {
+                return Some(ret);
+            }
+        }
+        None
+    }
+
+    /// Copies between any two paths of the union, mounts that can copy the
+    /// data themselves are used when both paths are on the same mount
+    /// otherwise the data is streamed between the mounts in large blocks
+    fn copy(
+        &self,
+        from: &Path,
+        to: &Path,
+        recursive: bool,
+        tracker: &CopyTracker,
+    ) -> Option<Result<()>> {
+        debug!("copy: from={} to={} recursive={}", from.display(), to.display(), recursive);
+        Some(self.copy_internal(from, to, recursive, tracker))
+    }
+
+    /// Searches under any path of the union, when the path is on a mount
+    /// that can search by itself then it does so otherwise the directories
+    /// are walked here (which is still much faster than the guest walking
+    /// them one call at a time)
+    fn search(
+        &self,
+        root: &Path,
+        query: &SearchQuery,
+        tracker: &SearchTracker,
+    ) -> Option<Result<()>> {
+        debug!("search: root={} glob={}", root.display(), query.glob);
+        Some(self.search_internal(root, query, tracker))
+    }
 }
 
 impl FileSystem for UnionFileSystem {
@@ -354,8 +656,18 @@ impl FileSystem for UnionFileSystem {
 
 fn filter_mounts(
     mounts: &Vec<MountPoint>,
-    mut target: &str,
+    target: &str,
 ) -> impl Iterator<Item = (String, StrongMountPoint)> {
+    // The `..` components must be resolved before the mount is selected
+    // otherwise they would be resolved within the mount itself
+    let normalized;
+    let target = if target.starts_with("/") {
+        normalized = normalize_path(target);
+        normalized.as_str()
+    } else {
+        target
+    };
+
     let mut biggest_path = 0usize;
     let mut ret = Vec::new();
     for mount in mounts.iter().rev() {
@@ -394,6 +706,26 @@ fn filter_mounts(
     ret.into_iter()
 }
 
+/// Resolves the `.` and `..` components of an absolute path without
+/// touching the file system (`..` at the root stays at the root)
+pub fn normalize_path(path: &str) -> String {
+    let mut comps = Vec::new();
+    for comp in path.split("/") {
+        match comp {
+            "" | "." => continue,
+            ".." => {
+                comps.pop();
+            }
+            comp => comps.push(comp),
+        }
+    }
+
+    let mut ret = String::with_capacity(path.len());
+    ret += "/";
+    ret += comps.join("/").as_str();
+    ret
+}
+
 #[derive(Debug)]
 pub struct UnionFileOpener {
     mounts: Vec<MountPoint>,
@@ -17,6 +17,10 @@ pub fn create_root_fs(inner: Option<Box<dyn MountedFileSystem>>) -> UnionFileSys
     mounts.mount("root", "/", false, inner, None);
     append_static_dir(&mut mounts, &STATIC_DIR);
 
+    // Device files are available to everything (processes get their own
+    // /dev which is bound to their terminal)
+    mounts.mount("dev", "/dev", true, Box::new(DevFileSystem::new()), None);
+
     // The WAPM installations will go to /.app as they are ripe for deduplication
     mounts.mount("app", "/.app", false, Box::new(TmpFileSystem::new()), None);
     mounts
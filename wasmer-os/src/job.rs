@@ -50,6 +50,19 @@ impl Job {
         }
     }
 
+    /// Returns the processes that were started as part of this job
+    pub fn pids(&self) -> Vec<Pid> {
+        let mut ret = Vec::new();
+        let mut rx = self.job_list_rx.lock().unwrap();
+        while let Ok(pid) = rx.try_recv() {
+            ret.push(pid);
+        }
+        for pid in ret.iter() {
+            let _ = self.job_list_tx.try_send(*pid);
+        }
+        ret
+    }
+
     pub fn terminate(&self, reactor: &mut Reactor, exit_code: NonZeroU32) {
         self.stdin.forced_exit(exit_code);
         let mut rx = self.job_list_rx.lock().unwrap();
@@ -15,6 +15,7 @@ pub mod job;
 pub mod pipe;
 pub mod poll;
 pub mod reactor;
+pub mod signal;
 pub mod state;
 pub mod stdio;
 pub mod stdout;
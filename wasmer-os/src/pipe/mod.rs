@@ -20,6 +20,8 @@ use super::poll::*;
 use super::stdio::*;
 use crate::api::*;
 
+mod tests;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ReceiverMode {
     // Partial reads are allowed
@@ -77,7 +79,11 @@ pub fn pipe(mode: ReceiverMode, flag: FdFlag) -> (Fd, Fd) {
     let (fd_tx, mut rx2) = pipe_out(flag);
     system.fork_shared(move || async move {
         while let Some(data) = rx2.recv().await {
-            let _ = tx2.send(data).await;
+            // Once the reader has gone the relay stops so that the
+            // writers see a broken pipe rather than writing into the void
+            if tx2.send(data).await.is_err() {
+                break;
+            }
         }
     });
     (fd_tx, fd_rx)
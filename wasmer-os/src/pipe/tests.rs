@@ -0,0 +1,70 @@
+#![cfg(test)]
+use std::io::ErrorKind;
+
+use crate::err::*;
+use crate::fd::*;
+
+use super::*;
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_pipe_data_before_reader_exits() {
+    let (mut fd, mut rx) = pipe_out(FdFlag::Stdout(false));
+    assert_eq!(fd.write("hello".as_bytes()).await.unwrap(), 5);
+
+    // Whatever was written before the reader went away is still delivered
+    let msg = rx.recv().await.unwrap();
+    assert_eq!(msg.len(), 5);
+    drop(rx);
+    assert!(fd.ctx.should_terminate().is_none());
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_pipe_broken_when_reader_exits() {
+    let (mut fd, rx) = pipe_out(FdFlag::Stdout(false));
+    drop(rx);
+
+    let err = fd.write("hello".as_bytes()).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+    assert_eq!(fd.ctx.should_terminate(), Some(ERR_SIGPIPE));
+
+    // Non-blocking writes are broken too (rather than reporting zero bytes)
+    let err = fd.try_write("hello".as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_pipe_stops_producer_that_ignores_errors() {
+    let (mut fd, mut rx) = pipe_out(FdFlag::Stdout(false));
+
+    // Consumer that exits after the first line (like `head -n 1`)
+    assert!(fd.write("line\n".as_bytes()).await.is_ok());
+    assert!(rx.recv().await.is_some());
+    drop(rx);
+
+    // Producer that keeps writing regardless of the errors (like `yes`),
+    // it is still stopped because the process is terminated
+    let mut writes = 0usize;
+    while fd.ctx.should_terminate().is_none() {
+        let _ = fd.write("line\n".as_bytes()).await;
+        writes += 1;
+        assert!(writes < 100, "producer was never stopped");
+    }
+    assert_eq!(writes, 1);
+    assert_eq!(fd.ctx.should_terminate(), Some(ERR_SIGPIPE));
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_pipe_is_broken_once_reader_exits() {
+    let (fd, rx) = pipe_out(FdFlag::Stdout(false));
+    assert!(fd.is_broken() == false);
+
+    // Loops in the shell check this between iterations rather than relying
+    // on the commands within them to notice that the pipe is broken
+    drop(rx);
+    assert!(fd.is_broken());
+    assert!(fd.ctx.should_terminate().is_none());
+}
@@ -24,6 +24,7 @@ use super::fd::*;
 use super::fs::*;
 use super::job::*;
 use super::poll::*;
+use super::signal::*;
 use super::stdio::*;
 
 #[derive(Debug)]
@@ -92,6 +93,51 @@ impl Reactor {
         ERR_OK as u32
     }
 
+    /// Delivers a signal to a process, returns true if the process handles
+    /// the signal itself or an error if the process does not exist
+    pub fn signal_process(&mut self, pid: Pid, sig: Signal) -> Result<bool, u32> {
+        let process = self.pid.get(&pid).ok_or(ERR_ESRCH)?;
+        debug!("signal {} sent to process (pid={})", sig, pid);
+        let handled = process.ctx.signal(sig);
+        if handled == false {
+            self.close_process(pid, ERR_TERMINATED);
+        }
+        Ok(handled)
+    }
+
+    /// Delivers a signal to all the processes of a job, returns true if any
+    /// of them handle the signal (and hence are still running)
+    pub fn signal_job(&mut self, job: &Job, sig: Signal) -> bool {
+        let mut handled = false;
+        for pid in job.pids() {
+            if let Ok(true) = self.signal_process(pid, sig) {
+                handled = true;
+            }
+        }
+        handled
+    }
+
+    /// Sends SIGHUP to every job (used when the console is closed), returns
+    /// true if any of the processes handle the signal
+    pub fn hangup(&mut self) -> bool {
+        let jobs = self.job.values().map(|a| a.clone()).collect::<Vec<_>>();
+        let mut handled = false;
+        for job in jobs {
+            if self.signal_job(&job, Signal::Hup) {
+                handled = true;
+            }
+        }
+        handled
+    }
+
+    /// Forcefully terminates all the jobs
+    pub fn terminate_all(&mut self) {
+        let jobs = self.job.values().map(|a| a.clone()).collect::<Vec<_>>();
+        for job in jobs {
+            self.close_job(job, NonZeroU32::new(ERR_TERMINATED).unwrap());
+        }
+    }
+
     pub fn generate_job(&mut self) -> Result<(u32, Job), u32> {
         let mut job_seed = 1;
         for _ in 0..10000 {
@@ -0,0 +1,78 @@
+use std::str::FromStr;
+
+mod tests;
+
+/// Amount of time a process that handles a signal is given to exit on its
+/// own before it is forcefully terminated
+pub const SIGNAL_GRACE_MS: u128 = 5000;
+
+/// Signals that can be delivered to a running process, processes that do
+/// not handle signals are terminated (the default disposition) while those
+/// that opened /dev/signal have the signal queued up for them to read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Signal {
+    Hup,
+    Int,
+    Kill,
+    Term,
+}
+
+impl Signal {
+    pub const ALL: [Signal; 4] = [Signal::Hup, Signal::Int, Signal::Kill, Signal::Term];
+
+    pub fn number(&self) -> u8 {
+        match self {
+            Signal::Hup => 1,
+            Signal::Int => 2,
+            Signal::Kill => 9,
+            Signal::Term => 15,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Signal::Hup => "HUP",
+            Signal::Int => "INT",
+            Signal::Kill => "KILL",
+            Signal::Term => "TERM",
+        }
+    }
+
+    pub fn from_number(num: u8) -> Option<Signal> {
+        Signal::ALL.iter().filter(|a| a.number() == num).map(|a| *a).next()
+    }
+
+    /// SIGKILL can not be caught and always terminates the process
+    pub fn can_handle(&self) -> bool {
+        *self != Signal::Kill
+    }
+
+    pub(crate) fn mask(&self) -> u32 {
+        1u32 << self.number()
+    }
+}
+
+impl std::fmt::Display for Signal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SIG{}", self.name())
+    }
+}
+
+impl FromStr for Signal {
+    type Err = String;
+
+    /// Accepts the name (with or without the SIG prefix) or the number
+    fn from_str(s: &str) -> Result<Signal, String> {
+        if let Ok(num) = u8::from_str(s) {
+            return Signal::from_number(num).ok_or_else(|| format!("unknown signal ({})", s));
+        }
+        let name = s.to_uppercase();
+        let name = name.trim_start_matches("SIG");
+        Signal::ALL
+            .iter()
+            .filter(|a| a.name() == name)
+            .map(|a| *a)
+            .next()
+            .ok_or_else(|| format!("unknown signal ({})", s))
+    }
+}
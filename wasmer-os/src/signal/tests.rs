@@ -0,0 +1,39 @@
+#![cfg(test)]
+use std::str::FromStr;
+
+use crate::bus::WasmCallerContext;
+
+use super::*;
+
+#[test]
+fn test_signal_parse() {
+    assert_eq!(Signal::from_str("INT"), Ok(Signal::Int));
+    assert_eq!(Signal::from_str("sigterm"), Ok(Signal::Term));
+    assert_eq!(Signal::from_str("SIGHUP"), Ok(Signal::Hup));
+    assert_eq!(Signal::from_str("9"), Ok(Signal::Kill));
+    assert!(Signal::from_str("SIGWINCH").is_err());
+    assert!(Signal::from_str("3").is_err());
+    assert_eq!(Signal::Int.to_string(), "SIGINT");
+}
+
+#[test]
+fn test_signal_default_disposition() {
+    let ctx = WasmCallerContext::default();
+    assert_eq!(ctx.signal(Signal::Int), false);
+    assert!(ctx.should_terminate().is_some());
+}
+
+#[test]
+fn test_signal_handled() {
+    let ctx = WasmCallerContext::default();
+    ctx.set_signal_handler(true);
+    assert_eq!(ctx.signal(Signal::Int), true);
+    assert_eq!(ctx.signal(Signal::Hup), true);
+    assert!(ctx.should_terminate().is_none());
+    assert_eq!(ctx.take_signals(), vec![Signal::Hup, Signal::Int]);
+    assert!(ctx.take_signals().is_empty());
+
+    // SIGKILL can never be caught
+    assert_eq!(ctx.signal(Signal::Kill), false);
+    assert!(ctx.should_terminate().is_some());
+}
@@ -22,6 +22,13 @@ use super::stdout::*;
 use super::pipe::*;
 use super::api::*;
 
+mod tests;
+mod unicode;
+
+pub use unicode::TtyCell;
+pub use unicode::TtyInputDecoder;
+use unicode::*;
+
 #[derive(Debug, Clone)]
 pub enum TtyMode {
     Null,
@@ -57,6 +64,50 @@ impl TtyInnerAsync {
     pub fn reset_history_cursor(&mut self) {
         self.cursor_history = 0;
     }
+
+    /// The cursor position is a byte offset into the line hence moving it must
+    /// step over whole grapheme clusters (which may be several characters long)
+    pub fn prev_char_pos(&self) -> usize {
+        prev_grapheme(&self.line, self.cursor_pos)
+    }
+
+    pub fn next_char_pos(&self) -> usize {
+        next_grapheme(&self.line, self.cursor_pos)
+    }
+
+    /// Cell on the terminal that the cursor is in when it is at this byte
+    /// offset of the line (the prompt starts at the beginning of a row)
+    pub fn cell(&self, pos: usize) -> TtyCell {
+        let cols = self.cols as usize;
+        let prompt = self.prompt.rsplit('\n').next().unwrap_or_default();
+        TtyCell::default()
+            .advance(prompt, cols)
+            .advance(&self.line[..pos], cols)
+    }
+
+    /// Escape sequences that draw the line again from the byte offset onwards
+    /// (clearing whatever was drawn there before) and then put the cursor
+    /// back where it belongs, `from` is where the cursor currently is
+    pub fn redraw_from(&self, pos: usize, from: TtyCell) -> String {
+        let mut chars = String::new();
+        chars += from.move_to(self.cell(pos)).as_str();
+        chars += Tty::TERM_CLEAR_BELOW;
+
+        let end = self.cell(self.line.len());
+        if pos < self.line.len() {
+            chars += Tty::TERM_WRAPAROUND;
+            chars += &self.line[pos..];
+
+            // Text that exactly fills a row leaves the cursor on the last
+            // column until the next character is drawn, it is moved onto the
+            // next row so that it is in the cell that the layout expects
+            if end.col == 0 {
+                chars += "\r\n";
+            }
+        }
+        chars += end.move_to(self.cell(self.cursor_pos)).as_str();
+        chars
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -203,35 +254,29 @@ impl Tty {
     }
 
     pub async fn restore_selected_history(&mut self) {
-        let cursor_history = {
+        let chars = {
             let mut inner = self.inner_async.lock().await;
             if inner.cursor_history > inner.history.len() {
                 inner.reset_line();
                 debug!("restore-history-over");
                 return;
             }
-            inner.history.len() - inner.cursor_history
-        };
-
-        self.set_cursor_to_start().await;
-        self.draw_undo().await;
+            let cursor_history = inner.history.len() - inner.cursor_history;
 
-        let right = {
-            let mut inner = self.inner_async.lock().await;
+            let from = inner.cell(inner.cursor_pos);
             let last = inner.history.get(cursor_history).map(|a| a.clone());
             if let Some(last) = last {
                 debug!("restore-history: pos={} val={}", cursor_history, last);
                 inner.cursor_pos = last.len();
-                inner.line = last.clone();
-                last as String
+                inner.line = last;
             } else {
                 inner.reset_line();
                 debug!("restore-history: pos={} miss", cursor_history);
-                String::new()
             }
+            inner.redraw_from(0, from)
         };
 
-        self.draw(right.as_str()).await
+        self.draw(chars.as_str()).await
     }
 
     pub async fn record_history(&self, cmd: String) {
@@ -276,81 +321,72 @@ impl Tty {
     }
 
     pub async fn backspace(&mut self) {
-        let echo = {
-            let inner = self.inner_async.lock().await;
+        let chars = {
+            let mut inner = self.inner_async.lock().await;
             if inner.cursor_pos <= 0 {
                 return;
             }
-            inner.echo
-        };
-
-        if echo {
-            self.draw(Tty::TERM_CURSOR_LEFT).await;
-            self.draw_undo().await;
-        }
-        let right = {
-            let mut inner = self.inner_async.lock().await;
-            let left = inner.line[..inner.cursor_pos - 1].to_string();
-            let right = inner.line[inner.cursor_pos..].to_string();
-            inner.line = format!("{}{}", left, right);
-            inner.cursor_pos -= 1;
-            right
+            let from = inner.cell(inner.cursor_pos);
+            let prev = inner.prev_char_pos();
+            let cursor_pos = inner.cursor_pos;
+            inner.line.replace_range(prev..cursor_pos, "");
+            inner.cursor_pos = prev;
+            if inner.echo == false {
+                return;
+            }
+            inner.redraw_from(prev, from)
         };
-        if echo {
-            self.draw_fixed(right.as_str()).await
-        }
+        self.draw(chars.as_str()).await
     }
 
     pub async fn delete(&mut self) {
-        let echo = {
-            let inner = self.inner_async.lock().await;
+        let chars = {
+            let mut inner = self.inner_async.lock().await;
             if inner.cursor_pos >= inner.line.len() {
                 return;
             }
-            inner.echo
-        };
-
-        if echo {
-            self.draw_undo().await;
-        }
-        let right = {
-            let mut inner = self.inner_async.lock().await;
-            let left = inner.line[..inner.cursor_pos].to_string();
-            let right = inner.line[inner.cursor_pos + 1..].to_string();
-            inner.line = format!("{}{}", left, right);
-            right
+            let from = inner.cell(inner.cursor_pos);
+            let next = inner.next_char_pos();
+            let cursor_pos = inner.cursor_pos;
+            inner.line.replace_range(cursor_pos..next, "");
+            if inner.echo == false {
+                return;
+            }
+            inner.redraw_from(cursor_pos, from)
         };
-        if echo {
-            self.draw_fixed(right.as_str()).await
-        }
+        self.draw(chars.as_str()).await
     }
 
     pub async fn cursor_left(&mut self) {
-        let echo = {
+        let chars = {
             let mut inner = self.inner_async.lock().await;
             if inner.cursor_pos <= 0 {
                 return;
             }
-            inner.cursor_pos -= 1;
-            inner.echo
+            let from = inner.cell(inner.cursor_pos);
+            inner.cursor_pos = inner.prev_char_pos();
+            if inner.echo == false {
+                return;
+            }
+            from.move_to(inner.cell(inner.cursor_pos))
         };
-        if echo {
-            self.draw(Tty::TERM_CURSOR_LEFT).await;
-        }
+        self.draw(chars.as_str()).await;
     }
 
     pub async fn cursor_right(&mut self) {
-        let echo = {
+        let chars = {
             let mut inner = self.inner_async.lock().await;
             if inner.cursor_pos >= inner.line.len() {
                 return;
             }
-            inner.cursor_pos += 1;
-            inner.echo
+            let from = inner.cell(inner.cursor_pos);
+            inner.cursor_pos = inner.next_char_pos();
+            if inner.echo == false {
+                return;
+            }
+            from.move_to(inner.cell(inner.cursor_pos))
         };
-        if echo {
-            self.draw(Tty::TERM_CURSOR_RIGHT).await;
-        }
+        self.draw(chars.as_str()).await;
     }
 
     pub async fn cursor_up(&mut self) {
@@ -420,39 +456,36 @@ impl Tty {
         }
     }
 
+    pub async fn get_echo(&self) -> bool {
+        self.inner_async.lock().await.echo
+    }
+
     pub async fn set_echo(&mut self, echo: bool) {
         self.inner_async.lock().await.echo = echo;
     }
 
     pub async fn add(&mut self, data: &str) {
-        let echo = self.inner_async.lock().await.echo;
-        if echo {
-            self.draw_undo().await;
-        }
-        let right = {
+        let chars = {
             let mut inner = self.inner_async.lock().await;
+            let from = inner.cell(inner.cursor_pos);
             let cursor_pos = inner.cursor_pos;
             inner.line.insert_str(cursor_pos, data);
-            inner.cursor_pos += data.len();
 
-            let right = if inner.cursor_pos < inner.line.len() {
-                Some(inner.line[inner.cursor_pos..].to_string())
-            } else {
-                None
+            // Combining marks join onto the character before them hence the
+            // line is drawn again from the start of that character and the
+            // cursor is kept on the boundary of a grapheme cluster
+            let redraw_pos = grapheme_start(&inner.line, cursor_pos);
+            let end = cursor_pos + data.len();
+            inner.cursor_pos = match grapheme_start(&inner.line, end) {
+                a if a == end => end,
+                a => next_grapheme(&inner.line, a),
             };
-            right
-        };
-        if echo {
-            let mut chars = String::new();
-            chars += Tty::TERM_WRAPAROUND;
-            chars += data;
-            if let Some(right) = right {
-                chars += Tty::TERM_CURSOR_SAVE;
-                chars += right.as_str();
-                chars += Tty::TERM_CURSOR_RESTORE;
+            if inner.echo == false {
+                return;
             }
-            self.draw(chars.as_str()).await;
-        }
+            inner.redraw_from(redraw_pos, from)
+        };
+        self.draw(chars.as_str()).await;
     }
 
     pub async fn draw_prompt(&mut self) {
@@ -480,37 +513,24 @@ impl Tty {
     }
 
     pub async fn set_cursor_to_start(&mut self) {
-        let shift_left = {
+        let chars = {
             let mut inner = self.inner_async.lock().await;
-            let pos = inner.cursor_pos;
+            let from = inner.cell(inner.cursor_pos);
             inner.cursor_pos = 0;
-            pos
+            from.move_to(inner.cell(0))
         };
-
-        let chars = std::iter::repeat(Tty::TERM_CURSOR_LEFT)
-            .take(shift_left)
-            .collect::<String>();
         if chars.len() > 0 {
             self.draw(chars.as_str()).await
         }
     }
 
     pub async fn set_cursor_to_end(&mut self) {
-        let shift_right = {
+        let chars = {
             let mut inner = self.inner_async.lock().await;
-            let pos = inner.cursor_pos;
-            if inner.line.len() > 0 {
-                inner.cursor_pos = inner.line.len();
-                inner.line.len() - pos
-            } else {
-                inner.cursor_pos = 0;
-                0
-            }
+            let from = inner.cell(inner.cursor_pos);
+            inner.cursor_pos = inner.line.len();
+            from.move_to(inner.cell(inner.cursor_pos))
         };
-
-        let chars = std::iter::repeat(Tty::TERM_CURSOR_RIGHT)
-            .take(shift_right)
-            .collect::<String>();
         if chars.len() > 0 {
             self.draw(chars.as_str()).await
         }
@@ -528,6 +548,19 @@ impl Tty {
         inner.line[inner.cursor_pos..].to_string()
     }
 
+    /// Draws the line that is being edited again (e.g. after a message was
+    /// printed over the prompt) leaving the cursor where it was
+    pub async fn draw_line(&mut self) {
+        let chars = {
+            let inner = self.inner_async.lock().await;
+            if inner.line.len() <= 0 {
+                return;
+            }
+            inner.redraw_from(0, inner.cell(0))
+        };
+        self.draw(chars.as_str()).await
+    }
+
     pub async fn draw_fixed(&mut self, data: &str) {
         let mut chars = String::new();
         chars += Tty::TERM_CURSOR_SAVE;
@@ -0,0 +1,185 @@
+#![cfg(test)]
+use tokio::sync::mpsc;
+
+use crate::fd::*;
+use crate::pipe::*;
+use crate::stdout::*;
+
+use super::*;
+
+fn create_tty() -> (Tty, mpsc::Receiver<FdMsg>) {
+    let (stdio, stdio_rx) = pipe_out(FdFlag::None);
+    let tty = Tty::new(
+        Stdout::new(stdio.clone()),
+        stdio.clone(),
+        stdio,
+        TtyOuter::Normal,
+    );
+    (tty, stdio_rx)
+}
+
+async fn line_and_cursor(tty: &Tty) -> (String, usize) {
+    let inner = tty.inner_async.lock().await;
+    (inner.line.clone(), inner.cursor_pos)
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_tty_multibyte_cursor_movement() {
+    let (mut tty, _rx) = create_tty();
+
+    // 'é' is two bytes and '€' is three bytes
+    tty.add("aé€b").await;
+    assert_eq!(line_and_cursor(&tty).await, ("aé€b".to_string(), 7));
+
+    tty.cursor_left().await;
+    assert_eq!(line_and_cursor(&tty).await.1, 6);
+    tty.cursor_left().await;
+    assert_eq!(line_and_cursor(&tty).await.1, 3);
+    tty.cursor_left().await;
+    assert_eq!(line_and_cursor(&tty).await.1, 1);
+
+    tty.cursor_right().await;
+    assert_eq!(line_and_cursor(&tty).await.1, 3);
+    tty.cursor_right().await;
+    assert_eq!(line_and_cursor(&tty).await.1, 6);
+
+    // Inserting in the middle of the line must land on a character boundary
+    tty.add("ñ").await;
+    assert_eq!(line_and_cursor(&tty).await, ("aé€ñb".to_string(), 8));
+
+    tty.set_cursor_to_start().await;
+    assert_eq!(line_and_cursor(&tty).await.1, 0);
+    tty.set_cursor_to_end().await;
+    assert_eq!(line_and_cursor(&tty).await.1, 9);
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_tty_multibyte_backspace_and_delete() {
+    let (mut tty, _rx) = create_tty();
+
+    tty.add("xü€").await;
+
+    // Backspace over the three byte character
+    tty.backspace().await;
+    assert_eq!(line_and_cursor(&tty).await, ("xü".to_string(), 3));
+
+    // Backspace over the two byte character
+    tty.backspace().await;
+    assert_eq!(line_and_cursor(&tty).await, ("x".to_string(), 1));
+
+    // Delete a multibyte character that is right of the cursor
+    tty.add("é").await;
+    tty.set_cursor_to_start().await;
+    tty.cursor_right().await;
+    tty.delete().await;
+    assert_eq!(line_and_cursor(&tty).await, ("x".to_string(), 1));
+
+    tty.backspace().await;
+    tty.backspace().await;
+    assert_eq!(line_and_cursor(&tty).await, (String::new(), 0));
+}
+
+async fn create_tty_with_bounds(cols: u32, prompt: &str) -> (Tty, mpsc::Receiver<FdMsg>) {
+    let (tty, rx) = create_tty();
+    tty.set_bounds(cols, 25).await;
+    tty.set_prompt(prompt.to_string(), prompt.to_string()).await;
+    (tty, rx)
+}
+
+fn drain(rx: &mut mpsc::Receiver<FdMsg>) -> String {
+    let mut ret = Vec::new();
+    while let Ok(msg) = rx.try_recv() {
+        if let FdMsg::Data { data, .. } = msg {
+            ret.extend_from_slice(&data[..]);
+        }
+    }
+    String::from_utf8(ret).unwrap()
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_tty_wide_characters() {
+    let (mut tty, mut rx) = create_tty_with_bounds(10, "$ ").await;
+
+    tty.add("中文").await;
+    assert_eq!(drain(&mut rx), "\x1b[0J\x1b[?7h中文");
+
+    // Each of the characters takes up two cells
+    tty.backspace().await;
+    assert_eq!(line_and_cursor(&tty).await, ("中".to_string(), 3));
+    assert_eq!(drain(&mut rx), "\x1b[5G\x1b[0J");
+
+    tty.add("😀a").await;
+    assert_eq!(drain(&mut rx), "\x1b[0J\x1b[?7h😀a");
+    tty.cursor_left().await;
+    tty.cursor_left().await;
+    assert_eq!(drain(&mut rx), "\x1b[7G\x1b[5G");
+
+    // Deleting the emoji pulls the rest of the line back over it
+    tty.delete().await;
+    assert_eq!(line_and_cursor(&tty).await, ("中a".to_string(), 3));
+    assert_eq!(drain(&mut rx), "\x1b[0J\x1b[?7ha\x1b[5G");
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_tty_wraps_wide_characters() {
+    let (mut tty, mut rx) = create_tty_with_bounds(5, "> ").await;
+
+    // The wide character does not fit on the end of the first row
+    tty.add("ab中").await;
+    assert_eq!(drain(&mut rx), "\x1b[0J\x1b[?7hab中");
+
+    tty.cursor_left().await;
+    assert_eq!(line_and_cursor(&tty).await.1, 2);
+    assert_eq!(drain(&mut rx), "\x1b[1A\x1b[5G");
+    tty.cursor_right().await;
+    assert_eq!(drain(&mut rx), "\x1b[1B\x1b[3G");
+
+    // Inserting fills up the first row and the cursor follows what it typed
+    tty.cursor_left().await;
+    drain(&mut rx);
+    tty.add("x").await;
+    assert_eq!(line_and_cursor(&tty).await, ("abx中".to_string(), 3));
+    assert_eq!(drain(&mut rx), "\x1b[0J\x1b[?7hx中\x1b[1G");
+
+    tty.set_cursor_to_start().await;
+    assert_eq!(drain(&mut rx), "\x1b[1A\x1b[3G");
+}
+
+#[tokio::main(flavor = "current_thread")]
+#[test]
+async fn test_tty_combining_marks() {
+    let (mut tty, mut rx) = create_tty_with_bounds(10, "> ").await;
+
+    // The accent joins onto the letter before it and takes up no cells
+    tty.add("e").await;
+    drain(&mut rx);
+    tty.add("\u{301}").await;
+    assert_eq!(line_and_cursor(&tty).await, ("e\u{301}".to_string(), 3));
+    assert_eq!(drain(&mut rx), "\x1b[3G\x1b[0J\x1b[?7he\u{301}");
+
+    // Moving and deleting treat them as the one character
+    tty.cursor_left().await;
+    assert_eq!(line_and_cursor(&tty).await.1, 0);
+    assert_eq!(drain(&mut rx), "\x1b[3G");
+    tty.delete().await;
+    assert_eq!(line_and_cursor(&tty).await, (String::new(), 0));
+    assert_eq!(drain(&mut rx), "\x1b[0J");
+}
+
+#[test]
+fn test_tty_input_decoder() {
+    let mut decoder = TtyInputDecoder::new();
+
+    // '€' is split across the two chunks
+    assert_eq!(decoder.push(&[b'a', 0xe2, 0x82]), "a");
+    assert_eq!(decoder.push(&[0xac, b'b']), "€b");
+
+    // Invalid bytes do not hold back the rest of the input
+    assert_eq!(decoder.push(&[0xff, b'c']), "\u{fffd}c");
+    assert_eq!(decoder.push(&[0xf0, 0x9f]), "");
+    assert_eq!(decoder.push(&[0x98, 0x80]), "😀");
+}
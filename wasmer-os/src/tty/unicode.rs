@@ -0,0 +1,143 @@
+//! Display width and layout of the line being edited. The line is edited
+//! one grapheme cluster at a time (so that a character followed by its
+//! combining marks, or an emoji built from several code points, is treated
+//! as the one character the user sees) and the cursor is moved by the
+//! number of cells that the clusters take up on the terminal, east-asian
+//! wide characters and emoji take two cells while combining marks take none.
+use std::str;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Number of cells a grapheme cluster takes up on the terminal
+pub fn grapheme_width(grapheme: &str) -> usize {
+    // Clusters such as emoji joined with zero-width-joiners are drawn as one
+    // glyph even though their code points add up to a wider width
+    grapheme.width().min(2)
+}
+
+/// Number of cells that the text takes up on the terminal
+pub fn str_width(text: &str) -> usize {
+    text.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Byte offset of the grapheme cluster that comes before the position
+pub fn prev_grapheme(line: &str, pos: usize) -> usize {
+    line[..pos]
+        .grapheme_indices(true)
+        .next_back()
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Byte offset of the grapheme cluster that comes after the position
+pub fn next_grapheme(line: &str, pos: usize) -> usize {
+    line[pos..]
+        .graphemes(true)
+        .next()
+        .map(|g| pos + g.len())
+        .unwrap_or(line.len())
+}
+
+/// Byte offset of the start of the grapheme cluster that the position is in
+pub fn grapheme_start(line: &str, pos: usize) -> usize {
+    line.grapheme_indices(true)
+        .map(|(i, _)| i)
+        .chain(std::iter::once(line.len()))
+        .take_while(|i| *i <= pos)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Cell on the terminal (relative to the row the prompt starts on)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TtyCell {
+    pub row: usize,
+    pub col: usize,
+}
+
+impl TtyCell {
+    /// Returns the cell that the cursor is left in after the text is drawn
+    /// from this cell on a terminal that is `cols` wide. A wide character
+    /// that does not fit at the end of a row is moved onto the next row (as
+    /// the terminal does) and a cursor that ends up past the last column is
+    /// placed at the start of the next row.
+    pub fn advance(mut self, text: &str, cols: usize) -> TtyCell {
+        let cols = cols.max(1);
+        for grapheme in text.graphemes(true) {
+            let width = grapheme_width(grapheme);
+            if self.col + width > cols && self.col > 0 {
+                self.row += 1;
+                self.col = 0;
+            }
+            self.col += width;
+            if self.col >= cols {
+                self.row += self.col / cols;
+                self.col %= cols;
+            }
+        }
+        self
+    }
+
+    /// Escape sequence that moves the cursor from this cell to another one
+    pub fn move_to(&self, to: TtyCell) -> String {
+        let mut ret = String::new();
+        if to.row < self.row {
+            ret += format!("\x1b[{}A", self.row - to.row).as_str();
+        } else if to.row > self.row {
+            ret += format!("\x1b[{}B", to.row - self.row).as_str();
+        }
+        if to.col != self.col {
+            ret += format!("\x1b[{}G", to.col + 1).as_str();
+        }
+        ret
+    }
+}
+
+/// Reassembles the input that arrives in chunks (e.g. from a socket) into
+/// text, a multi-byte character that is split across two chunks is held
+/// back until the rest of it arrives rather than being mangled
+#[derive(Debug, Default)]
+pub struct TtyInputDecoder {
+    pending: Vec<u8>,
+}
+
+impl TtyInputDecoder {
+    pub fn new() -> TtyInputDecoder {
+        TtyInputDecoder::default()
+    }
+
+    /// Returns the text that is complete so far, bytes that are invalid
+    /// are replaced with the replacement character
+    pub fn push(&mut self, data: &[u8]) -> String {
+        self.pending.extend_from_slice(data);
+
+        let mut ret = String::new();
+        let mut rest = &self.pending[..];
+        loop {
+            match str::from_utf8(rest) {
+                Ok(txt) => {
+                    ret += txt;
+                    rest = &[];
+                    break;
+                }
+                Err(err) => {
+                    let (valid, after) = rest.split_at(err.valid_up_to());
+                    ret += str::from_utf8(valid).unwrap_or_default();
+                    match err.error_len() {
+                        Some(len) => {
+                            ret.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                        // The sequence is incomplete so it waits for more data
+                        None => {
+                            rest = after;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        self.pending = rest.to_vec();
+        ret
+    }
+}
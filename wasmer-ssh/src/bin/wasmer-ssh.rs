@@ -14,18 +14,43 @@ use tokio::runtime::Builder;
 use wasmer_ssh::wasmer_os;
 use wasmer_os::bin_factory::CachedCompiledModules;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() {
     let opts: Opts = Opts::parse();
+    let verbose = opts.verbose > 0;
 
+    if let Err(err) = main_run(opts) {
+        ate::utils::ErrorRenderer::new()
+            .with_verbose(verbose)
+            .eprint(err.as_ref());
+        std::process::exit(1);
+    }
+}
+
+fn main_run(opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
     // Enable the logging
     log_init(opts.verbose, opts.debug);
 
     // Create the runtime
     let runtime = Arc::new(Builder::new_multi_thread().enable_all().build().unwrap());
 
+    let config = opts.config.map(|a| shellexpand::tilde(&a).to_string());
+    if opts.check_config {
+        match ate::prelude::ConfDocument::check_file(config.unwrap_or_default()) {
+            true => return Ok(()),
+            false => std::process::exit(1),
+        }
+    }
+    let subcmd = match opts.subcmd {
+        Some(a) => a,
+        None => {
+            eprintln!("a subcommand is required (see --help)");
+            std::process::exit(2);
+        }
+    };
+
     // Process the command
     let key_path = opts.key_path.clone();
-    match opts.subcmd {
+    match subcmd {
         SubCommand::Ssh(ssh) => {
             match ssh.action {
                 OptsSshAction::Host(host) => {
@@ -34,7 +59,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         let server_key: SshServerKey = load_key(key_path);
 
                         // Create the registry that will be used to validate logins
-                        let registry = ate::mesh::Registry::new(&conf_cmd()).await.cement();
+                        let conf = match config {
+                            Some(path) => ate::prelude::ConfAte::from_file(path)?,
+                            None => conf_cmd(),
+                        };
+                        let registry = ate::mesh::Registry::new(&conf).await.cement();
 
                         // Set the system
                         let (tx_exit, rx_exit) = watch::channel(false);
@@ -68,5 +97,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
         }
+        SubCommand::Play(play) => {
+            wasmer_ssh::recorder::play(play.file.as_str(), play.speed, play.max_idle)?;
+            Ok(())
+        }
     }
 }
@@ -10,12 +10,15 @@ use thrussh::ChannelId;
 use thrussh::CryptoVec;
 use wasmer_term::wasmer_os;
 
+use crate::recorder::SessionRecorder;
+
 pub struct ConsoleHandle {
     pub rect: Arc<Mutex<ConsoleRect>>,
     pub channel: ChannelId,
     pub handle: Handle,
     pub stdio_lock: Arc<Mutex<()>>,
     pub enable_stderr: bool,
+    pub recorder: Option<Arc<SessionRecorder>>,
 }
 
 #[async_trait]
@@ -24,6 +27,9 @@ for ConsoleHandle
 {
     /// Writes output to the SSH pipe
     async fn stdout(&self, data: Vec<u8>) {
+        if let Some(recorder) = self.recorder.as_ref() {
+            recorder.output(&data[..]);
+        }
         let channel = self.channel;
         let data = CryptoVec::from_slice(&data[..]);
         let mut handle = self.handle.clone();
@@ -32,6 +38,9 @@ for ConsoleHandle
 
     /// Writes output to the SSH pipe
     async fn stderr(&self, data: Vec<u8>) {
+        if let Some(recorder) = self.recorder.as_ref() {
+            recorder.output(&data[..]);
+        }
         let channel = self.channel;
         let data = CryptoVec::from_slice(&data[..]);
         let mut handle = self.handle.clone();
@@ -6,6 +6,8 @@ use std::sync::Mutex;
 use wasmer_os::api::ConsoleRect;
 use wasmer_os::api::System;
 use wasmer_os::console::Console;
+use wasmer_os::fs::SessionTmp;
+use wasmer_os::tty::TtyInputDecoder;
 use thrussh::server;
 use thrussh::server::Auth;
 use thrussh::server::Session;
@@ -20,6 +22,8 @@ use wasmer_term::wasmer_os::bin_factory::CachedCompiledModules;
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use crate::native_files::NativeFileInterface;
+use crate::recorder::RecordConfig;
+use crate::recorder::SessionRecorder;
 use crate::wizard::SshWizard;
 
 use super::console_handle::*;
@@ -38,6 +42,10 @@ pub struct Handler {
     pub wizard: Option<SshWizard>,
     pub compiled_modules: Arc<CachedCompiledModules>,
     pub stdio_lock: Arc<Mutex<()>>,
+    pub record: Option<RecordConfig>,
+    pub recorder: Option<Arc<SessionRecorder>>,
+    pub input: TtyInputDecoder,
+    pub tmp: SessionTmp,
 }
 
 impl server::Handler for Handler {
@@ -100,13 +108,21 @@ impl server::Handler for Handler {
 
     fn data(mut self, channel: ChannelId, data: &[u8], session: Session) -> Self::FutureUnit {
         trace!("data on channel {:?}: len={:?}", channel, data.len());
-        let data = String::from_utf8(data.to_vec()).map_err(|_| {
-            let err: SshServerError = SshServerErrorKind::BadData.into();
-            err
-        });
+        // Characters that are split across two packets are held back until
+        // the rest of them arrives
+        let data = self.input.push(data);
         Box::pin(async move {
-            let data = data?;
+            if data.is_empty() {
+                return Ok((self, session));
+            }
             if let Some(console) = self.console.as_mut() {
+                // Input is only recorded while it is echoed so that passwords
+                // typed into the wizard never end up in the recording
+                if let Some(recorder) = self.recorder.as_ref() {
+                    if console.tty().get_echo().await {
+                        recorder.input(data.as_str());
+                    }
+                }
                 console.on_data(data).await;
             }
             Ok((self, session))
@@ -119,6 +135,19 @@ impl server::Handler for Handler {
 
         let native_files = self.native_files.clone();
         Box::pin(async move {
+            // Start recording the session (if its enabled)
+            if let Some(config) = self.record.clone() {
+                let rect = self.rect.lock().unwrap().clone();
+                match SessionRecorder::new(config, self.peer_addr_str.as_str(), self.user.as_deref(), rect) {
+                    Ok(a) => {
+                        self.recorder.replace(Arc::new(a));
+                    }
+                    Err(err) => {
+                        warn!("failed to start the session recording ({}) - {}", self.peer_addr_str, err);
+                    }
+                }
+            }
+
             // Create the handle
             let handle = Arc::new(ConsoleHandle {
                 rect: self.rect.clone(),
@@ -126,6 +155,7 @@ impl server::Handler for Handler {
                 handle: session.handle(),
                 stdio_lock: self.stdio_lock.clone(),
                 enable_stderr: false,
+                recorder: self.recorder.clone(),
             });
 
             // Spawn a dedicated thread and wait for it to do its thing
@@ -151,6 +181,7 @@ impl server::Handler for Handler {
                         fs,
                         compiled_modules,
                     );
+                    console.set_tmp(self.tmp);
                     console.init().await;
                     self.console.replace(console);
 
@@ -184,6 +215,36 @@ impl server::Handler for Handler {
 
         self.finished(session)
     }
+
+    #[allow(unused_variables)]
+    fn window_change_request(
+        mut self,
+        channel: ChannelId,
+        col_width: u32,
+        row_height: u32,
+        pix_width: u32,
+        pix_height: u32,
+        session: Session,
+    ) -> Self::FutureUnit {
+        debug!("window_change_request");
+
+        let rect = {
+            let mut guard = self.rect.lock().unwrap();
+            guard.cols = col_width;
+            guard.rows = row_height;
+            guard.clone()
+        };
+        if let Some(recorder) = self.recorder.as_ref() {
+            recorder.resize(rect);
+        }
+
+        Box::pin(async move {
+            if let Some(console) = self.console.as_mut() {
+                console.on_resize().await;
+            }
+            Ok((self, session))
+        })
+    }
 }
 
 impl Drop for Handler {
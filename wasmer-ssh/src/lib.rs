@@ -4,6 +4,7 @@ pub mod error;
 pub mod handler;
 pub mod key;
 pub mod opt;
+pub mod recorder;
 pub mod server;
 pub mod system;
 pub mod utils;
@@ -2,6 +2,7 @@ use clap::Parser;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
+use super::play::OptsPlay;
 use super::ssh::OptsSsh;
 
 #[derive(Parser)]
@@ -20,9 +21,16 @@ pub struct Opts {
     /// Path to the secret server key
     #[clap(default_value = "~/wasmer/ssh.server.key")]
     pub key_path: String,
+    /// Configuration file (TOML, YAML or JSON) with an [ate] section - when supplied
+    /// the settings in the file are used to validate logins instead of the defaults
+    #[clap(long)]
+    pub config: Option<String>,
+    /// Validates the configuration file, reports every problem that it finds and exits
+    #[clap(long, requires = "config")]
+    pub check_config: bool,
 
     #[clap(subcommand)]
-    pub subcmd: SubCommand,
+    pub subcmd: Option<SubCommand>,
 }
 
 #[derive(Parser)]
@@ -30,4 +38,7 @@ pub enum SubCommand {
     /// Starts an SSH command
     #[clap()]
     Ssh(OptsSsh),
+    /// Replays a recorded console session
+    #[clap()]
+    Play(OptsPlay),
 }
@@ -32,4 +32,31 @@ pub struct OptsHost {
     /// Uses a local directory for native files rather than the published ate chain
     #[clap(long)]
     pub native_files_path: Option<String>,
+    /// Directory where the console sessions are recorded (one asciicast file per
+    /// session), when this is not set the sessions are not recorded
+    #[clap(long)]
+    pub record_dir: Option<String>,
+    /// Also records the keystrokes typed by users. WARNING: this may capture secrets
+    /// typed into programs running in the shell, only the input typed while echo is
+    /// off (e.g. the password prompts of the login wizard) is excluded
+    #[clap(long)]
+    pub record_input: bool,
+    /// Maximum size in bytes of a recording file before it is rotated
+    #[clap(long, default_value = "10485760")]
+    pub record_max_size: u64,
+    /// Maximum number of rotated recording files kept for each session
+    #[clap(long, default_value = "10")]
+    pub record_max_files: u32,
+    /// Keeps the temporary files (/tmp) of the sessions in their root file system
+    /// (e.g. a mounted chain) rather than in memory so that they persist
+    #[clap(long)]
+    pub tmp_persistent: bool,
+    /// Maximum size in bytes of the temporary files (/tmp) that a session may keep
+    /// in memory
+    #[clap(long, default_value = "67108864")]
+    pub tmp_max_size: u64,
+    /// Number of seconds after which persistent temporary files that have not been
+    /// modified are removed (this happens as sessions end)
+    #[clap(long)]
+    pub tmp_ttl: Option<u64>,
 }
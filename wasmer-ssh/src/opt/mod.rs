@@ -2,8 +2,10 @@ mod core;
 mod generate;
 mod ssh;
 mod host;
+mod play;
 
 pub use self::core::*;
 pub use generate::*;
 pub use ssh::*;
-pub use host::*;
\ No newline at end of file
+pub use host::*;
+pub use play::*;
\ No newline at end of file
@@ -0,0 +1,15 @@
+use clap::Parser;
+
+/// Replays a recorded console session to the local terminal
+#[derive(Parser)]
+pub struct OptsPlay {
+    /// Path to the recording that will be played
+    #[clap(index = 1)]
+    pub file: String,
+    /// Playback speed (e.g. 2.0 plays the session twice as fast)
+    #[clap(short, long, default_value = "1.0")]
+    pub speed: f64,
+    /// Caps the time spent waiting between output (in seconds)
+    #[clap(long)]
+    pub max_idle: Option<f64>,
+}
@@ -0,0 +1,268 @@
+#![allow(unused_imports)]
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tracing::{debug, error, info, instrument, span, trace, warn, Level};
+use wasmer_os::api::ConsoleRect;
+use wasmer_term::wasmer_os;
+
+/// Settings that control how the console sessions are recorded
+#[derive(Debug, Clone)]
+pub struct RecordConfig {
+    /// Directory where the recordings are written
+    pub dir: String,
+    /// When true the keystrokes are also recorded (except while echo is off)
+    pub include_input: bool,
+    /// Size in bytes that a recording may reach before it is rotated
+    pub max_size: u64,
+    /// Number of rotated files kept for each session
+    pub max_files: u32,
+}
+
+struct RecorderState {
+    file: Option<File>,
+    part: u32,
+    start: Instant,
+    written: u64,
+    rect: ConsoleRect,
+    partial: Vec<u8>,
+}
+
+/// Records a console session into asciicast (v2) files, once a file
+/// reaches the maximum size a new part is started and the oldest
+/// parts beyond the retention limit are deleted
+pub struct SessionRecorder {
+    config: RecordConfig,
+    dir: PathBuf,
+    name: String,
+    title: String,
+    state: Mutex<RecorderState>,
+}
+
+impl SessionRecorder {
+    pub fn new(
+        config: RecordConfig,
+        peer: &str,
+        user: Option<&str>,
+        rect: ConsoleRect,
+    ) -> io::Result<SessionRecorder> {
+        let dir = PathBuf::from(shellexpand::tilde(&config.dir).to_string());
+        std::fs::create_dir_all(&dir)?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let name = format!("{}-{}", now.as_millis(), peer)
+            .chars()
+            .map(|a| if a.is_ascii_alphanumeric() || a == '-' { a } else { '_' })
+            .collect::<String>();
+        let title = match user {
+            Some(user) => format!("{} ({})", user, peer),
+            None => peer.to_string(),
+        };
+
+        let ret = SessionRecorder {
+            config,
+            dir,
+            name,
+            title,
+            state: Mutex::new(RecorderState {
+                file: None,
+                part: 0,
+                start: Instant::now(),
+                written: 0,
+                rect,
+                partial: Vec::new(),
+            }),
+        };
+        {
+            let mut state = ret.state.lock().unwrap();
+            ret.open(&mut state)?;
+            info!("recording session to {}", ret.path(0).display());
+        }
+        Ok(ret)
+    }
+
+    /// Records output that was sent to the terminal
+    pub fn output(&self, data: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+
+        // Multi-byte characters may be split over writes so the incomplete
+        // tail is held back until the rest of the character arrives
+        let mut data = {
+            let mut partial = std::mem::take(&mut state.partial);
+            partial.extend_from_slice(data);
+            partial
+        };
+        let valid = match std::str::from_utf8(&data[..]) {
+            Ok(_) => data.len(),
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(_) => data.len(),
+        };
+        state.partial = data.split_off(valid);
+
+        let text = String::from_utf8_lossy(&data[..]);
+        if text.len() > 0 {
+            self.event(&mut state, "o", text.as_ref());
+        }
+    }
+
+    /// Records input typed by the user, callers must only pass data that
+    /// was received while echo is on (i.e. never passwords)
+    pub fn input(&self, data: &str) {
+        if self.config.include_input == false {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        self.event(&mut state, "i", data);
+    }
+
+    /// Records a change in the size of the terminal
+    pub fn resize(&self, rect: ConsoleRect) {
+        let mut state = self.state.lock().unwrap();
+        let size = format!("{}x{}", rect.cols, rect.rows);
+        state.rect = rect;
+        self.event(&mut state, "r", size.as_str());
+    }
+
+    fn path(&self, part: u32) -> PathBuf {
+        match part {
+            0 => self.dir.join(format!("{}.cast", self.name)),
+            n => self.dir.join(format!("{}.{}.cast", self.name, n)),
+        }
+    }
+
+    fn event(&self, state: &mut RecorderState, kind: &str, data: &str) {
+        if state.file.is_none() {
+            return;
+        }
+
+        let time = state.start.elapsed().as_secs_f64();
+        let line = match serde_json::to_string(&(time, kind, data)) {
+            Ok(a) => format!("{}\n", a),
+            Err(err) => {
+                debug!("failed to serialize recording event - {}", err);
+                return;
+            }
+        };
+
+        if let Err(err) = self.write(state, line) {
+            warn!("session recording stopped ({}) - {}", self.name, err);
+            state.file = None;
+        }
+    }
+
+    fn write(&self, state: &mut RecorderState, line: String) -> io::Result<()> {
+        if state.written + line.len() as u64 > self.config.max_size {
+            self.rotate(state)?;
+        }
+        if let Some(file) = state.file.as_mut() {
+            file.write_all(line.as_bytes())?;
+            state.written += line.len() as u64;
+        }
+        Ok(())
+    }
+
+    fn rotate(&self, state: &mut RecorderState) -> io::Result<()> {
+        state.part += 1;
+        if state.part >= self.config.max_files {
+            let expired = self.path(state.part - self.config.max_files);
+            if let Err(err) = std::fs::remove_file(&expired) {
+                debug!("failed to remove {} - {}", expired.display(), err);
+            }
+        }
+        self.open(state)
+    }
+
+    fn open(&self, state: &mut RecorderState) -> io::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let header = serde_json::json!({
+            "version": 2,
+            "width": state.rect.cols,
+            "height": state.rect.rows,
+            "timestamp": timestamp,
+            "title": self.title,
+            "env": { "TERM": "xterm-256color" },
+        });
+        let header = format!("{}\n", header);
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(self.path(state.part))?;
+        file.write_all(header.as_bytes())?;
+
+        state.file = Some(file);
+        state.start = Instant::now();
+        state.written = header.len() as u64;
+        Ok(())
+    }
+}
+
+/// Replays a recording to the local terminal, the delays between the
+/// output are divided by the speed and optionally capped at a maximum
+/// idle time
+pub fn play(path: &str, speed: f64, max_idle: Option<f64>) -> io::Result<()> {
+    if speed <= 0f64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the playback speed must be greater than zero",
+        ));
+    }
+
+    let path = shellexpand::tilde(path).to_string();
+    let file = File::open(path)?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header = match lines.next() {
+        Some(a) => a?,
+        None => {
+            return Err(invalid_data("the recording is empty"));
+        }
+    };
+    let header: serde_json::Value = serde_json::from_str(header.as_str())
+        .map_err(|err| invalid_data(err.to_string().as_str()))?;
+    if header["version"].as_u64() != Some(2) {
+        return Err(invalid_data("unsupported recording format (expected asciicast v2)"));
+    }
+
+    let stdout = io::stdout();
+    let mut last = 0f64;
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (time, kind, data): (f64, String, String) = serde_json::from_str(line.as_str())
+            .map_err(|err| invalid_data(err.to_string().as_str()))?;
+        if kind != "o" {
+            continue;
+        }
+
+        let mut delay = (time - last).max(0f64);
+        if let Some(max_idle) = max_idle {
+            delay = delay.min(max_idle);
+        }
+        last = time;
+        std::thread::sleep(Duration::from_secs_f64(delay / speed));
+
+        let mut stdout = stdout.lock();
+        stdout.write_all(data.as_bytes())?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
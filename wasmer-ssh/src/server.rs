@@ -4,16 +4,19 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use std::time::Duration;
 use wasmer_os::api::ConsoleRect;
+use wasmer_os::tty::TtyInputDecoder;
 use thrussh::server;
 use tokio::sync::watch;
 use wasmer_term::wasmer_os;
 use wasmer_os::bin_factory::CachedCompiledModules;
+use wasmer_os::fs::SessionTmp;
 use crate::native_files::NativeFileInterface;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, span, trace, warn, Level};
 
 use crate::key::SshServerKey;
 use crate::opt::*;
+use crate::recorder::RecordConfig;
 use crate::wizard::*;
 
 pub struct Server {
@@ -29,12 +32,28 @@ pub struct Server {
     pub compiled_modules: Arc<CachedCompiledModules>,
     pub exit_rx: watch::Receiver<bool>,
     pub stdio_lock: Arc<Mutex<()>>,
+    pub record: Option<RecordConfig>,
+    pub tmp: SessionTmp,
 }
 
 impl Server {
     pub async fn new(host: OptsHost, server_key: SshServerKey, registry: Arc<Registry>, compiled_modules: Arc<CachedCompiledModules>, native_files: NativeFileInterface, rx_exit: watch::Receiver<bool>) -> Self {
         // Succes
         let auth = wasmer_auth::prelude::origin_url(&host.auth_url, "auth");
+        let record = host.record_dir.clone().map(|dir| RecordConfig {
+            dir,
+            include_input: host.record_input,
+            max_size: host.record_max_size,
+            max_files: host.record_max_files,
+        });
+        let tmp = match host.tmp_persistent {
+            true => SessionTmp::Persistent {
+                ttl: host.tmp_ttl.map(Duration::from_secs),
+            },
+            false => SessionTmp::Ephemeral {
+                max_size: host.tmp_max_size,
+            },
+        };
         Self {
             native_files,
             listen: host.listen,
@@ -48,6 +67,8 @@ impl Server {
             compiled_modules,
             exit_rx: rx_exit,
             stdio_lock: Arc::new(Mutex::new(())),
+            record,
+            tmp,
         }
     }
     pub async fn listen(self) -> Result<(), Box<dyn std::error::Error>> {
@@ -96,6 +117,10 @@ impl server::Server for Server {
             wizard: Some(wizard),
             compiled_modules: self.compiled_modules.clone(),
             stdio_lock: self.stdio_lock.clone(),
+            record: self.record.clone(),
+            recorder: None,
+            tmp: self.tmp,
+            input: TtyInputDecoder::new(),
         }
     }
 }
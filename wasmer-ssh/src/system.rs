@@ -103,6 +103,26 @@ impl wasmer_os::api::SystemAbi for System {
         self.inner.sleep(ms)
     }
 
+    /// Reads the monotonic clock of the host
+    fn now_monotonic_nanos(&self) -> u128 {
+        self.inner.now_monotonic_nanos()
+    }
+
+    /// Resolution of the monotonic clock of the host
+    fn monotonic_resolution_nanos(&self) -> u128 {
+        self.inner.monotonic_resolution_nanos()
+    }
+
+    /// Puts the current thread to sleep until the monotonic clock reaches the deadline
+    fn sleep_until(&self, deadline_nanos: u128) -> AsyncResult<()> {
+        self.inner.sleep_until(deadline_nanos)
+    }
+
+    /// Fills the buffer with random bytes from the host
+    fn random(&self, buf: &mut [u8]) -> std::io::Result<()> {
+        self.inner.random(buf)
+    }
+
     /// Fetches a data file from the local context of the process
     fn fetch_file(&self, path: &str) -> AsyncResult<Result<Vec<u8>, u32>> {
         match &self.native_files {
@@ -137,6 +157,15 @@ impl wasmer_os::api::SystemAbi for System {
     async fn webgl(&self) -> Option<Box<dyn WebGlAbi>> {
         self.inner.webgl().await
     }
+
+    /// Downloads are only possible in the browser
+    fn download(
+        &self,
+        name: &str,
+        data: mpsc::Receiver<Vec<u8>>,
+    ) -> AsyncResult<Result<(), String>> {
+        self.inner.download(name, data)
+    }
 }
 
 fn conv_err(err: FileSystemError) -> u32 {
@@ -583,6 +583,7 @@ async fn login(
         email: username.clone(),
         secret: read_key,
         verification_code: state.verify_code.clone(),
+        user_agent: Some(format!("wasmer-ssh/{}", env!("CARGO_PKG_VERSION"))),
     };
 
     // Attempt the login request with a 10 second timeout
@@ -596,7 +597,7 @@ async fn login(
     };
     let result = match response {
         Ok(a) => a,
-        Err(LoginFailed::AccountLocked(_)) => {
+        Err(LoginFailed::AccountLocked(_)) | Err(LoginFailed::TemporarilyLocked(_)) => {
             return LoginResult::AccountLocked;
         }
         Err(LoginFailed::Unverified(_)) => {
@@ -14,6 +14,7 @@ use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::time::Duration;
+use std::time::Instant;
 use wasmer_os::api::abi::*;
 use wasmer_os::api::AsyncResult;
 use wasmer_os::api::SerializationFormat;
@@ -41,6 +42,7 @@ pub struct SysSystem {
     runtime: Arc<Runtime>,
     stdio_lock: Arc<Mutex<()>>,
     native_files_path: Option<PathBuf>,
+    epoch: Instant,
 }
 
 impl SysSystem {
@@ -54,6 +56,7 @@ impl SysSystem {
             runtime: Arc::new(runtime),
             stdio_lock: Arc::new(Mutex::new(())),
             native_files_path,
+            epoch: Instant::now(),
         }
     }
     pub fn new_with_runtime(native_files_path: Option<String>, exit: watch::Sender<bool>, runtime: Arc<Runtime>) -> SysSystem {
@@ -65,6 +68,7 @@ impl SysSystem {
             runtime,
             stdio_lock: Arc::new(Mutex::new(())),
             native_files_path,
+            epoch: Instant::now(),
         }
     }
 
@@ -173,6 +177,36 @@ impl SystemAbi for SysSystem {
         AsyncResult::new(SerializationFormat::Json, rx_done)
     }
 
+    /// Reads the monotonic clock of the operating system (via `std::time::Instant`)
+    fn now_monotonic_nanos(&self) -> u128 {
+        self.epoch.elapsed().as_nanos()
+    }
+
+    /// `std::time::Instant` has nanosecond resolution on all the platforms we support
+    fn monotonic_resolution_nanos(&self) -> u128 {
+        1
+    }
+
+    /// Sleeps on a dedicated thread as the timers of the async runtime only
+    /// have millisecond granularity which is too coarse for short sleeps
+    fn sleep_until(&self, deadline_nanos: u128) -> AsyncResult<()> {
+        let deadline = self.epoch + Duration::from_nanos(deadline_nanos as u64);
+        let (tx_done, rx_done) = mpsc::channel(1);
+        self.task_dedicated(Box::new(move || {
+            let now = Instant::now();
+            if deadline > now {
+                std::thread::sleep(deadline - now);
+            }
+            let _ = tx_done.blocking_send(());
+        }));
+        AsyncResult::new(SerializationFormat::Json, rx_done)
+    }
+
+    /// Fills the buffer with random bytes from the operating system
+    fn random(&self, buf: &mut [u8]) -> io::Result<()> {
+        getrandom::getrandom(buf).map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
+    }
+
     /// Fetches a data file from the local context of the process
     #[allow(unused)]
     fn fetch_file(&self, path: &str) -> AsyncResult<Result<Vec<u8>, u32>> {
@@ -315,6 +349,17 @@ impl SystemAbi for SysSystem {
     async fn webgl(&self) -> Option<Box<dyn WebGlAbi>> {
         None
     }
+
+    // Downloads are not supported here
+    fn download(
+        &self,
+        _name: &str,
+        _data: mpsc::Receiver<Vec<u8>>,
+    ) -> AsyncResult<Result<(), String>> {
+        let (tx, rx) = mpsc::channel(1);
+        let _ = tx.try_send(Err("downloads are only supported in the browser".to_string()));
+        AsyncResult::new(SerializationFormat::Json, rx)
+    }
 }
 
 #[async_trait]
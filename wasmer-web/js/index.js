@@ -5,7 +5,8 @@ import './workers-polyfill.js'
 async function run() {
   Error.stackTraceLimit = 20;
   await init();
-  await start();
+  // The handle lets the page upload files into the terminal
+  window.wasmerConsole = await start();
 }
 
 run();
@@ -1,3 +1,34 @@
 export function sleep(ms) {
     return new Promise(resolve => setTimeout(resolve, ms));
-}
\ No newline at end of file
+}
+
+// Milliseconds since the unix epoch with sub-millisecond precision, the
+// origin offset makes the value comparable between the window and workers
+export function now_ms() {
+    return performance.timeOrigin + performance.now();
+}
+
+// Browsers coarsen the timers to 5us when cross-origin isolated and to
+// 100us otherwise
+export function cross_origin_isolated() {
+    return globalThis.crossOriginIsolated === true;
+}
+
+// Timers are clamped to 4ms (or worse) so they are only used to get close
+// to the deadline while the last few milliseconds are waited out by polling
+export function sleep_until(deadline_ms) {
+    return new Promise(resolve => {
+        const tick = () => {
+            const remaining = deadline_ms - now_ms();
+            if (remaining <= 0) {
+                resolve();
+            } else if (remaining > 4) {
+                setTimeout(tick, remaining - 2);
+            } else {
+                while (now_ms() < deadline_ms) { }
+                resolve();
+            }
+        };
+        tick();
+    });
+}
@@ -1,4 +1,6 @@
 use chrono::prelude::*;
+use js_sys::Promise;
+use js_sys::Uint8Array;
 use wasmer_os::bin_factory::CachedCompiledModules;
 use std::sync::Arc;
 use wasmer_os::api::*;
@@ -9,6 +11,11 @@ use tokio::sync::mpsc;
 use tracing::{debug, error, info, trace, warn};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::future_to_promise;
+use wasmer_os::fs::UnionFileSystem;
+use web_sys::Blob;
+use web_sys::CompositionEvent;
+use web_sys::DragEvent;
 use web_sys::HtmlCanvasElement;
 use web_sys::KeyboardEvent;
 use web_sys::WebGl2RenderingContext;
@@ -24,6 +31,7 @@ use xterm_js_rs::{LogLevel, OnKeyEvent, Terminal, TerminalOptions};
 use crate::system::TerminalCommand;
 use crate::system::WebConsole;
 use crate::system::WebSystem;
+use crate::transfer::*;
 
 use super::common::*;
 use super::pool::*;
@@ -44,10 +52,79 @@ pub fn main() {
 pub enum InputEvent {
     Key(KeyboardEvent),
     Data(String),
+    Composed(String),
+    Notify(String),
 }
 
+/// Handle returned to the page when the terminal starts that lets it feed
+/// files into the virtual file system of the console
 #[wasm_bindgen]
-pub fn start() -> Result<(), JsValue> {
+#[derive(Clone)]
+pub struct ConsoleInput {
+    fs: UnionFileSystem,
+    tx: mpsc::Sender<InputEvent>,
+}
+
+#[wasm_bindgen]
+impl ConsoleInput {
+    /// Writes the bytes into a file in the /uploads directory and resolves
+    /// to the path of the file once it has been written
+    pub fn upload_file(&self, name: String, bytes: Uint8Array) -> Promise {
+        let input = self.clone();
+        future_to_promise(async move {
+            input
+                .upload(name.as_str(), UploadSource::Bytes(bytes))
+                .await
+                .map(|path| JsValue::from_str(path.as_str()))
+                .map_err(|err| JsValue::from_str(err.as_str()))
+        })
+    }
+}
+
+impl ConsoleInput {
+    async fn upload(&self, name: &str, source: UploadSource) -> Result<String, String> {
+        let ret = upload_file(&self.fs, name, source).await;
+        let msg = match &ret {
+            Ok(path) => format!("uploaded {}", path),
+            Err(err) => format!("upload failed - {}", err),
+        };
+        let _ = self.tx.send(InputEvent::Notify(msg)).await;
+        ret
+    }
+}
+
+/// Characters that are composed (dead keys, IME) are reported by the composition
+/// events and (depending on the browser) also by xterm.js itself, this filter
+/// makes sure that they are only delivered to the console once
+#[derive(Debug, Default)]
+struct CompositionFilter {
+    last: Option<(bool, String, i64)>,
+}
+
+impl CompositionFilter {
+    fn accept(&mut self, composed: bool, data: &str) -> bool {
+        let now: DateTime<Local> = Local::now();
+        let now = now.timestamp_millis();
+        if let Some((was_composed, what, when)) = self.last.take() {
+            if was_composed != composed && what == data && now - when < 500 {
+                return false;
+            }
+        }
+        if composed || data.is_ascii() == false {
+            self.last = Some((composed, data.to_string(), now));
+        }
+        true
+    }
+}
+
+/// Keys that are pressed while a composition is in progress (or dead keys that will
+/// start one) must not be synthesized into input as the composed string follows later
+fn is_composing(event: &KeyboardEvent) -> bool {
+    event.is_composing() || event.key_code() == 229 || event.key() == "Dead"
+}
+
+#[wasm_bindgen]
+pub fn start() -> Result<ConsoleInput, JsValue> {
     #[wasm_bindgen]
     extern "C" {
         #[wasm_bindgen(js_namespace = navigator, js_name = userAgent)]
@@ -138,6 +215,12 @@ pub fn start() -> Result<(), JsValue> {
     let compiled_modules = Arc::new(CachedCompiledModules::new(None));
 
     let fs = wasmer_os::fs::create_root_fs(None);
+
+    // Uploads hold their own strong reference to the mounts of the root
+    // file system so they remain reachable whatever the console does
+    let mut upload_fs = fs.clone();
+    upload_fs.solidify();
+
     let mut console = Console::new(
         location,
         user_agent,
@@ -147,14 +230,24 @@ pub fn start() -> Result<(), JsValue> {
         fs,
         compiled_modules,
     );
+    if let Some(language) = window.navigator().language() {
+        console.set_locale(language.as_str());
+    }
     let tty = console.tty().clone();
 
     let (tx, mut rx) = mpsc::channel(MAX_MPSC);
+    let input = ConsoleInput {
+        fs: upload_fs,
+        tx: tx.clone(),
+    };
 
     let tx_key = tx.clone();
     let callback = {
         Closure::wrap(Box::new(move |e: OnKeyEvent| {
             let event = e.dom_event();
+            if is_composing(&event) {
+                return;
+            }
             tx_key.blocking_send(InputEvent::Key(event)).unwrap();
         }) as Box<dyn FnMut(_)>)
     };
@@ -170,6 +263,73 @@ pub fn start() -> Result<(), JsValue> {
     terminal.on_data(callback.as_ref().unchecked_ref());
     callback.forget();
 
+    // Composed strings (dead keys and IME input) are taken directly from the hidden
+    // textarea that xterm.js uses to capture input and passed on as data
+    if let Some(textarea) = elem.query_selector(".xterm-helper-textarea")? {
+        let tx_composed = tx.clone();
+        let callback = {
+            Closure::wrap(Box::new(move |e: CompositionEvent| {
+                if let Some(data) = e.data() {
+                    if data.len() > 0 {
+                        tx_composed.blocking_send(InputEvent::Composed(data)).unwrap();
+                    }
+                }
+            }) as Box<dyn FnMut(_)>)
+        };
+        textarea.add_event_listener_with_callback("compositionend", callback.as_ref().unchecked_ref())?;
+        callback.forget();
+
+        let tx_composed = tx.clone();
+        let callback = {
+            Closure::wrap(Box::new(move |e: web_sys::InputEvent| {
+                if e.input_type() != "insertFromComposition" {
+                    return;
+                }
+                if let Some(data) = e.data() {
+                    if data.len() > 0 {
+                        tx_composed.blocking_send(InputEvent::Composed(data)).unwrap();
+                    }
+                }
+            }) as Box<dyn FnMut(_)>)
+        };
+        textarea.add_event_listener_with_callback("beforeinput", callback.as_ref().unchecked_ref())?;
+        callback.forget();
+    }
+
+    // Files that are dropped on the terminal are uploaded into the file system
+    {
+        let callback = {
+            Closure::wrap(Box::new(move |e: DragEvent| {
+                e.prevent_default();
+            }) as Box<dyn FnMut(_)>)
+        };
+        elem.add_event_listener_with_callback("dragover", callback.as_ref().unchecked_ref())?;
+        callback.forget();
+
+        let input = input.clone();
+        let callback = {
+            Closure::wrap(Box::new(move |e: DragEvent| {
+                e.prevent_default();
+                let files = match e.data_transfer().and_then(|a| a.files()) {
+                    Some(a) => a,
+                    None => return,
+                };
+                for n in 0..files.length() {
+                    if let Some(file) = files.get(n) {
+                        let input = input.clone();
+                        wasm_bindgen_futures::spawn_local(async move {
+                            let name = file.name();
+                            let blob: Blob = file.into();
+                            let _ = input.upload(name.as_str(), UploadSource::Blob(blob)).await;
+                        });
+                    }
+                }
+            }) as Box<dyn FnMut(_)>)
+        };
+        elem.add_event_listener_with_callback("drop", callback.as_ref().unchecked_ref())?;
+        callback.forget();
+    }
+
     /*
     {
         let addon = FitAddon::new();
@@ -235,6 +395,7 @@ pub fn start() -> Result<(), JsValue> {
         crate::glue::show_terminal();
 
         let mut last = None;
+        let mut composition = CompositionFilter::default();
         while let Some(event) = rx.recv().await {
             match event {
                 InputEvent::Key(event) => {
@@ -248,7 +409,19 @@ pub fn start() -> Result<(), JsValue> {
                         )
                         .await;
                 }
+                InputEvent::Notify(msg) => {
+                    console.on_notify(msg.as_str()).await;
+                }
+                InputEvent::Composed(data) => {
+                    if composition.accept(true, data.as_str()) {
+                        console.on_data(data).await;
+                    }
+                }
                 InputEvent::Data(data) => {
+                    if composition.accept(false, data.as_str()) == false {
+                        continue;
+                    }
+
                     // Due to a nasty bug in xterm.js on Android mobile it sends the keys you press
                     // twice in a row with a short interval between - this hack will avoid that bug
                     if is_mobile {
@@ -269,7 +442,7 @@ pub fn start() -> Result<(), JsValue> {
         }
     });
 
-    Ok(())
+    Ok(input)
 }
 
 #[wasm_bindgen(module = "/js/fit.ts")]
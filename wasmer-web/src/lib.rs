@@ -3,6 +3,7 @@ mod glue;
 mod interval;
 mod pool;
 mod system;
+mod transfer;
 mod ws;
 mod webgl;
 
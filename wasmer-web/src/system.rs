@@ -6,6 +6,9 @@ use wasmer_os::wasmer::vm::VMMemory;
 use wasmer_os::wasmer_wasi::WasiThreadError;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use once_cell::sync::Lazy;
 use wasmer_os::api::abi::SystemAbi;
 use tokio::sync::mpsc;
 #[allow(unused_imports, dead_code)]
@@ -16,6 +19,7 @@ use web_sys::WebGl2RenderingContext;
 
 use super::common::*;
 use super::pool::WebThreadPool;
+use super::transfer::*;
 use super::ws::WebSocket;
 use wasmer_os::api::*;
 use super::webgl::WebGl;
@@ -31,15 +35,18 @@ pub(crate) enum TerminalCommand {
 pub(crate) struct WebSystem {
     pool: WebThreadPool,
     webgl_tx: mpsc::Sender<WebGlCommand>,
+    download_tx: mpsc::Sender<DownloadRequest>,
 }
 
 impl WebSystem {
     pub(crate) fn new(pool: WebThreadPool, webgl2: WebGl2RenderingContext) -> WebSystem {
         let webgl_tx = GlContext::init(webgl2);
+        let download_tx = download_worker();
 
         WebSystem {
             pool,
             webgl_tx,
+            download_tx,
         }
     }
 }
@@ -109,6 +116,44 @@ impl SystemAbi for WebSystem {
         AsyncResult::new(SerializationFormat::Json, rx)
     }
 
+    fn now_monotonic_nanos(&self) -> u128 {
+        let nanos = ((now_ms() - *MONOTONIC_EPOCH).max(0.0) * 1_000_000.0) as u64;
+
+        // The clocks of the workers can disagree by a few microseconds so the
+        // value is clamped to make sure it never goes backwards
+        let last = MONOTONIC_LAST.fetch_max(nanos, Ordering::AcqRel);
+        last.max(nanos) as u128
+    }
+
+    fn monotonic_resolution_nanos(&self) -> u128 {
+        match cross_origin_isolated() {
+            true => 5_000,
+            false => 100_000,
+        }
+    }
+
+    fn sleep_until(&self, deadline_nanos: u128) -> AsyncResult<()> {
+        let deadline_ms = *MONOTONIC_EPOCH + (deadline_nanos as f64 / 1_000_000.0);
+        let (tx, rx) = mpsc::channel(1);
+        self.pool.spawn_shared(Box::new(move || {
+            Box::pin(async move {
+                let promise = sleep_until(deadline_ms);
+                let js_fut = JsFuture::from(promise);
+
+                let _ = js_fut.await;
+                let _ = tx.send(()).await;
+            })
+        }));
+        AsyncResult::new(SerializationFormat::Json, rx)
+    }
+
+    fn random(&self, buf: &mut [u8]) -> std::io::Result<()> {
+        // Backed by crypto.getRandomValues (works in both windows and workers)
+        getrandom::getrandom(buf).map_err(|err| {
+            std::io::Error::new(std::io::ErrorKind::Other, err.to_string())
+        })
+    }
+
     fn fetch_file(&self, path: &str) -> AsyncResult<Result<Vec<u8>, u32>> {
         let url = path.to_string();
         let headers = vec![("Accept".to_string(), "application/wasm".to_string())];
@@ -196,6 +241,23 @@ impl SystemAbi for WebSystem {
     async fn webgl(&self) -> Option<Box<dyn WebGlAbi>> {
         Some(Box::new(WebGl::new(&self.webgl_tx)))
     }
+
+    fn download(
+        &self,
+        name: &str,
+        data: mpsc::Receiver<Vec<u8>>,
+    ) -> AsyncResult<Result<(), String>> {
+        let (tx, rx) = mpsc::channel(1);
+        let req = DownloadRequest {
+            name: name.to_string(),
+            data,
+            ret: tx,
+        };
+        if let Err(mpsc::error::TrySendError::Full(req)) = self.download_tx.try_send(req) {
+            let _ = req.ret.try_send(Err("too many downloads are in progress".to_string()));
+        }
+        AsyncResult::new(SerializationFormat::Json, rx)
+    }
 }
 
 pub(crate) struct WebConsole {
@@ -249,5 +311,16 @@ impl ConsoleAbi for WebConsole {
 #[wasm_bindgen(module = "/js/time.js")]
 extern "C" {
     #[wasm_bindgen(js_name = "sleep")]
-    fn sleep(ms: i32) -> Promise;
+    pub(crate) fn sleep(ms: i32) -> Promise;
+    #[wasm_bindgen(js_name = "sleep_until")]
+    pub(crate) fn sleep_until(deadline_ms: f64) -> Promise;
+    #[wasm_bindgen(js_name = "now_ms")]
+    pub(crate) fn now_ms() -> f64;
+    #[wasm_bindgen(js_name = "cross_origin_isolated")]
+    pub(crate) fn cross_origin_isolated() -> bool;
 }
+
+/// Point in time (see `now_ms`) that the monotonic clock counts from, it is
+/// shared by all the workers as they run on the same memory
+static MONOTONIC_EPOCH: Lazy<f64> = Lazy::new(|| now_ms());
+static MONOTONIC_LAST: AtomicU64 = AtomicU64::new(0);
@@ -0,0 +1,188 @@
+use js_sys::Array;
+use js_sys::Uint8Array;
+use std::path::Path;
+use tokio::sync::mpsc;
+#[allow(unused_imports, dead_code)]
+use tracing::{debug, error, info, trace, warn};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use wasmer_os::common::MAX_MPSC;
+use wasmer_os::fs::AsyncifyFileSystem;
+use wasmer_os::fs::UnionFileSystem;
+use web_sys::Blob;
+use web_sys::BlobPropertyBag;
+use web_sys::HtmlAnchorElement;
+use web_sys::Url;
+
+/// Directory in the virtual file system that uploaded files are written to
+pub(crate) const UPLOAD_DIR: &'static str = "/uploads";
+/// Size of the chunks that uploads are copied into the file system with
+const UPLOAD_CHUNK_SIZE: u32 = 1048576;
+/// Longest file name (in bytes) that is accepted for an upload
+const MAX_FILE_NAME: usize = 255;
+
+/// Data that is being uploaded from the browser, it is only ever copied
+/// into memory one chunk at a time
+pub(crate) enum UploadSource {
+    Bytes(Uint8Array),
+    Blob(Blob),
+}
+
+impl UploadSource {
+    fn len(&self) -> u32 {
+        match self {
+            UploadSource::Bytes(a) => a.length(),
+            UploadSource::Blob(a) => a.size() as u32,
+        }
+    }
+
+    async fn chunk(&self, start: u32, end: u32) -> Result<Vec<u8>, JsValue> {
+        match self {
+            UploadSource::Bytes(a) => Ok(a.subarray(start, end).to_vec()),
+            UploadSource::Blob(a) => {
+                let slice = a.slice_with_f64_and_f64(start as f64, end as f64)?;
+                let buf = JsFuture::from(slice.array_buffer()).await?;
+                Ok(Uint8Array::new(&buf).to_vec())
+            }
+        }
+    }
+}
+
+/// Reduces the name supplied by the browser to a plain file name so that
+/// uploads can never escape the upload directory
+pub(crate) fn sanitize_file_name(name: &str) -> Option<String> {
+    let name = name.rsplit(|c| c == '/' || c == '\\').next().unwrap_or("");
+    let mut name = name
+        .chars()
+        .filter(|c| c.is_control() == false)
+        .collect::<String>()
+        .trim()
+        .to_string();
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+    while name.len() > MAX_FILE_NAME {
+        name.pop();
+    }
+    Some(name)
+}
+
+/// Writes an uploaded file into the upload directory (replacing any file
+/// with the same name) and returns the path that it was written to
+pub(crate) async fn upload_file(
+    fs: &UnionFileSystem,
+    name: &str,
+    source: UploadSource,
+) -> Result<String, String> {
+    let name = match sanitize_file_name(name) {
+        Some(a) => a,
+        None => {
+            return Err(format!("invalid file name ({})", name));
+        }
+    };
+    let path = format!("{}/{}", UPLOAD_DIR, name);
+
+    let fs = AsyncifyFileSystem::new(fs.clone());
+    let _ = fs.create_dir(Path::new(UPLOAD_DIR)).await;
+    let mut file = fs
+        .new_open_options()
+        .await
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(Path::new(path.as_str()))
+        .await
+        .map_err(|err| format!("failed to create {} - {}", path, err))?;
+
+    let len = source.len();
+    let mut pos = 0u32;
+    while pos < len {
+        let end = len.min(pos.saturating_add(UPLOAD_CHUNK_SIZE));
+        let chunk = source
+            .chunk(pos, end)
+            .await
+            .map_err(|err| format!("failed to read {} - {:?}", name, err))?;
+        file.write_all(chunk)
+            .await
+            .map_err(|err| format!("failed to write {} - {}", path, err))?;
+        pos = end;
+    }
+    file.flush()
+        .await
+        .map_err(|err| format!("failed to write {} - {}", path, err))?;
+
+    debug!("uploaded {} ({} bytes)", path, len);
+    Ok(path)
+}
+
+pub(crate) struct DownloadRequest {
+    pub name: String,
+    pub data: mpsc::Receiver<Vec<u8>>,
+    pub ret: mpsc::Sender<Result<(), String>>,
+}
+
+/// Downloads are triggered from the main thread as they need the document,
+/// this starts the loop that processes them and returns its channel
+pub(crate) fn download_worker() -> mpsc::Sender<DownloadRequest> {
+    let (tx, mut rx) = mpsc::channel::<DownloadRequest>(MAX_MPSC);
+    wasm_bindgen_futures::spawn_local(async move {
+        while let Some(mut req) = rx.recv().await {
+            let ret = download(req.name.as_str(), &mut req.data, &req.ret).await;
+            let _ = req.ret.send(ret).await;
+        }
+    });
+    tx
+}
+
+async fn download(
+    name: &str,
+    data: &mut mpsc::Receiver<Vec<u8>>,
+    ret: &mpsc::Sender<Result<(), String>>,
+) -> Result<(), String> {
+    // Each chunk is handed to the browser as soon as it arrives so that
+    // only the blob holds the whole file
+    let parts = Array::new();
+    while let Some(chunk) = data.recv().await {
+        parts.push(&Uint8Array::from(&chunk[..]));
+    }
+
+    // If the file could not be read to the end then nothing is waiting
+    // for the result and a truncated file must not be offered
+    if ret.is_closed() {
+        return Err("download was aborted".to_string());
+    }
+
+    let mut props = BlobPropertyBag::new();
+    props.type_("application/octet-stream");
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &props).map_err(js_err)?;
+    drop(parts);
+
+    let document = web_sys::window()
+        .and_then(|a| a.document())
+        .ok_or_else(|| "downloads require a browser window".to_string())?;
+    let body = document
+        .body()
+        .ok_or_else(|| "downloads require a browser window".to_string())?;
+
+    let url = Url::create_object_url_with_blob(&blob).map_err(js_err)?;
+    let anchor = document
+        .create_element("a")
+        .map_err(js_err)?
+        .dyn_into::<HtmlAnchorElement>()
+        .map_err(|_| "failed to create the download link".to_string())?;
+    anchor.set_href(url.as_str());
+    anchor.set_download(name);
+    body.append_child(&anchor).map_err(js_err)?;
+    anchor.click();
+    let _ = body.remove_child(&anchor);
+
+    // The browser needs a moment to start the download before the URL is released
+    let _ = JsFuture::from(crate::system::sleep(1000)).await;
+    let _ = Url::revoke_object_url(url.as_str());
+    Ok(())
+}
+
+fn js_err(err: JsValue) -> String {
+    format!("{:?}", err)
+}